@@ -0,0 +1,920 @@
+//! Cover, metadata and excerpt extraction for eBook files
+//!
+//! Shared by the Windows thumbnail provider, the macOS Quick Look extensions
+//! and the Linux thumbnailer, which add their platform's formats and
+//! rendering on top.
+//!
+//! Supports: EPUB, MOBI/AZW3/KF8, FB2, CBZ
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use zip::ZipArchive;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// EPUB extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Extract cover image bytes from an EPUB file.
+pub fn extract_epub_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    // Pass 1: Look for files with "cover" in the name
+    let mut candidates: Vec<(usize, String, u64)> = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_lowercase();
+        let size = file.size();
+        drop(file);
+
+        if is_image_extension(&name) && (name.contains("cover") || name.contains("front")) {
+            candidates.push((i, name, size));
+        }
+    }
+
+    // Sort by priority: exact "cover" match first, then by size
+    if !candidates.is_empty() {
+        candidates.sort_by(|a, b| {
+            let a_exact = a.1.contains("cover.") || a.1.ends_with("cover");
+            let b_exact = b.1.contains("cover.") || b.1.ends_with("cover");
+            match (a_exact, b_exact) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => b.2.cmp(&a.2),
+            }
+        });
+
+        let idx = candidates[0].0;
+        let mut file = archive.by_index(idx)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        return Ok(buf);
+    }
+
+    // Pass 2: Parse container.xml to find OPF, then parse OPF for cover
+    let container_xml = read_zip_file_to_string(&mut archive, "META-INF/container.xml");
+    if let Ok(xml) = container_xml {
+        if let Some(rootfile) = extract_attribute(&xml, "rootfile", "full-path") {
+            let opf_content = read_zip_file_to_string(&mut archive, &rootfile);
+            if let Ok(opf) = opf_content {
+                if let Some(cover_id) = find_cover_id_in_opf(&opf) {
+                    if let Some(href) = find_href_by_id_in_opf(&opf, &cover_id) {
+                        let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
+                        let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
+                        if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
+                            return Ok(bytes);
+                        }
+                    }
+                }
+                if let Some(href) = find_first_image_in_manifest(&opf) {
+                    let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
+                    let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
+                    if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
+                        return Ok(bytes);
+                    }
+                }
+            }
+        }
+    }
+
+    // Pass 3: Just grab the largest image file
+    let mut largest: Option<(usize, u64)> = None;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_lowercase();
+        let size = file.size();
+        drop(file);
+
+        if is_image_extension(&name) && (largest.is_none() || size > largest.unwrap().1) {
+            largest = Some((i, size));
+        }
+    }
+
+    if let Some((idx, _)) = largest {
+        let mut file = archive.by_index(idx)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        return Ok(buf);
+    }
+
+    Err(anyhow!("No cover image found in EPUB"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Preview
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Longest excerpt extracted for a preview, in characters.
+const PREVIEW_EXCERPT_CHARS: usize = 2000;
+
+/// What a preview shows for a book: the cover, metadata and opening text.
+pub struct BookPreview {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub publisher: Option<String>,
+    /// Opening paragraphs; only EPUB has them.
+    pub excerpt: Vec<String>,
+    pub cover: Option<Vec<u8>>,
+}
+
+/// Extract the cover, metadata and, for EPUB, opening text of a book.
+pub fn extract_preview(path: &Path, ext: &str) -> Result<BookPreview> {
+    let ext = ext.to_lowercase();
+    let cover = extract_cover_bytes_by_ext(path, &ext).ok();
+    let file = std::fs::File::open(path)?;
+    let (metadata, excerpt) = match ext.as_str() {
+        "epub" => extract_epub_metadata_and_excerpt(file)?,
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" => (extract_mobi_metadata(file)?, Vec::new()),
+        "fb2" => (extract_fb2_metadata(file)?, Vec::new()),
+        "cbz" => (BookMetadata::default(), Vec::new()),
+        _ => return Err(anyhow!("Unsupported format: {}", ext)),
+    };
+
+    Ok(BookPreview {
+        title: metadata.title,
+        authors: metadata.authors,
+        series: metadata.series,
+        publisher: metadata.publisher,
+        excerpt,
+        cover,
+    })
+}
+
+/// Metadata and the text of the spine in reading order, until there is
+/// enough of it.
+fn extract_epub_metadata_and_excerpt<R: Read + Seek>(
+    reader: R,
+) -> Result<(BookMetadata, Vec<String>)> {
+    let mut archive = ZipArchive::new(reader)?;
+    let (rootfile, opf) = read_epub_opf(&mut archive)?;
+
+    let mut excerpt = Vec::new();
+    let mut remaining = PREVIEW_EXCERPT_CHARS;
+    for idref in find_spine_idrefs(&opf) {
+        if remaining == 0 {
+            break;
+        }
+        let Some(href) = find_href_by_id_in_opf(&opf, &idref) else {
+            continue;
+        };
+        let doc_path = resolve_zip_path(&rootfile, &href);
+        let Ok(html) = read_zip_file_to_string(&mut archive, &doc_path) else {
+            continue;
+        };
+        for paragraph in html_to_paragraphs(&html) {
+            if remaining == 0 {
+                break;
+            }
+            let len = paragraph.chars().count();
+            if len > remaining {
+                excerpt.push(truncate_at_word(&paragraph, remaining));
+                remaining = 0;
+            } else {
+                excerpt.push(paragraph);
+                remaining -= len;
+            }
+        }
+    }
+
+    Ok((epub_metadata(&opf), excerpt))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// MOBI/AZW3/KF8 extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Record 0 of a MOBI/AZW3/KF8 file: the record table, the MOBI header and
+/// its EXTH metadata records.
+struct MobiHeader {
+    record_offsets: Vec<u32>,
+    first_img_idx: u32,
+    /// Text encoding of strings in the file: 65001 for UTF-8, 1252 otherwise.
+    encoding: u32,
+    full_name: Vec<u8>,
+    exth: Vec<(u32, Vec<u8>)>,
+    has_exth: bool,
+}
+
+impl MobiHeader {
+    fn exth_records(&self, rec_type: u32) -> impl Iterator<Item = &[u8]> {
+        self.exth
+            .iter()
+            .filter(move |(t, _)| *t == rec_type)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        if self.encoding == 65001 {
+            String::from_utf8_lossy(bytes).into_owned()
+        } else {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+    }
+}
+
+fn read_mobi_header<R: Read + Seek>(reader: &mut R) -> Result<MobiHeader> {
+    let mut header = [0u8; 78];
+    reader.read_exact(&mut header)?;
+
+    if &header[60..68] != b"BOOKMOBI" {
+        return Err(anyhow!("Not a valid MOBI file"));
+    }
+
+    let num_records = u16::from_be_bytes([header[76], header[77]]) as usize;
+
+    let mut record_offsets: Vec<u32> = Vec::with_capacity(num_records);
+    for _ in 0..num_records {
+        let mut rec = [0u8; 8];
+        reader.read_exact(&mut rec)?;
+        record_offsets.push(u32::from_be_bytes([rec[0], rec[1], rec[2], rec[3]]));
+    }
+
+    if record_offsets.is_empty() {
+        return Err(anyhow!("No records in MOBI file"));
+    }
+
+    reader.seek(SeekFrom::Start(record_offsets[0] as u64))?;
+    let mut mobi_header = [0u8; 256];
+    reader.read_exact(&mut mobi_header)?;
+
+    if &mobi_header[16..20] != b"MOBI" {
+        return Err(anyhow!("Invalid MOBI header"));
+    }
+
+    let be_u32 = |at: usize| {
+        u32::from_be_bytes([
+            mobi_header[at],
+            mobi_header[at + 1],
+            mobi_header[at + 2],
+            mobi_header[at + 3],
+        ])
+    };
+    let header_length = be_u32(20) as usize;
+    let encoding = be_u32(28);
+    let full_name_offset = be_u32(84) as u64;
+    let full_name_length = be_u32(88) as usize;
+    let first_img_idx = be_u32(108);
+    let exth_flags = be_u32(128);
+
+    let mut exth = Vec::new();
+    let has_exth = exth_flags & 0x40 != 0;
+    if has_exth {
+        let exth_offset = record_offsets[0] as u64 + 16 + header_length as u64;
+        reader.seek(SeekFrom::Start(exth_offset))?;
+
+        let mut exth_magic = [0u8; 4];
+        reader.read_exact(&mut exth_magic)?;
+        if &exth_magic != b"EXTH" {
+            return Err(anyhow!("EXTH header not found"));
+        }
+
+        let mut exth_len_bytes = [0u8; 4];
+        reader.read_exact(&mut exth_len_bytes)?;
+
+        let mut exth_count_bytes = [0u8; 4];
+        reader.read_exact(&mut exth_count_bytes)?;
+        let exth_count = u32::from_be_bytes(exth_count_bytes) as usize;
+
+        for _ in 0..exth_count {
+            let mut rec_header = [0u8; 8];
+            if reader.read_exact(&mut rec_header).is_err() {
+                break;
+            }
+            let rec_type =
+                u32::from_be_bytes([rec_header[0], rec_header[1], rec_header[2], rec_header[3]]);
+            let rec_len =
+                u32::from_be_bytes([rec_header[4], rec_header[5], rec_header[6], rec_header[7]])
+                    as usize;
+
+            let data_len = rec_len.saturating_sub(8);
+            let mut data = vec![0u8; data_len];
+            if reader.read_exact(&mut data).is_err() {
+                break;
+            }
+            exth.push((rec_type, data));
+        }
+    }
+
+    let mut full_name = vec![0u8; full_name_length.min(1024)];
+    reader.seek(SeekFrom::Start(record_offsets[0] as u64 + full_name_offset))?;
+    if reader.read_exact(&mut full_name).is_err() {
+        full_name.clear();
+    }
+
+    Ok(MobiHeader {
+        record_offsets,
+        first_img_idx,
+        encoding,
+        full_name,
+        exth,
+        has_exth,
+    })
+}
+
+/// Extract cover image from MOBI/AZW3/KF8 files.
+pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>> {
+    let header = read_mobi_header(&mut reader)?;
+    if !header.has_exth {
+        return Err(anyhow!("No EXTH header in MOBI file"));
+    }
+
+    let record_offsets = &header.record_offsets;
+    let first_img_idx = header.first_img_idx;
+    let cover_offset = header
+        .exth_records(201)
+        .find(|data| data.len() >= 4)
+        .map(|data| u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+
+    let cover_record_idx = if let Some(offset) = cover_offset {
+        first_img_idx + offset
+    } else {
+        first_img_idx
+    };
+
+    if cover_record_idx as usize >= record_offsets.len() {
+        return Err(anyhow!("Cover record index out of bounds"));
+    }
+
+    let start = record_offsets[cover_record_idx as usize] as u64;
+    let end = if (cover_record_idx as usize + 1) < record_offsets.len() {
+        record_offsets[cover_record_idx as usize + 1] as u64
+    } else {
+        reader.seek(SeekFrom::End(0))?;
+        reader.stream_position()?
+    };
+
+    let len = (end - start) as usize;
+    reader.seek(SeekFrom::Start(start))?;
+    let mut cover_data = vec![0u8; len];
+    reader.read_exact(&mut cover_data)?;
+
+    if cover_data.starts_with(&[0xFF, 0xD8, 0xFF])
+        || cover_data.starts_with(&[0x89, 0x50, 0x4E, 0x47])
+        || cover_data.starts_with(b"GIF")
+    {
+        return Ok(cover_data);
+    }
+
+    Err(anyhow!("No valid cover image found in MOBI"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CBZ extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Extract cover image from CBZ (comic book ZIP) file.
+pub fn extract_cbz_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    let mut images: Vec<(usize, String)> = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        drop(file);
+
+        if is_image_extension(&name.to_lowercase()) {
+            images.push((i, name));
+        }
+    }
+
+    images.sort_by(|a, b| a.1.cmp(&b.1));
+
+    if let Some((idx, _)) = images.first() {
+        let mut file = archive.by_index(*idx)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        return Ok(buf);
+    }
+
+    Err(anyhow!("No images found in CBZ"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// FB2 extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Extract cover image from FB2 (FictionBook) file.
+pub fn extract_fb2_cover_bytes<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+
+    let cover_id = if let Some(start) = content.find("<coverpage>") {
+        let end = content[start..].find("</coverpage>").unwrap_or(500);
+        let coverpage = &content[start..start + end];
+        if let Some(href_pos) = coverpage.find("href=\"#") {
+            let id_start = href_pos + 7;
+            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
+            Some(coverpage[id_start..id_start + id_end].to_string())
+        } else if let Some(href_pos) = coverpage.find("l:href=\"#") {
+            let id_start = href_pos + 9;
+            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
+            Some(coverpage[id_start..id_start + id_end].to_string())
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let search_pattern = if let Some(ref id) = cover_id {
+        format!("<binary id=\"{}\"", id)
+    } else {
+        "<binary".to_string()
+    };
+
+    if let Some(pos) = content.find(&search_pattern) {
+        if let Some(tag_end) = content[pos..].find('>') {
+            let data_start = pos + tag_end + 1;
+            if let Some(data_end) = content[data_start..].find("</binary>") {
+                let b64_data = content[data_start..data_start + data_end].trim();
+                let b64_clean: String = b64_data.chars().filter(|c| !c.is_whitespace()).collect();
+                let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
+                return Ok(bytes);
+            }
+        }
+    }
+
+    if cover_id.is_some() {
+        if let Some(pos) = content.find("<binary") {
+            if let Some(tag_end) = content[pos..].find('>') {
+                let data_start = pos + tag_end + 1;
+                if let Some(data_end) = content[data_start..].find("</binary>") {
+                    let b64_data = content[data_start..data_start + data_end].trim();
+                    let b64_clean: String =
+                        b64_data.chars().filter(|c| !c.is_whitespace()).collect();
+                    let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
+                    return Ok(bytes);
+                }
+            }
+        }
+    }
+
+    Err(anyhow!("No cover image found in FB2"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Book metadata
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// How much of an FB2 file is searched for its `<description>`.
+const FB2_DESCRIPTION_BYTES: u64 = 256 * 1024;
+
+/// Title, authors, series and publisher of a book.
+#[derive(Debug, Default)]
+pub struct BookMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub publisher: Option<String>,
+}
+
+/// Extract metadata from an EPUB, MOBI/AZW3/KF8 or FB2 file, told apart by
+/// their content so that a stream without a name will do.
+pub fn extract_metadata<R: Read + Seek>(mut reader: R) -> Result<BookMetadata> {
+    let mut magic = Vec::new();
+    reader.by_ref().take(68).read_to_end(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    if magic.starts_with(b"PK") {
+        extract_epub_metadata(reader)
+    } else if magic.get(60..68) == Some(b"BOOKMOBI".as_slice()) {
+        extract_mobi_metadata(reader)
+    } else {
+        extract_fb2_metadata(reader)
+    }
+}
+
+/// Extract metadata from an EPUB file's OPF package document.
+pub fn extract_epub_metadata<R: Read + Seek>(reader: R) -> Result<BookMetadata> {
+    let mut archive = ZipArchive::new(reader)?;
+    let (_, opf) = read_epub_opf(&mut archive)?;
+    Ok(epub_metadata(&opf))
+}
+
+/// Extract metadata from the EXTH records of a MOBI/AZW3/KF8 file.
+pub fn extract_mobi_metadata<R: Read + Seek>(mut reader: R) -> Result<BookMetadata> {
+    let header = read_mobi_header(&mut reader)?;
+    let texts = |rec_type: u32| {
+        header
+            .exth_records(rec_type)
+            .map(|data| header.decode(data).trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let full_name = header.decode(&header.full_name).trim().to_string();
+
+    Ok(BookMetadata {
+        title: texts(503)
+            .next()
+            .or_else(|| (!full_name.is_empty()).then_some(full_name)),
+        authors: texts(100).collect(),
+        series: None,
+        publisher: texts(101).next(),
+    })
+}
+
+/// Extract metadata from the `<description>` of an FB2 (FictionBook) file.
+pub fn extract_fb2_metadata<R: Read>(reader: R) -> Result<BookMetadata> {
+    let mut bytes = Vec::new();
+    reader.take(FB2_DESCRIPTION_BYTES).read_to_end(&mut bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    let title_info = element_inners(&content, "title-info")
+        .next()
+        .ok_or_else(|| anyhow!("No title-info in FB2"))?;
+    let authors = element_inners(title_info, "author")
+        .filter_map(|author| {
+            let names: Vec<String> = ["first-name", "middle-name", "last-name"]
+                .iter()
+                .filter_map(|part| find_element_text(author, part))
+                .collect();
+            if names.is_empty() {
+                find_element_text(author, "nickname")
+            } else {
+                Some(names.join(" "))
+            }
+        })
+        .collect();
+
+    Ok(BookMetadata {
+        title: find_element_text(title_info, "book-title"),
+        authors,
+        series: extract_attribute(title_info, "sequence", "name").map(|s| decode_entities(&s)),
+        publisher: element_inners(&content, "publish-info")
+            .next()
+            .and_then(|info| find_element_text(info, "publisher")),
+    })
+}
+
+fn epub_metadata(opf: &str) -> BookMetadata {
+    BookMetadata {
+        title: find_element_text(opf, "dc:title"),
+        authors: element_inners(opf, "dc:creator")
+            .map(|text| decode_entities(text.trim()))
+            .filter(|text| !text.is_empty())
+            .collect(),
+        series: find_meta_content(opf, "calibre:series").or_else(|| find_collection(opf)),
+        publisher: find_element_text(opf, "dc:publisher"),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Unified extraction by extension
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Extract cover image bytes based on file extension.
+pub fn extract_cover_bytes_by_ext(path: &Path, ext: &str) -> Result<Vec<u8>> {
+    let file = std::fs::File::open(path)?;
+    match ext.to_lowercase().as_str() {
+        "epub" => extract_epub_cover_bytes(file),
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" => extract_mobi_cover_bytes(file),
+        "cbz" => extract_cbz_cover_bytes(file),
+        "fb2" => extract_fb2_cover_bytes(file),
+        _ => Err(anyhow!("Unsupported format: {}", ext)),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Helper functions
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn is_image_extension(name: &str) -> bool {
+    name.ends_with(".jpg")
+        || name.ends_with(".jpeg")
+        || name.ends_with(".png")
+        || name.ends_with(".gif")
+        || name.ends_with(".webp")
+        || name.ends_with(".bmp")
+}
+
+fn read_zip_file_to_string<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<String> {
+    let mut file = archive.by_name(name)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn read_zip_file_to_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Vec<u8>> {
+    let mut file = archive.by_name(name)?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn extract_attribute(xml: &str, tag: &str, attr: &str) -> Option<String> {
+    let pattern = format!("<{}", tag);
+    if let Some(tag_pos) = xml.find(&pattern) {
+        let tag_end = xml[tag_pos..].find('>').unwrap_or(500) + tag_pos;
+        let tag_content = &xml[tag_pos..tag_end];
+
+        let attr_pattern = format!("{}=\"", attr);
+        if let Some(attr_pos) = tag_content.find(&attr_pattern) {
+            let value_start = attr_pos + attr_pattern.len();
+            if let Some(value_end) = tag_content[value_start..].find('"') {
+                return Some(tag_content[value_start..value_start + value_end].to_string());
+            }
+        }
+    }
+    None
+}
+
+fn find_cover_id_in_opf(opf: &str) -> Option<String> {
+    if let Some(pos) = opf.find("name=\"cover\"") {
+        let window_start = pos.saturating_sub(50);
+        let window_end = (pos + 100).min(opf.len());
+        let window = &opf[window_start..window_end];
+
+        if let Some(content_pos) = window.find("content=\"") {
+            let start = content_pos + 9;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+
+    if let Some(pos) = opf.find("properties=\"cover-image\"") {
+        let window_start = pos.saturating_sub(200);
+        let window_end = pos;
+        let window = &opf[window_start..window_end];
+
+        if let Some(id_pos) = window.rfind("id=\"") {
+            let start = id_pos + 4;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+
+    None
+}
+
+fn find_href_by_id_in_opf(opf: &str, id: &str) -> Option<String> {
+    let pattern = format!("id=\"{}\"", id);
+    if let Some(pos) = opf.find(&pattern) {
+        let window_start = pos.saturating_sub(10);
+        let window_end = (pos + 200).min(opf.len());
+        let window = &opf[window_start..window_end];
+
+        if let Some(href_pos) = window.find("href=\"") {
+            let start = href_pos + 6;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+    None
+}
+
+fn find_first_image_in_manifest(opf: &str) -> Option<String> {
+    let manifest_start = opf.find("<manifest")?;
+    let manifest_end = opf[manifest_start..]
+        .find("</manifest>")
+        .map(|e| manifest_start + e)?;
+    let manifest = &opf[manifest_start..manifest_end];
+
+    for media_type in ["image/jpeg", "image/png", "image/gif", "image/webp"] {
+        let pattern = format!("media-type=\"{}\"", media_type);
+        if let Some(pos) = manifest.find(&pattern) {
+            let window_start = pos.saturating_sub(200);
+            let window = &manifest[window_start..pos];
+
+            if let Some(href_pos) = window.rfind("href=\"") {
+                let start = href_pos + 6;
+                if let Some(end) = window[start..].find('"') {
+                    return Some(window[start..start + end].to_string());
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Path and content of the OPF package document of an EPUB.
+fn read_epub_opf<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<(String, String)> {
+    let container_xml = read_zip_file_to_string(archive, "META-INF/container.xml")?;
+    let rootfile = extract_attribute(&container_xml, "rootfile", "full-path")
+        .ok_or_else(|| anyhow!("No rootfile in container.xml"))?;
+    let opf = read_zip_file_to_string(archive, &rootfile)?;
+    Ok((rootfile, opf))
+}
+
+/// Raw content of each `<tag>` element in `xml`, skipping self-closing ones.
+fn element_inners<'a>(xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut rest = xml;
+    std::iter::from_fn(move || loop {
+        let pos = rest.find(&open)?;
+        let after = &rest[pos + open.len()..];
+        if !after.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            rest = after;
+            continue;
+        }
+        let gt = after.find('>')?;
+        if after[..gt].ends_with('/') {
+            rest = &after[gt + 1..];
+            continue;
+        }
+        let content = &after[gt + 1..];
+        let end = content.find(&close)?;
+        rest = &content[end + close.len()..];
+        return Some(&content[..end]);
+    })
+}
+
+fn find_element_text(xml: &str, tag: &str) -> Option<String> {
+    element_inners(xml, tag)
+        .map(|text| decode_entities(text.trim()))
+        .find(|text| !text.is_empty())
+}
+
+/// `content` of the EPUB 2 `<meta name="..." content="..."/>` with the given name.
+fn find_meta_content(opf: &str, name: &str) -> Option<String> {
+    let pos = opf.find(&format!("name=\"{}\"", name))?;
+    let tag_start = opf[..pos].rfind('<')?;
+    let tag_end = opf[pos..].find('>')? + pos;
+    extract_attribute(&opf[tag_start..=tag_end], "meta", "content")
+        .map(|content| decode_entities(content.trim()))
+        .filter(|content| !content.is_empty())
+}
+
+/// Name of the EPUB 3 collection the book belongs to.
+fn find_collection(opf: &str) -> Option<String> {
+    let pos = opf.find("property=\"belongs-to-collection\"")?;
+    let content_start = opf[pos..].find('>')? + pos + 1;
+    let content_end = opf[content_start..].find("</")? + content_start;
+    let name = decode_entities(opf[content_start..content_end].trim());
+    (!name.is_empty()).then_some(name)
+}
+
+fn find_spine_idrefs(opf: &str) -> Vec<String> {
+    let Some(spine_start) = opf.find("<spine") else {
+        return Vec::new();
+    };
+    let spine_end = opf[spine_start..]
+        .find("</spine>")
+        .map_or(opf.len(), |e| spine_start + e);
+    let spine = &opf[spine_start..spine_end];
+
+    let mut idrefs = Vec::new();
+    let mut rest = spine;
+    while let Some(pos) = rest.find("idref=\"") {
+        let start = pos + 7;
+        let Some(end) = rest[start..].find('"') else {
+            break;
+        };
+        idrefs.push(rest[start..start + end].to_string());
+        rest = &rest[start + end..];
+    }
+    idrefs
+}
+
+/// Resolve an href relative to the archive file that contains it.
+fn resolve_zip_path(from: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let mut parts: Vec<String> = from.split('/').map(str::to_string).collect();
+    parts.pop();
+    for segment in percent_decode(href).split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            segment => parts.push(segment.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Visible text of an (X)HTML document, one entry per non-empty block.
+fn html_to_paragraphs(html: &str) -> Vec<String> {
+    let body = html.find("<body").map_or(html, |pos| &html[pos..]);
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut rest = body;
+
+    while let Some(lt) = rest.find('<') {
+        current.push_str(&rest[..lt]);
+        let Some(gt) = rest[lt..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[lt + 1..lt + gt];
+        rest = &rest[lt + gt + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if !tag.starts_with('/') && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            rest = rest.find(&close).map_or("", |pos| &rest[pos..]);
+        } else if is_block_tag(&name) {
+            push_paragraph(&mut paragraphs, &current);
+            current.clear();
+        }
+    }
+    current.push_str(rest);
+    push_paragraph(&mut paragraphs, &current);
+    paragraphs
+}
+
+fn is_block_tag(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div" | "br" | "li" | "tr" | "blockquote" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+    )
+}
+
+fn push_paragraph(paragraphs: &mut Vec<String>, markup_text: &str) {
+    let text = decode_entities(markup_text);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        paragraphs.push(text);
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';').filter(|&semi| semi <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return text.to_string();
+    };
+    let head = &text[..cut];
+    let head = head
+        .rfind(char::is_whitespace)
+        .map_or(head, |pos| &head[..pos]);
+    format!("{}…", head.trim_end())
+}
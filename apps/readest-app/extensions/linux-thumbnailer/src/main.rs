@@ -0,0 +1,87 @@
+//! Freedesktop thumbnailer for Readest on Linux
+//!
+//! Run by file managers that follow the thumbnail spec (Nautilus, Nemo,
+//! Thunar through tumbler, Dolphin through kio-extras) as registered in
+//! `readest.thumbnailer`:
+//!
+//! ```text
+//! readest-thumbnailer -s <size> <input> <output>
+//! ```
+//!
+//! It writes the book's cover, scaled to fit `size` pixels, to `output` as a
+//! PNG. A non-zero exit tells the file manager to keep the generic icon.
+//!
+//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, CBZ
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Context, Result};
+use image::{imageops::FilterType, ImageFormat};
+
+/// Size used when the file manager doesn't pass `-s`, the spec's "normal".
+const DEFAULT_SIZE: u32 = 128;
+
+struct Args {
+    size: u32,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    let mut size = DEFAULT_SIZE;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "-s" || arg == "--size" {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {arg}"))?;
+            size = value
+                .parse()
+                .ok()
+                .filter(|&size| size > 0)
+                .ok_or_else(|| anyhow!("Invalid size: {value}"))?;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| anyhow!("Usage: readest-thumbnailer [-s SIZE] INPUT OUTPUT"))?;
+    Ok(Args {
+        size,
+        input,
+        output,
+    })
+}
+
+fn write_thumbnail(input: &Path, output: &Path, size: u32) -> Result<()> {
+    let ext = input
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let cover = book_extraction::extract_cover_bytes_by_ext(input, &ext)?;
+    let image = image::load_from_memory(&cover).context("Failed to decode cover")?;
+    // Covers smaller than the requested size are left as they are; the file
+    // manager scales them up if it wants to.
+    let image = if image.width() > size || image.height() > size {
+        image.resize(size, size, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    image
+        .save_with_format(output, ImageFormat::Png)
+        .context("Failed to write thumbnail")
+}
+
+fn main() -> ExitCode {
+    let result = parse_args(std::env::args().skip(1))
+        .and_then(|args| write_thumbnail(&args.input, &args.output, args.size));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("readest-thumbnailer: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,18 @@
+import Foundation
+import QuickLookUI
+import UniformTypeIdentifiers
+
+/// Space-bar previews with the cover, title, author and opening text.
+class PreviewProvider: QLPreviewProvider, QLPreviewingController {
+    func providePreview(for request: QLFilePreviewRequest) async throws -> QLPreviewReply {
+        guard let html = readestData(for: request.fileURL, readest_ql_preview_html) else {
+            throw ReadestQuickLookError.unreadable
+        }
+        let reply = QLPreviewReply(
+            dataOfContentType: .html,
+            contentSize: CGSize(width: 800, height: 600)
+        ) { _ in html }
+        reply.stringEncoding = .utf8
+        return reply
+    }
+}
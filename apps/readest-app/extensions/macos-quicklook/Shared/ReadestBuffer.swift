@@ -0,0 +1,14 @@
+import Foundation
+
+enum ReadestQuickLookError: Error {
+    case unreadable
+}
+
+/// Call into the Rust library with the file's path and take ownership of the
+/// bytes it returns.
+func readestData(for url: URL, _ body: (UnsafePointer<CChar>?) -> ReadestBuffer) -> Data? {
+    let buffer = url.withUnsafeFileSystemRepresentation(body)
+    defer { readest_ql_free(buffer) }
+    guard let bytes = buffer.data, buffer.len > 0 else { return nil }
+    return Data(bytes: bytes, count: buffer.len)
+}
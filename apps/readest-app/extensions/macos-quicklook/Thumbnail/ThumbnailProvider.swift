@@ -0,0 +1,32 @@
+import CoreGraphics
+import ImageIO
+import QuickLookThumbnailing
+
+/// Finder icons showing the book's cover.
+class ThumbnailProvider: QLThumbnailProvider {
+    override func provideThumbnail(
+        for request: QLFileThumbnailRequest,
+        _ handler: @escaping (QLThumbnailReply?, Error?) -> Void
+    ) {
+        guard let data = readestData(for: request.fileURL, readest_ql_cover),
+              let source = CGImageSourceCreateWithData(data as CFData, nil),
+              let image = CGImageSourceCreateImageAtIndex(source, 0, nil),
+              image.width > 0, image.height > 0
+        else {
+            handler(nil, ReadestQuickLookError.unreadable)
+            return
+        }
+
+        // Fit the cover into the requested size, keeping its aspect ratio.
+        let width = CGFloat(image.width)
+        let height = CGFloat(image.height)
+        let scale = min(request.maximumSize.width / width, request.maximumSize.height / height)
+        let size = CGSize(width: width * scale, height: height * scale)
+
+        handler(QLThumbnailReply(contextSize: size) { context -> Bool in
+            context.interpolationQuality = .high
+            context.draw(image, in: CGRect(origin: .zero, size: size))
+            return true
+        }, nil)
+    }
+}
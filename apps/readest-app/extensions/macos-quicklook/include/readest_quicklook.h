@@ -0,0 +1,23 @@
+// C interface of the macos_quicklook static library, imported by the Swift
+// Quick Look extensions through -import-objc-header.
+
+#ifndef READEST_QUICKLOOK_H
+#define READEST_QUICKLOOK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct {
+  uint8_t *data;
+  size_t len;
+} ReadestBuffer;
+
+// Encoded cover image of the book at `path`; empty if it has none.
+ReadestBuffer readest_ql_cover(const char *path);
+
+// UTF-8 HTML preview of the book at `path`; empty if it can't be read.
+ReadestBuffer readest_ql_preview_html(const char *path);
+
+void readest_ql_free(ReadestBuffer buffer);
+
+#endif
@@ -0,0 +1,102 @@
+//! Quick Look support for Readest on macOS
+//!
+//! Static library linked into the Quick Look app extensions bundled with the
+//! app: `ReadestThumbnail.appex` draws book covers as Finder icons and
+//! `ReadestPreview.appex` renders the space-bar preview with the cover, title,
+//! author and, for EPUB, the opening text. The extensions are thin Swift
+//! wrappers around the C functions below, declared in
+//! `include/readest_quicklook.h`.
+//!
+//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, CBZ
+
+mod preview;
+
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+/// Bytes handed to Swift, released with [`readest_ql_free`].
+#[repr(C)]
+pub struct ReadestBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl ReadestBuffer {
+    fn empty() -> Self {
+        ReadestBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        ReadestBuffer { data, len }
+    }
+}
+
+/// Run `f` on the UTF-8 path, turning failures and panics into an empty
+/// buffer so that Quick Look falls back to the file's icon.
+unsafe fn with_path(
+    path: *const c_char,
+    f: impl FnOnce(&Path, &str) -> anyhow::Result<Vec<u8>>,
+) -> ReadestBuffer {
+    if path.is_null() {
+        return ReadestBuffer::empty();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return ReadestBuffer::empty();
+    };
+    let path = Path::new(path);
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match catch_unwind(AssertUnwindSafe(|| f(path, &ext))) {
+        Ok(Ok(bytes)) => ReadestBuffer::from_vec(bytes),
+        _ => ReadestBuffer::empty(),
+    }
+}
+
+/// Encoded cover image (JPEG, PNG, GIF or WebP) of the book at `path`.
+///
+/// # Safety
+/// `path` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn readest_ql_cover(path: *const c_char) -> ReadestBuffer {
+    with_path(path, book_extraction::extract_cover_bytes_by_ext)
+}
+
+/// UTF-8 HTML document previewing the book at `path`.
+///
+/// # Safety
+/// `path` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn readest_ql_preview_html(path: *const c_char) -> ReadestBuffer {
+    with_path(path, |path, ext| {
+        let book = book_extraction::extract_preview(path, ext)?;
+        let fallback_title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(preview::render_html(&book, &fallback_title).into_bytes())
+    })
+}
+
+/// Release a buffer returned by this library.
+///
+/// # Safety
+/// `buffer` must come from [`readest_ql_cover`] or [`readest_ql_preview_html`]
+/// and not have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn readest_ql_free(buffer: ReadestBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
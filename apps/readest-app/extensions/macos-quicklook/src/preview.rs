@@ -0,0 +1,102 @@
+/// HTML for the space-bar preview
+///
+/// Quick Look shows the document in a web view sized to the preview window,
+/// so the layout is a cover column beside the metadata and excerpt that
+/// stacks on narrow windows and follows the system appearance.
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use book_extraction::BookPreview;
+
+const STYLE: &str = r#"
+:root { color-scheme: light dark; }
+body { margin: 0; padding: 24px; font: 14px/1.5 -apple-system, sans-serif; }
+.book { display: flex; gap: 24px; align-items: flex-start; }
+.cover { flex: 0 0 auto; max-width: 40%; max-height: calc(100vh - 48px);
+  box-shadow: 0 2px 12px rgba(0, 0, 0, 0.3); border-radius: 2px; }
+.details { flex: 1 1 auto; min-width: 0; }
+h1 { margin: 0 0 4px; font-size: 22px; line-height: 1.25; }
+.author { margin: 0 0 8px; font-size: 16px; opacity: 0.8; }
+.meta { margin: 0 0 16px; font-size: 12px; opacity: 0.6; }
+.excerpt p { margin: 0 0 0.8em; font-family: ui-serif, Georgia, serif; }
+@media (max-width: 480px) {
+  .book { flex-direction: column; align-items: center; }
+  .cover { max-width: 100%; }
+}
+"#;
+
+/// Render `book` as a standalone HTML document. `fallback_title` is used when
+/// the book doesn't name itself, as comics rarely do.
+pub fn render_html(book: &BookPreview, fallback_title: &str) -> String {
+    let title = book.title.as_deref().unwrap_or(fallback_title);
+
+    let mut html = String::with_capacity(4096);
+    html.push_str("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>");
+    html.push_str(&escape(title));
+    html.push_str("</title><style>");
+    html.push_str(STYLE);
+    html.push_str("</style></head><body><div class=\"book\">");
+
+    if let Some(cover) = &book.cover {
+        html.push_str(&format!(
+            "<img class=\"cover\" alt=\"\" src=\"data:{};base64,{}\">",
+            image_mime_type(cover),
+            general_purpose::STANDARD.encode(cover)
+        ));
+    }
+
+    html.push_str("<div class=\"details\"><h1>");
+    html.push_str(&escape(title));
+    html.push_str("</h1>");
+    if !book.authors.is_empty() {
+        html.push_str(&format!(
+            "<p class=\"author\">{}</p>",
+            escape(&book.authors.join(", "))
+        ));
+    }
+    let meta: Vec<&str> = [&book.series, &book.publisher]
+        .into_iter()
+        .filter_map(|field| field.as_deref())
+        .collect();
+    if !meta.is_empty() {
+        html.push_str(&format!(
+            "<p class=\"meta\">{}</p>",
+            escape(&meta.join(" · "))
+        ));
+    }
+    if !book.excerpt.is_empty() {
+        html.push_str("<div class=\"excerpt\">");
+        for paragraph in &book.excerpt {
+            html.push_str(&format!("<p>{}</p>", escape(paragraph)));
+        }
+        html.push_str("</div>");
+    }
+    html.push_str("</div></div></body></html>");
+    html
+}
+
+/// MIME type of an encoded image, told by its magic bytes.
+fn image_mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
+        "image/png"
+    } else if bytes.starts_with(b"GIF") {
+        "image/gif"
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
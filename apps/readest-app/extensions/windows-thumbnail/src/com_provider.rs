@@ -3,8 +3,9 @@
 /// Implements IThumbnailProvider and IInitializeWithItem for Windows Shell integration.
 /// This allows Windows Explorer to show book covers as thumbnails for eBook files.
 ///
-/// **Important**: Thumbnails are only shown when Readest.exe is the default application
-/// for the file type.
+/// Thumbnails are shown for every supported eBook file, unless the installer was told to
+/// keep them to files Readest opens (`RequireDefaultApp` on the CLSID key). PDF and TXT
+/// files, which other apps thumbnail too, only get one when Readest is their default app.
 ///
 /// ## CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567890}
 use std::cell::UnsafeCell;
@@ -22,8 +23,8 @@ use windows::Win32::Graphics::Gdi::{
 use windows::Win32::System::Com::{CoTaskMemFree, IClassFactory, IClassFactory_Impl};
 use windows::Win32::System::LibraryLoader::GetModuleFileNameW;
 use windows::Win32::System::Registry::{
-    RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegSetValueExW, HKEY, HKEY_CLASSES_ROOT,
-    KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegGetValueW, RegSetValueExW, HKEY,
+    HKEY_CLASSES_ROOT, KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ, RRF_RT_REG_DWORD,
 };
 use windows::Win32::UI::Shell::{
     AssocQueryStringW, IInitializeWithItem, IInitializeWithItem_Impl, IShellItem,
@@ -34,6 +35,14 @@ use windows_core::BOOL;
 use windows_core::{implement, Ref};
 
 use super::cached_thumbnail_for_path;
+use super::preview_provider::{
+    register_preview_handler, unregister_preview_handler, PreviewHandlerFactory,
+    CLSID_READEST_PREVIEW,
+};
+use super::property_provider::{
+    register_property_handler, unregister_property_handler, PropertyHandlerFactory,
+    CLSID_READEST_PROPERTIES,
+};
 
 // ─────────────────────────────────────────────────────────────────────────────
 // CLSID for Readest Thumbnail Provider
@@ -44,17 +53,21 @@ pub const CLSID_READEST_THUMBNAIL: GUID = GUID::from_u128(0xA1B2C3D4_E5F6_7890_A
 
 /// Supported file extensions
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
-    ".epub", ".mobi", ".azw", ".azw3", ".kf8", ".prc", ".fb2", ".cbz", ".cbr", ".txt",
+    ".epub", ".mobi", ".azw", ".azw3", ".kf8", ".prc", ".fb2", ".cbz", ".cbr", ".pdf", ".txt",
 ];
 
+/// Extensions other apps commonly provide thumbnails for, covered only when Readest
+/// opens them.
+const DEFAULT_APP_ONLY_EXTENSIONS: &[&str] = &[".pdf", ".txt"];
+
 // DLL reference counting
 static DLL_REF_COUNT: AtomicU32 = AtomicU32::new(0);
 static DLL_MODULE_PTR: AtomicIsize = AtomicIsize::new(0);
 
-fn dll_add_ref() {
+pub(crate) fn dll_add_ref() {
     DLL_REF_COUNT.fetch_add(1, Ordering::SeqCst);
 }
-fn dll_release() {
+pub(crate) fn dll_release() {
     DLL_REF_COUNT.fetch_sub(1, Ordering::SeqCst);
 }
 
@@ -62,7 +75,7 @@ fn set_dll_module(h: HMODULE) {
     DLL_MODULE_PTR.store(h.0 as isize, Ordering::SeqCst);
 }
 
-fn get_dll_module() -> Option<HMODULE> {
+pub(crate) fn get_dll_module() -> Option<HMODULE> {
     let ptr = DLL_MODULE_PTR.load(Ordering::SeqCst);
     if ptr == 0 {
         None
@@ -101,13 +114,36 @@ fn is_readest_default_for_extension(ext: &str) -> bool {
     false
 }
 
-/// Check if Readest is the default app for a specific file path.
-fn is_readest_default_for_file(path: &PathBuf) -> bool {
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        let ext_with_dot = format!(".{}", ext.to_lowercase());
-        return is_readest_default_for_extension(&ext_with_dot);
+/// Whether the installer was told to keep thumbnails to files Readest opens.
+fn is_default_app_required() -> bool {
+    let subkey = to_wide(&format!(
+        "CLSID\\{}",
+        clsid_string(&CLSID_READEST_THUMBNAIL)
+    ));
+    let name = to_wide("RequireDefaultApp");
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+
+    unsafe {
+        let result = RegGetValueW(
+            HKEY_CLASSES_ROOT,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(name.as_ptr()),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        );
+        result.is_ok() && value != 0
     }
-    false
+}
+
+/// Check if a thumbnail should be provided for a file with the given extension.
+fn should_provide_for_extension(ext: &str) -> bool {
+    let ext_with_dot = format!(".{}", ext);
+    let default_app_only =
+        DEFAULT_APP_ONLY_EXTENSIONS.contains(&ext_with_dot.as_str()) || is_default_app_required();
+    !default_app_only || is_readest_default_for_extension(&ext_with_dot)
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -115,16 +151,16 @@ fn is_readest_default_for_file(path: &PathBuf) -> bool {
 // ─────────────────────────────────────────────────────────────────────────────
 
 /// Interior mutability wrapper for COM single-threaded apartment
-struct ComCell<T>(UnsafeCell<T>);
+pub(crate) struct ComCell<T>(UnsafeCell<T>);
 
 impl<T> ComCell<T> {
-    fn new(value: T) -> Self {
+    pub(crate) fn new(value: T) -> Self {
         Self(UnsafeCell::new(value))
     }
-    fn get(&self) -> &T {
+    pub(crate) fn get(&self) -> &T {
         unsafe { &*self.0.get() }
     }
-    fn set(&self, value: T) {
+    pub(crate) fn set(&self, value: T) {
         unsafe {
             *self.0.get() = value;
         }
@@ -185,19 +221,19 @@ impl IInitializeWithItem_Impl for ThumbnailProvider_Impl {
 
             CoTaskMemFree(Some(path_pwstr.0 as *const c_void));
 
-            let is_default = is_readest_default_for_file(&path);
-            self.should_provide.set(is_default);
-
-            if !is_default {
-                return Ok(());
-            }
-
             let ext = path
                 .extension()
                 .and_then(|e| e.to_str())
                 .map(|s| s.to_lowercase())
                 .unwrap_or_default();
 
+            let should_provide = !ext.is_empty() && should_provide_for_extension(&ext);
+            self.should_provide.set(should_provide);
+
+            if !should_provide {
+                return Ok(());
+            }
+
             self.file_path.set(Some(path));
             self.file_ext.set(Some(ext));
         }
@@ -355,14 +391,19 @@ pub unsafe extern "system" fn DllGetClassObject(
     }
     *ppv = std::ptr::null_mut();
 
-    if *rclsid != CLSID_READEST_THUMBNAIL {
-        return E_NOINTERFACE;
-    }
     if *riid != IClassFactory::IID && *riid != IUnknown::IID {
         return E_NOINTERFACE;
     }
 
-    let factory: IClassFactory = ThumbnailProviderFactory::new().into();
+    let factory: IClassFactory = if *rclsid == CLSID_READEST_THUMBNAIL {
+        ThumbnailProviderFactory::new().into()
+    } else if *rclsid == CLSID_READEST_PREVIEW {
+        PreviewHandlerFactory::new().into()
+    } else if *rclsid == CLSID_READEST_PROPERTIES {
+        PropertyHandlerFactory::new().into()
+    } else {
+        return E_NOINTERFACE;
+    };
     factory.query(&*riid, ppv)
 }
 
@@ -397,28 +438,28 @@ fn get_dll_path() -> Option<String> {
     }
 }
 
-fn clsid_string() -> String {
+pub(crate) fn clsid_string(clsid: &GUID) -> String {
     format!(
         "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
-        CLSID_READEST_THUMBNAIL.data1,
-        CLSID_READEST_THUMBNAIL.data2,
-        CLSID_READEST_THUMBNAIL.data3,
-        CLSID_READEST_THUMBNAIL.data4[0],
-        CLSID_READEST_THUMBNAIL.data4[1],
-        CLSID_READEST_THUMBNAIL.data4[2],
-        CLSID_READEST_THUMBNAIL.data4[3],
-        CLSID_READEST_THUMBNAIL.data4[4],
-        CLSID_READEST_THUMBNAIL.data4[5],
-        CLSID_READEST_THUMBNAIL.data4[6],
-        CLSID_READEST_THUMBNAIL.data4[7]
+        clsid.data1,
+        clsid.data2,
+        clsid.data3,
+        clsid.data4[0],
+        clsid.data4[1],
+        clsid.data4[2],
+        clsid.data4[3],
+        clsid.data4[4],
+        clsid.data4[5],
+        clsid.data4[6],
+        clsid.data4[7]
     )
 }
 
-fn to_wide(s: &str) -> Vec<u16> {
+pub(crate) fn to_wide(s: &str) -> Vec<u16> {
     s.encode_utf16().chain(std::iter::once(0)).collect()
 }
 
-unsafe fn set_reg_value(key: HKEY, name: &str, value: &str) -> Result<(), HRESULT> {
+pub(crate) unsafe fn set_reg_value(key: HKEY, name: &str, value: &str) -> Result<(), HRESULT> {
     let name_w = to_wide(name);
     let value_w = to_wide(value);
     let bytes: &[u8] = std::slice::from_raw_parts(value_w.as_ptr() as *const u8, value_w.len() * 2);
@@ -429,7 +470,7 @@ unsafe fn set_reg_value(key: HKEY, name: &str, value: &str) -> Result<(), HRESUL
     }
 }
 
-unsafe fn create_reg_key(parent: HKEY, subkey: &str) -> Result<HKEY, HRESULT> {
+pub(crate) unsafe fn create_reg_key(parent: HKEY, subkey: &str) -> Result<HKEY, HRESULT> {
     let subkey_w = to_wide(subkey);
     let mut hkey = HKEY::default();
     let result = RegCreateKeyExW(
@@ -452,7 +493,7 @@ unsafe fn create_reg_key(parent: HKEY, subkey: &str) -> Result<HKEY, HRESULT> {
 
 unsafe fn register_server_impl() -> Result<(), HRESULT> {
     let dll_path = get_dll_path().ok_or(E_FAIL)?;
-    let clsid = clsid_string();
+    let clsid = clsid_string(&CLSID_READEST_THUMBNAIL);
 
     // CLSID key
     let clsid_key = create_reg_key(HKEY_CLASSES_ROOT, &format!("CLSID\\{}", clsid))?;
@@ -487,11 +528,13 @@ unsafe fn register_server_impl() -> Result<(), HRESULT> {
             let _ = RegCloseKey(ext_shellex_key);
         }
     }
-    Ok(())
+
+    register_preview_handler(&dll_path)?;
+    register_property_handler(&dll_path)
 }
 
 unsafe fn unregister_server_impl() -> Result<(), HRESULT> {
-    let clsid = clsid_string();
+    let clsid = clsid_string(&CLSID_READEST_THUMBNAIL);
     let clsid_path = to_wide(&format!("CLSID\\{}", clsid));
     let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(clsid_path.as_ptr()));
 
@@ -502,5 +545,8 @@ unsafe fn unregister_server_impl() -> Result<(), HRESULT> {
         ));
         let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(ext_path.as_ptr()));
     }
+
+    unregister_preview_handler();
+    unregister_property_handler();
     Ok(())
 }
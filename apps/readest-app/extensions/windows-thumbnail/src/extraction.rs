@@ -1,16 +1,23 @@
-/// Cover image extraction for various eBook formats
+/// Thumbnails and previews for Explorer
 ///
-/// Supports: EPUB, MOBI/AZW3/KF8, FB2, CBZ/CBR, TXT
+/// Covers, metadata and excerpts come from the shared `book_extraction`
+/// crate; this module adds PDF and TXT covers, the Readest overlay and the
+/// thumbnail cache.
+///
+/// Supports: EPUB, MOBI/AZW3/KF8, FB2, CBZ/CBR, PDF, TXT
 use anyhow::{anyhow, Result};
-use base64::engine::general_purpose;
-use base64::Engine as _;
 use directories_next::ProjectDirs;
 use image::{imageops, DynamicImage, Rgba};
 use md5::Context;
 use once_cell::sync::Lazy;
 use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
-use zip::ZipArchive;
+use windows::core::HSTRING;
+use windows::Data::Pdf::{PdfDocument, PdfPageRenderOptions};
+use windows::Storage::StorageFile;
+use windows::Storage::Streams::{DataReader, InMemoryRandomAccessStream};
+
+use super::progress::{draw_progress_badge, progress_for_path, Progress};
 
 /// Thumbnail cache directory (per-user)
 static CACHE_DIR: Lazy<Option<std::path::PathBuf>> = Lazy::new(|| {
@@ -22,316 +29,63 @@ static CACHE_DIR: Lazy<Option<std::path::PathBuf>> = Lazy::new(|| {
 });
 
 // ─────────────────────────────────────────────────────────────────────────────
-// EPUB extraction
-// ─────────────────────────────────────────────────────────────────────────────
-
-/// Extract cover image bytes from an EPUB file.
-pub fn extract_epub_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
-    let mut archive = ZipArchive::new(reader)?;
-
-    // Pass 1: Look for files with "cover" in the name
-    let mut candidates: Vec<(usize, String, u64)> = Vec::new();
-    for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let name = file.name().to_lowercase();
-        let size = file.size();
-        drop(file);
-
-        if is_image_extension(&name) && (name.contains("cover") || name.contains("front")) {
-            candidates.push((i, name, size));
-        }
-    }
-
-    // Sort by priority: exact "cover" match first, then by size
-    if !candidates.is_empty() {
-        candidates.sort_by(|a, b| {
-            let a_exact = a.1.contains("cover.") || a.1.ends_with("cover");
-            let b_exact = b.1.contains("cover.") || b.1.ends_with("cover");
-            match (a_exact, b_exact) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => b.2.cmp(&a.2),
-            }
-        });
-
-        let idx = candidates[0].0;
-        let mut file = archive.by_index(idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
-    }
-
-    // Pass 2: Parse container.xml to find OPF, then parse OPF for cover
-    let container_xml = read_zip_file_to_string(&mut archive, "META-INF/container.xml");
-    if let Ok(xml) = container_xml {
-        if let Some(rootfile) = extract_attribute(&xml, "rootfile", "full-path") {
-            let opf_content = read_zip_file_to_string(&mut archive, &rootfile);
-            if let Ok(opf) = opf_content {
-                if let Some(cover_id) = find_cover_id_in_opf(&opf) {
-                    if let Some(href) = find_href_by_id_in_opf(&opf, &cover_id) {
-                        let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
-                        let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
-                        if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
-                            return Ok(bytes);
-                        }
-                    }
-                }
-                if let Some(href) = find_first_image_in_manifest(&opf) {
-                    let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
-                    let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
-                    if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
-                        return Ok(bytes);
-                    }
-                }
-            }
-        }
-    }
-
-    // Pass 3: Just grab the largest image file
-    let mut largest: Option<(usize, u64)> = None;
-    for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let name = file.name().to_lowercase();
-        let size = file.size();
-        drop(file);
-
-        if is_image_extension(&name) && (largest.is_none() || size > largest.unwrap().1) {
-            largest = Some((i, size));
-        }
-    }
-
-    if let Some((idx, _)) = largest {
-        let mut file = archive.by_index(idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
-    }
-
-    Err(anyhow!("No cover image found in EPUB"))
-}
-
-// ─────────────────────────────────────────────────────────────────────────────
-// MOBI/AZW3/KF8 extraction
+// EPUB preview
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Extract cover image from MOBI/AZW3/KF8 files.
-pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>> {
-    let mut header = [0u8; 78];
-    reader.read_exact(&mut header)?;
-
-    if &header[60..68] != b"BOOKMOBI" {
-        return Err(anyhow!("Not a valid MOBI file"));
-    }
-
-    let num_records = u16::from_be_bytes([header[76], header[77]]) as usize;
-
-    let mut record_offsets: Vec<u32> = Vec::with_capacity(num_records);
-    for _ in 0..num_records {
-        let mut rec = [0u8; 8];
-        reader.read_exact(&mut rec)?;
-        record_offsets.push(u32::from_be_bytes([rec[0], rec[1], rec[2], rec[3]]));
-    }
-
-    if record_offsets.is_empty() {
-        return Err(anyhow!("No records in MOBI file"));
-    }
-
-    reader.seek(SeekFrom::Start(record_offsets[0] as u64))?;
-    let mut mobi_header = [0u8; 256];
-    reader.read_exact(&mut mobi_header)?;
-
-    if &mobi_header[16..20] != b"MOBI" {
-        return Err(anyhow!("Invalid MOBI header"));
-    }
-
-    let header_length = u32::from_be_bytes([
-        mobi_header[20],
-        mobi_header[21],
-        mobi_header[22],
-        mobi_header[23],
-    ]) as usize;
-
-    let exth_flags = u32::from_be_bytes([
-        mobi_header[128],
-        mobi_header[129],
-        mobi_header[130],
-        mobi_header[131],
-    ]);
-    if exth_flags & 0x40 == 0 {
-        return Err(anyhow!("No EXTH header in MOBI file"));
-    }
-
-    let exth_offset = record_offsets[0] as u64 + 16 + header_length as u64;
-    reader.seek(SeekFrom::Start(exth_offset))?;
-
-    let mut exth_magic = [0u8; 4];
-    reader.read_exact(&mut exth_magic)?;
-    if &exth_magic != b"EXTH" {
-        return Err(anyhow!("EXTH header not found"));
-    }
-
-    let mut exth_len_bytes = [0u8; 4];
-    reader.read_exact(&mut exth_len_bytes)?;
-
-    let mut exth_count_bytes = [0u8; 4];
-    reader.read_exact(&mut exth_count_bytes)?;
-    let exth_count = u32::from_be_bytes(exth_count_bytes) as usize;
-
-    let mut cover_offset: Option<u32> = None;
-    let first_img_idx = u32::from_be_bytes([
-        mobi_header[108],
-        mobi_header[109],
-        mobi_header[110],
-        mobi_header[111],
-    ]);
-
-    for _ in 0..exth_count {
-        let mut rec_header = [0u8; 8];
-        if reader.read_exact(&mut rec_header).is_err() {
-            break;
-        }
-        let rec_type =
-            u32::from_be_bytes([rec_header[0], rec_header[1], rec_header[2], rec_header[3]]);
-        let rec_len =
-            u32::from_be_bytes([rec_header[4], rec_header[5], rec_header[6], rec_header[7]])
-                as usize;
-
-        let data_len = rec_len.saturating_sub(8);
-        let mut data = vec![0u8; data_len];
-        if reader.read_exact(&mut data).is_err() {
-            break;
-        }
-
-        if rec_type == 201 && data_len >= 4 {
-            cover_offset = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
-        }
-    }
-
-    let cover_record_idx = if let Some(offset) = cover_offset {
-        first_img_idx + offset
-    } else {
-        first_img_idx
-    };
-
-    if cover_record_idx as usize >= record_offsets.len() {
-        return Err(anyhow!("Cover record index out of bounds"));
-    }
-
-    let start = record_offsets[cover_record_idx as usize] as u64;
-    let end = if (cover_record_idx as usize + 1) < record_offsets.len() {
-        record_offsets[cover_record_idx as usize + 1] as u64
-    } else {
-        reader.seek(SeekFrom::End(0))?;
-        reader.stream_position()?
-    };
-
-    let len = (end - start) as usize;
-    reader.seek(SeekFrom::Start(start))?;
-    let mut cover_data = vec![0u8; len];
-    reader.read_exact(&mut cover_data)?;
-
-    if cover_data.starts_with(&[0xFF, 0xD8, 0xFF])
-        || cover_data.starts_with(&[0x89, 0x50, 0x4E, 0x47])
-        || cover_data.starts_with(b"GIF")
-    {
-        return Ok(cover_data);
-    }
-
-    Err(anyhow!("No valid cover image found in MOBI"))
+/// What the preview pane shows for an EPUB.
+pub struct EpubPreview {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub excerpt: String,
+    pub cover: Option<Vec<u8>>,
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// CBZ extraction
-// ─────────────────────────────────────────────────────────────────────────────
-
-/// Extract cover image from CBZ (comic book ZIP) file.
-pub fn extract_cbz_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
-    let mut archive = ZipArchive::new(reader)?;
-
-    let mut images: Vec<(usize, String)> = Vec::new();
-    for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let name = file.name().to_string();
-        drop(file);
-
-        if is_image_extension(&name.to_lowercase()) {
-            images.push((i, name));
-        }
-    }
-
-    images.sort_by(|a, b| a.1.cmp(&b.1));
-
-    if let Some((idx, _)) = images.first() {
-        let mut file = archive.by_index(*idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
-    }
-
-    Err(anyhow!("No images found in CBZ"))
+/// Extract the cover, title, author and opening text of an EPUB file.
+pub fn extract_epub_preview(path: &Path) -> Result<EpubPreview> {
+    let preview = book_extraction::extract_preview(path, "epub")?;
+    Ok(EpubPreview {
+        title: preview.title,
+        author: (!preview.authors.is_empty()).then(|| preview.authors.join(", ")),
+        excerpt: preview.excerpt.join("\r\n\r\n"),
+        cover: preview.cover,
+    })
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// FB2 extraction
+// PDF extraction
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Extract cover image from FB2 (FictionBook) file.
-pub fn extract_fb2_cover_bytes<R: Read>(mut reader: R) -> Result<Vec<u8>> {
-    let mut content = String::new();
-    reader.read_to_string(&mut content)?;
-
-    let cover_id = if let Some(start) = content.find("<coverpage>") {
-        let end = content[start..].find("</coverpage>").unwrap_or(500);
-        let coverpage = &content[start..start + end];
-        if let Some(href_pos) = coverpage.find("href=\"#") {
-            let id_start = href_pos + 7;
-            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
-            Some(coverpage[id_start..id_start + id_end].to_string())
-        } else if let Some(href_pos) = coverpage.find("l:href=\"#") {
-            let id_start = href_pos + 9;
-            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
-            Some(coverpage[id_start..id_start + id_end].to_string())
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-
-    let search_pattern = if let Some(ref id) = cover_id {
-        format!("<binary id=\"{}\"", id)
-    } else {
-        "<binary".to_string()
-    };
-
-    if let Some(pos) = content.find(&search_pattern) {
-        if let Some(tag_end) = content[pos..].find('>') {
-            let data_start = pos + tag_end + 1;
-            if let Some(data_end) = content[data_start..].find("</binary>") {
-                let b64_data = content[data_start..data_start + data_end].trim();
-                let b64_clean: String = b64_data.chars().filter(|c| !c.is_whitespace()).collect();
-                let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
-                return Ok(bytes);
-            }
-        }
-    }
-
-    if cover_id.is_some() {
-        if let Some(pos) = content.find("<binary") {
-            if let Some(tag_end) = content[pos..].find('>') {
-                let data_start = pos + tag_end + 1;
-                if let Some(data_end) = content[data_start..].find("</binary>") {
-                    let b64_data = content[data_start..data_start + data_end].trim();
-                    let b64_clean: String =
-                        b64_data.chars().filter(|c| !c.is_whitespace()).collect();
-                    let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
-                    return Ok(bytes);
-                }
-            }
-        }
-    }
-
-    Err(anyhow!("No cover image found in FB2"))
+/// Widest the first page of a PDF is rendered, enough for the largest
+/// Explorer icons; smaller thumbnails are scaled down from it.
+const PDF_RENDER_WIDTH: u32 = 1024;
+
+/// Render the first page of a PDF to PNG with the system PDF renderer
+/// (Windows.Data.Pdf). Encrypted documents fail to load and get no cover.
+pub fn extract_pdf_cover_bytes(path: &Path) -> Result<Vec<u8>> {
+    // Explorer hands over absolute paths, which is what StorageFile takes.
+    let file = StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_os_str()))?.get()?;
+    let document = PdfDocument::LoadFromFileAsync(&file)?.get()?;
+    if document.PageCount()? == 0 {
+        return Err(anyhow!("PDF has no pages"));
+    }
+    let page = document.GetPage(0)?;
+
+    let options = PdfPageRenderOptions::new()?;
+    let page_width = page.Size()?.Width;
+    if page_width > PDF_RENDER_WIDTH as f32 {
+        options.SetDestinationWidth(PDF_RENDER_WIDTH)?;
+    }
+    // PNG is the default encoding.
+    let stream = InMemoryRandomAccessStream::new()?;
+    page.RenderWithOptionsToStreamAsync(&stream, &options)?
+        .get()?;
+
+    let size = stream.Size()? as u32;
+    let reader = DataReader::CreateDataReader(&stream.GetInputStreamAt(0)?)?;
+    reader.LoadAsync(size)?.get()?;
+    let mut buf = vec![0u8; size as usize];
+    reader.ReadBytes(&mut buf)?;
+    Ok(buf)
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -365,14 +119,12 @@ pub fn extract_txt_cover_bytes<R: Read>(mut reader: R, size: u32) -> Result<Vec<
 
 /// Extract cover image bytes based on file extension.
 pub fn extract_cover_bytes_by_ext(path: &Path, ext: &str) -> Result<Vec<u8>> {
-    let file = std::fs::File::open(path)?;
     match ext.to_lowercase().as_str() {
-        "epub" => extract_epub_cover_bytes(file),
-        "mobi" | "azw" | "azw3" | "kf8" | "prc" => extract_mobi_cover_bytes(file),
-        "cbz" | "cbr" => extract_cbz_cover_bytes(file),
-        "fb2" => extract_fb2_cover_bytes(file),
-        "txt" => extract_txt_cover_bytes(file, 256),
-        _ => Err(anyhow!("Unsupported format: {}", ext)),
+        "pdf" => extract_pdf_cover_bytes(path),
+        "txt" => extract_txt_cover_bytes(std::fs::File::open(path)?, 256),
+        // Only CBR files that are really zip archives get a cover.
+        "cbr" => book_extraction::extract_cbz_cover_bytes(std::fs::File::open(path)?),
+        ext => book_extraction::extract_cover_bytes_by_ext(path, ext),
     }
 }
 
@@ -380,8 +132,13 @@ pub fn extract_cover_bytes_by_ext(path: &Path, ext: &str) -> Result<Vec<u8>> {
 // Thumbnail creation with overlay
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Create a thumbnail from cover image bytes with Readest icon overlay.
-pub fn create_thumbnail_with_overlay(cover_bytes: &[u8], requested_size: u32) -> Result<Vec<u8>> {
+/// Create a thumbnail from cover image bytes with Readest icon overlay and,
+/// for books in the library, a reading progress badge.
+pub fn create_thumbnail_with_overlay(
+    cover_bytes: &[u8],
+    requested_size: u32,
+    progress: Option<Progress>,
+) -> Result<Vec<u8>> {
     let img = image::load_from_memory(cover_bytes)?;
     let thumbnail = img.thumbnail(requested_size, requested_size);
 
@@ -426,6 +183,10 @@ pub fn create_thumbnail_with_overlay(cover_bytes: &[u8], requested_size: u32) ->
         }
     }
 
+    if let Some(progress) = progress {
+        draw_progress_badge(&mut base, progress, requested_size);
+    }
+
     let mut out = Vec::new();
     DynamicImage::ImageRgba8(base).write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
     Ok(out)
@@ -502,6 +263,10 @@ pub fn cached_thumbnail_for_path(path: &Path, ext: &str, size: u32) -> Result<Ve
         hasher.consume(&buf);
     }
 
+    // Progress changes the badge, not the file, so it goes into the key too
+    let progress = progress_for_path(path);
+    hasher.consume([progress.map_or(0, Progress::cache_key)]);
+
     let digest = hasher.finalize();
     let key = format!("{:x}.png", digest);
 
@@ -515,7 +280,7 @@ pub fn cached_thumbnail_for_path(path: &Path, ext: &str, size: u32) -> Result<Ve
     }
 
     let cover = extract_cover_bytes_by_ext(path, ext)?;
-    let thumbnail = create_thumbnail_with_overlay(&cover, size)?;
+    let thumbnail = create_thumbnail_with_overlay(&cover, size, progress)?;
 
     if let Some(ref dir) = *CACHE_DIR {
         let cache_path = dir.join(&key);
@@ -524,125 +289,3 @@ pub fn cached_thumbnail_for_path(path: &Path, ext: &str, size: u32) -> Result<Ve
 
     Ok(thumbnail)
 }
-
-// ─────────────────────────────────────────────────────────────────────────────
-// Helper functions
-// ─────────────────────────────────────────────────────────────────────────────
-
-fn is_image_extension(name: &str) -> bool {
-    name.ends_with(".jpg")
-        || name.ends_with(".jpeg")
-        || name.ends_with(".png")
-        || name.ends_with(".gif")
-        || name.ends_with(".webp")
-        || name.ends_with(".bmp")
-}
-
-fn read_zip_file_to_string<R: Read + Seek>(
-    archive: &mut ZipArchive<R>,
-    name: &str,
-) -> Result<String> {
-    let mut file = archive.by_name(name)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    Ok(content)
-}
-
-fn read_zip_file_to_bytes<R: Read + Seek>(
-    archive: &mut ZipArchive<R>,
-    name: &str,
-) -> Result<Vec<u8>> {
-    let mut file = archive.by_name(name)?;
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
-    Ok(buf)
-}
-
-fn extract_attribute(xml: &str, tag: &str, attr: &str) -> Option<String> {
-    let pattern = format!("<{}", tag);
-    if let Some(tag_pos) = xml.find(&pattern) {
-        let tag_end = xml[tag_pos..].find('>').unwrap_or(500) + tag_pos;
-        let tag_content = &xml[tag_pos..tag_end];
-
-        let attr_pattern = format!("{}=\"", attr);
-        if let Some(attr_pos) = tag_content.find(&attr_pattern) {
-            let value_start = attr_pos + attr_pattern.len();
-            if let Some(value_end) = tag_content[value_start..].find('"') {
-                return Some(tag_content[value_start..value_start + value_end].to_string());
-            }
-        }
-    }
-    None
-}
-
-fn find_cover_id_in_opf(opf: &str) -> Option<String> {
-    if let Some(pos) = opf.find("name=\"cover\"") {
-        let window_start = pos.saturating_sub(50);
-        let window_end = (pos + 100).min(opf.len());
-        let window = &opf[window_start..window_end];
-
-        if let Some(content_pos) = window.find("content=\"") {
-            let start = content_pos + 9;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
-            }
-        }
-    }
-
-    if let Some(pos) = opf.find("properties=\"cover-image\"") {
-        let window_start = pos.saturating_sub(200);
-        let window_end = pos;
-        let window = &opf[window_start..window_end];
-
-        if let Some(id_pos) = window.rfind("id=\"") {
-            let start = id_pos + 4;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
-            }
-        }
-    }
-
-    None
-}
-
-fn find_href_by_id_in_opf(opf: &str, id: &str) -> Option<String> {
-    let pattern = format!("id=\"{}\"", id);
-    if let Some(pos) = opf.find(&pattern) {
-        let window_start = pos.saturating_sub(10);
-        let window_end = (pos + 200).min(opf.len());
-        let window = &opf[window_start..window_end];
-
-        if let Some(href_pos) = window.find("href=\"") {
-            let start = href_pos + 6;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
-            }
-        }
-    }
-    None
-}
-
-fn find_first_image_in_manifest(opf: &str) -> Option<String> {
-    let manifest_start = opf.find("<manifest")?;
-    let manifest_end = opf[manifest_start..]
-        .find("</manifest>")
-        .map(|e| manifest_start + e)?;
-    let manifest = &opf[manifest_start..manifest_end];
-
-    for media_type in ["image/jpeg", "image/png", "image/gif", "image/webp"] {
-        let pattern = format!("media-type=\"{}\"", media_type);
-        if let Some(pos) = manifest.find(&pattern) {
-            let window_start = pos.saturating_sub(200);
-            let window = &manifest[window_start..pos];
-
-            if let Some(href_pos) = window.rfind("href=\"") {
-                let start = href_pos + 6;
-                if let Some(end) = window[start..].find('"') {
-                    return Some(window[start..start + end].to_string());
-                }
-            }
-        }
-    }
-
-    None
-}
@@ -1,13 +1,20 @@
 //! Windows Thumbnail Provider for Readest
 //!
 //! This module provides Windows Explorer thumbnail support for eBook files.
-//! Thumbnails are only shown when Readest is set as the default application.
+//! EPUB files also get a preview pane handler showing the cover, title, author and
+//! opening text, and EPUB, MOBI and FB2 files a property handler for the Title,
+//! Author, Series and Publisher columns.
 //!
-//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, CBZ, CBR
+//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, CBZ, CBR, PDF
+//!
+//! Books in the Readest library also get a badge with their reading progress.
 
 #![allow(non_snake_case)]
 
 mod com_provider;
 mod extraction;
+mod preview_provider;
+mod progress;
+mod property_provider;
 
 pub use extraction::*;
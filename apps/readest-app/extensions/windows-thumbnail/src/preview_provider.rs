@@ -0,0 +1,663 @@
+/// Windows Preview Handler for Readest
+///
+/// Implements IPreviewHandler, IInitializeWithFile, IObjectWithSite and IOleWindow so that
+/// selecting an EPUB in Explorer shows its cover, title, author and opening text in the
+/// preview pane. Explorer hosts the handler in prevhost.exe, out of its own process.
+///
+/// ## CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567891}
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use windows::core::{w, IUnknown, Interface, GUID, HRESULT, PCWSTR};
+use windows::Win32::Foundation::{
+    CLASS_E_NOAGGREGATION, COLORREF, E_FAIL, E_INVALIDARG, E_NOTIMPL, HINSTANCE, HWND, LPARAM,
+    LRESULT, RECT, S_FALSE, WPARAM,
+};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateFontIndirectW, DeleteObject, DrawTextW, EndPaint, FillRect, GetSysColor,
+    GetSysColorBrush, InvalidateRect, SelectObject, SetBkMode, SetStretchBltMode, SetTextColor,
+    StretchDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, COLOR_GRAYTEXT, COLOR_WINDOW,
+    COLOR_WINDOWTEXT, DIB_RGB_COLORS, DRAW_TEXT_FORMAT, DT_CALCRECT, DT_CENTER, DT_EDITCONTROL,
+    DT_END_ELLIPSIS, DT_LEFT, DT_NOPREFIX, DT_WORDBREAK, HALFTONE, HDC, HFONT, PAINTSTRUCT,
+    SRCCOPY, SYS_COLOR_INDEX, TRANSPARENT,
+};
+use windows::Win32::System::Com::{IClassFactory, IClassFactory_Impl};
+use windows::Win32::System::Ole::{
+    IObjectWithSite, IObjectWithSite_Impl, IOleWindow, IOleWindow_Impl,
+};
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegDeleteKeyValueW, RegDeleteTreeW, HKEY_CLASSES_ROOT, HKEY_LOCAL_MACHINE,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetFocus, SetFocus};
+use windows::Win32::UI::Shell::PropertiesSystem::{IInitializeWithFile, IInitializeWithFile_Impl};
+use windows::Win32::UI::Shell::{IPreviewHandler, IPreviewHandlerFrame, IPreviewHandler_Impl};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, GetWindowLongPtrW, LoadCursorW,
+    RegisterClassW, SetParent, SetWindowLongPtrW, SetWindowPos, SystemParametersInfoW,
+    CREATESTRUCTW, GWLP_USERDATA, IDC_ARROW, MSG, NONCLIENTMETRICSW, SPI_GETNONCLIENTMETRICS,
+    SWP_NOACTIVATE, SWP_NOZORDER, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WINDOW_EX_STYLE,
+    WM_NCCREATE, WM_PAINT, WM_SIZE, WNDCLASSW, WS_CHILD, WS_CLIPSIBLINGS, WS_VISIBLE,
+};
+use windows_core::BOOL;
+use windows_core::{implement, Ref};
+
+use super::com_provider::{
+    clsid_string, create_reg_key, dll_add_ref, dll_release, get_dll_module, set_reg_value, to_wide,
+    ComCell,
+};
+use super::extract_epub_preview;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CLSID for Readest Preview Handler
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567891}
+pub const CLSID_READEST_PREVIEW: GUID = GUID::from_u128(0xA1B2C3D4_E5F6_7890_ABCD_EF1234567891);
+
+/// Supported file extensions
+pub const PREVIEW_EXTENSIONS: &[&str] = &[".epub"];
+
+/// Shell extension key preview handlers are registered under.
+const SHELL_PREVIEW_HANDLER: &str = "{8895b1c6-b41f-4c1c-a562-0d564250836f}";
+
+/// AppID of prevhost.exe, the surrogate process preview handlers run in.
+const PREVHOST_APPID: &str = "{6d2b5079-2f0b-48dd-ab7f-97cec514d30b}";
+
+const PREVIEW_NAME: &str = "Readest Preview Handler";
+const WINDOW_CLASS: PCWSTR = w!("ReadestPreviewWindow");
+
+/// Largest cover decoded for the pane, in pixels per side.
+const PREVIEW_COVER_SIZE: u32 = 600;
+/// Gap around and between the parts of the preview, in pixels.
+const MARGIN: i32 = 16;
+const TITLE_WEIGHT: i32 = 700;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Preview content
+// ─────────────────────────────────────────────────────────────────────────────
+
+struct CoverBitmap {
+    width: u32,
+    height: u32,
+    /// Top-down BGRA rows, flattened onto white.
+    bgra: Vec<u8>,
+}
+
+struct PreviewContent {
+    title: String,
+    author: Option<String>,
+    excerpt: String,
+    cover: Option<CoverBitmap>,
+}
+
+impl PreviewContent {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let preview = extract_epub_preview(path)?;
+        let title = preview.title.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        Ok(Self {
+            title,
+            author: preview.author,
+            excerpt: preview.excerpt,
+            cover: preview.cover.as_deref().and_then(cover_bitmap),
+        })
+    }
+}
+
+fn cover_bitmap(bytes: &[u8]) -> Option<CoverBitmap> {
+    let img = image::load_from_memory(bytes)
+        .ok()?
+        .thumbnail(PREVIEW_COVER_SIZE, PREVIEW_COVER_SIZE)
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+
+    let mut bgra = Vec::with_capacity((width * height * 4) as usize);
+    for pixel in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let over_white = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        bgra.extend_from_slice(&[over_white(b), over_white(g), over_white(r), 255]);
+    }
+    Some(CoverBitmap {
+        width,
+        height,
+        bgra,
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Preview window
+// ─────────────────────────────────────────────────────────────────────────────
+
+static REGISTER_CLASS: Once = Once::new();
+
+fn register_window_class(instance: HINSTANCE) {
+    REGISTER_CLASS.call_once(|| unsafe {
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(preview_wndproc),
+            hInstance: instance,
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: GetSysColorBrush(COLOR_WINDOW),
+            lpszClassName: WINDOW_CLASS,
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+    });
+}
+
+/// The window reads its content through `GWLP_USERDATA`; the handler owns it and
+/// destroys the window before dropping it.
+unsafe extern "system" fn preview_wndproc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_NCCREATE => {
+            let create = &*(lparam.0 as *const CREATESTRUCTW);
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, create.lpCreateParams as isize);
+        }
+        WM_PAINT => {
+            let content = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const PreviewContent;
+            if let Some(content) = content.as_ref() {
+                paint(hwnd, content);
+                return LRESULT(0);
+            }
+        }
+        WM_SIZE => {
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+        _ => {}
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// The message font, and a larger bold one for the title.
+struct Fonts {
+    title: HFONT,
+    body: HFONT,
+}
+
+impl Fonts {
+    unsafe fn new() -> Self {
+        let mut metrics = NONCLIENTMETRICSW {
+            cbSize: std::mem::size_of::<NONCLIENTMETRICSW>() as u32,
+            ..Default::default()
+        };
+        let _ = SystemParametersInfoW(
+            SPI_GETNONCLIENTMETRICS,
+            metrics.cbSize,
+            Some(&mut metrics as *mut NONCLIENTMETRICSW as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+        let body = metrics.lfMessageFont;
+        let mut title = body;
+        title.lfHeight = body.lfHeight * 3 / 2;
+        title.lfWeight = TITLE_WEIGHT;
+        Self {
+            title: CreateFontIndirectW(&title),
+            body: CreateFontIndirectW(&body),
+        }
+    }
+}
+
+impl Drop for Fonts {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeleteObject(self.title.into());
+            let _ = DeleteObject(self.body.into());
+        }
+    }
+}
+
+/// Cover at the top, then title, author and as much of the excerpt as fits.
+unsafe fn paint(hwnd: HWND, content: &PreviewContent) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+
+    let mut client = RECT::default();
+    let _ = GetClientRect(hwnd, &mut client);
+    FillRect(hdc, &client, GetSysColorBrush(COLOR_WINDOW));
+    SetBkMode(hdc, TRANSPARENT);
+
+    let (left, right, bottom) = (
+        client.left + MARGIN,
+        client.right - MARGIN,
+        client.bottom - MARGIN,
+    );
+    let mut top = client.top + MARGIN;
+
+    if right > left {
+        if let Some(cover) = &content.cover {
+            let max_w = right - left;
+            let max_h = (client.bottom - client.top) * 2 / 5;
+            let scale = (max_w as f32 / cover.width as f32)
+                .min(max_h as f32 / cover.height as f32)
+                .min(1.0);
+            let (w, h) = (
+                (cover.width as f32 * scale) as i32,
+                (cover.height as f32 * scale) as i32,
+            );
+            if w > 0 && h > 0 {
+                draw_cover(hdc, cover, left + (max_w - w) / 2, top, w, h);
+                top += h + MARGIN;
+            }
+        }
+
+        let fonts = Fonts::new();
+        let text_rect = |top: i32| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        top += draw_text(
+            hdc,
+            fonts.title,
+            COLOR_WINDOWTEXT,
+            &content.title,
+            text_rect(top),
+            DT_CENTER,
+        );
+        if let Some(author) = &content.author {
+            top += draw_text(
+                hdc,
+                fonts.body,
+                COLOR_GRAYTEXT,
+                author,
+                text_rect(top),
+                DT_CENTER,
+            );
+        }
+        top += MARGIN;
+        draw_text(
+            hdc,
+            fonts.body,
+            COLOR_WINDOWTEXT,
+            &content.excerpt,
+            text_rect(top),
+            DT_LEFT,
+        );
+    }
+
+    let _ = EndPaint(hwnd, &ps);
+}
+
+unsafe fn draw_cover(hdc: HDC, cover: &CoverBitmap, x: i32, y: i32, w: i32, h: i32) {
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: cover.width as i32,
+            biHeight: -(cover.height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    SetStretchBltMode(hdc, HALFTONE);
+    StretchDIBits(
+        hdc,
+        x,
+        y,
+        w,
+        h,
+        0,
+        0,
+        cover.width as i32,
+        cover.height as i32,
+        Some(cover.bgra.as_ptr() as *const c_void),
+        &bmi,
+        DIB_RGB_COLORS,
+        SRCCOPY,
+    );
+}
+
+/// Draw word-wrapped `text` from the top of `rect`, cut off with an ellipsis at its
+/// bottom, and return the height used.
+unsafe fn draw_text(
+    hdc: HDC,
+    font: HFONT,
+    color: SYS_COLOR_INDEX,
+    text: &str,
+    rect: RECT,
+    align: DRAW_TEXT_FORMAT,
+) -> i32 {
+    if text.is_empty() || rect.bottom <= rect.top {
+        return 0;
+    }
+    let mut wide: Vec<u16> = text.encode_utf16().collect();
+    let previous = SelectObject(hdc, font.into());
+    SetTextColor(hdc, COLORREF(GetSysColor(color)));
+
+    let format = align | DT_WORDBREAK | DT_EDITCONTROL | DT_NOPREFIX;
+    let mut measured = rect;
+    DrawTextW(hdc, &mut wide, &mut measured, format | DT_CALCRECT);
+    let mut bounds = RECT {
+        bottom: measured.bottom.min(rect.bottom),
+        ..rect
+    };
+    DrawTextW(hdc, &mut wide, &mut bounds, format | DT_END_ELLIPSIS);
+
+    SelectObject(hdc, previous);
+    bounds.bottom - bounds.top
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PreviewHandler
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[implement(IPreviewHandler, IInitializeWithFile, IObjectWithSite, IOleWindow)]
+pub struct PreviewHandler {
+    file_path: ComCell<Option<PathBuf>>,
+    parent: ComCell<HWND>,
+    rect: ComCell<RECT>,
+    window: ComCell<Option<HWND>>,
+    content: ComCell<Option<Box<PreviewContent>>>,
+    site: ComCell<Option<IUnknown>>,
+    frame: ComCell<Option<IPreviewHandlerFrame>>,
+}
+
+impl PreviewHandler {
+    pub fn new() -> Self {
+        dll_add_ref();
+        Self {
+            file_path: ComCell::new(None),
+            parent: ComCell::new(HWND::default()),
+            rect: ComCell::new(RECT::default()),
+            window: ComCell::new(None),
+            content: ComCell::new(None),
+            site: ComCell::new(None),
+            frame: ComCell::new(None),
+        }
+    }
+
+    fn resize_window(&self) {
+        if let Some(window) = *self.window.get() {
+            let rect = self.rect.get();
+            unsafe {
+                let _ = SetWindowPos(
+                    window,
+                    None,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+        }
+    }
+
+    fn destroy_window(&self) {
+        if let Some(window) = *self.window.get() {
+            unsafe {
+                let _ = DestroyWindow(window);
+            }
+        }
+        self.window.set(None);
+        self.content.set(None);
+    }
+}
+
+impl Default for PreviewHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PreviewHandler {
+    fn drop(&mut self) {
+        self.destroy_window();
+        dll_release();
+    }
+}
+
+impl IInitializeWithFile_Impl for PreviewHandler_Impl {
+    fn Initialize(&self, pszfilepath: &PCWSTR, _grfmode: u32) -> windows::core::Result<()> {
+        let path = unsafe { pszfilepath.to_string() }.map_err(|_| E_INVALIDARG)?;
+        self.file_path.set(Some(PathBuf::from(path)));
+        Ok(())
+    }
+}
+
+impl IPreviewHandler_Impl for PreviewHandler_Impl {
+    fn SetWindow(&self, hwnd: HWND, prc: *const RECT) -> windows::core::Result<()> {
+        if prc.is_null() {
+            return Err(E_INVALIDARG.into());
+        }
+        self.parent.set(hwnd);
+        self.rect.set(unsafe { *prc });
+        if let Some(window) = *self.window.get() {
+            unsafe {
+                let _ = SetParent(window, Some(hwnd));
+            }
+            self.resize_window();
+        }
+        Ok(())
+    }
+
+    fn SetRect(&self, prc: *const RECT) -> windows::core::Result<()> {
+        if prc.is_null() {
+            return Err(E_INVALIDARG.into());
+        }
+        self.rect.set(unsafe { *prc });
+        self.resize_window();
+        Ok(())
+    }
+
+    fn DoPreview(&self) -> windows::core::Result<()> {
+        if self.window.get().is_some() {
+            return Ok(());
+        }
+        let path = self.file_path.get().as_ref().ok_or(E_FAIL)?;
+        let content = Box::new(PreviewContent::load(path).map_err(|_| E_FAIL)?);
+
+        let module = get_dll_module().ok_or(E_FAIL)?;
+        let instance = HINSTANCE(module.0);
+        register_window_class(instance);
+
+        let rect = *self.rect.get();
+        let window = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                WINDOW_CLASS,
+                PCWSTR::null(),
+                WS_CHILD | WS_VISIBLE | WS_CLIPSIBLINGS,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                Some(*self.parent.get()),
+                None,
+                Some(instance),
+                Some(&*content as *const PreviewContent as *const c_void),
+            )?
+        };
+        self.content.set(Some(content));
+        self.window.set(Some(window));
+        Ok(())
+    }
+
+    fn Unload(&self) -> windows::core::Result<()> {
+        self.destroy_window();
+        self.file_path.set(None);
+        Ok(())
+    }
+
+    fn SetFocus(&self) -> windows::core::Result<()> {
+        if let Some(window) = *self.window.get() {
+            unsafe {
+                let _ = SetFocus(Some(window));
+            }
+        }
+        Ok(())
+    }
+
+    fn QueryFocus(&self) -> windows::core::Result<HWND> {
+        let focus = unsafe { GetFocus() };
+        if focus.is_invalid() {
+            Err(E_FAIL.into())
+        } else {
+            Ok(focus)
+        }
+    }
+
+    fn TranslateAccelerator(&self, pmsg: *const MSG) -> windows::core::Result<()> {
+        match self.frame.get() {
+            Some(frame) => unsafe { frame.TranslateAccelerator(pmsg) },
+            None => Err(S_FALSE.into()),
+        }
+    }
+}
+
+impl IObjectWithSite_Impl for PreviewHandler_Impl {
+    fn SetSite(&self, punksite: Ref<'_, IUnknown>) -> windows::core::Result<()> {
+        let site = punksite.ok().ok().cloned();
+        self.frame.set(
+            site.as_ref()
+                .and_then(|s| s.cast::<IPreviewHandlerFrame>().ok()),
+        );
+        self.site.set(site);
+        Ok(())
+    }
+
+    fn GetSite(&self, riid: *const GUID, ppvsite: *mut *mut c_void) -> windows::core::Result<()> {
+        if ppvsite.is_null() {
+            return Err(E_INVALIDARG.into());
+        }
+        unsafe {
+            *ppvsite = std::ptr::null_mut();
+            match self.site.get() {
+                Some(site) => site.query(&*riid, ppvsite).ok(),
+                None => Err(E_FAIL.into()),
+            }
+        }
+    }
+}
+
+impl IOleWindow_Impl for PreviewHandler_Impl {
+    fn GetWindow(&self) -> windows::core::Result<HWND> {
+        Ok(*self.parent.get())
+    }
+
+    fn ContextSensitiveHelp(&self, _fentermode: BOOL) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ClassFactory
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[implement(IClassFactory)]
+pub struct PreviewHandlerFactory;
+
+impl PreviewHandlerFactory {
+    pub fn new() -> Self {
+        dll_add_ref();
+        Self
+    }
+}
+
+impl Default for PreviewHandlerFactory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PreviewHandlerFactory {
+    fn drop(&mut self) {
+        dll_release();
+    }
+}
+
+impl IClassFactory_Impl for PreviewHandlerFactory_Impl {
+    fn CreateInstance(
+        &self,
+        punkouter: Ref<'_, IUnknown>,
+        riid: *const GUID,
+        ppvobject: *mut *mut c_void,
+    ) -> windows::core::Result<()> {
+        unsafe {
+            if ppvobject.is_null() {
+                return Err(E_INVALIDARG.into());
+            }
+            *ppvobject = std::ptr::null_mut();
+            if !punkouter.is_null() {
+                return Err(CLASS_E_NOAGGREGATION.into());
+            }
+
+            let handler: IPreviewHandler = PreviewHandler::new().into();
+            handler.query(&*riid, ppvobject).ok()
+        }
+    }
+
+    fn LockServer(&self, flock: BOOL) -> windows::core::Result<()> {
+        if flock.as_bool() {
+            dll_add_ref();
+        } else {
+            dll_release();
+        }
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Registration
+// ─────────────────────────────────────────────────────────────────────────────
+
+const PREVIEW_HANDLERS_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\PreviewHandlers";
+
+pub(crate) unsafe fn register_preview_handler(dll_path: &str) -> Result<(), HRESULT> {
+    let clsid = clsid_string(&CLSID_READEST_PREVIEW);
+
+    let clsid_key = create_reg_key(HKEY_CLASSES_ROOT, &format!("CLSID\\{}", clsid))?;
+    set_reg_value(clsid_key, "", PREVIEW_NAME)?;
+    set_reg_value(clsid_key, "AppID", PREVHOST_APPID)?;
+
+    let inproc_key = create_reg_key(clsid_key, "InprocServer32")?;
+    set_reg_value(inproc_key, "", dll_path)?;
+    set_reg_value(inproc_key, "ThreadingModel", "Apartment")?;
+    let _ = RegCloseKey(inproc_key);
+    let _ = RegCloseKey(clsid_key);
+
+    for ext in PREVIEW_EXTENSIONS {
+        let ext_shellex_path = format!("{}\\ShellEx\\{}", ext, SHELL_PREVIEW_HANDLER);
+        if let Ok(ext_shellex_key) = create_reg_key(HKEY_CLASSES_ROOT, &ext_shellex_path) {
+            let _ = set_reg_value(ext_shellex_key, "", &clsid);
+            let _ = RegCloseKey(ext_shellex_key);
+        }
+    }
+
+    // Explorer lists the preview handlers it may load here
+    if let Ok(handlers_key) = create_reg_key(HKEY_LOCAL_MACHINE, PREVIEW_HANDLERS_KEY) {
+        let _ = set_reg_value(handlers_key, &clsid, PREVIEW_NAME);
+        let _ = RegCloseKey(handlers_key);
+    }
+    Ok(())
+}
+
+pub(crate) unsafe fn unregister_preview_handler() {
+    let clsid = clsid_string(&CLSID_READEST_PREVIEW);
+    let clsid_path = to_wide(&format!("CLSID\\{}", clsid));
+    let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(clsid_path.as_ptr()));
+
+    for ext in PREVIEW_EXTENSIONS {
+        let ext_path = to_wide(&format!("{}\\ShellEx\\{}", ext, SHELL_PREVIEW_HANDLER));
+        let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(ext_path.as_ptr()));
+    }
+
+    let handlers_path = to_wide(PREVIEW_HANDLERS_KEY);
+    let clsid_name = to_wide(&clsid);
+    let _ = RegDeleteKeyValueW(
+        HKEY_LOCAL_MACHINE,
+        PCWSTR(handlers_path.as_ptr()),
+        PCWSTR(clsid_name.as_ptr()),
+    );
+}
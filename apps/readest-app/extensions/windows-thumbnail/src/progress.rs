@@ -0,0 +1,250 @@
+//! Reading progress badge
+//!
+//! Looks a book up in the Readest library (`library.json`) by the same
+//! partial MD5 hash the app uses, and draws how far it has been read as a
+//! small ring at the bottom-left of the thumbnail, or a check mark once the
+//! book is finished.
+
+use directories_next::BaseDirs;
+use image::{Rgba, RgbaImage};
+use md5::Context;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Tauri identifier of the app, the name of its config and data folders.
+const APP_IDENTIFIER: &str = "com.bilingify.readest";
+
+const BADGE_BACKDROP: [u8; 3] = [0x1f, 0x29, 0x37];
+const BADGE_TRACK: [u8; 3] = [0x6b, 0x72, 0x80];
+const BADGE_PROGRESS: [u8; 3] = [0x3b, 0x82, 0xf6];
+const BADGE_FINISHED: [u8; 3] = [0x22, 0xc5, 0x5e];
+const BADGE_CHECK: [u8; 3] = [0xff, 0xff, 0xff];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Percentage read, 1 to 99.
+    Reading(u8),
+    Finished,
+}
+
+impl Progress {
+    /// A byte that changes whenever the badge would look different.
+    pub fn cache_key(self) -> u8 {
+        match self {
+            Progress::Reading(percent) => percent,
+            Progress::Finished => 100,
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Library lookup
+// ─────────────────────────────────────────────────────────────────────────────
+
+struct Library {
+    path: PathBuf,
+    modified: SystemTime,
+    books: HashMap<String, Progress>,
+}
+
+/// The last library read, kept until `library.json` changes on disk so that
+/// a folder of books is not parsed once per thumbnail.
+static LIBRARY: Lazy<Mutex<Option<Library>>> = Lazy::new(|| Mutex::new(None));
+
+/// Reading progress of the book at `path`, if it is in the library and has
+/// been started.
+pub fn progress_for_path(path: &Path) -> Option<Progress> {
+    let library_path = library_path()?;
+    let modified = std::fs::metadata(&library_path).ok()?.modified().ok()?;
+    let hash = partial_md5(path).ok()?;
+
+    let mut library = LIBRARY.lock().ok()?;
+    let fresh = matches!(
+        library.as_ref(),
+        Some(l) if l.path == library_path && l.modified == modified
+    );
+    if !fresh {
+        let books = load_library(&library_path)?;
+        *library = Some(Library {
+            path: library_path,
+            modified,
+            books,
+        });
+    }
+    library.as_ref()?.books.get(&hash).copied()
+}
+
+/// Location of `library.json`: `Readest/Books` under the custom root folder
+/// chosen in settings, or under the app data folder.
+///
+/// Portable installs keep their data beside the executable but are not
+/// registered with Explorer, so they are not looked for here.
+fn library_path() -> Option<PathBuf> {
+    let dirs = BaseDirs::new()?;
+    let settings = dirs.config_dir().join(APP_IDENTIFIER).join("settings.json");
+    let custom_root = std::fs::read(settings)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+        .and_then(|settings| {
+            let dir = settings.get("customRootDir")?.as_str()?;
+            (!dir.is_empty()).then(|| PathBuf::from(dir))
+        });
+    let root = custom_root.unwrap_or_else(|| dirs.data_dir().join(APP_IDENTIFIER));
+    Some(root.join("Readest").join("Books").join("library.json"))
+}
+
+fn load_library(path: &Path) -> Option<HashMap<String, Progress>> {
+    let bytes = std::fs::read(path).ok()?;
+    let books: Vec<Value> = serde_json::from_slice(&bytes).ok()?;
+    Some(
+        books
+            .iter()
+            .filter(|book| matches!(book.get("deletedAt"), None | Some(Value::Null)))
+            .filter_map(|book| {
+                let hash = book.get("hash")?.as_str()?;
+                Some((hash.to_string(), book_progress(book)?))
+            })
+            .collect(),
+    )
+}
+
+/// Mirrors the percentage shown under the cover in the library.
+fn book_progress(book: &Value) -> Option<Progress> {
+    match book.get("readingStatus").and_then(Value::as_str) {
+        Some("finished") => return Some(Progress::Finished),
+        Some("unread") => return None,
+        _ => {}
+    }
+    let progress = book.get("progress")?.as_array()?;
+    let current = progress.first()?.as_f64()?;
+    let total = progress.get(1)?.as_f64()?;
+    if total <= 0.0 {
+        return None;
+    }
+    match (current / total * 100.0).round().clamp(0.0, 100.0) as u8 {
+        0 => None,
+        100 => Some(Progress::Finished),
+        percent => Some(Progress::Reading(percent)),
+    }
+}
+
+/// The book hash Readest files books under, as `partialMD5` computes it.
+fn partial_md5(path: &Path) -> std::io::Result<String> {
+    const STEP: i32 = 1024;
+    const SIZE: u64 = 1024;
+
+    let mut file = std::fs::File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut hasher = Context::new();
+    let mut buf = vec![0u8; SIZE as usize];
+
+    for i in -1i32..=10 {
+        // `step << (2 * i)` in JS shifts a 32-bit integer by the low five
+        // bits of the count. For i = -1 that is a shift by 30, which pushes
+        // 1024 out entirely and gives offset 0; for i = 0..=10 the offsets
+        // are 2^10 … 2^30 and never wrap.
+        let shifted = STEP.wrapping_shl(((2 * i) as u32) & 31);
+        let start = file_len.min(shifted.max(0) as u64);
+        if start >= file_len {
+            break;
+        }
+        let end = (start + SIZE).min(file_len);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        hasher.consume(&*chunk);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Badge drawing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Draw the progress badge at the bottom-left corner, the same size as the
+/// Readest icon on the other side.
+pub fn draw_progress_badge(base: &mut RgbaImage, progress: Progress, requested_size: u32) {
+    let diameter = (requested_size / 5).clamp(24, 48);
+    let (base_w, base_h) = base.dimensions();
+    if base_w < diameter + 8 || base_h < diameter + 8 {
+        return;
+    }
+
+    let radius = diameter as f32 / 2.0;
+    let (cx, cy) = (4.0 + radius, base_h as f32 - 4.0 - radius);
+    let thickness = (radius * 0.3).max(3.0);
+    let ring_radius = radius - thickness / 2.0 - 1.5;
+    let check = [
+        (-0.42 * radius, 0.02 * radius),
+        (-0.12 * radius, 0.32 * radius),
+        (0.42 * radius, -0.28 * radius),
+    ];
+
+    let y0 = base_h - 4 - diameter;
+    for y in y0..y0 + diameter {
+        for x in 4..4 + diameter {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let distance = (dx * dx + dy * dy).sqrt();
+            let disc = coverage(radius - distance);
+            if disc == 0.0 {
+                continue;
+            }
+
+            let pixel = base.get_pixel_mut(x, y);
+            match progress {
+                Progress::Finished => {
+                    blend(pixel, BADGE_FINISHED, disc);
+                    let stroke = distance_to_segment((dx, dy), check[0], check[1])
+                        .min(distance_to_segment((dx, dy), check[1], check[2]));
+                    blend(pixel, BADGE_CHECK, coverage(thickness / 2.0 - stroke));
+                }
+                Progress::Reading(percent) => {
+                    blend(pixel, BADGE_BACKDROP, disc * 0.85);
+                    let ring = coverage(thickness / 2.0 - (distance - ring_radius).abs());
+                    if ring > 0.0 {
+                        // Clockwise from twelve o'clock.
+                        let turn = (dx.atan2(-dy) / TAU).rem_euclid(1.0);
+                        let color = if turn * 100.0 < percent as f32 {
+                            BADGE_PROGRESS
+                        } else {
+                            BADGE_TRACK
+                        };
+                        blend(pixel, color, ring);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How much of a pixel lies inside an edge `inside` pixels away.
+fn coverage(inside: f32) -> f32 {
+    (inside + 0.5).clamp(0.0, 1.0)
+}
+
+fn distance_to_segment(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+    let (apx, apy) = (p.0 - a.0, p.1 - a.1);
+    let t = ((apx * abx + apy * aby) / (abx * abx + aby * aby)).clamp(0.0, 1.0);
+    let (x, y) = (apx - abx * t, apy - aby * t);
+    (x * x + y * y).sqrt()
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: [u8; 3], alpha: f32) {
+    if alpha <= 0.0 {
+        return;
+    }
+    for c in 0..3 {
+        let bg = pixel.0[c] as f32;
+        pixel.0[c] = (color[c] as f32 * alpha + bg * (1.0 - alpha)).round() as u8;
+    }
+    let bg_alpha = pixel.0[3] as f32 / 255.0;
+    pixel.0[3] = ((alpha + bg_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+}
@@ -0,0 +1,336 @@
+/// Windows Property Handler for Readest
+///
+/// Implements IPropertyStore and IInitializeWithStream so that Explorer can show the
+/// Title, Authors, Series and Publisher of EPUB, MOBI/AZW3 and FB2 files in its columns
+/// and Details tab. The metadata is read once, from the stream Explorer hands over, into
+/// an in-memory property store that the handler answers from. The handler is read-only.
+///
+/// ## CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567892}
+use std::ffi::c_void;
+use std::io::{Read, Seek, SeekFrom};
+
+use book_extraction::{extract_metadata, BookMetadata};
+use windows::core::{IUnknown, Interface, GUID, HRESULT, PCWSTR};
+use windows::Win32::Foundation::{
+    CLASS_E_NOAGGREGATION, E_INVALIDARG, E_UNEXPECTED, PROPERTYKEY, STG_E_ACCESSDENIED, S_FALSE,
+};
+use windows::Win32::System::Com::{
+    IClassFactory, IClassFactory_Impl, IStream, STREAM_SEEK_CUR, STREAM_SEEK_END, STREAM_SEEK_SET,
+};
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegDeleteKeyValueW, RegDeleteTreeW, HKEY_CLASSES_ROOT, HKEY_LOCAL_MACHINE,
+};
+use windows::Win32::UI::Shell::PropertiesSystem::{
+    IInitializeWithStream, IInitializeWithStream_Impl, IPropertyStore, IPropertyStoreCapabilities,
+    IPropertyStoreCapabilities_Impl, IPropertyStore_Impl, InitPropVariantFromStringVector,
+    PSCoerceToCanonicalValue, PSCreateMemoryPropertyStore,
+};
+use windows_core::{implement, Ref, BOOL, PROPVARIANT};
+
+use super::com_provider::{
+    clsid_string, create_reg_key, dll_add_ref, dll_release, set_reg_value, to_wide, ComCell,
+};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CLSID for Readest Property Handler
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567892}
+pub const CLSID_READEST_PROPERTIES: GUID = GUID::from_u128(0xA1B2C3D4_E5F6_7890_ABCD_EF1234567892);
+
+/// Supported file extensions
+pub const PROPERTY_EXTENSIONS: &[&str] =
+    &[".epub", ".mobi", ".azw", ".azw3", ".kf8", ".prc", ".fb2"];
+
+const PROPERTY_HANDLERS_KEY: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\PropertySystem\\PropertyHandlers";
+
+/// Properties listed in the Details tab, the Details pane and tooltips.
+const FULL_DETAILS: &str = "prop:System.Title;System.Author;System.Media.SeriesName;\
+                            System.Media.Publisher;System.Size;System.DateModified";
+const PREVIEW_DETAILS: &str =
+    "prop:System.Title;System.Author;System.Media.SeriesName;System.Media.Publisher";
+const INFO_TIP: &str = "prop:System.Title;System.Author;System.Media.SeriesName";
+
+/// System.Title
+const PKEY_TITLE: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0xF29F85E0_4FF9_1068_AB91_08002B27B3D9),
+    pid: 2,
+};
+/// System.Author
+const PKEY_AUTHOR: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0xF29F85E0_4FF9_1068_AB91_08002B27B3D9),
+    pid: 4,
+};
+/// System.Media.Publisher
+const PKEY_PUBLISHER: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0x64440492_4C8B_11D1_8B70_080036B11A03),
+    pid: 30,
+};
+/// System.Media.SeriesName
+const PKEY_SERIES: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0x64440492_4C8B_11D1_8B70_080036B11A03),
+    pid: 42,
+};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// IStream reader
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// `Read + Seek` over the COM stream of the file, for the metadata parsers.
+struct ComStream(IStream);
+
+impl Read for ComStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut read = 0u32;
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        unsafe {
+            self.0
+                .Read(buf.as_mut_ptr() as *mut c_void, len, Some(&mut read))
+                .ok()
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(read as usize)
+    }
+}
+
+impl Seek for ComStream {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (offset, origin) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, STREAM_SEEK_SET),
+            SeekFrom::Current(offset) => (offset, STREAM_SEEK_CUR),
+            SeekFrom::End(offset) => (offset, STREAM_SEEK_END),
+        };
+        let mut position = 0u64;
+        unsafe {
+            self.0
+                .Seek(offset, origin, Some(&mut position))
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(position)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PropertyHandler
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Fill an in-memory property store with the properties found in `metadata`.
+unsafe fn property_store(metadata: &BookMetadata) -> windows::core::Result<IPropertyStore> {
+    let store: IPropertyStore = PSCreateMemoryPropertyStore()?;
+
+    let texts = [
+        (&PKEY_TITLE, &metadata.title),
+        (&PKEY_SERIES, &metadata.series),
+        (&PKEY_PUBLISHER, &metadata.publisher),
+    ];
+    for (key, value) in texts {
+        if let Some(value) = value {
+            let mut propvar = PROPVARIANT::from(value.as_str());
+            PSCoerceToCanonicalValue(key, &mut propvar)?;
+            store.SetValue(key, &propvar)?;
+        }
+    }
+
+    if !metadata.authors.is_empty() {
+        let wide: Vec<Vec<u16>> = metadata.authors.iter().map(|a| to_wide(a)).collect();
+        let authors: Vec<PCWSTR> = wide.iter().map(|a| PCWSTR(a.as_ptr())).collect();
+        let propvar = InitPropVariantFromStringVector(Some(&authors))?;
+        store.SetValue(&PKEY_AUTHOR, &propvar)?;
+    }
+
+    Ok(store)
+}
+
+#[implement(IPropertyStore, IPropertyStoreCapabilities, IInitializeWithStream)]
+pub struct PropertyHandler {
+    store: ComCell<Option<IPropertyStore>>,
+}
+
+impl PropertyHandler {
+    pub fn new() -> Self {
+        dll_add_ref();
+        Self {
+            store: ComCell::new(None),
+        }
+    }
+
+    fn store(&self) -> windows::core::Result<&IPropertyStore> {
+        Ok(self.store.get().as_ref().ok_or(E_UNEXPECTED)?)
+    }
+}
+
+impl Default for PropertyHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PropertyHandler {
+    fn drop(&mut self) {
+        dll_release();
+    }
+}
+
+impl IInitializeWithStream_Impl for PropertyHandler_Impl {
+    fn Initialize(&self, pstream: Ref<'_, IStream>, _grfmode: u32) -> windows::core::Result<()> {
+        if self.store.get().is_some() {
+            return Err(E_UNEXPECTED.into());
+        }
+        let stream = pstream.ok()?.clone();
+        // An unreadable book gets no properties rather than failing Explorer's request
+        let metadata = extract_metadata(ComStream(stream)).unwrap_or_default();
+        let store = unsafe { property_store(&metadata)? };
+        self.store.set(Some(store));
+        Ok(())
+    }
+}
+
+impl IPropertyStore_Impl for PropertyHandler_Impl {
+    fn GetCount(&self) -> windows::core::Result<u32> {
+        unsafe { self.store()?.GetCount() }
+    }
+
+    fn GetAt(&self, iprop: u32, pkey: *mut PROPERTYKEY) -> windows::core::Result<()> {
+        unsafe { self.store()?.GetAt(iprop, pkey) }
+    }
+
+    fn GetValue(&self, key: *const PROPERTYKEY) -> windows::core::Result<PROPVARIANT> {
+        if key.is_null() {
+            return Err(E_INVALIDARG.into());
+        }
+        unsafe { self.store()?.GetValue(key) }
+    }
+
+    fn SetValue(
+        &self,
+        _key: *const PROPERTYKEY,
+        _propvar: *const PROPVARIANT,
+    ) -> windows::core::Result<()> {
+        Err(STG_E_ACCESSDENIED.into())
+    }
+
+    fn Commit(&self) -> windows::core::Result<()> {
+        Err(STG_E_ACCESSDENIED.into())
+    }
+}
+
+impl IPropertyStoreCapabilities_Impl for PropertyHandler_Impl {
+    fn IsPropertyWritable(&self, _key: *const PROPERTYKEY) -> windows::core::Result<()> {
+        // S_FALSE: nothing can be edited from the Details tab
+        Err(S_FALSE.into())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ClassFactory
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[implement(IClassFactory)]
+pub struct PropertyHandlerFactory;
+
+impl PropertyHandlerFactory {
+    pub fn new() -> Self {
+        dll_add_ref();
+        Self
+    }
+}
+
+impl Default for PropertyHandlerFactory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PropertyHandlerFactory {
+    fn drop(&mut self) {
+        dll_release();
+    }
+}
+
+impl IClassFactory_Impl for PropertyHandlerFactory_Impl {
+    fn CreateInstance(
+        &self,
+        punkouter: Ref<'_, IUnknown>,
+        riid: *const GUID,
+        ppvobject: *mut *mut c_void,
+    ) -> windows::core::Result<()> {
+        unsafe {
+            if ppvobject.is_null() {
+                return Err(E_INVALIDARG.into());
+            }
+            *ppvobject = std::ptr::null_mut();
+            if !punkouter.is_null() {
+                return Err(CLASS_E_NOAGGREGATION.into());
+            }
+
+            let handler: IPropertyStore = PropertyHandler::new().into();
+            handler.query(&*riid, ppvobject).ok()
+        }
+    }
+
+    fn LockServer(&self, flock: BOOL) -> windows::core::Result<()> {
+        if flock.as_bool() {
+            dll_add_ref();
+        } else {
+            dll_release();
+        }
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Registration
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub(crate) unsafe fn register_property_handler(dll_path: &str) -> Result<(), HRESULT> {
+    let clsid = clsid_string(&CLSID_READEST_PROPERTIES);
+
+    let clsid_key = create_reg_key(HKEY_CLASSES_ROOT, &format!("CLSID\\{}", clsid))?;
+    set_reg_value(clsid_key, "", "Readest Property Handler")?;
+
+    let inproc_key = create_reg_key(clsid_key, "InprocServer32")?;
+    set_reg_value(inproc_key, "", dll_path)?;
+    set_reg_value(inproc_key, "ThreadingModel", "Apartment")?;
+    let _ = RegCloseKey(inproc_key);
+    let _ = RegCloseKey(clsid_key);
+
+    for ext in PROPERTY_EXTENSIONS {
+        // The property system only looks up handlers per machine
+        let handler_path = format!("{}\\{}", PROPERTY_HANDLERS_KEY, ext);
+        if let Ok(handler_key) = create_reg_key(HKEY_LOCAL_MACHINE, &handler_path) {
+            let _ = set_reg_value(handler_key, "", &clsid);
+            let _ = RegCloseKey(handler_key);
+        }
+
+        let details_path = format!("SystemFileAssociations\\{}", ext);
+        if let Ok(details_key) = create_reg_key(HKEY_CLASSES_ROOT, &details_path) {
+            let _ = set_reg_value(details_key, "FullDetails", FULL_DETAILS);
+            let _ = set_reg_value(details_key, "PreviewDetails", PREVIEW_DETAILS);
+            let _ = set_reg_value(details_key, "InfoTip", INFO_TIP);
+            let _ = RegCloseKey(details_key);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) unsafe fn unregister_property_handler() {
+    let clsid = clsid_string(&CLSID_READEST_PROPERTIES);
+    let clsid_path = to_wide(&format!("CLSID\\{}", clsid));
+    let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(clsid_path.as_ptr()));
+
+    for ext in PROPERTY_EXTENSIONS {
+        let handler_path = to_wide(&format!("{}\\{}", PROPERTY_HANDLERS_KEY, ext));
+        let _ = RegDeleteTreeW(HKEY_LOCAL_MACHINE, PCWSTR(handler_path.as_ptr()));
+
+        let details_path = to_wide(&format!("SystemFileAssociations\\{}", ext));
+        for name in ["FullDetails", "PreviewDetails", "InfoTip"] {
+            let name = to_wide(name);
+            let _ = RegDeleteKeyValueW(
+                HKEY_CLASSES_ROOT,
+                PCWSTR(details_path.as_ptr()),
+                PCWSTR(name.as_ptr()),
+            );
+        }
+    }
+}
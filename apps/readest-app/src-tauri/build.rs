@@ -5,10 +5,19 @@ use std::{
 };
 
 fn main() {
+    println!("cargo:rerun-if-changed=../extensions/book-extraction/src");
     println!("cargo:rerun-if-changed=../extensions/windows-thumbnail/src");
+    for dir in ["src", "include", "Shared", "Thumbnail", "Preview"] {
+        println!("cargo:rerun-if-changed=../extensions/macos-quicklook/{dir}");
+    }
+    println!("cargo:rerun-if-changed=../extensions/linux-thumbnailer/src");
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
     if target_os == "windows" {
         build_windows_thumbnail();
+    } else if target_os == "macos" {
+        build_macos_quicklook();
+    } else if target_os == "linux" {
+        build_linux_thumbnailer();
     }
 
     propagate_sentry_dsn();
@@ -50,6 +59,136 @@ fn main() {
             "spawn_fresh_browser",
             "verify_update_signature",
             "install_nightly_update",
+            "list_archive_books",
+            "extract_archive_books",
+            "export_braille",
+            "get_book_content_policy",
+            "set_book_content_policy",
+            "scan_dir",
+            "cancel_scan",
+            "enqueue_download",
+            "list_downloads",
+            "pause_download",
+            "resume_download",
+            "cancel_download",
+            "set_max_concurrent_downloads",
+            "opds_fetch_feed",
+            "opds_search",
+            "opds_download",
+            "start_reading_server",
+            "stop_reading_server",
+            "get_reading_server_status",
+            "import_calibre_library",
+            "calibre_server_libraries",
+            "calibre_server_search",
+            "calibre_server_cover",
+            "calibre_server_download",
+            "calibre_server_get_positions",
+            "calibre_server_set_position",
+            "calibre_device_discover",
+            "calibre_device_connect",
+            "calibre_device_disconnect",
+            "kosync_document_digest",
+            "kosync_connect",
+            "kosync_get_progress",
+            "kosync_update_progress",
+            "import_koreader_stats",
+            "preview_moonreader_backup",
+            "preview_kindle_clippings",
+            "apple_books_scan",
+            "import_apple_books",
+            "sync_list",
+            "sync_head",
+            "sync_upload",
+            "sync_download",
+            "sync_delete",
+            "sync_changes",
+            "sync_quota",
+            "dropbox_connect",
+            "e2ee_create_key",
+            "e2ee_unlock",
+            "e2ee_status",
+            "e2ee_forget",
+            "e2ee_seal",
+            "e2ee_open",
+            "get_sync_transfer_policy",
+            "set_sync_transfer_policy",
+            "sync_merge_annotations",
+            "lan_sync_start",
+            "lan_sync_stop",
+            "lan_sync_status",
+            "lan_sync_pairing_code",
+            "lan_sync_discover",
+            "lan_sync_pair",
+            "lan_sync_unpair",
+            "get_sync_policy",
+            "set_sync_policy",
+            "set_sync_book_rule",
+            "sync_policy_evaluate",
+            "sync_queue_enqueue",
+            "sync_queue_list",
+            "sync_queue_state",
+            "sync_queue_retry",
+            "sync_queue_discard",
+            "get_proxy_settings",
+            "set_proxy_settings",
+            "list_trusted_hosts",
+            "trust_host",
+            "untrust_host",
+            "probe_host_certificate",
+            "secure_get",
+            "secure_set",
+            "secure_delete",
+            "secure_load_settings",
+            "secure_save_settings",
+            "list_client_certificates",
+            "import_client_certificate",
+            "remove_client_certificate",
+            "convert_book",
+            "list_conversions",
+            "cancel_conversion",
+            "check_epub",
+            "repair_epub",
+            "detect_text_encoding",
+            "convert_text_to_utf8",
+            "open_txt_book",
+            "get_txt_chapter",
+            "close_txt_book",
+            "parse_comic",
+            "open_comic",
+            "get_comic_page",
+            "close_comic",
+            "open_pdf",
+            "close_pdf",
+            "get_pdf_page_text",
+            "search_pdf",
+            "get_pdf_outline",
+            "detect_pdf_margins",
+            "parse_audio_metadata",
+            "start_tts_export",
+            "cancel_tts_export",
+            "update_taskbar_progress",
+            "finish_taskbar_progress",
+            "notify_job_finished",
+            "mpris_set_active",
+            "mpris_update_metadata",
+            "mpris_update_state",
+            "now_playing_set_active",
+            "now_playing_update_metadata",
+            "now_playing_update_state",
+            "register_default_app",
+            "update_jump_list",
+            "update_spotlight_index",
+            "update_recent_books_menus",
+            "set_screensaver_inhibited",
+            "update_tray_menu",
+            "set_tray_read_aloud",
+            "set_minimize_to_tray",
+            "open_book_window",
+            "set_keep_awake",
+            "get_system_idle_seconds",
+            "print_content",
+            "render_quote_image",
         ]),
     ))
     .expect("failed to run tauri-build");
@@ -198,3 +337,199 @@ fn build_windows_thumbnail() {
     fs::copy(dll_src, dll_dest).expect("Failed to copy windows_thumbnail DLL");
     println!("cargo:rerun-if-changed={}", dll_dest.display());
 }
+
+/// Build the freedesktop thumbnailer and copy it to `target/readest-thumbnailer`,
+/// where `bundle.linux.deb.files` and `bundle.linux.rpm.files` in
+/// `tauri.conf.json` pick it up.
+fn build_linux_thumbnailer() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let thumbnailer_crate_dir = manifest_dir
+        .join("..")
+        .join("extensions")
+        .join("linux-thumbnailer");
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "debug".into());
+
+    let mut cmd = Command::new(env::var("CARGO").unwrap_or("cargo".into()));
+    cmd.arg("build")
+        .arg("--package")
+        .arg("linux_thumbnailer")
+        .arg("--manifest-path")
+        .arg(thumbnailer_crate_dir.join("Cargo.toml"));
+    if profile == "release" {
+        cmd.arg("--release");
+    }
+
+    let target_triple = env::var("TARGET").unwrap_or_default();
+    let host_triple = env::var("HOST").unwrap_or_default();
+    if !target_triple.is_empty() && target_triple != host_triple {
+        cmd.arg("--target").arg(&target_triple);
+    }
+
+    let status = cmd
+        .status()
+        .expect("Failed to run cargo build for linux_thumbnailer");
+    if !status.success() {
+        panic!("Failed to build the Linux thumbnailer");
+    }
+
+    let bin_name = "readest-thumbnailer";
+    let candidate_paths = [
+        thumbnailer_crate_dir
+            .join("target")
+            .join(&target_triple)
+            .join(&profile)
+            .join(bin_name),
+        thumbnailer_crate_dir
+            .join("target")
+            .join(&profile)
+            .join(bin_name),
+    ];
+    let bin_src = candidate_paths
+        .iter()
+        .find(|p| p.exists())
+        .expect("Failed to find the built Linux thumbnailer");
+
+    let bin_dest = thumbnailer_crate_dir.join("target").join(bin_name);
+    fs::copy(bin_src, &bin_dest).expect("Failed to copy the Linux thumbnailer");
+    println!("cargo:rerun-if-changed={}", bin_dest.display());
+}
+
+/// Quick Look extensions bundled into `Contents/PlugIns` by `bundle.macOS.files`
+/// in `tauri.conf.json`, with the Swift sources they are built from.
+const QUICKLOOK_EXTENSIONS: &[(&str, &str, &[&str])] = &[
+    (
+        "ReadestThumbnail",
+        "Thumbnail/ThumbnailProvider.swift",
+        &["QuickLookThumbnailing", "ImageIO"],
+    ),
+    (
+        "ReadestPreview",
+        "Preview/PreviewProvider.swift",
+        &["QuickLookUI", "UniformTypeIdentifiers"],
+    ),
+];
+
+/// Build the `macos_quicklook` static library and link it into the Quick Look
+/// app extensions. Each target architecture gets its own executable under
+/// `target/<triple>/`; the `.appex` bundles in `target/` carry all that have
+/// been built, so a universal build ends up with fat extensions.
+fn build_macos_quicklook() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let ql_crate_dir = manifest_dir
+        .join("..")
+        .join("extensions")
+        .join("macos-quicklook");
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "debug".into());
+    let target_triple = env::var("TARGET").unwrap_or_default();
+
+    let mut cmd = Command::new(env::var("CARGO").unwrap_or("cargo".into()));
+    cmd.arg("build")
+        .arg("--package")
+        .arg("macos_quicklook")
+        .arg("--manifest-path")
+        .arg(ql_crate_dir.join("Cargo.toml"))
+        .arg("--target")
+        .arg(&target_triple);
+    if profile == "release" {
+        cmd.arg("--release");
+    }
+    let status = cmd
+        .status()
+        .expect("Failed to run cargo build for macos_quicklook");
+    if !status.success() {
+        panic!("Failed to build macos_quicklook static library");
+    }
+
+    let target_dir = ql_crate_dir.join("target");
+    let lib_dir = target_dir.join(&target_triple).join(&profile);
+    let arch = match env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+        Ok("aarch64") => "arm64",
+        _ => "x86_64",
+    };
+    let version = read_json_string_field(&manifest_dir.join("..").join("package.json"), "version")
+        .unwrap_or_else(|| env::var("CARGO_PKG_VERSION").unwrap());
+
+    for (name, source, frameworks) in QUICKLOOK_EXTENSIONS {
+        let executable = target_dir.join(&target_triple).join(name);
+        let mut cmd = Command::new("xcrun");
+        cmd.args(["--sdk", "macosx", "swiftc"])
+            .arg("-target")
+            .arg(format!("{arch}-apple-macos12.0"))
+            .arg("-module-name")
+            .arg(name)
+            .args(["-parse-as-library", "-application-extension"])
+            .arg("-import-objc-header")
+            .arg(ql_crate_dir.join("include").join("readest_quicklook.h"))
+            .arg(ql_crate_dir.join("Shared").join("ReadestBuffer.swift"))
+            .arg(ql_crate_dir.join(source))
+            .arg("-L")
+            .arg(&lib_dir)
+            .arg("-lmacos_quicklook")
+            .args(["-Xlinker", "-e", "-Xlinker", "_NSExtensionMain"])
+            .arg("-o")
+            .arg(&executable);
+        for framework in *frameworks {
+            cmd.args(["-framework", framework]);
+        }
+        if profile == "release" {
+            cmd.arg("-O");
+        }
+        let status = cmd.status().expect("Failed to run swiftc");
+        if !status.success() {
+            panic!("Failed to build the {name} Quick Look extension");
+        }
+
+        bundle_quicklook_extension(&ql_crate_dir, name, &version);
+    }
+}
+
+/// Assemble `target/<name>.appex` from the executables built so far and sign
+/// it, with `APPLE_SIGNING_IDENTITY` when set (as for the app itself) and
+/// ad hoc otherwise, since Quick Look only loads sandboxed extensions.
+fn bundle_quicklook_extension(ql_crate_dir: &Path, name: &str, version: &str) {
+    println!("cargo:rerun-if-env-changed=APPLE_SIGNING_IDENTITY");
+    let target_dir = ql_crate_dir.join("target");
+    let contents = target_dir.join(format!("{name}.appex")).join("Contents");
+    let macos_dir = contents.join("MacOS");
+    fs::create_dir_all(&macos_dir).expect("Failed to create Quick Look extension bundle");
+
+    let kind = name.trim_start_matches("Readest");
+    let info_plist = fs::read_to_string(ql_crate_dir.join(kind).join("Info.plist"))
+        .expect("Failed to read Quick Look extension Info.plist");
+    fs::write(
+        contents.join("Info.plist"),
+        info_plist.replace("{{VERSION}}", version),
+    )
+    .expect("Failed to write Quick Look extension Info.plist");
+
+    let executables: Vec<PathBuf> = ["aarch64-apple-darwin", "x86_64-apple-darwin"]
+        .iter()
+        .map(|triple| target_dir.join(triple).join(name))
+        .filter(|path| path.exists())
+        .collect();
+    let status = Command::new("lipo")
+        .arg("-create")
+        .args(&executables)
+        .arg("-output")
+        .arg(macos_dir.join(name))
+        .status()
+        .expect("Failed to run lipo");
+    if !status.success() {
+        panic!("Failed to create the {name} Quick Look extension executable");
+    }
+
+    let identity = env::var("APPLE_SIGNING_IDENTITY")
+        .ok()
+        .filter(|identity| !identity.is_empty())
+        .unwrap_or_else(|| "-".into());
+    let status = Command::new("codesign")
+        .args(["--force", "--options", "runtime", "--sign", &identity])
+        .arg("--entitlements")
+        .arg(ql_crate_dir.join("QuickLook.entitlements"))
+        .arg(target_dir.join(format!("{name}.appex")))
+        .status()
+        .expect("Failed to run codesign");
+    if !status.success() {
+        panic!("Failed to sign the {name} Quick Look extension");
+    }
+}
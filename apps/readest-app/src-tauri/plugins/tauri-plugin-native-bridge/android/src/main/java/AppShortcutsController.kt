@@ -0,0 +1,70 @@
+package com.readest.native_bridge
+
+import android.content.Context
+import android.content.Intent
+import android.graphics.Bitmap
+import android.graphics.BitmapFactory
+import android.net.Uri
+import android.util.Log
+import androidx.core.content.pm.ShortcutInfoCompat
+import androidx.core.content.pm.ShortcutManagerCompat
+import androidx.core.graphics.drawable.IconCompat
+import java.io.File
+import kotlin.math.min
+
+/**
+ * Long-press launcher shortcuts for the most recently read books.
+ *
+ * Each shortcut opens `readest://book/{hash}`, which the reader opens at the
+ * position saved for the book. The shortcuts are replaced as a whole on every
+ * update; one the user pinned to the home screen stays after its book drops
+ * out of the list.
+ */
+object AppShortcutsController {
+    private const val ICON_SIZE = 192
+
+    fun update(context: Context, books: List<AppShortcutBookArgs>) {
+        val max = ShortcutManagerCompat.getMaxShortcutCountPerActivity(context)
+        val shortcuts = books.take(max).mapIndexed { rank, book ->
+            val intent = Intent(Intent.ACTION_VIEW, Uri.parse("readest://book/${book.hash}"))
+                .setPackage(context.packageName)
+            val title = book.title.ifBlank { book.hash }
+            ShortcutInfoCompat.Builder(context, "book:${book.hash}")
+                .setShortLabel(title)
+                .setLongLabel(if (book.author.isBlank()) title else "$title — ${book.author}")
+                .setIcon(coverIcon(book.coverPath) ?: appIcon(context))
+                .setIntent(intent)
+                .setRank(rank)
+                .build()
+        }
+        try {
+            ShortcutManagerCompat.setDynamicShortcuts(context, shortcuts)
+        } catch (e: Exception) {
+            // Rate limited while the app is in the background.
+            Log.w("AppShortcuts", "Failed to set shortcuts: ${e.message}")
+        }
+    }
+
+    private fun appIcon(context: Context): IconCompat =
+        IconCompat.createWithResource(context, context.applicationInfo.icon)
+
+    // Center-crop the cover to a square; launchers mask it to their shape.
+    private fun coverIcon(path: String): IconCompat? {
+        if (path.isEmpty() || !File(path).exists()) return null
+        val bounds = BitmapFactory.Options().apply { inJustDecodeBounds = true }
+        BitmapFactory.decodeFile(path, bounds)
+        var sample = 1
+        while (min(bounds.outWidth, bounds.outHeight) / (sample * 2) >= ICON_SIZE) sample *= 2
+        val bitmap = BitmapFactory.decodeFile(path, BitmapFactory.Options().apply {
+            inSampleSize = sample
+        }) ?: return null
+        val side = min(bitmap.width, bitmap.height)
+        val square = Bitmap.createBitmap(
+            bitmap, (bitmap.width - side) / 2, (bitmap.height - side) / 2, side, side
+        )
+        val scaled = Bitmap.createScaledBitmap(square, ICON_SIZE, ICON_SIZE, true)
+        if (square !== bitmap) bitmap.recycle()
+        if (scaled !== square) square.recycle()
+        return IconCompat.createWithBitmap(scaled)
+    }
+}
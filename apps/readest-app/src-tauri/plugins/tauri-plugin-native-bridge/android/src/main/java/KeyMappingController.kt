@@ -0,0 +1,117 @@
+package com.readest.native_bridge
+
+import android.view.KeyEvent
+import android.view.ViewConfiguration
+
+/**
+ * Maps hardware keys (the volume keys) to reader actions such as turning the
+ * page, with per-key behavior for holding the key down.
+ *
+ * A held key sends repeated ACTION_DOWN events with a growing `repeatCount`,
+ * followed by one ACTION_UP. Depending on the mapping's long-press mode the
+ * repeats are turned into the same action at a steady interval (`repeat`),
+ * swallowed (`none`), or — for any other value — into a single long-press
+ * action sent once the key has been held for the system's long-press timeout.
+ * In that last case the short-press action waits for ACTION_UP, so a long
+ * press doesn't also turn the page.
+ *
+ * Mapped keys are consumed in both directions so the system never sees them
+ * and the volume doesn't change. While the activity is paused the mappings
+ * are suspended and the keys behave as volume keys again.
+ */
+object KeyMappingController {
+    const val REPEAT = "repeat"
+    const val NONE = "none"
+    private const val DEFAULT_REPEAT_INTERVAL_MS = 300L
+
+    class Mapping(
+        val keyName: String,
+        val action: String,
+        val longPress: String,
+        val repeatIntervalMs: Long,
+    )
+
+    private class HeldKey(var lastSentAt: Long, var longPressed: Boolean = false)
+
+    private val keyCodes = mapOf(
+        "VolumeUp" to KeyEvent.KEYCODE_VOLUME_UP,
+        "VolumeDown" to KeyEvent.KEYCODE_VOLUME_DOWN,
+    )
+
+    private var mappings: Map<Int, Mapping> = emptyMap()
+    private val held = mutableMapOf<Int, HeldKey>()
+    private var suspended = false
+
+    val isActive: Boolean
+        get() = mappings.isNotEmpty() && !suspended
+
+    /** Replace all mappings; keys without a known key code are ignored. */
+    fun setMappings(args: List<KeyMappingArgs>) {
+        mappings = args.mapNotNull { arg ->
+            val keyCode = keyCodes[arg.key] ?: return@mapNotNull null
+            val action = arg.action ?: return@mapNotNull null
+            keyCode to Mapping(
+                keyName = arg.key!!,
+                action = action,
+                longPress = arg.longPress ?: NONE,
+                repeatIntervalMs = arg.repeatIntervalMs ?: DEFAULT_REPEAT_INTERVAL_MS,
+            )
+        }.toMap()
+        held.clear()
+    }
+
+    /** Hand the keys back to the system while the app is in the background. */
+    fun setSuspended(value: Boolean) {
+        suspended = value
+        // The ACTION_UP of a key held while pausing never arrives.
+        held.clear()
+    }
+
+    /**
+     * Handle [event] if its key is mapped, calling [emit] with the key name,
+     * the action and whether it came from holding the key. Returns true when
+     * the event was consumed.
+     */
+    fun handle(event: KeyEvent, emit: (String, String, Boolean) -> Unit): Boolean {
+        if (!isActive) return false
+        val keyCode = event.keyCode
+        val mapping = mappings[keyCode] ?: return false
+        val deferShortPress = mapping.longPress != REPEAT && mapping.longPress != NONE
+
+        when (event.action) {
+            KeyEvent.ACTION_DOWN -> {
+                if (event.repeatCount == 0) {
+                    held[keyCode] = HeldKey(lastSentAt = event.eventTime)
+                    if (!deferShortPress) emit(mapping.keyName, mapping.action, false)
+                    return true
+                }
+                val state = held[keyCode] ?: return true
+                when (mapping.longPress) {
+                    NONE -> {}
+                    REPEAT -> {
+                        if (event.eventTime - state.lastSentAt >= mapping.repeatIntervalMs) {
+                            state.lastSentAt = event.eventTime
+                            emit(mapping.keyName, mapping.action, true)
+                        }
+                    }
+                    else -> {
+                        val heldFor = event.eventTime - event.downTime
+                        if (!state.longPressed &&
+                            heldFor >= ViewConfiguration.getLongPressTimeout()
+                        ) {
+                            state.longPressed = true
+                            emit(mapping.keyName, mapping.longPress, true)
+                        }
+                    }
+                }
+            }
+            KeyEvent.ACTION_UP -> {
+                val state = held.remove(keyCode)
+                if (deferShortPress && state != null && !state.longPressed) {
+                    emit(mapping.keyName, mapping.action, false)
+                }
+            }
+        }
+        return true
+    }
+}
@@ -0,0 +1,255 @@
+package com.readest.native_bridge
+
+import android.content.Context
+import android.content.res.AssetFileDescriptor
+import android.database.Cursor
+import android.database.MatrixCursor
+import android.graphics.Point
+import android.os.CancellationSignal
+import android.os.ParcelFileDescriptor
+import android.provider.DocumentsContract
+import android.provider.DocumentsContract.Document
+import android.provider.DocumentsContract.Root
+import android.provider.DocumentsProvider
+import android.webkit.MimeTypeMap
+import java.io.File
+import java.io.FileNotFoundException
+
+/**
+ * The library in the system Files app and document pickers.
+ *
+ * A "Readest" root holds a Books folder with every downloaded book, most
+ * recently read first, and an Annotations folder with the exported
+ * annotations. Other apps open them through the picker's one-off grant, so
+ * they need no storage or library permission. Everything is read-only.
+ *
+ * Like [LibraryProvider], this answers from [LibraryIndexStore] and works
+ * while the app is closed.
+ */
+class LibraryDocumentsProvider : DocumentsProvider() {
+    companion object {
+        private const val ROOT_ID = "readest"
+        private const val DOC_ROOT = "root"
+        private const val DOC_BOOKS = "books"
+        private const val DOC_ANNOTATIONS = "annotations"
+        private const val PREFIX_BOOK = "book:"
+        private const val PREFIX_ANNOTATION = "annotation:"
+        private const val MAX_RECENTS = 20
+
+        private val ROOT_COLUMNS = arrayOf(
+            Root.COLUMN_ROOT_ID, Root.COLUMN_DOCUMENT_ID, Root.COLUMN_TITLE,
+            Root.COLUMN_ICON, Root.COLUMN_FLAGS,
+        )
+        private val DOCUMENT_COLUMNS = arrayOf(
+            Document.COLUMN_DOCUMENT_ID, Document.COLUMN_DISPLAY_NAME, Document.COLUMN_MIME_TYPE,
+            Document.COLUMN_SIZE, Document.COLUMN_LAST_MODIFIED, Document.COLUMN_FLAGS,
+            Document.COLUMN_SUMMARY,
+        )
+
+        // What the reader opens (see EXTS / MIMETYPES in libs/document.ts);
+        // MimeTypeMap misses several of these on older systems.
+        private val BOOK_MIME_TYPES = mapOf(
+            "epub" to "application/epub+zip",
+            "pdf" to "application/pdf",
+            "mobi" to "application/x-mobipocket-ebook",
+            "azw" to "application/vnd.amazon.ebook",
+            "azw3" to "application/vnd.amazon.mobi8-ebook",
+            "cbz" to "application/vnd.comicbook+zip",
+            "fb2" to "application/x-fictionbook+xml",
+            "fbz" to "application/x-zip-compressed-fb2",
+            "txt" to "text/plain",
+            "md" to "text/markdown",
+        )
+
+        fun authority(context: Context) = "${context.packageName}.documents"
+
+        fun notifyChanged(context: Context) {
+            val authority = authority(context)
+            val resolver = context.contentResolver
+            resolver.notifyChange(DocumentsContract.buildRootsUri(authority), null)
+            listOf(DOC_ROOT, DOC_BOOKS, DOC_ANNOTATIONS).forEach {
+                resolver.notifyChange(DocumentsContract.buildChildDocumentsUri(authority, it), null)
+            }
+        }
+
+        private fun mimeType(file: File): String {
+            val ext = file.extension.lowercase()
+            return BOOK_MIME_TYPES[ext]
+                ?: MimeTypeMap.getSingleton().getMimeTypeFromExtension(ext)
+                ?: "application/octet-stream"
+        }
+    }
+
+    private class Entry(
+        val id: String,
+        val name: String,
+        val mimeType: String,
+        val file: File? = null,
+        val summary: String? = null,
+        val cover: File? = null,
+        /** When the book was last read; `null` for books never opened. */
+        val readAt: Long? = null,
+    )
+
+    override fun onCreate(): Boolean = true
+
+    override fun queryRoots(projection: Array<out String>?): Cursor {
+        val context = checkNotNull(context)
+        return MatrixCursor(projection ?: ROOT_COLUMNS).apply {
+            newRow()
+                .add(Root.COLUMN_ROOT_ID, ROOT_ID)
+                .add(Root.COLUMN_DOCUMENT_ID, DOC_ROOT)
+                .add(Root.COLUMN_TITLE, appLabel(context))
+                .add(Root.COLUMN_ICON, context.applicationInfo.icon)
+                .add(
+                    Root.COLUMN_FLAGS,
+                    Root.FLAG_LOCAL_ONLY or Root.FLAG_SUPPORTS_RECENTS or
+                        Root.FLAG_SUPPORTS_SEARCH or Root.FLAG_SUPPORTS_IS_CHILD,
+                )
+        }
+    }
+
+    override fun queryDocument(documentId: String, projection: Array<out String>?): Cursor {
+        val entry = entry(documentId) ?: throw FileNotFoundException(documentId)
+        return cursorOf(listOf(entry), projection)
+    }
+
+    override fun queryChildDocuments(
+        parentDocumentId: String,
+        projection: Array<out String>?,
+        sortOrder: String?,
+    ): Cursor {
+        val context = checkNotNull(context)
+        val children = when (parentDocumentId) {
+            DOC_ROOT -> listOfNotNull(entry(DOC_BOOKS), entry(DOC_ANNOTATIONS))
+            DOC_BOOKS -> books()
+            DOC_ANNOTATIONS -> annotations()
+            else -> throw FileNotFoundException(parentDocumentId)
+        }
+        return cursorOf(children, projection).apply {
+            setNotificationUri(
+                context.contentResolver,
+                DocumentsContract.buildChildDocumentsUri(authority(context), parentDocumentId),
+            )
+        }
+    }
+
+    override fun queryRecentDocuments(rootId: String, projection: Array<out String>?): Cursor =
+        cursorOf(books().filter { it.readAt != null }.take(MAX_RECENTS), projection)
+
+    override fun querySearchDocuments(
+        rootId: String,
+        query: String,
+        projection: Array<out String>?,
+    ): Cursor {
+        val matches = (books() + annotations()).filter {
+            it.name.contains(query, ignoreCase = true) ||
+                it.summary?.contains(query, ignoreCase = true) == true
+        }
+        return cursorOf(matches, projection)
+    }
+
+    override fun isChildDocument(parentDocumentId: String, documentId: String): Boolean =
+        when (parentDocumentId) {
+            DOC_ROOT -> documentId != DOC_ROOT
+            DOC_BOOKS -> documentId.startsWith(PREFIX_BOOK)
+            DOC_ANNOTATIONS -> documentId.startsWith(PREFIX_ANNOTATION)
+            else -> false
+        }
+
+    override fun openDocument(
+        documentId: String,
+        mode: String,
+        signal: CancellationSignal?,
+    ): ParcelFileDescriptor {
+        if (mode != "r") throw UnsupportedOperationException("The library is read-only")
+        val file = entry(documentId)?.file?.takeIf { it.isFile }
+            ?: throw FileNotFoundException(documentId)
+        return ParcelFileDescriptor.open(file, ParcelFileDescriptor.MODE_READ_ONLY)
+    }
+
+    override fun openDocumentThumbnail(
+        documentId: String,
+        sizeHint: Point?,
+        signal: CancellationSignal?,
+    ): AssetFileDescriptor {
+        val cover = entry(documentId)?.cover ?: throw FileNotFoundException(documentId)
+        val fd = ParcelFileDescriptor.open(cover, ParcelFileDescriptor.MODE_READ_ONLY)
+        return AssetFileDescriptor(fd, 0, AssetFileDescriptor.UNKNOWN_LENGTH)
+    }
+
+    private fun entry(documentId: String): Entry? {
+        val context = checkNotNull(context)
+        return when {
+            documentId == DOC_ROOT -> Entry(DOC_ROOT, appLabel(context), Document.MIME_TYPE_DIR)
+            documentId == DOC_BOOKS -> Entry(
+                DOC_BOOKS, context.getString(R.string.documents_books), Document.MIME_TYPE_DIR
+            )
+            documentId == DOC_ANNOTATIONS -> Entry(
+                DOC_ANNOTATIONS,
+                context.getString(R.string.documents_annotations),
+                Document.MIME_TYPE_DIR,
+            )
+            documentId.startsWith(PREFIX_BOOK) -> books().firstOrNull { it.id == documentId }
+            documentId.startsWith(PREFIX_ANNOTATION) ->
+                annotation(documentId.removePrefix(PREFIX_ANNOTATION))
+            else -> null
+        }
+    }
+
+    /** Downloaded books; ones only in the cloud have no file to hand out. */
+    private fun books(): List<Entry> =
+        LibraryIndexStore.read(checkNotNull(context)).mapNotNull { book ->
+            val file = book.optString("filePath").takeIf { !book.isNull("filePath") }
+                ?.let(::File)?.takeIf { it.isFile } ?: return@mapNotNull null
+            val cover = File(book.optString("coverPath")).takeIf { it.isFile }
+            Entry(
+                id = PREFIX_BOOK + book.optString("hash"),
+                name = file.name,
+                mimeType = mimeType(file),
+                file = file,
+                summary = book.optString("author").ifBlank { null },
+                cover = cover,
+                readAt = if (book.isNull("progress")) null else book.optLong("updatedAt"),
+            )
+        }
+
+    private fun annotations(): List<Entry> {
+        val dir = LibraryIndexStore.annotationsDir(checkNotNull(context)) ?: return emptyList()
+        return dir.listFiles { file -> file.isFile }.orEmpty()
+            .sortedByDescending { it.lastModified() }
+            .map { annotationEntry(it) }
+    }
+
+    // Only plain names inside the annotations directory resolve.
+    private fun annotation(name: String): Entry? {
+        val dir = LibraryIndexStore.annotationsDir(checkNotNull(context)) ?: return null
+        val file = File(dir, name)
+        if (file.parentFile?.canonicalPath != dir.canonicalPath || !file.isFile) return null
+        return annotationEntry(file)
+    }
+
+    private fun annotationEntry(file: File) =
+        Entry(PREFIX_ANNOTATION + file.name, file.name, mimeType(file), file)
+
+    private fun cursorOf(entries: List<Entry>, projection: Array<out String>?): Cursor =
+        MatrixCursor(projection ?: DOCUMENT_COLUMNS).apply {
+            entries.forEach { entry ->
+                val file = entry.file
+                newRow()
+                    .add(Document.COLUMN_DOCUMENT_ID, entry.id)
+                    .add(Document.COLUMN_DISPLAY_NAME, entry.name)
+                    .add(Document.COLUMN_MIME_TYPE, entry.mimeType)
+                    .add(Document.COLUMN_SIZE, file?.length())
+                    .add(Document.COLUMN_LAST_MODIFIED, entry.readAt ?: file?.lastModified())
+                    .add(
+                        Document.COLUMN_FLAGS,
+                        if (entry.cover != null) Document.FLAG_SUPPORTS_THUMBNAIL else 0,
+                    )
+                    .add(Document.COLUMN_SUMMARY, entry.summary)
+            }
+        }
+
+    private fun appLabel(context: Context) =
+        context.applicationInfo.loadLabel(context.packageManager).toString()
+}
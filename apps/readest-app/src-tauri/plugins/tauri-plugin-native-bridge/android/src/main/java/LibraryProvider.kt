@@ -0,0 +1,243 @@
+package com.readest.native_bridge
+
+import android.app.Activity
+import android.content.ContentProvider
+import android.content.ContentValues
+import android.content.Context
+import android.content.Intent
+import android.content.UriMatcher
+import android.database.Cursor
+import android.database.MatrixCursor
+import android.net.Uri
+import android.os.Bundle
+import android.os.ParcelFileDescriptor
+import android.util.Log
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.io.FileNotFoundException
+
+/**
+ * Read-only view of the library for other apps (automation tools,
+ * launchers), at `content://<applicationId>.library/books`.
+ *
+ * Rows carry the title, author, format, percent read and last update of each
+ * book, most recently updated first, plus a `cover_uri` served by this
+ * provider and an `open_uri` that opens the book where it was left off.
+ * Viewing a book's content URI opens it too, through [OpenBookActivity].
+ * Callers need the `<applicationId>.permission.READ_LIBRARY` permission.
+ * Any app can hold that normal-level permission, so nothing is answered
+ * until the user turns on sharing in the app's settings
+ * ([LibraryIndexStore.sharedWithApps]); until then queries come back empty.
+ *
+ * The provider answers from [LibraryIndexStore], which the app refreshes
+ * whenever the library changes, so queries work while the app is closed.
+ */
+class LibraryProvider : ContentProvider() {
+    companion object {
+        const val PATH_BOOKS = "books"
+        const val COLUMN_HASH = "hash"
+        const val COLUMN_TITLE = "title"
+        const val COLUMN_AUTHOR = "author"
+        const val COLUMN_FORMAT = "format"
+        const val COLUMN_PROGRESS = "progress"
+        const val COLUMN_UPDATED_AT = "updated_at"
+        const val COLUMN_COVER_URI = "cover_uri"
+        const val COLUMN_OPEN_URI = "open_uri"
+        const val MIME_BOOKS = "vnd.android.cursor.dir/vnd.readest.book"
+        const val MIME_BOOK = "vnd.android.cursor.item/vnd.readest.book"
+
+        private val COLUMNS = arrayOf(
+            "_id", COLUMN_HASH, COLUMN_TITLE, COLUMN_AUTHOR, COLUMN_FORMAT,
+            COLUMN_PROGRESS, COLUMN_UPDATED_AT, COLUMN_COVER_URI, COLUMN_OPEN_URI,
+        )
+        private const val MATCH_BOOKS = 1
+        private const val MATCH_BOOK = 2
+        private const val MATCH_COVER = 3
+
+        fun authority(context: Context) = "${context.packageName}.library"
+
+        fun booksUri(context: Context): Uri =
+            Uri.parse("content://${authority(context)}/$PATH_BOOKS")
+
+        fun openUri(hash: String): Uri = Uri.parse("readest://book/$hash")
+    }
+
+    private lateinit var matcher: UriMatcher
+
+    override fun onCreate(): Boolean {
+        val authority = authority(context ?: return false)
+        matcher = UriMatcher(UriMatcher.NO_MATCH).apply {
+            addURI(authority, PATH_BOOKS, MATCH_BOOKS)
+            addURI(authority, "$PATH_BOOKS/*", MATCH_BOOK)
+            addURI(authority, "$PATH_BOOKS/*/cover", MATCH_COVER)
+        }
+        return true
+    }
+
+    override fun query(
+        uri: Uri,
+        projection: Array<out String>?,
+        selection: String?,
+        selectionArgs: Array<out String>?,
+        sortOrder: String?,
+    ): Cursor? {
+        val context = context ?: return null
+        val books = if (LibraryIndexStore.sharedWithApps(context)) {
+            LibraryIndexStore.read(context)
+        } else {
+            emptyList()
+        }
+        val rows = when (matcher.match(uri)) {
+            MATCH_BOOKS -> books
+            MATCH_BOOK -> books.filter { it.optString("hash") == uri.lastPathSegment }
+            else -> return null
+        }
+        val columns = projection?.filter { it in COLUMNS }?.toTypedArray() ?: COLUMNS
+        val cursor = MatrixCursor(columns, rows.size)
+        rows.forEachIndexed { index, book ->
+            val hash = book.optString("hash")
+            val values = mapOf(
+                "_id" to index + 1L,
+                COLUMN_HASH to hash,
+                COLUMN_TITLE to book.optString("title"),
+                COLUMN_AUTHOR to book.optString("author"),
+                COLUMN_FORMAT to book.optString("format"),
+                COLUMN_PROGRESS to (if (book.isNull("progress")) null else book.optInt("progress")),
+                COLUMN_UPDATED_AT to book.optLong("updatedAt"),
+                COLUMN_COVER_URI to coverUri(context, hash, book)?.toString(),
+                COLUMN_OPEN_URI to openUri(hash).toString(),
+            )
+            cursor.addRow(columns.map { values[it] })
+        }
+        cursor.setNotificationUri(context.contentResolver, booksUri(context))
+        return cursor
+    }
+
+    override fun getType(uri: Uri): String? = when (matcher.match(uri)) {
+        MATCH_BOOKS -> MIME_BOOKS
+        MATCH_BOOK -> MIME_BOOK
+        MATCH_COVER -> "image/png"
+        else -> null
+    }
+
+    override fun openFile(uri: Uri, mode: String): ParcelFileDescriptor? {
+        val context = context ?: return null
+        if (matcher.match(uri) != MATCH_COVER || mode != "r" ||
+            !LibraryIndexStore.sharedWithApps(context)
+        ) {
+            throw FileNotFoundException(uri.toString())
+        }
+        val hash = uri.pathSegments[1]
+        val book = LibraryIndexStore.read(context).firstOrNull { it.optString("hash") == hash }
+        val cover = book?.let { File(it.optString("coverPath")) }
+        if (cover == null || !cover.isFile) throw FileNotFoundException(uri.toString())
+        return ParcelFileDescriptor.open(cover, ParcelFileDescriptor.MODE_READ_ONLY)
+    }
+
+    private fun coverUri(context: Context, hash: String, book: JSONObject): Uri? {
+        if (!File(book.optString("coverPath")).isFile) return null
+        return booksUri(context).buildUpon().appendPath(hash).appendPath("cover").build()
+    }
+
+    override fun insert(uri: Uri, values: ContentValues?): Uri? =
+        throw UnsupportedOperationException("The library is read-only")
+
+    override fun update(
+        uri: Uri,
+        values: ContentValues?,
+        selection: String?,
+        selectionArgs: Array<out String>?,
+    ): Int = throw UnsupportedOperationException("The library is read-only")
+
+    override fun delete(uri: Uri, selection: String?, selectionArgs: Array<out String>?): Int =
+        throw UnsupportedOperationException("The library is read-only")
+}
+
+/**
+ * The copy of the library [LibraryProvider] and [LibraryDocumentsProvider]
+ * serve, written by the `update_library_provider` command. The whole index is
+ * replaced each time.
+ */
+object LibraryIndexStore {
+    private const val FILE = "library_provider.json"
+
+    fun write(context: Context, args: UpdateLibraryProviderRequestArgs) {
+        val books = JSONArray()
+        args.books.forEach { book ->
+            books.put(
+                JSONObject()
+                    .put("hash", book.hash)
+                    .put("title", book.title)
+                    .put("author", book.author)
+                    .put("format", book.format)
+                    .put("progress", book.progress ?: JSONObject.NULL)
+                    .put("updatedAt", book.updatedAt)
+                    .put("coverPath", book.coverPath)
+                    .put("filePath", book.filePath ?: JSONObject.NULL)
+            )
+        }
+        val json = JSONObject()
+            .put("books", books)
+            .put("annotationsDir", args.annotationsDir)
+            .put("shareWithApps", args.shareWithApps)
+        val file = File(context.filesDir, FILE)
+        val tmp = File(context.filesDir, "$FILE.tmp")
+        tmp.writeText(json.toString())
+        if (!tmp.renameTo(file)) {
+            Log.w("LibraryProvider", "Failed to replace the library index")
+            tmp.delete()
+            return
+        }
+        context.contentResolver.notifyChange(LibraryProvider.booksUri(context), null)
+        LibraryDocumentsProvider.notifyChanged(context)
+    }
+
+    fun read(context: Context): List<JSONObject> {
+        val books = readIndex(context)?.optJSONArray("books") ?: return emptyList()
+        return (0 until books.length()).mapNotNull { books.optJSONObject(it) }
+    }
+
+    /** Whether the user lets other apps read the library through [LibraryProvider]. */
+    fun sharedWithApps(context: Context): Boolean =
+        readIndex(context)?.optBoolean("shareWithApps", false) ?: false
+
+    /** Where exported annotations are kept; `null` before the first update. */
+    fun annotationsDir(context: Context): File? =
+        readIndex(context)?.optString("annotationsDir")?.takeIf { it.isNotEmpty() }?.let(::File)
+
+    private fun readIndex(context: Context): JSONObject? {
+        val file = File(context.filesDir, FILE)
+        if (!file.exists()) return null
+        return try {
+            JSONObject(file.readText())
+        } catch (e: Exception) {
+            Log.w("LibraryProvider", "Failed to read the library index: ${e.message}")
+            null
+        }
+    }
+}
+
+/**
+ * Opens a book viewed through its [LibraryProvider] content URI by handing
+ * its `readest://book/{hash}` link to the app, if the library is shared with
+ * other apps. Has no UI.
+ */
+class OpenBookActivity : Activity() {
+    override fun onCreate(savedInstanceState: Bundle?) {
+        super.onCreate(savedInstanceState)
+        val hash = intent.data
+            ?.takeIf { LibraryIndexStore.sharedWithApps(this) }
+            ?.takeIf { it.authority == LibraryProvider.authority(this) }
+            ?.pathSegments?.getOrNull(1)
+            ?.takeIf { hash -> LibraryIndexStore.read(this).any { it.optString("hash") == hash } }
+        if (hash != null) {
+            startActivity(
+                Intent(Intent.ACTION_VIEW, LibraryProvider.openUri(hash))
+                    .setPackage(packageName)
+                    .addFlags(Intent.FLAG_ACTIVITY_NEW_TASK)
+            )
+        }
+        finish()
+    }
+}
@@ -3,10 +3,14 @@ package com.readest.native_bridge
 import android.Manifest
 import android.app.Activity
 import android.app.PendingIntent
+import android.content.BroadcastReceiver
 import android.content.ComponentName
 import android.content.ContentValues
 import android.content.Context
 import android.content.Intent
+import android.content.IntentFilter
+import android.net.ConnectivityManager
+import android.net.NetworkCapabilities
 import android.net.Uri
 import android.provider.MediaStore
 import android.util.Log
@@ -16,6 +20,8 @@ import android.provider.Settings
 import android.provider.DocumentsContract
 import android.view.View
 import android.view.KeyEvent
+import android.view.MotionEvent
+import android.view.Window
 import android.view.WindowInsets
 import android.view.WindowManager
 import android.view.WindowInsetsController
@@ -87,6 +93,7 @@ class InterceptKeysRequestArgs {
     var volumeKeys: Boolean? = null
     var backKey: Boolean? = null
     var pageTurnerKeys: Boolean? = null
+    var pageTurnerBindings: List<String>? = null
     var learnMode: Boolean? = null
 }
 
@@ -100,11 +107,40 @@ class SetScreenBrightnessRequestArgs {
     var brightness: Double? = null // 0.0 to 1.0
 }
 
+@InvokeArg
+class SetKeepAwakeRequestArgs {
+    var enabled: Boolean = false
+}
+
+@InvokeArg
+class KeyMappingArgs {
+    var key: String? = null
+    var action: String? = null
+    var longPress: String? = null
+    var repeatIntervalMs: Long? = null
+}
+
+@InvokeArg
+class SetKeyMappingRequestArgs {
+    var mappings: List<KeyMappingArgs> = emptyList()
+}
+
 @InvokeArg
 class OpenExternalUrlArgs {
     var url: String? = null
 }
 
+@InvokeArg
+class TakePersistableUriPermissionArgs {
+    var uri: String = ""
+    var write: Boolean = false
+}
+
+@InvokeArg
+class ReleasePersistableUriPermissionArgs {
+    var uri: String = ""
+}
+
 @InvokeArg
 class ShowLookupPopoverArgs {
     var word: String? = null
@@ -156,6 +192,44 @@ class UpdateReadingWidgetRequestArgs {
     var tts: UpdateReadingWidgetTtsArgs? = null
 }
 
+@InvokeArg
+class AppShortcutBookArgs {
+    var hash: String = ""
+    var title: String = ""
+    var author: String = ""
+    var coverPath: String = ""
+}
+
+@InvokeArg
+class UpdateAppShortcutsRequestArgs {
+    var books: List<AppShortcutBookArgs> = emptyList()
+}
+
+@InvokeArg
+class LibraryProviderBookArgs {
+    var hash: String = ""
+    var title: String = ""
+    var author: String = ""
+    var format: String = ""
+    var progress: Int? = null
+    var updatedAt: Long = 0
+    var coverPath: String = ""
+    var filePath: String? = null
+}
+
+@InvokeArg
+class UpdateLibraryProviderRequestArgs {
+    var books: List<LibraryProviderBookArgs> = emptyList()
+    var annotationsDir: String = ""
+    var shareWithApps: Boolean = false
+}
+
+@InvokeArg
+class UpdateContinueReadingWidgetRequestArgs {
+    var book: UpdateReadingWidgetBookArgs? = null
+    var emptyTitle: String = ""
+}
+
 data class ProductData(
     val id: String,
     val title: String,
@@ -201,8 +275,27 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
     // a dead Activity.
     private val pluginScope = CoroutineScope(SupervisorJob() + Dispatchers.Main)
 
+    // Tells the reading-statistics tracker the screen went off, so a book
+    // left open on a locked phone stops counting as read. The screen
+    // broadcasts are only delivered to receivers registered at runtime.
+    private val screenStateReceiver = object : BroadcastReceiver() {
+        override fun onReceive(context: Context, intent: Intent) {
+            val on = when (intent.action) {
+                Intent.ACTION_SCREEN_ON -> true
+                Intent.ACTION_SCREEN_OFF -> false
+                else -> return
+            }
+            triggerEvent("screen-state", JSObject().apply { put("on", on) })
+        }
+    }
+
     override fun onDestroy() {
         pluginScope.cancel()
+        try {
+            activity.unregisterReceiver(screenStateReceiver)
+        } catch (e: IllegalArgumentException) {
+            // Never registered.
+        }
         instance = null
     }
 
@@ -220,12 +313,41 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         webViewRef = webView
         super.load(webView)
         handleIntent(activity.intent)
+        ShareImports.listen { emitOrQueue("share-import", it) }
+        pluginScope.launch(Dispatchers.IO) { ShareImports.pruneCopies(activity) }
+        val screenFilter = IntentFilter().apply {
+            addAction(Intent.ACTION_SCREEN_ON)
+            addAction(Intent.ACTION_SCREEN_OFF)
+        }
+        ContextCompat.registerReceiver(
+            activity, screenStateReceiver, screenFilter, ContextCompat.RECEIVER_NOT_EXPORTED
+        )
+        // Mapped and page-turner keys are taken before the activity sees
+        // them, so they never reach its own key interception, the webview's
+        // focus navigation or the system volume.
+        val callback = activity.window.callback
+        activity.window.callback = object : Window.Callback by callback {
+            override fun dispatchKeyEvent(event: KeyEvent): Boolean =
+                dispatchHardwareKey(event) || callback.dispatchKeyEvent(event)
+
+            override fun dispatchGenericMotionEvent(event: MotionEvent): Boolean =
+                PageTurnerKeyController.handleMotion(event, ::forwardKey) ||
+                    callback.dispatchGenericMotionEvent(event)
+        }
     }
 
     override fun onNewIntent(intent: Intent) {
         handleIntent(intent)
     }
 
+    override fun onPause() {
+        KeyMappingController.setSuspended(true)
+    }
+
+    override fun onResume() {
+        KeyMappingController.setSuspended(false)
+    }
+
     private fun handleIntent(intent: Intent?) {
         if (intent == null) return
         Log.d("NativeBridgePlugin", "Received intent: action=${intent.action} data=${intent.data}")
@@ -717,6 +839,9 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
     @Command
     fun intercept_keys(invoke: Invoke) {
         val args = invoke.parseArgs(InterceptKeysRequestArgs::class.java)
+        args.pageTurnerKeys?.let { PageTurnerKeyController.enabled = it }
+        args.pageTurnerBindings?.let { PageTurnerKeyController.setBoundKeys(it) }
+        args.learnMode?.let { PageTurnerKeyController.learnMode = it }
         if (activity is KeyDownInterceptor) {
             val interceptor = activity as KeyDownInterceptor
             args.backKey?.let { interceptor.interceptBackKey(it) }
@@ -828,6 +953,62 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         invoke.resolve(ret)
     }
 
+    /**
+     * Keep the screen on while the reader's window is showing. The flag only
+     * holds while the activity is visible, so nothing needs undoing when the
+     * app goes to the background.
+     */
+    @Command
+    fun set_keep_awake(invoke: Invoke) {
+        val args = invoke.parseArgs(SetKeepAwakeRequestArgs::class.java)
+        activity.runOnUiThread {
+            if (args.enabled) {
+                activity.window.addFlags(WindowManager.LayoutParams.FLAG_KEEP_SCREEN_ON)
+            } else {
+                activity.window.clearFlags(WindowManager.LayoutParams.FLAG_KEEP_SCREEN_ON)
+            }
+            invoke.resolve()
+        }
+    }
+
+    /**
+     * Map the volume keys to reader actions, sent to the webview as
+     * `mapped-key` events. An empty list releases the keys.
+     */
+    @Command
+    fun set_key_mapping(invoke: Invoke) {
+        val args = invoke.parseArgs(SetKeyMappingRequestArgs::class.java)
+        activity.runOnUiThread {
+            KeyMappingController.setMappings(args.mappings)
+            invoke.resolve()
+        }
+    }
+
+    private fun dispatchHardwareKey(event: KeyEvent): Boolean {
+        if (PageTurnerKeyController.learnMode) {
+            return PageTurnerKeyController.handleKey(event, ::forwardKey)
+        }
+        return dispatchMappedKey(event) || PageTurnerKeyController.handleKey(event, ::forwardKey)
+    }
+
+    // Page-turner keys take the same path into the webview as the keys the
+    // activity intercepts (see `handleNativeKeyDown` in deviceStore.ts).
+    private fun forwardKey(name: String, keyCode: Int) {
+        webViewRef?.evaluateJavascript(
+            """try { window.onNativeKeyDown("$name", $keyCode); } catch (_) {}""",
+            null
+        )
+    }
+
+    private fun dispatchMappedKey(event: KeyEvent): Boolean =
+        KeyMappingController.handle(event) { key, action, longPress ->
+            triggerEvent("mapped-key", JSObject().apply {
+                put("key", key)
+                put("action", action)
+                put("longPress", longPress)
+            })
+        }
+
     @Command
     fun iap_is_available(invoke: Invoke) {
         val isAvailable = billingManager.isBillingAvailable()
@@ -1021,7 +1202,14 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         pendingFolderPickerInvoke = invoke
 
         try {
-            val intent = Intent(Intent.ACTION_OPEN_DOCUMENT_TREE)
+            // Without the persistable flag some pickers hand back a grant
+            // that lasts only until the process dies.
+            val intent = Intent(Intent.ACTION_OPEN_DOCUMENT_TREE).addFlags(
+                Intent.FLAG_GRANT_READ_URI_PERMISSION or
+                    Intent.FLAG_GRANT_WRITE_URI_PERMISSION or
+                    Intent.FLAG_GRANT_PERSISTABLE_URI_PERMISSION or
+                    Intent.FLAG_GRANT_PREFIX_URI_PERMISSION
+            )
             activity.startActivityForResult(intent, FOLDER_PICKER_REQUEST_CODE)
         } catch (e: Exception) {
             val result = JSObject()
@@ -1052,9 +1240,7 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
             result.put("path", null)
         } else {
             try {
-                val flags = Intent.FLAG_GRANT_READ_URI_PERMISSION or
-                          Intent.FLAG_GRANT_WRITE_URI_PERMISSION
-                activity.contentResolver.takePersistableUriPermission(uri, flags)
+                takePersistableTreePermission(uri)
                 result.put("cancelled", false)
                 result.put("uri", uri.toString())
                 result.put("path", extractPathFromUri(uri))
@@ -1075,6 +1261,100 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         pendingInvoke = null
     }
 
+    /**
+     * Persist read/write access to a picked tree, or read access alone from
+     * providers that only grant that (some USB and cloud roots).
+     */
+    private fun takePersistableTreePermission(uri: Uri) {
+        val read = Intent.FLAG_GRANT_READ_URI_PERMISSION
+        try {
+            activity.contentResolver.takePersistableUriPermission(
+                uri, read or Intent.FLAG_GRANT_WRITE_URI_PERMISSION
+            )
+        } catch (e: SecurityException) {
+            activity.contentResolver.takePersistableUriPermission(uri, read)
+        }
+    }
+
+    @Command
+    fun take_persistable_uri_permission(invoke: Invoke) {
+        val args = invoke.parseArgs(TakePersistableUriPermissionArgs::class.java)
+        val result = JSObject()
+        try {
+            var flags = Intent.FLAG_GRANT_READ_URI_PERMISSION
+            if (args.write) flags = flags or Intent.FLAG_GRANT_WRITE_URI_PERMISSION
+            activity.contentResolver.takePersistableUriPermission(Uri.parse(args.uri), flags)
+            result.put("success", true)
+        } catch (e: SecurityException) {
+            // Only a grant the app holds right now can be persisted; once one
+            // is lost the user has to pick the folder again.
+            result.put("success", false)
+            result.put("error", "Permission error: ${e.message}")
+        }
+        invoke.resolve(result)
+    }
+
+    @Command
+    fun release_persistable_uri_permission(invoke: Invoke) {
+        val args = invoke.parseArgs(ReleasePersistableUriPermissionArgs::class.java)
+        val uri = Uri.parse(args.uri)
+        val result = JSObject()
+        val granted = activity.contentResolver.persistedUriPermissions.firstOrNull { it.uri == uri }
+        try {
+            if (granted != null) {
+                val read = if (granted.isReadPermission) Intent.FLAG_GRANT_READ_URI_PERMISSION else 0
+                val write = if (granted.isWritePermission) Intent.FLAG_GRANT_WRITE_URI_PERMISSION else 0
+                activity.contentResolver.releasePersistableUriPermission(uri, read or write)
+            }
+            result.put("success", true)
+        } catch (e: SecurityException) {
+            result.put("success", false)
+            result.put("error", "Permission error: ${e.message}")
+        }
+        invoke.resolve(result)
+    }
+
+    @Command
+    fun list_persisted_uri_permissions(invoke: Invoke) {
+        pluginScope.launch {
+            val permissions = withContext(Dispatchers.IO) {
+                JSArray().apply {
+                    activity.contentResolver.persistedUriPermissions.forEach { granted ->
+                        put(JSObject().apply {
+                            put("uri", granted.uri.toString())
+                            put("path", extractPathFromUri(granted.uri))
+                            put("read", granted.isReadPermission)
+                            put("write", granted.isWritePermission)
+                            put("persistedTime", granted.persistedTime)
+                            put("accessible", isUriAccessible(granted.uri))
+                        })
+                    }
+                }
+            }
+            val result = JSObject()
+            result.put("permissions", permissions)
+            if (isActive) invoke.resolve(result)
+        }
+    }
+
+    /**
+     * Whether a granted URI still resolves. A grant outlives its folder: it
+     * stays listed after the folder is deleted or its SD card removed.
+     */
+    private fun isUriAccessible(uri: Uri): Boolean = try {
+        val documentUri = if (DocumentsContract.isTreeUri(uri)) {
+            val treeId = DocumentsContract.getTreeDocumentId(uri)
+            DocumentsContract.buildDocumentUriUsingTree(uri, treeId)
+        } else {
+            uri
+        }
+        activity.contentResolver.query(
+            documentUri, arrayOf(DocumentsContract.Document.COLUMN_DOCUMENT_ID), null, null, null
+        )?.use { it.moveToFirst() } ?: false
+    } catch (e: Exception) {
+        false
+    }
+
     private fun extractPathFromUri(uri: Uri): String? {
         val path = uri.path ?: return null
         return try {
@@ -1139,6 +1419,55 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         }
     }
 
+    @Command
+    fun update_continue_reading_widget(invoke: Invoke) {
+        val args = invoke.parseArgs(UpdateContinueReadingWidgetRequestArgs::class.java)
+        pluginScope.launch {
+            withContext(Dispatchers.IO) {
+                val snapshot = org.json.JSONObject().put("emptyTitle", args.emptyTitle)
+                args.book?.let { book ->
+                    // The widget draws its own progress bar, so the cover stays plain.
+                    ReadingWidgetStore.writeThumbnail(
+                        activity, book.hash, book.coverPath, null,
+                        fileName = ContinueReadingWidgetProvider.COVER_NAME,
+                    )
+                    snapshot.put(
+                        "book",
+                        org.json.JSONObject()
+                            .put("hash", book.hash)
+                            .put("title", book.title)
+                            .put("author", book.author)
+                            .put("percent", book.percent)
+                    )
+                }
+                ReadingWidgetStore.writeContinueReading(activity, snapshot.toString())
+            }
+            if (isActive) invoke.resolve()
+        }
+    }
+
+    @Command
+    fun update_library_provider(invoke: Invoke) {
+        val args = invoke.parseArgs(UpdateLibraryProviderRequestArgs::class.java)
+        pluginScope.launch {
+            withContext(Dispatchers.IO) {
+                LibraryIndexStore.write(activity, args)
+            }
+            if (isActive) invoke.resolve()
+        }
+    }
+
+    @Command
+    fun update_app_shortcuts(invoke: Invoke) {
+        val args = invoke.parseArgs(UpdateAppShortcutsRequestArgs::class.java)
+        pluginScope.launch {
+            withContext(Dispatchers.IO) {
+                AppShortcutsController.update(activity, args.books)
+            }
+            if (isActive) invoke.resolve()
+        }
+    }
+
     // ── Sync passphrase keychain ──────────────────────────────────────
     // Backed by EncryptedSharedPreferences, which derives an AES-GCM
     // master key from AndroidKeystore and stores the value-of-keys map
@@ -1510,6 +1839,34 @@ class NativeBridgePlugin(private val activity: Activity): Plugin(activity) {
         controller.show()
     }
 
+    /**
+     * Report the active network for the sync transfer policy. `metered`
+     * comes from NET_CAPABILITY_NOT_METERED, so a Wi-Fi hotspot or a network
+     * the user marked as metered counts as metered just like cellular.
+     */
+    @Command
+    fun get_network_status(invoke: Invoke) {
+        val ret = JSObject()
+        val manager = activity.getSystemService(Context.CONNECTIVITY_SERVICE) as? ConnectivityManager
+        val caps = manager?.getNetworkCapabilities(manager.activeNetwork)
+        if (caps == null) {
+            ret.put("connected", false)
+            ret.put("metered", false)
+            ret.put("kind", "none")
+        } else {
+            val kind = when {
+                caps.hasTransport(NetworkCapabilities.TRANSPORT_WIFI) -> "wifi"
+                caps.hasTransport(NetworkCapabilities.TRANSPORT_ETHERNET) -> "ethernet"
+                caps.hasTransport(NetworkCapabilities.TRANSPORT_CELLULAR) -> "cellular"
+                else -> "other"
+            }
+            ret.put("connected", caps.hasCapability(NetworkCapabilities.NET_CAPABILITY_INTERNET))
+            ret.put("metered", !caps.hasCapability(NetworkCapabilities.NET_CAPABILITY_NOT_METERED))
+            ret.put("kind", kind)
+        }
+        invoke.resolve(ret)
+    }
+
     /**
      * Trigger a deep e-ink full screen refresh (GC16 waveform) to clear
      * ghosting. Driven by the page-turner "Refresh Page" action on e-ink
@@ -0,0 +1,98 @@
+package com.readest.native_bridge
+
+import android.os.Build
+import android.view.KeyEvent
+import android.view.MotionEvent
+
+/**
+ * Captures the buttons of Bluetooth page-turner remotes ("clickers").
+ *
+ * Clickers pair as a keyboard, a media remote or a mouse, so a button may
+ * arrive as an arrow or page key, a media key, or a mouse button. Each is
+ * given a normalized name shared with iOS (`ArrowRight`, `PageDown`,
+ * `MediaNext`, `MouseBack`, ...) and forwarded to the webview, which maps
+ * the names the user has bound to page turns.
+ *
+ * Only the bound names are taken from the system, so unbound keys keep
+ * working as usual. In learn mode every key but Back is taken, so the
+ * settings UI can capture whichever button the user presses.
+ */
+object PageTurnerKeyController {
+    // Before the webview sends its bindings, take the media keys, which
+    // nothing else in the app listens to.
+    private val DEFAULT_KEYS = setOf(
+        "MediaNext", "MediaPrevious", "MediaPlayPause", "MediaFastForward", "MediaRewind",
+    )
+
+    private val keyNames = mapOf(
+        KeyEvent.KEYCODE_DPAD_UP to "ArrowUp",
+        KeyEvent.KEYCODE_DPAD_DOWN to "ArrowDown",
+        KeyEvent.KEYCODE_DPAD_LEFT to "ArrowLeft",
+        KeyEvent.KEYCODE_DPAD_RIGHT to "ArrowRight",
+        KeyEvent.KEYCODE_DPAD_CENTER to "DpadCenter",
+        KeyEvent.KEYCODE_PAGE_UP to "PageUp",
+        KeyEvent.KEYCODE_PAGE_DOWN to "PageDown",
+        KeyEvent.KEYCODE_SPACE to "Space",
+        KeyEvent.KEYCODE_ENTER to "Enter",
+        KeyEvent.KEYCODE_NUMPAD_ENTER to "Enter",
+        KeyEvent.KEYCODE_CAMERA to "Camera",
+        KeyEvent.KEYCODE_VOLUME_UP to "VolumeUp",
+        KeyEvent.KEYCODE_VOLUME_DOWN to "VolumeDown",
+        KeyEvent.KEYCODE_MEDIA_NEXT to "MediaNext",
+        KeyEvent.KEYCODE_MEDIA_PREVIOUS to "MediaPrevious",
+        KeyEvent.KEYCODE_MEDIA_PLAY_PAUSE to "MediaPlayPause",
+        KeyEvent.KEYCODE_MEDIA_FAST_FORWARD to "MediaFastForward",
+        KeyEvent.KEYCODE_MEDIA_REWIND to "MediaRewind",
+    )
+
+    private val mouseButtonNames = mapOf(
+        MotionEvent.BUTTON_SECONDARY to "MouseSecondary",
+        MotionEvent.BUTTON_TERTIARY to "MouseMiddle",
+        MotionEvent.BUTTON_BACK to "MouseBack",
+        MotionEvent.BUTTON_FORWARD to "MouseForward",
+    )
+
+    // Set from the plugin command thread, read on the UI thread.
+    @Volatile var enabled = false
+    @Volatile var learnMode = false
+    @Volatile private var boundKeys: Set<String> = DEFAULT_KEYS
+
+    fun setBoundKeys(keys: List<String>) {
+        boundKeys = keys.toSet()
+    }
+
+    fun keyName(keyCode: Int): String = keyNames[keyCode] ?: "Keycode$keyCode"
+
+    private fun takes(name: String) = learnMode || (enabled && name in boundKeys)
+
+    /**
+     * Forward [event] through [emit] if it's a page-turner key and return
+     * true when it was consumed. Repeats and key-ups of a taken key are
+     * consumed without forwarding.
+     */
+    fun handleKey(event: KeyEvent, emit: (String, Int) -> Unit): Boolean {
+        val keyCode = event.keyCode
+        if (keyCode == KeyEvent.KEYCODE_BACK) return false
+        val name = keyName(keyCode)
+        if (!takes(name)) return false
+        if (event.action == KeyEvent.ACTION_DOWN && event.repeatCount == 0) {
+            emit(name, keyCode)
+        }
+        return true
+    }
+
+    /** The same for the extra buttons of a mouse-type clicker. */
+    fun handleMotion(event: MotionEvent, emit: (String, Int) -> Unit): Boolean {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.M) return false
+        val action = event.actionMasked
+        if (action != MotionEvent.ACTION_BUTTON_PRESS &&
+            action != MotionEvent.ACTION_BUTTON_RELEASE
+        ) {
+            return false
+        }
+        val name = mouseButtonNames[event.actionButton] ?: return false
+        if (!takes(name)) return false
+        if (action == MotionEvent.ACTION_BUTTON_PRESS) emit(name, 0)
+        return true
+    }
+}
@@ -115,3 +115,50 @@ class ReadingWidgetProvider : AppWidgetProvider() {
         mgr.updateAppWidget(id, views)
     }
 }
+
+/**
+ * The book being read, with its title, author and progress; tapping it
+ * reopens the book where the reader left off. Updated by the app's Rust side
+ * whenever the reading progress changes.
+ */
+class ContinueReadingWidgetProvider : AppWidgetProvider() {
+    companion object {
+        /** Name of the cover thumbnail, shared by every instance of the widget. */
+        const val COVER_NAME = "continue_reading"
+    }
+
+    override fun onUpdate(context: Context, mgr: AppWidgetManager, ids: IntArray) {
+        for (id in ids) updateWidget(context, mgr, id)
+    }
+
+    private fun updateWidget(context: Context, mgr: AppWidgetManager, id: Int) {
+        val snapshot = ReadingWidgetStore.readContinueReading(context)
+        val views = RemoteViews(context.packageName, R.layout.widget_continue_reading)
+        val book = snapshot.optJSONObject("book")
+        if (book == null) {
+            views.setViewVisibility(R.id.empty, android.view.View.VISIBLE)
+            views.setViewVisibility(R.id.book, android.view.View.GONE)
+            views.setTextViewText(R.id.empty, snapshot.optString("emptyTitle"))
+            // With nothing to resume, a tap just opens the app.
+            context.packageManager.getLaunchIntentForPackage(context.packageName)?.let {
+                val flags = PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+                views.setOnClickPendingIntent(
+                    R.id.root, PendingIntent.getActivity(context, id, it, flags)
+                )
+            }
+        } else {
+            val percent = book.optInt("percent").coerceIn(0, 100)
+            views.setViewVisibility(R.id.empty, android.view.View.GONE)
+            views.setViewVisibility(R.id.book, android.view.View.VISIBLE)
+            setCover(context, views, R.id.cover, COVER_NAME)
+            views.setTextViewText(R.id.title, book.optString("title"))
+            views.setTextViewText(R.id.author, book.optString("author"))
+            views.setProgressBar(R.id.progress, 100, percent, false)
+            views.setTextViewText(R.id.percent, "$percent%")
+            views.setOnClickPendingIntent(
+                R.id.root, bookPendingIntent(context, book.optString("hash"), id)
+            )
+        }
+        mgr.updateAppWidget(id, views)
+    }
+}
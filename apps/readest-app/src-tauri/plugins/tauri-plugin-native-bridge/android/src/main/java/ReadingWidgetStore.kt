@@ -18,6 +18,7 @@ import kotlin.math.max
 object ReadingWidgetStore {
     const val PREFS = "reading_widget"
     const val KEY_SNAPSHOT = "snapshot"
+    const val KEY_CONTINUE_READING = "continue_reading"
     private const val THUMB_WIDTH = 240
     private const val THUMB_HEIGHT = 360
     private const val CORNER_RADIUS = 18f
@@ -25,8 +26,19 @@ object ReadingWidgetStore {
     fun coversDir(context: Context): File =
         File(context.filesDir, "widget/covers").apply { mkdirs() }
 
-    fun writeThumbnail(context: Context, hash: String, sourcePath: String, percent: Int) {
-        val dst = File(coversDir(context), "$hash.png")
+    /**
+     * Write the widget thumbnail of a cover to `<fileName>.png`. With a
+     * [percent] the progress bar and badge are baked in; without one the
+     * cover is left plain for a widget that shows progress itself.
+     */
+    fun writeThumbnail(
+        context: Context,
+        hash: String,
+        sourcePath: String,
+        percent: Int?,
+        fileName: String = hash,
+    ) {
+        val dst = File(coversDir(context), "$fileName.png")
         val src = File(sourcePath)
         if (!src.exists()) { dst.delete(); return }
         // Note: skip-if-unchanged removed because the composite depends on the live percent.
@@ -78,6 +90,11 @@ object ReadingWidgetStore {
             paint
         )
         scaled.recycle()
+        if (percent == null) {
+            dst.outputStream().use { rounded.compress(Bitmap.CompressFormat.PNG, 100, it) }
+            rounded.recycle()
+            return
+        }
 
         // Bake progress bar and % badge into the cover bitmap.
         val w = rounded.width.toFloat()
@@ -128,7 +145,19 @@ object ReadingWidgetStore {
     fun writeSnapshot(context: Context, json: String) {
         context.getSharedPreferences(PREFS, Context.MODE_PRIVATE)
             .edit().putString(KEY_SNAPSHOT, json).apply()
-        notifyWidgets(context)
+        notifyWidgets(context, ReadingWidgetProvider::class.java)
+    }
+
+    fun writeContinueReading(context: Context, json: String) {
+        context.getSharedPreferences(PREFS, Context.MODE_PRIVATE)
+            .edit().putString(KEY_CONTINUE_READING, json).apply()
+        notifyWidgets(context, ContinueReadingWidgetProvider::class.java)
+    }
+
+    fun readContinueReading(context: Context): JSONObject {
+        val raw = context.getSharedPreferences(PREFS, Context.MODE_PRIVATE)
+            .getString(KEY_CONTINUE_READING, null) ?: return JSONObject()
+        return runCatching { JSONObject(raw) }.getOrDefault(JSONObject())
     }
 
     fun readSnapshot(context: Context): JSONObject {
@@ -137,9 +166,8 @@ object ReadingWidgetStore {
         return runCatching { JSONObject(raw) }.getOrDefault(JSONObject())
     }
 
-    private fun notifyWidgets(context: Context) {
+    private fun notifyWidgets(context: Context, cls: Class<*>) {
         val mgr = AppWidgetManager.getInstance(context)
-        val cls = ReadingWidgetProvider::class.java
         val ids = mgr.getAppWidgetIds(ComponentName(context, cls))
         if (ids.isNotEmpty()) {
             val intent = android.content.Intent(AppWidgetManager.ACTION_APPWIDGET_UPDATE)
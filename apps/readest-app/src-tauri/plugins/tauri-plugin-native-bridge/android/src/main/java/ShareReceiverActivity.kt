@@ -0,0 +1,138 @@
+package com.readest.native_bridge
+
+import android.app.Activity
+import android.content.Context
+import android.content.Intent
+import android.net.Uri
+import android.os.Build
+import android.os.Bundle
+import android.provider.OpenableColumns
+import android.util.Log
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import java.io.File
+import java.util.concurrent.TimeUnit
+
+/**
+ * "Share → Readest" target for files and links.
+ *
+ * Has no UI: a shared file is copied into app storage while the share's
+ * temporary read grant is still valid, a shared link is picked out of the
+ * text, and both are handed to [ShareImports] before the app is brought to
+ * the front. The plugin emits them to the webview as a `share-import` event
+ * `{ files, urls }`, where the files are imported into the library and the
+ * links saved as articles.
+ */
+class ShareReceiverActivity : Activity() {
+    override fun onCreate(savedInstanceState: Bundle?) {
+        super.onCreate(savedInstanceState)
+        val shared = intent
+        val context = applicationContext
+        val urls = sharedUrls(shared)
+        val streams = if (urls.isEmpty()) sharedStreams(shared) else emptyList()
+        packageManager.getLaunchIntentForPackage(packageName)?.let {
+            it.addFlags(Intent.FLAG_ACTIVITY_NEW_TASK or Intent.FLAG_ACTIVITY_RESET_TASK_IF_NEEDED)
+            startActivity(it)
+        }
+        // The read grant on the shared URIs lasts while this activity does,
+        // so it finishes only once the copies are made.
+        Thread {
+            val files = streams.mapNotNull { ShareImports.copyToStorage(context, it) }
+            if (files.isNotEmpty() || urls.isNotEmpty()) ShareImports.deliver(files, urls)
+            runOnUiThread { finish() }
+        }.start()
+    }
+
+    /** The first http(s) link in the shared text; browsers may prepend a title. */
+    private fun sharedUrls(intent: Intent): List<String> {
+        if (intent.action != Intent.ACTION_SEND) return emptyList()
+        val text = intent.getStringExtra(Intent.EXTRA_TEXT)?.trim() ?: return emptyList()
+        val url = text.split(Regex("\\s+"))
+            .firstOrNull { it.startsWith("http://") || it.startsWith("https://") }
+        return listOfNotNull(url)
+    }
+
+    @Suppress("DEPRECATION")
+    private fun sharedStreams(intent: Intent): List<Uri> = when (intent.action) {
+        Intent.ACTION_SEND -> listOfNotNull(
+            if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
+                intent.getParcelableExtra(Intent.EXTRA_STREAM, Uri::class.java)
+            } else {
+                intent.getParcelableExtra(Intent.EXTRA_STREAM) as? Uri
+            }
+        )
+        Intent.ACTION_SEND_MULTIPLE ->
+            if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
+                intent.getParcelableArrayListExtra(Intent.EXTRA_STREAM, Uri::class.java)
+            } else {
+                intent.getParcelableArrayListExtra(Intent.EXTRA_STREAM)
+            } ?: emptyList()
+        else -> emptyList()
+    }
+}
+
+/**
+ * Hands shares from [ShareReceiverActivity] to the plugin, holding them
+ * until the plugin has loaded when the share cold-started the app.
+ */
+object ShareImports {
+    private const val DIR = "shared_imports"
+    // Copies are imported within moments; anything older was left behind.
+    private val MAX_AGE_MS = TimeUnit.DAYS.toMillis(1)
+
+    private val pending = mutableListOf<JSObject>()
+    private var listener: ((JSObject) -> Unit)? = null
+
+    /** Route shares to [onShare], starting with any that arrived before. */
+    fun listen(onShare: (JSObject) -> Unit) {
+        val queued = synchronized(this) {
+            listener = onShare
+            pending.toList().also { pending.clear() }
+        }
+        queued.forEach(onShare)
+    }
+
+    fun deliver(files: List<String>, urls: List<String>) {
+        val payload = JSObject().apply {
+            put("files", JSArray().apply { files.forEach { put(it) } })
+            put("urls", JSArray().apply { urls.forEach { put(it) } })
+        }
+        val onShare = synchronized(this) {
+            listener ?: run { pending.add(payload); null }
+        }
+        onShare?.invoke(payload)
+    }
+
+    /** Copy [uri] into app storage under its display name; null on failure. */
+    fun copyToStorage(context: Context, uri: Uri): String? = try {
+        // Each share gets its own folder, so same-named files don't collide.
+        val dir = File(context.filesDir, "$DIR/${System.currentTimeMillis()}-${uri.hashCode()}")
+        dir.mkdirs()
+        val dst = File(dir, displayName(context, uri))
+        context.contentResolver.openInputStream(uri)?.use { input ->
+            dst.outputStream().use { output -> input.copyTo(output) }
+        } ?: throw IllegalStateException("no input stream")
+        dst.absolutePath
+    } catch (e: Exception) {
+        Log.w("ShareImports", "Failed to copy shared $uri: ${e.message}")
+        null
+    }
+
+    /** Delete copies left from earlier shares. */
+    fun pruneCopies(context: Context) {
+        val cutoff = System.currentTimeMillis() - MAX_AGE_MS
+        File(context.filesDir, DIR).listFiles()
+            ?.filter { it.lastModified() < cutoff }
+            ?.forEach { it.deleteRecursively() }
+    }
+
+    private fun displayName(context: Context, uri: Uri): String {
+        val name = context.contentResolver
+            .query(uri, arrayOf(OpenableColumns.DISPLAY_NAME), null, null, null)
+            ?.use { cursor -> if (cursor.moveToFirst()) cursor.getString(0) else null }
+            ?: uri.lastPathSegment
+        // Keep the name (and its extension, which decides the format) but
+        // never a path.
+        return name?.substringAfterLast('/')?.takeIf { it.isNotBlank() } ?: "shared-book"
+    }
+}
@@ -41,6 +41,13 @@ const COMMANDS: &[&str] = &[
     "update_reading_widget",
     "capture_webview_region",
     "set_text_selection_suppressed",
+    "set_keep_awake",
+    "set_key_mapping",
+    "update_app_shortcuts",
+    "update_reading_intents",
+    "take_persistable_uri_permission",
+    "release_persistable_uri_permission",
+    "list_persisted_uri_permissions",
 ];
 
 fn main() {
@@ -1,8 +1,9 @@
-// Mirror of `src-tauri/gen/apple/ShareExtension/AppGroupBridge.swift`. The
-// two targets cannot share Swift source via the Xcode project layout we
-// use (xcodegen `sources:` blocks scope strictly to each target's
-// directory), so the schema is intentionally duplicated. Keep both files
-// byte-aligned when changing field names, keys, or encodings.
+// Mirror of `ios/ShareExtension/AppGroupBridge.swift`, the Share Extension
+// target bundled from this plugin. The two targets cannot share Swift
+// source via the Xcode project layout we use (xcodegen `sources:` blocks
+// scope strictly to each target's directory), so the schema is
+// intentionally duplicated. Keep both files byte-aligned when changing
+// field names, keys, or encodings.
 
 import Foundation
 
@@ -11,6 +12,8 @@ enum AppGroupBridge {
   static let groupsKey = "shareExtensionGroups"
   static let defaultGroupNameKey = "shareExtensionDefaultGroupName"
   static let pendingSavesKey = "shareExtensionPendingSaves"
+  static let pendingImportsKey = "shareExtensionPendingImports"
+  static let sharedImportsDirectoryName = "SharedImports"
 
   static var defaults: UserDefaults? {
     UserDefaults(suiteName: suiteName)
@@ -28,6 +31,14 @@ enum AppGroupBridge {
     let addedAt: String
   }
 
+  struct PendingImport: Codable, Equatable {
+    /// Relative to `sharedImportsDirectory()`: `<uuid>/<original file name>`.
+    let path: String
+    let groupId: String?
+    let groupName: String?
+    let addedAt: String
+  }
+
   static func readGroups() -> [LibraryGroup] {
     guard let data = defaults?.data(forKey: groupsKey) else { return [] }
     return (try? JSONDecoder().decode([LibraryGroup].self, from: data)) ?? []
@@ -66,6 +77,32 @@ enum AppGroupBridge {
     defaults?.removeObject(forKey: pendingSavesKey)
   }
 
+  /// Book files shared to the extension are copied here, inside the App
+  /// Group container, because the item provider's own copy is deleted as
+  /// soon as the extension completes.
+  static func sharedImportsDirectory() -> URL? {
+    FileManager.default
+      .containerURL(forSecurityApplicationGroupIdentifier: suiteName)?
+      .appendingPathComponent(sharedImportsDirectoryName, isDirectory: true)
+  }
+
+  static func readPendingImports() -> [PendingImport] {
+    guard let data = defaults?.data(forKey: pendingImportsKey) else { return [] }
+    return (try? JSONDecoder().decode([PendingImport].self, from: data)) ?? []
+  }
+
+  static func appendPendingImports(_ imports: [PendingImport]) {
+    var pending = readPendingImports()
+    pending.append(contentsOf: imports)
+    if let data = try? JSONEncoder().encode(pending) {
+      defaults?.set(data, forKey: pendingImportsKey)
+    }
+  }
+
+  static func clearPendingImports() {
+    defaults?.removeObject(forKey: pendingImportsKey)
+  }
+
   static func nowIso8601() -> String {
     let formatter = ISO8601DateFormatter()
     formatter.formatOptions = [.withInternetDateTime, .withFractionalSeconds]
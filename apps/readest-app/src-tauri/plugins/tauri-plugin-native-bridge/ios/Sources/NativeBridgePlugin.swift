@@ -2,6 +2,7 @@ import AVFoundation
 import AuthenticationServices
 import CoreText
 import MediaPlayer
+import Network
 import ObjectiveC
 import StoreKit
 import SwiftRs
@@ -44,6 +45,21 @@ class SetTextSelectionSuppressedRequestArgs: Decodable {
   let suppressed: Bool
 }
 
+class SetKeepAwakeRequestArgs: Decodable {
+  let enabled: Bool
+}
+
+class KeyMappingArgs: Decodable {
+  let key: String
+  let action: String
+  let longPress: String?
+  let repeatIntervalMs: Double?
+}
+
+class SetKeyMappingRequestArgs: Decodable {
+  let mappings: [KeyMappingArgs]
+}
+
 class SetSystemUIVisibilityRequestArgs: Decodable {
   let visible: Bool
   let darkMode: Bool
@@ -53,6 +69,7 @@ class InterceptKeysRequestArgs: Decodable {
   let backKey: Bool?
   let volumeKeys: Bool?
   let pageTurnerKeys: Bool?
+  let pageTurnerBindings: [String]?
   let learnMode: Bool?
 }
 
@@ -111,6 +128,8 @@ class VolumeKeyHandler: NSObject {
   private var volumeSlider: UISlider?
   private var ttsOwnsAudioSession = false
   private var ttsSessionObservers: [NSObjectProtocol] = []
+  /// Receives the key presses instead of `window.onNativeKeyDown` when set.
+  var onVolumeKey: ((String) -> Void)?
 
   override init() {
     super.init()
@@ -231,15 +250,17 @@ class VolumeKeyHandler: NSObject {
   ) {
     if keyPath == "outputVolume", let audioSession = self.audioSession, isIntercepting {
       let currentVolume = audioSession.outputVolume
-      if currentVolume > self.previousVolume {
+      let keyName: String? =
+        currentVolume > self.previousVolume
+        ? "VolumeUp" : currentVolume < self.previousVolume ? "VolumeDown" : nil
+      if let keyName = keyName {
         DispatchQueue.main.async { [weak self] in
-          self?.webView?.evaluateJavaScript(
-            "window.onNativeKeyDown('VolumeUp');", completionHandler: nil)
-        }
-      } else if currentVolume < self.previousVolume {
-        DispatchQueue.main.async { [weak self] in
-          self?.webView?.evaluateJavaScript(
-            "window.onNativeKeyDown('VolumeDown');", completionHandler: nil)
+          if let onVolumeKey = self?.onVolumeKey {
+            onVolumeKey(keyName)
+          } else {
+            self?.webView?.evaluateJavaScript(
+              "window.onNativeKeyDown('\(keyName)');", completionHandler: nil)
+          }
         }
       }
       self.previousVolume = currentVolume
@@ -248,33 +269,45 @@ class VolumeKeyHandler: NSObject {
   }
 }
 
+// Page-turner remotes that pair as media remotes. Keyboard-type remotes
+// (arrow and page keys) reach the webview as DOM key events, and
+// mouse-type ones as pointer events, so only media keys are handled here.
 class MediaKeyHandler {
   private weak var webView: WKWebView?
-  private var registered = false
+  private var registered: [(MPRemoteCommand, String)] = []
   private let commandCenter = MPRemoteCommandCenter.shared()
 
-  func start(webView: WKWebView) {
+  /// Take the media keys named in `keys`, or all of them when `keys` is nil.
+  /// Next and previous track are always taken, as before bindings were sent.
+  func start(webView: WKWebView, keys: Set<String>?) {
     self.webView = webView
-    if registered { return }
-    registered = true
-    commandCenter.nextTrackCommand.isEnabled = true
-    commandCenter.previousTrackCommand.isEnabled = true
-    commandCenter.nextTrackCommand.addTarget { [weak self] _ in
-      self?.forward("MediaNext")
-      return .success
-    }
-    commandCenter.previousTrackCommand.addTarget { [weak self] _ in
-      self?.forward("MediaPrevious")
-      return .success
+    stop()
+    let commands: [(MPRemoteCommand, String)] = [
+      (commandCenter.nextTrackCommand, "MediaNext"),
+      (commandCenter.previousTrackCommand, "MediaPrevious"),
+      (commandCenter.togglePlayPauseCommand, "MediaPlayPause"),
+      (commandCenter.skipForwardCommand, "MediaFastForward"),
+      (commandCenter.skipBackwardCommand, "MediaRewind"),
+    ]
+    for (command, name) in commands {
+      let always = name == "MediaNext" || name == "MediaPrevious"
+      guard always || keys?.contains(name) ?? true else { continue }
+      command.isEnabled = true
+      command.addTarget { [weak self] _ in
+        self?.forward(name)
+        return .success
+      }
+      registered.append((command, name))
     }
     logger.log("MediaKeyHandler: started")
   }
 
   func stop() {
-    if !registered { return }
-    registered = false
-    commandCenter.nextTrackCommand.removeTarget(nil)
-    commandCenter.previousTrackCommand.removeTarget(nil)
+    if registered.isEmpty { return }
+    for (command, _) in registered {
+      command.removeTarget(nil)
+    }
+    registered = []
     logger.log("MediaKeyHandler: stopped")
   }
 
@@ -518,6 +551,9 @@ class NativeBridgePlugin: Plugin {
   private var originalDelegate: UIApplicationDelegate?
   private var webViewLifecycleManager: WebViewLifecycleManager?
   private var traitChangeRegistered = false
+  static let bookShortcutType = "com.readest.book"
+  /// The loaded plugin, for App Intents that run outside any invoke.
+  private(set) static weak var current: NativeBridgePlugin?
 
   // Screen-brightness management. `UIScreen.main.brightness` is a *global*
   // device setting, not a per-window one: once the app writes to it, iOS
@@ -529,6 +565,31 @@ class NativeBridgePlugin: Plugin {
   private var appDesiredBrightness: CGFloat?
   private var systemBrightnessBeforeOverride: CGFloat?
 
+  // Volume keys mapped to reader actions (`set_key_mapping`). Volume KVO only
+  // reports steps, with no key-up, and iOS keeps stepping while a button is
+  // held, so steps of the same key closer together than `volumeHoldGap` make
+  // up one held press.
+  private static let volumeHoldGap: TimeInterval = 0.4
+  private static let defaultRepeatInterval: TimeInterval = 0.3
+  private var keyMappings: [String: KeyMappingArgs] = [:]
+  // Media keys the page turner has bindings for (`intercept_keys`).
+  private var pageTurnerBindings: [String]?
+  private var heldVolumeKey: HeldVolumeKey?
+
+  private final class HeldVolumeKey {
+    let name: String
+    var lastStepAt: Date
+    var lastSentAt: Date
+    var longPressed = false
+    var release: DispatchWorkItem?
+
+    init(name: String, at date: Date) {
+      self.name = name
+      self.lastStepAt = date
+      self.lastSentAt = date
+    }
+  }
+
   @objc public override func load(webview: WKWebView) {
     self.webView = webview
     logger.log("NativeBridgePlugin loaded")
@@ -537,6 +598,10 @@ class NativeBridgePlugin: Plugin {
     // covers Readest's annotation toolbar. See ContextMenuSuppressor.
     ContextMenuSuppressor.installIfNeeded()
 
+    // Start watching the network now so the first get_network_status call
+    // answers from a real path rather than the monitor's initial state.
+    _ = NativeBridgePlugin.pathMonitor
+
     // Register a WKScriptMessageHandler so JS can signal when its
     // share-extension hook has mounted. On `{type: 'ready'}` we run a
     // sync immediately, which is the cold-start path (app launched
@@ -578,6 +643,24 @@ class NativeBridgePlugin: Plugin {
       object: nil
     )
 
+    // Locking the device is the closest iOS comes to a screen-off event;
+    // the reading-statistics tracker stops counting on it.
+    NotificationCenter.default.addObserver(
+      self,
+      selector: #selector(screenDidLock),
+      name: UIApplication.protectedDataWillBecomeUnavailableNotification,
+      object: nil
+    )
+
+    NotificationCenter.default.addObserver(
+      self,
+      selector: #selector(screenDidUnlock),
+      name: UIApplication.protectedDataDidBecomeAvailableNotification,
+      object: nil
+    )
+
+    NativeBridgePlugin.current = self
+
     if let app = UIApplication.value(forKey: "sharedApplication") as? UIApplication {
       self.originalDelegate = app.delegate
       app.delegate = self
@@ -593,6 +676,14 @@ class NativeBridgePlugin: Plugin {
     FolderBookmarkStore.restoreAllPersisted()
   }
 
+  @objc func screenDidLock() {
+    trigger("screen-state", data: ["on": false])
+  }
+
+  @objc func screenDidUnlock() {
+    trigger("screen-state", data: ["on": true])
+  }
+
   @objc func appWillEnterForeground() {
     logger.log("NativeBridgePlugin: App will enter foreground")
     // Re-assert the app's brightness that was released on background (#4885).
@@ -603,7 +694,7 @@ class NativeBridgePlugin: Plugin {
   }
 
   @objc func appDidBecomeActive() {
-    if volumeKeyHandler != nil {
+    if interceptingVolumeKeys || !keyMappings.isEmpty {
       activateVolumeKeyInterception()
     }
     registerTraitChangeObserverIfNeeded()
@@ -638,6 +729,10 @@ class NativeBridgePlugin: Plugin {
   ///      queue only if JS confirmed receipt. If JS isn't ready yet,
   ///      leave the queue intact — the next activation (or the JS hook
   ///      on its own mount) will pick them up.
+  ///
+  ///   3. Pending imports (extension → host). Same handshake for book
+  ///      files, through `window.__readestOnShareExtensionImports`; see
+  ///      `deliverPendingImports`.
   private func syncShareExtensionState() {
     DispatchQueue.main.async { [weak self] in
       guard let self = self, let webView = self.webView else { return }
@@ -659,6 +754,7 @@ class NativeBridgePlugin: Plugin {
           AppGroupBridge.writeDefaultGroupName(defaultName)
         }
       }
+      self.deliverPendingImports(to: webView)
       // Push pending saves → JS, clear queue iff JS confirmed.
       let saves = AppGroupBridge.readPendingSaves()
       guard !saves.isEmpty else { return }
@@ -683,6 +779,55 @@ class NativeBridgePlugin: Plugin {
     }
   }
 
+  /// Move the book files the Share Extension queued out of the App Group
+  /// container into this app's sandbox, where the JS file APIs can read
+  /// them, then hand their paths to JS. A move that already happened on
+  /// an earlier, unacknowledged attempt is reused rather than repeated.
+  private func deliverPendingImports(to webView: WKWebView) {
+    let imports = AppGroupBridge.readPendingImports()
+    guard !imports.isEmpty, let sourceRoot = AppGroupBridge.sharedImportsDirectory() else {
+      return
+    }
+    let fileManager = FileManager.default
+    let targetRoot = fileManager.temporaryDirectory
+      .appendingPathComponent(AppGroupBridge.sharedImportsDirectoryName, isDirectory: true)
+    let payload: [[String: Any?]] = imports.compactMap { item in
+      let source = sourceRoot.appendingPathComponent(item.path)
+      let target = targetRoot.appendingPathComponent(item.path)
+      if !fileManager.fileExists(atPath: target.path) {
+        do {
+          try fileManager.createDirectory(
+            at: target.deletingLastPathComponent(), withIntermediateDirectories: true)
+          try fileManager.moveItem(at: source, to: target)
+          try? fileManager.removeItem(at: source.deletingLastPathComponent())
+        } catch {
+          logger.error("Failed to move shared import \(item.path): \(error)")
+          return nil
+        }
+      }
+      return [
+        "path": target.path,
+        "groupId": item.groupId,
+        "groupName": item.groupName,
+        "addedAt": item.addedAt,
+      ]
+    }
+    guard !payload.isEmpty else {
+      AppGroupBridge.clearPendingImports()
+      return
+    }
+    guard let json = try? JSONSerialization.data(withJSONObject: payload, options: []),
+      let jsonString = String(data: json, encoding: .utf8)
+    else { return }
+    let script =
+      "(window.__readestOnShareExtensionImports && window.__readestOnShareExtensionImports(\(jsonString))) === true"
+    webView.evaluateJavaScript(script) { result, _ in
+      if let acknowledged = result as? Bool, acknowledged {
+        AppGroupBridge.clearPendingImports()
+      }
+    }
+  }
+
   // Resolves the foreground window scene. Its trait collection reflects
   // the real system appearance and is unaffected by the per-window
   // `overrideUserInterfaceStyle` that `set_system_ui_visibility` applies.
@@ -725,6 +870,8 @@ class NativeBridgePlugin: Plugin {
     if let handler = volumeKeyHandler, handler.isIntercepting {
       handler.stopInterception()
     }
+    heldVolumeKey?.release?.cancel()
+    heldVolumeKey = nil
     // Hand screen brightness back to iOS so ambient auto-brightness resumes
     // while backgrounded; the override is re-applied on foreground (#4885).
     if appDesiredBrightness != nil, let original = systemBrightnessBeforeOverride {
@@ -809,7 +956,9 @@ class NativeBridgePlugin: Plugin {
         mediaKeyHandler = MediaKeyHandler()
       }
       if let webView = self.webView {
-        mediaKeyHandler?.start(webView: webView)
+        // Learn mode takes every media key so any of them can be bound.
+        let keys = mediaKeyState & 2 != 0 ? nil : pageTurnerBindings.map(Set.init)
+        mediaKeyHandler?.start(webView: webView, keys: keys)
       } else {
         logger.warning("Cannot start media key handler: webView is nil")
       }
@@ -854,6 +1003,109 @@ class NativeBridgePlugin: Plugin {
     invoke.resolve()
   }
 
+  // The idle timer only runs while the app is in the foreground, so the
+  // screen sleeps as usual once the app is left.
+  @objc public func set_keep_awake(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(SetKeepAwakeRequestArgs.self)
+    DispatchQueue.main.async {
+      UIApplication.shared.isIdleTimerDisabled = args.enabled
+    }
+    invoke.resolve()
+  }
+
+  // Map the volume keys to reader actions, sent as `mapped-key` events. The
+  // keys are handed back to the system in the background (see
+  // appDidEnterBackground) and taken again when the app becomes active.
+  @objc public func set_key_mapping(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(SetKeyMappingRequestArgs.self)
+    DispatchQueue.main.async {
+      self.finishHeldVolumeKey()
+      self.keyMappings = Dictionary(
+        args.mappings.map { ($0.key, $0) }, uniquingKeysWith: { _, last in last })
+      if self.keyMappings.isEmpty {
+        self.volumeKeyHandler?.onVolumeKey = nil
+        if !self.interceptingVolumeKeys {
+          self.volumeKeyHandler?.stopInterception()
+        }
+      } else {
+        self.activateVolumeKeyInterception()
+        self.volumeKeyHandler?.onVolumeKey = { [weak self] name in
+          self?.handleMappedVolumeKey(name)
+        }
+      }
+      invoke.resolve()
+    }
+  }
+
+  private func handleMappedVolumeKey(_ name: String) {
+    guard let mapping = keyMappings[name] else {
+      // Interception can't release a single key; unmapped presses go the old
+      // way if the reader asked for them.
+      if interceptingVolumeKeys {
+        webView?.evaluateJavaScript(
+          "window.onNativeKeyDown('\(name)');", completionHandler: nil)
+      }
+      return
+    }
+    let now = Date()
+    let longPress = mapping.longPress ?? "none"
+    if let held = heldVolumeKey, held.name == name,
+      now.timeIntervalSince(held.lastStepAt) < Self.volumeHoldGap
+    {
+      held.lastStepAt = now
+      switch longPress {
+      case "none":
+        break
+      case "repeat":
+        let interval =
+          mapping.repeatIntervalMs.map { $0 / 1000 } ?? Self.defaultRepeatInterval
+        if now.timeIntervalSince(held.lastSentAt) >= interval {
+          held.lastSentAt = now
+          emitMappedKey(name, action: mapping.action, longPress: true)
+        }
+      default:
+        if !held.longPressed {
+          held.longPressed = true
+          emitMappedKey(name, action: longPress, longPress: true)
+        }
+      }
+    } else {
+      finishHeldVolumeKey()
+      heldVolumeKey = HeldVolumeKey(name: name, at: now)
+      // With a long-press action the short press waits until the key is let
+      // go, so holding it doesn't also turn the page.
+      if longPress == "none" || longPress == "repeat" {
+        emitMappedKey(name, action: mapping.action, longPress: false)
+      }
+    }
+    scheduleVolumeKeyRelease()
+  }
+
+  private func scheduleVolumeKeyRelease() {
+    guard let held = heldVolumeKey else { return }
+    held.release?.cancel()
+    let release = DispatchWorkItem { [weak self] in
+      self?.finishHeldVolumeKey()
+    }
+    held.release = release
+    DispatchQueue.main.asyncAfter(deadline: .now() + Self.volumeHoldGap, execute: release)
+  }
+
+  private func finishHeldVolumeKey() {
+    guard let held = heldVolumeKey else { return }
+    heldVolumeKey = nil
+    held.release?.cancel()
+    guard let mapping = keyMappings[held.name] else { return }
+    let longPress = mapping.longPress ?? "none"
+    if longPress != "none" && longPress != "repeat" && !held.longPressed {
+      emitMappedKey(held.name, action: mapping.action, longPress: false)
+    }
+  }
+
+  private func emitMappedKey(_ name: String, action: String, longPress: Bool) {
+    trigger("mapped-key", data: ["key": name, "action": action, "longPress": longPress])
+  }
+
   @objc public func auth_with_safari(_ invoke: Invoke) throws {
     let args = try invoke.parseArgs(SafariAuthRequestArgs.self)
     let authUrl = URL(string: args.authUrl)!
@@ -933,6 +1185,7 @@ class NativeBridgePlugin: Plugin {
       let args = try invoke.parseArgs(InterceptKeysRequestArgs.self)
 
       if let volumeKeys = args.volumeKeys {
+        interceptingVolumeKeys = volumeKeys
         if volumeKeys {
           self.activateVolumeKeyInterception()
         } else {
@@ -948,7 +1201,9 @@ class NativeBridgePlugin: Plugin {
           // so iOS vacates the slot and AirPods / lock-screen play resumes
           // whatever app played before us. A single long-lived handler catches
           // the claim and leaves the TTS session untouched.
-          self.volumeKeyHandler?.stopInterception()
+          if self.keyMappings.isEmpty {
+            self.volumeKeyHandler?.stopInterception()
+          }
         }
       }
 
@@ -958,7 +1213,10 @@ class NativeBridgePlugin: Plugin {
       if let learnMode = args.learnMode {
         mediaKeyState = learnMode ? (mediaKeyState | 2) : (mediaKeyState & ~2)
       }
-      if args.pageTurnerKeys != nil || args.learnMode != nil {
+      if let bindings = args.pageTurnerBindings {
+        pageTurnerBindings = bindings
+      }
+      if args.pageTurnerKeys != nil || args.learnMode != nil || args.pageTurnerBindings != nil {
         DispatchQueue.main.async { [weak self] in
           self?.updateMediaKeyHandler()
         }
@@ -1605,6 +1863,36 @@ class NativeBridgePlugin: Plugin {
     }
   }
 
+  // NWPathMonitor only reports through its handler, so keep one running and
+  // answer from the latest path. `isExpensive` covers cellular and personal
+  // hotspots; `isConstrained` is Low Data Mode, which we treat as metered.
+  private static let pathMonitor: NWPathMonitor = {
+    let monitor = NWPathMonitor()
+    monitor.start(queue: DispatchQueue(label: "com.readest.network-status"))
+    return monitor
+  }()
+
+  @objc public func get_network_status(_ invoke: Invoke) {
+    let path = NativeBridgePlugin.pathMonitor.currentPath
+    let kind: String
+    if path.status != .satisfied {
+      kind = "none"
+    } else if path.usesInterfaceType(.wifi) {
+      kind = "wifi"
+    } else if path.usesInterfaceType(.wiredEthernet) {
+      kind = "ethernet"
+    } else if path.usesInterfaceType(.cellular) {
+      kind = "cellular"
+    } else {
+      kind = "other"
+    }
+    invoke.resolve([
+      "connected": path.status == .satisfied,
+      "metered": path.isExpensive || path.isConstrained,
+      "kind": kind,
+    ])
+  }
+
   // iOS devices have no e-ink panel; the "Refresh Page" page-turner action is
   // gated to e-ink Android in the UI, so this is only ever reached defensively.
   // Resolve as a soft no-op rather than rejecting.
@@ -1632,6 +1920,56 @@ class NativeBridgePlugin: Plugin {
     }
   }
 
+  /// Home-screen quick actions for the most recently read books. Quick
+  /// action icons can't show a cover, so each gets the book symbol; picking
+  /// one opens its `readest://book/{hash}` link (see `performActionFor`).
+  @objc public func update_app_shortcuts(_ invoke: Invoke) {
+    guard let args = try? invoke.parseArgs(UpdateAppShortcutsRequestArgs.self) else {
+      return invoke.reject("Failed to parse arguments")
+    }
+    DispatchQueue.main.async {
+      UIApplication.shared.shortcutItems = args.books.map { book in
+        UIApplicationShortcutItem(
+          type: NativeBridgePlugin.bookShortcutType,
+          localizedTitle: book.title.isEmpty ? book.hash : book.title,
+          localizedSubtitle: book.author.isEmpty ? nil : book.author,
+          icon: UIApplicationShortcutIcon(systemImageName: "book"),
+          userInfo: ["url": "readest://book/\(book.hash)" as NSString]
+        )
+      }
+      invoke.resolve()
+    }
+  }
+
+  /// What the Shortcuts / Siri intents in `ReadingIntents.swift` answer
+  /// from. Stored natively so "How many minutes did I read today?" can
+  /// answer without bringing the app forward.
+  @objc public func update_reading_intents(_ invoke: Invoke) {
+    guard let args = try? invoke.parseArgs(UpdateReadingIntentsRequestArgs.self) else {
+      return invoke.reject("Failed to parse arguments")
+    }
+    ReadingIntentsStore.write(
+      ReadingIntentsStore.State(
+        book: args.book.map { ReadingIntentsStore.Book(hash: $0.hash, title: $0.title) },
+        readingSecondsToday: args.readingSecondsToday,
+        updatedAt: Date()
+      ))
+    invoke.resolve()
+  }
+
+  /// Route an in-app `readest://` link the way one opened from outside the
+  /// app goes: through the original delegate to the deep-link plugin.
+  func openAppLink(_ url: URL) {
+    DispatchQueue.main.async {
+      let handled =
+        self.originalDelegate?.application?(UIApplication.shared, open: url, options: [:])
+        ?? false
+      if !handled {
+        UIApplication.shared.open(url)
+      }
+    }
+  }
+
   /// Snapshot a region of the webview for the mesh page-curl texture
   /// (#555). The rect is in CSS pixels of the JS viewport (== points of
   /// the WKWebView). Like Android, the snapshot is capped at 2x CSS
@@ -1901,6 +2239,22 @@ class ShowLookupPopoverArgs: Decodable {
   let word: String
 }
 
+struct AppShortcutBookArgs: Decodable {
+  let hash: String
+  let title: String
+  let author: String
+}
+struct UpdateAppShortcutsRequestArgs: Decodable {
+  let books: [AppShortcutBookArgs]
+}
+struct ReadingIntentsBookArgs: Decodable {
+  let hash: String
+  let title: String
+}
+struct UpdateReadingIntentsRequestArgs: Decodable {
+  let book: ReadingIntentsBookArgs?
+  let readingSecondsToday: Int
+}
 struct UpdateReadingWidgetBookArgs: Decodable {
   let hash: String
   let title: String
@@ -1999,6 +2353,27 @@ extension NativeBridgePlugin: UIApplicationDelegate {
       application, continue: continueUserActivity, restorationHandler: restorationHandler) ?? false
   }
 
+  // A recent-book quick action (`update_app_shortcuts`) is handed to the
+  // original delegate as an opened URL, so it reaches the webview through the
+  // deep-link plugin like any other `readest://book/{hash}` link.
+  public func application(
+    _ application: UIApplication, performActionFor shortcutItem: UIApplicationShortcutItem,
+    completionHandler: @escaping (Bool) -> Void
+  ) {
+    guard shortcutItem.type == NativeBridgePlugin.bookShortcutType,
+      let link = shortcutItem.userInfo?["url"] as? String, let url = URL(string: link)
+    else {
+      if self.originalDelegate?.application?(
+        application, performActionFor: shortcutItem, completionHandler: completionHandler) == nil
+      {
+        completionHandler(false)
+      }
+      return
+    }
+    completionHandler(
+      self.originalDelegate?.application?(application, open: url, options: [:]) ?? false)
+  }
+
   public func applicationDidBecomeActive(_ application: UIApplication) {
     self.originalDelegate?.applicationDidBecomeActive?(application)
   }
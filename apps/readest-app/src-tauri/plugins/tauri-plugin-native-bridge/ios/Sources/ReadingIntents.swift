@@ -0,0 +1,129 @@
+import AppIntents
+import Foundation
+import UIKit
+
+/// State the reading intents answer from, published by the webview through
+/// `update_reading_intents` whenever the library or today's reading time
+/// changes. Intents run in the app's own process, so standard defaults do.
+enum ReadingIntentsStore {
+  static let stateKey = "readingIntentsState"
+
+  struct Book: Codable {
+    let hash: String
+    let title: String
+  }
+
+  struct State: Codable {
+    /// The most recently read book, if any book has been opened.
+    let book: Book?
+    let readingSecondsToday: Int
+    let updatedAt: Date
+  }
+
+  static func read() -> State? {
+    guard let data = UserDefaults.standard.data(forKey: stateKey) else { return nil }
+    return try? JSONDecoder().decode(State.self, from: data)
+  }
+
+  static func write(_ state: State) {
+    guard let data = try? JSONEncoder().encode(state) else { return }
+    UserDefaults.standard.set(data, forKey: stateKey)
+  }
+
+  /// A report from an earlier day means nothing was read since midnight.
+  static func minutesReadToday(now: Date = Date()) -> Int {
+    guard let state = read(), Calendar.current.isDate(state.updatedAt, inSameDayAs: now) else {
+      return 0
+    }
+    return state.readingSecondsToday / 60
+  }
+}
+
+@available(iOS 16.0, *)
+enum ReadingIntentError: Error, CustomLocalizedStringResourceConvertible {
+  case noCurrentBook
+
+  var localizedStringResource: LocalizedStringResource {
+    switch self {
+    case .noCurrentBook:
+      return "Open a book in Readest first."
+    }
+  }
+}
+
+/// The book intents open the current book through the same
+/// `readest://book/{hash}` link as the home-screen quick actions, so the
+/// reader resumes at the saved position; `autoplay=tts` starts read-aloud.
+@available(iOS 16.0, *)
+private func openCurrentBook(autoplay: Bool) throws {
+  guard let book = ReadingIntentsStore.read()?.book else {
+    throw ReadingIntentError.noCurrentBook
+  }
+  let link = "readest://book/\(book.hash)" + (autoplay ? "?autoplay=tts" : "")
+  guard let url = URL(string: link) else { throw ReadingIntentError.noCurrentBook }
+  if let plugin = NativeBridgePlugin.current {
+    plugin.openAppLink(url)
+  } else {
+    DispatchQueue.main.async { UIApplication.shared.open(url) }
+  }
+}
+
+@available(iOS 16.0, *)
+struct OpenCurrentBookIntent: AppIntent {
+  static var title: LocalizedStringResource = "Open Current Book"
+  static var description = IntentDescription("Opens the book you read last where you left off.")
+  static var openAppWhenRun = true
+
+  func perform() async throws -> some IntentResult {
+    try openCurrentBook(autoplay: false)
+    return .result()
+  }
+}
+
+@available(iOS 16.0, *)
+struct StartReadAloudIntent: AppIntent {
+  static var title: LocalizedStringResource = "Start Read Aloud"
+  static var description = IntentDescription(
+    "Opens the book you read last and starts reading it aloud.")
+  static var openAppWhenRun = true
+
+  func perform() async throws -> some IntentResult {
+    try openCurrentBook(autoplay: true)
+    return .result()
+  }
+}
+
+@available(iOS 16.0, *)
+struct ReadingTimeTodayIntent: AppIntent {
+  static var title: LocalizedStringResource = "Reading Time Today"
+  static var description = IntentDescription("Tells how many minutes you read today.")
+
+  func perform() async throws -> some IntentResult & ReturnsValue<Int> & ProvidesDialog {
+    let minutes = ReadingIntentsStore.minutesReadToday()
+    return .result(value: minutes, dialog: "You read \(minutes) minutes today.")
+  }
+}
+
+@available(iOS 16.0, *)
+struct ReadestAppShortcuts: AppShortcutsProvider {
+  static var appShortcuts: [AppShortcut] {
+    AppShortcut(
+      intent: OpenCurrentBookIntent(),
+      phrases: [
+        "Open my book in \(.applicationName)",
+        "Continue reading in \(.applicationName)",
+      ])
+    AppShortcut(
+      intent: StartReadAloudIntent(),
+      phrases: [
+        "Read aloud in \(.applicationName)",
+        "Start read aloud in \(.applicationName)",
+      ])
+    AppShortcut(
+      intent: ReadingTimeTodayIntent(),
+      phrases: [
+        "How many minutes did I read today in \(.applicationName)",
+        "My reading time today in \(.applicationName)",
+      ])
+  }
+}
@@ -54,6 +54,22 @@ pub(crate) async fn set_text_selection_suppressed<R: Runtime>(
     app.native_bridge().set_text_selection_suppressed(payload)
 }
 
+#[command]
+pub(crate) async fn set_keep_awake<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetKeepAwakeRequest,
+) -> Result<()> {
+    app.native_bridge().set_keep_awake(payload)
+}
+
+#[command]
+pub(crate) async fn set_key_mapping<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetKeyMappingRequest,
+) -> Result<()> {
+    app.native_bridge().set_key_mapping(payload)
+}
+
 #[command]
 pub(crate) async fn install_package<R: Runtime>(
     app: AppHandle<R>,
@@ -174,6 +190,30 @@ pub(crate) async fn get_external_sdcard_path<R: Runtime>(
     app.native_bridge().get_external_sdcard_path()
 }
 
+#[command]
+pub(crate) async fn take_persistable_uri_permission<R: Runtime>(
+    app: AppHandle<R>,
+    payload: TakePersistableUriPermissionRequest,
+) -> Result<PersistableUriPermissionResponse> {
+    app.native_bridge().take_persistable_uri_permission(payload)
+}
+
+#[command]
+pub(crate) async fn release_persistable_uri_permission<R: Runtime>(
+    app: AppHandle<R>,
+    payload: ReleasePersistableUriPermissionRequest,
+) -> Result<PersistableUriPermissionResponse> {
+    app.native_bridge()
+        .release_persistable_uri_permission(payload)
+}
+
+#[command]
+pub(crate) async fn list_persisted_uri_permissions<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<ListPersistedUriPermissionsResponse> {
+    app.native_bridge().list_persisted_uri_permissions()
+}
+
 #[command]
 pub(crate) async fn open_external_url<R: Runtime>(
     app: AppHandle<R>,
@@ -297,6 +337,22 @@ pub(crate) async fn update_reading_widget<R: Runtime>(
     app.native_bridge().update_reading_widget(payload)
 }
 
+#[command]
+pub(crate) async fn update_app_shortcuts<R: Runtime>(
+    app: AppHandle<R>,
+    payload: UpdateAppShortcutsRequest,
+) -> Result<()> {
+    app.native_bridge().update_app_shortcuts(payload)
+}
+
+#[command]
+pub(crate) async fn update_reading_intents<R: Runtime>(
+    app: AppHandle<R>,
+    payload: UpdateReadingIntentsRequest,
+) -> Result<()> {
+    app.native_bridge().update_reading_intents(payload)
+}
+
 /// Snapshot a region of the calling webview and return it as binary PNG
 /// (`tauri::ipc::Response`, no JSON encoding) for the mesh page-curl
 /// texture (#555). Platforms without a capture implementation reject,
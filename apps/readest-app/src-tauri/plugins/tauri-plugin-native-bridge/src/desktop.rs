@@ -78,6 +78,14 @@ impl<R: Runtime> NativeBridge<R> {
         Err(crate::Error::UnsupportedPlatformError)
     }
 
+    pub fn set_keep_awake(&self, _payload: SetKeepAwakeRequest) -> crate::Result<()> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
+    pub fn set_key_mapping(&self, _payload: SetKeyMappingRequest) -> crate::Result<()> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
     pub fn install_package(
         &self,
         _payload: InstallPackageRequest,
@@ -173,6 +181,26 @@ impl<R: Runtime> NativeBridge<R> {
         Err(crate::Error::UnsupportedPlatformError)
     }
 
+    pub fn take_persistable_uri_permission(
+        &self,
+        _payload: TakePersistableUriPermissionRequest,
+    ) -> crate::Result<PersistableUriPermissionResponse> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
+    pub fn release_persistable_uri_permission(
+        &self,
+        _payload: ReleasePersistableUriPermissionRequest,
+    ) -> crate::Result<PersistableUriPermissionResponse> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
+    pub fn list_persisted_uri_permissions(
+        &self,
+    ) -> crate::Result<ListPersistedUriPermissionsResponse> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
     pub fn open_external_url(
         &self,
         _payload: OpenExternalUrlRequest,
@@ -353,6 +381,16 @@ impl<R: Runtime> NativeBridge<R> {
         }
     }
 
+    /// Desktop OSes don't expose a portable metered flag, so report an
+    /// unmetered connection and let transfer policies pass.
+    pub fn get_network_status(&self) -> crate::Result<GetNetworkStatusResponse> {
+        Ok(GetNetworkStatusResponse {
+            connected: true,
+            metered: false,
+            kind: "other".to_string(),
+        })
+    }
+
     /// E-ink panels exist only on the mobile (Android) side. Desktop has no
     /// e-ink controller, so this is unsupported here.
     pub fn refresh_eink_screen(&self) -> crate::Result<RefreshEinkScreenResponse> {
@@ -364,6 +402,32 @@ impl<R: Runtime> NativeBridge<R> {
         Ok(())
     }
 
+    pub fn update_continue_reading_widget(
+        &self,
+        _payload: UpdateContinueReadingWidgetRequest,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
+    pub fn update_app_shortcuts(&self, _payload: UpdateAppShortcutsRequest) -> crate::Result<()> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
+    pub fn update_reading_intents(
+        &self,
+        _payload: UpdateReadingIntentsRequest,
+    ) -> crate::Result<()> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
+
+    pub fn update_library_provider(
+        &self,
+        _payload: UpdateLibraryProviderRequest,
+    ) -> crate::Result<()> {
+        // The content provider is Android-only; desktop is a no-op.
+        Ok(())
+    }
+
     /// Snapshot a region of `window`'s webview as PNG bytes for the mesh
     /// page-curl texture (#555). macOS only so far; Windows
     /// (`ICoreWebView2::CapturePreview`) and Linux
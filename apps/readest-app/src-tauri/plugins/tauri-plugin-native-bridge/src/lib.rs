@@ -92,6 +92,13 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             commands::update_reading_widget,
             commands::capture_webview_region,
             commands::set_text_selection_suppressed,
+            commands::set_keep_awake,
+            commands::set_key_mapping,
+            commands::update_app_shortcuts,
+            commands::update_reading_intents,
+            commands::take_persistable_uri_permission,
+            commands::release_persistable_uri_permission,
+            commands::list_persisted_uri_permissions,
         ])
         .setup(|app, api| {
             #[cfg(mobile)]
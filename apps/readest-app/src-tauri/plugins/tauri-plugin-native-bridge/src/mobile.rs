@@ -78,6 +78,22 @@ impl<R: Runtime> NativeBridge<R> {
     }
 }
 
+impl<R: Runtime> NativeBridge<R> {
+    pub fn set_keep_awake(&self, payload: SetKeepAwakeRequest) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("set_keep_awake", payload)
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeBridge<R> {
+    pub fn set_key_mapping(&self, payload: SetKeyMappingRequest) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("set_key_mapping", payload)
+            .map_err(Into::into)
+    }
+}
+
 impl<R: Runtime> NativeBridge<R> {
     pub fn install_package(
         &self,
@@ -225,6 +241,32 @@ impl<R: Runtime> NativeBridge<R> {
             .run_mobile_plugin("get_external_sdcard_path", ())
             .map_err(Into::into)
     }
+
+    pub fn take_persistable_uri_permission(
+        &self,
+        payload: TakePersistableUriPermissionRequest,
+    ) -> crate::Result<PersistableUriPermissionResponse> {
+        self.0
+            .run_mobile_plugin("take_persistable_uri_permission", payload)
+            .map_err(Into::into)
+    }
+
+    pub fn release_persistable_uri_permission(
+        &self,
+        payload: ReleasePersistableUriPermissionRequest,
+    ) -> crate::Result<PersistableUriPermissionResponse> {
+        self.0
+            .run_mobile_plugin("release_persistable_uri_permission", payload)
+            .map_err(Into::into)
+    }
+
+    pub fn list_persisted_uri_permissions(
+        &self,
+    ) -> crate::Result<ListPersistedUriPermissionsResponse> {
+        self.0
+            .run_mobile_plugin("list_persisted_uri_permissions", ())
+            .map_err(Into::into)
+    }
 }
 
 impl<R: Runtime> NativeBridge<R> {
@@ -343,6 +385,14 @@ impl<R: Runtime> NativeBridge<R> {
     }
 }
 
+impl<R: Runtime> NativeBridge<R> {
+    pub fn get_network_status(&self) -> crate::Result<GetNetworkStatusResponse> {
+        self.0
+            .run_mobile_plugin("get_network_status", ())
+            .map_err(Into::into)
+    }
+}
+
 impl<R: Runtime> NativeBridge<R> {
     pub fn refresh_eink_screen(&self) -> crate::Result<RefreshEinkScreenResponse> {
         self.0
@@ -371,6 +421,39 @@ impl<R: Runtime> NativeBridge<R> {
             .run_mobile_plugin("update_reading_widget", payload)
             .map_err(Into::into)
     }
+
+    pub fn update_continue_reading_widget(
+        &self,
+        payload: UpdateContinueReadingWidgetRequest,
+    ) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("update_continue_reading_widget", payload)
+            .map_err(Into::into)
+    }
+
+    pub fn update_app_shortcuts(&self, payload: UpdateAppShortcutsRequest) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("update_app_shortcuts", payload)
+            .map_err(Into::into)
+    }
+
+    pub fn update_reading_intents(
+        &self,
+        payload: UpdateReadingIntentsRequest,
+    ) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("update_reading_intents", payload)
+            .map_err(Into::into)
+    }
+
+    pub fn update_library_provider(
+        &self,
+        payload: UpdateLibraryProviderRequest,
+    ) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("update_library_provider", payload)
+            .map_err(Into::into)
+    }
 }
 
 impl<R: Runtime> NativeBridge<R> {
@@ -60,6 +60,33 @@ pub struct SetTextSelectionSuppressedRequest {
     pub suppressed: bool,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetKeepAwakeRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyMapping {
+    /// `VolumeUp` or `VolumeDown`.
+    pub key: String,
+    /// Sent in a `mapped-key` event when the key is pressed.
+    pub action: String,
+    /// What holding the key does: `repeat` sends `action` again every
+    /// `repeat_interval_ms`, `none` ignores the hold, and any other value is
+    /// sent once as the action of a long press.
+    pub long_press: Option<String>,
+    pub repeat_interval_ms: Option<u64>,
+}
+
+/// Replaces the key mappings; an empty list hands the keys back to the system.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetKeyMappingRequest {
+    pub mappings: Vec<KeyMapping>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InstallPackageRequest {
@@ -106,6 +133,12 @@ pub struct GetSysFontsListResponse {
 pub struct InterceptKeysRequest {
     pub volume_keys: Option<bool>,
     pub back_key: Option<bool>,
+    /// Take the page-turner keys from the system and forward them.
+    pub page_turner_keys: Option<bool>,
+    /// Normalized names of the keys the page turner has bindings for.
+    pub page_turner_bindings: Option<Vec<String>>,
+    /// Forward every key so the settings can capture a binding.
+    pub learn_mode: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -230,6 +263,50 @@ pub struct GetExternalSDCardPathResponse {
     pub error: Option<String>,
 }
 
+/// Keep access to a folder picked through the Storage Access Framework
+/// across reboots. `uri` is the tree URI the picker returned.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakePersistableUriPermissionRequest {
+    pub uri: String,
+    #[serde(default)]
+    pub write: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleasePersistableUriPermissionRequest {
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistableUriPermissionResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedUriPermission {
+    pub uri: String,
+    /// Filesystem path of the tree, when it is on local storage.
+    pub path: Option<String>,
+    pub read: bool,
+    pub write: bool,
+    /// Milliseconds since the epoch.
+    pub persisted_time: i64,
+    /// Whether the folder still resolves; `false` once it was deleted or
+    /// its storage removed.
+    pub accessible: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPersistedUriPermissionsResponse {
+    pub permissions: Vec<PersistedUriPermission>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestManageStoragePermissionResponse {
@@ -402,6 +479,18 @@ pub struct GetSecureItemResponse {
     pub error: Option<String>,
 }
 
+/// The active network as the OS reports it. `metered` follows the platform's
+/// own notion (cellular, hotspots, or a Wi-Fi the user marked as metered),
+/// which is what "Wi-Fi only" transfer policies should honor.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetNetworkStatusResponse {
+    pub connected: bool,
+    pub metered: bool,
+    /// `wifi`, `cellular`, `ethernet`, `other`, or `none`.
+    pub kind: String,
+}
+
 /// Result of a deep e-ink full screen refresh. `success: false` means no
 /// known e-ink controller responded on this device (e.g. a non-e-ink
 /// Android phone) — not a hard error.
@@ -439,6 +528,83 @@ pub struct UpdateReadingWidgetRequest {
     pub tts: Option<ReadingWidgetTts>,
 }
 
+/// The book on the "Continue reading" widget; `None` shows `empty_title`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateContinueReadingWidgetRequest {
+    pub book: Option<ReadingWidgetBook>,
+    pub empty_title: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppShortcutBook {
+    pub hash: String,
+    pub title: String,
+    pub author: String,
+    /// Cover image for the shortcut's icon; the app icon when it is missing.
+    pub cover_path: String,
+}
+
+/// Replaces the launcher's long-press shortcuts with these books, most
+/// recent first. Each opens `readest://book/{hash}`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAppShortcutsRequest {
+    pub books: Vec<AppShortcutBook>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingIntentsBook {
+    pub hash: String,
+    pub title: String,
+}
+
+/// What the iOS App Intents answer from without waking the webview: the
+/// book "Open current book" and "Start read-aloud" act on, and the time
+/// read today for "How many minutes did I read today?".
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReadingIntentsRequest {
+    pub book: Option<ReadingIntentsBook>,
+    /// Seconds read since local midnight, across all books.
+    pub reading_seconds_today: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryProviderBook {
+    pub hash: String,
+    pub title: String,
+    pub author: String,
+    pub format: String,
+    /// Percent read, 0 to 100; `None` for a book never opened.
+    pub progress: Option<u8>,
+    /// Milliseconds since the epoch.
+    pub updated_at: i64,
+    /// Served as the book's cover URI; may not exist.
+    pub cover_path: String,
+    /// The book file the documents provider serves; `None` while the book
+    /// is only in the cloud.
+    #[serde(default)]
+    pub file_path: Option<String>,
+}
+
+/// The library other Android apps read through the content and documents
+/// providers, most recently updated first. Replaces the previous copy.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateLibraryProviderRequest {
+    pub books: Vec<LibraryProviderBook>,
+    /// Exported annotations, listed by the documents provider.
+    pub annotations_dir: String,
+    /// The user let other apps read the library; until then the content
+    /// provider answers nothing. The documents provider is unaffected.
+    #[serde(default)]
+    pub share_with_apps: bool,
+}
+
 /// Region of the webview to snapshot for the mesh page-curl (#555),
 /// in CSS pixels of the webview viewport (origin top-left). The native
 /// side applies the screen scale factor.
@@ -0,0 +1,38 @@
+use tauri_plugin_native_bridge::{LibraryProviderBook, UpdateLibraryProviderRequest};
+
+#[test]
+fn serializes_camel_case_payload() {
+    let req = UpdateLibraryProviderRequest {
+        books: vec![LibraryProviderBook {
+            hash: "h1".into(),
+            title: "T".into(),
+            author: "A".into(),
+            format: "EPUB".into(),
+            progress: None,
+            updated_at: 1_700_000_000_000,
+            cover_path: "/x/h1/cover.png".into(),
+            file_path: Some("/x/h1/T.epub".into()),
+        }],
+        annotations_dir: "/x/exports/annotations".into(),
+        share_with_apps: true,
+    };
+    let json = serde_json::to_value(&req).unwrap();
+    let book = &json["books"][0];
+    assert_eq!(book["updatedAt"], 1_700_000_000_000i64);
+    assert_eq!(book["coverPath"], "/x/h1/cover.png");
+    assert!(book["progress"].is_null());
+    assert_eq!(book["filePath"], "/x/h1/T.epub");
+    assert_eq!(json["annotationsDir"], "/x/exports/annotations");
+    assert_eq!(json["shareWithApps"], true);
+}
+
+#[test]
+fn round_trips_progress() {
+    let json = r#"{"books": [{"hash":"h1","title":"T","author":"","format":"PDF",
+      "progress":42,"updatedAt":1,"coverPath":""}], "annotationsDir": ""}"#;
+    let req: UpdateLibraryProviderRequest = serde_json::from_str(json).unwrap();
+    assert_eq!(req.books[0].progress, Some(42));
+    assert_eq!(req.books[0].format, "PDF");
+    assert_eq!(req.books[0].file_path, None);
+    assert!(!req.share_with_apps);
+}
@@ -1,4 +1,4 @@
-use tauri_plugin_native_bridge::UpdateReadingWidgetRequest;
+use tauri_plugin_native_bridge::{UpdateContinueReadingWidgetRequest, UpdateReadingWidgetRequest};
 
 #[test]
 fn deserializes_camel_case_payload() {
@@ -39,3 +39,18 @@ fn tts_is_none_when_absent() {
     let req: UpdateReadingWidgetRequest = serde_json::from_str(json).unwrap();
     assert!(req.tts.is_none());
 }
+
+#[test]
+fn continue_reading_book_is_optional() {
+    let json = r#"{
+      "book": {"hash":"h1","title":"T","author":"A","percent":5,"coverPath":"/x/h1/cover.png"},
+      "emptyTitle": "E"
+    }"#;
+    let req: UpdateContinueReadingWidgetRequest = serde_json::from_str(json).unwrap();
+    assert_eq!(req.book.expect("book should be Some").percent, 5);
+
+    let json = r#"{"book": null, "emptyTitle": "E"}"#;
+    let req: UpdateContinueReadingWidgetRequest = serde_json::from_str(json).unwrap();
+    assert!(req.book.is_none());
+    assert_eq!(req.empty_title, "E");
+}
@@ -5,6 +5,7 @@ import android.app.Notification
 import android.app.NotificationChannel
 import android.app.NotificationManager
 import android.app.PendingIntent
+import android.content.ComponentName
 import android.content.Context
 import android.content.Intent
 import android.content.pm.ServiceInfo
@@ -13,6 +14,7 @@ import android.os.Build
 import android.os.Bundle
 import android.os.Handler
 import android.os.Looper
+import android.service.quicksettings.TileService
 import android.util.Log
 import android.graphics.Bitmap
 import android.content.BroadcastReceiver
@@ -41,7 +43,6 @@ import java.io.FileOutputStream
 class MediaPlaybackService : MediaBrowserServiceCompat() {
     private var mediaSession: MediaSessionCompat? = null
     private lateinit var player: ExoPlayer
-    private lateinit var stateBuilder: PlaybackStateCompat.Builder
     private lateinit var audioManager: AudioManager
 
     // True only between session activation (TTS playback started) and
@@ -53,6 +54,8 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
     // Resume after a TRANSIENT focus loss only if the loss is what paused us
     // (nav prompt, call); a user pause before the loss must stay a pause.
     private var resumeOnFocusGain = false
+    // An audio-interruption "began" went out, so GAIN owes it an "ended".
+    private var interruptionReported = false
 
     // The real TTS audio renders in the WebView (or TextToSpeech), so pausing
     // the local keep-alive player alone would keep speech talking over the
@@ -65,8 +68,13 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         Log.i("MediaPlaybackService", "Audio focus changed: $focusChange, playing=${player.isPlaying}")
         when (focusChange) {
             AudioManager.AUDIOFOCUS_GAIN -> {
-                if (resumeOnFocusGain) {
-                    resumeOnFocusGain = false
+                val resume = resumeOnFocusGain
+                resumeOnFocusGain = false
+                if (interruptionReported) {
+                    interruptionReported = false
+                    reportInterruption("ended", shouldResume = resume)
+                }
+                if (resume) {
                     player.play()
                     pluginEventTrigger?.invoke("media-session-play", JSObject())
                     updatePlaybackState()
@@ -75,21 +83,33 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
             // Spoken audio pauses for transient loss instead of ducking or
             // talking over it (speech ducked under speech is unintelligible);
             // setWillPauseWhenDucked routes CAN_DUCK here rather than letting
-            // the system auto-duck.
+            // the system auto-duck. In duck mode the system ducks us from O
+            // on; CAN_DUCK only arrives here before that, when speech just
+            // carries on under the (short) prompt.
             AudioManager.AUDIOFOCUS_LOSS_TRANSIENT,
             AudioManager.AUDIOFOCUS_LOSS_TRANSIENT_CAN_DUCK -> {
-                resumeOnFocusGain = player.isPlaying
+                val duck = focusChange == AudioManager.AUDIOFOCUS_LOSS_TRANSIENT_CAN_DUCK &&
+                    interruptionMode == INTERRUPTION_DUCK
                 if (player.isPlaying) {
-                    player.pause()
-                    pluginEventTrigger?.invoke("media-session-pause", JSObject())
-                    updatePlaybackState()
+                    interruptionReported = true
+                    reportInterruption("began", ducked = duck)
+                }
+                if (!duck) {
+                    resumeOnFocusGain = player.isPlaying
+                    if (player.isPlaying) {
+                        player.pause()
+                        pluginEventTrigger?.invoke("media-session-pause", JSObject())
+                        updatePlaybackState()
+                    }
                 }
             }
             // Permanent loss (another media app took over): pause and stay
             // paused; the system never sends a GAIN after this.
             AudioManager.AUDIOFOCUS_LOSS -> {
                 resumeOnFocusGain = false
+                interruptionReported = false
                 if (player.isPlaying) {
+                    reportInterruption("began", permanent = true)
                     player.pause()
                     pluginEventTrigger?.invoke("media-session-pause", JSObject())
                     updatePlaybackState()
@@ -98,6 +118,22 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         }
     }
 
+    // Sent ahead of the media-session-pause/-play it explains, so the reader
+    // knows the pause isn't the user's and can hold its place through it.
+    private fun reportInterruption(
+        phase: String,
+        ducked: Boolean = false,
+        permanent: Boolean = false,
+        shouldResume: Boolean = false,
+    ) {
+        pluginEventTrigger?.invoke("audio-interruption", JSObject().apply {
+            put("phase", phase)
+            put("ducked", ducked)
+            put("permanent", permanent)
+            put("shouldResume", shouldResume)
+        })
+    }
+
     // Headphones unplugged / Bluetooth dropped: pause, never auto-resume —
     // otherwise spoken audio blasts from the phone speaker.
     private var noisyReceiverRegistered = false
@@ -127,7 +163,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
                         .setContentType(AudioAttributes.CONTENT_TYPE_SPEECH)
                         .build()
                 )
-                .setWillPauseWhenDucked(true)
+                .setWillPauseWhenDucked(interruptionMode != INTERRUPTION_DUCK)
                 .setOnAudioFocusChangeListener(afChangeListener)
                 .build()
             focusRequest = request
@@ -144,6 +180,12 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         }
     }
 
+    private fun refreshFocus() {
+        if (!sessionActive || focusRequest == null) return
+        abandonFocus()
+        requestFocus()
+    }
+
     private fun abandonFocus() {
         if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
             focusRequest?.let { audioManager.abandonAudioFocusRequest(it) }
@@ -161,6 +203,11 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         private const val CURRENT_READING_MEDIA_ID = "readest_current_reading"
         private const val RESUME_MEDIA_ID = "readest_resume_last_book"
         const val ACTION_ACTIVATE_SESSION = "ACTIVATE_SESSION"
+        const val INTERRUPTION_PAUSE = "pause"
+        const val INTERRUPTION_DUCK = "duck"
+        // Custom session actions, also sent by the notification's buttons
+        const val ACTION_NEXT_CHAPTER = "com.readest.native_tts.NEXT_CHAPTER"
+        const val ACTION_PREVIOUS_CHAPTER = "com.readest.native_tts.PREVIOUS_CHAPTER"
 
         private const val PREFS_LAST_BOOK = "media_last_book"
         private const val KEY_HASH = "hash"
@@ -196,6 +243,14 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         var currentPositionMs: Long = 0L
         @Volatile
         var currentDurationMs: Long = 0L
+        // Whether the scrubber can be dragged (a timeline to seek in), and
+        // the chapter being read, which decides the chapter skip actions.
+        @Volatile
+        var currentSeekable: Boolean = true
+        @Volatile
+        var currentChapterIndex: Int = -1
+        @Volatile
+        var currentChapterCount: Int = 0
 
         // Last book read aloud, persisted across process death so the Android
         // Auto browse tree can offer a "Resume last book" entry when opened cold
@@ -218,7 +273,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
                 .apply()
         }
 
-        private fun loadLastBook(context: Context) {
+        fun loadLastBook(context: Context) {
             val prefs = context.getSharedPreferences(PREFS_LAST_BOOK, Context.MODE_PRIVATE)
             lastBookHash = prefs.getString(KEY_HASH, null)
             lastBookTitle = prefs.getString(KEY_TITLE, null)
@@ -228,6 +283,37 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         @Volatile
         private var instance: MediaPlaybackService? = null
 
+        // Read by the Quick Settings tile, on the main thread.
+        val isSessionActive: Boolean
+            get() = instance?.sessionActive == true
+        val isPlaying: Boolean
+            get() = instance?.let { it.sessionActive && it.player.isPlaying } == true
+
+        // The Quick Settings tile pauses and resumes through the session's
+        // transport controls, so it takes the same path as the lock-screen
+        // button. Returns false when there is no session to control.
+        fun togglePlayPause(): Boolean {
+            val service = instance ?: return false
+            if (!service.sessionActive) return false
+            val controls = service.mediaSession?.controller?.transportControls ?: return false
+            if (service.player.isPlaying) controls.pause() else controls.play()
+            return true
+        }
+
+        // What a navigation prompt and the like do to speech: pause it, or
+        // let the system duck it.
+        @Volatile
+        var interruptionMode: String = INTERRUPTION_PAUSE
+            private set
+
+        // Focus held now was requested under the old mode; ask again.
+        fun setInterruptionMode(mode: String) {
+            if (mode == interruptionMode) return
+            interruptionMode = mode
+            val service = instance ?: return
+            Handler(Looper.getMainLooper()).post { service.refreshFocus() }
+        }
+
         // Deactivate via an in-process call instead of stopService: while a
         // media browser client (Android Auto) keeps the service bound,
         // stopService neither runs onDestroy nor clears the foreground
@@ -258,9 +344,19 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         // position/duration are null when the update only reports a play/pause
         // flip (that payload omits them); keep the last known values so the
         // scrubber does not snap back to 0 on pause.
-        fun pushPlaybackState(playing: Boolean, position: Long?, duration: Long?) {
+        fun pushPlaybackState(
+            playing: Boolean,
+            position: Long?,
+            duration: Long?,
+            seekable: Boolean? = null,
+            chapterIndex: Int? = null,
+            chapterCount: Int? = null,
+        ) {
             if (position != null) currentPositionMs = position
             if (duration != null) currentDurationMs = duration
+            if (seekable != null) currentSeekable = seekable
+            if (chapterIndex != null) currentChapterIndex = chapterIndex
+            if (chapterCount != null) currentChapterCount = chapterCount
             val service = instance ?: return
             Handler(Looper.getMainLooper()).post { service.applyPlaybackState(playing) }
         }
@@ -277,18 +373,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         player = ExoPlayer.Builder(this).build()
 
         mediaSession = MediaSessionCompat(baseContext, "ReadestMediaSession").apply {
-            stateBuilder = PlaybackStateCompat.Builder().setActions(
-                PlaybackStateCompat.ACTION_PLAY or
-                PlaybackStateCompat.ACTION_PLAY_PAUSE or
-                PlaybackStateCompat.ACTION_PAUSE or
-                PlaybackStateCompat.ACTION_STOP or
-                PlaybackStateCompat.ACTION_SKIP_TO_NEXT or
-                PlaybackStateCompat.ACTION_SKIP_TO_PREVIOUS or
-                PlaybackStateCompat.ACTION_SEEK_TO or
-                PlaybackStateCompat.ACTION_PLAY_FROM_MEDIA_ID or
-                PlaybackStateCompat.ACTION_PLAY_FROM_SEARCH
-            )
-            setPlaybackState(stateBuilder.build())
+            setPlaybackState(playbackState(PlaybackStateCompat.STATE_NONE, 0L))
             setCallback(SessionCallback())
             setSessionToken(sessionToken)
         }
@@ -330,6 +415,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
 
             mediaSession?.isActive = true
             notifyChildrenChanged(MEDIA_ROOT_ID)
+            refreshReadAloudTile()
         }
         // Always post the notification: activation arrives through
         // startForegroundService, which requires startForeground promptly.
@@ -351,9 +437,10 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
 
         mediaSession?.isActive = false
         mediaSession?.setPlaybackState(
-            stateBuilder.setState(PlaybackStateCompat.STATE_STOPPED, 0L, 1f).build()
+            playbackState(PlaybackStateCompat.STATE_STOPPED, 0L)
         )
         notifyChildrenChanged(MEDIA_ROOT_ID)
+        refreshReadAloudTile()
 
         ServiceCompat.stopForeground(this, ServiceCompat.STOP_FOREGROUND_REMOVE)
         stopSelf()
@@ -388,6 +475,12 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
             pluginEventTrigger?.invoke("media-session-previous", JSObject())
         }
 
+        // Chapter skip (Android Auto and the Android 13+ media controls show
+        // the session's custom actions), relayed like next/previous.
+        override fun onCustomAction(action: String?, extras: Bundle?) {
+            relayChapterAction(action)
+        }
+
         // Scrubber drag: hand the target back to the JS controller, which owns
         // the real audio timeline (seekToTime), and optimistically move the
         // thumb so the lock screen feels responsive before the seek lands.
@@ -396,7 +489,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
             pluginEventTrigger?.invoke("media-session-seek", JSObject().apply { put("position", pos) })
             val state = if (player.isPlaying) PlaybackStateCompat.STATE_PLAYING else PlaybackStateCompat.STATE_PAUSED
             mediaSession?.setPlaybackState(
-                stateBuilder.setState(state, pos, 1f).build()
+                playbackState(state, pos)
             )
         }
 
@@ -426,6 +519,68 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         }
     }
 
+    // Ask the system to redraw the Quick Settings tile for the new state.
+    private fun refreshReadAloudTile() {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.N) return
+        try {
+            TileService.requestListeningState(
+                this,
+                ComponentName(this, ReadAloudTileService::class.java)
+            )
+        } catch (e: Exception) {
+            // Some OEM builds throw while the tile has never been added.
+            Log.d("MediaPlaybackService", "Tile refresh rejected: ${e.message}")
+        }
+    }
+
+    private fun hasPreviousChapter() = currentChapterIndex > 0
+    private fun hasNextChapter() = currentChapterIndex in 0 until currentChapterCount - 1
+
+    // Built fresh each time: the seek and chapter actions come and go with
+    // the section being read.
+    private fun playbackState(state: Int, position: Long): PlaybackStateCompat {
+        var actions = PlaybackStateCompat.ACTION_PLAY or
+            PlaybackStateCompat.ACTION_PLAY_PAUSE or
+            PlaybackStateCompat.ACTION_PAUSE or
+            PlaybackStateCompat.ACTION_STOP or
+            PlaybackStateCompat.ACTION_SKIP_TO_NEXT or
+            PlaybackStateCompat.ACTION_SKIP_TO_PREVIOUS or
+            PlaybackStateCompat.ACTION_PLAY_FROM_MEDIA_ID or
+            PlaybackStateCompat.ACTION_PLAY_FROM_SEARCH
+        if (currentSeekable) actions = actions or PlaybackStateCompat.ACTION_SEEK_TO
+        val builder = PlaybackStateCompat.Builder()
+            .setActions(actions)
+            .setState(state, position, 1f)
+        if (hasPreviousChapter()) {
+            builder.addCustomAction(
+                ACTION_PREVIOUS_CHAPTER, "Previous chapter", android.R.drawable.ic_media_rew
+            )
+        }
+        if (hasNextChapter()) {
+            builder.addCustomAction(
+                ACTION_NEXT_CHAPTER, "Next chapter", android.R.drawable.ic_media_ff
+            )
+        }
+        return builder.build()
+    }
+
+    private fun relayChapterAction(action: String?) {
+        when (action) {
+            ACTION_NEXT_CHAPTER ->
+                pluginEventTrigger?.invoke("media-session-next-chapter", JSObject())
+            ACTION_PREVIOUS_CHAPTER ->
+                pluginEventTrigger?.invoke("media-session-previous-chapter", JSObject())
+        }
+    }
+
+    private fun chapterPendingIntent(action: String): PendingIntent {
+        val intent = Intent(this, MediaPlaybackService::class.java).setAction(action)
+        return PendingIntent.getService(
+            this, action.hashCode(), intent,
+            PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+        )
+    }
+
     private fun updatePlaybackState() {
         if (!sessionActive) return
         val state = if (player.isPlaying) PlaybackStateCompat.STATE_PLAYING else PlaybackStateCompat.STATE_PAUSED
@@ -434,9 +589,12 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         // player.currentPosition saturates at ~10s and would freeze the car /
         // lock-screen scrubber there while the book plays on.
         mediaSession?.setPlaybackState(
-            stateBuilder.setState(state, currentPositionMs, 1f).build()
+            playbackState(state, currentPositionMs)
         )
         showNotification(state)
+        // Also reached from the keep-alive player's onIsPlayingChanged, so
+        // this covers every play/pause flip.
+        refreshReadAloudTile()
     }
 
     // Last section duration written to the session metadata; the scrubber only
@@ -535,7 +693,7 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
         // Its position is never read for the scrubber; only play/pause matters.
         val state = if (playing) PlaybackStateCompat.STATE_PLAYING else PlaybackStateCompat.STATE_PAUSED
         mediaSession?.setPlaybackState(
-            stateBuilder.setState(state, currentPositionMs, 1f).build()
+            playbackState(state, currentPositionMs)
         )
         // Refresh the scrubber length when the section duration changes.
         if (currentDurationMs != appliedDurationMs) {
@@ -577,6 +735,17 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
             setVisibility(NotificationCompat.VISIBILITY_PUBLIC)
             setSmallIcon(R.drawable.notification_icon)
 
+            // Chapter skip flanks the paragraph controls when there's a chapter
+            // to go to; the compact view keeps previous, play/pause and next.
+            var compactStart = 0
+            if (hasPreviousChapter()) {
+                addAction(
+                    android.R.drawable.ic_media_rew,
+                    "Previous chapter",
+                    chapterPendingIntent(ACTION_PREVIOUS_CHAPTER)
+                )
+                compactStart = 1
+            }
             addAction(
                 android.R.drawable.ic_media_previous,
                 "Previous",
@@ -614,10 +783,18 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
                 )
             )
 
+            if (hasNextChapter()) {
+                addAction(
+                    android.R.drawable.ic_media_ff,
+                    "Next chapter",
+                    chapterPendingIntent(ACTION_NEXT_CHAPTER)
+                )
+            }
+
             setStyle(
                 androidx.media.app.NotificationCompat.MediaStyle()
                     .setMediaSession(mediaSession?.sessionToken)
-                    .setShowActionsInCompactView(0, 1, 2)
+                    .setShowActionsInCompactView(compactStart, compactStart + 1, compactStart + 2)
             )
         }
         return builder.build()
@@ -672,6 +849,9 @@ class MediaPlaybackService : MediaBrowserServiceCompat() {
             ACTION_ACTIVATE_SESSION -> {
                 activateSession()
             }
+            ACTION_NEXT_CHAPTER, ACTION_PREVIOUS_CHAPTER -> {
+                if (sessionActive) relayChapterAction(intent.action)
+            }
             Intent.ACTION_MEDIA_BUTTON -> {
                 if (sessionActive) {
                     MediaButtonReceiver.handleIntent(mediaSession, intent)
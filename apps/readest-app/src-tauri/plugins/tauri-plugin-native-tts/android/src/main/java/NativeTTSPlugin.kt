@@ -6,6 +6,9 @@ import android.os.Handler
 import android.os.Looper
 import android.app.Activity
 import android.content.Context
+import android.media.AudioDeviceCallback
+import android.media.AudioDeviceInfo
+import android.media.AudioManager
 import android.provider.Settings
 import android.speech.tts.TextToSpeech
 import android.speech.tts.UtteranceProgressListener
@@ -13,12 +16,14 @@ import android.speech.tts.Voice
 import android.util.Log
 import android.graphics.Bitmap
 import android.graphics.BitmapFactory
+import androidx.annotation.RequiresApi
 import androidx.core.content.ContextCompat
 import app.tauri.annotation.Command
 import app.tauri.annotation.InvokeArg
 import app.tauri.annotation.Permission
 import app.tauri.annotation.TauriPlugin
 import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
 import app.tauri.plugin.JSObject
 import app.tauri.plugin.Plugin
 import app.tauri.plugin.PluginResult
@@ -53,13 +58,37 @@ data class TTSVoiceData(
 data class TTSMessageEvent(
     val code: String, // 'boundary' | 'error' | 'end'
     val message: String? = null,
-    val mark: String? = null
+    val mark: String? = null,
+    // For 'word' and 'sentence' boundaries: the range being spoken, in
+    // UTF-16 units of the utterance text.
+    val charIndex: Int? = null,
+    val charLength: Int? = null
 )
 
 @InvokeArg
 class SpeakArgs(
     val text: String? = "",
-    val preload: Boolean? = false
+    val preload: Boolean? = false,
+    // Always false here: SSML is reduced to plain text before it reaches Android.
+    val ssml: Boolean? = false
+)
+
+@InvokeArg
+class SynthesizeArgs(
+    val text: String? = "",
+    val path: String? = null,
+    val voice: String? = null,
+    val rate: Float? = null
+)
+
+@InvokeArg
+class TTSEventArgs(
+    val utteranceId: String? = null,
+    val code: String? = null,
+    val message: String? = null,
+    val mark: String? = null,
+    val charIndex: Int? = null,
+    val charLength: Int? = null
 )
 
 @InvokeArg
@@ -77,6 +106,28 @@ class SetVoiceArgs(
     val voice: String? = null
 )
 
+@InvokeArg
+class SetAudioOutputArgs(
+    val id: String? = null
+)
+
+@InvokeArg
+class SetSystemEngineArgs {
+  var name: String? = null
+}
+
+@InvokeArg
+class SetInterruptionModeArgs {
+  var mode: String? = null
+}
+
+@InvokeArg
+class SetSleepTimerArgs {
+  var seconds: Long? = null
+  var endOfChapter: Boolean = false
+  var shakeToExtend: Long? = null
+}
+
 @InvokeArg
 class UpdateMediaSessionMetadataArgs {
   var title: String? = null
@@ -90,6 +141,9 @@ class UpdateMediaSessionStateArgs {
   var playing: Boolean? = null
   var position: Int? = null // in milliseconds
   var duration: Int? = null // in milliseconds
+  var seekable: Boolean? = null
+  var chapterIndex: Int? = null
+  var chapterCount: Int? = null
 }
 
 @InvokeArg
@@ -114,7 +168,12 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
     companion object {
         private const val TAG = "NativeTTSPlugin"
         private const val CHANNEL_NAME = "tts_events"
+        private const val AUDIO_ROUTE_EVENT = "audio-route-changed"
+        private const val SLEEP_TIMER_FIRED_EVENT = "sleep-timer-fired"
+        private const val SLEEP_TIMER_CHANGED_EVENT = "sleep-timer-changed"
         private const val IDLE_TIMEOUT_MS = 30L * 60 * 1000 // 30 minutes
+        private const val PREFS_ENGINE = "native_tts_engine"
+        private const val KEY_ENGINE = "engine"
         var NOTIFICATION_TITLE = "Read Aloud"
         var NOTIFICATION_TEXT = "Ready to read aloud"
         var FOREGROUND_SERVICE_TITLE = "Read Aloud"
@@ -130,8 +189,33 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
 
     private val eventChannels = ConcurrentHashMap<String, Channel<TTSMessageEvent>>()
     private val speakingJobs = ConcurrentHashMap<String, Job>()
+    // synthesize invokes waiting for their file to be written, by utterance id
+    private val pendingSyntheses = ConcurrentHashMap<String, Invoke>()
+    // Sentence start offsets of each utterance being spoken, and the sentence
+    // last reported, to turn word ranges into sentence boundaries too
+    private val utteranceSentences = ConcurrentHashMap<String, IntArray>()
+    private val currentSentence = ConcurrentHashMap<String, Int>()
     private val coroutineScope = CoroutineScope(Dispatchers.Main + SupervisorJob())
 
+    private val audioManager by lazy {
+        activity.getSystemService(Context.AUDIO_SERVICE) as AudioManager
+    }
+    private var audioDeviceCallback: AudioDeviceCallback? = null
+    // The output media was routed to when the devices last changed
+    private var currentOutputId: Int? = null
+
+    private val sleepTimer by lazy {
+        SleepTimer(
+            activity,
+            onFired = { sleepTimerFired() },
+            onChanged = { state -> trigger(SLEEP_TIMER_CHANGED_EVENT, state) },
+        )
+    }
+
+    private val enginePrefs by lazy {
+        activity.getSharedPreferences(PREFS_ENGINE, Context.MODE_PRIVATE)
+    }
+
     private val idleHandler = Handler(Looper.getMainLooper())
     private val idleShutdownRunnable = Runnable {
         Log.d(TAG, "Idle timeout reached, shutting down TTS engine to save battery")
@@ -141,6 +225,7 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
     @Command
     fun init(invoke: Invoke) {
         cancelIdleTimer()
+        watchAudioOutputs()
         coroutineScope.launch {
             try {
                 val success = initializeTTS()
@@ -157,10 +242,7 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
     
     private suspend fun initializeTTS(): Boolean = suspendCancellableCoroutine { continuation ->
         try {
-            val preferredEngine = Settings.Secure.getString(
-                activity.contentResolver,
-                Settings.Secure.TTS_DEFAULT_SYNTH
-            )
+            val preferredEngine = activeEngine()
             textToSpeech = TextToSpeech(activity, { status ->
                 when (status) {
                     TextToSpeech.SUCCESS -> {
@@ -183,9 +265,18 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
         }
     }
     
+    // The engine picked with set_system_engine, else the system default.
+    // TextToSpeech falls back to the default itself if the picked one has
+    // since been uninstalled.
+    private fun activeEngine(): String? {
+        return enginePrefs.getString(KEY_ENGINE, null)
+            ?: Settings.Secure.getString(activity.contentResolver, Settings.Secure.TTS_DEFAULT_SYNTH)
+    }
+
     private fun setupTTSListener() {
         textToSpeech?.setOnUtteranceProgressListener(object : UtteranceProgressListener() {
             override fun onStart(utteranceId: String?) {
+                if (utteranceId != null && pendingSyntheses.containsKey(utteranceId)) return
                 utteranceId?.let { id ->
                     isSpeaking.set(true)
                     sendEvent(id, TTSMessageEvent("boundary", "start"))
@@ -193,8 +284,13 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
             }
             
             override fun onDone(utteranceId: String?) {
+                utteranceId?.let { pendingSyntheses.remove(it) }?.let {
+                    it.resolve()
+                    return
+                }
                 utteranceId?.let { id ->
                     isSpeaking.set(false)
+                    forgetSentences(id)
                     sendEvent(id, TTSMessageEvent("end"))
                     closeEventChannel(id)
                 }
@@ -202,16 +298,26 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
 
             @Deprecated("deprecated in API level 21")
             override fun onError(utteranceId: String?) {
+                utteranceId?.let { pendingSyntheses.remove(it) }?.let {
+                    it.reject("TTS synthesis error")
+                    return
+                }
                 utteranceId?.let { id ->
                     isSpeaking.set(false)
+                    forgetSentences(id)
                     sendEvent(id, TTSMessageEvent("error", "TTS playback error"))
                     closeEventChannel(id)
                 }
             }
             
             override fun onError(utteranceId: String?, errorCode: Int) {
+                utteranceId?.let { pendingSyntheses.remove(it) }?.let {
+                    it.reject("TTS synthesis error:$errorCode")
+                    return
+                }
                 utteranceId?.let { id ->
                     isSpeaking.set(false)
+                    forgetSentences(id)
                     sendEvent(id, TTSMessageEvent("error", "TTS playback error:$errorCode"))
                     closeEventChannel(id)
                 }
@@ -219,11 +325,49 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
             
             override fun onRangeStart(utteranceId: String?, start: Int, end: Int, frame: Int) {
                 utteranceId?.let { id ->
-                    sendEvent(id, TTSMessageEvent("boundary", "range", "pos:$start-$end"))
+                    sentenceAt(id, start)?.let { (sentenceStart, sentenceEnd) ->
+                        sendEvent(id, TTSMessageEvent("boundary", "sentence",
+                            charIndex = sentenceStart, charLength = sentenceEnd - sentenceStart))
+                    }
+                    sendEvent(id, TTSMessageEvent("boundary", "word",
+                        charIndex = start, charLength = end - start))
                 }
             }
         })
     }
+
+    // Android only reports word ranges, so sentences come from a
+    // BreakIterator over the utterance in the voice's language.
+    private fun rememberSentences(utteranceId: String, text: String) {
+        val locale = textToSpeech?.voice?.locale ?: Locale.getDefault()
+        val iterator = java.text.BreakIterator.getSentenceInstance(locale)
+        iterator.setText(text)
+        val starts = mutableListOf<Int>()
+        var boundary = iterator.first()
+        while (boundary != java.text.BreakIterator.DONE) {
+            starts.add(boundary)
+            boundary = iterator.next()
+        }
+        utteranceSentences[utteranceId] = starts.toIntArray()
+        currentSentence.remove(utteranceId)
+    }
+
+    // The sentence a word starting at `offset` falls in, when it isn't the
+    // one reported last.
+    private fun sentenceAt(utteranceId: String, offset: Int): Pair<Int, Int>? {
+        val starts = utteranceSentences[utteranceId] ?: return null
+        val index = starts.indexOfLast { it <= offset }
+        if (index < 0 || index + 1 >= starts.size || currentSentence[utteranceId] == index) {
+            return null
+        }
+        currentSentence[utteranceId] = index
+        return Pair(starts[index], starts[index + 1])
+    }
+
+    private fun forgetSentences(utteranceId: String) {
+        utteranceSentences.remove(utteranceId)
+        currentSentence.remove(utteranceId)
+    }
     
     @Command
     fun speak(invoke: Invoke) {
@@ -284,6 +428,10 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
                 
                 val params = Bundle().apply {
                     putString(TextToSpeech.Engine.KEY_PARAM_UTTERANCE_ID, utteranceId)
+                    putFloat(TextToSpeech.Engine.KEY_PARAM_VOLUME, sleepTimer.volume)
+                }
+                if (!preload) {
+                    rememberSentences(utteranceId, text)
                 }
                 
                 val result = textToSpeech?.speak(
@@ -302,6 +450,55 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
         }
     }
     
+    // Renders speech into a WAV file instead of the speaker, for exporting a
+    // book as an audiobook. Resolves once the engine has finished the file.
+    @Command
+    fun synthesize(invoke: Invoke) {
+        cancelIdleTimer()
+
+        val args = invoke.parseArgs(SynthesizeArgs::class.java)
+        val text = args.text ?: ""
+        val path = args.path ?: ""
+        if (text.isEmpty() || path.isEmpty()) {
+            invoke.reject("Text and path cannot be empty")
+            return
+        }
+
+        val utteranceId = "synthesize-${UUID.randomUUID()}"
+
+        coroutineScope.launch {
+            try {
+                if (!isInitialized.get() && !initializeTTS()) {
+                    invoke.reject("Failed to initialize TTS engine")
+                    return@launch
+                }
+                val tts = textToSpeech
+                if (tts == null) {
+                    invoke.reject("TTS engine is not available")
+                    return@launch
+                }
+                args.voice?.let { name ->
+                    tts.voices?.find { it.name == name }?.let { tts.setVoice(it) }
+                }
+                tts.setSpeechRate(args.rate ?: currentRate.get())
+                tts.setPitch(currentPitch.get())
+
+                pendingSyntheses[utteranceId] = invoke
+                val params = Bundle().apply {
+                    putString(TextToSpeech.Engine.KEY_PARAM_UTTERANCE_ID, utteranceId)
+                }
+                val result = tts.synthesizeToFile(text, params, java.io.File(path), utteranceId)
+                if (result != TextToSpeech.SUCCESS) {
+                    pendingSyntheses.remove(utteranceId)
+                    invoke.reject("Failed to start synthesis")
+                }
+            } catch (e: Exception) {
+                pendingSyntheses.remove(utteranceId)
+                invoke.reject("Exception during synthesis: ${e.message}")
+            }
+        }
+    }
+
     private fun startEventStream(utteranceId: String) {
         coroutineScope.launch {
             val channel = eventChannels[utteranceId] ?: return@launch
@@ -312,6 +509,8 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
                         put("code", event.code)
                         event.message?.let { put("message", it) }
                         event.mark?.let { put("mark", it) }
+                        event.charIndex?.let { put("charIndex", it) }
+                        event.charLength?.let { put("charLength", it) }
                     }
                     trigger(CHANNEL_NAME, eventData)
                 }
@@ -327,15 +526,240 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
         }
     }
     
+    // Relays an event raised by the Rust side (the Piper engine) to the
+    // same listeners as the TextToSpeech events.
+    @Command
+    fun emit_tts_event(invoke: Invoke) {
+        val args = invoke.parseArgs(TTSEventArgs::class.java)
+        val eventData = JSObject().apply {
+            put("utteranceId", args.utteranceId ?: "")
+            put("code", args.code ?: "")
+            args.message?.let { put("message", it) }
+            args.mark?.let { put("mark", it) }
+            args.charIndex?.let { put("charIndex", it) }
+            args.charLength?.let { put("charLength", it) }
+        }
+        trigger(CHANNEL_NAME, eventData)
+        invoke.resolve()
+    }
+
     private fun closeEventChannel(utteranceId: String) {
         coroutineScope.launch {
             eventChannels[utteranceId]?.close()
             eventChannels.remove(utteranceId)
+            forgetSentences(utteranceId)
             speakingJobs[utteranceId]?.cancel()
             speakingJobs.remove(utteranceId)
         }
     }
     
+    // Outputs media can play through, in the order Android routes to them;
+    // call and system-sound routes are left out.
+    @RequiresApi(Build.VERSION_CODES.M)
+    private fun mediaOutputs(): List<AudioDeviceInfo> {
+        return audioManager.getDevices(AudioManager.GET_DEVICES_OUTPUTS)
+            .filter { outputKind(it.type) != null }
+            .sortedBy { routePriority(outputKind(it.type)) }
+    }
+
+    private fun outputKind(type: Int): String? = when (type) {
+        AudioDeviceInfo.TYPE_BUILTIN_SPEAKER -> "speaker"
+        AudioDeviceInfo.TYPE_WIRED_HEADSET,
+        AudioDeviceInfo.TYPE_WIRED_HEADPHONES,
+        AudioDeviceInfo.TYPE_LINE_ANALOG,
+        AudioDeviceInfo.TYPE_LINE_DIGITAL -> "wired"
+        AudioDeviceInfo.TYPE_BLUETOOTH_A2DP,
+        26, // TYPE_BLE_HEADSET
+        27 -> "bluetooth" // TYPE_BLE_SPEAKER
+        AudioDeviceInfo.TYPE_USB_DEVICE,
+        AudioDeviceInfo.TYPE_USB_ACCESSORY,
+        22 -> "usb" // TYPE_USB_HEADSET
+        AudioDeviceInfo.TYPE_HDMI,
+        AudioDeviceInfo.TYPE_HDMI_ARC -> "hdmi"
+        AudioDeviceInfo.TYPE_DOCK,
+        AudioDeviceInfo.TYPE_AUX_LINE -> "other"
+        else -> null
+    }
+
+    // Roughly how Android picks the media route: Bluetooth, then a wired or
+    // USB accessory, then the speaker.
+    private fun routePriority(kind: String?): Int = when (kind) {
+        "bluetooth" -> 0
+        "usb", "wired" -> 1
+        "hdmi", "other" -> 2
+        else -> 3
+    }
+
+    @RequiresApi(Build.VERSION_CODES.M)
+    private fun audioOutputsJson(outputs: List<AudioDeviceInfo>): JSArray {
+        val current = outputs.firstOrNull()?.id
+        val array = JSArray()
+        outputs.forEach { device ->
+            array.put(JSObject().apply {
+                put("id", device.id.toString())
+                put("name", device.productName?.toString() ?: "")
+                put("kind", outputKind(device.type))
+                put("selected", device.id == current)
+            })
+        }
+        return array
+    }
+
+    // Reports outputs coming and going. Headphones being unplugged already
+    // pause playback through the media session (ACTION_AUDIO_BECOMING_NOISY);
+    // `lost` tells the webview why.
+    private fun watchAudioOutputs() {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.M || audioDeviceCallback != null) return
+        currentOutputId = mediaOutputs().firstOrNull()?.id
+        val callback = object : AudioDeviceCallback() {
+            override fun onAudioDevicesAdded(addedDevices: Array<out AudioDeviceInfo>) {
+                routeChanged(lost = false)
+            }
+
+            override fun onAudioDevicesRemoved(removedDevices: Array<out AudioDeviceInfo>) {
+                routeChanged(lost = removedDevices.any { it.id == currentOutputId })
+            }
+        }
+        audioManager.registerAudioDeviceCallback(callback, Handler(Looper.getMainLooper()))
+        audioDeviceCallback = callback
+    }
+
+    @RequiresApi(Build.VERSION_CODES.M)
+    private fun routeChanged(lost: Boolean) {
+        val outputs = mediaOutputs()
+        val current = outputs.firstOrNull()?.id
+        if (current == currentOutputId && !lost) return
+        currentOutputId = current
+        trigger(AUDIO_ROUTE_EVENT, JSObject().apply {
+            put("outputs", audioOutputsJson(outputs))
+            put("lost", lost)
+        })
+    }
+
+    @Command
+    fun list_audio_outputs(invoke: Invoke) {
+        val outputs = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.M) {
+            audioOutputsJson(mediaOutputs())
+        } else {
+            JSArray()
+        }
+        invoke.resolve(JSObject().apply {
+            put("outputs", outputs)
+            put("selectable", false)
+        })
+    }
+
+    // TextToSpeech plays on the media stream, which Android routes itself;
+    // the user picks the output from the system's output switcher.
+    @Command
+    fun set_audio_output(invoke: Invoke) {
+        val args = invoke.parseArgs(SetAudioOutputArgs::class.java)
+        if (args.id == null) {
+            invoke.resolve()
+        } else {
+            invoke.reject("Audio output is chosen from the system output switcher on Android")
+        }
+    }
+
+    // Devices often carry the vendor's engine next to Google's, with very
+    // different voices.
+    @Command
+    fun list_system_engines(invoke: Invoke) {
+        coroutineScope.launch {
+            try {
+                if (!isInitialized.get()) {
+                    initializeTTS()
+                }
+                val active = activeEngine()
+                val engines = textToSpeech?.engines?.map { engine ->
+                    JSObject().apply {
+                        put("name", engine.name)
+                        put("label", engine.label)
+                        put("selected", engine.name == active)
+                    }
+                } ?: emptyList()
+                invoke.resolve(JSObject().apply {
+                    put("engines", JSONArray(engines))
+                    put("selectable", true)
+                })
+            } catch (e: Exception) {
+                invoke.reject("Exception listing engines: ${e.message}")
+            }
+        }
+    }
+
+    // Switching engines restarts TextToSpeech: whatever is being spoken
+    // stops, and the voices are the new engine's from here on.
+    @Command
+    fun set_system_engine(invoke: Invoke) {
+        val args = invoke.parseArgs(SetSystemEngineArgs::class.java)
+        enginePrefs.edit().apply {
+            if (args.name == null) remove(KEY_ENGINE) else putString(KEY_ENGINE, args.name)
+        }.apply()
+        coroutineScope.launch {
+            try {
+                textToSpeech?.stop()
+                textToSpeech?.shutdown()
+                textToSpeech = null
+                isInitialized.set(false)
+                isSpeaking.set(false)
+                isPaused.set(false)
+                speakingJobs.values.forEach { it.cancel() }
+                eventChannels.values.forEach { it.close() }
+                speakingJobs.clear()
+                eventChannels.clear()
+                if (initializeTTS()) {
+                    invoke.resolve()
+                } else {
+                    invoke.reject("Failed to start TTS engine: ${args.name ?: "default"}")
+                }
+            } catch (e: Exception) {
+                invoke.reject("Exception switching engine: ${e.message}")
+            }
+        }
+    }
+
+    @Command
+    fun set_interruption_mode(invoke: Invoke) {
+        val args = invoke.parseArgs(SetInterruptionModeArgs::class.java)
+        when (args.mode) {
+            MediaPlaybackService.INTERRUPTION_PAUSE, MediaPlaybackService.INTERRUPTION_DUCK -> {
+                MediaPlaybackService.setInterruptionMode(args.mode!!)
+                invoke.resolve()
+            }
+            else -> invoke.reject("Unknown interruption mode: ${args.mode}")
+        }
+    }
+
+    @Command
+    fun set_sleep_timer(invoke: Invoke) {
+        val args = invoke.parseArgs(SetSleepTimerArgs::class.java)
+        sleepTimer.set(
+            args.seconds,
+            args.endOfChapter,
+            args.shakeToExtend,
+            MediaPlaybackService.currentChapterIndex,
+        )
+        invoke.resolve(sleepTimer.state())
+    }
+
+    @Command
+    fun get_sleep_timer(invoke: Invoke) {
+        invoke.resolve(sleepTimer.state())
+    }
+
+    // Speech stops here, since the WebView may be too throttled to do it
+    // promptly; the client then ends the session.
+    private fun sleepTimerFired() {
+        try {
+            textToSpeech?.stop()
+            isSpeaking.set(false)
+        } catch (e: Exception) {
+            Log.e(TAG, "Error stopping TTS for the sleep timer", e)
+        }
+        trigger(SLEEP_TIMER_FIRED_EVENT, sleepTimer.state())
+    }
+
     @Command
     fun pause(invoke: Invoke) {
         try {
@@ -527,7 +951,11 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
                 isPlaying,
                 args.position?.toLong(),
                 args.duration?.toLong(),
+                args.seekable,
+                args.chapterIndex,
+                args.chapterCount,
             )
+            args.chapterIndex?.let { sleepTimer.chapterChanged(it) }
             invoke.resolve()
         } catch (e: Exception) {
             invoke.reject("Failed to update playback state: ${e.message}")
@@ -607,6 +1035,11 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
     fun destroy() {
         try {
             cancelIdleTimer()
+            sleepTimer.destroy()
+            if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.M) {
+                audioDeviceCallback?.let { audioManager.unregisterAudioDeviceCallback(it) }
+            }
+            audioDeviceCallback = null
 
             MediaPlaybackService.requestDeactivation()
             MediaPlaybackService.pluginEventTrigger = null
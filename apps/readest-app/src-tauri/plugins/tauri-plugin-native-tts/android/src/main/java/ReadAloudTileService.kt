@@ -0,0 +1,76 @@
+package com.readest.native_tts
+
+import android.annotation.SuppressLint
+import android.app.PendingIntent
+import android.content.Intent
+import android.graphics.drawable.Icon
+import android.net.Uri
+import android.os.Build
+import android.service.quicksettings.Tile
+import android.service.quicksettings.TileService
+import androidx.annotation.RequiresApi
+
+/**
+ * Quick Settings tile that pauses and resumes read-aloud without opening the
+ * app.
+ *
+ * The toggle goes through the media session's transport controls, the same
+ * path as the lock-screen and notification buttons, so the reader, the
+ * notification and the tile all follow one playback state. With no read-aloud
+ * session the tile reopens the last book read aloud and starts reading it,
+ * like Android Auto's "Resume last book"; with no such book it is unavailable.
+ */
+@RequiresApi(Build.VERSION_CODES.N)
+class ReadAloudTileService : TileService() {
+    override fun onStartListening() {
+        super.onStartListening()
+        updateTile()
+    }
+
+    override fun onClick() {
+        super.onClick()
+        if (MediaPlaybackService.togglePlayPause()) {
+            // The session redraws the tile once its state changes; flip it
+            // now so it doesn't lag the round trip.
+            qsTile?.let {
+                it.state = if (it.state == Tile.STATE_ACTIVE) Tile.STATE_INACTIVE else Tile.STATE_ACTIVE
+                it.updateTile()
+            }
+            return
+        }
+        val hash = MediaPlaybackService.lastBookHash ?: return
+        val intent = Intent(Intent.ACTION_VIEW, Uri.parse("readest://book/$hash?autoplay=tts"))
+            .setPackage(packageName)
+            .addFlags(Intent.FLAG_ACTIVITY_NEW_TASK)
+        unlockAndRun { startReader(intent) }
+    }
+
+    @SuppressLint("StartActivityAndCollapseDeprecated")
+    private fun startReader(intent: Intent) {
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.UPSIDE_DOWN_CAKE) {
+            val flags = PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+            startActivityAndCollapse(PendingIntent.getActivity(this, 0, intent, flags))
+        } else {
+            @Suppress("DEPRECATION")
+            startActivityAndCollapse(intent)
+        }
+    }
+
+    private fun updateTile() {
+        val tile = qsTile ?: return
+        // Cold process: the service hasn't run to restore the last book.
+        if (MediaPlaybackService.lastBookHash == null) MediaPlaybackService.loadLastBook(this)
+        tile.icon = Icon.createWithResource(this, R.drawable.ic_tile_read_aloud)
+        tile.label = getString(R.string.read_aloud_tile_label)
+        tile.state = when {
+            MediaPlaybackService.isPlaying -> Tile.STATE_ACTIVE
+            MediaPlaybackService.isSessionActive -> Tile.STATE_INACTIVE
+            MediaPlaybackService.lastBookHash != null -> Tile.STATE_INACTIVE
+            else -> Tile.STATE_UNAVAILABLE
+        }
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q) {
+            tile.subtitle = MediaPlaybackService.lastBookTitle
+        }
+        tile.updateTile()
+    }
+}
@@ -0,0 +1,144 @@
+package com.readest.native_tts
+
+import android.content.Context
+import android.hardware.Sensor
+import android.hardware.SensorEvent
+import android.hardware.SensorEventListener
+import android.hardware.SensorManager
+import android.os.Handler
+import android.os.Looper
+import android.os.SystemClock
+import app.tauri.plugin.JSObject
+import kotlin.math.sqrt
+
+// The sleep timer, kept here rather than in the WebView: a main-looper
+// Handler keeps counting with the screen off (the media foreground service
+// keeps the process alive), where the WebView's timers get throttled.
+// Speech fades over the last ten seconds, each utterance starting at the
+// volume left; in the last minute a shake of the device adds time, when
+// asked for. The end of the chapter is noticed from the chapter index the
+// media session is updated with.
+class SleepTimer(
+    context: Context,
+    private val onFired: () -> Unit,
+    private val onChanged: (JSObject) -> Unit,
+) : SensorEventListener {
+
+    companion object {
+        private const val FADE_MS = 10_000L
+        private const val SHAKE_WINDOW_MS = 60_000L
+        private const val TICK_MS = 1_000L
+        // Well past walking or turning over in bed.
+        private const val SHAKE_THRESHOLD_G = 2.5f
+        private const val SHAKE_DEBOUNCE_MS = 1_500L
+    }
+
+    private val handler = Handler(Looper.getMainLooper())
+    private val sensorManager = context.getSystemService(Context.SENSOR_SERVICE) as SensorManager?
+
+    // elapsedRealtime() at which speech stops, when counting down
+    private var deadline: Long? = null
+    private var endOfChapter = false
+    // The chapter being read when armed; -1 until the session reports one
+    private var chapter = -1
+    private var shakeExtendMs: Long? = null
+    private var lastShakeAt = 0L
+    private var listening = false
+
+    private val tick = object : Runnable {
+        override fun run() {
+            val left = remaining() ?: return
+            if (left <= 0) {
+                fire()
+            } else {
+                updateShakeListener()
+                handler.postDelayed(this, minOf(TICK_MS, left))
+            }
+        }
+    }
+
+    val active: Boolean
+        get() = deadline != null || endOfChapter
+
+    // Volume for the next utterance: full until the fade, then falling to
+    // nothing.
+    val volume: Float
+        get() {
+            val left = remaining() ?: return 1f
+            return (left.toFloat() / FADE_MS).coerceIn(0f, 1f)
+        }
+
+    private fun remaining(): Long? = deadline?.let { it - SystemClock.elapsedRealtime() }
+
+    fun set(seconds: Long?, endOfChapter: Boolean, shakeToExtend: Long?, currentChapter: Int) {
+        cancel()
+        deadline = seconds?.let { SystemClock.elapsedRealtime() + it * 1000 }
+        this.endOfChapter = endOfChapter
+        chapter = currentChapter
+        shakeExtendMs = shakeToExtend?.let { it * 1000 }
+        if (deadline != null) {
+            handler.post(tick)
+        }
+    }
+
+    fun cancel() {
+        handler.removeCallbacks(tick)
+        deadline = null
+        endOfChapter = false
+        chapter = -1
+        shakeExtendMs = null
+        updateShakeListener()
+    }
+
+    fun chapterChanged(index: Int) {
+        if (!endOfChapter || index < 0) return
+        if (chapter < 0) {
+            chapter = index
+        } else if (index != chapter) {
+            fire()
+        }
+    }
+
+    fun state(): JSObject = JSObject().apply {
+        put("active", active)
+        put("remainingMs", remaining()?.coerceAtLeast(0))
+        put("endOfChapter", endOfChapter)
+    }
+
+    fun destroy() {
+        cancel()
+    }
+
+    private fun fire() {
+        cancel()
+        onFired()
+    }
+
+    private fun updateShakeListener() {
+        val left = remaining()
+        val wanted = shakeExtendMs != null && left != null && left <= SHAKE_WINDOW_MS
+        if (wanted == listening) return
+        val sensor = sensorManager?.getDefaultSensor(Sensor.TYPE_ACCELEROMETER) ?: return
+        if (wanted) {
+            sensorManager.registerListener(this, sensor, SensorManager.SENSOR_DELAY_UI)
+        } else {
+            sensorManager.unregisterListener(this)
+        }
+        listening = wanted
+    }
+
+    override fun onSensorChanged(event: SensorEvent) {
+        val (x, y, z) = Triple(event.values[0], event.values[1], event.values[2])
+        val g = sqrt(x * x + y * y + z * z) / SensorManager.GRAVITY_EARTH
+        val now = SystemClock.elapsedRealtime()
+        if (g < SHAKE_THRESHOLD_G || now - lastShakeAt < SHAKE_DEBOUNCE_MS) return
+        lastShakeAt = now
+        val extend = shakeExtendMs ?: return
+        val current = deadline ?: return
+        deadline = maxOf(current, now) + extend
+        updateShakeListener()
+        onChanged(state())
+    }
+
+    override fun onAccuracyChanged(sensor: Sensor?, accuracy: Int) {}
+}
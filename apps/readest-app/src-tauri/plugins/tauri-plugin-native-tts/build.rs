@@ -15,6 +15,23 @@ const COMMANDS: &[&str] = &[
     "playout_enqueue",
     "playout_control",
     "playout_position",
+    "set_engine",
+    "list_piper_voices",
+    "download_piper_voice",
+    "cancel_piper_download",
+    "delete_piper_voice",
+    "set_cloud_voices",
+    "set_voice_map",
+    "get_voice_map",
+    "set_pronunciations",
+    "get_pronunciations",
+    "list_system_engines",
+    "set_system_engine",
+    "list_audio_outputs",
+    "set_audio_output",
+    "set_sleep_timer",
+    "get_sleep_timer",
+    "set_interruption_mode",
     "register_listener",
     "remove_listener",
     "check_permissions",
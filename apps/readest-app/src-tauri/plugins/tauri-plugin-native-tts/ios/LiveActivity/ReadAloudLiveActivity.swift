@@ -0,0 +1,105 @@
+// Lock-screen and Dynamic Island card shown while read-aloud runs. Built
+// into the ReadestWidget extension (see ../README.md); the native-tts plugin
+// starts, updates and ends the activity from its media session state.
+
+import ActivityKit
+import SwiftUI
+import WidgetKit
+
+@available(iOS 16.1, *)
+struct ReadAloudLiveActivity: Widget {
+  var body: some WidgetConfiguration {
+    ActivityConfiguration(for: ReadAloudActivityAttributes.self) { context in
+      HStack(spacing: 12) {
+        Image(systemName: "headphones")
+          .font(.title2)
+          .foregroundColor(.accentColor)
+        VStack(alignment: .leading, spacing: 2) {
+          Text(context.attributes.bookTitle)
+            .font(.headline)
+            .lineLimit(1)
+          Text(context.state.chapter.isEmpty ? context.attributes.bookAuthor : context.state.chapter)
+            .font(.subheadline)
+            .foregroundColor(.secondary)
+            .lineLimit(1)
+          ReadAloudElapsedText(state: context.state)
+            .font(.caption.monospacedDigit())
+            .foregroundColor(.secondary)
+        }
+        Spacer(minLength: 0)
+        ReadAloudToggleButton(playing: context.state.playing)
+      }
+      .padding(16)
+    } dynamicIsland: { context in
+      DynamicIsland {
+        DynamicIslandExpandedRegion(.leading) {
+          Image(systemName: "headphones")
+            .font(.title2)
+            .foregroundColor(.accentColor)
+        }
+        DynamicIslandExpandedRegion(.trailing) {
+          ReadAloudToggleButton(playing: context.state.playing)
+        }
+        DynamicIslandExpandedRegion(.center) {
+          Text(context.attributes.bookTitle)
+            .font(.headline)
+            .lineLimit(1)
+        }
+        DynamicIslandExpandedRegion(.bottom) {
+          HStack {
+            Text(context.state.chapter)
+              .lineLimit(1)
+            Spacer(minLength: 8)
+            ReadAloudElapsedText(state: context.state)
+              .monospacedDigit()
+          }
+          .font(.subheadline)
+          .foregroundColor(.secondary)
+        }
+      } compactLeading: {
+        Image(systemName: context.state.playing ? "headphones" : "pause.fill")
+          .foregroundColor(.accentColor)
+      } compactTrailing: {
+        ReadAloudElapsedText(state: context.state)
+          .monospacedDigit()
+          .frame(maxWidth: 52)
+      } minimal: {
+        Image(systemName: "headphones")
+          .foregroundColor(.accentColor)
+      }
+    }
+  }
+}
+
+/// Counts up on its own while playing; frozen at the elapsed time when paused.
+@available(iOS 16.1, *)
+private struct ReadAloudElapsedText: View {
+  let state: ReadAloudActivityAttributes.ContentState
+
+  var body: some View {
+    if state.playing {
+      Text(state.timerStart, style: .timer)
+    } else {
+      Text(Duration.seconds(state.elapsed).formatted(.time(pattern: .minuteSecond)))
+    }
+  }
+}
+
+@available(iOS 16.1, *)
+private struct ReadAloudToggleButton: View {
+  let playing: Bool
+
+  var body: some View {
+    let symbol = Image(systemName: playing ? "pause.circle.fill" : "play.circle.fill")
+      .font(.largeTitle)
+      .foregroundColor(.accentColor)
+    // Buttons in Live Activities need App Intents (iOS 17); earlier systems
+    // show the state only, and a tap opens the app.
+    if #available(iOS 17.0, *) {
+      Button(intent: ToggleReadAloudIntent()) { symbol }
+        .buttonStyle(.plain)
+    } else {
+      symbol
+    }
+  }
+}
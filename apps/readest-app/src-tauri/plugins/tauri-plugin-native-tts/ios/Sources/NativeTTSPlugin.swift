@@ -11,6 +11,23 @@ private let keepAliveLog = Logger(subsystem: "com.bilingify.readest", category:
 class SpeakArgs: Decodable {
   let text: String?
   let preload: Bool?
+  let ssml: Bool?
+}
+
+class SynthesizeArgs: Decodable {
+  let text: String?
+  let path: String?
+  let voice: String?
+  let rate: Float?
+}
+
+class TTSEventArgs: Decodable {
+  let utteranceId: String
+  let code: String
+  let message: String?
+  let mark: String?
+  let charIndex: Int?
+  let charLength: Int?
 }
 
 class SetRateArgs: Decodable {
@@ -25,6 +42,14 @@ class SetVoiceArgs: Decodable {
   let voice: String?
 }
 
+class SetAudioOutputArgs: Decodable {
+  let id: String?
+}
+
+class SetSystemEngineArgs: Decodable {
+  let name: String?
+}
+
 class UpdateMediaSessionMetadataArgs: Decodable {
   let title: String?
   let artist: String?
@@ -42,6 +67,10 @@ class UpdateMediaSessionStateArgs: Decodable {
   let playing: Bool?
   let position: Double?  // milliseconds
   let duration: Double?  // milliseconds
+  let seekable: Bool?
+  let chapterIndex: Int?
+  let chapterCount: Int?
+  let chapterTitle: String?
 }
 
 class SetMediaSessionActiveArgs: Decodable {
@@ -50,6 +79,8 @@ class SetMediaSessionActiveArgs: Decodable {
   let notificationText: String?
   let foregroundServiceTitle: String?
   let foregroundServiceText: String?
+  let bookTitle: String?
+  let bookAuthor: String?
 }
 
 struct PlayoutEnqueueArgs: Decodable {
@@ -101,12 +132,54 @@ struct GetVoicesResponse: Encodable {
   let voices: [VoiceData]
 }
 
+struct AudioOutputData: Encodable {
+  let id: String
+  let name: String
+  let kind: String
+  let selected: Bool
+}
+
+struct AudioOutputsResponse: Encodable {
+  let outputs: [AudioOutputData]
+  let selectable: Bool
+}
+
+struct SystemEngineData: Encodable {
+  let name: String
+  let label: String
+  let selected: Bool
+}
+
+struct SystemEnginesResponse: Encodable {
+  let engines: [SystemEngineData]
+  let selectable: Bool
+}
+
+class SetInterruptionModeArgs: Decodable {
+  let mode: String?
+}
+
+struct AudioInterruptionEvent: Encodable {
+  let phase: String
+  let ducked: Bool
+  let permanent: Bool
+  let shouldResume: Bool
+}
+
+struct AudioRouteChangedEvent: Encodable {
+  let outputs: [AudioOutputData]
+  let lost: Bool
+}
+
 /// Native iOS Text-to-Speech backed by `AVSpeechSynthesizer`, mirroring the
 /// Android `NativeTTSPlugin` (Android `TextToSpeech`). The shared TypeScript
 /// `NativeTTSClient` drives both platforms through the same plugin command and
 /// `tts_events` channel contract.
 class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   private let synthesizer = AVSpeechSynthesizer()
+  // Renders `synthesize` requests to files; separate from `synthesizer` so
+  // an audiobook export never cuts into read-aloud playback.
+  private let fileSynthesizer = AVSpeechSynthesizer()
 
   // App-level controls. `rate` arrives pre-curved by the JS client (see
   // `avRate(from:)`); `pitch` is a direct multiplier (1.0 == normal).
@@ -117,6 +190,10 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   // Maps a live utterance to the UUID the JS client awaits, so delegate
   // callbacks can route `tts_events` to the right async iterator.
   private var utteranceIds = [ObjectIdentifier: String]()
+  // Sentence ranges of each utterance being spoken, and the one reported
+  // last, so word ranges can be reported as sentence boundaries too.
+  private var utteranceSentences = [ObjectIdentifier: [NSRange]]()
+  private var currentSentence = [ObjectIdentifier: Int]()
 
   // Remote command targets we registered. The lock-screen command center
   // (`MPRemoteCommandCenter.shared()`) is app-global and also used by
@@ -124,6 +201,15 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   // tokens and remove only our own on teardown.
   private var remoteCommandTargets: [(MPRemoteCommand, Any)] = []
   private var mediaSessionActive = false
+  // The chapter last pushed with the media session state, for the sleep
+  // timer's end-of-chapter stop.
+  private var currentChapterIndex = -1
+  // Lock-screen / Dynamic Island card for the session (iOS 16.2+).
+  private let readAloudActivity = ReadAloudActivityController()
+  private lazy var sleepTimer = SleepTimer(
+    onVolume: { [weak self] volume in self?.playoutPlayer?.volume = volume },
+    onFired: { [weak self] in self?.sleepTimerFired() },
+    onChanged: { [weak self] state in try? self?.trigger("sleep-timer-changed", data: state) })
 
   // Audio-session interruption + route-change observers (registered while the
   // media session is active). .spokenAudio is a two-way contract: navigation
@@ -145,6 +231,9 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   // an interruption can outlast any time window (a phone call), so the pair
   // is matched by this flag, not by time.
   private var interruptionForwarded = false
+  // "duck" claims the session in the default mode, where navigation prompts
+  // duck speech (without telling us) instead of interrupting it.
+  private var interruptionMode = "pause"
 
   private func withinSessionOpWindow() -> Bool {
     return Date().timeIntervalSince(lastSessionOpTime) < 2.0
@@ -207,6 +296,15 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   override init() {
     super.init()
     synthesizer.delegate = self
+    if #available(iOS 17.0, *) {
+      NotificationCenter.default.addObserver(
+        forName: ToggleReadAloudIntent.toggleNotification, object: nil, queue: .main
+      ) { [weak self] _ in
+        guard let self = self, self.mediaSessionActive else { return }
+        self.triggerMediaSession(
+          self.readAloudActivity.isPlaying ? "media-session-pause" : "media-session-play")
+      }
+    }
   }
 
   // MARK: - Lifecycle
@@ -236,22 +334,26 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       let rate = currentRate
       let pitch = currentPitch
       let voiceId = currentVoiceId
+      let ssml = args.ssml ?? false
 
       // Resolve immediately with the id; events stream over `tts_events`.
       invoke.resolve(SpeakResponse(utteranceId: utteranceId))
 
       DispatchQueue.main.async {
-        let utterance = AVSpeechUtterance(string: text)
+        let utterance = self.makeUtterance(text, ssml: ssml)
         utterance.rate = self.avRate(from: rate)
         utterance.pitchMultiplier = self.avPitch(from: pitch)
         // Each sentence is a separate utterance spoken after a gap, so the audio
         // route goes cold between them and the first word can be clipped. A small
         // pre-utterance delay plays silence first to warm the route. See #4676.
         utterance.preUtteranceDelay = 0.1
+        utterance.volume = self.sleepTimer.volume
         if !voiceId.isEmpty, let voice = AVSpeechSynthesisVoice(identifier: voiceId) {
           utterance.voice = voice
         }
         self.utteranceIds[ObjectIdentifier(utterance)] = utteranceId
+        self.utteranceSentences[ObjectIdentifier(utterance)] =
+          self.sentenceRanges(of: utterance.speechString)
         self.synthesizer.speak(utterance)
       }
     } catch {
@@ -259,6 +361,58 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     }
   }
 
+  // Renders speech into a WAV file instead of the speaker, for exporting a
+  // book as an audiobook. The synthesizer hands over PCM buffers and ends
+  // with an empty one, which is when the file is complete.
+  @objc public func synthesize(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(SynthesizeArgs.self)
+      let text = args.text ?? ""
+      let path = args.path ?? ""
+      if text.isEmpty || path.isEmpty {
+        invoke.reject("Text and path cannot be empty")
+        return
+      }
+
+      let utterance = AVSpeechUtterance(string: text)
+      utterance.rate = avRate(from: args.rate ?? currentRate)
+      utterance.pitchMultiplier = avPitch(from: currentPitch)
+      let voiceId = args.voice ?? currentVoiceId
+      if !voiceId.isEmpty, let voice = AVSpeechSynthesisVoice(identifier: voiceId) {
+        utterance.voice = voice
+      }
+      let url = URL(fileURLWithPath: path)
+
+      DispatchQueue.main.async {
+        var output: AVAudioFile?
+        var finished = false
+        self.fileSynthesizer.write(utterance) { buffer in
+          guard !finished, let pcm = buffer as? AVAudioPCMBuffer else { return }
+          if pcm.frameLength == 0 {
+            finished = true
+            output = nil  // closes the file
+            invoke.resolve()
+            return
+          }
+          do {
+            if output == nil {
+              output = try AVAudioFile(
+                forWriting: url, settings: pcm.format.settings,
+                commonFormat: pcm.format.commonFormat, interleaved: pcm.format.isInterleaved)
+            }
+            try output?.write(from: pcm)
+          } catch {
+            finished = true
+            output = nil
+            invoke.reject("Failed to write speech: \(error.localizedDescription)")
+          }
+        }
+      }
+    } catch {
+      invoke.reject("Failed to synthesize: \(error.localizedDescription)")
+    }
+  }
+
   @objc public func pause(_ invoke: Invoke) {
     // Mirror Android: pause is implemented as stop. The JS client returns
     // `false` from pause(), so the controller stops and re-speaks the current
@@ -278,6 +432,8 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     DispatchQueue.main.async {
       self.synthesizer.stopSpeaking(at: .immediate)
       self.utteranceIds.removeAll()
+      self.utteranceSentences.removeAll()
+      self.currentSentence.removeAll()
     }
     invoke.resolve()
   }
@@ -356,11 +512,32 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   func speechSynthesizer(
     _ synthesizer: AVSpeechSynthesizer, didFinish utterance: AVSpeechUtterance
   ) {
+    forgetSentences(of: utterance)
     if let id = utteranceIds.removeValue(forKey: ObjectIdentifier(utterance)) {
       sendEvent(utteranceId: id, code: "end")
     }
   }
 
+  // Offsets are UTF-16 units of the utterance text, as on Android and in
+  // JavaScript strings.
+  func speechSynthesizer(
+    _ synthesizer: AVSpeechSynthesizer, willSpeakRangeOfSpeechString characterRange: NSRange,
+    utterance: AVSpeechUtterance
+  ) {
+    let key = ObjectIdentifier(utterance)
+    guard let id = utteranceIds[key] else { return }
+    if let sentences = utteranceSentences[key],
+      let index = sentences.firstIndex(where: { NSLocationInRange(characterRange.location, $0) }),
+      currentSentence[key] != index
+    {
+      currentSentence[key] = index
+      sendEvent(
+        utteranceId: id, code: "boundary", message: "sentence",
+        range: sentences[index])
+    }
+    sendEvent(utteranceId: id, code: "boundary", message: "word", range: characterRange)
+  }
+
   func speechSynthesizer(
     _ synthesizer: AVSpeechSynthesizer, didCancel utterance: AVSpeechUtterance
   ) {
@@ -368,6 +545,41 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     // treats a finished utterance as a cue to advance, which must not happen on
     // a manual stop or pause (mirrors Android, where stop() emits no onDone).
     utteranceIds.removeValue(forKey: ObjectIdentifier(utterance))
+    forgetSentences(of: utterance)
+  }
+
+  // SSML arrives already reduced to the elements AVSpeechSynthesizer knows.
+  // iOS 16 reads it; earlier systems speak the text with the tags dropped.
+  private func makeUtterance(_ text: String, ssml: Bool) -> AVSpeechUtterance {
+    guard ssml else { return AVSpeechUtterance(string: text) }
+    if #available(iOS 16.0, *), let utterance = AVSpeechUtterance(ssmlRepresentation: text) {
+      return utterance
+    }
+    let plain = text
+      .replacingOccurrences(of: "<[^>]*>", with: "", options: .regularExpression)
+      .replacingOccurrences(of: "&lt;", with: "<")
+      .replacingOccurrences(of: "&gt;", with: ">")
+      .replacingOccurrences(of: "&quot;", with: "\"")
+      .replacingOccurrences(of: "&apos;", with: "'")
+      .replacingOccurrences(of: "&amp;", with: "&")
+    return AVSpeechUtterance(string: plain)
+  }
+
+  private func sentenceRanges(of text: String) -> [NSRange] {
+    let string = text as NSString
+    var ranges = [NSRange]()
+    string.enumerateSubstrings(
+      in: NSRange(location: 0, length: string.length),
+      options: [.bySentences, .substringNotRequired]
+    ) { _, _, enclosingRange, _ in
+      ranges.append(enclosingRange)
+    }
+    return ranges
+  }
+
+  private func forgetSentences(of utterance: AVSpeechUtterance) {
+    utteranceSentences.removeValue(forKey: ObjectIdentifier(utterance))
+    currentSentence.removeValue(forKey: ObjectIdentifier(utterance))
   }
 
   // MARK: - Media session (lock screen / now playing)
@@ -378,8 +590,13 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       let active = args.active ?? true
       DispatchQueue.main.async {
         if active {
+          if !self.mediaSessionActive {
+            self.readAloudActivity.start(
+              bookTitle: args.bookTitle ?? "", bookAuthor: args.bookAuthor ?? "")
+          }
           self.activateRemoteCommands()
         } else {
+          self.readAloudActivity.end()
           self.deactivateRemoteCommands()
         }
       }
@@ -396,6 +613,9 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       let position = args.position
       let duration = args.duration
       DispatchQueue.main.async {
+        if let seekable = args.seekable {
+          self.activeCommandCenter().changePlaybackPositionCommand.isEnabled = seekable
+        }
         // DIAGNOSTIC (remove before release): per-push session snapshot, to
         // see when WebKit steals the category back and what state we run in.
         let snap = AVAudioSession.sharedInstance()
@@ -420,8 +640,17 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
         if let duration = duration, duration > 0 {
           info[MPMediaItemPropertyPlaybackDuration] = duration / 1000.0
         }
+        if let index = args.chapterIndex, let count = args.chapterCount, count > 0 {
+          info[MPNowPlayingInfoPropertyChapterNumber] = index
+          info[MPNowPlayingInfoPropertyChapterCount] = count
+        }
+        if let index = args.chapterIndex {
+          self.currentChapterIndex = index
+          self.sleepTimer.chapterChanged(index)
+        }
         center.nowPlayingInfo = info
         self.setSystemPlaybackState(playing: playing)
+        self.readAloudActivity.update(playing: playing, chapter: args.chapterTitle)
       }
       invoke.resolve()
     } catch {
@@ -503,10 +732,29 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     invoke.resolve(["postNotification": "granted"])
   }
 
+  // Relays an event raised by the Rust side (the Piper engine) to the same
+  // listeners as the AVSpeechSynthesizer events.
+  @objc public func emit_tts_event(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(TTSEventArgs.self)
+      var range: NSRange?
+      if let index = args.charIndex, let length = args.charLength {
+        range = NSRange(location: index, length: length)
+      }
+      sendEvent(
+        utteranceId: args.utteranceId, code: args.code, message: args.message, mark: args.mark,
+        range: range)
+      invoke.resolve()
+    } catch {
+      invoke.reject("Failed to relay event: \(error.localizedDescription)")
+    }
+  }
+
   // MARK: - Helpers
 
   private func sendEvent(
-    utteranceId: String, code: String, message: String? = nil, mark: String? = nil
+    utteranceId: String, code: String, message: String? = nil, mark: String? = nil,
+    range: NSRange? = nil
   ) {
     var data: JSObject = ["utteranceId": utteranceId, "code": code]
     if let message = message {
@@ -515,6 +763,10 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     if let mark = mark {
       data["mark"] = mark
     }
+    if let range = range {
+      data["charIndex"] = range.location
+      data["charLength"] = range.length
+    }
     trigger("tts_events", data: data)
   }
 
@@ -532,7 +784,8 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       "audio session before claim: category=\(session.category.rawValue) options=\(session.categoryOptions.rawValue) mode=\(session.mode.rawValue)"
     )
     do {
-      try session.setCategory(.playback, mode: .spokenAudio, options: [])
+      let mode: AVAudioSession.Mode = interruptionMode == "duck" ? .default : .spokenAudio
+      try session.setCategory(.playback, mode: mode, options: [])
       try session.setActive(true)
     } catch {
       keepAliveLog.error("audio session claim failed: \(error.localizedDescription)")
@@ -609,6 +862,7 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       }
       interruptionForwarded = true
       keepAliveLog.log("interruption began")
+      reportInterruption("began")
       triggerMediaSession("media-session-pause")
     case .ended:
       let optionsRaw = info[AVAudioSessionInterruptionOptionKey] as? UInt ?? 0
@@ -617,7 +871,9 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       let wasForwarded = interruptionForwarded
       interruptionForwarded = false
       keepAliveLog.log("interruption ended shouldResume=\(shouldResume) forwarded=\(wasForwarded)")
-      guard wasForwarded, shouldResume else { return }
+      guard wasForwarded else { return }
+      reportInterruption("ended", shouldResume: shouldResume)
+      guard shouldResume else { return }
       // The interruption deactivated our session; reclaim before resuming.
       claimAudioSession()
       triggerMediaSession("media-session-play")
@@ -626,16 +882,147 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     }
   }
 
+  // Sent ahead of the media-session-pause/-play it explains, so the reader
+  // knows the pause isn't the user's and can hold its place through it.
+  private func reportInterruption(_ phase: String, shouldResume: Bool = false) {
+    try? trigger(
+      "audio-interruption",
+      data: AudioInterruptionEvent(
+        phase: phase, ducked: false, permanent: false, shouldResume: shouldResume))
+  }
+
+  @objc public func set_interruption_mode(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(SetInterruptionModeArgs.self)
+      guard let mode = args.mode, mode == "pause" || mode == "duck" else {
+        invoke.reject("Unknown interruption mode: \(args.mode ?? "")")
+        return
+      }
+      DispatchQueue.main.async {
+        let changed = mode != self.interruptionMode
+        self.interruptionMode = mode
+        // The session is claimed while speaking; re-claim it in the new mode.
+        if changed && self.mediaSessionActive {
+          self.claimAudioSession()
+        }
+        invoke.resolve()
+      }
+    } catch {
+      invoke.reject("Failed to set interruption mode: \(error.localizedDescription)")
+    }
+  }
+
   private func handleAudioRouteChange(_ note: Notification) {
     guard let info = note.userInfo,
       let reasonRaw = info[AVAudioSessionRouteChangeReasonKey] as? UInt,
       let reason = AVAudioSession.RouteChangeReason(rawValue: reasonRaw),
-      reason == .oldDeviceUnavailable
+      reason == .newDeviceAvailable || reason == .oldDeviceUnavailable
+        || reason == .override
     else { return }
-    // Headphones unplugged / Bluetooth dropped: pause, never auto-resume —
-    // otherwise spoken audio blasts from the speaker.
-    keepAliveLog.log("route lost (oldDeviceUnavailable)")
-    triggerMediaSession("media-session-pause")
+    let lost = reason == .oldDeviceUnavailable
+    if lost {
+      // Headphones unplugged / Bluetooth dropped: pause, never auto-resume —
+      // otherwise spoken audio blasts from the speaker.
+      keepAliveLog.log("route lost (oldDeviceUnavailable)")
+      triggerMediaSession("media-session-pause")
+    }
+    try? trigger(
+      "audio-route-changed", data: AudioRouteChangedEvent(outputs: currentOutputs(), lost: lost))
+  }
+
+  // MARK: - Sleep timer
+
+  @objc public func set_sleep_timer(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(SetSleepTimerArgs.self)
+      DispatchQueue.main.async {
+        self.sleepTimer.set(
+          seconds: args.seconds, endOfChapter: args.endOfChapter ?? false,
+          shakeToExtend: args.shakeToExtend, currentChapter: self.currentChapterIndex)
+        invoke.resolve(self.sleepTimer.state)
+      }
+    } catch {
+      invoke.reject("Failed to set sleep timer: \(error.localizedDescription)")
+    }
+  }
+
+  @objc public func get_sleep_timer(_ invoke: Invoke) {
+    DispatchQueue.main.async {
+      invoke.resolve(self.sleepTimer.state)
+    }
+  }
+
+  // Speech stops here, since the WebView may be suspended; the client then
+  // ends the session.
+  private func sleepTimerFired() {
+    synthesizer.stopSpeaking(at: .immediate)
+    playoutPlayer?.pause()
+    try? trigger("sleep-timer-fired", data: sleepTimer.state)
+  }
+
+  // MARK: - Audio outputs
+
+  // Only the current route is known: iOS lists no other outputs, and the
+  // user switches between them from Control Center or the route picker.
+  private func currentOutputs() -> [AudioOutputData] {
+    AVAudioSession.sharedInstance().currentRoute.outputs.map { port in
+      AudioOutputData(
+        id: port.uid, name: port.portName, kind: outputKind(port.portType), selected: true)
+    }
+  }
+
+  private func outputKind(_ type: AVAudioSession.Port) -> String {
+    switch type {
+    case .builtInSpeaker, .builtInReceiver:
+      return "speaker"
+    case .headphones, .lineOut:
+      return "wired"
+    case .bluetoothA2DP, .bluetoothLE, .bluetoothHFP:
+      return "bluetooth"
+    case .usbAudio:
+      return "usb"
+    case .HDMI:
+      return "hdmi"
+    case .airPlay:
+      return "air-play"
+    default:
+      return "other"
+    }
+  }
+
+  @objc public func list_audio_outputs(_ invoke: Invoke) {
+    invoke.resolve(AudioOutputsResponse(outputs: currentOutputs(), selectable: false))
+  }
+
+  @objc public func set_audio_output(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(SetAudioOutputArgs.self)
+      if args.id == nil {
+        invoke.resolve()
+      } else {
+        invoke.reject("Audio output is chosen from Control Center on iOS")
+      }
+    } catch {
+      invoke.reject("Failed to set audio output: \(error.localizedDescription)")
+    }
+  }
+
+  // AVSpeechSynthesizer is the only engine on iOS.
+  @objc public func list_system_engines(_ invoke: Invoke) {
+    invoke.resolve(SystemEnginesResponse(engines: [], selectable: false))
+  }
+
+  @objc public func set_system_engine(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(SetSystemEngineArgs.self)
+      if args.name == nil {
+        invoke.resolve()
+      } else {
+        invoke.reject("iOS has a single speech engine")
+      }
+    } catch {
+      invoke.reject("Failed to set engine: \(error.localizedDescription)")
+    }
   }
 
   private func activateRemoteCommands() {
@@ -713,6 +1100,32 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
       self?.triggerMediaSession("media-session-seek-forward")
       return .success
     }
+    // Scrubber drag, enabled per section by update_media_session_state once
+    // there is a timeline to seek in. Milliseconds, as on Android.
+    center.changePlaybackPositionCommand.isEnabled = false
+    addRemoteTarget(center.changePlaybackPositionCommand) { [weak self] event in
+      guard let event = event as? MPChangePlaybackPositionCommandEvent else {
+        return .commandFailed
+      }
+      self?.trigger("media-session-seek", data: ["position": event.positionTime * 1000])
+      return .success
+    }
+    // There is no chapter command: holding the track buttons (lock screen,
+    // headset) skips chapters, as a press skips paragraphs.
+    center.seekForwardCommand.isEnabled = true
+    addRemoteTarget(center.seekForwardCommand) { [weak self] event in
+      if (event as? MPSeekCommandEvent)?.type == .beginSeeking {
+        self?.triggerMediaSession("media-session-next-chapter")
+      }
+      return .success
+    }
+    center.seekBackwardCommand.isEnabled = true
+    addRemoteTarget(center.seekBackwardCommand) { [weak self] event in
+      if (event as? MPSeekCommandEvent)?.type == .beginSeeking {
+        self?.triggerMediaSession("media-session-previous-chapter")
+      }
+      return .success
+    }
   }
 
   // Single surface (see activeInfoCenter): commands live on the shared center,
@@ -1078,6 +1491,7 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
     if playoutPlayer == nil {
       let player = AVPlayer()
       player.allowsExternalPlayback = false
+      player.volume = sleepTimer.volume
       playoutPlayer = player
     }
     bindNowPlayingSession(to: playoutPlayer!)
@@ -0,0 +1,46 @@
+// Mirror of `ios/LiveActivity/ReadAloudActivityAttributes.swift`, which the
+// ReadestWidget extension compiles to render the read-aloud Live Activity.
+// ActivityKit matches the app's and the extension's attributes by type name
+// and Codable shape, so keep both files byte-aligned below this header.
+
+import ActivityKit
+import AppIntents
+import Foundation
+
+@available(iOS 16.1, *)
+struct ReadAloudActivityAttributes: ActivityAttributes {
+  struct ContentState: Codable, Hashable {
+    var chapter: String
+    var playing: Bool
+    /// Time read aloud before the current playing stretch, in seconds.
+    var elapsed: TimeInterval
+    /// When the current playing stretch began; nil while paused. The card
+    /// counts up from `playingSince - elapsed` on its own, so the activity
+    /// is only updated on play/pause and chapter changes.
+    var playingSince: Date?
+
+    var timerStart: Date {
+      (playingSince ?? Date()).addingTimeInterval(-elapsed)
+    }
+  }
+
+  var bookTitle: String
+  var bookAuthor: String
+}
+
+/// The card's pause / resume button. Live Activity intents always run in
+/// the app process, so the widget extension never executes `perform`; the
+/// plugin observes the notification and drives read-aloud like the
+/// lock-screen play/pause command does.
+@available(iOS 17.0, *)
+struct ToggleReadAloudIntent: LiveActivityIntent {
+  static let toggleNotification = Notification.Name("ReadestReadAloudActivityToggle")
+
+  static var title: LocalizedStringResource = "Pause or Resume Read Aloud"
+  static var isDiscoverable = false
+
+  func perform() async throws -> some IntentResult {
+    NotificationCenter.default.post(name: Self.toggleNotification, object: nil)
+    return .result()
+  }
+}
@@ -0,0 +1,72 @@
+import ActivityKit
+import Foundation
+import os
+
+private let activityLog = Logger(subsystem: "com.bilingify.readest", category: "TTSLiveActivity")
+
+/// Drives the read-aloud Live Activity from the media session the JS client
+/// feeds: `set_media_session_active` starts and ends it, and
+/// `update_media_session_state` reports play/pause and the chapter. Only
+/// changes reach ActivityKit, which budgets updates.
+final class ReadAloudActivityController {
+  private var activity: Any?
+  private var chapter = ""
+  private var playing = false
+  private var elapsed: TimeInterval = 0
+  private var playingSince: Date?
+
+  var isPlaying: Bool { playing }
+
+  func start(bookTitle: String, bookAuthor: String) {
+    guard #available(iOS 16.2, *) else { return }
+    end()
+    guard ActivityAuthorizationInfo().areActivitiesEnabled else { return }
+    chapter = ""
+    playing = false
+    elapsed = 0
+    playingSince = nil
+    let attributes = ReadAloudActivityAttributes(bookTitle: bookTitle, bookAuthor: bookAuthor)
+    do {
+      activity = try Activity.request(
+        attributes: attributes, content: ActivityContent(state: contentState, staleDate: nil))
+    } catch {
+      activityLog.error("Failed to start read-aloud activity: \(error.localizedDescription)")
+    }
+  }
+
+  /// Apply a media session state push; `nil` fields keep their value.
+  func update(playing: Bool, chapter: String?) {
+    guard #available(iOS 16.2, *), let activity = activity as? Activity<ReadAloudActivityAttributes>
+    else { return }
+    let chapter = chapter ?? self.chapter
+    guard playing != self.playing || chapter != self.chapter else { return }
+    let now = Date()
+    if let since = playingSince {
+      elapsed += now.timeIntervalSince(since)
+    }
+    playingSince = playing ? now : nil
+    self.playing = playing
+    self.chapter = chapter
+    let content = ActivityContent(state: contentState, staleDate: nil)
+    Task { await activity.update(content) }
+  }
+
+  func end() {
+    guard #available(iOS 16.2, *), let activity = activity as? Activity<ReadAloudActivityAttributes>
+    else { return }
+    self.activity = nil
+    if let since = playingSince {
+      elapsed += Date().timeIntervalSince(since)
+    }
+    playing = false
+    playingSince = nil
+    let content = ActivityContent(state: contentState, staleDate: nil)
+    Task { await activity.end(content, dismissalPolicy: .immediate) }
+  }
+
+  @available(iOS 16.1, *)
+  private var contentState: ReadAloudActivityAttributes.ContentState {
+    ReadAloudActivityAttributes.ContentState(
+      chapter: chapter, playing: playing, elapsed: elapsed, playingSince: playingSince)
+  }
+}
@@ -0,0 +1,157 @@
+import CoreMotion
+import Foundation
+
+struct SetSleepTimerArgs: Decodable {
+  let seconds: Double?
+  let endOfChapter: Bool?
+  let shakeToExtend: Double?
+}
+
+struct SleepTimerState: Encodable {
+  let active: Bool
+  let remainingMs: Int?
+  let endOfChapter: Bool
+}
+
+/// The sleep timer, kept natively rather than in the WebView, whose timers
+/// stall once the app is in the background with the screen locked. Speech
+/// fades over the last ten seconds; in the last minute a shake of the device
+/// adds time, when asked for. The end of the chapter is noticed from the
+/// chapter index the media session is updated with. Main thread only.
+final class SleepTimer {
+  private static let fade: TimeInterval = 10
+  private static let shakeWindow: TimeInterval = 60
+  // Well past walking or turning over in bed.
+  private static let shakeThreshold = 2.5
+  private static let shakeDebounce: TimeInterval = 1.5
+
+  private let onVolume: (Float) -> Void
+  private let onFired: () -> Void
+  private let onChanged: (SleepTimerState) -> Void
+  private let motion = CMMotionManager()
+
+  private var deadline: Date?
+  private var endOfChapter = false
+  // The chapter being read when armed; -1 until the session reports one.
+  private var chapter = -1
+  private var shakeExtend: TimeInterval?
+  private var lastShakeAt = Date.distantPast
+  private var timer: Timer?
+
+  init(
+    onVolume: @escaping (Float) -> Void,
+    onFired: @escaping () -> Void,
+    onChanged: @escaping (SleepTimerState) -> Void
+  ) {
+    self.onVolume = onVolume
+    self.onFired = onFired
+    self.onChanged = onChanged
+  }
+
+  /// Volume for speech now: full until the fade, then falling to nothing.
+  var volume: Float {
+    guard let remaining = remaining() else { return 1 }
+    return Float(min(max(remaining / SleepTimer.fade, 0), 1))
+  }
+
+  var state: SleepTimerState {
+    SleepTimerState(
+      active: deadline != nil || endOfChapter,
+      remainingMs: remaining().map { Int(max($0, 0) * 1000) },
+      endOfChapter: endOfChapter)
+  }
+
+  private func remaining() -> TimeInterval? {
+    deadline?.timeIntervalSinceNow
+  }
+
+  func set(seconds: Double?, endOfChapter: Bool, shakeToExtend: Double?, currentChapter: Int) {
+    cancel()
+    deadline = seconds.map { Date().addingTimeInterval($0) }
+    self.endOfChapter = endOfChapter
+    chapter = currentChapter
+    shakeExtend = shakeToExtend
+    if deadline != nil {
+      let timer = Timer(timeInterval: 0.25, repeats: true) { [weak self] _ in self?.tick() }
+      RunLoop.main.add(timer, forMode: .common)
+      self.timer = timer
+    }
+  }
+
+  func cancel() {
+    timer?.invalidate()
+    timer = nil
+    let faded = volume < 1
+    deadline = nil
+    endOfChapter = false
+    chapter = -1
+    shakeExtend = nil
+    updateShakeListener()
+    if faded {
+      onVolume(1)
+    }
+  }
+
+  func chapterChanged(_ index: Int) {
+    guard endOfChapter, index >= 0 else { return }
+    if chapter < 0 {
+      chapter = index
+    } else if index != chapter {
+      fire()
+    }
+  }
+
+  private func tick() {
+    guard let remaining = remaining() else { return }
+    if remaining <= 0 {
+      fire()
+      return
+    }
+    if remaining <= SleepTimer.fade {
+      onVolume(volume)
+    }
+    updateShakeListener()
+  }
+
+  private func fire() {
+    timer?.invalidate()
+    timer = nil
+    deadline = nil
+    endOfChapter = false
+    chapter = -1
+    shakeExtend = nil
+    updateShakeListener()
+    onFired()
+    // The next session starts at full volume.
+    onVolume(1)
+  }
+
+  private func updateShakeListener() {
+    let wanted =
+      shakeExtend != nil && (remaining().map { $0 <= SleepTimer.shakeWindow } ?? false)
+    guard motion.isAccelerometerAvailable, wanted != motion.isAccelerometerActive else { return }
+    if wanted {
+      motion.accelerometerUpdateInterval = 0.1
+      motion.startAccelerometerUpdates(to: .main) { [weak self] data, _ in
+        guard let self = self, let a = data?.acceleration else { return }
+        // CoreMotion reports in g already.
+        self.accelerated((a.x * a.x + a.y * a.y + a.z * a.z).squareRoot())
+      }
+    } else {
+      motion.stopAccelerometerUpdates()
+    }
+  }
+
+  private func accelerated(_ g: Double) {
+    let now = Date()
+    guard g >= SleepTimer.shakeThreshold,
+      now.timeIntervalSince(lastShakeAt) >= SleepTimer.shakeDebounce,
+      let extend = shakeExtend, let current = deadline
+    else { return }
+    lastShakeAt = now
+    deadline = max(current, now).addingTimeInterval(extend)
+    onVolume(volume)
+    updateShakeListener()
+    onChanged(state)
+  }
+}
@@ -0,0 +1,130 @@
+//! Synthesized segments kept on disk, so re-reading a page, skipping back
+//! a sentence or opening the book again plays without a round trip.
+//!
+//! Entries are keyed by everything that changes the audio: voice, rate and
+//! text. The directory is trimmed to a size budget after each write,
+//! oldest-used first; a hit refreshes the entry's modification time. A
+//! segment's word and sentence marks sit next to its audio.
+
+use md5::{Digest, Md5};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::marks::{self, Mark};
+
+pub struct SegmentCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+pub fn key(voice: &str, rate: f32, text: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(voice.as_bytes());
+    hasher.update([0]);
+    hasher.update(rate.to_bits().to_le_bytes());
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+impl SegmentCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.mp3"))
+    }
+
+    fn marks_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.marks"))
+    }
+
+    /// A segment's audio and marks.
+    pub fn get(&self, key: &str) -> Option<(Vec<u8>, Vec<Mark>)> {
+        let path = self.path(key);
+        let data = fs::read(&path).ok()?;
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        let marks = fs::read_to_string(self.marks_path(key))
+            .map(|encoded| marks::decode(&encoded))
+            .unwrap_or_default();
+        Some((data, marks))
+    }
+
+    /// Store a segment, its audio written aside and renamed into place last
+    /// so a reader never sees half of one.
+    pub fn put(&self, key: &str, data: &[u8], marks: &[Mark]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.marks_path(key), marks::encode(marks))?;
+        let path = self.path(key);
+        let part = path.with_extension("part");
+        fs::write(&part, data)?;
+        fs::rename(&part, &path)?;
+        self.trim(&path)
+    }
+
+    /// Remove the least recently used entries until the cache fits its
+    /// budget, sparing `keep`.
+    fn trim(&self, keep: &Path) -> io::Result<()> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                metadata.is_file().then(|| {
+                    let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    (used, metadata.len(), entry.path())
+                })
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path != keep && fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn cache(name: &str, max_bytes: u64) -> SegmentCache {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        SegmentCache::new(dir, max_bytes)
+    }
+
+    #[test]
+    fn keys_change_with_voice_rate_and_text() {
+        let base = key("en-US-AriaNeural", 1.0, "Hello.");
+        assert_eq!(base, key("en-US-AriaNeural", 1.0, "Hello."));
+        assert_ne!(base, key("en-US-GuyNeural", 1.0, "Hello."));
+        assert_ne!(base, key("en-US-AriaNeural", 1.2, "Hello."));
+        assert_ne!(base, key("en-US-AriaNeural", 1.0, "Hello!"));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_past_the_budget() {
+        let cache = cache("tts-segment-cache-test", 10);
+        cache.put("a", b"aaaa", &[]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put("b", b"bbbb", &[]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get("a"), Some((b"aaaa".to_vec(), Vec::new())));
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put("c", b"cccc", &[]).unwrap();
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+    }
+}
@@ -0,0 +1,468 @@
+//! Microsoft Edge's Read Aloud service: the neural voices the web client
+//! already uses (`src/libs/edgeTTS.ts`), spoken to from Rust.
+//!
+//! A request is one WebSocket connection: a `speech.config` message picking
+//! MP3 output, an SSML message with the text, then binary frames of audio
+//! until a `turn.end` text message, with `audio.metadata` messages along
+//! the way for each word and sentence as it's reached. Connections carry a
+//! `Sec-MS-GEC` token, a hash of the client token and the time rounded down
+//! to five minutes.
+//!
+//! The WebSocket is an HTTP/1.1 upgrade made with the app's client
+//! ([`Http`]), so it goes through the same proxy as everything else.
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use super::marks::MarkKind;
+use crate::http::Http;
+use crate::models::TTSVoice;
+
+const BASE_URL: &str = "speech.platform.bing.com/consumer/speech/synthesize/readaloud";
+const TRUSTED_CLIENT_TOKEN: &str = "6A5AA1D4EAFF4E9FB37E23D68491D6F4";
+const CHROMIUM_FULL_VERSION: &str = "143.0.3650.75";
+const ORIGIN: &str = "chrome-extension://jdiccldimpdaibmpdkjnbmckianbfold";
+const OUTPUT_FORMAT: &str = "audio-24khz-48kbitrate-mono-mp3";
+/// Seconds from 1601-01-01, the Windows epoch, to the Unix one.
+const WINDOWS_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// Voice ids as `get_all_voices` lists them, so `set_voice` can tell a
+/// cloud voice from a local one.
+pub const VOICE_PREFIX: &str = "edge:";
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn sec_ms_gec(unix_secs: u64) -> String {
+    let ticks = (unix_secs + WINDOWS_EPOCH_OFFSET) / 300 * 300 * 10_000_000;
+    let digest = Sha256::digest(format!("{ticks}{TRUSTED_CLIENT_TOKEN}"));
+    format!("{digest:X}")
+}
+
+fn auth_query() -> String {
+    format!(
+        "TrustedClientToken={TRUSTED_CLIENT_TOKEN}&Sec-MS-GEC={}&Sec-MS-GEC-Version=1-{CHROMIUM_FULL_VERSION}",
+        sec_ms_gec(unix_secs())
+    )
+}
+
+/// 32 hex digits that differ per call, for connection and request ids.
+fn random_hex() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let seed = format!(
+        "{nanos}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let digest = Sha256::digest(seed);
+    format!("{:x}", digest)[..32].to_string()
+}
+
+fn user_agent() -> String {
+    let major = CHROMIUM_FULL_VERSION.split('.').next().unwrap_or_default();
+    format!(
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+         Chrome/{major}.0.0.0 Safari/537.36 Edg/{major}.0.0.0"
+    )
+}
+
+/// `Thu Oct 15 2026 09:30:00 GMT+0000 (Coordinated Universal Time)`, the
+/// JavaScript `Date` string the service expects in `X-Timestamp`.
+fn timestamp(unix_secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = unix_secs / 86_400;
+    let secs = unix_secs % 86_400;
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{} {} {day:02} {year} {:02}:{:02}:{:02} GMT+0000 (Coordinated Universal Time)",
+        DAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// The locale a voice name starts with, `en-US` for `en-US-AriaNeural`.
+pub fn voice_lang(voice: &str) -> &str {
+    let mut dashes = voice.match_indices('-').map(|(i, _)| i);
+    match (dashes.next(), dashes.next()) {
+        (Some(_), Some(end)) => &voice[..end],
+        _ => voice,
+    }
+}
+
+/// `markup` is the escaped body from
+/// [`Utterance::markup`](crate::ssml::Utterance::markup).
+fn ssml(voice: &str, rate: f32, markup: &str) -> String {
+    format!(
+        "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xml:lang=\"{}\">\
+         <voice name=\"{voice}\"><prosody rate=\"{rate}\">{markup}</prosody></voice></speak>",
+        voice_lang(voice),
+    )
+}
+
+fn config_message(timestamp: &str) -> String {
+    format!(
+        "X-Timestamp:{timestamp}\r\nContent-Type:application/json; charset=utf-8\r\nPath:speech.config\r\n\r\n\
+         {{\"context\":{{\"synthesis\":{{\"audio\":{{\"metadataoptions\":{{\
+         \"sentenceBoundaryEnabled\":true,\"wordBoundaryEnabled\":true}},\
+         \"outputFormat\":\"{OUTPUT_FORMAT}\"}}}}}}}}"
+    )
+}
+
+fn ssml_message(request_id: &str, timestamp: &str, ssml: &str) -> String {
+    format!(
+        "X-RequestId:{request_id}\r\nContent-Type:application/ssml+xml\r\nX-Timestamp:{timestamp}Z\r\nPath:ssml\r\n\r\n{ssml}"
+    )
+}
+
+/// The `Path` header of a text or binary frame's header block.
+fn path(headers: &str) -> Option<&str> {
+    headers
+        .lines()
+        .find_map(|line| line.strip_prefix("Path:"))
+        .map(str::trim)
+}
+
+/// The audio in a binary frame: a big-endian header length, the headers,
+/// then the MP3 bytes. `None` for frames that aren't audio.
+fn audio_payload(frame: &[u8]) -> Option<&[u8]> {
+    let len = usize::from(u16::from_be_bytes([*frame.first()?, *frame.get(1)?]));
+    let headers = std::str::from_utf8(frame.get(2..2 + len)?).ok()?;
+    (path(headers) == Some("audio")).then(|| &frame[2 + len..])
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    #[serde(rename = "Metadata")]
+    items: Vec<MetadataItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataItem {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Data")]
+    data: Option<MetadataData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataData {
+    /// In 100 ns ticks from the start of the audio.
+    #[serde(rename = "Offset", default)]
+    offset: u64,
+    #[serde(default)]
+    text: MetadataText,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MetadataText {
+    #[serde(rename = "Text", default)]
+    text: String,
+}
+
+/// What arrives while a segment streams.
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    Audio(&'a [u8]),
+    /// A word or sentence starting `at_ms` into the audio.
+    Boundary {
+        kind: MarkKind,
+        at_ms: u64,
+        text: String,
+    },
+}
+
+/// The boundaries in the body of an `audio.metadata` message.
+fn boundaries(body: &str) -> Vec<Event<'static>> {
+    let Ok(metadata) = serde_json::from_str::<Metadata>(body) else {
+        return Vec::new();
+    };
+    metadata
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            let kind = match item.kind.as_str() {
+                "WordBoundary" => MarkKind::Word,
+                "SentenceBoundary" => MarkKind::Sentence,
+                _ => return None,
+            };
+            let data = item.data?;
+            Some(Event::Boundary {
+                kind,
+                at_ms: data.offset / 10_000,
+                text: data.text.text,
+            })
+        })
+        .collect()
+}
+
+/// Open the WebSocket: a `GET` asking to upgrade, answered with `101` and
+/// the accept key derived from ours.
+async fn connect(http: &Http) -> Result<WebSocketStream<reqwest::Upgraded>, String> {
+    let url = format!(
+        "https://{BASE_URL}/edge/v1?{}&ConnectionId={}",
+        auth_query(),
+        random_hex()
+    );
+    let key = generate_key();
+    let response = http
+        .upgrade_client()
+        .map_err(|e| e.to_string())?
+        .get(url)
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", &key)
+        .header("Pragma", "no-cache")
+        .header("Cache-Control", "no-cache")
+        .header("Origin", ORIGIN)
+        .header("User-Agent", user_agent())
+        .header(
+            "Cookie",
+            format!("muid={};", random_hex().to_ascii_uppercase()),
+        )
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let accept = response.headers().get("Sec-WebSocket-Accept");
+    if accept.map(|value| value.as_bytes()) != Some(derive_accept_key(key.as_bytes()).as_bytes()) {
+        return Err("bad Sec-WebSocket-Accept".to_string());
+    }
+    let upgraded = response.upgrade().await.map_err(|e| e.to_string())?;
+    Ok(WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await)
+}
+
+/// Speak `markup` with Edge voice `voice` (`en-US-AriaNeural`), handing
+/// MP3 chunks and word and sentence boundaries to `sink` as they arrive.
+/// Stops early, successfully, when `sink` returns false.
+pub async fn synthesize(
+    http: &Http,
+    voice: &str,
+    rate: f32,
+    markup: &str,
+    mut sink: impl FnMut(Event<'_>) -> bool,
+) -> Result<(), String> {
+    let mut socket = connect(http)
+        .await
+        .map_err(|e| format!("connect to Edge TTS: {e}"))?;
+    let now = timestamp(unix_secs());
+    socket
+        .send(Message::Text(config_message(&now)))
+        .await
+        .map_err(|e| e.to_string())?;
+    socket
+        .send(Message::Text(ssml_message(
+            &random_hex(),
+            &now,
+            &ssml(voice, rate, markup),
+        )))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut received = false;
+    while let Some(message) = socket.next().await {
+        match message.map_err(|e| format!("Edge TTS: {e}"))? {
+            Message::Binary(frame) => {
+                if let Some(audio) = audio_payload(&frame).filter(|a| !a.is_empty()) {
+                    received = true;
+                    if !sink(Event::Audio(audio)) {
+                        break;
+                    }
+                }
+            }
+            Message::Text(text) => {
+                let (headers, body) = text.split_once("\r\n\r\n").unwrap_or((text.as_str(), ""));
+                match path(headers) {
+                    Some("turn.end") => break,
+                    Some("audio.metadata") => {
+                        if !boundaries(body).into_iter().all(&mut sink) {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    let _ = socket.close(None).await;
+    if received {
+        Ok(())
+    } else {
+        Err(format!("Edge TTS returned no audio for {voice}"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EdgeVoice {
+    pub short_name: String,
+    pub locale: String,
+    #[serde(default)]
+    pub status: String,
+}
+
+pub fn voices_url() -> String {
+    format!("https://{BASE_URL}/voices/list?{}", auth_query())
+}
+
+pub fn parse_voices(json: &[u8]) -> Result<Vec<EdgeVoice>, String> {
+    serde_json::from_slice(json).map_err(|e| format!("parse Edge voice list: {e}"))
+}
+
+/// Edge voices in the shape `get_all_voices` lists them, named as the web
+/// client names them: `Aria` for `en-US-AriaNeural`.
+pub fn tts_voices(voices: &[EdgeVoice]) -> Vec<TTSVoice> {
+    voices
+        .iter()
+        .filter(|voice| voice.status != "Deprecated")
+        .map(|voice| {
+            let name = voice
+                .short_name
+                .strip_prefix(&format!("{}-", voice.locale))
+                .unwrap_or(&voice.short_name)
+                .replace("Neural", "");
+            TTSVoice {
+                id: format!("{VOICE_PREFIX}{}", voice.short_name),
+                name: format!("{name} (Edge)"),
+                lang: voice.locale.clone(),
+                disabled: false,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssml::Utterance;
+
+    #[test]
+    fn signs_with_the_five_minute_window() {
+        let gec = sec_ms_gec(1_760_000_000);
+        assert_eq!(gec.len(), 64);
+        assert!(gec
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase()));
+        assert_eq!(gec, sec_ms_gec(1_760_000_000 - 1_760_000_000 % 300 + 299));
+        assert_ne!(gec, sec_ms_gec(1_760_000_000 + 300));
+    }
+
+    #[test]
+    fn formats_timestamps_like_javascript_dates() {
+        assert_eq!(
+            timestamp(0),
+            "Thu Jan 01 1970 00:00:00 GMT+0000 (Coordinated Universal Time)"
+        );
+        assert_eq!(
+            timestamp(1_792_056_600),
+            "Thu Oct 15 2026 09:30:00 GMT+0000 (Coordinated Universal Time)"
+        );
+        assert_eq!(
+            timestamp(951_782_400),
+            "Tue Feb 29 2000 00:00:00 GMT+0000 (Coordinated Universal Time)"
+        );
+    }
+
+    #[test]
+    fn escapes_text_into_ssml() {
+        let utterance = Utterance::plain("<Tom & \"Jerry\">");
+        assert_eq!(
+            ssml("fr-FR-DeniseNeural", 1.2, utterance.markup()),
+            "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xml:lang=\"fr-FR\">\
+             <voice name=\"fr-FR-DeniseNeural\"><prosody rate=\"1.2\">\
+             &lt;Tom &amp; &quot;Jerry&quot;&gt;</prosody></voice></speak>"
+        );
+        assert_eq!(voice_lang("zh-CN-liaoning-XiaobeiNeural"), "zh-CN");
+    }
+
+    #[test]
+    fn extracts_audio_from_binary_frames() {
+        let headers = b"X-RequestId:abc\r\nContent-Type:audio/mpeg\r\nPath:audio\r\n";
+        let mut frame = (headers.len() as u16).to_be_bytes().to_vec();
+        frame.extend_from_slice(headers);
+        frame.extend_from_slice(&[0xff, 0xf3]);
+        assert_eq!(audio_payload(&frame), Some(&[0xff, 0xf3][..]));
+
+        let mut other = 4u16.to_be_bytes().to_vec();
+        other.extend_from_slice(b"Path");
+        assert_eq!(audio_payload(&other), None);
+        assert_eq!(audio_payload(&[0, 9, 1]), None);
+    }
+
+    #[test]
+    fn reads_word_and_sentence_boundaries() {
+        let body = r#"{"Metadata": [
+            {"Type": "SentenceBoundary", "Data": {"Offset": 1000000, "Duration": 8750000,
+             "text": {"Text": "Hello there.", "Length": 12, "BoundaryType": "SentenceBoundary"}}},
+            {"Type": "WordBoundary", "Data": {"Offset": 1000000, "Duration": 3125000,
+             "text": {"Text": "Hello", "Length": 5, "BoundaryType": "WordBoundary"}}},
+            {"Type": "SessionEnd", "Data": {"Offset": 0}}
+        ]}"#;
+        assert_eq!(
+            boundaries(body),
+            [
+                Event::Boundary {
+                    kind: MarkKind::Sentence,
+                    at_ms: 100,
+                    text: "Hello there.".to_string()
+                },
+                Event::Boundary {
+                    kind: MarkKind::Word,
+                    at_ms: 100,
+                    text: "Hello".to_string()
+                },
+            ]
+        );
+        assert!(boundaries("not json").is_empty());
+    }
+
+    #[test]
+    fn lists_voices_by_their_short_names() {
+        let json = br#"[
+            {"Name": "Microsoft Server Speech Text to Speech Voice (en-US, AriaNeural)",
+             "ShortName": "en-US-AriaNeural", "Gender": "Female", "Locale": "en-US",
+             "Status": "GA"},
+            {"ShortName": "en-US-OldNeural", "Locale": "en-US", "Status": "Deprecated"}
+        ]"#;
+        let voices = tts_voices(&parse_voices(json).unwrap());
+        assert_eq!(voices.len(), 1);
+        assert_eq!(voices[0].id, "edge:en-US-AriaNeural");
+        assert_eq!(voices[0].name, "Aria (Edge)");
+        assert_eq!(voices[0].lang, "en-US");
+    }
+}
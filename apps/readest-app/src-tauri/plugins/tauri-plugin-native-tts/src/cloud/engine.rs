@@ -0,0 +1,518 @@
+use rodio::Decoder;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime};
+
+use super::cache::{self, SegmentCache};
+use super::edge::{self, Event, VOICE_PREFIX};
+use super::marks::{Locator, Mark};
+use super::stream::StreamBuffer;
+use super::{CACHE_DIR, CACHE_MAX_BYTES, VOICE_LIST_FILENAME};
+use crate::http::Http;
+use crate::models::*;
+use crate::player::{Player, PlayerSink};
+use crate::ssml::Utterance;
+use crate::{Error, NativeTtsExt, Result};
+
+/// How often playback is checked for the next word or sentence mark.
+const MARK_POLL: Duration = Duration::from_millis(20);
+
+fn tts_error(e: impl Display) -> Error {
+    Error::NativeTTSError(e.to_string())
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "cloud-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The primary language subtag, lowercased: `pt` for `pt-BR` or `pt_PT`.
+fn primary_lang(lang: &str) -> String {
+    lang.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// What `get_all_voices` lists: the local voices, then the cloud voices
+/// `mode` lets in. In fallback mode those are the ones for languages no
+/// usable local voice speaks, so a client that picks the first voice
+/// matching the book lands on a local one whenever there is one.
+fn offered(local: Vec<TTSVoice>, cloud: Vec<TTSVoice>, mode: CloudVoices) -> Vec<TTSVoice> {
+    let covered: Vec<String> = local
+        .iter()
+        .filter(|voice| !voice.disabled)
+        .map(|voice| primary_lang(&voice.lang))
+        .collect();
+    let cloud = cloud.into_iter().filter(|voice| match mode {
+        CloudVoices::Off => false,
+        CloudVoices::Fallback => !covered.contains(&primary_lang(&voice.lang)),
+        CloudVoices::All => true,
+    });
+    local.into_iter().chain(cloud).collect()
+}
+
+struct Settings {
+    mode: CloudVoices,
+    /// The Edge voice picked with `set_voice`, without its prefix; while
+    /// there is one, playback goes to the cloud.
+    voice: Option<String>,
+    rate: f32,
+}
+
+struct Playing {
+    sink: Arc<PlayerSink>,
+    audio: Arc<StreamBuffer>,
+}
+
+/// Edge neural voices behind the plugin's playback commands.
+pub struct Cloud<R: Runtime> {
+    app: AppHandle<R>,
+    settings: Mutex<Settings>,
+    playing: Mutex<Option<Playing>>,
+    /// Bumped by `stop` and every `speak`; shared with the fetch tasks so a
+    /// superseded request stops streaming.
+    generation: Arc<AtomicU64>,
+    /// The voice list, fetched once per run.
+    voices: Mutex<Option<Vec<TTSVoice>>>,
+    http: Http,
+}
+
+impl<R: Runtime> Cloud<R> {
+    pub fn new(app: &AppHandle<R>, http: Http) -> Self {
+        Self {
+            app: app.clone(),
+            settings: Mutex::new(Settings {
+                mode: CloudVoices::Off,
+                voice: None,
+                rate: 1.0,
+            }),
+            playing: Mutex::new(None),
+            generation: Arc::new(AtomicU64::new(0)),
+            voices: Mutex::new(None),
+            http,
+        }
+    }
+
+    fn cache_dir(&self) -> Result<PathBuf> {
+        Ok(self
+            .app
+            .path()
+            .app_cache_dir()
+            .map_err(tts_error)?
+            .join(CACHE_DIR))
+    }
+
+    fn segments(&self) -> Result<SegmentCache> {
+        Ok(SegmentCache::new(
+            self.cache_dir()?.join("segments"),
+            CACHE_MAX_BYTES,
+        ))
+    }
+
+    pub fn set_mode(&self, mode: CloudVoices) -> Result<()> {
+        let deselected = {
+            let mut settings = self.settings.lock().unwrap();
+            settings.mode = mode;
+            mode == CloudVoices::Off && settings.voice.take().is_some()
+        };
+        if deselected {
+            self.interrupt();
+        }
+        Ok(())
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.settings.lock().unwrap().voice.is_some()
+    }
+
+    /// Take `voice` if it's a cloud voice, returning whether it was; a
+    /// local voice hands playback back to the local engine.
+    pub fn select_voice(&self, voice: &str) -> Result<bool> {
+        let mut settings = self.settings.lock().unwrap();
+        match voice.strip_prefix(VOICE_PREFIX) {
+            Some(_) if settings.mode == CloudVoices::Off => {
+                Err(tts_error("cloud voices are turned off"))
+            }
+            Some(name) => {
+                settings.voice = Some(name.to_string());
+                Ok(true)
+            }
+            None => {
+                if settings.voice.take().is_some() {
+                    drop(settings);
+                    self.interrupt();
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    pub fn init(&self) -> Result<InitResponse> {
+        Ok(InitResponse { success: true })
+    }
+
+    fn emit_tts_event(&self, utterance_id: &str, code: &str, message: Option<String>) {
+        let payload = TTSEventPayload::new(utterance_id, code, message);
+        if let Err(e) = self.app.native_tts().emit_tts_event(payload) {
+            log::warn!("cloud TTS event {code} for {utterance_id}: {e}");
+        }
+    }
+
+    /// Stream a segment from the service into a buffer, and its marks into
+    /// a list, caching both once the segment is complete. Gives up when
+    /// `generation` moves on.
+    fn fetch(
+        &self,
+        generation: u64,
+        key: String,
+        voice: String,
+        rate: f32,
+        utterance: Utterance,
+    ) -> Result<(Arc<StreamBuffer>, Arc<Mutex<Vec<Mark>>>)> {
+        let segments = self.segments()?;
+        let audio = StreamBuffer::new();
+        let marks = Arc::new(Mutex::new(Vec::new()));
+        let (buffer, found) = (audio.clone(), marks.clone());
+        let current = self.generation.clone();
+        let http = self.http.clone();
+        tauri::async_runtime::spawn(async move {
+            let live = || current.load(Ordering::SeqCst) == generation;
+            let mut locator = Locator::new(utterance.text());
+            let result = edge::synthesize(&http, &voice, rate, utterance.markup(), |event| {
+                match event {
+                    Event::Audio(chunk) => buffer.push(chunk),
+                    Event::Boundary { kind, at_ms, text } => {
+                        if let Some(mark) = locator.locate(kind, at_ms, &text) {
+                            found.lock().unwrap().push(mark);
+                        }
+                    }
+                }
+                live()
+            })
+            .await;
+            match result {
+                Ok(()) if live() => {
+                    let data = buffer.finish();
+                    let marks = found.lock().unwrap().clone();
+                    if let Err(e) = segments.put(&key, &data, &marks) {
+                        log::warn!("cache cloud TTS segment: {e}");
+                    }
+                }
+                Ok(()) => buffer.fail("superseded".to_string()),
+                Err(e) => buffer.fail(e),
+            }
+        });
+        Ok((audio, marks))
+    }
+
+    /// Play one utterance from the cache or the network, returning once it
+    /// has played out or been superseded. Words and sentences are announced
+    /// as `boundary` events when playback reaches them.
+    fn play(
+        &self,
+        generation: u64,
+        utterance_id: &str,
+        utterance: &Utterance,
+        voice: &str,
+        rate: f32,
+    ) -> Result<bool> {
+        let key = cache::key(voice, rate, utterance.markup());
+        let (audio, marks) = match self.segments()?.get(&key) {
+            Some((data, marks)) => (StreamBuffer::complete(data), Arc::new(Mutex::new(marks))),
+            None => self.fetch(generation, key, voice.to_string(), rate, utterance.clone())?,
+        };
+        let sink = Player::shared()
+            .and_then(|player| player.sink())
+            .map_err(tts_error)?;
+        {
+            let mut playing = self.playing.lock().unwrap();
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return Ok(false);
+            }
+            *playing = Some(Playing {
+                sink: sink.clone(),
+                audio: audio.clone(),
+            });
+        }
+        let current = || self.generation.load(Ordering::SeqCst) == generation;
+        // Probing blocks until the first frames are in; the rest decodes
+        // as it arrives.
+        let decoder = match Decoder::new_mp3(audio.reader()) {
+            Ok(decoder) => decoder,
+            Err(_) if !current() => return Ok(false),
+            Err(e) => return Err(tts_error(audio.error().unwrap_or_else(|| e.to_string()))),
+        };
+        sink.append(decoder);
+        let mut next = 0;
+        while !sink.empty() && current() {
+            let position = sink.get_pos().as_millis() as u64;
+            let due: Vec<Mark> = {
+                let marks = marks.lock().unwrap();
+                let count = marks[next..]
+                    .iter()
+                    .take_while(|mark| mark.at_ms <= position)
+                    .count();
+                next += count;
+                marks[next - count..next].to_vec()
+            };
+            for mark in due {
+                let event = TTSEventPayload::range(
+                    utterance_id,
+                    mark.kind.as_str(),
+                    utterance.text(),
+                    mark.start,
+                    mark.end,
+                );
+                if let Err(e) = self.app.native_tts().emit_tts_event(event) {
+                    log::warn!("cloud TTS boundary for {utterance_id}: {e}");
+                }
+            }
+            std::thread::sleep(MARK_POLL);
+        }
+        sink.sleep_until_end();
+        match audio.error() {
+            Some(e) if current() => Err(tts_error(e)),
+            _ => Ok(current()),
+        }
+    }
+
+    /// Fetch a segment into the cache ahead of its `speak`, without
+    /// touching what's playing.
+    fn preload(&self, utterance: Utterance, voice: String, rate: f32) -> Result<()> {
+        let segments = self.segments()?;
+        let key = cache::key(&voice, rate, utterance.markup());
+        if segments.get(&key).is_some() {
+            return Ok(());
+        }
+        let http = self.http.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut data = Vec::new();
+            let mut marks = Vec::new();
+            let mut locator = Locator::new(utterance.text());
+            let result = edge::synthesize(&http, &voice, rate, utterance.markup(), |event| {
+                match event {
+                    Event::Audio(chunk) => data.extend_from_slice(chunk),
+                    Event::Boundary { kind, at_ms, text } => {
+                        marks.extend(locator.locate(kind, at_ms, &text));
+                    }
+                }
+                true
+            })
+            .await;
+            if let Err(e) =
+                result.and_then(|()| segments.put(&key, &data, &marks).map_err(|e| e.to_string()))
+            {
+                log::warn!("preload cloud TTS segment: {e}");
+            }
+        });
+        Ok(())
+    }
+
+    /// Start speaking `text`, plain or SSML, in the background, reporting
+    /// through `tts_events` like the other engines.
+    pub fn speak(&self, args: SpeakArgs) -> Result<SpeakResponse> {
+        let utterance_id = new_id();
+        let (voice, rate) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.voice.clone(), settings.rate)
+        };
+        let voice = voice.ok_or_else(|| tts_error("no cloud voice selected"))?;
+        let utterance = Utterance::new(&args.text, args.ssml);
+        if args.preload || utterance.is_silent() {
+            if !utterance.is_silent() {
+                self.preload(utterance, voice, rate)?;
+            }
+            self.emit_tts_event(&utterance_id, "end", None);
+            return Ok(SpeakResponse { utterance_id });
+        }
+
+        let generation = self.interrupt();
+        let app = self.app.clone();
+        let id = utterance_id.clone();
+        std::thread::Builder::new()
+            .name("cloud-speak".into())
+            .spawn(move || {
+                let cloud = app.state::<Cloud<R>>();
+                cloud.emit_tts_event(&id, "boundary", Some("start".to_string()));
+                match cloud.play(generation, &id, &utterance, &voice, rate) {
+                    Ok(true) => cloud.emit_tts_event(&id, "end", None),
+                    Ok(false) => {}
+                    Err(e) => cloud.emit_tts_event(&id, "error", Some(e.to_string())),
+                }
+            })
+            .map_err(tts_error)?;
+        Ok(SpeakResponse { utterance_id })
+    }
+
+    /// Supersede whatever is playing; returns the new generation.
+    fn interrupt(&self) -> u64 {
+        let mut playing = self.playing.lock().unwrap();
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(playing) = playing.take() {
+            // Wake a decoder waiting on the network before stopping it.
+            playing.audio.fail("stopped".to_string());
+            playing.sink.stop();
+        }
+        generation
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        if let Some(playing) = self.playing.lock().unwrap().as_ref() {
+            playing.sink.pause();
+        }
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        if let Some(playing) = self.playing.lock().unwrap().as_ref() {
+            playing.sink.play();
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.interrupt();
+        Ok(())
+    }
+
+    /// Kept even while a local voice is reading, so switching to a cloud
+    /// voice keeps the pace. Takes effect from the next utterance.
+    pub fn set_rate(&self, args: &SetRateArgs) -> Result<()> {
+        self.settings.lock().unwrap().rate = args.rate;
+        Ok(())
+    }
+
+    /// The service's voices have a fixed pitch here; accepted so the client
+    /// needn't care which engine it's driving.
+    pub fn set_pitch(&self, _args: SetPitchArgs) -> Result<()> {
+        Ok(())
+    }
+
+    async fn voices(&self) -> Result<Vec<TTSVoice>> {
+        if let Some(voices) = self.voices.lock().unwrap().clone() {
+            return Ok(voices);
+        }
+        let cached = self.cache_dir()?.join(VOICE_LIST_FILENAME);
+        let fetched = async {
+            let json = self
+                .http
+                .client()
+                .map_err(tts_error)?
+                .get(edge::voices_url())
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(tts_error)?
+                .bytes()
+                .await
+                .map_err(tts_error)?;
+            let voices = edge::parse_voices(&json).map_err(tts_error)?;
+            if let Some(dir) = cached.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&cached, &json)?;
+            Ok::<_, Error>(voices)
+        };
+        let voices = match fetched.await {
+            Ok(voices) => voices,
+            Err(e) => {
+                log::warn!("fetch Edge voice list: {e}");
+                let json = std::fs::read(&cached).map_err(|_| e)?;
+                edge::parse_voices(&json).map_err(tts_error)?
+            }
+        };
+        let voices = edge::tts_voices(&voices);
+        *self.voices.lock().unwrap() = Some(voices.clone());
+        Ok(voices)
+    }
+
+    /// The local engine's voices plus the cloud voices the mode offers.
+    /// Without a network, and without a voice list from an earlier run,
+    /// that's just the local ones.
+    pub async fn with_voices(&self, local: Result<GetVoicesResponse>) -> Result<GetVoicesResponse> {
+        let mode = self.settings.lock().unwrap().mode;
+        if mode == CloudVoices::Off {
+            return local;
+        }
+        let cloud = match self.voices().await {
+            Ok(voices) => voices,
+            Err(e) => {
+                log::warn!("no cloud voices: {e}");
+                return local;
+            }
+        };
+        let local = match local {
+            Ok(response) => response.voices,
+            Err(e) => {
+                log::warn!("no local voices: {e}");
+                Vec::new()
+            }
+        };
+        Ok(GetVoicesResponse {
+            voices: offered(local, cloud, mode),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(id: &str, lang: &str, disabled: bool) -> TTSVoice {
+        TTSVoice {
+            id: id.to_string(),
+            name: id.to_string(),
+            lang: lang.to_string(),
+            disabled,
+        }
+    }
+
+    fn ids(voices: &[TTSVoice]) -> Vec<&str> {
+        voices.iter().map(|v| v.id.as_str()).collect()
+    }
+
+    #[test]
+    fn falls_back_only_for_languages_without_a_usable_local_voice() {
+        let local = vec![
+            voice("en_US-amy", "en_US", false),
+            voice("ja-JP-network", "ja-JP", true),
+        ];
+        let cloud = vec![
+            voice("edge:en-GB-SoniaNeural", "en-GB", false),
+            voice("edge:ja-JP-NanamiNeural", "ja-JP", false),
+            voice("edge:ta-IN-PallaviNeural", "ta-IN", false),
+        ];
+        assert_eq!(
+            ids(&offered(
+                local.clone(),
+                cloud.clone(),
+                CloudVoices::Fallback
+            )),
+            [
+                "en_US-amy",
+                "ja-JP-network",
+                "edge:ja-JP-NanamiNeural",
+                "edge:ta-IN-PallaviNeural"
+            ]
+        );
+        assert_eq!(
+            offered(local.clone(), cloud.clone(), CloudVoices::All).len(),
+            5
+        );
+        assert_eq!(
+            ids(&offered(local, cloud, CloudVoices::Off)),
+            ["en_US-amy", "ja-JP-network"]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Word and sentence boundaries of a cloud segment: when each is spoken and
+//! where it is in the text that was sent.
+//!
+//! The service reports the words it speaks, not their offsets, so each one
+//! is found in the text after the previous one. Cached segments keep their
+//! marks next to the audio, one per line.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkKind {
+    Word,
+    Sentence,
+}
+
+impl MarkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Word => "word",
+            Self::Sentence => "sentence",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+    pub kind: MarkKind,
+    /// From the start of the segment's audio.
+    pub at_ms: u64,
+    /// Byte range in the segment's text.
+    pub start: usize,
+    pub end: usize,
+}
+
+pub fn encode(marks: &[Mark]) -> String {
+    marks
+        .iter()
+        .map(|mark| {
+            let kind = match mark.kind {
+                MarkKind::Word => 'w',
+                MarkKind::Sentence => 's',
+            };
+            format!("{kind} {} {} {}\n", mark.at_ms, mark.start, mark.end)
+        })
+        .collect()
+}
+
+/// Marks from [`encode`]; lines that don't parse are skipped.
+pub fn decode(encoded: &str) -> Vec<Mark> {
+    encoded
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let kind = match fields.next()? {
+                "w" => MarkKind::Word,
+                "s" => MarkKind::Sentence,
+                _ => return None,
+            };
+            let mut number = || fields.next()?.parse().ok();
+            let (at_ms, start, end) = (number()?, number()?, number()?);
+            Some(Mark {
+                kind,
+                at_ms: at_ms as u64,
+                start,
+                end,
+            })
+        })
+        .collect()
+}
+
+/// Places reported words and sentences in the text, in order.
+pub struct Locator<'a> {
+    text: &'a str,
+    word_from: usize,
+    sentence_from: usize,
+}
+
+impl<'a> Locator<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            word_from: 0,
+            sentence_from: 0,
+        }
+    }
+
+    /// The mark for `spoken`, the next `kind` the service reported at
+    /// `at_ms`; `None` when it isn't in the rest of the text, e.g. a
+    /// number the service spelled out.
+    pub fn locate(&mut self, kind: MarkKind, at_ms: u64, spoken: &str) -> Option<Mark> {
+        let spoken = spoken.trim();
+        if spoken.is_empty() {
+            return None;
+        }
+        let from = match kind {
+            MarkKind::Word => &mut self.word_from,
+            MarkKind::Sentence => &mut self.sentence_from,
+        };
+        let start = *from + self.text.get(*from..)?.find(spoken)?;
+        let end = start + spoken.len();
+        *from = end;
+        Some(Mark {
+            kind,
+            at_ms,
+            start,
+            end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_repeated_words_in_order() {
+        let text = "The cat saw the other cat.";
+        let mut locator = Locator::new(text);
+        let cats: Vec<usize> = [0, 400]
+            .into_iter()
+            .filter_map(|at_ms| locator.locate(MarkKind::Word, at_ms, "cat"))
+            .map(|mark| mark.start)
+            .collect();
+        assert_eq!(cats, [4, 22]);
+        assert_eq!(locator.locate(MarkKind::Word, 500, "dog"), None);
+        let sentence = locator.locate(MarkKind::Sentence, 0, text).unwrap();
+        assert_eq!((sentence.start, sentence.end), (0, text.len()));
+    }
+
+    #[test]
+    fn round_trips_through_the_cache_format() {
+        let marks = vec![
+            Mark {
+                kind: MarkKind::Sentence,
+                at_ms: 100,
+                start: 0,
+                end: 12,
+            },
+            Mark {
+                kind: MarkKind::Word,
+                at_ms: 100,
+                start: 0,
+                end: 5,
+            },
+        ];
+        assert_eq!(decode(&encode(&marks)), marks);
+        assert_eq!(decode("w 1 2\nx 1 2 3\n"), []);
+    }
+}
@@ -0,0 +1,47 @@
+//! Cloud neural voices, behind the `cloud` feature.
+//!
+//! Microsoft Edge's Read Aloud voices ([`edge`]) cover well over a hundred
+//! locales, which fills the gap when a book is in a language neither the
+//! system nor an installed Piper voice speaks. Audio streams in over a
+//! WebSocket and plays as it arrives ([`stream`]); finished segments are
+//! kept in `tts-cloud/` under the app cache dir ([`cache`]), so replays and
+//! `preload`ed sentences need no network. The service reports each word
+//! and sentence it speaks; they're found in the text ([`marks`]) and
+//! raised as `boundary` events as playback reaches them.
+//!
+//! `set_cloud_voices` decides whether `get_all_voices` offers them: not at
+//! all, only for languages without a usable local voice, or always. Cloud
+//! voice ids start with `edge:`; picking one with `set_voice` routes the
+//! playback commands here until a local voice is picked again, so the
+//! fallback is just the client choosing the best voice for the language,
+//! as it already does.
+//!
+//! Builds without the feature get [`Cloud`] from `unavailable.rs`, which
+//! never offers a cloud voice.
+
+#[cfg(feature = "cloud")]
+mod cache;
+#[cfg(feature = "cloud")]
+mod edge;
+#[cfg(feature = "cloud")]
+mod engine;
+#[cfg(feature = "cloud")]
+mod marks;
+#[cfg(feature = "cloud")]
+mod stream;
+#[cfg(not(feature = "cloud"))]
+mod unavailable;
+
+#[cfg(feature = "cloud")]
+pub use engine::Cloud;
+#[cfg(not(feature = "cloud"))]
+pub use unavailable::Cloud;
+
+#[cfg(feature = "cloud")]
+const CACHE_DIR: &str = "tts-cloud";
+#[cfg(feature = "cloud")]
+const VOICE_LIST_FILENAME: &str = "edge-voices.json";
+/// Budget for cached segments: about eight hours of speech at the 48 kbps
+/// the service sends.
+#[cfg(feature = "cloud")]
+const CACHE_MAX_BYTES: u64 = 160 * 1024 * 1024;
@@ -0,0 +1,164 @@
+//! A byte buffer filled by the network and read by the decoder at the same
+//! time, so playback starts with the first chunk of audio rather than the
+//! last.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct State {
+    data: Vec<u8>,
+    done: bool,
+    error: Option<String>,
+}
+
+/// The writing end, shared with any number of [`StreamReader`]s.
+#[derive(Default)]
+pub struct StreamBuffer {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl StreamBuffer {
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    /// A buffer that already holds all of its bytes, e.g. from the cache.
+    pub fn complete(data: Vec<u8>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                data,
+                done: true,
+                error: None,
+            }),
+            changed: Condvar::new(),
+        })
+    }
+
+    pub fn push(&self, chunk: &[u8]) {
+        self.state.lock().unwrap().data.extend_from_slice(chunk);
+        self.changed.notify_all();
+    }
+
+    /// No more bytes are coming; returns everything that was pushed.
+    pub fn finish(&self) -> Vec<u8> {
+        let mut state = self.state.lock().unwrap();
+        state.done = true;
+        self.changed.notify_all();
+        state.data.clone()
+    }
+
+    /// End the stream with an error, which readers see once they've read
+    /// what arrived before it.
+    pub fn fail(&self, error: String) {
+        let mut state = self.state.lock().unwrap();
+        state.done = true;
+        state.error = Some(error);
+        self.changed.notify_all();
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.state.lock().unwrap().error.clone()
+    }
+
+    pub fn reader(self: &Arc<Self>) -> StreamReader {
+        StreamReader {
+            buffer: self.clone(),
+            pos: 0,
+        }
+    }
+}
+
+/// Reads block until the bytes asked for have arrived or the stream has
+/// ended. Seeking to the end waits for the end.
+pub struct StreamReader {
+    buffer: Arc<StreamBuffer>,
+    pos: u64,
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let state = self.buffer.state.lock().unwrap();
+        let pos = self.pos as usize;
+        let state = self
+            .buffer
+            .changed
+            .wait_while(state, |s| s.data.len() <= pos && !s.done)
+            .unwrap();
+        if pos >= state.data.len() {
+            return match &state.error {
+                Some(error) => Err(io::Error::other(error.clone())),
+                None => Ok(0),
+            };
+        }
+        let n = buf.len().min(state.data.len() - pos);
+        buf[..n].copy_from_slice(&state.data[pos..pos + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for StreamReader {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let pos = match from {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let state = self.buffer.state.lock().unwrap();
+                let state = self.buffer.changed.wait_while(state, |s| !s.done).unwrap();
+                (state.data.len() as u64).checked_add_signed(offset)
+            }
+        };
+        self.pos = pos
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn reads_chunks_as_they_arrive() {
+        let buffer = StreamBuffer::new();
+        let mut reader = buffer.reader();
+        let writer = buffer.clone();
+        let feed = thread::spawn(move || {
+            writer.push(b"ID3");
+            writer.push(b"frames");
+            writer.finish()
+        });
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"ID3frames");
+        assert_eq!(feed.join().unwrap(), b"ID3frames");
+    }
+
+    #[test]
+    fn seeks_within_what_has_arrived() {
+        let buffer = StreamBuffer::complete(b"abcdef".to_vec());
+        let mut reader = buffer.reader();
+        let mut two = [0; 2];
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        reader.read_exact(&mut two).unwrap();
+        assert_eq!(&two, b"cd");
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 5);
+        assert!(reader.seek(SeekFrom::Current(-9)).is_err());
+    }
+
+    #[test]
+    fn reports_a_failure_after_the_bytes_before_it() {
+        let buffer = StreamBuffer::new();
+        buffer.push(b"ab");
+        buffer.fail("connection reset".to_string());
+        let mut reader = buffer.reader();
+        let mut two = [0; 2];
+        reader.read_exact(&mut two).unwrap();
+        let err = reader.read(&mut two).unwrap_err();
+        assert_eq!(err.to_string(), "connection reset");
+        assert_eq!(buffer.error().as_deref(), Some("connection reset"));
+    }
+}
@@ -0,0 +1,56 @@
+use std::marker::PhantomData;
+use tauri::{AppHandle, Runtime};
+
+use crate::http::Http;
+use crate::models::*;
+use crate::{Error, Result};
+
+fn unavailable() -> Error {
+    Error::NativeTTSError("cloud voices are not available in this build".to_string())
+}
+
+/// Stand-in for builds without the `cloud` feature: no cloud voice is ever
+/// listed or selected, so the playback commands never reach it.
+pub struct Cloud<R: Runtime>(PhantomData<fn() -> R>);
+
+impl<R: Runtime> Cloud<R> {
+    pub fn new(_app: &AppHandle<R>, _http: Http) -> Self {
+        Self(PhantomData)
+    }
+    pub fn set_mode(&self, mode: CloudVoices) -> Result<()> {
+        match mode {
+            CloudVoices::Off => Ok(()),
+            CloudVoices::Fallback | CloudVoices::All => Err(unavailable()),
+        }
+    }
+    pub fn is_selected(&self) -> bool {
+        false
+    }
+    pub fn select_voice(&self, _voice: &str) -> Result<bool> {
+        Ok(false)
+    }
+    pub fn init(&self) -> Result<InitResponse> {
+        Err(unavailable())
+    }
+    pub fn speak(&self, _args: SpeakArgs) -> Result<SpeakResponse> {
+        Err(unavailable())
+    }
+    pub fn pause(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn resume(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn stop(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn set_rate(&self, _args: &SetRateArgs) -> Result<()> {
+        Ok(())
+    }
+    pub fn set_pitch(&self, _args: SetPitchArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub async fn with_voices(&self, local: Result<GetVoicesResponse>) -> Result<GetVoicesResponse> {
+        local
+    }
+}
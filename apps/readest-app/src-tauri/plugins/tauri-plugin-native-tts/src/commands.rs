@@ -1,11 +1,55 @@
-use tauri::{command, AppHandle, Runtime};
+#[cfg(desktop)]
+use tauri::ipc::Channel;
+use tauri::{command, AppHandle, Manager, Runtime, State};
 
+use crate::cloud::Cloud;
 use crate::models::*;
+use crate::piper::Piper;
+use crate::pronunciation::Pronunciations;
+use crate::ssml::Utterance;
+use crate::voice_map::VoiceMap;
 use crate::NativeTtsExt;
 use crate::Result;
 
+fn piper<R: Runtime>(app: &AppHandle<R>) -> State<'_, Piper<R>> {
+    app.state::<Piper<R>>()
+}
+
+fn cloud<R: Runtime>(app: &AppHandle<R>) -> State<'_, Cloud<R>> {
+    app.state::<Cloud<R>>()
+}
+
+/// What the system engine is handed for `speak`: iOS reads SSML itself, so
+/// it gets the normalized markup; Android only takes plain text.
+fn system_speech(payload: SpeakArgs) -> SpeakArgs {
+    if !payload.ssml {
+        return payload;
+    }
+    let utterance = Utterance::parse_ssml(&payload.text);
+    if cfg!(target_os = "ios") {
+        SpeakArgs {
+            text: format!("<speak>{}</speak>", utterance.markup()),
+            ..payload
+        }
+    } else {
+        SpeakArgs {
+            text: utterance.text().to_string(),
+            ssml: false,
+            ..payload
+        }
+    }
+}
+
 #[command]
 pub(crate) async fn init<R: Runtime>(app: AppHandle<R>) -> Result<InitResponse> {
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.init();
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.init();
+    }
     app.native_tts().init()
 }
 
@@ -14,42 +58,139 @@ pub(crate) async fn speak<R: Runtime>(
     app: AppHandle<R>,
     payload: SpeakArgs,
 ) -> Result<SpeakResponse> {
-    app.native_tts().speak(payload)
+    let voice_map = app.state::<VoiceMap>();
+    if let Some(voice) = voice_map.switch_for(payload.lang.as_deref()) {
+        if let Err(e) = select_voice(&app, SetVoiceArgs { voice }) {
+            voice_map.reset();
+            return Err(e);
+        }
+    }
+    let pronunciations = app.state::<Pronunciations>();
+    let payload = match pronunciations.apply(payload.book.as_deref(), &payload.text, payload.ssml) {
+        Some(text) => SpeakArgs {
+            text,
+            ssml: true,
+            ..payload
+        },
+        None => payload,
+    };
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.speak(payload);
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.speak(payload);
+    }
+    // The system engines can't render ahead and would speak it instead.
+    if payload.preload {
+        return Ok(SpeakResponse {
+            utterance_id: String::new(),
+        });
+    }
+    app.native_tts().speak(system_speech(payload))
 }
 
 #[command]
 pub(crate) async fn pause<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.pause();
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.pause();
+    }
     app.native_tts().pause()
 }
 
 #[command]
 pub(crate) async fn resume<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.resume();
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.resume();
+    }
     app.native_tts().resume()
 }
 
 #[command]
 pub(crate) async fn stop<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.stop();
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.stop();
+    }
     app.native_tts().stop()
 }
 
 #[command]
 pub(crate) async fn set_rate<R: Runtime>(app: AppHandle<R>, payload: SetRateArgs) -> Result<()> {
+    let cloud = cloud(&app);
+    cloud.set_rate(&payload)?;
+    if cloud.is_selected() {
+        return Ok(());
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.set_rate(payload);
+    }
     app.native_tts().set_rate(payload)
 }
 
 #[command]
 pub(crate) async fn set_pitch<R: Runtime>(app: AppHandle<R>, payload: SetPitchArgs) -> Result<()> {
+    let cloud = cloud(&app);
+    if cloud.is_selected() {
+        return cloud.set_pitch(payload);
+    }
+    let piper = piper(&app);
+    if piper.is_active() {
+        return piper.set_pitch(payload);
+    }
     app.native_tts().set_pitch(payload)
 }
 
+fn select_voice<R: Runtime>(app: &AppHandle<R>, payload: SetVoiceArgs) -> Result<()> {
+    let piper = piper(app);
+    if cloud(app).select_voice(&payload.voice)? {
+        // Don't leave the local voice talking over the cloud one.
+        if piper.is_active() {
+            piper.stop().ok();
+        } else {
+            app.native_tts().stop().ok();
+        }
+        return Ok(());
+    }
+    if piper.is_active() {
+        return piper.set_voice(payload);
+    }
+    app.native_tts().set_voice(payload)
+}
+
 #[command]
 pub(crate) async fn set_voice<R: Runtime>(app: AppHandle<R>, payload: SetVoiceArgs) -> Result<()> {
-    app.native_tts().set_voice(payload)
+    let voice = payload.voice.clone();
+    select_voice(&app, payload)?;
+    app.state::<VoiceMap>().chose(&voice);
+    Ok(())
 }
 
 #[command]
 pub(crate) async fn get_all_voices<R: Runtime>(app: AppHandle<R>) -> Result<GetVoicesResponse> {
-    app.native_tts().get_all_voices()
+    let piper = piper(&app);
+    let local = if piper.is_active() {
+        piper.get_all_voices()
+    } else {
+        app.native_tts().get_all_voices()
+    };
+    cloud(&app).with_voices(local).await
 }
 
 #[command]
@@ -104,3 +245,215 @@ pub(crate) async fn playout_control<R: Runtime>(
 pub(crate) async fn playout_position<R: Runtime>(app: AppHandle<R>) -> Result<PlayoutPositionResponse> {
     app.native_tts().playout_position()
 }
+
+/// Pick the engine behind `speak` and the other playback commands.
+#[command]
+pub(crate) async fn set_engine<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetEngineArgs,
+) -> Result<()> {
+    // Silence the system voice rather than leave it talking over Piper.
+    if payload.engine == TtsEngine::Piper {
+        app.native_tts().stop().ok();
+    }
+    // The other engine has its own voice selected.
+    app.state::<VoiceMap>().reset();
+    piper(&app).set_engine(payload.engine)
+}
+
+#[command]
+pub(crate) async fn list_piper_voices<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<PiperVoicesResponse> {
+    piper(&app).list_voices().await
+}
+
+#[command]
+pub(crate) async fn download_piper_voice<R: Runtime>(
+    app: AppHandle<R>,
+    payload: PiperVoiceArgs,
+) -> Result<()> {
+    piper(&app).download_voice(payload).await
+}
+
+#[command]
+pub(crate) async fn cancel_piper_download<R: Runtime>(
+    app: AppHandle<R>,
+    payload: PiperVoiceArgs,
+) -> Result<()> {
+    piper(&app).cancel_download(payload)
+}
+
+#[command]
+pub(crate) async fn delete_piper_voice<R: Runtime>(
+    app: AppHandle<R>,
+    payload: PiperVoiceArgs,
+) -> Result<()> {
+    piper(&app).delete_voice(payload)
+}
+
+/// Whether `get_all_voices` offers cloud voices, and for which languages.
+#[command]
+pub(crate) async fn set_cloud_voices<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetCloudVoicesArgs,
+) -> Result<()> {
+    cloud(&app).set_mode(payload.mode)
+}
+
+#[command]
+pub(crate) async fn set_voice_map<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetVoiceMapArgs,
+) -> Result<()> {
+    app.state::<VoiceMap>().set(payload.voices);
+    Ok(())
+}
+
+#[command]
+pub(crate) async fn get_voice_map<R: Runtime>(app: AppHandle<R>) -> Result<GetVoiceMapResponse> {
+    let voices = app.state::<VoiceMap>().get();
+    Ok(GetVoiceMapResponse { voices })
+}
+
+#[command]
+pub(crate) async fn set_pronunciations<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetPronunciationsArgs,
+) -> Result<()> {
+    app.state::<Pronunciations>()
+        .set(payload.book, payload.entries)
+}
+
+#[command]
+pub(crate) async fn get_pronunciations<R: Runtime>(
+    app: AppHandle<R>,
+    payload: GetPronunciationsArgs,
+) -> Result<GetPronunciationsResponse> {
+    let entries = app.state::<Pronunciations>().get(payload.book.as_deref());
+    Ok(GetPronunciationsResponse { entries })
+}
+
+#[command]
+pub(crate) async fn list_system_engines<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<SystemEnginesResponse> {
+    app.native_tts().list_system_engines()
+}
+
+/// Which installed engine the system voices come from; `None` follows the
+/// system default. The voices are the new engine's afterwards.
+#[command]
+pub(crate) async fn set_system_engine<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetSystemEngineArgs,
+) -> Result<()> {
+    app.native_tts().set_system_engine(payload)?;
+    app.state::<VoiceMap>().reset();
+    Ok(())
+}
+
+#[command]
+pub(crate) async fn list_audio_outputs<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<AudioOutputsResponse> {
+    app.native_tts().list_audio_outputs()
+}
+
+/// Where speech plays from the next utterance on; `None` follows the
+/// system default.
+#[command]
+pub(crate) async fn set_audio_output<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetAudioOutputArgs,
+) -> Result<()> {
+    app.native_tts().set_audio_output(payload)
+}
+
+/// A desktop route change seen by the player. Speech is paused at once when
+/// its device went away, rather than wait for the webview to do it.
+#[cfg(all(desktop, any(feature = "piper", feature = "cloud")))]
+pub(crate) fn audio_route_changed<R: Runtime>(
+    app: &AppHandle<R>,
+    outputs: Vec<AudioOutput>,
+    lost: bool,
+) {
+    if lost {
+        let cloud = cloud(app);
+        let paused = if cloud.is_selected() {
+            cloud.pause()
+        } else {
+            piper(app).pause()
+        };
+        paused.ok();
+    }
+    let event = AudioRouteChangedEvent { outputs, lost };
+    app.native_tts()
+        .emit_event("audio-route-changed", event)
+        .ok();
+}
+
+/// Whether speech pauses or ducks under a navigation prompt and the like;
+/// `audio-interruption` events report either.
+#[command]
+pub(crate) async fn set_interruption_mode<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetInterruptionModeArgs,
+) -> Result<()> {
+    app.native_tts().set_interruption_mode(payload)
+}
+
+/// Stop speech after a while, fading it out, or at the end of the chapter.
+#[command]
+pub(crate) async fn set_sleep_timer<R: Runtime>(
+    app: AppHandle<R>,
+    payload: SetSleepTimerArgs,
+) -> Result<SleepTimerState> {
+    app.native_tts().set_sleep_timer(payload)
+}
+
+#[command]
+pub(crate) async fn get_sleep_timer<R: Runtime>(app: AppHandle<R>) -> Result<SleepTimerState> {
+    app.native_tts().get_sleep_timer()
+}
+
+/// The desktop sleep timer ran out: speech stops here, so it does even
+/// when the webview is too throttled to do it.
+#[cfg(desktop)]
+pub(crate) fn sleep_timer_fired<R: Runtime>(app: &AppHandle<R>) {
+    let cloud = cloud(app);
+    let stopped = if cloud.is_selected() {
+        cloud.stop()
+    } else {
+        piper(app).stop()
+    };
+    stopped.ok();
+    app.native_tts()
+        .emit_event("sleep-timer-fired", SleepTimerState::default())
+        .ok();
+}
+
+/// Mobile plugins get event listeners from the Tauri runtime; on desktop
+/// the plugin keeps them itself, for the events Piper and the cloud
+/// voices raise.
+#[cfg(desktop)]
+#[command]
+pub(crate) async fn register_listener<R: Runtime>(
+    app: AppHandle<R>,
+    event: String,
+    handler: Channel<serde_json::Value>,
+) -> Result<()> {
+    app.native_tts().register_listener(event, handler);
+    Ok(())
+}
+
+#[cfg(desktop)]
+#[command]
+pub(crate) async fn remove_listener<R: Runtime>(
+    app: AppHandle<R>,
+    event: String,
+    channel_id: u32,
+) -> Result<()> {
+    app.native_tts().remove_listener(&event, channel_id);
+    Ok(())
+}
@@ -1,17 +1,123 @@
-use serde::de::DeserializeOwned;
-use tauri::{plugin::PluginApi, AppHandle, Runtime};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+#[cfg(windows)]
+use tauri::Manager;
+use tauri::{ipc::Channel, plugin::PluginApi, AppHandle, Runtime};
 
 use crate::models::*;
+use crate::sleep_timer::SleepTimer;
+
+/// Words per minute of `say` and espeak-ng at their normal pace.
+#[cfg(not(windows))]
+const NORMAL_WPM: f32 = 175.0;
+
+/// SAPI through PowerShell. Arguments come in through the environment and
+/// the text through stdin, so nothing needs quoting.
+#[cfg(windows)]
+const SAPI_SCRIPT: &str = "[Console]::InputEncoding = [Text.Encoding]::UTF8; \
+    Add-Type -AssemblyName System.Speech; \
+    $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+    if ($env:TTS_VOICE) { $s.SelectVoice($env:TTS_VOICE) }; \
+    $s.Rate = [int]$env:TTS_RATE; \
+    $s.SetOutputToWaveFile($env:TTS_OUTPUT); \
+    $s.Speak([Console]::In.ReadToEnd()); \
+    $s.Dispose()";
+
+/// The command-line voices to try, best first: `say` on macOS, SAPI on
+/// Windows, espeak-ng (or the older espeak) elsewhere. Each reads the text
+/// from stdin and writes a WAV file.
+fn system_voices(path: &str, voice: Option<&str>, rate: f32) -> Vec<Command> {
+    #[cfg(target_os = "macos")]
+    {
+        let mut say = Command::new("say");
+        say.args([
+            "--file-format=WAVE",
+            "--data-format=LEI16@22050",
+            "-f",
+            "-",
+            "-o",
+        ])
+        .arg(path)
+        .arg("-r")
+        .arg(format!("{:.0}", NORMAL_WPM * rate));
+        if let Some(voice) = voice {
+            say.arg("-v").arg(voice);
+        }
+        vec![say]
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        // SAPI rates run from -10 to 10, roughly a third to three times
+        // the normal pace.
+        let sapi_rate = (10.0 * rate.ln() / 3f32.ln()).round().clamp(-10.0, 10.0);
+        let mut powershell = Command::new("powershell");
+        powershell
+            .args(["-NoProfile", "-NonInteractive", "-Command", SAPI_SCRIPT])
+            .env("TTS_OUTPUT", path)
+            .env("TTS_RATE", format!("{sapi_rate:.0}"))
+            .env("TTS_VOICE", voice.unwrap_or_default())
+            .creation_flags(CREATE_NO_WINDOW);
+        vec![powershell]
+    }
+    #[cfg(not(any(target_os = "macos", windows)))]
+    {
+        ["espeak-ng", "espeak"]
+            .into_iter()
+            .map(|program| {
+                let mut espeak = Command::new(program);
+                espeak
+                    .arg("-w")
+                    .arg(path)
+                    .arg("-s")
+                    .arg(format!("{:.0}", NORMAL_WPM * rate))
+                    .arg("--stdin");
+                if let Some(voice) = voice {
+                    espeak.arg("-v").arg(voice);
+                }
+                espeak
+            })
+            .collect()
+    }
+}
+
+fn run_voice(mut command: Command, text: &str) -> std::io::Result<std::process::Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    child.wait_with_output()
+}
 
 pub fn init<R: Runtime, C: DeserializeOwned>(
     app: &AppHandle<R>,
     _api: PluginApi<R, C>,
 ) -> crate::Result<NativeTts<R>> {
-    Ok(NativeTts(app.clone()))
+    Ok(NativeTts {
+        app: app.clone(),
+        listeners: Mutex::new(Vec::new()),
+        sleep_timer: SleepTimer::new(),
+        #[cfg(windows)]
+        smtc: crate::smtc::Smtc::default(),
+    })
 }
 
 /// Access to the native-tts APIs.
-pub struct NativeTts<R: Runtime>(AppHandle<R>);
+pub struct NativeTts<R: Runtime> {
+    app: AppHandle<R>,
+    /// Channels registered through `addPluginListener`, by event name.
+    listeners: Mutex<Vec<(String, Channel<serde_json::Value>)>>,
+    sleep_timer: SleepTimer,
+    #[cfg(windows)]
+    smtc: crate::smtc::Smtc,
+}
 
 impl<R: Runtime> NativeTts<R> {
     pub fn init(&self) -> crate::Result<InitResponse> {
@@ -20,6 +126,27 @@ impl<R: Runtime> NativeTts<R> {
     pub fn speak(&self, _args: SpeakArgs) -> crate::Result<SpeakResponse> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    /// There is no in-process engine on desktop, so files are rendered with
+    /// the platform's command-line voice.
+    pub fn synthesize(&self, args: SynthesizeArgs) -> crate::Result<()> {
+        let rate = args.rate.unwrap_or(1.0).clamp(0.25, 4.0);
+        for command in system_voices(&args.path, args.voice.as_deref(), rate) {
+            let output = match run_voice(command, &args.text) {
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                result => result?,
+            };
+            if !output.status.success() {
+                return Err(crate::Error::NativeTTSError(format!(
+                    "speech synthesis failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            return Ok(());
+        }
+        Err(crate::Error::NativeTTSError(
+            "no speech synthesizer installed".to_string(),
+        ))
+    }
     pub fn pause(&self) -> crate::Result<()> {
         Err(crate::Error::UnsupportedPlatformError)
     }
@@ -41,18 +168,71 @@ impl<R: Runtime> NativeTts<R> {
     pub fn get_all_voices(&self) -> crate::Result<GetVoicesResponse> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    /// Windows hands the media session to the System Media Transport
+    /// Controls; other desktops have none here.
+    #[cfg(windows)]
+    pub fn set_media_session_active(
+        &self,
+        payload: SetMediaSessionActiveRequest,
+    ) -> crate::Result<()> {
+        use crate::NativeTtsExt;
+
+        if !payload.active {
+            self.smtc.deactivate();
+            return Ok(());
+        }
+        let window = self
+            .app
+            .get_webview_window("main")
+            .ok_or_else(|| crate::Error::NativeTTSError("no main window".into()))?;
+        let hwnd = window
+            .hwnd()
+            .map_err(|e| crate::Error::NativeTTSError(e.to_string()))?;
+        let app = self.app.clone();
+        let emit = move |event: &'static str, position: Option<u64>| {
+            let payload = match position {
+                Some(position) => serde_json::json!({ "position": position }),
+                None => serde_json::json!({}),
+            };
+            let _ = app.native_tts().emit_event(event, payload);
+        };
+        self.smtc
+            .activate(hwnd.0 as _, std::sync::Arc::new(emit))
+            .map_err(crate::Error::NativeTTSError)
+    }
+    #[cfg(windows)]
+    pub fn update_media_session_state(
+        &self,
+        payload: UpdateMediaSessionStateRequest,
+    ) -> crate::Result<()> {
+        self.smtc
+            .update_state(&payload)
+            .map_err(crate::Error::NativeTTSError)
+    }
+    #[cfg(windows)]
+    pub fn update_media_session_metadata(
+        &self,
+        payload: UpdateMediaSessionMetadataRequest,
+    ) -> crate::Result<()> {
+        self.smtc
+            .update_metadata(&payload)
+            .map_err(crate::Error::NativeTTSError)
+    }
+    #[cfg(not(windows))]
     pub fn set_media_session_active(
         &self,
         _payload: SetMediaSessionActiveRequest,
     ) -> crate::Result<()> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    #[cfg(not(windows))]
     pub fn update_media_session_state(
         &self,
         _payload: UpdateMediaSessionStateRequest,
     ) -> crate::Result<()> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    #[cfg(not(windows))]
     pub fn update_media_session_metadata(
         &self,
         _payload: UpdateMediaSessionMetadataRequest,
@@ -62,6 +242,105 @@ impl<R: Runtime> NativeTts<R> {
     pub fn update_carplay_state(&self, _payload: UpdateCarPlayStateRequest) -> crate::Result<()> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    /// The devices Piper and the cloud voices can play through; without
+    /// them, audio goes wherever the system sends it.
+    pub fn list_audio_outputs(&self) -> crate::Result<AudioOutputsResponse> {
+        #[cfg(any(feature = "piper", feature = "cloud"))]
+        {
+            Ok(AudioOutputsResponse {
+                outputs: crate::player::outputs(),
+                selectable: true,
+            })
+        }
+        #[cfg(not(any(feature = "piper", feature = "cloud")))]
+        {
+            Ok(AudioOutputsResponse {
+                outputs: Vec::new(),
+                selectable: false,
+            })
+        }
+    }
+    /// The desktop speech APIs offer voices, not engines.
+    pub fn list_system_engines(&self) -> crate::Result<SystemEnginesResponse> {
+        Ok(SystemEnginesResponse {
+            engines: Vec::new(),
+            selectable: false,
+        })
+    }
+
+    pub fn set_system_engine(&self, args: SetSystemEngineArgs) -> crate::Result<()> {
+        match args.name {
+            None => Ok(()),
+            Some(_) => Err(crate::Error::UnsupportedPlatformError),
+        }
+    }
+
+    pub fn set_audio_output(&self, args: SetAudioOutputArgs) -> crate::Result<()> {
+        #[cfg(any(feature = "piper", feature = "cloud"))]
+        {
+            crate::player::select(args.id).map_err(crate::Error::NativeTTSError)
+        }
+        #[cfg(not(any(feature = "piper", feature = "cloud")))]
+        {
+            match args.id {
+                None => Ok(()),
+                Some(_) => Err(crate::Error::UnsupportedPlatformError),
+            }
+        }
+    }
+    /// Desktop systems don't take the audio away from apps.
+    pub fn set_interruption_mode(&self, _args: SetInterruptionModeArgs) -> crate::Result<()> {
+        Ok(())
+    }
+    /// Only Piper and the cloud voices play in-process, so they're the ones
+    /// faded and stopped; the client stops the rest on `sleep-timer-fired`.
+    pub fn set_sleep_timer(&self, args: SetSleepTimerArgs) -> crate::Result<SleepTimerState> {
+        let app = self.app.clone();
+        self.sleep_timer
+            .set(
+                args,
+                |volume| {
+                    #[cfg(any(feature = "piper", feature = "cloud"))]
+                    crate::player::set_volume(volume);
+                    #[cfg(not(any(feature = "piper", feature = "cloud")))]
+                    let _ = volume;
+                },
+                move || crate::commands::sleep_timer_fired(&app),
+            )
+            .map_err(crate::Error::NativeTTSError)
+    }
+    pub fn get_sleep_timer(&self) -> crate::Result<SleepTimerState> {
+        Ok(self.sleep_timer.state())
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn register_listener(&self, event: String, handler: Channel<serde_json::Value>) {
+        self.listeners.lock().unwrap().push((event, handler));
+    }
+    pub fn remove_listener(&self, event: &str, channel_id: u32) {
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|(name, channel)| name != event || channel.id() != channel_id);
+    }
+    /// Deliver a `tts_events` event raised on the Rust side (Piper) to the
+    /// webview's listeners.
+    pub fn emit_tts_event(&self, payload: TTSEventPayload) -> crate::Result<()> {
+        self.emit_event("tts_events", payload)
+    }
+    /// Deliver `event` to the webview's listeners for it.
+    pub fn emit_event(&self, event: &str, payload: impl Serialize) -> crate::Result<()> {
+        let value = serde_json::to_value(payload)
+            .map_err(|e| crate::Error::NativeTTSError(e.to_string()))?;
+        for (name, channel) in self.listeners.lock().unwrap().iter() {
+            if name == event {
+                // A closed webview's channel just drops the event.
+                let _ = channel.send(value.clone());
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<R: Runtime> NativeTts<R> {
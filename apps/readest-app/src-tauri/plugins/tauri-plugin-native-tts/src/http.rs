@@ -0,0 +1,30 @@
+//! The HTTP clients Piper voice downloads and the cloud voices go out with.
+//!
+//! The app hands [`crate::init_with_http`] the builder its own clients start
+//! from, so the plugin follows the app's proxy and trusted hosts, including
+//! changes made while it runs: a client is built per request rather than
+//! once at startup. [`crate::init`] uses reqwest's defaults.
+
+use std::sync::Arc;
+
+type MakeBuilder = dyn Fn() -> reqwest::ClientBuilder + Send + Sync;
+
+#[derive(Clone)]
+#[cfg_attr(not(any(feature = "piper", feature = "cloud")), allow(dead_code))]
+pub struct Http(Arc<MakeBuilder>);
+
+#[cfg_attr(not(any(feature = "piper", feature = "cloud")), allow(dead_code))]
+impl Http {
+    pub fn new(make_builder: impl Fn() -> reqwest::ClientBuilder + Send + Sync + 'static) -> Self {
+        Self(Arc::new(make_builder))
+    }
+
+    pub fn client(&self) -> reqwest::Result<reqwest::Client> {
+        (self.0)().build()
+    }
+
+    /// A client limited to HTTP/1.1, which a WebSocket upgrade needs.
+    pub fn upgrade_client(&self) -> reqwest::Result<reqwest::Client> {
+        (self.0)().http1_only().build()
+    }
+}
@@ -10,9 +10,21 @@ mod desktop;
 #[cfg(mobile)]
 mod mobile;
 
+mod cloud;
 mod commands;
 mod error;
+mod http;
 mod models;
+mod piper;
+#[cfg(any(feature = "piper", feature = "cloud"))]
+mod player;
+mod pronunciation;
+#[cfg(desktop)]
+mod sleep_timer;
+#[cfg(windows)]
+mod smtc;
+mod ssml;
+mod voice_map;
 
 pub use error::{Error, Result};
 
@@ -34,6 +46,16 @@ impl<R: Runtime, T: Manager<R>> crate::NativeTtsExt<R> for T {
 
 /// Initializes the plugin.
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    init_with_http(reqwest::Client::builder)
+}
+
+/// Initializes the plugin with the app's HTTP settings: Piper voice
+/// downloads and the cloud voices build their clients from
+/// `make_builder`, so they go through the same proxy as the app.
+pub fn init_with_http<R: Runtime>(
+    make_builder: impl Fn() -> reqwest::ClientBuilder + Send + Sync + 'static,
+) -> TauriPlugin<R> {
+    let http = http::Http::new(make_builder);
     Builder::new("native-tts")
         .invoke_handler(tauri::generate_handler![
             commands::init,
@@ -52,6 +74,27 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             commands::playout_enqueue,
             commands::playout_control,
             commands::playout_position,
+            commands::set_engine,
+            commands::list_piper_voices,
+            commands::download_piper_voice,
+            commands::cancel_piper_download,
+            commands::delete_piper_voice,
+            commands::set_cloud_voices,
+            commands::set_voice_map,
+            commands::get_voice_map,
+            commands::set_pronunciations,
+            commands::get_pronunciations,
+            commands::list_system_engines,
+            commands::set_system_engine,
+            commands::list_audio_outputs,
+            commands::set_audio_output,
+            commands::set_sleep_timer,
+            commands::get_sleep_timer,
+            commands::set_interruption_mode,
+            #[cfg(desktop)]
+            commands::register_listener,
+            #[cfg(desktop)]
+            commands::remove_listener,
         ])
         .setup(|app, api| {
             #[cfg(mobile)]
@@ -59,6 +102,17 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             #[cfg(desktop)]
             let native_tts = desktop::init(app, api)?;
             app.manage(native_tts);
+            app.manage(piper::Piper::new(app, http.clone()));
+            app.manage(cloud::Cloud::new(app, http));
+            app.manage(voice_map::VoiceMap::new());
+            app.manage(pronunciation::Pronunciations::new());
+            #[cfg(all(desktop, any(feature = "piper", feature = "cloud")))]
+            {
+                let app = app.clone();
+                player::watch(move |outputs, lost| {
+                    commands::audio_route_changed(&app, outputs, lost)
+                });
+            }
             Ok(())
         })
         .build()
@@ -38,6 +38,24 @@ impl<R: Runtime> NativeTts<R> {
     }
 }
 
+impl<R: Runtime> NativeTts<R> {
+    pub fn synthesize(&self, payload: SynthesizeArgs) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("synthesize", payload)
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    /// Raise a `tts_events` event from the Rust side (Piper) through the
+    /// native plugin, which owns the listeners on mobile.
+    pub fn emit_tts_event(&self, payload: TTSEventPayload) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("emit_tts_event", payload)
+            .map_err(Into::into)
+    }
+}
+
 impl<R: Runtime> NativeTts<R> {
     pub fn pause(&self) -> crate::Result<()> {
         self.0.run_mobile_plugin("pause", ()).map_err(Into::into)
@@ -158,3 +176,59 @@ impl<R: Runtime> NativeTts<R> {
             .map_err(Into::into)
     }
 }
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn list_audio_outputs(&self) -> crate::Result<AudioOutputsResponse> {
+        self.0
+            .run_mobile_plugin("list_audio_outputs", ())
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn list_system_engines(&self) -> crate::Result<SystemEnginesResponse> {
+        self.0
+            .run_mobile_plugin("list_system_engines", ())
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn set_system_engine(&self, payload: SetSystemEngineArgs) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("set_system_engine", payload)
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn set_audio_output(&self, payload: SetAudioOutputArgs) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("set_audio_output", payload)
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn set_sleep_timer(&self, payload: SetSleepTimerArgs) -> crate::Result<SleepTimerState> {
+        self.0
+            .run_mobile_plugin("set_sleep_timer", payload)
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn get_sleep_timer(&self) -> crate::Result<SleepTimerState> {
+        self.0
+            .run_mobile_plugin("get_sleep_timer", ())
+            .map_err(Into::into)
+    }
+}
+
+impl<R: Runtime> NativeTts<R> {
+    pub fn set_interruption_mode(&self, payload: SetInterruptionModeArgs) -> crate::Result<()> {
+        self.0
+            .run_mobile_plugin("set_interruption_mode", payload)
+            .map_err(Into::into)
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,6 +31,27 @@ pub struct SpeakArgs {
     pub text: String,
     #[serde(default)]
     pub preload: bool,
+    /// `text` is SSML; engines that can't read it get the plain text.
+    #[serde(default)]
+    pub ssml: bool,
+    /// Language of `text`, which picks its voice from the voice map.
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// Book `text` is from, whose pronunciations apply before the library's.
+    #[serde(default)]
+    pub book: Option<String>,
+}
+
+/// Speak `text` into a WAV file at `path` instead of the speaker.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SynthesizeArgs {
+    pub text: String,
+    pub path: String,
+    /// Voice id as listed by `get_all_voices`; the current voice when unset.
+    pub voice: Option<String>,
+    /// Speech rate multiplier; the current rate when unset.
+    pub rate: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,6 +105,15 @@ pub struct UpdateMediaSessionStateRequest {
     pub playing: bool,
     pub position: Option<f64>,
     pub duration: Option<f64>,
+    /// Whether the lock screen's seek bar can be dragged, which sends
+    /// `media-session-seek`; left as it was when unset.
+    pub seekable: Option<bool>,
+    /// The chapter being read, out of `chapter_count`, which decides whether
+    /// the `media-session-next-chapter` and `-previous-chapter` actions show.
+    pub chapter_index: Option<u32>,
+    pub chapter_count: Option<u32>,
+    /// The chapter's label, for the iOS read-aloud Live Activity.
+    pub chapter_title: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -138,3 +169,320 @@ pub struct PlayoutPositionResponse {
     pub position_ms: f64,
     pub playing: bool,
 }
+
+/// Which engine `speak` and the playback commands drive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsEngine {
+    /// The platform voice: Android's TextToSpeech, AVSpeechSynthesizer.
+    #[default]
+    System,
+    /// Piper neural voices, run in-process.
+    Piper,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetEngineArgs {
+    pub engine: TtsEngine,
+}
+
+/// When `get_all_voices` offers the cloud voices alongside the local ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudVoices {
+    /// Local voices only.
+    #[default]
+    Off,
+    /// Cloud voices for the languages no local voice speaks.
+    Fallback,
+    /// Every cloud voice.
+    All,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCloudVoicesArgs {
+    pub mode: CloudVoices,
+}
+
+/// Voice ids by language tag, for `speak` calls tagged with a language.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVoiceMapArgs {
+    pub voices: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetVoiceMapResponse {
+    pub voices: HashMap<String, String>,
+}
+
+/// An entry of the pronunciation dictionary.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pronunciation {
+    /// A word or phrase, matched as whole words, or a regular expression.
+    pub pattern: String,
+    /// What's said instead; a regex's may refer to its groups as `$1`.
+    pub replacement: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// `replacement` is IPA rather than words.
+    #[serde(default)]
+    pub ipa: bool,
+}
+
+/// The pronunciations of `book`, or of the whole library when unset.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPronunciationsArgs {
+    #[serde(default)]
+    pub book: Option<String>,
+    pub entries: Vec<Pronunciation>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPronunciationsArgs {
+    #[serde(default)]
+    pub book: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPronunciationsResponse {
+    pub entries: Vec<Pronunciation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioOutputKind {
+    Speaker,
+    Wired,
+    Bluetooth,
+    Usb,
+    Hdmi,
+    AirPlay,
+    Other,
+}
+
+/// A device speech can play through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioOutput {
+    pub id: String,
+    pub name: String,
+    pub kind: AudioOutputKind,
+    /// Picked with `set_audio_output`, or where audio goes now when
+    /// nothing is picked.
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioOutputsResponse {
+    pub outputs: Vec<AudioOutput>,
+    /// Whether `set_audio_output` can pick one. Android and iOS route media
+    /// themselves, through the system output switcher.
+    pub selectable: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAudioOutputArgs {
+    /// `None` follows the system default.
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+/// A speech engine installed on the device, e.g. Google's or the vendor's
+/// on Android.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemEngine {
+    /// Package name, what `set_system_engine` takes.
+    pub name: String,
+    pub label: String,
+    /// Picked with `set_system_engine`, or the system default when nothing
+    /// is picked.
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemEnginesResponse {
+    pub engines: Vec<SystemEngine>,
+    /// Whether `set_system_engine` can pick one; only Android has several.
+    pub selectable: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSystemEngineArgs {
+    /// `None` follows the system default.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Payload of the `audio-route-changed` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioRouteChangedEvent {
+    pub outputs: Vec<AudioOutput>,
+    /// The output in use went away, e.g. headphones were unplugged; speech
+    /// has been paused rather than carry on from the speaker.
+    pub lost: bool,
+}
+
+/// What speech does when another app briefly needs the audio, e.g. a
+/// navigation prompt. Calls and alarms always pause it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptionMode {
+    /// Pause, and resume once the interruption is over.
+    #[default]
+    Pause,
+    /// Carry on more quietly underneath it.
+    Duck,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetInterruptionModeArgs {
+    pub mode: InterruptionMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptionPhase {
+    Began,
+    Ended,
+}
+
+/// Payload of the `audio-interruption` event. A `media-session-pause`
+/// accompanies a pausing `began`, and a `media-session-play` an `ended`
+/// that resumes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioInterruptionEvent {
+    pub phase: InterruptionPhase,
+    /// Speech carries on ducked instead of pausing.
+    pub ducked: bool,
+    /// For `began`: another app took the audio for good, so no `ended`
+    /// follows.
+    pub permanent: bool,
+    /// For `ended`: speech that the interruption paused resumes.
+    pub should_resume: bool,
+}
+
+/// Arms the sleep timer; with neither `seconds` nor `end_of_chapter` set it
+/// is turned off.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSleepTimerArgs {
+    /// Stop after this long, fading out over the last ten seconds.
+    #[serde(default)]
+    pub seconds: Option<u64>,
+    /// Stop once the chapter reported through `update_media_session_state`
+    /// changes.
+    #[serde(default)]
+    pub end_of_chapter: bool,
+    /// Seconds a shake of the device adds to the timer; shakes are ignored
+    /// when unset, and on desktop.
+    #[serde(default)]
+    pub shake_to_extend: Option<u64>,
+}
+
+/// Payload of `get_sleep_timer` and the `sleep-timer-changed` event;
+/// `sleep-timer-fired` is sent once speech has been stopped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepTimerState {
+    pub active: bool,
+    /// Time left when counting down.
+    pub remaining_ms: Option<u64>,
+    pub end_of_chapter: bool,
+}
+
+/// A `tts_events` payload, for events raised on the Rust side.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TTSEventPayload {
+    pub utterance_id: String,
+    pub code: String,
+    pub message: Option<String>,
+    pub mark: Option<String>,
+    /// For `word` and `sentence` boundaries, the range being spoken, in
+    /// UTF-16 units of the utterance text as the platform engines report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_index: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_length: Option<u32>,
+}
+
+impl TTSEventPayload {
+    pub fn new(utterance_id: &str, code: &str, message: Option<String>) -> Self {
+        Self {
+            utterance_id: utterance_id.to_string(),
+            code: code.to_string(),
+            message,
+            mark: None,
+            char_index: None,
+            char_length: None,
+        }
+    }
+
+    /// A `boundary` event for `kind` (`word` or `sentence`) covering
+    /// `text[start..end]`, byte offsets that are converted to UTF-16.
+    pub fn range(utterance_id: &str, kind: &str, text: &str, start: usize, end: usize) -> Self {
+        let char_index = text[..start].encode_utf16().count() as u32;
+        let char_length = text[start..end].encode_utf16().count() as u32;
+        Self {
+            char_index: Some(char_index),
+            char_length: Some(char_length),
+            ..Self::new(utterance_id, "boundary", Some(kind.to_string()))
+        }
+    }
+}
+
+/// A Piper voice from the published catalog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiperVoice {
+    /// Catalog key, e.g. `en_US-lessac-medium`.
+    pub id: String,
+    pub name: String,
+    /// BCP 47 tag, e.g. `en-US`.
+    pub lang: String,
+    /// `x_low`, `low`, `medium` or `high`.
+    pub quality: String,
+    pub num_speakers: u32,
+    /// Model plus config.
+    pub size_bytes: u64,
+    pub installed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiperVoicesResponse {
+    pub voices: Vec<PiperVoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiperVoiceArgs {
+    pub voice: String,
+}
+
+/// Emitted as `native-tts://piper-download` while a voice downloads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiperDownloadProgress {
+    pub voice: String,
+    pub downloaded: u64,
+    pub total: u64,
+}
@@ -0,0 +1,230 @@
+//! The published Piper voice catalog and the voices installed from it.
+//!
+//! The catalog is `voices.json` from the `rhasspy/piper-voices` repository,
+//! pinned to a release so file paths and checksums don't move under us. A
+//! voice is two files, the `.onnx` model and its `.onnx.json` config,
+//! stored flat in the voices directory as `<id>.onnx` and `<id>.onnx.json`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::models::PiperVoice;
+
+const RELEASE_URL: &str = "https://huggingface.co/rhasspy/piper-voices/resolve/v1.0.0";
+pub const CATALOG_FILENAME: &str = "voices.json";
+
+#[derive(Debug, Deserialize)]
+struct Language {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct File {
+    size_bytes: u64,
+    md5_digest: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    name: String,
+    language: Language,
+    quality: String,
+    #[serde(default)]
+    num_speakers: u32,
+    files: HashMap<String, File>,
+}
+
+/// A file to fetch for a voice.
+#[derive(Debug, PartialEq)]
+pub struct VoiceFile {
+    pub url: String,
+    pub size_bytes: u64,
+    pub md5: String,
+    /// `.onnx` or `.onnx.json`.
+    pub extension: &'static str,
+}
+
+pub struct Catalog(HashMap<String, Entry>);
+
+pub fn catalog_url() -> String {
+    format!("{RELEASE_URL}/{CATALOG_FILENAME}")
+}
+
+pub fn model_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.onnx"))
+}
+
+pub fn config_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.onnx.json"))
+}
+
+pub fn is_installed(dir: &Path, id: &str) -> bool {
+    model_path(dir, id).is_file() && config_path(dir, id).is_file()
+}
+
+/// Catalog keys are `<lang>_<REGION>-<name>-<quality>`; turn the language
+/// part into a BCP 47 tag.
+fn bcp47(code: &str) -> String {
+    code.replace('_', "-")
+}
+
+impl Catalog {
+    pub fn parse(json: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(json)
+            .map(Self)
+            .map_err(|e| format!("parse Piper voice catalog: {e}"))
+    }
+
+    /// Every voice, sorted by language then name, marked installed when
+    /// both of its files are in `dir`.
+    pub fn voices(&self, dir: &Path) -> Vec<PiperVoice> {
+        let mut voices: Vec<PiperVoice> = self
+            .0
+            .iter()
+            .map(|(id, entry)| PiperVoice {
+                id: id.clone(),
+                name: entry.name.clone(),
+                lang: bcp47(&entry.language.code),
+                quality: entry.quality.clone(),
+                num_speakers: entry.num_speakers.max(1),
+                size_bytes: self.files(id).iter().map(|f| f.size_bytes).sum(),
+                installed: is_installed(dir, id),
+            })
+            .collect();
+        voices.sort_by(|a, b| (&a.lang, &a.name, &a.id).cmp(&(&b.lang, &b.name, &b.id)));
+        voices
+    }
+
+    /// The config then the model for voice `id`; empty when the catalog
+    /// doesn't have it.
+    pub fn files(&self, id: &str) -> Vec<VoiceFile> {
+        let Some(entry) = self.0.get(id) else {
+            return Vec::new();
+        };
+        let mut files: Vec<VoiceFile> = entry
+            .files
+            .iter()
+            .filter_map(|(path, file)| {
+                let extension = [".onnx.json", ".onnx"]
+                    .into_iter()
+                    .find(|ext| path.ends_with(ext))?;
+                Some(VoiceFile {
+                    url: format!("{RELEASE_URL}/{path}"),
+                    size_bytes: file.size_bytes,
+                    md5: file.md5_digest.to_ascii_lowercase(),
+                    extension,
+                })
+            })
+            .collect();
+        files.sort_by_key(|f| f.extension == ".onnx");
+        files
+    }
+}
+
+/// The voices installed in `dir`, described from their ids alone: what
+/// `get_all_voices` lists, and what the catalog falls back to offline.
+pub fn installed(dir: &Path) -> Vec<PiperVoice> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut voices: Vec<PiperVoice> = entries
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name().into_string().ok()?;
+            let id = file_name.strip_suffix(".onnx")?;
+            if !is_installed(dir, id) {
+                return None;
+            }
+            let mut parts = id.splitn(3, '-');
+            let (lang, name, quality) = (parts.next()?, parts.next()?, parts.next()?);
+            let size_bytes = [model_path(dir, id), config_path(dir, id)]
+                .iter()
+                .filter_map(|path| path.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            Some(PiperVoice {
+                id: id.to_string(),
+                name: name.to_string(),
+                lang: bcp47(lang),
+                quality: quality.to_string(),
+                num_speakers: 1,
+                size_bytes,
+                installed: true,
+            })
+        })
+        .collect();
+    voices.sort_by(|a, b| (&a.lang, &a.name, &a.id).cmp(&(&b.lang, &b.name, &b.id)));
+    voices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"{
+        "en_GB-alba-medium": {
+            "key": "en_GB-alba-medium",
+            "name": "alba",
+            "language": {"code": "en_GB", "family": "en", "region": "GB"},
+            "quality": "medium",
+            "num_speakers": 1,
+            "speaker_id_map": {},
+            "files": {
+                "en/en_GB/alba/medium/en_GB-alba-medium.onnx": {
+                    "size_bytes": 63201294, "md5_digest": "2BD8A2E3C0C4B5B1"
+                },
+                "en/en_GB/alba/medium/en_GB-alba-medium.onnx.json": {
+                    "size_bytes": 4888, "md5_digest": "ab21"
+                },
+                "en/en_GB/alba/medium/MODEL_CARD": {
+                    "size_bytes": 267, "md5_digest": "cd44"
+                }
+            },
+            "aliases": []
+        },
+        "de_DE-thorsten-high": {
+            "key": "de_DE-thorsten-high",
+            "name": "thorsten",
+            "language": {"code": "de_DE"},
+            "quality": "high",
+            "files": {}
+        }
+    }"#;
+
+    #[test]
+    fn lists_voices_with_their_download_size() {
+        let dir = std::env::temp_dir().join("piper-catalog-test");
+        let catalog = Catalog::parse(CATALOG.as_bytes()).unwrap();
+        let voices = catalog.voices(&dir);
+        let ids: Vec<&str> = voices.iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(ids, ["de_DE-thorsten-high", "en_GB-alba-medium"]);
+        assert_eq!(voices[1].lang, "en-GB");
+        assert_eq!(voices[1].size_bytes, 63201294 + 4888);
+        assert_eq!(voices[0].num_speakers, 1);
+        assert!(!voices[1].installed);
+    }
+
+    #[test]
+    fn fetches_the_config_before_the_model() {
+        let catalog = Catalog::parse(CATALOG.as_bytes()).unwrap();
+        let files = catalog.files("en_GB-alba-medium");
+        assert_eq!(
+            files,
+            [
+                VoiceFile {
+                    url: format!("{RELEASE_URL}/en/en_GB/alba/medium/en_GB-alba-medium.onnx.json"),
+                    size_bytes: 4888,
+                    md5: "ab21".into(),
+                    extension: ".onnx.json",
+                },
+                VoiceFile {
+                    url: format!("{RELEASE_URL}/en/en_GB/alba/medium/en_GB-alba-medium.onnx"),
+                    size_bytes: 63201294,
+                    md5: "2bd8a2e3c0c4b5b1".into(),
+                    extension: ".onnx",
+                },
+            ]
+        );
+        assert!(catalog.files("xx_XX-missing-low").is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! A voice's `.onnx.json`: sample rate, phonemizer settings and the map
+//! from phonemes to the model's input ids.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const PAD: &str = "_";
+const BOS: &str = "^";
+const EOS: &str = "$";
+
+#[derive(Debug, Deserialize)]
+pub struct VoiceConfig {
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub espeak: EspeakConfig,
+    #[serde(default)]
+    pub inference: InferenceConfig,
+    #[serde(default)]
+    pub phoneme_type: PhonemeType,
+    pub phoneme_id_map: HashMap<String, Vec<i64>>,
+    #[serde(default = "one")]
+    pub num_speakers: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioConfig {
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EspeakConfig {
+    pub voice: String,
+}
+
+impl Default for EspeakConfig {
+    fn default() -> Self {
+        Self {
+            voice: "en-us".to_string(),
+        }
+    }
+}
+
+/// The defaults Piper trains with.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct InferenceConfig {
+    pub noise_scale: f32,
+    pub length_scale: f32,
+    pub noise_w: f32,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            noise_scale: 0.667,
+            length_scale: 1.0,
+            noise_w: 0.8,
+        }
+    }
+}
+
+/// What the model's ids stand for: espeak-ng IPA phonemes, or the
+/// characters of the text itself for languages espeak-ng doesn't cover.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PhonemeType {
+    #[default]
+    Espeak,
+    Text,
+}
+
+impl VoiceConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+        serde_json::from_slice(&json).map_err(|e| format!("parse {}: {e}", path.display()))
+    }
+
+    fn ids(&self, symbol: &str) -> &[i64] {
+        self.phoneme_id_map.get(symbol).map_or(&[], Vec::as_slice)
+    }
+
+    /// Model input for one sentence: `^`, then each phoneme followed by the
+    /// pad, then `$`. Phonemes the voice wasn't trained on are dropped.
+    pub fn phoneme_ids(&self, phonemes: &str) -> Vec<i64> {
+        let mut ids = self.ids(BOS).to_vec();
+        ids.extend_from_slice(self.ids(PAD));
+        let mut symbol = [0u8; 4];
+        for phoneme in phonemes.chars() {
+            let mapped = self.ids(phoneme.encode_utf8(&mut symbol));
+            if mapped.is_empty() {
+                continue;
+            }
+            ids.extend_from_slice(mapped);
+            ids.extend_from_slice(self.ids(PAD));
+        }
+        ids.extend_from_slice(self.ids(EOS));
+        ids
+    }
+
+    /// `length_scale` for a speech rate multiplier: Piper stretches
+    /// phoneme durations rather than resampling, so pitch is kept.
+    pub fn length_scale(&self, rate: f32) -> f32 {
+        self.inference.length_scale / rate.clamp(0.25, 4.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VoiceConfig {
+        serde_json::from_str(
+            r#"{
+                "audio": {"sample_rate": 22050, "quality": "medium"},
+                "espeak": {"voice": "de"},
+                "inference": {"noise_scale": 0.5, "length_scale": 1.2, "noise_w": 0.8},
+                "phoneme_id_map": {
+                    "_": [0], "^": [1], "$": [2], " ": [3],
+                    "h": [20], "a": [14], "ː": [32], "l": [24], "o": [27]
+                },
+                "num_speakers": 1,
+                "speaker_id_map": {}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn intersperses_the_pad_and_drops_unknown_phonemes() {
+        let config = config();
+        assert_eq!(config.phoneme_type, PhonemeType::Espeak);
+        assert_eq!(
+            config.phoneme_ids("haːlo?"),
+            [1, 0, 20, 0, 14, 0, 32, 0, 24, 0, 27, 0, 2]
+        );
+    }
+
+    #[test]
+    fn faster_speech_shortens_phonemes() {
+        let config = config();
+        assert!((config.length_scale(1.0) - 1.2).abs() < 1e-6);
+        assert!((config.length_scale(2.0) - 0.6).abs() < 1e-6);
+        assert!((config.length_scale(100.0) - 0.3).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,632 @@
+use futures_util::TryStreamExt;
+use md5::{Digest, Md5};
+use rodio::Sink;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::io::AsyncWriteExt;
+
+use super::catalog::{self, Catalog, VoiceFile, CATALOG_FILENAME};
+use super::lookahead::{Announcer, Claim, Key, Lookahead, Piece, Rendering};
+use super::model::Model;
+use super::{DOWNLOAD_EVENT, VOICES_DIR};
+use crate::http::Http;
+use crate::models::*;
+use crate::player::{self, Player, PlayerSink};
+use crate::ssml::{Segment, Utterance};
+use crate::{Error, NativeTtsExt, Result};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How often playback checks whether the client has claimed the segment
+/// chained on behind the one it asked for.
+const CLAIM_POLL: Duration = Duration::from_millis(20);
+
+fn tts_error(e: impl Display) -> Error {
+    Error::NativeTTSError(e.to_string())
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "piper-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+struct Settings {
+    engine: TtsEngine,
+    voice: Option<String>,
+    rate: f32,
+}
+
+/// A segment for the renderer thread.
+struct Job {
+    voice: String,
+    rate: f32,
+    utterance: Arc<Utterance>,
+    rendering: Arc<Rendering>,
+}
+
+/// Render `utterance` into `rendering` a sentence at a time, pauses as
+/// silence, until it's done or cancelled.
+fn render(
+    model: &Model,
+    rate: f32,
+    utterance: &Utterance,
+    rendering: &Rendering,
+) -> std::result::Result<(), String> {
+    let sample_rate = model.sample_rate();
+    let text = utterance.text();
+    for segment in utterance.segments() {
+        if rendering.is_cancelled() {
+            break;
+        }
+        match segment {
+            Segment::Pause(ms) => {
+                let len = u64::from(sample_rate) * u64::from(*ms) / 1000;
+                rendering.push(Piece {
+                    range: None,
+                    sample_rate,
+                    samples: vec![0.0; len as usize],
+                });
+            }
+            Segment::Speech(speech) => {
+                model.synthesize(&text[speech.clone()], rate, |range, samples| {
+                    rendering.push(Piece {
+                        range: Some(speech.start + range.start..speech.start + range.end),
+                        sample_rate,
+                        samples,
+                    });
+                    !rendering.is_cancelled()
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Loaded {
+    id: String,
+    model: Arc<Model>,
+}
+
+/// Piper voices behind the plugin's playback commands, plus the manager
+/// for downloading them.
+pub struct Piper<R: Runtime> {
+    app: AppHandle<R>,
+    settings: Mutex<Settings>,
+    /// The last voice used, kept loaded: a model is tens of megabytes and
+    /// takes a moment to load.
+    loaded: Mutex<Option<Loaded>>,
+    /// The sink of the utterance playing now.
+    sink: Mutex<Option<Arc<PlayerSink>>>,
+    /// Segments preloaded for the `speak` calls to come.
+    lookahead: Lookahead,
+    renderer: Mutex<Option<mpsc::Sender<Job>>>,
+    /// Bumped by `stop` and every `speak` that doesn't pick up a chained
+    /// segment, so an utterance that has been superseded stops rendering and
+    /// doesn't report its end.
+    generation: AtomicU64,
+    downloads: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    http: Http,
+}
+
+impl<R: Runtime> Piper<R> {
+    pub fn new(app: &AppHandle<R>, http: Http) -> Self {
+        Self {
+            app: app.clone(),
+            settings: Mutex::new(Settings {
+                engine: TtsEngine::System,
+                voice: None,
+                rate: 1.0,
+            }),
+            loaded: Mutex::new(None),
+            sink: Mutex::new(None),
+            lookahead: Lookahead::new(),
+            renderer: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            downloads: Mutex::new(HashMap::new()),
+            http,
+        }
+    }
+
+    fn voices_dir(&self) -> Result<PathBuf> {
+        Ok(self
+            .app
+            .path()
+            .app_data_dir()
+            .map_err(tts_error)?
+            .join(VOICES_DIR))
+    }
+
+    pub fn set_engine(&self, engine: TtsEngine) -> Result<()> {
+        if engine == TtsEngine::System {
+            self.stop()?;
+            self.lookahead.clear();
+        }
+        self.settings.lock().unwrap().engine = engine;
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.lock().unwrap().engine == TtsEngine::Piper
+    }
+
+    pub fn init(&self) -> Result<InitResponse> {
+        Ok(InitResponse { success: true })
+    }
+
+    fn emit_tts_event(&self, utterance_id: &str, code: &str, message: Option<String>) {
+        self.emit(TTSEventPayload::new(utterance_id, code, message));
+    }
+
+    fn emit(&self, payload: TTSEventPayload) {
+        let (code, utterance_id) = (payload.code.clone(), payload.utterance_id.clone());
+        if let Err(e) = self.app.native_tts().emit_tts_event(payload) {
+            log::warn!("Piper event {code} for {utterance_id}: {e}");
+        }
+    }
+
+    fn model(&self, voice: &str) -> Result<Arc<Model>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(loaded) = loaded.as_ref().filter(|l| l.id == voice) {
+            return Ok(loaded.model.clone());
+        }
+        let dir = self.voices_dir()?;
+        if !catalog::is_installed(&dir, voice) {
+            return Err(tts_error(format!("Piper voice {voice} is not installed")));
+        }
+        let model = Arc::new(
+            Model::load(
+                &catalog::model_path(&dir, voice),
+                &catalog::config_path(&dir, voice),
+            )
+            .map_err(tts_error)?,
+        );
+        *loaded = Some(Loaded {
+            id: voice.to_string(),
+            model: model.clone(),
+        });
+        Ok(model)
+    }
+
+    /// Queue a segment for the renderer thread, which works through them in
+    /// the order they're asked for, preloaded or not.
+    fn render(&self, job: Job) -> Result<()> {
+        let mut renderer = self.renderer.lock().unwrap();
+        let job = match renderer.as_ref().map(|sender| sender.send(job)) {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(mpsc::SendError(job))) => job,
+            None => job,
+        };
+        let (sender, jobs) = mpsc::channel::<Job>();
+        let app = self.app.clone();
+        std::thread::Builder::new()
+            .name("piper-render".into())
+            .spawn(move || {
+                for job in jobs {
+                    let result = if job.rendering.is_cancelled() {
+                        Ok(())
+                    } else {
+                        app.state::<Piper<R>>()
+                            .model(&job.voice)
+                            .map_err(|e| e.to_string())
+                            .and_then(|model| {
+                                render(&model, job.rate, &job.utterance, &job.rendering)
+                            })
+                    };
+                    job.rendering.finish(result);
+                }
+            })
+            .map_err(tts_error)?;
+        sender.send(job).map_err(tts_error)?;
+        *renderer = Some(sender);
+        Ok(())
+    }
+
+    /// Queue `payload` to be announced when playback reaches this point.
+    fn announce_at(&self, sink: &Sink, announcer: &Arc<Announcer>, payload: TTSEventPayload) {
+        let (app, announcer) = (self.app.clone(), announcer.clone());
+        player::append_callback(sink, move || {
+            announcer.announce(payload.clone(), |payload| {
+                app.state::<Piper<R>>().emit(payload);
+            });
+        });
+    }
+
+    /// Play a segment as it's rendered, then the queued segments after it
+    /// for as long as the client claims them. Each sentence is announced as
+    /// a `sentence` boundary as it starts to play, Piper having no word
+    /// timings to offer, and each segment's `end` as it finishes.
+    fn play(
+        &self,
+        generation: u64,
+        mut utterance: Arc<Utterance>,
+        mut rendering: Arc<Rendering>,
+        mut announcer: Arc<Announcer>,
+    ) {
+        let sink = match Player::shared().and_then(|player| player.sink()) {
+            Ok(sink) => sink,
+            Err(e) => {
+                let payload = TTSEventPayload::new("", "error", Some(e));
+                return announcer.announce(payload, |payload| self.emit(payload));
+            }
+        };
+        {
+            let mut current = self.sink.lock().unwrap();
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            *current = Some(sink.clone());
+        }
+        let live = || self.generation.load(Ordering::SeqCst) == generation;
+        loop {
+            let text = utterance.text();
+            let mut index = 0;
+            let ended = loop {
+                match rendering.piece(index, live) {
+                    Ok(Some(piece)) => {
+                        if let Some(range) = piece.range {
+                            let payload = TTSEventPayload::range(
+                                "",
+                                "sentence",
+                                text,
+                                range.start,
+                                range.end,
+                            );
+                            self.announce_at(&sink, &announcer, payload);
+                        }
+                        player::append(&sink, piece.sample_rate, piece.samples);
+                        index += 1;
+                    }
+                    Ok(None) => break TTSEventPayload::new("", "end", None),
+                    Err(e) => break TTSEventPayload::new("", "error", Some(e)),
+                }
+            };
+            if !live() {
+                // Free the renderer for what superseded it, unless this is
+                // still queued to be claimed.
+                if announcer.is_claimed() {
+                    rendering.cancel();
+                }
+                return;
+            }
+            let failed = ended.code == "error";
+            self.announce_at(&sink, &announcer, ended);
+            if failed {
+                return;
+            }
+            // Run ahead of the client by one segment at most.
+            while !announcer.is_claimed() {
+                if !live() {
+                    return;
+                }
+                if sink.empty() {
+                    self.lookahead.unchain(&announcer);
+                    return;
+                }
+                std::thread::sleep(CLAIM_POLL);
+            }
+            let Some(next) = self.lookahead.chain(generation) else {
+                return;
+            };
+            (utterance, rendering, announcer) = next;
+            let start = TTSEventPayload::new("", "boundary", Some("start".to_string()));
+            self.announce_at(&sink, &announcer, start);
+        }
+    }
+
+    /// Start speaking `text`, plain or SSML, in the background; progress
+    /// arrives as `tts_events` for the returned utterance id, as with the
+    /// system engines. With `preload`, the text is only rendered ahead, for
+    /// a `speak` to come.
+    pub fn speak(&self, args: SpeakArgs) -> Result<SpeakResponse> {
+        let utterance_id = new_id();
+        let (voice, rate) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.voice.clone(), settings.rate)
+        };
+        let voice = match voice {
+            Some(voice) => voice,
+            None => catalog::installed(&self.voices_dir()?)
+                .into_iter()
+                .next()
+                .map(|voice| voice.id)
+                .ok_or_else(|| tts_error("no Piper voice installed"))?,
+        };
+        let utterance = Utterance::new(&args.text, args.ssml);
+        if utterance.is_silent() && !args.preload {
+            let app = self.app.clone();
+            let id = utterance_id.clone();
+            std::thread::Builder::new()
+                .name("piper-speak".into())
+                .spawn(move || app.state::<Piper<R>>().emit_tts_event(&id, "end", None))
+                .map_err(tts_error)?;
+            return Ok(SpeakResponse { utterance_id });
+        }
+        let key = Key {
+            voice: voice.clone(),
+            rate,
+            text: args.text,
+            ssml: args.ssml,
+        };
+
+        if args.preload {
+            if let Some((utterance, rendering)) = self.lookahead.offer(key, utterance) {
+                self.render(Job {
+                    voice,
+                    rate,
+                    utterance,
+                    rendering,
+                })?;
+            }
+            self.emit_tts_event(&utterance_id, "end", None);
+            return Ok(SpeakResponse { utterance_id });
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let claim = self
+            .lookahead
+            .claim(&key, generation, &utterance_id, |payload| {
+                self.emit(payload)
+            });
+        let (utterance, rendering) = match claim {
+            // Already playing on from the segment before.
+            Claim::Chained => return Ok(SpeakResponse { utterance_id }),
+            Claim::Ready(utterance, rendering) => (utterance, rendering),
+            Claim::Missed => {
+                let (utterance, rendering) = (Arc::new(utterance), Rendering::new());
+                self.render(Job {
+                    voice,
+                    rate,
+                    utterance: utterance.clone(),
+                    rendering: rendering.clone(),
+                })?;
+                (utterance, rendering)
+            }
+        };
+        let generation = self.interrupt();
+        let app = self.app.clone();
+        let id = utterance_id.clone();
+        std::thread::Builder::new()
+            .name("piper-speak".into())
+            .spawn(move || {
+                let piper = app.state::<Piper<R>>();
+                piper.emit_tts_event(&id, "boundary", Some("start".to_string()));
+                piper.play(generation, utterance, rendering, Announcer::claimed(&id));
+            })
+            .map_err(tts_error)?;
+        Ok(SpeakResponse { utterance_id })
+    }
+
+    /// Supersede whatever is playing; returns the new generation.
+    fn interrupt(&self) -> u64 {
+        let mut sink = self.sink.lock().unwrap();
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(sink) = sink.take() {
+            sink.stop();
+        }
+        generation
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.pause();
+        }
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.play();
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.interrupt();
+        Ok(())
+    }
+
+    /// Takes effect from the next utterance; Piper changes the pace by
+    /// stretching phonemes as it renders them, keeping the pitch.
+    pub fn set_rate(&self, args: SetRateArgs) -> Result<()> {
+        self.settings.lock().unwrap().rate = args.rate;
+        Ok(())
+    }
+
+    /// Piper voices have a fixed pitch; accepted so the client needn't care
+    /// which engine it's driving.
+    pub fn set_pitch(&self, _args: SetPitchArgs) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_voice(&self, args: SetVoiceArgs) -> Result<()> {
+        self.settings.lock().unwrap().voice = Some(args.voice);
+        Ok(())
+    }
+
+    /// The installed voices, in the shape the system engines list theirs.
+    pub fn get_all_voices(&self) -> Result<GetVoicesResponse> {
+        let voices = catalog::installed(&self.voices_dir()?)
+            .into_iter()
+            .map(|voice| TTSVoice {
+                name: format!("{} ({})", voice.name, voice.quality.replace('_', " ")),
+                id: voice.id,
+                lang: voice.lang,
+                disabled: false,
+            })
+            .collect();
+        Ok(GetVoicesResponse { voices })
+    }
+
+    async fn catalog(&self, dir: &Path) -> Result<Catalog> {
+        let cached = dir.join(CATALOG_FILENAME);
+        let fetched = async {
+            let response = self
+                .http
+                .client()
+                .map_err(tts_error)?
+                .get(catalog::catalog_url())
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(tts_error)?;
+            let json = response.bytes().await.map_err(tts_error)?;
+            let catalog = Catalog::parse(&json).map_err(tts_error)?;
+            tokio::fs::create_dir_all(dir).await?;
+            tokio::fs::write(&cached, &json).await?;
+            Ok::<_, Error>(catalog)
+        };
+        match fetched.await {
+            Ok(catalog) => Ok(catalog),
+            Err(e) => {
+                log::warn!("fetch Piper voice catalog: {e}");
+                let json = tokio::fs::read(&cached).await.map_err(|_| e)?;
+                Catalog::parse(&json).map_err(tts_error)
+            }
+        }
+    }
+
+    /// Every voice in the catalog, or just the installed ones when it can't
+    /// be fetched and was never cached.
+    pub async fn list_voices(&self) -> Result<PiperVoicesResponse> {
+        let dir = self.voices_dir()?;
+        let voices = match self.catalog(&dir).await {
+            Ok(catalog) => catalog.voices(&dir),
+            Err(_) => catalog::installed(&dir),
+        };
+        Ok(PiperVoicesResponse { voices })
+    }
+
+    /// Download a voice from the catalog, emitting `DOWNLOAD_EVENT` as it
+    /// goes. Files are checked against the catalog's MD5 before they're
+    /// moved into place, so a voice is either fully installed or absent.
+    pub async fn download_voice(&self, args: PiperVoiceArgs) -> Result<()> {
+        let voice = args.voice;
+        let dir = self.voices_dir()?;
+        let files = self.catalog(&dir).await?.files(&voice);
+        if files.is_empty() {
+            return Err(tts_error(format!("unknown Piper voice {voice}")));
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        {
+            let mut downloads = self.downloads.lock().unwrap();
+            if downloads.contains_key(&voice) {
+                return Err(tts_error(format!("{voice} is already downloading")));
+            }
+            downloads.insert(voice.clone(), cancel.clone());
+        }
+
+        let mut progress = PiperDownloadProgress {
+            voice: voice.clone(),
+            downloaded: 0,
+            total: files.iter().map(|f| f.size_bytes).sum(),
+        };
+        let mut result = tokio::fs::create_dir_all(&dir).await.map_err(Error::from);
+        for file in &files {
+            if result.is_err() {
+                break;
+            }
+            let dest = dir.join(format!("{voice}{}", file.extension));
+            result = self.fetch(file, &dest, &cancel, &mut progress).await;
+        }
+        self.downloads.lock().unwrap().remove(&voice);
+        if result.is_err() {
+            for file in &files {
+                let _ =
+                    tokio::fs::remove_file(dir.join(format!("{voice}{}", file.extension))).await;
+            }
+        }
+        result
+    }
+
+    async fn fetch(
+        &self,
+        file: &VoiceFile,
+        dest: &Path,
+        cancel: &AtomicBool,
+        progress: &mut PiperDownloadProgress,
+    ) -> Result<()> {
+        let part = PathBuf::from(format!("{}.part", dest.display()));
+        let result = async {
+            let response = self
+                .http
+                .client()
+                .map_err(tts_error)?
+                .get(&file.url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(tts_error)?;
+            let mut out = tokio::fs::File::create(&part).await?;
+            let mut hasher = Md5::new();
+            let mut stream = response.bytes_stream();
+            let mut last_progress = Instant::now();
+            while let Some(chunk) = stream.try_next().await.map_err(tts_error)? {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(tts_error("download cancelled"));
+                }
+                hasher.update(&chunk);
+                out.write_all(&chunk).await?;
+                progress.downloaded += chunk.len() as u64;
+                if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                    last_progress = Instant::now();
+                    let _ = self.app.emit(DOWNLOAD_EVENT, progress.clone());
+                }
+            }
+            out.flush().await?;
+            if format!("{:x}", hasher.finalize()) != file.md5 {
+                return Err(tts_error(format!("checksum mismatch for {}", file.url)));
+            }
+            tokio::fs::rename(&part, dest).await?;
+            let _ = self.app.emit(DOWNLOAD_EVENT, progress.clone());
+            Ok::<(), Error>(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&part).await;
+        }
+        result
+    }
+
+    pub fn cancel_download(&self, args: PiperVoiceArgs) -> Result<()> {
+        if let Some(cancel) = self.downloads.lock().unwrap().get(&args.voice) {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    pub fn delete_voice(&self, args: PiperVoiceArgs) -> Result<()> {
+        let was_loaded = {
+            let mut loaded = self.loaded.lock().unwrap();
+            let hit = loaded.as_ref().is_some_and(|l| l.id == args.voice);
+            if hit {
+                *loaded = None;
+            }
+            hit
+        };
+        if was_loaded {
+            self.interrupt();
+        }
+        let dir = self.voices_dir()?;
+        for path in [
+            catalog::model_path(&dir, &args.voice),
+            catalog::config_path(&dir, &args.voice),
+        ] {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Segments rendered ahead of their `speak`, so playback isn't held up at
+//! every sentence while Piper synthesizes the next one.
+//!
+//! A `speak` with `preload` offers its text to the queue; up to
+//! [`LOOKAHEAD`] segments are rendered in the background, in order, into a
+//! [`Rendering`] each. Once the segment playing has been handed to the
+//! sink, the head of the queue is chained on behind it, so there's no gap
+//! for the client's round trip either. A chained segment's events wait in
+//! its [`Announcer`] until the `speak` for that text claims it.
+//!
+//! A `speak` for text that isn't queued means the reader moved elsewhere:
+//! the queue is dropped and its renderings cancelled. `stop` doesn't touch
+//! the queue, as the client stops between paragraphs too, but a segment
+//! chained before it is played afresh when claimed.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::models::TTSEventPayload;
+use crate::ssml::Utterance;
+
+/// Segments rendered ahead of the one playing.
+pub const LOOKAHEAD: usize = 2;
+/// How often a wait for the renderer checks whether it's still wanted.
+const POLL: Duration = Duration::from_millis(20);
+
+/// What a segment's audio depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub voice: String,
+    pub rate: f32,
+    pub text: String,
+    pub ssml: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Piece {
+    /// The sentence this is, as a byte range of the utterance text; `None`
+    /// for a pause.
+    pub range: Option<Range<usize>>,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+#[derive(Default)]
+struct Rendered {
+    pieces: Vec<Piece>,
+    done: bool,
+    error: Option<String>,
+}
+
+/// One segment's audio, filled in by the renderer while it's played.
+#[derive(Default)]
+pub struct Rendering {
+    rendered: Mutex<Rendered>,
+    changed: Condvar,
+    cancelled: AtomicBool,
+}
+
+impl Rendering {
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    pub fn push(&self, piece: Piece) {
+        self.rendered.lock().unwrap().pieces.push(piece);
+        self.changed.notify_all();
+    }
+
+    pub fn finish(&self, result: Result<(), String>) {
+        let mut rendered = self.rendered.lock().unwrap();
+        rendered.done = true;
+        rendered.error = result.err();
+        self.changed.notify_all();
+    }
+
+    /// Ask the renderer to give up; it checks between sentences.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.changed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Piece `index`, waiting for it to be rendered while `live` holds;
+    /// `Ok(None)` once there are no more or it's no longer wanted.
+    pub fn piece(&self, index: usize, live: impl Fn() -> bool) -> Result<Option<Piece>, String> {
+        let mut rendered = self.rendered.lock().unwrap();
+        loop {
+            if let Some(piece) = rendered.pieces.get(index) {
+                return Ok(Some(piece.clone()));
+            }
+            if let Some(e) = &rendered.error {
+                return Err(e.clone());
+            }
+            if rendered.done || self.is_cancelled() || !live() {
+                return Ok(None);
+            }
+            rendered = self.changed.wait_timeout(rendered, POLL).unwrap().0;
+        }
+    }
+}
+
+/// Where a segment's events go: to its utterance id once it has one, held
+/// until then.
+#[derive(Default)]
+pub struct Announcer(Mutex<(Option<String>, Vec<TTSEventPayload>)>);
+
+impl Announcer {
+    pub fn claimed(utterance_id: &str) -> Arc<Self> {
+        Arc::new(Self(Mutex::new((
+            Some(utterance_id.to_string()),
+            Vec::new(),
+        ))))
+    }
+
+    pub fn is_claimed(&self) -> bool {
+        self.0.lock().unwrap().0.is_some()
+    }
+
+    /// `payload`'s utterance id is filled in here.
+    pub fn announce(&self, mut payload: TTSEventPayload, emit: impl Fn(TTSEventPayload)) {
+        let mut state = self.0.lock().unwrap();
+        match &state.0 {
+            Some(id) => {
+                payload.utterance_id = id.clone();
+                emit(payload);
+            }
+            None => state.1.push(payload),
+        }
+    }
+
+    /// Give the segment its id and emit what it held, in order.
+    fn claim(&self, utterance_id: &str, emit: impl Fn(TTSEventPayload)) {
+        let mut state = self.0.lock().unwrap();
+        state.0 = Some(utterance_id.to_string());
+        for mut payload in state.1.drain(..) {
+            payload.utterance_id = utterance_id.to_string();
+            emit(payload);
+        }
+    }
+}
+
+struct Ahead {
+    key: Key,
+    utterance: Arc<Utterance>,
+    rendering: Arc<Rendering>,
+    /// Set while chained behind the segment that was playing in that
+    /// generation.
+    chained: Option<(u64, Arc<Announcer>)>,
+}
+
+pub enum Claim {
+    /// Already chained on and playing, or about to; its events now go out.
+    Chained,
+    /// Rendered or rendering, to be played.
+    Ready(Arc<Utterance>, Arc<Rendering>),
+    Missed,
+}
+
+#[derive(Default)]
+pub struct Lookahead(Mutex<VecDeque<Ahead>>);
+
+impl Lookahead {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a segment to be rendered, returning what the renderer should
+    /// fill; `None` if it's queued already or the queue is full.
+    pub fn offer(
+        &self,
+        key: Key,
+        utterance: Utterance,
+    ) -> Option<(Arc<Utterance>, Arc<Rendering>)> {
+        let mut queue = self.0.lock().unwrap();
+        if queue.len() >= LOOKAHEAD || queue.iter().any(|ahead| ahead.key == key) {
+            return None;
+        }
+        let (utterance, rendering) = (Arc::new(utterance), Rendering::new());
+        queue.push_back(Ahead {
+            key,
+            utterance: utterance.clone(),
+            rendering: rendering.clone(),
+            chained: None,
+        });
+        Some((utterance, rendering))
+    }
+
+    /// Take the queued segment for `key`, dropping any queued before it, or
+    /// everything when it isn't queued. A segment chained in `generation`
+    /// is given `utterance_id` and left playing.
+    pub fn claim(
+        &self,
+        key: &Key,
+        generation: u64,
+        utterance_id: &str,
+        emit: impl Fn(TTSEventPayload),
+    ) -> Claim {
+        let mut queue = self.0.lock().unwrap();
+        let Some(index) = queue.iter().position(|ahead| &ahead.key == key) else {
+            for ahead in queue.drain(..) {
+                ahead.rendering.cancel();
+            }
+            return Claim::Missed;
+        };
+        for ahead in queue.drain(..index) {
+            ahead.rendering.cancel();
+        }
+        let ahead = queue.pop_front().unwrap();
+        match ahead.chained {
+            Some((chained_in, announcer)) if chained_in == generation => {
+                announcer.claim(utterance_id, emit);
+                Claim::Chained
+            }
+            _ => Claim::Ready(ahead.utterance, ahead.rendering),
+        }
+    }
+
+    /// The segment to chain on behind the one playing in `generation`: the
+    /// head of the queue, unless it's chained already.
+    pub fn chain(
+        &self,
+        generation: u64,
+    ) -> Option<(Arc<Utterance>, Arc<Rendering>, Arc<Announcer>)> {
+        let mut queue = self.0.lock().unwrap();
+        let head = queue.front_mut()?;
+        if matches!(&head.chained, Some((chained_in, _)) if *chained_in == generation) {
+            return None;
+        }
+        let announcer = Arc::new(Announcer::default());
+        head.chained = Some((generation, announcer.clone()));
+        Some((head.utterance.clone(), head.rendering.clone(), announcer))
+    }
+
+    /// Played out without being claimed: play it afresh when it is.
+    pub fn unchain(&self, announcer: &Arc<Announcer>) {
+        let mut queue = self.0.lock().unwrap();
+        for ahead in queue.iter_mut() {
+            let chained_here = matches!(&ahead.chained, Some((_, a)) if Arc::ptr_eq(a, announcer));
+            if chained_here && !announcer.is_claimed() {
+                ahead.chained = None;
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        for ahead in self.0.lock().unwrap().drain(..) {
+            ahead.rendering.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn key(text: &str) -> Key {
+        Key {
+            voice: "en_US-lessac-medium".to_string(),
+            rate: 1.0,
+            text: text.to_string(),
+            ssml: false,
+        }
+    }
+
+    fn offer(lookahead: &Lookahead, text: &str) -> Option<Arc<Rendering>> {
+        lookahead
+            .offer(key(text), Utterance::plain(text))
+            .map(|(_, rendering)| rendering)
+    }
+
+    #[test]
+    fn queues_a_bounded_number_of_distinct_segments() {
+        let lookahead = Lookahead::new();
+        assert!(offer(&lookahead, "One.").is_some());
+        assert!(offer(&lookahead, "One.").is_none());
+        assert!(offer(&lookahead, "Two.").is_some());
+        assert!(offer(&lookahead, "Three.").is_none());
+    }
+
+    #[test]
+    fn claiming_drops_what_was_skipped() {
+        let lookahead = Lookahead::new();
+        let one = offer(&lookahead, "One.").unwrap();
+        let two = offer(&lookahead, "Two.").unwrap();
+        assert!(matches!(
+            lookahead.claim(&key("Two."), 0, "u", |_| {}),
+            Claim::Ready(..)
+        ));
+        assert!(one.is_cancelled() && !two.is_cancelled());
+
+        let three = offer(&lookahead, "Three.").unwrap();
+        assert!(matches!(
+            lookahead.claim(&key("Elsewhere."), 0, "u", |_| {}),
+            Claim::Missed
+        ));
+        assert!(three.is_cancelled());
+    }
+
+    #[test]
+    fn chained_events_wait_for_the_claim() {
+        let lookahead = Lookahead::new();
+        offer(&lookahead, "One.");
+        let (_, _, announcer) = lookahead.chain(1).unwrap();
+        assert!(lookahead.chain(1).is_none());
+        let emitted = RefCell::new(Vec::new());
+        let emit = |payload: TTSEventPayload| {
+            emitted
+                .borrow_mut()
+                .push(format!("{} {}", payload.utterance_id, payload.code))
+        };
+        announcer.announce(TTSEventPayload::new("", "end", None), emit);
+        assert!(emitted.borrow().is_empty());
+        assert!(matches!(
+            lookahead.claim(&key("One."), 1, "u1", emit),
+            Claim::Chained
+        ));
+        assert_eq!(*emitted.borrow(), ["u1 end"]);
+    }
+
+    #[test]
+    fn a_stop_or_an_unclaimed_play_unchains() {
+        let lookahead = Lookahead::new();
+        offer(&lookahead, "One.");
+        lookahead.chain(1).unwrap();
+        // Stopped since: generation 2 plays it afresh.
+        assert!(matches!(
+            lookahead.claim(&key("One."), 2, "u", |_| {}),
+            Claim::Ready(..)
+        ));
+
+        offer(&lookahead, "Two.");
+        let (_, _, announcer) = lookahead.chain(2).unwrap();
+        lookahead.unchain(&announcer);
+        assert!(matches!(
+            lookahead.claim(&key("Two."), 2, "u", |_| {}),
+            Claim::Ready(..)
+        ));
+    }
+
+    #[test]
+    fn waits_for_pieces_until_rendering_ends() {
+        let rendering = Rendering::new();
+        let renderer = rendering.clone();
+        let thread = std::thread::spawn(move || {
+            renderer.push(Piece {
+                range: Some(0..4),
+                sample_rate: 22050,
+                samples: vec![0.5; 4],
+            });
+            renderer.finish(Ok(()));
+        });
+        let first = rendering.piece(0, || true).unwrap().unwrap();
+        assert_eq!(first.range, Some(0..4));
+        thread.join().unwrap();
+        assert!(rendering.piece(1, || true).unwrap().is_none());
+        assert!(Rendering::new().piece(0, || false).unwrap().is_none());
+    }
+}
@@ -0,0 +1,45 @@
+//! Piper neural voices, behind the `piper` feature.
+//!
+//! Piper (<https://github.com/rhasspy/piper>) runs VITS voice models of a
+//! few tens of megabytes through onnxruntime, fast enough for real time on
+//! a phone, and sounds far better than the robotic Android engines or the
+//! espeak voices that are often all a Linux desktop has. Text is turned into
+//! IPA with espeak-ng ([`phonemize`]), then into the model's phoneme ids
+//! ([`config`]), and the model renders it a sentence at a time ([`model`])
+//! into rodio ([`crate::player`]). Segments the client preloads are rendered
+//! ahead and played straight on from the one before ([`lookahead`]).
+//!
+//! `set_engine` switches the plugin's `speak`, `pause`, `resume`, `stop`,
+//! `set_rate`, `set_voice` and `get_all_voices` over to Piper, so the client
+//! drives it exactly as it does the system voice, `tts_events` included.
+//! Voices are listed from the published catalog and downloaded into
+//! `piper-voices/` in the app data dir ([`catalog`]) with the app's HTTP
+//! client ([`crate::http`]), with progress on `native-tts://piper-download`.
+//!
+//! Builds without the feature get [`Piper`] from `unavailable.rs`, which
+//! stays on the system engine and reports Piper as not built in.
+
+#[cfg(feature = "piper")]
+mod catalog;
+#[cfg(feature = "piper")]
+mod config;
+#[cfg(feature = "piper")]
+mod engine;
+#[cfg(feature = "piper")]
+mod lookahead;
+#[cfg(feature = "piper")]
+mod model;
+#[cfg(feature = "piper")]
+mod phonemize;
+#[cfg(not(feature = "piper"))]
+mod unavailable;
+
+#[cfg(feature = "piper")]
+pub use engine::Piper;
+#[cfg(not(feature = "piper"))]
+pub use unavailable::Piper;
+
+#[cfg_attr(not(feature = "piper"), allow(dead_code))]
+pub const DOWNLOAD_EVENT: &str = "native-tts://piper-download";
+#[cfg_attr(not(feature = "piper"), allow(dead_code))]
+const VOICES_DIR: &str = "piper-voices";
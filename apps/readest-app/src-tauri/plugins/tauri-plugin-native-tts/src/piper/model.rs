@@ -0,0 +1,97 @@
+//! A loaded Piper voice: the VITS model in an onnxruntime session plus its
+//! config.
+
+use ort::session::{builder::GraphOptimizationLevel, Session};
+use ort::value::Tensor;
+use std::ops::Range;
+use std::path::Path;
+
+use super::config::{PhonemeType, VoiceConfig};
+use super::phonemize::{phonemize, sentences};
+
+/// Pause between sentences, which the model renders one at a time.
+const SENTENCE_GAP_MS: u32 = 200;
+
+pub struct Model {
+    session: Session,
+    pub config: VoiceConfig,
+}
+
+impl Model {
+    pub fn load(onnx: &Path, config: &Path) -> Result<Self, String> {
+        let config = VoiceConfig::load(config)?;
+        let session = Session::builder()
+            .and_then(|b| b.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|b| b.commit_from_file(onnx))
+            .map_err(|e| format!("load {}: {e}", onnx.display()))?;
+        Ok(Self { session, config })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.audio.sample_rate
+    }
+
+    fn infer(&self, ids: Vec<i64>, rate: f32) -> ort::Result<Vec<f32>> {
+        let inference = &self.config.inference;
+        let len = ids.len();
+        let input = Tensor::from_array(([1usize, len], ids))?;
+        let lengths = Tensor::from_array(([1usize], vec![len as i64]))?;
+        let scales = Tensor::from_array((
+            [3usize],
+            vec![
+                inference.noise_scale,
+                self.config.length_scale(rate),
+                inference.noise_w,
+            ],
+        ))?;
+        let outputs = if self.config.num_speakers > 1 {
+            // Multi-speaker voices take a speaker id; the first is the one
+            // the catalog samples are recorded with.
+            let sid = Tensor::from_array(([1usize], vec![0i64]))?;
+            self.session.run(ort::inputs![
+                "input" => input,
+                "input_lengths" => lengths,
+                "scales" => scales,
+                "sid" => sid,
+            ]?)?
+        } else {
+            self.session.run(ort::inputs![
+                "input" => input,
+                "input_lengths" => lengths,
+                "scales" => scales,
+            ]?)?
+        };
+        let (_, samples) = outputs["output"].try_extract_raw_tensor::<f32>()?;
+        Ok(samples.to_vec())
+    }
+
+    /// Speak `text` a sentence at a time, handing each one's byte range in
+    /// `text` and samples (mono, at [`Self::sample_rate`]) to `sink` as soon
+    /// as it's rendered, so playback can start before the paragraph is
+    /// done. Stops early when `sink` returns false.
+    pub fn synthesize(
+        &self,
+        text: &str,
+        rate: f32,
+        mut sink: impl FnMut(Range<usize>, Vec<f32>) -> bool,
+    ) -> Result<(), String> {
+        let gap = (u64::from(self.sample_rate()) * u64::from(SENTENCE_GAP_MS) / 1000) as usize;
+        for (sentence, terminator) in sentences(text) {
+            let phonemes = match self.config.phoneme_type {
+                PhonemeType::Espeak => phonemize(sentence, terminator, &self.config.espeak.voice)?,
+                PhonemeType::Text => format!("{sentence}{terminator}"),
+            };
+            let ids = self.config.phoneme_ids(&phonemes);
+            let mut samples = self
+                .infer(ids, rate)
+                .map_err(|e| format!("Piper inference failed: {e}"))?;
+            samples.resize(samples.len() + gap, 0.0);
+            // `sentences` hands out slices of `text`.
+            let start = sentence.as_ptr() as usize - text.as_ptr() as usize;
+            if !sink(start..start + sentence.len(), samples) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Text to IPA phonemes through the espeak-ng library, the phonemizer
+//! Piper voices are trained against.
+//!
+//! espeak-ng keeps its voice and dictionaries in globals, so every call goes
+//! through one lock. Its data is found the usual way: `ESPEAK_DATA_PATH`,
+//! then the compiled-in location.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+use std::sync::{Mutex, PoisonError};
+
+const AUDIO_OUTPUT_SYNCHRONOUS: c_int = 2;
+const CHARS_UTF8: c_int = 1;
+const PHONEMES_IPA: c_int = 0x02;
+const EE_OK: c_int = 0;
+
+#[link(name = "espeak-ng")]
+extern "C" {
+    fn espeak_Initialize(
+        output: c_int,
+        buflength: c_int,
+        path: *const c_char,
+        options: c_int,
+    ) -> c_int;
+    fn espeak_SetVoiceByName(name: *const c_char) -> c_int;
+    fn espeak_TextToPhonemes(
+        textptr: *mut *const c_void,
+        textmode: c_int,
+        phonememode: c_int,
+    ) -> *const c_char;
+}
+
+struct Espeak {
+    initialized: bool,
+    voice: String,
+}
+
+static ESPEAK: Mutex<Espeak> = Mutex::new(Espeak {
+    initialized: false,
+    voice: String::new(),
+});
+
+/// Marks that end a sentence when followed by whitespace or the end.
+const TERMINATORS: &[char] = &['.', '!', '?', ';', ':', '…'];
+
+/// Split text into sentences, each with the punctuation that ended it
+/// (`.` when none did). Piper voices learned intonation from these marks,
+/// and espeak-ng drops them from its output.
+pub fn sentences(text: &str) -> Vec<(&str, char)> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_break = chars.peek().map_or(true, |&(_, next)| next.is_whitespace());
+        if !TERMINATORS.contains(&c) || !at_break {
+            continue;
+        }
+        let sentence = text[start..i].trim();
+        if sentence.chars().any(char::is_alphanumeric) {
+            out.push((sentence, if c == '…' { '.' } else { c }));
+        }
+        start = i + c.len_utf8();
+    }
+    let rest = text[start..].trim();
+    if rest.chars().any(char::is_alphanumeric) {
+        out.push((rest, '.'));
+    }
+    out
+}
+
+/// IPA phonemes for one sentence in espeak-ng voice `voice`, clauses
+/// joined with a comma and the sentence's own terminator at the end.
+pub fn phonemize(sentence: &str, terminator: char, voice: &str) -> Result<String, String> {
+    let text = CString::new(sentence.replace('\0', " ")).map_err(|e| e.to_string())?;
+    let mut espeak = ESPEAK.lock().unwrap_or_else(PoisonError::into_inner);
+    if !espeak.initialized {
+        // SAFETY: called once, under the lock, with a null data path.
+        let rate = unsafe { espeak_Initialize(AUDIO_OUTPUT_SYNCHRONOUS, 0, ptr::null(), 0) };
+        if rate < 0 {
+            return Err("espeak-ng failed to initialize; is its data installed?".to_string());
+        }
+        espeak.initialized = true;
+    }
+    if espeak.voice != voice {
+        let name = CString::new(voice).map_err(|e| e.to_string())?;
+        // SAFETY: `name` outlives the call; espeak copies what it keeps.
+        if unsafe { espeak_SetVoiceByName(name.as_ptr()) } != EE_OK {
+            return Err(format!("espeak-ng has no voice {voice}"));
+        }
+        espeak.voice = voice.to_string();
+    }
+
+    let mut clauses = Vec::new();
+    let mut cursor = text.as_ptr().cast::<c_void>();
+    while !cursor.is_null() {
+        // SAFETY: `cursor` points into `text`, which outlives the loop;
+        // espeak advances it clause by clause and nulls it at the end. The
+        // returned buffer is valid until the next call and copied at once.
+        let phonemes = unsafe { espeak_TextToPhonemes(&mut cursor, CHARS_UTF8, PHONEMES_IPA) };
+        if phonemes.is_null() {
+            break;
+        }
+        let clause = unsafe { CStr::from_ptr(phonemes) }.to_string_lossy();
+        let clause = clause.trim();
+        if !clause.is_empty() {
+            clauses.push(clause.to_string());
+        }
+    }
+    let mut phonemes = clauses.join(", ");
+    phonemes.push(terminator);
+    Ok(phonemes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sentences_on_terminators_before_whitespace() {
+        assert_eq!(
+            sentences("Watson? Yes… 3.5 litres; fine.  And then"),
+            [
+                ("Watson", '?'),
+                ("Yes", '.'),
+                ("3.5 litres", ';'),
+                ("fine", '.'),
+                ("And then", '.'),
+            ]
+        );
+        assert!(sentences(" ... — ").is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use std::marker::PhantomData;
+use tauri::{AppHandle, Runtime};
+
+use crate::http::Http;
+use crate::models::*;
+use crate::{Error, Result};
+
+fn unavailable() -> Error {
+    Error::NativeTTSError("Piper voices are not available in this build".to_string())
+}
+
+/// Stand-in for builds without the `piper` feature: the system engine is
+/// the only one, and the voice manager reports Piper as unavailable.
+pub struct Piper<R: Runtime>(PhantomData<fn() -> R>);
+
+impl<R: Runtime> Piper<R> {
+    pub fn new(_app: &AppHandle<R>, _http: Http) -> Self {
+        Self(PhantomData)
+    }
+    pub fn set_engine(&self, engine: TtsEngine) -> Result<()> {
+        match engine {
+            TtsEngine::System => Ok(()),
+            TtsEngine::Piper => Err(unavailable()),
+        }
+    }
+    pub fn is_active(&self) -> bool {
+        false
+    }
+    pub fn init(&self) -> Result<InitResponse> {
+        Err(unavailable())
+    }
+    pub fn speak(&self, _args: SpeakArgs) -> Result<SpeakResponse> {
+        Err(unavailable())
+    }
+    pub fn pause(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn resume(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn stop(&self) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn set_rate(&self, _args: SetRateArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn set_pitch(&self, _args: SetPitchArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn set_voice(&self, _args: SetVoiceArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn get_all_voices(&self) -> Result<GetVoicesResponse> {
+        Err(unavailable())
+    }
+    pub async fn list_voices(&self) -> Result<PiperVoicesResponse> {
+        Err(unavailable())
+    }
+    pub async fn download_voice(&self, _args: PiperVoiceArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn cancel_download(&self, _args: PiperVoiceArgs) -> Result<()> {
+        Err(unavailable())
+    }
+    pub fn delete_voice(&self, _args: PiperVoiceArgs) -> Result<()> {
+        Err(unavailable())
+    }
+}
@@ -0,0 +1,297 @@
+//! Audio output for the engines that render speech in-process, Piper and
+//! the cloud voices.
+//!
+//! rodio's `OutputStream` isn't `Send`, so it's opened on a thread of its
+//! own; everything else goes through the stream handle and per-utterance
+//! sinks, which are. Both engines share the one device, opened on first
+//! use: the system default, or the one picked with [`select`]. A new pick
+//! is opened for the next utterance, and the old device closed once the
+//! sinks playing on it are gone.
+//!
+//! cpal has no device ids or change notifications, so outputs go by name
+//! and [`watch`] polls for them coming and going.
+//!
+//! [`set_volume`] reaches every sink still playing, and those opened after;
+//! the sleep timer fades out with it.
+
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{cpal, OutputStream, OutputStreamHandle, Sink};
+use std::ops::Deref;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::models::{AudioOutput, AudioOutputKind};
+
+static SHARED: Mutex<Option<Arc<Player>>> = Mutex::new(None);
+/// Name of the device picked with [`select`]; `None` follows the default.
+static SELECTED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Volume of every sink, lowered by [`set_volume`].
+static VOLUME: Mutex<f32> = Mutex::new(1.0);
+/// The sinks handed out, for [`set_volume`] to reach the ones still around.
+static SINKS: Mutex<Vec<Weak<PlayerSink>>> = Mutex::new(Vec::new());
+
+/// How often [`watch`] looks at the devices while one is open.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct Player {
+    handle: OutputStreamHandle,
+    /// Name of the device opened.
+    device: String,
+    /// Dropped with the player, which lets the audio thread close the
+    /// device.
+    _close: mpsc::Sender<()>,
+}
+
+impl Player {
+    /// The output device, opened on the first call and again after
+    /// [`select`] picked another.
+    pub fn shared() -> Result<Arc<Self>, String> {
+        let selected = SELECTED.lock().unwrap().clone();
+        let mut shared = SHARED.lock().unwrap();
+        if let Some(player) = shared.as_ref() {
+            if selected
+                .as_ref()
+                .map_or(true, |name| *name == player.device)
+            {
+                return Ok(player.clone());
+            }
+        }
+        let opened = Arc::new(Self::open(selected)?);
+        *shared = Some(opened.clone());
+        Ok(opened)
+    }
+
+    fn open(name: Option<String>) -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel();
+        let (close, closed) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("tts-audio".into())
+            .spawn(move || {
+                let opened = find_device(name.as_deref()).and_then(|device| {
+                    let (stream, handle) = OutputStream::try_from_device(&device)
+                        .map_err(|e| format!("open audio output: {e}"))?;
+                    Ok((stream, handle, device.name().unwrap_or_default()))
+                });
+                match opened {
+                    Ok((_stream, handle, device)) => {
+                        if tx.send(Ok((handle, device))).is_ok() {
+                            // Held until the player is dropped: dropping
+                            // `_stream` closes the device.
+                            let _ = closed.recv();
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                    }
+                }
+            })
+            .map_err(|e| format!("spawn audio thread: {e}"))?;
+        let (handle, device) = rx.recv().map_err(|_| "audio thread exited".to_string())??;
+        Ok(Self {
+            handle,
+            device,
+            _close: close,
+        })
+    }
+
+    /// A fresh sink for one utterance.
+    pub fn sink(self: &Arc<Self>) -> Result<Arc<PlayerSink>, String> {
+        let sink = Sink::try_new(&self.handle).map_err(|e| format!("open audio sink: {e}"))?;
+        let volume = VOLUME.lock().unwrap();
+        sink.set_volume(*volume);
+        let sink = Arc::new(PlayerSink {
+            sink,
+            _player: self.clone(),
+        });
+        let mut sinks = SINKS.lock().unwrap();
+        sinks.retain(|sink| sink.strong_count() > 0);
+        sinks.push(Arc::downgrade(&sink));
+        Ok(sink)
+    }
+}
+
+/// A sink that keeps its device open while it's around, even once another
+/// has been selected.
+pub struct PlayerSink {
+    sink: Sink,
+    _player: Arc<Player>,
+}
+
+impl Deref for PlayerSink {
+    type Target = Sink;
+
+    fn deref(&self) -> &Sink {
+        &self.sink
+    }
+}
+
+fn find_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    match name {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| "no audio output device".to_string()),
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| format!("list audio outputs: {e}"))?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("audio output not found: {name}")),
+    }
+}
+
+/// Names of the output devices, and of the default one.
+fn device_names() -> (Vec<String>, Option<String>) {
+    let host = cpal::default_host();
+    let names = host
+        .output_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    (names, default)
+}
+
+/// cpal only has names to go by; these are the words drivers put in them.
+fn kind_of(name: &str) -> AudioOutputKind {
+    let name = name.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| name.contains(word));
+    if has(&["bluetooth", "airpods", "bluez", "a2dp"]) {
+        AudioOutputKind::Bluetooth
+    } else if has(&["airplay"]) {
+        AudioOutputKind::AirPlay
+    } else if has(&["hdmi", "displayport"]) {
+        AudioOutputKind::Hdmi
+    } else if has(&["usb", "dac"]) {
+        AudioOutputKind::Usb
+    } else if has(&["headphone", "headset", "line out"]) {
+        AudioOutputKind::Wired
+    } else if has(&["speaker"]) {
+        AudioOutputKind::Speaker
+    } else {
+        AudioOutputKind::Other
+    }
+}
+
+fn to_outputs(names: &[String], selected: Option<&str>) -> Vec<AudioOutput> {
+    names
+        .iter()
+        .map(|name| AudioOutput {
+            id: name.clone(),
+            name: name.clone(),
+            kind: kind_of(name),
+            selected: selected == Some(name.as_str()),
+        })
+        .collect()
+}
+
+/// The output devices; the selected one is where the next utterance plays.
+pub fn outputs() -> Vec<AudioOutput> {
+    let (names, default) = device_names();
+    let selected = SELECTED.lock().unwrap().clone().or(default);
+    to_outputs(&names, selected.as_deref())
+}
+
+/// Play from the next utterance on through the device named `name`, or the
+/// system default for `None`.
+pub fn select(name: Option<String>) -> Result<(), String> {
+    if let Some(name) = &name {
+        find_device(Some(name))?;
+    }
+    *SELECTED.lock().unwrap() = name;
+    Ok(())
+}
+
+/// Set the volume of all speech, from 0 for silence to 1 for full.
+pub fn set_volume(volume: f32) {
+    let volume = volume.clamp(0.0, 1.0);
+    // Held across the update so a sink opened meanwhile can't miss it.
+    let mut current = VOLUME.lock().unwrap();
+    *current = volume;
+    for sink in SINKS.lock().unwrap().iter().filter_map(Weak::upgrade) {
+        sink.set_volume(volume);
+    }
+}
+
+/// Poll the devices while one is open, calling `changed` with the outputs
+/// whenever they change and whether the one playing went away, e.g.
+/// headphones unplugged. The device is let go of when it's lost, or when
+/// following the default and that moved, so the next utterance opens the
+/// default.
+pub fn watch(changed: impl Fn(Vec<AudioOutput>, bool) + Send + 'static) {
+    let spawned = std::thread::Builder::new()
+        .name("tts-audio-route".into())
+        .spawn(move || {
+            let mut last = None;
+            loop {
+                std::thread::sleep(WATCH_INTERVAL);
+                let Some(opened) = SHARED.lock().unwrap().as_ref().map(|p| p.device.clone()) else {
+                    last = None;
+                    continue;
+                };
+                let devices = device_names();
+                if last.as_ref() == Some(&devices) {
+                    continue;
+                }
+                let (names, default) = &devices;
+                let known = last.is_some();
+                last = Some(devices.clone());
+                if !known {
+                    continue;
+                }
+                let mut selected = SELECTED.lock().unwrap();
+                let lost = !names.contains(&opened);
+                if lost && selected.as_ref() == Some(&opened) {
+                    *selected = None;
+                }
+                if lost || (selected.is_none() && default.as_ref() != Some(&opened)) {
+                    *SHARED.lock().unwrap() = None;
+                }
+                let current = selected.clone().or_else(|| default.clone());
+                drop(selected);
+                changed(to_outputs(names, current.as_deref()), lost);
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("spawn audio route watcher: {e}");
+    }
+}
+
+/// Queue mono samples on `sink`.
+#[cfg(feature = "piper")]
+pub fn append(sink: &Sink, sample_rate: u32, samples: Vec<f32>) {
+    sink.append(rodio::buffer::SamplesBuffer::new(1, sample_rate, samples));
+}
+
+/// Queue `callback` to run when playback reaches this point of `sink`.
+#[cfg(feature = "piper")]
+pub fn append_callback(sink: &Sink, callback: impl Fn() + Send + 'static) {
+    sink.append(rodio::source::EmptyCallback::<f32>::new(Box::new(callback)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_kinds_from_names() {
+        assert_eq!(kind_of("AirPods Pro"), AudioOutputKind::Bluetooth);
+        assert_eq!(
+            kind_of("Speakers (Realtek(R) Audio)"),
+            AudioOutputKind::Speaker
+        );
+        assert_eq!(kind_of("FiiO USB DAC-E10"), AudioOutputKind::Usb);
+        assert_eq!(kind_of("hdmi:CARD=HDMI,DEV=0"), AudioOutputKind::Hdmi);
+        assert_eq!(kind_of("default"), AudioOutputKind::Other);
+    }
+
+    #[test]
+    fn marks_the_selected_output() {
+        let outputs = to_outputs(
+            &["Speakers".to_string(), "AirPods".to_string()],
+            Some("AirPods"),
+        );
+        assert!(!outputs[0].selected && outputs[1].selected);
+        assert_eq!(outputs[1].id, "AirPods");
+    }
+}
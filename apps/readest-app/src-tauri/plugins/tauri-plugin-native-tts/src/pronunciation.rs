@@ -0,0 +1,302 @@
+//! The pronunciation dictionary: words, or patterns, and how to say them.
+//!
+//! The client keeps one table for the library and one per book, set with
+//! `set_pronunciations`. Before `speak` hands an utterance to an engine,
+//! the book's entries and then the library's are matched against its text,
+//! and each match is marked up in place: `<sub alias>` for a replacement,
+//! `<phoneme>` for IPA. The cloud voices and iOS read that markup; the
+//! other engines speak the alias, or the word as written where they can't
+//! read IPA. Boundary offsets then refer to the alias, as for any `<sub>`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use regex::{Captures, Regex, RegexBuilder};
+
+use crate::models::Pronunciation;
+use crate::ssml::{escape, rewrite_text};
+use crate::{Error, Result};
+
+/// Scripts written without spaces, where a word has no edges to match.
+fn unspaced(c: char) -> bool {
+    matches!(c,
+        '\u{0e00}'..='\u{0eff}'
+        | '\u{1000}'..='\u{109f}'
+        | '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}')
+}
+
+fn word_edge(c: Option<char>) -> &'static str {
+    match c {
+        Some(c) if c.is_alphanumeric() && !unspaced(c) => r"\b",
+        _ => "",
+    }
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: String,
+    /// `$1` and `${name}` in the replacement refer to the match.
+    expand: bool,
+    ipa: bool,
+}
+
+impl Rule {
+    fn compile(entry: &Pronunciation) -> Result<Option<Self>> {
+        if entry.pattern.is_empty() {
+            return Ok(None);
+        }
+        // A word matches whole words only, so "Rome" leaves "Romeo" alone.
+        let source = if entry.is_regex {
+            entry.pattern.clone()
+        } else {
+            format!(
+                "{}{}{}",
+                word_edge(entry.pattern.chars().next()),
+                regex::escape(&entry.pattern),
+                word_edge(entry.pattern.chars().last()),
+            )
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(!entry.case_sensitive)
+            .build()
+            .map_err(|e| {
+                Error::NativeTTSError(format!("Invalid pronunciation {}: {e}", entry.pattern))
+            })?;
+        Ok(Some(Self {
+            regex,
+            replacement: entry.replacement.clone(),
+            expand: entry.is_regex,
+            ipa: entry.ipa,
+        }))
+    }
+
+    fn markup(&self, caps: &Captures) -> String {
+        let mut said = String::new();
+        if self.expand {
+            caps.expand(&self.replacement, &mut said);
+        } else {
+            said.push_str(&self.replacement);
+        }
+        let written = escape(&caps[0]);
+        if self.ipa {
+            format!(
+                "<phoneme alphabet=\"ipa\" ph=\"{}\">{written}</phoneme>",
+                escape(&said)
+            )
+        } else {
+            format!("<sub alias=\"{}\">{written}</sub>", escape(&said))
+        }
+    }
+}
+
+/// Text, or markup already standing in for some of it.
+enum Piece {
+    Text(String),
+    Said(String),
+}
+
+/// `text` as markup with `rules` applied, each to what earlier ones left;
+/// `None` when none matched.
+fn say(rules: &[&Rule], text: &str) -> Option<String> {
+    let mut pieces = vec![Piece::Text(text.to_string())];
+    let mut matched = false;
+    for rule in rules {
+        let mut next = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            let Piece::Text(text) = piece else {
+                next.push(piece);
+                continue;
+            };
+            let mut last = 0;
+            for caps in rule.regex.captures_iter(&text) {
+                let whole = caps.get(0).expect("group 0 is the match");
+                if whole.as_str().is_empty() {
+                    continue;
+                }
+                if whole.start() > last {
+                    next.push(Piece::Text(text[last..whole.start()].to_string()));
+                }
+                next.push(Piece::Said(rule.markup(&caps)));
+                last = whole.end();
+                matched = true;
+            }
+            if last < text.len() {
+                next.push(Piece::Text(text[last..].to_string()));
+            }
+        }
+        pieces = next;
+    }
+    matched.then(|| {
+        pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => escape(text),
+                Piece::Said(markup) => markup.clone(),
+            })
+            .collect()
+    })
+}
+
+#[derive(Default)]
+struct Table {
+    entries: Vec<Pronunciation>,
+    rules: Vec<Rule>,
+}
+
+impl Table {
+    fn new(entries: Vec<Pronunciation>) -> Result<Self> {
+        let mut rules = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            rules.extend(Rule::compile(entry)?);
+        }
+        Ok(Self { entries, rules })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    library: Table,
+    books: HashMap<String, Table>,
+}
+
+#[derive(Default)]
+pub struct Pronunciations(Mutex<State>);
+
+impl Pronunciations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the table of `book`, or the library's when `None`. Nothing
+    /// changes if a pattern doesn't compile.
+    pub fn set(&self, book: Option<String>, entries: Vec<Pronunciation>) -> Result<()> {
+        let table = Table::new(entries)?;
+        let mut state = self.0.lock().unwrap();
+        match book {
+            Some(book) if table.entries.is_empty() => {
+                state.books.remove(&book);
+            }
+            Some(book) => {
+                state.books.insert(book, table);
+            }
+            None => state.library = table,
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, book: Option<&str>) -> Vec<Pronunciation> {
+        let state = self.0.lock().unwrap();
+        match book {
+            Some(book) => state
+                .books
+                .get(book)
+                .map(|table| table.entries.clone())
+                .unwrap_or_default(),
+            None => state.library.entries.clone(),
+        }
+    }
+
+    /// `text` as SSML with the pronunciations of `book` and the library
+    /// marked up, or `None` when none of them occur.
+    pub fn apply(&self, book: Option<&str>, text: &str, ssml: bool) -> Option<String> {
+        let state = self.0.lock().unwrap();
+        let book = book.and_then(|book| state.books.get(book));
+        let rules: Vec<&Rule> = book
+            .into_iter()
+            .flat_map(|table| &table.rules)
+            .chain(&state.library.rules)
+            .collect();
+        if rules.is_empty() {
+            return None;
+        }
+        if ssml {
+            rewrite_text(text, |text| say(&rules, text))
+        } else {
+            say(&rules, text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, replacement: &str) -> Pronunciation {
+        Pronunciation {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            is_regex: false,
+            case_sensitive: false,
+            ipa: false,
+        }
+    }
+
+    #[test]
+    fn marks_up_whole_words() {
+        let dictionary = Pronunciations::new();
+        dictionary
+            .set(
+                None,
+                vec![entry("Rome", "Roam"), entry("C++", "C plus plus")],
+            )
+            .unwrap();
+        assert_eq!(
+            dictionary
+                .apply(None, "rome & Romeo, C++", false)
+                .as_deref(),
+            Some(
+                "<sub alias=\"Roam\">rome</sub> &amp; Romeo, \
+                 <sub alias=\"C plus plus\">C++</sub>"
+            )
+        );
+        assert_eq!(dictionary.apply(None, "Romeo", false), None);
+    }
+
+    #[test]
+    fn tries_the_book_before_the_library() {
+        let dictionary = Pronunciations::new();
+        let ipa = Pronunciation {
+            ipa: true,
+            ..entry("Hermione", "hɜːˈmaɪ.ə.ni")
+        };
+        dictionary
+            .set(None, vec![entry("Hermione", "Her-my-oh-nee")])
+            .unwrap();
+        dictionary.set(Some("book".into()), vec![ipa]).unwrap();
+        assert_eq!(
+            dictionary
+                .apply(Some("book"), "<s>Hermione</s>", true)
+                .as_deref(),
+            Some("<s><phoneme alphabet=\"ipa\" ph=\"hɜːˈmaɪ.ə.ni\">Hermione</phoneme></s>")
+        );
+        assert_eq!(
+            dictionary
+                .apply(Some("other"), "Hermione", false)
+                .as_deref(),
+            Some("<sub alias=\"Her-my-oh-nee\">Hermione</sub>")
+        );
+    }
+
+    #[test]
+    fn expands_regex_groups_and_rejects_bad_patterns() {
+        let dictionary = Pronunciations::new();
+        let chapter = Pronunciation {
+            is_regex: true,
+            case_sensitive: true,
+            ..entry(r"Ch\. (\d+)", "Chapter $1")
+        };
+        dictionary.set(None, vec![chapter]).unwrap();
+        assert_eq!(
+            dictionary.apply(None, "Ch. 12, ch. 3", false).as_deref(),
+            Some("<sub alias=\"Chapter 12\">Ch. 12</sub>, ch. 3")
+        );
+        let broken = Pronunciation {
+            is_regex: true,
+            ..entry("(", "x")
+        };
+        assert!(dictionary.set(None, vec![broken]).is_err());
+        assert_eq!(dictionary.get(None).len(), 1);
+    }
+}
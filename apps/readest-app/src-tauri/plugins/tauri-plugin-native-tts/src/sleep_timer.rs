@@ -0,0 +1,173 @@
+//! The desktop sleep timer.
+//!
+//! Android and iOS run theirs in the native plugin, where it keeps time
+//! with the screen off, fades the system voice and listens for shakes. Here
+//! a thread counts down instead, fading Piper and the cloud voices through
+//! the player and stopping them when time is up. Desktop builds don't get
+//! chapter changes pushed to them, so `end_of_chapter` is left to the
+//! client, which starts each chapter itself.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::{SetSleepTimerArgs, SleepTimerState};
+
+/// Speech fades out over this much of the timer's end.
+const FADE: Duration = Duration::from_secs(10);
+const TICK: Duration = Duration::from_millis(200);
+
+struct Armed {
+    /// Tells the ticker of a timer since replaced to leave.
+    id: u64,
+    deadline: Option<Instant>,
+    end_of_chapter: bool,
+}
+
+#[derive(Default)]
+struct State {
+    armed: Option<Armed>,
+    last_id: u64,
+}
+
+#[derive(Default)]
+pub struct SleepTimer(Arc<Mutex<State>>);
+
+/// Volume with `remaining` left on the timer: full until the fade, then
+/// falling to nothing.
+fn fade_volume(remaining: Duration) -> f32 {
+    (remaining.as_secs_f32() / FADE.as_secs_f32()).min(1.0)
+}
+
+fn state_of(armed: Option<&Armed>, now: Instant) -> SleepTimerState {
+    match armed {
+        None => SleepTimerState::default(),
+        Some(armed) => SleepTimerState {
+            active: true,
+            remaining_ms: armed
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(now).as_millis() as u64),
+            end_of_chapter: armed.end_of_chapter,
+        },
+    }
+}
+
+impl SleepTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arm the timer, replacing the one armed before, or turn it off.
+    /// `volume` is handed the fade as it goes, and `fired` runs once time
+    /// is up.
+    pub fn set(
+        &self,
+        args: SetSleepTimerArgs,
+        volume: impl Fn(f32) + Send + 'static,
+        fired: impl FnOnce() + Send + 'static,
+    ) -> Result<SleepTimerState, String> {
+        let now = Instant::now();
+        let deadline = args
+            .seconds
+            .map(|seconds| now + Duration::from_secs(seconds));
+        let mut state = self.0.lock().unwrap();
+        state.last_id += 1;
+        let id = state.last_id;
+        state.armed = (deadline.is_some() || args.end_of_chapter).then_some(Armed {
+            id,
+            deadline,
+            end_of_chapter: args.end_of_chapter,
+        });
+        let armed = state_of(state.armed.as_ref(), now);
+        drop(state);
+        // Whatever the last timer faded comes back up.
+        volume(1.0);
+        if let Some(deadline) = deadline {
+            let state = self.0.clone();
+            let spawned = std::thread::Builder::new()
+                .name("tts-sleep-timer".into())
+                .spawn(move || tick(&state, id, deadline, volume, fired));
+            if let Err(e) = spawned {
+                self.0.lock().unwrap().armed = None;
+                return Err(format!("spawn sleep timer: {e}"));
+            }
+        }
+        Ok(armed)
+    }
+
+    pub fn state(&self) -> SleepTimerState {
+        state_of(self.0.lock().unwrap().armed.as_ref(), Instant::now())
+    }
+}
+
+fn tick(
+    state: &Mutex<State>,
+    id: u64,
+    deadline: Instant,
+    volume: impl Fn(f32),
+    fired: impl FnOnce(),
+) {
+    loop {
+        std::thread::sleep(TICK);
+        let mut state = state.lock().unwrap();
+        if state.armed.as_ref().map(|armed| armed.id) != Some(id) {
+            return;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            state.armed = None;
+            drop(state);
+            fired();
+            volume(1.0);
+            return;
+        }
+        volume(fade_volume(remaining));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_over_the_last_ten_seconds() {
+        assert_eq!(fade_volume(Duration::from_secs(60)), 1.0);
+        assert_eq!(fade_volume(Duration::from_secs(10)), 1.0);
+        assert_eq!(fade_volume(Duration::from_secs(5)), 0.5);
+        assert_eq!(fade_volume(Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn arms_and_turns_off() {
+        let timer = SleepTimer::new();
+        let state = timer
+            .set(
+                SetSleepTimerArgs {
+                    end_of_chapter: true,
+                    ..Default::default()
+                },
+                |_| {},
+                || {},
+            )
+            .unwrap();
+        assert!(state.active && state.end_of_chapter);
+        assert_eq!(state.remaining_ms, None);
+
+        let state = timer
+            .set(
+                SetSleepTimerArgs {
+                    seconds: Some(600),
+                    ..Default::default()
+                },
+                |_| {},
+                || panic!("fired early"),
+            )
+            .unwrap();
+        assert!(state.active && !state.end_of_chapter);
+        assert!(state.remaining_ms.is_some_and(|ms| ms > 590_000));
+
+        timer
+            .set(SetSleepTimerArgs::default(), |_| {}, || {})
+            .unwrap();
+        assert_eq!(timer.state(), SleepTimerState::default());
+    }
+}
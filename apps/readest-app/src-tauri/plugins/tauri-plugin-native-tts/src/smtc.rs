@@ -0,0 +1,240 @@
+//! Windows System Media Transport Controls: the media overlay, the lock
+//! screen and the media keys.
+//!
+//! WebView2 doesn't hand the page's media session to them, and read-aloud
+//! plays from WebAudio or from this plugin anyway, so the controls for the
+//! main window are driven from here through the same media session commands
+//! the mobile plugins take. Their buttons come back as the
+//! `media-session-*` events those plugins send.
+
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use windows::core::{factory, HSTRING};
+use windows::Foundation::{TimeSpan, TypedEventHandler, Uri};
+use windows::Media::{
+    MediaPlaybackStatus, MediaPlaybackType, PlaybackPositionChangeRequestedEventArgs,
+    SystemMediaTransportControls, SystemMediaTransportControlsButton,
+    SystemMediaTransportControlsButtonPressedEventArgs,
+    SystemMediaTransportControlsTimelineProperties,
+};
+use windows::Storage::Streams::{
+    DataWriter, InMemoryRandomAccessStream, RandomAccessStreamReference,
+};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::WinRT::ISystemMediaTransportControlsInterop;
+
+use crate::models::{UpdateMediaSessionMetadataRequest, UpdateMediaSessionStateRequest};
+
+/// Hands an event to the webview: its name and, for a seek, the position
+/// asked for in milliseconds.
+pub type Emit = Arc<dyn Fn(&'static str, Option<u64>) + Send + Sync>;
+
+struct Session {
+    controls: SystemMediaTransportControls,
+    button_token: i64,
+    seek_token: i64,
+    seekable: bool,
+}
+
+#[derive(Default)]
+pub struct Smtc(Mutex<Option<Session>>);
+
+fn event_for(button: SystemMediaTransportControlsButton) -> Option<&'static str> {
+    match button {
+        SystemMediaTransportControlsButton::Play => Some("media-session-play"),
+        // Stop keeps the pause mapping the other surfaces have.
+        SystemMediaTransportControlsButton::Pause | SystemMediaTransportControlsButton::Stop => {
+            Some("media-session-pause")
+        }
+        SystemMediaTransportControlsButton::Next => Some("media-session-next"),
+        SystemMediaTransportControlsButton::Previous => Some("media-session-previous"),
+        _ => None,
+    }
+}
+
+/// Milliseconds as a `TimeSpan`, which counts in 100ns ticks.
+fn time_span(ms: f64) -> TimeSpan {
+    TimeSpan {
+        Duration: (ms.max(0.0) * 10_000.0) as i64,
+    }
+}
+
+fn data_url_bytes(url: &str) -> Option<Vec<u8>> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    header.strip_suffix(";base64")?;
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+/// The cover, from a data URL or a web URL; anything else is left out.
+fn thumbnail(artwork: &str) -> windows::core::Result<Option<RandomAccessStreamReference>> {
+    if artwork.starts_with("http://") || artwork.starts_with("https://") {
+        let uri = Uri::CreateUri(&HSTRING::from(artwork))?;
+        return RandomAccessStreamReference::CreateFromUri(&uri).map(Some);
+    }
+    let Some(bytes) = data_url_bytes(artwork) else {
+        return Ok(None);
+    };
+    let stream = InMemoryRandomAccessStream::new()?;
+    let writer = DataWriter::CreateDataWriter(&stream)?;
+    writer.WriteBytes(&bytes)?;
+    writer.StoreAsync()?.get()?;
+    writer.DetachStream()?;
+    stream.Seek(0)?;
+    RandomAccessStreamReference::CreateFromStream(&stream).map(Some)
+}
+
+impl Smtc {
+    /// Take the controls of the window `hwnd` for read-aloud.
+    pub fn activate(&self, hwnd: *mut c_void, emit: Emit) -> Result<(), String> {
+        let mut session = self.0.lock().unwrap();
+        if session.is_some() {
+            return Ok(());
+        }
+        let controls = (|| {
+            let interop =
+                factory::<SystemMediaTransportControls, ISystemMediaTransportControlsInterop>()?;
+            unsafe { interop.GetForWindow::<SystemMediaTransportControls>(HWND(hwnd)) }
+        })()
+        .map_err(|e| format!("SMTC unavailable: {e}"))?;
+        let on_button = emit.clone();
+        let result = (|| {
+            controls.SetIsPlayEnabled(true)?;
+            controls.SetIsPauseEnabled(true)?;
+            controls.SetIsStopEnabled(true)?;
+            controls.SetIsNextEnabled(true)?;
+            controls.SetIsPreviousEnabled(true)?;
+            let button_token = controls.ButtonPressed(&TypedEventHandler::<
+                SystemMediaTransportControls,
+                SystemMediaTransportControlsButtonPressedEventArgs,
+            >::new(move |_, args| {
+                if let Some(event) = event_for(args.ok()?.Button()?) {
+                    on_button(event, None);
+                }
+                Ok(())
+            }))?;
+            let seek_token = controls.PlaybackPositionChangeRequested(&TypedEventHandler::<
+                SystemMediaTransportControls,
+                PlaybackPositionChangeRequestedEventArgs,
+            >::new(
+                move |_, args| {
+                    let position = args.ok()?.RequestedPlaybackPosition()?;
+                    emit(
+                        "media-session-seek",
+                        Some((position.Duration.max(0) / 10_000) as u64),
+                    );
+                    Ok(())
+                },
+            ))?;
+            controls.SetIsEnabled(true)?;
+            Ok::<_, windows::core::Error>((button_token, seek_token))
+        })();
+        let (button_token, seek_token) = result.map_err(|e| e.to_string())?;
+        *session = Some(Session {
+            controls,
+            button_token,
+            seek_token,
+            seekable: false,
+        });
+        Ok(())
+    }
+
+    /// Hand the controls back; the overlay forgets read-aloud.
+    pub fn deactivate(&self) {
+        let Some(session) = self.0.lock().unwrap().take() else {
+            return;
+        };
+        let controls = session.controls;
+        let _ = controls.RemoveButtonPressed(session.button_token);
+        let _ = controls.RemovePlaybackPositionChangeRequested(session.seek_token);
+        let _ = controls.SetPlaybackStatus(MediaPlaybackStatus::Closed);
+        if let Ok(updater) = controls.DisplayUpdater() {
+            let _ = updater.ClearAll();
+            let _ = updater.Update();
+        }
+        let _ = controls.SetIsEnabled(false);
+    }
+
+    pub fn update_metadata(
+        &self,
+        payload: &UpdateMediaSessionMetadataRequest,
+    ) -> Result<(), String> {
+        let session = self.0.lock().unwrap();
+        let Some(session) = session.as_ref() else {
+            return Ok(());
+        };
+        let result = (|| {
+            let updater = session.controls.DisplayUpdater()?;
+            updater.ClearAll()?;
+            updater.SetType(MediaPlaybackType::Music)?;
+            let music = updater.MusicProperties()?;
+            music.SetTitle(&HSTRING::from(payload.title.as_deref().unwrap_or_default()))?;
+            music.SetArtist(&HSTRING::from(
+                payload.artist.as_deref().unwrap_or_default(),
+            ))?;
+            music.SetAlbumTitle(&HSTRING::from(payload.album.as_deref().unwrap_or_default()))?;
+            if let Some(cover) = payload.artwork.as_deref() {
+                // A cover that won't load still leaves the rest to show.
+                if let Ok(Some(thumbnail)) = thumbnail(cover) {
+                    updater.SetThumbnail(&thumbnail)?;
+                }
+            }
+            updater.Update()
+        })();
+        result.map_err(|e| e.to_string())
+    }
+
+    pub fn update_state(&self, payload: &UpdateMediaSessionStateRequest) -> Result<(), String> {
+        let mut session = self.0.lock().unwrap();
+        let Some(session) = session.as_mut() else {
+            return Ok(());
+        };
+        if let Some(seekable) = payload.seekable {
+            session.seekable = seekable;
+        }
+        let controls = &session.controls;
+        let seekable = session.seekable;
+        let result = (|| {
+            controls.SetPlaybackStatus(if payload.playing {
+                MediaPlaybackStatus::Playing
+            } else {
+                MediaPlaybackStatus::Paused
+            })?;
+            let Some(duration) = payload.duration.filter(|&duration| duration > 0.0) else {
+                return Ok(());
+            };
+            let position = payload.position.unwrap_or(0.0).min(duration);
+            let timeline = SystemMediaTransportControlsTimelineProperties::new()?;
+            timeline.SetStartTime(time_span(0.0))?;
+            timeline.SetEndTime(time_span(duration))?;
+            timeline.SetPosition(time_span(position))?;
+            // An empty seek range leaves the overlay's bar undraggable.
+            timeline.SetMinSeekTime(time_span(0.0))?;
+            timeline.SetMaxSeekTime(time_span(if seekable { duration } else { 0.0 }))?;
+            controls.UpdateTimelineProperties(&timeline)
+        })();
+        result.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_cover_data_urls() {
+        assert_eq!(
+            data_url_bytes("data:image/png;base64,AAEC"),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(data_url_bytes("data:image/png,AAEC"), None);
+        assert_eq!(data_url_bytes("https://example.com/cover.png"), None);
+    }
+
+    #[test]
+    fn counts_time_in_ticks() {
+        assert_eq!(time_span(1.5).Duration, 15_000);
+        assert_eq!(time_span(-20.0).Duration, 0);
+    }
+}
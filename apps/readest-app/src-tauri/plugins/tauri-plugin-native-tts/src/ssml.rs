@@ -0,0 +1,636 @@
+//! The SSML `speak` accepts, reduced to what each engine can take.
+//!
+//! Input is a lenient subset: `<break>`, `<p>` and `<s>`, `<emphasis>`,
+//! `<prosody>`, `<say-as>`, `<sub>`, `<phoneme>` and `<lang>`, with or
+//! without a `<speak>` root. Anything else is dropped, keeping its text.
+//! From it come three views of the same utterance:
+//!
+//! - the plain text, what engines without SSML speak and what boundary
+//!   offsets refer to;
+//! - normalized markup, only the supported elements with checked
+//!   attributes, for engines that read SSML (the cloud voices, iOS);
+//! - speech and pauses, for engines that render text a piece at a time and
+//!   can put silence between them (Piper).
+
+use std::ops::Range;
+
+/// The pause a paragraph ends with.
+const PARAGRAPH_MS: u32 = 500;
+/// Longest pause honoured, so a typo can't stall reading.
+const MAX_BREAK_MS: u32 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// A byte range of [`Utterance::text`].
+    Speech(Range<usize>),
+    Pause(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utterance {
+    text: String,
+    markup: String,
+    segments: Vec<Segment>,
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters make SSML engines drop the request.
+            c if c.is_control() && !c.is_whitespace() => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded = entity.and_then(|(name, end)| {
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `500ms`, `1.5s` or a `strength`, in milliseconds.
+fn break_ms(time: Option<&str>, strength: Option<&str>) -> u32 {
+    let parsed = time.and_then(|time| {
+        let time = time.trim();
+        let (number, scale) = match time.strip_suffix("ms") {
+            Some(ms) => (ms, 1.0),
+            None => (time.strip_suffix('s')?, 1000.0),
+        };
+        let ms = number.trim().parse::<f32>().ok()? * scale;
+        (ms.is_finite() && ms >= 0.0).then_some(ms as u32)
+    });
+    let ms = parsed.unwrap_or(match strength {
+        Some("none") => 0,
+        Some("x-weak") => 100,
+        Some("weak") => 250,
+        Some("strong") => 750,
+        Some("x-strong") => 1200,
+        _ => 400,
+    });
+    ms.min(MAX_BREAK_MS)
+}
+
+/// Attribute values passed on into markup: words, numbers and the signs
+/// prosody uses, nothing that could open a tag or end the quote.
+fn safe_value(value: &str) -> Option<&str> {
+    let ok = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '+' | '-' | '%' | '_' | ':'));
+    ok.then_some(value)
+}
+
+/// Where the tag `markup` starts with ends: its first `>` outside quotes.
+fn tag_end(markup: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, String)>,
+    closing: bool,
+    empty: bool,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(inner: &'a str) -> Option<Self> {
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let (empty, inner) = match inner.trim_end().strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let inner = inner.trim();
+        let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+        let name = &inner[..name_end];
+        if name.is_empty() {
+            return None;
+        }
+        let mut attrs = Vec::new();
+        let mut rest = &inner[name_end..];
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let end = value[1..].find(quote)? + 1;
+            attrs.push((key, unescape(&value[1..end])));
+            rest = &value[end + 1..];
+        }
+        Some(Self {
+            name,
+            attrs,
+            closing,
+            empty,
+        })
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+enum Token<'a> {
+    /// Text as written, still escaped.
+    Text(&'a str),
+    /// A tag and its markup.
+    Tag(&'a str, Tag<'a>),
+}
+
+/// Walk `ssml` as text and tags. Comments, declarations and processing
+/// instructions are skipped; markup that doesn't parse is text.
+fn scan<'a>(ssml: &'a str, mut on: impl FnMut(Token<'a>)) {
+    let mut rest = ssml;
+    while let Some(lt) = rest.find('<') {
+        on(Token::Text(&rest[..lt]));
+        rest = &rest[lt..];
+        let skip_to = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(end) = skip_to {
+            rest = rest.find(end).map_or("", |i| &rest[i + end.len()..]);
+            continue;
+        }
+        // A `<` that can't start a tag is text, as in "a < b".
+        let starts_tag = rest[1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/');
+        let Some(gt) = starts_tag.then(|| tag_end(rest)).flatten() else {
+            on(Token::Text(&rest[..1]));
+            rest = &rest[1..];
+            continue;
+        };
+        match Tag::parse(&rest[1..gt]) {
+            Some(tag) => on(Token::Tag(&rest[..=gt], tag)),
+            None => on(Token::Text(&rest[..=gt])),
+        }
+        rest = &rest[gt + 1..];
+    }
+    on(Token::Text(rest));
+}
+
+/// `ssml` with its text passed through `rewrite`, which returns the markup
+/// to put in place of text it changes. Text inside `<sub>`, `<say-as>` and
+/// `<phoneme>` already says how it's spoken and is left alone. `None` when
+/// nothing changed.
+pub fn rewrite_text(ssml: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(ssml.len());
+    let mut changed = false;
+    let mut spoken_as = 0usize;
+    scan(ssml, |token| match token {
+        Token::Text(raw) if spoken_as == 0 => match rewrite(&unescape(raw)) {
+            Some(markup) => {
+                changed = true;
+                out.push_str(&markup);
+            }
+            None => out.push_str(raw),
+        },
+        Token::Text(raw) => out.push_str(raw),
+        Token::Tag(raw, tag) => {
+            if matches!(tag.name, "sub" | "say-as" | "phoneme") && !tag.empty {
+                spoken_as = if tag.closing {
+                    spoken_as.saturating_sub(1)
+                } else {
+                    spoken_as + 1
+                };
+            }
+            out.push_str(raw);
+        }
+    });
+    changed.then_some(out)
+}
+
+/// Text inside `<say-as>`, `<sub>` or `<phoneme>`, spoken as a whole once it closes.
+struct Capture {
+    name: String,
+    open: String,
+    spoken_as: Option<String>,
+    text: String,
+}
+
+#[derive(Default)]
+struct Builder {
+    text: String,
+    markup: String,
+    segments: Vec<Segment>,
+    speech_start: Option<usize>,
+    /// Open wrapping elements, with the markup that closes them.
+    open: Vec<(String, &'static str)>,
+    capture: Option<Capture>,
+}
+
+impl Builder {
+    fn speak(&mut self, spoken: &str, markup: &str) {
+        if spoken.trim().is_empty() && self.speech_start.is_none() {
+            self.markup.push_str(markup);
+            return;
+        }
+        self.speech_start.get_or_insert(self.text.len());
+        self.text.push_str(spoken);
+        self.markup.push_str(markup);
+    }
+
+    fn end_speech(&mut self) {
+        if let Some(start) = self.speech_start.take() {
+            self.segments.push(Segment::Speech(start..self.text.len()));
+        }
+    }
+
+    fn pause(&mut self, ms: u32) {
+        self.end_speech();
+        if ms == 0 {
+            return;
+        }
+        self.markup.push_str(&format!("<break time=\"{ms}ms\"/>"));
+        match self.segments.last_mut() {
+            Some(Segment::Pause(total)) => *total = (*total + ms).min(MAX_BREAK_MS),
+            Some(Segment::Speech(_)) => {
+                self.text.push('\n');
+                self.segments.push(Segment::Pause(ms));
+            }
+            // Nothing to pause after.
+            None => {}
+        }
+    }
+
+    fn text(&mut self, raw: &str) {
+        let text = unescape(raw);
+        match self.capture.as_mut() {
+            Some(capture) => capture.text.push_str(&text),
+            None => self.speak(&text, &escape(&text)),
+        }
+    }
+
+    fn open(&mut self, tag: &Tag) {
+        let wrapper = |name: &str, attrs: &[(&str, Option<&str>)]| {
+            let mut open = format!("<{name}");
+            for (key, value) in attrs {
+                if let Some(value) = value.and_then(safe_value) {
+                    open.push_str(&format!(" {key}=\"{value}\""));
+                }
+            }
+            open.push('>');
+            open
+        };
+        match tag.name {
+            "break" => self.pause(break_ms(tag.attr("time"), tag.attr("strength"))),
+            "say-as" | "sub" | "phoneme" => {
+                let (open, spoken_as) = if tag.name == "sub" {
+                    let alias = tag.attr("alias").unwrap_or_default();
+                    (
+                        format!("<sub alias=\"{}\">", escape(alias)),
+                        Some(alias.to_string()),
+                    )
+                } else if tag.name == "phoneme" {
+                    // Engines that can't read the pronunciation say the text.
+                    let alphabet = tag.attr("alphabet").and_then(safe_value);
+                    let open = format!(
+                        "<phoneme alphabet=\"{}\" ph=\"{}\">",
+                        alphabet.unwrap_or("ipa"),
+                        escape(tag.attr("ph").unwrap_or_default())
+                    );
+                    (open, None)
+                } else {
+                    let interpret_as = tag.attr("interpret-as");
+                    let open = wrapper(
+                        "say-as",
+                        &[
+                            ("interpret-as", interpret_as),
+                            ("format", tag.attr("format")),
+                            ("detail", tag.attr("detail")),
+                        ],
+                    );
+                    (open, interpret_as.map(str::to_string))
+                };
+                if !tag.empty {
+                    self.capture = Some(Capture {
+                        name: tag.name.to_string(),
+                        open,
+                        spoken_as,
+                        text: String::new(),
+                    });
+                }
+            }
+            "emphasis" | "prosody" | "lang" => {
+                let open = match tag.name {
+                    "emphasis" => wrapper("emphasis", &[("level", tag.attr("level"))]),
+                    "prosody" => wrapper(
+                        "prosody",
+                        &[
+                            ("rate", tag.attr("rate")),
+                            ("pitch", tag.attr("pitch")),
+                            ("volume", tag.attr("volume")),
+                        ],
+                    ),
+                    _ => wrapper("lang", &[("xml:lang", tag.attr("xml:lang"))]),
+                };
+                let close = match tag.name {
+                    "emphasis" => "</emphasis>",
+                    "prosody" => "</prosody>",
+                    _ => "</lang>",
+                };
+                if !tag.empty {
+                    self.markup.push_str(&open);
+                    self.open.push((tag.name.to_string(), close));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if let Some(capture) = self.capture.take_if_named(name) {
+            let spoken = match (capture.name.as_str(), capture.spoken_as.as_deref()) {
+                ("sub", Some(alias)) => alias.to_string(),
+                (_, Some("characters" | "spell-out")) => {
+                    let letters: Vec<String> = capture
+                        .text
+                        .chars()
+                        .filter(|c| !c.is_whitespace())
+                        .map(String::from)
+                        .collect();
+                    letters.join(" ")
+                }
+                _ => capture.text.clone(),
+            };
+            let markup = format!(
+                "{}{}</{}>",
+                capture.open,
+                escape(&capture.text),
+                capture.name
+            );
+            self.speak(&spoken, &markup);
+            return;
+        }
+        match name {
+            "p" => self.pause(PARAGRAPH_MS),
+            "s" => self.speak(" ", " "),
+            _ => {
+                if let Some(index) = self.open.iter().rposition(|(open, _)| open == name) {
+                    for (_, close) in self.open.drain(index..).rev() {
+                        self.markup.push_str(close);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Utterance {
+        self.capture.take();
+        for (_, close) in self.open.drain(..).rev() {
+            self.markup.push_str(close);
+        }
+        self.end_speech();
+        while let Some(Segment::Pause(_)) = self.segments.last() {
+            self.segments.pop();
+            if self.text.ends_with('\n') {
+                self.text.pop();
+            }
+        }
+        Utterance {
+            text: self.text,
+            markup: self.markup,
+            segments: self.segments,
+        }
+    }
+}
+
+trait TakeIfNamed {
+    fn take_if_named(&mut self, name: &str) -> Option<Capture>;
+}
+
+impl TakeIfNamed for Option<Capture> {
+    fn take_if_named(&mut self, name: &str) -> Option<Capture> {
+        if self.as_ref().is_some_and(|capture| capture.name == name) {
+            self.take()
+        } else {
+            None
+        }
+    }
+}
+
+impl Utterance {
+    pub fn plain(text: &str) -> Self {
+        let segments = if text.trim().is_empty() {
+            Vec::new()
+        } else {
+            vec![Segment::Speech(0..text.len())]
+        };
+        Self {
+            text: text.to_string(),
+            markup: escape(text),
+            segments,
+        }
+    }
+
+    /// Never fails: markup that doesn't parse is read as text.
+    pub fn parse_ssml(ssml: &str) -> Self {
+        let mut builder = Builder::default();
+        scan(ssml, |token| match token {
+            Token::Text(raw) => builder.text(raw),
+            Token::Tag(_, tag) if tag.closing => builder.close(tag.name),
+            Token::Tag(_, tag) => {
+                builder.open(&tag);
+                if tag.empty && matches!(tag.name, "p" | "s") {
+                    builder.close(tag.name);
+                }
+            }
+        });
+        builder.finish()
+    }
+
+    pub fn new(text: &str, ssml: bool) -> Self {
+        if ssml {
+            Self::parse_ssml(text)
+        } else {
+            Self::plain(text)
+        }
+    }
+
+    /// What's spoken, without markup; boundary offsets point into this.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The body of a `<speak>` element, escaped and normalized.
+    pub fn markup(&self) -> &str {
+        &self.markup
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn is_silent(&self) -> bool {
+        !self
+            .segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Speech(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech(utterance: &Utterance) -> Vec<&str> {
+        utterance
+            .segments()
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Speech(range) => Some(&utterance.text()[range.clone()]),
+                Segment::Pause(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_speech_at_breaks_and_paragraphs() {
+        let utterance = Utterance::parse_ssml(
+            "<speak><p>Chapter One<break time=\"1.5s\"/></p><p>It was &quot;late&quot;.</p></speak>",
+        );
+        assert_eq!(utterance.text(), "Chapter One\nIt was \"late\".");
+        assert_eq!(
+            utterance.segments(),
+            [
+                Segment::Speech(0..11),
+                Segment::Pause(1500 + PARAGRAPH_MS),
+                Segment::Speech(12..26),
+            ]
+        );
+        assert_eq!(
+            utterance.markup(),
+            "Chapter One<break time=\"1500ms\"/><break time=\"500ms\"/>\
+             It was &quot;late&quot;.<break time=\"500ms\"/>"
+        );
+    }
+
+    #[test]
+    fn speaks_substitutions_and_spelled_out_text() {
+        let utterance = Utterance::parse_ssml(
+            "<sub alias=\"Doctor\">Dr.</sub> <say-as interpret-as=\"characters\">BBC</say-as> \
+             on <say-as interpret-as=\"date\" format=\"ymd\">2024-03-01</say-as>",
+        );
+        assert_eq!(utterance.text(), "Doctor B B C on 2024-03-01");
+        assert_eq!(
+            utterance.markup(),
+            "<sub alias=\"Doctor\">Dr.</sub> <say-as interpret-as=\"characters\">BBC</say-as> \
+             on <say-as interpret-as=\"date\" format=\"ymd\">2024-03-01</say-as>"
+        );
+    }
+
+    #[test]
+    fn speaks_the_text_of_phonemes() {
+        let utterance = Utterance::parse_ssml(
+            "<phoneme alphabet=\"ipa\" ph=\"həˈmaɪ.ə.ni\">Hermione</phoneme> smiled",
+        );
+        assert_eq!(utterance.text(), "Hermione smiled");
+        assert_eq!(
+            utterance.markup(),
+            "<phoneme alphabet=\"ipa\" ph=\"həˈmaɪ.ə.ni\">Hermione</phoneme> smiled"
+        );
+    }
+
+    #[test]
+    fn rewrites_only_text_not_yet_spoken_as() {
+        let ssml = "<p>Dr. Who &amp; <sub alias=\"Doctor\">Dr.</sub></p><!-- x -->";
+        let rewritten = rewrite_text(ssml, |text| {
+            text.contains("Dr.")
+                .then(|| escape(&text.replace("Dr.", "Doctor")))
+        });
+        assert_eq!(
+            rewritten.as_deref(),
+            Some("<p>Doctor Who &amp; <sub alias=\"Doctor\">Dr.</sub></p>")
+        );
+        assert_eq!(rewrite_text(ssml, |_| None), None);
+    }
+
+    #[test]
+    fn keeps_supported_wrappers_and_drops_the_rest() {
+        let utterance = Utterance::parse_ssml(
+            "<!-- note --><voice name=\"x\"><emphasis level=\"strong\">Never</emphasis> \
+             <prosody rate=\"slow\" onload=\"x\">ever <mark name=\"m\"/>again</voice>",
+        );
+        assert_eq!(utterance.text(), "Never ever again");
+        assert_eq!(
+            utterance.markup(),
+            "<emphasis level=\"strong\">Never</emphasis> <prosody rate=\"slow\">ever again</prosody>"
+        );
+        assert_eq!(speech(&utterance), ["Never ever again"]);
+    }
+
+    #[test]
+    fn reads_broken_markup_as_text() {
+        let utterance = Utterance::parse_ssml("a < b & c <emphasis level='\"><x'>d");
+        assert_eq!(utterance.text(), "a < b & c d");
+        assert!(!utterance.markup().contains("<x"));
+        assert!(Utterance::parse_ssml("<speak><break/></speak>").is_silent());
+    }
+
+    #[test]
+    fn plain_text_is_one_segment() {
+        let utterance = Utterance::new("Fish & chips", false);
+        assert_eq!(utterance.markup(), "Fish &amp; chips");
+        assert_eq!(speech(&utterance), ["Fish & chips"]);
+        assert!(Utterance::plain("  ").is_silent());
+    }
+}
@@ -0,0 +1,147 @@
+//! Which voice speaks which language, for books that switch languages
+//! mid-chapter.
+//!
+//! The client sets a table of language tags to voice ids with
+//! `set_voice_map` and tags each `speak` with the language of its segment.
+//! Before a segment is spoken its voice is selected: the one mapped to the
+//! exact tag, else to a shorter form of it (`zh-hant` for `zh-Hant-TW`,
+//! then `zh`), else the voice last picked with `set_voice`. A voice is only
+//! selected when it changes, so a book in a single language never switches.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// `en_US` and `en-us` are the same tag.
+fn normalize(lang: &str) -> String {
+    lang.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+#[derive(Default)]
+struct State {
+    voices: HashMap<String, String>,
+    /// Picked with `set_voice`; speaks whatever isn't mapped.
+    chosen: Option<String>,
+    /// Selected on the engine now, as far as the map knows.
+    active: Option<String>,
+}
+
+impl State {
+    fn lookup(&self, lang: &str) -> Option<&String> {
+        let mut tag = normalize(lang);
+        loop {
+            if let Some(voice) = self.voices.get(&tag) {
+                return Some(voice);
+            }
+            tag.truncate(tag.rfind('-')?);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct VoiceMap(Mutex<State>);
+
+impl VoiceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the table; an empty one turns switching off.
+    pub fn set(&self, voices: HashMap<String, String>) {
+        self.0.lock().unwrap().voices = voices
+            .into_iter()
+            .filter(|(lang, voice)| !lang.trim().is_empty() && !voice.is_empty())
+            .map(|(lang, voice)| (normalize(&lang), voice))
+            .collect();
+    }
+
+    pub fn get(&self) -> HashMap<String, String> {
+        self.0.lock().unwrap().voices.clone()
+    }
+
+    /// `set_voice` selected `voice`: it speaks unmapped segments from now on.
+    pub fn chose(&self, voice: &str) {
+        let mut state = self.0.lock().unwrap();
+        state.chosen = Some(voice.to_string());
+        state.active = Some(voice.to_string());
+    }
+
+    /// Forget what's selected, e.g. after the engine changed under it, so
+    /// the next segment selects its voice again.
+    pub fn reset(&self) {
+        self.0.lock().unwrap().active = None;
+    }
+
+    /// The voice to select before speaking a segment in `lang`, or `None`
+    /// when the right one is already selected. It's taken as selected from
+    /// here on; call [`Self::reset`] if selecting it fails.
+    pub fn switch_for(&self, lang: Option<&str>) -> Option<String> {
+        let mut state = self.0.lock().unwrap();
+        if state.voices.is_empty() {
+            return None;
+        }
+        let target = lang
+            .and_then(|lang| state.lookup(lang))
+            .or(state.chosen.as_ref())?
+            .clone();
+        if state.active.as_ref() == Some(&target) {
+            return None;
+        }
+        state.active = Some(target.clone());
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> VoiceMap {
+        let map = VoiceMap::new();
+        map.set(
+            pairs
+                .iter()
+                .map(|(lang, voice)| (lang.to_string(), voice.to_string()))
+                .collect(),
+        );
+        map
+    }
+
+    #[test]
+    fn falls_back_to_shorter_tags() {
+        let voices = map(&[
+            ("zh", "zh-voice"),
+            ("zh-Hant", "hant-voice"),
+            ("pt_BR", "br"),
+        ]);
+        assert_eq!(
+            voices.switch_for(Some("zh-Hant-TW")).as_deref(),
+            Some("hant-voice")
+        );
+        assert_eq!(
+            voices.switch_for(Some("zh-CN")).as_deref(),
+            Some("zh-voice")
+        );
+        assert_eq!(voices.switch_for(Some("PT-br")).as_deref(), Some("br"));
+    }
+
+    #[test]
+    fn switches_only_when_the_voice_changes() {
+        let voices = map(&[("fr", "denise")]);
+        voices.chose("aria");
+        assert_eq!(voices.switch_for(Some("en")), None);
+        assert_eq!(voices.switch_for(Some("fr-FR")).as_deref(), Some("denise"));
+        assert_eq!(voices.switch_for(Some("fr")), None);
+        // Back to the chosen voice for unmapped and untagged segments.
+        assert_eq!(voices.switch_for(None).as_deref(), Some("aria"));
+        voices.reset();
+        assert_eq!(voices.switch_for(Some("de")).as_deref(), Some("aria"));
+    }
+
+    #[test]
+    fn an_empty_map_never_switches() {
+        let voices = map(&[("", "x"), ("de", "")]);
+        voices.chose("aria");
+        assert_eq!(voices.switch_for(Some("de")), None);
+        assert!(voices.get().is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+// Import books that arrive wrapped in a `.zip` / `.rar` download.
+//
+// Store and forum downloads frequently ship one or more EPUBs (or a
+// PDF + EPUB pair) inside a plain archive. The importer used to reject
+// those outright, or — for `.zip` — hand the whole archive to foliate-js,
+// which only understands the single-book `fb2.zip` / CBZ layouts. This
+// module lets the import flow:
+//   1. peek inside an archive and list the book files it contains
+//      (`list_archive_books`, also used by `dir_scanner::read_dir` when a
+//      folder scan is asked to look into archives);
+//   2. extract the entries the user picked into a destination directory
+//      (`extract_archive_books`), streaming per-entry progress over a
+//      `Channel` the same way `transfer_file` reports download progress.
+//
+// Extraction never trusts entry names: every entry is written under its
+// bare file name (no directories, no `..`), de-duplicated against what
+// is already in the destination. The destination itself goes through
+// the same scope check as `download_file`, so the webview can't use this
+// to drop files outside the library / granted folders.
+//
+// `.epub` and `.cbz` files are zips too, but they are books — only the
+// `zip` / `rar` extensions are treated as containers. RAR needs the `rar`
+// feature; without it `.rar` archives are still recognised but listing or
+// extracting them fails with `RAR_UNAVAILABLE`.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{ipc::Channel, AppHandle};
+use zip::ZipArchive;
+
+use crate::transfer_file::ensure_path_allowed;
+
+/// Book formats we look for inside an archive. Mirrors
+/// `SUPPORTED_BOOK_EXTS` in `services/constants.ts`, minus `zip` (nested
+/// archives are not descended into).
+pub const ARCHIVE_BOOK_EXTENSIONS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "fb2", "cbz", "cbr", "cb7", "pdf", "txt", "md",
+];
+
+/// Error for RAR archives in builds without the `rar` feature.
+pub(crate) const RAR_UNAVAILABLE: &str = "RAR archives are not supported in this build";
+
+/// Extensions treated as book containers rather than books.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Rar,
+}
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "zip" => Some(ArchiveKind::Zip),
+            "rar" => Some(ArchiveKind::Rar),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBookEntry {
+    /// Entry name as stored in the archive (may contain directories).
+    /// Passed back verbatim to `extract_archive_books`.
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveExtractProgress {
+    /// Entry currently being written.
+    pub entry: String,
+    /// 1-based index of `entry` among the requested entries.
+    pub index: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedArchiveBook {
+    pub name: String,
+    /// Absolute path of the extracted file on disk.
+    pub path: String,
+    pub size: u64,
+}
+
+/// True when `name` (an archive entry name) looks like a book we can import.
+/// Hidden files and macOS resource forks (`__MACOSX/._foo.epub`) are skipped.
+fn is_book_entry(name: &str) -> bool {
+    let normalized = name.replace('\\', "/");
+    if normalized.ends_with('/') || normalized.starts_with("__MACOSX/") {
+        return false;
+    }
+    let file_name = normalized.rsplit('/').next().unwrap_or("");
+    if file_name.is_empty() || file_name.starts_with('.') {
+        return false;
+    }
+    match file_name.rsplit_once('.') {
+        Some((_, ext)) => ARCHIVE_BOOK_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Bare file name of an archive entry, stripped of any directory part so a
+/// malicious `../../x.epub` can only ever land directly in the destination.
+fn entry_file_name(name: &str) -> Option<String> {
+    let normalized = name.replace('\\', "/");
+    let file_name = normalized.rsplit('/').next()?.trim();
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        return None;
+    }
+    Some(file_name.to_string())
+}
+
+/// Pick a destination path for `file_name` under `dir` that doesn't clobber
+/// an existing file: `book.epub`, then `book (1).epub`, `book (2).epub`, …
+fn unique_destination(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (file_name, None),
+    };
+    for n in 1.. {
+        let name = match ext {
+            Some(ext) => format!("{stem} ({n}).{ext}"),
+            None => format!("{stem} ({n})"),
+        };
+        let candidate = dir.join(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+    }
+    unreachable!()
+}
+
+pub fn list_archive_books_sync(path: &Path) -> Result<Vec<ArchiveBookEntry>, String> {
+    match ArchiveKind::from_path(path) {
+        Some(ArchiveKind::Zip) => list_zip_books(path),
+        Some(ArchiveKind::Rar) => list_rar_books(path),
+        None => Err(format!("not an archive: {}", path.display())),
+    }
+}
+
+fn list_zip_books(path: &Path) -> Result<Vec<ArchiveBookEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    let mut books = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip
+            .by_index_raw(i)
+            .map_err(|e| format!("zip entry {i}: {e}"))?;
+        if entry.is_file() && is_book_entry(entry.name()) {
+            books.push(ArchiveBookEntry {
+                name: entry.name().to_string(),
+                size: entry.size(),
+            });
+        }
+    }
+    Ok(books)
+}
+
+#[cfg(not(feature = "rar"))]
+fn list_rar_books(_path: &Path) -> Result<Vec<ArchiveBookEntry>, String> {
+    Err(RAR_UNAVAILABLE.to_string())
+}
+
+#[cfg(feature = "rar")]
+fn list_rar_books(path: &Path) -> Result<Vec<ArchiveBookEntry>, String> {
+    let archive = unrar::Archive::new(path)
+        .open_for_listing()
+        .map_err(|e| format!("rar open failed: {e}"))?;
+    let mut books = Vec::new();
+    for header in archive {
+        let header = header.map_err(|e| format!("rar entry: {e}"))?;
+        let name = header.filename.to_string_lossy().to_string();
+        if header.is_file() && is_book_entry(&name) {
+            books.push(ArchiveBookEntry {
+                name,
+                size: header.unpacked_size,
+            });
+        }
+    }
+    Ok(books)
+}
+
+fn extract_archive_books_sync(
+    path: &Path,
+    entries: &[String],
+    dest_dir: &Path,
+    on_progress: &Channel<ArchiveExtractProgress>,
+) -> Result<Vec<ExtractedArchiveBook>, String> {
+    std::fs::create_dir_all(dest_dir).map_err(|e| format!("create dest dir: {e}"))?;
+    match ArchiveKind::from_path(path) {
+        Some(ArchiveKind::Zip) => extract_zip_books(path, entries, dest_dir, on_progress),
+        Some(ArchiveKind::Rar) => extract_rar_books(path, entries, dest_dir, on_progress),
+        None => Err(format!("not an archive: {}", path.display())),
+    }
+}
+
+/// Where entry `name` is written under `dest_dir`.
+fn entry_destination(name: &str, dest_dir: &Path) -> Result<PathBuf, String> {
+    let file_name = entry_file_name(name).ok_or_else(|| format!("invalid entry name: {name}"))?;
+    Ok(unique_destination(dest_dir, &file_name))
+}
+
+fn write_entry(
+    name: &str,
+    dest_dir: &Path,
+    reader: &mut dyn Read,
+) -> Result<ExtractedArchiveBook, String> {
+    let dest = entry_destination(name, dest_dir)?;
+    let mut out = File::create(&dest).map_err(|e| format!("create {}: {e}", dest.display()))?;
+    let size = std::io::copy(reader, &mut out).map_err(|e| format!("extract {name}: {e}"))?;
+    out.flush()
+        .map_err(|e| format!("flush {}: {e}", dest.display()))?;
+    Ok(ExtractedArchiveBook {
+        name: name.to_string(),
+        path: dest.to_string_lossy().to_string(),
+        size,
+    })
+}
+
+fn extract_zip_books(
+    path: &Path,
+    entries: &[String],
+    dest_dir: &Path,
+    on_progress: &Channel<ArchiveExtractProgress>,
+) -> Result<Vec<ExtractedArchiveBook>, String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    let mut extracted = Vec::with_capacity(entries.len());
+    for (i, name) in entries.iter().enumerate() {
+        let _ = on_progress.send(ArchiveExtractProgress {
+            entry: name.clone(),
+            index: i + 1,
+            total: entries.len(),
+        });
+        let mut entry = zip
+            .by_name(name)
+            .map_err(|e| format!("entry {name}: {e}"))?;
+        extracted.push(write_entry(name, dest_dir, &mut entry)?);
+    }
+    Ok(extracted)
+}
+
+#[cfg(not(feature = "rar"))]
+fn extract_rar_books(
+    _path: &Path,
+    _entries: &[String],
+    _dest_dir: &Path,
+    _on_progress: &Channel<ArchiveExtractProgress>,
+) -> Result<Vec<ExtractedArchiveBook>, String> {
+    Err(RAR_UNAVAILABLE.to_string())
+}
+
+#[cfg(feature = "rar")]
+fn extract_rar_books(
+    path: &Path,
+    entries: &[String],
+    dest_dir: &Path,
+    on_progress: &Channel<ArchiveExtractProgress>,
+) -> Result<Vec<ExtractedArchiveBook>, String> {
+    // RAR is a sequential format: walk the headers once and extract only the
+    // entries that were requested, skipping the rest without decompressing.
+    // unrar writes each one straight to its destination file.
+    let mut archive = unrar::Archive::new(path)
+        .open_for_processing()
+        .map_err(|e| format!("rar open failed: {e}"))?;
+    let mut extracted = Vec::with_capacity(entries.len());
+    while let Some(header) = archive
+        .read_header()
+        .map_err(|e| format!("rar header: {e}"))?
+    {
+        let name = header.entry().filename.to_string_lossy().to_string();
+        archive = if header.entry().is_file() && entries.contains(&name) {
+            let _ = on_progress.send(ArchiveExtractProgress {
+                entry: name.clone(),
+                index: extracted.len() + 1,
+                total: entries.len(),
+            });
+            let dest = entry_destination(&name, dest_dir)?;
+            let next = header
+                .extract_to(&dest)
+                .map_err(|e| format!("extract {name}: {e}"))?;
+            let size = std::fs::metadata(&dest)
+                .map_err(|e| format!("extract {name}: {e}"))?
+                .len();
+            extracted.push(ExtractedArchiveBook {
+                name,
+                path: dest.to_string_lossy().to_string(),
+                size,
+            });
+            next
+        } else {
+            header.skip().map_err(|e| format!("rar skip: {e}"))?
+        };
+    }
+    if extracted.len() < entries.len() {
+        let missing: Vec<&String> = entries
+            .iter()
+            .filter(|e| !extracted.iter().any(|x| &x.name == *e))
+            .collect();
+        return Err(format!("entries not found in archive: {missing:?}"));
+    }
+    Ok(extracted)
+}
+
+/// List the importable books inside a `.zip` / `.rar` archive.
+#[tauri::command]
+pub async fn list_archive_books(
+    app: AppHandle,
+    file_path: String,
+) -> Result<Vec<ArchiveBookEntry>, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || list_archive_books_sync(Path::new(&file_path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Extract the selected `entries` of an archive into `dest_dir` and return
+/// where each one landed. Progress is reported once per entry.
+#[tauri::command]
+pub async fn extract_archive_books(
+    app: AppHandle,
+    file_path: String,
+    entries: Vec<String>,
+    dest_dir: String,
+    on_progress: Channel<ArchiveExtractProgress>,
+) -> Result<Vec<ExtractedArchiveBook>, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    ensure_path_allowed(&app, &dest_dir).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        extract_archive_books_sync(
+            Path::new(&file_path),
+            &entries,
+            Path::new(&dest_dir),
+            &on_progress,
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn write_test_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut buf = Vec::new();
+        {
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            for (name, data) in entries {
+                w.start_file(*name, zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                w.write_all(data).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        std::fs::write(path, buf).unwrap();
+    }
+
+    #[test]
+    fn archive_kind_is_detected_by_extension_only() {
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/x/Books.ZIP")),
+            Some(ArchiveKind::Zip)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/x/books.rar")),
+            Some(ArchiveKind::Rar)
+        );
+        // EPUB and CBZ are zips on disk but are books, not containers.
+        assert_eq!(ArchiveKind::from_path(Path::new("/x/book.epub")), None);
+        assert_eq!(ArchiveKind::from_path(Path::new("/x/comic.cbz")), None);
+    }
+
+    #[test]
+    fn book_entries_skip_junk_and_directories() {
+        assert!(is_book_entry("Author/Title.epub"));
+        assert!(is_book_entry("Title.PDF"));
+        assert!(!is_book_entry("Author/"));
+        assert!(!is_book_entry("__MACOSX/Author/._Title.epub"));
+        assert!(!is_book_entry("Author/.hidden.epub"));
+        assert!(!is_book_entry("readme.nfo"));
+        assert!(!is_book_entry("nested.zip"));
+    }
+
+    #[test]
+    fn entry_file_name_strips_directories_and_traversal() {
+        assert_eq!(
+            entry_file_name("../../etc/book.epub").as_deref(),
+            Some("book.epub")
+        );
+        assert_eq!(
+            entry_file_name("dir\\sub\\book.pdf").as_deref(),
+            Some("book.pdf")
+        );
+        assert_eq!(entry_file_name("dir/"), None);
+        assert_eq!(entry_file_name(".."), None);
+    }
+
+    #[test]
+    fn unique_destination_does_not_clobber() {
+        let dir = std::env::temp_dir().join("readest-archive-unique-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unique_destination(&dir, "a.epub"), dir.join("a.epub"));
+        std::fs::write(dir.join("a.epub"), b"x").unwrap();
+        assert_eq!(unique_destination(&dir, "a.epub"), dir.join("a (1).epub"));
+        std::fs::write(dir.join("a (1).epub"), b"x").unwrap();
+        assert_eq!(unique_destination(&dir, "a.epub"), dir.join("a (2).epub"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lists_and_extracts_zip_books() {
+        let dir = std::env::temp_dir().join("readest-archive-zip-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("books.zip");
+        write_test_zip(
+            &archive,
+            &[
+                ("Series/One.epub", b"epub-one"),
+                ("Series/cover.jpg", b"jpg"),
+                ("Two.pdf", b"%PDF-1.4"),
+            ],
+        );
+
+        let books = list_archive_books_sync(&archive).unwrap();
+        let names: Vec<&str> = books.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["Series/One.epub", "Two.pdf"]);
+        assert_eq!(books[0].size, 8);
+
+        let out = dir.join("out");
+        std::fs::create_dir_all(&out).unwrap();
+        let file = File::open(&archive).unwrap();
+        let mut zip = ZipArchive::new(file).unwrap();
+        let mut entry = zip.by_name("Series/One.epub").unwrap();
+        let extracted = write_entry("Series/One.epub", &out, &mut entry).unwrap();
+        assert_eq!(extracted.size, 8);
+        assert_eq!(
+            std::fs::read(out.join("One.epub")).unwrap(),
+            b"epub-one".to_vec()
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,745 @@
+//! Chapter marks embedded in audiobook files.
+//!
+//! Three layouts cover nearly every audiobook in the wild:
+//!   - ID3v2 `CHAP` frames in MP3s (the ID3v2 Chapter Frame Addendum);
+//!   - MP4 chapters in M4B/M4A, either a QuickTime text track that the
+//!     audio track points at through `tref/chap` (what iTunes and Audible
+//!     write) or a Nero `chpl` atom under `moov/udta` (what ffmpeg and
+//!     mp4v2 add next to it);
+//!   - `CHAPTERxxx` / `CHAPTERxxxNAME` Vorbis comments in FLAC, Ogg and
+//!     Opus files.
+//!
+//! lofty reads the tags but none of these, so they are parsed here.
+
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Bound on the marks read from one file, against corrupt counts.
+const MAX_CHAPTERS: usize = 10_000;
+/// Largest ID3v2 tag or `moov` box read into memory. A 40-hour M4B's
+/// sample tables come to a few tens of MB at most.
+const MAX_READ: u64 = 64 << 20;
+/// Longest chapter title read from a text track sample.
+const MAX_TITLE: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioChapter {
+    pub title: String,
+    pub start_ms: u64,
+    /// The next chapter's start, or the end of the audio.
+    pub end_ms: u64,
+}
+
+#[derive(Debug)]
+struct Mark {
+    start_ms: u64,
+    title: String,
+}
+
+/// Order the marks, drop duplicates and marks past the end, and give each
+/// chapter the start of the next as its end.
+fn finish(mut marks: Vec<Mark>, duration_ms: u64) -> Vec<AudioChapter> {
+    marks.sort_by_key(|m| m.start_ms);
+    marks.dedup_by_key(|m| m.start_ms);
+    if duration_ms > 0 {
+        marks.retain(|m| m.start_ms < duration_ms);
+    }
+    marks.truncate(MAX_CHAPTERS);
+    let mut chapters = Vec::with_capacity(marks.len());
+    let mut marks = marks.into_iter().peekable();
+    while let Some(mark) = marks.next() {
+        let end_ms = marks
+            .peek()
+            .map_or(duration_ms, |next| next.start_ms)
+            .max(mark.start_ms);
+        chapters.push(AudioChapter {
+            title: mark.title,
+            start_ms: mark.start_ms,
+            end_ms,
+        });
+    }
+    chapters
+}
+
+fn be_u16(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn be_u64(b: &[u8]) -> u64 {
+    u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+}
+
+fn syncsafe(b: &[u8]) -> u32 {
+    b[..4]
+        .iter()
+        .fold(0, |size, &byte| (size << 7) | u32::from(byte & 0x7f))
+}
+
+/// Undo ID3 unsynchronisation: every `FF 00` was written for a lone `FF`.
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut after_ff = false;
+    for &byte in data {
+        if !(after_ff && byte == 0) {
+            out.push(byte);
+        }
+        after_ff = byte == 0xff;
+    }
+    out
+}
+
+/// Text with a NUL-terminated UTF-16 / UTF-8 / Latin-1 payload, decoded up
+/// to the first terminator.
+fn decode_text(encoding: u8, text: &[u8]) -> String {
+    let text = match encoding {
+        0 => text
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| char::from(b))
+            .collect(),
+        1 | 2 => {
+            let mut units: Vec<u16> = text.chunks_exact(2).map(be_u16).collect();
+            match units.first() {
+                Some(0xfeff) => {
+                    units.remove(0);
+                }
+                Some(0xfffe) => {
+                    units.remove(0);
+                    units.iter_mut().for_each(|u| *u = u.swap_bytes());
+                }
+                _ => {}
+            }
+            let end = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+            String::from_utf16_lossy(&units[..end])
+        }
+        _ => {
+            let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+            String::from_utf8_lossy(&text[..end]).into_owned()
+        }
+    };
+    text.trim().to_string()
+}
+
+/// The frames of an ID3v2.3 or v2.4 tag body. Compressed and encrypted
+/// frames are skipped; nothing chapters need is ever stored that way.
+fn id3_frames(mut body: &[u8], version: u8) -> Vec<([u8; 4], Cow<'_, [u8]>)> {
+    let mut frames = Vec::new();
+    while body.len() >= 10 && body[0] != 0 {
+        let id = [body[0], body[1], body[2], body[3]];
+        let size = if version == 4 {
+            syncsafe(&body[4..8])
+        } else {
+            be_u32(&body[4..8])
+        } as usize;
+        let format = body[9];
+        let Some(data) = body.get(10..10 + size) else {
+            break;
+        };
+        body = &body[10 + size..];
+        let (skip, grouped, length, unsync) = if version == 4 {
+            (
+                format & 0x0c != 0,
+                format & 0x40 != 0,
+                format & 0x01 != 0,
+                format & 0x02 != 0,
+            )
+        } else {
+            (format & 0xc0 != 0, format & 0x20 != 0, false, false)
+        };
+        if skip {
+            continue;
+        }
+        let header = usize::from(grouped) + if length { 4 } else { 0 };
+        let Some(data) = data.get(header..) else {
+            continue;
+        };
+        let data = if unsync {
+            Cow::Owned(resynchronise(data))
+        } else {
+            Cow::Borrowed(data)
+        };
+        frames.push((id, data));
+    }
+    frames
+}
+
+/// A `CHAP` frame: element id, start/end time and byte offsets, then
+/// sub-frames of which `TIT2` holds the title.
+fn chap_frame(data: &[u8], version: u8) -> Option<Mark> {
+    let id_end = data.iter().position(|&b| b == 0)?;
+    let times = data.get(id_end + 1..id_end + 17)?;
+    let title = id3_frames(&data[id_end + 17..], version)
+        .into_iter()
+        .find(|(id, _)| id == b"TIT2")
+        .and_then(|(_, text)| {
+            let (&encoding, text) = text.split_first()?;
+            Some(decode_text(encoding, text))
+        })
+        .unwrap_or_default();
+    Some(Mark {
+        start_ms: u64::from(be_u32(&times[..4])),
+        title,
+    })
+}
+
+/// Chapters from the `CHAP` frames of the ID3v2 tag at the start of an
+/// MP3. Empty when there is no tag, or it has no chapters.
+pub fn id3_chapters<R: Read + Seek>(
+    reader: &mut R,
+    duration_ms: u64,
+) -> Result<Vec<AudioChapter>, String> {
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|e| format!("seek: {e}"))?;
+    let mut header = [0u8; 10];
+    if reader.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(Vec::new());
+    }
+    // ID3v2.2 predates the chapter frames.
+    let version = header[3];
+    if !(3..=4).contains(&version) {
+        return Ok(Vec::new());
+    }
+    let flags = header[5];
+    let size = u64::from(syncsafe(&header[6..10]));
+    if size > MAX_READ {
+        return Err(format!("ID3 tag too large: {size} bytes"));
+    }
+    let mut tag = vec![0; size as usize];
+    reader
+        .read_exact(&mut tag)
+        .map_err(|e| format!("read ID3 tag: {e}"))?;
+    if version == 3 && flags & 0x80 != 0 {
+        tag = resynchronise(&tag);
+    }
+    let mut body = &tag[..];
+    if flags & 0x40 != 0 && body.len() >= 4 {
+        let extended = if version == 4 {
+            syncsafe(body) as usize
+        } else {
+            be_u32(body) as usize + 4
+        };
+        body = body.get(extended..).unwrap_or_default();
+    }
+    let marks = id3_frames(body, version)
+        .into_iter()
+        .filter(|(id, _)| id == b"CHAP")
+        .filter_map(|(_, data)| chap_frame(&data, version))
+        .take(MAX_CHAPTERS)
+        .collect();
+    Ok(finish(marks, duration_ms))
+}
+
+/// Iterates the boxes laid out back to back in `data`, yielding each
+/// one's type and content.
+struct Boxes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Boxes<'a> {
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.data;
+        if data.len() < 8 {
+            return None;
+        }
+        let kind = [data[4], data[5], data[6], data[7]];
+        let (header, size) = match be_u32(data) {
+            0 => (8, data.len() as u64),
+            1 if data.len() >= 16 => (16, be_u64(&data[8..16])),
+            size => (8, u64::from(size)),
+        };
+        if size < header as u64 || size > data.len() as u64 {
+            self.data = &[];
+            return None;
+        }
+        self.data = &data[size as usize..];
+        Some((kind, &data[header..size as usize]))
+    }
+}
+
+fn boxes(data: &[u8]) -> Boxes<'_> {
+    Boxes { data }
+}
+
+/// The content of the first box at `path` below `data`.
+fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| {
+        boxes(data)
+            .find(|(k, _)| k == *kind)
+            .map(|(_, content)| content)
+    })
+}
+
+/// Read the top-level `moov` box, skipping over `mdat` and anything else
+/// on the way.
+fn read_moov<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let end = reader
+        .seek(SeekFrom::End(0))
+        .map_err(|e| format!("seek: {e}"))?;
+    let mut pos = 0;
+    while pos + 8 <= end {
+        reader
+            .seek(SeekFrom::Start(pos))
+            .map_err(|e| format!("seek: {e}"))?;
+        let mut header = [0u8; 16];
+        reader
+            .read_exact(&mut header[..8])
+            .map_err(|e| format!("read box header: {e}"))?;
+        let (header_len, size) = match be_u32(&header) {
+            0 => (8, end - pos),
+            1 => {
+                reader
+                    .read_exact(&mut header[8..])
+                    .map_err(|e| format!("read box header: {e}"))?;
+                (16, be_u64(&header[8..]))
+            }
+            size => (8, u64::from(size)),
+        };
+        if size < header_len || pos + size > end {
+            return Ok(None);
+        }
+        if &header[4..8] == b"moov" {
+            let len = size - header_len;
+            if len > MAX_READ {
+                return Err(format!("moov box too large: {len} bytes"));
+            }
+            let mut moov = vec![0; len as usize];
+            reader
+                .read_exact(&mut moov)
+                .map_err(|e| format!("read moov: {e}"))?;
+            return Ok(Some(moov));
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+/// A Nero `chpl` box: version, flags, a reserved word in version 1, a
+/// count, then per chapter a start in 100 ns units and a length-prefixed
+/// UTF-8 title.
+fn chpl_marks(data: &[u8]) -> Vec<Mark> {
+    let Some(&version) = data.first() else {
+        return Vec::new();
+    };
+    let mut pos = if version == 0 { 4 } else { 8 };
+    let Some(&count) = data.get(pos) else {
+        return Vec::new();
+    };
+    pos += 1;
+    let mut marks = Vec::with_capacity(count.into());
+    for _ in 0..count {
+        let Some(start) = data.get(pos..pos + 8) else {
+            break;
+        };
+        let Some(&len) = data.get(pos + 8) else {
+            break;
+        };
+        let Some(title) = data.get(pos + 9..pos + 9 + usize::from(len)) else {
+            break;
+        };
+        pos += 9 + usize::from(len);
+        marks.push(Mark {
+            start_ms: be_u64(start) / 10_000,
+            title: decode_text(3, title),
+        });
+    }
+    marks
+}
+
+/// Skip the version/flags word and read the entry count of a full box.
+fn table(data: &[u8]) -> Option<(usize, &[u8])> {
+    let count = be_u32(data.get(4..8)?) as usize;
+    Some((count, &data[8..]))
+}
+
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = find_box(trak, &[b"tkhd"])?;
+    let at = if tkhd.first()? == &1 { 20 } else { 12 };
+    tkhd.get(at..at + 4).map(be_u32)
+}
+
+/// One sample of a track; `start` is in the track's timescale.
+struct Sample {
+    start: u64,
+    offset: u64,
+    size: u32,
+}
+
+/// The timescale and samples of a track, from its sample tables.
+fn samples(trak: &[u8]) -> Option<(u32, Vec<Sample>)> {
+    let mdia = find_box(trak, &[b"mdia"])?;
+    let mdhd = find_box(mdia, &[b"mdhd"])?;
+    let at = if mdhd.first()? == &1 { 20 } else { 12 };
+    let timescale = be_u32(mdhd.get(at..at + 4)?);
+    let stbl = find_box(mdia, &[b"minf", b"stbl"])?;
+
+    let (count, entries) = table(find_box(stbl, &[b"stts"])?)?;
+    let mut starts = Vec::new();
+    let mut time = 0u64;
+    for entry in entries.chunks_exact(8).take(count) {
+        for _ in 0..be_u32(entry) {
+            if starts.len() == MAX_CHAPTERS {
+                break;
+            }
+            starts.push(time);
+            time += u64::from(be_u32(&entry[4..]));
+        }
+    }
+
+    let stsz = find_box(stbl, &[b"stsz"])?;
+    let fixed = be_u32(stsz.get(4..8)?);
+    let count = (be_u32(stsz.get(8..12)?) as usize).min(starts.len());
+    let sizes: Vec<u32> = if fixed != 0 {
+        vec![fixed; count]
+    } else {
+        stsz[12..].chunks_exact(4).take(count).map(be_u32).collect()
+    };
+
+    let chunks: Vec<u64> = if let Some(stco) = find_box(stbl, &[b"stco"]) {
+        let (count, entries) = table(stco)?;
+        entries
+            .chunks_exact(4)
+            .take(count)
+            .map(|b| u64::from(be_u32(b)))
+            .collect()
+    } else {
+        let (count, entries) = table(find_box(stbl, &[b"co64"])?)?;
+        entries.chunks_exact(8).take(count).map(be_u64).collect()
+    };
+    let (count, entries) = table(find_box(stbl, &[b"stsc"])?)?;
+    let runs: Vec<(usize, u32)> = entries
+        .chunks_exact(12)
+        .take(count)
+        .map(|e| (be_u32(e) as usize, be_u32(&e[4..])))
+        .collect();
+
+    let mut out = Vec::with_capacity(sizes.len());
+    for (i, &chunk_offset) in chunks.iter().enumerate() {
+        let chunk = i + 1;
+        let per_chunk = runs
+            .iter()
+            .take_while(|(first, _)| *first <= chunk)
+            .last()
+            .map_or(1, |&(_, n)| n);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            let Some(&size) = sizes.get(out.len()) else {
+                break;
+            };
+            out.push(Sample {
+                start: starts[out.len()],
+                offset,
+                size,
+            });
+            offset += u64::from(size);
+        }
+        if out.len() == sizes.len() {
+            break;
+        }
+    }
+    Some((timescale, out))
+}
+
+/// Chapters from the QuickTime text track some other track references
+/// through `tref/chap`. Each sample is a length-prefixed title.
+fn text_track_marks<R: Read + Seek>(reader: &mut R, moov: &[u8]) -> Result<Vec<Mark>, String> {
+    let traks: Vec<&[u8]> = boxes(moov)
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, trak)| trak)
+        .collect();
+    let Some(chapter_id) = traks
+        .iter()
+        .find_map(|trak| find_box(trak, &[b"tref", b"chap"]))
+        .and_then(|chap| chap.get(..4))
+        .map(be_u32)
+    else {
+        return Ok(Vec::new());
+    };
+    let Some((timescale, samples)) = traks
+        .iter()
+        .find(|trak| track_id(trak) == Some(chapter_id))
+        .and_then(|trak| samples(trak))
+    else {
+        return Ok(Vec::new());
+    };
+    if timescale == 0 {
+        return Ok(Vec::new());
+    }
+    let mut marks = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let mut title = String::new();
+        if sample.size >= 2 {
+            reader
+                .seek(SeekFrom::Start(sample.offset))
+                .map_err(|e| format!("seek: {e}"))?;
+            let mut len = [0u8; 2];
+            reader
+                .read_exact(&mut len)
+                .map_err(|e| format!("read chapter title: {e}"))?;
+            let len = usize::from(be_u16(&len))
+                .min(sample.size as usize - 2)
+                .min(MAX_TITLE);
+            let mut text = vec![0; len];
+            reader
+                .read_exact(&mut text)
+                .map_err(|e| format!("read chapter title: {e}"))?;
+            // A byte order mark means UTF-16; otherwise it's UTF-8.
+            let encoding = if text.starts_with(&[0xfe, 0xff]) || text.starts_with(&[0xff, 0xfe]) {
+                1
+            } else {
+                3
+            };
+            title = decode_text(encoding, &text);
+        }
+        marks.push(Mark {
+            start_ms: sample.start * 1000 / u64::from(timescale),
+            title,
+        });
+    }
+    Ok(marks)
+}
+
+/// Chapters of an MP4 (M4B/M4A). The QuickTime text track wins over a
+/// Nero `chpl` box when a file has both, as it does in Apple's players.
+pub fn mp4_chapters<R: Read + Seek>(
+    reader: &mut R,
+    duration_ms: u64,
+) -> Result<Vec<AudioChapter>, String> {
+    let Some(moov) = read_moov(reader)? else {
+        return Ok(Vec::new());
+    };
+    let mut marks = text_track_marks(reader, &moov)?;
+    if marks.is_empty() {
+        if let Some(chpl) = find_box(&moov, &[b"udta", b"chpl"]) {
+            marks = chpl_marks(chpl);
+        }
+    }
+    Ok(finish(marks, duration_ms))
+}
+
+/// `HH:MM:SS.mmm` as written in `CHAPTERxxx` comments.
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let mut parts = value.trim().rsplit(':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+    let hours: u64 = parts.next().map_or(Some(0), |h| h.parse().ok())?;
+    if parts.next().is_some() || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Chapters from `CHAPTER001=00:00:00.000` / `CHAPTER001NAME=Title` pairs
+/// among a file's Vorbis comments.
+pub fn vorbis_chapters<'a>(
+    comments: impl IntoIterator<Item = (&'a str, &'a str)>,
+    duration_ms: u64,
+) -> Vec<AudioChapter> {
+    let mut starts = std::collections::BTreeMap::new();
+    let mut names = std::collections::HashMap::new();
+    for (key, value) in comments {
+        let key = key.to_ascii_uppercase();
+        let Some(rest) = key.strip_prefix("CHAPTER") else {
+            continue;
+        };
+        let (number, name) = match rest.strip_suffix("NAME") {
+            Some(number) => (number, true),
+            None => (rest, false),
+        };
+        let Ok(number) = number.parse::<u32>() else {
+            continue;
+        };
+        if name {
+            names.insert(number, value.trim().to_string());
+        } else if let Some(start_ms) = parse_timestamp(value) {
+            starts.insert(number, start_ms);
+        }
+    }
+    let marks = starts
+        .into_iter()
+        .take(MAX_CHAPTERS)
+        .map(|(number, start_ms)| Mark {
+            start_ms,
+            title: names.remove(&number).unwrap_or_default(),
+        })
+        .collect();
+    finish(marks, duration_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn chap(id: &str, start: u32, end: u32, title: &str) -> Vec<u8> {
+        let mut data = id.as_bytes().to_vec();
+        data.push(0);
+        for n in [start, end, u32::MAX, u32::MAX] {
+            data.extend_from_slice(&n.to_be_bytes());
+        }
+        let mut text = vec![3];
+        text.extend_from_slice(title.as_bytes());
+        data.extend(frame(b"TIT2", &text));
+        frame(b"CHAP", &data)
+    }
+
+    fn id3_tag(frames: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = frames.concat();
+        let size = body.len() as u32;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        for shift in [21, 14, 7, 0] {
+            tag.push(((size >> shift) & 0x7f) as u8);
+        }
+        tag.extend(body);
+        tag.extend_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        tag
+    }
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut out = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn full_box(kind: &[u8; 4], words: &[u32]) -> Vec<u8> {
+        let content: Vec<u8> = [0u32]
+            .iter()
+            .chain(words)
+            .flat_map(|w| w.to_be_bytes())
+            .collect();
+        mp4_box(kind, &content)
+    }
+
+    #[test]
+    fn reads_id3_chap_frames_in_time_order() {
+        let tag = id3_tag(&[
+            frame(b"TIT2", b"\x03Book"),
+            chap("ch1", 90_000, 200_000, "Two"),
+            chap("ch0", 0, 90_000, "One"),
+        ]);
+        let chapters = id3_chapters(&mut Cursor::new(tag), 200_000).unwrap();
+        assert_eq!(
+            chapters,
+            [
+                AudioChapter {
+                    title: "One".into(),
+                    start_ms: 0,
+                    end_ms: 90_000
+                },
+                AudioChapter {
+                    title: "Two".into(),
+                    start_ms: 90_000,
+                    end_ms: 200_000
+                },
+            ]
+        );
+        assert!(id3_chapters(&mut Cursor::new(b"fLaC".to_vec()), 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn decodes_utf16_titles_with_either_byte_order() {
+        assert_eq!(decode_text(1, b"\xff\xfeO\x00n\x00e\x00\x00\x00"), "One");
+        assert_eq!(decode_text(1, b"\xfe\xff\x00O\x00n\x00e"), "One");
+        assert_eq!(decode_text(0, b"Caf\xe9\x00junk"), "Caf\u{e9}");
+        assert_eq!(resynchronise(b"\xff\x00\xe0\xff\x00"), b"\xff\xe0\xff");
+    }
+
+    #[test]
+    fn reads_nero_chapters_from_udta() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Opening"), (600_000_000, "Middle")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let moov = mp4_box(b"moov", &mp4_box(b"udta", &mp4_box(b"chpl", &chpl)));
+        let mut file = mp4_box(b"ftyp", b"M4B ");
+        file.extend(mp4_box(b"mdat", &[0; 32]));
+        file.extend(moov);
+        let chapters = mp4_chapters(&mut Cursor::new(file), 90_000).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "Middle");
+        assert_eq!((chapters[1].start_ms, chapters[1].end_ms), (60_000, 90_000));
+    }
+
+    #[test]
+    fn reads_the_referenced_text_track() {
+        // Two samples of 1s and 2s at timescale 1000, in one chunk that
+        // starts right after the 8-byte mdat header.
+        let samples = [b"\x00\x05Intro".as_slice(), b"\x00\x04Main".as_slice()];
+        let mdat: Vec<u8> = samples.concat();
+        let mut file = mp4_box(b"ftyp", b"M4B ");
+        let chunk_offset = file.len() as u32 + 8;
+        file.extend(mp4_box(b"mdat", &mdat));
+
+        let audio = mp4_box(
+            b"trak",
+            &[
+                full_box(b"tkhd", &[0, 0, 1]),
+                mp4_box(b"tref", &mp4_box(b"chap", &2u32.to_be_bytes())),
+            ]
+            .concat(),
+        );
+        let stbl = [
+            full_box(b"stts", &[2, 1, 1000, 1, 2000]),
+            full_box(b"stsz", &[0, 2, 7, 6]),
+            full_box(b"stsc", &[1, 1, 2, 1]),
+            full_box(b"stco", &[1, chunk_offset]),
+        ]
+        .concat();
+        let mdia = [
+            full_box(b"mdhd", &[0, 0, 1000, 3000]),
+            mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+        ]
+        .concat();
+        let text = mp4_box(
+            b"trak",
+            &[full_box(b"tkhd", &[0, 0, 2]), mp4_box(b"mdia", &mdia)].concat(),
+        );
+        file.extend(mp4_box(b"moov", &[audio, text].concat()));
+
+        let chapters = mp4_chapters(&mut Cursor::new(file), 3_000).unwrap();
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Intro", "Main"]);
+        assert_eq!((chapters[1].start_ms, chapters[1].end_ms), (1_000, 3_000));
+    }
+
+    #[test]
+    fn reads_vorbis_chapter_comments() {
+        let chapters = vorbis_chapters(
+            [
+                ("CHAPTER002", "00:10:00.500"),
+                ("chapter002name", "Second"),
+                ("CHAPTER001", "00:00:00.000"),
+                ("CHAPTER001NAME", "First"),
+                ("CHAPTER003", "garbage"),
+                ("TITLE", "Book"),
+            ],
+            3_600_000,
+        );
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "First");
+        assert_eq!(chapters[0].end_ms, 600_500);
+        assert_eq!(chapters[1].end_ms, 3_600_000);
+        assert_eq!(parse_timestamp("1:02:03.25"), Some(3_723_250));
+    }
+}
@@ -0,0 +1,226 @@
+//! Import fast path for audiobooks.
+//!
+//! `parse_audio_metadata` does for MP3, M4B/M4A, FLAC, Ogg and Opus files
+//! what `parse_mobi_metadata` and `parse_comic` do for books: partialMD5
+//! and everything the library grid shows, in one round trip. There is no
+//! JS-side parser to defer to for audio, so the tags are read here too:
+//! lofty for the title, authors, narrators, series, duration and embedded
+//! cover art, and [`super::chapters`] for the chapter marks.
+
+use lofty::file::FileType;
+use lofty::picture::PictureType;
+use lofty::prelude::*;
+use lofty::tag::Tag;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use super::chapters::{id3_chapters, mp4_chapters, vorbis_chapters, AudioChapter};
+use crate::parser_common::{compute_partial_md5, maybe_resize_cover, RawCoverImage};
+
+/// Free-form keys taggers use for the narrator: `TXXX:NARRATOR` in ID3,
+/// `©nrt` in Audible's M4Bs and `NARRATOR` / `PERFORMER` in Vorbis
+/// comments.
+const NARRATOR_KEYS: &[&str] = &["NARRATOR", "©nrt", "PERFORMER"];
+/// Free-form keys for the series name, when the movement isn't set.
+const SERIES_KEYS: &[&str] = &["SERIES", "©grp"];
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedAudio {
+    pub partial_md5: String,
+    /// The album, which audiobook taggers set to the book title, or the
+    /// track title when there is no album.
+    pub title: Option<String>,
+    /// Album artist, falling back to the track artist.
+    pub authors: Vec<String>,
+    pub narrators: Vec<String>,
+    pub series: Option<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    pub genre: Option<String>,
+    pub language: Option<String>,
+    pub year: Option<u32>,
+    pub duration_ms: u64,
+    pub chapters: Vec<AudioChapter>,
+    /// The front cover picture, or the first picture when none is marked
+    /// as the front cover, downscaled for the library grid.
+    pub cover: Option<RawCoverImage>,
+}
+
+/// Tauri command: read an audiobook's partialMD5, tags, duration, chapters
+/// and cover in one IPC round-trip.
+#[tauri::command]
+pub async fn parse_audio_metadata(file_path: String) -> Result<ParsedAudio, String> {
+    tauri::async_runtime::spawn_blocking(move || parse_audio_metadata_sync(Path::new(&file_path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn parse_audio_metadata_sync(path: &Path) -> Result<ParsedAudio, String> {
+    if !path.is_file() {
+        return Err(format!("file not found: {}", path.display()));
+    }
+    let partial_md5 = compute_partial_md5(path).map_err(|e| format!("partial_md5 failed: {e}"))?;
+
+    let tagged = lofty::read_from_path(path).map_err(|e| format!("read audio tags: {e}"))?;
+    let duration_ms = tagged.properties().duration().as_millis() as u64;
+    let tag = tagged.primary_tag().or_else(|| tagged.first_tag());
+
+    // A file whose chapters can't be read still imports, as one long
+    // chapter.
+    let chapters = match read_chapters(path, tagged.file_type(), tag, duration_ms) {
+        Ok(chapters) => chapters,
+        Err(e) => {
+            log::warn!("audio chapters in {}: {e}", path.display());
+            Vec::new()
+        }
+    };
+
+    let mut parsed = ParsedAudio {
+        partial_md5,
+        duration_ms,
+        chapters,
+        ..Default::default()
+    };
+    let Some(tag) = tag else {
+        return Ok(parsed);
+    };
+    parsed.title = text(tag.album().as_deref()).or_else(|| text(tag.title().as_deref()));
+    parsed.authors = people(
+        tag.get_string(&ItemKey::AlbumArtist)
+            .or(tag.artist().as_deref()),
+    );
+    parsed.narrators = people(
+        free_form(tag, NARRATOR_KEYS)
+            .or_else(|| tag.get_string(&ItemKey::Composer))
+            .map(strip_narrated_by),
+    );
+    parsed.series = text(
+        tag.get_string(&ItemKey::Movement)
+            .or_else(|| free_form(tag, SERIES_KEYS)),
+    );
+    parsed.publisher = text(tag.get_string(&ItemKey::Label));
+    parsed.description = text(
+        tag.get_string(&ItemKey::Description)
+            .or_else(|| tag.get_string(&ItemKey::Comment)),
+    );
+    parsed.genre = text(tag.genre().as_deref());
+    parsed.language = text(tag.get_string(&ItemKey::Language));
+    parsed.year = tag.year();
+    parsed.cover = tag
+        .pictures()
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| tag.pictures().first())
+        .map(|picture| {
+            let mime = picture.mime_type().map_or("image/jpeg", |m| m.as_str());
+            let (bytes, mime) = maybe_resize_cover(picture.data().to_vec(), mime);
+            RawCoverImage { bytes, mime }
+        });
+    Ok(parsed)
+}
+
+fn read_chapters(
+    path: &Path,
+    file_type: FileType,
+    tag: Option<&Tag>,
+    duration_ms: u64,
+) -> Result<Vec<AudioChapter>, String> {
+    match file_type {
+        FileType::Mpeg => {
+            let file = File::open(path).map_err(|e| format!("open: {e}"))?;
+            id3_chapters(&mut BufReader::new(file), duration_ms)
+        }
+        FileType::Mp4 => {
+            let file = File::open(path).map_err(|e| format!("open: {e}"))?;
+            mp4_chapters(&mut BufReader::new(file), duration_ms)
+        }
+        _ => Ok(tag.map_or_else(Vec::new, |tag| {
+            let comments = tag.items().filter_map(|item| match item.key() {
+                ItemKey::Unknown(key) => Some((key.as_str(), item.value().text()?)),
+                _ => None,
+            });
+            vorbis_chapters(comments, duration_ms)
+        })),
+    }
+}
+
+/// A trimmed, non-empty tag value.
+fn text(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// The value of the first free-form item with one of `keys`.
+fn free_form<'a>(tag: &'a Tag, keys: &[&str]) -> Option<&'a str> {
+    tag.items().find_map(|item| match item.key() {
+        ItemKey::Unknown(key) if keys.iter().any(|k| key.eq_ignore_ascii_case(k)) => {
+            item.value().text()
+        }
+        _ => None,
+    })
+}
+
+/// Narrator tags often carry the credit line rather than just the name.
+fn strip_narrated_by(value: &str) -> &str {
+    let value = value.trim();
+    for prefix in ["narrated by ", "read by "] {
+        let credited = value
+            .get(..prefix.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(prefix));
+        if credited && value.len() > prefix.len() {
+            return &value[prefix.len()..];
+        }
+    }
+    value
+}
+
+/// Split a multi-person tag. ID3v2.4 separates values with NUL and most
+/// taggers with `;`; `&` and ` and ` join co-authors in a single name.
+/// `,` and `/` are left alone since they appear inside names ("Tolkien,
+/// J. R. R.", "AC/DC").
+fn people(value: Option<&str>) -> Vec<String> {
+    let Some(value) = value else {
+        return Vec::new();
+    };
+    let mut out: Vec<String> = Vec::new();
+    for part in value
+        .split(['\0', ';', '&'])
+        .flat_map(|part| part.split(" and "))
+    {
+        let name = part.trim();
+        if !name.is_empty() && !out.iter().any(|n| n == name) {
+            out.push(name.to_string());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_co_authors_and_drops_duplicates() {
+        assert_eq!(
+            people(Some("Terry Pratchett & Neil Gaiman; Neil Gaiman")),
+            ["Terry Pratchett", "Neil Gaiman"]
+        );
+        assert_eq!(
+            people(Some("Tolkien, J. R. R.\0Christopher Tolkien")),
+            ["Tolkien, J. R. R.", "Christopher Tolkien"]
+        );
+        assert!(people(Some(" ; ")).is_empty());
+    }
+
+    #[test]
+    fn strips_the_narrator_credit() {
+        assert_eq!(strip_narrated_by("Narrated by Stephen Fry"), "Stephen Fry");
+        assert_eq!(strip_narrated_by(" read by Jim Dale"), "Jim Dale");
+        assert_eq!(strip_narrated_by("Read by"), "Read by");
+    }
+}
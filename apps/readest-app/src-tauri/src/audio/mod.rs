@@ -0,0 +1,7 @@
+//! Audiobook import: tags, duration, chapter marks and cover art of MP3,
+//! M4B/M4A, FLAC, Ogg and Opus files, so audiobooks get a proper title,
+//! author and thumbnail in the library grid ([`metadata`]). Chapter marks
+//! come from the container formats lofty doesn't cover ([`chapters`]).
+
+pub mod chapters;
+pub mod metadata;
@@ -0,0 +1,276 @@
+// Custom `bookres` URI scheme that serves entries of a packaged (zip-based)
+// book to the WebView with the book's content-security policy attached.
+//
+// Documents loaded through blob URLs inherit the app's own CSP, which is far
+// too permissive for untrusted EPUB3 scripts and can't vary per book. Serving
+// the spine through this scheme instead lets every response carry a
+// `Content-Security-Policy` header derived from the book's `ContentPolicy`
+// (see `content_policy.rs`), so scripts, network access and storage are
+// allowed or denied by the WebView itself.
+//
+// The reader's section loader (`DocumentLoader` in `libs/document.ts`) reads
+// spine documents from here, and foliate-js then re-wraps the text into a
+// blob URL, which drops response headers. HTML documents therefore also get
+// the policy as a `<meta http-equiv>` tag, which the blob document keeps.
+//
+// URL shape: `http://bookres.localhost/<entry path>?path=<book file>&book=<hash>`
+// (`bookres://localhost/...` on macOS/Linux). The entry path is the zip-relative
+// href exactly as foliate-js resolves it, so relative links inside a chapter
+// keep working. Security mirrors `range_file`: only book files allowed by
+// `asset_protocol_scope` are opened.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use tauri::http::{Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+use zip::ZipArchive;
+
+use crate::content_policy::{self, ContentPolicy, ContentPolicyStore};
+use crate::epub_parser::read_zip_entry;
+use crate::range_file::{cors_origin, error, is_safe_path};
+
+/// Scheme name; the WebView reaches it at `http://bookres.localhost/`.
+pub const SCHEME: &str = "bookres";
+
+struct BookQuery {
+    path: PathBuf,
+    book: Option<String>,
+}
+
+fn parse_query(uri_query: Option<&str>) -> Option<BookQuery> {
+    let query = uri_query?;
+    let mut path: Option<PathBuf> = None;
+    let mut book: Option<String> = None;
+    for pair in query.split('&') {
+        let mut it = pair.splitn(2, '=');
+        let key = it.next().unwrap_or("");
+        let val = percent_encoding::percent_decode_str(it.next().unwrap_or(""))
+            .decode_utf8_lossy()
+            .into_owned();
+        if val.is_empty() {
+            continue;
+        }
+        match key {
+            "path" => path = Some(PathBuf::from(val)),
+            "book" => book = Some(val),
+            _ => {}
+        }
+    }
+    Some(BookQuery { path: path?, book })
+}
+
+/// Zip-relative entry name from the URI path. Left percent-encoded on purpose:
+/// `read_zip_entry` tries the literal name first and then the decoded one.
+fn entry_name(uri_path: &str) -> Option<&str> {
+    let entry = uri_path.trim_start_matches('/');
+    if entry.is_empty() || entry.split('/').any(|seg| seg == "..") {
+        return None;
+    }
+    Some(entry)
+}
+
+fn content_type(entry: &str) -> &'static str {
+    let ext = entry.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "xhtml" | "xht" => "application/xhtml+xml",
+        "html" | "htm" => "text/html",
+        "svg" => "image/svg+xml",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "xml" | "opf" | "ncx" => "application/xml",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "ogg" | "opus" => "audio/ogg",
+        "smil" => "application/smil+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_document(mime: &str) -> bool {
+    matches!(
+        mime,
+        "application/xhtml+xml" | "text/html" | "image/svg+xml"
+    )
+}
+
+/// Insert `snippet` right after the opening `<head>` tag so it takes effect
+/// before any script in the document; documents without a head get it up
+/// front. Works on the raw bytes so documents in other encodings pass through
+/// unchanged.
+pub(crate) fn inject_into_head(body: &[u8], snippet: &str) -> Vec<u8> {
+    // `<head>` or `<head attr..>`, but not `<header>`.
+    let insert_at = body
+        .windows(5)
+        .enumerate()
+        .filter(|(_, w)| w.eq_ignore_ascii_case(b"<head"))
+        .map(|(i, _)| i)
+        .find(|&i| matches!(body.get(i + 5), Some(b'>' | b' ' | b'\t' | b'\r' | b'\n')))
+        .and_then(|i| body[i..].iter().position(|&b| b == b'>').map(|j| i + j + 1))
+        .unwrap_or(0);
+    let mut out = Vec::with_capacity(body.len() + snippet.len());
+    out.extend_from_slice(&body[..insert_at]);
+    out.extend_from_slice(snippet.as_bytes());
+    out.extend_from_slice(&body[insert_at..]);
+    out
+}
+
+/// `<meta>` carrying `csp`; the policy only has `'` quotes, never `"`.
+fn csp_meta(csp: &str) -> String {
+    format!(r#"<meta http-equiv="Content-Security-Policy" content="{csp}"/>"#)
+}
+
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    responder.respond(build_response(ctx.app_handle(), &request));
+}
+
+fn build_response<R: Runtime>(app: &AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let origin = cors_origin(request);
+
+    let (query, entry) = match (
+        parse_query(request.uri().query()),
+        entry_name(request.uri().path()),
+    ) {
+        (Some(q), Some(e)) => (q, e.to_string()),
+        _ => return error(&origin, StatusCode::BAD_REQUEST),
+    };
+
+    if !is_safe_path(&query.path) {
+        log::warn!("bookres: rejected unsafe path: {:?}", query.path);
+        return error(&origin, StatusCode::FORBIDDEN);
+    }
+    if !app.asset_protocol_scope().is_allowed(&query.path) {
+        log::warn!("bookres: path not allowed by asset scope: {:?}", query.path);
+        return error(&origin, StatusCode::FORBIDDEN);
+    }
+
+    let file = match File::open(&query.path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return error(&origin, StatusCode::NOT_FOUND)
+        }
+        Err(_) => return error(&origin, StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let mut zip = match ZipArchive::new(BufReader::new(file)) {
+        Ok(z) => z,
+        Err(_) => return error(&origin, StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    };
+    let mut body = match read_zip_entry(&mut zip, &entry) {
+        Ok(b) => b,
+        Err(_) => return error(&origin, StatusCode::NOT_FOUND),
+    };
+
+    let policy = app
+        .try_state::<ContentPolicyStore>()
+        .map(|store| store.resolve(query.book.as_deref()))
+        .unwrap_or_else(ContentPolicy::default);
+
+    let mime = content_type(&entry);
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header("Access-Control-Allow-Origin", origin)
+        .header("Content-Type", mime)
+        .header("X-Content-Type-Options", "nosniff")
+        .header("Cache-Control", "no-store");
+    if is_document(mime) {
+        let csp = content_policy::csp_for_policy(&policy);
+        if mime != "image/svg+xml" {
+            let mut snippet = csp_meta(&csp);
+            if content_policy::needs_storage_guard(&policy) {
+                snippet.push_str(content_policy::STORAGE_GUARD_SCRIPT);
+            }
+            body = inject_into_head(&body, &snippet);
+        }
+        response = response.header("Content-Security-Policy", csp);
+    }
+    response
+        .header("Content-Length", body.len().to_string())
+        .body(body)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_path_and_book() {
+        let q = parse_query(Some("path=%2Fbooks%2Fa.epub&book=abc123")).unwrap();
+        assert_eq!(q.path, PathBuf::from("/books/a.epub"));
+        assert_eq!(q.book.as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn book_is_optional_but_path_is_not() {
+        assert!(parse_query(Some("path=%2Fa.epub")).unwrap().book.is_none());
+        assert!(parse_query(Some("book=abc")).is_none());
+        assert!(parse_query(None).is_none());
+    }
+
+    #[test]
+    fn entry_name_rejects_traversal_and_empty() {
+        assert_eq!(
+            entry_name("/OEBPS/Text/ch1.xhtml"),
+            Some("OEBPS/Text/ch1.xhtml")
+        );
+        assert_eq!(entry_name("/"), None);
+        assert_eq!(entry_name("/OEBPS/../../etc/passwd"), None);
+    }
+
+    #[test]
+    fn content_type_by_extension() {
+        assert_eq!(content_type("a/B.XHTML"), "application/xhtml+xml");
+        assert_eq!(content_type("style.css"), "text/css");
+        assert_eq!(content_type("fonts/x.woff2"), "font/woff2");
+        assert_eq!(content_type("noext"), "application/octet-stream");
+        assert!(is_document(content_type("cover.svg")));
+        assert!(!is_document(content_type("app.js")));
+    }
+
+    #[test]
+    fn snippet_goes_after_head_open_tag() {
+        let html = b"<html><HEAD lang=\"en\"><script>x()</script></HEAD><header/></html>";
+        let out = String::from_utf8(inject_into_head(html, "<script>g()</script>")).unwrap();
+        assert_eq!(
+            out,
+            "<html><HEAD lang=\"en\"><script>g()</script><script>x()</script></HEAD><header/></html>"
+        );
+    }
+
+    #[test]
+    fn snippet_prepended_without_head() {
+        let out = String::from_utf8(inject_into_head(b"<p>hi</p>", "<s/>")).unwrap();
+        assert_eq!(out, "<s/><p>hi</p>");
+    }
+
+    #[test]
+    fn non_utf8_bytes_pass_through() {
+        // "café" in Latin-1 after the head.
+        let html = b"<head><title>caf\xe9</title></head>";
+        assert_eq!(
+            inject_into_head(html, "<s/>"),
+            b"<head><s/><title>caf\xe9</title></head>".to_vec()
+        );
+    }
+
+    #[test]
+    fn csp_meta_keeps_policy_intact() {
+        let csp = content_policy::csp_for_policy(&ContentPolicy::default());
+        let meta = csp_meta(&csp);
+        assert!(meta.contains("script-src 'none'"));
+        assert_eq!(meta.matches('"').count(), 4);
+    }
+}
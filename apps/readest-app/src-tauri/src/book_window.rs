@@ -0,0 +1,397 @@
+//! Reader windows on desktop.
+//!
+//! `open_book_window` opens books in a window of their own labeled after
+//! them, `reader-{hash}`, so tauri-plugin-window-state keeps a size and
+//! position per book and opening a book again focuses its window. Files
+//! handed to a second launch while the main window shows a book open in a
+//! new window too, instead of replacing that book.
+//!
+//! On quit, the books open in reader windows are saved with each window's
+//! geometry and monitor to `window-session.json` in the app config directory,
+//! and setup opens them again at the next launch.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use tauri::utils::config::BackgroundThrottlingPolicy;
+#[cfg(target_os = "windows")]
+use tauri::webview::ScrollBarStyle;
+#[cfg(target_os = "macos")]
+use tauri::TitleBarStyle;
+use tauri::{
+    AppHandle, Manager, PhysicalPosition, PhysicalSize, Url, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder,
+};
+
+/// Reader windows share the label prefix the webview and the macOS traffic
+/// lights look for.
+const LABEL_PREFIX: &str = "reader";
+/// Joins the book ids of a parallel read, as in the reader's `ids` param.
+const BOOK_IDS_SEPARATOR: &str = "+";
+/// The reader windows open at the last quit, in the app config directory.
+const SESSION_FILENAME: &str = "window-session.json";
+
+/// What every window the webview gets needs from setup.
+pub struct BookWindows {
+    init_script: String,
+    /// Numbers the windows opened for files, which have no book id yet.
+    opened_for_files: AtomicUsize,
+    /// Whether the session has been saved for this quit.
+    session_saved: AtomicBool,
+}
+
+impl BookWindows {
+    pub fn new(init_script: String) -> Self {
+        BookWindows {
+            init_script,
+            opened_for_files: AtomicUsize::new(0),
+            session_saved: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Where a reader window was, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// A reader window to open again at launch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowSession {
+    book_ids: Vec<String>,
+    /// None for a minimized window, which reopens at the default place.
+    geometry: Option<WindowGeometry>,
+    /// Name of the monitor the window was on.
+    monitor: Option<String>,
+}
+
+/// The label of the window reading `book_ids`. Labels take only ASCII
+/// letters, digits and `-/:_`.
+fn window_label(book_ids: &[String]) -> String {
+    let ids = book_ids
+        .iter()
+        .map(|id| {
+            id.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("_");
+    format!("{LABEL_PREFIX}-{ids}")
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, NON_ALPHANUMERIC).to_string()
+}
+
+fn reader_url(book_ids: &[String]) -> String {
+    format!("reader?ids={}", encode(&book_ids.join(BOOK_IDS_SEPARATOR)))
+}
+
+fn library_url(files: &[PathBuf]) -> String {
+    let params = files
+        .iter()
+        .map(|file| format!("file={}", encode(&file.to_string_lossy())))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("library?{params}")
+}
+
+/// The books a reader window shows, from the `ids` param of its URL.
+fn reader_book_ids(url: &Url) -> Option<Vec<String>> {
+    if url.path().trim_end_matches('/') != "/reader" {
+        return None;
+    }
+    let (_, ids) = url.query_pairs().find(|(key, _)| key == "ids")?;
+    let ids: Vec<String> = ids
+        .split(BOOK_IDS_SEPARATOR)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!ids.is_empty()).then_some(ids)
+}
+
+/// Whether a window at `geometry` starts on the monitor at `position` and
+/// `size`, so it can be put back where it was.
+fn starts_on(
+    geometry: &WindowGeometry,
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+) -> bool {
+    let right = position.x.saturating_add(size.width as i32);
+    let bottom = position.y.saturating_add(size.height as i32);
+    (position.x..right).contains(&geometry.x) && (position.y..bottom).contains(&geometry.y)
+}
+
+/// Build a window like the main one, showing `url`.
+fn build_window(app: &AppHandle, label: &str, url: String) -> tauri::Result<WebviewWindow> {
+    let windows = app.state::<BookWindows>();
+    let builder = WebviewWindowBuilder::new(app, label, WebviewUrl::App(PathBuf::from(url)))
+        .background_throttling(BackgroundThrottlingPolicy::Disabled)
+        .background_color(tauri::window::Color(50, 49, 48, 255))
+        .initialization_script(&windows.init_script)
+        .inner_size(800.0, 600.0)
+        .center()
+        .resizable(true);
+
+    #[cfg(target_os = "macos")]
+    let builder = builder
+        .decorations(true)
+        .title_bar_style(TitleBarStyle::Overlay)
+        .title("");
+
+    #[cfg(not(target_os = "macos"))]
+    let builder = builder.decorations(false).shadow(true).title("Readest");
+
+    #[cfg(target_os = "windows")]
+    let builder = builder
+        .transparent(false)
+        .scroll_bar_style(ScrollBarStyle::FluentOverlay);
+
+    // Opaque for the same reason as the main window (#3682).
+    #[cfg(target_os = "linux")]
+    let builder = builder.transparent(false);
+
+    builder.build()
+}
+
+fn focus(window: &WebviewWindow) {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// Open `book_ids` (several for a parallel read) in a window of their own,
+/// or focus the window already reading them. Returns the window's label.
+#[tauri::command]
+pub fn open_book_window(app: AppHandle, book_ids: Vec<String>) -> Result<String, String> {
+    if book_ids.is_empty() {
+        return Err("No book to open".into());
+    }
+    let label = window_label(&book_ids);
+    if let Some(window) = app.get_webview_window(&label) {
+        focus(&window);
+        return Ok(label);
+    }
+    build_window(&app, &label, reader_url(&book_ids))
+        .map_err(|e| format!("Failed to open the book window: {e}"))?;
+    Ok(label)
+}
+
+/// Whether the main window is reading, so that files from a second launch
+/// should go to a new window.
+pub fn main_window_is_reading(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.url().ok())
+        .is_some_and(|url| url.path().starts_with("/reader"))
+}
+
+/// Import and open `files` in a new window, as the webview does for files
+/// opened with Readest.
+pub fn open_files_window(app: &AppHandle, files: &[PathBuf]) -> tauri::Result<()> {
+    let windows = app.state::<BookWindows>();
+    let number = windows.opened_for_files.fetch_add(1, Ordering::Relaxed);
+    let label = format!("{LABEL_PREFIX}-files-{number}");
+    build_window(app, &label, library_url(files))?;
+    Ok(())
+}
+
+fn window_session(window: &WebviewWindow) -> Option<WindowSession> {
+    if !window.label().starts_with(LABEL_PREFIX) {
+        return None;
+    }
+    let book_ids = reader_book_ids(&window.url().ok()?)?;
+    let geometry = if window.is_minimized().unwrap_or(false) {
+        None
+    } else {
+        match (window.outer_position(), window.inner_size()) {
+            (Ok(position), Ok(size)) => Some(WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            }),
+            _ => None,
+        }
+    };
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|monitor| monitor.name().cloned());
+    Some(WindowSession {
+        book_ids,
+        geometry,
+        monitor,
+    })
+}
+
+/// Save the reader windows open now. Called when the app quits; only the
+/// first call of a quit saves, since the windows may be gone by the next.
+pub fn save_session(app: &AppHandle) {
+    let Some(windows) = app.try_state::<BookWindows>() else {
+        return;
+    };
+    if windows.session_saved.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let Ok(config_dir) = app.path().app_config_dir() else {
+        return;
+    };
+    let mut open = app.webview_windows().into_iter().collect::<Vec<_>>();
+    open.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let sessions: Vec<WindowSession> = open
+        .iter()
+        .filter_map(|(_, window)| window_session(window))
+        .collect();
+    let result = serde_json::to_string_pretty(&sessions)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            fs::create_dir_all(&config_dir)
+                .and_then(|_| fs::write(config_dir.join(SESSION_FILENAME), json))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log::warn!("Failed to save the window session: {e}");
+    }
+}
+
+/// Open the reader windows saved at the last quit, where they were when the
+/// monitor they were on is still connected.
+pub fn restore_session(app: &AppHandle) {
+    let Ok(config_dir) = app.path().app_config_dir() else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(config_dir.join(SESSION_FILENAME)) else {
+        return;
+    };
+    let sessions: Vec<WindowSession> = match serde_json::from_str(&contents) {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            log::warn!("Ignoring {SESSION_FILENAME}: {e}");
+            return;
+        }
+    };
+    let monitors = app.available_monitors().unwrap_or_default();
+    for session in sessions {
+        let label = window_label(&session.book_ids);
+        if app.get_webview_window(&label).is_some() {
+            continue;
+        }
+        let window = match build_window(app, &label, reader_url(&session.book_ids)) {
+            Ok(window) => window,
+            Err(e) => {
+                log::warn!("Failed to restore the book window {label}: {e}");
+                continue;
+            }
+        };
+        let Some(geometry) = session.geometry else {
+            continue;
+        };
+        let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+        let on_screen = monitors.iter().any(|monitor| {
+            monitor.name() == session.monitor.as_ref()
+                && starts_on(&geometry, *monitor.position(), *monitor.size())
+        });
+        if on_screen {
+            let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+        }
+    }
+}
+
+/// Whether the second launch's arguments are all files to open.
+pub fn are_files(files: &[PathBuf]) -> bool {
+    !files.is_empty() && files.iter().all(|file| file.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_are_labeled_after_their_books() {
+        assert_eq!(
+            window_label(&["0a1b2c".into()]),
+            "reader-0a1b2c".to_string()
+        );
+        assert_eq!(
+            window_label(&["0a1b".into(), "ff.e9".into()]),
+            "reader-0a1b_ff_e9".to_string()
+        );
+    }
+
+    #[test]
+    fn reader_windows_show_the_books_in_their_url() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert_eq!(
+            reader_book_ids(&url("tauri://localhost/reader?ids=0a1b%2B2c3d")),
+            Some(vec!["0a1b".to_string(), "2c3d".to_string()])
+        );
+        assert_eq!(
+            reader_book_ids(&url("http://localhost:3000/reader/?ids=0a1b&view=scroll")),
+            Some(vec!["0a1b".to_string()])
+        );
+        assert_eq!(reader_book_ids(&url("tauri://localhost/reader")), None);
+        assert_eq!(
+            reader_book_ids(&url("tauri://localhost/library?ids=0a1b")),
+            None
+        );
+    }
+
+    #[test]
+    fn windows_return_only_to_the_monitor_they_started_on() {
+        let monitor = (
+            PhysicalPosition::new(-1920, 0),
+            PhysicalSize::new(1920, 1080),
+        );
+        let at = |x, y| WindowGeometry {
+            x,
+            y,
+            width: 800,
+            height: 600,
+        };
+        assert!(starts_on(&at(-1800, 100), monitor.0, monitor.1));
+        assert!(!starts_on(&at(100, 100), monitor.0, monitor.1));
+        assert!(!starts_on(&at(-1800, 1080), monitor.0, monitor.1));
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let session = WindowSession {
+            book_ids: vec!["0a1b".into()],
+            geometry: Some(WindowGeometry {
+                x: 10,
+                y: 20,
+                width: 800,
+                height: 600,
+            }),
+            monitor: Some("DELL U2720Q".into()),
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        assert!(json.contains(r#""bookIds":["0a1b"]"#));
+        assert_eq!(
+            serde_json::from_str::<WindowSession>(&json).unwrap(),
+            session
+        );
+    }
+
+    #[test]
+    fn urls_encode_their_params() {
+        assert_eq!(
+            reader_url(&["0a1b".into(), "2c3d".into()]),
+            "reader?ids=0a1b%2B2c3d"
+        );
+        assert_eq!(
+            library_url(&[PathBuf::from("/books/A B.epub"), PathBuf::from("/c&d.pdf")]),
+            "library?file=%2Fbooks%2FA%20B%2Eepub&file=%2Fc%26d%2Epdf"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+// Braille export (BRF / BRL).
+//
+// Turns book text into embosser-ready braille files so braille-display
+// users and transcribers don't have to round-trip through a separate
+// tool. The JS side already owns text extraction (foliate-js renders the
+// sections), so it sends us plain-text sections and we do the rest:
+//   - pick a liblouis translation table for the book language
+//     (`table_for_language`), with an explicit override for
+//     transcribers who want a specific grade / code;
+//   - translate each section to North American ASCII braille;
+//   - word-wrap and paginate to the embosser geometry (40 cells × 25
+//     lines by default) and write either
+//       * `.brf` — ASCII braille, CRLF line endings, form feed between
+//         pages (what embossers and BANA tooling expect), or
+//       * `.brl` — Unicode braille patterns (U+2800 block), one line per
+//         row, unpaginated, for refreshable displays and screen readers.
+//
+// liblouis is a C library, so the real translator lives behind the
+// `braille` cargo feature (enabled for desktop release builds that link
+// the system liblouis). Builds without it fall back to a small built-in
+// *uncontracted* (grade 1) translator that covers Latin letters, digits
+// and common punctuation — enough for a readable proof, and clearly
+// reported back to the caller via `contracted: false`.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::transfer_file::ensure_path_allowed;
+
+/// Standard embosser geometry (US letter, 11.5" × 11" braille paper).
+const DEFAULT_CELLS_PER_LINE: usize = 40;
+const DEFAULT_LINES_PER_PAGE: usize = 25;
+
+/// North American ASCII braille: character at index `n` is the ASCII
+/// representation of the dot pattern `U+2800 + n` (dots 1-6).
+const ASCII_BRAILLE: &[u8; 64] =
+    b" A1B'K2L@CIF/MSP\"E3H9O6R^DJG>NTQ,*5<-U8V.%[$+X!&;:4\\0Z7(_?W]#Y)=";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrailleFormat {
+    Brf,
+    Brl,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrailleSection {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrailleExportOptions {
+    /// BCP-47 language of the book (`en`, `en-GB`, `de`, …).
+    pub language: String,
+    /// Explicit liblouis table list (e.g. `en-ueb-g1.ctb`); overrides the
+    /// language mapping.
+    pub table: Option<String>,
+    pub format: BrailleFormat,
+    pub cells_per_line: Option<usize>,
+    pub lines_per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrailleExportResult {
+    pub output_path: String,
+    pub pages: usize,
+    /// Table actually used, or `None` for the built-in fallback.
+    pub table: Option<String>,
+    /// Whether the table contracts (grade 2): false for grade 1 and
+    /// computer braille tables and for the built-in fallback.
+    pub contracted: bool,
+}
+
+/// liblouis contracted (grade 2) table for a BCP-47 language tag. Falls
+/// back to the primary subtag, then to Unified English Braille.
+#[cfg(any(test, feature = "braille"))]
+pub fn table_for_language(lang: &str) -> &'static str {
+    let lang = lang.to_lowercase().replace('_', "-");
+    match lang.as_str() {
+        "en-us" => return "en-ueb-g2.ctb",
+        "en-gb" => return "en-gb-g2.ctb",
+        "pt-br" => return "pt-pt-g2.ctb",
+        "zh-tw" | "zh-hant" => return "zh-tw.ctb",
+        _ => {}
+    }
+    match lang.split('-').next().unwrap_or("") {
+        "de" => "de-g2.ctb",
+        "fr" => "fr-bfu-g2.ctb",
+        "es" => "es-g2.ctb",
+        "it" => "it-it-comp6.utb",
+        "pt" => "pt-pt-g2.ctb",
+        "nl" => "nl-NL-g0.utb",
+        "da" => "da-dk-g26.ctb",
+        "sv" => "sv-g1.ctb",
+        "no" | "nb" | "nn" => "no-no-g2.ctb",
+        "fi" => "fi-fi-8dot.ctb",
+        "pl" => "pl-pl-comp8.ctb",
+        "cs" => "cs-g1.ctb",
+        "ru" => "ru-litbrl.ctb",
+        "uk" => "uk.utb",
+        "el" => "gr-gr-g1.utb",
+        "ar" => "ar-ar-g1.utb",
+        "he" => "he-IL.utb",
+        "hi" => "hi-in-g1.utb",
+        "zh" => "zh-chn.ctb",
+        "ja" => "ja-kantenji.utb",
+        "ko" => "ko-g2.ctb",
+        _ => "en-ueb-g2.ctb",
+    }
+}
+
+/// Whether a liblouis table list translates to contracted braille, going by
+/// the grade in the table names (`en-ueb-g2.ctb`, `da-dk-g26.ctb`). Grade 0
+/// and 1, computer braille (`comp6`, `comp8`) and tables that name no grade
+/// are uncontracted.
+fn is_contracted_table(tables: &str) -> bool {
+    tables.split(',').any(|table| {
+        let table = table.trim().to_ascii_lowercase();
+        table.contains("-g2") || table.contains("-g3")
+    })
+}
+
+/// Built-in uncontracted translation to ASCII braille for builds without
+/// liblouis. Letters map to themselves (upper-case ASCII braille), with
+/// the UEB capital indicator (dot 6, `,`) before capitals and the number
+/// sign (`#`) before a run of digits. Opening double quotes are dots 2-3-6
+/// (`8`) and closing ones dots 3-5-6 (`0`); a straight `"` opens at the start
+/// of a word and closes otherwise. Unknown characters are dropped.
+#[cfg(any(test, not(feature = "braille")))]
+fn translate_uncontracted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 4);
+    let mut in_number = false;
+    let mut prev: Option<char> = None;
+    for ch in text.chars() {
+        let at_word_start = prev.map_or(true, |p| p.is_whitespace() || "([{—–-".contains(p));
+        prev = Some(ch);
+        if ch.is_ascii_digit() {
+            if !in_number {
+                out.push('#');
+                in_number = true;
+            }
+            // 1-9 → a-i, 0 → j
+            let letter = match ch {
+                '0' => 'J',
+                d => (b'A' + (d as u8 - b'1')) as char,
+            };
+            out.push(letter);
+            continue;
+        }
+        if ch == '.' && in_number {
+            out.push('4');
+            continue;
+        }
+        in_number = false;
+        match ch {
+            'a'..='z' => out.push(ch.to_ascii_uppercase()),
+            'A'..='Z' => {
+                out.push(',');
+                out.push(ch);
+            }
+            ' ' | '\t' => out.push(' '),
+            '\n' => out.push('\n'),
+            ',' => out.push('1'),
+            ';' => out.push('2'),
+            ':' => out.push('3'),
+            '.' => out.push('4'),
+            '!' => out.push('6'),
+            '?' => out.push('8'),
+            '\'' | '’' | '‘' => out.push('\''),
+            '“' => out.push('8'),
+            '”' => out.push('0'),
+            '"' if at_word_start => out.push('8'),
+            '"' => out.push('0'),
+            '-' | '–' | '—' => out.push('-'),
+            '(' | ')' => out.push('7'),
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(feature = "braille")]
+fn translate(text: &str, table: &str) -> Result<String, String> {
+    // Prefix the BRF display table so liblouis emits North American ASCII
+    // braille regardless of the translation table's native display.
+    let tables = format!("en-us-brf.dis,{table}");
+    let louis = louis::API::new();
+    Ok(text
+        .split('\n')
+        .map(|para| louis.translate_simple(&tables, para, false, Default::default()))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Word-wrap ASCII braille to `width` cells. Words longer than a line are
+/// hard-split; paragraph breaks (`\n`) are preserved.
+fn wrap_lines(braille: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in braille.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ').filter(|w| !w.is_empty()) {
+            let mut word = word;
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let (head, tail) = word.split_at(width);
+                lines.push(head.to_string());
+                word = tail;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            if line.is_empty() {
+                line.push_str(word);
+            } else if line.len() + 1 + word.len() <= width {
+                line.push(' ');
+                line.push_str(word);
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn ascii_to_unicode_braille(line: &str) -> String {
+    line.chars()
+        .map(|c| {
+            let upper = c.to_ascii_uppercase() as u8;
+            match ASCII_BRAILLE.iter().position(|&b| b == upper) {
+                Some(n) => char::from_u32(0x2800 + n as u32).unwrap_or(' '),
+                None => '\u{2800}',
+            }
+        })
+        .collect()
+}
+
+/// Lay out translated lines as a BRF document: CRLF line endings and a
+/// form feed between pages. Returns the document and its page count.
+fn paginate_brf(lines: &[String], lines_per_page: usize) -> (String, usize) {
+    let pages: Vec<&[String]> = lines.chunks(lines_per_page.max(1)).collect();
+    let doc = pages
+        .iter()
+        .map(|page| page.join("\r\n"))
+        .collect::<Vec<_>>()
+        .join("\r\n\x0c");
+    (doc + "\r\n", pages.len().max(1))
+}
+
+fn export_braille_sync(
+    sections: &[BrailleSection],
+    options: &BrailleExportOptions,
+    output_path: &Path,
+) -> Result<BrailleExportResult, String> {
+    let width = options.cells_per_line.unwrap_or(DEFAULT_CELLS_PER_LINE);
+    let height = options.lines_per_page.unwrap_or(DEFAULT_LINES_PER_PAGE);
+    if width < 10 || height < 5 {
+        return Err("braille page geometry too small".to_string());
+    }
+
+    #[cfg(feature = "braille")]
+    let table = Some(
+        options
+            .table
+            .clone()
+            .unwrap_or_else(|| table_for_language(&options.language).to_string()),
+    );
+    #[cfg(not(feature = "braille"))]
+    let table: Option<String> = {
+        log::info!(
+            "braille: liblouis not compiled in, using uncontracted fallback for {} (table override: {:?})",
+            options.language,
+            options.table
+        );
+        None
+    };
+
+    let mut lines = Vec::new();
+    for section in sections {
+        let mut source = String::new();
+        if let Some(title) = section.title.as_deref().filter(|t| !t.trim().is_empty()) {
+            source.push_str(title.trim());
+            source.push_str("\n\n");
+        }
+        source.push_str(&section.text.replace("\r\n", "\n"));
+
+        #[cfg(feature = "braille")]
+        let braille = translate(&source, table.as_deref().unwrap_or_default())?;
+        #[cfg(not(feature = "braille"))]
+        let braille = translate_uncontracted(&source);
+
+        lines.extend(wrap_lines(&braille, width));
+        lines.push(String::new());
+    }
+
+    let (document, pages) = match options.format {
+        BrailleFormat::Brf => paginate_brf(&lines, height),
+        BrailleFormat::Brl => {
+            let doc = lines
+                .iter()
+                .map(|l| ascii_to_unicode_braille(l))
+                .collect::<Vec<_>>()
+                .join("\n");
+            (doc + "\n", lines.len().div_ceil(height).max(1))
+        }
+    };
+
+    std::fs::write(output_path, document).map_err(|e| format!("write braille file: {e}"))?;
+
+    Ok(BrailleExportResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        pages,
+        contracted: table.as_deref().is_some_and(is_contracted_table),
+        table,
+    })
+}
+
+/// Translate `sections` to braille and write them to `output_path` as BRF
+/// (embosser) or BRL (Unicode braille) text.
+#[tauri::command]
+pub async fn export_braille(
+    app: AppHandle,
+    sections: Vec<BrailleSection>,
+    options: BrailleExportOptions,
+    output_path: String,
+) -> Result<BrailleExportResult, String> {
+    ensure_path_allowed(&app, &output_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        export_braille_sync(&sections, &options, Path::new(&output_path))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_for_language_prefers_region_then_primary_subtag() {
+        assert_eq!(table_for_language("en-GB"), "en-gb-g2.ctb");
+        assert_eq!(table_for_language("en"), "en-ueb-g2.ctb");
+        assert_eq!(table_for_language("de-AT"), "de-g2.ctb");
+        assert_eq!(table_for_language("fr_CA"), "fr-bfu-g2.ctb");
+        assert_eq!(table_for_language("xx"), "en-ueb-g2.ctb");
+    }
+
+    #[test]
+    fn uncontracted_translation_marks_capitals_and_numbers() {
+        assert_eq!(translate_uncontracted("Hi"), ",HI");
+        assert_eq!(translate_uncontracted("page 12."), "PAGE #AB4");
+        assert_eq!(translate_uncontracted("3.5"), "#C4E");
+        assert_eq!(translate_uncontracted("a, b"), "A1 B");
+    }
+
+    #[test]
+    fn uncontracted_translation_opens_and_closes_quotes() {
+        assert_eq!(translate_uncontracted("“a” b"), "8A0 B");
+        assert_eq!(translate_uncontracted("\"a\" \"b\"."), "8A0 8B04");
+        assert_eq!(translate_uncontracted("(\"a\")"), "78A07");
+    }
+
+    #[test]
+    fn only_grade_two_tables_count_as_contracted() {
+        assert!(is_contracted_table("en-ueb-g2.ctb"));
+        assert!(is_contracted_table("da-dk-g26.ctb"));
+        assert!(is_contracted_table("en-us-brf.dis, fr-bfu-g2.ctb"));
+        assert!(!is_contracted_table("en-ueb-g1.ctb"));
+        assert!(!is_contracted_table("it-it-comp6.utb"));
+        assert!(!is_contracted_table("nl-NL-g0.utb"));
+        assert!(!is_contracted_table("ru-litbrl.ctb"));
+    }
+
+    #[test]
+    fn wrap_lines_respects_width_and_paragraphs() {
+        let lines = wrap_lines("AAAA BBBB CCCC\nDD", 9);
+        assert_eq!(lines, vec!["AAAA BBBB", "CCCC", "DD"]);
+        let lines = wrap_lines("ABCDEFGHIJKL", 5);
+        assert_eq!(lines, vec!["ABCDE", "FGHIJ", "KL"]);
+    }
+
+    #[test]
+    fn brf_pages_are_separated_by_form_feed() {
+        let lines: Vec<String> = (0..5).map(|i| format!("L{i}")).collect();
+        let (doc, pages) = paginate_brf(&lines, 2);
+        assert_eq!(pages, 3);
+        assert_eq!(doc, "L0\r\nL1\r\n\x0cL2\r\nL3\r\n\x0cL4\r\n");
+    }
+
+    #[test]
+    fn ascii_braille_maps_to_unicode_patterns() {
+        // A = dot 1, B = dots 1-2, space = blank cell.
+        assert_eq!(ascii_to_unicode_braille("AB "), "\u{2801}\u{2803}\u{2800}");
+        assert_eq!(ascii_to_unicode_braille("#"), "\u{283C}");
+    }
+}
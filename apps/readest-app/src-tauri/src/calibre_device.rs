@@ -0,0 +1,713 @@
+// Calibre "Smart Device" (wireless device) client.
+//
+// Desktop Calibre's *Connect to: Smart device* driver is the one Calibre
+// Companion and KOReader speak. Calibre is the server; the reading device:
+//   1. finds it, either by sending `hello` to the UDP broadcast ports
+//      Calibre listens on (it answers with its name and TCP port) or by
+//      browsing the `_calibresmartdeviceapp._tcp` mDNS service
+//      (`calibre_device_discover`);
+//   2. connects over TCP (`calibre_device_connect`) and answers Calibre's
+//      requests. Every message is `<len>[<opcode>, {json}]`, `len` being
+//      the decimal byte length of the JSON array; a pushed book's bytes
+//      follow its `SEND_BOOK` message raw.
+//
+// Books Calibre sends land in `destDir` under their Calibre `lpath` and are
+// announced with `calibre-device://book` so the frontend runs them through
+// its import flow. What we hold is remembered in `destDir/.calibre-device.json`
+// so Calibre's device view (and its "on device" column) survive reconnects.
+// Sending books from Readest back to Calibre is not supported.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::transfer_file::ensure_path_allowed;
+
+pub const BOOK_EVENT: &str = "calibre-device://book";
+pub const DELETED_EVENT: &str = "calibre-device://deleted";
+pub const STATE_EVENT: &str = "calibre-device://state";
+
+/// Ports Calibre listens on for `hello` broadcasts (`BROADCAST_PORTS` in
+/// calibre's `smart_device_app` driver).
+const BROADCAST_PORTS: &[u16] = &[54982, 48123, 39001, 44044, 59678];
+const MDNS_SERVICE: &str = "_calibresmartdeviceapp._tcp.local.";
+const CACHE_FILENAME: &str = ".calibre-device.json";
+const PROTOCOL_VERSION: u64 = 1;
+/// Largest JSON message we accept; book bytes are streamed separately.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+const ACCEPTED_EXTENSIONS: &[&str] = &[
+    "epub", "azw3", "mobi", "azw", "fb2", "fbz", "cbz", "pdf", "txt", "md",
+];
+
+mod opcode {
+    pub const OK: u64 = 0;
+    pub const SET_CALIBRE_DEVICE_INFO: u64 = 1;
+    pub const SET_CALIBRE_DEVICE_NAME: u64 = 2;
+    pub const GET_DEVICE_INFORMATION: u64 = 3;
+    pub const TOTAL_SPACE: u64 = 4;
+    pub const FREE_SPACE: u64 = 5;
+    pub const GET_BOOK_COUNT: u64 = 6;
+    pub const SEND_BOOKLISTS: u64 = 7;
+    pub const SEND_BOOK: u64 = 8;
+    pub const GET_INITIALIZATION_INFO: u64 = 9;
+    pub const BOOK_DONE: u64 = 11;
+    pub const NOOP: u64 = 12;
+    pub const DELETE_BOOK: u64 = 13;
+    pub const GET_BOOK_FILE_SEGMENT: u64 = 14;
+    pub const GET_BOOK_METADATA: u64 = 15;
+    pub const SEND_BOOK_METADATA: u64 = 16;
+    pub const DISPLAY_MESSAGE: u64 = 17;
+    pub const CALIBRE_BUSY: u64 = 18;
+    pub const SET_LIBRARY_INFO: u64 = 19;
+    pub const ERROR: u64 = 20;
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreInstance {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreDeviceOptions {
+    pub host: String,
+    pub port: u16,
+    /// Directory books are written into (usually an import staging dir).
+    pub dest_dir: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreDeviceState {
+    pub connected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedBook {
+    pub path: String,
+    pub lpath: String,
+    /// Calibre's metadata for the book (title, authors, series, tags, …).
+    pub metadata: Value,
+    /// 1-based position in the current batch.
+    pub index: u64,
+    pub total: u64,
+}
+
+/// The live connection, so `calibre_device_disconnect` can unblock a
+/// session waiting on Calibre.
+#[derive(Default)]
+struct SessionHandle {
+    cancelled: AtomicBool,
+    stream: Mutex<Option<TcpStream>>,
+}
+
+impl SessionHandle {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CalibreDevice(Mutex<Option<Arc<SessionHandle>>>);
+
+/// What's on the "device", keyed by lpath, with Calibre's metadata.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceCache {
+    device_uuid: String,
+    books: BTreeMap<String, Value>,
+}
+
+impl DeviceCache {
+    fn load(dir: &Path) -> Self {
+        let mut cache: DeviceCache = std::fs::read_to_string(dir.join(CACHE_FILENAME))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        if cache.device_uuid.is_empty() {
+            let mut bytes = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            cache.device_uuid = bytes.iter().map(|b| format!("{b:02x}")).collect();
+        }
+        cache
+    }
+
+    fn save(&self, dir: &Path) {
+        match serde_json::to_string(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(dir.join(CACHE_FILENAME), json) {
+                    log::warn!("calibre device: failed to save cache: {e}");
+                }
+            }
+            Err(e) => log::warn!("calibre device: failed to encode cache: {e}"),
+        }
+    }
+}
+
+/// Parse Calibre's broadcast reply:
+/// `calibre wireless device client (on <host>);<content server port>,<port>`.
+fn parse_broadcast_reply(reply: &str, from: IpAddr) -> Option<CalibreInstance> {
+    let (label, ports) = reply.rsplit_once(';')?;
+    let port = ports.rsplit(',').next()?.trim().parse().ok()?;
+    let name = label
+        .split_once("(on ")
+        .and_then(|(_, rest)| rest.strip_suffix(')'))
+        .unwrap_or(label)
+        .to_string();
+    Some(CalibreInstance {
+        name,
+        host: from.to_string(),
+        port,
+    })
+}
+
+fn discover_broadcast(timeout: Duration) -> Vec<CalibreInstance> {
+    let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) else {
+        return Vec::new();
+    };
+    let _ = socket.set_broadcast(true);
+    for port in BROADCAST_PORTS {
+        let _ = socket.send_to(b"hello", (Ipv4Addr::BROADCAST, *port));
+    }
+    let deadline = Instant::now() + timeout;
+    let mut found = Vec::new();
+    let mut buf = [0u8; 1024];
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        if left.is_zero() || socket.set_read_timeout(Some(left)).is_err() {
+            break;
+        }
+        match socket.recv_from(&mut buf) {
+            Ok((n, from)) => {
+                let reply = String::from_utf8_lossy(&buf[..n]);
+                if let Some(instance) = parse_broadcast_reply(&reply, from.ip()) {
+                    if !found.contains(&instance) {
+                        found.push(instance);
+                    }
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    found
+}
+
+fn discover_mdns(timeout: Duration) -> Vec<CalibreInstance> {
+    let Ok(daemon) = mdns_sd::ServiceDaemon::new() else {
+        return Vec::new();
+    };
+    let Ok(receiver) = daemon.browse(MDNS_SERVICE) else {
+        return Vec::new();
+    };
+    let deadline = Instant::now() + timeout;
+    let mut found = Vec::new();
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(mdns_sd::ServiceEvent::ServiceResolved(info)) => {
+                let name = info
+                    .get_fullname()
+                    .trim_end_matches(MDNS_SERVICE)
+                    .trim_end_matches('.')
+                    .to_string();
+                if let Some(addr) = info.get_addresses().iter().find(|a| a.is_ipv4()) {
+                    found.push(CalibreInstance {
+                        name,
+                        host: addr.to_string(),
+                        port: info.get_port(),
+                    });
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.shutdown();
+    found
+}
+
+/// Find Calibre instances with the wireless device connection running.
+#[tauri::command]
+pub async fn calibre_device_discover(
+    timeout_ms: Option<u64>,
+) -> Result<Vec<CalibreInstance>, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    let broadcast = tauri::async_runtime::spawn_blocking(move || discover_broadcast(timeout));
+    let mdns = tauri::async_runtime::spawn_blocking(move || discover_mdns(timeout));
+    let mut found = broadcast.await.map_err(|e| format!("join error: {e}"))?;
+    for instance in mdns.await.map_err(|e| format!("join error: {e}"))? {
+        if !found
+            .iter()
+            .any(|f| f.host == instance.host && f.port == instance.port)
+        {
+            found.push(instance);
+        }
+    }
+    Ok(found)
+}
+
+fn encode_message(op: u64, payload: &Value) -> Vec<u8> {
+    let body = json!([op, payload]).to_string();
+    format!("{}{body}", body.len()).into_bytes()
+}
+
+/// Read one `<len>[op, payload]` message. Returns `None` on a clean EOF.
+fn read_message(reader: &mut impl Read) -> Result<Option<(u64, Value)>, String> {
+    let mut len_digits = String::new();
+    let mut byte = [0u8; 1];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) if len_digits.is_empty() => return Ok(None),
+            Ok(0) => return Err("connection closed mid-message".to_string()),
+            Ok(_) if byte[0].is_ascii_digit() && len_digits.len() < 12 => {
+                len_digits.push(byte[0] as char)
+            }
+            Ok(_) if byte[0] == b'[' && !len_digits.is_empty() => break,
+            Ok(_) => return Err(format!("unexpected byte {:#04x} in header", byte[0])),
+            Err(e) => return Err(format!("read failed: {e}")),
+        }
+    }
+    let len: usize = len_digits
+        .parse()
+        .map_err(|_| "invalid length".to_string())?;
+    if len == 0 || len > MAX_MESSAGE_LEN {
+        return Err(format!("invalid message length {len}"));
+    }
+    let mut body = vec![0u8; len];
+    body[0] = b'[';
+    reader
+        .read_exact(&mut body[1..])
+        .map_err(|e| format!("read failed: {e}"))?;
+    let value: Value = serde_json::from_slice(&body).map_err(|e| format!("invalid json: {e}"))?;
+    let op = value
+        .get(0)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "missing opcode".to_string())?;
+    Ok(Some((op, value.get(1).cloned().unwrap_or(Value::Null))))
+}
+
+/// `sha1(password + challenge)` as Calibre checks it.
+fn password_hash(password: &str, challenge: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(password.as_bytes());
+    hasher.update(challenge.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Resolve a Calibre `lpath` under `dir`, refusing anything that would
+/// escape it.
+fn lpath_destination(dir: &Path, lpath: &str) -> Option<PathBuf> {
+    let relative = Path::new(lpath);
+    let safe = !lpath.is_empty()
+        && relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    safe.then(|| dir.join(relative))
+}
+
+fn disk_space(dir: &Path) -> (u64, u64) {
+    let total = fs4::total_space(dir).unwrap_or(0);
+    let free = fs4::available_space(dir).unwrap_or(0);
+    (total, free)
+}
+
+struct Session<'a> {
+    app: &'a AppHandle,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    dir: PathBuf,
+    cache: DeviceCache,
+    options: &'a CalibreDeviceOptions,
+    cancelled: &'a AtomicBool,
+}
+
+impl Session<'_> {
+    fn send(&mut self, op: u64, payload: Value) -> Result<(), String> {
+        self.stream
+            .write_all(&encode_message(op, &payload))
+            .map_err(|e| format!("write failed: {e}"))
+    }
+
+    fn ok(&mut self, payload: Value) -> Result<(), String> {
+        self.send(opcode::OK, payload)
+    }
+
+    fn emit_state(&self, library_name: Option<String>, error: Option<String>) {
+        let _ = self.app.emit(
+            STATE_EVENT,
+            CalibreDeviceState {
+                connected: error.is_none() && !self.cancelled.load(Ordering::Relaxed),
+                library_name,
+                error,
+            },
+        );
+    }
+
+    fn initialization_info(&self, args: &Value) -> Value {
+        let password_hash = match (
+            self.options.password.as_deref().filter(|p| !p.is_empty()),
+            args.get("passwordChallenge").and_then(Value::as_str),
+        ) {
+            (Some(password), Some(challenge)) if !challenge.is_empty() => {
+                password_hash(password, challenge)
+            }
+            _ => String::new(),
+        };
+        json!({
+            "appName": "Readest",
+            "versionOK": args.get("serverProtocolVersion").and_then(Value::as_u64)
+                .map_or(true, |v| v >= PROTOCOL_VERSION),
+            "acceptedExtensions": ACCEPTED_EXTENSIONS,
+            "extensionPathLengths": ACCEPTED_EXTENSIONS.iter()
+                .map(|e| (e.to_string(), json!(255)))
+                .collect::<serde_json::Map<_, _>>(),
+            "cacheUsesLpaths": true,
+            "canAcceptLibraryInfo": true,
+            "canDeleteMultipleBooks": true,
+            "canReceiveBookBinary": true,
+            "canSendOkToSendbook": true,
+            "canStreamBooks": true,
+            "canStreamMetadata": true,
+            "canUseCachedMetadata": false,
+            "ccVersionNumber": env!("CARGO_PKG_VERSION"),
+            "coverHeight": 240,
+            "deviceKind": std::env::consts::OS,
+            "deviceName": self.options.device_name.as_deref().unwrap_or("Readest"),
+            "maxBookContentPacketLen": 4096,
+            "passwordHash": password_hash,
+            "useUuidFileNames": false,
+        })
+    }
+
+    fn receive_book(&mut self, args: &Value) -> Result<(), String> {
+        let lpath = args
+            .get("lpath")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        let length = args.get("length").and_then(Value::as_u64).unwrap_or(0);
+        let dest = lpath_destination(&self.dir, &lpath);
+        if args
+            .get("wantsSendOkToSendbook")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            if dest.is_none() {
+                return self.send(opcode::ERROR, json!({ "message": "invalid lpath" }));
+            }
+            self.ok(json!({ "lpath": lpath }))?;
+        }
+        let mut limited = (&mut self.reader).take(length);
+        let Some(dest) = dest else {
+            // Drain so the stream stays in sync.
+            std::io::copy(&mut limited, &mut std::io::sink()).map_err(|e| e.to_string())?;
+            return Ok(());
+        };
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("create dir: {e}"))?;
+        }
+        let mut file =
+            std::fs::File::create(&dest).map_err(|e| format!("create {}: {e}", dest.display()))?;
+        let written =
+            std::io::copy(&mut limited, &mut file).map_err(|e| format!("receive {lpath}: {e}"))?;
+        if written != length {
+            let _ = std::fs::remove_file(&dest);
+            return Err(format!("connection closed while receiving {lpath}"));
+        }
+        let metadata = args.get("metadata").cloned().unwrap_or(Value::Null);
+        self.cache.books.insert(lpath.clone(), metadata.clone());
+        self.cache.save(&self.dir);
+        let _ = self.app.emit(
+            BOOK_EVENT,
+            ReceivedBook {
+                path: dest.to_string_lossy().to_string(),
+                lpath,
+                metadata,
+                index: args.get("thisBook").and_then(Value::as_u64).unwrap_or(0) + 1,
+                total: args.get("totalBooks").and_then(Value::as_u64).unwrap_or(1),
+            },
+        );
+        Ok(())
+    }
+
+    fn delete_books(&mut self, args: &Value) -> Result<(), String> {
+        let lpaths: Vec<String> = args
+            .get("lpaths")
+            .and_then(Value::as_array)
+            .map(|a| {
+                a.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.ok(json!({}))?;
+        for lpath in lpaths {
+            let uuid = self
+                .cache
+                .books
+                .remove(&lpath)
+                .and_then(|m| m.get("uuid").cloned())
+                .unwrap_or(Value::Null);
+            if let Some(path) = lpath_destination(&self.dir, &lpath) {
+                let _ = std::fs::remove_file(&path);
+                let _ = self.app.emit(DELETED_EVENT, path.to_string_lossy());
+            }
+            self.ok(json!({ "uuid": uuid }))?;
+        }
+        self.cache.save(&self.dir);
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        let mut library_name = None;
+        while !self.cancelled.load(Ordering::Relaxed) {
+            let message = match read_message(&mut self.reader) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(e) if self.cancelled.load(Ordering::Relaxed) => {
+                    log::debug!("calibre device: stopped ({e})");
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            let (op, args) = message;
+            match op {
+                opcode::GET_INITIALIZATION_INFO => {
+                    library_name = args
+                        .get("currentLibraryName")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                    let info = self.initialization_info(&args);
+                    self.ok(info)?;
+                    self.emit_state(library_name.clone(), None);
+                }
+                opcode::GET_DEVICE_INFORMATION => {
+                    let info = json!({
+                        "device_info": {
+                            "device_store_uuid": self.cache.device_uuid,
+                            "device_name": self.options.device_name.as_deref().unwrap_or("Readest"),
+                        },
+                        "version": PROTOCOL_VERSION,
+                        "device_version": env!("CARGO_PKG_VERSION"),
+                    });
+                    self.ok(info)?;
+                }
+                opcode::TOTAL_SPACE => {
+                    let (total, _) = disk_space(&self.dir);
+                    self.ok(json!({ "total_space_on_device": total }))?;
+                }
+                opcode::FREE_SPACE => {
+                    let (_, free) = disk_space(&self.dir);
+                    self.ok(json!({ "free_space_on_device": free }))?;
+                }
+                opcode::GET_BOOK_COUNT => {
+                    let books: Vec<Value> = self.cache.books.values().cloned().collect();
+                    self.ok(json!({
+                        "count": books.len(),
+                        "willStream": true,
+                        "willScan": true,
+                    }))?;
+                    for book in books {
+                        self.ok(book)?;
+                    }
+                }
+                opcode::SEND_BOOK_METADATA => {
+                    if let Some(data) = args.get("data") {
+                        if let Some(lpath) = data.get("lpath").and_then(Value::as_str) {
+                            if let Some(entry) = self.cache.books.get_mut(lpath) {
+                                *entry = data.clone();
+                            }
+                        }
+                    }
+                    let index = args.get("index").and_then(Value::as_u64).unwrap_or(0);
+                    let count = args.get("count").and_then(Value::as_u64).unwrap_or(0);
+                    if index + 1 >= count {
+                        self.cache.save(&self.dir);
+                    }
+                }
+                opcode::SEND_BOOK => self.receive_book(&args)?,
+                opcode::DELETE_BOOK => self.delete_books(&args)?,
+                opcode::SET_CALIBRE_DEVICE_INFO
+                | opcode::SET_CALIBRE_DEVICE_NAME
+                | opcode::SET_LIBRARY_INFO
+                | opcode::NOOP => self.ok(json!({}))?,
+                opcode::DISPLAY_MESSAGE => {
+                    // messageKind 1 is Calibre rejecting our password.
+                    let message = args
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("")
+                        .to_string();
+                    if args.get("messageKind").and_then(Value::as_u64) == Some(1) {
+                        return Err(format!("password rejected: {message}"));
+                    }
+                    log::info!("calibre device: {message}");
+                }
+                opcode::SEND_BOOKLISTS | opcode::BOOK_DONE | opcode::CALIBRE_BUSY => {}
+                opcode::GET_BOOK_FILE_SEGMENT | opcode::GET_BOOK_METADATA => {
+                    self.send(
+                        opcode::ERROR,
+                        json!({ "message": "sending books to Calibre is not supported" }),
+                    )?;
+                }
+                other => log::debug!("calibre device: ignoring opcode {other}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_session(app: &AppHandle, options: &CalibreDeviceOptions, handle: &SessionHandle) {
+    let cancelled = &handle.cancelled;
+    let dir = PathBuf::from(&options.dest_dir);
+    let result = (|| {
+        std::fs::create_dir_all(&dir).map_err(|e| format!("create dest dir: {e}"))?;
+        let ip: IpAddr = options
+            .host
+            .parse()
+            .map_err(|e| format!("invalid host: {e}"))?;
+        let stream =
+            TcpStream::connect_timeout(&SocketAddr::new(ip, options.port), Duration::from_secs(10))
+                .map_err(|e| format!("connect failed: {e}"))?;
+        let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        *handle.stream.lock().unwrap() = Some(stream.try_clone().map_err(|e| e.to_string())?);
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mut session = Session {
+            app,
+            stream,
+            reader,
+            cache: DeviceCache::load(&dir),
+            dir: dir.clone(),
+            options,
+            cancelled,
+        };
+        let result = session.run();
+        session.cache.save(&dir);
+        result
+    })();
+    let error = result.err();
+    if let Some(error) = &error {
+        log::warn!("calibre device: {error}");
+    }
+    let _ = app.emit(
+        STATE_EVENT,
+        CalibreDeviceState {
+            connected: false,
+            library_name: None,
+            error,
+        },
+    );
+}
+
+/// Connect to a Calibre instance as its wireless device. Runs until
+/// Calibre disconnects or `calibre_device_disconnect` is called; progress
+/// is reported through events.
+#[tauri::command]
+pub fn calibre_device_connect(
+    app: AppHandle,
+    device: State<'_, CalibreDevice>,
+    options: CalibreDeviceOptions,
+) -> Result<(), String> {
+    ensure_path_allowed(&app, &options.dest_dir).map_err(|e| e.to_string())?;
+    let handle = Arc::new(SessionHandle::default());
+    if let Some(previous) = device.0.lock().unwrap().replace(handle.clone()) {
+        previous.cancel();
+    }
+    std::thread::spawn(move || run_session(&app, &options, &handle));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn calibre_device_disconnect(device: State<'_, CalibreDevice>) {
+    if let Some(handle) = device.0.lock().unwrap().take() {
+        handle.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_broadcast_reply() {
+        let from: IpAddr = "192.168.1.5".parse().unwrap();
+        assert_eq!(
+            parse_broadcast_reply("calibre wireless device client (on desk);8080,9090", from),
+            Some(CalibreInstance {
+                name: "desk".into(),
+                host: "192.168.1.5".into(),
+                port: 9090,
+            })
+        );
+        assert_eq!(parse_broadcast_reply("garbage", from), None);
+    }
+
+    #[test]
+    fn round_trips_wire_messages() {
+        let payload = json!({ "lpath": "Herbert/Dune.epub", "length": 4 });
+        let encoded = encode_message(opcode::SEND_BOOK, &payload);
+        assert!(encoded.starts_with(b"4"));
+        let mut stream = encoded.as_slice();
+        let (op, args) = read_message(&mut stream).unwrap().unwrap();
+        assert_eq!(op, opcode::SEND_BOOK);
+        assert_eq!(args, payload);
+        assert!(read_message(&mut stream).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(read_message(&mut b"12x".as_slice()).is_err());
+        assert!(read_message(&mut b"0[".as_slice()).is_err());
+        assert!(read_message(&mut b"10[0,".as_slice()).is_err());
+    }
+
+    #[test]
+    fn password_hash_is_sha1_of_password_then_challenge() {
+        // sha1("abc")
+        assert_eq!(
+            password_hash("ab", "c"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn lpaths_cannot_escape_dest_dir() {
+        let dir = Path::new("/books");
+        assert_eq!(
+            lpath_destination(dir, "Herbert/Dune.epub"),
+            Some(PathBuf::from("/books/Herbert/Dune.epub"))
+        );
+        assert_eq!(lpath_destination(dir, "../x.epub"), None);
+        assert_eq!(lpath_destination(dir, "/etc/passwd"), None);
+        assert_eq!(lpath_destination(dir, ""), None);
+    }
+}
@@ -0,0 +1,664 @@
+// Import an existing Calibre library.
+//
+// Calibre keeps its catalogue in `<library>/metadata.db` (SQLite) and the
+// files under `<library>/<Author>/<Title (id)>/`. This module reads that
+// database read-only, maps each book's authors, series, tags, rating,
+// identifiers and custom columns onto the shape of `BookMetadata` in
+// `libs/document.ts`, and picks the best format on disk for each book.
+//
+// With `dryRun` the library is only inspected: the report lists what would
+// be imported and what would be skipped (e.g. no supported format or a
+// missing file). Otherwise the chosen file and its cover are copied into
+// `destDir` — the same scope-checked destination `extract_archive_books`
+// uses — and the frontend feeds the copies through the normal import flow
+// with the returned metadata applied on top.
+//
+// Calibre may be running while we read; the database is opened read-only
+// and nothing is ever written back to the Calibre library.
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{ipc::Channel, AppHandle};
+
+use crate::transfer_file::ensure_path_allowed;
+
+const METADATA_DB: &str = "metadata.db";
+const COVER_FILENAME: &str = "cover.jpg";
+
+/// Calibre format names we can open, most preferred first.
+const FORMAT_PREFERENCE: &[&str] = &[
+    "EPUB", "AZW3", "MOBI", "AZW", "FB2", "FBZ", "CBZ", "PDF", "TXT", "MD",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreImportOptions {
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Overrides `FORMAT_PREFERENCE`, e.g. `["PDF", "EPUB"]`.
+    #[serde(default)]
+    pub preferred_formats: Vec<String>,
+}
+
+/// A Calibre custom column value, matching `CalibreCustomColumn` in
+/// `libs/document.ts`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreColumn {
+    pub label: String,
+    pub name: String,
+    pub datatype: String,
+    pub value: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreBookMetadata {
+    pub title: String,
+    /// Authors joined with `" & "`, as Calibre displays them.
+    pub author: String,
+    pub language: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub subject: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isbn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series_index: Option<f64>,
+    pub calibre_columns: Vec<CalibreColumn>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreBook {
+    pub calibre_id: i64,
+    pub uuid: Option<String>,
+    pub metadata: CalibreBookMetadata,
+    /// Rating on a 0–5 scale (Calibre stores half-stars as 0–10).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f64>,
+    /// Every `type:value` identifier, e.g. `isbn`, `goodreads`, `amazon`.
+    pub identifiers: HashMap<String, String>,
+    /// Calibre format name of the file picked for import, e.g. `EPUB`.
+    pub format: String,
+    /// The file inside the Calibre library, or its copy when not a dry run.
+    pub file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedCalibreBook {
+    pub calibre_id: i64,
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreImportReport {
+    pub dry_run: bool,
+    pub books: Vec<CalibreBook>,
+    pub skipped: Vec<SkippedCalibreBook>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreImportProgress {
+    pub title: String,
+    /// 1-based index of the book being processed.
+    pub index: usize,
+    pub total: usize,
+}
+
+struct BookRow {
+    id: i64,
+    title: String,
+    uuid: Option<String>,
+    path: String,
+    pubdate: Option<String>,
+    series_index: Option<f64>,
+    has_cover: bool,
+}
+
+struct CustomColumnDef {
+    id: i64,
+    label: String,
+    name: String,
+    datatype: String,
+    is_multiple: bool,
+    normalized: bool,
+}
+
+fn open_library(library_dir: &Path) -> Result<Connection, String> {
+    let db = library_dir.join(METADATA_DB);
+    if !db.is_file() {
+        return Err(format!("not a Calibre library: {}", library_dir.display()));
+    }
+    Connection::open_with_flags(
+        &db,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("open {}: {e}", db.display()))
+}
+
+fn query_strings(conn: &Connection, sql: &str, book: i64) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([book], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn query_string(conn: &Connection, sql: &str, book: i64) -> Result<Option<String>, String> {
+    conn.prepare_cached(sql)
+        .and_then(|mut stmt| stmt.query_row([book], |row| row.get(0)).optional())
+        .map_err(|e| e.to_string())
+}
+
+fn read_books(conn: &Connection) -> Result<Vec<BookRow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, uuid, path, pubdate, series_index, has_cover \
+             FROM books ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(BookRow {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                uuid: row.get(2)?,
+                path: row.get(3)?,
+                pubdate: row.get(4)?,
+                series_index: row.get(5)?,
+                has_cover: row.get::<_, Option<bool>>(6)?.unwrap_or(false),
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn read_custom_columns(conn: &Connection) -> Result<Vec<CustomColumnDef>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, label, name, datatype, is_multiple, normalized \
+             FROM custom_columns WHERE mark_for_delete = 0 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(CustomColumnDef {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                name: row.get(2)?,
+                datatype: row.get(3)?,
+                is_multiple: row.get(4)?,
+                normalized: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Calibre writes "no date" as year 101; treat that as missing.
+fn normalize_date(date: Option<String>) -> Option<String> {
+    date.filter(|d| !d.starts_with("0101-") && !d.is_empty())
+}
+
+fn sql_value_to_json(value: rusqlite::types::Value, datatype: &str) -> Value {
+    use rusqlite::types::Value as Sql;
+    match (value, datatype) {
+        (Sql::Integer(i), "bool") => Value::Bool(i != 0),
+        // Ratings are half-stars in the database, like the built-in one.
+        (Sql::Integer(i), "rating") => serde_json::json!(i as f64 / 2.0),
+        (Sql::Integer(i), _) => serde_json::json!(i),
+        (Sql::Real(f), _) => serde_json::json!(f),
+        (Sql::Text(s), _) => Value::String(s),
+        _ => Value::Null,
+    }
+}
+
+fn read_custom_column(
+    conn: &Connection,
+    def: &CustomColumnDef,
+    book: i64,
+) -> Result<Option<CalibreColumn>, String> {
+    // `composite` columns are templates evaluated by Calibre and have no
+    // table of their own.
+    if def.datatype == "composite" {
+        return Ok(None);
+    }
+    let table = format!("custom_column_{}", def.id);
+    let link = format!("books_custom_column_{}_link", def.id);
+    let is_series = def.datatype == "series";
+    let sql = if def.normalized {
+        let extra = if is_series { "l.extra" } else { "NULL" };
+        format!(
+            "SELECT v.value, {extra} FROM {link} l JOIN {table} v ON v.id = l.value \
+             WHERE l.book = ?1 ORDER BY l.id"
+        )
+    } else {
+        format!("SELECT value, NULL FROM {table} WHERE book = ?1")
+    };
+    let mut stmt = conn.prepare_cached(&sql).map_err(|e| e.to_string())?;
+    let rows: Vec<(rusqlite::types::Value, Option<f64>)> = stmt
+        .query_map([book], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+    let extra = rows.first().and_then(|(_, extra)| *extra);
+    let mut values = rows
+        .into_iter()
+        .map(|(v, _)| sql_value_to_json(v, &def.datatype));
+    let value = if def.is_multiple {
+        Value::Array(values.collect())
+    } else {
+        values.next().unwrap_or(Value::Null)
+    };
+    Ok(Some(CalibreColumn {
+        label: def.label.clone(),
+        name: def.name.clone(),
+        datatype: def.datatype.clone(),
+        value,
+        extra,
+    }))
+}
+
+/// Pick the format to import from the `data` rows of a book: the first
+/// entry of `preference` the book has, as `(FORMAT, file name stem)`.
+fn pick_format(available: &[(String, String)], preference: &[String]) -> Option<(String, String)> {
+    preference.iter().find_map(|wanted| {
+        available
+            .iter()
+            .find(|(format, _)| format.eq_ignore_ascii_case(wanted))
+            .cloned()
+    })
+}
+
+fn read_book(
+    conn: &Connection,
+    library_dir: &Path,
+    row: &BookRow,
+    custom_columns: &[CustomColumnDef],
+    preference: &[String],
+) -> Result<Result<CalibreBook, String>, String> {
+    let mut stmt = conn
+        .prepare_cached("SELECT format, name FROM data WHERE book = ?1")
+        .map_err(|e| e.to_string())?;
+    let available: Vec<(String, String)> = stmt
+        .query_map([row.id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let Some((format, stem)) = pick_format(&available, preference) else {
+        let formats: Vec<&str> = available.iter().map(|(f, _)| f.as_str()).collect();
+        return Ok(Err(if formats.is_empty() {
+            "no files".to_string()
+        } else {
+            format!("no supported format ({})", formats.join(", "))
+        }));
+    };
+    let book_dir = library_dir.join(&row.path);
+    let file_path = book_dir.join(format!("{stem}.{}", format.to_lowercase()));
+    if !file_path.is_file() {
+        return Ok(Err(format!("missing file {}", file_path.display())));
+    }
+    let cover_path = book_dir.join(COVER_FILENAME);
+    let cover_path =
+        (row.has_cover && cover_path.is_file()).then(|| cover_path.to_string_lossy().to_string());
+
+    let authors = query_strings(
+        conn,
+        "SELECT a.name FROM books_authors_link l JOIN authors a ON a.id = l.author \
+         WHERE l.book = ?1 ORDER BY l.id",
+        row.id,
+    )?;
+    let tags = query_strings(
+        conn,
+        "SELECT t.name FROM books_tags_link l JOIN tags t ON t.id = l.tag \
+         WHERE l.book = ?1 ORDER BY t.name",
+        row.id,
+    )?;
+    let languages = query_strings(
+        conn,
+        "SELECT g.lang_code FROM books_languages_link l JOIN languages g ON g.id = l.lang_code \
+         WHERE l.book = ?1 ORDER BY l.item_order",
+        row.id,
+    )?;
+    let series = query_string(
+        conn,
+        "SELECT s.name FROM books_series_link l JOIN series s ON s.id = l.series \
+         WHERE l.book = ?1",
+        row.id,
+    )?;
+    let publisher = query_string(
+        conn,
+        "SELECT p.name FROM books_publishers_link l JOIN publishers p ON p.id = l.publisher \
+         WHERE l.book = ?1",
+        row.id,
+    )?;
+    let description = query_string(conn, "SELECT text FROM comments WHERE book = ?1", row.id)?;
+    let rating: Option<i64> = conn
+        .prepare_cached(
+            "SELECT r.rating FROM books_ratings_link l JOIN ratings r ON r.id = l.rating \
+             WHERE l.book = ?1",
+        )
+        .and_then(|mut stmt| stmt.query_row([row.id], |r| r.get(0)).optional())
+        .map_err(|e| e.to_string())?
+        .flatten();
+
+    let mut stmt = conn
+        .prepare_cached("SELECT type, val FROM identifiers WHERE book = ?1")
+        .map_err(|e| e.to_string())?;
+    let identifiers: HashMap<String, String> = stmt
+        .query_map([row.id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut calibre_columns = Vec::new();
+    for def in custom_columns {
+        calibre_columns.extend(read_custom_column(conn, def, row.id)?);
+    }
+
+    let metadata = CalibreBookMetadata {
+        title: row.title.clone(),
+        author: authors.join(" & "),
+        language: languages,
+        publisher,
+        published: normalize_date(row.pubdate.clone()),
+        description: description.filter(|d| !d.trim().is_empty()),
+        subject: tags,
+        identifier: row.uuid.as_ref().map(|u| format!("urn:uuid:{u}")),
+        isbn: identifiers.get("isbn").cloned(),
+        series_index: series.as_ref().and(row.series_index),
+        series,
+        calibre_columns,
+    };
+    Ok(Ok(CalibreBook {
+        calibre_id: row.id,
+        uuid: row.uuid.clone(),
+        metadata,
+        rating: rating.filter(|r| *r > 0).map(|r| r as f64 / 2.0),
+        identifiers,
+        format,
+        file_path: file_path.to_string_lossy().to_string(),
+        cover_path,
+    }))
+}
+
+/// Copy the book file (and cover) into `dest_dir/<calibre id>/` so books
+/// with identical file names don't collide, and point `book` at the copies.
+fn copy_into(book: &mut CalibreBook, dest_dir: &Path) -> Result<(), String> {
+    let dir = dest_dir.join(book.calibre_id.to_string());
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let copy = |src: &str| -> Result<String, String> {
+        let src = PathBuf::from(src);
+        let dest = dir.join(src.file_name().unwrap_or_default());
+        std::fs::copy(&src, &dest).map_err(|e| format!("copy {}: {e}", src.display()))?;
+        Ok(dest.to_string_lossy().to_string())
+    };
+    book.file_path = copy(&book.file_path)?;
+    if let Some(cover) = book.cover_path.take() {
+        book.cover_path = Some(copy(&cover)?);
+    }
+    Ok(())
+}
+
+fn import_library_sync(
+    library_dir: &Path,
+    dest_dir: Option<&Path>,
+    options: &CalibreImportOptions,
+    on_progress: &dyn Fn(CalibreImportProgress),
+) -> Result<CalibreImportReport, String> {
+    let conn = open_library(library_dir)?;
+    let preference: Vec<String> = if options.preferred_formats.is_empty() {
+        FORMAT_PREFERENCE.iter().map(|f| f.to_string()).collect()
+    } else {
+        options
+            .preferred_formats
+            .iter()
+            .map(|f| f.to_uppercase())
+            .collect()
+    };
+    // Older libraries predate custom columns entirely.
+    let custom_columns = read_custom_columns(&conn).unwrap_or_default();
+    let rows = read_books(&conn)?;
+    let mut report = CalibreImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    for (i, row) in rows.iter().enumerate() {
+        on_progress(CalibreImportProgress {
+            title: row.title.clone(),
+            index: i + 1,
+            total: rows.len(),
+        });
+        let result = read_book(&conn, library_dir, row, &custom_columns, &preference)?;
+        let result = match (result, dest_dir) {
+            (Ok(mut book), Some(dest_dir)) if !options.dry_run => {
+                copy_into(&mut book, dest_dir).map(|_| book)
+            }
+            (result, _) => result,
+        };
+        match result {
+            Ok(book) => report.books.push(book),
+            Err(reason) => report.skipped.push(SkippedCalibreBook {
+                calibre_id: row.id,
+                title: row.title.clone(),
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Read a Calibre library and, unless `options.dryRun`, copy the selected
+/// format of every book into `dest_dir`. Progress is reported per book.
+#[tauri::command]
+pub async fn import_calibre_library(
+    app: AppHandle,
+    library_dir: String,
+    dest_dir: String,
+    options: CalibreImportOptions,
+    on_progress: Channel<CalibreImportProgress>,
+) -> Result<CalibreImportReport, String> {
+    ensure_path_allowed(&app, &library_dir).map_err(|e| e.to_string())?;
+    if !options.dry_run {
+        ensure_path_allowed(&app, &dest_dir).map_err(|e| e.to_string())?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        import_library_sync(
+            Path::new(&library_dir),
+            Some(Path::new(&dest_dir)),
+            &options,
+            &|progress| {
+                let _ = on_progress.send(progress);
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "
+        CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, uuid TEXT, path TEXT,
+            pubdate TEXT, series_index REAL, has_cover BOOL);
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+        CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER);
+        CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT);
+        CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER,
+            lang_code INTEGER, item_order INTEGER);
+        CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER);
+        CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER,
+            publisher INTEGER);
+        CREATE TABLE ratings (id INTEGER PRIMARY KEY, rating INTEGER);
+        CREATE TABLE books_ratings_link (id INTEGER PRIMARY KEY, book INTEGER, rating INTEGER);
+        CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER, text TEXT);
+        CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+        CREATE TABLE data (id INTEGER PRIMARY KEY, book INTEGER, format TEXT, name TEXT);
+        CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT, name TEXT,
+            datatype TEXT, mark_for_delete BOOL, is_multiple BOOL, normalized BOOL);
+        CREATE TABLE custom_column_1 (id INTEGER PRIMARY KEY, value TEXT);
+        CREATE TABLE books_custom_column_1_link (id INTEGER PRIMARY KEY, book INTEGER,
+            value INTEGER);
+        CREATE TABLE custom_column_2 (id INTEGER PRIMARY KEY, book INTEGER, value INTEGER);
+    ";
+
+    const DATA: &str = "
+        INSERT INTO books VALUES (1, 'Dune', 'u-1', 'Frank Herbert/Dune (1)',
+            '1965-08-01T00:00:00+00:00', 1.0, 1);
+        INSERT INTO books VALUES (2, 'Scan', 'u-2', 'Unknown/Scan (2)',
+            '0101-01-01T00:00:00+00:00', 1.0, 0);
+        INSERT INTO authors VALUES (1, 'Frank Herbert');
+        INSERT INTO books_authors_link VALUES (1, 1, 1);
+        INSERT INTO tags VALUES (1, 'SF'), (2, 'Classic');
+        INSERT INTO books_tags_link VALUES (1, 1, 1), (2, 1, 2);
+        INSERT INTO languages VALUES (1, 'eng');
+        INSERT INTO books_languages_link VALUES (1, 1, 1, 0);
+        INSERT INTO series VALUES (1, 'Dune Chronicles');
+        INSERT INTO books_series_link VALUES (1, 1, 1);
+        INSERT INTO ratings VALUES (1, 8);
+        INSERT INTO books_ratings_link VALUES (1, 1, 1);
+        INSERT INTO identifiers VALUES (1, 1, 'isbn', '9780441013593');
+        INSERT INTO data VALUES (1, 1, 'PDF', 'Dune - Frank Herbert'),
+            (2, 1, 'EPUB', 'Dune - Frank Herbert'), (3, 2, 'DJVU', 'Scan');
+        INSERT INTO custom_columns VALUES (1, 'shelf', 'Shelf', 'text', 0, 1, 1),
+            (2, 'read', 'Read', 'bool', 0, 0, 0);
+        INSERT INTO custom_column_1 VALUES (1, 'Favorites'), (2, 'Desert');
+        INSERT INTO books_custom_column_1_link VALUES (1, 1, 1), (2, 1, 2);
+        INSERT INTO custom_column_2 VALUES (1, 1, 1);
+    ";
+
+    fn fresh_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("readest-calibre-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn make_library(name: &str) -> PathBuf {
+        let dir = fresh_dir(name);
+        let conn = Connection::open(dir.join(METADATA_DB)).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute_batch(DATA).unwrap();
+        let book_dir = dir.join("Frank Herbert/Dune (1)");
+        std::fs::create_dir_all(&book_dir).unwrap();
+        std::fs::write(book_dir.join("Dune - Frank Herbert.epub"), b"epub").unwrap();
+        std::fs::write(book_dir.join("Dune - Frank Herbert.pdf"), b"pdf").unwrap();
+        std::fs::write(book_dir.join(COVER_FILENAME), b"jpg").unwrap();
+        dir
+    }
+
+    #[test]
+    fn dry_run_maps_metadata_without_copying() {
+        let library = make_library("dry");
+        let dest = fresh_dir("dry-dest");
+        let seen = std::cell::RefCell::new(Vec::new());
+        let report = import_library_sync(
+            &library,
+            Some(&dest),
+            &CalibreImportOptions {
+                dry_run: true,
+                ..Default::default()
+            },
+            &|p| seen.borrow_mut().push((p.index, p.total)),
+        )
+        .unwrap();
+        assert_eq!(*seen.borrow(), vec![(1, 2), (2, 2)]);
+        assert_eq!(report.books.len(), 1);
+        let book = &report.books[0];
+        assert_eq!(book.format, "EPUB");
+        assert!(book.file_path.starts_with(&*library.to_string_lossy()));
+        assert_eq!(book.rating, Some(4.0));
+        let m = &book.metadata;
+        assert_eq!(m.author, "Frank Herbert");
+        assert_eq!(m.subject, vec!["Classic", "SF"]);
+        assert_eq!(m.language, vec!["eng"]);
+        assert_eq!(m.series.as_deref(), Some("Dune Chronicles"));
+        assert_eq!(m.series_index, Some(1.0));
+        assert_eq!(m.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(m.calibre_columns.len(), 2);
+        assert_eq!(
+            m.calibre_columns[0].value,
+            serde_json::json!(["Favorites", "Desert"])
+        );
+        assert_eq!(m.calibre_columns[1].value, Value::Bool(true));
+        assert_eq!(std::fs::read_dir(&dest).unwrap().count(), 0);
+
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].reason, "no supported format (DJVU)");
+        let _ = std::fs::remove_dir_all(&library);
+        let _ = std::fs::remove_dir_all(&dest);
+    }
+
+    #[test]
+    fn import_copies_preferred_format_and_cover() {
+        let library = make_library("copy");
+        let dest = fresh_dir("copy-dest");
+        let report = import_library_sync(
+            &library,
+            Some(&dest),
+            &CalibreImportOptions {
+                dry_run: false,
+                preferred_formats: vec!["pdf".into()],
+            },
+            &|_| {},
+        )
+        .unwrap();
+        let book = &report.books[0];
+        assert_eq!(book.format, "PDF");
+        assert_eq!(
+            PathBuf::from(&book.file_path),
+            dest.join("1/Dune - Frank Herbert.pdf")
+        );
+        assert!(PathBuf::from(book.cover_path.as_ref().unwrap()).is_file());
+        let _ = std::fs::remove_dir_all(&library);
+        let _ = std::fs::remove_dir_all(&dest);
+    }
+
+    #[test]
+    fn placeholder_dates_are_dropped() {
+        assert_eq!(
+            normalize_date(Some("0101-01-01T00:00:00+00:00".into())),
+            None
+        );
+        assert_eq!(
+            normalize_date(Some("1965-08-01T00:00:00+00:00".into())).as_deref(),
+            Some("1965-08-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn rejects_non_calibre_dirs() {
+        let dir = fresh_dir("empty");
+        assert!(open_library(&dir).is_err());
+    }
+}
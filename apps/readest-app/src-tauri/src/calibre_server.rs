@@ -0,0 +1,645 @@
+// Client for a Calibre Content Server (`calibre-server`).
+//
+// Talks to the server's AJAX API to list libraries, search and page
+// through books, fetch covers, download a format through the download
+// manager, and read/write the "last read position" Calibre's own viewer
+// syncs with. The server is addressed per call by `CalibreServer` so the
+// frontend keeps owning the list of servers and their credentials.
+//
+// Authentication: calibre-server's default `auto` mode asks for HTTP
+// Digest over plain HTTP and Basic over HTTPS. Requests go out without
+// credentials first and answer whichever challenge comes back, so both
+// modes (and anonymous servers) work without configuration.
+
+use md5::{Digest, Md5};
+use rand::RngCore;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State, Url};
+
+use crate::download_manager::{DownloadItem, DownloadManager};
+
+/// Device name reported with pushed reading positions.
+const POSITION_DEVICE: &str = "readest";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreServer {
+    /// Base URL, e.g. `http://192.168.1.10:8080`, optionally with the
+    /// `--url-prefix` the server was started with.
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreLibrary {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreLibraries {
+    pub libraries: Vec<CalibreLibrary>,
+    pub default_library: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreServerBook {
+    pub id: i64,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub uuid: Option<String>,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub tags: Vec<String>,
+    /// 0–5 stars.
+    pub rating: Option<f64>,
+    /// Upper-case Calibre format names, e.g. `EPUB`.
+    pub formats: Vec<String>,
+    pub languages: Vec<String>,
+    pub publisher: Option<String>,
+    pub pubdate: Option<String>,
+    pub comments: Option<String>,
+    pub identifiers: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibreSearchResult {
+    pub total: u64,
+    pub offset: u64,
+    pub books: Vec<CalibreServerBook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrePosition {
+    pub device: String,
+    pub cfi: String,
+    /// Seconds since the epoch.
+    pub epoch: f64,
+    #[serde(alias = "pos_frac")]
+    pub pos_frac: f64,
+}
+
+/// Parse the parameters of a `WWW-Authenticate` challenge. Values may be
+/// quoted and quoted values may contain commas (`qop="auth,auth-int"`).
+fn parse_challenge(params: &str) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let Some(eq) = rest.find('=') else { break };
+        let key = rest[..eq]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let value;
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            value = quoted[..end].to_string();
+            rest = quoted.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+        out.insert(key, value);
+    }
+    out
+}
+
+fn md5_hex(input: &str) -> String {
+    Md5::digest(input.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// RFC 2617 `Authorization: Digest …` value for `method uri`.
+fn digest_authorization(
+    challenge: &HashMap<String, String>,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> String {
+    let realm = challenge.get("realm").map(String::as_str).unwrap_or("");
+    let nonce = challenge.get("nonce").map(String::as_str).unwrap_or("");
+    let ha1 = md5_hex(&format!("{username}:{realm}:{password}"));
+    let ha2 = md5_hex(&format!("{method}:{uri}"));
+    let qop_auth = challenge
+        .get("qop")
+        .is_some_and(|q| q.split(',').any(|q| q.trim() == "auth"));
+    let mut header = format!(
+        "Digest username=\"{username}\", realm=\"{realm}\", nonce=\"{nonce}\", uri=\"{uri}\", algorithm=MD5"
+    );
+    if qop_auth {
+        let nc = "00000001";
+        let response = md5_hex(&format!("{ha1}:{nonce}:{nc}:{cnonce}:auth:{ha2}"));
+        header.push_str(&format!(
+            ", response=\"{response}\", qop=auth, nc={nc}, cnonce=\"{cnonce}\""
+        ));
+    } else {
+        let response = md5_hex(&format!("{ha1}:{nonce}:{ha2}"));
+        header.push_str(&format!(", response=\"{response}\""));
+    }
+    if let Some(opaque) = challenge.get("opaque") {
+        header.push_str(&format!(", opaque=\"{opaque}\""));
+    }
+    header
+}
+
+fn basic_authorization(username: &str, password: &str) -> String {
+    use base64::Engine;
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"))
+    )
+}
+
+fn new_cnonce() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Answer a `WWW-Authenticate` header for `method url`, or `None` when the
+/// server has no credentials configured or the scheme is unsupported.
+fn authorization_for(
+    server: &CalibreServer,
+    www_authenticate: &str,
+    method: &Method,
+    url: &Url,
+) -> Option<String> {
+    let username = server.username.as_deref()?;
+    let password = server.password.as_deref().unwrap_or("");
+    let (scheme, params) = www_authenticate
+        .trim()
+        .split_once(' ')
+        .unwrap_or((www_authenticate.trim(), ""));
+    if scheme.eq_ignore_ascii_case("digest") {
+        let uri = match url.query() {
+            Some(q) => format!("{}?{q}", url.path()),
+            None => url.path().to_string(),
+        };
+        Some(digest_authorization(
+            &parse_challenge(params),
+            username,
+            password,
+            method.as_str(),
+            &uri,
+            &new_cnonce(),
+        ))
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        Some(basic_authorization(username, password))
+    } else {
+        None
+    }
+}
+
+fn client(server: &CalibreServer) -> Result<reqwest::Client, String> {
+    crate::net::client_builder_for(&server.url)
+        .user_agent(concat!("Readest/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn endpoint(server: &CalibreServer, path: &str) -> Result<Url, String> {
+    let base = format!("{}/", server.url.trim_end_matches('/'));
+    Url::parse(&base)
+        .and_then(|u| u.join(path.trim_start_matches('/')))
+        .map_err(|e| format!("invalid server url: {e}"))
+}
+
+fn segment(s: &str) -> String {
+    percent_encoding::utf8_percent_encode(s, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+async fn send(
+    server: &CalibreServer,
+    method: Method,
+    url: Url,
+    body: Option<&Value>,
+) -> Result<reqwest::Response, String> {
+    let client = client(server)?;
+    let build = |authorization: Option<&str>| {
+        let mut request = client.request(method.clone(), url.clone());
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        request
+    };
+    let mut response = build(None)
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {e}"))?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get("www-authenticate")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        let authorization = authorization_for(server, &challenge, &method, &url)
+            .ok_or_else(|| "authentication required".to_string())?;
+        response = build(Some(&authorization))
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {e}"))?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err("invalid username or password".to_string());
+        }
+    }
+    if !response.status().is_success() {
+        return Err(format!(
+            "request failed with status code {}",
+            response.status().as_u16()
+        ));
+    }
+    Ok(response)
+}
+
+async fn get_json(server: &CalibreServer, url: Url) -> Result<Value, String> {
+    send(server, Method::GET, url, None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("invalid response: {e}"))
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|a| {
+            a.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Map one entry of `/ajax/books` to our model.
+fn parse_book(id: i64, value: &Value) -> CalibreServerBook {
+    CalibreServerBook {
+        id,
+        title: string_field(value, "title").unwrap_or_default(),
+        authors: string_list(value.get("authors")),
+        uuid: string_field(value, "uuid"),
+        series: string_field(value, "series"),
+        series_index: value.get("series_index").and_then(Value::as_f64),
+        tags: string_list(value.get("tags")),
+        rating: value
+            .get("rating")
+            .and_then(Value::as_f64)
+            .filter(|r| *r > 0.0)
+            .map(|r| r / 2.0),
+        formats: string_list(value.get("formats"))
+            .into_iter()
+            .map(|f| f.to_uppercase())
+            .collect(),
+        languages: string_list(value.get("languages")),
+        publisher: string_field(value, "publisher"),
+        pubdate: string_field(value, "pubdate").filter(|d| !d.starts_with("0101-")),
+        comments: string_field(value, "comments"),
+        identifiers: value
+            .get("identifiers")
+            .and_then(Value::as_object)
+            .map(|m| {
+                m.iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+#[tauri::command]
+pub async fn calibre_server_libraries(server: CalibreServer) -> Result<CalibreLibraries, String> {
+    let info = get_json(&server, endpoint(&server, "ajax/library-info")?).await?;
+    let libraries = info
+        .get("library_map")
+        .and_then(Value::as_object)
+        .map(|m| {
+            m.iter()
+                .map(|(id, name)| CalibreLibrary {
+                    id: id.clone(),
+                    name: name.as_str().unwrap_or(id).to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(CalibreLibraries {
+        libraries,
+        default_library: string_field(&info, "default_library"),
+    })
+}
+
+/// Search a library with Calibre's query language (empty lists everything)
+/// and return one page of books with their metadata.
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
+#[tauri::command]
+pub async fn calibre_server_search(
+    server: CalibreServer,
+    library_id: String,
+    query: String,
+    offset: Option<u64>,
+    num: Option<u64>,
+    sort: Option<String>,
+    sort_order: Option<String>,
+) -> Result<CalibreSearchResult, String> {
+    let mut url = endpoint(&server, &format!("ajax/search/{}", segment(&library_id)))?;
+    url.query_pairs_mut()
+        .append_pair("query", &query)
+        .append_pair("offset", &offset.unwrap_or(0).to_string())
+        .append_pair("num", &num.unwrap_or(50).to_string())
+        .append_pair("sort", sort.as_deref().unwrap_or("timestamp"))
+        .append_pair("sort_order", sort_order.as_deref().unwrap_or("desc"));
+    let search = get_json(&server, url).await?;
+    let ids: Vec<i64> = search
+        .get("book_ids")
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_i64).collect())
+        .unwrap_or_default();
+    let total = search.get("total_num").and_then(Value::as_u64).unwrap_or(0);
+    let offset = search.get("offset").and_then(Value::as_u64).unwrap_or(0);
+    if ids.is_empty() {
+        return Ok(CalibreSearchResult {
+            total,
+            offset,
+            books: Vec::new(),
+        });
+    }
+
+    let mut url = endpoint(&server, &format!("ajax/books/{}", segment(&library_id)))?;
+    let joined: Vec<String> = ids.iter().map(i64::to_string).collect();
+    url.query_pairs_mut().append_pair("ids", &joined.join(","));
+    let metadata = get_json(&server, url).await?;
+    // `/ajax/books` is keyed by id and has `null` for ids that vanished
+    // between the two calls; keep the search order.
+    let books = ids
+        .iter()
+        .filter_map(|id| {
+            let value = metadata.get(id.to_string()).filter(|v| v.is_object())?;
+            Some(parse_book(*id, value))
+        })
+        .collect();
+    Ok(CalibreSearchResult {
+        total,
+        offset,
+        books,
+    })
+}
+
+/// Raw cover (or thumbnail) bytes; the webview can't answer a Digest
+/// challenge on an `<img>` load itself.
+#[tauri::command]
+pub async fn calibre_server_cover(
+    server: CalibreServer,
+    library_id: String,
+    book_id: i64,
+    thumbnail: Option<bool>,
+) -> Result<tauri::ipc::Response, String> {
+    let kind = if thumbnail.unwrap_or(false) {
+        "thumb"
+    } else {
+        "cover"
+    };
+    let url = endpoint(
+        &server,
+        &format!("get/{kind}/{book_id}/{}", segment(&library_id)),
+    )?;
+    let bytes = send(&server, Method::GET, url, None)
+        .await?
+        .bytes()
+        .await
+        .map_err(|e| format!("read body: {e}"))?;
+    Ok(tauri::ipc::Response::new(bytes.to_vec()))
+}
+
+/// Queue `format` of a book for download into `dest_dir`.
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
+#[tauri::command]
+pub async fn calibre_server_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    server: CalibreServer,
+    library_id: String,
+    book_id: i64,
+    format: String,
+    title: String,
+    dest_dir: String,
+) -> Result<DownloadItem, String> {
+    let format = format.to_uppercase();
+    let url = endpoint(
+        &server,
+        &format!("get/{format}/{book_id}/{}", segment(&library_id)),
+    )?;
+    // The download manager replays fixed headers, so answer the challenge
+    // for this URL up front. Calibre's Digest nonces stay valid for an hour
+    // and aren't bound to a request count, which covers a paused/resumed
+    // download within a session.
+    let mut headers = HashMap::new();
+    if server.username.is_some() {
+        let response = client(&server)?
+            .head(url.clone())
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {e}"))?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let challenge = response
+                .headers()
+                .get("www-authenticate")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            if let Some(authorization) = authorization_for(&server, challenge, &Method::GET, &url) {
+                headers.insert("Authorization".to_string(), authorization);
+            }
+        }
+    }
+    let safe_title: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let safe_title = match safe_title.trim().trim_matches('.') {
+        "" => format!("calibre-{book_id}"),
+        t => t.to_string(),
+    };
+    let dest_path = PathBuf::from(dest_dir).join(format!("{safe_title}.{}", format.to_lowercase()));
+    manager.enqueue(
+        &app,
+        url.to_string(),
+        dest_path.to_string_lossy().to_string(),
+        headers,
+        None,
+    )
+}
+
+/// Last read positions Calibre has stored for a book format, newest first.
+#[tauri::command]
+pub async fn calibre_server_get_positions(
+    server: CalibreServer,
+    library_id: String,
+    book_id: i64,
+    format: String,
+) -> Result<Vec<CalibrePosition>, String> {
+    let url = endpoint(
+        &server,
+        &format!(
+            "book-get-last-read-position/{}/{book_id}-{}",
+            segment(&library_id),
+            format.to_uppercase()
+        ),
+    )?;
+    let result = get_json(&server, url).await?;
+    let mut positions: Vec<CalibrePosition> = result
+        .as_object()
+        .into_iter()
+        .flat_map(|m| m.values())
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|p| serde_json::from_value(p.clone()).ok())
+        .collect();
+    positions.sort_by(|a, b| b.epoch.total_cmp(&a.epoch));
+    Ok(positions)
+}
+
+/// Push a reading position (an EPUB CFI plus overall fraction) to Calibre.
+/// Needs a server user with write access.
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
+#[tauri::command]
+pub async fn calibre_server_set_position(
+    server: CalibreServer,
+    library_id: String,
+    book_id: i64,
+    format: String,
+    cfi: String,
+    pos_frac: f64,
+) -> Result<(), String> {
+    let url = endpoint(
+        &server,
+        &format!(
+            "book-set-last-read-position/{}/{book_id}/{}",
+            segment(&library_id),
+            format.to_uppercase()
+        ),
+    )?;
+    let body = serde_json::json!({
+        "device": POSITION_DEVICE,
+        "cfi": cfi,
+        "pos_frac": pos_frac.clamp(0.0, 1.0),
+    });
+    send(&server, Method::POST, url, Some(&body)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_challenge_params() {
+        let c = parse_challenge(
+            r#"realm="calibre", qop="auth,auth-int", nonce="abc,def", algorithm=MD5, opaque="x""#,
+        );
+        assert_eq!(c["realm"], "calibre");
+        assert_eq!(c["qop"], "auth,auth-int");
+        assert_eq!(c["nonce"], "abc,def");
+        assert_eq!(c["algorithm"], "MD5");
+        assert_eq!(c["opaque"], "x");
+    }
+
+    #[test]
+    fn digest_matches_rfc2617_example() {
+        let challenge = parse_challenge(
+            r#"realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        );
+        let header = digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        );
+        assert!(header.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(header.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    #[test]
+    fn answers_basic_and_skips_anonymous() {
+        let url = Url::parse("https://calibre.example/ajax/library-info").unwrap();
+        let mut server = CalibreServer {
+            url: "https://calibre.example".into(),
+            username: None,
+            password: None,
+        };
+        assert!(authorization_for(&server, "Basic realm=\"x\"", &Method::GET, &url).is_none());
+        server.username = Some("user".into());
+        server.password = Some("pass".into());
+        assert_eq!(
+            authorization_for(&server, "Basic realm=\"x\"", &Method::GET, &url).as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+    }
+
+    #[test]
+    fn endpoint_keeps_url_prefix() {
+        let server = CalibreServer {
+            url: "http://host:8080/calibre/".into(),
+            username: None,
+            password: None,
+        };
+        assert_eq!(
+            endpoint(&server, "ajax/library-info").unwrap().as_str(),
+            "http://host:8080/calibre/ajax/library-info"
+        );
+    }
+
+    #[test]
+    fn maps_ajax_book() {
+        let value = serde_json::json!({
+            "title": "Dune",
+            "authors": ["Frank Herbert"],
+            "series": "Dune Chronicles",
+            "series_index": 1.0,
+            "rating": 8,
+            "formats": ["epub", "pdf"],
+            "pubdate": "0101-01-01T00:00:00+00:00",
+            "identifiers": { "isbn": "9780441013593" },
+        });
+        let book = parse_book(7, &value);
+        assert_eq!(book.id, 7);
+        assert_eq!(book.authors, vec!["Frank Herbert"]);
+        assert_eq!(book.rating, Some(4.0));
+        assert_eq!(book.formats, vec!["EPUB", "PDF"]);
+        assert_eq!(book.pubdate, None);
+        assert_eq!(book.identifiers["isbn"], "9780441013593");
+    }
+}
@@ -0,0 +1,355 @@
+//! Page access for CBZ, CBR and CB7 comics.
+//!
+//! The container is told apart by its magic bytes rather than the
+//! extension, since "CBR" files are often ZIPs and the other way round.
+//! ZIP entries can be read in any order; RAR and 7z archives are usually
+//! solid, where an entry can only be decompressed by decompressing every
+//! entry before it, so those are read with `stream_pages` in a single pass
+//! in archive order. CBR needs the `rar` feature; without it RAR comics
+//! are still recognised but fail to open.
+
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+#[cfg(not(feature = "rar"))]
+use crate::archive_import::RAR_UNAVAILABLE;
+
+pub const PAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "avif", "bmp"];
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const RAR_MAGIC: &[u8] = b"Rar!\x1a\x07";
+const SEVEN_ZIP_MAGIC: &[u8] = b"7z\xbc\xaf\x27\x1c";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComicFormat {
+    Cbz,
+    Cbr,
+    Cb7,
+}
+
+impl ComicFormat {
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        if head.starts_with(ZIP_MAGIC) {
+            Some(ComicFormat::Cbz)
+        } else if head.starts_with(RAR_MAGIC) {
+            Some(ComicFormat::Cbr)
+        } else if head.starts_with(SEVEN_ZIP_MAGIC) {
+            Some(ComicFormat::Cb7)
+        } else {
+            None
+        }
+    }
+
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let mut head = [0u8; 8];
+        let n = File::open(path)
+            .and_then(|mut f| f.read(&mut head))
+            .map_err(|e| format!("open failed: {e}"))?;
+        Self::from_magic(&head[..n]).ok_or_else(|| "not a comic archive".to_string())
+    }
+
+    /// Whether pages can only be read front to back.
+    pub fn is_sequential(self) -> bool {
+        !matches!(self, ComicFormat::Cbz)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicPage {
+    /// Entry name in the archive.
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+}
+
+/// Whether the entry `name` is a page: an image that isn't hidden or a
+/// macOS resource fork.
+pub fn is_page(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+    if name.starts_with("__MACOSX/") {
+        return false;
+    }
+    let file_name = name.rsplit('/').next().unwrap_or("");
+    if file_name.starts_with('.') {
+        return false;
+    }
+    file_name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| PAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Compare entry names with digit runs as numbers, so `page2` sorts
+/// before `page10` the way readers (and scanners' numbering) expect.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let da = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let db = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let na = a[..da].trim_start_matches('0');
+            let nb = b[..db].trim_start_matches('0');
+            let ordering = na.len().cmp(&nb.len()).then_with(|| na.cmp(nb));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (&a[da..], &b[db..]);
+        } else {
+            let ordering = x.to_lowercase().cmp(y.to_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| format!("zip open failed: {e}"))
+}
+
+fn open_7z(path: &Path) -> Result<sevenz_rust::SevenZReader<File>, String> {
+    sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())
+        .map_err(|e| format!("7z open failed: {e}"))
+}
+
+/// Every entry in the archive, pages or not, with its size.
+pub fn list_entries(path: &Path, format: ComicFormat) -> Result<Vec<ComicPage>, String> {
+    let mut entries = Vec::new();
+    match format {
+        ComicFormat::Cbz => {
+            let mut zip = open_zip(path)?;
+            for i in 0..zip.len() {
+                let entry = zip
+                    .by_index_raw(i)
+                    .map_err(|e| format!("zip entry {i}: {e}"))?;
+                if entry.is_file() {
+                    entries.push(ComicPage {
+                        name: entry.name().to_string(),
+                        size: entry.size(),
+                    });
+                }
+            }
+        }
+        #[cfg(not(feature = "rar"))]
+        ComicFormat::Cbr => return Err(RAR_UNAVAILABLE.to_string()),
+        #[cfg(feature = "rar")]
+        ComicFormat::Cbr => {
+            let archive = unrar::Archive::new(path)
+                .open_for_listing()
+                .map_err(|e| format!("rar open failed: {e}"))?;
+            for header in archive {
+                let header = header.map_err(|e| format!("rar entry: {e}"))?;
+                if header.is_file() {
+                    entries.push(ComicPage {
+                        name: header.filename.to_string_lossy().into_owned(),
+                        size: header.unpacked_size,
+                    });
+                }
+            }
+        }
+        ComicFormat::Cb7 => {
+            let archive = open_7z(path)?;
+            for entry in &archive.archive().files {
+                if !entry.is_directory() {
+                    entries.push(ComicPage {
+                        name: entry.name().to_string(),
+                        size: entry.size(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// The pages among `entries`, in reading order.
+pub fn pages_of(entries: Vec<ComicPage>) -> Vec<ComicPage> {
+    let mut pages: Vec<ComicPage> = entries.into_iter().filter(|e| is_page(&e.name)).collect();
+    pages.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    pages
+}
+
+/// The comic's pages in reading order.
+pub fn list_pages(path: &Path, format: ComicFormat) -> Result<Vec<ComicPage>, String> {
+    Ok(pages_of(list_entries(path, format)?))
+}
+
+/// Read the entries named in `wanted` in one pass, in archive order,
+/// handing each to `f` as it is decompressed. Entries that aren't wanted
+/// are skipped without being kept; `f` returns `false` to stop early.
+pub fn stream_entries(
+    path: &Path,
+    format: ComicFormat,
+    wanted: &[&str],
+    mut f: impl FnMut(&str, Vec<u8>) -> Result<bool, String>,
+) -> Result<(), String> {
+    match format {
+        ComicFormat::Cbz => {
+            let mut zip = open_zip(path)?;
+            for name in wanted {
+                let mut entry = zip
+                    .by_name(name)
+                    .map_err(|e| format!("entry {name}: {e}"))?;
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("extract {name}: {e}"))?;
+                if !f(name, data)? {
+                    break;
+                }
+            }
+        }
+        #[cfg(not(feature = "rar"))]
+        ComicFormat::Cbr => return Err(RAR_UNAVAILABLE.to_string()),
+        #[cfg(feature = "rar")]
+        ComicFormat::Cbr => {
+            let mut archive = unrar::Archive::new(path)
+                .open_for_processing()
+                .map_err(|e| format!("rar open failed: {e}"))?;
+            while let Some(header) = archive
+                .read_header()
+                .map_err(|e| format!("rar header: {e}"))?
+            {
+                let name = header.entry().filename.to_string_lossy().into_owned();
+                archive = if header.entry().is_file() && wanted.contains(&name.as_str()) {
+                    let (data, next) = header.read().map_err(|e| format!("extract {name}: {e}"))?;
+                    if !f(&name, data)? {
+                        break;
+                    }
+                    next
+                } else {
+                    header.skip().map_err(|e| format!("rar skip: {e}"))?
+                };
+            }
+        }
+        ComicFormat::Cb7 => {
+            let mut archive = open_7z(path)?;
+            // The callback can't return our error type, so park it here.
+            let mut failure = None;
+            archive
+                .for_each_entries(|entry, reader| {
+                    if entry.is_directory() || !wanted.contains(&entry.name()) {
+                        // Solid blocks still have to be decoded through.
+                        std::io::copy(reader, &mut std::io::sink())?;
+                        return Ok(true);
+                    }
+                    let mut data = Vec::with_capacity(entry.size() as usize);
+                    reader.read_to_end(&mut data)?;
+                    match f(entry.name(), data) {
+                        Ok(more) => Ok(more),
+                        Err(e) => {
+                            failure = Some(e);
+                            Ok(false)
+                        }
+                    }
+                })
+                .map_err(|e| format!("7z extract failed: {e}"))?;
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read a single entry. For RAR and 7z this decodes everything before it.
+pub fn read_entry(path: &Path, format: ComicFormat, name: &str) -> Result<Vec<u8>, String> {
+    let mut found = None;
+    stream_entries(path, format, &[name], |_, data| {
+        found = Some(data);
+        Ok(false)
+    })?;
+    found.ok_or_else(|| format!("entry not found: {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn formats_are_sniffed_from_magic() {
+        assert_eq!(
+            ComicFormat::from_magic(b"PK\x03\x04rest"),
+            Some(ComicFormat::Cbz)
+        );
+        assert_eq!(
+            ComicFormat::from_magic(b"Rar!\x1a\x07\x01\x00"),
+            Some(ComicFormat::Cbr)
+        );
+        assert_eq!(
+            ComicFormat::from_magic(b"7z\xbc\xaf\x27\x1c\x00\x04"),
+            Some(ComicFormat::Cb7)
+        );
+        assert_eq!(ComicFormat::from_magic(b"%PDF-1.7"), None);
+    }
+
+    #[test]
+    fn pages_sort_naturally_and_skip_junk() {
+        assert!(is_page("ch1/Page 01.JPG"));
+        assert!(!is_page("__MACOSX/ch1/._01.jpg"));
+        assert!(!is_page("ch1/.thumb.png"));
+        assert!(!is_page("ComicInfo.xml"));
+        let mut names = ["p10.jpg", "p9.jpg", "P1.jpg", "cover.jpg", "p010b.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["cover.jpg", "P1.jpg", "p9.jpg", "p10.jpg", "p010b.jpg"]
+        );
+    }
+
+    #[test]
+    fn zip_comics_list_and_stream_pages() {
+        let path = std::env::temp_dir().join(format!("readest-comic-{}.cbr", std::process::id()));
+        let mut buf = Vec::new();
+        {
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            for (name, data) in [
+                ("10.png", b"ten".as_slice()),
+                ("2.png", b"two"),
+                ("ComicInfo.xml", b"<ComicInfo/>"),
+            ] {
+                w.start_file(name, zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                w.write_all(data).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        std::fs::write(&path, buf).unwrap();
+
+        // Named .cbr, but it's a ZIP.
+        let format = ComicFormat::detect(&path).unwrap();
+        assert_eq!(format, ComicFormat::Cbz);
+        let pages = list_pages(&path, format).unwrap();
+        let names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["2.png", "10.png"]);
+        let mut seen = Vec::new();
+        stream_entries(&path, format, &names, |name, data| {
+            seen.push((name.to_string(), data));
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(
+            seen,
+            [
+                ("2.png".to_string(), b"two".to_vec()),
+                ("10.png".to_string(), b"ten".to_vec())
+            ]
+        );
+        assert_eq!(
+            read_entry(&path, format, "ComicInfo.xml").unwrap(),
+            b"<ComicInfo/>"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,221 @@
+//! `ComicInfo.xml`, the ComicRack metadata file CBZ/CBR/CB7 taggers embed.
+//!
+//! Only the fields the library uses are kept: series and issue number for
+//! sorting, credits, publication date, language, the manga flag (which
+//! decides the reading direction) and which page is the front cover.
+//! Unknown elements are ignored, and so is a file that isn't valid XML.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+
+use crate::epub_parser::{local_name, strip_xml_bom};
+
+pub const COMIC_INFO: &str = "ComicInfo.xml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicInfo {
+    pub title: Option<String>,
+    pub series: Option<String>,
+    /// The issue number as written: "1", "12.5", "0a".
+    pub number: Option<String>,
+    /// `number` as a sort key, from its leading digits.
+    pub series_index: Option<f64>,
+    pub volume: Option<u32>,
+    /// Issues in the series.
+    pub count: Option<u32>,
+    pub summary: Option<String>,
+    pub writers: Vec<String>,
+    pub pencillers: Vec<String>,
+    pub publisher: Option<String>,
+    pub genres: Vec<String>,
+    pub language: Option<String>,
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, from Year / Month / Day.
+    pub date: Option<String>,
+    pub manga: bool,
+    pub reading_direction: ReadingDirection,
+    /// Index of the page marked `FrontCover`, when it isn't the first.
+    pub cover_page: Option<usize>,
+}
+
+/// Whether the archive entry `name` is the comic's metadata file.
+pub fn is_comic_info(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+    !name.starts_with("__MACOSX/")
+        && name
+            .rsplit('/')
+            .next()
+            .is_some_and(|n| n.eq_ignore_ascii_case(COMIC_INFO))
+}
+
+fn list(text: &str) -> Vec<String> {
+    text.split([',', ';'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn leading_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (c == '.' && i > 0) || (c == '-' && i == 0)))
+        .map_or(text.len(), |(i, _)| i);
+    text[..end].trim_end_matches('.').parse().ok()
+}
+
+pub fn parse_comic_info(bytes: &[u8]) -> Result<ComicInfo, String> {
+    let bytes = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(bytes.as_ref());
+    reader.config_mut().trim_text(true);
+    let mut info = ComicInfo::default();
+    let (mut year, mut month, mut day) = (None::<u32>, None::<u32>, None::<u32>);
+    let mut buf = Vec::new();
+    let mut field: Option<Vec<u8>> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = local_name(e.name().as_ref()).to_vec();
+                if name == b"Page" {
+                    let attr = |key: &[u8]| {
+                        e.attributes()
+                            .flatten()
+                            .find(|a| a.key.as_ref() == key)
+                            .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+                    };
+                    let is_cover = attr(b"Type").is_some_and(|t| t == "FrontCover");
+                    if is_cover && info.cover_page.is_none() {
+                        info.cover_page = attr(b"Image").and_then(|i| i.trim().parse().ok());
+                    }
+                } else {
+                    field = Some(name);
+                }
+            }
+            Ok(Event::Text(t)) => {
+                let Some(name) = field.take() else {
+                    continue;
+                };
+                let text = t
+                    .unescape()
+                    .map(|v| v.trim().to_string())
+                    .unwrap_or_default();
+                if text.is_empty() {
+                    continue;
+                }
+                match name.as_slice() {
+                    b"Title" => info.title = Some(text),
+                    b"Series" => info.series = Some(text),
+                    b"Number" => {
+                        info.series_index = leading_number(&text);
+                        info.number = Some(text);
+                    }
+                    b"Volume" => info.volume = text.parse().ok(),
+                    b"Count" => info.count = text.parse().ok(),
+                    b"Summary" => info.summary = Some(text),
+                    b"Writer" => info.writers = list(&text),
+                    b"Penciller" => info.pencillers = list(&text),
+                    b"Publisher" => info.publisher = Some(text),
+                    b"Genre" => info.genres = list(&text),
+                    b"LanguageISO" => info.language = Some(text),
+                    b"Year" => year = text.parse().ok().filter(|&y| y > 0),
+                    b"Month" => month = text.parse().ok().filter(|m| (1..=12).contains(m)),
+                    b"Day" => day = text.parse().ok().filter(|d| (1..=31).contains(d)),
+                    b"Manga" => {
+                        info.manga = text.starts_with("Yes");
+                        if text == "YesAndRightToLeft" {
+                            info.reading_direction = ReadingDirection::Rtl;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => field = None,
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("{COMIC_INFO}: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+    info.date = match (year, month, day) {
+        (Some(y), Some(m), Some(d)) => Some(format!("{y:04}-{m:02}-{d:02}")),
+        (Some(y), Some(m), None) => Some(format!("{y:04}-{m:02}")),
+        (Some(y), _, _) => Some(format!("{y:04}")),
+        _ => None,
+    };
+    // The first page is the cover anyway.
+    info.cover_page = info.cover_page.filter(|&i| i > 0);
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_series_credits_and_direction() {
+        let xml = "\u{feff}<?xml version=\"1.0\"?>\
+            <ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\
+              <Title>The Long Night</Title><Series>Night Watch</Series>\
+              <Number>12.5</Number><Volume>2</Volume><Count>24</Count>\
+              <Summary>Rain &amp; neon.</Summary>\
+              <Writer>A. Writer, B. Writer</Writer><Penciller>C. Artist</Penciller>\
+              <Publisher>Example Comics</Publisher><Genre>Noir; Sci-Fi</Genre>\
+              <Year>2021</Year><Month>3</Month><LanguageISO>ja</LanguageISO>\
+              <Manga>YesAndRightToLeft</Manga>\
+              <Pages><Page Image=\"0\" Type=\"InnerCover\"/><Page Image=\"1\" Type=\"FrontCover\"/></Pages>\
+            </ComicInfo>";
+        let info = parse_comic_info(xml.as_bytes()).unwrap();
+        assert_eq!(
+            info,
+            ComicInfo {
+                title: Some("The Long Night".into()),
+                series: Some("Night Watch".into()),
+                number: Some("12.5".into()),
+                series_index: Some(12.5),
+                volume: Some(2),
+                count: Some(24),
+                summary: Some("Rain & neon.".into()),
+                writers: vec!["A. Writer".into(), "B. Writer".into()],
+                pencillers: vec!["C. Artist".into()],
+                publisher: Some("Example Comics".into()),
+                genres: vec!["Noir".into(), "Sci-Fi".into()],
+                language: Some("ja".into()),
+                date: Some("2021-03".into()),
+                manga: true,
+                reading_direction: ReadingDirection::Rtl,
+                cover_page: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn issue_numbers_sort_by_leading_digits() {
+        assert_eq!(leading_number("7"), Some(7.0));
+        assert_eq!(leading_number("0a"), Some(0.0));
+        assert_eq!(leading_number("-1"), Some(-1.0));
+        assert_eq!(leading_number("3."), Some(3.0));
+        assert_eq!(leading_number("½"), None);
+        let info =
+            parse_comic_info(b"<ComicInfo><Manga>Yes</Manga><Year>0</Year></ComicInfo>").unwrap();
+        assert!(info.manga);
+        assert_eq!(info.reading_direction, ReadingDirection::Ltr);
+        assert_eq!(info.date, None);
+    }
+
+    #[test]
+    fn finds_the_metadata_entry() {
+        assert!(is_comic_info("ComicInfo.xml"));
+        assert!(is_comic_info("Issue 1/comicinfo.XML"));
+        assert!(!is_comic_info("__MACOSX/._ComicInfo.xml"));
+        assert!(!is_comic_info("ComicInfo.xml.bak"));
+    }
+}
@@ -0,0 +1,12 @@
+//! Comic book archives.
+//!
+//! [`archive`] lists and reads the pages of CBZ, CBR (RAR) and CB7 (7z)
+//! files, streaming solid archives in one pass; [`info`] parses the
+//! `ComicInfo.xml` metadata they carry; [`reader`] has the import fast
+//! path and serves pages to the reader; [`scaled`] serves them downscaled
+//! to the screen through the `comicpage` URI scheme.
+
+pub mod archive;
+pub mod info;
+pub mod reader;
+pub mod scaled;
@@ -0,0 +1,393 @@
+//! Import and paging for comic archives.
+//!
+//! `parse_comic` is the import fast path: partialMD5, the page list, the
+//! `ComicInfo.xml` metadata and a cover thumbnail cut from the first page
+//! (or the page ComicInfo marks as the front cover), in one round trip.
+//!
+//! For reading, `open_comic` lists the pages and `get_comic_page` serves
+//! them one at a time as raw image bytes. CBZ pages are read straight out
+//! of the ZIP. CBR and CB7 archives are streamed on a background thread,
+//! front to back in a single pass, into a per-comic cache directory; a
+//! page request waits only until that page has come out of the stream, so
+//! the first pages show up without extracting the whole archive first.
+//! `close_comic` stops the stream and deletes the cache. Pages can also be
+//! fetched downscaled through the `comicpage` scheme (see [`super::scaled`]).
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use super::archive::{
+    list_entries, list_pages, pages_of, read_entry, stream_entries, ComicFormat, ComicPage,
+};
+use super::info::{is_comic_info, parse_comic_info, ComicInfo};
+use super::scaled::ScaledPages;
+use crate::parser_common::{compute_partial_md5, maybe_resize_cover, RawCoverImage};
+use crate::transfer_file::ensure_path_allowed;
+
+const CACHE_DIR: &str = "comics";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedComic {
+    pub partial_md5: String,
+    pub format: ComicFormat,
+    pub pages: Vec<ComicPage>,
+    /// From `ComicInfo.xml`, when the archive has a readable one.
+    pub info: Option<ComicInfo>,
+    /// The cover page, downscaled by `maybe_resize_cover`.
+    pub cover: Option<RawCoverImage>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedComic {
+    pub id: String,
+    pub format: ComicFormat,
+    pub pages: Vec<ComicPage>,
+}
+
+/// Progress of a background extraction; pages land in `dir` as `<index>`.
+struct Extraction {
+    dir: PathBuf,
+    state: Mutex<Extracted>,
+    ready: Condvar,
+    cancelled: AtomicBool,
+}
+
+#[derive(Default)]
+struct Extracted {
+    done: Vec<bool>,
+    finished: bool,
+    error: Option<String>,
+}
+
+pub(super) struct OpenComic {
+    path: PathBuf,
+    format: ComicFormat,
+    pub(super) pages: Vec<ComicPage>,
+    /// `None` for CBZ, whose pages are read on demand.
+    extraction: Option<Arc<Extraction>>,
+    /// Downscaled pages served through the `comicpage` scheme.
+    pub(super) scaled: ScaledPages,
+}
+
+impl OpenComic {
+    /// The original image bytes of page `index`; blocks until a streamed
+    /// archive has got that far.
+    pub(super) fn page(&self, index: usize) -> Result<Vec<u8>, String> {
+        let page = self
+            .pages
+            .get(index)
+            .ok_or_else(|| format!("page {index} out of range"))?;
+        match &self.extraction {
+            Some(extraction) => cached_page(extraction, index),
+            None => read_entry(&self.path, self.format, &page.name),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Comics {
+    open: Mutex<HashMap<String, Arc<OpenComic>>>,
+}
+
+impl Comics {
+    pub(super) fn get(&self, id: &str) -> Option<Arc<OpenComic>> {
+        self.open.lock().unwrap().get(id).cloned()
+    }
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "comic-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+pub(super) fn mime_for(name: &str) -> &'static str {
+    let ext = name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        _ => "image/jpeg",
+    }
+}
+
+fn parse_comic_sync(path: &Path) -> Result<ParsedComic, String> {
+    if !path.is_file() {
+        return Err(format!("file not found: {}", path.display()));
+    }
+    let partial_md5 = compute_partial_md5(path).map_err(|e| format!("partial_md5 failed: {e}"))?;
+    let format = ComicFormat::detect(path)?;
+    let entries = list_entries(path, format)?;
+    let info_name = entries
+        .iter()
+        .find(|e| is_comic_info(&e.name))
+        .map(|e| e.name.clone());
+    let pages = pages_of(entries);
+
+    // ComicInfo.xml and the first page in one pass, which matters for
+    // solid archives where ComicInfo is often stored last.
+    let mut wanted: Vec<&str> = info_name.iter().map(String::as_str).collect();
+    wanted.extend(pages.first().map(|p| p.name.as_str()));
+    let mut found: HashMap<String, Vec<u8>> = HashMap::new();
+    stream_entries(path, format, &wanted, |name, data| {
+        found.insert(name.to_string(), data);
+        Ok(found.len() < wanted.len())
+    })?;
+
+    let info =
+        info_name
+            .and_then(|name| found.remove(&name))
+            .and_then(|xml| match parse_comic_info(&xml) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    log::warn!("Ignoring ComicInfo.xml in {}: {e}", path.display());
+                    None
+                }
+            });
+    let cover_index = info
+        .as_ref()
+        .and_then(|i| i.cover_page)
+        .filter(|&i| i < pages.len())
+        .unwrap_or(0);
+    let cover = match pages.get(cover_index) {
+        Some(page) => {
+            let bytes = match found.remove(&page.name) {
+                Some(bytes) => bytes,
+                None => read_entry(path, format, &page.name)?,
+            };
+            let (bytes, mime) = maybe_resize_cover(bytes, mime_for(&page.name));
+            Some(RawCoverImage { bytes, mime })
+        }
+        None => None,
+    };
+    Ok(ParsedComic {
+        partial_md5,
+        format,
+        pages,
+        info,
+        cover,
+    })
+}
+
+/// Stream every page of a sequential archive into `extraction.dir`.
+fn extract_pages(path: &Path, format: ComicFormat, pages: &[ComicPage], extraction: &Extraction) {
+    let index: HashMap<&str, usize> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.name.as_str(), i))
+        .collect();
+    let names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+    let result = stream_entries(path, format, &names, |name, data| {
+        if extraction.cancelled.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let Some(&i) = index.get(name) else {
+            return Ok(true);
+        };
+        std::fs::write(extraction.dir.join(i.to_string()), data)
+            .map_err(|e| format!("cache page {name}: {e}"))?;
+        extraction.state.lock().unwrap().done[i] = true;
+        extraction.ready.notify_all();
+        Ok(true)
+    });
+    let cancelled = extraction.cancelled.load(Ordering::Relaxed);
+    {
+        let mut state = extraction.state.lock().unwrap();
+        state.finished = true;
+        if let Err(e) = result {
+            if !cancelled {
+                log::error!("Extracting {} failed: {e}", path.display());
+                state.error = Some(e);
+            }
+        }
+    }
+    extraction.ready.notify_all();
+    if cancelled {
+        let _ = std::fs::remove_dir_all(&extraction.dir);
+    }
+}
+
+/// Wait for page `index` to come out of the stream and read it.
+fn cached_page(extraction: &Extraction, index: usize) -> Result<Vec<u8>, String> {
+    let mut state = extraction.state.lock().unwrap();
+    while !state.done[index] && !state.finished {
+        state = extraction.ready.wait(state).unwrap();
+    }
+    if !state.done[index] {
+        return Err(state
+            .error
+            .clone()
+            .unwrap_or_else(|| format!("page {index} is missing from the archive")));
+    }
+    drop(state);
+    std::fs::read(extraction.dir.join(index.to_string()))
+        .map_err(|e| format!("read cached page: {e}"))
+}
+
+/// partialMD5, page list and cover thumbnail of a CBZ, CBR or CB7 file.
+#[tauri::command]
+pub async fn parse_comic(file_path: String) -> Result<ParsedComic, String> {
+    tauri::async_runtime::spawn_blocking(move || parse_comic_sync(Path::new(&file_path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Open a comic for `get_comic_page`. RAR and 7z comics start streaming
+/// their pages into the cache right away.
+#[tauri::command]
+pub async fn open_comic(
+    app: AppHandle,
+    comics: State<'_, Comics>,
+    file_path: String,
+) -> Result<OpenedComic, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    let cache_root = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join(CACHE_DIR);
+    let path = PathBuf::from(&file_path);
+    let (format, pages) = {
+        let path = path.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let format = ComicFormat::detect(&path)?;
+            Ok::<_, String>((format, list_pages(&path, format)?))
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))??
+    };
+
+    let id = new_id();
+    let extraction = if format.is_sequential() {
+        if comics.open.lock().unwrap().is_empty() {
+            // Leftovers from a previous run.
+            let _ = std::fs::remove_dir_all(&cache_root);
+        }
+        let dir = cache_root.join(&id);
+        std::fs::create_dir_all(&dir).map_err(|e| format!("create cache dir: {e}"))?;
+        let extraction = Arc::new(Extraction {
+            dir,
+            state: Mutex::new(Extracted {
+                done: vec![false; pages.len()],
+                ..Default::default()
+            }),
+            ready: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+        });
+        {
+            let (path, pages, extraction) = (path.clone(), pages.clone(), extraction.clone());
+            std::thread::spawn(move || extract_pages(&path, format, &pages, &extraction));
+        }
+        Some(extraction)
+    } else {
+        None
+    };
+    let comic = OpenComic {
+        path,
+        format,
+        pages: pages.clone(),
+        extraction,
+        scaled: ScaledPages::default(),
+    };
+    comics
+        .open
+        .lock()
+        .unwrap()
+        .insert(id.clone(), Arc::new(comic));
+    Ok(OpenedComic { id, format, pages })
+}
+
+/// The image bytes of page `index` of an opened comic.
+#[tauri::command]
+pub async fn get_comic_page(
+    comics: State<'_, Comics>,
+    id: String,
+    index: usize,
+) -> Result<tauri::ipc::Response, String> {
+    let comic = comics
+        .get(&id)
+        .ok_or_else(|| format!("no open comic: {id}"))?;
+    let bytes = tauri::async_runtime::spawn_blocking(move || comic.page(index))
+        .await
+        .map_err(|e| format!("join error: {e}"))??;
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+#[tauri::command]
+pub fn close_comic(comics: State<'_, Comics>, id: String) {
+    let Some(comic) = comics.open.lock().unwrap().remove(&id) else {
+        return;
+    };
+    comic.scaled.close();
+    if let Some(extraction) = &comic.extraction {
+        extraction.cancelled.store(true, Ordering::Relaxed);
+        let finished = extraction.state.lock().unwrap().finished;
+        // A running stream removes the directory itself once it stops.
+        if finished {
+            let _ = std::fs::remove_dir_all(&extraction.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_pages_wait_for_the_stream() {
+        let dir = std::env::temp_dir().join(format!("readest-comic-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let extraction = Arc::new(Extraction {
+            dir: dir.clone(),
+            state: Mutex::new(Extracted {
+                done: vec![false; 2],
+                ..Default::default()
+            }),
+            ready: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+        });
+        let writer = {
+            let extraction = extraction.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                std::fs::write(extraction.dir.join("0"), b"first").unwrap();
+                extraction.state.lock().unwrap().done[0] = true;
+                extraction.ready.notify_all();
+                let mut state = extraction.state.lock().unwrap();
+                state.finished = true;
+                state.error = Some("rar header: bad data".into());
+                drop(state);
+                extraction.ready.notify_all();
+            })
+        };
+        assert_eq!(cached_page(&extraction, 0).unwrap(), b"first");
+        writer.join().unwrap();
+        assert_eq!(
+            cached_page(&extraction, 1).unwrap_err(),
+            "rar header: bad data"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn page_mime_follows_extension() {
+        assert_eq!(mime_for("001.PNG"), "image/png");
+        assert_eq!(mime_for("a/b.jpeg"), "image/jpeg");
+        assert_eq!(mime_for("x.webp"), "image/webp");
+    }
+}
@@ -0,0 +1,408 @@
+//! Custom `comicpage` URI scheme that serves the pages of an opened comic
+//! downscaled to the device's resolution.
+//!
+//! Scanned pages are often 20-40 MP; handing those to the WebView as-is
+//! means decoding a 100+ MB bitmap per page, which is where the memory
+//! spikes and jank on Android come from. Here a page is decoded in Rust,
+//! resized to fit the requested box and re-encoded, so the WebView only
+//! ever sees screen-sized images. Pages that already fit are passed
+//! through untouched, without a decode.
+//!
+//! URL shape: `http://comicpage.localhost/<comic id>/<page index>?w=<px>&h=<px>&prefetch=<n>`
+//! (`comicpage://localhost/...` on macOS/Linux), where the id comes from
+//! `open_comic` and `w`/`h` are the viewport in device pixels. After a
+//! page is served, the next `prefetch` pages are scaled on a background
+//! thread so turning the page doesn't wait on a decode. Only comics opened
+//! through `open_comic`, which checks the path, can be reached.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{ImageEncoder, ImageReader};
+use tauri::http::{Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+
+use super::reader::{mime_for, Comics, OpenComic};
+use crate::range_file::{cors_origin, error};
+
+/// Scheme name; the WebView reaches it at `http://comicpage.localhost/`.
+pub const SCHEME: &str = "comicpage";
+
+/// Upper bound for either side of the requested box.
+const MAX_EDGE: u32 = 8192;
+const DEFAULT_PREFETCH: usize = 2;
+const MAX_PREFETCH: usize = 8;
+/// Scaled pages kept per comic: the prefetch window plus a few behind it
+/// for paging back.
+const CACHE_PAGES: usize = MAX_PREFETCH + 4;
+const JPEG_QUALITY: u8 = 90;
+/// Triangle rather than Lanczos3 for the same reason as covers (see
+/// `parser_common::COVER_RESIZE_FILTER`): it is several times faster, and
+/// at a downscale to screen size the difference doesn't show.
+const RESIZE_FILTER: FilterType = FilterType::Triangle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    width: u32,
+    height: u32,
+}
+
+struct ScaledPage {
+    index: usize,
+    bounds: Bounds,
+    bytes: Arc<Vec<u8>>,
+    mime: &'static str,
+}
+
+#[derive(Default)]
+struct PrefetchQueue {
+    pending: Range<usize>,
+    bounds: Option<Bounds>,
+    running: bool,
+}
+
+/// Per-comic state of the scheme, owned by the `OpenComic`.
+#[derive(Default)]
+pub(super) struct ScaledPages {
+    cache: Mutex<VecDeque<ScaledPage>>,
+    /// Held while a page is decoded, so the request and the prefetch thread
+    /// never hold two full-size bitmaps of the same comic at once.
+    decoding: Mutex<()>,
+    prefetch: Mutex<PrefetchQueue>,
+    closed: AtomicBool,
+}
+
+impl ScaledPages {
+    fn cached(&self, index: usize, bounds: Bounds) -> Option<(Arc<Vec<u8>>, &'static str)> {
+        self.cache
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.index == index && p.bounds == bounds)
+            .map(|p| (p.bytes.clone(), p.mime))
+    }
+
+    fn insert(&self, page: ScaledPage) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|p| p.index != page.index);
+        if cache.len() >= CACHE_PAGES {
+            cache.pop_front();
+        }
+        cache.push_back(page);
+    }
+
+    /// Stop prefetching and drop the cached pages; called on `close_comic`.
+    pub(super) fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.prefetch.lock().unwrap().pending = 0..0;
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+/// The size that fits `width`×`height` inside `bounds` with the aspect
+/// ratio kept, or `None` when the image already fits.
+fn fit(width: u32, height: u32, bounds: Bounds) -> Option<(u32, u32)> {
+    if width <= bounds.width && height <= bounds.height {
+        return None;
+    }
+    let scale = f64::min(
+        bounds.width as f64 / width as f64,
+        bounds.height as f64 / height as f64,
+    );
+    let w = ((width as f64 * scale).round() as u32).max(1);
+    let h = ((height as f64 * scale).round() as u32).max(1);
+    Some((w, h))
+}
+
+/// Downscale an encoded page to fit `bounds`. Opaque pages come back as
+/// JPEG and pages with transparency as PNG; anything that can't be decoded
+/// (WebP and AVIF aren't compiled in) is returned as it was.
+fn scale_page(bytes: Vec<u8>, hint_mime: &'static str, bounds: Bounds) -> (Vec<u8>, &'static str) {
+    let dimensions = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|r| r.into_dimensions().ok());
+    let Some((width, height)) = dimensions else {
+        return (bytes, hint_mime);
+    };
+    let Some((w, h)) = fit(width, height, bounds) else {
+        return (bytes, hint_mime);
+    };
+    let img = match image::load_from_memory(&bytes) {
+        Ok(img) => img,
+        Err(_) => return (bytes, hint_mime),
+    };
+    let resized = img.resize_exact(w, h, RESIZE_FILTER);
+    drop(img);
+
+    let mut out = Vec::new();
+    let encoded = if resized.color().has_alpha() {
+        let rgba = resized.to_rgba8();
+        PngEncoder::new(Cursor::new(&mut out))
+            .write_image(rgba.as_raw(), w, h, image::ExtendedColorType::Rgba8)
+            .map(|_| "image/png")
+    } else {
+        let rgb = resized.to_rgb8();
+        JpegEncoder::new_with_quality(Cursor::new(&mut out), JPEG_QUALITY)
+            .encode(rgb.as_raw(), w, h, image::ExtendedColorType::Rgb8)
+            .map(|_| "image/jpeg")
+    };
+    match encoded {
+        Ok(mime) => (out, mime),
+        Err(_) => (bytes, hint_mime),
+    }
+}
+
+fn scaled_page(
+    comic: &OpenComic,
+    index: usize,
+    bounds: Bounds,
+) -> Result<(Arc<Vec<u8>>, &'static str), String> {
+    if let Some(hit) = comic.scaled.cached(index, bounds) {
+        return Ok(hit);
+    }
+    // Read before taking the lock: on a streamed archive this can wait for
+    // the extraction, and a page that is already out shouldn't queue
+    // behind one that isn't.
+    let original = comic.page(index)?;
+    let _decoding = comic.scaled.decoding.lock().unwrap();
+    // The prefetch thread may have finished it while we waited.
+    if let Some(hit) = comic.scaled.cached(index, bounds) {
+        return Ok(hit);
+    }
+    let hint_mime = mime_for(&comic.pages[index].name);
+    let (bytes, mime) = scale_page(original, hint_mime, bounds);
+    let bytes = Arc::new(bytes);
+    comic.scaled.insert(ScaledPage {
+        index,
+        bounds,
+        bytes: bytes.clone(),
+        mime,
+    });
+    Ok((bytes, mime))
+}
+
+/// Queue pages `from..from + count` for scaling, replacing whatever was
+/// still queued, and start the worker if it isn't running.
+fn prefetch(comic: &Arc<OpenComic>, from: usize, count: usize, bounds: Bounds) {
+    let end = from.saturating_add(count).min(comic.pages.len());
+    let mut queue = comic.scaled.prefetch.lock().unwrap();
+    queue.pending = from.min(end)..end;
+    queue.bounds = Some(bounds);
+    if queue.running || queue.pending.is_empty() {
+        return;
+    }
+    queue.running = true;
+    let comic = comic.clone();
+    std::thread::spawn(move || prefetch_worker(&comic));
+}
+
+fn prefetch_worker(comic: &OpenComic) {
+    loop {
+        let (index, bounds) = {
+            let mut queue = comic.scaled.prefetch.lock().unwrap();
+            match queue.pending.next().zip(queue.bounds) {
+                Some(next) if !comic.scaled.closed.load(Ordering::Relaxed) => next,
+                _ => {
+                    queue.running = false;
+                    return;
+                }
+            }
+        };
+        if let Err(e) = scaled_page(comic, index, bounds) {
+            log::debug!("comicpage: prefetching page {index} failed: {e}");
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PageRequest {
+    id: String,
+    index: usize,
+    bounds: Bounds,
+    prefetch: usize,
+}
+
+fn parse_request(uri_path: &str, uri_query: Option<&str>) -> Option<PageRequest> {
+    let (id, index) = uri_path.trim_start_matches('/').split_once('/')?;
+    if id.is_empty() {
+        return None;
+    }
+    let index = index.parse().ok()?;
+    let (mut width, mut height, mut prefetch) = (None, None, DEFAULT_PREFETCH);
+    for pair in uri_query?.split('&') {
+        let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "w" => width = val.parse::<u32>().ok(),
+            "h" => height = val.parse::<u32>().ok(),
+            "prefetch" => {
+                prefetch = val
+                    .parse::<usize>()
+                    .map_or(DEFAULT_PREFETCH, |n| n.min(MAX_PREFETCH))
+            }
+            _ => {}
+        }
+    }
+    let edge = |v: Option<u32>| v.filter(|&v| v > 0).map(|v| v.min(MAX_EDGE));
+    Some(PageRequest {
+        id: id.to_string(),
+        index,
+        bounds: Bounds {
+            width: edge(width)?,
+            height: edge(height)?,
+        },
+        prefetch,
+    })
+}
+
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    // Decoding a page takes long enough to matter, so it gets a blocking
+    // thread rather than holding up the WebView's request thread.
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        responder.respond(build_response(&app, &request));
+    });
+}
+
+fn build_response<R: Runtime>(app: &AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let origin = cors_origin(request);
+
+    let Some(req) = parse_request(request.uri().path(), request.uri().query()) else {
+        return error(&origin, StatusCode::BAD_REQUEST);
+    };
+    let Some(comic) = app.try_state::<Comics>().and_then(|c| c.get(&req.id)) else {
+        return error(&origin, StatusCode::NOT_FOUND);
+    };
+    if req.index >= comic.pages.len() {
+        return error(&origin, StatusCode::NOT_FOUND);
+    }
+
+    let (bytes, mime) = match scaled_page(&comic, req.index, req.bounds) {
+        Ok(page) => page,
+        Err(e) => {
+            log::warn!("comicpage: page {} of {}: {e}", req.index, req.id);
+            return error(&origin, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    prefetch(&comic, req.index + 1, req.prefetch, req.bounds);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Access-Control-Allow-Origin", origin)
+        .header("Content-Type", mime)
+        .header("X-Content-Type-Options", "nosniff")
+        .header("Cache-Control", "no-store")
+        .header("Content-Length", bytes.len().to_string())
+        .body(bytes.as_ref().clone())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(img: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        img.write_to(&mut Cursor::new(&mut out), format).unwrap();
+        out
+    }
+
+    #[test]
+    fn fits_inside_the_box() {
+        let screen = Bounds {
+            width: 1080,
+            height: 1920,
+        };
+        assert_eq!(fit(4000, 6000, screen), Some((1080, 1620)));
+        assert_eq!(fit(6000, 4000, screen), Some((1080, 720)));
+        assert_eq!(fit(1000, 1500, screen), None);
+        assert_eq!(fit(100_000, 1, screen), Some((1080, 1)));
+    }
+
+    #[test]
+    fn large_pages_are_downscaled() {
+        let page = encode(
+            image::DynamicImage::ImageRgb8(image::RgbImage::new(800, 1200)),
+            image::ImageFormat::Png,
+        );
+        let bounds = Bounds {
+            width: 200,
+            height: 200,
+        };
+        let (out, mime) = scale_page(page, "image/png", bounds);
+        assert_eq!(mime, "image/jpeg");
+        let decoded = image::load_from_memory(&out).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (133, 200));
+
+        let transparent = encode(
+            image::DynamicImage::ImageRgba8(image::RgbaImage::new(400, 400)),
+            image::ImageFormat::Png,
+        );
+        assert_eq!(scale_page(transparent, "image/png", bounds).1, "image/png");
+    }
+
+    #[test]
+    fn small_and_undecodable_pages_pass_through() {
+        let page = encode(
+            image::DynamicImage::ImageRgb8(image::RgbImage::new(100, 150)),
+            image::ImageFormat::Png,
+        );
+        let bounds = Bounds {
+            width: 200,
+            height: 200,
+        };
+        assert_eq!(
+            scale_page(page.clone(), "image/png", bounds),
+            (page, "image/png")
+        );
+        let junk = b"RIFF\0\0\0\0WEBPVP8 ".to_vec();
+        assert_eq!(
+            scale_page(junk.clone(), "image/webp", bounds),
+            (junk, "image/webp")
+        );
+    }
+
+    #[test]
+    fn parses_page_urls() {
+        assert_eq!(
+            parse_request("/comic-1a-0/12", Some("w=1080&h=2340&prefetch=3")),
+            Some(PageRequest {
+                id: "comic-1a-0".into(),
+                index: 12,
+                bounds: Bounds {
+                    width: 1080,
+                    height: 2340
+                },
+                prefetch: 3,
+            })
+        );
+        let clamped = parse_request("/c/0", Some("w=99999&h=10&prefetch=50")).unwrap();
+        assert_eq!(clamped.bounds.width, MAX_EDGE);
+        assert_eq!(clamped.prefetch, MAX_PREFETCH);
+        assert_eq!(
+            parse_request("/c/0", Some("w=10&h=10")).unwrap().prefetch,
+            DEFAULT_PREFETCH
+        );
+        assert!(parse_request("/c/0", Some("w=10")).is_none());
+        assert_eq!(
+            parse_request("/c/0", Some("w=10&h=10&prefetch=x"))
+                .unwrap()
+                .prefetch,
+            DEFAULT_PREFETCH
+        );
+        assert!(parse_request("/c/0", Some("w=0&h=10")).is_none());
+        assert!(parse_request("/c/x", Some("w=10&h=10")).is_none());
+        assert!(parse_request("/c", Some("w=10&h=10")).is_none());
+        assert!(parse_request("/c/0", None).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Per-book content-security policy for scripted EPUB3 content.
+//!
+//! EPUB3 allows JavaScript, and interactive textbooks rely on it, but a book
+//! is untrusted input rendered inside the privileged app origin. Rather than
+//! a global on/off switch, each book gets a [`ContentPolicy`] that the
+//! `bookres` protocol (see `book_resource.rs`) turns into a CSP header and
+//! `<meta>` tag on every document it serves, so enforcement happens in the
+//! WebView's CSP engine and not in frontend code a book could tamper with:
+//!
+//!   - `scripts`: `script-src 'none'` unless allowed;
+//!   - `network`: `connect-src 'none'` and book-local-only images/media/fonts
+//!     unless allowed, so a book can't beacon out or pull remote payloads;
+//!   - `storage`: when scripts are allowed but storage isn't, served HTML
+//!     gets a guard prepended that replaces `localStorage`/`sessionStorage`/
+//!     `indexedDB` before any book script runs.
+//!
+//! The safe default denies all three. Overrides are stored per book hash in
+//! `content-policies.json` under the app config dir and edited through
+//! `set_book_content_policy`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+const POLICY_FILENAME: &str = "content-policies.json";
+
+/// Sources the reader itself always needs: the protocol serving the book,
+/// and blob/data URLs foliate-js creates for resources.
+const BOOK_SOURCES: &str = "'self' bookres: http://bookres.localhost blob: data:";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentPolicy {
+    pub scripts: bool,
+    pub network: bool,
+    pub storage: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedContentPolicy {
+    pub policy: ContentPolicy,
+    /// True when the book has a stored override, false for the default.
+    pub is_override: bool,
+    /// The `Content-Security-Policy` header the protocol attaches.
+    pub csp: String,
+}
+
+/// Build the `Content-Security-Policy` header value for `policy`.
+pub fn csp_for_policy(policy: &ContentPolicy) -> String {
+    let remote = if policy.network { " https:" } else { "" };
+    let mut directives = vec![
+        format!("default-src {BOOK_SOURCES}"),
+        format!("style-src {BOOK_SOURCES} 'unsafe-inline'{remote}"),
+        format!("img-src {BOOK_SOURCES}{remote}"),
+        format!("media-src {BOOK_SOURCES}{remote}"),
+        format!("font-src {BOOK_SOURCES}{remote}"),
+        "object-src 'none'".to_string(),
+        "form-action 'none'".to_string(),
+        "base-uri 'self'".to_string(),
+    ];
+    directives.push(if policy.scripts {
+        format!("script-src {BOOK_SOURCES} 'unsafe-inline'")
+    } else {
+        "script-src 'none'".to_string()
+    });
+    directives.push(if policy.network {
+        "connect-src 'self' https:".to_string()
+    } else {
+        "connect-src 'none'".to_string()
+    });
+    directives.join("; ")
+}
+
+/// Inline script prepended to HTML documents when scripts may run but
+/// storage is denied. Runs before any book script because it is injected
+/// ahead of the document's own `<head>` content.
+pub const STORAGE_GUARD_SCRIPT: &str = "<script>(function(){var d=function(){throw new DOMException('Storage is disabled for this book','SecurityError')};['localStorage','sessionStorage','indexedDB'].forEach(function(k){try{Object.defineProperty(window,k,{get:d,configurable:false})}catch(e){}});try{Object.defineProperty(document,'cookie',{get:function(){return''},set:function(){},configurable:false})}catch(e){}})();</script>";
+
+/// Whether a served document needs [`STORAGE_GUARD_SCRIPT`].
+pub fn needs_storage_guard(policy: &ContentPolicy) -> bool {
+    policy.scripts && !policy.storage
+}
+
+/// Per-book overrides, keyed by book hash, persisted as JSON.
+pub struct ContentPolicyStore {
+    path: Option<PathBuf>,
+    overrides: Mutex<HashMap<String, ContentPolicy>>,
+}
+
+impl ContentPolicyStore {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> Self {
+        let path = app
+            .path()
+            .app_config_dir()
+            .ok()
+            .map(|dir| dir.join(POLICY_FILENAME));
+        let overrides = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            overrides: Mutex::new(overrides),
+        }
+    }
+
+    pub fn get(&self, book_hash: &str) -> Option<ContentPolicy> {
+        self.overrides.lock().unwrap().get(book_hash).copied()
+    }
+
+    pub fn resolve(&self, book_hash: Option<&str>) -> ContentPolicy {
+        book_hash.and_then(|h| self.get(h)).unwrap_or_default()
+    }
+
+    fn set(&self, book_hash: &str, policy: Option<ContentPolicy>) -> Result<(), String> {
+        let mut overrides = self.overrides.lock().unwrap();
+        match policy {
+            Some(policy) if policy != ContentPolicy::default() => {
+                overrides.insert(book_hash.to_string(), policy);
+            }
+            _ => {
+                overrides.remove(book_hash);
+            }
+        }
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("create config dir: {e}"))?;
+        }
+        let json = serde_json::to_string_pretty(&*overrides).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("write {POLICY_FILENAME}: {e}"))
+    }
+}
+
+#[tauri::command]
+pub fn get_book_content_policy(
+    store: State<'_, ContentPolicyStore>,
+    book_hash: String,
+) -> ResolvedContentPolicy {
+    let stored = store.get(&book_hash);
+    let policy = stored.unwrap_or_default();
+    ResolvedContentPolicy {
+        policy,
+        is_override: stored.is_some(),
+        csp: csp_for_policy(&policy),
+    }
+}
+
+/// Store a per-book override; `None` (or the default policy) resets the book
+/// to the safe default.
+#[tauri::command]
+pub fn set_book_content_policy(
+    store: State<'_, ContentPolicyStore>,
+    book_hash: String,
+    policy: Option<ContentPolicy>,
+) -> Result<(), String> {
+    store.set(&book_hash, policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_denies_everything() {
+        let csp = csp_for_policy(&ContentPolicy::default());
+        assert!(csp.contains("script-src 'none'"));
+        assert!(csp.contains("connect-src 'none'"));
+        assert!(csp.contains("object-src 'none'"));
+        assert!(!csp.contains("https:"));
+        assert!(!needs_storage_guard(&ContentPolicy::default()));
+    }
+
+    #[test]
+    fn scripts_without_network_stay_book_local() {
+        let policy = ContentPolicy {
+            scripts: true,
+            ..Default::default()
+        };
+        let csp = csp_for_policy(&policy);
+        assert!(csp.contains("script-src 'self' bookres:"));
+        assert!(csp.contains("connect-src 'none'"));
+        assert!(needs_storage_guard(&policy));
+    }
+
+    #[test]
+    fn network_allows_remote_https_only() {
+        let policy = ContentPolicy {
+            network: true,
+            ..Default::default()
+        };
+        let csp = csp_for_policy(&policy);
+        assert!(csp.contains("connect-src 'self' https:"));
+        assert!(csp.contains("img-src 'self' bookres: http://bookres.localhost blob: data: https:"));
+        assert!(csp.contains("script-src 'none'"));
+    }
+
+    #[test]
+    fn storage_guard_only_when_scripts_allowed_and_storage_denied() {
+        let all = ContentPolicy {
+            scripts: true,
+            network: false,
+            storage: true,
+        };
+        assert!(!needs_storage_guard(&all));
+    }
+}
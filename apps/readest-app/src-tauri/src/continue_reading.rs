@@ -0,0 +1,151 @@
+//! The book on the Android "Continue reading" home-screen widget.
+//!
+//! The webview reports the most recently read book through
+//! `update_continue_reading` whenever the library changes, which includes
+//! every saved reading position. Progress is reported in whole percent, so
+//! most page turns change nothing; only reports that differ from the last one
+//! reach the widget, through the native bridge's
+//! `update_continue_reading_widget`. Elsewhere reports are tracked and
+//! dropped.
+
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use tauri::{AppHandle, State};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueReadingBook {
+    hash: String,
+    title: String,
+    author: String,
+    /// Percent read, 0 to 100.
+    percent: u8,
+    /// Absolute path of the cover image, which may not exist.
+    cover_path: String,
+}
+
+/// What the widget shows: the book, or a hint when nothing is being read.
+#[derive(Debug, Clone, PartialEq)]
+struct Shown {
+    book: Option<ContinueReadingBook>,
+    empty_title: String,
+}
+
+#[derive(Default)]
+pub struct ContinueReading {
+    shown: Mutex<Option<Shown>>,
+}
+
+impl ContinueReading {
+    /// Record `next` and return whether it differs from what is shown.
+    fn replace(&self, next: Shown) -> bool {
+        let mut shown = self.shown.lock().unwrap();
+        if shown.as_ref() == Some(&next) {
+            return false;
+        }
+        *shown = Some(next);
+        true
+    }
+
+    /// Forget what is shown, so the next report goes through.
+    fn forget(&self) {
+        *self.shown.lock().unwrap() = None;
+    }
+}
+
+#[cfg(target_os = "android")]
+fn publish(app: &AppHandle, shown: Shown) -> Result<(), String> {
+    use tauri_plugin_native_bridge::{
+        NativeBridgeExt, ReadingWidgetBook, UpdateContinueReadingWidgetRequest,
+    };
+
+    let book = shown.book.map(|book| ReadingWidgetBook {
+        hash: book.hash,
+        title: book.title,
+        author: book.author,
+        percent: book.percent.min(100),
+        cover_path: book.cover_path,
+    });
+    app.native_bridge()
+        .update_continue_reading_widget(UpdateContinueReadingWidgetRequest {
+            book,
+            empty_title: shown.empty_title,
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "android"))]
+fn publish(_app: &AppHandle, _shown: Shown) -> Result<(), String> {
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_continue_reading(
+    app: AppHandle,
+    state: State<'_, ContinueReading>,
+    book: Option<ContinueReadingBook>,
+    empty_title: String,
+) -> Result<(), String> {
+    let shown = Shown { book, empty_title };
+    if !state.replace(shown.clone()) {
+        return Ok(());
+    }
+    publish(&app, shown).inspect_err(|_| state.forget())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(percent: u8) -> Shown {
+        Shown {
+            book: Some(ContinueReadingBook {
+                hash: "h1".into(),
+                title: "Title".into(),
+                author: "Author".into(),
+                percent,
+                cover_path: "/books/h1/cover.png".into(),
+            }),
+            empty_title: "Your books will appear here".into(),
+        }
+    }
+
+    #[test]
+    fn skips_unchanged_reports() {
+        let state = ContinueReading::default();
+        assert!(state.replace(shown(10)));
+        assert!(!state.replace(shown(10)));
+        assert!(state.replace(shown(11)));
+    }
+
+    #[test]
+    fn clearing_the_book_is_a_change() {
+        let state = ContinueReading::default();
+        assert!(state.replace(shown(10)));
+        let empty = Shown {
+            book: None,
+            ..shown(10)
+        };
+        assert!(state.replace(empty.clone()));
+        assert!(!state.replace(empty));
+    }
+
+    #[test]
+    fn reports_again_after_forgetting() {
+        let state = ContinueReading::default();
+        assert!(state.replace(shown(10)));
+        state.forget();
+        assert!(state.replace(shown(10)));
+    }
+
+    #[test]
+    fn deserializes_camel_case_book() {
+        let book: ContinueReadingBook = serde_json::from_str(
+            r#"{"hash":"h1","title":"T","author":"A","percent":42,"coverPath":"/c.png"}"#,
+        )
+        .unwrap();
+        assert_eq!(book.percent, 42);
+        assert_eq!(book.cover_path, "/c.png");
+    }
+}
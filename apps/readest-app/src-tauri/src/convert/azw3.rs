@@ -0,0 +1,582 @@
+// Writing a `Book` as a KF8 (AZW3) file.
+//
+// Each content document becomes one skeleton (everything but the body's
+// content, with an `aid` on `<body>`) followed in the text flow by one
+// fragment (the body's content); stylesheets become extra flows. Paths
+// turn into `kindle:` references: images into `kindle:embed:`, stylesheet
+// links into `kindle:flow:` and internal links into
+// `kindle:pos:fid:XXXX:off:YYYYYYYYYY`. Link offsets are only known once
+// every fragment is final, so links are written as fixed-width
+// placeholders first and patched in place afterwards.
+
+use std::collections::HashMap;
+
+use super::book::{resolve, sniff_image, Book};
+use super::indx::{build_index, CncxBuilder, TagDef};
+use super::kindle::{encode_base32, rewrite_css_urls, NULL_INDEX};
+use super::palmdb::{palmdoc_compress, write_records, COMPRESSION_PALMDOC, RECORD_SIZE};
+use super::xhtml::{body_bounds, id_offsets, rewrite_attrs};
+use super::Progress;
+
+const MOBI_HEADER_LEN: usize = 264;
+const POS_PREFIX: &str = "kindle:pos:fid:";
+
+const SKEL_TAGX: [TagDef; 3] = [
+    TagDef::new(1, 1, 0x03),
+    TagDef::new(6, 2, 0x0c),
+    TagDef::END,
+];
+const FRAG_TAGX: [TagDef; 5] = [
+    TagDef::new(2, 1, 0x01),
+    TagDef::new(3, 1, 0x02),
+    TagDef::new(4, 1, 0x04),
+    TagDef::new(6, 2, 0x08),
+    TagDef::END,
+];
+const NCX_TAGX: [TagDef; 9] = [
+    TagDef::new(1, 1, 0x01),
+    TagDef::new(2, 1, 0x02),
+    TagDef::new(3, 1, 0x04),
+    TagDef::new(4, 1, 0x08),
+    TagDef::new(21, 1, 0x10),
+    TagDef::new(22, 1, 0x20),
+    TagDef::new(23, 1, 0x40),
+    TagDef::new(6, 2, 0x80),
+    TagDef::END,
+];
+
+const FLIS: &[u8] = b"FLIS\0\0\0\x08\0\x41\0\0\0\0\0\0\xff\xff\xff\xff\0\x01\0\x03\0\0\0\x03\0\0\0\x01\xff\xff\xff\xff";
+const EOF: &[u8] = b"\xe9\x8e\r\n";
+
+fn fcis_record(text_length: usize) -> Vec<u8> {
+    let mut record = b"FCIS\0\0\0\x14\0\0\0\x10\0\0\0\x02\0\0\0\0".to_vec();
+    record.extend_from_slice(&(text_length as u32).to_be_bytes());
+    record.extend_from_slice(b"\0\0\0\0\0\0\0\x28\0\0\0\0\0\0\0\x28\0\0\0\x08\0\x01\0\x01\0\0\0\0");
+    record
+}
+
+/// One document split for the text flow.
+struct Chunk {
+    skeleton: String,
+    /// Where the fragment goes back into the skeleton.
+    insert_at: usize,
+    fragment: String,
+}
+
+fn placeholder(fid: usize, target: usize) -> String {
+    format!(
+        "{POS_PREFIX}{}:off:{}",
+        encode_base32(fid as u32, 4),
+        encode_base32(target as u32, 10)
+    )
+}
+
+/// Replace the target numbers in `placeholder`s with real offsets.
+fn patch_positions(text: &mut String, offsets: &[u32]) {
+    let mut from = 0;
+    while let Some(n) = text[from..].find(POS_PREFIX) {
+        let start = from + n + POS_PREFIX.len() + 4 + ":off:".len();
+        from = start;
+        let Some(digits) = text.get(start..start + 10) else {
+            break;
+        };
+        let Some(target) = super::kindle::decode_base32(digits) else {
+            continue;
+        };
+        let offset = offsets.get(target as usize).copied().unwrap_or(0);
+        text.replace_range(start..start + 10, &encode_base32(offset, 10));
+    }
+}
+
+fn split_document(html: &str, aid: usize) -> Chunk {
+    let aid_attr = format!(" aid=\"{}\"", encode_base32(aid as u32, 4));
+    match body_bounds(html) {
+        Some((open, close)) => {
+            let open_end = open.1 - 1;
+            let open_end = html[..open_end].trim_end_matches('/').len();
+            let mut skeleton = String::with_capacity(open.1 + html.len() - close.0 + 16);
+            skeleton.push_str(&html[..open_end]);
+            skeleton.push_str(&aid_attr);
+            skeleton.push('>');
+            let insert_at = skeleton.len();
+            skeleton.push_str(&html[close.0..]);
+            Chunk {
+                skeleton,
+                insert_at,
+                fragment: html[open.1..close.0].to_string(),
+            }
+        }
+        None => {
+            let head = format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                 <html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title></title></head><body{aid_attr}>"
+            );
+            Chunk {
+                insert_at: head.len(),
+                skeleton: format!("{head}</body></html>"),
+                fragment: html.to_string(),
+            }
+        }
+    }
+}
+
+/// Split `text` into PalmDOC records. Each carries the bytes of a UTF-8
+/// sequence it cuts off, plus their count, as a multibyte trailing entry.
+fn text_records(text: &[u8], progress: &mut Progress) -> Result<Vec<Vec<u8>>, String> {
+    let mut records = Vec::with_capacity(text.len() / RECORD_SIZE + 1);
+    let count = text.len().div_ceil(RECORD_SIZE);
+    for (i, chunk) in text.chunks(RECORD_SIZE).enumerate() {
+        let mut record = palmdoc_compress(chunk);
+        let end = (i + 1) * RECORD_SIZE;
+        let overlap: Vec<u8> = text
+            .get(end..)
+            .unwrap_or_default()
+            .iter()
+            .take(3)
+            .take_while(|&&b| b & 0xc0 == 0x80)
+            .copied()
+            .collect();
+        record.extend_from_slice(&overlap);
+        record.push(overlap.len() as u8);
+        records.push(record);
+        if i % 64 == 0 {
+            progress.report(0.2 + 0.5 * i as f32 / count.max(1) as f32)?;
+        }
+    }
+    Ok(records)
+}
+
+/// TOC entries in level order, as the NCX index stores them: the
+/// permutation, plus each entry's parent and level (by original position).
+fn level_order(depths: &[u32]) -> (Vec<usize>, Vec<Option<usize>>, Vec<u32>) {
+    let mut parents = Vec::with_capacity(depths.len());
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, &depth) in depths.iter().enumerate() {
+        while let Some(&top) = stack.last() {
+            if depths[top] < depth {
+                break;
+            }
+            stack.pop();
+        }
+        parents.push(stack.last().copied());
+        stack.push(i);
+    }
+    let mut levels: Vec<u32> = Vec::with_capacity(depths.len());
+    for parent in &parents {
+        levels.push(parent.map_or(0, |p| levels[p] + 1));
+    }
+    let mut order: Vec<usize> = (0..depths.len()).collect();
+    order.sort_by_key(|&i| levels[i]);
+    (order, parents, levels)
+}
+
+struct Exth(Vec<(u32, Vec<u8>)>);
+
+impl Exth {
+    fn text(&mut self, kind: u32, value: &str) {
+        if !value.trim().is_empty() {
+            self.0.push((kind, value.trim().as_bytes().to_vec()));
+        }
+    }
+
+    fn number(&mut self, kind: u32, value: u32) {
+        self.0.push((kind, value.to_be_bytes().to_vec()));
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (kind, data) in &self.0 {
+            body.extend_from_slice(&kind.to_be_bytes());
+            body.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+            body.extend_from_slice(data);
+        }
+        let padding = (4 - body.len() % 4) % 4;
+        let mut out = b"EXTH".to_vec();
+        out.extend_from_slice(&(body.len() as u32 + 12).to_be_bytes());
+        out.extend_from_slice(&(self.0.len() as u32).to_be_bytes());
+        out.extend(body);
+        out.extend(std::iter::repeat(0).take(padding));
+        out
+    }
+}
+
+/// Record numbers record 0 points at.
+struct Layout {
+    text_length: usize,
+    text_records: usize,
+    first_image: u32,
+    images: u32,
+    cover: Option<u32>,
+    skel: u32,
+    frag: u32,
+    ncx: u32,
+    fdst: u32,
+    flows: u32,
+    flis: u32,
+    fcis: u32,
+}
+
+fn record0(book: &Book, layout: &Layout) -> Vec<u8> {
+    let metadata = &book.metadata;
+    let mut exth = Exth(Vec::new());
+    for author in &metadata.authors {
+        exth.text(100, author);
+    }
+    exth.text(101, metadata.publisher.as_deref().unwrap_or_default());
+    exth.text(103, metadata.description.as_deref().unwrap_or_default());
+    exth.text(104, metadata.identifier.as_deref().unwrap_or_default());
+    for subject in &metadata.subjects {
+        exth.text(105, subject);
+    }
+    exth.text(106, metadata.date.as_deref().unwrap_or_default());
+    exth.text(503, &metadata.title);
+    exth.text(524, metadata.language.as_deref().unwrap_or_default());
+    exth.text(501, "EBOK");
+    exth.number(125, layout.images);
+    if let Some(cover) = layout.cover {
+        exth.number(201, cover);
+        exth.number(202, cover);
+        exth.number(203, 0);
+    }
+    let exth = exth.encode();
+
+    let mut record = vec![0u8; 16 + MOBI_HEADER_LEN];
+    let mut put =
+        |pos: usize, value: u32| record[pos..pos + 4].copy_from_slice(&value.to_be_bytes());
+    // PalmDOC header.
+    put(0, (COMPRESSION_PALMDOC as u32) << 16);
+    put(4, layout.text_length as u32);
+    put(8, ((layout.text_records as u32) << 16) | RECORD_SIZE as u32);
+    // MOBI header; every index we don't write is NULL.
+    for pos in (40..80).step_by(4) {
+        put(pos, NULL_INDEX);
+    }
+    put(20, MOBI_HEADER_LEN as u32);
+    put(24, 2);
+    put(28, 65001);
+    put(32, rand::random());
+    put(36, 8);
+    put(80, layout.text_records as u32 + 1);
+    let name_offset = 16 + MOBI_HEADER_LEN + exth.len();
+    put(84, name_offset as u32);
+    put(88, metadata.title.len() as u32);
+    put(104, 8);
+    put(
+        108,
+        if layout.images > 0 {
+            layout.first_image
+        } else {
+            NULL_INDEX
+        },
+    );
+    put(128, 0x50);
+    put(164, NULL_INDEX);
+    put(192, layout.fdst);
+    put(196, layout.flows);
+    put(200, layout.fcis);
+    put(204, 1);
+    put(208, layout.flis);
+    put(212, 1);
+    put(224, NULL_INDEX);
+    put(232, NULL_INDEX);
+    put(236, NULL_INDEX);
+    put(240, 1); // extra data flags at 242: multibyte trailing entries
+    put(244, layout.ncx);
+    put(248, layout.frag);
+    put(252, layout.skel);
+    put(256, NULL_INDEX);
+    put(260, NULL_INDEX);
+    record[16..20].copy_from_slice(b"MOBI");
+    record.extend(exth);
+    record.extend_from_slice(metadata.title.as_bytes());
+    record.extend_from_slice(&[0, 0]);
+    while record.len() % 4 != 0 {
+        record.push(0);
+    }
+    record
+}
+
+/// Write `book` as an AZW3 file.
+pub fn write_azw3(book: &Book, progress: &mut Progress) -> Result<Vec<u8>, String> {
+    // Only formats Kindles display become image records.
+    let images: Vec<(usize, &'static str)> = book
+        .images
+        .iter()
+        .enumerate()
+        .filter_map(|(i, image)| sniff_image(&image.data).map(|(mime, _)| (i, mime)))
+        .collect();
+    let embed = |path: &str| -> Option<String> {
+        let index = book.image(path)?;
+        let n = images.iter().position(|&(i, _)| i == index)?;
+        Some(format!(
+            "kindle:embed:{}?mime={}",
+            encode_base32(n as u32 + 1, 4),
+            images[n].1
+        ))
+    };
+
+    // Split and rewrite the documents, collecting link targets.
+    let mut targets: Vec<(usize, Option<String>)> = Vec::new();
+    let mut target_of = |doc: usize, id: Option<String>| {
+        let key = (doc, id);
+        match targets.iter().position(|t| *t == key) {
+            Some(n) => n,
+            None => {
+                targets.push(key);
+                targets.len() - 1
+            }
+        }
+    };
+    let mut chunks = Vec::with_capacity(book.documents.len());
+    for (n, doc) in book.documents.iter().enumerate() {
+        let html = doc.text();
+        let html = rewrite_attrs(&html, |tag, attr, value| {
+            if !matches!(attr, "src" | "href" | "xlink:href") {
+                return None;
+            }
+            let (path, fragment) = resolve(&doc.path, &value.replace("&amp;", "&"))?;
+            if let Some(embed) = embed(&path) {
+                return Some(embed);
+            }
+            if let Some(style) = book.style(&path).filter(|_| tag == "link") {
+                return Some(format!(
+                    "kindle:flow:{}?mime=text/css",
+                    encode_base32(style as u32 + 1, 4)
+                ));
+            }
+            let target = book.document(&path)?;
+            Some(placeholder(target, target_of(target, fragment)))
+        });
+        let html = rewrite_css_urls(&html, |url| embed(&resolve(&doc.path, url)?.0));
+        chunks.push(split_document(&html, n));
+    }
+    progress.report(0.1)?;
+
+    // Work out where each link lands and patch the placeholders.
+    let fragment_ids: Vec<HashMap<String, usize>> =
+        chunks.iter().map(|c| id_offsets(&c.fragment)).collect();
+    let offsets: Vec<u32> = targets
+        .iter()
+        .map(|(doc, id)| {
+            id.as_ref()
+                .and_then(|id| fragment_ids[*doc].get(id))
+                .copied()
+                .unwrap_or(0) as u32
+        })
+        .collect();
+    for chunk in &mut chunks {
+        patch_positions(&mut chunk.fragment, &offsets);
+        patch_positions(&mut chunk.skeleton, &offsets);
+    }
+
+    // The text flow, then one flow per stylesheet.
+    let mut text = Vec::new();
+    let mut skel_entries = Vec::new();
+    let mut frag_entries = Vec::new();
+    let mut frag_starts = Vec::new();
+    let mut frag_cncx = CncxBuilder::default();
+    for (n, chunk) in chunks.iter().enumerate() {
+        let skel_pos = text.len();
+        text.extend_from_slice(chunk.skeleton.as_bytes());
+        let frag_pos = text.len();
+        text.extend_from_slice(chunk.fragment.as_bytes());
+        skel_entries.push((
+            format!("SKEL{n:010}"),
+            vec![
+                (1, vec![1]),
+                (6, vec![skel_pos as u32, chunk.skeleton.len() as u32]),
+            ],
+        ));
+        let selector = frag_cncx.add(&format!("P-//*[@aid='{}']", encode_base32(n as u32, 4)));
+        let insert_pos = skel_pos + chunk.insert_at;
+        frag_entries.push((
+            format!("{insert_pos:010}"),
+            vec![
+                (2, vec![selector]),
+                (3, vec![n as u32]),
+                (4, vec![n as u32]),
+                (6, vec![chunk.insert_at as u32, chunk.fragment.len() as u32]),
+            ],
+        ));
+        frag_starts.push(frag_pos);
+    }
+    let mut flows = vec![(0, text.len())];
+    for style in &book.styles {
+        let css = rewrite_css_urls(&style.text(), |url| embed(&resolve(&style.path, url)?.0));
+        let start = text.len();
+        text.extend_from_slice(css.as_bytes());
+        flows.push((start, text.len()));
+    }
+
+    // The NCX, in level order with parent and child links.
+    let mut ncx_cncx = CncxBuilder::default();
+    let toc: Vec<(u32, u32, u32, u32)> = book
+        .toc
+        .iter()
+        .filter_map(|entry| {
+            let (path, fragment) = resolve("", &entry.href)?;
+            let doc = book.document(&path)?;
+            let off = fragment
+                .and_then(|id| fragment_ids[doc].get(&id).copied())
+                .unwrap_or(0);
+            let label = ncx_cncx.add(&entry.label);
+            Some((label, entry.depth, doc as u32, off as u32))
+        })
+        .collect();
+    let depths: Vec<u32> = toc.iter().map(|t| t.1).collect();
+    let (order, parents, levels) = level_order(&depths);
+    let mut position = vec![0; order.len()];
+    for (new, &old) in order.iter().enumerate() {
+        position[old] = new;
+    }
+    let text_offset = |i: usize| frag_starts[toc[i].2 as usize] + toc[i].3 as usize;
+    let ncx_entries: Vec<(String, Vec<(u8, Vec<u32>)>)> = order
+        .iter()
+        .enumerate()
+        .map(|(new, &old)| {
+            let (label, _, fid, off) = toc[old];
+            let offset = text_offset(old);
+            let next = (old + 1..toc.len())
+                .map(text_offset)
+                .find(|&o| o > offset)
+                .unwrap_or(flows[0].1);
+            let mut values = vec![
+                (1, vec![offset as u32]),
+                (2, vec![next.saturating_sub(offset).max(1) as u32]),
+                (3, vec![label]),
+                (4, vec![levels[old]]),
+            ];
+            if let Some(parent) = parents[old] {
+                values.push((21, vec![position[parent] as u32]));
+            }
+            let children: Vec<usize> = (0..toc.len())
+                .filter(|&c| parents[c] == Some(old))
+                .map(|c| position[c])
+                .collect();
+            if let (Some(first), Some(last)) = (children.iter().min(), children.iter().max()) {
+                values.push((22, vec![*first as u32]));
+                values.push((23, vec![*last as u32]));
+            }
+            values.push((6, vec![fid, off]));
+            (format!("{new:03X}"), values)
+        })
+        .collect();
+    progress.report(0.2)?;
+
+    let text_records = text_records(&text, progress)?;
+    let mut records = vec![Vec::new()];
+    records.extend(text_records);
+    let text_record_count = records.len() - 1;
+    let first_image = records.len() as u32;
+    for &(i, _) in &images {
+        records.push(book.images[i].data.clone());
+    }
+    let cover = book
+        .cover
+        .as_deref()
+        .and_then(|path| book.image(path))
+        .and_then(|index| images.iter().position(|&(i, _)| i == index))
+        .map(|n| n as u32);
+
+    let skel = records.len() as u32;
+    records.extend(build_index(
+        &SKEL_TAGX,
+        &skel_entries,
+        CncxBuilder::default(),
+    ));
+    let frag = records.len() as u32;
+    records.extend(build_index(&FRAG_TAGX, &frag_entries, frag_cncx));
+    let ncx = if ncx_entries.is_empty() {
+        NULL_INDEX
+    } else {
+        let ncx = records.len() as u32;
+        records.extend(build_index(&NCX_TAGX, &ncx_entries, ncx_cncx));
+        ncx
+    };
+    let fdst = records.len() as u32;
+    let mut fdst_record = b"FDST".to_vec();
+    fdst_record.extend_from_slice(&12u32.to_be_bytes());
+    fdst_record.extend_from_slice(&(flows.len() as u32).to_be_bytes());
+    for &(start, end) in &flows {
+        fdst_record.extend_from_slice(&(start as u32).to_be_bytes());
+        fdst_record.extend_from_slice(&(end as u32).to_be_bytes());
+    }
+    records.push(fdst_record);
+    let flis = records.len() as u32;
+    records.push(FLIS.to_vec());
+    let fcis = records.len() as u32;
+    records.push(fcis_record(text.len()));
+    records.push(EOF.to_vec());
+
+    let layout = Layout {
+        text_length: text.len(),
+        text_records: text_record_count,
+        first_image,
+        images: images.len() as u32,
+        cover,
+        skel,
+        frag,
+        ncx,
+        fdst,
+        flows: flows.len() as u32,
+        flis,
+        fcis,
+    };
+    records[0] = record0(book, &layout);
+    progress.report(0.95)?;
+    Ok(write_records(&book.metadata.title, &records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_split_at_the_body() {
+        let chunk = split_document(
+            "<html><head><title>T</title></head><body class=\"x\"><p id=\"a\">Hi</p></body></html>",
+            33,
+        );
+        assert_eq!(
+            chunk.skeleton,
+            "<html><head><title>T</title></head><body class=\"x\" aid=\"0011\"></body></html>"
+        );
+        assert_eq!(&chunk.skeleton[chunk.insert_at..], "</body></html>");
+        assert_eq!(chunk.fragment, "<p id=\"a\">Hi</p>");
+    }
+
+    #[test]
+    fn placeholders_are_patched_in_place() {
+        let mut html = format!(
+            "<a href=\"{}\">x</a><a href=\"{}\">y</a>",
+            placeholder(2, 0),
+            placeholder(0, 1)
+        );
+        let len = html.len();
+        patch_positions(&mut html, &[40, 1234]);
+        assert_eq!(html.len(), len);
+        assert!(html.contains("kindle:pos:fid:0002:off:0000000018"));
+        assert!(html.contains("kindle:pos:fid:0000:off:000000016I"));
+    }
+
+    #[test]
+    fn toc_levels_come_first() {
+        // A, A.1, A.1.a, B, B.1
+        let (order, parents, levels) = level_order(&[0, 1, 2, 0, 1]);
+        assert_eq!(order, vec![0, 3, 1, 4, 2]);
+        assert_eq!(parents, vec![None, Some(0), Some(1), None, Some(3)]);
+        assert_eq!(levels, vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn text_records_carry_split_characters() {
+        let mut text = vec![b'a'; RECORD_SIZE - 1];
+        text.extend_from_slice("é and more".as_bytes());
+        let mut progress = Progress::detached();
+        let records = text_records(&text, &mut progress).unwrap();
+        assert_eq!(records.len(), 2);
+        // The second byte of "é" opens the next record.
+        assert_eq!(&records[0][records[0].len() - 2..], &[0xa9, 1]);
+        assert_eq!(*records[1].last().unwrap(), 0);
+    }
+}
@@ -0,0 +1,200 @@
+// The in-memory book every converter reads into and writes out of.
+//
+// Paths are relative to the package root (the OPF's directory for an
+// EPUB) and use `/`. Links inside a document are relative to that
+// document, as they are on disk, so `resolve` / `relative` translate
+// between the two.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    /// ISBN, ASIN or URN; a UUID is made up when there's none.
+    pub identifier: Option<String>,
+    pub subjects: Vec<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub path: String,
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Resource {
+    pub fn new(path: impl Into<String>, media_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            path: path.into(),
+            media_type: media_type.to_string(),
+            data,
+        }
+    }
+
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    pub label: String,
+    /// Package-relative, with an optional `#fragment`.
+    pub href: String,
+    /// 0 for top-level entries.
+    pub depth: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    pub metadata: Metadata,
+    /// XHTML content documents in reading order.
+    pub documents: Vec<Resource>,
+    pub styles: Vec<Resource>,
+    pub images: Vec<Resource>,
+    pub toc: Vec<TocEntry>,
+    /// Path of the cover image, one of `images`.
+    pub cover: Option<String>,
+}
+
+impl Book {
+    pub fn image(&self, path: &str) -> Option<usize> {
+        self.images.iter().position(|r| r.path == path)
+    }
+
+    pub fn style(&self, path: &str) -> Option<usize> {
+        self.styles.iter().position(|r| r.path == path)
+    }
+
+    pub fn document(&self, path: &str) -> Option<usize> {
+        self.documents.iter().position(|r| r.path == path)
+    }
+}
+
+/// Collapse `.` and `..` segments.
+pub fn normalize(path: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out.join("/")
+}
+
+/// The package path `href` (relative to the document at `base`) points
+/// to, and its fragment. `None` for external links.
+pub fn resolve(base: &str, href: &str) -> Option<(String, Option<String>)> {
+    if href.contains("://") || href.starts_with("mailto:") || href.starts_with("data:") {
+        return None;
+    }
+    let (path, fragment) = match href.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment.to_string())),
+        None => (href, None),
+    };
+    let path = path.split('?').next().unwrap_or(path);
+    let path = percent_encoding::percent_decode_str(path)
+        .decode_utf8_lossy()
+        .into_owned();
+    if path.is_empty() {
+        return Some((base.to_string(), fragment));
+    }
+    let dir = base.rfind('/').map_or("", |i| &base[..i]);
+    let joined = if dir.is_empty() || path.starts_with('/') {
+        path
+    } else {
+        format!("{dir}/{path}")
+    };
+    Some((normalize(&joined), fragment))
+}
+
+/// The href from the document at `from` to the package path `to`.
+pub fn relative(from: &str, to: &str) -> String {
+    let from_dir: Vec<&str> = from.split('/').collect();
+    let from_dir = &from_dir[..from_dir.len() - 1];
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len() - 1);
+    let mut parts: Vec<&str> = vec![".."; from_dir.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+pub fn media_type_for(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "xhtml" | "html" | "htm" => "application/xhtml+xml",
+        "css" => "text/css",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ncx" => "application/x-dtbncx+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Image type from magic bytes, for records that carry no name.
+pub fn sniff_image(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some(("image/jpeg", "jpg"))
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if data.starts_with(b"BM") && data.len() > 14 {
+        Some(("image/bmp", "bmp"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hrefs_resolve_against_their_document() {
+        assert_eq!(
+            resolve("text/ch1.xhtml", "../images/a%20b.jpg"),
+            Some(("images/a b.jpg".to_string(), None))
+        );
+        assert_eq!(
+            resolve("text/ch1.xhtml", "ch2.xhtml#note-3"),
+            Some(("text/ch2.xhtml".to_string(), Some("note-3".to_string())))
+        );
+        assert_eq!(
+            resolve("text/ch1.xhtml", "#top"),
+            Some(("text/ch1.xhtml".to_string(), Some("top".to_string())))
+        );
+        assert_eq!(resolve("ch1.xhtml", "https://example.com/"), None);
+    }
+
+    #[test]
+    fn relative_hrefs_round_trip() {
+        assert_eq!(
+            relative("text/ch1.xhtml", "images/c.jpg"),
+            "../images/c.jpg"
+        );
+        assert_eq!(relative("text/ch1.xhtml", "text/ch2.xhtml"), "ch2.xhtml");
+        assert_eq!(relative("nav.xhtml", "text/ch2.xhtml"), "text/ch2.xhtml");
+        assert_eq!(relative("a/b/c.xhtml", "a/d.css"), "../d.css");
+        for (from, to) in [("a/b/c.xhtml", "x/y.png"), ("c.xhtml", "c.xhtml")] {
+            let href = relative(from, to);
+            assert_eq!(resolve(from, &href).unwrap().0, to);
+        }
+    }
+}
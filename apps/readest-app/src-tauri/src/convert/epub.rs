@@ -0,0 +1,631 @@
+// Reading an EPUB into a `Book` (for EPUB → AZW3) and writing a `Book`
+// out as an EPUB 3 package with an EPUB 2 NCX alongside the nav document
+// (for MOBI/AZW3 → EPUB).
+//
+// Book paths are relative to the OPF's directory, which is also how the
+// manifest addresses them, so hrefs inside documents keep working as-is.
+
+use std::io::{Cursor, Write};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::book::{media_type_for, relative, resolve, Book, Metadata, Resource, TocEntry};
+use super::xhtml::{escape, text_content, tokenize, Token};
+use super::Progress;
+use crate::epub_parser::{local_name, read_rootfile_path, read_zip_entry, strip_xml_bom};
+
+const PACKAGE_DIR: &str = "OEBPS";
+const NAV_PATH: &str = "nav.xhtml";
+const NCX_PATH: &str = "toc.ncx";
+
+#[derive(Default)]
+struct ManifestItem {
+    id: String,
+    href: String,
+    media_type: String,
+    properties: String,
+}
+
+#[derive(Default)]
+struct Package {
+    metadata: Metadata,
+    manifest: Vec<ManifestItem>,
+    spine: Vec<String>,
+    spine_toc: Option<String>,
+    cover_id: Option<String>,
+}
+
+fn attr_value(e: &quick_xml::events::BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| local_name(a.key.as_ref()) == name)
+        .map(|a| {
+            a.unescape_value()
+                .map(|v| v.into_owned())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&a.value).into_owned())
+        })
+}
+
+fn parse_opf(bytes: &[u8]) -> Result<Package, String> {
+    let bytes = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(bytes.as_ref());
+    reader.config_mut().trim_text(true);
+    let mut package = Package::default();
+    let mut buf = Vec::new();
+    let mut field: Option<Vec<u8>> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = local_name(e.name().as_ref()).to_vec();
+                match name.as_slice() {
+                    b"item" => package.manifest.push(ManifestItem {
+                        id: attr_value(&e, b"id").unwrap_or_default(),
+                        href: attr_value(&e, b"href").unwrap_or_default(),
+                        media_type: attr_value(&e, b"media-type").unwrap_or_default(),
+                        properties: attr_value(&e, b"properties").unwrap_or_default(),
+                    }),
+                    b"itemref" => package.spine.extend(attr_value(&e, b"idref")),
+                    b"spine" => package.spine_toc = attr_value(&e, b"toc"),
+                    b"meta" if attr_value(&e, b"name").as_deref() == Some("cover") => {
+                        package.cover_id = attr_value(&e, b"content");
+                    }
+                    _ => field = Some(name),
+                }
+            }
+            Ok(Event::Text(t)) => {
+                let Some(name) = field.take() else {
+                    continue;
+                };
+                let text = t
+                    .unescape()
+                    .map(|v| v.trim().to_string())
+                    .unwrap_or_default();
+                if text.is_empty() {
+                    continue;
+                }
+                let metadata = &mut package.metadata;
+                match name.as_slice() {
+                    b"title" if metadata.title.is_empty() => metadata.title = text,
+                    b"creator" => metadata.authors.push(text),
+                    b"subject" => metadata.subjects.push(text),
+                    b"publisher" => metadata.publisher = metadata.publisher.take().or(Some(text)),
+                    b"description" => {
+                        metadata.description = metadata.description.take().or(Some(text))
+                    }
+                    b"language" => metadata.language = metadata.language.take().or(Some(text)),
+                    b"identifier" => {
+                        metadata.identifier = metadata.identifier.take().or(Some(text))
+                    }
+                    b"date" => metadata.date = metadata.date.take().or(Some(text)),
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => field = None,
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("content.opf: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(package)
+}
+
+/// TOC entries from an EPUB 3 nav document's `toc` nav.
+fn nav_toc(path: &str, html: &str) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    let mut in_toc = false;
+    let mut depth = 0u32;
+    let mut link: Option<(String, usize)> = None;
+    for token in tokenize(html) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        let name = tag.name.to_ascii_lowercase();
+        match (name.as_str(), tag.closing) {
+            ("nav", false) => {
+                in_toc = tag
+                    .attr(html, "epub:type")
+                    .is_some_and(|t| t.split_whitespace().any(|t| t == "toc"));
+            }
+            ("nav", true) => in_toc = false,
+            ("ol", false) if in_toc => depth += 1,
+            ("ol", true) if in_toc => depth = depth.saturating_sub(1),
+            ("a", false) if in_toc => {
+                link = tag
+                    .attr(html, "href")
+                    .map(|href| (href.to_string(), tag.span.1));
+            }
+            ("a", true) if in_toc => {
+                let Some((href, start)) = link.take() else {
+                    continue;
+                };
+                let label = text_content(&html[start..tag.span.0]);
+                if let Some(href) = package_href(path, &href) {
+                    toc.push(TocEntry {
+                        label,
+                        href,
+                        depth: depth.saturating_sub(1),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    toc
+}
+
+/// TOC entries from an EPUB 2 NCX.
+fn ncx_toc(path: &str, xml: &str) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    let mut depth = 0u32;
+    let mut label: Option<String> = None;
+    let mut text_start = None;
+    for token in tokenize(xml) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        match (tag.name, tag.closing) {
+            ("navPoint", false) => {
+                depth += 1;
+                label = None;
+            }
+            ("navPoint", true) => depth = depth.saturating_sub(1),
+            ("text", false) => text_start = Some(tag.span.1),
+            ("text", true) => {
+                if let Some(start) = text_start.take() {
+                    label.get_or_insert_with(|| text_content(&xml[start..tag.span.0]));
+                }
+            }
+            ("content", false) if depth > 0 => {
+                let href = tag.attr(xml, "src").and_then(|src| package_href(path, src));
+                if let (Some(href), Some(label)) = (href, label.take()) {
+                    toc.push(TocEntry {
+                        label,
+                        href,
+                        depth: depth - 1,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    toc
+}
+
+/// An href inside the document at `base`, as a package path with its
+/// fragment.
+fn package_href(base: &str, href: &str) -> Option<String> {
+    let (path, fragment) = resolve(base, &href.replace("&amp;", "&"))?;
+    Some(match fragment {
+        Some(fragment) => format!("{path}#{fragment}"),
+        None => path,
+    })
+}
+
+/// Read an EPUB.
+pub fn read_epub(data: &[u8], progress: &mut Progress) -> Result<Book, String> {
+    let mut zip = ZipArchive::new(Cursor::new(data)).map_err(|e| format!("zip: {e}"))?;
+    let opf_path = read_rootfile_path(&mut zip)?;
+    let opf_dir = opf_path
+        .rfind('/')
+        .map_or("", |i| &opf_path[..i])
+        .to_string();
+    let package = parse_opf(&read_zip_entry(&mut zip, &opf_path)?)?;
+    let mut read = |path: &str| {
+        let entry = if opf_dir.is_empty() {
+            path.to_string()
+        } else {
+            format!("{opf_dir}/{path}")
+        };
+        read_zip_entry(&mut zip, &entry)
+    };
+    let item_path = |item: &ManifestItem| resolve("package.opf", &item.href).map(|(path, _)| path);
+
+    let mut book = Book {
+        metadata: package.metadata,
+        ..Book::default()
+    };
+    for (n, idref) in package.spine.iter().enumerate() {
+        let Some(item) = package.manifest.iter().find(|i| i.id == *idref) else {
+            continue;
+        };
+        let Some(path) = item_path(item) else {
+            continue;
+        };
+        match read(&path) {
+            Ok(data) => book
+                .documents
+                .push(Resource::new(path, &item.media_type, data)),
+            Err(e) => log::warn!("Skipping spine item {path}: {e}"),
+        }
+        if n % 16 == 0 {
+            progress.report(0.3 * n as f32 / package.spine.len() as f32)?;
+        }
+    }
+    if book.documents.is_empty() {
+        return Err("the EPUB has no readable content documents".into());
+    }
+    for item in &package.manifest {
+        let Some(path) = item_path(item) else {
+            continue;
+        };
+        // Some packagers leave media types out; go by the extension then.
+        let media_type = match item.media_type.as_str() {
+            "" => media_type_for(&path),
+            media_type => media_type,
+        };
+        let is_style = media_type == "text/css";
+        if !is_style && !media_type.starts_with("image/") {
+            continue;
+        }
+        let Ok(data) = read(&path) else {
+            continue;
+        };
+        let is_cover = item
+            .properties
+            .split_whitespace()
+            .any(|p| p == "cover-image")
+            || package.cover_id.as_deref() == Some(item.id.as_str());
+        if is_cover {
+            book.cover = Some(path.clone());
+        }
+        let resource = Resource::new(path, media_type, data);
+        if is_style {
+            book.styles.push(resource);
+        } else {
+            book.images.push(resource);
+        }
+    }
+
+    let nav = package
+        .manifest
+        .iter()
+        .find(|i| i.properties.split_whitespace().any(|p| p == "nav"));
+    if let Some(path) = nav.and_then(item_path) {
+        if let Ok(data) = read(&path) {
+            book.toc = nav_toc(&path, &String::from_utf8_lossy(&data));
+        }
+    }
+    if book.toc.is_empty() {
+        let ncx = package
+            .manifest
+            .iter()
+            .find(|i| Some(&i.id) == package.spine_toc.as_ref())
+            .or_else(|| {
+                package
+                    .manifest
+                    .iter()
+                    .find(|i| i.media_type == "application/x-dtbncx+xml")
+            });
+        if let Some(path) = ncx.and_then(item_path) {
+            if let Ok(data) = read(&path) {
+                book.toc = ncx_toc(&path, &String::from_utf8_lossy(&data));
+            }
+        }
+    }
+    Ok(book)
+}
+
+fn new_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn modified_now() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = crate::sync::s3::civil_from_days((secs / 86_400) as i64);
+    let secs = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn opf(book: &Book, identifier: &str) -> String {
+    let metadata = &book.metadata;
+    let mut meta = String::new();
+    let mut dc = |name: &str, value: &str| {
+        meta.push_str(&format!("    <dc:{name}>{}</dc:{name}>\n", escape(value)));
+    };
+    dc("title", &metadata.title);
+    for author in &metadata.authors {
+        dc("creator", author);
+    }
+    dc("language", metadata.language.as_deref().unwrap_or("en"));
+    for (name, value) in [
+        ("publisher", &metadata.publisher),
+        ("description", &metadata.description),
+        ("date", &metadata.date),
+    ] {
+        if let Some(value) = value {
+            dc(name, value);
+        }
+    }
+    for subject in &metadata.subjects {
+        dc("subject", subject);
+    }
+    meta.push_str(&format!(
+        "    <meta property=\"dcterms:modified\">{}</meta>\n",
+        modified_now()
+    ));
+
+    let mut manifest = String::new();
+    manifest.push_str(&format!(
+        "    <item id=\"nav\" href=\"{NAV_PATH}\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         \x20   <item id=\"ncx\" href=\"{NCX_PATH}\" media-type=\"application/x-dtbncx+xml\"/>\n"
+    ));
+    let mut item = |id: String, resource: &Resource, properties: &str| {
+        manifest.push_str(&format!(
+            "    <item id=\"{id}\" href=\"{}\" media-type=\"{}\"{properties}/>\n",
+            escape(&resource.path),
+            escape(&resource.media_type)
+        ));
+    };
+    for (n, doc) in book.documents.iter().enumerate() {
+        item(format!("doc{n}"), doc, "");
+    }
+    for (n, style) in book.styles.iter().enumerate() {
+        item(format!("css{n}"), style, "");
+    }
+    let mut cover_id = None;
+    for (n, image) in book.images.iter().enumerate() {
+        if book.cover.as_deref() == Some(image.path.as_str()) {
+            cover_id = Some(format!("img{n}"));
+            item(format!("img{n}"), image, " properties=\"cover-image\"");
+        } else {
+            item(format!("img{n}"), image, "");
+        }
+    }
+    if let Some(id) = cover_id {
+        meta.push_str(&format!("    <meta name=\"cover\" content=\"{id}\"/>\n"));
+    }
+    let spine: String = (0..book.documents.len())
+        .map(|n| format!("    <itemref idref=\"doc{n}\"/>\n"))
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"bookid\">\n\
+         \x20 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         \x20   <dc:identifier id=\"bookid\">{}</dc:identifier>\n{meta}  </metadata>\n\
+         \x20 <manifest>\n{manifest}  </manifest>\n\
+         \x20 <spine toc=\"ncx\">\n{spine}  </spine>\n\
+         </package>\n",
+        escape(identifier)
+    )
+}
+
+/// The TOC as nested lists; entries deeper than their predecessor's child
+/// level are pulled up so the nesting stays valid.
+fn nav(book: &Book) -> String {
+    let mut body = String::new();
+    let mut open: Vec<u32> = Vec::new();
+    for entry in &book.toc {
+        let depth = entry.depth.min(open.len() as u32);
+        while open.len() as u32 > depth + 1 {
+            open.pop();
+            body.push_str("</li></ol>");
+        }
+        if open.len() as u32 == depth + 1 {
+            body.push_str("</li>");
+        } else {
+            body.push_str("<ol>");
+            open.push(depth);
+        }
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            escape(&relative(NAV_PATH, &entry.href)),
+            escape(&entry.label)
+        ));
+    }
+    for _ in open {
+        body.push_str("</li></ol>");
+    }
+    if body.is_empty() {
+        body = format!(
+            "<ol><li><a href=\"{}\">{}</a></li></ol>",
+            escape(&relative(NAV_PATH, &book.documents[0].path)),
+            escape(&book.metadata.title)
+        );
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+         <head><title>{}</title></head>\n\
+         <body><nav epub:type=\"toc\" id=\"toc\">{body}</nav></body>\n</html>\n",
+        escape(&book.metadata.title)
+    )
+}
+
+fn ncx(book: &Book, identifier: &str) -> String {
+    let mut points = String::new();
+    let mut open = 0u32;
+    for (n, entry) in book.toc.iter().enumerate() {
+        let depth = entry.depth.min(open);
+        while open > depth {
+            points.push_str("</navPoint>\n");
+            open -= 1;
+        }
+        points.push_str(&format!(
+            "<navPoint id=\"np{n}\" playOrder=\"{}\"><navLabel><text>{}</text></navLabel>\
+             <content src=\"{}\"/>\n",
+            n + 1,
+            escape(&entry.label),
+            escape(&relative(NCX_PATH, &entry.href))
+        ));
+        open += 1;
+    }
+    for _ in 0..open {
+        points.push_str("</navPoint>\n");
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+         <head><meta name=\"dtb:uid\" content=\"{}\"/></head>\n\
+         <docTitle><text>{}</text></docTitle>\n<navMap>\n{points}</navMap>\n</ncx>\n",
+        escape(identifier),
+        escape(&book.metadata.title)
+    )
+}
+
+/// Write `book` as an EPUB.
+pub fn write_epub(book: &Book, progress: &mut Progress) -> Result<Vec<u8>, String> {
+    if book.documents.is_empty() {
+        return Err("the book has no content".into());
+    }
+    let identifier = book.metadata.identifier.clone().unwrap_or_else(new_uuid);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, data: &[u8], options: SimpleFileOptions| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(data).map_err(|e| e.to_string())
+    };
+    add("mimetype", b"application/epub+zip", stored)?;
+    add(
+        "META-INF/container.xml",
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+             \x20 <rootfiles>\n\
+             \x20   <rootfile full-path=\"{PACKAGE_DIR}/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+             \x20 </rootfiles>\n\
+             </container>\n"
+        )
+        .as_bytes(),
+        deflated,
+    )?;
+    add(
+        &format!("{PACKAGE_DIR}/content.opf"),
+        opf(book, &identifier).as_bytes(),
+        deflated,
+    )?;
+    add(
+        &format!("{PACKAGE_DIR}/{NAV_PATH}"),
+        nav(book).as_bytes(),
+        deflated,
+    )?;
+    add(
+        &format!("{PACKAGE_DIR}/{NCX_PATH}"),
+        ncx(book, &identifier).as_bytes(),
+        deflated,
+    )?;
+    let resources: Vec<&Resource> = book
+        .documents
+        .iter()
+        .chain(&book.styles)
+        .chain(&book.images)
+        .collect();
+    for (n, resource) in resources.iter().enumerate() {
+        // Images are compressed already.
+        let options = if resource.media_type.starts_with("image/") {
+            stored
+        } else {
+            deflated
+        };
+        add(
+            &format!("{PACKAGE_DIR}/{}", resource.path),
+            &resource.data,
+            options,
+        )?;
+        if n % 16 == 0 {
+            progress.report(n as f32 / resources.len() as f32)?;
+        }
+    }
+    let cursor = zip.finish().map_err(|e| e.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(label: &str, href: &str, depth: u32) -> TocEntry {
+        TocEntry {
+            label: label.into(),
+            href: href.into(),
+            depth,
+        }
+    }
+
+    #[test]
+    fn nav_toc_reads_nested_lists() {
+        let html = r##"<nav epub:type="landmarks"><ol><li><a href="x.xhtml">Skip</a></li></ol></nav>
+            <nav epub:type="toc"><ol>
+              <li><a href="text/ch1.xhtml">One</a>
+                <ol><li><a href="text/ch1.xhtml#s1"><span>One</span> &amp; a half</a></li></ol>
+              </li>
+              <li><a href="text/ch2.xhtml">Two</a></li>
+            </ol></nav>"##;
+        assert_eq!(
+            nav_toc("nav.xhtml", html),
+            vec![
+                entry("One", "text/ch1.xhtml", 0),
+                entry("One & a half", "text/ch1.xhtml#s1", 1),
+                entry("Two", "text/ch2.xhtml", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn ncx_toc_reads_nav_points() {
+        let xml = r#"<ncx><navMap>
+            <navPoint id="a"><navLabel><text>One</text></navLabel><content src="Text/ch1.xhtml"/>
+              <navPoint id="b"><navLabel><text>Sub</text></navLabel><content src="Text/ch1.xhtml#x"/></navPoint>
+            </navPoint>
+            <navPoint id="c"><navLabel><text>Two</text></navLabel><content src="Text/ch2.xhtml"/></navPoint>
+            </navMap></ncx>"#;
+        assert_eq!(
+            ncx_toc("toc.ncx", xml),
+            vec![
+                entry("One", "Text/ch1.xhtml", 0),
+                entry("Sub", "Text/ch1.xhtml#x", 1),
+                entry("Two", "Text/ch2.xhtml", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn toc_nesting_survives_depth_jumps() {
+        let book = Book {
+            documents: vec![Resource::new(
+                "text/a.xhtml",
+                "application/xhtml+xml",
+                Vec::new(),
+            )],
+            toc: vec![
+                entry("A", "text/a.xhtml", 0),
+                entry("A.1.a", "text/a.xhtml#x", 2),
+                entry("B", "text/a.xhtml#y", 0),
+            ],
+            ..Book::default()
+        };
+        let nav = nav(&book);
+        assert!(nav.contains(
+            "<ol><li><a href=\"text/a.xhtml\">A</a><ol><li><a href=\"text/a.xhtml#x\">A.1.a</a>\
+             </li></ol></li><li><a href=\"text/a.xhtml#y\">B</a></li></ol>"
+        ));
+        let ncx = ncx(&book, "id");
+        assert_eq!(ncx.matches("<navPoint ").count(), 3);
+        assert_eq!(ncx.matches("</navPoint>").count(), 3);
+        assert_eq!(nav_toc(NAV_PATH, &nav).len(), 3);
+    }
+}
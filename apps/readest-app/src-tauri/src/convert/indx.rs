@@ -0,0 +1,397 @@
+// INDX tables: the indices KF8 uses to locate skeletons, fragments and
+// table-of-contents entries.
+//
+// An index is a header record (with the TAGX table describing each
+// entry's fields), one or more data records of entries, and optionally
+// CNCX records holding the strings entries refer to by offset. An entry
+// is a length-prefixed name, control bytes saying which tags are present
+// (and how many values each carries), then the values as forward varints.
+
+use std::collections::HashMap;
+
+use super::palmdb::{decode_varint, encode_varint, u16_at, u32_at};
+
+const HEADER_LEN: usize = 192;
+/// IDXT offsets are 16-bit, so data records stay well under 64 KiB.
+const MAX_RECORD_LEN: usize = 0xf000;
+
+/// One TAGX field: tag number, values per occurrence and its bit mask in
+/// the control byte. `end` marks the boundary between control bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagDef {
+    pub tag: u8,
+    pub values: u8,
+    pub mask: u8,
+    pub end: bool,
+}
+
+impl TagDef {
+    pub const fn new(tag: u8, values: u8, mask: u8) -> Self {
+        Self {
+            tag,
+            values,
+            mask,
+            end: false,
+        }
+    }
+
+    pub const END: TagDef = TagDef {
+        tag: 0,
+        values: 0,
+        mask: 0,
+        end: true,
+    };
+}
+
+pub type Tags = HashMap<u8, Vec<u32>>;
+
+#[derive(Debug, Default)]
+pub struct Index {
+    pub entries: Vec<(String, Tags)>,
+    pub cncx: HashMap<u32, String>,
+}
+
+fn parse_tagx(data: &[u8]) -> Option<(usize, Vec<TagDef>)> {
+    if !data.starts_with(b"TAGX") {
+        return None;
+    }
+    let len = u32_at(data, 4)? as usize;
+    let control_bytes = u32_at(data, 8)? as usize;
+    let tags = data
+        .get(12..len.min(data.len()))?
+        .chunks_exact(4)
+        .map(|t| TagDef {
+            tag: t[0],
+            values: t[1],
+            mask: t[2],
+            end: t[3] & 1 != 0,
+        })
+        .collect();
+    Some((control_bytes, tags))
+}
+
+fn parse_entry(data: &[u8], control_count: usize, tagx: &[TagDef]) -> Tags {
+    let mut tags = Tags::new();
+    let Some(control) = data.get(..control_count) else {
+        return tags;
+    };
+    let mut rest = &data[control_count..];
+    // Each present tag with its value count, or byte length when the
+    // entry spells that out.
+    let mut present: Vec<(u8, Result<usize, usize>)> = Vec::new();
+    let mut byte = 0;
+    for def in tagx {
+        if def.end {
+            byte += 1;
+            continue;
+        }
+        let Some(&control) = control.get(byte) else {
+            break;
+        };
+        let value = control & def.mask;
+        if value == 0 {
+            continue;
+        }
+        if value == def.mask && def.mask.count_ones() > 1 {
+            let Some((len, used)) = decode_varint(rest) else {
+                return tags;
+            };
+            rest = &rest[used..];
+            present.push((def.tag, Err(len as usize)));
+        } else {
+            let count = (value >> def.mask.trailing_zeros()) as usize;
+            present.push((def.tag, Ok(count * def.values as usize)));
+        }
+    }
+    for (tag, amount) in present {
+        let mut values = Vec::new();
+        match amount {
+            Ok(count) => {
+                for _ in 0..count {
+                    let Some((value, used)) = decode_varint(rest) else {
+                        break;
+                    };
+                    rest = &rest[used..];
+                    values.push(value);
+                }
+            }
+            Err(len) => {
+                let mut consumed = 0;
+                while consumed < len {
+                    let Some((value, used)) = decode_varint(rest) else {
+                        break;
+                    };
+                    rest = &rest[used..];
+                    consumed += used;
+                    values.push(value);
+                }
+            }
+        }
+        tags.insert(tag, values);
+    }
+    tags
+}
+
+fn parse_cncx(records: &[&[u8]]) -> HashMap<u32, String> {
+    let mut strings = HashMap::new();
+    for (n, record) in records.iter().enumerate() {
+        let mut pos = 0;
+        while pos < record.len() {
+            if record[pos] == 0 {
+                pos += 1;
+                continue;
+            }
+            let Some((len, used)) = decode_varint(&record[pos..]) else {
+                break;
+            };
+            let start = pos + used;
+            let end = (start + len as usize).min(record.len());
+            let text = String::from_utf8_lossy(&record[start..end]).into_owned();
+            strings.insert((n as u32) * 0x10000 + pos as u32, text);
+            pos = end;
+        }
+    }
+    strings
+}
+
+/// Read the index whose header is record `first`.
+pub fn read_index(records: &[&[u8]], first: usize) -> Result<Index, String> {
+    let header = records.get(first).ok_or("index record out of range")?;
+    if !header.starts_with(b"INDX") {
+        return Err("not an INDX record".into());
+    }
+    let data_count = u32_at(header, 24).unwrap_or(0) as usize;
+    let cncx_count = u32_at(header, 52).unwrap_or(0) as usize;
+    let tagx_offset = u32_at(header, 180).unwrap_or(HEADER_LEN as u32) as usize;
+    let (control_count, tagx) = header
+        .get(tagx_offset..)
+        .and_then(parse_tagx)
+        .ok_or("index has no TAGX table")?;
+
+    let mut index = Index::default();
+    let cncx_start = first + data_count + 1;
+    if cncx_count > 0 {
+        let cncx: Vec<&[u8]> = records
+            .iter()
+            .skip(cncx_start)
+            .take(cncx_count)
+            .copied()
+            .collect();
+        index.cncx = parse_cncx(&cncx);
+    }
+    for record in records.iter().skip(first + 1).take(data_count) {
+        let idxt = u32_at(record, 20).ok_or("truncated INDX record")? as usize;
+        let count = u32_at(record, 24).ok_or("truncated INDX record")? as usize;
+        let mut starts = Vec::with_capacity(count + 1);
+        for j in 0..count {
+            starts.push(u16_at(record, idxt + 4 + 2 * j).ok_or("truncated IDXT")? as usize);
+        }
+        starts.push(idxt);
+        for pair in starts.windows(2) {
+            let Some(entry) = record.get(pair[0]..pair[1].max(pair[0])) else {
+                continue;
+            };
+            let Some((&name_len, rest)) = entry.split_first() else {
+                continue;
+            };
+            let name_len = (name_len as usize).min(rest.len());
+            let name = String::from_utf8_lossy(&rest[..name_len]).into_owned();
+            let tags = parse_entry(&rest[name_len..], control_count, &tagx);
+            index.entries.push((name, tags));
+        }
+    }
+    Ok(index)
+}
+
+/// Strings for an index's CNCX records, handing out the offsets entries
+/// store.
+#[derive(Default)]
+pub struct CncxBuilder {
+    records: Vec<Vec<u8>>,
+    offsets: HashMap<String, u32>,
+}
+
+impl CncxBuilder {
+    pub fn add(&mut self, text: &str) -> u32 {
+        if let Some(&offset) = self.offsets.get(text) {
+            return offset;
+        }
+        let mut raw = encode_varint(text.len() as u32);
+        raw.extend_from_slice(text.as_bytes());
+        if self
+            .records
+            .last()
+            .map_or(true, |r| r.len() + raw.len() > MAX_RECORD_LEN)
+        {
+            self.records.push(Vec::new());
+        }
+        let n = self.records.len() - 1;
+        let record = &mut self.records[n];
+        let offset = (n as u32) * 0x10000 + record.len() as u32;
+        record.extend_from_slice(&raw);
+        self.offsets.insert(text.to_string(), offset);
+        offset
+    }
+}
+
+fn pad4(buf: &mut Vec<u8>) {
+    while buf.len() % 4 != 0 {
+        buf.push(0);
+    }
+}
+
+fn encode_entry(name: &str, tagx: &[TagDef], values: &[(u8, Vec<u32>)]) -> Vec<u8> {
+    let control_count = tagx.iter().filter(|t| t.end).count();
+    let mut control = vec![0u8; control_count];
+    let mut body = Vec::new();
+    let mut byte = 0;
+    for def in tagx {
+        if def.end {
+            byte += 1;
+            continue;
+        }
+        let Some((_, tag_values)) = values.iter().find(|(tag, _)| *tag == def.tag) else {
+            continue;
+        };
+        let occurrences = tag_values.len() / def.values.max(1) as usize;
+        if occurrences == 0 {
+            continue;
+        }
+        control[byte] |= ((occurrences as u8) << def.mask.trailing_zeros()) & def.mask;
+        for &value in tag_values {
+            body.extend(encode_varint(value));
+        }
+    }
+    let mut entry = vec![name.len() as u8];
+    entry.extend_from_slice(name.as_bytes());
+    entry.extend(control);
+    entry.extend(body);
+    entry
+}
+
+fn indx_header(kind: u32, idxt: usize, count: usize) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(b"INDX");
+    header.extend_from_slice(&(HEADER_LEN as u32).to_be_bytes());
+    header.extend_from_slice(&0u32.to_be_bytes());
+    header.extend_from_slice(&kind.to_be_bytes());
+    header.extend_from_slice(&0u32.to_be_bytes());
+    header.extend_from_slice(&(idxt as u32).to_be_bytes());
+    header.extend_from_slice(&(count as u32).to_be_bytes());
+    header.resize(HEADER_LEN, 0);
+    header
+}
+
+fn with_idxt(mut record: Vec<u8>, offsets: &[usize]) -> Vec<u8> {
+    pad4(&mut record);
+    let idxt = record.len();
+    record[20..24].copy_from_slice(&(idxt as u32).to_be_bytes());
+    record.extend_from_slice(b"IDXT");
+    for &offset in offsets {
+        record.extend_from_slice(&(offset as u16).to_be_bytes());
+    }
+    pad4(&mut record);
+    record
+}
+
+/// Build the records of an index: header, data records, then CNCX.
+/// `entries` must already be in the order readers expect.
+pub fn build_index(
+    tagx: &[TagDef],
+    entries: &[(String, Vec<(u8, Vec<u32>)>)],
+    cncx: CncxBuilder,
+) -> Vec<Vec<u8>> {
+    let encoded: Vec<Vec<u8>> = entries
+        .iter()
+        .map(|(name, values)| encode_entry(name, tagx, values))
+        .collect();
+
+    // Pack entries into data records.
+    let mut data_records = Vec::new();
+    let mut last_names = Vec::new();
+    let mut i = 0;
+    while i < encoded.len() {
+        let mut record = indx_header(1, 0, 0);
+        let mut offsets = Vec::new();
+        while i < encoded.len() {
+            let size = record.len() + encoded[i].len() + 2 * (offsets.len() + 1) + 8;
+            if !offsets.is_empty() && size > MAX_RECORD_LEN {
+                break;
+            }
+            offsets.push(record.len());
+            record.extend_from_slice(&encoded[i]);
+            i += 1;
+        }
+        record[24..28].copy_from_slice(&(offsets.len() as u32).to_be_bytes());
+        last_names.push((entries[i - 1].0.clone(), offsets.len()));
+        data_records.push(with_idxt(record, &offsets));
+    }
+
+    let mut header = indx_header(0, 0, data_records.len());
+    header[28..32].copy_from_slice(&65001u32.to_be_bytes());
+    header[36..40].copy_from_slice(&(entries.len() as u32).to_be_bytes());
+    header[52..56].copy_from_slice(&(cncx.records.len() as u32).to_be_bytes());
+    header[180..184].copy_from_slice(&(HEADER_LEN as u32).to_be_bytes());
+    let control_count = tagx.iter().filter(|t| t.end).count();
+    header.extend_from_slice(b"TAGX");
+    header.extend_from_slice(&(12 + 4 * tagx.len() as u32).to_be_bytes());
+    header.extend_from_slice(&(control_count as u32).to_be_bytes());
+    for def in tagx {
+        header.extend_from_slice(&[def.tag, def.values, def.mask, def.end as u8]);
+    }
+    pad4(&mut header);
+    let mut offsets = Vec::new();
+    for (name, count) in &last_names {
+        offsets.push(header.len());
+        header.push(name.len() as u8);
+        header.extend_from_slice(name.as_bytes());
+        header.extend_from_slice(&(*count as u16).to_be_bytes());
+    }
+    let header = with_idxt(header, &offsets);
+
+    let mut records = vec![header];
+    records.extend(data_records);
+    records.extend(cncx.records);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAGX: [TagDef; 4] = [
+        TagDef::new(2, 1, 1),
+        TagDef::new(3, 1, 2),
+        TagDef::new(6, 2, 8),
+        TagDef::END,
+    ];
+
+    #[test]
+    fn indices_round_trip() {
+        let mut cncx = CncxBuilder::default();
+        let selector = cncx.add("P-//*[@aid='0']");
+        assert_eq!(cncx.add("P-//*[@aid='0']"), selector);
+        let second = cncx.add("Chapter Two");
+        let entries: Vec<(String, Vec<(u8, Vec<u32>)>)> = (0..5000)
+            .map(|i| {
+                let mut values = vec![(2, vec![selector]), (6, vec![i * 10, 500])];
+                if i % 2 == 0 {
+                    values.push((3, vec![second]));
+                }
+                (format!("{:010}", i), values)
+            })
+            .collect();
+        let records = build_index(&TAGX, &entries, cncx);
+        assert!(records.len() > 3, "entries spill over several records");
+        let mut file: Vec<&[u8]> = vec![b"record 0"];
+        file.extend(records.iter().map(Vec::as_slice));
+        let index = read_index(&file, 1).unwrap();
+        assert_eq!(index.entries.len(), 5000);
+        let (name, tags) = &index.entries[1234];
+        assert_eq!(name, "0000001234");
+        assert_eq!(tags[&6], vec![12340, 500]);
+        assert_eq!(index.cncx[&tags[&2][0]], "P-//*[@aid='0']");
+        assert_eq!(index.cncx[&tags[&3][0]], "Chapter Two");
+        assert!(!index.entries[1235].1.contains_key(&3));
+    }
+}
@@ -0,0 +1,945 @@
+// Reading MOBI / AZW / AZW3 into a `Book`.
+//
+// Two layouts share the PalmDB container:
+//   - MOBI 6: one HTML stream with `<mbp:pagebreak/>` between sections,
+//     images referenced as `recindex="00001"` and links as byte offsets
+//     (`filepos="0000012345"`) into the decompressed text;
+//   - KF8 (AZW3, and the second half of "joint" files Kindlegen emits):
+//     XHTML split into skeletons with fragments spliced in at positions
+//     recorded in the SKEL / FRAG indices, CSS in separate flows listed by
+//     the FDST record, and `kindle:embed:` / `kindle:flow:` /
+//     `kindle:pos:fid:` references in place of paths.
+// Joint files are read through their KF8 half, which keeps the styling.
+// DRM-protected books are refused.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::book::{sniff_image, Book, Metadata, Resource, TocEntry};
+use super::indx::{read_index, Index};
+use super::palmdb::{
+    palmdoc_decompress, read_records, trailing_entries_len, u16_at, u32_at, HuffCdic,
+    COMPRESSION_HUFFCDIC, COMPRESSION_NONE, COMPRESSION_PALMDOC,
+};
+use super::xhtml::{escape, id_offsets, rewrite_attrs, text_content, tidy, tokenize, Token};
+use super::Progress;
+
+pub const NULL_INDEX: u32 = 0xffff_ffff;
+const BASE32: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+/// Records that end the run of image records.
+const RESOURCE_END: [&[u8]; 4] = [b"BOUNDARY", b"FLIS", b"FCIS", b"\xe9\x8e\r\n"];
+/// Windows-1252 code points for bytes 0x80..=0x9F; the rest match Latin-1.
+const CP1252_HIGH: [u16; 32] = [
+    0x20ac, 0x81, 0x201a, 0x192, 0x201e, 0x2026, 0x2020, 0x2021, 0x2c6, 0x2030, 0x160, 0x2039,
+    0x152, 0x8d, 0x17d, 0x8f, 0x90, 0x2018, 0x2019, 0x201c, 0x201d, 0x2022, 0x2013, 0x2014, 0x2dc,
+    0x2122, 0x161, 0x203a, 0x153, 0x9d, 0x17e, 0x178,
+];
+
+pub fn decode_base32(s: &str) -> Option<u32> {
+    s.bytes().try_fold(0u32, |acc, b| {
+        let digit = BASE32.iter().position(|&d| d == b.to_ascii_uppercase())?;
+        acc.checked_mul(32)?.checked_add(digit as u32)
+    })
+}
+
+pub fn encode_base32(mut value: u32, width: usize) -> String {
+    let mut digits = Vec::new();
+    while value != 0 || digits.is_empty() {
+        digits.push(BASE32[(value % 32) as usize]);
+        value /= 32;
+    }
+    while digits.len() < width {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+fn decode_cp1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9f => char::from_u32(CP1252_HIGH[(b - 0x80) as usize] as u32).unwrap_or('?'),
+            _ => b as char,
+        })
+        .collect()
+}
+
+/// The fields of a MOBI header (record 0) the reader needs.
+#[derive(Debug, Clone)]
+struct Header {
+    compression: u16,
+    text_length: usize,
+    text_records: usize,
+    encryption: u16,
+    utf8: bool,
+    version: u32,
+    first_image: u32,
+    huff: (u32, u32),
+    extra_flags: u16,
+    fdst: u32,
+    ncx: u32,
+    frag: u32,
+    skel: u32,
+    exth: HashMap<u32, Vec<Vec<u8>>>,
+    full_name: String,
+}
+
+impl Header {
+    fn parse(record: &[u8]) -> Result<Self, String> {
+        if record.get(16..20) != Some(b"MOBI") {
+            return Err("not a MOBI file".into());
+        }
+        let field = |pos: usize| u32_at(record, pos).unwrap_or(NULL_INDEX);
+        let header_len = field(20) as usize;
+        let utf8 = field(28) == 65001;
+        let version = field(36);
+        let mut exth = HashMap::new();
+        if field(128) & 0x40 != 0 {
+            let start = 16 + header_len;
+            if record.get(start..start + 4) == Some(b"EXTH") {
+                let count = u32_at(record, start + 8).unwrap_or(0);
+                let mut pos = start + 12;
+                for _ in 0..count {
+                    let (Some(kind), Some(len)) = (u32_at(record, pos), u32_at(record, pos + 4))
+                    else {
+                        break;
+                    };
+                    let Some(data) = record.get(pos + 8..pos + (len as usize).max(8)) else {
+                        break;
+                    };
+                    exth.entry(kind)
+                        .or_insert_with(Vec::new)
+                        .push(data.to_vec());
+                    pos += (len as usize).max(8);
+                }
+            }
+        }
+        let name_start = field(84) as usize;
+        let name_len = field(88) as usize;
+        let name = record
+            .get(name_start..name_start.saturating_add(name_len))
+            .unwrap_or_default();
+        let full_name = if utf8 {
+            String::from_utf8_lossy(name).into_owned()
+        } else {
+            decode_cp1252(name)
+        };
+        let kf8 = version >= 8;
+        Ok(Self {
+            compression: u16_at(record, 0).unwrap_or(COMPRESSION_NONE),
+            text_length: field(4) as usize,
+            text_records: u16_at(record, 8).unwrap_or(0) as usize,
+            encryption: u16_at(record, 12).unwrap_or(0),
+            utf8,
+            version,
+            first_image: field(108),
+            huff: (field(112), field(116)),
+            extra_flags: if header_len >= 0xe4 {
+                u16_at(record, 242).unwrap_or(0)
+            } else {
+                0
+            },
+            fdst: if kf8 { field(192) } else { NULL_INDEX },
+            ncx: if header_len >= 0xe8 {
+                field(244)
+            } else {
+                NULL_INDEX
+            },
+            frag: if kf8 { field(248) } else { NULL_INDEX },
+            skel: if kf8 { field(252) } else { NULL_INDEX },
+            exth,
+            full_name,
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        if self.utf8 {
+            String::from_utf8_lossy(bytes).into_owned()
+        } else {
+            decode_cp1252(bytes)
+        }
+    }
+
+    fn exth_strings(&self, kind: u32) -> Vec<String> {
+        self.exth
+            .get(&kind)
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|v| self.decode(v).trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn exth_u32(&self, kind: u32) -> Option<u32> {
+        self.exth.get(&kind)?.first().and_then(|v| u32_at(v, 0))
+    }
+
+    fn metadata(&self) -> Metadata {
+        let first = |kind| self.exth_strings(kind).into_iter().next();
+        Metadata {
+            title: first(503).unwrap_or_else(|| self.full_name.clone()),
+            authors: self.exth_strings(100),
+            publisher: first(101),
+            description: first(103),
+            language: first(524),
+            identifier: first(104).or_else(|| first(113)),
+            subjects: self.exth_strings(105),
+            date: first(106),
+        }
+    }
+}
+
+/// The records of one book section: the whole file, or the KF8 half of a
+/// joint file with record 0 at its boundary.
+struct Section<'a> {
+    records: &'a [&'a [u8]],
+    base: usize,
+    header: Header,
+}
+
+impl<'a> Section<'a> {
+    fn record(&self, index: u32) -> Option<&'a [u8]> {
+        if index == NULL_INDEX {
+            return None;
+        }
+        self.records.get(self.base + index as usize).copied()
+    }
+
+    fn text(&self, progress: &mut Progress) -> Result<Vec<u8>, String> {
+        let mut huff = match self.header.compression {
+            COMPRESSION_HUFFCDIC => {
+                let (index, count) = self.header.huff;
+                let huff = self.record(index).ok_or("missing HUFF record")?;
+                let cdics: Vec<&[u8]> = (1..count).filter_map(|i| self.record(index + i)).collect();
+                Some(HuffCdic::new(huff, &cdics)?)
+            }
+            COMPRESSION_NONE | COMPRESSION_PALMDOC => None,
+            other => return Err(format!("unsupported MOBI compression {other}")),
+        };
+        let mut text = Vec::with_capacity(self.header.text_length);
+        let count = self.header.text_records;
+        for i in 1..=count {
+            let record = self
+                .record(i as u32)
+                .ok_or("the text records are truncated")?;
+            let record =
+                &record[..record.len() - trailing_entries_len(record, self.header.extra_flags)];
+            match (&mut huff, self.header.compression) {
+                (Some(huff), _) => text.extend(huff.decompress(record)),
+                (None, COMPRESSION_PALMDOC) => text.extend(palmdoc_decompress(record)),
+                _ => text.extend_from_slice(record),
+            }
+            if i % 64 == 0 {
+                progress.report(0.3 * i as f32 / count as f32)?;
+            }
+        }
+        text.truncate(self.header.text_length);
+        Ok(text)
+    }
+
+    /// Image records by their 1-based resource number, the numbering both
+    /// `recindex` and `kindle:embed` use.
+    fn images(&self) -> HashMap<u32, Resource> {
+        let mut images = HashMap::new();
+        if self.header.first_image == NULL_INDEX {
+            return images;
+        }
+        // Joint files keep the images in the MOBI 6 half, so the KF8
+        // header's index may be absolute rather than section-relative.
+        let relative = self.base + self.header.first_image as usize;
+        let absolute = self.header.first_image as usize;
+        let looks_like_image =
+            |i: usize| self.records.get(i).and_then(|r| sniff_image(r)).is_some();
+        let start = if looks_like_image(relative) || !looks_like_image(absolute) {
+            relative
+        } else {
+            absolute
+        };
+        for (n, record) in self.records.iter().enumerate().skip(start) {
+            if RESOURCE_END.iter().any(|magic| record.starts_with(magic)) {
+                break;
+            }
+            if let Some((mime, ext)) = sniff_image(record) {
+                let number = (n - start + 1) as u32;
+                let path = format!("images/image{number:05}.{ext}");
+                images.insert(number, Resource::new(path, mime, record.to_vec()));
+            }
+        }
+        images
+    }
+
+    fn index(&self, first: u32) -> Option<Index> {
+        if first == NULL_INDEX {
+            return None;
+        }
+        read_index(self.records, self.base + first as usize)
+            .map_err(|e| log::warn!("Ignoring a broken MOBI index: {e}"))
+            .ok()
+    }
+}
+
+fn open_section<'a>(records: &'a [&'a [u8]]) -> Result<Section<'a>, String> {
+    let first = records.first().ok_or("empty file")?;
+    let header = Header::parse(first)?;
+    let boundary = header.exth_u32(121).filter(|&b| b != NULL_INDEX);
+    if let Some(boundary) = boundary {
+        // EXTH 121 names the KF8 record 0, or the BOUNDARY record just
+        // before it in some writers' output.
+        for base in [boundary as usize, boundary as usize + 1] {
+            if let Some(kf8) = records.get(base).and_then(|r| Header::parse(r).ok()) {
+                if kf8.version >= 8 {
+                    return Ok(Section {
+                        records,
+                        base,
+                        header: kf8,
+                    });
+                }
+            }
+        }
+    }
+    Ok(Section {
+        records,
+        base: 0,
+        header,
+    })
+}
+
+pub fn xhtml_document(title: &str, head: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+         <head>\n<title>{}</title>\n{head}</head>\n<body>\n{body}\n</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// The TOC the NCX index describes, given a way to turn an entry into an
+/// href.
+fn ncx_toc(
+    index: &Index,
+    mut href: impl FnMut(&HashMap<u8, Vec<u32>>) -> Option<String>,
+) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    for i in reading_order(index) {
+        let tags = &index.entries[i].1;
+        let label = tags
+            .get(&3)
+            .and_then(|v| v.first())
+            .and_then(|offset| index.cncx.get(offset))
+            .map(|label| label.trim().to_string())
+            .unwrap_or_default();
+        let Some(href) = href(tags) else {
+            continue;
+        };
+        if label.is_empty() {
+            continue;
+        }
+        let depth = tags.get(&4).and_then(|v| v.first()).copied().unwrap_or(0);
+        toc.push(TocEntry { label, href, depth });
+    }
+    toc
+}
+
+/// NCX entries are stored level by level, with parent and child-range
+/// tags (21, 22, 23) linking them; walk them depth-first. Indices without
+/// those tags are already in reading order.
+fn reading_order(index: &Index) -> Vec<usize> {
+    let first = |i: usize, tag: u8| {
+        index.entries[i]
+            .1
+            .get(&tag)
+            .and_then(|v| v.first())
+            .copied()
+    };
+    let count = index.entries.len();
+    if !(0..count).any(|i| first(i, 22).is_some()) {
+        return (0..count).collect();
+    }
+    let mut order = Vec::with_capacity(count);
+    let mut seen = vec![false; count];
+    let mut stack: Vec<usize> = (0..count)
+        .filter(|&i| first(i, 21).is_none())
+        .rev()
+        .collect();
+    while let Some(i) = stack.pop() {
+        if std::mem::replace(&mut seen[i], true) {
+            continue;
+        }
+        order.push(i);
+        if let (Some(start), Some(end)) = (first(i, 22), first(i, 23)) {
+            let end = (end as usize).min(count.saturating_sub(1));
+            stack.extend((start as usize..=end).rev());
+        }
+    }
+    order
+}
+
+/// A TOC of the documents' first headings, for books without an NCX.
+fn heading_toc(documents: &[Resource]) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    for doc in documents {
+        let text = doc.text();
+        let mut heading = None;
+        let mut start = None;
+        for token in tokenize(&text) {
+            let Token::Tag(tag) = token else {
+                continue;
+            };
+            let name = tag.name.to_ascii_lowercase();
+            if !matches!(name.as_str(), "h1" | "h2" | "h3") {
+                continue;
+            }
+            match (tag.closing, start) {
+                (false, None) => start = Some(tag.span.1),
+                (true, Some(s)) => {
+                    heading = Some(text_content(&text[s..tag.span.0]));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if let Some(label) = heading.filter(|h| !h.is_empty()) {
+            toc.push(TocEntry {
+                label,
+                href: doc.path.clone(),
+                depth: 0,
+            });
+        }
+    }
+    toc
+}
+
+/// Byte offsets `filepos` links point at, plus any extra targets.
+fn filepos_targets(text: &[u8]) -> BTreeSet<usize> {
+    let mut targets = BTreeSet::new();
+    let needle = b"filepos=";
+    let mut i = 0;
+    while let Some(n) = text[i..]
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+    {
+        let mut j = i + n + needle.len();
+        while matches!(text.get(j), Some(b'"' | b'\'')) {
+            j += 1;
+        }
+        let digits: String = text[j..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .map(|&b| b as char)
+            .collect();
+        if let Ok(pos) = digits.parse() {
+            targets.insert(pos);
+        }
+        i = j;
+    }
+    targets
+}
+
+/// Insert `<a id="fileposN"/>` at each target, moved out of any tag it
+/// would land in.
+fn insert_anchors(text: &[u8], targets: &BTreeSet<usize>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + targets.len() * 24);
+    let mut copied = 0;
+    for &target in targets {
+        let mut pos = target.min(text.len());
+        let last_open = text[..pos].iter().rposition(|&b| b == b'<');
+        let last_close = text[..pos].iter().rposition(|&b| b == b'>');
+        if let Some(open) = last_open {
+            if last_close.map_or(true, |close| close < open) {
+                pos = open;
+            }
+        }
+        let pos = pos.max(copied);
+        out.extend_from_slice(&text[copied..pos]);
+        out.extend_from_slice(format!("<a id=\"filepos{target}\"></a>").as_bytes());
+        copied = pos;
+    }
+    out.extend_from_slice(&text[copied..]);
+    out
+}
+
+fn find_ignore_case(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+        .map(|n| from + n)
+}
+
+/// Split MOBI 6 HTML at its page breaks, dropping the `<head>`.
+fn split_pages(html: &str) -> Vec<&str> {
+    let body_start = match (
+        find_ignore_case(html, "<head", 0),
+        find_ignore_case(html, "</head>", 0),
+    ) {
+        (Some(_), Some(end)) => end + "</head>".len(),
+        _ => 0,
+    };
+    let mut pages = Vec::new();
+    let mut start = body_start;
+    while let Some(brk) = find_ignore_case(html, "<mbp:pagebreak", start) {
+        pages.push(&html[start..brk]);
+        start = html[brk..].find('>').map_or(html.len(), |n| brk + n + 1);
+    }
+    pages.push(&html[start..]);
+    pages
+}
+
+fn rename_attr(html: &str, from: &str, to: &str) -> String {
+    html.replace(&format!(" {from}=\""), &format!(" {to}=\""))
+}
+
+fn mobi6_book(section: &Section, progress: &mut Progress) -> Result<Book, String> {
+    let header = &section.header;
+    let text = section.text(progress)?;
+    let ncx = section.index(header.ncx);
+    let mut targets = filepos_targets(&text);
+    if let Some(ncx) = &ncx {
+        for (_, tags) in &ncx.entries {
+            if let Some(&offset) = tags.get(&1).and_then(|v| v.first()) {
+                targets.insert(offset as usize);
+            }
+        }
+    }
+    let html = header.decode(&insert_anchors(&text, &targets));
+    progress.report(0.4)?;
+
+    let images = section.images();
+    let pages: Vec<String> = split_pages(&html).into_iter().map(tidy).collect();
+    let pages: Vec<String> = pages
+        .into_iter()
+        .filter(|page| {
+            !text_content(page).is_empty()
+                || page.contains("<img")
+                || page.contains(" id=\"filepos")
+        })
+        .collect();
+    let path = |n: usize| format!("text/part{n:04}.xhtml");
+    let mut anchor_page: HashMap<usize, usize> = HashMap::new();
+    for (n, page) in pages.iter().enumerate() {
+        for id in id_offsets(page).keys() {
+            if let Some(target) = id.strip_prefix("filepos").and_then(|t| t.parse().ok()) {
+                anchor_page.insert(target, n);
+            }
+        }
+    }
+    let target_href = |value: &str, from: Option<usize>| -> Option<String> {
+        let target: usize = value.trim().parse().ok()?;
+        let page = *anchor_page.get(&target)?;
+        Some(match from {
+            Some(from) if from == page => format!("#filepos{target}"),
+            Some(_) => format!("part{page:04}.xhtml#filepos{target}"),
+            None => format!("{}#filepos{target}", path(page)),
+        })
+    };
+
+    let mut documents = Vec::new();
+    for (n, page) in pages.iter().enumerate() {
+        let body = rewrite_attrs(page, |tag, attr, value| match (tag, attr) {
+            ("a", "filepos") => target_href(value, Some(n)).or_else(|| Some(String::new())),
+            ("img", "recindex") => {
+                let number: u32 = value.trim().parse().ok()?;
+                let image = images.get(&number)?;
+                Some(format!("../{}", image.path))
+            }
+            _ => None,
+        });
+        let body = rename_attr(&rename_attr(&body, "filepos", "href"), "recindex", "src");
+        let title = format!("{} ({})", header.full_name, n + 1);
+        documents.push(Resource::new(
+            path(n),
+            "application/xhtml+xml",
+            xhtml_document(&title, "", &body).into_bytes(),
+        ));
+    }
+
+    let mut toc = ncx
+        .as_ref()
+        .map(|ncx| {
+            ncx_toc(ncx, |tags| {
+                let offset = *tags.get(&1)?.first()?;
+                target_href(&offset.to_string(), None)
+            })
+        })
+        .unwrap_or_default();
+    if toc.is_empty() {
+        toc = heading_toc(&documents);
+    }
+    let mut images: Vec<(u32, Resource)> = images.into_iter().collect();
+    images.sort_by_key(|(n, _)| *n);
+    let cover = cover_path(header, &images);
+    Ok(Book {
+        metadata: header.metadata(),
+        documents,
+        styles: Vec::new(),
+        images: images.into_iter().map(|(_, r)| r).collect(),
+        toc,
+        cover,
+    })
+}
+
+fn cover_path(header: &Header, images: &[(u32, Resource)]) -> Option<String> {
+    let offset = header.exth_u32(201).filter(|&o| o != NULL_INDEX)?;
+    images
+        .iter()
+        .find(|(n, _)| *n == offset + 1)
+        .map(|(_, r)| r.path.clone())
+}
+
+/// A fragment of KF8 text: where it was spliced in, and into which part.
+struct Fragment {
+    insert_pos: usize,
+    part: usize,
+}
+
+struct Part {
+    start: usize,
+    html: String,
+}
+
+/// Assemble the KF8 text flow into its files using the SKEL and FRAG
+/// indices: each skeleton is followed in the flow by its fragments, which
+/// go back in at the positions the FRAG entries name.
+fn assemble_parts(flow: &[u8], skel: &Index, frag: &Index) -> (Vec<Part>, Vec<Fragment>) {
+    let mut parts = Vec::new();
+    let mut fragments = Vec::new();
+    let mut frag_entries = frag.entries.iter();
+    for (_, tags) in &skel.entries {
+        let count = tags.get(&1).and_then(|v| v.first()).copied().unwrap_or(0) as usize;
+        let geometry = tags.get(&6).cloned().unwrap_or_default();
+        let (Some(&skel_pos), Some(&skel_len)) = (geometry.first(), geometry.get(1)) else {
+            continue;
+        };
+        let skel_pos = skel_pos as usize;
+        let mut base = (skel_pos + skel_len as usize).min(flow.len());
+        let mut skeleton = flow[skel_pos.min(base)..base].to_vec();
+        for (name, tags) in frag_entries.by_ref().take(count) {
+            let insert_pos: usize = name.trim().parse().unwrap_or(skel_pos);
+            let len = tags.get(&6).and_then(|v| v.get(1)).copied().unwrap_or(0) as usize;
+            let end = (base + len).min(flow.len());
+            let at = insert_pos.saturating_sub(skel_pos).min(skeleton.len());
+            skeleton.splice(at..at, flow[base..end].iter().copied());
+            fragments.push(Fragment {
+                insert_pos,
+                part: parts.len(),
+            });
+            base = end;
+        }
+        parts.push(Part {
+            start: skel_pos,
+            html: String::from_utf8_lossy(&skeleton).into_owned(),
+        });
+    }
+    (parts, fragments)
+}
+
+/// The `id` of the element at or nearest before `pos` in `html`.
+fn id_near(html: &str, pos: usize) -> Option<String> {
+    let mut best = None;
+    for token in tokenize(html) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.span.0 > pos && best.is_some() {
+            break;
+        }
+        if tag.closing {
+            continue;
+        }
+        if let Some(id) = tag.attr(html, "id").or_else(|| tag.attr(html, "name")) {
+            best = Some(id.to_string());
+            if tag.span.0 >= pos {
+                break;
+            }
+        }
+    }
+    best
+}
+
+fn kf8_book(section: &Section, progress: &mut Progress) -> Result<Book, String> {
+    let header = &section.header;
+    let text = section.text(progress)?;
+    let mut flows: Vec<(usize, usize)> = Vec::new();
+    if let Some(fdst) = section
+        .record(header.fdst)
+        .filter(|r| r.starts_with(b"FDST"))
+    {
+        let count = u32_at(fdst, 8).unwrap_or(0) as usize;
+        for i in 0..count {
+            let (Some(start), Some(end)) = (u32_at(fdst, 12 + i * 8), u32_at(fdst, 16 + i * 8))
+            else {
+                break;
+            };
+            let end = (end as usize).min(text.len());
+            flows.push(((start as usize).min(end), end));
+        }
+    }
+    if flows.is_empty() {
+        flows.push((0, text.len()));
+    }
+    let flow0 = &text[flows[0].0..flows[0].1];
+
+    let (parts, fragments) = match (section.index(header.skel), section.index(header.frag)) {
+        (Some(skel), Some(frag)) if !skel.entries.is_empty() => assemble_parts(flow0, &skel, &frag),
+        _ => (
+            vec![Part {
+                start: 0,
+                html: String::from_utf8_lossy(flow0).into_owned(),
+            }],
+            Vec::new(),
+        ),
+    };
+    progress.report(0.4)?;
+
+    let images = section.images();
+    let part_path = |n: usize| format!("text/part{n:04}.xhtml");
+    let resolve_pos = |fid: u32, off: u32| -> Option<(usize, Option<String>)> {
+        let fragment = fragments.get(fid as usize)?;
+        let part = parts.get(fragment.part)?;
+        let local = (fragment.insert_pos + off as usize).checked_sub(part.start)?;
+        Some((fragment.part, id_near(&part.html, local)))
+    };
+    // kindle:embed:0001?mime=image/jpeg → the image's path from `from_dir`.
+    let embed = |value: &str, prefix: &str| -> Option<String> {
+        let rest = value.trim().strip_prefix("kindle:embed:")?;
+        let number = decode_base32(rest.split(['?', '#']).next()?)?;
+        Some(format!("{prefix}{}", images.get(&number)?.path))
+    };
+    let flow_path = |n: usize| format!("styles/flow{n:04}.css");
+    let rewrite = |from_part: Option<usize>, value: &str| -> Option<String> {
+        let value = value.trim();
+        if let Some(rest) = value.strip_prefix("kindle:pos:fid:") {
+            let (fid, off) = rest.split_once(":off:")?;
+            let off = off.split('?').next()?;
+            let (part, id) = resolve_pos(decode_base32(fid)?, decode_base32(off)?)?;
+            let file = if from_part == Some(part) {
+                String::new()
+            } else {
+                format!("part{part:04}.xhtml")
+            };
+            return Some(match id {
+                Some(id) => format!("{file}#{}", escape(&id)),
+                None if file.is_empty() => "#".to_string(),
+                None => file,
+            });
+        }
+        if let Some(rest) = value.strip_prefix("kindle:flow:") {
+            let n = decode_base32(rest.split('?').next()?)? as usize;
+            return Some(format!("../{}", flow_path(n)));
+        }
+        embed(value, "../")
+    };
+
+    let mut styles = Vec::new();
+    for (n, &(start, end)) in flows.iter().enumerate().skip(1) {
+        let css = String::from_utf8_lossy(&text[start..end]).into_owned();
+        styles.push(Resource::new(
+            flow_path(n),
+            "text/css",
+            rewrite_css_urls(&css, |url| embed(url, "../")).into_bytes(),
+        ));
+    }
+
+    let mut documents = Vec::new();
+    for (n, part) in parts.iter().enumerate() {
+        let html = rewrite_attrs(&part.html, |_, attr, value| match attr {
+            "src" | "href" | "xlink:href" => rewrite(Some(n), value),
+            _ => None,
+        });
+        let html = rewrite_css_urls(&html, |url| embed(url, "../"));
+        documents.push(Resource::new(
+            part_path(n),
+            "application/xhtml+xml",
+            html.into_bytes(),
+        ));
+        if n % 16 == 0 {
+            progress.report(0.4 + 0.2 * n as f32 / parts.len().max(1) as f32)?;
+        }
+    }
+
+    let mut toc = section
+        .index(header.ncx)
+        .map(|ncx| {
+            ncx_toc(&ncx, |tags| {
+                let pos_fid = tags.get(&6)?;
+                let (part, id) = resolve_pos(*pos_fid.first()?, *pos_fid.get(1)?)?;
+                Some(match id {
+                    Some(id) => format!("{}#{id}", part_path(part)),
+                    None => part_path(part),
+                })
+            })
+        })
+        .unwrap_or_default();
+    if toc.is_empty() {
+        toc = heading_toc(&documents);
+    }
+    let mut images: Vec<(u32, Resource)> = images.into_iter().collect();
+    images.sort_by_key(|(n, _)| *n);
+    let cover = cover_path(header, &images);
+    Ok(Book {
+        metadata: header.metadata(),
+        documents,
+        styles,
+        images: images.into_iter().map(|(_, r)| r).collect(),
+        toc,
+        cover,
+    })
+}
+
+/// Rewrite the targets of CSS `url(...)`s; `f` returns `None` to keep one.
+pub fn rewrite_css_urls(css: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(n) = rest.find("url(") {
+        let (before, after) = rest.split_at(n + 4);
+        out.push_str(before);
+        let Some(close) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let raw = &after[..close];
+        let url = raw.trim().trim_matches(['"', '\'']);
+        match f(url) {
+            Some(new) => {
+                out.push('"');
+                out.push_str(&new);
+                out.push('"');
+            }
+            None => out.push_str(raw),
+        }
+        rest = &after[close..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Read a MOBI, AZW or AZW3 file.
+pub fn read_kindle(data: &[u8], progress: &mut Progress) -> Result<Book, String> {
+    let records = read_records(data)?;
+    let section = open_section(&records)?;
+    if section.header.encryption != 0 {
+        return Err("this book is DRM-protected and can't be converted".into());
+    }
+    if section.header.version >= 8 {
+        kf8_book(&section, progress)
+    } else {
+        mobi6_book(&section, progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base32_round_trips() {
+        assert_eq!(decode_base32("0001"), Some(1));
+        assert_eq!(decode_base32("000V"), Some(31));
+        assert_eq!(decode_base32("0010"), Some(32));
+        assert_eq!(decode_base32("00Z"), None);
+        assert_eq!(encode_base32(32, 4), "0010");
+        assert_eq!(encode_base32(0, 10), "0000000000");
+        assert_eq!(decode_base32(&encode_base32(123_456, 10)), Some(123_456));
+    }
+
+    #[test]
+    fn cp1252_maps_the_windows_range() {
+        assert_eq!(
+            decode_cp1252(b"\x93Hi\x94 \x96 caf\xe9"),
+            "\u{201c}Hi\u{201d} \u{2013} caf\u{e9}"
+        );
+    }
+
+    #[test]
+    fn filepos_anchors_land_outside_tags() {
+        let text = b"<p>One</p><mbp:pagebreak/><p>Two <a filepos=0000000003>x</a></p>";
+        let targets = filepos_targets(text);
+        assert_eq!(targets.iter().copied().collect::<Vec<_>>(), vec![3]);
+        let anchored = insert_anchors(text, &targets);
+        assert!(anchored.starts_with(b"<p><a id=\"filepos3\"></a>One</p>"));
+        // A target inside a tag moves to the tag's start.
+        let anchored = insert_anchors(b"<p class=x>y</p>", &BTreeSet::from([5]));
+        assert_eq!(anchored, b"<a id=\"filepos5\"></a><p class=x>y</p>");
+    }
+
+    #[test]
+    fn pages_split_at_page_breaks() {
+        let html = "<html><head><guide><reference type=toc filepos=1/></guide></head>\
+                    <body><p>A</p><mbp:pagebreak/><p>B</p><MBP:PAGEBREAK /><p>C</p></body></html>";
+        let pages: Vec<String> = split_pages(html).into_iter().map(tidy).collect();
+        assert_eq!(pages, vec!["<p>A</p>", "<p>B</p>", "<p>C</p>"]);
+    }
+
+    #[test]
+    fn kf8_fragments_are_spliced_into_skeletons() {
+        let skel0 = b"<html><body aid=\"0\"></body></html>";
+        let frag0 = b"<p id=\"a\">First</p>";
+        let skel1 = b"<html><body aid=\"1\"><div></div></body></html>";
+        let frag1 = b"<p>Second</p>";
+        let frag2 = b"<p id=\"b\">Third</p>";
+        let mut flow = Vec::new();
+        for piece in [&skel0[..], frag0, skel1, frag1, frag2] {
+            flow.extend_from_slice(piece);
+        }
+        let at0 = skel0.len() - "</body></html>".len();
+        let skel1_pos = skel0.len() + frag0.len();
+        let at1 = skel1_pos + "<html><body aid=\"1\"><div>".len();
+        let entry = |name: String, tags: &[(u8, Vec<u32>)]| (name, tags.iter().cloned().collect());
+        let skel = Index {
+            entries: vec![
+                entry(
+                    "SKEL0000000000".into(),
+                    &[(1, vec![1]), (6, vec![0, skel0.len() as u32])],
+                ),
+                entry(
+                    "SKEL0000000001".into(),
+                    &[
+                        (1, vec![2]),
+                        (6, vec![skel1_pos as u32, skel1.len() as u32]),
+                    ],
+                ),
+            ],
+            cncx: HashMap::new(),
+        };
+        let frag = Index {
+            entries: vec![
+                entry(format!("{at0:010}"), &[(6, vec![0, frag0.len() as u32])]),
+                entry(format!("{at1:010}"), &[(6, vec![0, frag1.len() as u32])]),
+                entry(
+                    format!("{:010}", at1 + frag1.len()),
+                    &[(6, vec![0, frag2.len() as u32])],
+                ),
+            ],
+            cncx: HashMap::new(),
+        };
+        let (parts, fragments) = assemble_parts(&flow, &skel, &frag);
+        assert_eq!(
+            parts[0].html,
+            "<html><body aid=\"0\"><p id=\"a\">First</p></body></html>"
+        );
+        assert_eq!(
+            parts[1].html,
+            "<html><body aid=\"1\"><div><p>Second</p><p id=\"b\">Third</p></div></body></html>"
+        );
+        let third = &fragments[2];
+        let local = third.insert_pos - parts[third.part].start;
+        assert_eq!(id_near(&parts[1].html, local), Some("b".to_string()));
+    }
+
+    #[test]
+    fn css_urls_are_rewritten() {
+        let css = "@font-face { src: url(kindle:embed:0003?mime=font/otf) } div { background: url('a.png') }";
+        let out = rewrite_css_urls(css, |url| {
+            url.starts_with("kindle:")
+                .then(|| "../fonts/f.otf".to_string())
+        });
+        assert_eq!(
+            out,
+            "@font-face { src: url(\"../fonts/f.otf\") } div { background: url('a.png') }"
+        );
+    }
+}
@@ -0,0 +1,851 @@
+// Reading a Markdown file, an HTML file or a folder of HTML into a `Book`,
+// so notes and web exports can be packaged as EPUB (or AZW3) by the same
+// writers the other conversions use.
+//
+// Markdown is rendered with pulldown-cmark and split into one document
+// per top-level heading. HTML pages become one document each, in natural
+// file name order with `index.html` first; their linked and inline
+// stylesheets come along, scripts don't. Either way:
+//   - every heading gets an id (a GitHub-style slug unless it has one) and
+//     the TOC is built from the top three heading levels;
+//   - local images (from `<img>`, SVG `<image>` and CSS `url()`) are
+//     copied into the book, remote ones are left alone;
+//   - links between imported pages, and `#fragment` links into a split
+//     Markdown file, are pointed at the document the target ended up in.
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::book::{media_type_for, relative, sniff_image, Book, Metadata, Resource, TocEntry};
+use super::kindle::{rewrite_css_urls, xhtml_document};
+use super::xhtml::{
+    body_bounds, escape, id_offsets, rewrite_attrs, text_content, tidy, tokenize, Token,
+};
+use super::Progress;
+use crate::comic::archive::natural_cmp;
+
+const MARKDOWN_EXTENSIONS: [&str; 5] = ["md", "markdown", "mdown", "mkd", "mkdn"];
+const HTML_EXTENSIONS: [&str; 3] = ["html", "htm", "xhtml"];
+/// Headings this many levels below the top one still make the TOC.
+const TOC_LEVELS: u32 = 3;
+const MARKDOWN_CSS: &str = "body { line-height: 1.5; }\n\
+pre { white-space: pre-wrap; word-wrap: break-word; padding: 0.5em; background: rgba(127, 127, 127, 0.1); }\n\
+code { font-family: monospace; }\n\
+blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid rgba(127, 127, 127, 0.4); }\n\
+table { border-collapse: collapse; }\n\
+th, td { border: 1px solid rgba(127, 127, 127, 0.4); padding: 0.25em 0.5em; }\n\
+img { max-width: 100%; }\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    Markdown,
+    /// An HTML file or a folder of them.
+    Html,
+}
+
+impl Markup {
+    /// What `path` holds, going by its extension (or being a folder).
+    pub fn detect(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Markup::Html);
+        }
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        if MARKDOWN_EXTENSIONS.contains(&ext.as_str()) {
+            Some(Markup::Markdown)
+        } else if HTML_EXTENSIONS.contains(&ext.as_str()) {
+            Some(Markup::Html)
+        } else {
+            None
+        }
+    }
+}
+
+/// One imported source file.
+struct Page {
+    source: PathBuf,
+    title: Option<String>,
+    /// Tidied body fragments; a Markdown file may split into several.
+    parts: Vec<String>,
+    /// Book paths of the page's stylesheets.
+    styles: Vec<String>,
+}
+
+/// Images and stylesheets copied into the book, keyed by source file.
+#[derive(Default)]
+struct Assets {
+    images: Vec<Resource>,
+    styles: Vec<Resource>,
+    copied: HashMap<PathBuf, String>,
+}
+
+impl Assets {
+    /// The book path of the image at `file`, copying it in on first use.
+    /// `None` when it can't be read or isn't an image.
+    fn image(&mut self, file: &Path) -> Option<String> {
+        let file = file.canonicalize().ok()?;
+        if let Some(path) = self.copied.get(&file) {
+            return Some(path.clone());
+        }
+        let data = std::fs::read(&file).ok()?;
+        let ext = file
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        let (media_type, ext) = match sniff_image(&data) {
+            Some((media_type, sniffed)) => (media_type, sniffed.to_string()),
+            None if media_type_for(&file.to_string_lossy()).starts_with("image/") => {
+                (media_type_for(&file.to_string_lossy()), ext)
+            }
+            None => return None,
+        };
+        let path = format!("images/image{:04}.{ext}", self.images.len() + 1);
+        self.images
+            .push(Resource::new(path.clone(), media_type, data));
+        self.copied.insert(file, path.clone());
+        Some(path)
+    }
+
+    /// Add `css`, found in (or at) `base`, as the stylesheet `path`, with
+    /// the images it references copied in.
+    fn add_style(&mut self, path: String, css: &str, base: &Path) -> String {
+        let css = rewrite_css_urls(css, |url| {
+            let file = local_file(base, url)?.0;
+            let image = self.image(&file)?;
+            Some(relative(&path, &image))
+        });
+        self.styles
+            .push(Resource::new(path.clone(), "text/css", css.into_bytes()));
+        path
+    }
+
+    /// The book path of the stylesheet at `file`, copying it in on first use.
+    fn stylesheet(&mut self, file: &Path) -> Option<String> {
+        let file = file.canonicalize().ok()?;
+        if let Some(path) = self.copied.get(&file) {
+            return Some(path.clone());
+        }
+        let css = std::fs::read(&file).ok()?;
+        let path = format!("styles/style{:04}.css", self.styles.len() + 1);
+        let path = self.add_style(path, &String::from_utf8_lossy(&css), &file);
+        self.copied.insert(file, path.clone());
+        Some(path)
+    }
+}
+
+/// The file `href` (relative to the file at `base`) names, and its
+/// fragment. `None` for external and `data:` URLs.
+fn local_file(base: &Path, href: &str) -> Option<(PathBuf, Option<String>)> {
+    let href = href.trim();
+    if !href.starts_with('#') && href.contains(':') {
+        // A scheme (`https:`, `mailto:`, `data:`); a Windows drive letter
+        // would be absolute and outside the import anyway.
+        return None;
+    }
+    let (path, fragment) = match href.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment.to_string())),
+        None => (href, None),
+    };
+    let path = path.split('?').next().unwrap_or(path);
+    let path = percent_encoding::percent_decode_str(path)
+        .decode_utf8_lossy()
+        .into_owned();
+    if path.is_empty() {
+        return Some((base.to_path_buf(), fragment));
+    }
+    let dir = base.parent().unwrap_or(Path::new(""));
+    Some((dir.join(path.trim_start_matches('/')), fragment))
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A GitHub-style anchor for a heading: lowercase, punctuation dropped,
+/// spaces as hyphens, numbered when taken.
+fn slug(text: &str, taken: &mut HashSet<String>) -> String {
+    let mut base: String = text
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect();
+    if base.is_empty() || !base.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        // XML ids can't start with a digit or hyphen.
+        base.insert_str(0, "h-");
+    }
+    let mut id = base.clone();
+    let mut n = 1;
+    while taken.contains(&id) {
+        id = format!("{base}-{n}");
+        n += 1;
+    }
+    taken.insert(id.clone());
+    id
+}
+
+struct Heading {
+    level: u32,
+    id: String,
+    text: String,
+}
+
+fn heading_level(name: &str) -> Option<u32> {
+    match name.to_ascii_lowercase().as_bytes() {
+        [b'h', n @ b'1'..=b'6'] => Some(u32::from(n - b'0')),
+        _ => None,
+    }
+}
+
+/// Give every heading in `body` an id and list them.
+fn index_headings(body: &str, taken: &mut HashSet<String>) -> (String, Vec<Heading>) {
+    let mut headings = Vec::new();
+    let mut insertions = Vec::new();
+    let mut open: Option<(u32, Option<String>, usize)> = None;
+    for token in tokenize(body) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        let Some(level) = heading_level(tag.name) else {
+            continue;
+        };
+        if !tag.closing {
+            let id = tag.attr(body, "id").map(str::to_string);
+            open = Some((level, id, tag.span.1));
+            continue;
+        }
+        let Some((open_level, id, start)) = open.take() else {
+            continue;
+        };
+        if open_level != level {
+            continue;
+        }
+        let text = text_content(&body[start..tag.span.0]);
+        let id = match id {
+            Some(id) => id,
+            None => {
+                let id = slug(&text, taken);
+                // Before the `>` of the start tag.
+                insertions.push((start - 1, id.clone()));
+                id
+            }
+        };
+        headings.push(Heading { level, id, text });
+    }
+    let mut out = String::with_capacity(body.len() + insertions.len() * 16);
+    let mut copied = 0;
+    for (at, id) in insertions {
+        out.push_str(&body[copied..at]);
+        out.push_str(&format!(" id=\"{}\"", escape(&id)));
+        copied = at;
+    }
+    out.push_str(&body[copied..]);
+    (out, headings)
+}
+
+/// Cut every `<name>…</name>` element out of `src`, returning what's left
+/// and the elements' contents. Works on raw text, so script bodies full
+/// of `<` don't confuse it.
+fn take_elements(src: &str, name: &str) -> (String, Vec<String>) {
+    let lower = src.to_ascii_lowercase();
+    let open = format!("<{name}");
+    let close = format!("</{name}");
+    let mut out = String::with_capacity(src.len());
+    let mut contents = Vec::new();
+    let mut pos = 0;
+    while let Some(n) = lower[pos..].find(&open) {
+        let start = pos + n;
+        let after = start + open.len();
+        if lower[after..].starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            out.push_str(&src[pos..after]);
+            pos = after;
+            continue;
+        }
+        out.push_str(&src[pos..start]);
+        let Some(tag_end) = lower[after..].find('>').map(|n| after + n + 1) else {
+            pos = src.len();
+            break;
+        };
+        let end = lower[tag_end..]
+            .find(&close)
+            .map_or(src.len(), |n| tag_end + n);
+        contents.push(src[tag_end..end].to_string());
+        pos = lower[end..].find('>').map_or(src.len(), |n| end + n + 1);
+    }
+    out.push_str(&src[pos.min(src.len())..]);
+    (out, contents)
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&data);
+    Ok(String::from_utf8_lossy(data).into_owned())
+}
+
+/// Split YAML front matter (`---` fenced, at the very top) off `text` and
+/// read the scalar keys metadata cares about.
+fn front_matter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut keys = HashMap::new();
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (keys, text);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (keys, text);
+    };
+    let body = rest[end + 4..]
+        .split_once('\n')
+        .map_or("", |(_, body)| body);
+    for line in rest[..end].lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(['"', '\'']);
+        if !key.starts_with(' ') && !value.is_empty() {
+            keys.insert(key.trim().to_ascii_lowercase(), value.to_string());
+        }
+    }
+    (keys, body)
+}
+
+fn read_markdown(path: &Path) -> Result<(Page, Metadata), String> {
+    let text = read_text(path)?;
+    let (keys, markdown) = front_matter(&text);
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    let events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+
+    // Split before each top-level heading, if there's more than one.
+    let levels: Vec<u32> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Start(Tag::Heading { level, .. }) => Some(*level as u32),
+            _ => None,
+        })
+        .collect();
+    let top = levels.iter().copied().min();
+    let split_at = top.filter(|&top| levels.iter().filter(|&&l| l == top).count() > 1);
+    let mut chunks: Vec<Vec<Event>> = vec![Vec::new()];
+    for event in events {
+        let starts_chapter = matches!(
+            (&event, split_at),
+            (Event::Start(Tag::Heading { level, .. }), Some(top)) if *level as u32 == top
+        );
+        if starts_chapter && chunks.last().is_some_and(|c| !c.is_empty()) {
+            chunks.push(Vec::new());
+        }
+        if let Some(chunk) = chunks.last_mut() {
+            chunk.push(event);
+        }
+    }
+    let parts = chunks
+        .into_iter()
+        .map(|chunk| {
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, chunk.into_iter());
+            tidy(&html)
+        })
+        .filter(|part| !part.trim().is_empty())
+        .collect();
+
+    let authors = keys
+        .get("author")
+        .or_else(|| keys.get("authors"))
+        .map(|a| {
+            a.trim_matches(['[', ']'])
+                .split(',')
+                .map(|a| a.trim().trim_matches(['"', '\'']).to_string())
+                .filter(|a| !a.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let metadata = Metadata {
+        title: keys.get("title").cloned().unwrap_or_default(),
+        authors,
+        description: keys.get("description").cloned(),
+        language: keys.get("lang").or_else(|| keys.get("language")).cloned(),
+        date: keys.get("date").cloned(),
+        ..Default::default()
+    };
+    let page = Page {
+        source: path.canonicalize().map_err(|e| e.to_string())?,
+        title: None,
+        parts,
+        styles: Vec::new(),
+    };
+    Ok((page, metadata))
+}
+
+/// The HTML files under `dir`, in reading order. Hidden entries are skipped.
+fn html_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        let mut entries: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        entries.sort_by(|a, b| {
+            let name = |p: &PathBuf| p.file_name().map(|n| n.to_string_lossy().into_owned());
+            let index =
+                |p: &PathBuf| name(p).is_some_and(|n| n.to_ascii_lowercase().starts_with("index."));
+            // Files before subfolders, `index.*` first.
+            a.is_dir()
+                .cmp(&b.is_dir())
+                .then_with(|| index(b).cmp(&index(a)))
+                .then_with(|| {
+                    natural_cmp(&name(a).unwrap_or_default(), &name(b).unwrap_or_default())
+                })
+        });
+        for path in entries {
+            if path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else if Markup::detect(&path) == Some(Markup::Html) {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    Ok(files)
+}
+
+fn read_html(path: &Path, assets: &mut Assets) -> Result<Page, String> {
+    let source = path.canonicalize().map_err(|e| e.to_string())?;
+    let raw = read_text(path)?;
+    let (raw, _) = take_elements(&raw, "script");
+    let (raw, _) = take_elements(&raw, "noscript");
+    let body_start = body_bounds(&raw).map_or(0, |(open, _)| open.0);
+
+    let mut title = None;
+    let mut styles = Vec::new();
+    let tokens = tokenize(&raw);
+    for (i, token) in tokens.iter().enumerate() {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.closing || (body_start > 0 && tag.span.0 >= body_start) {
+            continue;
+        }
+        if tag.name.eq_ignore_ascii_case("title") && title.is_none() {
+            if let Some(Token::Text(text)) = tokens.get(i + 1) {
+                title = Some(text_content(text)).filter(|t| !t.is_empty());
+            }
+        } else if tag.name.eq_ignore_ascii_case("link")
+            && tag.attr(&raw, "rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("stylesheet"))
+            })
+        {
+            let style = tag
+                .attr(&raw, "href")
+                .and_then(|href| local_file(&source, href))
+                .and_then(|(file, _)| assets.stylesheet(&file));
+            styles.extend(style);
+        }
+    }
+
+    let (raw, inline) = take_elements(&raw, "style");
+    let inline = inline.join("\n");
+    if !inline.trim().is_empty() {
+        let path = format!("styles/style{:04}.css", assets.styles.len() + 1);
+        styles.push(assets.add_style(path, &inline, &source));
+    }
+
+    let body = match body_bounds(&raw) {
+        Some((open, close)) => &raw[open.1..close.0],
+        // No `<body>`: whatever follows the head.
+        None => {
+            let lower = raw.to_ascii_lowercase();
+            lower
+                .find("</head>")
+                .map_or(raw.as_str(), |n| &raw[n + "</head>".len()..])
+        }
+    };
+    let (body, _) = take_elements(body, "title");
+    Ok(Page {
+        source,
+        title,
+        parts: vec![tidy(&body)],
+        styles,
+    })
+}
+
+struct Document {
+    path: String,
+    page: usize,
+    body: String,
+    headings: Vec<Heading>,
+    ids: HashSet<String>,
+}
+
+/// Number the pages' parts as documents, resolve links and images, and
+/// build the TOC.
+fn assemble(
+    pages: Vec<Page>,
+    mut assets: Assets,
+    mut metadata: Metadata,
+    extra_styles: &[String],
+    progress: &mut Progress,
+) -> Result<Book, String> {
+    let mut documents: Vec<Document> = Vec::new();
+    let mut docs_of: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (page_index, page) in pages.iter().enumerate() {
+        let mut taken: HashSet<String> = HashSet::new();
+        for part in &page.parts {
+            taken.extend(id_offsets(part).into_keys());
+        }
+        for part in &page.parts {
+            let (body, headings) = index_headings(part, &mut taken);
+            let ids = id_offsets(&body).into_keys().collect();
+            docs_of
+                .entry(page.source.clone())
+                .or_default()
+                .push(documents.len());
+            documents.push(Document {
+                path: format!("text/part{:04}.xhtml", documents.len() + 1),
+                page: page_index,
+                body,
+                headings,
+                ids,
+            });
+        }
+    }
+    if documents.is_empty() {
+        return Err("nothing to import".into());
+    }
+
+    let target_doc = |file: &Path, fragment: Option<&str>| -> Option<usize> {
+        let docs = docs_of.get(&file.canonicalize().ok()?)?;
+        fragment
+            .and_then(|f| docs.iter().copied().find(|&d| documents[d].ids.contains(f)))
+            .or_else(|| docs.first().copied())
+    };
+    let mut book_documents = Vec::with_capacity(documents.len());
+    let total = documents.len();
+    for (index, doc) in documents.iter().enumerate() {
+        let page = &pages[doc.page];
+        let body = rewrite_attrs(&doc.body, |tag, attr, value| {
+            let tag = tag.to_ascii_lowercase();
+            let attr = attr.to_ascii_lowercase();
+            let (file, fragment) = local_file(&page.source, &unescape(value))?;
+            match (tag.as_str(), attr.as_str()) {
+                ("img", "src") | ("image", "href" | "xlink:href") => {
+                    let image = assets.image(&file)?;
+                    Some(escape(&relative(&doc.path, &image)))
+                }
+                ("a", "href") => {
+                    let target = &documents[target_doc(&file, fragment.as_deref())?].path;
+                    let href = match fragment {
+                        Some(fragment) if *target == doc.path => format!("#{fragment}"),
+                        Some(fragment) => format!("{}#{fragment}", relative(&doc.path, target)),
+                        None => relative(&doc.path, target),
+                    };
+                    Some(escape(&href))
+                }
+                _ => None,
+            }
+        });
+        let head: String = extra_styles
+            .iter()
+            .chain(&page.styles)
+            .map(|style| {
+                format!(
+                    "<link rel=\"stylesheet\" type=\"text/css\" href=\"{}\"/>\n",
+                    escape(&relative(&doc.path, style))
+                )
+            })
+            .collect();
+        let title = doc
+            .headings
+            .first()
+            .map(|h| h.text.as_str())
+            .or(page.title.as_deref())
+            .unwrap_or_default();
+        book_documents.push(Resource::new(
+            doc.path.clone(),
+            "application/xhtml+xml",
+            xhtml_document(title, &head, &body).into_bytes(),
+        ));
+        progress.report((index + 1) as f32 / total as f32)?;
+    }
+
+    let top = documents
+        .iter()
+        .flat_map(|d| &d.headings)
+        .map(|h| h.level)
+        .min();
+    let mut toc = Vec::new();
+    for doc in &documents {
+        let page = &pages[doc.page];
+        let headings: Vec<_> = match top {
+            Some(top) => doc
+                .headings
+                .iter()
+                .filter(|h| h.level < top + TOC_LEVELS && !h.text.is_empty())
+                .collect(),
+            None => Vec::new(),
+        };
+        if headings.is_empty() {
+            // A page without headings still gets an entry, under its title.
+            if let Some(title) = &page.title {
+                toc.push(TocEntry {
+                    label: title.clone(),
+                    href: doc.path.clone(),
+                    depth: 0,
+                });
+            }
+            continue;
+        }
+        for heading in headings {
+            toc.push(TocEntry {
+                label: heading.text.clone(),
+                href: format!("{}#{}", doc.path, heading.id),
+                depth: heading.level - top.unwrap_or(heading.level),
+            });
+        }
+    }
+
+    if metadata.title.is_empty() {
+        metadata.title = pages
+            .first()
+            .and_then(|p| p.title.clone())
+            .or_else(|| {
+                documents
+                    .iter()
+                    .flat_map(|d| &d.headings)
+                    .find(|h| Some(h.level) == top)
+                    .map(|h| h.text.clone())
+            })
+            .unwrap_or_default();
+    }
+    Ok(Book {
+        metadata,
+        documents: book_documents,
+        styles: assets.styles,
+        images: assets.images,
+        toc,
+        cover: None,
+    })
+}
+
+/// Read the Markdown file, HTML file or folder of HTML at `path`.
+pub fn read_markup(path: &Path, markup: Markup, progress: &mut Progress) -> Result<Book, String> {
+    let mut assets = Assets::default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut book = match markup {
+        Markup::Markdown => {
+            let (page, metadata) = read_markdown(path)?;
+            let style = assets.add_style("styles/markdown.css".into(), MARKDOWN_CSS, path);
+            assemble(vec![page], assets, metadata, &[style], progress)?
+        }
+        Markup::Html => {
+            let files = if path.is_dir() {
+                html_files(path)?
+            } else {
+                vec![path.to_path_buf()]
+            };
+            let mut pages = Vec::with_capacity(files.len());
+            for file in &files {
+                pages.push(read_html(file, &mut assets)?);
+            }
+            assemble(pages, assets, Metadata::default(), &[], progress)?
+        }
+    };
+    if book.metadata.title.is_empty() {
+        book.metadata.title = stem;
+    }
+    Ok(book)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("readest-markup-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, data: &[u8]) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, data).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn doc(book: &Book, i: usize) -> String {
+        book.documents[i].text().into_owned()
+    }
+
+    #[test]
+    fn slugs_and_heading_ids() {
+        let mut taken = HashSet::from(["intro".to_string()]);
+        let (body, headings) = index_headings(
+            "<h1>Intro</h1><p>x</p><h2 id=\"keep\">Kept &amp; <em>as is</em></h2><h2>2. Setup!</h2>",
+            &mut taken,
+        );
+        assert_eq!(
+            body,
+            "<h1 id=\"intro-1\">Intro</h1><p>x</p><h2 id=\"keep\">Kept &amp; <em>as is</em></h2>\
+             <h2 id=\"h-2-setup\">2. Setup!</h2>"
+        );
+        let listed: Vec<_> = headings
+            .iter()
+            .map(|h| (h.level, h.id.as_str(), h.text.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (1, "intro-1", "Intro"),
+                (2, "keep", "Kept & as is"),
+                (2, "h-2-setup", "2. Setup!")
+            ]
+        );
+    }
+
+    #[test]
+    fn scripts_and_styles_are_cut_out() {
+        let (rest, scripts) = take_elements(
+            "<p>a</p><SCRIPT type=x>if (a<b) {}</script><scripts/><p>b</p>",
+            "script",
+        );
+        assert_eq!(rest, "<p>a</p><scripts/><p>b</p>");
+        assert_eq!(scripts, ["if (a<b) {}"]);
+    }
+
+    #[test]
+    fn front_matter_is_split_off() {
+        let (keys, body) =
+            front_matter("---\ntitle: \"Notes\"\nauthors: [A, 'B']\ntags:\n  - x\n---\n# Hi\n");
+        assert_eq!(body, "# Hi\n");
+        assert_eq!(keys.get("title").map(String::as_str), Some("Notes"));
+        assert_eq!(keys.get("authors").map(String::as_str), Some("[A, 'B']"));
+        assert!(!keys.contains_key("tags"));
+        assert_eq!(front_matter("# No front matter").1, "# No front matter");
+    }
+
+    #[test]
+    fn html_folder_becomes_a_book() {
+        let dir = TempDir::new("html");
+        dir.write(
+            "index.html",
+            b"<html><head><title>Site</title><link rel=stylesheet href=css/site.css>\
+              <style>p { color: red }</style><script>var x = '<p>';</script></head>\
+              <body><h1>Welcome</h1><img src=\"img/logo.png\"><a href=\"page2.html#more\">next</a>\
+              <a href=\"https://example.com/\">out</a></body></html>",
+        );
+        dir.write("page10.html", b"<body><h2>Last</h2></body>");
+        dir.write(
+            "page2.html",
+            b"<title>Two</title><h1>Second</h1><p id=more>More</p>",
+        );
+        dir.write("css/site.css", b"body { background: url(../img/logo.png) }");
+        dir.write("img/logo.png", PNG);
+        dir.write(".hidden/skip.html", b"<p>no</p>");
+
+        let book = read_markup(&dir.0, Markup::Html, &mut Progress::detached()).unwrap();
+        assert_eq!(book.metadata.title, "Site");
+        assert_eq!(book.documents.len(), 3);
+        assert_eq!(book.images.len(), 1);
+        assert_eq!(book.images[0].path, "images/image0001.png");
+        assert_eq!(
+            book.styles
+                .iter()
+                .map(|s| s.text().into_owned())
+                .collect::<Vec<_>>(),
+            [
+                "body { background: url(\"../images/image0001.png\") }",
+                "p { color: red }"
+            ]
+        );
+        let index = doc(&book, 0);
+        assert!(index.contains("<h1 id=\"welcome\">Welcome</h1>"));
+        assert!(index.contains("<img src=\"../images/image0001.png\"/>"));
+        assert!(index.contains("href=\"part0002.xhtml#more\""));
+        assert!(index.contains("href=\"https://example.com/\""));
+        assert!(index.contains("href=\"../styles/style0001.css\""));
+        assert!(!index.contains("var x"));
+        assert!(doc(&book, 1).contains("<title>Second</title>"));
+        assert!(!doc(&book, 1).contains(">Two<"));
+        let toc: Vec<_> = book
+            .toc
+            .iter()
+            .map(|t| (t.label.as_str(), t.href.as_str(), t.depth))
+            .collect();
+        assert_eq!(
+            toc,
+            [
+                ("Welcome", "text/part0001.xhtml#welcome", 0),
+                ("Second", "text/part0002.xhtml#second", 0),
+                ("Last", "text/part0003.xhtml#last", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_splits_at_top_level_headings() {
+        let dir = TempDir::new("md");
+        dir.write("pics/a.png", PNG);
+        let path = dir.write(
+            "notes.md",
+            b"---\ntitle: My Notes\nauthor: Ann, Bo\n---\nIntro text.\n\n\
+              # One\n\nSee [two](#two) and ![a](pics/a.png).\n\n## Detail\n\n\
+              # Two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+        );
+        let book = read_markup(&path, Markup::Markdown, &mut Progress::detached()).unwrap();
+        assert_eq!(book.metadata.title, "My Notes");
+        assert_eq!(book.metadata.authors, ["Ann", "Bo"]);
+        assert_eq!(book.documents.len(), 3);
+        assert!(doc(&book, 0).contains("<p>Intro text.</p>"));
+        let one = doc(&book, 1);
+        assert!(one.contains("<h1 id=\"one\">One</h1>"));
+        assert!(one.contains("href=\"part0003.xhtml#two\""));
+        assert!(one.contains("src=\"../images/image0001.png\""));
+        assert!(one.contains("href=\"../styles/markdown.css\""));
+        assert!(doc(&book, 2).contains("<table>"));
+        let toc: Vec<_> = book
+            .toc
+            .iter()
+            .map(|t| (t.label.as_str(), t.depth))
+            .collect();
+        assert_eq!(toc, [("One", 0), ("Detail", 1), ("Two", 0)]);
+    }
+}
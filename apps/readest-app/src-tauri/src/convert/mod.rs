@@ -0,0 +1,542 @@
+//! Built-in format conversion: MOBI / AZW / AZW3 → EPUB and EPUB (or
+//! MOBI) → AZW3, without Calibre, plus packaging Markdown and HTML as
+//! books.
+//!
+//! Every conversion reads the source into an in-memory `book::Book`
+//! (documents, stylesheets, images, TOC and metadata) and writes the
+//! target from that:
+//!
+//!   - `kindle` reads MOBI 6 and KF8 (`palmdb` has the container and text
+//!     codecs, `indx` the index tables);
+//!   - `epub` reads and writes EPUB packages;
+//!   - `azw3` writes KF8;
+//!   - `markup` reads a Markdown file, an HTML file or a folder of HTML;
+//!   - `xhtml` has the markup helpers both directions share.
+//!
+//! Jobs run one at a time, off the async runtime, from an in-memory queue.
+//! Output goes to `<dest>.part` and is renamed into place on success, so a
+//! failed or cancelled job never leaves a truncated book behind. Progress
+//! is emitted as `convert://progress` and every status change as
+//! `convert://state` (the full job).
+
+mod azw3;
+mod book;
+mod epub;
+mod indx;
+mod kindle;
+mod markup;
+mod palmdb;
+mod xhtml;
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::format_sniff::{sniff_format, BookFormat};
+use crate::notifications;
+use crate::taskbar_progress;
+use crate::transfer_file::ensure_path_allowed;
+
+pub const PROGRESS_EVENT: &str = "convert://progress";
+pub const STATE_EVENT: &str = "convert://state";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const CANCELLED: &str = "cancelled";
+const UNSUPPORTED: &str = "only EPUB, MOBI, AZW, AZW3, Markdown and HTML files can be converted";
+/// Share of a job's progress the reading half accounts for.
+const READ_SHARE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversionTarget {
+    Epub,
+    Azw3,
+}
+
+impl ConversionTarget {
+    fn extension(self) -> &'static str {
+        match self {
+            ConversionTarget::Epub => "epub",
+            ConversionTarget::Azw3 => "azw3",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConversionStatus {
+    Queued,
+    Converting,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionJob {
+    pub id: String,
+    pub input_path: String,
+    pub output_path: String,
+    pub target: ConversionTarget,
+    pub status: ConversionStatus,
+    /// 0.0 to 1.0.
+    pub progress: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionProgress {
+    pub id: String,
+    pub progress: f32,
+}
+
+/// Progress reporting and cancellation for the converters: `report` takes
+/// the fraction of the current stage done and fails once the job has been
+/// cancelled, so converters bail out with `?`.
+pub struct Progress {
+    cancelled: Arc<AtomicBool>,
+    start: f32,
+    share: f32,
+    last: Option<Instant>,
+    sink: Box<dyn FnMut(f32) + Send>,
+}
+
+impl Progress {
+    fn new(cancelled: Arc<AtomicBool>, sink: impl FnMut(f32) + Send + 'static) -> Self {
+        Self {
+            cancelled,
+            start: 0.0,
+            share: 1.0,
+            last: None,
+            sink: Box::new(sink),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self::new(Arc::new(AtomicBool::new(false)), |_| {})
+    }
+
+    fn stage(&mut self, start: f32, share: f32) {
+        self.start = start;
+        self.share = share;
+    }
+
+    pub fn report(&mut self, fraction: f32) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+        if self.last.map_or(true, |t| t.elapsed() >= PROGRESS_INTERVAL) {
+            self.last = Some(Instant::now());
+            (self.sink)(self.start + self.share * fraction.clamp(0.0, 1.0));
+        }
+        Ok(())
+    }
+}
+
+/// Convert the file at `input` and return the target's bytes.
+fn convert(
+    input: &Path,
+    target: ConversionTarget,
+    progress: &mut Progress,
+) -> Result<Vec<u8>, String> {
+    progress.stage(0.0, READ_SHARE);
+    let book = match markup::Markup::detect(input) {
+        Some(markup) => markup::read_markup(input, markup, progress)?,
+        None => {
+            let format = sniff_format(input).ok_or("unrecognised book format")?;
+            let data = std::fs::read(input).map_err(|e| e.to_string())?;
+            match format {
+                BookFormat::Mobi => kindle::read_kindle(&data, progress)?,
+                BookFormat::Epub => epub::read_epub(&data, progress)?,
+                _ => return Err(UNSUPPORTED.into()),
+            }
+        }
+    };
+    progress.stage(READ_SHARE, 1.0 - READ_SHARE);
+    match target {
+        ConversionTarget::Epub => epub::write_epub(&book, progress),
+        ConversionTarget::Azw3 => azw3::write_azw3(&book, progress),
+    }
+}
+
+struct Inner {
+    jobs: Mutex<Vec<ConversionJob>>,
+    /// Cancel flag of the job being converted, if any.
+    running: Mutex<Option<(String, Arc<AtomicBool>)>>,
+}
+
+pub struct Converter(Arc<Inner>);
+
+impl Default for Converter {
+    fn default() -> Self {
+        Self(Arc::new(Inner {
+            jobs: Mutex::new(Vec::new()),
+            running: Mutex::new(None),
+        }))
+    }
+}
+
+impl Inner {
+    fn get(&self, id: &str) -> Option<ConversionJob> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|j| j.id == id)
+            .cloned()
+    }
+
+    /// Apply `f` to the job and broadcast the new state.
+    fn update(&self, app: &AppHandle, id: &str, f: impl FnOnce(&mut ConversionJob)) {
+        let updated = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.iter_mut().find(|j| j.id == id).map(|job| {
+                f(job);
+                job.clone()
+            })
+        };
+        if let Some(job) = updated {
+            let _ = app.emit(STATE_EVENT, &job);
+        }
+    }
+
+    fn set_progress(&self, id: &str, progress: f32) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|j| j.id == id) {
+            job.progress = progress;
+        }
+    }
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "cv-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Task id of the job on the taskbar progress bar.
+fn taskbar_task(id: &str) -> String {
+    format!("conversion:{id}")
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn part_path(dest: &str) -> PathBuf {
+    PathBuf::from(format!("{dest}.part"))
+}
+
+/// `<input stem>.<ext>` next to the input, numbered if that's taken.
+fn default_output_path(input: &Path, target: ConversionTarget) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "book".to_string());
+    let ext = target.extension();
+    let mut candidate = input.with_file_name(format!("{stem}.{ext}"));
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = input.with_file_name(format!("{stem} ({n}).{ext}"));
+        n += 1;
+    }
+    candidate
+}
+
+/// Start the next queued job unless one is already converting.
+fn schedule(app: &AppHandle, inner: &Arc<Inner>) {
+    let mut running = inner.running.lock().unwrap();
+    if running.is_some() {
+        return;
+    }
+    let Some(job) = inner
+        .jobs
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|j| j.status == ConversionStatus::Queued)
+        .cloned()
+    else {
+        return;
+    };
+    let cancelled = Arc::new(AtomicBool::new(false));
+    *running = Some((job.id.clone(), cancelled.clone()));
+    drop(running);
+    inner.update(app, &job.id, |job| {
+        job.status = ConversionStatus::Converting
+    });
+
+    let app = app.clone();
+    let inner = inner.clone();
+    tauri::async_runtime::spawn(async move {
+        let progress = {
+            let app = app.clone();
+            let inner = inner.clone();
+            let id = job.id.clone();
+            Progress::new(cancelled, move |progress| {
+                inner.set_progress(&id, progress);
+                taskbar_progress::report(&app, &taskbar_task(&id), progress as f64);
+                let _ = app.emit(
+                    PROGRESS_EVENT,
+                    ConversionProgress {
+                        id: id.clone(),
+                        progress,
+                    },
+                );
+            })
+        };
+        let part = part_path(&job.output_path);
+        let result = {
+            let job = job.clone();
+            let part = part.clone();
+            tauri::async_runtime::spawn_blocking(move || run(&job, &part, progress))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()))
+        };
+        let failed = matches!(&result, Err(e) if e != CANCELLED);
+        taskbar_progress::finish(&app, &taskbar_task(&job.id), failed);
+        match result {
+            Ok(()) => {
+                inner.update(&app, &job.id, |job| {
+                    job.status = ConversionStatus::Completed;
+                    job.progress = 1.0;
+                });
+                notifications::notify(
+                    &app,
+                    "Conversion finished",
+                    &format!("{} is ready.", file_name(&job.output_path)),
+                );
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&part);
+                if e == CANCELLED {
+                    inner.update(&app, &job.id, |job| {
+                        job.status = ConversionStatus::Cancelled
+                    });
+                } else {
+                    log::error!("Converting {} failed: {e}", job.input_path);
+                    notifications::notify(
+                        &app,
+                        "Conversion failed",
+                        &format!("{}: {e}", file_name(&job.input_path)),
+                    );
+                    inner.update(&app, &job.id, |job| {
+                        job.status = ConversionStatus::Failed;
+                        job.error = Some(e);
+                    });
+                }
+            }
+        }
+        *inner.running.lock().unwrap() = None;
+        schedule(&app, &inner);
+    });
+}
+
+fn run(job: &ConversionJob, part: &Path, mut progress: Progress) -> Result<(), String> {
+    let input = Path::new(&job.input_path);
+    let output = convert(input, job.target, &mut progress)?;
+    progress.report(1.0)?;
+    if let Some(dir) = part.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(part, output).map_err(|e| e.to_string())?;
+    std::fs::rename(part, &job.output_path).map_err(|e| e.to_string())
+}
+
+/// Queue a conversion of `input_path` to `target`. Without an
+/// `output_path` the result goes next to the input.
+#[tauri::command]
+pub fn convert_book(
+    app: AppHandle,
+    converter: State<'_, Converter>,
+    input_path: String,
+    output_path: Option<String>,
+    target: ConversionTarget,
+) -> Result<ConversionJob, String> {
+    ensure_path_allowed(&app, &input_path).map_err(|e| e.to_string())?;
+    let input = Path::new(&input_path);
+    if markup::Markup::detect(input).is_none() {
+        match (sniff_format(input), target) {
+            (Some(BookFormat::Epub), ConversionTarget::Epub) => {
+                return Err("the book is already an EPUB".into())
+            }
+            (Some(BookFormat::Epub | BookFormat::Mobi), _) => {}
+            _ => return Err(UNSUPPORTED.into()),
+        }
+    }
+    let output_path = match output_path {
+        Some(path) => path,
+        None => default_output_path(input, target)
+            .to_string_lossy()
+            .into_owned(),
+    };
+    ensure_path_allowed(&app, &output_path).map_err(|e| e.to_string())?;
+    if output_path == input_path {
+        return Err("the output would overwrite the input".into());
+    }
+
+    let job = ConversionJob {
+        id: new_id(),
+        input_path,
+        output_path,
+        target,
+        status: ConversionStatus::Queued,
+        progress: 0.0,
+        error: None,
+    };
+    converter.0.jobs.lock().unwrap().push(job.clone());
+    let _ = app.emit(STATE_EVENT, &job);
+    schedule(&app, &converter.0);
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn list_conversions(converter: State<'_, Converter>) -> Vec<ConversionJob> {
+    converter.0.jobs.lock().unwrap().clone()
+}
+
+/// Cancel a queued or running conversion. Finished jobs are dropped from
+/// the list instead.
+#[tauri::command]
+pub fn cancel_conversion(
+    app: AppHandle,
+    converter: State<'_, Converter>,
+    id: String,
+) -> Result<(), String> {
+    let job = converter
+        .0
+        .get(&id)
+        .ok_or_else(|| format!("unknown conversion: {id}"))?;
+    match job.status {
+        ConversionStatus::Queued => {
+            converter
+                .0
+                .update(&app, &id, |job| job.status = ConversionStatus::Cancelled);
+        }
+        ConversionStatus::Converting => {
+            if let Some((running, cancelled)) = &*converter.0.running.lock().unwrap() {
+                if *running == id {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        _ => converter.0.jobs.lock().unwrap().retain(|j| j.id != id),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use book::{Book, Metadata, Resource, TocEntry};
+
+    fn sample_book() -> Book {
+        let chapter = |n: usize, body: &str| {
+            Resource::new(
+                format!("text/ch{n}.xhtml"),
+                "application/xhtml+xml",
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">\
+                     <head><title>Ch {n}</title><link rel=\"stylesheet\" href=\"../styles/main.css\"/></head>\
+                     <body>{body}</body></html>"
+                )
+                .into_bytes(),
+            )
+        };
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        Book {
+            metadata: Metadata {
+                title: "Sample Book".into(),
+                authors: vec!["A. Writer".into()],
+                language: Some("en".into()),
+                identifier: Some("urn:isbn:9780000000000".into()),
+                ..Metadata::default()
+            },
+            documents: vec![
+                chapter(
+                    1,
+                    "<h1 id=\"c1\">One</h1><p>Café <a href=\"ch2.xhtml#s2\">next</a></p>\
+                     <img src=\"../images/cover.png\" alt=\"\"/>",
+                ),
+                chapter(
+                    2,
+                    &format!(
+                        "<h1>Two</h1>{}<p id=\"s2\">There</p>",
+                        "<p>filler</p>".repeat(800)
+                    ),
+                ),
+            ],
+            styles: vec![Resource::new(
+                "styles/main.css",
+                "text/css",
+                b"p { margin: 0 }".to_vec(),
+            )],
+            images: vec![Resource::new("images/cover.png", "image/png", png)],
+            toc: vec![
+                TocEntry {
+                    label: "One".into(),
+                    href: "text/ch1.xhtml".into(),
+                    depth: 0,
+                },
+                TocEntry {
+                    label: "There".into(),
+                    href: "text/ch2.xhtml#s2".into(),
+                    depth: 1,
+                },
+            ],
+            cover: Some("images/cover.png".into()),
+        }
+    }
+
+    #[test]
+    fn azw3_round_trips_through_the_kindle_reader() {
+        let mut progress = Progress::detached();
+        let azw3 = azw3::write_azw3(&sample_book(), &mut progress).unwrap();
+        let book = kindle::read_kindle(&azw3, &mut progress).unwrap();
+
+        assert_eq!(book.metadata.title, "Sample Book");
+        assert_eq!(book.metadata.authors, vec!["A. Writer".to_string()]);
+        assert_eq!(book.documents.len(), 2);
+        assert_eq!(book.styles.len(), 1);
+        assert_eq!(book.images.len(), 1);
+        assert_eq!(book.cover.as_deref(), Some(book.images[0].path.as_str()));
+
+        let ch1 = book.documents[0].text();
+        assert!(ch1.contains("Café"));
+        assert!(ch1.contains("href=\"part0001.xhtml#s2\""));
+        assert!(ch1.contains(&format!("src=\"../{}\"", book.images[0].path)));
+        assert!(ch1.contains(&format!("href=\"../{}\"", book.styles[0].path)));
+
+        let labels: Vec<(&str, u32)> = book
+            .toc
+            .iter()
+            .map(|e| (e.label.as_str(), e.depth))
+            .collect();
+        assert_eq!(labels, vec![("One", 0), ("There", 1)]);
+        assert_eq!(book.toc[1].href, "text/part0001.xhtml#s2");
+    }
+
+    #[test]
+    fn cancelled_jobs_stop_at_the_next_report() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut progress = Progress::new(cancelled.clone(), |_| {});
+        assert!(progress.report(0.5).is_ok());
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(progress.report(0.6), Err(CANCELLED.to_string()));
+    }
+}
@@ -0,0 +1,413 @@
+// PalmDB container plus the text codecs MOBI files use.
+//
+// A MOBI/AZW3 file is a PalmDB database: a 78-byte header, a table of
+// record offsets and then the records back to back. Text records are
+// either stored raw, PalmDOC-compressed (a byte-oriented LZ77 variant) or
+// HUFF/CDIC-compressed (Kindlegen's Huffman coding for large books), and
+// each may carry "trailing entries" after the compressed payload that have
+// to be cut off first.
+
+use std::collections::HashMap;
+
+pub const RECORD_SIZE: usize = 4096;
+const HEADER_LEN: usize = 78;
+
+pub const COMPRESSION_NONE: u16 = 1;
+pub const COMPRESSION_PALMDOC: u16 = 2;
+pub const COMPRESSION_HUFFCDIC: u16 = 17480;
+
+pub fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+pub fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Split a PalmDB file into its records.
+pub fn read_records(data: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let count = u16_at(data, 76).ok_or("not a PalmDB file")? as usize;
+    let mut offsets = Vec::with_capacity(count + 1);
+    for i in 0..count {
+        let offset = u32_at(data, HEADER_LEN + i * 8).ok_or("truncated record table")?;
+        offsets.push(offset as usize);
+    }
+    offsets.push(data.len());
+    let mut records = Vec::with_capacity(count);
+    for pair in offsets.windows(2) {
+        let (start, end) = (pair[0], pair[1].max(pair[0]));
+        records.push(
+            data.get(start..end)
+                .ok_or_else(|| format!("record at {start} lies outside the file"))?,
+        );
+    }
+    Ok(records)
+}
+
+/// Assemble a PalmDB file from `records`, typed `BOOKMOBI`.
+pub fn write_records(name: &str, records: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut db_name: Vec<u8> = name
+        .bytes()
+        .map(|b| if b.is_ascii_alphanumeric() { b } else { b'_' })
+        .take(31)
+        .collect();
+    db_name.resize(32, 0);
+    out.extend_from_slice(&db_name);
+    out.extend_from_slice(&[0; 4]); // attributes, version
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0);
+    out.extend_from_slice(&now.to_be_bytes()); // created
+    out.extend_from_slice(&now.to_be_bytes()); // modified
+    out.extend_from_slice(&[0; 16]); // backup, modnum, appinfo, sortinfo
+    out.extend_from_slice(b"BOOKMOBI");
+    out.extend_from_slice(&((2 * records.len()).saturating_sub(1) as u32).to_be_bytes());
+    out.extend_from_slice(&[0; 4]); // next record list
+    out.extend_from_slice(&(records.len() as u16).to_be_bytes());
+
+    let mut offset = HEADER_LEN + records.len() * 8 + 2;
+    for (i, record) in records.iter().enumerate() {
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&((2 * i) as u32).to_be_bytes());
+        offset += record.len();
+    }
+    out.extend_from_slice(&[0; 2]);
+    for record in records {
+        out.extend_from_slice(record);
+    }
+    out
+}
+
+/// The size of the trailing entries `flags` (MOBI header offset 0xF2)
+/// says `record` ends with.
+pub fn trailing_entries_len(record: &[u8], flags: u16) -> usize {
+    let mut len = 0;
+    let mut bits = flags >> 1;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            len += backward_varint(&record[..record.len().saturating_sub(len)]);
+        }
+        bits >>= 1;
+    }
+    if flags & 1 != 0 {
+        if let Some(last) = record.len().checked_sub(len + 1).map(|i| record[i]) {
+            len += (last & 0x3) as usize + 1;
+        }
+    }
+    len.min(record.len())
+}
+
+/// A variable-width integer stored backwards at the end of `data`, as the
+/// trailing entries record their own size.
+fn backward_varint(data: &[u8]) -> usize {
+    let mut value = 0usize;
+    let mut shift = 0;
+    for &byte in data.iter().rev() {
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 != 0 || shift >= 28 {
+            break;
+        }
+    }
+    value
+}
+
+/// Forward variable-width integer as INDX entries and CNCX strings use it:
+/// 7 bits per byte, most significant first, the last byte flagged.
+pub fn encode_varint(mut value: u32) -> Vec<u8> {
+    let mut bytes = vec![(value & 0x7f) as u8 | 0x80];
+    value >>= 7;
+    while value != 0 {
+        bytes.push((value & 0x7f) as u8);
+        value >>= 7;
+    }
+    bytes.reverse();
+    bytes
+}
+
+/// Decode a forward varint, returning it with the bytes consumed.
+pub fn decode_varint(data: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, &byte) in data.iter().enumerate().take(5) {
+        value = (value << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 != 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+pub fn palmdoc_decompress(data: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(RECORD_SIZE);
+    let mut i = 0;
+    while i < data.len() {
+        let c = data[i];
+        i += 1;
+        match c {
+            0 | 0x09..=0x7f => out.push(c),
+            0x01..=0x08 => {
+                let end = (i + c as usize).min(data.len());
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            0x80..=0xbf => {
+                let Some(&next) = data.get(i) else {
+                    break;
+                };
+                i += 1;
+                let pair = ((c as usize) << 8) | next as usize;
+                let distance = (pair >> 3) & 0x7ff;
+                let len = (pair & 0x7) + 3;
+                if distance == 0 || distance > out.len() {
+                    continue;
+                }
+                let start = out.len() - distance;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => {
+                out.push(b' ');
+                out.push(c ^ 0x80);
+            }
+        }
+    }
+    out
+}
+
+pub fn palmdoc_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    // Positions seen so far for each 3-byte prefix, newest last.
+    let mut seen: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+    let remember = |seen: &mut HashMap<[u8; 3], Vec<usize>>, pos: usize| {
+        if let Some(key) = data.get(pos..pos + 3) {
+            seen.entry([key[0], key[1], key[2]]).or_default().push(pos);
+        }
+    };
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if let Some(key) = data.get(i..i + 3) {
+            if let Some(candidates) = seen.get(&[key[0], key[1], key[2]]) {
+                for &start in candidates.iter().rev() {
+                    let distance = i - start;
+                    if distance > 2047 {
+                        break;
+                    }
+                    let len = (0..10.min(data.len() - i))
+                        .take_while(|&k| data[start + k] == data[i + k])
+                        .count();
+                    if len > best.0 {
+                        best = (len, distance);
+                    }
+                    if len == 10 {
+                        break;
+                    }
+                }
+            }
+        }
+        if best.0 >= 3 {
+            let (len, distance) = best;
+            let pair = 0x8000 | (distance << 3) | (len - 3);
+            out.extend_from_slice(&(pair as u16).to_be_bytes());
+            for pos in i..i + len {
+                remember(&mut seen, pos);
+            }
+            i += len;
+            continue;
+        }
+        let c = data[i];
+        if c == b' ' && matches!(data.get(i + 1), Some(0x40..=0x7f)) {
+            out.push(data[i + 1] ^ 0x80);
+            remember(&mut seen, i);
+            remember(&mut seen, i + 1);
+            i += 2;
+            continue;
+        }
+        if c == 0 || (0x09..=0x7f).contains(&c) {
+            out.push(c);
+            remember(&mut seen, i);
+            i += 1;
+            continue;
+        }
+        let run = data[i..]
+            .iter()
+            .take(8)
+            .take_while(|&&b| !(b == 0 || (0x09..=0x7f).contains(&b)))
+            .count();
+        out.push(run as u8);
+        out.extend_from_slice(&data[i..i + run]);
+        for pos in i..i + run {
+            remember(&mut seen, pos);
+        }
+        i += run;
+    }
+    out
+}
+
+/// Kindlegen's Huffman decoder, loaded from the HUFF record and the CDIC
+/// phrase dictionaries that follow it.
+pub struct HuffCdic {
+    dict1: Vec<(u32, bool, u64)>,
+    min_code: Vec<u64>,
+    max_code: Vec<u64>,
+    dictionary: Vec<(Vec<u8>, bool)>,
+}
+
+impl HuffCdic {
+    pub fn new(huff: &[u8], cdics: &[&[u8]]) -> Result<Self, String> {
+        if !huff.starts_with(b"HUFF\x00\x00\x00\x18") {
+            return Err("bad HUFF record".into());
+        }
+        let off1 = u32_at(huff, 8).ok_or("bad HUFF record")? as usize;
+        let off2 = u32_at(huff, 12).ok_or("bad HUFF record")? as usize;
+        let mut dict1 = Vec::with_capacity(256);
+        for i in 0..256 {
+            let v = u32_at(huff, off1 + i * 4).ok_or("truncated HUFF record")?;
+            let code_len = v & 0x1f;
+            if code_len == 0 {
+                return Err("bad HUFF code length".into());
+            }
+            let max_code = (((v >> 8) as u64 + 1) << (32 - code_len)) - 1;
+            dict1.push((code_len, v & 0x80 != 0, max_code));
+        }
+        let mut min_code = vec![0u64];
+        let mut max_code = vec![u64::MAX >> 32];
+        for code_len in 1..=32u32 {
+            let i = off2 + (code_len as usize - 1) * 8;
+            let min = u32_at(huff, i).ok_or("truncated HUFF record")? as u64;
+            let max = u32_at(huff, i + 4).ok_or("truncated HUFF record")? as u64;
+            min_code.push(min << (32 - code_len));
+            max_code.push(((max + 1) << (32 - code_len)) - 1);
+        }
+        let mut dictionary = Vec::new();
+        for cdic in cdics {
+            if !cdic.starts_with(b"CDIC\x00\x00\x00\x10") {
+                return Err("bad CDIC record".into());
+            }
+            let phrases = u32_at(cdic, 8).ok_or("bad CDIC record")? as usize;
+            let bits = u32_at(cdic, 12).ok_or("bad CDIC record")?;
+            let n = (1usize << bits.min(31)).min(phrases.saturating_sub(dictionary.len()));
+            for k in 0..n {
+                let off = u16_at(cdic, 16 + k * 2).ok_or("truncated CDIC record")? as usize;
+                let len = u16_at(cdic, 16 + off).ok_or("truncated CDIC record")?;
+                let start = 18 + off;
+                let end = (start + (len & 0x7fff) as usize).min(cdic.len());
+                dictionary.push((cdic[start.min(end)..end].to_vec(), len & 0x8000 != 0));
+            }
+        }
+        Ok(Self {
+            dict1,
+            min_code,
+            max_code,
+            dictionary,
+        })
+    }
+
+    pub fn decompress(&mut self, data: &[u8]) -> Vec<u8> {
+        self.unpack(data, 0)
+    }
+
+    fn unpack(&mut self, data: &[u8], depth: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        if depth > 32 {
+            return out;
+        }
+        let mut padded = data.to_vec();
+        padded.extend_from_slice(&[0; 8]);
+        let word = |pos: usize| u64::from_be_bytes(padded[pos..pos + 8].try_into().unwrap());
+        let mut bits_left = data.len() as i64 * 8;
+        let mut pos = 0;
+        let mut x = word(pos);
+        let mut n: i32 = 32;
+        loop {
+            if n <= 0 {
+                pos += 4;
+                if pos + 8 > padded.len() {
+                    break;
+                }
+                x = word(pos);
+                n += 32;
+            }
+            let code = (x >> n) & 0xffff_ffff;
+            let (mut code_len, term, mut max_code) = self.dict1[(code >> 24) as usize];
+            if !term {
+                while (code_len as usize) < 32 && code < self.min_code[code_len as usize] {
+                    code_len += 1;
+                }
+                max_code = self.max_code[code_len as usize];
+            }
+            n -= code_len as i32;
+            bits_left -= code_len as i64;
+            if bits_left < 0 {
+                break;
+            }
+            let index = ((max_code - code) >> (32 - code_len)) as usize;
+            let Some((phrase, flat)) = self.dictionary.get(index).cloned() else {
+                break;
+            };
+            if flat {
+                out.extend_from_slice(&phrase);
+            } else {
+                let expanded = self.unpack(&phrase, depth + 1);
+                out.extend_from_slice(&expanded);
+                self.dictionary[index] = (expanded, true);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palmdoc_round_trips() {
+        let samples: [&[u8]; 4] = [
+            b"",
+            b"<p>The quick brown fox jumps over the lazy dog.</p><p>The quick brown fox</p>",
+            "caf\u{e9} na\u{ef}ve \u{2014} \u{201c}quoted\u{201d}".as_bytes(),
+            &[0u8, 1, 2, 3, 200, 201, 202, 32, 65, 32, 32, 9, 255],
+        ];
+        for sample in samples {
+            assert_eq!(palmdoc_decompress(&palmdoc_compress(sample)), sample);
+        }
+        let repetitive = b"abcabcabcabcabcabcabcabc".repeat(100);
+        let compressed = palmdoc_compress(&repetitive);
+        assert!(compressed.len() < repetitive.len() / 3);
+        assert_eq!(palmdoc_decompress(&compressed), repetitive);
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_383, 16_384, 0x0fff_ffff] {
+            let bytes = encode_varint(value);
+            assert_eq!(decode_varint(&bytes), Some((value, bytes.len())));
+        }
+        assert_eq!(encode_varint(0), vec![0x80]);
+    }
+
+    #[test]
+    fn trailing_entries_are_measured() {
+        // A 3-byte TBS entry (size stored backwards) after a 2-byte
+        // multibyte overlap (2 bytes, count byte 0b01).
+        let record = [b'a', b'b', 0xC3, 0x01, 0x00, 0x00, 0x83];
+        assert_eq!(trailing_entries_len(&record, 0b11), 5);
+        assert_eq!(trailing_entries_len(&record[..4], 0b01), 2);
+        assert_eq!(trailing_entries_len(b"plain", 0), 0);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let records = vec![b"header".to_vec(), Vec::new(), b"text".to_vec()];
+        let file = write_records("My Book: A Story", &records);
+        assert_eq!(&file[60..68], b"BOOKMOBI");
+        assert_eq!(&file[..16], b"My_Book__A_Story");
+        let read = read_records(&file).unwrap();
+        assert_eq!(read, vec![&b"header"[..], &b""[..], &b"text"[..]]);
+        assert!(read_records(b"short").is_err());
+    }
+}
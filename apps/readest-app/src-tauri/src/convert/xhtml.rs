@@ -0,0 +1,581 @@
+// Markup helpers shared by the converters.
+//
+// MOBI 6 text is tag soup (unquoted attributes, unclosed `<p>`s, HTML
+// entities, `mbp:` tags), while EPUB content documents have to be
+// well-formed XML. `tidy` turns the former into the latter. The
+// tokenizer keeps byte spans so `rewrite_attrs` can swap attribute values
+// (image and link targets) without re-serialising documents that are
+// already fine.
+
+use std::collections::HashMap;
+
+const VOID: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "wbr",
+];
+/// Starting one of these closes an open `<p>`.
+const BLOCKS: [&str; 20] = [
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "blockquote",
+    "pre",
+    "hr",
+    "center",
+    "section",
+    "figure",
+    "address",
+    "li",
+];
+/// Wrappers `tidy` drops (content kept) because callers add their own.
+const DROPPED: [&str; 7] = [
+    "html",
+    "head",
+    "body",
+    "guide",
+    "reference",
+    "title",
+    "meta",
+];
+
+/// Latin-1 entity names, in code point order from U+00A0.
+const LATIN1_ENTITIES: [&str; 96] = [
+    "nbsp", "iexcl", "cent", "pound", "curren", "yen", "brvbar", "sect", "uml", "copy", "ordf",
+    "laquo", "not", "shy", "reg", "macr", "deg", "plusmn", "sup2", "sup3", "acute", "micro",
+    "para", "middot", "cedil", "sup1", "ordm", "raquo", "frac14", "frac12", "frac34", "iquest",
+    "Agrave", "Aacute", "Acirc", "Atilde", "Auml", "Aring", "AElig", "Ccedil", "Egrave", "Eacute",
+    "Ecirc", "Euml", "Igrave", "Iacute", "Icirc", "Iuml", "ETH", "Ntilde", "Ograve", "Oacute",
+    "Ocirc", "Otilde", "Ouml", "times", "Oslash", "Ugrave", "Uacute", "Ucirc", "Uuml", "Yacute",
+    "THORN", "szlig", "agrave", "aacute", "acirc", "atilde", "auml", "aring", "aelig", "ccedil",
+    "egrave", "eacute", "ecirc", "euml", "igrave", "iacute", "icirc", "iuml", "eth", "ntilde",
+    "ograve", "oacute", "ocirc", "otilde", "ouml", "divide", "oslash", "ugrave", "uacute", "ucirc",
+    "uuml", "yacute", "thorn", "yuml",
+];
+const OTHER_ENTITIES: [(&str, u32); 34] = [
+    ("OElig", 338),
+    ("oelig", 339),
+    ("Scaron", 352),
+    ("scaron", 353),
+    ("Yuml", 376),
+    ("fnof", 402),
+    ("circ", 710),
+    ("tilde", 732),
+    ("ensp", 8194),
+    ("emsp", 8195),
+    ("thinsp", 8201),
+    ("zwnj", 8204),
+    ("zwj", 8205),
+    ("lrm", 8206),
+    ("rlm", 8207),
+    ("ndash", 8211),
+    ("mdash", 8212),
+    ("lsquo", 8216),
+    ("rsquo", 8217),
+    ("sbquo", 8218),
+    ("ldquo", 8220),
+    ("rdquo", 8221),
+    ("bdquo", 8222),
+    ("dagger", 8224),
+    ("Dagger", 8225),
+    ("bull", 8226),
+    ("hellip", 8230),
+    ("prime", 8242),
+    ("Prime", 8243),
+    ("lsaquo", 8249),
+    ("rsaquo", 8250),
+    ("euro", 8364),
+    ("trade", 8482),
+    ("rarr", 8594),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attr<'a> {
+    pub name: &'a str,
+    /// Byte span of the value in the source, without quotes.
+    pub value: Option<(usize, usize)>,
+    pub quoted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag<'a> {
+    pub name: &'a str,
+    pub closing: bool,
+    pub self_closing: bool,
+    pub attrs: Vec<Attr<'a>>,
+    /// Byte span of the whole tag in the source.
+    pub span: (usize, usize),
+}
+
+impl<'a> Tag<'a> {
+    pub fn attr(&self, src: &'a str, name: &str) -> Option<&'a str> {
+        self.attrs
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(name))
+            .map(|a| a.value.map_or("", |(s, e)| &src[s..e]))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    Tag(Tag<'a>),
+    /// Comments, doctypes, processing instructions, CDATA.
+    Markup(&'a str),
+}
+
+fn is_name_byte(b: u8) -> bool {
+    !(b.is_ascii_whitespace() || matches!(b, b'>' | b'/' | b'=' | b'<' | b'"' | b'\''))
+}
+
+fn parse_tag(src: &str, start: usize) -> Option<(Tag<'_>, usize)> {
+    let bytes = src.as_bytes();
+    let mut i = start + 1;
+    let closing = bytes.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+    if !bytes.get(i)?.is_ascii_alphabetic() {
+        return None;
+    }
+    let name_start = i;
+    while i < bytes.len() && is_name_byte(bytes[i]) {
+        i += 1;
+    }
+    let name = &src[name_start..i];
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => break,
+            Some(b'>') => {
+                i += 1;
+                break;
+            }
+            Some(b'/') => {
+                i += 1;
+                if bytes.get(i) == Some(&b'>') {
+                    self_closing = true;
+                    i += 1;
+                    break;
+                }
+                continue;
+            }
+            Some(b'<') => break,
+            _ => {}
+        }
+        let attr_start = i;
+        while i < bytes.len() && is_name_byte(bytes[i]) {
+            i += 1;
+        }
+        if i == attr_start {
+            // A stray quote or `=`; skip it.
+            i += 1;
+            continue;
+        }
+        let attr_name = &src[attr_start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut value = None;
+        let mut quoted = false;
+        if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            match bytes.get(i) {
+                Some(&q @ (b'"' | b'\'')) => {
+                    let value_start = i + 1;
+                    let end = src[value_start..]
+                        .find(q as char)
+                        .map_or(src.len(), |n| value_start + n);
+                    value = Some((value_start, end));
+                    quoted = true;
+                    i = (end + 1).min(src.len());
+                }
+                _ => {
+                    let value_start = i;
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    value = Some((value_start, i));
+                }
+            }
+        }
+        attrs.push(Attr {
+            name: attr_name,
+            value,
+            quoted,
+        });
+    }
+    Some((
+        Tag {
+            name,
+            closing,
+            self_closing,
+            attrs,
+            span: (start, i),
+        },
+        i,
+    ))
+}
+
+pub fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while let Some(offset) = src[i..].find('<') {
+        let start = i + offset;
+        let rest = &src[start..];
+        let markup_end = if rest.starts_with("<!--") {
+            Some(rest.find("-->").map_or(src.len(), |n| start + n + 3))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(rest.find("]]>").map_or(src.len(), |n| start + n + 3))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(rest.find('>').map_or(src.len(), |n| start + n + 1))
+        } else {
+            None
+        };
+        let (token, end) = match markup_end {
+            Some(end) => (Token::Markup(&src[start..end]), end),
+            None => match parse_tag(src, start) {
+                Some((tag, end)) => (Token::Tag(tag), end),
+                None => {
+                    i = start + 1;
+                    continue;
+                }
+            },
+        };
+        if text_start < start {
+            tokens.push(Token::Text(&src[text_start..start]));
+        }
+        tokens.push(token);
+        i = end;
+        text_start = end;
+    }
+    if text_start < src.len() {
+        tokens.push(Token::Text(&src[text_start..]));
+    }
+    tokens
+}
+
+fn entity_char(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    if let Some(i) = LATIN1_ENTITIES.iter().position(|&e| e == name) {
+        return char::from_u32(0xa0 + i as u32);
+    }
+    OTHER_ENTITIES
+        .iter()
+        .find(|(e, _)| *e == name)
+        .and_then(|&(_, code)| char::from_u32(code))
+}
+
+/// Escape `text` for XML while keeping the entities it already uses:
+/// the five XML ones stay, HTML ones become characters and a bare `&`
+/// becomes `&amp;`.
+pub fn escape_markup(text: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '&' => {
+                let entity = rest[1..]
+                    .find(';')
+                    .filter(|&n| n > 0 && n <= 10)
+                    .map(|n| &rest[1..1 + n])
+                    .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric() || c == '#'));
+                match entity {
+                    Some(e @ ("amp" | "lt" | "gt" | "quot" | "apos")) => {
+                        out.push('&');
+                        out.push_str(e);
+                        out.push(';');
+                        rest = &rest[e.len() + 2..];
+                        continue;
+                    }
+                    Some(e) => {
+                        if let Some(ch) = entity_char(e) {
+                            out.push_str(&escape(&ch.to_string()));
+                            rest = &rest[e.len() + 2..];
+                            continue;
+                        }
+                        out.push_str("&amp;");
+                    }
+                    None => out.push_str("&amp;"),
+                }
+            }
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Escape plain text for XML text or a double-quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// Turn an HTML fragment into well-formed XHTML: lowercase names, quoted
+/// and de-duplicated attributes, void elements self-closed, unbalanced
+/// tags closed or dropped, and `mbp:`/document wrapper tags removed.
+pub fn tidy(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 8);
+    let mut stack: Vec<String> = Vec::new();
+    let close = |out: &mut String, name: &str| {
+        out.push_str("</");
+        out.push_str(name);
+        out.push('>');
+    };
+    for token in tokenize(html) {
+        let tag = match token {
+            Token::Text(text) => {
+                out.push_str(&escape_markup(text, false));
+                continue;
+            }
+            Token::Markup(_) => continue,
+            Token::Tag(tag) => tag,
+        };
+        let name = tag.name.to_ascii_lowercase();
+        if name.contains(':') || !is_xml_name(&name) || DROPPED.contains(&name.as_str()) {
+            continue;
+        }
+        if tag.closing {
+            if let Some(pos) = stack.iter().rposition(|n| *n == name) {
+                while stack.len() > pos {
+                    let open = stack.pop().unwrap_or_default();
+                    close(&mut out, &open);
+                }
+            }
+            continue;
+        }
+        if BLOCKS.contains(&name.as_str()) {
+            while stack
+                .last()
+                .is_some_and(|n| n == "p" || (name == "li" && n == "li"))
+            {
+                let open = stack.pop().unwrap_or_default();
+                close(&mut out, &open);
+            }
+        }
+        out.push('<');
+        out.push_str(&name);
+        let mut seen: Vec<String> = Vec::new();
+        for attr in &tag.attrs {
+            let attr_name = attr.name.to_ascii_lowercase();
+            if !is_xml_name(&attr_name) || seen.contains(&attr_name) {
+                continue;
+            }
+            let value = attr.value.map_or(attr_name.as_str(), |(s, e)| &html[s..e]);
+            out.push(' ');
+            out.push_str(&attr_name);
+            out.push_str("=\"");
+            out.push_str(&escape_markup(value, true));
+            out.push('"');
+            seen.push(attr_name);
+        }
+        if tag.self_closing || VOID.contains(&name.as_str()) {
+            out.push_str("/>");
+        } else {
+            out.push('>');
+            stack.push(name);
+        }
+    }
+    while let Some(open) = stack.pop() {
+        close(&mut out, &open);
+    }
+    out
+}
+
+/// Replace attribute values: `f(tag, attribute, value)` returns the new
+/// raw (already escaped) value, or `None` to leave it alone. Everything
+/// else is copied through byte for byte.
+pub fn rewrite_attrs(src: &str, mut f: impl FnMut(&str, &str, &str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut copied = 0;
+    for token in tokenize(src) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.closing {
+            continue;
+        }
+        for attr in &tag.attrs {
+            let Some((start, end)) = attr.value else {
+                continue;
+            };
+            let Some(value) = f(tag.name, attr.name, &src[start..end]) else {
+                continue;
+            };
+            out.push_str(&src[copied..start]);
+            if attr.quoted {
+                out.push_str(&value);
+            } else {
+                out.push('"');
+                out.push_str(&value);
+                out.push('"');
+            }
+            copied = end;
+        }
+    }
+    out.push_str(&src[copied..]);
+    out
+}
+
+/// Byte spans of the body's opening tag and of `</body>`.
+pub fn body_bounds(doc: &str) -> Option<((usize, usize), (usize, usize))> {
+    let mut open = None;
+    let mut close = None;
+    for token in tokenize(doc) {
+        if let Token::Tag(tag) = token {
+            if tag.name.eq_ignore_ascii_case("body") {
+                if !tag.closing && open.is_none() {
+                    open = Some(tag.span);
+                } else if tag.closing {
+                    close = Some(tag.span);
+                }
+            }
+        }
+    }
+    match (open, close) {
+        (Some(open), Some(close)) if open.1 <= close.0 => Some((open, close)),
+        _ => None,
+    }
+}
+
+/// Offsets of the start tags carrying each `id` (or an anchor's `name`).
+pub fn id_offsets(src: &str) -> HashMap<String, usize> {
+    let mut ids = HashMap::new();
+    for token in tokenize(src) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.closing {
+            continue;
+        }
+        let id = tag.attr(src, "id").or_else(|| {
+            tag.name
+                .eq_ignore_ascii_case("a")
+                .then(|| tag.attr(src, "name"))
+                .flatten()
+        });
+        if let Some(id) = id {
+            ids.entry(id.to_string()).or_insert(tag.span.0);
+        }
+    }
+    ids
+}
+
+/// The text content of a fragment, whitespace collapsed.
+pub fn text_content(src: &str) -> String {
+    let mut text = String::new();
+    for token in tokenize(src) {
+        if let Token::Text(t) = token {
+            text.push_str(t);
+            text.push(' ');
+        }
+    }
+    let decoded = escape_markup(&text, false)
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_soup_becomes_well_formed() {
+        let html = "<P align=center>One &amp; two &nbsp;&mdash; A&B<br><IMG recindex=00001>\
+                    <mbp:pagebreak/><p>Second <b>bold <i>both</b> after</p><font size=\"+1\">x</font>\
+                    </div><!-- note --><p width=1 width=2>&bogus; 3 < 4";
+        assert_eq!(
+            tidy(html),
+            "<p align=\"center\">One &amp; two \u{a0}\u{2014} A&amp;B<br/><img recindex=\"00001\"/>\
+             </p><p>Second <b>bold <i>both</i></b> after</p><font size=\"+1\">x</font>\
+             <p width=\"1\">&amp;bogus; 3 &lt; 4</p>"
+        );
+    }
+
+    #[test]
+    fn list_items_and_paragraphs_close_implicitly() {
+        assert_eq!(
+            tidy("<ul><li>a<li>b</ul><p>x<p>y"),
+            "<ul><li>a</li><li>b</li></ul><p>x</p><p>y</p>"
+        );
+    }
+
+    #[test]
+    fn attributes_are_rewritten_in_place() {
+        let src = "<img src='a.jpg' alt=x><a href=ch2.html#n>go</a><p class=\"k\">";
+        let out = rewrite_attrs(src, |tag, attr, value| match (tag, attr) {
+            ("img", "src") => Some(format!("../images/{value}")),
+            ("a", "href") => Some("part2.xhtml#n".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            out,
+            "<img src='../images/a.jpg' alt=x><a href=\"part2.xhtml#n\">go</a><p class=\"k\">"
+        );
+    }
+
+    #[test]
+    fn bodies_and_ids_are_located() {
+        let doc = "<?xml version=\"1.0\"?><html><head/><body class=\"c\"><p id=\"a\">x</p>\
+                   <a name=\"b\"/></body></html>";
+        let ((_, inner_start), (inner_end, _)) = body_bounds(doc).unwrap();
+        assert_eq!(
+            &doc[inner_start..inner_end],
+            "<p id=\"a\">x</p><a name=\"b\"/>"
+        );
+        let ids = id_offsets(doc);
+        assert!(doc[ids["a"]..].starts_with("<p id"));
+        assert!(doc[ids["b"]..].starts_with("<a name"));
+        assert_eq!(
+            text_content("<h1>Part&nbsp;One</h1>\n<p>Tom &amp; Jerry</p>"),
+            "Part One Tom & Jerry"
+        );
+    }
+}
@@ -1,46 +1,340 @@
-use std::path::Path;
-use tauri::AppHandle;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, State};
 use tauri_plugin_fs::FsExt;
 use walkdir::WalkDir;
 
+use crate::archive_import::{list_archive_books_sync, ArchiveBookEntry, ArchiveKind};
+use crate::format_sniff::{sniff_format, BookFormat};
+
 #[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ScannedFile {
     pub path: String,
     pub size: u64,
+    /// Format detected from the first bytes of every candidate file,
+    /// independent of its extension, so a mislabelled book still gets the
+    /// right parser. `None` when the bytes aren't a recognised book
+    /// container.
+    pub format: Option<BookFormat>,
+    /// Book files found inside a `.zip` / `.rar` when the scan was asked to
+    /// peek into archives. `None` for regular files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_entries: Option<Vec<ArchiveBookEntry>>,
+}
+
+/// A symlinked directory the scan refused to enter because it leads back to
+/// a directory already walked (a loop, or a second link to the same folder).
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedLink {
+    pub path: String,
+    /// The directory first reached under this identity.
+    pub target: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub files: Vec<ScannedFile>,
+    pub skipped_links: Vec<SkippedLink>,
+}
+
+/// Identity of a directory independent of the path it was reached by: the
+/// (device, inode) pair on Unix, the canonical path elsewhere.
+#[cfg(unix)]
+type DirKey = (u64, u64);
+#[cfg(not(unix))]
+type DirKey = PathBuf;
+
+#[cfg(unix)]
+fn dir_key(path: &Path) -> Option<DirKey> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path) -> Option<DirKey> {
+    std::fs::canonicalize(path).ok()
+}
+
+/// Visited-directory set for walks that follow symlinks. WalkDir's own loop
+/// check only catches links to an ancestor; NAS mounts also produce sibling
+/// links to the same share, which would otherwise be imported twice.
+#[derive(Default)]
+struct VisitedDirs(HashMap<DirKey, PathBuf>);
+
+impl VisitedDirs {
+    /// Records `path` and returns the path it was first seen at if the same
+    /// directory has already been walked.
+    fn revisit(&mut self, path: &Path) -> Option<PathBuf> {
+        let key = dir_key(path)?;
+        match self.0.get(&key) {
+            Some(first) => Some(first.clone()),
+            None => {
+                self.0.insert(key, path.to_path_buf());
+                None
+            }
+        }
+    }
+}
+
+/// Server-side constraints on a directory walk, so scanning a whole SD card
+/// doesn't drag in Trash folders, `.git` and the like.
+struct ScanFilter {
+    excludes: Vec<glob::Pattern>,
+    min_file_size: u64,
 }
 
+impl ScanFilter {
+    fn new(exclude_globs: &[String], min_file_size: Option<u64>) -> Result<Self, String> {
+        let excludes = exclude_globs
+            .iter()
+            .map(|g| glob::Pattern::new(g).map_err(|e| format!("Invalid exclude glob {g:?}: {e}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            excludes,
+            min_file_size: min_file_size.unwrap_or(0),
+        })
+    }
+
+    /// Patterns without a `/` match any single path component (`.git`,
+    /// `.Trash-*`), patterns with one match the path relative to the scan root
+    /// (`Downloads/tmp/**`). Excluded directories are pruned, not descended.
+    fn is_excluded(&self, root: &Path, path: &Path) -> bool {
+        if self.excludes.is_empty() {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+        self.excludes.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches(&relative_str)
+            } else {
+                relative
+                    .components()
+                    .any(|c| pattern.matches(&c.as_os_str().to_string_lossy()))
+            }
+        })
+    }
+
+    fn is_too_small(&self, path: &Path) -> bool {
+        self.min_file_size > 0
+            && std::fs::metadata(path)
+                .map(|m| m.len() < self.min_file_size)
+                .unwrap_or(true)
+    }
+}
+
+/// Everything that shapes one directory walk; `read_dir` takes these as flat
+/// arguments, `scan_dir` as a single object.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanOptions {
+    pub path: String,
+    pub recursive: bool,
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub peek_archives: bool,
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    pub max_depth: Option<usize>,
+    pub min_file_size: Option<u64>,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    pub dirs_visited: u64,
+    pub files_found: u64,
+    pub current_dir: String,
+}
+
+/// Progress is throttled so a fast walk over local flash doesn't flood the
+/// IPC channel.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Hooks a long-running `scan_dir` hands to the walk: a cancel flag checked
+/// between entries and a progress sink.
+struct ScanControl<'a> {
+    cancelled: &'a AtomicBool,
+    on_progress: &'a dyn Fn(ScanProgress),
+}
+
+/// Cancel flags of in-flight `scan_dir` calls, keyed by the caller's task id.
+#[derive(Default)]
+pub struct ScanTasks(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
 #[tauri::command]
 pub fn read_dir(
     app: AppHandle,
     path: String,
     recursive: bool,
     extensions: Vec<String>,
-) -> Result<Vec<ScannedFile>, String> {
-    let scope = app.fs_scope();
-    let path_buf = std::path::PathBuf::from(&path);
+    peek_archives: Option<bool>,
+    exclude_globs: Option<Vec<String>>,
+    max_depth: Option<usize>,
+    min_file_size: Option<u64>,
+    follow_symlinks: Option<bool>,
+) -> Result<ScanResult, String> {
+    let options = ScanOptions {
+        path,
+        recursive,
+        extensions,
+        peek_archives: peek_archives.unwrap_or(false),
+        exclude_globs: exclude_globs.unwrap_or_default(),
+        max_depth,
+        min_file_size,
+        follow_symlinks: follow_symlinks.unwrap_or(false),
+    };
+    check_scope(&app, &options.path)?;
+    scan(&options, None)
+}
+
+/// Same walk as `read_dir`, but off the command thread, reporting progress
+/// on `on_progress` and abortable with `cancel_scan(task_id)`.
+#[tauri::command]
+pub async fn scan_dir(
+    app: AppHandle,
+    tasks: State<'_, ScanTasks>,
+    task_id: String,
+    options: ScanOptions,
+    on_progress: Channel<ScanProgress>,
+) -> Result<ScanResult, String> {
+    check_scope(&app, &options.path)?;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    tasks
+        .0
+        .lock()
+        .unwrap()
+        .insert(task_id.clone(), cancelled.clone());
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let on_progress = move |p: ScanProgress| {
+            let _ = on_progress.send(p);
+        };
+        let control = ScanControl {
+            cancelled: &cancelled,
+            on_progress: &on_progress,
+        };
+        scan(&options, Some(&control))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"));
+
+    tasks.0.lock().unwrap().remove(&task_id);
+    result?
+}
 
-    if !scope.is_allowed(&path_buf) && !path_buf.to_string_lossy().contains("Readest") {
+#[tauri::command]
+pub fn cancel_scan(tasks: State<'_, ScanTasks>, task_id: String) -> bool {
+    match tasks.0.lock().unwrap().get(&task_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+fn check_scope(app: &AppHandle, path: &str) -> Result<(), String> {
+    let path_buf = PathBuf::from(path);
+    if !app.fs_scope().is_allowed(&path_buf) && !path.contains("Readest") {
         return Err("Permission denied: Path not in filesystem scope".to_string());
     }
+    Ok(())
+}
 
+fn scan(options: &ScanOptions, control: Option<&ScanControl>) -> Result<ScanResult, String> {
+    let path_buf = PathBuf::from(&options.path);
     let mut files = Vec::new();
+    let mut skipped_links = Vec::new();
 
-    let normalized_extensions: Vec<String> =
-        extensions.iter().map(|ext| ext.to_lowercase()).collect();
+    let normalized_extensions: Vec<String> = options
+        .extensions
+        .iter()
+        .map(|ext| ext.to_lowercase())
+        .collect();
+    let peek_archives = options.peek_archives;
+    let filter = ScanFilter::new(&options.exclude_globs, options.min_file_size)?;
 
-    if recursive {
-        for entry_result in WalkDir::new(&path).into_iter() {
+    let mut dirs_visited = 0u64;
+    let mut last_progress = Instant::now();
+    let mut check_control = |dir: Option<&Path>, files_found: usize| -> Result<(), String> {
+        let Some(control) = control else {
+            return Ok(());
+        };
+        if control.cancelled.load(Ordering::Relaxed) {
+            return Err("Scan cancelled".to_string());
+        }
+        if let Some(dir) = dir {
+            dirs_visited += 1;
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                (control.on_progress)(ScanProgress {
+                    dirs_visited,
+                    files_found: files_found as u64,
+                    current_dir: dir.to_string_lossy().to_string(),
+                });
+            }
+        }
+        Ok(())
+    };
+
+    if options.recursive {
+        let follow_symlinks = options.follow_symlinks;
+        let mut visited = VisitedDirs::default();
+        let mut walker = WalkDir::new(&path_buf).follow_links(follow_symlinks);
+        if let Some(depth) = options.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        let mut walker = walker
+            .into_iter()
+            .filter_entry(|entry| !filter.is_excluded(&path_buf, entry.path()));
+        while let Some(entry_result) = walker.next() {
             match entry_result {
                 Ok(entry) => {
-                    if entry.file_type().is_file() {
+                    let is_dir = entry.file_type().is_dir();
+                    check_control(is_dir.then_some(entry.path()), files.len())?;
+                    if follow_symlinks && is_dir {
+                        if let Some(first) = visited.revisit(entry.path()) {
+                            log::warn!(
+                                "RUST: Skipping already visited directory {} (same as {})",
+                                entry.path().display(),
+                                first.display()
+                            );
+                            skipped_links.push(SkippedLink {
+                                path: entry.path().to_string_lossy().to_string(),
+                                target: first.to_string_lossy().to_string(),
+                            });
+                            walker.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    if entry.file_type().is_file() && !filter.is_too_small(entry.path()) {
                         if let Some(scanned_file) =
-                            process_file_entry(entry.path(), &normalized_extensions)
+                            process_file_entry(entry.path(), &normalized_extensions, peek_archives)
                         {
                             files.push(scanned_file);
                         }
                     }
                 }
                 Err(e) => {
+                    if let (Some(path), Some(ancestor)) = (e.path(), e.loop_ancestor()) {
+                        skipped_links.push(SkippedLink {
+                            path: path.to_string_lossy().to_string(),
+                            target: ancestor.to_string_lossy().to_string(),
+                        });
+                    }
                     log::warn!("RUST: Skipping file due to error: {}", e);
                 }
             }
@@ -48,13 +342,18 @@ pub fn read_dir(
     } else {
         match std::fs::read_dir(&path_buf) {
             Ok(entries) => {
+                check_control(Some(&path_buf), 0)?;
                 for entry_result in entries {
+                    check_control(None, files.len())?;
                     match entry_result {
                         Ok(entry) => {
                             let path = entry.path();
-                            if path.is_file() {
+                            if path.is_file()
+                                && !filter.is_excluded(&path_buf, &path)
+                                && !filter.is_too_small(&path)
+                            {
                                 if let Some(scanned_file) =
-                                    process_file_entry(&path, &normalized_extensions)
+                                    process_file_entry(&path, &normalized_extensions, peek_archives)
                                 {
                                     files.push(scanned_file);
                                 }
@@ -72,25 +371,188 @@ pub fn read_dir(
         }
     }
 
-    Ok(files)
+    Ok(ScanResult {
+        files,
+        skipped_links,
+    })
+}
+
+/// A file is picked up when its extension is requested OR when its sniffed
+/// format is, so misnamed downloads (`book.bin`, an AZW3 saved as `.tmp`)
+/// still make it into the import list with the right parser hint.
+///
+/// Every candidate is sniffed, requested extension or not: only the first
+/// KiB is read (plus the entry list of ZIP/RAR/7z files), and a `.pdf` that
+/// is really an EPUB would otherwise reach the wrong parser.
+fn process_file_entry(
+    path: &Path,
+    extensions: &[String],
+    peek_archives: bool,
+) -> Option<ScannedFile> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let wildcard = extensions.is_empty() || extensions.contains(&"*".to_string());
+    let ext_match = extension
+        .as_ref()
+        .is_some_and(|ext| extensions.contains(ext));
+    let is_archive = peek_archives && ArchiveKind::from_path(path).is_some();
+    let format = sniff_format(path);
+    // An EPUB or comic saved as `.zip` / `.rar` is a book, not an archive
+    // of books.
+    if is_archive
+        && !matches!(
+            format,
+            Some(BookFormat::Epub | BookFormat::Cbz | BookFormat::Cbr)
+        )
+    {
+        return process_archive_entry(path, format);
+    }
+    let format_match = format.is_some_and(|f| {
+        f.extensions()
+            .iter()
+            .any(|ext| extensions.iter().any(|e| e == ext))
+    });
+    if !(wildcard || ext_match || format_match) {
+        return None;
+    }
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    Some(ScannedFile {
+        path: path.to_string_lossy().to_string(),
+        size,
+        format,
+        archive_entries: None,
+    })
 }
 
-fn process_file_entry(path: &Path, extensions: &[String]) -> Option<ScannedFile> {
-    if extensions.is_empty() || extensions.contains(&"*".to_string()) {
-        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-        return Some(ScannedFile {
-            path: path.to_string_lossy().to_string(),
-            size,
-        });
-    } else if let Some(ext) = path.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        if extensions.contains(&ext_str) {
+/// Archives are reported only when they actually contain importable books,
+/// so a folder of unrelated `.zip` downloads doesn't flood the import list.
+fn process_archive_entry(path: &Path, format: Option<BookFormat>) -> Option<ScannedFile> {
+    match list_archive_books_sync(path) {
+        Ok(entries) if !entries.is_empty() => {
             let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-            return Some(ScannedFile {
+            Some(ScannedFile {
                 path: path.to_string_lossy().to_string(),
                 size,
-            });
+                format,
+                archive_entries: Some(entries),
+            })
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!(
+                "RUST: Skipping unreadable archive {}: {}",
+                path.display(),
+                e
+            );
+            None
         }
     }
-    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(globs: &[&str]) -> ScanFilter {
+        let globs: Vec<String> = globs.iter().map(|g| g.to_string()).collect();
+        ScanFilter::new(&globs, None).unwrap()
+    }
+
+    #[test]
+    fn bare_pattern_matches_any_component() {
+        let f = filter(&[".git", ".Trash-*", "node_modules"]);
+        let root = Path::new("/sdcard");
+        assert!(f.is_excluded(root, Path::new("/sdcard/proj/.git")));
+        assert!(f.is_excluded(root, Path::new("/sdcard/.Trash-1000/files/a.epub")));
+        assert!(f.is_excluded(root, Path::new("/sdcard/x/node_modules/y/b.pdf")));
+        assert!(!f.is_excluded(root, Path::new("/sdcard/Books/a.epub")));
+    }
+
+    #[test]
+    fn slash_pattern_matches_relative_path() {
+        let f = filter(&["Download/tmp/**"]);
+        let root = Path::new("/sdcard");
+        assert!(f.is_excluded(root, Path::new("/sdcard/Download/tmp/a.epub")));
+        assert!(!f.is_excluded(root, Path::new("/sdcard/Books/Download/tmp/a.epub")));
+    }
+
+    #[test]
+    fn root_itself_is_never_excluded() {
+        let f = filter(&["sdcard"]);
+        assert!(!f.is_excluded(Path::new("/sdcard"), Path::new("/sdcard")));
+    }
+
+    #[test]
+    fn revisiting_a_directory_reports_first_path() {
+        let dir = std::env::temp_dir().join(format!("readest-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut visited = VisitedDirs::default();
+        assert!(visited.revisit(&dir).is_none());
+        assert_eq!(visited.revisit(&dir.join(".")), Some(dir.clone()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cancelled_scan_stops_with_error() {
+        let cancelled = AtomicBool::new(true);
+        let control = ScanControl {
+            cancelled: &cancelled,
+            on_progress: &|_| {},
+        };
+        let options = ScanOptions {
+            path: std::env::temp_dir().to_string_lossy().to_string(),
+            recursive: true,
+            extensions: vec!["*".to_string()],
+            peek_archives: false,
+            exclude_globs: Vec::new(),
+            max_depth: Some(1),
+            min_file_size: None,
+            follow_symlinks: false,
+        };
+        assert_eq!(
+            scan(&options, Some(&control)).err().as_deref(),
+            Some("Scan cancelled")
+        );
+    }
+
+    #[test]
+    fn sniffs_every_candidate_file() {
+        let dir = std::env::temp_dir().join(format!("readest-sniff-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let misnamed = dir.join("book.bin");
+        let named = dir.join("book.pdf");
+        let mislabelled = dir.join("book.epub");
+        let photo = dir.join("photo.jpg");
+        for path in [&misnamed, &named, &mislabelled] {
+            std::fs::write(path, b"%PDF-1.7\n").unwrap();
+        }
+        std::fs::write(&photo, b"\xff\xd8\xff\xe0").unwrap();
+        let pdf = ["pdf".to_string()];
+        let found = process_file_entry(&misnamed, &pdf, false).unwrap();
+        assert_eq!(found.format, Some(BookFormat::Pdf));
+        assert_eq!(
+            process_file_entry(&named, &pdf, false).unwrap().format,
+            Some(BookFormat::Pdf)
+        );
+        // A requested extension doesn't stop the contents from deciding.
+        let epub = ["epub".to_string()];
+        assert_eq!(
+            process_file_entry(&mislabelled, &epub, false)
+                .unwrap()
+                .format,
+            Some(BookFormat::Pdf)
+        );
+        let all = ["*".to_string()];
+        assert_eq!(
+            process_file_entry(&photo, &all, false).unwrap().format,
+            None
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_glob_is_an_error() {
+        assert!(ScanFilter::new(&["[".to_string()], None).is_err());
+    }
 }
@@ -0,0 +1,660 @@
+//! Background download manager for large books and audiobooks.
+//!
+//! `transfer_file::download_file` is a single request bound to the lifetime
+//! of one `invoke`: a network blip or an app restart throws away everything
+//! fetched so far. This keeps a queue of downloads instead:
+//!
+//!   - bytes land in `<dest>.part`, and a retry or a later session resumes
+//!     from its length with `Range: bytes=<len>-` (guarded by `If-Range` on
+//!     the ETag / Last-Modified we saw, so a changed file restarts cleanly);
+//!   - at most `max_concurrent` items transfer at once, the rest wait queued;
+//!   - the queue is persisted to `downloads.json` in the app data dir on every
+//!     status change and reloaded on startup, with interrupted items re-queued;
+//!   - transient failures (connection errors, 5xx, 408/429) retry with
+//!     exponential backoff before the item is marked failed.
+//!
+//! Request headers are kept in memory only, since callers pass credentials
+//! in them. A download that must survive a restart with its credentials
+//! names a secure-store entry in `auth_ref` instead, read back each time the
+//! request is built.
+//!
+//! Progress is emitted as `download://progress` and every status change as
+//! `download://state` (the full item), so any window can render the queue.
+
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::AsyncWriteExt;
+
+use crate::transfer_file::{ensure_path_allowed, TransferStats};
+
+const QUEUE_FILENAME: &str = "downloads.json";
+const DEFAULT_MAX_CONCURRENT: usize = 2;
+const MAX_RETRIES: u32 = 4;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+pub const PROGRESS_EVENT: &str = "download://progress";
+pub const STATE_EVENT: &str = "download://state";
+
+// Control signals written by the commands and polled by the transfer loop
+// between chunks.
+const CONTROL_RUN: u8 = 0;
+const CONTROL_PAUSE: u8 = 1;
+const CONTROL_CANCEL: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Paused,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadItem {
+    pub id: String,
+    pub url: String,
+    pub dest_path: String,
+    /// Extra request headers for this session; never written to the queue.
+    #[serde(skip)]
+    pub headers: HashMap<String, String>,
+    /// Secure-store key of a JSON object of headers to attach to the
+    /// request, e.g. `opds:<catalog id>` for a catalog's `Authorization`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_ref: Option<String>,
+    pub status: DownloadStatus,
+    #[serde(default)]
+    pub downloaded: u64,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Validator for `If-Range` so a resumed request never splices bytes
+    /// from two different versions of the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validator: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub id: String,
+    pub downloaded: u64,
+    pub total: u64,
+    pub transfer_speed: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedQueue {
+    max_concurrent: Option<usize>,
+    items: Vec<DownloadItem>,
+}
+
+struct Inner {
+    path: Option<PathBuf>,
+    items: Mutex<Vec<DownloadItem>>,
+    active: Mutex<HashMap<String, Arc<AtomicU8>>>,
+    max_concurrent: AtomicUsize,
+}
+
+pub struct DownloadManager(Arc<Inner>);
+
+enum Outcome {
+    Completed,
+    Stopped,
+}
+
+enum TransferError {
+    Retryable(String),
+    Fatal(String),
+}
+
+impl From<reqwest::Error> for TransferError {
+    fn from(e: reqwest::Error) -> Self {
+        TransferError::Retryable(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for TransferError {
+    fn from(e: std::io::Error) -> Self {
+        TransferError::Fatal(e.to_string())
+    }
+}
+
+fn part_path(dest: &str) -> PathBuf {
+    PathBuf::from(format!("{dest}.part"))
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "dl-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Headers stored under `key` in the secure store. A missing or unreadable
+/// entry sends the request without them, and the server's 401 fails it.
+fn stored_headers(app: &AppHandle, key: &str) -> HashMap<String, String> {
+    match crate::secure_store::get(app, key) {
+        Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::warn!("Ignoring malformed headers in {key}: {e}");
+            HashMap::new()
+        }),
+        Ok(None) => HashMap::new(),
+        Err(e) => {
+            log::warn!("Failed to read {key} from the secure store: {e}");
+            HashMap::new()
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5))
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+impl DownloadManager {
+    pub fn load(app: &AppHandle) -> Self {
+        let path = app
+            .path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| dir.join(QUEUE_FILENAME));
+        let queue: PersistedQueue = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        let mut items = queue.items;
+        // Anything mid-transfer when the app quit goes back in line.
+        for item in items.iter_mut() {
+            if item.status == DownloadStatus::Downloading {
+                item.status = DownloadStatus::Queued;
+            }
+        }
+        Self(Arc::new(Inner {
+            path,
+            items: Mutex::new(items),
+            active: Mutex::new(HashMap::new()),
+            max_concurrent: AtomicUsize::new(
+                queue
+                    .max_concurrent
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT)
+                    .max(1),
+            ),
+        }))
+    }
+}
+
+impl Inner {
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let queue = PersistedQueue {
+            max_concurrent: Some(self.max_concurrent.load(Ordering::Relaxed)),
+            items: self.items.lock().unwrap().clone(),
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string_pretty(&queue) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(path, json) {
+                    log::warn!("Failed to persist download queue: {e}");
+                }
+            }
+            Err(e) => log::warn!("Failed to serialize download queue: {e}"),
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<DownloadItem> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|i| i.id == id)
+            .cloned()
+    }
+
+    /// Apply `f` to the item, persist the queue and broadcast the new state.
+    fn update(&self, app: &AppHandle, id: &str, f: impl FnOnce(&mut DownloadItem)) {
+        let updated = {
+            let mut items = self.items.lock().unwrap();
+            items.iter_mut().find(|i| i.id == id).map(|item| {
+                f(item);
+                item.clone()
+            })
+        };
+        if let Some(item) = updated {
+            self.persist();
+            let _ = app.emit(STATE_EVENT, &item);
+        }
+    }
+
+    fn set_progress(&self, id: &str, downloaded: u64, total: u64) {
+        if let Some(item) = self.items.lock().unwrap().iter_mut().find(|i| i.id == id) {
+            item.downloaded = downloaded;
+            item.total = total;
+        }
+    }
+}
+
+/// Start queued downloads until the concurrency limit is reached.
+fn schedule(app: &AppHandle, inner: &Arc<Inner>) {
+    let mut active = inner.active.lock().unwrap();
+    let limit = inner.max_concurrent.load(Ordering::Relaxed);
+    let queued: Vec<String> = inner
+        .items
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|i| i.status == DownloadStatus::Queued && !active.contains_key(&i.id))
+        .map(|i| i.id.clone())
+        .collect();
+    for id in queued {
+        if active.len() >= limit {
+            break;
+        }
+        let control = Arc::new(AtomicU8::new(CONTROL_RUN));
+        active.insert(id.clone(), control.clone());
+        inner.update(app, &id, |item| {
+            item.status = DownloadStatus::Downloading;
+            item.error = None;
+        });
+        let app = app.clone();
+        let inner = inner.clone();
+        tauri::async_runtime::spawn(async move {
+            run_download(&app, &inner, &id, &control).await;
+            inner.active.lock().unwrap().remove(&id);
+            schedule(&app, &inner);
+        });
+    }
+}
+
+async fn run_download(app: &AppHandle, inner: &Arc<Inner>, id: &str, control: &AtomicU8) {
+    let mut attempt = 0;
+    loop {
+        let Some(item) = inner.get(id) else {
+            return;
+        };
+        match transfer(app, inner, &item, control).await {
+            Ok(Outcome::Completed) => {
+                inner.update(app, id, |item| {
+                    item.status = DownloadStatus::Completed;
+                    item.downloaded = item.total.max(item.downloaded);
+                });
+                return;
+            }
+            Ok(Outcome::Stopped) => return,
+            Err(TransferError::Retryable(e)) if attempt < MAX_RETRIES => {
+                log::warn!("Download {id} interrupted ({e}), retrying");
+                tokio::time::sleep(backoff(attempt)).await;
+                if control.load(Ordering::Relaxed) != CONTROL_RUN {
+                    return;
+                }
+                attempt += 1;
+            }
+            Err(TransferError::Retryable(e)) | Err(TransferError::Fatal(e)) => {
+                log::error!("Download {id} failed: {e}");
+                inner.update(app, id, |item| {
+                    item.status = DownloadStatus::Failed;
+                    item.error = Some(e);
+                });
+                return;
+            }
+        }
+    }
+}
+
+async fn transfer(
+    app: &AppHandle,
+    inner: &Inner,
+    item: &DownloadItem,
+    control: &AtomicU8,
+) -> Result<Outcome, TransferError> {
+    let part = part_path(&item.dest_path);
+    if let Some(dir) = part.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut offset = tokio::fs::metadata(&part)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let client = crate::net::client_builder_for(&item.url).build()?;
+    let mut request = client.get(&item.url);
+    for (key, value) in &item.headers {
+        request = request.header(key, value);
+    }
+    if let Some(auth_ref) = &item.auth_ref {
+        for (key, value) in stored_headers(app, auth_ref) {
+            request = request.header(key, value);
+        }
+    }
+    if offset > 0 {
+        request = request.header("Range", format!("bytes={offset}-"));
+        if let Some(validator) = &item.validator {
+            request = request.header("If-Range", validator);
+        }
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+        // Either the part already holds the whole file, or it's longer than
+        // the remote one; only the first is a success.
+        if item.total > 0 && offset == item.total {
+            tokio::fs::rename(&part, &item.dest_path).await?;
+            return Ok(Outcome::Completed);
+        }
+        tokio::fs::remove_file(&part).await?;
+        return Err(TransferError::Retryable(
+            "partial download no longer matches remote file".to_string(),
+        ));
+    }
+    if !status.is_success() {
+        let message = format!("request failed with status code {}", status.as_u16());
+        return Err(if is_retryable_status(status) {
+            TransferError::Retryable(message)
+        } else {
+            TransferError::Fatal(message)
+        });
+    }
+
+    // 200 to a ranged request means the server ignored the range (or the
+    // validator no longer matched): start over.
+    let resumed = status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !resumed {
+        offset = 0;
+    }
+    let validator = response
+        .headers()
+        .get("etag")
+        .or_else(|| response.headers().get("last-modified"))
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let total = response
+        .content_length()
+        .map(|len| len + offset)
+        .unwrap_or(item.total);
+    inner.update(app, &item.id, |item| {
+        item.validator = validator;
+        item.total = total;
+        item.downloaded = offset;
+    });
+
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .await?;
+    let mut file = tokio::io::BufWriter::new(file);
+    let mut stream = response.bytes_stream();
+    let mut stats = TransferStats::default();
+    let mut downloaded = offset;
+    let mut last_progress = Instant::now();
+
+    loop {
+        match control.load(Ordering::Relaxed) {
+            CONTROL_RUN => {}
+            _ => {
+                file.flush().await?;
+                return Ok(Outcome::Stopped);
+            }
+        }
+        let Some(chunk) = stream.try_next().await? else {
+            break;
+        };
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        stats.record_chunk_transfer(chunk.len());
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            inner.set_progress(&item.id, downloaded, total);
+            let _ = app.emit(
+                PROGRESS_EVENT,
+                DownloadProgress {
+                    id: item.id.clone(),
+                    downloaded,
+                    total,
+                    transfer_speed: stats.transfer_speed,
+                },
+            );
+        }
+    }
+    file.flush().await?;
+    drop(file);
+
+    if total > 0 && downloaded < total {
+        inner.set_progress(&item.id, downloaded, total);
+        return Err(TransferError::Retryable(format!(
+            "connection closed after {downloaded} of {total} bytes"
+        )));
+    }
+    tokio::fs::rename(&part, &item.dest_path).await?;
+    inner.set_progress(&item.id, downloaded, total.max(downloaded));
+    Ok(Outcome::Completed)
+}
+
+/// Re-queue downloads interrupted by the previous session.
+pub fn resume_pending(app: &AppHandle) {
+    let inner = app.state::<DownloadManager>().0.clone();
+    schedule(app, &inner);
+}
+
+impl DownloadManager {
+    /// Add a download to the queue and start it if a slot is free.
+    pub fn enqueue(
+        &self,
+        app: &AppHandle,
+        url: String,
+        dest_path: String,
+        headers: HashMap<String, String>,
+        auth_ref: Option<String>,
+    ) -> Result<DownloadItem, String> {
+        ensure_path_allowed(app, &dest_path).map_err(|e| e.to_string())?;
+        let item = DownloadItem {
+            id: new_id(),
+            url,
+            dest_path,
+            headers,
+            auth_ref,
+            status: DownloadStatus::Queued,
+            downloaded: 0,
+            total: 0,
+            error: None,
+            validator: None,
+        };
+        self.0.items.lock().unwrap().push(item.clone());
+        self.0.persist();
+        let _ = app.emit(STATE_EVENT, &item);
+        schedule(app, &self.0);
+        Ok(item)
+    }
+}
+
+#[tauri::command]
+pub fn enqueue_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    url: String,
+    dest_path: String,
+    headers: Option<HashMap<String, String>>,
+    auth_ref: Option<String>,
+) -> Result<DownloadItem, String> {
+    manager.enqueue(&app, url, dest_path, headers.unwrap_or_default(), auth_ref)
+}
+
+#[tauri::command]
+pub fn list_downloads(manager: State<'_, DownloadManager>) -> Vec<DownloadItem> {
+    manager.0.items.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn pause_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    if let Some(control) = manager.0.active.lock().unwrap().get(&id) {
+        control.store(CONTROL_PAUSE, Ordering::Relaxed);
+    }
+    manager.0.update(&app, &id, |item| {
+        if matches!(
+            item.status,
+            DownloadStatus::Queued | DownloadStatus::Downloading
+        ) {
+            item.status = DownloadStatus::Paused;
+        }
+    });
+    Ok(())
+}
+
+/// Resume a paused download or retry a failed one.
+#[tauri::command]
+pub fn resume_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    let item = manager
+        .0
+        .get(&id)
+        .ok_or_else(|| format!("unknown download: {id}"))?;
+    if matches!(item.status, DownloadStatus::Paused | DownloadStatus::Failed) {
+        manager.0.update(&app, &id, |item| {
+            item.status = DownloadStatus::Queued;
+            item.error = None;
+        });
+        schedule(&app, &manager.0);
+    }
+    Ok(())
+}
+
+/// Stop a download and drop it from the queue, deleting the partial file.
+#[tauri::command]
+pub async fn cancel_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    if let Some(control) = manager.0.active.lock().unwrap().get(&id) {
+        control.store(CONTROL_CANCEL, Ordering::Relaxed);
+    }
+    let removed = {
+        let mut items = manager.0.items.lock().unwrap();
+        let pos = items.iter().position(|i| i.id == id);
+        pos.map(|pos| items.remove(pos))
+    };
+    let Some(item) = removed else {
+        return Ok(());
+    };
+    manager.0.persist();
+    if item.status != DownloadStatus::Completed {
+        // Give an in-flight transfer a moment to notice and close the file.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let part = part_path(&item.dest_path);
+        if Path::new(&part).exists() {
+            tokio::fs::remove_file(&part)
+                .await
+                .map_err(|e| format!("Failed to remove partial download: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_max_concurrent_downloads(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    max_concurrent: usize,
+) {
+    manager
+        .0
+        .max_concurrent
+        .store(max_concurrent.max(1), Ordering::Relaxed);
+    manager.0.persist();
+    schedule(&app, &manager.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_file_sits_next_to_destination() {
+        assert_eq!(
+            part_path("/books/a.m4b"),
+            PathBuf::from("/books/a.m4b.part")
+        );
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        assert_ne!(new_id(), new_id());
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(10), Duration::from_secs(32));
+    }
+
+    #[test]
+    fn transient_statuses_are_retried() {
+        assert!(is_retryable_status(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(is_retryable_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(reqwest::StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(reqwest::StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn persisted_queue_round_trips() {
+        let queue = PersistedQueue {
+            max_concurrent: Some(3),
+            items: vec![DownloadItem {
+                id: "dl-1".into(),
+                url: "https://example.com/a.epub".into(),
+                dest_path: "/books/a.epub".into(),
+                headers: HashMap::from([("Authorization".into(), "Bearer secret".into())]),
+                auth_ref: Some("opds:catalog-1".into()),
+                status: DownloadStatus::Paused,
+                downloaded: 10,
+                total: 100,
+                error: None,
+                validator: Some("\"abc\"".into()),
+            }],
+        };
+        let json = serde_json::to_string(&queue).unwrap();
+        assert!(json.contains("\"status\":\"paused\""));
+        assert!(!json.contains("secret"));
+        let back: PersistedQueue = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.items[0].validator.as_deref(), Some("\"abc\""));
+        assert_eq!(back.items[0].auth_ref.as_deref(), Some("opds:catalog-1"));
+        assert!(back.items[0].headers.is_empty());
+        assert_eq!(back.max_concurrent, Some(3));
+    }
+}
@@ -486,7 +486,7 @@ fn locate_toc_sources(opf_bytes: &[u8]) -> Result<LocatedTocSources, String> {
 // block above is retained here for navigation from EPUB-side call sites.)
 // ---------------------------------------------------------------------------
 
-fn read_zip_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> Result<Vec<u8>, String> {
+pub(crate) fn read_zip_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> Result<Vec<u8>, String> {
     // Two-pass lookup, mirroring what epub-rs does (archive.rs) and what
     // foliate-js does on the JS side: many EPUBs declare manifest hrefs that
     // are percent-encoded (e.g. "Text/My%20Chapter.xhtml" or CJK %E4%BB%96)
@@ -516,7 +516,7 @@ fn read_by_name<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<V
     Ok(buf)
 }
 
-fn read_rootfile_path<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<String, String> {
+pub(crate) fn read_rootfile_path<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<String, String> {
     let bytes = read_zip_entry(zip, "META-INF/container.xml")?;
     let normalized = strip_xml_bom(&bytes);
     let mut reader = Reader::from_reader(normalized.as_ref());
@@ -794,7 +794,7 @@ fn guess_image_mime(path: &str) -> &'static str {
 ///     publisher tools (notably old Adobe InDesign exports) still emit it.
 ///
 /// Returns a `Cow` so the common (UTF-8, no BOM) case stays zero-copy.
-fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
+pub(crate) fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
     if bytes.len() >= 3 && bytes[0] == 0xEF && bytes[1] == 0xBB && bytes[2] == 0xBF {
         return Cow::Borrowed(&bytes[3..]);
     }
@@ -823,7 +823,7 @@ fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
     Cow::Borrowed(bytes)
 }
 
-fn local_name(qname: &[u8]) -> &[u8] {
+pub(crate) fn local_name(qname: &[u8]) -> &[u8] {
     match qname.iter().rposition(|b| *b == b':') {
         Some(idx) => &qname[idx + 1..],
         None => qname,
@@ -0,0 +1,708 @@
+//! EPUB check & repair.
+//!
+//! EPUBs from sketchy tooling fail to open in ways foliate-js can only
+//! report as "invalid zip" or "cannot read container". This walks the
+//! archive and reports structural problems, and can write a repaired copy
+//! fixing the ones that are safe to fix mechanically:
+//!
+//!   - a damaged central directory (entries recovered from their local
+//!     headers);
+//!   - a missing, misplaced, compressed or wrong `mimetype` entry;
+//!   - a missing or broken `META-INF/container.xml` when the archive has a
+//!     package document it can point at instead;
+//!   - Windows-style `\` separators and duplicate entry names.
+//!
+//! Problems inside the package (missing manifest resources, dangling spine
+//! references, malformed OPF) are reported but left alone.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use tauri::AppHandle;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::epub_parser::{local_name, strip_xml_bom};
+use crate::transfer_file::ensure_path_allowed;
+
+const MIMETYPE: &[u8] = b"application/epub+zip";
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueCode {
+    BrokenZip,
+    CorruptEntry,
+    BackslashPaths,
+    DuplicateEntry,
+    MissingMimetype,
+    MimetypeNotFirst,
+    MimetypeCompressed,
+    BadMimetype,
+    MissingContainer,
+    BadContainer,
+    MissingPackage,
+    MalformedPackage,
+    MissingResource,
+    BrokenSpineReference,
+    EmptySpine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// Reading systems may refuse the book.
+    Error,
+    /// The book opens, but something in it is broken.
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubIssue {
+    pub code: IssueCode,
+    pub severity: Severity,
+    pub message: String,
+    /// Whether `repair_epub` fixes it.
+    pub fixable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubReport {
+    pub issues: Vec<EpubIssue>,
+    /// Where the repaired copy went, when `repair_epub` wrote one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaired_path: Option<String>,
+    /// What a fresh check of the repaired copy still finds.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<EpubIssue>,
+}
+
+impl EpubReport {
+    fn push(
+        &mut self,
+        code: IssueCode,
+        severity: Severity,
+        fixable: bool,
+        message: String,
+    ) -> &mut EpubIssue {
+        self.issues.push(EpubIssue {
+            code,
+            severity,
+            message,
+            fixable,
+            path: None,
+        });
+        let last = self.issues.len() - 1;
+        &mut self.issues[last]
+    }
+
+    fn needs_repair(&self) -> bool {
+        self.issues.iter().any(|i| i.fixable)
+    }
+}
+
+struct Entry {
+    name: String,
+    data: Vec<u8>,
+    stored: bool,
+}
+
+/// What the check worked out about the archive, for the repair to reuse.
+struct Scan {
+    entries: Vec<Entry>,
+    /// Normalised name → first entry with it.
+    lookup: HashMap<String, usize>,
+    /// The package document to point `container.xml` at, when the
+    /// existing one is missing or broken.
+    new_rootfile: Option<String>,
+}
+
+fn read_entries(data: &[u8], report: &mut EpubReport) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    match ZipArchive::new(Cursor::new(data)) {
+        Ok(mut zip) => {
+            for i in 0..zip.len() {
+                let mut file = match zip.by_index(i) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        report.push(
+                            IssueCode::CorruptEntry,
+                            Severity::Error,
+                            false,
+                            format!("Entry {i} can't be read: {e}"),
+                        );
+                        continue;
+                    }
+                };
+                if file.is_dir() {
+                    continue;
+                }
+                let name = file.name().to_string();
+                let stored = file.compression() == CompressionMethod::Stored;
+                let mut buf = Vec::with_capacity(file.size() as usize);
+                match file.read_to_end(&mut buf) {
+                    Ok(_) => entries.push(Entry {
+                        name,
+                        data: buf,
+                        stored,
+                    }),
+                    Err(e) => {
+                        report
+                            .push(
+                                IssueCode::CorruptEntry,
+                                Severity::Error,
+                                false,
+                                format!("{name} is corrupt: {e}"),
+                            )
+                            .path = Some(name);
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            // Without a central directory, walk the local file headers.
+            let mut reader = Cursor::new(data);
+            while let Ok(Some(mut file)) = zip::read::read_zipfile_from_stream(&mut reader) {
+                let name = file.name().to_string();
+                let stored = file.compression() == CompressionMethod::Stored;
+                let mut buf = Vec::new();
+                if file.is_dir() || file.read_to_end(&mut buf).is_err() {
+                    continue;
+                }
+                entries.push(Entry {
+                    name,
+                    data: buf,
+                    stored,
+                });
+            }
+            if entries.is_empty() {
+                return Err(format!("not a readable zip archive: {e}"));
+            }
+            report.push(
+                IssueCode::BrokenZip,
+                Severity::Error,
+                true,
+                format!(
+                    "The zip directory is damaged ({e}); {} entries were recovered",
+                    entries.len()
+                ),
+            );
+        }
+    }
+    Ok(entries)
+}
+
+fn rootfile_path(bytes: &[u8]) -> Result<Option<String>, String> {
+    let bytes = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(bytes.as_ref());
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Empty(e)) | Ok(Event::Start(e))
+                if local_name(e.name().as_ref()) == b"rootfile" =>
+            {
+                let path = e
+                    .attributes()
+                    .flatten()
+                    .find(|a| a.key.as_ref() == b"full-path")
+                    .map(|a| String::from_utf8_lossy(&a.value).into_owned());
+                return Ok(path);
+            }
+            Ok(Event::Eof) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+struct Package {
+    /// (id, href) pairs.
+    manifest: Vec<(String, String)>,
+    spine: Vec<String>,
+}
+
+fn parse_package(bytes: &[u8]) -> Result<Package, String> {
+    let bytes = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(bytes.as_ref());
+    let mut buf = Vec::new();
+    let mut package = Package {
+        manifest: Vec::new(),
+        spine: Vec::new(),
+    };
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Empty(e)) | Ok(Event::Start(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.as_ref() == name)
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+                };
+                match local_name(e.name().as_ref()) {
+                    b"item" => package.manifest.push((
+                        attr(b"id").unwrap_or_default(),
+                        attr(b"href").unwrap_or_default(),
+                    )),
+                    b"itemref" => package.spine.extend(attr(b"idref")),
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => return Ok(package),
+            Err(e) => return Err(format!("at byte {}: {e}", reader.buffer_position())),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Collapse `.`/`..` and turn `\` into `/`.
+fn normalize(name: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+/// The archive path an OPF href points at.
+fn resolve_href(opf_path: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let decoded = percent_encoding::percent_decode_str(href).decode_utf8_lossy();
+    match opf_path.rfind('/') {
+        Some(i) => normalize(&format!("{}/{decoded}", &opf_path[..i])),
+        None => normalize(&decoded),
+    }
+}
+
+fn scan(data: &[u8], report: &mut EpubReport) -> Result<Scan, String> {
+    let entries = read_entries(data, report)?;
+
+    let backslashes = entries.iter().filter(|e| e.name.contains('\\')).count();
+    if backslashes > 0 {
+        report.push(
+            IssueCode::BackslashPaths,
+            Severity::Error,
+            true,
+            format!("{backslashes} entries use `\\` as the path separator"),
+        );
+    }
+    let mut lookup = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let name = normalize(&entry.name);
+        if lookup.contains_key(&name) {
+            report
+                .push(
+                    IssueCode::DuplicateEntry,
+                    Severity::Warning,
+                    true,
+                    format!("{name} is in the archive more than once"),
+                )
+                .path = Some(name);
+        } else {
+            lookup.insert(name, i);
+        }
+    }
+
+    match lookup.get("mimetype").map(|&i| (i, &entries[i])) {
+        None => report.push(
+            IssueCode::MissingMimetype,
+            Severity::Error,
+            true,
+            "The mimetype entry is missing".into(),
+        ),
+        Some((i, entry)) => {
+            if i != 0 {
+                report.push(
+                    IssueCode::MimetypeNotFirst,
+                    Severity::Error,
+                    true,
+                    "The mimetype entry isn't the first in the archive".into(),
+                );
+            }
+            if !entry.stored {
+                report.push(
+                    IssueCode::MimetypeCompressed,
+                    Severity::Error,
+                    true,
+                    "The mimetype entry is compressed".into(),
+                );
+            }
+            if String::from_utf8_lossy(&entry.data).trim().as_bytes() != MIMETYPE {
+                report.push(
+                    IssueCode::BadMimetype,
+                    Severity::Error,
+                    true,
+                    format!(
+                        "The mimetype entry reads \"{}\"",
+                        String::from_utf8_lossy(&entry.data).trim()
+                    ),
+                );
+            }
+        }
+    }
+
+    let container = lookup
+        .get(CONTAINER_PATH)
+        .map(|&i| entries[i].data.as_slice());
+    let rootfile = match container.map(rootfile_path) {
+        None => Err((
+            IssueCode::MissingContainer,
+            "META-INF/container.xml is missing".to_string(),
+        )),
+        Some(Err(e)) => Err((
+            IssueCode::BadContainer,
+            format!("META-INF/container.xml is malformed: {e}"),
+        )),
+        Some(Ok(None)) => Err((
+            IssueCode::BadContainer,
+            "META-INF/container.xml names no package document".to_string(),
+        )),
+        Some(Ok(Some(path))) if !lookup.contains_key(&normalize(&path)) => Err((
+            IssueCode::BadContainer,
+            format!("META-INF/container.xml points at {path}, which isn't in the archive"),
+        )),
+        Some(Ok(Some(path))) => Ok(normalize(&path)),
+    };
+    let mut new_rootfile = None;
+    let opf_path = match rootfile {
+        Ok(path) => path,
+        Err((code, message)) => {
+            let mut candidates: Vec<&String> = lookup
+                .keys()
+                .filter(|name| name.to_ascii_lowercase().ends_with(".opf"))
+                .collect();
+            candidates.sort_by_key(|name| (name.matches('/').count(), name.to_string()));
+            let Some(found) = candidates.first().map(|s| s.to_string()) else {
+                report.push(code, Severity::Error, false, message);
+                report.push(
+                    IssueCode::MissingPackage,
+                    Severity::Error,
+                    false,
+                    "The archive has no package document (.opf)".into(),
+                );
+                return Ok(Scan {
+                    entries,
+                    lookup,
+                    new_rootfile,
+                });
+            };
+            report.push(
+                code,
+                Severity::Error,
+                true,
+                format!("{message}; it will point at {found}"),
+            );
+            new_rootfile = Some(found.clone());
+            found
+        }
+    };
+
+    let package = match parse_package(&entries[lookup[&opf_path]].data) {
+        Ok(package) => package,
+        Err(e) => {
+            report
+                .push(
+                    IssueCode::MalformedPackage,
+                    Severity::Error,
+                    false,
+                    format!("{opf_path} is malformed XML {e}"),
+                )
+                .path = Some(opf_path);
+            return Ok(Scan {
+                entries,
+                lookup,
+                new_rootfile,
+            });
+        }
+    };
+    let mut ids = HashSet::new();
+    for (id, href) in &package.manifest {
+        ids.insert(id.as_str());
+        if href.contains("://") {
+            continue;
+        }
+        let path = resolve_href(&opf_path, href);
+        if !lookup.contains_key(&path) {
+            report
+                .push(
+                    IssueCode::MissingResource,
+                    Severity::Warning,
+                    false,
+                    format!("The manifest lists {path}, which isn't in the archive"),
+                )
+                .path = Some(path);
+        }
+    }
+    if package.spine.is_empty() {
+        report.push(
+            IssueCode::EmptySpine,
+            Severity::Error,
+            false,
+            "The spine lists no content documents".into(),
+        );
+    }
+    for idref in &package.spine {
+        if !ids.contains(idref.as_str()) {
+            report.push(
+                IssueCode::BrokenSpineReference,
+                Severity::Warning,
+                false,
+                format!("The spine refers to \"{idref}\", which isn't in the manifest"),
+            );
+        }
+    }
+    Ok(Scan {
+        entries,
+        lookup,
+        new_rootfile,
+    })
+}
+
+fn container_xml(rootfile: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+         \x20 <rootfiles>\n\
+         \x20   <rootfile full-path=\"{rootfile}\" media-type=\"application/oebps-package+xml\"/>\n\
+         \x20 </rootfiles>\n\
+         </container>\n"
+    )
+}
+
+/// Re-zip the scanned entries: a stored `mimetype` first, then
+/// `container.xml`, then everything else once, under normalised names.
+fn rebuild(scan: &Scan) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, data: &[u8], options: SimpleFileOptions| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(data).map_err(|e| e.to_string())
+    };
+    add("mimetype", MIMETYPE, stored)?;
+    match (&scan.new_rootfile, scan.lookup.get(CONTAINER_PATH)) {
+        (Some(rootfile), _) => add(CONTAINER_PATH, container_xml(rootfile).as_bytes(), deflated)?,
+        (None, Some(&i)) => add(CONTAINER_PATH, &scan.entries[i].data, deflated)?,
+        (None, None) => {}
+    }
+    let mut entries: Vec<(&String, &usize)> = scan.lookup.iter().collect();
+    entries.sort_by_key(|&(_, &i)| i);
+    for (name, &i) in entries {
+        if name == "mimetype" || name == CONTAINER_PATH {
+            continue;
+        }
+        let entry = &scan.entries[i];
+        add(
+            name,
+            &entry.data,
+            if entry.stored { stored } else { deflated },
+        )?;
+    }
+    let cursor = zip.finish().map_err(|e| e.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+fn check(data: &[u8]) -> Result<(EpubReport, Scan), String> {
+    let mut report = EpubReport::default();
+    let scan = scan(data, &mut report)?;
+    Ok((report, scan))
+}
+
+fn repair(data: &[u8], output: &Path) -> Result<EpubReport, String> {
+    let (mut report, scan) = check(data)?;
+    if !report.needs_repair() {
+        return Ok(report);
+    }
+    let repaired = rebuild(&scan)?;
+    report.remaining = check(&repaired)?.0.issues;
+    let part = output.with_extension("epub.part");
+    std::fs::write(&part, &repaired).map_err(|e| e.to_string())?;
+    std::fs::rename(&part, output).map_err(|e| e.to_string())?;
+    report.repaired_path = Some(output.to_string_lossy().into_owned());
+    Ok(report)
+}
+
+/// Report the structural problems of the EPUB at `file_path`.
+#[tauri::command]
+pub async fn check_epub(app: AppHandle, file_path: String) -> Result<EpubReport, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let data = std::fs::read(&file_path).map_err(|e| format!("open failed: {e}"))?;
+        check(&data).map(|(report, _)| report)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Check the EPUB at `file_path` and, if anything fixable turns up, write
+/// a repaired copy to `output_path` (by default over the original).
+#[tauri::command]
+pub async fn repair_epub(
+    app: AppHandle,
+    file_path: String,
+    output_path: Option<String>,
+) -> Result<EpubReport, String> {
+    let output_path = output_path.unwrap_or_else(|| file_path.clone());
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    ensure_path_allowed(&app, &output_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let data = std::fs::read(&file_path).map_err(|e| format!("open failed: {e}"))?;
+        repair(&data, Path::new(&output_path))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <manifest>
+    <item id="c1" href="text/ch%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="img" href="images/missing.png" media-type="image/png"/>
+  </manifest>
+  <spine><itemref idref="c1"/><itemref idref="gone"/></spine>
+</package>"#;
+
+    fn build(entries: &[(&str, &[u8], CompressionMethod)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data, method) in entries {
+            zip.start_file(
+                *name,
+                SimpleFileOptions::default().compression_method(*method),
+            )
+            .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn codes(issues: &[EpubIssue]) -> Vec<IssueCode> {
+        issues.iter().map(|i| i.code).collect()
+    }
+
+    #[test]
+    fn a_well_formed_epub_only_reports_package_problems() {
+        let data = build(&[
+            ("mimetype", MIMETYPE, CompressionMethod::Stored),
+            (
+                CONTAINER_PATH,
+                container_xml("OEBPS/content.opf").as_bytes(),
+                CompressionMethod::Deflated,
+            ),
+            (
+                "OEBPS/content.opf",
+                OPF.as_bytes(),
+                CompressionMethod::Deflated,
+            ),
+            (
+                "OEBPS/text/ch 1.xhtml",
+                b"<html/>",
+                CompressionMethod::Deflated,
+            ),
+        ]);
+        let (report, _) = check(&data).unwrap();
+        assert_eq!(
+            codes(&report.issues),
+            vec![IssueCode::MissingResource, IssueCode::BrokenSpineReference]
+        );
+        assert_eq!(
+            report.issues[0].path.as_deref(),
+            Some("OEBPS/images/missing.png")
+        );
+        assert!(!report.needs_repair());
+    }
+
+    #[test]
+    fn structural_problems_are_repaired() {
+        let data = build(&[
+            (
+                "OEBPS\\content.opf",
+                OPF.as_bytes(),
+                CompressionMethod::Deflated,
+            ),
+            (
+                "OEBPS\\text\\ch 1.xhtml",
+                b"<html/>",
+                CompressionMethod::Deflated,
+            ),
+            (
+                "mimetype",
+                b"application/zip\n",
+                CompressionMethod::Deflated,
+            ),
+        ]);
+        let (report, _) = check(&data).unwrap();
+        assert_eq!(
+            codes(&report.issues),
+            vec![
+                IssueCode::BackslashPaths,
+                IssueCode::MimetypeNotFirst,
+                IssueCode::MimetypeCompressed,
+                IssueCode::BadMimetype,
+                IssueCode::MissingContainer,
+                IssueCode::MissingResource,
+                IssueCode::BrokenSpineReference,
+            ]
+        );
+
+        let dir = std::env::temp_dir().join("readest-epub-repair-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("repaired.epub");
+        let report = repair(&data, &output).unwrap();
+        assert_eq!(
+            report.repaired_path.as_deref(),
+            Some(output.to_string_lossy().as_ref())
+        );
+        assert_eq!(
+            codes(&report.remaining),
+            vec![IssueCode::MissingResource, IssueCode::BrokenSpineReference]
+        );
+
+        let repaired = std::fs::read(&output).unwrap();
+        assert_eq!(&repaired[30..38], b"mimetype");
+        let mut zip = ZipArchive::new(Cursor::new(repaired)).unwrap();
+        assert_eq!(
+            zip.by_index(0).unwrap().compression(),
+            CompressionMethod::Stored
+        );
+        assert!(zip.by_name("OEBPS/text/ch 1.xhtml").is_ok());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn entries_are_recovered_without_a_central_directory() {
+        let data = build(&[
+            ("mimetype", MIMETYPE, CompressionMethod::Stored),
+            (
+                CONTAINER_PATH,
+                container_xml("content.opf").as_bytes(),
+                CompressionMethod::Deflated,
+            ),
+            ("content.opf", OPF.as_bytes(), CompressionMethod::Deflated),
+            ("text/ch 1.xhtml", b"<html/>", CompressionMethod::Deflated),
+        ]);
+        // Cut the archive off inside the central directory.
+        let end = data.windows(4).rposition(|w| w == b"PK\x01\x02").unwrap();
+        let (report, scan) = check(&data[..end]).unwrap();
+        assert_eq!(report.issues[0].code, IssueCode::BrokenZip);
+        assert_eq!(scan.entries.len(), 4);
+        assert!(rebuild(&scan).is_ok());
+    }
+}
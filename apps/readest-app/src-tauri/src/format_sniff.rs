@@ -0,0 +1,258 @@
+// Content sniffing for book files.
+//
+// Extensions lie: browser downloads land as `.bin` / `.tmp` / no extension,
+// and some stores ship AZW3 renamed to `.mobi` or EPUBs as `.zip`. The
+// scanner used to trust the extension alone, so such files were either
+// skipped or handed to the wrong parser. This looks at the bytes instead:
+//
+//   - PDF: `%PDF-` within the first 1 KiB (the spec allows leading junk);
+//   - EPUB: a ZIP whose first entry is the stored `mimetype` file holding
+//     `application/epub+zip` (OCF requirement), falling back to the
+//     presence of `META-INF/container.xml` for sloppy packagers;
+//   - CBZ: a ZIP that isn't an EPUB and holds only images (plus an optional
+//     ComicInfo.xml);
+//   - CBR / CB7: a RAR or 7z archive holding only images, by the same rule;
+//     other RARs are archives of books, other 7z files aren't books at all;
+//   - MOBI/AZW: the PalmDB type/creator `BOOKMOBI` at offset 60;
+//   - FB2: an XML document with a `<FictionBook` root.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::comic::archive::{list_entries, ComicFormat};
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const EPUB_MIMETYPE: &[u8] = b"application/epub+zip";
+const RAR_MAGIC: &[u8] = b"Rar!\x1a\x07";
+const SEVEN_ZIP_MAGIC: &[u8] = b"7z\xbc\xaf\x27\x1c";
+const MOBI_MAGIC: &[u8] = b"BOOKMOBI";
+const MOBI_MAGIC_OFFSET: usize = 60;
+/// Enough for the PDF search window and the `mimetype` local file header.
+const SNIFF_LEN: usize = 1024;
+
+const COMIC_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "avif", "bmp"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookFormat {
+    Epub,
+    Pdf,
+    Mobi,
+    Cbz,
+    Cbr,
+    Cb7,
+    Fb2,
+    Zip,
+    Rar,
+}
+
+impl BookFormat {
+    /// File extensions a caller may use to ask for this format.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            BookFormat::Epub => &["epub"],
+            BookFormat::Pdf => &["pdf"],
+            BookFormat::Mobi => &["mobi", "azw", "azw3", "prc"],
+            BookFormat::Cbz => &["cbz"],
+            BookFormat::Cbr => &["cbr"],
+            BookFormat::Cb7 => &["cb7"],
+            BookFormat::Fb2 => &["fb2"],
+            BookFormat::Zip => &["zip"],
+            BookFormat::Rar => &["rar"],
+        }
+    }
+}
+
+/// Detect the format of the file at `path` from its contents. `None` when
+/// the file can't be read or isn't a recognised book container.
+pub fn sniff_format(path: &Path) -> Option<BookFormat> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    match sniff_bytes(&head)? {
+        BookFormat::Zip => Some(sniff_zip_contents(path)),
+        BookFormat::Rar => sniff_comic_archive(path, ComicFormat::Cbr),
+        BookFormat::Cb7 => sniff_comic_archive(path, ComicFormat::Cb7),
+        format => Some(format),
+    }
+}
+
+/// Classify from the leading bytes alone. ZIPs that don't carry an EPUB
+/// `mimetype` first entry come back as [`BookFormat::Zip`] and need a look
+/// at the central directory to tell EPUB/CBZ/plain archive apart; RAR
+/// ([`BookFormat::Rar`]) and 7z ([`BookFormat::Cb7`]) likewise need their
+/// entry list checked.
+fn sniff_bytes(head: &[u8]) -> Option<BookFormat> {
+    if head.starts_with(ZIP_MAGIC) {
+        return Some(if has_epub_mimetype_entry(head) {
+            BookFormat::Epub
+        } else {
+            BookFormat::Zip
+        });
+    }
+    if head.starts_with(RAR_MAGIC) {
+        return Some(BookFormat::Rar);
+    }
+    if head.starts_with(SEVEN_ZIP_MAGIC) {
+        return Some(BookFormat::Cb7);
+    }
+    if head.windows(PDF_MAGIC.len()).any(|w| w == PDF_MAGIC) {
+        return Some(BookFormat::Pdf);
+    }
+    if head.get(MOBI_MAGIC_OFFSET..MOBI_MAGIC_OFFSET + MOBI_MAGIC.len()) == Some(MOBI_MAGIC) {
+        return Some(BookFormat::Mobi);
+    }
+    if String::from_utf8_lossy(head).contains("<FictionBook") {
+        return Some(BookFormat::Fb2);
+    }
+    None
+}
+
+/// OCF puts an uncompressed `mimetype` entry first, so its name starts at
+/// byte 30 of the local file header and its data right after the extra field.
+fn has_epub_mimetype_entry(head: &[u8]) -> bool {
+    let u16_at = |i: usize| {
+        head.get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+    };
+    let (Some(name_len), Some(extra_len)) = (u16_at(26), u16_at(28)) else {
+        return false;
+    };
+    if head.get(30..30 + name_len) != Some(b"mimetype".as_slice()) {
+        return false;
+    }
+    let data = 30 + name_len + extra_len;
+    head.get(data..data + EPUB_MIMETYPE.len()) == Some(EPUB_MIMETYPE)
+}
+
+fn sniff_zip_contents(path: &Path) -> BookFormat {
+    match File::open(path).map(|f| ZipArchive::new(BufReader::new(f))) {
+        Ok(Ok(zip)) => classify_zip_names(zip.file_names()),
+        _ => BookFormat::Zip,
+    }
+}
+
+/// A RAR or 7z archive is a comic when its entries would make a ZIP a CBZ.
+fn sniff_comic_archive(path: &Path, comic: ComicFormat) -> Option<BookFormat> {
+    let is_comic = list_entries(path, comic).is_ok_and(|entries| {
+        classify_zip_names(entries.iter().map(|e| e.name.as_str())) == BookFormat::Cbz
+    });
+    match (comic, is_comic) {
+        (ComicFormat::Cbr, true) => Some(BookFormat::Cbr),
+        (ComicFormat::Cbr, false) => Some(BookFormat::Rar),
+        (_, true) => Some(BookFormat::Cb7),
+        (_, false) => None,
+    }
+}
+
+fn classify_zip_names<'a>(names: impl Iterator<Item = &'a str>) -> BookFormat {
+    let mut images = 0usize;
+    let mut others = 0usize;
+    for name in names {
+        if name.eq_ignore_ascii_case("META-INF/container.xml") {
+            return BookFormat::Epub;
+        }
+        if name.ends_with('/') {
+            continue;
+        }
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        let ext = file_name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        if COMIC_IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+            images += 1;
+        } else if !file_name.eq_ignore_ascii_case("ComicInfo.xml") && !file_name.starts_with('.') {
+            others += 1;
+        }
+    }
+    if images > 0 && others == 0 {
+        BookFormat::Cbz
+    } else {
+        BookFormat::Zip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_header(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut h = ZIP_MAGIC.to_vec();
+        h.extend_from_slice(&[0u8; 22]);
+        h.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        h.extend_from_slice(&0u16.to_le_bytes());
+        h.extend_from_slice(name.as_bytes());
+        h.extend_from_slice(data);
+        h
+    }
+
+    #[test]
+    fn detects_epub_from_mimetype_entry() {
+        let head = local_header("mimetype", EPUB_MIMETYPE);
+        assert_eq!(sniff_bytes(&head), Some(BookFormat::Epub));
+    }
+
+    #[test]
+    fn zip_without_mimetype_needs_central_directory() {
+        let head = local_header("page001.jpg", b"\xff\xd8");
+        assert_eq!(sniff_bytes(&head), Some(BookFormat::Zip));
+    }
+
+    #[test]
+    fn detects_pdf_with_leading_junk() {
+        assert_eq!(sniff_bytes(b"%PDF-1.7\n"), Some(BookFormat::Pdf));
+        assert_eq!(sniff_bytes(b"\r\n\0garbage%PDF-1.4"), Some(BookFormat::Pdf));
+    }
+
+    #[test]
+    fn detects_mobi_palmdb_header() {
+        let mut head = vec![0u8; 78];
+        head[MOBI_MAGIC_OFFSET..MOBI_MAGIC_OFFSET + 8].copy_from_slice(MOBI_MAGIC);
+        assert_eq!(sniff_bytes(&head), Some(BookFormat::Mobi));
+    }
+
+    #[test]
+    fn detects_fb2() {
+        let head = br#"<?xml version="1.0" encoding="utf-8"?><FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0">"#;
+        assert_eq!(sniff_bytes(head), Some(BookFormat::Fb2));
+    }
+
+    #[test]
+    fn rar_and_7z_need_their_entries_checked() {
+        assert_eq!(sniff_bytes(b"Rar!\x1a\x07\x01\x00"), Some(BookFormat::Rar));
+        assert_eq!(
+            sniff_bytes(b"7z\xbc\xaf\x27\x1c\x00\x04"),
+            Some(BookFormat::Cb7)
+        );
+    }
+
+    #[test]
+    fn unknown_bytes_are_none() {
+        assert_eq!(sniff_bytes(b"hello world"), None);
+        assert_eq!(sniff_bytes(b""), None);
+    }
+
+    #[test]
+    fn classifies_zip_by_entry_names() {
+        let epub = ["OEBPS/a.xhtml", "META-INF/container.xml"];
+        assert_eq!(classify_zip_names(epub.into_iter()), BookFormat::Epub);
+        let comic = [
+            "ch1/",
+            "ch1/001.JPG",
+            "ch1/002.png",
+            "ComicInfo.xml",
+            ".DS_Store",
+        ];
+        assert_eq!(classify_zip_names(comic.into_iter()), BookFormat::Cbz);
+        let misc = ["001.jpg", "readme.txt"];
+        assert_eq!(classify_zip_names(misc.into_iter()), BookFormat::Zip);
+    }
+}
@@ -0,0 +1,247 @@
+//! System-wide keyboard shortcuts on desktop.
+//!
+//! The shortcuts work while Readest is in the background: one plays or
+//! pauses read-aloud, the other saves what is on the clipboard to the
+//! library. `global-shortcuts.json` in the app config directory maps actions
+//! to other accelerators, or to `null` for none, e.g.
+//! `{ "toggleReadAloud": "Ctrl+Shift+Space", "captureClipboard": null }`. It
+//! is read at launch. Pressing one sends a `global-shortcut` event: read-aloud
+//! to the window focused last, so the book being read gets it, and clipboard
+//! captures to the main window with the clipboard read here.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+const SHORTCUT_EVENT: &str = "global-shortcut";
+/// Accelerator overrides, in the app config directory.
+const GLOBAL_SHORTCUTS_FILENAME: &str = "global-shortcuts.json";
+
+/// The actions and their default accelerators. Global shortcuts take their
+/// keys from every other app, so the defaults need three keys.
+const GLOBAL_ACTIONS: &[(&str, &str)] = &[
+    ("toggleReadAloud", "CmdOrCtrl+Alt+P"),
+    ("captureClipboard", "CmdOrCtrl+Alt+V"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutAction {
+    action: &'static str,
+    /// The clipboard's link, for `captureClipboard`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// The clipboard's text when it is not a link, for `captureClipboard`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Default)]
+pub struct GlobalShortcuts {
+    /// Actions by the id of their shortcut.
+    actions: Mutex<HashMap<u32, &'static str>>,
+    /// Label of the window focused last.
+    last_focused: Mutex<Option<String>>,
+}
+
+/// Accelerator overrides by action from `filename` in the app config
+/// directory, empty when there is none or it doesn't parse. Shared with the
+/// macOS menu's `menu-shortcuts.json`.
+pub(crate) fn load_accelerator_overrides(
+    app: &AppHandle,
+    filename: &str,
+) -> HashMap<String, Option<String>> {
+    let Ok(config_dir) = app.path().app_config_dir() else {
+        return HashMap::new();
+    };
+    let Ok(contents) = fs::read_to_string(config_dir.join(filename)) else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("Ignoring {filename}: {e}");
+        HashMap::new()
+    })
+}
+
+/// The accelerator of `action` after `overrides`: the override if there is
+/// one (`null` or blank for none), otherwise `default`.
+pub(crate) fn resolve_accelerator(
+    overrides: &HashMap<String, Option<String>>,
+    action: &str,
+    default: Option<&str>,
+) -> Option<String> {
+    match overrides.get(action) {
+        Some(custom) => custom.clone().filter(|keys| !keys.trim().is_empty()),
+        None => default.map(str::to_string),
+    }
+}
+
+/// The actions that have an accelerator after `overrides`.
+fn resolve_shortcuts(overrides: &HashMap<String, Option<String>>) -> Vec<(&'static str, String)> {
+    GLOBAL_ACTIONS
+        .iter()
+        .filter_map(|&(action, default)| {
+            resolve_accelerator(overrides, action, Some(default)).map(|keys| (action, keys))
+        })
+        .collect()
+}
+
+/// What a capture saves: a link is clipped as an article, anything else
+/// is imported as text.
+fn capture_action(clipboard: &str) -> Option<ShortcutAction> {
+    let content = clipboard.trim();
+    if content.is_empty() {
+        return None;
+    }
+    let lowercase = content.to_ascii_lowercase();
+    let is_link = (lowercase.starts_with("http://") || lowercase.starts_with("https://"))
+        && !content.contains(char::is_whitespace);
+    Some(ShortcutAction {
+        action: "captureClipboard",
+        url: is_link.then(|| content.to_string()),
+        text: (!is_link).then(|| content.to_string()),
+    })
+}
+
+fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let Some(shortcuts) = app.try_state::<GlobalShortcuts>() else {
+        return;
+    };
+    let Some(action) = shortcuts
+        .actions
+        .lock()
+        .unwrap()
+        .get(&shortcut.id())
+        .copied()
+    else {
+        return;
+    };
+    match action {
+        "toggleReadAloud" => {
+            let target = shortcuts
+                .last_focused
+                .lock()
+                .unwrap()
+                .clone()
+                .filter(|label| app.get_webview_window(label).is_some())
+                .unwrap_or_else(|| "main".to_string());
+            let _ = app.emit_to(
+                target.as_str(),
+                SHORTCUT_EVENT,
+                ShortcutAction {
+                    action,
+                    url: None,
+                    text: None,
+                },
+            );
+        }
+        "captureClipboard" => match app.clipboard().read_text() {
+            Ok(clipboard) => match capture_action(&clipboard) {
+                Some(capture) => {
+                    let _ = app.emit_to("main", SHORTCUT_EVENT, capture);
+                }
+                None => log::info!("Nothing on the clipboard to capture"),
+            },
+            Err(e) => log::warn!("Failed to read the clipboard: {e}"),
+        },
+        _ => {}
+    }
+}
+
+/// Register the shortcuts. Call once during setup.
+pub fn setup(app: &AppHandle) -> tauri::Result<()> {
+    app.manage(GlobalShortcuts::default());
+    app.plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(handle_shortcut)
+            .build(),
+    )?;
+    let shortcuts = app.state::<GlobalShortcuts>();
+    let mut actions = shortcuts.actions.lock().unwrap();
+    let overrides = load_accelerator_overrides(app, GLOBAL_SHORTCUTS_FILENAME);
+    for (action, keys) in resolve_shortcuts(&overrides) {
+        let shortcut = match keys.parse::<Shortcut>() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                log::warn!("Invalid global shortcut {keys:?} for {action}: {e}");
+                continue;
+            }
+        };
+        // Another app may hold the keys already; the rest still register.
+        if let Err(e) = app.global_shortcut().register(shortcut) {
+            log::warn!("Failed to register global shortcut {keys:?} for {action}: {e}");
+            continue;
+        }
+        actions.insert(shortcut.id(), action);
+    }
+    Ok(())
+}
+
+/// Note that the window labeled `label` got focus.
+pub fn window_focused(app: &AppHandle, label: &str) {
+    if let Some(shortcuts) = app.try_state::<GlobalShortcuts>() {
+        *shortcuts.last_focused.lock().unwrap() = Some(label.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_or_clear_default_shortcuts() {
+        let overrides = HashMap::from([
+            (
+                "toggleReadAloud".to_string(),
+                Some("Ctrl+Shift+Space".to_string()),
+            ),
+            ("captureClipboard".to_string(), None),
+        ]);
+        assert_eq!(
+            resolve_shortcuts(&overrides),
+            [("toggleReadAloud", "Ctrl+Shift+Space".to_string())]
+        );
+        let overrides = HashMap::from([("toggleReadAloud".to_string(), Some(" ".to_string()))]);
+        assert_eq!(
+            resolve_shortcuts(&overrides),
+            [("captureClipboard", "CmdOrCtrl+Alt+V".to_string())]
+        );
+    }
+
+    #[test]
+    fn captures_links_as_urls_and_anything_else_as_text() {
+        let capture = capture_action("  https://example.com/article\n").unwrap();
+        assert_eq!(capture.url.as_deref(), Some("https://example.com/article"));
+        assert_eq!(capture.text, None);
+
+        let capture = capture_action("https://example.com is a good read").unwrap();
+        assert_eq!(capture.url, None);
+        assert_eq!(
+            capture.text.as_deref(),
+            Some("https://example.com is a good read")
+        );
+
+        assert_eq!(capture_action(" \n\t"), None);
+    }
+
+    #[test]
+    fn actions_serialize_for_the_webview() {
+        let action = ShortcutAction {
+            action: "captureClipboard",
+            url: Some("https://example.com".into()),
+            text: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"action":"captureClipboard","url":"https://example.com"}"#
+        );
+    }
+}
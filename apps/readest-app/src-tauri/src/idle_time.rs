@@ -0,0 +1,89 @@
+//! How long the user has left the computer alone, on desktop.
+//!
+//! Reading statistics would otherwise count the minutes after someone walks
+//! away as reading; the tracker asks `get_system_idle_seconds` before ending
+//! a page event and ends it at the last keyboard or mouse input instead. The
+//! time comes from `GetLastInputInfo` on Windows, the HID event source on
+//! macOS, and on Linux from Mutter's idle monitor on GNOME or the
+//! `org.freedesktop.ScreenSaver` session idle time elsewhere.
+
+#[cfg(target_os = "windows")]
+fn idle_seconds() -> Result<u64, String> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return Err("GetLastInputInfo failed".into());
+    }
+    // Both tick counts wrap every 49.7 days, together.
+    let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Ok(u64::from(idle_ms) / 1000)
+}
+
+#[cfg(target_os = "macos")]
+fn idle_seconds() -> Result<u64, String> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+    }
+    /// `kCGEventSourceStateHIDSystemState`: input from the hardware.
+    const HID_SYSTEM_STATE: i32 = 1;
+    /// `kCGAnyInputEventType`.
+    const ANY_INPUT_EVENT_TYPE: u32 = !0;
+
+    let seconds =
+        unsafe { CGEventSourceSecondsSinceLastEventType(HID_SYSTEM_STATE, ANY_INPUT_EVENT_TYPE) };
+    Ok(seconds.max(0.0) as u64)
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::proxy(
+    interface = "org.gnome.Mutter.IdleMonitor",
+    default_service = "org.gnome.Mutter.IdleMonitor",
+    default_path = "/org/gnome/Mutter/IdleMonitor/Core"
+)]
+trait MutterIdleMonitor {
+    /// Milliseconds since the last input.
+    fn get_idletime(&self) -> zbus::Result<u64>;
+}
+
+#[cfg(target_os = "linux")]
+async fn idle_seconds() -> Result<u64, String> {
+    use crate::screensaver::FreedesktopScreenSaverProxy;
+
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| e.to_string())?;
+    // GNOME doesn't implement GetSessionIdleTime; KDE and others don't run
+    // Mutter.
+    let mutter = async {
+        MutterIdleMonitorProxy::new(&connection)
+            .await?
+            .get_idletime()
+            .await
+    };
+    if let Ok(idle_ms) = mutter.await {
+        return Ok(idle_ms / 1000);
+    }
+    FreedesktopScreenSaverProxy::new(&connection)
+        .await
+        .map_err(|e| e.to_string())?
+        .get_session_idle_time()
+        .await
+        .map(u64::from)
+        .map_err(|e| e.to_string())
+}
+
+/// Seconds since the last keyboard or mouse input anywhere on the system.
+#[tauri::command]
+pub async fn get_system_idle_seconds() -> Result<u64, String> {
+    #[cfg(target_os = "linux")]
+    let idle = idle_seconds().await;
+    #[cfg(not(target_os = "linux"))]
+    let idle = idle_seconds();
+    idle.map_err(|e| format!("Failed to read the system idle time: {e}"))
+}
@@ -0,0 +1,187 @@
+//! Keeping the computer from sleeping while a book reads aloud or scrolls.
+//!
+//! Reader windows ask through `set_keep_awake` while read-aloud or auto-scroll
+//! runs, and the system stays awake while any window asks. The backends are
+//! `SetThreadExecutionState` on Windows (`windows::power`), an IOKit power
+//! assertion on macOS (`macos::power`) and a logind idle inhibitor on Linux.
+//! None of them keeps the screen on; that is the "Keep Screen Awake" setting.
+//! On iOS and Android the command does nothing: read-aloud keeps the device
+//! awake through its audio session, and the reader keeps the screen on through
+//! the native bridge's `set_keep_awake`.
+
+use std::collections::HashSet;
+
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+use tokio::sync::Mutex;
+
+#[cfg(target_os = "linux")]
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    fn inhibit(
+        &self,
+        what: &str,
+        who: &str,
+        why: &str,
+        mode: &str,
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+}
+
+/// Shown by systems that list what is keeping them awake.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+const REASON: &str = "Reading a book aloud or scrolling through it";
+
+/// What keeps the system awake while held.
+#[cfg(target_os = "macos")]
+type Held = crate::macos::power::PowerAssertion;
+/// Logind lifts the inhibitor when its file descriptor closes.
+#[cfg(target_os = "linux")]
+type Held = zbus::zvariant::OwnedFd;
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+type Held = ();
+
+#[cfg(target_os = "windows")]
+async fn acquire(app: &AppHandle) -> Result<Held, String> {
+    crate::windows::power::set_system_required(app, true)
+}
+
+#[cfg(target_os = "macos")]
+async fn acquire(_app: &AppHandle) -> Result<Held, String> {
+    crate::macos::power::PowerAssertion::prevent_idle_sleep(REASON)
+}
+
+#[cfg(target_os = "linux")]
+async fn acquire(_app: &AppHandle) -> Result<Held, String> {
+    async {
+        let connection = zbus::Connection::system().await?;
+        Login1ManagerProxy::new(&connection)
+            .await?
+            // "idle" keeps the system from suspending when left alone, and
+            // still lets the user suspend it.
+            .inhibit("idle", "Readest", REASON, "block")
+            .await
+    }
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(mobile)]
+async fn acquire(_app: &AppHandle) -> Result<Held, String> {
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn release(app: &AppHandle, _held: Held) {
+    if let Err(e) = crate::windows::power::set_system_required(app, false) {
+        log::warn!("Failed to let the system sleep: {e}");
+    }
+}
+
+/// Dropping what is held lets the system sleep again.
+#[cfg(not(target_os = "windows"))]
+fn release(_app: &AppHandle, _held: Held) {}
+
+/// What a change of the windows asking calls for.
+#[derive(Debug, PartialEq)]
+enum Change {
+    Acquire,
+    Release,
+    Keep,
+}
+
+fn change_for(windows: &HashSet<String>, held: bool) -> Change {
+    match (windows.is_empty(), held) {
+        (false, false) => Change::Acquire,
+        (true, true) => Change::Release,
+        _ => Change::Keep,
+    }
+}
+
+#[derive(Default)]
+struct Holders {
+    /// Labels of the windows asking to keep the system awake.
+    windows: HashSet<String>,
+    held: Option<Held>,
+}
+
+#[derive(Default)]
+pub struct KeepAwake(Mutex<Holders>);
+
+async fn update(
+    app: &AppHandle,
+    keep_awake: &KeepAwake,
+    label: &str,
+    asking: bool,
+) -> Result<(), String> {
+    let mut holders = keep_awake.0.lock().await;
+    if asking {
+        holders.windows.insert(label.to_string());
+    } else {
+        holders.windows.remove(label);
+    }
+    match change_for(&holders.windows, holders.held.is_some()) {
+        Change::Acquire => {
+            let held = acquire(app)
+                .await
+                .map_err(|e| format!("Failed to keep the system awake: {e}"))?;
+            holders.held = Some(held);
+        }
+        Change::Release => {
+            if let Some(held) = holders.held.take() {
+                release(app, held);
+            }
+        }
+        Change::Keep => {}
+    }
+    Ok(())
+}
+
+/// Keep the system from sleeping while the calling window, or any other,
+/// asks to.
+#[tauri::command]
+pub async fn set_keep_awake(
+    app: AppHandle,
+    window: WebviewWindow,
+    keep_awake: State<'_, KeepAwake>,
+    enabled: bool,
+) -> Result<(), String> {
+    update(&app, &keep_awake, window.label(), enabled).await
+}
+
+/// Drop what the window labeled `label` asked for, when it closes without
+/// saying so.
+pub fn window_destroyed(app: &AppHandle, label: &str) {
+    let app = app.clone();
+    let label = label.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Some(keep_awake) = app.try_state::<KeepAwake>() {
+            if let Err(e) = update(&app, &keep_awake, &label, false).await {
+                log::warn!("{e}");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_while_any_window_asks() {
+        let mut windows = HashSet::new();
+        assert_eq!(change_for(&windows, false), Change::Keep);
+
+        windows.insert("main".to_string());
+        assert_eq!(change_for(&windows, false), Change::Acquire);
+        windows.insert("reader-0a1b".to_string());
+        assert_eq!(change_for(&windows, true), Change::Keep);
+
+        windows.remove("main");
+        assert_eq!(change_for(&windows, true), Change::Keep);
+        windows.remove("reader-0a1b");
+        assert_eq!(change_for(&windows, true), Change::Release);
+    }
+}
@@ -0,0 +1,742 @@
+// Import highlights and notes from a Kindle's `My Clippings.txt`.
+//
+// The file is a flat log of entries separated by `==========` lines:
+//
+//   Title (Author)
+//   - Your Highlight on page 12 | Location 170-171 | Added on Sunday, March 3, 2019 10:20:30 PM
+//
+//   The highlighted text
+//   ==========
+//
+// The second line is written in the Kindle's UI language, so kind, page,
+// location and date are recognised by keyword across the locales Kindles
+// ship with rather than by a fixed pattern. Kindle appends a new entry
+// whenever a highlight is extended, and stores a note as its own entry at
+// the highlight's end location; both are folded back together here.
+//
+// Like the Moon+ importer this only builds a preview with ranked library
+// matches (`library_match`). Kindle locations have no mapping to CFIs, so
+// the frontend locates each highlight by its text once the user has
+// confirmed the book.
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::library_match::{find_matches, BookMatch, LibraryBookRef};
+use crate::transfer_file::ensure_path_allowed;
+
+const SEPARATOR: &str = "==========";
+const BOM: char = '\u{feff}';
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClippingKind {
+    Highlight,
+    Note,
+    Bookmark,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindleClipping {
+    pub kind: ClippingKind,
+    /// Highlighted text, or the note's text for an unpaired note.
+    pub text: String,
+    /// Note attached to a highlight; empty otherwise.
+    pub note: String,
+    /// Printed page label as Kindle shows it (may be roman).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_start: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_end: Option<u32>,
+    /// Kindle's wall-clock time as `YYYY-MM-DDTHH:MM:SS`, without a zone;
+    /// `new Date(addedAt)` reads it as local time, which is what it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindleBook {
+    pub title: String,
+    pub author: String,
+    pub clippings: Vec<KindleClipping>,
+    pub matches: Vec<BookMatch>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindleClippingsPreview {
+    pub books: Vec<KindleBook>,
+    /// Entries whose metadata line couldn't be understood.
+    pub skipped: usize,
+}
+
+// Keywords are matched against the lower-cased metadata line. Bookmarks are
+// checked first and highlights before notes, so e.g. `nota` can't shadow a
+// highlight line.
+const BOOKMARK_WORDS: &[&str] = &[
+    "bookmark",
+    "lesezeichen",
+    "signet",
+    "marcador",
+    "segnalibro",
+    "bladwijzer",
+    "закладка",
+    "ブックマーク",
+    "书签",
+];
+const HIGHLIGHT_WORDS: &[&str] = &[
+    "highlight",
+    "markierung",
+    "surlignement",
+    "subrayado",
+    "evidenziazione",
+    "destaque",
+    "markering",
+    "выделение",
+    "ハイライト",
+    "标注",
+];
+const NOTE_WORDS: &[&str] = &[
+    "note",
+    "notiz",
+    "nota",
+    "notitie",
+    "заметка",
+    "メモ",
+    "笔记",
+];
+/// Longer spellings first so `location` wins over `loc.`.
+const LOCATION_WORDS: &[&str] = &[
+    "location",
+    "loc.",
+    "posición",
+    "posizione",
+    "posição",
+    "position",
+    "emplacement",
+    "locatie",
+    "позиция",
+    "位置",
+];
+const PAGE_WORDS: &[&str] = &["page", "seite", "página", "pagina", "strona", "страниц"];
+/// CJK page markers follow the number: `12ページ`, `第 12 页`.
+const PAGE_SUFFIXES: &[&str] = &["ページ", "页"];
+
+const MONTHS: &[(&str, u32)] = &[
+    // English
+    ("january", 1),
+    ("jan", 1),
+    ("february", 2),
+    ("feb", 2),
+    ("march", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("may", 5),
+    ("june", 6),
+    ("jun", 6),
+    ("july", 7),
+    ("jul", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sep", 9),
+    ("sept", 9),
+    ("october", 10),
+    ("oct", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("december", 12),
+    ("dec", 12),
+    // German
+    ("januar", 1),
+    ("februar", 2),
+    ("märz", 3),
+    ("mai", 5),
+    ("juni", 6),
+    ("juli", 7),
+    ("oktober", 10),
+    ("dezember", 12),
+    // French
+    ("janvier", 1),
+    ("février", 2),
+    ("mars", 3),
+    ("avril", 4),
+    ("juin", 6),
+    ("juillet", 7),
+    ("août", 8),
+    ("septembre", 9),
+    ("octobre", 10),
+    ("novembre", 11),
+    ("décembre", 12),
+    // Spanish
+    ("enero", 1),
+    ("febrero", 2),
+    ("marzo", 3),
+    ("abril", 4),
+    ("mayo", 5),
+    ("junio", 6),
+    ("julio", 7),
+    ("agosto", 8),
+    ("septiembre", 9),
+    ("setiembre", 9),
+    ("octubre", 10),
+    ("noviembre", 11),
+    ("diciembre", 12),
+    // Italian
+    ("gennaio", 1),
+    ("febbraio", 2),
+    ("aprile", 4),
+    ("maggio", 5),
+    ("giugno", 6),
+    ("luglio", 7),
+    ("settembre", 9),
+    ("ottobre", 10),
+    ("dicembre", 12),
+    // Portuguese
+    ("janeiro", 1),
+    ("fevereiro", 2),
+    ("março", 3),
+    ("maio", 5),
+    ("junho", 6),
+    ("julho", 7),
+    ("setembro", 9),
+    ("outubro", 10),
+    ("dezembro", 12),
+    // Dutch
+    ("januari", 1),
+    ("februari", 2),
+    ("maart", 3),
+    ("mei", 5),
+    ("augustus", 8),
+];
+
+/// Split `Title (Author)` on the last balanced parenthesised group, so
+/// `Dune (Dune Chronicles 1) (Frank Herbert)` keeps its series note.
+fn split_title_author(line: &str) -> (String, String) {
+    let line = line.trim_matches(|c: char| c == BOM || c.is_whitespace());
+    if line.ends_with(')') {
+        let mut depth = 0;
+        for (i, c) in line.char_indices().rev() {
+            match c {
+                ')' => depth += 1,
+                '(' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let title = line[..i].trim();
+                        if title.is_empty() {
+                            break;
+                        }
+                        let author = line[i + 1..line.len() - 1].trim();
+                        return (title.to_string(), author.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    (line.to_string(), String::new())
+}
+
+fn classify(meta: &str) -> Option<ClippingKind> {
+    let has = |words: &[&str]| words.iter().any(|w| meta.contains(w));
+    if has(BOOKMARK_WORDS) {
+        Some(ClippingKind::Bookmark)
+    } else if has(HIGHLIGHT_WORDS) {
+        Some(ClippingKind::Highlight)
+    } else if has(NOTE_WORDS) {
+        Some(ClippingKind::Note)
+    } else {
+        None
+    }
+}
+
+/// `170-171`, `170–171` or old Kindles' abbreviated `170-71`.
+fn parse_range(s: &str) -> Option<(u32, u32)> {
+    let s = s.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let start_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let start_str = &s[..start_len];
+    let start: u32 = start_str.parse().ok()?;
+    let rest = &s[start_len..];
+    let Some(rest) = rest.strip_prefix(['-', '–']) else {
+        return Some((start, start));
+    };
+    let end_len = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let end_str = &rest[..end_len];
+    if end_str.is_empty() {
+        return Some((start, start));
+    }
+    let end: u32 = if end_str.len() < start_str.len() {
+        let prefix = &start_str[..start_str.len() - end_str.len()];
+        format!("{prefix}{end_str}").parse().ok()?
+    } else {
+        end_str.parse().ok()?
+    };
+    Some((start, end.max(start)))
+}
+
+fn parse_location(meta: &str) -> Option<(u32, u32)> {
+    meta.split('|').find_map(|segment| {
+        let idx = LOCATION_WORDS
+            .iter()
+            .find_map(|w| segment.find(w).map(|i| i + w.len()))?;
+        parse_range(&segment[idx..])
+    })
+}
+
+fn parse_page(meta: &str) -> Option<String> {
+    meta.split('|').find_map(|segment| {
+        if let Some(idx) = PAGE_WORDS
+            .iter()
+            .find_map(|w| segment.find(w).map(|i| i + w.len()))
+        {
+            let token: String = segment[idx..]
+                .trim_start()
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '-')
+                .collect();
+            return (!token.is_empty()).then_some(token);
+        }
+        let idx = PAGE_SUFFIXES.iter().find_map(|w| segment.find(w))?;
+        let before = segment[..idx].trim_end();
+        let digits = before.len()
+            - before
+                .chars()
+                .rev()
+                .take_while(char::is_ascii_digit)
+                .count();
+        let token = &before[digits..];
+        (!token.is_empty()).then(|| token.to_string())
+    })
+}
+
+/// Find the first `H:MM[:SS]` in `s`: `(start, end, h, m, s)` byte offsets
+/// and values.
+fn find_time(s: &str) -> Option<(usize, usize, u32, u32, u32)> {
+    let bytes = s.as_bytes();
+    let digits_at = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let n = digits_at(i);
+        if n == 0 {
+            i += 1;
+            continue;
+        }
+        let colon = i + n;
+        if (1..=2).contains(&n) && bytes.get(colon) == Some(&b':') && digits_at(colon + 1) == 2 {
+            let hour = s[i..colon].parse().ok()?;
+            let minute = s[colon + 1..colon + 3].parse().ok()?;
+            let mut end = colon + 3;
+            let mut second = 0;
+            if bytes.get(end) == Some(&b':') && digits_at(end + 1) == 2 {
+                second = s[end + 1..end + 3].parse().ok()?;
+                end += 3;
+            }
+            return Some((i, end, hour, minute, second));
+        }
+        i += n;
+    }
+    None
+}
+
+/// Parse the `Added on …` part of a metadata line in any Kindle locale into
+/// `YYYY-MM-DDTHH:MM:SS`.
+fn parse_added_at(meta: &str) -> Option<String> {
+    let text = meta.rsplit('|').next()?.to_lowercase();
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    let mut date_text = text.clone();
+    if let Some((start, end, h, m, s)) = find_time(&text) {
+        (hour, minute, second) = (h, m, s);
+        let after = text[end..].trim_start();
+        let before = &text[..start];
+        let pm = after.starts_with("pm")
+            || after.starts_with("p.m.")
+            || ["下午", "午後", "晚上"].iter().any(|w| before.contains(w));
+        let am = after.starts_with("am")
+            || after.starts_with("a.m.")
+            || ["上午", "午前", "凌晨"].iter().any(|w| before.contains(w));
+        if pm && hour < 12 {
+            hour += 12;
+        } else if am && hour == 12 {
+            hour = 0;
+        }
+        date_text.replace_range(start..end, " ");
+    }
+
+    // Digit runs with the character that follows them (for `年/月/日`).
+    let mut numbers: Vec<(u32, usize, Option<char>)> = Vec::new();
+    let mut chars = date_text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        let mut end = i + 1;
+        while let Some(&(j, d)) = chars.peek() {
+            if !d.is_ascii_digit() {
+                break;
+            }
+            end = j + 1;
+            chars.next();
+        }
+        let value = date_text[i..end].parse().ok()?;
+        let next = date_text[end..].chars().next();
+        numbers.push((value, end - i, next));
+    }
+
+    let (year, month, day) = if numbers.iter().any(|n| n.2 == Some('年')) {
+        let find = |marker: char| numbers.iter().find(|n| n.2 == Some(marker)).map(|n| n.0);
+        (find('年')?, find('月')?, find('日')?)
+    } else {
+        let year = numbers.iter().find(|n| n.1 == 4)?.0;
+        let month_name = date_text
+            .split(|c: char| !c.is_alphabetic())
+            .find_map(|word| MONTHS.iter().find(|(name, _)| *name == word))
+            .map(|(_, m)| *m);
+        let others: Vec<u32> = numbers.iter().filter(|n| n.1 != 4).map(|n| n.0).collect();
+        match month_name {
+            Some(month) => (year, month, *others.first()?),
+            // Numeric dates: `2019-03-03`, `03/03/2019` (US) or `03.03.2019`.
+            None => {
+                let (a, b) = (*others.first()?, *others.get(1)?);
+                let year_first = numbers.first().is_some_and(|n| n.1 == 4);
+                if year_first || (a <= 12 && date_text.contains('/')) {
+                    (year, a, b)
+                } else {
+                    (year, b, a)
+                }
+            }
+        }
+    };
+    let valid = (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && hour < 24
+        && minute < 60
+        && second < 60;
+    valid.then(|| format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}"))
+}
+
+struct RawEntry {
+    title: String,
+    author: String,
+    clipping: KindleClipping,
+}
+
+fn parse_entry(block: &[&str]) -> Option<RawEntry> {
+    let mut lines = block
+        .iter()
+        .map(|l| l.trim_start_matches(BOM))
+        .skip_while(|l| l.trim().is_empty());
+    let (title, author) = split_title_author(lines.next()?);
+    let meta_line = lines.next()?.trim();
+    let meta = meta_line.to_lowercase();
+    let kind = classify(&meta)?;
+    let location = parse_location(&meta);
+    let text = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+    Some(RawEntry {
+        title,
+        author,
+        clipping: KindleClipping {
+            kind,
+            text,
+            note: String::new(),
+            page: parse_page(&meta),
+            location_start: location.map(|l| l.0),
+            location_end: location.map(|l| l.1),
+            added_at: parse_added_at(meta_line),
+        },
+    })
+}
+
+fn overlaps(a: &KindleClipping, b: &KindleClipping) -> bool {
+    match (
+        a.location_start,
+        a.location_end,
+        b.location_start,
+        b.location_end,
+    ) {
+        (Some(a0), Some(a1), Some(b0), Some(b1)) => a0 <= b1 && b0 <= a1,
+        _ => a.page.is_some() && a.page == b.page,
+    }
+}
+
+/// Drop superseded versions of extended highlights and duplicate
+/// bookmarks, then attach each note to the highlight it was made on.
+fn merge_clippings(entries: Vec<KindleClipping>) -> Vec<KindleClipping> {
+    let mut merged: Vec<KindleClipping> = Vec::new();
+    for clipping in entries {
+        let superseded = merged.iter().position(|prev| {
+            prev.kind == clipping.kind
+                && match clipping.kind {
+                    ClippingKind::Highlight => {
+                        overlaps(prev, &clipping)
+                            && (clipping.text.contains(prev.text.as_str())
+                                || prev.text.contains(clipping.text.as_str()))
+                    }
+                    ClippingKind::Bookmark => {
+                        prev.location_start == clipping.location_start && prev.page == clipping.page
+                    }
+                    ClippingKind::Note => false,
+                }
+        });
+        match superseded {
+            Some(i) => merged[i] = clipping,
+            None => merged.push(clipping),
+        }
+    }
+
+    let (notes, mut rest): (Vec<_>, Vec<_>) = merged
+        .into_iter()
+        .partition(|c| c.kind == ClippingKind::Note);
+    for note in notes {
+        let target = note.location_start.and_then(|at| {
+            rest.iter_mut().rev().find(|c| {
+                c.kind == ClippingKind::Highlight
+                    && c.note.is_empty()
+                    && c.location_start.is_some_and(|s| s <= at)
+                    && c.location_end.is_some_and(|e| at <= e)
+            })
+        });
+        match target {
+            Some(highlight) => highlight.note = note.text,
+            None => rest.push(note),
+        }
+    }
+    rest.sort_by_key(|c| c.location_start.unwrap_or(u32::MAX));
+    rest
+}
+
+fn parse_clippings(content: &str, library: &[LibraryBookRef]) -> KindleClippingsPreview {
+    let mut skipped = 0;
+    // Books in first-seen order.
+    let mut books: Vec<(String, String, Vec<KindleClipping>)> = Vec::new();
+    let lines: Vec<&str> = content.lines().collect();
+    for block in lines.split(|l| {
+        l.trim_start_matches(BOM)
+            .trim_start()
+            .starts_with(SEPARATOR)
+    }) {
+        if block.iter().all(|l| l.trim_matches(BOM).trim().is_empty()) {
+            continue;
+        }
+        let Some(entry) = parse_entry(block) else {
+            skipped += 1;
+            continue;
+        };
+        if entry.clipping.kind != ClippingKind::Bookmark && entry.clipping.text.is_empty() {
+            continue;
+        }
+        match books
+            .iter_mut()
+            .find(|(t, a, _)| *t == entry.title && *a == entry.author)
+        {
+            Some((_, _, clippings)) => clippings.push(entry.clipping),
+            None => books.push((entry.title, entry.author, vec![entry.clipping])),
+        }
+    }
+    let books = books
+        .into_iter()
+        .map(|(title, author, clippings)| KindleBook {
+            matches: find_matches(library, &title, &author, None, None),
+            clippings: merge_clippings(clippings),
+            title,
+            author,
+        })
+        .collect();
+    KindleClippingsPreview { books, skipped }
+}
+
+/// Parse a `My Clippings.txt` and propose a library match for each book.
+/// Nothing is written; see the module docs for how the result is applied.
+#[tauri::command]
+pub async fn preview_kindle_clippings(
+    app: AppHandle,
+    file_path: String,
+    library: Vec<LibraryBookRef>,
+) -> Result<KindleClippingsPreview, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = std::fs::read(Path::new(&file_path))
+            .map_err(|e| format!("failed to read {file_path}: {e}"))?;
+        Ok(parse_clippings(&String::from_utf8_lossy(&bytes), &library))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_title_and_author() {
+        assert_eq!(
+            split_title_author("\u{feff}Dune (Dune Chronicles 1) (Frank Herbert)"),
+            ("Dune (Dune Chronicles 1)".into(), "Frank Herbert".into())
+        );
+        assert_eq!(
+            split_title_author("Untitled Document"),
+            ("Untitled Document".into(), String::new())
+        );
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range(" 170-171 "), Some((170, 171)));
+        assert_eq!(parse_range(" 1170-75"), Some((1170, 1175)));
+        assert_eq!(parse_range("No. 42"), Some((42, 42)));
+        assert_eq!(parse_range("none"), None);
+    }
+
+    #[test]
+    fn parses_metadata_in_several_locales() {
+        let cases = [
+            (
+                "- Your Highlight on page 12 | Location 170-171 | Added on Sunday, March 3, 2019 10:20:30 PM",
+                ClippingKind::Highlight,
+                Some("12"),
+                Some((170, 171)),
+                "2019-03-03T22:20:30",
+            ),
+            (
+                "- Your Note on Location 171 | Added on Sunday, 3 March 2019 09:05:00",
+                ClippingKind::Note,
+                None,
+                Some((171, 171)),
+                "2019-03-03T09:05:00",
+            ),
+            (
+                "- Highlight Loc. 170-71 | Added on Sunday, March 3, 2019, 12:20 AM",
+                ClippingKind::Highlight,
+                None,
+                Some((170, 171)),
+                "2019-03-03T00:20:00",
+            ),
+            (
+                "- Ihre Markierung auf Seite 12 | bei Position 170-171 | Hinzugefügt am Sonntag, 3. März 2019 22:20:30",
+                ClippingKind::Highlight,
+                Some("12"),
+                Some((170, 171)),
+                "2019-03-03T22:20:30",
+            ),
+            (
+                "- Votre signet sur la page xii | emplacement 170 | Ajouté le dimanche 3 mars 2019 22:20:30",
+                ClippingKind::Bookmark,
+                Some("xii"),
+                Some((170, 170)),
+                "2019-03-03T22:20:30",
+            ),
+            (
+                "- Tu nota en la página 12 | posición 171 | Añadido el domingo, 3 de marzo de 2019 22:20:30",
+                ClippingKind::Note,
+                Some("12"),
+                Some((171, 171)),
+                "2019-03-03T22:20:30",
+            ),
+            (
+                "- 12ページ|位置No. 170-171のハイライト |作成日: 2019年3月3日 日曜日 22:20:30",
+                ClippingKind::Highlight,
+                Some("12"),
+                Some((170, 171)),
+                "2019-03-03T22:20:30",
+            ),
+            (
+                "- 您在第 12 页（位置 #170-171）的标注 | 添加于 2019年3月3日星期日 下午10:20:30",
+                ClippingKind::Highlight,
+                Some("12"),
+                Some((170, 171)),
+                "2019-03-03T22:20:30",
+            ),
+        ];
+        for (line, kind, page, location, added) in cases {
+            let meta = line.to_lowercase();
+            assert_eq!(classify(&meta), Some(kind), "{line}");
+            assert_eq!(parse_page(&meta).as_deref(), page, "{line}");
+            assert_eq!(parse_location(&meta), location, "{line}");
+            assert_eq!(parse_added_at(line).as_deref(), Some(added), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_numeric_dates() {
+        assert_eq!(
+            parse_added_at("- Your Highlight | Added on 2019-03-04 10:00").as_deref(),
+            Some("2019-03-04T10:00:00")
+        );
+        assert_eq!(
+            parse_added_at("- Your Highlight | Added on 03/04/2019 10:00").as_deref(),
+            Some("2019-03-04T10:00:00")
+        );
+        assert_eq!(
+            parse_added_at("- Ihre Markierung | Hinzugefügt am 04.03.2019 10:00").as_deref(),
+            Some("2019-03-04T10:00:00")
+        );
+        assert_eq!(parse_added_at("- Your Highlight on Location 5"), None);
+    }
+
+    #[test]
+    fn groups_merges_and_pairs_notes() {
+        let content = "\u{feff}Dune (Frank Herbert)\r\n\
+- Your Highlight on Location 100-101 | Added on Monday, March 4, 2019 10:00:00 AM\r\n\
+\r\n\
+Fear is the mind-killer.\r\n\
+==========\r\n\
+\u{feff}Dune (Frank Herbert)\r\n\
+- Your Highlight on Location 100-102 | Added on Monday, March 4, 2019 10:01:00 AM\r\n\
+\r\n\
+I must not fear. Fear is the mind-killer.\r\n\
+==========\r\n\
+Dune (Frank Herbert)\r\n\
+- Your Note on Location 102 | Added on Monday, March 4, 2019 10:02:00 AM\r\n\
+\r\n\
+Litany against fear\r\n\
+==========\r\n\
+Dune (Frank Herbert)\r\n\
+- Your Bookmark on Location 300 | Added on Monday, March 4, 2019 11:00:00 AM\r\n\
+\r\n\
+\r\n\
+==========\r\n\
+Neuromancer (William Gibson)\r\n\
+- Your Note on Location 5 | Added on Monday, March 4, 2019 11:00:00 AM\r\n\
+\r\n\
+Standalone note\r\n\
+==========\r\n\
+Broken entry\r\n\
+==========\r\n";
+        let library = vec![LibraryBookRef {
+            hash: "h1".into(),
+            title: "Dune".into(),
+            author: "Herbert, Frank".into(),
+            file_name: None,
+        }];
+        let preview = parse_clippings(content, &library);
+        assert_eq!(preview.skipped, 1);
+        assert_eq!(preview.books.len(), 2);
+
+        let dune = &preview.books[0];
+        assert_eq!(dune.matches[0].hash, "h1");
+        assert_eq!(dune.clippings.len(), 2);
+        let highlight = &dune.clippings[0];
+        assert_eq!(highlight.kind, ClippingKind::Highlight);
+        assert_eq!(highlight.text, "I must not fear. Fear is the mind-killer.");
+        assert_eq!(highlight.note, "Litany against fear");
+        assert_eq!(highlight.location_end, Some(102));
+        assert_eq!(dune.clippings[1].kind, ClippingKind::Bookmark);
+
+        let neuromancer = &preview.books[1];
+        assert!(neuromancer.matches.is_empty());
+        assert_eq!(neuromancer.clippings[0].kind, ClippingKind::Note);
+        assert_eq!(neuromancer.clippings[0].text, "Standalone note");
+    }
+}
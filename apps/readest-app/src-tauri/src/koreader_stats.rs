@@ -0,0 +1,400 @@
+// Import KOReader's reading statistics (`statistics.sqlite3`).
+//
+// Readest's statistics database uses KOReader's schema (see the
+// `statistics` migration in `services/database/migrations`): a `book`
+// table keyed by the partial MD5 and one `page_stat_data` row per page
+// read. Importing is therefore a straight copy of those rows, streamed to
+// the frontend in batches shaped like `StatBook` / `PageStatEvent` (see
+// `types/statistics.ts`) so it can hand each batch to
+// `StatisticsDb.applyRemoteEvents`, whose unique keys make re-imports
+// idempotent.
+//
+// Databases older than KOReader's 2018 schema only have a `page_stat`
+// table with `period` instead of `duration` and no per-row page count;
+// those rows are read too, using the book's page count.
+//
+// The returned summary (per-book time, pages, sessions and date range)
+// lets the UI show what will be merged before committing with
+// `dryRun: false`.
+
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{ipc::Channel, AppHandle};
+
+use crate::transfer_file::ensure_path_allowed;
+
+const BATCH_SIZE: usize = 5000;
+/// A gap longer than this between page events starts a new session;
+/// matches KOReader's default idle timeout.
+const SESSION_GAP_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KoreaderStatsImportOptions {
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Only import events at or after this Unix time.
+    #[serde(default)]
+    pub since: Option<i64>,
+    /// Only import books with these MD5s (e.g. the ones in the library).
+    #[serde(default)]
+    pub book_md5s: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatBook {
+    pub book_md5: String,
+    pub title: String,
+    pub authors: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageStatEvent {
+    pub book_md5: String,
+    pub page: i64,
+    pub start_time: i64,
+    pub duration: i64,
+    pub total_pages: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsBatch {
+    pub books: Vec<StatBook>,
+    pub events: Vec<PageStatEvent>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedBookSummary {
+    pub book_md5: String,
+    pub title: String,
+    pub authors: String,
+    pub total_read_time: i64,
+    pub pages_read: usize,
+    pub sessions: usize,
+    pub first_read: i64,
+    pub last_read: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KoreaderStatsImportReport {
+    pub dry_run: bool,
+    pub events: usize,
+    pub books: Vec<ImportedBookSummary>,
+    /// Books skipped because they have no MD5 (KOReader only records one
+    /// since 2019) or aren't in `bookMd5s`.
+    pub skipped_books: usize,
+}
+
+struct BookInfo {
+    book: StatBook,
+    pages: i64,
+}
+
+fn has_table(conn: &Connection, name: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n > 0)
+    .map_err(|e| e.to_string())
+}
+
+fn read_books(conn: &Connection) -> Result<HashMap<i64, BookInfo>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, title, authors, md5, pages FROM book")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut books = HashMap::new();
+    for row in rows {
+        let (id, title, authors, md5, pages) = row.map_err(|e| e.to_string())?;
+        books.insert(
+            id,
+            BookInfo {
+                book: StatBook {
+                    book_md5: md5.unwrap_or_default().to_lowercase(),
+                    title: title.unwrap_or_default(),
+                    authors: authors.unwrap_or_default(),
+                },
+                pages: pages.unwrap_or(0),
+            },
+        );
+    }
+    Ok(books)
+}
+
+#[derive(Default)]
+struct Tally {
+    total_read_time: i64,
+    pages: std::collections::HashSet<i64>,
+    starts: Vec<i64>,
+}
+
+fn count_sessions(starts: &mut [i64]) -> usize {
+    starts.sort_unstable();
+    starts
+        .windows(2)
+        .filter(|w| w[1] - w[0] > SESSION_GAP_SECS)
+        .count()
+        + usize::from(!starts.is_empty())
+}
+
+fn import_stats_sync(
+    db_path: &Path,
+    options: &KoreaderStatsImportOptions,
+    on_batch: &dyn Fn(StatsBatch),
+) -> Result<KoreaderStatsImportReport, String> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("open {}: {e}", db_path.display()))?;
+    if !has_table(&conn, "book")? {
+        return Err("not a KOReader statistics database".to_string());
+    }
+    let books = read_books(&conn)?;
+    let wanted = |md5: &str| {
+        !md5.is_empty()
+            && options.book_md5s.as_ref().map_or(true, |list| {
+                list.iter().any(|m| m.eq_ignore_ascii_case(md5))
+            })
+    };
+    let skipped_books = books.values().filter(|b| !wanted(&b.book.book_md5)).count();
+
+    let sql = if has_table(&conn, "page_stat_data")? {
+        "SELECT id_book, page, start_time, duration, total_pages FROM page_stat_data \
+         WHERE start_time >= ?1 ORDER BY start_time"
+    } else if has_table(&conn, "page_stat")? {
+        "SELECT id_book, page, start_time, period, 0 FROM page_stat \
+         WHERE start_time >= ?1 ORDER BY start_time"
+    } else {
+        return Err("no page statistics found".to_string());
+    };
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query([options.since.unwrap_or(0)])
+        .map_err(|e| e.to_string())?;
+
+    let mut report = KoreaderStatsImportReport {
+        dry_run: options.dry_run,
+        skipped_books,
+        ..Default::default()
+    };
+    let mut tallies: HashMap<i64, Tally> = HashMap::new();
+    let mut events = Vec::with_capacity(BATCH_SIZE);
+    let mut batch_books: HashMap<i64, StatBook> = HashMap::new();
+    let flush = |events: &mut Vec<PageStatEvent>, books: &mut HashMap<i64, StatBook>| {
+        if !events.is_empty() {
+            on_batch(StatsBatch {
+                books: books.drain().map(|(_, b)| b).collect(),
+                events: std::mem::take(events),
+            });
+        }
+    };
+
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let id_book: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let Some(info) = books.get(&id_book) else {
+            continue;
+        };
+        if !wanted(&info.book.book_md5) {
+            continue;
+        }
+        let page: i64 = row.get(1).map_err(|e| e.to_string())?;
+        let start_time: i64 = row.get(2).map_err(|e| e.to_string())?;
+        let duration: i64 = row.get(3).map_err(|e| e.to_string())?;
+        let total_pages = match row.get::<_, i64>(4).map_err(|e| e.to_string())? {
+            0 => info.pages,
+            n => n,
+        };
+        if page <= 0 || duration <= 0 {
+            continue;
+        }
+        report.events += 1;
+        let tally = tallies.entry(id_book).or_default();
+        tally.total_read_time += duration;
+        tally.pages.insert(page);
+        tally.starts.push(start_time);
+        if options.dry_run {
+            continue;
+        }
+        batch_books
+            .entry(id_book)
+            .or_insert_with(|| info.book.clone());
+        events.push(PageStatEvent {
+            book_md5: info.book.book_md5.clone(),
+            page,
+            start_time,
+            duration,
+            total_pages,
+        });
+        if events.len() >= BATCH_SIZE {
+            flush(&mut events, &mut batch_books);
+        }
+    }
+    flush(&mut events, &mut batch_books);
+
+    report.books = tallies
+        .into_iter()
+        .filter_map(|(id, mut tally)| {
+            let info = books.get(&id)?;
+            Some(ImportedBookSummary {
+                book_md5: info.book.book_md5.clone(),
+                title: info.book.title.clone(),
+                authors: info.book.authors.clone(),
+                total_read_time: tally.total_read_time,
+                pages_read: tally.pages.len(),
+                first_read: tally.starts.iter().copied().min().unwrap_or(0),
+                last_read: tally.starts.iter().copied().max().unwrap_or(0),
+                sessions: count_sessions(&mut tally.starts),
+            })
+        })
+        .collect();
+    report.books.sort_by(|a, b| b.last_read.cmp(&a.last_read));
+    Ok(report)
+}
+
+/// Read a KOReader `statistics.sqlite3`. Unless `options.dryRun`, page
+/// events are streamed to `on_batch` for the frontend to merge.
+#[tauri::command]
+pub async fn import_koreader_stats(
+    app: AppHandle,
+    db_path: String,
+    options: KoreaderStatsImportOptions,
+    on_batch: Channel<StatsBatch>,
+) -> Result<KoreaderStatsImportReport, String> {
+    ensure_path_allowed(&app, &db_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        import_stats_sync(Path::new(&db_path), &options, &|batch| {
+            let _ = on_batch.send(batch);
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn make_db(name: &str, legacy: bool) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "readest-kostats-{name}-{}.sqlite3",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE book (id integer PRIMARY KEY, title text, authors text,
+                md5 text, pages integer);
+             INSERT INTO book VALUES (1, 'Dune', 'Frank Herbert', 'ABCDEF', 400),
+                (2, 'Old', 'Someone', NULL, 100);",
+        )
+        .unwrap();
+        if legacy {
+            conn.execute_batch(
+                "CREATE TABLE page_stat (id_book integer, page integer,
+                    start_time integer, period integer);
+                 INSERT INTO page_stat VALUES (1, 1, 1000, 30), (1, 2, 1040, 40);",
+            )
+            .unwrap();
+        } else {
+            conn.execute_batch(
+                "CREATE TABLE page_stat_data (id_book integer, page integer,
+                    start_time integer, duration integer, total_pages integer);
+                 INSERT INTO page_stat_data VALUES
+                    (1, 1, 1000, 30, 400), (1, 2, 1040, 40, 400),
+                    (1, 3, 5000, 20, 400), (1, 3, 6000, 0, 400),
+                    (2, 1, 2000, 10, 100);",
+            )
+            .unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn streams_events_and_summarizes_sessions() {
+        let path = make_db("modern", false);
+        let batches = RefCell::new(Vec::new());
+        let report = import_stats_sync(&path, &Default::default(), &|b| {
+            batches.borrow_mut().push(b)
+        })
+        .unwrap();
+        let batches = batches.into_inner();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].events.len(), 3);
+        assert_eq!(batches[0].books[0].book_md5, "abcdef");
+        assert_eq!(report.events, 3);
+        assert_eq!(report.skipped_books, 1);
+        let dune = &report.books[0];
+        assert_eq!(dune.total_read_time, 90);
+        assert_eq!(dune.pages_read, 3);
+        assert_eq!(dune.sessions, 2);
+        assert_eq!((dune.first_read, dune.last_read), (1000, 5000));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn dry_run_and_filters_emit_nothing() {
+        let path = make_db("dry", false);
+        let batches = RefCell::new(0);
+        let report = import_stats_sync(
+            &path,
+            &KoreaderStatsImportOptions {
+                dry_run: true,
+                since: Some(1040),
+                book_md5s: None,
+            },
+            &|_| *batches.borrow_mut() += 1,
+        )
+        .unwrap();
+        assert_eq!(*batches.borrow(), 0);
+        assert_eq!(report.events, 2);
+
+        let report = import_stats_sync(
+            &path,
+            &KoreaderStatsImportOptions {
+                dry_run: true,
+                since: None,
+                book_md5s: Some(vec!["other".into()]),
+            },
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(report.events, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reads_legacy_page_stat_table() {
+        let path = make_db("legacy", true);
+        let batches = RefCell::new(Vec::new());
+        import_stats_sync(&path, &Default::default(), &|b| {
+            batches.borrow_mut().push(b)
+        })
+        .unwrap();
+        let events = &batches.borrow()[0].events;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].duration, 40);
+        assert_eq!(events[1].total_pages, 400);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,317 @@
+// KOReader progress sync (koreader-sync-server / kosync) backend.
+//
+// Native counterpart of `services/sync/KOSyncClient.ts`. Talking to the
+// server from Rust sidesteps CORS without the web proxy, and — unlike the
+// webview — can see the file on disk, so both of KOReader's document
+// digests are available:
+//   - `binary`: the partial MD5 over a handful of 1 KiB slices. It's the
+//     same scheme as Readest's book hash, so `parser_common` computes it;
+//   - `filename`: MD5 of the file's base name, for libraries whose files
+//     differ byte-wise between devices (e.g. Calibre rewriting metadata).
+//
+// Auth mirrors the TS client: the `X-Auth-User` / `X-Auth-Key` headers
+// first, then HTTP Basic for servers (some CWA builds) that answer the
+// former with 401 or 400.
+//
+// Both carry credentials, so the server's certificate is always checked.
+// A server behind a private CA or a self-signed certificate is trusted
+// per host through `net::trust`, like any other self-hosted server.
+
+use md5::{Digest, Md5};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::parser_common::compute_partial_md5;
+use crate::transfer_file::ensure_path_allowed;
+
+const ACCEPT: &str = "application/vnd.koreader.v1+json";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KoSyncServer {
+    pub server_url: String,
+    pub username: String,
+    /// `md5(password)`, as registered with the server.
+    #[serde(default)]
+    pub userkey: String,
+    /// Plain password, only needed for HTTP Basic servers.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KoSyncChecksumMethod {
+    Binary,
+    Filename,
+}
+
+/// Progress as stored by the server; field names follow the wire format
+/// so the TS side can share its `KoSyncProgress` type.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KoSyncProgress {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KoSyncConnectResult {
+    pub success: bool,
+    pub message: String,
+    /// The key to store in settings when `success`.
+    pub userkey: String,
+}
+
+pub(crate) fn md5_hex(input: &[u8]) -> String {
+    Md5::digest(input)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// KOReader's document digest for `path`.
+pub(crate) fn document_digest(path: &Path, method: KoSyncChecksumMethod) -> Result<String, String> {
+    match method {
+        KoSyncChecksumMethod::Binary => {
+            compute_partial_md5(path).map_err(|e| format!("partial_md5 failed: {e}"))
+        }
+        KoSyncChecksumMethod::Filename => {
+            let name = path
+                .file_name()
+                .ok_or_else(|| format!("no file name: {}", path.display()))?;
+            Ok(md5_hex(name.to_string_lossy().as_bytes()))
+        }
+    }
+}
+
+fn client(server_url: &str) -> Result<reqwest::Client, String> {
+    crate::net::client_builder_for(server_url)
+        .user_agent(concat!("Readest/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn request(
+    server: &KoSyncServer,
+    method: Method,
+    endpoint: &str,
+    body: Option<&Value>,
+    use_auth: bool,
+) -> Result<reqwest::Response, String> {
+    let client = client(&server.server_url)?;
+    let url = format!("{}{endpoint}", server.server_url.trim_end_matches('/'));
+    let send = |http_auth: bool| {
+        let mut request = client
+            .request(method.clone(), &url)
+            .header("Accept", ACCEPT);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        if use_auth {
+            request = match (http_auth, server.password.as_deref()) {
+                (true, Some(password)) => request.basic_auth(&server.username, Some(password)),
+                _ => request
+                    .header("X-Auth-User", &server.username)
+                    .header("X-Auth-Key", &server.userkey),
+            };
+        }
+        request.send()
+    };
+    let response = send(false)
+        .await
+        .map_err(|e| format!("request failed: {e}"))?;
+    let rejected = matches!(
+        response.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::BAD_REQUEST
+    );
+    if use_auth && rejected && server.password.is_some() {
+        return send(true).await.map_err(|e| format!("request failed: {e}"));
+    }
+    Ok(response)
+}
+
+/// A wrong server URL can land on a web UI that answers 200 with HTML;
+/// only a JSON object counts as a kosync reply.
+async fn json_object(response: reqwest::Response) -> Option<Value> {
+    response.json::<Value>().await.ok().filter(Value::is_object)
+}
+
+#[tauri::command]
+pub async fn kosync_document_digest(
+    app: AppHandle,
+    file_path: String,
+    method: KoSyncChecksumMethod,
+) -> Result<String, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || document_digest(Path::new(&file_path), method))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Log in, registering the account first if the server doesn't know it.
+#[tauri::command]
+pub async fn kosync_connect(
+    server_url: String,
+    username: String,
+    password: String,
+) -> Result<KoSyncConnectResult, String> {
+    let userkey = md5_hex(password.as_bytes());
+    let server = KoSyncServer {
+        server_url,
+        username: username.clone(),
+        userkey: userkey.clone(),
+        password: Some(password),
+    };
+    let result = |success: bool, message: &str| KoSyncConnectResult {
+        success,
+        message: message.to_string(),
+        userkey: if success {
+            userkey.clone()
+        } else {
+            String::new()
+        },
+    };
+    let not_kosync = "Not a KOReader Sync server. Check the Server URL.";
+
+    let auth = request(&server, Method::GET, "/users/auth", None, true).await?;
+    let status = auth.status();
+    if status.is_success() {
+        return Ok(match json_object(auth).await {
+            Some(_) => result(true, "Login successful."),
+            None => result(false, not_kosync),
+        });
+    }
+    if status != StatusCode::UNAUTHORIZED {
+        let message = json_object(auth)
+            .await
+            .and_then(|v| v.get("message")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("Authorization failed with status: {}", status.as_u16()));
+        return Ok(result(false, &message));
+    }
+
+    let body = serde_json::json!({ "username": username, "password": userkey });
+    let register = request(&server, Method::POST, "/users/create", Some(&body), false).await?;
+    let status = register.status();
+    let reply = json_object(register).await;
+    Ok(match (status, reply) {
+        (s, Some(_)) if s.is_success() => result(true, "Registration successful."),
+        (s, None) if s.is_success() => result(false, not_kosync),
+        (StatusCode::PAYMENT_REQUIRED, _) => result(false, "Invalid credentials."),
+        (_, reply) => {
+            let message = reply
+                .and_then(|v| v.get("message")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| "Registration failed.".to_string());
+            result(false, &message)
+        }
+    })
+}
+
+/// Stored progress for `document`, or `None` when the server has no
+/// position for it.
+#[tauri::command]
+pub async fn kosync_get_progress(
+    server: KoSyncServer,
+    document: String,
+) -> Result<Option<KoSyncProgress>, String> {
+    let endpoint = format!("/syncs/progress/{document}");
+    let response = request(&server, Method::GET, &endpoint, None, true).await?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "request failed with status code {}",
+            response.status().as_u16()
+        ));
+    }
+    let Some(value) = json_object(response).await else {
+        return Ok(None);
+    };
+    let mut progress: KoSyncProgress = serde_json::from_value(value).unwrap_or_default();
+    // koreader-sync-server doesn't echo `document` on GET; key validity on
+    // an actual position instead, like the TS client.
+    let has_position = progress.progress.as_deref().is_some_and(|p| !p.is_empty())
+        || progress.percentage.is_some_and(f64::is_finite);
+    if !has_position {
+        return Ok(None);
+    }
+    progress.document.get_or_insert(document);
+    Ok(Some(progress))
+}
+
+#[tauri::command]
+pub async fn kosync_update_progress(
+    server: KoSyncServer,
+    progress: KoSyncProgress,
+) -> Result<(), String> {
+    if progress.document.as_deref().map_or(true, str::is_empty) {
+        return Err("missing document digest".to_string());
+    }
+    let body = serde_json::to_value(&progress).map_err(|e| e.to_string())?;
+    let response = request(&server, Method::PUT, "/syncs/progress", Some(&body), true).await?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "request failed with status code {}",
+            response.status().as_u16()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filename_digest_is_md5_of_base_name() {
+        let digest = document_digest(
+            Path::new("/books/Dune.epub"),
+            KoSyncChecksumMethod::Filename,
+        )
+        .unwrap();
+        assert_eq!(digest, md5_hex(b"Dune.epub"));
+        assert_eq!(
+            digest,
+            document_digest(Path::new("other/Dune.epub"), KoSyncChecksumMethod::Filename).unwrap()
+        );
+    }
+
+    #[test]
+    fn binary_digest_matches_book_hash() {
+        let path = std::env::temp_dir().join(format!("readest-kosync-{}", std::process::id()));
+        std::fs::write(&path, vec![7u8; 5000]).unwrap();
+        assert_eq!(
+            document_digest(&path, KoSyncChecksumMethod::Binary).unwrap(),
+            compute_partial_md5(&path).unwrap()
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn userkey_is_md5_of_password() {
+        assert_eq!(md5_hex(b"password"), "5f4dcc3b5aa765d61d8327deb882cf99");
+    }
+
+    #[test]
+    fn progress_uses_wire_field_names() {
+        let progress: KoSyncProgress = serde_json::from_str(
+            r#"{"progress":"/body/DocFragment[3]","percentage":0.42,"device":"kobo","device_id":"abc","timestamp":1700000000}"#,
+        )
+        .unwrap();
+        assert_eq!(progress.device_id.as_deref(), Some("abc"));
+        let json = serde_json::to_value(&progress).unwrap();
+        assert_eq!(json["device_id"], "abc");
+        assert!(json.get("document").is_none());
+    }
+}
@@ -22,31 +22,77 @@ use tauri_plugin_fs::FsExt;
 
 #[cfg(desktop)]
 use tauri::{Listener, Url};
+mod archive_import;
+mod audio;
+mod book_resource;
+#[cfg(desktop)]
+mod book_window;
+mod braille_export;
+mod calibre_device;
+mod calibre_import;
+mod calibre_server;
 mod clip_url;
+mod comic;
+mod content_policy;
+mod continue_reading;
+mod convert;
 mod dir_scanner;
 #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
 mod discord_rpc;
+mod download_manager;
 mod epub_parser;
+mod epub_repair;
+mod format_sniff;
+#[cfg(desktop)]
+mod global_shortcuts;
+#[cfg(desktop)]
+mod idle_time;
+mod keep_awake;
+mod kindle_clippings;
+mod koreader_stats;
+mod kosync;
+mod library_match;
+mod library_provider;
 #[cfg(target_os = "macos")]
 mod macos;
 mod mobi_parser;
+mod moonreader_import;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod net;
 mod nightly_update;
+mod notifications;
+mod oauth_loopback;
+mod opds;
 mod parser_common;
+mod pdf;
+#[cfg(desktop)]
+mod print;
+mod quote_image;
 mod range_file;
+mod reading_server;
+#[cfg(target_os = "linux")]
+mod screensaver;
+mod secure_store;
 mod sentry_config;
 #[cfg(desktop)]
 mod spawn_fresh_browser;
+mod sync;
+mod taskbar_progress;
 mod transfer_file;
 #[cfg(desktop)]
+mod tray;
+mod tts_export;
+mod txt;
+#[cfg(desktop)]
 mod window_state;
 #[cfg(target_os = "windows")]
 use tauri::webview::ScrollBarStyle;
-use tauri::{command, Emitter, WebviewUrl, WebviewWindowBuilder, Window};
+use tauri::{command, Emitter, WebviewUrl, WebviewWindowBuilder};
 #[cfg(target_os = "android")]
 use tauri_plugin_native_bridge::register_select_directory_callback;
 #[cfg(target_os = "android")]
 use tauri_plugin_native_bridge::{NativeBridgeExt, OpenExternalUrlRequest};
-use tauri_plugin_oauth::start;
 #[cfg(not(target_os = "android"))]
 use tauri_plugin_opener::OpenerExt;
 use transfer_file::{download_file, upload_file};
@@ -224,16 +270,6 @@ fn set_window_open_with_files(app: &AppHandle, files: Vec<PathBuf>) {
     }
 }
 
-#[command]
-async fn start_server(window: Window) -> Result<u16, String> {
-    start(move |url| {
-        // Because of the unprotected localhost port, you must verify the URL here.
-        // Preferebly send back only the token, or nothing at all if you can handle everything else in Rust.
-        let _ = window.emit("redirect_uri", url);
-    })
-    .map_err(|err| err.to_string())
-}
-
 #[tauri::command]
 fn get_environment_variable(name: &str) -> String {
     std::env::var(String::from(name)).unwrap_or(String::from(""))
@@ -402,7 +438,7 @@ pub fn run() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_oauth::init())
         .invoke_handler(tauri::generate_handler![
-            start_server,
+            oauth_loopback::start_server,
             download_file,
             upload_file,
             get_environment_variable,
@@ -412,12 +448,145 @@ pub fn run() {
             is_updater_disabled,
             allow_paths_in_scopes,
             dir_scanner::read_dir,
+            dir_scanner::scan_dir,
+            dir_scanner::cancel_scan,
+            download_manager::enqueue_download,
+            download_manager::list_downloads,
+            download_manager::pause_download,
+            download_manager::resume_download,
+            download_manager::cancel_download,
+            download_manager::set_max_concurrent_downloads,
+            convert::convert_book,
+            convert::list_conversions,
+            convert::cancel_conversion,
+            kindle_clippings::preview_kindle_clippings,
+            koreader_stats::import_koreader_stats,
+            kosync::kosync_document_digest,
+            kosync::kosync_connect,
+            kosync::kosync_get_progress,
+            kosync::kosync_update_progress,
+            moonreader_import::preview_moonreader_backup,
+            opds::opds_fetch_feed,
+            opds::opds_search,
+            opds::opds_download,
+            reading_server::start_reading_server,
+            reading_server::stop_reading_server,
+            reading_server::get_reading_server_status,
+            sync::sync_list,
+            sync::sync_head,
+            sync::sync_upload,
+            sync::sync_download,
+            sync::sync_delete,
+            sync::sync_changes,
+            sync::sync_quota,
+            sync::dropbox::dropbox_connect,
+            sync::crypto::e2ee_create_key,
+            sync::crypto::e2ee_unlock,
+            sync::crypto::e2ee_status,
+            sync::crypto::e2ee_forget,
+            sync::crypto::e2ee_seal,
+            sync::crypto::e2ee_open,
+            sync::transfer::get_sync_transfer_policy,
+            sync::transfer::set_sync_transfer_policy,
+            sync::merge::sync_merge_annotations,
+            sync::lan::lan_sync_start,
+            sync::lan::lan_sync_stop,
+            sync::lan::lan_sync_status,
+            sync::lan::lan_sync_pairing_code,
+            sync::lan::lan_sync_discover,
+            sync::lan::lan_sync_pair,
+            sync::lan::lan_sync_unpair,
+            sync::policy::get_sync_policy,
+            sync::policy::set_sync_policy,
+            sync::policy::set_sync_book_rule,
+            sync::policy::sync_policy_evaluate,
+            sync::queue::sync_queue_enqueue,
+            sync::queue::sync_queue_list,
+            sync::queue::sync_queue_state,
+            sync::queue::sync_queue_retry,
+            sync::queue::sync_queue_discard,
+            net::get_proxy_settings,
+            net::set_proxy_settings,
+            net::trust::list_trusted_hosts,
+            net::trust::trust_host,
+            net::trust::untrust_host,
+            net::trust::probe_host_certificate,
+            net::identity::list_client_certificates,
+            net::identity::import_client_certificate,
+            net::identity::remove_client_certificate,
+            secure_store::secure_get,
+            secure_store::secure_set,
+            secure_store::secure_delete,
+            secure_store::secure_load_settings,
+            secure_store::secure_save_settings,
+            archive_import::list_archive_books,
+            archive_import::extract_archive_books,
+            braille_export::export_braille,
+            calibre_device::calibre_device_discover,
+            calibre_device::calibre_device_connect,
+            calibre_device::calibre_device_disconnect,
+            calibre_import::import_calibre_library,
+            calibre_server::calibre_server_libraries,
+            calibre_server::calibre_server_search,
+            calibre_server::calibre_server_cover,
+            calibre_server::calibre_server_download,
+            calibre_server::calibre_server_get_positions,
+            calibre_server::calibre_server_set_position,
+            content_policy::get_book_content_policy,
+            content_policy::set_book_content_policy,
             epub_parser::parse_epub_metadata,
             epub_parser::extract_epub_cover_full,
             epub_parser::parse_epub_full,
+            epub_repair::check_epub,
+            epub_repair::repair_epub,
+            txt::encoding::detect_text_encoding,
+            txt::encoding::convert_text_to_utf8,
+            txt::chapters::open_txt_book,
+            txt::chapters::get_txt_chapter,
+            txt::chapters::close_txt_book,
+            comic::reader::parse_comic,
+            comic::reader::open_comic,
+            comic::reader::get_comic_page,
+            comic::reader::close_comic,
+            pdf::render::open_pdf,
+            pdf::render::close_pdf,
+            pdf::text::get_pdf_page_text,
+            pdf::text::search_pdf,
+            pdf::outline::get_pdf_outline,
+            pdf::margins::detect_pdf_margins,
+            audio::metadata::parse_audio_metadata,
+            tts_export::start_tts_export,
+            tts_export::cancel_tts_export,
+            taskbar_progress::update_taskbar_progress,
+            taskbar_progress::finish_taskbar_progress,
+            notifications::notify_job_finished,
+            #[cfg(target_os = "linux")]
+            mpris::mpris_set_active,
+            #[cfg(target_os = "linux")]
+            mpris::mpris_update_metadata,
+            #[cfg(target_os = "linux")]
+            mpris::mpris_update_state,
+            #[cfg(target_os = "linux")]
+            screensaver::set_screensaver_inhibited,
+            #[cfg(desktop)]
+            tray::update_tray_menu,
+            #[cfg(desktop)]
+            tray::set_tray_read_aloud,
+            #[cfg(desktop)]
+            tray::set_minimize_to_tray,
+            #[cfg(target_os = "macos")]
+            macos::now_playing::now_playing_set_active,
+            #[cfg(target_os = "macos")]
+            macos::now_playing::now_playing_update_metadata,
+            #[cfg(target_os = "macos")]
+            macos::now_playing::now_playing_update_state,
             mobi_parser::parse_mobi_metadata,
             mobi_parser::extract_mobi_cover_full,
             #[cfg(target_os = "macos")]
+            macos::apple_books::apple_books_scan,
+            #[cfg(target_os = "macos")]
+            macos::apple_books::import_apple_books,
+            #[cfg(target_os = "macos")]
             macos::safari_auth::auth_with_safari,
             #[cfg(target_os = "macos")]
             macos::apple_auth::start_apple_sign_in,
@@ -432,9 +601,27 @@ pub fn run() {
             clip_url::clip_url,
             #[cfg(desktop)]
             spawn_fresh_browser::spawn_fresh_browser,
+            #[cfg(desktop)]
+            book_window::open_book_window,
+            keep_awake::set_keep_awake,
+            continue_reading::update_continue_reading,
+            library_provider::refresh_library_provider,
+            #[cfg(desktop)]
+            idle_time::get_system_idle_seconds,
+            #[cfg(desktop)]
+            print::print_content,
+            quote_image::render_quote_image,
             nightly_update::verify_update_signature,
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
             nightly_update::install_nightly_update,
+            #[cfg(target_os = "windows")]
+            windows::file_associations::register_default_app,
+            #[cfg(target_os = "windows")]
+            windows::jump_list::update_jump_list,
+            #[cfg(target_os = "macos")]
+            macos::spotlight::update_spotlight_index,
+            #[cfg(target_os = "macos")]
+            macos::menu::update_recent_books_menus,
         ])
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_persisted_scope::init())
@@ -448,24 +635,48 @@ pub fn run() {
         .plugin(tauri_plugin_device_info::init())
         .plugin(tauri_plugin_turso::init())
         .plugin(tauri_plugin_native_bridge::init())
-        .plugin(tauri_plugin_native_tts::init())
+        // Piper voice downloads and cloud voices go through the proxy too.
+        .plugin(tauri_plugin_native_tts::init_with_http(net::client_builder))
         .plugin(tauri_plugin_webview_upgrade::init())
         // Serves local file byte-ranges to `RemoteFile` via `?path=&start=&end=`
         // (range-in-URL, not a `Range` header) so Android's WebView doesn't
         // re-apply the offset. Scope-gated by `asset_protocol_scope`.
-        .register_asynchronous_uri_scheme_protocol(range_file::SCHEME, range_file::handle);
+        .register_asynchronous_uri_scheme_protocol(range_file::SCHEME, range_file::handle)
+        // Serves book entries with the per-book Content-Security-Policy from
+        // `content_policy` attached, so EPUB3 scripts run sandboxed.
+        .register_asynchronous_uri_scheme_protocol(book_resource::SCHEME, book_resource::handle)
+        // Serves comic pages decoded and downscaled to the requested size,
+        // prefetching the pages after it.
+        .register_asynchronous_uri_scheme_protocol(comic::scaled::SCHEME, comic::scaled::handle)
+        // Serves PDF pages rasterized by PDFium (`pdfium` feature).
+        .register_asynchronous_uri_scheme_protocol(pdf::render::SCHEME, pdf::render::handle);
+
+    // Serves the documents shown in print windows (`print_content`).
+    #[cfg(desktop)]
+    let builder = builder.register_asynchronous_uri_scheme_protocol(print::SCHEME, print::handle);
 
     #[cfg(desktop)]
     let builder = builder.plugin(
         tauri_plugin_single_instance::Builder::new()
             .callback(move |app, argv, cwd| {
                 if let Some(window) = app.get_webview_window("main") {
+                    // Hidden when minimized to the tray.
+                    let _ = window.show();
                     let _ = window.set_focus();
                 }
                 let files = get_files_from_argv(argv.clone());
                 if !files.is_empty() {
                     allow_file_in_scopes(app, files.clone());
                 }
+                // Keep the book the main window is reading and open the files
+                // in a window of their own.
+                if book_window::are_files(&files) && book_window::main_window_is_reading(app) {
+                    if let Err(e) = book_window::open_files_window(app, &files) {
+                        log::error!("Failed to open files in a new window: {e}");
+                    } else {
+                        return;
+                    }
+                }
                 app.emit("single-instance", SingleInstancePayload { args: argv, cwd })
                     .unwrap();
             })
@@ -478,6 +689,9 @@ pub fn run() {
     #[cfg(desktop)]
     let builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
 
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_notification::init());
+
     // Strip invalid geometry from the saved window state before the
     // window-state plugin loads it, so a bad `.window-state.json` (e.g. the
     // Windows minimized `-32000` sentinel) can't crash WebView2 on launch.
@@ -521,6 +735,39 @@ pub fn run() {
                 use tauri::Manager;
                 app.add_capability(include_str!("../capabilities-extra/webdriver.json"))?;
             }
+            net::load(app.handle());
+            app.manage(content_policy::ContentPolicyStore::load(app.handle()));
+            app.manage(dir_scanner::ScanTasks::default());
+            app.manage(download_manager::DownloadManager::load(app.handle()));
+            download_manager::resume_pending(app.handle());
+            app.manage(convert::Converter::default());
+            app.manage(txt::chapters::TxtBooks::default());
+            app.manage(comic::reader::Comics::default());
+            app.manage(pdf::engine::PdfEngine::default());
+            app.manage(pdf::render::PdfPageCache::default());
+            app.manage(tts_export::TtsExports::default());
+            #[cfg(target_os = "linux")]
+            app.manage(mpris::Mpris::default());
+            #[cfg(target_os = "linux")]
+            app.manage(screensaver::ScreenSaver::default());
+            #[cfg(target_os = "macos")]
+            app.manage(macos::now_playing::NowPlaying::default());
+            app.manage(reading_server::ReadingServer::default());
+            app.manage(calibre_device::CalibreDevice::default());
+            app.manage(sync::transfer::TransferControl::load(app.handle()));
+            app.manage(sync::lan::LanSync::default());
+            app.manage(sync::policy::SyncPolicyStore::load(app.handle()));
+            app.manage(sync::queue::SyncQueue::start(app.handle()));
+            app.manage(secure_store::SecureSettings::default());
+            app.manage(taskbar_progress::TaskbarProgress::default());
+            app.manage(notifications::Notifier::default());
+            app.manage(keep_awake::KeepAwake::default());
+            app.manage(continue_reading::ContinueReading::default());
+            app.manage(library_provider::LibraryProvider::default());
+            app.manage(quote_image::QuoteImages::default());
+            #[cfg(desktop)]
+            app.manage(print::PrintJobs::default());
+
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
             {
                 use std::sync::{Arc, Mutex};
@@ -624,6 +871,9 @@ pub fn run() {
                 updater_disabled = updater_disabled
             );
 
+            #[cfg(desktop)]
+            app.manage(book_window::BookWindows::new(init_script.clone()));
+
             let app_handle = app.handle().clone();
             let win_builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::default())
                 .background_throttling(BackgroundThrottlingPolicy::Disabled)
@@ -698,7 +948,22 @@ pub fn run() {
                 builder
             };
 
-            #[cfg(not(target_os = "macos"))]
+            #[cfg(all(not(target_os = "macos"), desktop))]
+            {
+                let window = win_builder.build().unwrap();
+                // With "Minimize to Tray" on, closing the main window hides it
+                // to the tray icon instead of quitting; the tray's Quit exits.
+                let window_for_close = window.clone();
+                window.on_window_event(move |event| {
+                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                        if tray::minimizes_to_tray(window_for_close.app_handle()) {
+                            api.prevent_close();
+                            let _ = window_for_close.hide();
+                        }
+                    }
+                });
+            }
+            #[cfg(mobile)]
             {
                 win_builder.build().unwrap();
             }
@@ -734,6 +999,21 @@ pub fn run() {
             #[cfg(target_os = "macos")]
             macos::menu::setup_macos_menu(app.handle())?;
 
+            #[cfg(target_os = "macos")]
+            macos::spotlight::init();
+
+            #[cfg(target_os = "macos")]
+            macos::services::init();
+
+            #[cfg(desktop)]
+            tray::setup(app.handle())?;
+
+            #[cfg(desktop)]
+            global_shortcuts::setup(app.handle())?;
+
+            #[cfg(desktop)]
+            book_window::restore_session(app.handle());
+
             app.handle().emit("window-ready", ()).unwrap();
 
             Ok(())
@@ -743,6 +1023,30 @@ pub fn run() {
         .run(
             #[allow(unused_variables)]
             |app_handle, event| {
+                // Read-aloud shortcuts go to the window focused last.
+                #[cfg(desktop)]
+                if let tauri::RunEvent::WindowEvent {
+                    label,
+                    event: tauri::WindowEvent::Focused(true),
+                    ..
+                } = &event
+                {
+                    global_shortcuts::window_focused(app_handle, label);
+                }
+                #[cfg(desktop)]
+                if let tauri::RunEvent::WindowEvent {
+                    label,
+                    event: tauri::WindowEvent::Destroyed,
+                    ..
+                } = &event
+                {
+                    keep_awake::window_destroyed(app_handle, label);
+                }
+                // Save the open book windows while they are still there.
+                #[cfg(desktop)]
+                if let tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit = &event {
+                    book_window::save_session(app_handle);
+                }
                 #[cfg(target_os = "macos")]
                 match event {
                     tauri::RunEvent::Opened { urls } => {
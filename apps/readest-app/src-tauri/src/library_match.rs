@@ -0,0 +1,176 @@
+// Match books found in another app's export against the Readest library.
+//
+// Importers (Moon+ Reader backups, Kindle clippings, …) only know a title,
+// an author and sometimes a file name. The frontend passes a lightweight
+// view of the library (`LibraryBookRef`) and each importer asks for ranked
+// candidates; nothing is attached until the user has reviewed the
+// matches, so a wrong guess costs a click rather than misplaced notes.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryBookRef {
+    pub hash: String,
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    /// Original file name (`Book.sourceTitle` / the imported file's name).
+    #[serde(default)]
+    pub file_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BookMatch {
+    pub hash: String,
+    /// 0–1; `1.0` is an exact hash or file-name match.
+    pub score: f64,
+    /// `hash`, `fileName`, `title` or `titleAuthor`.
+    pub reason: String,
+}
+
+/// Minimum score worth showing as a candidate.
+const MIN_SCORE: f64 = 0.5;
+
+/// Lower-case, drop punctuation and collapse whitespace, so `"Dune: Deluxe
+/// Edition"` and `"dune deluxe edition"` compare equal.
+pub(crate) fn normalize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Title without a trailing subtitle or bracketed edition note.
+fn main_title(title: &str) -> String {
+    let cut = title.find([':', '(', '[']).unwrap_or(title.len());
+    normalize(&title[..cut])
+}
+
+fn file_stem(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let stem = base.rsplit_once('.').map_or(base, |(stem, _)| stem);
+    normalize(stem)
+}
+
+/// Jaccard similarity of the word sets.
+fn word_overlap(a: &str, b: &str) -> f64 {
+    let a: std::collections::HashSet<&str> = a.split_whitespace().collect();
+    let b: std::collections::HashSet<&str> = b.split_whitespace().collect();
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// Author names are often written "Last, First" on one side and
+/// "First Last" on the other; compare as word sets.
+fn author_matches(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize(a), normalize(b));
+    !a.is_empty() && !b.is_empty() && word_overlap(&a, &b) >= 0.5
+}
+
+/// Ranked candidates for a book described by `title`, `author` and
+/// optionally a `file_name` / partial-MD5 `hash`, best first.
+pub(crate) fn find_matches(
+    library: &[LibraryBookRef],
+    title: &str,
+    author: &str,
+    file_name: Option<&str>,
+    hash: Option<&str>,
+) -> Vec<BookMatch> {
+    let wanted_title = normalize(title);
+    let wanted_main = main_title(title);
+    let wanted_stem = file_name.map(file_stem).filter(|s| !s.is_empty());
+    let mut matches: Vec<BookMatch> = library
+        .iter()
+        .filter_map(|book| {
+            let (score, reason) = if hash.is_some_and(|h| h.eq_ignore_ascii_case(&book.hash)) {
+                (1.0, "hash")
+            } else if wanted_stem.is_some()
+                && book.file_name.as_deref().map(file_stem) == wanted_stem
+            {
+                (1.0, "fileName")
+            } else {
+                let title = normalize(&book.title);
+                let similarity = if title == wanted_title || main_title(&book.title) == wanted_main
+                {
+                    0.9
+                } else {
+                    0.8 * word_overlap(&title, &wanted_title)
+                };
+                if author_matches(&book.author, author) {
+                    ((similarity + 0.1_f64).min(0.99), "titleAuthor")
+                } else {
+                    (similarity, "title")
+                }
+            };
+            (score >= MIN_SCORE).then(|| BookMatch {
+                hash: book.hash.clone(),
+                score,
+                reason: reason.to_string(),
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Vec<LibraryBookRef> {
+        vec![
+            LibraryBookRef {
+                hash: "h1".into(),
+                title: "Dune: Deluxe Edition".into(),
+                author: "Frank Herbert".into(),
+                file_name: Some("dune.epub".into()),
+            },
+            LibraryBookRef {
+                hash: "h2".into(),
+                title: "Children of Dune".into(),
+                author: "Frank Herbert".into(),
+                file_name: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn normalizes_case_and_punctuation() {
+        assert_eq!(
+            normalize("  Dune:  Deluxe—Edition! "),
+            "dune deluxe edition"
+        );
+    }
+
+    #[test]
+    fn ranks_exact_matches_first() {
+        let matches = find_matches(&library(), "Dune", "Herbert, Frank", None, None);
+        assert_eq!(matches[0].hash, "h1");
+        assert_eq!(matches[0].reason, "titleAuthor");
+        assert!(matches.iter().all(|m| m.score < 1.0));
+
+        let by_file = find_matches(&library(), "x", "", Some("/sdcard/Books/Dune.EPUB"), None);
+        assert_eq!(by_file[0].hash, "h1");
+        assert_eq!(by_file[0].score, 1.0);
+
+        let by_hash = find_matches(&library(), "x", "", None, Some("H2"));
+        assert_eq!(by_hash[0].hash, "h2");
+    }
+
+    #[test]
+    fn drops_weak_candidates() {
+        assert!(find_matches(&library(), "Neuromancer", "William Gibson", None, None).is_empty());
+    }
+}
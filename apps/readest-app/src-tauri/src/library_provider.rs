@@ -0,0 +1,304 @@
+//! The library index behind the Android content and documents providers.
+//!
+//! Other apps query the library through the native bridge's `LibraryProvider`
+//! (automation tools, launchers) and `LibraryDocumentsProvider` (the system
+//! Files app and document pickers). Both answer while the webview is not
+//! running, so they serve a copy of this index. The webview asks for a
+//! refresh whenever the library changes; the index is rebuilt from
+//! `library.json` in the books directory and handed to the native bridge's
+//! `update_library_provider` only when it differs from the last one. Elsewhere
+//! the index is built and dropped.
+//!
+//! `LibraryProvider` is guarded by a normal-level permission that any app can
+//! ask for, so it only answers once the user turns on sharing in the
+//! settings; the index carries that choice. `LibraryDocumentsProvider` is
+//! only reachable through the system picker and always answers.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use crate::transfer_file::ensure_path_allowed;
+
+const LIBRARY_FILENAME: &str = "library.json";
+const COVER_FILENAME: &str = "cover.png";
+
+#[derive(Debug, Clone, PartialEq)]
+struct IndexedBook {
+    hash: String,
+    title: String,
+    author: String,
+    format: String,
+    /// Percent read, 0 to 100; `None` for a book never opened.
+    progress: Option<u8>,
+    /// Milliseconds since the epoch.
+    updated_at: i64,
+    /// Absolute path of the cover image, which may not exist.
+    cover_path: String,
+    /// Absolute path of the book file; `None` until it is downloaded.
+    file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LibraryIndex {
+    books: Vec<IndexedBook>,
+    /// Where exported annotations are kept, listed next to the books.
+    annotations_dir: String,
+    /// Other apps may read the books through `LibraryProvider`.
+    share_with_apps: bool,
+}
+
+#[derive(Default)]
+pub struct LibraryProvider {
+    published: Mutex<Option<LibraryIndex>>,
+}
+
+impl LibraryProvider {
+    /// Record `next` and return whether it differs from what was published.
+    fn replace(&self, next: &LibraryIndex) -> bool {
+        let mut published = self.published.lock().unwrap();
+        if published.as_ref() == Some(next) {
+            return false;
+        }
+        *published = Some(next.clone());
+        true
+    }
+
+    /// Forget what was published, so the next refresh goes through.
+    fn forget(&self) {
+        *self.published.lock().unwrap() = None;
+    }
+}
+
+fn progress_percent(progress: Option<&Value>) -> Option<u8> {
+    let pair = progress?.as_array()?;
+    let current = pair.first()?.as_f64()?;
+    let total = pair.get(1)?.as_f64()?;
+    if total <= 0.0 {
+        return None;
+    }
+    Some((current / total * 100.0).round().clamp(0.0, 100.0) as u8)
+}
+
+/// The book file in its `{hash}` directory, named after the title with the
+/// format's extension (`EPUB` -> `.epub`).
+fn book_file(book_dir: &Path, format: &str) -> Option<String> {
+    if format.is_empty() {
+        return None;
+    }
+    std::fs::read_dir(book_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(format))
+        })
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Books that aren't deleted, most recently updated first.
+fn build_index(books_dir: &Path) -> Vec<IndexedBook> {
+    let Some(Value::Array(books)) = std::fs::read_to_string(books_dir.join(LIBRARY_FILENAME))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+    else {
+        return Vec::new();
+    };
+    let text = |book: &Value, key: &str| {
+        book.get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut index: Vec<IndexedBook> = books
+        .iter()
+        .filter(|b| b.get("deletedAt").map_or(true, Value::is_null))
+        .filter_map(|b| {
+            let hash = b.get("hash")?.as_str()?.to_string();
+            let book_dir = books_dir.join(&hash);
+            let format = text(b, "format");
+            Some(IndexedBook {
+                title: text(b, "title"),
+                author: text(b, "author"),
+                progress: progress_percent(b.get("progress")),
+                updated_at: b.get("updatedAt").and_then(Value::as_f64).unwrap_or(0.0) as i64,
+                cover_path: book_dir.join(COVER_FILENAME).to_string_lossy().into_owned(),
+                file_path: book_file(&book_dir, &format),
+                format,
+                hash,
+            })
+        })
+        .collect();
+    index.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    index
+}
+
+#[cfg(target_os = "android")]
+fn publish(app: &AppHandle, index: LibraryIndex) -> Result<(), String> {
+    use tauri_plugin_native_bridge::{
+        LibraryProviderBook, NativeBridgeExt, UpdateLibraryProviderRequest,
+    };
+
+    let books = index
+        .books
+        .into_iter()
+        .map(|book| LibraryProviderBook {
+            hash: book.hash,
+            title: book.title,
+            author: book.author,
+            format: book.format,
+            progress: book.progress,
+            updated_at: book.updated_at,
+            cover_path: book.cover_path,
+            file_path: book.file_path,
+        })
+        .collect();
+    app.native_bridge()
+        .update_library_provider(UpdateLibraryProviderRequest {
+            books,
+            annotations_dir: index.annotations_dir,
+            share_with_apps: index.share_with_apps,
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "android"))]
+fn publish(_app: &AppHandle, _index: LibraryIndex) -> Result<(), String> {
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn refresh_library_provider(
+    app: AppHandle,
+    state: State<'_, LibraryProvider>,
+    books_dir: String,
+    annotations_dir: String,
+    share_with_apps: bool,
+) -> Result<(), String> {
+    ensure_path_allowed(&app, &books_dir).map_err(|e| e.to_string())?;
+    ensure_path_allowed(&app, &annotations_dir).map_err(|e| e.to_string())?;
+    let index = LibraryIndex {
+        books: build_index(Path::new(&books_dir)),
+        annotations_dir,
+        share_with_apps,
+    };
+    if !state.replace(&index) {
+        return Ok(());
+    }
+    publish(&app, index).inspect_err(|_| state.forget())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "readest-library-provider-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_library(dir: &Path, library: Value) {
+        std::fs::write(dir.join(LIBRARY_FILENAME), library.to_string()).unwrap();
+    }
+
+    #[test]
+    fn indexes_live_books_most_recent_first() {
+        let dir = books_dir("live");
+        write_library(
+            &dir,
+            serde_json::json!([
+                { "hash": "old", "title": "Old", "author": "A", "format": "EPUB",
+                  "progress": [3, 12], "updatedAt": 100 },
+                { "hash": "gone", "title": "Gone", "updatedAt": 300, "deletedAt": 301 },
+                { "hash": "new", "title": "New", "format": "PDF", "updatedAt": 200 },
+                { "title": "No hash", "updatedAt": 400 },
+            ]),
+        );
+        let index = build_index(&dir);
+        let hashes: Vec<&str> = index.iter().map(|b| b.hash.as_str()).collect();
+        assert_eq!(hashes, ["new", "old"]);
+        assert_eq!(index[0].progress, None);
+        assert_eq!(index[1].progress, Some(25));
+        assert_eq!(index[1].author, "A");
+        assert!(index[1].cover_path.ends_with("old/cover.png"));
+    }
+
+    #[test]
+    fn finds_downloaded_book_files() {
+        let dir = books_dir("files");
+        write_library(
+            &dir,
+            serde_json::json!([
+                { "hash": "here", "title": "Here", "format": "EPUB", "updatedAt": 2 },
+                { "hash": "cloud", "title": "Cloud", "format": "PDF", "updatedAt": 1 },
+            ]),
+        );
+        std::fs::create_dir_all(dir.join("here")).unwrap();
+        std::fs::write(dir.join("here").join(COVER_FILENAME), b"png").unwrap();
+        std::fs::write(dir.join("here").join("Here.epub"), b"epub").unwrap();
+        std::fs::create_dir_all(dir.join("cloud")).unwrap();
+        std::fs::write(dir.join("cloud").join(COVER_FILENAME), b"png").unwrap();
+
+        let index = build_index(&dir);
+        assert!(index[0]
+            .file_path
+            .as_deref()
+            .unwrap()
+            .ends_with("here/Here.epub"));
+        assert_eq!(index[1].file_path, None);
+    }
+
+    #[test]
+    fn missing_library_is_empty() {
+        let dir = books_dir("missing");
+        assert!(build_index(&dir).is_empty());
+    }
+
+    #[test]
+    fn progress_is_rounded_and_clamped() {
+        let percent = |v: Value| progress_percent(Some(&v));
+        assert_eq!(percent(serde_json::json!([1, 3])), Some(33));
+        assert_eq!(percent(serde_json::json!([5, 4])), Some(100));
+        assert_eq!(percent(serde_json::json!([0, 0])), None);
+        assert_eq!(percent(Value::Null), None);
+    }
+
+    #[test]
+    fn skips_unchanged_indexes() {
+        let state = LibraryProvider::default();
+        let dir = books_dir("unchanged");
+        write_library(
+            &dir,
+            serde_json::json!([{ "hash": "h", "title": "T", "updatedAt": 1 }]),
+        );
+        let index = LibraryIndex {
+            books: build_index(&dir),
+            annotations_dir: "/exports/annotations".into(),
+            share_with_apps: false,
+        };
+        assert!(state.replace(&index));
+        assert!(!state.replace(&index));
+        state.forget();
+        assert!(state.replace(&index));
+        let shared = LibraryIndex {
+            share_with_apps: true,
+            ..index.clone()
+        };
+        assert!(state.replace(&shared));
+        let moved = LibraryIndex {
+            annotations_dir: "/elsewhere".into(),
+            ..index
+        };
+        assert!(state.replace(&moved));
+    }
+}
@@ -0,0 +1,640 @@
+// Import the Apple Books (iBooks) library.
+//
+// Apple Books keeps its data in other apps' containers under `~/Library`:
+//   - `BKAgentService/…/iBooks/Books`: the book files. EPUBs are stored
+//     unpacked, as `*.epub` directories, and are zipped back up here;
+//   - iCloud Drive's `iCloud~com~apple~iBooks/Documents`: books synced
+//     from other devices when "iCloud Drive" is on for Books;
+//   - `iBooksX/…/BKLibrary/BKLibrary-*.sqlite`: the catalogue (asset id,
+//     title, author, path) in a Core Data store;
+//   - `iBooksX/…/AEAnnotation/AEAnnotation*.sqlite`: highlights, notes and
+//     bookmarks per asset id. Locations are plain EPUB CFIs, so they can be
+//     attached to the imported book as-is.
+//
+// PDF annotations live inside the PDF itself and come along with the file.
+// Store purchases are FairPlay-encrypted (`META-INF/sinf.xml`) and are
+// reported as skipped. macOS asks the user once before letting Readest read
+// another app's container; a denial surfaces as an unreadable directory.
+
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{ipc::Channel, AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::transfer_file::ensure_path_allowed;
+
+const BOOKS_DIR: &str = "Library/Containers/com.apple.BKAgentService/Data/Documents/iBooks/Books";
+const ICLOUD_BOOKS_DIR: &str = "Library/Mobile Documents/iCloud~com~apple~iBooks/Documents";
+const BKLIBRARY_DIR: &str = "Library/Containers/com.apple.iBooksX/Data/Documents/BKLibrary";
+const AEANNOTATION_DIR: &str = "Library/Containers/com.apple.iBooksX/Data/Documents/AEAnnotation";
+/// Core Data timestamps count seconds from 2001-01-01.
+const CORE_DATA_EPOCH_SECS: f64 = 978_307_200.0;
+/// `ZANNOTATIONTYPE`: 1 bookmarks, 2 highlights and notes. 3 is the last
+/// reading position, which Readest tracks itself.
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_HIGHLIGHT: i64 = 2;
+/// Package files Apple Books adds that don't belong in a portable EPUB.
+const PACKAGE_JUNK: &[&str] = &[
+    "iTunesMetadata.plist",
+    "iTunesMetadata-original.plist",
+    "iTunesArtwork",
+    ".DS_Store",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleBook {
+    pub asset_id: String,
+    pub title: String,
+    pub author: String,
+    pub path: String,
+    /// `epub` or `pdf`.
+    pub format: String,
+    pub annotation_count: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleBooksLibrary {
+    pub books: Vec<AppleBook>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleAnnotation {
+    /// `highlight` or `bookmark`.
+    pub kind: String,
+    pub cfi: String,
+    pub text: String,
+    pub note: String,
+    /// `highlight` or `underline`; mirrors `HighlightStyle`.
+    pub style: String,
+    /// Mirrors `HighlightColor`.
+    pub color: String,
+    /// Milliseconds since the epoch.
+    pub created_at: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleBooksImportOptions {
+    #[serde(default)]
+    pub include_annotations: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedAppleBook {
+    pub asset_id: String,
+    pub title: String,
+    pub author: String,
+    /// The copied (and for EPUB packages, zipped) file in `dest_dir`.
+    pub file_path: String,
+    pub annotations: Vec<AppleAnnotation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedAppleBook {
+    pub asset_id: String,
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleBooksImportReport {
+    pub books: Vec<ImportedAppleBook>,
+    pub skipped: Vec<SkippedAppleBook>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleBooksImportProgress {
+    pub title: String,
+    /// 1-based index of the book being processed.
+    pub index: usize,
+    pub total: usize,
+}
+
+fn open_read_only(path: &Path) -> Result<Connection, String> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("open {}: {e}", path.display()))
+}
+
+/// `*.sqlite` stores in `dir` whose name starts with `prefix`. Apple Books
+/// versions the file name (`AEAnnotation_v10312011_1727_local.sqlite`).
+fn find_stores(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut stores: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(prefix) && n.ends_with(".sqlite"))
+        })
+        .collect();
+    stores.sort();
+    stores
+}
+
+fn book_format(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "epub" => Some("epub"),
+        "pdf" => Some("pdf"),
+        _ => None,
+    }
+}
+
+/// Catalogue entries from `BKLibrary` that point at an EPUB or PDF.
+fn read_catalog(conn: &Connection) -> Result<Vec<AppleBook>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT ZASSETID, ZTITLE, ZAUTHOR, ZPATH FROM ZBKLIBRARYASSET
+             WHERE ZASSETID IS NOT NULL AND ZPATH IS NOT NULL",
+        )
+        .map_err(|e| format!("query BKLibrary: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(|e| format!("query BKLibrary: {e}"))?;
+    Ok(rows
+        .flatten()
+        .filter_map(|(asset_id, title, author, path)| {
+            let format = book_format(Path::new(&path))?;
+            let title = title.unwrap_or_else(|| file_title(Path::new(&path)));
+            Some(AppleBook {
+                asset_id,
+                title,
+                author: author.unwrap_or_default(),
+                path,
+                format: format.to_string(),
+                annotation_count: 0,
+            })
+        })
+        .collect())
+}
+
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Books found on disk but missing from the catalogue (or when the
+/// catalogue can't be read). The file stem stands in for the asset id.
+fn scan_books_dir(dir: &Path) -> Vec<AppleBook> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let format = book_format(&path)?;
+            let title = file_title(&path);
+            Some(AppleBook {
+                asset_id: title.clone(),
+                title,
+                author: String::new(),
+                path: path.to_string_lossy().to_string(),
+                format: format.to_string(),
+                annotation_count: 0,
+            })
+        })
+        .collect()
+}
+
+fn annotation_counts(conn: &Connection) -> Result<HashMap<String, usize>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT ZANNOTATIONASSETID, COUNT(*) FROM ZAEANNOTATION
+             WHERE ZANNOTATIONDELETED = 0 AND ZANNOTATIONTYPE IN (?1, ?2)
+             GROUP BY ZANNOTATIONASSETID",
+        )
+        .map_err(|e| format!("query AEAnnotation: {e}"))?;
+    let rows = stmt
+        .query_map([TYPE_BOOKMARK, TYPE_HIGHLIGHT], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })
+        .map_err(|e| format!("query AEAnnotation: {e}"))?;
+    Ok(rows.flatten().collect())
+}
+
+/// Apple Books' `ZANNOTATIONSTYLE`: 0 is its red underline, 1–5 are the
+/// green, blue, yellow, pink and purple highlighters.
+fn style_and_color(style: i64, is_underline: bool) -> (&'static str, &'static str) {
+    let color = match style {
+        0 => "red",
+        1 => "green",
+        2 => "blue",
+        4 => "red",
+        5 => "violet",
+        _ => "yellow",
+    };
+    let kind = if is_underline || style == 0 {
+        "underline"
+    } else {
+        "highlight"
+    };
+    (kind, color)
+}
+
+fn core_data_to_ms(seconds: f64) -> i64 {
+    ((seconds + CORE_DATA_EPOCH_SECS) * 1000.0) as i64
+}
+
+fn read_annotations(conn: &Connection, asset_id: &str) -> Result<Vec<AppleAnnotation>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT ZANNOTATIONTYPE, ZANNOTATIONLOCATION, ZANNOTATIONSELECTEDTEXT,
+                    ZANNOTATIONNOTE, ZANNOTATIONSTYLE, ZANNOTATIONISUNDERLINE,
+                    ZANNOTATIONCREATIONDATE
+             FROM ZAEANNOTATION
+             WHERE ZANNOTATIONASSETID = ?1 AND ZANNOTATIONDELETED = 0
+               AND ZANNOTATIONTYPE IN (?2, ?3) AND ZANNOTATIONLOCATION IS NOT NULL
+             ORDER BY ZANNOTATIONCREATIONDATE",
+        )
+        .map_err(|e| format!("query AEAnnotation: {e}"))?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![asset_id, TYPE_BOOKMARK, TYPE_HIGHLIGHT],
+            |row| {
+                let kind: i64 = row.get(0)?;
+                let (style, color) = style_and_color(
+                    row.get::<_, Option<i64>>(4)?.unwrap_or(3),
+                    row.get::<_, Option<i64>>(5)?.unwrap_or(0) != 0,
+                );
+                Ok(AppleAnnotation {
+                    kind: if kind == TYPE_BOOKMARK {
+                        "bookmark"
+                    } else {
+                        "highlight"
+                    }
+                    .to_string(),
+                    cfi: row.get(1)?,
+                    text: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    note: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    style: style.to_string(),
+                    color: color.to_string(),
+                    created_at: core_data_to_ms(row.get::<_, Option<f64>>(6)?.unwrap_or(0.0)),
+                })
+            },
+        )
+        .map_err(|e| format!("query AEAnnotation: {e}"))?;
+    Ok(rows.flatten().collect())
+}
+
+fn scan_library(home: &Path) -> Result<AppleBooksLibrary, String> {
+    let mut books: Vec<AppleBook> = Vec::new();
+    // The stores are private Core Data schemas; if one can't be read, fall
+    // back to what's on disk rather than failing the whole scan.
+    for store in find_stores(&home.join(BKLIBRARY_DIR), "BKLibrary") {
+        match open_read_only(&store).and_then(|conn| read_catalog(&conn)) {
+            Ok(catalog) => books.extend(catalog),
+            Err(e) => log::warn!("skipping Apple Books catalogue: {e}"),
+        }
+    }
+    for dir in [home.join(BOOKS_DIR), home.join(ICLOUD_BOOKS_DIR)] {
+        for book in scan_books_dir(&dir) {
+            if !books.iter().any(|b| b.path == book.path) {
+                books.push(book);
+            }
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    books.retain(|b| Path::new(&b.path).exists() && seen.insert(b.asset_id.clone()));
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for store in find_stores(&home.join(AEANNOTATION_DIR), "AEAnnotation") {
+        match open_read_only(&store).and_then(|conn| annotation_counts(&conn)) {
+            Ok(store_counts) => {
+                for (asset_id, count) in store_counts {
+                    *counts.entry(asset_id).or_default() += count;
+                }
+            }
+            Err(e) => log::warn!("skipping Apple Books annotations: {e}"),
+        }
+    }
+    for book in &mut books {
+        book.annotation_count = counts.get(&book.asset_id).copied().unwrap_or(0);
+    }
+    books.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
+    Ok(AppleBooksLibrary { books })
+}
+
+fn is_encrypted_package(dir: &Path) -> bool {
+    dir.join("META-INF/sinf.xml").is_file()
+}
+
+fn is_encrypted_epub(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|f| zip::ZipArchive::new(f).ok())
+        .is_some_and(|mut zip| zip.by_name("META-INF/sinf.xml").is_ok())
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Zip an unpacked EPUB directory into `dest`, with `mimetype` first and
+/// stored as the OCF spec requires.
+fn package_epub(dir: &Path, dest: &Path) -> Result<(), String> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files).map_err(|e| format!("read {}: {e}", dir.display()))?;
+    let file = File::create(dest).map_err(|e| format!("create {}: {e}", dest.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("mimetype", stored)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(b"application/epub+zip")
+        .map_err(|e| e.to_string())?;
+    let mut buf = Vec::new();
+    for path in files {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if name == "mimetype" || PACKAGE_JUNK.iter().any(|junk| name.ends_with(junk)) {
+            continue;
+        }
+        buf.clear();
+        File::open(&path)
+            .and_then(|mut f| f.read_to_end(&mut buf))
+            .map_err(|e| format!("read {}: {e}", path.display()))?;
+        zip.start_file(name, deflated).map_err(|e| e.to_string())?;
+        zip.write_all(&buf).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Copy (or package) `book` into `dest_dir/<asset id>/`, so books with the
+/// same file name don't collide.
+fn copy_book(book: &AppleBook, dest_dir: &Path) -> Result<PathBuf, String> {
+    let src = Path::new(&book.path);
+    let safe_id: String = book
+        .asset_id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let dir = dest_dir.join(safe_id);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let dest = dir.join(src.file_name().unwrap_or_default());
+    if src.is_dir() {
+        if is_encrypted_package(src) {
+            return Err("DRM-protected".to_string());
+        }
+        package_epub(src, &dest)?;
+    } else {
+        if book.format == "epub" && is_encrypted_epub(src) {
+            return Err("DRM-protected".to_string());
+        }
+        std::fs::copy(src, &dest).map_err(|e| format!("copy {}: {e}", src.display()))?;
+    }
+    Ok(dest)
+}
+
+fn import_books_sync(
+    home: &Path,
+    asset_ids: &[String],
+    dest_dir: &Path,
+    options: &AppleBooksImportOptions,
+    on_progress: &dyn Fn(AppleBooksImportProgress),
+) -> Result<AppleBooksImportReport, String> {
+    let library = scan_library(home)?;
+    let annotation_stores: Vec<Connection> = if options.include_annotations {
+        find_stores(&home.join(AEANNOTATION_DIR), "AEAnnotation")
+            .iter()
+            .map(|p| open_read_only(p))
+            .collect::<Result<_, _>>()?
+    } else {
+        Vec::new()
+    };
+    let mut report = AppleBooksImportReport::default();
+    for (i, asset_id) in asset_ids.iter().enumerate() {
+        let Some(book) = library.books.iter().find(|b| &b.asset_id == asset_id) else {
+            report.skipped.push(SkippedAppleBook {
+                asset_id: asset_id.clone(),
+                title: String::new(),
+                reason: "not found in the Apple Books library".to_string(),
+            });
+            continue;
+        };
+        on_progress(AppleBooksImportProgress {
+            title: book.title.clone(),
+            index: i + 1,
+            total: asset_ids.len(),
+        });
+        let file_path = match copy_book(book, dest_dir) {
+            Ok(path) => path,
+            Err(reason) => {
+                report.skipped.push(SkippedAppleBook {
+                    asset_id: asset_id.clone(),
+                    title: book.title.clone(),
+                    reason,
+                });
+                continue;
+            }
+        };
+        let mut annotations = Vec::new();
+        if book.format == "epub" {
+            for conn in &annotation_stores {
+                annotations.extend(read_annotations(conn, asset_id)?);
+            }
+        }
+        report.books.push(ImportedAppleBook {
+            asset_id: asset_id.clone(),
+            title: book.title.clone(),
+            author: book.author.clone(),
+            file_path: file_path.to_string_lossy().to_string(),
+            annotations,
+        });
+    }
+    Ok(report)
+}
+
+fn home_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .home_dir()
+        .map_err(|e| format!("no home directory: {e}"))
+}
+
+/// List the EPUBs and PDFs in the Apple Books library with their
+/// annotation counts.
+#[tauri::command]
+pub async fn apple_books_scan(app: AppHandle) -> Result<AppleBooksLibrary, String> {
+    let home = home_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || scan_library(&home))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Copy the selected books into `dest_dir` and, if asked, read their
+/// annotations. The frontend imports the files and attaches the
+/// annotations by CFI.
+#[tauri::command]
+pub async fn import_apple_books(
+    app: AppHandle,
+    asset_ids: Vec<String>,
+    dest_dir: String,
+    options: AppleBooksImportOptions,
+    on_progress: Channel<AppleBooksImportProgress>,
+) -> Result<AppleBooksImportReport, String> {
+    ensure_path_allowed(&app, &dest_dir).map_err(|e| e.to_string())?;
+    let home = home_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        import_books_sync(
+            &home,
+            &asset_ids,
+            Path::new(&dest_dir),
+            &options,
+            &|progress| {
+                let _ = on_progress.send(progress);
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fresh_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("readest-apple-books-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const ANNOTATION_SCHEMA: &str = "
+        CREATE TABLE ZAEANNOTATION (Z_PK INTEGER PRIMARY KEY, ZANNOTATIONASSETID TEXT,
+            ZANNOTATIONTYPE INTEGER, ZANNOTATIONDELETED INTEGER, ZANNOTATIONLOCATION TEXT,
+            ZANNOTATIONSELECTEDTEXT TEXT, ZANNOTATIONNOTE TEXT, ZANNOTATIONSTYLE INTEGER,
+            ZANNOTATIONISUNDERLINE INTEGER, ZANNOTATIONCREATIONDATE REAL);
+        INSERT INTO ZAEANNOTATION VALUES
+            (1, 'A1', 2, 0, 'epubcfi(/6/4!/4/2/1,:0,:5)', 'Hello', 'greeting', 1, 0, 600000000.5),
+            (2, 'A1', 2, 1, 'epubcfi(/6/4!/4/4/1,:0,:5)', 'Gone', NULL, 3, 0, 600000001),
+            (3, 'A1', 1, 0, 'epubcfi(/6/6!/4/2)', NULL, NULL, NULL, NULL, 600000002),
+            (4, 'A1', 3, 0, 'epubcfi(/6/8!/4/2)', NULL, NULL, NULL, NULL, 600000003),
+            (5, 'B2', 2, 0, 'epubcfi(/6/2!/4/2/1,:0,:3)', 'Hey', NULL, 0, 1, 600000004);";
+
+    #[test]
+    fn reads_live_annotations() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(ANNOTATION_SCHEMA).unwrap();
+        let annotations = read_annotations(&conn, "A1").unwrap();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(
+            annotations[0],
+            AppleAnnotation {
+                kind: "highlight".into(),
+                cfi: "epubcfi(/6/4!/4/2/1,:0,:5)".into(),
+                text: "Hello".into(),
+                note: "greeting".into(),
+                style: "highlight".into(),
+                color: "green".into(),
+                created_at: 1_578_307_200_500,
+            }
+        );
+        assert_eq!(annotations[1].kind, "bookmark");
+
+        let counts = annotation_counts(&conn).unwrap();
+        assert_eq!(counts.get("A1"), Some(&2));
+        assert_eq!(counts.get("B2"), Some(&1));
+        assert_eq!(read_annotations(&conn, "B2").unwrap()[0].style, "underline");
+    }
+
+    #[test]
+    fn packages_unpacked_epubs() {
+        let dir = fresh_dir("package");
+        let book = dir.join("Book.epub");
+        std::fs::create_dir_all(book.join("META-INF")).unwrap();
+        std::fs::create_dir_all(book.join("OEBPS")).unwrap();
+        std::fs::write(book.join("mimetype"), "application/epub+zip").unwrap();
+        std::fs::write(book.join("META-INF/container.xml"), "<container/>").unwrap();
+        std::fs::write(book.join("OEBPS/content.opf"), "<package/>").unwrap();
+        std::fs::write(book.join("iTunesMetadata.plist"), "<plist/>").unwrap();
+
+        let dest = dir.join("out.epub");
+        package_epub(&book, &dest).unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let names: Vec<String> = (0..zip.len())
+            .map(|i| zip.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["mimetype", "META-INF/container.xml", "OEBPS/content.opf"]
+        );
+        assert_eq!(
+            zip.by_name("mimetype").unwrap().compression(),
+            CompressionMethod::Stored
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn skips_drm_protected_books() {
+        let dir = fresh_dir("drm");
+        let src = dir.join("Bought.epub");
+        std::fs::create_dir_all(src.join("META-INF")).unwrap();
+        std::fs::write(src.join("META-INF/sinf.xml"), "<sinf/>").unwrap();
+        let book = AppleBook {
+            asset_id: "123".into(),
+            title: "Bought".into(),
+            author: String::new(),
+            path: src.to_string_lossy().to_string(),
+            format: "epub".into(),
+            annotation_count: 0,
+        };
+        assert_eq!(
+            copy_book(&book, &dir.join("dest")).unwrap_err(),
+            "DRM-protected"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,17 +1,58 @@
 use crate::allow_file_in_scopes;
+use crate::global_shortcuts::{load_accelerator_overrides, resolve_accelerator};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr};
 use std::path::PathBuf;
+
+use cocoa::base::{id, nil, BOOL, NO};
+use cocoa::foundation::NSString;
+use objc::declare::ClassDecl;
+use objc::runtime::{class_addMethod, object_getClass, Class, Imp, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use serde::Deserialize;
 use tauri::menu::MenuEvent;
-use tauri::menu::{MenuItemBuilder, SubmenuBuilder, HELP_SUBMENU_ID};
+use tauri::menu::{MenuItem, MenuItemBuilder, Submenu, SubmenuBuilder, HELP_SUBMENU_ID};
 use tauri::AppHandle;
 use tauri::Emitter;
+use tauri::Manager;
 use tauri_plugin_opener::OpenerExt;
 
+use super::spotlight::{book_link, open_deep_link};
+use super::system_dictionary::run_on_main_thread;
+
+const OPEN_RECENT_ID: &str = "open_recent";
+/// Prefix of the Open Recent item ids, followed by the book hash.
+const OPEN_RECENT_ITEM_PREFIX: &str = "open_recent:";
+/// Prefix of the Go menu item ids, followed by the action.
+const MENU_ACTION_ITEM_PREFIX: &str = "menu_action:";
+/// Event the Go menu items send to the webview, handled by `useShortcuts`.
+const MENU_ACTION_EVENT: &str = "menu-action";
+/// Accelerator overrides for the Go menu, in the app config directory.
+const MENU_SHORTCUTS_FILENAME: &str = "menu-shortcuts.json";
+
+/// Items of the Go menu: the `useShortcuts` action each one triggers, its
+/// title and default accelerator. `menu-shortcuts.json` maps actions to
+/// other accelerators, or to `null` for none, e.g.
+/// `{ "onGoNext": "Cmd+Right", "onShowSearchBar": null }`. It is read at
+/// launch. An accelerator takes its keys before the webview sees them, even
+/// in text fields, so the defaults stay off plain keys.
+const MENU_ACTIONS: &[(&str, &str, Option<&str>)] = &[
+    ("onGoNext", "Next Page", Some("Alt+Cmd+Right")),
+    ("onGoPrev", "Previous Page", Some("Alt+Cmd+Left")),
+    ("onToggleSideBar", "Table of Contents", Some("Ctrl+Cmd+S")),
+    ("onShowSearchBar", "Search in Book", Some("Cmd+F")),
+];
+
 #[derive(Clone, serde::Serialize)]
 #[allow(dead_code)]
 struct OpenFilesPayload {
     files: Vec<String>,
 }
 
+/// File ▸ Open Recent, refilled by `update_recent_books_menus`.
+pub struct OpenRecentMenu(Submenu<tauri::Wry>);
+
 pub fn setup_macos_menu(app: &AppHandle) -> tauri::Result<()> {
     let global_menu = app.menu().unwrap();
 
@@ -33,9 +74,25 @@ pub fn setup_macos_menu(app: &AppHandle) -> tauri::Result<()> {
     }) {
         if let Some(file_submenu) = file_menu.as_submenu() {
             file_submenu.insert(&open_item, 0)?;
+            let open_recent = SubmenuBuilder::with_id(app, OPEN_RECENT_ID, "Open Recent")
+                .enabled(false)
+                .build()?;
+            file_submenu.insert(&open_recent, 1)?;
+            app.manage(OpenRecentMenu(open_recent));
         }
     }
 
+    let overrides = load_accelerator_overrides(app, MENU_SHORTCUTS_FILENAME);
+    let go_menu = build_go_menu(app, &overrides)?;
+    let window_menu_position = global_menu.items()?.iter().position(|item| {
+        item.as_submenu()
+            .is_some_and(|submenu| submenu.text().ok().as_deref() == Some("Window"))
+    });
+    match window_menu_position {
+        Some(position) => global_menu.insert(&go_menu, position)?,
+        None => global_menu.append(&go_menu)?,
+    }
+
     global_menu.append(
         &SubmenuBuilder::new(app, "Help")
             .text("privacy_policy", "Privacy Policy")
@@ -49,12 +106,27 @@ pub fn setup_macos_menu(app: &AppHandle) -> tauri::Result<()> {
         handle_menu_event(app, &event);
     });
 
+    run_on_main_thread(|| unsafe { install_dock_menu() });
+
     Ok(())
 }
 
 pub fn handle_menu_event(app: &AppHandle, event: &MenuEvent) {
     let opener = app.opener();
-    if event.id() == "open_file" {
+    let id = event.id().as_ref();
+    if let Some(hash) = id.strip_prefix(OPEN_RECENT_ITEM_PREFIX) {
+        let link = book_link(hash);
+        run_on_main_thread(move || unsafe { open_link(&link) });
+    } else if let Some(action) = id.strip_prefix(MENU_ACTION_ITEM_PREFIX) {
+        // Only the focused window acts, as a key press would.
+        let focused = app
+            .webview_windows()
+            .into_values()
+            .find(|window| window.is_focused().unwrap_or(false));
+        if let Some(window) = focused {
+            let _ = app.emit_to(window.label(), MENU_ACTION_EVENT, action);
+        }
+    } else if event.id() == "open_file" {
         handle_open_file(app);
     } else if event.id() == "privacy_policy" {
         let _ = opener.open_url("https://readest.com/privacy-policy", None::<&str>);
@@ -65,6 +137,69 @@ pub fn handle_menu_event(app: &AppHandle, event: &MenuEvent) {
     }
 }
 
+/// The Go menu's actions with their titles and accelerators after
+/// `overrides`.
+fn resolve_menu_shortcuts(
+    overrides: &HashMap<String, Option<String>>,
+) -> Vec<(&'static str, &'static str, Option<String>)> {
+    MENU_ACTIONS
+        .iter()
+        .map(|&(action, title, default)| {
+            (
+                action,
+                title,
+                resolve_accelerator(overrides, action, default),
+            )
+        })
+        .collect()
+}
+
+fn build_go_menu(
+    app: &AppHandle,
+    overrides: &HashMap<String, Option<String>>,
+) -> tauri::Result<Submenu<tauri::Wry>> {
+    let mut menu = SubmenuBuilder::new(app, "Go");
+    for (action, title, accelerator) in resolve_menu_shortcuts(overrides) {
+        let id = format!("{MENU_ACTION_ITEM_PREFIX}{action}");
+        let item = match &accelerator {
+            Some(keys) => MenuItemBuilder::with_id(id.clone(), title)
+                .accelerator(keys)
+                .build(app)
+                .or_else(|e| {
+                    log::warn!("Invalid accelerator {keys:?} for {action}: {e}");
+                    MenuItem::with_id(app, id, title, true, None::<&str>)
+                })?,
+            None => MenuItem::with_id(app, id, title, true, None::<&str>)?,
+        };
+        if action == "onToggleSideBar" {
+            menu = menu.separator();
+        }
+        menu = menu.item(&item);
+    }
+    menu.build()
+}
+
+fn refresh_open_recent(app: &AppHandle, books: &[RecentBook]) -> tauri::Result<()> {
+    let Some(open_recent) = app.try_state::<OpenRecentMenu>() else {
+        return Ok(());
+    };
+    let submenu = &open_recent.0;
+    for item in submenu.items()? {
+        submenu.remove(&item)?;
+    }
+    for book in books {
+        let id = format!("{OPEN_RECENT_ITEM_PREFIX}{}", book.hash);
+        submenu.append(&MenuItem::with_id(
+            app,
+            id,
+            &book.title,
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    submenu.set_enabled(!books.is_empty())
+}
+
 fn handle_open_file(app: &AppHandle) {
     use tauri_plugin_dialog::DialogExt;
 
@@ -74,7 +209,9 @@ fn handle_open_file(app: &AppHandle) {
         .file()
         .add_filter(
             "Files",
-            &["epub", "pdf", "mobi", "azw", "azw3", "fb2", "cbz", "txt"],
+            &[
+                "epub", "pdf", "mobi", "azw", "azw3", "fb2", "cbz", "cbr", "cb7", "txt",
+            ],
         )
         .pick_file(move |file_path| {
             if let Some(path) = file_path {
@@ -86,3 +223,228 @@ fn handle_open_file(app: &AppHandle) {
             }
         });
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Dock menu
+// ─────────────────────────────────────────────────────────────────────────────
+//
+// The webview publishes the recently read books through
+// `update_recent_books_menus`, which also fills File ▸ Open Recent;
+// right-clicking the Dock icon lists them under "Continue Reading" (the last
+// book read) and "Recent Books". tao's app delegate has no
+// `applicationDockMenu:`, so one is added to its class that hands out the menu
+// built from the last update. Picking a book opens its `readest://book/{hash}`
+// link through the deep-link handler, as Spotlight results do.
+
+/// Objective-C type encoding of `applicationDockMenu:`.
+const DOCK_MENU_TYPES: &CStr = c"@@:@";
+
+#[derive(Debug, Deserialize)]
+pub struct RecentBook {
+    hash: String,
+    title: String,
+    /// Percent read, for books that have been opened.
+    progress: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentBooksLabels {
+    continue_reading: String,
+    recent_books: String,
+}
+
+thread_local! {
+    /// The menu handed to the Dock, retained; nil until the first update.
+    static DOCK_MENU: Cell<id> = const { Cell::new(nil) };
+    /// Target of the book items, created along with the first menu.
+    static DOCK_MENU_TARGET: Cell<id> = const { Cell::new(nil) };
+}
+
+fn dock_item_title(book: &RecentBook) -> String {
+    match book.progress {
+        Some(progress) => format!("{} — {progress}%", book.title),
+        None => book.title.clone(),
+    }
+}
+
+unsafe fn ns_string(s: &str) -> id {
+    let string: id = NSString::alloc(nil).init_str(s);
+    msg_send![string, autorelease]
+}
+
+extern "C" fn application_dock_menu(_this: &Object, _sel: Sel, _application: id) -> id {
+    DOCK_MENU.with(Cell::get)
+}
+
+extern "C" fn open_book(_this: &Object, _sel: Sel, sender: id) {
+    unsafe {
+        let link: id = msg_send![sender, representedObject];
+        if link.is_null() {
+            return;
+        }
+        let utf8: *const c_char = msg_send![link, UTF8String];
+        if utf8.is_null() {
+            return;
+        }
+        open_link(&CStr::from_ptr(utf8).to_string_lossy());
+    }
+}
+
+/// Open a Readest link through the app delegate. Must run on the main thread.
+pub(super) unsafe fn open_link(link: &str) {
+    let application: id = msg_send![class!(NSApplication), sharedApplication];
+    let delegate: id = msg_send![application, delegate];
+    if !delegate.is_null() {
+        open_deep_link(&*delegate, application, link);
+    }
+}
+
+fn dock_menu_target_class() -> &'static Class {
+    let class_name = "ReadestDockMenuTarget";
+    let mut decl = match Class::get(class_name) {
+        Some(class) => return class,
+        None => ClassDecl::new(class_name, class!(NSObject)).unwrap(),
+    };
+    unsafe {
+        decl.add_method(
+            sel!(openBook:),
+            open_book as extern "C" fn(&Object, Sel, id),
+        );
+    }
+    decl.register()
+}
+
+unsafe fn dock_menu_target() -> id {
+    DOCK_MENU_TARGET.with(|target| {
+        if target.get().is_null() {
+            let object: id = msg_send![dock_menu_target_class(), new];
+            target.set(object);
+        }
+        target.get()
+    })
+}
+
+unsafe fn install_dock_menu() {
+    let application: id = msg_send![class!(NSApplication), sharedApplication];
+    let delegate: id = msg_send![application, delegate];
+    if delegate.is_null() {
+        log::warn!("No app delegate to serve the Dock menu");
+        return;
+    }
+    let class = object_getClass(delegate as *const Object) as *mut Class;
+    let imp: Imp =
+        std::mem::transmute(application_dock_menu as extern "C" fn(&Object, Sel, id) -> id);
+    let added: BOOL = class_addMethod(
+        class,
+        sel!(applicationDockMenu:),
+        imp,
+        DOCK_MENU_TYPES.as_ptr(),
+    );
+    if added == NO {
+        log::warn!("The app delegate already has a Dock menu");
+    }
+}
+
+unsafe fn add_item(menu: id, title: &str, link: Option<&str>) {
+    let item: id = msg_send![class!(NSMenuItem), alloc];
+    let item: id = msg_send![item,
+        initWithTitle: ns_string(title)
+        action: sel!(openBook:)
+        keyEquivalent: ns_string("")];
+    match link {
+        Some(link) => {
+            let _: () = msg_send![item, setTarget: dock_menu_target()];
+            let _: () = msg_send![item, setRepresentedObject: ns_string(link)];
+        }
+        None => {
+            let _: () = msg_send![item, setEnabled: NO];
+        }
+    }
+    let _: () = msg_send![menu, addItem: item];
+    let _: () = msg_send![item, release];
+}
+
+/// A retained menu with `books`, most recent first.
+unsafe fn build_dock_menu(books: &[RecentBook], labels: &RecentBooksLabels) -> id {
+    let menu: id = msg_send![class!(NSMenu), alloc];
+    let menu: id = msg_send![menu, initWithTitle: ns_string("")];
+    let _: () = msg_send![menu, setAutoenablesItems: NO];
+
+    let Some((current, recent)) = books.split_first() else {
+        return menu;
+    };
+    add_item(menu, &labels.continue_reading, None);
+    add_item(
+        menu,
+        &dock_item_title(current),
+        Some(&book_link(&current.hash)),
+    );
+    if !recent.is_empty() {
+        let separator: id = msg_send![class!(NSMenuItem), separatorItem];
+        let _: () = msg_send![menu, addItem: separator];
+        add_item(menu, &labels.recent_books, None);
+        for book in recent {
+            add_item(menu, &dock_item_title(book), Some(&book_link(&book.hash)));
+        }
+    }
+    menu
+}
+
+/// Replace the books in the Dock menu and File ▸ Open Recent with `books`,
+/// most recently read first.
+#[tauri::command]
+pub fn update_recent_books_menus(
+    app: AppHandle,
+    books: Vec<RecentBook>,
+    labels: RecentBooksLabels,
+) -> Result<(), String> {
+    refresh_open_recent(&app, &books).map_err(|e| format!("Failed to update Open Recent: {e}"))?;
+    run_on_main_thread(move || unsafe {
+        let menu = build_dock_menu(&books, &labels);
+        let previous = DOCK_MENU.with(|current| current.replace(menu));
+        if !previous.is_null() {
+            let _: () = msg_send![previous, release];
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dock_items_show_progress_of_opened_books() {
+        let book = |progress| RecentBook {
+            hash: "h".into(),
+            title: "Dune".into(),
+            progress,
+        };
+        assert_eq!(dock_item_title(&book(Some(42))), "Dune — 42%");
+        assert_eq!(dock_item_title(&book(None)), "Dune");
+    }
+
+    #[test]
+    fn menu_shortcuts_override_or_clear_defaults() {
+        let overrides = HashMap::from([
+            ("onGoNext".to_string(), Some("Cmd+Right".to_string())),
+            ("onShowSearchBar".to_string(), None),
+            ("onToggleSideBar".to_string(), Some(" ".to_string())),
+            ("onUnknown".to_string(), Some("Cmd+U".to_string())),
+        ]);
+        let accelerators: Vec<_> = resolve_menu_shortcuts(&overrides)
+            .into_iter()
+            .map(|(action, _, accelerator)| (action, accelerator))
+            .collect();
+        assert_eq!(
+            accelerators,
+            [
+                ("onGoNext", Some("Cmd+Right".to_string())),
+                ("onGoPrev", Some("Alt+Cmd+Left".to_string())),
+                ("onToggleSideBar", None),
+                ("onShowSearchBar", None),
+            ]
+        );
+    }
+}
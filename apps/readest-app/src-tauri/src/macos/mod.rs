@@ -1,6 +1,11 @@
+pub mod apple_books;
 pub mod apple_auth;
 pub mod menu;
+pub mod now_playing;
 pub mod os_version;
+pub mod power;
 pub mod safari_auth;
+pub mod services;
+pub mod spotlight;
 pub mod system_dictionary;
 pub mod traffic_light;
@@ -0,0 +1,359 @@
+//! macOS Now Playing for read-aloud: Control Center, AirPods taps and
+//! the keyboard's media keys.
+//!
+//! WKWebView only publishes media elements it plays itself, and
+//! read-aloud speaks through WebAudio or the native TTS plugin, so the
+//! session is published from here through `MPNowPlayingInfoCenter` and
+//! `MPRemoteCommandCenter`. The webview feeds it metadata and playback
+//! state; commands go back to it as `now-playing-action` events named
+//! after the Media Session actions the TTS media bridge handles, as the
+//! Linux MPRIS session does.
+//!
+//! Both centers are driven from the main thread. The command targets
+//! and the artwork live there in a thread-local; what the webview last
+//! reported lives in the managed `NowPlaying` state.
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+use base64::Engine;
+use block::ConcreteBlock;
+use cocoa::base::{id, nil, NO, YES};
+use cocoa::foundation::{NSSize, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use super::system_dictionary::run_on_main_thread;
+
+#[link(name = "MediaPlayer", kind = "framework")]
+extern "C" {
+    static MPMediaItemPropertyTitle: id;
+    static MPMediaItemPropertyArtist: id;
+    static MPMediaItemPropertyAlbumTitle: id;
+    static MPMediaItemPropertyPlaybackDuration: id;
+    static MPMediaItemPropertyArtwork: id;
+    static MPNowPlayingInfoPropertyElapsedPlaybackTime: id;
+    static MPNowPlayingInfoPropertyPlaybackRate: id;
+}
+
+const ACTION_EVENT: &str = "now-playing-action";
+
+/// `MPRemoteCommandHandlerStatus`
+const HANDLER_SUCCESS: isize = 0;
+const HANDLER_COMMAND_FAILED: isize = 200;
+
+/// `MPNowPlayingPlaybackState`. macOS routes the media keys to the app
+/// whose state says it is playing.
+const PLAYBACK_STATE_PLAYING: usize = 1;
+const PLAYBACK_STATE_PAUSED: usize = 2;
+const PLAYBACK_STATE_STOPPED: usize = 3;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlayingMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    /// A data URL; other covers are left out.
+    artwork: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlayingPlaybackState {
+    playing: bool,
+    /// Milliseconds, as with the native TTS media session.
+    position: Option<f64>,
+    duration: Option<f64>,
+    #[serde(default)]
+    seekable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NowPlayingAction {
+    action: &'static str,
+    /// Milliseconds, for `seekto`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<u64>,
+}
+
+/// What the webview last reported.
+#[derive(Debug, Clone, Default)]
+struct Info {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    playing: bool,
+    /// Seconds, as Now Playing counts them.
+    elapsed: f64,
+    duration: Option<f64>,
+    seekable: bool,
+}
+
+#[derive(Default)]
+pub struct NowPlaying(Mutex<Option<Info>>);
+
+#[derive(Clone, Copy)]
+enum Command {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Previous,
+    Seek,
+}
+
+impl Command {
+    const ALL: [Command; 6] = [
+        Command::Play,
+        Command::Pause,
+        Command::Toggle,
+        Command::Next,
+        Command::Previous,
+        Command::Seek,
+    ];
+
+    fn action(self) -> &'static str {
+        match self {
+            Command::Play => "play",
+            Command::Pause => "pause",
+            Command::Toggle => "toggle",
+            Command::Next => "nexttrack",
+            Command::Previous => "previoustrack",
+            Command::Seek => "seekto",
+        }
+    }
+
+    unsafe fn get(self, center: id) -> id {
+        match self {
+            Command::Play => msg_send![center, playCommand],
+            Command::Pause => msg_send![center, pauseCommand],
+            Command::Toggle => msg_send![center, togglePlayPauseCommand],
+            Command::Next => msg_send![center, nextTrackCommand],
+            Command::Previous => msg_send![center, previousTrackCommand],
+            Command::Seek => msg_send![center, changePlaybackPositionCommand],
+        }
+    }
+}
+
+/// An object released when dropped, for the artwork block to own the
+/// image it hands out.
+struct Owned(id);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![self.0, release];
+        }
+    }
+}
+
+thread_local! {
+    /// The command targets added, to remove when the session ends.
+    static TARGETS: RefCell<Vec<(id, id)>> = const { RefCell::new(Vec::new()) };
+    /// The `MPMediaItemArtwork` for the current cover, retained.
+    static ARTWORK: RefCell<Option<Owned>> = const { RefCell::new(None) };
+}
+
+unsafe fn ns_string(s: &str) -> id {
+    let string: id = NSString::alloc(nil).init_str(s);
+    msg_send![string, autorelease]
+}
+
+unsafe fn ns_number(value: f64) -> id {
+    msg_send![class!(NSNumber), numberWithDouble: value]
+}
+
+fn cover_bytes(url: &str) -> Option<Vec<u8>> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    header.strip_suffix(";base64")?;
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+/// A retained `MPMediaItemArtwork` for the image in `bytes`.
+unsafe fn artwork(bytes: &[u8]) -> Option<Owned> {
+    let data: id = msg_send![class!(NSData), dataWithBytes: bytes.as_ptr() length: bytes.len()];
+    let image: id = msg_send![class!(NSImage), alloc];
+    let image: id = msg_send![image, initWithData: data];
+    if image.is_null() {
+        return None;
+    }
+    let image = Owned(image);
+    let size: NSSize = msg_send![image.0, size];
+    // The block (and with it the image) is released along with the
+    // artwork.
+    let handler = ConcreteBlock::new(move |_size: NSSize| -> id { image.0 }).copy();
+    let artwork: id = msg_send![class!(MPMediaItemArtwork), alloc];
+    let artwork: id = msg_send![artwork, initWithBoundsSize: size requestHandler: &*handler];
+    (!artwork.is_null()).then_some(Owned(artwork))
+}
+
+unsafe fn publish(info: &Info) {
+    let dict: id = msg_send![class!(NSMutableDictionary), dictionary];
+    let set = |key: id, value: id| {
+        let _: () = msg_send![dict, setObject: value forKey: key];
+    };
+    if let Some(title) = &info.title {
+        set(MPMediaItemPropertyTitle, ns_string(title));
+    }
+    if let Some(artist) = &info.artist {
+        set(MPMediaItemPropertyArtist, ns_string(artist));
+    }
+    if let Some(album) = &info.album {
+        set(MPMediaItemPropertyAlbumTitle, ns_string(album));
+    }
+    if let Some(duration) = info.duration {
+        set(MPMediaItemPropertyPlaybackDuration, ns_number(duration));
+    }
+    set(
+        MPNowPlayingInfoPropertyElapsedPlaybackTime,
+        ns_number(info.elapsed),
+    );
+    set(
+        MPNowPlayingInfoPropertyPlaybackRate,
+        ns_number(if info.playing { 1.0 } else { 0.0 }),
+    );
+    ARTWORK.with(|artwork| {
+        if let Some(artwork) = artwork.borrow().as_ref() {
+            set(MPMediaItemPropertyArtwork, artwork.0);
+        }
+    });
+    let center: id = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+    let _: () = msg_send![center, setNowPlayingInfo: dict];
+    let state = if info.playing {
+        PLAYBACK_STATE_PLAYING
+    } else {
+        PLAYBACK_STATE_PAUSED
+    };
+    let _: () = msg_send![center, setPlaybackState: state];
+
+    let commands: id = msg_send![class!(MPRemoteCommandCenter), sharedCommandCenter];
+    let seek = Command::Seek.get(commands);
+    let seekable = info.seekable && info.duration.is_some();
+    let _: () = msg_send![seek, setEnabled: if seekable { YES } else { NO }];
+}
+
+unsafe fn add_targets(app: AppHandle) {
+    let center: id = msg_send![class!(MPRemoteCommandCenter), sharedCommandCenter];
+    TARGETS.with(|targets| {
+        let mut targets = targets.borrow_mut();
+        for command in Command::ALL {
+            let app = app.clone();
+            let handler = ConcreteBlock::new(move |event: id| -> isize {
+                let position = match command {
+                    Command::Seek => {
+                        let seconds: f64 = msg_send![event, positionTime];
+                        Some((seconds.max(0.0) * 1000.0) as u64)
+                    }
+                    _ => None,
+                };
+                let action = NowPlayingAction {
+                    action: command.action(),
+                    position,
+                };
+                match app.emit(ACTION_EVENT, action) {
+                    Ok(()) => HANDLER_SUCCESS,
+                    Err(_) => HANDLER_COMMAND_FAILED,
+                }
+            })
+            .copy();
+            let remote = command.get(center);
+            let _: () = msg_send![remote, setEnabled: YES];
+            let target: id = msg_send![remote, addTargetWithHandler: &*handler];
+            targets.push((remote, target));
+        }
+    });
+}
+
+unsafe fn end_session() {
+    TARGETS.with(|targets| {
+        for (remote, target) in targets.borrow_mut().drain(..) {
+            let _: () = msg_send![remote, removeTarget: target];
+        }
+    });
+    ARTWORK.with(|artwork| artwork.borrow_mut().take());
+    let center: id = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+    let _: () = msg_send![center, setNowPlayingInfo: nil];
+    let _: () = msg_send![center, setPlaybackState: PLAYBACK_STATE_STOPPED];
+}
+
+/// Publish read-aloud to Now Playing, or take it off.
+#[tauri::command]
+pub fn now_playing_set_active(
+    app: AppHandle,
+    now_playing: State<'_, NowPlaying>,
+    active: bool,
+) -> Result<(), String> {
+    let mut info = now_playing.0.lock().map_err(|e| e.to_string())?;
+    if !active {
+        if info.take().is_some() {
+            run_on_main_thread(|| unsafe { end_session() });
+        }
+        return Ok(());
+    }
+    if info.is_none() {
+        *info = Some(Info::default());
+        run_on_main_thread(move || unsafe { add_targets(app) });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn now_playing_update_metadata(
+    now_playing: State<'_, NowPlaying>,
+    metadata: NowPlayingMetadata,
+) -> Result<(), String> {
+    let mut info = now_playing.0.lock().map_err(|e| e.to_string())?;
+    let Some(info) = info.as_mut() else {
+        return Ok(());
+    };
+    info.title = metadata.title.filter(|title| !title.is_empty());
+    info.artist = metadata.artist.filter(|artist| !artist.is_empty());
+    info.album = metadata.album.filter(|album| !album.is_empty());
+    let cover = metadata.artwork.as_deref().and_then(cover_bytes);
+    let info = info.clone();
+    run_on_main_thread(move || unsafe {
+        let cover = cover.and_then(|bytes| artwork(&bytes));
+        ARTWORK.with(|artwork| *artwork.borrow_mut() = cover);
+        publish(&info);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn now_playing_update_state(
+    now_playing: State<'_, NowPlaying>,
+    state: NowPlayingPlaybackState,
+) -> Result<(), String> {
+    let mut info = now_playing.0.lock().map_err(|e| e.to_string())?;
+    let Some(info) = info.as_mut() else {
+        return Ok(());
+    };
+    info.playing = state.playing;
+    info.duration = state
+        .duration
+        .filter(|&duration| duration > 0.0)
+        .map(|duration| duration / 1000.0);
+    info.elapsed = state.position.unwrap_or(0.0).max(0.0) / 1000.0;
+    info.seekable = state.seekable;
+    let info = info.clone();
+    run_on_main_thread(move || unsafe { publish(&info) });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_covers_from_data_urls() {
+        assert_eq!(
+            cover_bytes("data:image/png;base64,AAEC"),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(cover_bytes("data:image/png,AAEC"), None);
+        assert_eq!(cover_bytes("/icon.png"), None);
+    }
+}
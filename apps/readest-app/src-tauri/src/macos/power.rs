@@ -0,0 +1,52 @@
+//! IOKit power assertion backend for [`crate::keep_awake`].
+
+use std::ffi::c_void;
+
+use objc2::rc::Retained;
+use objc2_foundation::NSString;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPMAssertionCreateWithName(
+        assertion_type: *const c_void,
+        assertion_level: u32,
+        assertion_name: *const c_void,
+        assertion_id: *mut u32,
+    ) -> i32;
+    fn IOPMAssertionRelease(assertion_id: u32) -> i32;
+}
+
+/// `kIOPMAssertionTypePreventUserIdleSystemSleep`, a `CFSTR` in the headers.
+const PREVENT_USER_IDLE_SYSTEM_SLEEP: &str = "PreventUserIdleSystemSleep";
+const IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+const IO_RETURN_SUCCESS: i32 = 0;
+
+/// Keeps the Mac from idle sleep until dropped. The display may still sleep.
+pub struct PowerAssertion(u32);
+
+impl PowerAssertion {
+    pub fn prevent_idle_sleep(reason: &str) -> Result<Self, String> {
+        // NSString is toll-free bridged with the CFString IOKit takes.
+        let assertion_type = NSString::from_str(PREVENT_USER_IDLE_SYSTEM_SLEEP);
+        let name = NSString::from_str(reason);
+        let mut id = 0;
+        let status = unsafe {
+            IOPMAssertionCreateWithName(
+                Retained::as_ptr(&assertion_type).cast(),
+                IOPM_ASSERTION_LEVEL_ON,
+                Retained::as_ptr(&name).cast(),
+                &mut id,
+            )
+        };
+        if status != IO_RETURN_SUCCESS {
+            return Err(format!("IOPMAssertionCreateWithName returned {status:#x}"));
+        }
+        Ok(PowerAssertion(id))
+    }
+}
+
+impl Drop for PowerAssertion {
+    fn drop(&mut self) {
+        unsafe { IOPMAssertionRelease(self.0) };
+    }
+}
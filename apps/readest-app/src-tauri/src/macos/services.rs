@@ -0,0 +1,133 @@
+//! "Look Up in Readest" in the Services menu.
+//!
+//! `Info.plist` declares the service (`NSServices`, message
+//! `lookUpInReadest`), limited to selections of a few words, so it shows
+//! up in the Services and context menus of any app. The system calls the
+//! provider that [`init`] registers with the selected text on a
+//! pasteboard, launching Readest first if needed. The provider turns it
+//! into a `readest://lookup?q=…` link and opens it through the deep-link
+//! path, where the webview shows the word in the dictionary.
+
+use std::ffi::{c_char, CStr};
+
+use cocoa::base::{id, nil, YES};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use super::menu::open_link;
+use super::system_dictionary::run_on_main_thread;
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSPasteboardTypeString: id;
+    fn NSUpdateDynamicServices();
+}
+
+/// Longest query passed on, in characters. `NSWordLimit` already keeps
+/// the selection short; this bounds what a misbehaving app can send.
+const MAX_QUERY_CHARS: usize = 100;
+
+/// The `readest://lookup` link for `selection`, with its whitespace
+/// collapsed, or `None` when there is nothing to look up.
+fn lookup_link(selection: &str) -> Option<String> {
+    let query: String = selection
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_QUERY_CHARS)
+        .collect();
+    if query.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "readest://lookup?q={}",
+        utf8_percent_encode(&query, NON_ALPHANUMERIC)
+    ))
+}
+
+extern "C" fn look_up_in_readest(
+    _this: &Object,
+    _sel: Sel,
+    pasteboard: id,
+    _user_data: id,
+    _error: *mut id,
+) {
+    unsafe {
+        let string: id = msg_send![pasteboard, stringForType: NSPasteboardTypeString];
+        if string.is_null() {
+            return;
+        }
+        let utf8: *const c_char = msg_send![string, UTF8String];
+        if utf8.is_null() {
+            return;
+        }
+        let Some(link) = lookup_link(&CStr::from_ptr(utf8).to_string_lossy()) else {
+            return;
+        };
+        let application: id = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![application, activateIgnoringOtherApps: YES];
+        open_link(&link);
+    }
+}
+
+fn services_provider_class() -> &'static Class {
+    let class_name = "ReadestServicesProvider";
+    let mut decl = match Class::get(class_name) {
+        Some(class) => return class,
+        None => ClassDecl::new(class_name, class!(NSObject)).unwrap(),
+    };
+    unsafe {
+        decl.add_method(
+            sel!(lookUpInReadest:userData:error:),
+            look_up_in_readest as extern "C" fn(&Object, Sel, id, id, *mut id),
+        );
+    }
+    decl.register()
+}
+
+/// Register the Services provider. Call during setup, so that a lookup
+/// launching the app is delivered once it has finished launching.
+pub fn init() {
+    run_on_main_thread(|| unsafe {
+        let provider: id = msg_send![services_provider_class(), new];
+        if provider == nil {
+            log::warn!("Failed to create the Services provider");
+            return;
+        }
+        let application: id = msg_send![class!(NSApplication), sharedApplication];
+        // NSApplication doesn't retain its services provider; the provider
+        // is kept for the lifetime of the app.
+        let _: () = msg_send![application, setServicesProvider: provider];
+        NSUpdateDynamicServices();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_to_the_selected_words() {
+        assert_eq!(
+            lookup_link("  serendipity\n").as_deref(),
+            Some("readest://lookup?q=serendipity")
+        );
+        assert_eq!(
+            lookup_link("ad\thoc  café").as_deref(),
+            Some("readest://lookup?q=ad%20hoc%20caf%C3%A9")
+        );
+        assert_eq!(lookup_link(" \n "), None);
+    }
+
+    #[test]
+    fn caps_long_selections() {
+        let link = lookup_link(&"a".repeat(500)).unwrap();
+        assert_eq!(
+            link,
+            format!("readest://lookup?q={}", "a".repeat(MAX_QUERY_CHARS))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Library books in Spotlight.
+//!
+//! The webview publishes the library through `update_spotlight_index`,
+//! which replaces Readest's `CSSearchableIndex` domain with an item per
+//! book: title, authors and cover, keyed by the book hash. Picking a
+//! result continues a `CSSearchableItemActionType` user activity in the
+//! app, which tao's app delegate doesn't handle, so [`init`] adds the
+//! method to it. The handler turns the activity into a
+//! `readest://book/{hash}` link and passes it to the delegate's
+//! `application:openURLs:`, so the book opens through the same deep-link
+//! path as the home-screen widget, cold start included.
+
+use std::ffi::{c_char, CStr};
+use std::path::{Path, PathBuf};
+
+use block::ConcreteBlock;
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::NSString;
+use objc::runtime::{class_addMethod, object_getClass, Class, Imp, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use serde::Deserialize;
+
+use super::system_dictionary::run_on_main_thread;
+
+#[link(name = "CoreSpotlight", kind = "framework")]
+extern "C" {
+    static CSSearchableItemActionType: id;
+    static CSSearchableItemActivityIdentifier: id;
+}
+
+/// Domain of Readest's items, replaced as a whole on every update.
+const DOMAIN: &str = "com.bilingify.readest.library";
+/// Type the items are filed under in Spotlight's results.
+const CONTENT_TYPE: &str = "public.content";
+
+/// Objective-C type encoding of
+/// `application:continueUserActivity:restorationHandler:`.
+#[cfg(target_arch = "aarch64")]
+const CONTINUE_USER_ACTIVITY_TYPES: &CStr = c"B@:@@@?";
+#[cfg(not(target_arch = "aarch64"))]
+const CONTINUE_USER_ACTIVITY_TYPES: &CStr = c"c@:@@@?";
+
+#[derive(Debug, Deserialize)]
+pub struct SpotlightBook {
+    hash: String,
+    title: String,
+    author: String,
+}
+
+pub(super) fn book_link(hash: &str) -> String {
+    format!("readest://book/{hash}")
+}
+
+/// The names in an author string such as "A, B & C".
+fn author_names(author: &str) -> Vec<&str> {
+    author
+        .split([',', ';', '&'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The cover the library keeps for `hash`, once it has been extracted.
+fn cover_path(books_dir: &Path, hash: &str) -> Option<PathBuf> {
+    let path = books_dir.join(hash).join("cover.png");
+    path.exists().then_some(path)
+}
+
+/// An object released when dropped, for the completion block to own the
+/// items it indexes.
+struct Owned(id);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![self.0, release];
+        }
+    }
+}
+
+unsafe fn ns_string(s: &str) -> id {
+    let string: id = NSString::alloc(nil).init_str(s);
+    msg_send![string, autorelease]
+}
+
+unsafe fn searchable_item(book: &SpotlightBook, books_dir: &Path) -> id {
+    let attributes: id = msg_send![class!(CSSearchableItemAttributeSet), alloc];
+    let attributes: id = msg_send![attributes, initWithItemContentType: ns_string(CONTENT_TYPE)];
+    let _: () = msg_send![attributes, setTitle: ns_string(&book.title)];
+    let _: () = msg_send![attributes, setDisplayName: ns_string(&book.title)];
+    let names = author_names(&book.author);
+    if !names.is_empty() {
+        let authors: id = msg_send![class!(NSMutableArray), array];
+        for name in names {
+            let _: () = msg_send![authors, addObject: ns_string(name)];
+        }
+        let _: () = msg_send![attributes, setAuthorNames: authors];
+        let _: () = msg_send![attributes, setContentDescription: ns_string(&book.author)];
+    }
+    if let Some(cover) = cover_path(books_dir, &book.hash) {
+        let path = ns_string(&cover.to_string_lossy());
+        let url: id = msg_send![class!(NSURL), fileURLWithPath: path];
+        let _: () = msg_send![attributes, setThumbnailURL: url];
+    }
+
+    let item: id = msg_send![class!(CSSearchableItem), alloc];
+    let item: id = msg_send![item,
+        initWithUniqueIdentifier: ns_string(&book.hash)
+        domainIdentifier: ns_string(DOMAIN)
+        attributeSet: attributes];
+    let _: () = msg_send![attributes, release];
+    msg_send![item, autorelease]
+}
+
+unsafe fn replace_index(books: &[SpotlightBook], books_dir: &Path) {
+    let items: id = msg_send![class!(NSMutableArray), arrayWithCapacity: books.len()];
+    for book in books {
+        let _: () = msg_send![items, addObject: searchable_item(book, books_dir)];
+    }
+    let items: id = msg_send![items, retain];
+    let items = Owned(items);
+
+    let index: id = msg_send![class!(CSSearchableIndex), defaultSearchableIndex];
+    let domains: id = msg_send![class!(NSArray), arrayWithObject: ns_string(DOMAIN)];
+    // Deleting the domain first drops the books removed from the library.
+    let completion = ConcreteBlock::new(move |error: id| {
+        if !error.is_null() {
+            log::warn!("Failed to clear the Spotlight index");
+            return;
+        }
+        let _: () = msg_send![index, indexSearchableItems: items.0 completionHandler: nil];
+    })
+    .copy();
+    let _: () = msg_send![index,
+        deleteSearchableItemsWithDomainIdentifiers: domains
+        completionHandler: &*completion];
+}
+
+unsafe fn ns_string_to_string(string: id) -> Option<String> {
+    let utf8: *const c_char = msg_send![string, UTF8String];
+    (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned())
+}
+
+extern "C" fn continue_user_activity(
+    this: &Object,
+    _sel: Sel,
+    application: id,
+    activity: id,
+    _restoration_handler: id,
+) -> BOOL {
+    unsafe {
+        let activity_type: id = msg_send![activity, activityType];
+        let is_search_result: BOOL =
+            msg_send![activity_type, isEqualToString: CSSearchableItemActionType];
+        if is_search_result == NO {
+            return NO;
+        }
+        let user_info: id = msg_send![activity, userInfo];
+        let hash: id = msg_send![user_info, objectForKey: CSSearchableItemActivityIdentifier];
+        if hash.is_null() {
+            return NO;
+        }
+        let Some(hash) = ns_string_to_string(hash) else {
+            return NO;
+        };
+        if open_deep_link(this, application, &book_link(&hash)) {
+            YES
+        } else {
+            NO
+        }
+    }
+}
+
+/// Open `link` as if the system had delivered it, so that it takes the
+/// deep-link path: the delegate reports it as `RunEvent::Opened`, which the
+/// deep-link plugin passes on to the webview.
+pub(super) unsafe fn open_deep_link(delegate: &Object, application: id, link: &str) -> bool {
+    let url: id = msg_send![class!(NSURL), URLWithString: ns_string(link)];
+    if url.is_null() {
+        return false;
+    }
+    let urls: id = msg_send![class!(NSArray), arrayWithObject: url];
+    let _: () = msg_send![delegate, application: application openURLs: urls];
+    true
+}
+
+/// Open the books picked in Spotlight. Call once the app delegate is set,
+/// before launch finishes, so that a result launching the app is handled
+/// too.
+pub fn init() {
+    run_on_main_thread(|| unsafe {
+        let application: id = msg_send![class!(NSApplication), sharedApplication];
+        let delegate: id = msg_send![application, delegate];
+        if delegate.is_null() {
+            log::warn!("No app delegate to open Spotlight results with");
+            return;
+        }
+        let class = object_getClass(delegate as *const Object) as *mut Class;
+        let imp: Imp = std::mem::transmute(
+            continue_user_activity as extern "C" fn(&Object, Sel, id, id, id) -> BOOL,
+        );
+        let added = class_addMethod(
+            class,
+            sel!(application:continueUserActivity:restorationHandler:),
+            imp,
+            CONTINUE_USER_ACTIVITY_TYPES.as_ptr(),
+        );
+        if added == NO {
+            log::warn!("The app delegate already continues user activities");
+        }
+    });
+}
+
+/// Replace Readest's Spotlight items with `books`. `books_dir` is the
+/// library's Books directory, where each book's cover is kept.
+#[tauri::command]
+pub fn update_spotlight_index(books: Vec<SpotlightBook>, books_dir: String) {
+    run_on_main_thread(move || unsafe {
+        replace_index(&books, Path::new(&books_dir));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_author_names() {
+        assert_eq!(
+            author_names("Terry Pratchett & Neil Gaiman"),
+            ["Terry Pratchett", "Neil Gaiman"]
+        );
+        assert_eq!(author_names("A, B; C"), ["A", "B", "C"]);
+        assert!(author_names("").is_empty());
+    }
+
+    #[test]
+    fn links_to_the_book() {
+        assert_eq!(book_link("abc123"), "readest://book/abc123");
+    }
+}
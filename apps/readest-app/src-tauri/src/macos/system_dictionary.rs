@@ -333,7 +333,7 @@ unsafe impl Sync for ThreadSafeNSWindow {}
 /// thread; otherwise enqueued on `NSOperationQueue mainQueue`. AppKit
 /// requires NSView calls (and most NSWindow accessors) to run on the
 /// main thread.
-fn run_on_main_thread<F>(f: F)
+pub(super) fn run_on_main_thread<F>(f: F)
 where
     F: FnOnce() + Send + 'static,
 {
@@ -0,0 +1,436 @@
+// Import annotations and positions from a Moon+ Reader backup.
+//
+// A `.mrpro` backup is a zip of numbered `N.tag` files plus `_names.list`,
+// whose Nth line is the original on-device path of `N.tag`. Two kinds of
+// files matter:
+//   - `…/databases/mrbooks.db`: SQLite; the `notes` table holds highlights
+//     (`original` text, colour, underline/strikethrough) and bookmarks, each
+//     anchored by chapter index, split index and character offset;
+//   - `*.po`: one per book, the last reading position as
+//     `<stamp>*<chapter>@<split>#<offset>:<percent>%`.
+//
+// Moon+ positions can't be turned into CFIs without the book's DOM, so this
+// module only builds a preview: per-book highlights/bookmarks/position plus
+// ranked library matches (`library_match`). The frontend shows it for
+// review, then locates each highlight's `text` within its chapter and
+// creates the notes for the books the user confirmed.
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tauri::AppHandle;
+use zip::ZipArchive;
+
+use crate::library_match::{find_matches, BookMatch, LibraryBookRef};
+use crate::parser_common::compute_partial_md5;
+use crate::transfer_file::ensure_path_allowed;
+
+const NAMES_LIST: &str = "_names.list";
+const DATABASE_NAME: &str = "mrbooks.db";
+const POSITION_EXT: &str = ".po";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MoonPosition {
+    pub chapter: u32,
+    pub split_index: u32,
+    pub offset: u64,
+    /// 0–100.
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoonAnnotation {
+    pub chapter: u32,
+    pub split_index: u32,
+    pub offset: u64,
+    pub length: u64,
+    /// Highlighted text; for bookmarks, the label Moon+ gave them.
+    pub text: String,
+    pub note: String,
+    /// `#rrggbb`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// `highlight`, `underline` or `squiggly` (Moon+'s strikethrough);
+    /// mirrors `HighlightStyle`.
+    pub style: String,
+    /// Milliseconds since the epoch.
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoonBook {
+    /// On-device path Moon+ recorded for the book.
+    pub file_name: String,
+    pub title: String,
+    pub author: String,
+    pub highlights: Vec<MoonAnnotation>,
+    pub bookmarks: Vec<MoonAnnotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<MoonPosition>,
+    pub matches: Vec<BookMatch>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoonReaderPreview {
+    pub books: Vec<MoonBook>,
+}
+
+/// Parse a `.po` file: `1703471*12@2#20432:64.3%`. The leading stamp is
+/// optional in older versions.
+fn parse_position(content: &str) -> Option<MoonPosition> {
+    let rest = content.trim();
+    let rest = rest.split_once('*').map_or(rest, |(_, r)| r);
+    let (chapter, rest) = rest.split_once('@')?;
+    let (split, rest) = rest.split_once('#')?;
+    let (offset, percent) = rest.split_once(':').unwrap_or((rest, "0"));
+    Some(MoonPosition {
+        chapter: chapter.trim().parse().ok()?,
+        split_index: split.trim().parse().ok()?,
+        offset: offset.trim().parse().ok()?,
+        percent: percent.trim().trim_end_matches('%').parse().unwrap_or(0.0),
+    })
+}
+
+/// Moon+ stores colours as signed 32-bit ARGB.
+fn argb_to_hex(argb: i64) -> Option<String> {
+    (argb != 0).then(|| format!("#{:06x}", (argb as u32) & 0x00ff_ffff))
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Map each archive entry to the original path it was backed up from.
+/// Backups without `_names.list` (or plain zips) keep real names.
+fn entry_paths(zip: &mut ZipArchive<File>) -> Result<Vec<(String, String)>, String> {
+    let entries: Vec<String> = zip.file_names().map(str::to_string).collect();
+    let names: Option<Vec<String>> = match zip.by_name(NAMES_LIST) {
+        Ok(mut list) => {
+            let mut text = String::new();
+            list.read_to_string(&mut text)
+                .map_err(|e| format!("read {NAMES_LIST}: {e}"))?;
+            Some(text.lines().map(|l| l.trim().to_string()).collect())
+        }
+        Err(_) => None,
+    };
+    let Some(names) = names else {
+        return Ok(entries.into_iter().map(|e| (e.clone(), e)).collect());
+    };
+    // Numbering is 1-based in current versions; tolerate 0-based ones.
+    let first = if entries.iter().any(|e| e == "0.tag") {
+        0
+    } else {
+        1
+    };
+    Ok(names
+        .into_iter()
+        .enumerate()
+        .filter(|(_, name)| !name.is_empty())
+        .filter_map(|(i, name)| {
+            let entry = format!("{}.tag", i + first);
+            entries.contains(&entry).then_some((entry, name))
+        })
+        .collect())
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, entry: &str) -> Result<Vec<u8>, String> {
+    let mut file = zip
+        .by_name(entry)
+        .map_err(|e| format!("entry {entry}: {e}"))?;
+    let mut buf = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut buf)
+        .map_err(|e| format!("read {entry}: {e}"))?;
+    Ok(buf)
+}
+
+#[derive(Default)]
+struct BookAcc {
+    file_name: String,
+    title: String,
+    author: String,
+    highlights: Vec<MoonAnnotation>,
+    bookmarks: Vec<MoonAnnotation>,
+}
+
+fn read_notes(db: &Path, books: &mut BTreeMap<String, BookAcc>) -> Result<(), String> {
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("open {DATABASE_NAME}: {e}"))?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT book, filename, lastChapter, lastSplitIndex, lastPosition, highlightLength, \
+             highlightColor, time, bookmark, note, original, underline, strikethrough \
+             FROM notes ORDER BY lastChapter, lastSplitIndex, lastPosition",
+        )
+        .map_err(|e| format!("read notes: {e}"))?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let get_str = |i: usize| -> String {
+            row.get::<_, Option<String>>(i)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let get_int =
+            |i: usize| -> i64 { row.get::<_, Option<i64>>(i).ok().flatten().unwrap_or(0) };
+        let file_name = get_str(1);
+        if file_name.is_empty() {
+            continue;
+        }
+        let text = get_str(10);
+        let length = get_int(5).max(0) as u64;
+        let style = if get_int(12) != 0 {
+            "squiggly"
+        } else if get_int(11) != 0 {
+            "underline"
+        } else {
+            "highlight"
+        };
+        let annotation = MoonAnnotation {
+            chapter: get_int(2).max(0) as u32,
+            split_index: get_int(3).max(0) as u32,
+            offset: get_int(4).max(0) as u64,
+            length,
+            color: argb_to_hex(get_int(6)),
+            created_at: get_int(7),
+            note: get_str(9),
+            style: style.to_string(),
+            text: if text.is_empty() { get_str(8) } else { text },
+        };
+        let acc = books.entry(file_name.to_lowercase()).or_default();
+        if acc.file_name.is_empty() {
+            acc.file_name = file_name;
+            acc.title = get_str(0);
+        }
+        if length > 0 || !get_str(10).is_empty() {
+            acc.highlights.push(annotation);
+        } else {
+            acc.bookmarks.push(annotation);
+        }
+    }
+    // `books` carries authors in recent versions only.
+    if let Ok(mut stmt) = conn.prepare("SELECT lowerFilename, author FROM books") {
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+            ))
+        });
+        for (lower, author) in rows.into_iter().flatten().flatten() {
+            if let (Some(lower), Some(author)) = (lower, author) {
+                if let Some(acc) = books.get_mut(&lower) {
+                    acc.author = author;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn title_from_path(path: &str) -> String {
+    let name = base_name(path);
+    name.rsplit_once('.')
+        .map_or(name, |(stem, _)| stem)
+        .to_string()
+}
+
+fn preview_backup_sync(
+    backup: &Path,
+    library: &[LibraryBookRef],
+    local_hash: &dyn Fn(&str) -> Option<String>,
+) -> Result<MoonReaderPreview, String> {
+    let file = File::open(backup).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("not a Moon+ backup: {e}"))?;
+    let paths = entry_paths(&mut zip)?;
+
+    let mut books: BTreeMap<String, BookAcc> = BTreeMap::new();
+    let db_entry = paths
+        .iter()
+        .find(|(_, path)| base_name(path) == DATABASE_NAME)
+        .map(|(entry, _)| entry.clone())
+        .ok_or_else(|| format!("no {DATABASE_NAME} in backup"))?;
+    let db_path = std::env::temp_dir().join(format!(
+        "readest-moonreader-{}-{}.db",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    ));
+    std::fs::write(&db_path, read_entry(&mut zip, &db_entry)?)
+        .map_err(|e| format!("extract {DATABASE_NAME}: {e}"))?;
+    let notes = read_notes(&db_path, &mut books);
+    let _ = std::fs::remove_file(&db_path);
+    notes?;
+
+    let mut positions: BTreeMap<String, MoonPosition> = BTreeMap::new();
+    for (entry, path) in &paths {
+        let name = base_name(path);
+        let Some(book_name) = name.strip_suffix(POSITION_EXT) else {
+            continue;
+        };
+        let content = read_entry(&mut zip, entry)?;
+        if let Some(position) = parse_position(&String::from_utf8_lossy(&content)) {
+            positions.insert(book_name.to_lowercase(), position);
+        }
+    }
+
+    // Books with only a position (read, never annotated) are worth
+    // importing too; they're keyed by base name rather than full path.
+    let mut by_base: BTreeMap<String, BookAcc> = BTreeMap::new();
+    for (_, acc) in books {
+        by_base.insert(base_name(&acc.file_name).to_lowercase(), acc);
+    }
+    for name in positions.keys() {
+        by_base.entry(name.clone()).or_insert_with(|| BookAcc {
+            file_name: name.clone(),
+            ..Default::default()
+        });
+    }
+
+    let books = by_base
+        .into_iter()
+        .map(|(key, acc)| {
+            let title = if acc.title.is_empty() {
+                title_from_path(&acc.file_name)
+            } else {
+                acc.title
+            };
+            let hash = local_hash(&acc.file_name);
+            let matches = find_matches(
+                library,
+                &title,
+                &acc.author,
+                Some(&acc.file_name),
+                hash.as_deref(),
+            );
+            MoonBook {
+                position: positions.get(&key).cloned(),
+                file_name: acc.file_name,
+                title,
+                author: acc.author,
+                highlights: acc.highlights,
+                bookmarks: acc.bookmarks,
+                matches,
+            }
+        })
+        .collect();
+    Ok(MoonReaderPreview { books })
+}
+
+/// Read a Moon+ Reader backup and propose a library match for each book.
+/// Nothing is written; see the module docs for how the result is applied.
+#[tauri::command]
+pub async fn preview_moonreader_backup(
+    app: AppHandle,
+    backup_path: String,
+    library: Vec<LibraryBookRef>,
+) -> Result<MoonReaderPreview, String> {
+    ensure_path_allowed(&app, &backup_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        // On Android the backup usually comes from this very device, so the
+        // recorded book paths may still exist; hashing them gives an exact
+        // match. Paths outside the granted scopes are left alone.
+        let local_hash = |path: &str| {
+            ensure_path_allowed(&app, path).ok()?;
+            compute_partial_md5(Path::new(path)).ok()
+        };
+        preview_backup_sync(Path::new(&backup_path), &library, &local_hash)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn parses_positions() {
+        assert_eq!(
+            parse_position("1703471*12@2#20432:64.3%"),
+            Some(MoonPosition {
+                chapter: 12,
+                split_index: 2,
+                offset: 20432,
+                percent: 64.3,
+            })
+        );
+        assert_eq!(parse_position("3@0#15").map(|p| p.offset), Some(15));
+        assert_eq!(parse_position("garbage"), None);
+    }
+
+    #[test]
+    fn converts_argb_colors() {
+        assert_eq!(argb_to_hex(-256).as_deref(), Some("#ffff00"));
+        assert_eq!(argb_to_hex(0), None);
+    }
+
+    fn write_backup(path: &Path, db: &Path) {
+        let mut buf = Vec::new();
+        {
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            let options = zip::write::SimpleFileOptions::default();
+            w.start_file(NAMES_LIST, options).unwrap();
+            w.write_all(
+                b"/data/data/com.flyersoft.moonreaderp/databases/mrbooks.db\n\
+                  /sdcard/Books/MoonReader/Dune.epub.po\n",
+            )
+            .unwrap();
+            w.start_file("1.tag", options).unwrap();
+            w.write_all(&std::fs::read(db).unwrap()).unwrap();
+            w.start_file("2.tag", options).unwrap();
+            w.write_all(b"1*3@0#120:25.5%").unwrap();
+            w.finish().unwrap();
+        }
+        std::fs::write(path, buf).unwrap();
+    }
+
+    #[test]
+    fn previews_backup_with_matches() {
+        let dir = std::env::temp_dir().join(format!("readest-moon-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("mrbooks.db");
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (book TEXT, filename TEXT, lastChapter INTEGER,
+                lastSplitIndex INTEGER, lastPosition INTEGER, highlightLength INTEGER,
+                highlightColor INTEGER, time INTEGER, bookmark TEXT, note TEXT,
+                original TEXT, underline INTEGER, strikethrough INTEGER);
+             INSERT INTO notes VALUES ('Dune', '/sdcard/Books/Dune.epub', 3, 0, 100, 12,
+                -256, 1700000000000, '', 'fear', 'Fear is the mind-killer', 0, 0);
+             INSERT INTO notes VALUES ('Dune', '/sdcard/Books/Dune.epub', 5, 0, 0, 0,
+                0, 1700000001000, 'Chapter 5', '', '', 0, 0);",
+        )
+        .unwrap();
+        drop(conn);
+        let backup = dir.join("backup.mrpro");
+        write_backup(&backup, &db);
+
+        let library = vec![LibraryBookRef {
+            hash: "h1".into(),
+            title: "Dune".into(),
+            author: "Frank Herbert".into(),
+            file_name: None,
+        }];
+        let preview = preview_backup_sync(&backup, &library, &|_| None).unwrap();
+        assert_eq!(preview.books.len(), 1);
+        let book = &preview.books[0];
+        assert_eq!(book.title, "Dune");
+        assert_eq!(book.highlights.len(), 1);
+        assert_eq!(book.highlights[0].text, "Fear is the mind-killer");
+        assert_eq!(book.highlights[0].color.as_deref(), Some("#ffff00"));
+        assert_eq!(book.bookmarks.len(), 1);
+        assert_eq!(book.position.as_ref().map(|p| p.chapter), Some(3));
+        assert_eq!(book.matches[0].hash, "h1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
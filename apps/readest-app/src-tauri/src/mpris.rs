@@ -0,0 +1,537 @@
+//! MPRIS media controls on Linux.
+//!
+//! Read-aloud plays through WebAudio with no media element behind it, so
+//! WebKitGTK has nothing to publish to the desktop's media panel, and media
+//! keys go nowhere. While a session is active this serves
+//! `org.mpris.MediaPlayer2` on the session bus. The webview feeds it
+//! metadata and playback state through `mpris_update_metadata` and
+//! `mpris_update_state`. Requests from the panel and the media keys go back
+//! to it as `mpris-action` events, named after the Media Session actions the
+//! TTS media bridge already handles.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use base64::Engine;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, Value};
+use zbus::{connection, fdo, interface, Connection};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.readest";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+// There is only ever the one track: whatever is being read.
+const TRACK_ID: &str = "/com/bilingify/readest/CurrentTrack";
+const ACTION_EVENT: &str = "mpris-action";
+const COVER_PREFIX: &str = "mpris-cover-";
+/// A reported position this far off the one the clock ran to is a jump
+/// (a seek or a skip), announced with `Seeked`.
+const SEEK_TOLERANCE_MS: u64 = 1500;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MprisMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    /// A data URL (written out to the cache, since panels load covers by
+    /// URL) or a URL the panel can load itself.
+    artwork: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MprisPlaybackState {
+    playing: bool,
+    /// Milliseconds, as with the native TTS media session.
+    position: Option<u64>,
+    duration: Option<u64>,
+    #[serde(default)]
+    seekable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MprisAction {
+    action: &'static str,
+    /// Milliseconds, for `seekto`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<u64>,
+}
+
+#[derive(Default)]
+struct Track {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    art_url: Option<String>,
+}
+
+/// Where the reader is. Panels read `Position` without being told it
+/// changed, so it runs on from the last report while playing.
+#[derive(Default)]
+struct Clock {
+    playing: bool,
+    position_ms: u64,
+    reported_at: Option<Instant>,
+    duration_ms: Option<u64>,
+    seekable: bool,
+}
+
+impl Clock {
+    fn position_at(&self, now: Instant) -> u64 {
+        let elapsed = match (self.playing, self.reported_at) {
+            (true, Some(at)) => now.saturating_duration_since(at).as_millis() as u64,
+            _ => 0,
+        };
+        let position = self.position_ms + elapsed;
+        self.duration_ms
+            .map_or(position, |duration| position.min(duration))
+    }
+
+    /// Take a new report; true when the position jumped rather than ran on.
+    fn update(&mut self, state: &MprisPlaybackState, now: Instant) -> bool {
+        let expected = self.position_at(now);
+        let position = state.position.unwrap_or(expected);
+        let jumped = self.reported_at.is_some() && position.abs_diff(expected) > SEEK_TOLERANCE_MS;
+        *self = Clock {
+            playing: state.playing,
+            position_ms: position,
+            reported_at: Some(now),
+            duration_ms: state.duration.filter(|&duration| duration > 0),
+            seekable: state.seekable,
+        };
+        jumped
+    }
+}
+
+fn micros(ms: u64) -> i64 {
+    (ms as i64).saturating_mul(1000)
+}
+
+/// `org.mpris.MediaPlayer2`: the application itself.
+struct Root {
+    app: AppHandle,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {
+        if let Some(window) = self.app.get_webview_window("main") {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "Readest".into()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`: transport and what is playing.
+struct Player {
+    app: AppHandle,
+    track: Track,
+    clock: Clock,
+}
+
+impl Player {
+    fn send(&self, action: &'static str, position: Option<u64>) {
+        let _ = self
+            .app
+            .emit(ACTION_EVENT, MprisAction { action, position });
+    }
+
+    fn seek_to(&self, position_us: i64) {
+        if !self.clock.seekable {
+            return;
+        }
+        let position = (position_us.max(0) / 1000) as u64;
+        let position = self
+            .clock
+            .duration_ms
+            .map_or(position, |duration| position.min(duration));
+        self.send("seekto", Some(position));
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send("nexttrack", None);
+    }
+
+    fn previous(&self) {
+        self.send("previoustrack", None);
+    }
+
+    fn pause(&self) {
+        self.send("pause", None);
+    }
+
+    fn play_pause(&self) {
+        self.send("toggle", None);
+    }
+
+    fn stop(&self) {
+        self.send("stop", None);
+    }
+
+    fn play(&self) {
+        self.send("play", None);
+    }
+
+    fn seek(&self, offset: i64) {
+        let position = micros(self.clock.position_at(Instant::now()));
+        self.seek_to(position.saturating_add(offset));
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        // Requests for a track since replaced are to be ignored.
+        if track_id.as_str() == TRACK_ID && position >= 0 {
+            self.seek_to(position);
+        }
+    }
+
+    fn open_uri(&self, _uri: String) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(
+            "Readest opens books itself".into(),
+        ))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        if self.clock.playing {
+            "Playing"
+        } else {
+            "Paused"
+        }
+        .into()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<&'static str, Value<'static>> {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "mpris:trackid",
+            Value::from(ObjectPath::from_static_str_unchecked(TRACK_ID)),
+        );
+        if let Some(duration) = self.clock.duration_ms {
+            metadata.insert("mpris:length", Value::from(micros(duration)));
+        }
+        if let Some(title) = &self.track.title {
+            metadata.insert("xesam:title", Value::from(title.clone()));
+        }
+        if let Some(artist) = &self.track.artist {
+            metadata.insert("xesam:artist", Value::from(vec![artist.clone()]));
+        }
+        if let Some(album) = &self.track.album {
+            metadata.insert("xesam:album", Value::from(album.clone()));
+        }
+        if let Some(art_url) = &self.track.art_url {
+            metadata.insert("mpris:artUrl", Value::from(art_url.clone()));
+        }
+        metadata
+    }
+
+    // Changes without a signal, as the spec has it; panels poll it.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(self.clock.position_at(Instant::now()))
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.clock.seekable && self.clock.duration_ms.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// The bus connection, held while a read-aloud session is active; dropping
+/// it takes Readest off the media panel.
+#[derive(Default)]
+pub struct Mpris(Mutex<Option<Connection>>);
+
+/// Splits `data:image/png;base64,...` into an extension and its bytes.
+fn decode_data_url(url: &str) -> Option<(&str, Vec<u8>)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    let extension = match mime {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => return None,
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .ok()?;
+    Some((extension, bytes))
+}
+
+/// A URL the panel can load the cover from. Data URLs are written to the
+/// cache under a name that changes with the cover, as panels cache covers
+/// by URL; the one written before is removed.
+fn cover_url(app: &AppHandle, artwork: &str) -> Option<String> {
+    if !artwork.starts_with("data:") {
+        return (artwork.starts_with("file://") || artwork.starts_with("http"))
+            .then(|| artwork.to_string());
+    }
+    let (extension, bytes) = decode_data_url(artwork)?;
+    let dir = app.path().app_cache_dir().ok()?;
+    let name = format!("{COVER_PREFIX}{:x}.{extension}", Md5::digest(&bytes));
+    let path = dir.join(&name);
+    if !path.exists() {
+        std::fs::create_dir_all(&dir).ok()?;
+        remove_covers(app);
+        std::fs::write(&path, &bytes).ok()?;
+    }
+    Some(format!("file://{}", path.display()))
+}
+
+fn remove_covers(app: &AppHandle) {
+    let Ok(dir) = app.path().app_cache_dir() else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(COVER_PREFIX)
+        {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Put Readest on the media panel, or take it off.
+#[tauri::command]
+pub async fn mpris_set_active(
+    app: AppHandle,
+    mpris: State<'_, Mpris>,
+    active: bool,
+) -> Result<(), String> {
+    let mut connection = mpris.0.lock().await;
+    if !active {
+        *connection = None;
+        remove_covers(&app);
+        return Ok(());
+    }
+    if connection.is_some() {
+        return Ok(());
+    }
+    let player = Player {
+        app: app.clone(),
+        track: Track::default(),
+        clock: Clock::default(),
+    };
+    let built = connection::Builder::session()
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, Root { app: app.clone() }))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, player))
+        .map_err(|e| format!("Failed to set up MPRIS: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to connect to the session bus: {e}"))?;
+    *connection = Some(built);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn mpris_update_metadata(
+    app: AppHandle,
+    mpris: State<'_, Mpris>,
+    metadata: MprisMetadata,
+) -> Result<(), String> {
+    let connection = mpris.0.lock().await;
+    let Some(connection) = connection.as_ref() else {
+        return Ok(());
+    };
+    let art_url = metadata
+        .artwork
+        .as_deref()
+        .and_then(|artwork| cover_url(&app, artwork));
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await
+        .map_err(|e| e.to_string())?;
+    player.get_mut().await.track = Track {
+        title: metadata.title.filter(|title| !title.is_empty()),
+        artist: metadata.artist.filter(|artist| !artist.is_empty()),
+        album: metadata.album.filter(|album| !album.is_empty()),
+        art_url,
+    };
+    player
+        .get()
+        .await
+        .metadata_changed(player.signal_emitter())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn mpris_update_state(
+    mpris: State<'_, Mpris>,
+    state: MprisPlaybackState,
+) -> Result<(), String> {
+    let connection = mpris.0.lock().await;
+    let Some(connection) = connection.as_ref() else {
+        return Ok(());
+    };
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await
+        .map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    let (jumped, duration_changed) = {
+        let mut player = player.get_mut().await;
+        let duration = player.clock.duration_ms;
+        let jumped = player.clock.update(&state, now);
+        (jumped, player.clock.duration_ms != duration)
+    };
+    let emitter = player.signal_emitter();
+    let player = player.get().await;
+    let result = async {
+        player.playback_status_changed(emitter).await?;
+        player.can_seek_changed(emitter).await?;
+        if duration_changed {
+            player.metadata_changed(emitter).await?;
+        }
+        if jumped {
+            Player::seeked(emitter, micros(player.clock.position_at(now))).await?;
+        }
+        Ok::<(), zbus::Error>(())
+    };
+    result.await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn report(playing: bool, position: u64) -> MprisPlaybackState {
+        MprisPlaybackState {
+            playing,
+            position: Some(position),
+            duration: Some(60_000),
+            seekable: true,
+        }
+    }
+
+    #[test]
+    fn position_runs_on_while_playing() {
+        let start = Instant::now();
+        let mut clock = Clock::default();
+        assert!(!clock.update(&report(true, 10_000), start));
+        assert_eq!(clock.position_at(start + Duration::from_secs(5)), 15_000);
+        assert_eq!(clock.position_at(start + Duration::from_secs(500)), 60_000);
+
+        assert!(!clock.update(&report(false, 15_000), start + Duration::from_secs(5)));
+        assert_eq!(clock.position_at(start + Duration::from_secs(50)), 15_000);
+    }
+
+    #[test]
+    fn a_jump_is_a_seek() {
+        let start = Instant::now();
+        let mut clock = Clock::default();
+        clock.update(&report(true, 10_000), start);
+        // Sentence marks land a little off the clock.
+        assert!(!clock.update(&report(true, 11_200), start + Duration::from_secs(2)));
+        assert!(clock.update(&report(true, 40_000), start + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn decodes_cover_data_urls() {
+        let (extension, bytes) = decode_data_url("data:image/jpeg;base64,AAEC").unwrap();
+        assert_eq!(extension, "jpg");
+        assert_eq!(bytes, [0, 1, 2]);
+        assert!(decode_data_url("data:text/plain;base64,AAEC").is_none());
+        assert!(decode_data_url("data:image/png,raw").is_none());
+    }
+}
@@ -0,0 +1,236 @@
+//! Client certificates for servers behind mutual TLS.
+//!
+//! A WebDAV, kosync or custom sync server fronted by an mTLS proxy rejects
+//! connections that don't present a certificate it issued. The user imports
+//! one per server as a PKCS#12 (`.p12` / `.pfx`) bundle, keyed by the
+//! server's host like the [`super::trust`] entries, and
+//! [`super::client_builder_for`] presents it whenever a client is built for
+//! that host. Other hosts never see it.
+//!
+//! The bundle is copied to `client-certs/` in the app config dir and its
+//! password goes to the keychain; `client-certs.json` lists what's
+//! installed. Bundles are decrypted once at startup (or on import) and kept
+//! in memory.
+
+use base64::Engine;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Manager};
+
+use super::trust::{fingerprint, normalize_host};
+use crate::transfer_file::ensure_path_allowed;
+
+const INDEX_FILENAME: &str = "client-certs.json";
+const CERTS_DIR: &str = "client-certs";
+
+/// A decrypted client certificate chain and its key.
+#[derive(Debug)]
+pub struct ClientIdentity {
+    pub(super) certs: Vec<CertificateDer<'static>>,
+    pub(super) key: PrivateKeyDer<'static>,
+}
+
+impl ClientIdentity {
+    /// The key and chain as one PEM, the form `reqwest::Identity` takes.
+    pub(super) fn to_pem(&self) -> String {
+        let mut pem = pem_block("PRIVATE KEY", self.key.secret_der());
+        for cert in &self.certs {
+            pem.push_str(&pem_block("CERTIFICATE", cert.as_ref()));
+        }
+        pem
+    }
+}
+
+/// An installed certificate, as listed in `client-certs.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientCertificate {
+    pub host: String,
+    /// The bundle's file name under `client-certs/`.
+    pub file: String,
+    /// SHA-256 of the leaf certificate, lowercase hex.
+    pub fingerprint: String,
+}
+
+static IDENTITIES: RwLock<Option<HashMap<String, Arc<ClientIdentity>>>> = RwLock::new(None);
+
+fn pem_block(label: &str, der: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {label}-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {label}-----\n"));
+    pem
+}
+
+fn parse_pkcs12(data: &[u8], password: &str) -> Result<ClientIdentity, String> {
+    let store = p12_keystore::KeyStore::from_pkcs12(data, password)
+        .map_err(|e| format!("could not open the PKCS#12 bundle: {e}"))?;
+    let (_, chain) = store
+        .private_key_chain()
+        .ok_or("the PKCS#12 bundle holds no private key")?;
+    let certs: Vec<CertificateDer<'static>> = chain
+        .chain()
+        .iter()
+        .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+        .collect();
+    if certs.is_empty() {
+        return Err("the PKCS#12 bundle holds no certificate for its key".into());
+    }
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(chain.key().to_vec()));
+    Ok(ClientIdentity { certs, key })
+}
+
+fn password_key(host: &str) -> String {
+    format!("client_cert.{host}")
+}
+
+/// The identity to present to the host of `url`, if one is installed.
+pub(super) fn for_url(url: &str) -> Option<Arc<ClientIdentity>> {
+    let identities = IDENTITIES.read().unwrap();
+    let identities = identities.as_ref()?;
+    let host = normalize_host(url).ok()?;
+    identities.get(&host).cloned()
+}
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|_| "no config directory".to_string())
+}
+
+fn read_index(app: &AppHandle) -> Vec<ClientCertificate> {
+    config_dir(app)
+        .ok()
+        .and_then(|dir| std::fs::read_to_string(dir.join(INDEX_FILENAME)).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(app: &AppHandle, index: &[ClientCertificate]) -> Result<(), String> {
+    let dir = config_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(INDEX_FILENAME), json).map_err(|e| e.to_string())
+}
+
+fn open(app: &AppHandle, entry: &ClientCertificate) -> Result<ClientIdentity, String> {
+    let data = std::fs::read(config_dir(app)?.join(CERTS_DIR).join(&entry.file))
+        .map_err(|e| format!("could not read {}: {e}", entry.file))?;
+    let password = crate::sync::load_secret(app, &password_key(&entry.host)).unwrap_or_default();
+    parse_pkcs12(&data, &password)
+}
+
+fn install(host: &str, identity: Option<ClientIdentity>) {
+    let mut identities = IDENTITIES.write().unwrap();
+    let identities = identities.get_or_insert_with(HashMap::new);
+    match identity {
+        Some(identity) => identities.insert(host.to_string(), Arc::new(identity)),
+        None => identities.remove(host),
+    };
+}
+
+/// Decrypt the installed certificates; called once during setup.
+pub fn load(app: &AppHandle) {
+    for entry in read_index(app) {
+        match open(app, &entry) {
+            Ok(identity) => install(&entry.host, Some(identity)),
+            Err(e) => log::warn!("Ignoring the client certificate for {}: {e}", entry.host),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_client_certificates(app: AppHandle) -> Vec<ClientCertificate> {
+    read_index(&app)
+}
+
+/// Install the PKCS#12 bundle at `pkcs12_path` as the client certificate
+/// for `host` (a bare host or a server URL), replacing any earlier one.
+#[tauri::command]
+pub fn import_client_certificate(
+    app: AppHandle,
+    host: String,
+    pkcs12_path: String,
+    password: String,
+) -> Result<ClientCertificate, String> {
+    ensure_path_allowed(&app, &pkcs12_path).map_err(|e| e.to_string())?;
+    let host = normalize_host(&host)?;
+    let data =
+        std::fs::read(&pkcs12_path).map_err(|e| format!("could not read the bundle: {e}"))?;
+    let identity = parse_pkcs12(&data, &password)?;
+    // Fail now rather than on the first request if rustls can't use it.
+    super::trust::tls_config_with_identity(&identity)?;
+
+    let entry = ClientCertificate {
+        fingerprint: fingerprint(identity.certs[0].as_ref()),
+        file: format!("{}.p12", fingerprint(host.as_bytes())),
+        host,
+    };
+    let dir = config_dir(&app)?.join(CERTS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(&entry.file), &data).map_err(|e| e.to_string())?;
+    crate::sync::save_secret(&app, &password_key(&entry.host), &password)
+        .map_err(|e| e.to_string())?;
+
+    let mut index = read_index(&app);
+    index.retain(|e| e.host != entry.host);
+    index.push(entry.clone());
+    write_index(&app, &index)?;
+    install(&entry.host, Some(identity));
+    Ok(entry)
+}
+
+#[tauri::command]
+pub fn remove_client_certificate(app: AppHandle, host: String) -> Result<(), String> {
+    let host = normalize_host(&host)?;
+    let mut index = read_index(&app);
+    let Some(position) = index.iter().position(|e| e.host == host) else {
+        return Ok(());
+    };
+    let entry = index.remove(position);
+    write_index(&app, &index)?;
+    install(&host, None);
+    let _ = std::fs::remove_file(config_dir(&app)?.join(CERTS_DIR).join(&entry.file));
+    crate::sync::save_secret(&app, &password_key(&host), "").map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pem_blocks_wrap_at_64_columns() {
+        let pem = pem_block("CERTIFICATE", &[0u8; 100]);
+        let lines: Vec<&str> = pem.lines().collect();
+        assert_eq!(lines[0], "-----BEGIN CERTIFICATE-----");
+        assert_eq!(lines[1].len(), 64);
+        assert_eq!(lines[2].len(), 136 - 64);
+        assert_eq!(lines[3], "-----END CERTIFICATE-----");
+    }
+
+    #[test]
+    fn garbage_is_not_a_bundle() {
+        assert!(parse_pkcs12(b"not a pkcs12 file", "secret").is_err());
+    }
+
+    #[test]
+    fn identities_are_matched_by_host() {
+        install(
+            "dav.example.org",
+            Some(ClientIdentity {
+                certs: vec![CertificateDer::from(vec![1, 2, 3])],
+                key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(vec![4, 5, 6])),
+            }),
+        );
+        assert!(for_url("https://DAV.example.org:8443/remote.php/dav").is_some());
+        assert!(for_url("https://other.example.org/").is_none());
+        install("dav.example.org", None);
+        assert!(for_url("https://dav.example.org/").is_none());
+    }
+}
@@ -0,0 +1,473 @@
+//! Per-host certificate trust for self-hosted servers.
+//!
+//! A Nextcloud, WebDAV or kosync server behind a private CA or a
+//! self-signed certificate fails the normal web PKI checks. Rather than a
+//! switch that turns verification off, each [`TrustedHost`] opts one host
+//! in to either the roots of a private CA (the chain and name are still
+//! verified, against the public roots plus those), a SHA-256 pin of the
+//! server's own certificate, or, as a last resort, any certificate at all.
+//! Every other host is checked as before.
+//!
+//! [`probe_host_certificate`] reports what a server presents, so the UI can
+//! show the fingerprint for the user to confirm before pinning it. Entries
+//! live in `trusted-hosts.json` in the app config dir; while there are any,
+//! [`super::client_builder`] installs a [`HostVerifier`] on every client.
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Manager};
+
+use super::identity::ClientIdentity;
+
+const TRUST_FILENAME: &str = "trusted-hosts.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum HostTrust {
+    /// Root certificates of a private CA, PEM.
+    Ca { pem: String },
+    /// The server's own certificate, SHA-256 hex. The certificate may be
+    /// self-signed or expired; it just has to be this one.
+    Fingerprint { sha256: String },
+    /// Whatever certificate the server presents. The connection is still
+    /// encrypted but no longer authenticated; for servers whose certificate
+    /// changes too often to pin.
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedHost {
+    pub host: String,
+    #[serde(flatten)]
+    pub trust: HostTrust,
+}
+
+/// What a server presented to [`probe_host_certificate`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub host: String,
+    pub fingerprint: String,
+    /// The certificate already passes the normal checks; nothing to trust.
+    pub trusted: bool,
+}
+
+/// SHA-256 of a DER certificate, lowercase hex.
+pub(crate) fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The host name a user typed or pasted: a bare host or a URL.
+pub(super) fn normalize_host(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    let host = if input.contains("://") {
+        reqwest::Url::parse(input)
+            .map_err(|e| format!("invalid URL: {e}"))?
+            .host_str()
+            .ok_or("the URL has no host")?
+            .to_string()
+    } else {
+        input.to_string()
+    };
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if host.is_empty() || host.contains(['/', ' ']) {
+        return Err(format!("invalid host {input:?}"));
+    }
+    Ok(host)
+}
+
+/// Accepts `AB:CD:…` as browsers show it as well as plain hex.
+fn normalize_fingerprint(input: &str) -> Result<String, String> {
+    let hex: String = input
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' '))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("a SHA-256 fingerprint is 64 hex digits".into());
+    }
+    Ok(hex)
+}
+
+fn parse_pem(pem: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_slice_iter(pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid PEM: {e:?}"))?;
+    if certs.is_empty() {
+        return Err("no certificate in the PEM".into());
+    }
+    Ok(certs)
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn public_roots() -> RootCertStore {
+    RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    }
+}
+
+fn webpki(
+    roots: RootCertStore,
+    provider: &Arc<CryptoProvider>,
+) -> Result<Arc<WebPkiServerVerifier>, String> {
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| format!("TLS setup failed: {e}"))
+}
+
+#[derive(Debug)]
+enum Check {
+    Roots(Arc<WebPkiServerVerifier>),
+    Pin(String),
+    Any,
+}
+
+/// The normal web PKI checks, except for the hosts the user opted in.
+#[derive(Debug)]
+struct HostVerifier {
+    default: Arc<WebPkiServerVerifier>,
+    hosts: HashMap<String, Check>,
+}
+
+impl HostVerifier {
+    fn new(trusted: &[TrustedHost], provider: &Arc<CryptoProvider>) -> Result<Self, String> {
+        let mut hosts = HashMap::new();
+        for entry in trusted {
+            let check = match &entry.trust {
+                HostTrust::Ca { pem } => {
+                    let mut roots = public_roots();
+                    for cert in parse_pem(pem)? {
+                        roots
+                            .add(cert)
+                            .map_err(|e| format!("unusable CA certificate: {e}"))?;
+                    }
+                    Check::Roots(webpki(roots, provider)?)
+                }
+                HostTrust::Fingerprint { sha256 } => Check::Pin(sha256.clone()),
+                HostTrust::Any => Check::Any,
+            };
+            hosts.insert(entry.host.clone(), check);
+        }
+        Ok(Self {
+            default: webpki(public_roots(), provider)?,
+            hosts,
+        })
+    }
+}
+
+impl ServerCertVerifier for HostVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let host = server_name.to_str().to_ascii_lowercase();
+        let verifier = match self.hosts.get(&host) {
+            Some(Check::Pin(pin)) => {
+                return if fingerprint(end_entity.as_ref()) == *pin {
+                    Ok(ServerCertVerified::assertion())
+                } else {
+                    Err(rustls::Error::General(format!(
+                        "certificate of {host} does not match the trusted fingerprint"
+                    )))
+                };
+            }
+            Some(Check::Any) => return Ok(ServerCertVerified::assertion()),
+            Some(Check::Roots(verifier)) => verifier,
+            None => &self.default,
+        };
+        verifier.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.default.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.default.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.default.supported_verify_schemes()
+    }
+}
+
+/// A client config with `verifier`, presenting `identity` when the server
+/// asks for a client certificate.
+fn client_config(
+    verifier: Arc<dyn ServerCertVerifier>,
+    identity: Option<&ClientIdentity>,
+) -> Result<rustls::ClientConfig, String> {
+    let builder = rustls::ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS setup failed: {e}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier);
+    match identity {
+        Some(identity) => builder
+            .with_client_auth_cert(identity.certs.clone(), identity.key.clone_key())
+            .map_err(|e| format!("unusable client certificate: {e}")),
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// The verifier clients use while any host is trusted.
+static ACTIVE: RwLock<Option<Arc<HostVerifier>>> = RwLock::new(None);
+
+pub(super) fn tls_config() -> Option<rustls::ClientConfig> {
+    let verifier = ACTIVE.read().unwrap().clone()?;
+    client_config(verifier, None)
+        .map_err(|e| log::warn!("Ignoring the trusted hosts: {e}"))
+        .ok()
+}
+
+/// The TLS config for a client presenting `identity`, still honouring the
+/// trusted hosts.
+pub(super) fn tls_config_with_identity(
+    identity: &ClientIdentity,
+) -> Result<rustls::ClientConfig, String> {
+    let verifier: Arc<dyn ServerCertVerifier> = match ACTIVE.read().unwrap().clone() {
+        Some(verifier) => verifier,
+        None => webpki(public_roots(), &provider())?,
+    };
+    client_config(verifier, Some(identity))
+}
+
+fn apply(trusted: &[TrustedHost]) -> Result<(), String> {
+    let verifier = if trusted.is_empty() {
+        None
+    } else {
+        Some(Arc::new(HostVerifier::new(trusted, &provider())?))
+    };
+    *ACTIVE.write().unwrap() = verifier;
+    Ok(())
+}
+
+fn trust_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(TRUST_FILENAME))
+}
+
+fn read_trusted(app: &AppHandle) -> Vec<TrustedHost> {
+    trust_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_trusted(app: &AppHandle, trusted: &[TrustedHost]) -> Result<(), String> {
+    let path = trust_path(app).ok_or("no config directory")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string(trusted).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+fn update(app: &AppHandle, change: impl FnOnce(&mut Vec<TrustedHost>)) -> Result<(), String> {
+    let mut trusted = read_trusted(app);
+    change(&mut trusted);
+    apply(&trusted)?;
+    write_trusted(app, &trusted)
+}
+
+/// Apply the saved entries; called once during setup.
+pub fn load(app: &AppHandle) {
+    if let Err(e) = apply(&read_trusted(app)) {
+        log::warn!("Ignoring the trusted hosts: {e}");
+    }
+}
+
+/// Records the certificate a server presents, and whether the normal checks
+/// pass, without rejecting it.
+#[derive(Debug)]
+struct Recorder {
+    default: Arc<WebPkiServerVerifier>,
+    seen: Mutex<Option<(String, bool)>>,
+}
+
+impl ServerCertVerifier for Recorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let trusted = self
+            .default
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+            .is_ok();
+        *self.seen.lock().unwrap() = Some((fingerprint(end_entity.as_ref()), trusted));
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.default.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.default.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.default.supported_verify_schemes()
+    }
+}
+
+#[tauri::command]
+pub fn list_trusted_hosts(app: AppHandle) -> Vec<TrustedHost> {
+    read_trusted(&app)
+}
+
+/// Trust a private CA or pin a certificate for one host, replacing any
+/// earlier entry for it.
+#[tauri::command]
+pub fn trust_host(app: AppHandle, host: String, trust: HostTrust) -> Result<TrustedHost, String> {
+    let host = normalize_host(&host)?;
+    let trust = match trust {
+        HostTrust::Ca { pem } => {
+            parse_pem(&pem)?;
+            HostTrust::Ca { pem }
+        }
+        HostTrust::Fingerprint { sha256 } => HostTrust::Fingerprint {
+            sha256: normalize_fingerprint(&sha256)?,
+        },
+        HostTrust::Any => HostTrust::Any,
+    };
+    let entry = TrustedHost { host, trust };
+    let added = entry.clone();
+    update(&app, move |trusted| {
+        trusted.retain(|t| t.host != entry.host);
+        trusted.push(entry);
+    })?;
+    Ok(added)
+}
+
+#[tauri::command]
+pub fn untrust_host(app: AppHandle, host: String) -> Result<(), String> {
+    let host = normalize_host(&host)?;
+    update(&app, |trusted| trusted.retain(|t| t.host != host))
+}
+
+/// Connect to `url` and report the certificate it presents. Nothing is
+/// sent beyond a plain `HEAD` request.
+#[tauri::command]
+pub async fn probe_host_certificate(url: String) -> Result<CertificateInfo, String> {
+    let host = normalize_host(&url)?;
+    let provider = provider();
+    let recorder = Arc::new(Recorder {
+        default: webpki(public_roots(), &provider)?,
+        seen: Mutex::new(None),
+    });
+    let client = super::proxied()
+        .use_preconfigured_tls(client_config(recorder.clone(), None)?)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let request = client.head(&url).send().await;
+    let Some((fingerprint, trusted)) = recorder.seen.lock().unwrap().take() else {
+        return Err(match request {
+            Err(e) => format!("could not connect: {e}"),
+            Ok(_) => "the server did not present a certificate".into(),
+        });
+    };
+    Ok(CertificateInfo {
+        host,
+        fingerprint,
+        trusted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_are_normalized() {
+        assert_eq!(
+            normalize_host(" Cloud.Example.org. ").unwrap(),
+            "cloud.example.org"
+        );
+        assert_eq!(
+            normalize_host("https://cloud.example.org:8443/remote.php/dav").unwrap(),
+            "cloud.example.org"
+        );
+        assert_eq!(normalize_host("https://[::1]:8443/").unwrap(), "::1");
+        assert!(normalize_host("").is_err());
+        assert!(normalize_host("a b").is_err());
+    }
+
+    #[test]
+    fn fingerprints_accept_colon_separated_hex() {
+        let colons = ["AB"; 32].join(":");
+        assert_eq!(normalize_fingerprint(&colons).unwrap(), "ab".repeat(32));
+        assert!(normalize_fingerprint("abcd").is_err());
+        assert!(normalize_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn trust_entries_round_trip() {
+        let entry = TrustedHost {
+            host: "nas.local".into(),
+            trust: HostTrust::Fingerprint {
+                sha256: "ab".repeat(32),
+            },
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["kind"], "fingerprint");
+        assert_eq!(json["host"], "nas.local");
+        assert_eq!(serde_json::from_value::<TrustedHost>(json).unwrap(), entry);
+        assert!(parse_pem("not a certificate").is_err());
+
+        let any: TrustedHost =
+            serde_json::from_value(serde_json::json!({ "host": "nas.local", "kind": "any" }))
+                .unwrap();
+        assert_eq!(any.trust, HostTrust::Any);
+    }
+}
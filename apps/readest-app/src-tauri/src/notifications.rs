@@ -0,0 +1,99 @@
+//! Desktop notifications when background work ends.
+//!
+//! Conversions, imports and sync carry on while the window is minimized or
+//! behind other apps, so how they ended is announced through the system
+//! notification center (Windows toasts, macOS Notification Center, the
+//! freedesktop notification daemon on Linux). Nothing is shown while the
+//! window has focus, where the in-app toasts already cover it. Mobile has no
+//! backend: jobs there stop with the app.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager};
+
+/// Recent notifications by throttle key.
+#[derive(Default)]
+pub struct Notifier {
+    last_sent: Mutex<HashMap<String, Instant>>,
+}
+
+impl Notifier {
+    /// Whether a notification under `key` may go out at `now`, recording it
+    /// if so.
+    fn admit(&self, key: &str, interval: Duration, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        if let Some(at) = last_sent.get(key) {
+            if now.duration_since(*at) < interval {
+                return false;
+            }
+        }
+        last_sent.insert(key.to_string(), now);
+        true
+    }
+}
+
+/// Announce a finished job, unless the user is looking at the app.
+pub fn notify(app: &AppHandle, title: &str, body: &str) {
+    if in_foreground(app) {
+        return;
+    }
+    show(app, title, body);
+}
+
+/// Like [`notify`], but at most once per `interval` for `key`, for failures
+/// that tend to repeat (every sync request fails the same way while signed
+/// out).
+pub fn notify_throttled(app: &AppHandle, key: &str, interval: Duration, title: &str, body: &str) {
+    if in_foreground(app) {
+        return;
+    }
+    let Some(notifier) = app.try_state::<Notifier>() else {
+        return;
+    };
+    if notifier.admit(key, interval, Instant::now()) {
+        show(app, title, body);
+    }
+}
+
+fn in_foreground(app: &AppHandle) -> bool {
+    app.get_webview_window("main").is_some_and(|window| {
+        window.is_focused().unwrap_or(false) && !window.is_minimized().unwrap_or(false)
+    })
+}
+
+#[cfg(desktop)]
+fn show(app: &AppHandle, title: &str, body: &str) {
+    use tauri_plugin_notification::NotificationExt;
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification: {e}");
+    }
+}
+
+#[cfg(mobile)]
+fn show(_app: &AppHandle, _title: &str, _body: &str) {}
+
+/// Announce a job the frontend ran, such as an import, with its own
+/// localized text.
+#[tauri::command]
+pub fn notify_job_finished(app: AppHandle, title: String, body: String) {
+    notify(&app, &title, &body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_per_key() {
+        let notifier = Notifier::default();
+        let interval = Duration::from_secs(60);
+        let start = Instant::now();
+        assert!(notifier.admit("sync:dropbox", interval, start));
+        assert!(!notifier.admit("sync:dropbox", interval, start + Duration::from_secs(30)));
+        assert!(notifier.admit("sync:gdrive", interval, start + Duration::from_secs(30)));
+        assert!(notifier.admit("sync:dropbox", interval, start + interval));
+    }
+}
@@ -0,0 +1,289 @@
+//! Loopback redirect server for desktop OAuth sign-in.
+//!
+//! Anything on the machine can hit a localhost port, so the callback is not
+//! trusted just for arriving. [`start_server`] generates the PKCE verifier
+//! and `state` here and hands the webview only the challenge to put in its
+//! authorization URL. A request whose `state` doesn't match is ignored; the
+//! first one that does stops the server. An authorization code is
+//! exchanged in Rust with the verifier, and the webview receives just the
+//! resulting tokens (or the provider's error) as [`CALLBACK_EVENT`], never
+//! the raw URL.
+//!
+//! Providers that match redirect URIs exactly need the port to be stable,
+//! so it can be fixed with [`LoopbackOptions::port`]; otherwise the plugin
+//! picks a free one. Providers that don't echo `state` back (implicit flows
+//! such as Supabase's) get it through the redirect URI's query instead:
+//! append `?state=` to the `redirectUri` returned.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{Emitter, Url, Window};
+use tauri_plugin_oauth::OauthConfig;
+
+use crate::sync::oauth::{exchange_code, pkce_pair, random_token, TokenSet};
+
+pub const CALLBACK_EVENT: &str = "oauth://callback";
+const RESPONSE: &str =
+    "<html><body style=\"font-family:sans-serif;text-align:center;padding-top:4em\">\
+     <p>You can close this tab and return to Readest.</p></body></html>";
+/// An abandoned sign-in tab gives no signal, so stop listening eventually.
+const DEADLINE: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopbackOptions {
+    /// Listen on exactly this port, for providers that require the
+    /// registered redirect URI verbatim.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Where to exchange an authorization code (a standard form-encoded
+    /// token endpoint). Without it only implicit-flow tokens are accepted.
+    #[serde(default)]
+    pub token_endpoint: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// What the webview needs to build its authorization URL.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopbackSession {
+    pub port: u16,
+    pub redirect_uri: String,
+    pub state: String,
+    pub code_challenge: String,
+    pub code_challenge_method: &'static str,
+}
+
+/// Payload of [`CALLBACK_EVENT`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthCallback {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Epoch milliseconds, when the provider said.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Supabase's `type` (`recovery`, `signup`) and `next`, passed through.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl OAuthCallback {
+    fn failed(error: &str, description: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            error_description: Some(description.into()),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<TokenSet> for OAuthCallback {
+    fn from(tokens: TokenSet) -> Self {
+        Self {
+            access_token: Some(tokens.access_token),
+            refresh_token: tokens.refresh_token,
+            expires_at: Some(tokens.expires_at),
+            ..Self::default()
+        }
+    }
+}
+
+/// The query and fragment parameters of a redirect, if it carries our
+/// `state`. The fragment wins, as in `parseOAuthCallbackUrl`.
+fn callback_params(url: &str, state: &str) -> Option<HashMap<String, String>> {
+    let url = Url::parse(url)
+        .or_else(|_| Url::parse(&format!("http://localhost{url}")))
+        .ok()?;
+    let mut params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    if let Some(fragment) = url.fragment() {
+        // Implicit flows put the tokens here, form-encoded like a query.
+        let fragment = Url::parse(&format!("http://localhost/?{fragment}")).ok()?;
+        params.extend(fragment.query_pairs().into_owned());
+    }
+    (params.get("state").map(String::as_str) == Some(state)).then_some(params)
+}
+
+/// What a matching redirect carried, short of exchanging a code.
+enum Outcome {
+    Done(OAuthCallback),
+    Code(String),
+}
+
+fn outcome(mut params: HashMap<String, String>, now_ms: i64) -> Outcome {
+    if let Some(error) = params.remove("error") {
+        return Outcome::Done(OAuthCallback {
+            error: Some(error),
+            error_code: params.remove("error_code"),
+            error_description: params.remove("error_description"),
+            ..OAuthCallback::default()
+        });
+    }
+    if let Some(access_token) = params.remove("access_token") {
+        let expires_at = params
+            .get("expires_at")
+            .and_then(|s| s.parse::<i64>().ok())
+            .map(|secs| secs * 1000)
+            .or_else(|| {
+                let expires_in = params.get("expires_in")?.parse::<i64>().ok()?;
+                Some(now_ms + expires_in * 1000)
+            });
+        return Outcome::Done(OAuthCallback {
+            access_token: Some(access_token),
+            refresh_token: params.remove("refresh_token"),
+            expires_at,
+            kind: params.remove("type"),
+            next: params.remove("next"),
+            ..OAuthCallback::default()
+        });
+    }
+    match params.remove("code") {
+        Some(code) => Outcome::Code(code),
+        None => Outcome::Done(OAuthCallback::failed(
+            "invalid_request",
+            "the redirect carried no token or code",
+        )),
+    }
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Start a one-shot loopback server for a sign-in.
+#[tauri::command]
+pub async fn start_server(
+    window: Window,
+    options: Option<LoopbackOptions>,
+) -> Result<LoopbackSession, String> {
+    let options = options.unwrap_or_default();
+    let (verifier, challenge) = pkce_pair();
+    let state = random_token(32);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let config = OauthConfig {
+        ports: options.port.map(|port| vec![port]),
+        response: Some(RESPONSE.into()),
+    };
+    let port = tauri_plugin_oauth::start_with_config(config, move |url| {
+        let _ = tx.send(url);
+    })
+    .map_err(|e| format!("redirect listener: {e}"))?;
+    let redirect_uri = format!("http://localhost:{port}");
+
+    let expected = state.clone();
+    let exchange_uri = redirect_uri.clone();
+    tauri::async_runtime::spawn(async move {
+        let params = tokio::time::timeout(DEADLINE, async {
+            while let Some(url) = rx.recv().await {
+                if let Some(params) = callback_params(&url, &expected) {
+                    return Some(params);
+                }
+                log::debug!("Ignoring a loopback request without the sign-in state");
+            }
+            None
+        })
+        .await
+        .ok()
+        .flatten();
+        if let Err(e) = tauri_plugin_oauth::cancel(port) {
+            log::warn!("Failed to stop the OAuth redirect listener: {e}");
+        }
+        let Some(params) = params else {
+            return;
+        };
+        let callback = match outcome(params, now_ms()) {
+            Outcome::Done(callback) => callback,
+            Outcome::Code(code) => match (&options.token_endpoint, &options.client_id) {
+                (Some(endpoint), Some(client_id)) => exchange_code(
+                    &crate::net::client(),
+                    endpoint,
+                    client_id,
+                    &code,
+                    &verifier,
+                    &exchange_uri,
+                )
+                .await
+                .map(OAuthCallback::from)
+                .unwrap_or_else(|e| OAuthCallback::failed("token_exchange_failed", e.message)),
+                _ => OAuthCallback::failed(
+                    "unsupported_response_type",
+                    "got an authorization code but no token endpoint to exchange it at",
+                ),
+            },
+        };
+        if let Err(e) = window.emit_to(window.label(), CALLBACK_EVENT, callback) {
+            log::warn!("Failed to deliver the OAuth callback: {e}");
+        }
+    });
+
+    Ok(LoopbackSession {
+        port,
+        redirect_uri,
+        state,
+        code_challenge: challenge,
+        code_challenge_method: "S256",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(outcome: Outcome) -> OAuthCallback {
+        match outcome {
+            Outcome::Done(callback) => callback,
+            Outcome::Code(code) => panic!("unexpected code {code}"),
+        }
+    }
+
+    #[test]
+    fn requests_without_our_state_are_ignored() {
+        assert!(callback_params("/favicon.ico", "s1").is_none());
+        assert!(callback_params("/?code=abc&state=other", "s1").is_none());
+        assert!(callback_params("http://localhost:1/?code=abc", "s1").is_none());
+        let params = callback_params("http://localhost:1/?code=abc&state=s1", "s1").unwrap();
+        assert_eq!(params["code"], "abc");
+    }
+
+    #[test]
+    fn implicit_tokens_come_from_the_fragment() {
+        let url = "http://localhost:1/?state=s1#access_token=at&refresh_token=rt&expires_in=3600&type=recovery";
+        let callback = done(outcome(callback_params(url, "s1").unwrap(), 1_000));
+        assert_eq!(callback.access_token.as_deref(), Some("at"));
+        assert_eq!(callback.refresh_token.as_deref(), Some("rt"));
+        assert_eq!(callback.expires_at, Some(3_601_000));
+        assert_eq!(callback.kind.as_deref(), Some("recovery"));
+        let json = serde_json::to_value(&callback).unwrap();
+        assert_eq!(json["type"], "recovery");
+        assert!(json.get("error").is_none());
+    }
+
+    #[test]
+    fn errors_and_codes_are_told_apart() {
+        let denied = "/?state=s1&error=access_denied&error_description=nope";
+        let callback = done(outcome(callback_params(denied, "s1").unwrap(), 0));
+        assert_eq!(callback.error.as_deref(), Some("access_denied"));
+        assert_eq!(callback.error_description.as_deref(), Some("nope"));
+        assert!(callback.access_token.is_none());
+
+        let code = outcome(callback_params("/?state=s1&code=abc", "s1").unwrap(), 0);
+        assert!(matches!(code, Outcome::Code(code) if code == "abc"));
+        let empty = done(outcome(callback_params("/?state=s1", "s1").unwrap(), 0));
+        assert_eq!(empty.error.as_deref(), Some("invalid_request"));
+    }
+}
@@ -0,0 +1,397 @@
+//! OPDS 1.2 (Atom) feed parser.
+//!
+//! A single streaming pass with quick-xml, keyed on local names so the
+//! `atom:` / `dc:` / `opds:` / `opensearch:` prefixes catalogs pick don't
+//! matter. Entries carrying an acquisition link become publications, the
+//! rest become navigation entries.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use tauri::Url;
+
+use super::model::{
+    acquisition_kind, OpdsAcquisition, OpdsFeed, OpdsLink, OpdsNavigation, OpdsPrice,
+    OpdsPublication, IMAGE_RELS, THUMBNAIL_RELS,
+};
+use super::resolve_href;
+use crate::epub_parser::{local_name, strip_xml_bom};
+
+/// A `<link>` whose children (`opds:price`, `opds:indirectAcquisition`)
+/// are still being read.
+#[derive(Default)]
+struct PendingLink {
+    link: OpdsLink,
+    price: Option<OpdsPrice>,
+    indirect_types: Vec<String>,
+}
+
+#[derive(Default)]
+struct PendingEntry {
+    publication: OpdsPublication,
+    nav_link: Option<OpdsLink>,
+    count: Option<u64>,
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| local_name(a.key.as_ref()) == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+pub fn parse_atom(bytes: &[u8], base: &Url) -> Result<OpdsFeed, String> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    reader.config_mut().trim_text(true);
+
+    let mut feed = OpdsFeed {
+        version: "1.2".to_string(),
+        ..Default::default()
+    };
+    let mut entry: Option<PendingEntry> = None;
+    let mut link: Option<PendingLink> = None;
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut text = String::new();
+    let mut buf = Vec::new();
+    let mut saw_feed = false;
+
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("xml: {e}"))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                let name = local_name(e.name().as_ref()).to_vec();
+                let in_rich_text = path
+                    .iter()
+                    .any(|n| n.as_slice() == b"content" || n.as_slice() == b"summary");
+                match name.as_slice() {
+                    b"feed" => saw_feed = true,
+                    b"entry" => entry = Some(PendingEntry::default()),
+                    b"link" => {
+                        link = Some(PendingLink {
+                            link: OpdsLink {
+                                href: attr(e, b"href")
+                                    .map(|h| resolve_href(base, &h))
+                                    .unwrap_or_default(),
+                                rel: attr(e, b"rel"),
+                                media_type: attr(e, b"type"),
+                                title: attr(e, b"title"),
+                                templated: false,
+                            },
+                            ..Default::default()
+                        });
+                        if let (Some(count), Some(entry)) = (attr(e, b"count"), entry.as_mut()) {
+                            entry.count = count.parse().ok();
+                        }
+                    }
+                    b"price" => {
+                        if let Some(link) = link.as_mut() {
+                            link.price = Some(OpdsPrice {
+                                value: 0.0,
+                                currency: attr(e, b"currencycode").unwrap_or_default(),
+                            });
+                        }
+                    }
+                    b"indirectAcquisition" => {
+                        if let (Some(link), Some(t)) = (link.as_mut(), attr(e, b"type")) {
+                            link.indirect_types.push(t);
+                        }
+                    }
+                    b"category" => {
+                        if let Some(entry) = entry.as_mut() {
+                            if let Some(subject) = attr(e, b"label").or_else(|| attr(e, b"term")) {
+                                entry.publication.subjects.push(subject);
+                            }
+                        }
+                    }
+                    // Calibre's `<meta name="calibre:series" content=..>`.
+                    b"meta" => {
+                        if let (Some(entry), Some(meta)) = (entry.as_mut(), attr(e, b"name")) {
+                            let content = attr(e, b"content");
+                            match meta.as_str() {
+                                "calibre:series" => entry.publication.series = content,
+                                "calibre:series_index" => {
+                                    entry.publication.series_index =
+                                        content.and_then(|c| c.parse().ok())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                if is_empty {
+                    if name.as_slice() == b"link" {
+                        finish_link(&mut feed, entry.as_mut(), link.take());
+                    }
+                } else {
+                    if !in_rich_text {
+                        text.clear();
+                    } else if !text.is_empty() && !text.ends_with(' ') {
+                        text.push(' ');
+                    }
+                    path.push(name);
+                }
+            }
+            Event::Text(t) => {
+                if let Ok(s) = t.unescape() {
+                    text.push_str(&s);
+                }
+            }
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(_) => {
+                let Some(name) = path.pop() else {
+                    continue;
+                };
+                let parent = path.last().map(|p| p.as_slice());
+                let in_rich_text = path
+                    .iter()
+                    .any(|n| n.as_slice() == b"content" || n.as_slice() == b"summary");
+                if in_rich_text {
+                    if !text.is_empty() && !text.ends_with(' ') {
+                        text.push(' ');
+                    }
+                    continue;
+                }
+                let value = non_empty(&text);
+                text.clear();
+                match name.as_slice() {
+                    b"link" => {
+                        finish_link(&mut feed, entry.as_mut(), link.take());
+                        continue;
+                    }
+                    b"price" => {
+                        if let Some(price) = link.as_mut().and_then(|l| l.price.as_mut()) {
+                            price.value = value.and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                        }
+                        continue;
+                    }
+                    b"entry" => {
+                        if let Some(done) = entry.take() {
+                            finish_entry(&mut feed, done);
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+                match (name.as_slice(), entry.as_mut()) {
+                    (b"name", Some(entry)) if parent == Some(b"author".as_slice()) => {
+                        if let Some(v) = value {
+                            entry.publication.authors.push(v);
+                        }
+                    }
+                    (b"title", Some(entry)) => entry.publication.title = value.unwrap_or_default(),
+                    (b"id", Some(entry)) => entry.publication.id = value.unwrap_or_default(),
+                    (b"updated", Some(entry)) => entry.publication.updated = value,
+                    (b"published" | b"issued", Some(entry)) => entry.publication.published = value,
+                    (b"summary", Some(entry)) => entry.publication.summary = value,
+                    (b"content", Some(entry)) => {
+                        if entry.publication.summary.is_none() {
+                            entry.publication.summary = value;
+                        }
+                    }
+                    (b"language", Some(entry)) => entry.publication.language = value,
+                    (b"publisher", Some(entry)) => entry.publication.publisher = value,
+                    (b"identifier", Some(entry)) => entry.publication.identifier = value,
+                    (b"title", None) if parent == Some(b"feed".as_slice()) => {
+                        feed.title = value.unwrap_or_default()
+                    }
+                    (b"subtitle", None) => feed.subtitle = value,
+                    (b"id", None) if parent == Some(b"feed".as_slice()) => feed.id = value,
+                    (b"updated", None) if parent == Some(b"feed".as_slice()) => {
+                        feed.updated = value
+                    }
+                    (b"icon", None) => feed.icon = value.map(|v| resolve_href(base, &v)),
+                    (b"totalResults", None) => {
+                        feed.total_results = value.and_then(|v| v.parse().ok())
+                    }
+                    (b"itemsPerPage", None) => {
+                        feed.items_per_page = value.and_then(|v| v.parse().ok())
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !saw_feed {
+        return Err("not an Atom feed".to_string());
+    }
+    Ok(feed)
+}
+
+fn finish_link(
+    feed: &mut OpdsFeed,
+    entry: Option<&mut PendingEntry>,
+    pending: Option<PendingLink>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let link = pending.link;
+    if link.href.is_empty() {
+        return;
+    }
+    let Some(entry) = entry else {
+        feed.absorb_link(link);
+        return;
+    };
+    let rel = link.rel.clone().unwrap_or_default();
+    let publication = &mut entry.publication;
+    if let Some(kind) = acquisition_kind(&rel) {
+        publication.acquisitions.push(OpdsAcquisition {
+            href: link.href,
+            kind,
+            media_type: link.media_type,
+            title: link.title,
+            price: pending.price,
+            indirect_types: pending.indirect_types,
+        });
+    } else if IMAGE_RELS.contains(&rel.as_str()) {
+        publication.cover = Some(link.href);
+    } else if THUMBNAIL_RELS.contains(&rel.as_str()) {
+        publication.thumbnail = Some(link.href);
+    } else {
+        let is_catalog = link
+            .media_type
+            .as_deref()
+            .is_some_and(|t| t.contains("opds-catalog") || t.contains("atom+xml"));
+        if is_catalog && entry.nav_link.is_none() {
+            entry.nav_link = Some(link.clone());
+        }
+        publication.links.push(link);
+    }
+}
+
+fn finish_entry(feed: &mut OpdsFeed, entry: PendingEntry) {
+    let publication = entry.publication;
+    if !publication.acquisitions.is_empty() {
+        feed.publications.push(publication);
+        return;
+    }
+    let Some(link) = entry
+        .nav_link
+        .or_else(|| publication.links.first().cloned())
+    else {
+        return;
+    };
+    feed.navigation.push(OpdsNavigation {
+        title: publication.title,
+        href: link.href,
+        rel: link.rel,
+        media_type: link.media_type,
+        summary: publication.summary,
+        count: entry.count,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAV_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:opensearch="http://a9.com/-/spec/opensearch/1.1/">
+  <id>urn:root</id>
+  <title>My Catalog</title>
+  <updated>2024-01-01T00:00:00Z</updated>
+  <link rel="search" href="/opensearch.xml" type="application/opensearchdescription+xml"/>
+  <link rel="next" href="?page=2" type="application/atom+xml;profile=opds-catalog;kind=navigation"/>
+  <opensearch:totalResults>42</opensearch:totalResults>
+  <entry>
+    <title>New Books</title>
+    <id>urn:new</id>
+    <content type="text">Recently added</content>
+    <link rel="subsection" href="new" type="application/atom+xml;profile=opds-catalog;kind=acquisition"/>
+  </entry>
+</feed>"#;
+
+    const ACQ_FEED: &str = r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/terms/" xmlns:opds="http://opds-spec.org/2010/catalog">
+  <title>New</title>
+  <entry>
+    <title>Moby-Dick</title>
+    <id>urn:uuid:1</id>
+    <author><name>Herman Melville</name></author>
+    <dc:language>en</dc:language>
+    <dc:issued>1851</dc:issued>
+    <category term="fiction" label="Fiction"/>
+    <summary type="xhtml"><div>A <b>whale</b> of a tale</div></summary>
+    <link rel="http://opds-spec.org/image" href="/covers/1.jpg" type="image/jpeg"/>
+    <link rel="http://opds-spec.org/image/thumbnail" href="/thumbs/1.jpg" type="image/jpeg"/>
+    <link rel="http://opds-spec.org/acquisition/open-access" href="/dl/1.epub" type="application/epub+zip"/>
+    <link rel="http://opds-spec.org/acquisition/buy" href="/buy/1" type="text/html">
+      <opds:price currencycode="USD">2.99</opds:price>
+      <opds:indirectAcquisition type="application/vnd.adobe.adept+xml"/>
+    </link>
+  </entry>
+</feed>"#;
+
+    fn base() -> Url {
+        Url::parse("https://books.example.com/opds/").unwrap()
+    }
+
+    #[test]
+    fn parses_navigation_feed() {
+        let feed = parse_atom(NAV_FEED.as_bytes(), &base()).unwrap();
+        assert_eq!(feed.title, "My Catalog");
+        assert_eq!(feed.id.as_deref(), Some("urn:root"));
+        assert_eq!(feed.total_results, Some(42));
+        assert_eq!(
+            feed.next.as_deref(),
+            Some("https://books.example.com/opds/?page=2")
+        );
+        assert_eq!(
+            feed.search.as_ref().map(|s| s.href.as_str()),
+            Some("https://books.example.com/opensearch.xml")
+        );
+        assert_eq!(feed.navigation.len(), 1);
+        assert_eq!(feed.navigation[0].title, "New Books");
+        assert_eq!(
+            feed.navigation[0].href,
+            "https://books.example.com/opds/new"
+        );
+        assert_eq!(
+            feed.navigation[0].summary.as_deref(),
+            Some("Recently added")
+        );
+        assert!(feed.publications.is_empty());
+    }
+
+    #[test]
+    fn parses_acquisition_entry() {
+        let feed = parse_atom(ACQ_FEED.as_bytes(), &base()).unwrap();
+        let publication = &feed.publications[0];
+        assert_eq!(publication.title, "Moby-Dick");
+        assert_eq!(publication.authors, vec!["Herman Melville"]);
+        assert_eq!(publication.language.as_deref(), Some("en"));
+        assert_eq!(publication.published.as_deref(), Some("1851"));
+        assert_eq!(publication.subjects, vec!["Fiction"]);
+        assert_eq!(publication.summary.as_deref(), Some("A whale of a tale"));
+        assert_eq!(
+            publication.cover.as_deref(),
+            Some("https://books.example.com/covers/1.jpg")
+        );
+        assert_eq!(publication.acquisitions.len(), 2);
+        assert_eq!(publication.acquisitions[0].kind, "open-access");
+        let buy = &publication.acquisitions[1];
+        assert_eq!(buy.kind, "buy");
+        let price = buy.price.as_ref().unwrap();
+        assert_eq!(price.currency, "USD");
+        assert!((price.value - 2.99).abs() < f64::EPSILON);
+        assert_eq!(buy.indirect_types, vec!["application/vnd.adobe.adept+xml"]);
+    }
+
+    #[test]
+    fn rejects_non_feed_xml() {
+        assert!(parse_atom(b"<html><body/></html>", &base()).is_err());
+    }
+}
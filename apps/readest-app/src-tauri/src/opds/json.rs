@@ -0,0 +1,351 @@
+//! OPDS 2.0 (JSON) feed parser.
+//!
+//! Walks `serde_json::Value` rather than deserializing into strict structs:
+//! real catalogs disagree on whether `author`, `language`, `rel` and
+//! `subject` are strings, objects or arrays, and one odd field shouldn't
+//! make a whole page unreadable.
+
+use serde_json::Value;
+use tauri::Url;
+
+use super::model::{
+    acquisition_kind, OpdsAcquisition, OpdsFeed, OpdsGroup, OpdsLink, OpdsNavigation, OpdsPrice,
+    OpdsPublication, IMAGE_RELS, THUMBNAIL_RELS,
+};
+use super::{resolve_href, resolve_template};
+
+fn str_of(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A string, `{ "name": .. }` object, or an array of either.
+fn names_of(v: &Value) -> Vec<String> {
+    match v {
+        Value::Array(items) => items.iter().flat_map(names_of).collect(),
+        Value::Object(obj) => obj.get("name").and_then(first_string).into_iter().collect(),
+        other => str_of(other).into_iter().collect(),
+    }
+}
+
+/// Localized strings come as plain strings or `{ "en": .., "fr": .. }`.
+fn first_string(v: &Value) -> Option<String> {
+    match v {
+        Value::Object(map) => map.values().find_map(str_of),
+        Value::Array(items) => items.iter().find_map(first_string),
+        other => str_of(other),
+    }
+}
+
+fn rels_of(v: &Value) -> Vec<String> {
+    match v {
+        Value::Array(items) => items.iter().filter_map(str_of).collect(),
+        other => str_of(other).into_iter().collect(),
+    }
+}
+
+fn parse_link(v: &Value, base: &Url) -> Option<(OpdsLink, Vec<String>)> {
+    let href = v.get("href").and_then(str_of)?;
+    let rels = v.get("rel").map(rels_of).unwrap_or_default();
+    let templated = v.get("templated").and_then(Value::as_bool).unwrap_or(false);
+    let link = OpdsLink {
+        href: if templated {
+            resolve_template(base, &href)
+        } else {
+            resolve_href(base, &href)
+        },
+        rel: rels.first().cloned(),
+        media_type: v.get("type").and_then(str_of),
+        title: v.get("title").and_then(first_string),
+        templated,
+    };
+    Some((link, rels))
+}
+
+/// Parsed links paired with their raw JSON, for callers that need
+/// `properties`.
+fn links_of<'a>(v: Option<&'a Value>, base: &Url) -> Vec<(OpdsLink, Vec<String>, &'a Value)> {
+    v.and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|raw| parse_link(raw, base).map(|(link, rels)| (link, rels, raw)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn indirect_types(v: Option<&Value>) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = v.and_then(|v| v.as_array().and_then(|a| a.first()).or(Some(v)));
+    while let Some(node) = current {
+        match node.get("type").and_then(str_of) {
+            Some(t) => out.push(t),
+            None => break,
+        }
+        current = node
+            .get("child")
+            .and_then(|c| c.as_array().and_then(|a| a.first()).or(Some(c)));
+    }
+    out
+}
+
+fn parse_navigation(v: &Value, base: &Url) -> Option<OpdsNavigation> {
+    let (link, _) = parse_link(v, base)?;
+    Some(OpdsNavigation {
+        title: link.title.clone().unwrap_or_default(),
+        href: link.href,
+        rel: link.rel,
+        media_type: link.media_type,
+        summary: None,
+        count: v
+            .pointer("/properties/numberOfItems")
+            .and_then(Value::as_u64),
+    })
+}
+
+fn parse_publication(v: &Value, base: &Url) -> OpdsPublication {
+    let empty = Value::Null;
+    let metadata = v.get("metadata").unwrap_or(&empty);
+    let mut publication = OpdsPublication {
+        id: metadata
+            .get("identifier")
+            .and_then(str_of)
+            .unwrap_or_default(),
+        title: metadata
+            .get("title")
+            .and_then(first_string)
+            .unwrap_or_default(),
+        authors: metadata.get("author").map(names_of).unwrap_or_default(),
+        summary: metadata.get("description").and_then(first_string),
+        language: metadata.get("language").and_then(first_string),
+        publisher: metadata
+            .get("publisher")
+            .map(names_of)
+            .and_then(|p| p.into_iter().next()),
+        published: metadata.get("published").and_then(str_of),
+        updated: metadata.get("modified").and_then(str_of),
+        identifier: metadata.get("identifier").and_then(str_of),
+        subjects: metadata.get("subject").map(names_of).unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(series) = metadata.pointer("/belongsTo/series") {
+        let series = series.as_array().and_then(|a| a.first()).unwrap_or(series);
+        publication.series = names_of(series).into_iter().next();
+        publication.series_index = series.get("position").and_then(Value::as_f64);
+    }
+
+    for (link, rels, raw) in links_of(v.get("links"), base) {
+        if let Some(kind) = rels.iter().find_map(|r| acquisition_kind(r)) {
+            let properties = raw.get("properties");
+            publication.acquisitions.push(OpdsAcquisition {
+                href: link.href,
+                kind,
+                media_type: link.media_type,
+                title: link.title,
+                price: properties.and_then(|p| p.get("price")).map(|p| OpdsPrice {
+                    value: p.get("value").and_then(Value::as_f64).unwrap_or(0.0),
+                    currency: p.get("currency").and_then(str_of).unwrap_or_default(),
+                }),
+                indirect_types: indirect_types(
+                    properties.and_then(|p| p.get("indirectAcquisition")),
+                ),
+            });
+        } else {
+            publication.links.push(link);
+        }
+    }
+
+    for (link, rels, _) in links_of(v.get("images"), base) {
+        let is_thumb = rels.iter().any(|r| THUMBNAIL_RELS.contains(&r.as_str()));
+        if is_thumb {
+            publication.thumbnail.get_or_insert(link.href);
+        } else if publication.cover.is_none()
+            || rels.iter().any(|r| IMAGE_RELS.contains(&r.as_str()))
+        {
+            publication.cover = Some(link.href);
+        }
+    }
+    if publication.thumbnail.is_none() {
+        publication.thumbnail = publication.cover.clone();
+    }
+    publication
+}
+
+fn parse_collections(v: &Value, base: &Url) -> (Vec<OpdsNavigation>, Vec<OpdsPublication>) {
+    let navigation = v
+        .get("navigation")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|n| parse_navigation(n, base))
+                .collect()
+        })
+        .unwrap_or_default();
+    let publications = v
+        .get("publications")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().map(|p| parse_publication(p, base)).collect())
+        .unwrap_or_default();
+    (navigation, publications)
+}
+
+pub fn parse_json(bytes: &[u8], base: &Url) -> Result<OpdsFeed, String> {
+    let root: Value = serde_json::from_slice(bytes).map_err(|e| format!("json: {e}"))?;
+    let metadata = root
+        .get("metadata")
+        .ok_or_else(|| "not an OPDS 2 feed: missing metadata".to_string())?;
+
+    // A single publication document (`application/opds-publication+json`)
+    // is surfaced as a one-item feed.
+    if root.get("navigation").is_none()
+        && root.get("publications").is_none()
+        && root.get("groups").is_none()
+        && root
+            .get("links")
+            .and_then(Value::as_array)
+            .is_some_and(|links| {
+                links.iter().any(|l| {
+                    l.get("rel")
+                        .map(rels_of)
+                        .unwrap_or_default()
+                        .iter()
+                        .any(|r| acquisition_kind(r).is_some())
+                })
+            })
+    {
+        let publication = parse_publication(&root, base);
+        return Ok(OpdsFeed {
+            version: "2.0".to_string(),
+            title: publication.title.clone(),
+            publications: vec![publication],
+            ..Default::default()
+        });
+    }
+
+    let (navigation, publications) = parse_collections(&root, base);
+    let mut feed = OpdsFeed {
+        version: "2.0".to_string(),
+        id: metadata.get("identifier").and_then(str_of),
+        title: metadata
+            .get("title")
+            .and_then(first_string)
+            .unwrap_or_default(),
+        subtitle: metadata.get("subtitle").and_then(first_string),
+        updated: metadata.get("modified").and_then(str_of),
+        total_results: metadata.get("numberOfItems").and_then(Value::as_u64),
+        items_per_page: metadata.get("itemsPerPage").and_then(Value::as_u64),
+        navigation,
+        publications,
+        ..Default::default()
+    };
+    for (link, _, _) in links_of(root.get("links"), base) {
+        feed.absorb_link(link);
+    }
+    if let Some(groups) = root.get("groups").and_then(Value::as_array) {
+        for group in groups {
+            let (navigation, publications) = parse_collections(group, base);
+            let href = links_of(group.get("links"), base)
+                .into_iter()
+                .find(|(_, rels, _)| rels.iter().any(|r| r == "self"))
+                .map(|(l, _, _)| l.href);
+            feed.groups.push(OpdsGroup {
+                title: group
+                    .pointer("/metadata/title")
+                    .and_then(first_string)
+                    .unwrap_or_default(),
+                href,
+                navigation,
+                publications,
+            });
+        }
+    }
+    Ok(feed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/opds/root.json").unwrap()
+    }
+
+    const FEED: &str = r#"{
+      "metadata": { "title": "Catalog", "numberOfItems": 120, "itemsPerPage": 20 },
+      "links": [
+        { "rel": "self", "href": "root.json", "type": "application/opds+json" },
+        { "rel": "next", "href": "root.json?page=2", "type": "application/opds+json" },
+        { "rel": "search", "href": "/search{?query}", "type": "application/opds+json", "templated": true }
+      ],
+      "navigation": [
+        { "href": "new.json", "title": "New", "type": "application/opds+json", "properties": { "numberOfItems": 7 } }
+      ],
+      "publications": [{
+        "metadata": {
+          "identifier": "urn:isbn:9780000000001",
+          "title": { "en": "The Title" },
+          "author": [{ "name": "A. Writer" }, "B. Writer"],
+          "language": ["en"],
+          "subject": [{ "name": "Adventure" }],
+          "belongsTo": { "series": { "name": "Saga", "position": 2 } }
+        },
+        "links": [
+          { "rel": "http://opds-spec.org/acquisition/borrow", "href": "/loan/1", "type": "application/vnd.readium.lcp.license.v1.0+json",
+            "properties": { "indirectAcquisition": [{ "type": "application/epub+zip" }] } }
+        ],
+        "images": [{ "href": "/img/1.jpg", "type": "image/jpeg" }]
+      }],
+      "groups": [{ "metadata": { "title": "Featured" }, "links": [{ "rel": "self", "href": "featured.json" }], "publications": [] }]
+    }"#;
+
+    #[test]
+    fn parses_feed_paging_and_search() {
+        let feed = parse_json(FEED.as_bytes(), &base()).unwrap();
+        assert_eq!(feed.version, "2.0");
+        assert_eq!(feed.title, "Catalog");
+        assert_eq!(feed.total_results, Some(120));
+        assert_eq!(
+            feed.next.as_deref(),
+            Some("https://example.com/opds/root.json?page=2")
+        );
+        let search = feed.search.unwrap();
+        assert!(search.templated);
+        assert_eq!(search.href, "https://example.com/search{?query}");
+        assert_eq!(feed.navigation[0].href, "https://example.com/opds/new.json");
+        assert_eq!(feed.navigation[0].count, Some(7));
+        assert_eq!(feed.groups[0].title, "Featured");
+        assert_eq!(
+            feed.groups[0].href.as_deref(),
+            Some("https://example.com/opds/featured.json")
+        );
+    }
+
+    #[test]
+    fn parses_publication_metadata() {
+        let feed = parse_json(FEED.as_bytes(), &base()).unwrap();
+        let publication = &feed.publications[0];
+        assert_eq!(publication.title, "The Title");
+        assert_eq!(publication.authors, vec!["A. Writer", "B. Writer"]);
+        assert_eq!(publication.language.as_deref(), Some("en"));
+        assert_eq!(publication.subjects, vec!["Adventure"]);
+        assert_eq!(publication.series.as_deref(), Some("Saga"));
+        assert_eq!(publication.series_index, Some(2.0));
+        assert_eq!(
+            publication.cover.as_deref(),
+            Some("https://example.com/img/1.jpg")
+        );
+        let acquisition = &publication.acquisitions[0];
+        assert_eq!(acquisition.kind, "borrow");
+        assert_eq!(acquisition.indirect_types, vec!["application/epub+zip"]);
+    }
+
+    #[test]
+    fn rejects_json_without_metadata() {
+        assert!(parse_json(b"{\"foo\": 1}", &base()).is_err());
+    }
+}
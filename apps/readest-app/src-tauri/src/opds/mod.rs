@@ -0,0 +1,387 @@
+//! OPDS catalog client.
+//!
+//! Fetches OPDS 1.2 (Atom) and 2.0 (JSON) catalogs, follows paging and
+//! search links, and hands acquisitions to the download manager. Feeds are
+//! parsed here and returned as the typed model in [`model`], so the
+//! frontend never sees raw XML/JSON or has to resolve relative links.
+//!
+//! Authentication is per call (`basic` or `bearer`); credentials live in
+//! the frontend's catalog settings and are only attached to requests for
+//! the catalog the caller names. A queued download outlives the call, so its
+//! header goes to the secure store under `opds:<catalog id>` and only that
+//! key is kept in the download queue.
+
+mod atom;
+mod json;
+pub mod model;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State, Url};
+
+use crate::download_manager::{DownloadItem, DownloadManager};
+use model::{OpdsAuth, OpdsFeed, OpdsLink};
+
+const ACCEPT: &str = "application/opds+json, application/atom+xml;profile=opds-catalog;q=0.9, \
+                      application/atom+xml;q=0.8, application/xml;q=0.7, */*;q=0.5";
+
+pub(crate) fn resolve_href(base: &Url, href: &str) -> String {
+    base.join(href)
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| href.to_string())
+}
+
+/// Resolve a search template. `{` isn't a URL char, so only the part before
+/// the first expression is resolved and the template tail is kept verbatim.
+pub(crate) fn resolve_template(base: &Url, template: &str) -> String {
+    match template.find('{') {
+        Some(i) => format!("{}{}", resolve_href(base, &template[..i]), &template[i..]),
+        None => resolve_href(base, template),
+    }
+}
+
+fn auth_header(auth: &OpdsAuth) -> (String, String) {
+    use base64::Engine;
+    let value = match auth {
+        OpdsAuth::Basic { username, password } => format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"))
+        ),
+        OpdsAuth::Bearer { token } => format!("Bearer {token}"),
+    };
+    ("Authorization".to_string(), value)
+}
+
+struct Fetched {
+    body: Vec<u8>,
+    content_type: String,
+    url: Url,
+}
+
+async fn fetch(url: &str, auth: Option<&OpdsAuth>) -> Result<Fetched, String> {
+    let client = crate::net::client_builder_for(url)
+        .user_agent(concat!("Readest/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut request = client.get(url).header("Accept", ACCEPT);
+    if let Some(auth) = auth {
+        let (key, value) = auth_header(auth);
+        request = request.header(key, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {e}"))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get("www-authenticate")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        return Err(format!("authentication required {challenge}")
+            .trim_end()
+            .to_string());
+    }
+    if !status.is_success() {
+        return Err(format!(
+            "request failed with status code {}",
+            status.as_u16()
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let final_url = response.url().clone();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("read body: {e}"))?
+        .to_vec();
+    Ok(Fetched {
+        body,
+        content_type,
+        url: final_url,
+    })
+}
+
+fn parse_feed(fetched: &Fetched) -> Result<OpdsFeed, String> {
+    let looks_json = fetched.content_type.contains("json")
+        || fetched
+            .body
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|b| *b == b'{');
+    if looks_json {
+        json::parse_json(&fetched.body, &fetched.url)
+    } else {
+        atom::parse_atom(&fetched.body, &fetched.url)
+    }
+}
+
+/// Expand an OpenSearch (`{searchTerms}`, `{startPage?}`) or RFC 6570
+/// (`{?query,page}`) template with `query`. Parameters other than the
+/// search terms are optional in both styles and dropped.
+fn expand_template(template: &str, query: &str) -> String {
+    let encoded: String = url_encode(query);
+    let mut out = String::with_capacity(template.len() + encoded.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let expr = &rest[start + 1..start + len];
+        match expr.strip_prefix('?').or_else(|| expr.strip_prefix('&')) {
+            Some(vars) => {
+                let mut sep = if expr.starts_with('?') && !out.contains('?') {
+                    '?'
+                } else {
+                    '&'
+                };
+                for var in vars.split(',') {
+                    if matches!(var, "query" | "q" | "searchTerms" | "title") {
+                        out.push(sep);
+                        out.push_str(var);
+                        out.push('=');
+                        out.push_str(&encoded);
+                        sep = '&';
+                    }
+                }
+            }
+            None => {
+                if expr.trim_end_matches('?') == "searchTerms" || expr == "query" {
+                    out.push_str(&encoded);
+                }
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn url_encode(s: &str) -> String {
+    percent_encoding::utf8_percent_encode(s, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+/// Pick the Atom/OPDS `<Url template>` out of an OpenSearch description.
+fn opensearch_template(body: &[u8], base: &Url) -> Option<String> {
+    use quick_xml::events::Event;
+    let normalized = crate::epub_parser::strip_xml_bom(body);
+    let mut reader = quick_xml::Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut fallback = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if crate::epub_parser::local_name(e.name().as_ref()) == b"Url" =>
+            {
+                let mut template = None;
+                let mut media_type = String::new();
+                for a in e.attributes().flatten() {
+                    let value = a.unescape_value().map(|v| v.into_owned()).ok();
+                    match a.key.as_ref() {
+                        b"template" => template = value,
+                        b"type" => media_type = value.unwrap_or_default(),
+                        _ => {}
+                    }
+                }
+                if let Some(template) = template {
+                    let template = resolve_template(base, &template);
+                    if media_type.contains("atom") || media_type.contains("opds") {
+                        return Some(template);
+                    }
+                    fallback.get_or_insert(template);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    fallback
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = cleaned.trim().trim_matches('.');
+    if trimmed.is_empty() {
+        "book".to_string()
+    } else {
+        trimmed.chars().take(180).collect()
+    }
+}
+
+fn extension_for(media_type: &str) -> Option<&'static str> {
+    let base = media_type.split(';').next().unwrap_or("").trim();
+    Some(match base {
+        "application/epub+zip" => "epub",
+        "application/pdf" => "pdf",
+        "application/x-mobipocket-ebook" => "mobi",
+        "application/vnd.amazon.ebook" | "application/x-mobi8-ebook" => "azw3",
+        "application/x-fictionbook+xml" | "text/fb2+xml" => "fb2",
+        "application/vnd.comicbook+zip" | "application/x-cbz" => "cbz",
+        "application/vnd.comicbook-rar" | "application/x-cbr" => "cbr",
+        "text/plain" => "txt",
+        _ => return None,
+    })
+}
+
+#[tauri::command]
+pub async fn opds_fetch_feed(url: String, auth: Option<OpdsAuth>) -> Result<OpdsFeed, String> {
+    let fetched = fetch(&url, auth.as_ref()).await?;
+    parse_feed(&fetched)
+}
+
+/// Run a search through the `search` link of a feed.
+#[tauri::command]
+pub async fn opds_search(
+    search: OpdsLink,
+    query: String,
+    auth: Option<OpdsAuth>,
+) -> Result<OpdsFeed, String> {
+    let is_description = search
+        .media_type
+        .as_deref()
+        .is_some_and(|t| t.contains("opensearchdescription"));
+    let template = if is_description && !search.templated {
+        let description = fetch(&search.href, auth.as_ref()).await?;
+        opensearch_template(&description.body, &description.url)
+            .ok_or_else(|| "OpenSearch description has no usable template".to_string())?
+    } else {
+        search.href
+    };
+    let url = expand_template(&template, &query);
+    let fetched = fetch(&url, auth.as_ref()).await?;
+    parse_feed(&fetched)
+}
+
+fn auth_store_key(catalog_id: &str) -> String {
+    format!("opds:{catalog_id}")
+}
+
+/// Queue an acquisition link for download into `dest_dir`. The file name is
+/// derived from `title` and the acquisition's media type.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
+pub fn opds_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    href: String,
+    title: String,
+    media_type: Option<String>,
+    dest_dir: String,
+    catalog_id: Option<String>,
+    auth: Option<OpdsAuth>,
+) -> Result<DownloadItem, String> {
+    let mut file_name = sanitize_file_name(&title);
+    if let Some(ext) = media_type.as_deref().and_then(extension_for) {
+        if !file_name.to_lowercase().ends_with(&format!(".{ext}")) {
+            file_name = format!("{file_name}.{ext}");
+        }
+    }
+    let dest_path = PathBuf::from(dest_dir).join(file_name);
+    let headers: HashMap<String, String> = auth.as_ref().map(auth_header).into_iter().collect();
+    // With a catalog to key it by, the header is re-read from the secure
+    // store at send time so a resumed download still authenticates; if the
+    // store refuses it, it's attached for this session only.
+    let (headers, auth_ref) = match catalog_id.filter(|_| !headers.is_empty()) {
+        Some(catalog_id) => {
+            let key = auth_store_key(&catalog_id);
+            let stored = serde_json::to_string(&headers)
+                .map_err(|e| e.to_string())
+                .and_then(|json| crate::secure_store::set(&app, &key, &json));
+            match stored {
+                Ok(()) => (HashMap::new(), Some(key)),
+                Err(e) => {
+                    log::warn!("Failed to store credentials for {key}: {e}");
+                    (headers, None)
+                }
+            }
+        }
+        None => (headers, None),
+    };
+    manager.enqueue(
+        &app,
+        href,
+        dest_path.to_string_lossy().to_string(),
+        headers,
+        auth_ref,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_opensearch_template() {
+        assert_eq!(
+            expand_template(
+                "https://x.org/search?q={searchTerms}&p={startPage?}",
+                "war & peace"
+            ),
+            "https://x.org/search?q=war%20%26%20peace&p="
+        );
+    }
+
+    #[test]
+    fn expands_rfc6570_query_template() {
+        assert_eq!(
+            expand_template("https://x.org/search{?query}", "dune"),
+            "https://x.org/search?query=dune"
+        );
+        assert_eq!(
+            expand_template("https://x.org/search?lang=en{&query,page}", "dune"),
+            "https://x.org/search?lang=en&query=dune"
+        );
+    }
+
+    #[test]
+    fn picks_atom_template_from_description() {
+        let osd = br#"<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+          <Url type="text/html" template="/html?q={searchTerms}"/>
+          <Url type="application/atom+xml" template="/opds/search/{searchTerms}"/>
+        </OpenSearchDescription>"#;
+        let base = Url::parse("https://x.org/opensearch.xml").unwrap();
+        assert_eq!(
+            opensearch_template(osd, &base).as_deref(),
+            Some("https://x.org/opds/search/{searchTerms}")
+        );
+    }
+
+    #[test]
+    fn basic_auth_header() {
+        let (key, value) = auth_header(&OpdsAuth::Basic {
+            username: "user".into(),
+            password: "pass".into(),
+        });
+        assert_eq!(key, "Authorization");
+        assert_eq!(value, "Basic dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn file_name_is_sanitized_and_extended() {
+        assert_eq!(sanitize_file_name("A/B: C?"), "A_B_ C_");
+        assert_eq!(sanitize_file_name("..."), "book");
+        assert_eq!(extension_for("application/epub+zip"), Some("epub"));
+        assert_eq!(
+            extension_for("application/pdf; charset=binary"),
+            Some("pdf")
+        );
+        assert_eq!(extension_for("text/html"), None);
+    }
+}
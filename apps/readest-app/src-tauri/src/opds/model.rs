@@ -0,0 +1,190 @@
+//! Typed OPDS model shared by the 1.2 (Atom) and 2.0 (JSON) parsers.
+//!
+//! Both catalog generations are flattened into the same shape so the
+//! frontend never branches on the feed version. Every `href` is resolved
+//! against the URL the document was fetched from before it leaves Rust.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsLink {
+    pub href: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// OPDS 2 RFC 6570 template (`{?query}`) that needs expanding.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub templated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsPrice {
+    pub value: f64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsAcquisition {
+    pub href: String,
+    /// `open-access`, `borrow`, `buy`, `sample`, `subscribe` or plain
+    /// `acquisition` — the suffix of the `http://opds-spec.org/acquisition`
+    /// relation.
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<OpdsPrice>,
+    /// Media types reached through `indirectAcquisition`, outermost first
+    /// (e.g. an ACSM wrapping an EPUB).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub indirect_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsNavigation {
+    pub title: String,
+    pub href: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsPublication {
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series_index: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    pub acquisitions: Vec<OpdsAcquisition>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<OpdsLink>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsGroup {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    pub navigation: Vec<OpdsNavigation>,
+    pub publications: Vec<OpdsPublication>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpdsFeed {
+    /// `1.2` or `2.0`.
+    pub version: String,
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    pub links: Vec<OpdsLink>,
+    pub navigation: Vec<OpdsNavigation>,
+    pub publications: Vec<OpdsPublication>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<OpdsGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<String>,
+    /// The catalog's search link, passed back to `opds_search` as-is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<OpdsLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_results: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items_per_page: Option<u64>,
+}
+
+impl OpdsFeed {
+    /// Route a feed-level link into the paging/search slots.
+    pub(super) fn absorb_link(&mut self, link: OpdsLink) {
+        match link.rel.as_deref() {
+            Some("next") => self.next = Some(link.href.clone()),
+            Some("previous") | Some("prev") => self.previous = Some(link.href.clone()),
+            Some("first") => self.first = Some(link.href.clone()),
+            Some("last") => self.last = Some(link.href.clone()),
+            Some("search") => {
+                // Prefer an Atom/OPDS-typed search over e.g. an HTML one.
+                let better = match self.search.as_ref().map(|s| s.media_type.as_deref()) {
+                    None | Some(None) => true,
+                    Some(Some(t)) => t.contains("html"),
+                };
+                if better {
+                    self.search = Some(link.clone());
+                }
+            }
+            _ => {}
+        }
+        self.links.push(link);
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OpdsAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+pub const ACQUISITION_REL: &str = "http://opds-spec.org/acquisition";
+pub const IMAGE_RELS: &[&str] = &["http://opds-spec.org/image", "http://opds-spec.org/cover"];
+pub const THUMBNAIL_RELS: &[&str] = &[
+    "http://opds-spec.org/image/thumbnail",
+    "http://opds-spec.org/thumbnail",
+];
+
+/// `kind` for an acquisition relation, or `None` if `rel` isn't one.
+pub fn acquisition_kind(rel: &str) -> Option<String> {
+    let rest = rel.strip_prefix(ACQUISITION_REL)?;
+    match rest.strip_prefix('/') {
+        Some(kind) if !kind.is_empty() => Some(kind.to_string()),
+        _ if rest.is_empty() => Some("acquisition".to_string()),
+        _ => None,
+    }
+}
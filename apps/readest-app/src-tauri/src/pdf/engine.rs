@@ -0,0 +1,165 @@
+//! The PDFium render thread.
+//!
+//! PDFium isn't thread-safe and pdfium-render's documents borrow the
+//! library handle, so one long-lived thread owns both the library and
+//! every open document. Commands hand it jobs over a channel with
+//! [`PdfEngine::call`] and block on the reply, which keeps rendering off
+//! the UI thread and serialises it, so at most one page bitmap is being
+//! produced at a time.
+
+#[cfg(feature = "pdfium")]
+use std::collections::HashMap;
+#[cfg(feature = "pdfium")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "pdfium")]
+use std::sync::mpsc::{self, Sender};
+#[cfg(feature = "pdfium")]
+use std::sync::Mutex;
+
+#[cfg(feature = "pdfium")]
+use pdfium_render::prelude::*;
+
+#[cfg(not(feature = "pdfium"))]
+pub const UNAVAILABLE: &str = "PDFium rendering is not available in this build";
+
+#[cfg(feature = "pdfium")]
+const THREAD_EXITED: &str = "PDFium render thread exited";
+
+/// The documents open on the render thread, keyed by the id `open_pdf`
+/// handed out.
+#[cfg(feature = "pdfium")]
+pub struct Documents {
+    pdfium: &'static Pdfium,
+    open: HashMap<String, PdfDocument<'static>>,
+}
+
+#[cfg(feature = "pdfium")]
+impl Documents {
+    /// Load a document without keeping it open.
+    pub fn load(
+        &self,
+        path: &Path,
+        password: Option<String>,
+    ) -> Result<PdfDocument<'static>, String> {
+        // pdfium-render ties the password's lifetime to the document's.
+        // Passwords are short and rare, so leaking them is the simple way
+        // to satisfy that.
+        let password: Option<&'static str> = password.map(|p| &*Box::leak(p.into_boxed_str()));
+        self.pdfium
+            .load_pdf_from_file(path, password)
+            .map_err(|e| format!("open {}: {e}", path.display()))
+    }
+
+    pub fn open(
+        &mut self,
+        id: String,
+        path: &Path,
+        password: Option<String>,
+    ) -> Result<&PdfDocument<'static>, String> {
+        let doc = self.load(path, password)?;
+        Ok(self.open.entry(id).or_insert(doc))
+    }
+
+    pub fn get(&self, id: &str) -> Result<&PdfDocument<'static>, String> {
+        self.open
+            .get(id)
+            .ok_or_else(|| format!("no open PDF: {id}"))
+    }
+
+    pub fn page(&self, id: &str, index: usize) -> Result<PdfPage<'static>, String> {
+        let page_index = u16::try_from(index).map_err(|_| format!("page {index} out of range"))?;
+        self.get(id)?
+            .pages()
+            .get(page_index)
+            .map_err(|e| format!("page {index}: {e}"))
+    }
+
+    pub fn page_count(&self, id: &str) -> Result<usize, String> {
+        Ok(self.get(id)?.pages().len() as usize)
+    }
+
+    pub fn close(&mut self, id: &str) {
+        self.open.remove(id);
+    }
+}
+
+#[cfg(feature = "pdfium")]
+type Job = Box<dyn FnOnce(&mut Documents) + Send>;
+
+#[derive(Default)]
+pub struct PdfEngine {
+    #[cfg(feature = "pdfium")]
+    jobs: Mutex<Option<Sender<Job>>>,
+}
+
+/// Load PDFium from `library_dir` (the app's bundled copy) or, failing
+/// that, from the system library path, which is also where Android finds
+/// the `libpdfium.so` packaged in the APK.
+#[cfg(feature = "pdfium")]
+fn bind(library_dir: Option<&Path>) -> Result<Pdfium, String> {
+    let bundled = library_dir
+        .map(|dir| Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(dir)));
+    let bindings = match bundled {
+        Some(Ok(bindings)) => bindings,
+        _ => Pdfium::bind_to_system_library().map_err(|e| format!("load PDFium: {e}"))?,
+    };
+    Ok(Pdfium::new(bindings))
+}
+
+#[cfg(feature = "pdfium")]
+impl PdfEngine {
+    /// Start the render thread unless it is already running. Fails when the
+    /// PDFium library can't be loaded.
+    pub fn start(&self, library_dir: Option<PathBuf>) -> Result<(), String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.is_some() {
+            return Ok(());
+        }
+        let (tx, rx) = mpsc::channel::<Job>();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("pdfium".into())
+            .spawn(move || {
+                let pdfium = match bind(library_dir.as_deref()) {
+                    Ok(pdfium) => pdfium,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+                // Lives as long as the thread, which is as long as the app.
+                let pdfium: &'static Pdfium = Box::leak(Box::new(pdfium));
+                let mut docs = Documents {
+                    pdfium,
+                    open: HashMap::new(),
+                };
+                for job in rx {
+                    job(&mut docs);
+                }
+            })
+            .map_err(|e| format!("spawn PDFium thread: {e}"))?;
+        ready_rx.recv().map_err(|_| THREAD_EXITED.to_string())??;
+        *jobs = Some(tx);
+        Ok(())
+    }
+
+    /// Run `job` on the render thread and wait for its result.
+    pub fn call<T: Send + 'static>(
+        &self,
+        job: impl FnOnce(&mut Documents) -> Result<T, String> + Send + 'static,
+    ) -> Result<T, String> {
+        let tx = self
+            .jobs
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| "no PDF is open".to_string())?;
+        let (reply_tx, reply_rx) = mpsc::channel();
+        tx.send(Box::new(move |docs: &mut Documents| {
+            let _ = reply_tx.send(job(docs));
+        }))
+        .map_err(|_| THREAD_EXITED.to_string())?;
+        reply_rx.recv().map_err(|_| THREAD_EXITED.to_string())?
+    }
+}
@@ -0,0 +1,395 @@
+//! Margin detection for PDFs, for cropping scanned pages to their content.
+//!
+//! `detect_pdf_margins` renders every page of an opened PDF at a small
+//! size, finds the box around its ink and returns one crop rectangle per
+//! page. The `pdfpage` scheme applies a rectangle passed as `crop=x,y,w,h`.
+//!
+//! Cropping each page to its own content makes the text jump in size and
+//! position from page to page, so by default the boxes are pooled: odd
+//! and even pages get one crop each (their margins differ on bound scans),
+//! taken generously enough that a single full-bleed page doesn't undo the
+//! crop for the rest. Blank pages aren't cropped.
+
+#![cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+
+use std::ops::Range;
+
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[cfg(feature = "pdfium")]
+use super::engine::PdfEngine;
+#[cfg(not(feature = "pdfium"))]
+use super::engine::UNAVAILABLE;
+#[cfg(feature = "pdfium")]
+use tauri::Manager;
+
+/// Width pages are rendered at for analysis.
+const ANALYSIS_WIDTH: i32 = 300;
+/// Pixels darker than this count as ink.
+const INK_LUMA: u8 = 200;
+/// Rows and columns need this many ink pixels to count as content, so
+/// dust and speckle in the margins are ignored.
+const MIN_INK: u32 = 2;
+/// Rows and columns at the edges that are mostly ink are the dark borders
+/// scanners leave around the paper, not content.
+const BORDER_FRACTION: f32 = 0.8;
+const DEFAULT_PADDING: f32 = 0.01;
+/// Pooled crops cover this share of the pages' content boxes on each side.
+const POOL_PERCENTILE: f32 = 0.95;
+/// Crops smaller than this share of the page are ignored as misdetections.
+const MIN_CROP: f32 = 0.1;
+
+/// A crop rectangle as fractions of the page, origin at the top-left.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfCrop {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PdfCrop {
+    pub const FULL: PdfCrop = PdfCrop {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    fn from_edges(left: f32, top: f32, right: f32, bottom: f32) -> PdfCrop {
+        let (left, top) = (left.clamp(0.0, 1.0), top.clamp(0.0, 1.0));
+        let (right, bottom) = (right.clamp(left, 1.0), bottom.clamp(top, 1.0));
+        PdfCrop {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    fn pad(&self, padding: f32) -> PdfCrop {
+        PdfCrop::from_edges(
+            self.x - padding,
+            self.y - padding,
+            self.right() + padding,
+            self.bottom() + padding,
+        )
+    }
+
+    /// `x,y,w,h` as used in `pdfpage` URLs. Rejects rectangles outside the
+    /// page and ones too small to be a real crop.
+    pub fn parse(value: &str) -> Option<PdfCrop> {
+        let mut parts = value.split(',').map(|v| v.trim().parse::<f32>().ok());
+        let crop = PdfCrop {
+            x: parts.next()??,
+            y: parts.next()??,
+            width: parts.next()??,
+            height: parts.next()??,
+        };
+        let valid = parts.next().is_none()
+            && crop.x >= 0.0
+            && crop.y >= 0.0
+            && crop.width >= MIN_CROP
+            && crop.height >= MIN_CROP
+            && crop.right() <= 1.0 + f32::EPSILON
+            && crop.bottom() <= 1.0 + f32::EPSILON;
+        valid.then_some(crop)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CropMode {
+    /// Every page cropped to its own content.
+    Page,
+    /// One crop for odd pages and one for even pages.
+    #[default]
+    OddEven,
+    /// One crop for the whole document.
+    Uniform,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarginOptions {
+    pub mode: CropMode,
+    /// Space kept around the content, as a fraction of the page.
+    pub padding: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfMargins {
+    /// One entry per page; `None` for pages to show uncropped.
+    pub pages: Vec<Option<PdfCrop>>,
+}
+
+/// Ink pixels per row and per column within `xs`×`ys`.
+fn profiles(image: &GrayImage, xs: Range<u32>, ys: Range<u32>) -> (Vec<u32>, Vec<u32>) {
+    let (width, height) = image.dimensions();
+    let mut rows = vec![0u32; height as usize];
+    let mut cols = vec![0u32; width as usize];
+    for y in ys {
+        for x in xs.clone() {
+            if image.get_pixel(x, y).0[0] < INK_LUMA {
+                rows[y as usize] += 1;
+                cols[x as usize] += 1;
+            }
+        }
+    }
+    (rows, cols)
+}
+
+/// `profile` without the scanner borders at either end.
+fn inside_borders(profile: &[u32], length: u32) -> Range<u32> {
+    let border = (length as f32 * BORDER_FRACTION) as u32;
+    let mut start = 0;
+    let mut end = profile.len();
+    while start < end && profile[start] > border {
+        start += 1;
+    }
+    while end > start && profile[end - 1] > border {
+        end -= 1;
+    }
+    start as u32..end as u32
+}
+
+/// The first and last index of `profile` that hold content.
+fn ink_extent(profile: &[u32]) -> Option<(usize, usize)> {
+    let first = profile.iter().position(|&n| n >= MIN_INK)?;
+    let last = profile.iter().rposition(|&n| n >= MIN_INK)?;
+    Some((first, last))
+}
+
+/// The box around the ink on a grayscale rendering of a page, or `None`
+/// for a blank page.
+fn content_box(image: &GrayImage) -> Option<PdfCrop> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    // Find the borders first, then look for content inside them only, so
+    // a border along one edge doesn't count as ink in every row or column
+    // crossing it.
+    let (rows, cols) = profiles(image, 0..width, 0..height);
+    let xs = inside_borders(&cols, height);
+    let ys = inside_borders(&rows, width);
+    let (rows, cols) = profiles(image, xs, ys);
+    let (top, bottom) = ink_extent(&rows)?;
+    let (left, right) = ink_extent(&cols)?;
+    let (w, h) = (width as f32, height as f32);
+    Some(PdfCrop::from_edges(
+        left as f32 / w,
+        top as f32 / h,
+        (right + 1) as f32 / w,
+        (bottom + 1) as f32 / h,
+    ))
+}
+
+/// The value below which `share` of `values` fall.
+fn percentile(values: &mut [f32], share: f32) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let i = ((values.len() - 1) as f32 * share).round() as usize;
+    values[i]
+}
+
+/// One crop covering most of `boxes`: each edge is pushed out far enough
+/// to take in [`POOL_PERCENTILE`] of the pages.
+fn pool(boxes: &[PdfCrop]) -> Option<PdfCrop> {
+    if boxes.is_empty() {
+        return None;
+    }
+    let edges = |f: fn(&PdfCrop) -> f32| boxes.iter().map(f).collect::<Vec<_>>();
+    let low = 1.0 - POOL_PERCENTILE;
+    Some(PdfCrop::from_edges(
+        percentile(&mut edges(|b| b.x), low),
+        percentile(&mut edges(|b| b.y), low),
+        percentile(&mut edges(PdfCrop::right), POOL_PERCENTILE),
+        percentile(&mut edges(PdfCrop::bottom), POOL_PERCENTILE),
+    ))
+}
+
+/// Crops for every page from their content boxes.
+fn crops(boxes: &[Option<PdfCrop>], mode: CropMode, padding: f32) -> Vec<Option<PdfCrop>> {
+    let group = |index: usize| match mode {
+        CropMode::Page => index,
+        CropMode::OddEven => index % 2,
+        CropMode::Uniform => 0,
+    };
+    let pooled: Vec<Option<PdfCrop>> = match mode {
+        CropMode::Page => boxes.to_vec(),
+        _ => {
+            let groups = if mode == CropMode::OddEven { 2 } else { 1 };
+            (0..groups)
+                .map(|g| {
+                    let members: Vec<PdfCrop> = boxes
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, _)| group(i) == g)
+                        .filter_map(|(_, b)| *b)
+                        .collect();
+                    pool(&members)
+                })
+                .collect()
+        }
+    };
+    boxes
+        .iter()
+        .enumerate()
+        .map(|(i, content)| {
+            content.as_ref()?;
+            let crop = pooled.get(group(i)).copied().flatten()?.pad(padding);
+            let worthwhile =
+                crop.width >= MIN_CROP && crop.height >= MIN_CROP && crop != PdfCrop::FULL;
+            worthwhile.then_some(crop)
+        })
+        .collect()
+}
+
+/// Detect the content box of every page of an opened PDF and turn them
+/// into crop rectangles for the `pdfpage` scheme.
+#[tauri::command]
+pub async fn detect_pdf_margins(
+    app: AppHandle,
+    id: String,
+    options: Option<MarginOptions>,
+) -> Result<PdfMargins, String> {
+    let options = options.unwrap_or_default();
+    let padding = options.padding.unwrap_or(DEFAULT_PADDING).clamp(0.0, 0.25);
+    #[cfg(feature = "pdfium")]
+    {
+        use pdfium_render::prelude::PdfRenderConfig;
+
+        tauri::async_runtime::spawn_blocking(move || {
+            let engine = app.state::<PdfEngine>();
+            let pages = {
+                let id = id.clone();
+                engine.call(move |docs| docs.page_count(&id))?
+            };
+            let mut boxes = Vec::with_capacity(pages);
+            for index in 0..pages {
+                let id = id.clone();
+                // One job per page, so page rendering can get in between.
+                let image = engine.call(move |docs| {
+                    let config = PdfRenderConfig::new()
+                        .set_target_width(ANALYSIS_WIDTH)
+                        .set_maximum_height(ANALYSIS_WIDTH * 4);
+                    docs.page(&id, index)?
+                        .render_with_config(&config)
+                        .map(|bitmap| bitmap.as_image())
+                        .map_err(|e| format!("render page {index}: {e}"))
+                })?;
+                boxes.push(content_box(&image.to_luma8()));
+            }
+            Ok(PdfMargins {
+                pages: crops(&boxes, options.mode, padding),
+            })
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = (app, id, padding);
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn crop(x: f32, y: f32, width: f32, height: f32) -> PdfCrop {
+        PdfCrop {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn assert_close(a: Option<PdfCrop>, b: Option<PdfCrop>) {
+        let close = match (a, b) {
+            (Some(a), Some(b)) => [a.x - b.x, a.y - b.y, a.width - b.width, a.height - b.height]
+                .iter()
+                .all(|d| d.abs() < 1e-4),
+            (a, b) => a == b,
+        };
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    fn page_with_ink(x: std::ops::Range<u32>, y: std::ops::Range<u32>) -> GrayImage {
+        GrayImage::from_fn(100, 200, |px, py| {
+            if x.contains(&px) && y.contains(&py) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        })
+    }
+
+    #[test]
+    fn finds_the_ink_and_ignores_specks_and_borders() {
+        let mut page = page_with_ink(20..80, 40..160);
+        // A speck of dust in the margin.
+        page.put_pixel(5, 5, Luma([0]));
+        // A black scanner border down the right edge.
+        for y in 0..200 {
+            for x in 97..100 {
+                page.put_pixel(x, y, Luma([0]));
+            }
+        }
+        assert_close(content_box(&page), Some(crop(0.2, 0.2, 0.6, 0.6)));
+        assert_eq!(
+            content_box(&GrayImage::from_pixel(100, 200, Luma([255]))),
+            None
+        );
+    }
+
+    #[test]
+    fn odd_and_even_pages_are_pooled_separately() {
+        let recto = Some(crop(0.3, 0.1, 0.6, 0.8));
+        let verso = Some(crop(0.1, 0.1, 0.6, 0.8));
+        let boxes = [recto, verso, recto, None, recto];
+        let out = crops(&boxes, CropMode::OddEven, 0.0);
+        assert_close(out[0], recto);
+        assert_close(out[1], verso);
+        assert_eq!(out[3], None);
+        let uniform = crops(&boxes, CropMode::Uniform, 0.0);
+        assert_close(uniform[0], Some(crop(0.1, 0.1, 0.8, 0.8)));
+    }
+
+    #[test]
+    fn one_full_bleed_page_does_not_undo_the_crop() {
+        let mut boxes = vec![Some(crop(0.2, 0.2, 0.6, 0.6)); 30];
+        boxes[7] = Some(PdfCrop::FULL);
+        let out = crops(&boxes, CropMode::Uniform, 0.0);
+        assert_close(out[0], Some(crop(0.2, 0.2, 0.6, 0.6)));
+        // The page's own crop, though, would be no crop at all.
+        assert_eq!(crops(&boxes, CropMode::Page, 0.0)[7], None);
+    }
+
+    #[test]
+    fn parses_url_crops() {
+        assert_eq!(
+            PdfCrop::parse("0.1,0.2,0.8,0.7"),
+            Some(crop(0.1, 0.2, 0.8, 0.7))
+        );
+        assert_eq!(PdfCrop::parse("0.5,0,0.6,1"), None);
+        assert_eq!(PdfCrop::parse("0,0,0.05,1"), None);
+        assert_eq!(PdfCrop::parse("0,0,1"), None);
+        assert_eq!(PdfCrop::parse("0,0,1,1,1"), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! Native PDF backend built on PDFium, behind the `pdfium` feature.
+//!
+//! pdf.js rendering inside the WebView is slow and memory-hungry on
+//! Android, where large scanned PDFs were a regular source of ANRs. With
+//! this backend the pages are rasterized by PDFium on a dedicated thread
+//! ([`engine`]) and handed to the WebView as images sized for the screen
+//! ([`render`]). The text layer ([`text`]), bookmarks and page labels
+//! ([`outline`]) are read there too, and pages are measured for margin
+//! cropping ([`margins`]). The PDFium shared library is loaded
+//! at runtime from the app's resources or the system library path; builds
+//! without the feature, or devices without the library, report the backend
+//! as unavailable and the reader stays on pdf.js.
+
+pub mod engine;
+pub mod margins;
+pub mod outline;
+pub mod render;
+pub mod text;
@@ -0,0 +1,206 @@
+//! Bookmarks and page labels of a PDF.
+//!
+//! `get_pdf_outline` reads the bookmark tree for the TOC and the page
+//! labels the document defines (front matter as i, ii, iii, then 1, 2,
+//! 3...), so the TOC and the page numbers shown in the reader match the
+//! printed ones. It takes a path rather than an `open_pdf` id: the
+//! document is loaded on the render thread just for the call.
+
+#![cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::transfer_file::ensure_path_allowed;
+
+#[cfg(feature = "pdfium")]
+use super::engine::PdfEngine;
+#[cfg(not(feature = "pdfium"))]
+use super::engine::UNAVAILABLE;
+#[cfg(feature = "pdfium")]
+use pdfium_render::prelude::*;
+#[cfg(feature = "pdfium")]
+use std::path::Path;
+#[cfg(feature = "pdfium")]
+use tauri::Manager;
+
+/// Bounds for malformed outlines whose sibling or child links loop.
+const MAX_DEPTH: usize = 32;
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfTocItem {
+    pub title: String,
+    /// Zero-based page index; `None` when the bookmark points nowhere in
+    /// the document (an external link, or a broken destination).
+    pub page: Option<usize>,
+    /// The printed label of `page`.
+    pub page_label: Option<String>,
+    pub children: Vec<PdfTocItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfOutline {
+    pub toc: Vec<PdfTocItem>,
+    /// One label per page, or empty when the document's labels are just
+    /// its page numbers (or it defines none).
+    pub page_labels: Vec<String>,
+}
+
+/// Fill pages without a label with their page number, and drop the
+/// labels entirely when they add nothing over the page numbers.
+fn page_labels(labels: Vec<Option<String>>) -> Vec<String> {
+    let labels: Vec<String> = labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            label
+                .filter(|l| !l.trim().is_empty())
+                .unwrap_or_else(|| (i + 1).to_string())
+        })
+        .collect();
+    let plain = labels
+        .iter()
+        .enumerate()
+        .all(|(i, label)| *label == (i + 1).to_string());
+    if plain {
+        Vec::new()
+    } else {
+        labels
+    }
+}
+
+/// Give every item with a page the label of that page.
+fn label_items(items: &mut [PdfTocItem], labels: &[String]) {
+    for item in items {
+        item.page_label = item.page.and_then(|p| labels.get(p).cloned());
+        label_items(&mut item.children, labels);
+    }
+}
+
+#[cfg(feature = "pdfium")]
+fn toc_items(first: Option<PdfBookmark>, depth: usize, budget: &mut usize) -> Vec<PdfTocItem> {
+    let mut items = Vec::new();
+    let mut next = first;
+    while let Some(bookmark) = next {
+        if *budget == 0 {
+            break;
+        }
+        *budget -= 1;
+        let page = bookmark
+            .destination()
+            .and_then(|d| d.page_index().ok())
+            .map(|i| i as usize);
+        let children = if depth + 1 < MAX_DEPTH {
+            toc_items(bookmark.first_child(), depth + 1, budget)
+        } else {
+            Vec::new()
+        };
+        items.push(PdfTocItem {
+            title: bookmark.title().unwrap_or_default().trim().to_string(),
+            page,
+            page_label: None,
+            children,
+        });
+        next = bookmark.next_sibling();
+    }
+    items
+}
+
+#[cfg(feature = "pdfium")]
+fn read_outline(doc: &PdfDocument) -> PdfOutline {
+    let mut budget = MAX_ENTRIES;
+    let mut toc = toc_items(doc.bookmarks().root(), 0, &mut budget);
+    let labels = page_labels(
+        doc.pages()
+            .iter()
+            .map(|page| page.label().map(str::to_string))
+            .collect(),
+    );
+    label_items(&mut toc, &labels);
+    PdfOutline {
+        toc,
+        page_labels: labels,
+    }
+}
+
+#[tauri::command]
+pub async fn get_pdf_outline(
+    app: AppHandle,
+    file_path: String,
+    password: Option<String>,
+) -> Result<PdfOutline, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    #[cfg(feature = "pdfium")]
+    {
+        let library_dir = app.path().resource_dir().ok();
+        tauri::async_runtime::spawn_blocking(move || {
+            let engine = app.state::<PdfEngine>();
+            engine.start(library_dir)?;
+            engine.call(move |docs| {
+                let doc = docs.load(Path::new(&file_path), password)?;
+                Ok(read_outline(&doc))
+            })
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = password;
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, page: Option<usize>, children: Vec<PdfTocItem>) -> PdfTocItem {
+        PdfTocItem {
+            title: title.into(),
+            page,
+            page_label: None,
+            children,
+        }
+    }
+
+    #[test]
+    fn roman_front_matter_keeps_its_labels() {
+        let labels = page_labels(vec![
+            Some("i".into()),
+            Some("ii".into()),
+            Some("1".into()),
+            None,
+        ]);
+        assert_eq!(labels, ["i", "ii", "1", "4"]);
+    }
+
+    #[test]
+    fn plain_page_numbers_are_dropped() {
+        assert!(page_labels(vec![Some("1".into()), None, Some(" ".into())]).is_empty());
+        assert!(page_labels(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn toc_items_get_their_page_labels() {
+        let labels = page_labels(vec![Some("i".into()), Some("1".into()), Some("2".into())]);
+        let mut toc = vec![
+            item("Preface", Some(0), vec![]),
+            item(
+                "Part One",
+                Some(1),
+                vec![
+                    item("Chapter 1", Some(2), vec![]),
+                    item("Web", None, vec![]),
+                ],
+            ),
+        ];
+        label_items(&mut toc, &labels);
+        assert_eq!(toc[0].page_label.as_deref(), Some("i"));
+        assert_eq!(toc[1].children[0].page_label.as_deref(), Some("2"));
+        assert_eq!(toc[1].children[1].page_label, None);
+    }
+}
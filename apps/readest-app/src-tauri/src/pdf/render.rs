@@ -0,0 +1,502 @@
+//! Opening PDFs on the render thread and serving their pages as images.
+//!
+//! `open_pdf` loads the document and returns its page sizes in points, so
+//! the reader can lay out every page before any is rendered. Pages are
+//! then fetched through the custom `pdfpage` URI scheme:
+//!
+//! `http://pdfpage.localhost/<pdf id>/<page index>?w=<px>&h=<px>[&crop=x,y,w,h]`
+//! (`pdfpage://localhost/...` on macOS/Linux)
+//!
+//! The page is rendered to fit `w`×`h` device pixels (zoomed views just ask
+//! for a bigger box, capped at [`MAX_PIXELS`]) and returned as a PNG. With
+//! `crop`, a rectangle from `detect_pdf_margins`, only that part of the page
+//! is returned, scaled to fit the box.
+//! Recently rendered pages are kept in [`PdfPageCache`] so paging back and
+//! forth doesn't render them again. `close_pdf` drops the document and its
+//! cached pages.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::http::{Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, State, UriSchemeContext, UriSchemeResponder};
+
+use super::margins::PdfCrop;
+use crate::range_file::{cors_origin, error};
+use crate::transfer_file::ensure_path_allowed;
+
+#[cfg(feature = "pdfium")]
+use super::engine::PdfEngine;
+#[cfg(not(feature = "pdfium"))]
+use super::engine::UNAVAILABLE;
+#[cfg(feature = "pdfium")]
+use std::path::Path;
+
+/// Scheme name; the WebView reaches it at `http://pdfpage.localhost/`.
+pub const SCHEME: &str = "pdfpage";
+
+/// Upper bound for either side of the requested box.
+const MAX_EDGE: u32 = 8192;
+/// Upper bound for a rendered page, about a 4K screen at 2x zoom.
+const MAX_PIXELS: f64 = 16_000_000.0;
+/// Encoded pages kept across all open PDFs.
+const CACHE_BYTES: usize = 48 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfPageSize {
+    /// In PDF points.
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedPdf {
+    pub id: String,
+    pub pages: Vec<PdfPageSize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    width: u32,
+    height: u32,
+}
+
+#[cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+struct RenderedPage {
+    id: String,
+    index: usize,
+    bounds: Bounds,
+    crop: Option<PdfCrop>,
+    png: Arc<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct PdfPageCache {
+    pages: Mutex<VecDeque<RenderedPage>>,
+}
+
+impl PdfPageCache {
+    fn get(
+        &self,
+        id: &str,
+        index: usize,
+        bounds: Bounds,
+        crop: Option<PdfCrop>,
+    ) -> Option<Arc<Vec<u8>>> {
+        self.pages
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.id == id && p.index == index && p.bounds == bounds && p.crop == crop)
+            .map(|p| p.png.clone())
+    }
+
+    #[cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+    fn insert(&self, page: RenderedPage) {
+        let mut pages = self.pages.lock().unwrap();
+        pages.retain(|p| !(p.id == page.id && p.index == page.index));
+        pages.push_back(page);
+        let mut total: usize = pages.iter().map(|p| p.png.len()).sum();
+        while total > CACHE_BYTES && pages.len() > 1 {
+            if let Some(evicted) = pages.pop_front() {
+                total -= evicted.png.len();
+            }
+        }
+    }
+
+    fn evict(&self, id: &str) {
+        self.pages.lock().unwrap().retain(|p| p.id != id);
+    }
+}
+
+#[cfg(feature = "pdfium")]
+fn new_id() -> String {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "pdf-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The pixel size a `width`×`height` point page renders at to fit
+/// `bounds`, scaled down further if it would exceed [`MAX_PIXELS`].
+#[cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+fn render_size(width: f32, height: f32, bounds: Bounds) -> (u32, u32) {
+    let (width, height) = (width.max(1.0) as f64, height.max(1.0) as f64);
+    let mut scale = f64::min(bounds.width as f64 / width, bounds.height as f64 / height);
+    let pixels = width * height * scale * scale;
+    if pixels > MAX_PIXELS {
+        scale *= (MAX_PIXELS / pixels).sqrt();
+    }
+    let w = ((width * scale).round() as u32).max(1);
+    let h = ((height * scale).round() as u32).max(1);
+    (w, h)
+}
+
+/// The full-page size to render at so that `crop` of a `width`×`height`
+/// point page fits `bounds`, and the cropped part as `(x, y, w, h)` pixels
+/// of that rendering.
+#[cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+fn cropped_render_size(
+    width: f32,
+    height: f32,
+    crop: PdfCrop,
+    bounds: Bounds,
+) -> ((u32, u32), (u32, u32, u32, u32)) {
+    let (cw, ch) = render_size(width * crop.width, height * crop.height, bounds);
+    let w = ((cw as f32 / crop.width).round() as u32).max(cw);
+    let h = ((ch as f32 / crop.height).round() as u32).max(ch);
+    let x = ((crop.x * w as f32).round() as u32).min(w - cw);
+    let y = ((crop.y * h as f32).round() as u32).min(h - ch);
+    ((w, h), (x, y, cw, ch))
+}
+
+#[cfg(feature = "pdfium")]
+fn render_page(
+    engine: &PdfEngine,
+    id: &str,
+    index: usize,
+    bounds: Bounds,
+    crop: Option<PdfCrop>,
+) -> Result<Vec<u8>, String> {
+    use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+    use image::ImageEncoder;
+    use pdfium_render::prelude::PdfRenderConfig;
+
+    let id = id.to_string();
+    let image = engine.call(move |docs| {
+        let page = docs.page(&id, index)?;
+        let ((w, h), (x, y, cw, ch)) = cropped_render_size(
+            page.width().value,
+            page.height().value,
+            crop.unwrap_or(PdfCrop::FULL),
+            bounds,
+        );
+        let config = PdfRenderConfig::new().set_target_size(w as i32, h as i32);
+        let bitmap = page
+            .render_with_config(&config)
+            .map_err(|e| format!("render page {index}: {e}"))?;
+        Ok((bitmap.as_image(), (x, y, cw, ch)))
+    })?;
+    let (image, (x, y, cw, ch)) = image;
+    let image = if (cw, ch) == (image.width(), image.height()) {
+        image
+    } else {
+        image.crop_imm(x, y, cw, ch)
+    };
+    // Encoded here rather than on the render thread, so the next page can
+    // start rendering meanwhile. Fast compression: the PNG only crosses
+    // into the WebView, it isn't stored.
+    let rgb = image.to_rgb8();
+    drop(image);
+    let mut png = Vec::new();
+    PngEncoder::new_with_quality(&mut png, CompressionType::Fast, FilterType::Sub)
+        .write_image(
+            rgb.as_raw(),
+            rgb.width(),
+            rgb.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| format!("encode page {index}: {e}"))?;
+    Ok(png)
+}
+
+/// Open a PDF on the render thread. Fails when the backend isn't compiled
+/// in or PDFium can't be loaded, in which case the reader uses pdf.js.
+#[tauri::command]
+pub async fn open_pdf(
+    app: AppHandle,
+    file_path: String,
+    password: Option<String>,
+) -> Result<OpenedPdf, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    #[cfg(feature = "pdfium")]
+    {
+        let library_dir = app.path().resource_dir().ok();
+        tauri::async_runtime::spawn_blocking(move || {
+            let engine = app.state::<PdfEngine>();
+            engine.start(library_dir)?;
+            let id = new_id();
+            engine.call(move |docs| {
+                let doc = docs.open(id.clone(), Path::new(&file_path), password)?;
+                let pages = doc
+                    .pages()
+                    .iter()
+                    .map(|page| PdfPageSize {
+                        width: page.width().value,
+                        height: page.height().value,
+                    })
+                    .collect();
+                Ok(OpenedPdf { id, pages })
+            })
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = password;
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn close_pdf(
+    app: AppHandle,
+    cache: State<'_, PdfPageCache>,
+    id: String,
+) -> Result<(), String> {
+    cache.evict(&id);
+    #[cfg(feature = "pdfium")]
+    {
+        tauri::async_runtime::spawn_blocking(move || {
+            app.state::<PdfEngine>().call(move |docs| {
+                docs.close(&id);
+                Ok(())
+            })
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = app;
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PageRequest {
+    id: String,
+    index: usize,
+    bounds: Bounds,
+    crop: Option<PdfCrop>,
+}
+
+fn parse_request(uri_path: &str, uri_query: Option<&str>) -> Option<PageRequest> {
+    let (id, index) = uri_path.trim_start_matches('/').split_once('/')?;
+    if id.is_empty() {
+        return None;
+    }
+    let index = index.parse().ok()?;
+    let (mut width, mut height, mut crop) = (None, None, None);
+    for pair in uri_query?.split('&') {
+        let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "w" => width = val.parse::<u32>().ok(),
+            "h" => height = val.parse::<u32>().ok(),
+            // Ignored rather than refused when invalid: the page is still
+            // worth showing uncropped.
+            "crop" => {
+                crop =
+                    PdfCrop::parse(&percent_encoding::percent_decode_str(val).decode_utf8_lossy())
+            }
+            _ => {}
+        }
+    }
+    let edge = |v: Option<u32>| v.filter(|&v| v > 0).map(|v| v.min(MAX_EDGE));
+    Some(PageRequest {
+        id: id.to_string(),
+        index,
+        bounds: Bounds {
+            width: edge(width)?,
+            height: edge(height)?,
+        },
+        crop,
+    })
+}
+
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    // Waits on the render thread, so keep it off the WebView's request
+    // thread.
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        responder.respond(build_response(&app, &request));
+    });
+}
+
+fn build_response<R: Runtime>(app: &AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let origin = cors_origin(request);
+
+    let Some(req) = parse_request(request.uri().path(), request.uri().query()) else {
+        return error(&origin, StatusCode::BAD_REQUEST);
+    };
+    let Some(cache) = app.try_state::<PdfPageCache>() else {
+        return error(&origin, StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    let png = match cache.get(&req.id, req.index, req.bounds, req.crop) {
+        Some(png) => png,
+        #[cfg(feature = "pdfium")]
+        None => {
+            let Some(engine) = app.try_state::<PdfEngine>() else {
+                return error(&origin, StatusCode::SERVICE_UNAVAILABLE);
+            };
+            match render_page(&engine, &req.id, req.index, req.bounds, req.crop) {
+                Ok(png) => {
+                    let png = Arc::new(png);
+                    cache.insert(RenderedPage {
+                        id: req.id.clone(),
+                        index: req.index,
+                        bounds: req.bounds,
+                        crop: req.crop,
+                        png: png.clone(),
+                    });
+                    png
+                }
+                Err(e) => {
+                    log::warn!("pdfpage: page {} of {}: {e}", req.index, req.id);
+                    return error(&origin, StatusCode::NOT_FOUND);
+                }
+            }
+        }
+        #[cfg(not(feature = "pdfium"))]
+        None => {
+            log::debug!("pdfpage: {UNAVAILABLE}");
+            return error(&origin, StatusCode::NOT_IMPLEMENTED);
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Access-Control-Allow-Origin", origin)
+        .header("Content-Type", "image/png")
+        .header("X-Content-Type-Options", "nosniff")
+        .header("Cache-Control", "no-store")
+        .header("Content-Length", png.len().to_string())
+        .body(png.as_ref().clone())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_fit_the_box_and_the_pixel_cap() {
+        let screen = Bounds {
+            width: 1080,
+            height: 2000,
+        };
+        // US Letter: width-bound on a phone.
+        assert_eq!(render_size(612.0, 792.0, screen), (1080, 1398));
+        // Small pages are scaled up; PDF is vector.
+        assert_eq!(
+            render_size(
+                100.0,
+                100.0,
+                Bounds {
+                    width: 500,
+                    height: 800
+                }
+            ),
+            (500, 500)
+        );
+        let (w, h) = render_size(
+            612.0,
+            792.0,
+            Bounds {
+                width: MAX_EDGE,
+                height: MAX_EDGE,
+            },
+        );
+        assert!((w as f64) * (h as f64) <= MAX_PIXELS * 1.001);
+        assert_eq!(render_size(0.0, 0.0, screen), (1080, 1080));
+    }
+
+    #[test]
+    fn crops_render_the_page_larger_and_cut_out_the_rectangle() {
+        let screen = Bounds {
+            width: 1080,
+            height: 2000,
+        };
+        let crop = PdfCrop::parse("0.25,0.25,0.5,0.5").unwrap();
+        assert_eq!(
+            cropped_render_size(612.0, 792.0, crop, screen),
+            ((2160, 2796), (540, 699, 1080, 1398))
+        );
+        assert_eq!(
+            cropped_render_size(612.0, 792.0, PdfCrop::FULL, screen),
+            ((1080, 1398), (0, 0, 1080, 1398))
+        );
+    }
+
+    #[test]
+    fn cache_is_bounded_and_evicts_by_pdf() {
+        let cache = PdfPageCache::default();
+        let page = |id: &str, index, len| RenderedPage {
+            id: id.into(),
+            index,
+            bounds: Bounds {
+                width: 1,
+                height: 1,
+            },
+            crop: None,
+            png: Arc::new(vec![0; len]),
+        };
+        let b = Bounds {
+            width: 1,
+            height: 1,
+        };
+        cache.insert(page("a", 0, CACHE_BYTES / 2));
+        cache.insert(page("b", 0, CACHE_BYTES / 2));
+        cache.insert(page("b", 1, 10));
+        assert!(cache.get("a", 0, b, None).is_none());
+        assert!(cache.get("b", 0, b, None).is_some());
+        assert!(cache.get("b", 0, b, Some(PdfCrop::FULL)).is_none());
+        cache.evict("b");
+        assert!(cache.get("b", 1, b, None).is_none());
+    }
+
+    #[test]
+    fn parses_page_urls() {
+        assert_eq!(
+            parse_request("/pdf-1a-0/3", Some("w=1080&h=2340")),
+            Some(PageRequest {
+                id: "pdf-1a-0".into(),
+                index: 3,
+                bounds: Bounds {
+                    width: 1080,
+                    height: 2340
+                },
+                crop: None,
+            })
+        );
+        assert_eq!(
+            parse_request("/p/0", Some("w=10&h=10&crop=0.25%2C0.25%2C0.5%2C0.5"))
+                .unwrap()
+                .crop,
+            PdfCrop::parse("0.25,0.25,0.5,0.5")
+        );
+        assert_eq!(
+            parse_request("/p/0", Some("w=10&h=10&crop=bad"))
+                .unwrap()
+                .crop,
+            None
+        );
+        assert_eq!(
+            parse_request("/p/0", Some("w=99999&h=1"))
+                .unwrap()
+                .bounds
+                .width,
+            MAX_EDGE
+        );
+        assert!(parse_request("/p/0", Some("h=10")).is_none());
+        assert!(parse_request("/p/-1", Some("w=10&h=10")).is_none());
+        assert!(parse_request("/p/0", None).is_none());
+    }
+}
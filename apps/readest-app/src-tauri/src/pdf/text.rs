@@ -0,0 +1,434 @@
+//! The text layer of PDFs opened with `open_pdf`.
+//!
+//! `get_pdf_page_text` returns a page's text line by line with each line's
+//! box, which is what selection snapping and copy need. `search_pdf` finds
+//! a query across the whole document and returns, per hit, the page, an
+//! excerpt and the boxes to highlight, so search works without shipping
+//! the file to pdf.js.
+//!
+//! Boxes are in PDF points with the origin at the top-left of the page, the
+//! same space as the page sizes `open_pdf` reports. Matching ignores case
+//! unless asked not to, treats any run of whitespace (line breaks included)
+//! as one space, and skips the hyphens PDFium reports at line ends, so a
+//! phrase that wraps onto the next line is still found.
+
+#![cfg_attr(not(feature = "pdfium"), allow(dead_code))]
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[cfg(not(feature = "pdfium"))]
+use super::engine::UNAVAILABLE;
+#[cfg(feature = "pdfium")]
+use super::engine::{Documents, PdfEngine};
+#[cfg(feature = "pdfium")]
+use tauri::Manager;
+
+const DEFAULT_LIMIT: usize = 1000;
+/// Characters of context on each side of a hit.
+const EXCERPT_CHARS: usize = 40;
+/// PDFium's marker for a hyphen it detected at the end of a line.
+const LINE_END_HYPHEN: char = '\u{2}';
+const SOFT_HYPHEN: char = '\u{ad}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfTextRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PdfTextRect {
+    fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    fn union(&self, other: &PdfTextRect) -> PdfTextRect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        PdfTextRect {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+
+    /// Whether the two boxes sit on the same line: they overlap vertically
+    /// by at least half the shorter one's height.
+    fn same_line(&self, other: &PdfTextRect) -> bool {
+        let overlap = self.bottom().min(other.bottom()) - self.y.max(other.y);
+        overlap >= self.height.min(other.height) / 2.0
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfTextLine {
+    pub text: String,
+    pub rect: PdfTextRect,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfPageText {
+    pub index: usize,
+    /// The page's text in reading order, lines separated by `\n`.
+    pub text: String,
+    pub lines: Vec<PdfTextLine>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfSearchOptions {
+    pub case_sensitive: bool,
+    /// Stop after this many hits; defaults to 1000.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfExcerpt {
+    pub pre: String,
+    pub matched: String,
+    pub post: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfSearchHit {
+    pub index: usize,
+    pub excerpt: PdfExcerpt,
+    /// One box per line the hit spans.
+    pub rects: Vec<PdfTextRect>,
+}
+
+/// A page's characters with their boxes; `None` where PDFium has none,
+/// as for the line breaks it synthesises.
+#[derive(Debug, Default)]
+struct PageChars {
+    chars: Vec<char>,
+    boxes: Vec<Option<PdfTextRect>>,
+}
+
+#[cfg(feature = "pdfium")]
+fn page_chars(docs: &Documents, id: &str, index: usize) -> Result<PageChars, String> {
+    let page = docs.page(id, index)?;
+    let page_height = page.height().value;
+    let text = page
+        .text()
+        .map_err(|e| format!("text of page {index}: {e}"))?;
+    let mut out = PageChars::default();
+    for ch in text.chars().iter() {
+        let Some(c) = ch.unicode_char() else {
+            continue;
+        };
+        let rect = ch.loose_bounds().ok().map(|r| PdfTextRect {
+            x: r.left().value,
+            y: page_height - r.top().value,
+            width: r.width().value,
+            height: r.height().value,
+        });
+        out.chars.push(c);
+        out.boxes
+            .push(rect.filter(|r| r.width > 0.0 && r.height > 0.0));
+    }
+    Ok(out)
+}
+
+/// Merge the boxes of `range` into one box per line.
+fn line_rects(page: &PageChars, range: Range<usize>) -> Vec<PdfTextRect> {
+    let mut rects: Vec<PdfTextRect> = Vec::new();
+    for (c, rect) in page.chars[range.clone()].iter().zip(&page.boxes[range]) {
+        let Some(rect) = rect else {
+            continue;
+        };
+        if c.is_whitespace() {
+            continue;
+        }
+        match rects.last_mut() {
+            Some(last) if last.same_line(rect) => *last = last.union(rect),
+            _ => rects.push(*rect),
+        }
+    }
+    rects
+}
+
+fn lines(page: &PageChars) -> Vec<PdfTextLine> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for end in 0..=page.chars.len() {
+        let at_break = end == page.chars.len() || matches!(page.chars[end], '\r' | '\n');
+        if !at_break {
+            continue;
+        }
+        let text: String = page.chars[start..end]
+            .iter()
+            .filter(|&&c| c != LINE_END_HYPHEN && c != SOFT_HYPHEN)
+            .collect();
+        let rect = line_rects(page, start..end)
+            .into_iter()
+            .reduce(|a, b| a.union(&b));
+        if let Some(rect) = rect.filter(|_| !text.trim().is_empty()) {
+            out.push(PdfTextLine {
+                text: text.trim_end().to_string(),
+                rect,
+            });
+        }
+        start = end + 1;
+    }
+    out
+}
+
+/// `chars` normalised for matching, each with the index it came from.
+/// A word hyphenated across a line break is joined back together.
+fn normalize(chars: &[char], case_sensitive: bool) -> Vec<(char, usize)> {
+    let mut out: Vec<(char, usize)> = Vec::with_capacity(chars.len());
+    let mut joining = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if c == LINE_END_HYPHEN || c == SOFT_HYPHEN {
+            joining = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            if !joining && out.last().is_some_and(|&(last, _)| last != ' ') {
+                out.push((' ', i));
+            }
+            continue;
+        }
+        joining = false;
+        if case_sensitive {
+            out.push((c, i));
+        } else {
+            out.extend(c.to_lowercase().map(|l| (l, i)));
+        }
+    }
+    out
+}
+
+/// Non-overlapping matches of `query` in `chars`, as ranges of `chars`.
+fn find_matches(chars: &[char], query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    let query: Vec<char> = normalize(&query.trim().chars().collect::<Vec<_>>(), case_sensitive)
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let haystack = normalize(chars, case_sensitive);
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + query.len() <= haystack.len() {
+        let window = &haystack[i..i + query.len()];
+        if window.iter().map(|&(c, _)| c).eq(query.iter().copied()) {
+            matches.push(window[0].1..window[query.len() - 1].1 + 1);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+fn collapse(chars: &[char]) -> String {
+    let text: String = normalize(chars, true).into_iter().map(|(c, _)| c).collect();
+    text.trim_end().to_string()
+}
+
+fn excerpt(chars: &[char], range: Range<usize>) -> PdfExcerpt {
+    let pre_start = range.start.saturating_sub(EXCERPT_CHARS);
+    let post_end = (range.end + EXCERPT_CHARS).min(chars.len());
+    let pre = collapse(&chars[pre_start..range.start]);
+    let post = collapse(&chars[range.end..post_end]);
+    let spaced = |edge: Option<&char>| edge.is_some_and(|c| c.is_whitespace());
+    PdfExcerpt {
+        pre: if spaced(chars[..range.start].last()) {
+            format!("{pre} ")
+        } else {
+            pre
+        },
+        matched: collapse(&chars[range.clone()]),
+        post: if spaced(chars.get(range.end)) {
+            format!(" {post}")
+        } else {
+            post
+        },
+    }
+}
+
+fn search_page(
+    page: &PageChars,
+    index: usize,
+    query: &str,
+    case_sensitive: bool,
+) -> Vec<PdfSearchHit> {
+    find_matches(&page.chars, query, case_sensitive)
+        .into_iter()
+        .map(|range| PdfSearchHit {
+            index,
+            excerpt: excerpt(&page.chars, range.clone()),
+            rects: line_rects(page, range),
+        })
+        .collect()
+}
+
+/// The text of page `index`, with a box for each line.
+#[tauri::command]
+pub async fn get_pdf_page_text(
+    app: AppHandle,
+    id: String,
+    index: usize,
+) -> Result<PdfPageText, String> {
+    #[cfg(feature = "pdfium")]
+    {
+        tauri::async_runtime::spawn_blocking(move || {
+            let page = app
+                .state::<PdfEngine>()
+                .call(move |docs| page_chars(docs, &id, index))?;
+            let lines = lines(&page);
+            let text = lines
+                .iter()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(PdfPageText { index, text, lines })
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = (app, id, index);
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+/// Find `query` in every page of an opened PDF. Pages are read one render
+/// thread job at a time, so page rendering isn't held up by a long search.
+#[tauri::command]
+pub async fn search_pdf(
+    app: AppHandle,
+    id: String,
+    query: String,
+    options: Option<PdfSearchOptions>,
+) -> Result<Vec<PdfSearchHit>, String> {
+    let options = options.unwrap_or_default();
+    let limit = options.limit.unwrap_or(DEFAULT_LIMIT);
+    #[cfg(feature = "pdfium")]
+    {
+        tauri::async_runtime::spawn_blocking(move || {
+            let engine = app.state::<PdfEngine>();
+            let pages = {
+                let id = id.clone();
+                engine.call(move |docs| docs.page_count(&id))?
+            };
+            let mut hits = Vec::new();
+            for index in 0..pages {
+                let page = {
+                    let id = id.clone();
+                    engine.call(move |docs| page_chars(docs, &id, index))?
+                };
+                hits.extend(search_page(&page, index, &query, options.case_sensitive));
+                if hits.len() >= limit {
+                    hits.truncate(limit);
+                    break;
+                }
+            }
+            Ok(hits)
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+    }
+    #[cfg(not(feature = "pdfium"))]
+    {
+        let _ = (app, id, query, limit);
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Option<PdfTextRect> {
+        Some(PdfTextRect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Two lines, "Hello wor-" / "ld again", laid out 10pt per character.
+    fn sample() -> PageChars {
+        let mut page = PageChars::default();
+        for (line, text) in ["Hello wor\u{2}", "ld again"].iter().enumerate() {
+            if line > 0 {
+                page.chars.extend(['\r', '\n']);
+                page.boxes.extend([None, None]);
+            }
+            for (i, c) in text.chars().enumerate() {
+                page.chars.push(c);
+                page.boxes
+                    .push(rect(i as f32 * 10.0, line as f32 * 20.0, 10.0, 12.0));
+            }
+        }
+        page
+    }
+
+    #[test]
+    fn matches_across_case_whitespace_and_line_end_hyphens() {
+        let page = sample();
+        let text: String = page.chars.iter().collect();
+        let at = |s: &str| text.find(s).unwrap();
+        assert_eq!(
+            find_matches(&page.chars, "WORLD", false),
+            vec![at("wor")..at("ld") + 2]
+        );
+        assert!(find_matches(&page.chars, "WORLD", true).is_empty());
+        assert_eq!(
+            find_matches(&page.chars, "world   again", false),
+            vec![at("wor")..at("again") + 5]
+        );
+        assert!(find_matches(&page.chars, "  ", false).is_empty());
+    }
+
+    #[test]
+    fn hits_get_one_box_per_line_and_an_excerpt() {
+        let page = sample();
+        let hits = search_page(&page, 3, "world", false);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].index, 3);
+        assert_eq!(
+            hits[0].rects,
+            vec![
+                // Takes in the hyphen at the end of the first line.
+                rect(60.0, 0.0, 40.0, 12.0).unwrap(),
+                rect(0.0, 20.0, 20.0, 12.0).unwrap(),
+            ]
+        );
+        assert_eq!(
+            hits[0].excerpt,
+            PdfExcerpt {
+                pre: "Hello ".into(),
+                matched: "world".into(),
+                post: " again".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn lines_split_on_breaks() {
+        let lines = lines(&sample());
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["Hello wor", "ld again"]);
+        assert_eq!(lines[1].rect, rect(0.0, 20.0, 80.0, 12.0).unwrap());
+    }
+}
@@ -0,0 +1,132 @@
+//! Printing a chapter or a selection on desktop.
+//!
+//! `window.print()` in the webview prints nothing on macOS and only the
+//! visible page, columns and all, elsewhere. The reader sends the HTML to
+//! print to `print_content` instead, which shows it in a window of its own
+//! and opens the system print dialog from there, where the webview lays it
+//! out in pages. Each system's dialog saves a PDF too: the PDF button on
+//! macOS, "Microsoft Print to PDF" on Windows and "Print to File" on Linux.
+//!
+//! The print window loads the document from memory through the `printdoc`
+//! scheme with a policy that allows no scripts or network, and none of the
+//! app's capabilities apply to it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tauri::http::{Request, Response, StatusCode};
+use tauri::webview::PageLoadEvent;
+use tauri::{
+    AppHandle, Manager, Runtime, UriSchemeContext, UriSchemeResponder, Url, WebviewUrl,
+    WebviewWindowBuilder, WindowEvent,
+};
+
+/// Scheme name; the print window reaches it at `printdoc://localhost/<id>`
+/// (`http://printdoc.localhost/<id>` on Windows).
+pub const SCHEME: &str = "printdoc";
+
+const LABEL_PREFIX: &str = "print";
+
+/// Everything the document needs is inlined by the reader.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src data:; \
+    style-src 'unsafe-inline' data:; font-src data:";
+
+/// Documents waiting in, or shown by, a print window.
+#[derive(Default)]
+pub struct PrintJobs {
+    next_id: AtomicU64,
+    documents: Mutex<HashMap<u64, String>>,
+}
+
+fn document_url(id: u64) -> Result<Url, String> {
+    let url = if cfg!(target_os = "windows") {
+        format!("http://{SCHEME}.localhost/{id}")
+    } else {
+        format!("{SCHEME}://localhost/{id}")
+    };
+    Url::parse(&url).map_err(|e| e.to_string())
+}
+
+/// The job id in a `printdoc` URI path.
+fn document_id(uri_path: &str) -> Option<u64> {
+    uri_path.trim_start_matches('/').parse().ok()
+}
+
+fn response(status: StatusCode, body: Vec<u8>) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header("Content-Security-Policy", CONTENT_SECURITY_POLICY)
+        .header("Cache-Control", "no-store")
+        .body(body)
+        .unwrap()
+}
+
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let document = document_id(request.uri().path()).and_then(|id| {
+        let jobs = ctx.app_handle().try_state::<PrintJobs>()?;
+        let documents = jobs.documents.lock().unwrap();
+        documents.get(&id).cloned()
+    });
+    responder.respond(match document {
+        Some(html) => response(StatusCode::OK, html.into_bytes()),
+        None => response(StatusCode::NOT_FOUND, Vec::new()),
+    });
+}
+
+/// Show `html` in a print window titled `title` and open the print dialog
+/// once it has loaded. The window stays open as a preview until closed.
+#[tauri::command]
+pub fn print_content(app: AppHandle, html: String, title: String) -> Result<(), String> {
+    let jobs = app.state::<PrintJobs>();
+    let id = jobs.next_id.fetch_add(1, Ordering::Relaxed);
+    jobs.documents.lock().unwrap().insert(id, html);
+
+    let window = WebviewWindowBuilder::new(
+        &app,
+        format!("{LABEL_PREFIX}-{id}"),
+        WebviewUrl::External(document_url(id)?),
+    )
+    .title(title)
+    .inner_size(720.0, 900.0)
+    .on_page_load(|webview, payload| {
+        if payload.event() == PageLoadEvent::Finished {
+            if let Err(e) = webview.print() {
+                log::error!("Failed to open the print dialog: {e}");
+            }
+        }
+    })
+    .build()
+    .map_err(|e| {
+        jobs.documents.lock().unwrap().remove(&id);
+        format!("Failed to open the print window: {e}")
+    })?;
+
+    let app_handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            if let Some(jobs) = app_handle.try_state::<PrintJobs>() {
+                jobs.documents.lock().unwrap().remove(&id);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_are_found_by_the_id_in_their_url() {
+        let url = document_url(42).unwrap();
+        assert_eq!(document_id(url.path()), Some(42));
+        assert_eq!(document_id("/"), None);
+        assert_eq!(document_id("/../42"), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! Quote images for sharing a highlighted passage.
+//!
+//! `render_quote_image` lays out the passage with its book's author, title
+//! and cover on a card in the reader's theme colors and returns it as a PNG.
+//! Text goes through cosmic-text with the system's fonts, so any script the
+//! system can show comes out shaped, wrapped and in its direction, the same
+//! on every platform and without screenshotting the webview.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use cosmic_text::{
+    Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping, Style, SwashCache, Weight,
+};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+const WIDTH: u32 = 1080;
+const PADDING: f32 = 88.0;
+/// Tall enough for the longest quote at its smallest size.
+const MAX_HEIGHT: u32 = 2400;
+/// Longer passages are cut at a word and end with an ellipsis.
+const MAX_QUOTE_CHARS: usize = 700;
+const ACCENT_BAR_WIDTH: u32 = 8;
+const QUOTE_INDENT: f32 = 48.0;
+/// The cover next to the attribution fits in this box.
+const COVER_BOX: (u32, u32) = (128, 192);
+
+const DEFAULT_BACKGROUND: [u8; 3] = [250, 247, 240];
+const DEFAULT_FOREGROUND: [u8; 3] = [34, 34, 34];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteImageRequest {
+    text: String,
+    title: String,
+    #[serde(default)]
+    author: String,
+    chapter: Option<String>,
+    /// The book's cover on disk, shown when the asset scope allows it.
+    cover_path: Option<PathBuf>,
+    /// `#rrggbb` colors from the reader's theme.
+    background: Option<String>,
+    foreground: Option<String>,
+    /// The highlight's color, for the bar along the quote.
+    accent: Option<String>,
+}
+
+struct Fonts {
+    system: FontSystem,
+    swash: SwashCache,
+}
+
+impl Fonts {
+    fn load() -> Self {
+        Fonts {
+            system: FontSystem::new(),
+            swash: SwashCache::new(),
+        }
+    }
+}
+
+/// Loading the system's fonts takes a while, so it happens once, at the
+/// first quote.
+#[derive(Default)]
+pub struct QuoteImages(Mutex<Option<Fonts>>);
+
+struct Colors {
+    background: [u8; 3],
+    foreground: [u8; 3],
+    muted: [u8; 3],
+    accent: [u8; 3],
+}
+
+fn parse_color(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim().strip_prefix('#')?;
+    let channel = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+    match hex.len() {
+        6 => Some([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?]),
+        3 => Some([
+            channel(0, 1)? * 17,
+            channel(1, 1)? * 17,
+            channel(2, 1)? * 17,
+        ]),
+        _ => None,
+    }
+}
+
+/// `from` moved `amount` of the way to `to`.
+fn mix(from: [u8; 3], to: [u8; 3], amount: f32) -> [u8; 3] {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    [
+        channel(from[0], to[0]),
+        channel(from[1], to[1]),
+        channel(from[2], to[2]),
+    ]
+}
+
+impl Colors {
+    fn from_request(request: &QuoteImageRequest) -> Self {
+        let color = |value: &Option<String>| value.as_deref().and_then(parse_color);
+        let background = color(&request.background).unwrap_or(DEFAULT_BACKGROUND);
+        let foreground = color(&request.foreground).unwrap_or(DEFAULT_FOREGROUND);
+        let muted = mix(foreground, background, 0.4);
+        Colors {
+            background,
+            foreground,
+            muted,
+            accent: color(&request.accent).unwrap_or(muted),
+        }
+    }
+}
+
+/// The passage as it goes on the card: paragraphs kept, runs of blank lines
+/// and spaces squeezed, and cut to `MAX_QUOTE_CHARS` at a word.
+fn quote_text(text: &str) -> String {
+    let paragraphs: Vec<String> = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+    let quote = paragraphs.join("\n");
+    if quote.chars().count() <= MAX_QUOTE_CHARS {
+        return quote;
+    }
+    let cut: String = quote.chars().take(MAX_QUOTE_CHARS).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        // Scripts without spaces between words are cut where they reach the limit.
+        Some(i) if i > cut.len() / 2 => &cut[..i],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// Shorter quotes set larger.
+fn quote_font_size(quote: &str) -> f32 {
+    match quote.chars().count() {
+        0..=120 => 56.0,
+        121..=280 => 46.0,
+        281..=480 => 40.0,
+        _ => 34.0,
+    }
+}
+
+/// Where the passage comes from: the title, then the chapter when it says
+/// something more.
+fn source_line(title: &str, chapter: Option<&str>) -> String {
+    let title = title.trim();
+    match chapter.map(str::trim) {
+        Some(chapter) if !chapter.is_empty() && chapter != title => {
+            format!("{title} · {chapter}")
+        }
+        _ => title.to_string(),
+    }
+}
+
+struct TextBlock {
+    buffer: Buffer,
+    height: f32,
+}
+
+fn layout(fonts: &mut Fonts, text: &str, size: f32, width: f32, attrs: Attrs) -> TextBlock {
+    let metrics = Metrics::new(size, (size * 1.4).round());
+    let mut buffer = Buffer::new(&mut fonts.system, metrics);
+    buffer.set_size(&mut fonts.system, Some(width), None);
+    buffer.set_text(&mut fonts.system, text, attrs, Shaping::Advanced);
+    buffer.shape_until_scroll(&mut fonts.system, false);
+    let height = buffer.layout_runs().count() as f32 * metrics.line_height;
+    TextBlock { buffer, height }
+}
+
+/// Blend `color` over the `width` by `height` rectangle at `x`, `y`.
+fn fill(canvas: &mut RgbaImage, x: i32, y: i32, width: u32, height: u32, color: [u8; 4]) {
+    let alpha = color[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    let x_range = x.max(0)..(x + width as i32).min(canvas.width() as i32);
+    let y_range = y.max(0)..(y + height as i32).min(canvas.height() as i32);
+    for py in y_range {
+        for px in x_range.clone() {
+            let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+            for (under, &over) in pixel.0.iter_mut().zip(&color[..3]) {
+                *under = (over as f32 * alpha + *under as f32 * (1.0 - alpha)).round() as u8;
+            }
+        }
+    }
+}
+
+fn draw(
+    canvas: &mut RgbaImage,
+    fonts: &mut Fonts,
+    block: &TextBlock,
+    at: (f32, f32),
+    rgb: [u8; 3],
+) {
+    let (ox, oy) = (at.0.round() as i32, at.1.round() as i32);
+    let color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+    block.buffer.draw(
+        &mut fonts.system,
+        &mut fonts.swash,
+        color,
+        |x, y, w, h, c| fill(canvas, ox + x, oy + y, w, h, [c.r(), c.g(), c.b(), c.a()]),
+    );
+}
+
+/// The cover scaled into `COVER_BOX`, when it can be read.
+fn load_cover(app: &AppHandle, path: &Path) -> Option<RgbaImage> {
+    if !app.asset_protocol_scope().is_allowed(path) {
+        log::warn!("quote image: cover not allowed by asset scope: {path:?}");
+        return None;
+    }
+    let bytes = std::fs::read(path)
+        .map_err(|e| log::warn!("quote image: failed to read cover {path:?}: {e}"))
+        .ok()?;
+    let cover = image::load_from_memory(&bytes)
+        .map_err(|e| log::warn!("quote image: failed to decode cover {path:?}: {e}"))
+        .ok()?;
+    Some(
+        cover
+            .resize(COVER_BOX.0, COVER_BOX.1, FilterType::Triangle)
+            .to_rgba8(),
+    )
+}
+
+fn render(
+    fonts: &mut Fonts,
+    request: &QuoteImageRequest,
+    cover: Option<&RgbaImage>,
+) -> Result<Vec<u8>, String> {
+    let colors = Colors::from_request(request);
+    let content_width = WIDTH as f32 - PADDING * 2.0;
+
+    let quote = quote_text(&request.text);
+    let quote_block = layout(
+        fonts,
+        &quote,
+        quote_font_size(&quote),
+        content_width - QUOTE_INDENT,
+        Attrs::new().family(Family::Serif),
+    );
+
+    let (cover_width, cover_height) = cover.map_or((0, 0), |c| (c.width(), c.height()));
+    let text_x = PADDING
+        + if cover.is_some() {
+            cover_width as f32 + 40.0
+        } else {
+            0.0
+        };
+    let text_width = WIDTH as f32 - PADDING - text_x;
+    let author = request.author.trim();
+    let author_block = (!author.is_empty()).then(|| {
+        layout(
+            fonts,
+            &format!("— {author}"),
+            34.0,
+            text_width,
+            Attrs::new()
+                .family(Family::SansSerif)
+                .weight(Weight::SEMIBOLD),
+        )
+    });
+    let source_block = layout(
+        fonts,
+        &source_line(&request.title, request.chapter.as_deref()),
+        30.0,
+        text_width,
+        Attrs::new().family(Family::Serif).style(Style::Italic),
+    );
+    let footer_block = layout(
+        fonts,
+        "Readest",
+        26.0,
+        content_width,
+        Attrs::new().family(Family::SansSerif).weight(Weight::BOLD),
+    );
+
+    let quote_top = PADDING;
+    let row_top = quote_top + quote_block.height + 72.0;
+    let attribution_height =
+        author_block.as_ref().map_or(0.0, |b| b.height + 8.0) + source_block.height;
+    let row_height = attribution_height.max(cover_height as f32);
+    let footer_top = row_top + row_height + 64.0;
+    let height = ((footer_top + footer_block.height + PADDING).ceil() as u32).min(MAX_HEIGHT);
+
+    let [r, g, b] = colors.background;
+    let mut canvas = RgbaImage::from_pixel(WIDTH, height, Rgba([r, g, b, 255]));
+
+    let [r, g, b] = colors.accent;
+    fill(
+        &mut canvas,
+        PADDING as i32,
+        quote_top as i32,
+        ACCENT_BAR_WIDTH,
+        quote_block.height as u32,
+        [r, g, b, 255],
+    );
+    draw(
+        &mut canvas,
+        fonts,
+        &quote_block,
+        (PADDING + QUOTE_INDENT, quote_top),
+        colors.foreground,
+    );
+
+    if let Some(cover) = cover {
+        let y = row_top + (row_height - cover_height as f32) / 2.0;
+        imageops::overlay(&mut canvas, cover, PADDING as i64, y as i64);
+    }
+    let mut y = row_top + (row_height - attribution_height) / 2.0;
+    if let Some(author_block) = &author_block {
+        draw(
+            &mut canvas,
+            fonts,
+            author_block,
+            (text_x, y),
+            colors.foreground,
+        );
+        y += author_block.height + 8.0;
+    }
+    draw(&mut canvas, fonts, &source_block, (text_x, y), colors.muted);
+    draw(
+        &mut canvas,
+        fonts,
+        &footer_block,
+        (PADDING, footer_top),
+        colors.muted,
+    );
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(canvas)
+        .to_rgb8()
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode the quote image: {e}"))?;
+    Ok(png)
+}
+
+/// Render a highlighted passage as a PNG quote card.
+#[tauri::command]
+pub async fn render_quote_image(
+    app: AppHandle,
+    request: QuoteImageRequest,
+) -> Result<tauri::ipc::Response, String> {
+    if request.text.trim().is_empty() {
+        return Err("Nothing to quote".into());
+    }
+    let png = tauri::async_runtime::spawn_blocking(move || {
+        let cover = request
+            .cover_path
+            .as_ref()
+            .and_then(|path| load_cover(&app, path));
+        let images = app.state::<QuoteImages>();
+        let mut fonts = images.0.lock().unwrap();
+        let fonts = fonts.get_or_insert_with(Fonts::load);
+        render(fonts, &request, cover.as_ref())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+    Ok(tauri::ipc::Response::new(png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(text: &str) -> QuoteImageRequest {
+        QuoteImageRequest {
+            text: text.into(),
+            title: "Moby-Dick".into(),
+            author: "Herman Melville".into(),
+            chapter: Some("Loomings".into()),
+            cover_path: None,
+            background: Some("#1e1e1e".into()),
+            foreground: Some("#eee".into()),
+            accent: None,
+        }
+    }
+
+    #[test]
+    fn parses_theme_colors() {
+        assert_eq!(parse_color("#1e1E1e"), Some([30, 30, 30]));
+        assert_eq!(parse_color(" #fa0"), Some([255, 170, 0]));
+        assert_eq!(parse_color("1e1e1e"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(mix([0, 0, 0], [200, 100, 50], 0.5), [100, 50, 25]);
+    }
+
+    #[test]
+    fn squeezes_and_shortens_quotes() {
+        assert_eq!(
+            quote_text("  Call me   Ishmael.\r\n\n\n Some years ago "),
+            "Call me Ishmael.\nSome years ago"
+        );
+        let long = quote_text(&"word ".repeat(300));
+        assert!(long.ends_with("word…"));
+        assert!(long.chars().count() <= MAX_QUOTE_CHARS + 1);
+        let unspaced = quote_text(&"字".repeat(800));
+        assert_eq!(unspaced.chars().count(), MAX_QUOTE_CHARS + 1);
+    }
+
+    #[test]
+    fn names_the_chapter_when_it_adds_to_the_title() {
+        assert_eq!(
+            source_line("Moby-Dick", Some("Loomings")),
+            "Moby-Dick · Loomings"
+        );
+        assert_eq!(source_line("Moby-Dick", Some("Moby-Dick")), "Moby-Dick");
+        assert_eq!(source_line("Moby-Dick", Some(" ")), "Moby-Dick");
+    }
+
+    #[test]
+    fn renders_a_png_card() {
+        // No fonts, so no glyphs, but the card is laid out and encoded.
+        let mut fonts = Fonts {
+            system: FontSystem::new_with_locale_and_db(
+                "en-US".into(),
+                cosmic_text::fontdb::Database::new(),
+            ),
+            swash: SwashCache::new(),
+        };
+        let cover = RgbaImage::from_pixel(100, 150, Rgba([200, 0, 0, 255]));
+        let png = render(&mut fonts, &request("Call me Ishmael."), Some(&cover)).unwrap();
+        let card = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(card.width(), WIDTH);
+        assert!(card.height() >= 150 + 2 * PADDING as u32);
+        assert_eq!(card.get_pixel(0, 0).0, [30, 30, 30]);
+    }
+}
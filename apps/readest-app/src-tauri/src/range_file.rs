@@ -73,7 +73,7 @@ fn parse_query(uri_query: Option<&str>) -> Option<RangeQuery> {
 /// `..`/symlinks) for existing files, so this is redundant for the security
 /// outcome — but it fails closed and keeps the handler obviously-correct
 /// instead of relying on that canonicalization subtlety.
-fn is_safe_path(path: &Path) -> bool {
+pub(crate) fn is_safe_path(path: &Path) -> bool {
     path.is_absolute()
         && !path.to_string_lossy().contains('\0')
         && !path.components().any(|c| matches!(c, Component::ParentDir))
@@ -89,7 +89,7 @@ pub fn handle<R: Runtime>(
     responder.respond(build_response(ctx.app_handle(), &request));
 }
 
-fn cors_origin(request: &Request<Vec<u8>>) -> String {
+pub(crate) fn cors_origin(request: &Request<Vec<u8>>) -> String {
     request
         .headers()
         .get("origin")
@@ -98,7 +98,7 @@ fn cors_origin(request: &Request<Vec<u8>>) -> String {
         .unwrap_or_else(|| "*".to_string())
 }
 
-fn error(origin: &str, status: StatusCode) -> Response<Vec<u8>> {
+pub(crate) fn error(origin: &str, status: StatusCode) -> Response<Vec<u8>> {
     Response::builder()
         .status(status)
         .header("Access-Control-Allow-Origin", origin)
@@ -0,0 +1,671 @@
+//! Embedded web reading server.
+//!
+//! Lets a browser on another device on the LAN read from this library. The
+//! server serves:
+//!
+//!   - `GET /`: a plain list of the library's books, each linking to
+//!     `/read/{hash}`, which redirects to the reader page for that book;
+//!   - the bundled frontend, straight out of Tauri's embedded assets, so no
+//!     separate web build has to ship with the app. HTML pages get a
+//!     `window.__READEST_READING_SERVER` flag, which makes the frontend run
+//!     as the web app and load books and progress from the API below;
+//!   - `GET /api/books`: the library, minus deleted books, as
+//!     `{ hash, title, author, format, progress, updatedAt }`;
+//!   - `GET /api/books/{hash}/file`: the book file;
+//!   - `GET /api/books/{hash}/cover`: its cover PNG;
+//!   - `GET /api/books/{hash}/progress`: `{ location, progress, updatedAt }`;
+//!   - `PUT /api/books/{hash}/progress`: the same body, answered `202` when
+//!     accepted, `409` with the stored progress when that is newer, and
+//!     `404` for an unknown book.
+//!
+//! Every request must carry the per-session token, as a bearer header, a
+//! `?token=` query parameter (which sets a cookie so the page's follow-up
+//! requests authenticate) or that cookie. The token is random per start and
+//! never persisted. TLS is optional and takes a PEM certificate/key pair.
+//!
+//! The server never writes book configs itself: the app owns them, and an
+//! open reader would overwrite a config changed behind its back. Accepted
+//! progress is emitted as `reading-server://progress` and the frontend
+//! applies it, moving an open reader or saving the config through the app's
+//! book service.
+
+use axum::body::Body;
+use axum::extract::{Path as AxumPath, Request, State as AxumState};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use futures_util::TryStreamExt;
+use rand::RngCore;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+use crate::book_resource::inject_into_head;
+use crate::transfer_file::ensure_path_allowed;
+
+pub const PROGRESS_EVENT: &str = "reading-server://progress";
+const TOKEN_COOKIE: &str = "readest_token";
+const LIBRARY_FILENAME: &str = "library.json";
+const CONFIG_FILENAME: &str = "config.json";
+const COVER_FILENAME: &str = "cover.png";
+/// Tells the frontend it was loaded from this server rather than the app.
+const CLIENT_FLAG_SCRIPT: &str = "<script>window.__READEST_READING_SERVER=true;</script>";
+const BOOK_EXTENSIONS: &[&str] = &["epub", "pdf", "mobi", "azw", "azw3", "fb2", "cbz", "txt"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsOptions {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingServerOptions {
+    /// Absolute path of the library's `Books` directory.
+    pub books_dir: String,
+    /// `0` picks a free port.
+    #[serde(default)]
+    pub port: u16,
+    /// Listen on all interfaces instead of loopback only.
+    #[serde(default)]
+    pub lan: bool,
+    pub tls: Option<TlsOptions>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingServerInfo {
+    pub port: u16,
+    pub token: String,
+    pub tls: bool,
+    /// URLs to open on the other device, token included.
+    pub urls: Vec<String>,
+}
+
+struct Running {
+    info: ReadingServerInfo,
+    handle: axum_server::Handle,
+}
+
+#[derive(Default)]
+pub struct ReadingServer(Mutex<Option<Running>>);
+
+struct ServerState {
+    app: AppHandle,
+    books_dir: PathBuf,
+    token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookProgress {
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub progress: Option<[u64; 2]>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent {
+    hash: String,
+    #[serde(flatten)]
+    progress: BookProgress,
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Compare without short-circuiting so response timing doesn't leak how
+/// much of a guessed token was right.
+pub(crate) fn token_matches(candidate: &str, token: &str) -> bool {
+    candidate.len() == token.len()
+        && candidate
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Book hashes are partial-MD5 hex digests; anything else could be used to
+/// walk out of the books directory.
+fn is_valid_hash(hash: &str) -> bool {
+    !hash.is_empty() && hash.len() <= 64 && hash.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == name).then_some(v)
+    })
+}
+
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| {
+            let (k, v) = c.trim().split_once('=')?;
+            (k == name).then_some(v)
+        })
+}
+
+async fn require_token(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let from_query = request
+        .uri()
+        .query()
+        .and_then(|q| query_param(q, "token"))
+        .map(|t| t.to_string());
+    let authorized = [
+        bearer,
+        from_query.as_deref(),
+        cookie_value(headers, TOKEN_COOKIE),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|t| token_matches(t, &state.token));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let mut response = next.run(request).await;
+    if from_query.is_some() {
+        if let Ok(cookie) = HeaderValue::from_str(&format!(
+            "{TOKEN_COOKIE}={}; Path=/; HttpOnly; SameSite=Strict",
+            state.token
+        )) {
+            response.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+    }
+    response
+}
+
+fn read_json(path: &std::path::Path) -> Option<Value> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+async fn list_books(AxumState(state): AxumState<Arc<ServerState>>) -> Response {
+    let Some(Value::Array(books)) = read_json(&state.books_dir.join(LIBRARY_FILENAME)) else {
+        return Json(Vec::<Value>::new()).into_response();
+    };
+    let books: Vec<Value> = books
+        .into_iter()
+        .filter(|b| b.get("deletedAt").map_or(true, Value::is_null))
+        .map(|b| {
+            let pick = |k: &str| b.get(k).cloned().unwrap_or(Value::Null);
+            serde_json::json!({
+                "hash": pick("hash"),
+                "title": pick("title"),
+                "author": pick("author"),
+                "format": pick("format"),
+                "progress": pick("progress"),
+                "updatedAt": pick("updatedAt"),
+            })
+        })
+        .collect();
+    Json(books).into_response()
+}
+
+fn find_book_file(dir: &std::path::Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| BOOK_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+}
+
+async fn stream_file(path: PathBuf, content_type: &str) -> Response {
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(f) => f,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let len = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    let stream = FramedRead::new(file, BytesCodec::new()).map_ok(|b| b.freeze());
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, len);
+    // The browser reader imports the file and needs its extension to tell
+    // the format; book file names are `{title}.{ext}`, so send only that.
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        builder = builder.header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"book.{ext}\""),
+        );
+    }
+    builder
+        .body(Body::from_stream(stream))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+async fn book_file(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    AxumPath(hash): AxumPath<String>,
+) -> Response {
+    if !is_valid_hash(&hash) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    match find_book_file(&state.books_dir.join(&hash)) {
+        Some(path) => stream_file(path, "application/octet-stream").await,
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn book_cover(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    AxumPath(hash): AxumPath<String>,
+) -> Response {
+    if !is_valid_hash(&hash) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    stream_file(
+        state.books_dir.join(&hash).join(COVER_FILENAME),
+        "image/png",
+    )
+    .await
+}
+
+/// Titles and authors go into the index page verbatim otherwise.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_index(books: &[Value]) -> String {
+    let items: String = books
+        .iter()
+        .filter(|b| b.get("deletedAt").map_or(true, Value::is_null))
+        .filter_map(|b| {
+            let hash = b.get("hash").and_then(Value::as_str)?;
+            if !is_valid_hash(hash) {
+                return None;
+            }
+            let text = |k: &str| b.get(k).and_then(Value::as_str).unwrap_or_default();
+            let author = text("author");
+            Some(format!(
+                "<li><a href=\"/read/{hash}\">{}</a>{}</li>",
+                escape_html(text("title")),
+                if author.is_empty() {
+                    String::new()
+                } else {
+                    format!(" &middot; {}", escape_html(author))
+                }
+            ))
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"/>\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\"/>\
+         <title>Readest</title></head><body><ul>{items}</ul></body></html>"
+    )
+}
+
+async fn index(AxumState(state): AxumState<Arc<ServerState>>) -> Html<String> {
+    let books = match read_json(&state.books_dir.join(LIBRARY_FILENAME)) {
+        Some(Value::Array(books)) => books,
+        _ => Vec::new(),
+    };
+    Html(render_index(&books))
+}
+
+/// Open a book in the bundled reader page, which fetches it from the API.
+async fn read_book(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    AxumPath(hash): AxumPath<String>,
+) -> Response {
+    if !is_valid_hash(&hash) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    if find_book_file(&state.books_dir.join(&hash)).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Redirect::to(&format!("/reader?ids={hash}")).into_response()
+}
+
+fn progress_from_config(config: &Value) -> BookProgress {
+    BookProgress {
+        location: config
+            .get("location")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        progress: config
+            .get("progress")
+            .and_then(|p| serde_json::from_value(p.clone()).ok()),
+        updated_at: config.get("updatedAt").and_then(Value::as_u64).unwrap_or(0),
+    }
+}
+
+async fn get_progress(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    AxumPath(hash): AxumPath<String>,
+) -> Response {
+    if !is_valid_hash(&hash) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let config = read_json(&state.books_dir.join(&hash).join(CONFIG_FILENAME))
+        .unwrap_or(Value::Object(Default::default()));
+    Json(progress_from_config(&config)).into_response()
+}
+
+/// Last-writer-wins on `updatedAt` against the stored config.
+fn is_newer(config: &Value, incoming: &BookProgress) -> bool {
+    incoming.updated_at > progress_from_config(config).updated_at
+}
+
+async fn put_progress(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    AxumPath(hash): AxumPath<String>,
+    Json(incoming): Json<BookProgress>,
+) -> Response {
+    if !is_valid_hash(&hash) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let dir = state.books_dir.join(&hash);
+    if !dir.is_dir() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let config = read_json(&dir.join(CONFIG_FILENAME)).unwrap_or(Value::Object(Default::default()));
+    if !is_newer(&config, &incoming) {
+        return (StatusCode::CONFLICT, Json(progress_from_config(&config))).into_response();
+    }
+    if let Err(e) = state.app.emit(
+        PROGRESS_EVENT,
+        ProgressEvent {
+            hash,
+            progress: incoming,
+        },
+    ) {
+        log::error!("reading server: failed to hand progress to the app: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    StatusCode::ACCEPTED.into_response()
+}
+
+fn mime_for(path: &str) -> &'static str {
+    match path.rsplit('.').next().unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript",
+        "css" => "text/css",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Serve the embedded frontend. Routes without a file extension fall back
+/// to the page's `.html` export and then to `index.html`.
+async fn ui_asset(AxumState(state): AxumState<Arc<ServerState>>, request: Request) -> Response {
+    let path = request.uri().path().trim_end_matches('/');
+    let resolver = state.app.asset_resolver();
+    let candidates = if path
+        .rsplit('/')
+        .next()
+        .is_some_and(|last| last.contains('.'))
+    {
+        vec![path.to_string()]
+    } else {
+        vec![
+            format!("{path}.html"),
+            format!("{path}/index.html"),
+            "/index.html".into(),
+        ]
+    };
+    for candidate in candidates {
+        if let Some(asset) = resolver.get(candidate.clone()) {
+            let mime = if asset.mime_type.is_empty() {
+                mime_for(&candidate).to_string()
+            } else {
+                asset.mime_type.clone()
+            };
+            let body = if mime.starts_with("text/html") {
+                inject_into_head(&asset.bytes, CLIENT_FLAG_SCRIPT)
+            } else {
+                asset.bytes
+            };
+            return ([(header::CONTENT_TYPE, mime)], body).into_response();
+        }
+    }
+    StatusCode::NOT_FOUND.into_response()
+}
+
+/// Server config for a PEM certificate chain and key. The provider is passed
+/// explicitly: with both ring and aws-lc-rs in the build, rustls has no
+/// process-wide default to fall back on.
+fn tls_server_config(tls: &TlsOptions) -> Result<rustls::ServerConfig, String> {
+    let certs = CertificateDer::pem_file_iter(&tls.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load TLS certificate: {e}"))?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key_path)
+        .map_err(|e| format!("Failed to load TLS key: {e}"))?;
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| e.to_string())?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(|e| format!("Invalid TLS certificate: {e}"))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// Best guess at the address other LAN devices can reach us on: the source
+/// address the OS would use for an outbound route. No packet is sent.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+    socket.local_addr().ok().map(|a| a.ip())
+}
+
+#[tauri::command]
+pub async fn start_reading_server(
+    app: AppHandle,
+    server: State<'_, ReadingServer>,
+    options: ReadingServerOptions,
+) -> Result<ReadingServerInfo, String> {
+    ensure_path_allowed(&app, &options.books_dir).map_err(|e| e.to_string())?;
+    if let Some(running) = server.0.lock().unwrap().as_ref() {
+        return Ok(running.info.clone());
+    }
+
+    let token = generate_token();
+    let state = Arc::new(ServerState {
+        app: app.clone(),
+        books_dir: PathBuf::from(&options.books_dir),
+        token: token.clone(),
+    });
+    let router = Router::new()
+        .route("/", get(index))
+        .route("/read/{hash}", get(read_book))
+        .route("/api/books", get(list_books))
+        .route("/api/books/{hash}/file", get(book_file))
+        .route("/api/books/{hash}/cover", get(book_cover))
+        .route(
+            "/api/books/{hash}/progress",
+            get(get_progress).put(put_progress),
+        )
+        .fallback(ui_asset)
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state);
+
+    let ip = if options.lan {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    };
+    let listener = std::net::TcpListener::bind(SocketAddr::new(ip, options.port))
+        .map_err(|e| format!("Failed to bind reading server: {e}"))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let handle = axum_server::Handle::new();
+
+    let tls = options.tls.is_some();
+    if let Some(tls) = &options.tls {
+        let config = RustlsConfig::from_config(Arc::new(tls_server_config(tls)?));
+        let server = axum_server::from_tcp_rustls(listener, config)
+            .handle(handle.clone())
+            .serve(router.into_make_service());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = server.await {
+                log::error!("reading server stopped: {e}");
+            }
+        });
+    } else {
+        let server = axum_server::from_tcp(listener)
+            .handle(handle.clone())
+            .serve(router.into_make_service());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = server.await {
+                log::error!("reading server stopped: {e}");
+            }
+        });
+    }
+
+    let scheme = if tls { "https" } else { "http" };
+    let mut hosts = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+    if options.lan {
+        hosts.extend(lan_address());
+    }
+    let urls = hosts
+        .into_iter()
+        .map(|host| format!("{scheme}://{host}:{port}/?token={token}"))
+        .collect();
+    let info = ReadingServerInfo {
+        port,
+        token,
+        tls,
+        urls,
+    };
+    log::info!("reading server listening on port {port} (tls: {tls})");
+    *server.0.lock().unwrap() = Some(Running {
+        info: info.clone(),
+        handle,
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_reading_server(server: State<'_, ReadingServer>) {
+    if let Some(running) = server.0.lock().unwrap().take() {
+        running
+            .handle
+            .graceful_shutdown(Some(std::time::Duration::from_secs(3)));
+    }
+}
+
+#[tauri::command]
+pub fn get_reading_server_status(server: State<'_, ReadingServer>) -> Option<ReadingServerInfo> {
+    server.0.lock().unwrap().as_ref().map(|r| r.info.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_hex() {
+        let a = generate_token();
+        assert_eq!(a.len(), 48);
+        assert!(a.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(a, generate_token());
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abd", "abc"));
+        assert!(!token_matches("ab", "abc"));
+    }
+
+    #[test]
+    fn hash_validation_blocks_traversal() {
+        assert!(is_valid_hash("0123456789abcdef0123456789abcdef"));
+        assert!(!is_valid_hash(".."));
+        assert!(!is_valid_hash("a/b"));
+        assert!(!is_valid_hash(""));
+    }
+
+    #[test]
+    fn reads_token_from_query_and_cookie() {
+        assert_eq!(query_param("a=1&token=xyz", "token"), Some("xyz"));
+        assert_eq!(query_param("a=1", "token"), None);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("theme=dark; readest_token=t0k"),
+        );
+        assert_eq!(cookie_value(&headers, TOKEN_COOKIE), Some("t0k"));
+    }
+
+    #[test]
+    fn index_links_live_books_and_escapes_titles() {
+        let books = serde_json::json!([
+            { "hash": "abc123", "title": "Tom & <Jerry>", "author": "A \"B\"" },
+            { "hash": "def456", "title": "Gone", "deletedAt": 1 },
+            { "hash": "../x", "title": "Bad" },
+        ]);
+        let html = render_index(books.as_array().unwrap());
+        assert!(html.contains(r#"<a href="/read/abc123">Tom &amp; &lt;Jerry&gt;</a>"#));
+        assert!(html.contains("A &quot;B&quot;"));
+        assert!(!html.contains("def456"));
+        assert!(!html.contains("Bad"));
+    }
+
+    #[test]
+    fn progress_is_last_writer_wins() {
+        let config = serde_json::json!({ "location": "old", "updatedAt": 10 });
+        let progress = |updated_at| BookProgress {
+            location: Some("epubcfi(/6/4)".into()),
+            progress: Some([12, 300]),
+            updated_at,
+        };
+        assert!(!is_newer(&config, &progress(5)));
+        assert!(!is_newer(&config, &progress(10)));
+        assert!(is_newer(&config, &progress(20)));
+        assert!(is_newer(&Value::Null, &progress(1)));
+    }
+}
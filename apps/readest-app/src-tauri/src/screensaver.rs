@@ -0,0 +1,164 @@
+//! Screen saver inhibition on Linux.
+//!
+//! WebKitGTK has no Screen Wake Lock, so "Keep Screen Awake" did nothing and
+//! the screen blanked mid-chapter. The webview asks for the screen to stay
+//! on through `set_screensaver_inhibited`, for as long as any of its reasons
+//! stands: reading with the setting on and the window focused, or reading
+//! aloud. That holds an inhibit on `org.freedesktop.ScreenSaver`, which
+//! GNOME, KDE, Xfce and most other desktops serve on the session bus. The
+//! desktop drops an inhibit along with the connection that took it, so the
+//! connection is held with it and closed when released.
+
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+use tauri::State;
+use tokio::sync::Mutex;
+use zbus::{proxy, Connection};
+
+const APP_NAME: &str = "Readest";
+
+#[proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver"
+)]
+pub(crate) trait FreedesktopScreenSaver {
+    fn inhibit(&self, application_name: &str, reason_for_inhibit: &str) -> zbus::Result<u32>;
+
+    fn un_inhibit(&self, cookie: u32) -> zbus::Result<()>;
+
+    /// Seconds since the last input, for `idle_time`.
+    fn get_session_idle_time(&self) -> zbus::Result<u32>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InhibitReason {
+    Reading,
+    ReadAloud,
+}
+
+impl InhibitReason {
+    /// Shown by desktops that list what is keeping the screen on.
+    fn description(self) -> &'static str {
+        match self {
+            InhibitReason::Reading => "Reading a book",
+            InhibitReason::ReadAloud => "Reading a book aloud",
+        }
+    }
+}
+
+/// What a change of reasons calls for.
+#[derive(Debug, PartialEq)]
+enum Change {
+    Inhibit(&'static str),
+    Release,
+    Keep,
+}
+
+fn change_for(reasons: &BTreeSet<InhibitReason>, held: bool) -> Change {
+    // Reading aloud is the more telling description when both stand.
+    match (reasons.last(), held) {
+        (Some(reason), false) => Change::Inhibit(reason.description()),
+        (None, true) => Change::Release,
+        _ => Change::Keep,
+    }
+}
+
+struct Inhibit {
+    connection: Connection,
+    cookie: u32,
+}
+
+#[derive(Default)]
+struct Inhibitor {
+    reasons: BTreeSet<InhibitReason>,
+    held: Option<Inhibit>,
+}
+
+#[derive(Default)]
+pub struct ScreenSaver(Mutex<Inhibitor>);
+
+async fn inhibit(description: &str) -> zbus::Result<Inhibit> {
+    let connection = Connection::session().await?;
+    let cookie = FreedesktopScreenSaverProxy::new(&connection)
+        .await?
+        .inhibit(APP_NAME, description)
+        .await?;
+    Ok(Inhibit { connection, cookie })
+}
+
+async fn release(inhibit: Inhibit) -> zbus::Result<()> {
+    FreedesktopScreenSaverProxy::new(&inhibit.connection)
+        .await?
+        .un_inhibit(inhibit.cookie)
+        .await
+}
+
+/// Keep the screen on for `reason`, or stop doing so. The screen saver is
+/// inhibited while any reason stands.
+#[tauri::command]
+pub async fn set_screensaver_inhibited(
+    screensaver: State<'_, ScreenSaver>,
+    reason: InhibitReason,
+    inhibited: bool,
+) -> Result<(), String> {
+    let mut inhibitor = screensaver.0.lock().await;
+    if inhibited {
+        inhibitor.reasons.insert(reason);
+    } else {
+        inhibitor.reasons.remove(&reason);
+    }
+    match change_for(&inhibitor.reasons, inhibitor.held.is_some()) {
+        Change::Inhibit(description) => {
+            let held = inhibit(description)
+                .await
+                .map_err(|e| format!("Failed to inhibit the screen saver: {e}"))?;
+            inhibitor.held = Some(held);
+        }
+        Change::Release => {
+            if let Some(held) = inhibitor.held.take() {
+                // Dropping the connection lifts the inhibit anyway.
+                if let Err(e) = release(held).await {
+                    log::warn!("Failed to release the screen saver inhibit: {e}");
+                }
+            }
+        }
+        Change::Keep => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inhibits_while_any_reason_stands() {
+        let mut reasons = BTreeSet::new();
+        assert_eq!(change_for(&reasons, false), Change::Keep);
+
+        reasons.insert(InhibitReason::Reading);
+        assert_eq!(
+            change_for(&reasons, false),
+            Change::Inhibit("Reading a book")
+        );
+        reasons.insert(InhibitReason::ReadAloud);
+        assert_eq!(change_for(&reasons, true), Change::Keep);
+
+        reasons.remove(&InhibitReason::Reading);
+        assert_eq!(change_for(&reasons, true), Change::Keep);
+        reasons.remove(&InhibitReason::ReadAloud);
+        assert_eq!(change_for(&reasons, true), Change::Release);
+    }
+
+    #[test]
+    fn describes_reading_aloud_first() {
+        let reasons = BTreeSet::from([InhibitReason::Reading, InhibitReason::ReadAloud]);
+        assert_eq!(
+            change_for(&reasons, false),
+            Change::Inhibit("Reading a book aloud")
+        );
+    }
+}
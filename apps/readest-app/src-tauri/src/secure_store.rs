@@ -0,0 +1,280 @@
+//! Secrets in the platform credential store instead of plaintext config.
+//!
+//! Everything goes through the native bridge's keyed secure store: the OS
+//! keyring (Keychain, Credential Manager, Secret Service) on desktop, the
+//! Keychain on iOS and Keystore-encrypted preferences on Android. The
+//! `secure_get` / `secure_set` / `secure_delete` commands expose it to the
+//! frontend with string results, like the other app commands.
+//!
+//! `settings.json` used to carry sync tokens and passwords in the clear.
+//! [`secure_save_settings`] now moves the fields in [`SETTINGS_SECRETS`] into
+//! the store and blanks them in what gets written, and
+//! [`secure_load_settings`] fills them back in on load. A plaintext value
+//! still in the file (an older build wrote it) is kept and flagged so the
+//! caller saves once, which migrates it. If the store is unavailable (no
+//! Secret Service on a bare Linux session, say) the field stays in the
+//! file rather than being lost.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use tauri_plugin_native_bridge::{GetSecureItemRequest, NativeBridgeExt, SetSecureItemRequest};
+
+/// JSON pointers of the secret fields in `settings.json`.
+const SETTINGS_SECRETS: [&str; 7] = [
+    "/kosync/userkey",
+    "/kosync/password",
+    "/readwise/accessToken",
+    "/hardcover/accessToken",
+    "/webdav/password",
+    "/aiSettings/aiGatewayApiKey",
+    "/aiSettings/openrouterApiKey",
+];
+const MAX_KEY_LEN: usize = 128;
+
+/// Store key for a settings field: `/kosync/userkey` is
+/// `settings.kosync.userkey`.
+fn settings_key(pointer: &str) -> String {
+    format!("settings{}", pointer.replace('/', "."))
+}
+
+fn check_key(key: &str) -> Result<(), String> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid secure store key {key:?}"))
+    }
+}
+
+pub(crate) fn get(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    let response = app
+        .native_bridge()
+        .get_secure_item(GetSecureItemRequest {
+            key: key.to_string(),
+        })
+        .map_err(|e| e.to_string())?;
+    match (response.value, response.error) {
+        (Some(value), _) => Ok(Some(value)),
+        (None, Some(e)) => Err(e),
+        (None, None) => Ok(None),
+    }
+}
+
+pub(crate) fn set(app: &AppHandle, key: &str, value: &str) -> Result<(), String> {
+    let response = app
+        .native_bridge()
+        .set_secure_item(SetSecureItemRequest {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .map_err(|e| e.to_string())?;
+    if response.success {
+        Ok(())
+    } else {
+        Err(response.error.unwrap_or_else(|| "unknown error".into()))
+    }
+}
+
+pub(crate) fn delete(app: &AppHandle, key: &str) -> Result<(), String> {
+    let response = app
+        .native_bridge()
+        .clear_secure_item(GetSecureItemRequest {
+            key: key.to_string(),
+        })
+        .map_err(|e| e.to_string())?;
+    if response.success {
+        Ok(())
+    } else {
+        Err(response.error.unwrap_or_else(|| "unknown error".into()))
+    }
+}
+
+/// The non-empty string at `pointer`.
+fn secret_at<'a>(settings: &'a Value, pointer: &str) -> Option<&'a str> {
+    settings
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+}
+
+/// Fill empty secret fields from `stored`. Returns whether the file still
+/// held plaintext secrets.
+fn fill(settings: &mut Value, mut stored: impl FnMut(&str) -> Option<String>) -> bool {
+    let mut plaintext = false;
+    for pointer in SETTINGS_SECRETS {
+        if secret_at(settings, pointer).is_some() {
+            plaintext = true;
+            continue;
+        }
+        let (parent, field) = pointer.rsplit_once('/').unwrap_or_default();
+        let Some(Value::Object(section)) = settings.pointer_mut(parent) else {
+            continue;
+        };
+        if let Some(value) = stored(pointer) {
+            section.insert(field.to_string(), Value::String(value));
+        }
+    }
+    plaintext
+}
+
+/// Hand each secret field to `store` (`None` when it's empty or absent) and
+/// blank the ones it accepted.
+fn strip(settings: &mut Value, mut store: impl FnMut(&str, Option<&str>) -> bool) {
+    for pointer in SETTINGS_SECRETS {
+        let secret = secret_at(settings, pointer).map(str::to_string);
+        if store(pointer, secret.as_deref()) && secret.is_some() {
+            if let Some(field) = settings.pointer_mut(pointer) {
+                *field = Value::String(String::new());
+            }
+        }
+    }
+}
+
+/// The stored settings secrets as last read or written, so saving the
+/// settings doesn't rewrite unchanged keychain entries every time.
+#[derive(Default)]
+pub struct SecureSettings(Mutex<HashMap<String, Option<String>>>);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedSettings {
+    pub settings: Value,
+    /// Plaintext secrets were found; save to move them into the store.
+    pub needs_save: bool,
+}
+
+#[tauri::command]
+pub fn secure_get(app: AppHandle, key: String) -> Result<Option<String>, String> {
+    check_key(&key)?;
+    get(&app, &key)
+}
+
+#[tauri::command]
+pub fn secure_set(app: AppHandle, key: String, value: String) -> Result<(), String> {
+    check_key(&key)?;
+    set(&app, &key, &value)
+}
+
+/// Remove `key`; a missing one is not an error.
+#[tauri::command]
+pub fn secure_delete(app: AppHandle, key: String) -> Result<(), String> {
+    check_key(&key)?;
+    delete(&app, &key)
+}
+
+/// Put the secrets back into settings just read from `settings.json`.
+#[tauri::command]
+pub fn secure_load_settings(
+    app: AppHandle,
+    cache: State<'_, SecureSettings>,
+    mut settings: Value,
+) -> LoadedSettings {
+    let mut cache = cache.0.lock().unwrap();
+    let needs_save = fill(&mut settings, |pointer| {
+        let key = settings_key(pointer);
+        let value = get(&app, &key).unwrap_or_else(|e| {
+            log::warn!("Failed to read {key} from the secure store: {e}");
+            None
+        });
+        cache.insert(key, value.clone());
+        value
+    });
+    LoadedSettings {
+        settings,
+        needs_save,
+    }
+}
+
+/// Move the secrets out of settings about to be written to
+/// `settings.json`, returning what to write.
+#[tauri::command]
+pub fn secure_save_settings(
+    app: AppHandle,
+    cache: State<'_, SecureSettings>,
+    mut settings: Value,
+) -> Value {
+    let mut cache = cache.0.lock().unwrap();
+    strip(&mut settings, |pointer, secret| {
+        let key = settings_key(pointer);
+        if cache
+            .get(&key)
+            .is_some_and(|known| known.as_deref() == secret)
+        {
+            return true;
+        }
+        let result = match secret {
+            Some(secret) => set(&app, &key, secret),
+            None => delete(&app, &key),
+        };
+        match result {
+            Ok(()) => {
+                cache.insert(key, secret.map(str::to_string));
+                true
+            }
+            Err(e) => {
+                log::warn!("Keeping {key} in settings.json; the secure store failed: {e}");
+                cache.remove(&key);
+                false
+            }
+        }
+    });
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keys_are_checked() {
+        assert!(check_key("settings.kosync.userkey").is_ok());
+        assert!(check_key("opds:catalog-1").is_ok());
+        assert!(check_key("").is_err());
+        assert!(check_key("a/b").is_err());
+        assert!(check_key(&"k".repeat(MAX_KEY_LEN + 1)).is_err());
+        assert_eq!(settings_key("/kosync/userkey"), "settings.kosync.userkey");
+    }
+
+    #[test]
+    fn saving_moves_secrets_out_and_loading_restores_them() {
+        let original = json!({
+            "kosync": { "username": "reader", "userkey": "abc", "password": "" },
+            "readwise": { "enabled": true, "accessToken": "rw-token" },
+            "webdav": { "password": "dav" },
+        });
+        let mut store: HashMap<String, Option<String>> = HashMap::new();
+        let mut written = original.clone();
+        strip(&mut written, |pointer, secret| {
+            store.insert(pointer.to_string(), secret.map(str::to_string));
+            pointer != "/webdav/password"
+        });
+        assert_eq!(written["kosync"]["userkey"], "");
+        assert_eq!(written["readwise"]["accessToken"], "");
+        assert_eq!(written["kosync"]["username"], "reader");
+        // The store refused this one, so it stays in the file.
+        assert_eq!(written["webdav"]["password"], "dav");
+        assert_eq!(store["/kosync/password"], None);
+
+        let mut loaded = written.clone();
+        let plaintext = fill(&mut loaded, |pointer| store.get(pointer).cloned().flatten());
+        assert!(plaintext);
+        assert_eq!(loaded, original);
+        // Sections missing from the file aren't created.
+        assert!(loaded.get("hardcover").is_none());
+    }
+
+    #[test]
+    fn loading_without_plaintext_needs_no_save() {
+        let mut settings = json!({ "kosync": { "userkey": "" } });
+        assert!(!fill(&mut settings, |_| Some("stored".into())));
+        assert_eq!(settings["kosync"]["userkey"], "stored");
+    }
+}
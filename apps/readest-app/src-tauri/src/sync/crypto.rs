@@ -0,0 +1,421 @@
+//! Optional end-to-end encryption for synced data.
+//!
+//! Everything is sealed with XChaCha20-Poly1305 under a 32-byte key derived
+//! from the user's passphrase with Argon2id. The key is kept in the OS
+//! keychain (under [`KEY_ITEM`]) and never leaves the device; what's shared
+//! between devices is a [`KeyHeader`] — salt, KDF cost and a check value —
+//! that the frontend stores next to the synced library so a second device
+//! can re-derive the same key from the passphrase and know when it's wrong.
+//!
+//! Files use a chunked STREAM layout so a 300 MB book is never held in
+//! memory:
+//!
+//! ```text
+//! MAGIC (8) | nonce prefix (19) | chunk 0 | chunk 1 | … | last chunk
+//! ```
+//!
+//! Each chunk is [`CHUNK_SIZE`] bytes of plaintext plus a 16-byte tag,
+//! sealed with nonce `prefix ‖ counter (BE32) ‖ last-flag` and the header
+//! as associated data, so chunks can't be reordered, dropped or swapped
+//! between files. Annotations and progress go through [`seal`] / [`open`],
+//! which produce the same layout in memory.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use tauri::AppHandle;
+use tauri_plugin_native_bridge::{GetSecureItemRequest, NativeBridgeExt};
+
+use super::{load_secret, save_secret, SyncError, SyncErrorCode};
+
+/// Secure-store key holding the derived key, base64.
+pub(crate) const KEY_ITEM: &str = "sync_e2ee_key";
+const MAGIC: &[u8; 8] = b"RDE2EE\x00\x01";
+const PREFIX_LEN: usize = 19;
+const HEADER_LEN: usize = MAGIC.len() + PREFIX_LEN;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const CHECK_PLAINTEXT: &[u8] = b"readest-e2ee-check";
+/// OWASP's Argon2id baseline: 64 MiB, 3 passes, one lane.
+const DEFAULT_MEMORY_KIB: u32 = 64 * 1024;
+const DEFAULT_ITERATIONS: u32 = 3;
+const DEFAULT_PARALLELISM: u32 = 1;
+
+/// What another device needs, besides the passphrase, to rebuild the key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyHeader {
+    pub version: u32,
+    /// Base64 Argon2id salt.
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Base64 of a known plaintext sealed under the key.
+    pub check: String,
+}
+
+fn crypto_error(message: impl Into<String>) -> SyncError {
+    SyncError::new(SyncErrorCode::Unknown, message)
+}
+
+fn wrong_key() -> SyncError {
+    SyncError::new(
+        SyncErrorCode::AuthFailed,
+        "decryption failed: wrong key or corrupted data",
+    )
+}
+
+fn derive_key(passphrase: &str, header: &KeyHeader) -> Result<[u8; KEY_LEN], SyncError> {
+    let salt = STANDARD
+        .decode(&header.salt)
+        .map_err(|e| crypto_error(format!("bad salt: {e}")))?;
+    let params = Params::new(
+        header.memory_kib,
+        header.iterations,
+        header.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| crypto_error(format!("bad KDF parameters: {e}")))?;
+    let mut key = [0; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| crypto_error(format!("key derivation failed: {e}")))?;
+    Ok(key)
+}
+
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> XNonce {
+    let mut nonce = [0; 24];
+    nonce[..PREFIX_LEN].copy_from_slice(prefix);
+    nonce[PREFIX_LEN..PREFIX_LEN + 4].copy_from_slice(&counter.to_be_bytes());
+    nonce[23] = last as u8;
+    nonce.into()
+}
+
+/// Read until `buf` is full or the reader is exhausted.
+fn read_full(reader: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    let mut filled = 0;
+    while filled < len {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    buf.truncate(filled);
+    Ok(buf)
+}
+
+/// Stream-encrypt `reader` into `writer`.
+pub(crate) fn encrypt_stream(
+    key: &[u8; KEY_LEN],
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> Result<(), SyncError> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let mut header = [0; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    rand::thread_rng().fill_bytes(&mut header[MAGIC.len()..]);
+    let prefix = &header[MAGIC.len()..];
+    writer
+        .write_all(&header)
+        .map_err(|e| SyncError::io("write", e))?;
+
+    let mut counter = 0u32;
+    let mut current = read_full(reader, CHUNK_SIZE).map_err(|e| SyncError::io("read", e))?;
+    loop {
+        // One chunk of lookahead tells us which chunk is the last.
+        let next = read_full(reader, CHUNK_SIZE).map_err(|e| SyncError::io("read", e))?;
+        let last = next.is_empty();
+        let sealed = cipher
+            .encrypt(
+                &chunk_nonce(prefix, counter, last),
+                Payload {
+                    msg: &current,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| crypto_error("encryption failed"))?;
+        writer
+            .write_all(&sealed)
+            .map_err(|e| SyncError::io("write", e))?;
+        if last {
+            return writer.flush().map_err(|e| SyncError::io("write", e));
+        }
+        current = next;
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| crypto_error("file too large to encrypt"))?;
+    }
+}
+
+/// Stream-decrypt `reader` into `writer`. Fails on a wrong key and on any
+/// tampering, including a truncated file.
+pub(crate) fn decrypt_stream(
+    key: &[u8; KEY_LEN],
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> Result<(), SyncError> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let header = read_full(reader, HEADER_LEN).map_err(|e| SyncError::io("read", e))?;
+    if header.len() < HEADER_LEN || &header[..MAGIC.len()] != MAGIC {
+        return Err(crypto_error("not an encrypted Readest file"));
+    }
+    let prefix = &header[MAGIC.len()..];
+
+    let mut counter = 0u32;
+    let mut current =
+        read_full(reader, CHUNK_SIZE + TAG_LEN).map_err(|e| SyncError::io("read", e))?;
+    loop {
+        let next = read_full(reader, CHUNK_SIZE + TAG_LEN).map_err(|e| SyncError::io("read", e))?;
+        let last = next.is_empty();
+        let plain = cipher
+            .decrypt(
+                &chunk_nonce(prefix, counter, last),
+                Payload {
+                    msg: &current,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| wrong_key())?;
+        writer
+            .write_all(&plain)
+            .map_err(|e| SyncError::io("write", e))?;
+        if last {
+            return writer.flush().map_err(|e| SyncError::io("write", e));
+        }
+        current = next;
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| crypto_error("encrypted file is malformed"))?;
+    }
+}
+
+pub(crate) fn seal(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>, SyncError> {
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    encrypt_stream(key, &mut &plaintext[..], &mut out)?;
+    Ok(out)
+}
+
+pub(crate) fn open(key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>, SyncError> {
+    let mut out = Vec::with_capacity(sealed.len());
+    decrypt_stream(key, &mut &sealed[..], &mut out)?;
+    Ok(out)
+}
+
+pub(crate) fn encrypt_file(key: &[u8; KEY_LEN], src: &str, dst: &str) -> Result<(), SyncError> {
+    let mut reader =
+        std::io::BufReader::new(std::fs::File::open(src).map_err(|e| SyncError::io("open", e))?);
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(dst).map_err(|e| SyncError::io("create", e))?,
+    );
+    encrypt_stream(key, &mut reader, &mut writer)
+}
+
+pub(crate) fn decrypt_file(key: &[u8; KEY_LEN], src: &str, dst: &str) -> Result<(), SyncError> {
+    let mut reader =
+        std::io::BufReader::new(std::fs::File::open(src).map_err(|e| SyncError::io("open", e))?);
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(dst).map_err(|e| SyncError::io("create", e))?,
+    );
+    let result = decrypt_stream(key, &mut reader, &mut writer);
+    if result.is_err() {
+        drop(writer);
+        let _ = std::fs::remove_file(dst);
+    }
+    result
+}
+
+fn new_header(passphrase: &str) -> Result<(KeyHeader, [u8; KEY_LEN]), SyncError> {
+    let mut salt = [0; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let mut header = KeyHeader {
+        version: 1,
+        salt: STANDARD.encode(salt),
+        memory_kib: DEFAULT_MEMORY_KIB,
+        iterations: DEFAULT_ITERATIONS,
+        parallelism: DEFAULT_PARALLELISM,
+        check: String::new(),
+    };
+    let key = derive_key(passphrase, &header)?;
+    header.check = STANDARD.encode(seal(&key, CHECK_PLAINTEXT)?);
+    Ok((header, key))
+}
+
+fn unlock_key(passphrase: &str, header: &KeyHeader) -> Result<[u8; KEY_LEN], SyncError> {
+    if header.version != 1 {
+        return Err(crypto_error(format!(
+            "unsupported encryption version {}",
+            header.version
+        )));
+    }
+    let key = derive_key(passphrase, header)?;
+    let check = STANDARD
+        .decode(&header.check)
+        .map_err(|e| crypto_error(format!("bad check value: {e}")))?;
+    match open(&key, &check) {
+        Ok(plain) if plain == CHECK_PLAINTEXT => Ok(key),
+        _ => Err(SyncError::new(
+            SyncErrorCode::AuthFailed,
+            "wrong encryption passphrase",
+        )),
+    }
+}
+
+fn store_key(app: &AppHandle, key: &[u8; KEY_LEN]) -> Result<(), SyncError> {
+    save_secret(app, KEY_ITEM, &STANDARD.encode(key))
+}
+
+/// The stored key, or `None` when encryption isn't set up on this device.
+pub(crate) fn load_key(app: &AppHandle) -> Result<Option<[u8; KEY_LEN]>, SyncError> {
+    let Ok(stored) = load_secret(app, KEY_ITEM) else {
+        return Ok(None);
+    };
+    let bytes = STANDARD
+        .decode(stored.trim())
+        .map_err(|e| crypto_error(format!("stored key is unreadable: {e}")))?;
+    let key = bytes
+        .try_into()
+        .map_err(|_| crypto_error("stored key has the wrong length"))?;
+    Ok(Some(key))
+}
+
+/// The stored key, failing when encryption was requested but isn't set up.
+pub(crate) fn require_key(app: &AppHandle) -> Result<[u8; KEY_LEN], SyncError> {
+    load_key(app)?.ok_or_else(|| {
+        SyncError::new(
+            SyncErrorCode::AuthFailed,
+            "encryption is enabled but this device has no key; enter the passphrase",
+        )
+    })
+}
+
+pub(crate) async fn blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, SyncError> + Send + 'static,
+) -> Result<T, SyncError> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| crypto_error(format!("join error: {e}")))?
+}
+
+/// Start encrypting: derive a key from a new passphrase, keep it in the
+/// keychain, and return the header to store with the synced library.
+#[tauri::command]
+pub async fn e2ee_create_key(app: AppHandle, passphrase: String) -> Result<KeyHeader, SyncError> {
+    let (header, key) = blocking(move || new_header(&passphrase)).await?;
+    store_key(&app, &key)?;
+    Ok(header)
+}
+
+/// Join an encrypted library from another device. A wrong passphrase is
+/// `AUTH_FAILED` and leaves nothing stored.
+#[tauri::command]
+pub async fn e2ee_unlock(
+    app: AppHandle,
+    passphrase: String,
+    header: KeyHeader,
+) -> Result<(), SyncError> {
+    let key = blocking(move || unlock_key(&passphrase, &header)).await?;
+    store_key(&app, &key)
+}
+
+/// Whether this device holds a key.
+#[tauri::command]
+pub fn e2ee_status(app: AppHandle) -> Result<bool, SyncError> {
+    Ok(load_key(&app)?.is_some())
+}
+
+/// Drop the key from this device.
+#[tauri::command]
+pub fn e2ee_forget(app: AppHandle) -> Result<(), SyncError> {
+    app.native_bridge()
+        .clear_secure_item(GetSecureItemRequest {
+            key: KEY_ITEM.to_string(),
+        })
+        .map_err(|e| crypto_error(format!("keychain: {e}")))?;
+    Ok(())
+}
+
+/// Encrypt a small JSON payload (annotations, progress) for upload.
+#[tauri::command]
+pub fn e2ee_seal(app: AppHandle, plaintext: String) -> Result<String, SyncError> {
+    let key = require_key(&app)?;
+    Ok(STANDARD.encode(seal(&key, plaintext.as_bytes())?))
+}
+
+/// Decrypt a payload produced by [`e2ee_seal`] on any device.
+#[tauri::command]
+pub fn e2ee_open(app: AppHandle, sealed: String) -> Result<String, SyncError> {
+    let key = require_key(&app)?;
+    let bytes = STANDARD
+        .decode(sealed.trim())
+        .map_err(|e| crypto_error(format!("not an encrypted payload: {e}")))?;
+    String::from_utf8(open(&key, &bytes)?)
+        .map_err(|_| crypto_error("decrypted payload is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LEN] = [7; KEY_LEN];
+
+    #[test]
+    fn round_trips_across_chunk_boundaries() {
+        for len in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE * 2 + 5] {
+            let plain: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let sealed = seal(&KEY, &plain).unwrap();
+            let chunks = len.div_ceil(CHUNK_SIZE).max(1);
+            assert_eq!(sealed.len(), HEADER_LEN + len + chunks * TAG_LEN);
+            assert_eq!(open(&KEY, &sealed).unwrap(), plain);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_keys_and_tampering() {
+        let plain = vec![1u8; CHUNK_SIZE * 2];
+        let sealed = seal(&KEY, &plain).unwrap();
+        assert_eq!(
+            open(&[8; KEY_LEN], &sealed).unwrap_err().code,
+            SyncErrorCode::AuthFailed
+        );
+        // Dropping the final chunk must not yield a valid shorter file.
+        let truncated = &sealed[..HEADER_LEN + CHUNK_SIZE + TAG_LEN];
+        assert!(open(&KEY, truncated).is_err());
+        let mut flipped = sealed.clone();
+        flipped[HEADER_LEN + 3] ^= 1;
+        assert!(open(&KEY, &flipped).is_err());
+        assert!(open(&KEY, b"plain text").is_err());
+    }
+
+    #[test]
+    fn header_unlocks_only_with_its_passphrase() {
+        let mut salt = [0; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        // Cheap parameters keep the test fast; the format is the same.
+        let mut header = KeyHeader {
+            version: 1,
+            salt: STANDARD.encode(salt),
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+            check: String::new(),
+        };
+        let key = derive_key("correct horse", &header).unwrap();
+        header.check = STANDARD.encode(seal(&key, CHECK_PLAINTEXT).unwrap());
+        assert_eq!(unlock_key("correct horse", &header).unwrap(), key);
+        assert_eq!(
+            unlock_key("battery staple", &header).unwrap_err().code,
+            SyncErrorCode::AuthFailed
+        );
+    }
+}
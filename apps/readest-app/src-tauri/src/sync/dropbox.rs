@@ -0,0 +1,811 @@
+//! Dropbox backend.
+//!
+//! Runs inside the app's "App folder" (`/Apps/Readest`): Dropbox scopes
+//! every API path to it, so the logical `/Readest/books/...` tree maps 1:1
+//! onto API paths. Sign-in runs natively with PKCE over a loopback redirect
+//! ([`dropbox_connect`]) and stores the token set in the keychain in the
+//! same shape as the TS OAuth providers, so [`OAuthClient`] refreshes it
+//! like any other.
+//!
+//! Files over [`SINGLE_UPLOAD_LIMIT`] go up through an upload session in
+//! [`CHUNK_SIZE`] appends; when an append lands but its response is lost,
+//! Dropbox's `incorrect_offset` error says where to continue. The session id
+//! and offset are journaled after every append, so an interrupted upload is
+//! continued by the next attempt. Incremental pulls follow `list_folder`
+//! cursors.
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::SeekFrom;
+use std::time::Duration;
+use tauri::{ipc::Channel, AppHandle, Url};
+use tauri_plugin_oauth::OauthConfig;
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::oauth::{exchange_code, pkce_pair, random_token, OAuthClient};
+use super::transfer::Upload;
+use super::{
+    normalize, progress, save_secret, RemoteChange, RemoteChanges, RemoteEntry, RemoteHead,
+    StorageQuota, SyncError, SyncErrorCode,
+};
+use crate::transfer_file::{ProgressPayload, TransferStats};
+
+const AUTHORIZE_ENDPOINT: &str = "https://www.dropbox.com/oauth2/authorize";
+const TOKEN_ENDPOINT: &str = "https://api.dropboxapi.com/oauth2/token";
+const API_ENDPOINT: &str = "https://api.dropboxapi.com/2";
+const CONTENT_ENDPOINT: &str = "https://content.dropboxapi.com/2";
+const API_ARG_HEADER: &str = "Dropbox-API-Arg";
+const API_RESULT_HEADER: &str = "Dropbox-API-Result";
+const OCTET_STREAM: &str = "application/octet-stream";
+const DEFAULT_TOKEN_KEY: &str = "dropbox_token_set";
+/// Dropbox matches redirect URIs exactly, so these loopback ports must be
+/// registered as `http://localhost:<port>` in the app console.
+const REDIRECT_PORTS: [u16; 3] = [53682, 53683, 53684];
+const REDIRECT_RESPONSE: &str =
+    "<html><body style=\"font-family:sans-serif;text-align:center;padding-top:4em\">\
+     <p>Dropbox is connected. You can close this tab and return to Readest.</p></body></html>";
+/// An abandoned consent tab gives no signal, so give up eventually.
+const CONNECT_DEADLINE: Duration = Duration::from_secs(15 * 60);
+/// `files/upload` takes up to 150 MiB, but smaller requests restart cheaply.
+const SINGLE_UPLOAD_LIMIT: u64 = 8 * 1024 * 1024;
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+const LIST_LIMIT: u32 = 2000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropboxTarget {
+    /// The app key registered with Dropbox.
+    pub client_id: String,
+    #[serde(default = "default_token_key")]
+    pub token_key: String,
+}
+
+fn default_token_key() -> String {
+    DEFAULT_TOKEN_KEY.to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropboxAccount {
+    pub display_name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    /// `file`, `folder` or `deleted`.
+    #[serde(rename = ".tag")]
+    tag: String,
+    name: String,
+    path_display: Option<String>,
+    path_lower: Option<String>,
+    id: Option<String>,
+    size: Option<u64>,
+    server_modified: Option<String>,
+    rev: Option<String>,
+}
+
+impl Metadata {
+    fn is_folder(&self) -> bool {
+        self.tag == "folder"
+    }
+
+    fn is_deleted(&self) -> bool {
+        self.tag == "deleted"
+    }
+
+    fn into_change(self) -> RemoteChange {
+        RemoteChange {
+            is_directory: self.is_folder(),
+            removed: self.is_deleted(),
+            // Deleted entries carry no id; the lowercased path is stable.
+            id: self
+                .id
+                .or_else(|| self.path_lower.clone())
+                .unwrap_or_default(),
+            path: self.path_display,
+            size: self.size,
+            last_modified: self.server_modified,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListFolder {
+    entries: Vec<Metadata>,
+    cursor: String,
+    has_more: bool,
+}
+
+#[derive(Deserialize)]
+struct Cursor {
+    cursor: String,
+}
+
+#[derive(Deserialize)]
+struct UploadSession {
+    session_id: String,
+}
+
+#[derive(Deserialize)]
+struct SpaceUsage {
+    used: u64,
+    allocation: Allocation,
+}
+
+#[derive(Deserialize)]
+struct Allocation {
+    allocated: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    error_summary: String,
+    #[serde(default)]
+    error: Value,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    email: String,
+    name: AccountName,
+}
+
+#[derive(Deserialize)]
+struct AccountName {
+    display_name: String,
+}
+
+/// `Dropbox-API-Arg` must be HTTP-header-safe JSON: anything outside
+/// printable ASCII is escaped as `\uXXXX`.
+fn header_arg(value: &Value) -> String {
+    let mut out = String::new();
+    for c in value.to_string().chars() {
+        if (' '..='~').contains(&c) {
+            out.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    out
+}
+
+/// Dropbox reports endpoint errors as 409 with an `error_summary` like
+/// `path/not_found/..`; map those onto the engine's codes.
+fn classify_error(status: u16, body: &str, operation: &str) -> SyncError {
+    let summary = serde_json::from_str::<ApiError>(body)
+        .ok()
+        .map(|e| e.error_summary);
+    let detail = summary
+        .clone()
+        .unwrap_or_else(|| body.trim().chars().take(300).collect());
+    let mut error =
+        SyncError::from_status(status, format!("{operation} failed ({status}): {detail}"));
+    if status == 409 {
+        let summary = summary.unwrap_or_default();
+        error.code = if summary.contains("not_found") {
+            SyncErrorCode::NotFound
+        } else if summary.contains("conflict") || summary.starts_with("reset") {
+            SyncErrorCode::Conflict
+        } else {
+            SyncErrorCode::Unknown
+        };
+        error.reason = Some(summary.trim_end_matches(['/', '.']).to_string());
+    }
+    error
+}
+
+async fn dropbox_error(response: reqwest::Response, operation: &str) -> SyncError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    classify_error(status, &body, operation)
+}
+
+/// The offset an upload session actually holds, from an
+/// `incorrect_offset` append error.
+fn correct_offset(body: &str) -> Option<u64> {
+    let error = serde_json::from_str::<ApiError>(body).ok()?.error;
+    if error.get(".tag")?.as_str()? != "incorrect_offset" {
+        return None;
+    }
+    error.get("correct_offset")?.as_u64()
+}
+
+fn authorize_url(client_id: &str, challenge: &str, state: &str, redirect_uri: &str) -> String {
+    Url::parse_with_params(
+        AUTHORIZE_ENDPOINT,
+        &[
+            ("client_id", client_id),
+            ("response_type", "code"),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256"),
+            // Without this Dropbox issues a short-lived token and no refresh.
+            ("token_access_type", "offline"),
+            ("redirect_uri", redirect_uri),
+            ("state", state),
+        ],
+    )
+    .map(String::from)
+    .unwrap_or_default()
+}
+
+/// The authorization code from a loopback request, or `None` for requests
+/// that aren't our redirect (favicons, a stale tab's `state`).
+fn parse_redirect(url: &str, state: &str) -> Option<Result<String, SyncError>> {
+    let url = Url::parse(url)
+        .or_else(|_| Url::parse(&format!("http://localhost{url}")))
+        .ok()?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    if param("state").as_deref() != Some(state) {
+        return None;
+    }
+    if let Some(code) = param("code") {
+        return Some(Ok(code));
+    }
+    let reason = param("error_description")
+        .or_else(|| param("error"))
+        .unwrap_or_else(|| "no code returned".to_string());
+    Some(Err(SyncError::new(
+        SyncErrorCode::AuthFailed,
+        format!("Dropbox sign-in failed: {reason}"),
+    )))
+}
+
+pub(crate) struct DropboxBackend {
+    auth: OAuthClient,
+}
+
+impl DropboxBackend {
+    pub(crate) fn new(app: &AppHandle, target: DropboxTarget) -> Result<Self, SyncError> {
+        let auth = OAuthClient::load(
+            app,
+            crate::net::client(),
+            &target.token_key,
+            &target.client_id,
+            TOKEN_ENDPOINT,
+        )?;
+        Ok(Self { auth })
+    }
+
+    async fn rpc(&self, route: &str, args: &Value) -> Result<reqwest::Response, SyncError> {
+        let url = format!("{API_ENDPOINT}/{route}");
+        self.auth
+            .send(|| Ok(self.auth.http().post(&url).json(args)))
+            .await
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        route: &str,
+        args: &Value,
+        operation: &str,
+    ) -> Result<T, SyncError> {
+        let response = self.rpc(route, args).await?;
+        if !response.status().is_success() {
+            return Err(dropbox_error(response, operation).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// A content-endpoint call: arguments in the header, bytes in the body.
+    /// Call a content endpoint; `upload` paces the body under the
+    /// transfer policy.
+    async fn content(
+        &self,
+        route: &str,
+        args: &Value,
+        body: Vec<u8>,
+        upload: Option<&Upload>,
+    ) -> Result<reqwest::Response, SyncError> {
+        let url = format!("{CONTENT_ENDPOINT}/{route}");
+        let arg = header_arg(args);
+        self.auth
+            .send(|| {
+                Ok(self
+                    .auth
+                    .http()
+                    .post(&url)
+                    .header(API_ARG_HEADER, &arg)
+                    .header(reqwest::header::CONTENT_TYPE, OCTET_STREAM)
+                    .body(match upload {
+                        Some(upload) => upload.body(body.clone()),
+                        None => body.clone().into(),
+                    }))
+            })
+            .await
+    }
+
+    pub(crate) async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        let base = normalize(path);
+        let response = self
+            .rpc(
+                "files/list_folder",
+                &json!({ "path": base, "limit": LIST_LIMIT }),
+            )
+            .await?;
+        if !response.status().is_success() {
+            let error = dropbox_error(response, "list").await;
+            return match error.code {
+                SyncErrorCode::NotFound => Ok(Vec::new()),
+                _ => Err(error),
+            };
+        }
+        let mut page: ListFolder = response.json().await?;
+        let mut entries = Vec::new();
+        loop {
+            for meta in page.entries.into_iter().filter(|m| !m.is_deleted()) {
+                entries.push(RemoteEntry {
+                    path: format!("{base}/{}", meta.name),
+                    is_directory: meta.is_folder(),
+                    name: meta.name,
+                    size: meta.size,
+                    last_modified: meta.server_modified,
+                });
+            }
+            if !page.has_more {
+                return Ok(entries);
+            }
+            page = self
+                .call(
+                    "files/list_folder/continue",
+                    &json!({ "cursor": page.cursor }),
+                    "list",
+                )
+                .await?;
+        }
+    }
+
+    pub(crate) async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        let path = normalize(path);
+        // `get_metadata` rejects the root; it always exists.
+        if path.is_empty() {
+            return Ok(Some(RemoteHead {
+                size: None,
+                etag: None,
+            }));
+        }
+        let response = self
+            .rpc("files/get_metadata", &json!({ "path": path }))
+            .await?;
+        if !response.status().is_success() {
+            let error = dropbox_error(response, "head").await;
+            return match error.code {
+                SyncErrorCode::NotFound => Ok(None),
+                _ => Err(error),
+            };
+        }
+        let meta: Metadata = response.json().await?;
+        Ok(Some(RemoteHead {
+            size: meta.size,
+            etag: meta.rev,
+        }))
+    }
+
+    pub(crate) async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let response = self
+            .content(
+                "files/download",
+                &json!({ "path": normalize(remote_path) }),
+                Vec::new(),
+                None,
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Err(dropbox_error(response, "download").await);
+        }
+        let total = response
+            .headers()
+            .get(API_RESULT_HEADER)
+            .and_then(|v| serde_json::from_slice::<Metadata>(v.as_bytes()).ok())
+            .and_then(|meta| meta.size)
+            .or(response.content_length())
+            .unwrap_or(0);
+        let partial = format!("{local_path}.part");
+        let mut out = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stats = TransferStats::default();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk)
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+            stats.record_chunk_transfer(chunk.len());
+            let _ = on_progress.send(progress(&stats, total));
+        }
+        out.flush().await.map_err(|e| SyncError::io("write", e))?;
+        drop(out);
+        tokio::fs::rename(&partial, local_path)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+
+    pub(crate) async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let path = normalize(remote_path);
+        if path.is_empty() {
+            return Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                format!("not a file path: {remote_path}"),
+            ));
+        }
+        let size = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| SyncError::io("stat", e))?
+            .len();
+        let commit = json!({ "path": path, "mode": "overwrite", "mute": true });
+        if size <= SINGLE_UPLOAD_LIMIT {
+            let url = format!("{CONTENT_ENDPOINT}/files/upload");
+            let arg = header_arg(&commit);
+            let response = self
+                .auth
+                .send(|| {
+                    let file =
+                        std::fs::File::open(local_path).map_err(|e| SyncError::io("open", e))?;
+                    let body = upload.file_body(
+                        on_progress.clone(),
+                        tokio::fs::File::from_std(file),
+                        size,
+                    );
+                    Ok(self
+                        .auth
+                        .http()
+                        .post(&url)
+                        .header(API_ARG_HEADER, &arg)
+                        .header(reqwest::header::CONTENT_TYPE, OCTET_STREAM)
+                        .header(reqwest::header::CONTENT_LENGTH, size)
+                        .body(body))
+                })
+                .await?;
+            if !response.status().is_success() {
+                return Err(dropbox_error(response, "upload").await);
+            }
+            return Ok(());
+        }
+
+        let mut pending = upload.resume();
+        loop {
+            let resumed = pending.is_some();
+            let (session_id, offset) = match pending.take() {
+                Some(pending) => (pending.session, pending.offset),
+                None => {
+                    let session_id = self.start_session().await?;
+                    upload.save(&session_id, 0, &[]);
+                    (session_id, 0)
+                }
+            };
+            let result = self
+                .send_session(
+                    &session_id,
+                    offset,
+                    local_path,
+                    size,
+                    &commit,
+                    upload,
+                    &on_progress,
+                )
+                .await;
+            match result {
+                Ok(()) => {
+                    upload.finish();
+                    return Ok(());
+                }
+                // Keep the session journaled; the next attempt continues it.
+                Err(e) if e.code == SyncErrorCode::Network => return Err(e),
+                // The journaled session expired or was closed: start over.
+                Err(_) if resumed => upload.finish(),
+                Err(e) => {
+                    upload.finish();
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn start_session(&self) -> Result<String, SyncError> {
+        let response = self
+            .content(
+                "files/upload_session/start",
+                &json!({ "close": false }),
+                Vec::new(),
+                None,
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Err(dropbox_error(response, "upload").await);
+        }
+        let session: UploadSession = response.json().await?;
+        Ok(session.session_id)
+    }
+
+    /// Append from `offset` to the end of the file, then commit.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_session(
+        &self,
+        session_id: &str,
+        mut offset: u64,
+        local_path: &str,
+        size: u64,
+        commit: &Value,
+        upload: &Upload,
+        on_progress: &Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| SyncError::io("open", e))?;
+        let mut stats = TransferStats::default();
+        stats.total_transferred = offset;
+        while offset < size {
+            upload.check_network()?;
+            let len = CHUNK_SIZE.min(size - offset);
+            let mut chunk = vec![0; len as usize];
+            file.seek(SeekFrom::Start(offset))
+                .await
+                .map_err(|e| SyncError::io("seek", e))?;
+            file.read_exact(&mut chunk)
+                .await
+                .map_err(|e| SyncError::io("read", e))?;
+            let cursor = json!({ "session_id": session_id, "offset": offset });
+            let response = self
+                .content(
+                    "files/upload_session/append_v2",
+                    &json!({ "cursor": cursor, "close": false }),
+                    chunk,
+                    Some(upload),
+                )
+                .await?;
+            let status = response.status().as_u16();
+            if response.status().is_success() {
+                offset += len;
+                stats.record_chunk_transfer(len as usize);
+                let _ = on_progress.send(progress(&stats, size));
+                upload.save(session_id, offset, &[]);
+                continue;
+            }
+            let body = response.text().await.unwrap_or_default();
+            match correct_offset(&body) {
+                // A retried append whose first attempt already landed, or a
+                // journaled offset that lags the session.
+                Some(correct) if status == 409 && correct != offset => offset = correct,
+                _ => return Err(classify_error(status, &body, "upload")),
+            }
+        }
+        let cursor = json!({ "session_id": session_id, "offset": size });
+        let response = self
+            .content(
+                "files/upload_session/finish",
+                &json!({ "cursor": cursor, "commit": commit }),
+                Vec::new(),
+                None,
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Err(dropbox_error(response, "upload").await);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        let path = normalize(path);
+        if path.is_empty() {
+            return Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                "refusing to delete the app folder",
+            ));
+        }
+        let response = self
+            .rpc("files/delete_v2", &json!({ "path": path }))
+            .await?;
+        if !response.status().is_success() {
+            let error = dropbox_error(response, "delete").await;
+            if error.code != SyncErrorCode::NotFound {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes since `cursor`; without one, the current cursor for the
+    /// whole app folder. An expired cursor fails as `CONFLICT` with reason
+    /// `reset`, meaning "list everything again".
+    pub(crate) async fn changes(&self, cursor: Option<&str>) -> Result<RemoteChanges, SyncError> {
+        let Some(cursor) = cursor else {
+            let latest: Cursor = self
+                .call(
+                    "files/list_folder/get_latest_cursor",
+                    &json!({ "path": "", "recursive": true, "include_deleted": true }),
+                    "changes",
+                )
+                .await?;
+            return Ok(RemoteChanges {
+                changes: Vec::new(),
+                cursor: latest.cursor,
+            });
+        };
+        let mut changes = Vec::new();
+        let mut cursor = cursor.to_string();
+        loop {
+            let page: ListFolder = self
+                .call(
+                    "files/list_folder/continue",
+                    &json!({ "cursor": cursor }),
+                    "changes",
+                )
+                .await?;
+            changes.extend(page.entries.into_iter().map(Metadata::into_change));
+            cursor = page.cursor;
+            if !page.has_more {
+                return Ok(RemoteChanges { changes, cursor });
+            }
+        }
+    }
+
+    pub(crate) async fn quota(&self) -> Result<StorageQuota, SyncError> {
+        let usage: SpaceUsage = self
+            .call("users/get_space_usage", &Value::Null, "quota")
+            .await?;
+        Ok(StorageQuota {
+            used: usage.used,
+            limit: usage.allocation.allocated,
+        })
+    }
+}
+
+/// Sign in to Dropbox in the system browser and store the tokens under
+/// `token_key` (default `dropbox_token_set`).
+#[tauri::command]
+pub async fn dropbox_connect(
+    app: AppHandle,
+    client_id: String,
+    token_key: Option<String>,
+) -> Result<DropboxAccount, SyncError> {
+    let token_key = token_key.unwrap_or_else(default_token_key);
+    let (verifier, challenge) = pkce_pair();
+    let state = random_token(32);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let config = OauthConfig {
+        ports: Some(REDIRECT_PORTS.to_vec()),
+        response: Some(REDIRECT_RESPONSE.into()),
+    };
+    let port = tauri_plugin_oauth::start_with_config(config, move |url| {
+        let _ = tx.send(url);
+    })
+    .map_err(|e| SyncError::new(SyncErrorCode::Unknown, format!("redirect listener: {e}")))?;
+    let redirect_uri = format!("http://localhost:{port}");
+
+    let result = async {
+        app.opener()
+            .open_url(
+                authorize_url(&client_id, &challenge, &state, &redirect_uri),
+                None::<&str>,
+            )
+            .map_err(|e| SyncError::new(SyncErrorCode::Unknown, format!("open browser: {e}")))?;
+        let code = tokio::time::timeout(CONNECT_DEADLINE, async {
+            while let Some(url) = rx.recv().await {
+                if let Some(result) = parse_redirect(&url, &state) {
+                    return result;
+                }
+            }
+            Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                "redirect listener closed",
+            ))
+        })
+        .await
+        .map_err(|_| SyncError::new(SyncErrorCode::AuthFailed, "Dropbox sign-in timed out"))??;
+
+        let http = crate::net::client();
+        let tokens = exchange_code(
+            &http,
+            TOKEN_ENDPOINT,
+            &client_id,
+            &code,
+            &verifier,
+            &redirect_uri,
+        )
+        .await?;
+        let stored = serde_json::to_string(&tokens)
+            .map_err(|e| SyncError::new(SyncErrorCode::Unknown, e.to_string()))?;
+        save_secret(&app, &token_key, &stored)?;
+
+        let response = http
+            .post(format!("{API_ENDPOINT}/users/get_current_account"))
+            .bearer_auth(&tokens.access_token)
+            .json(&Value::Null)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(dropbox_error(response, "account").await);
+        }
+        let account: Account = response.json().await?;
+        Ok(DropboxAccount {
+            display_name: account.name.display_name,
+            email: account.email,
+        })
+    }
+    .await;
+
+    if let Err(e) = tauri_plugin_oauth::cancel(port) {
+        log::warn!("Failed to stop the Dropbox redirect listener: {e}");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_header_args_to_ascii() {
+        let arg = header_arg(&json!({ "path": "/Readest/книга 📖.epub" }));
+        assert!(arg.is_ascii());
+        assert!(arg.contains("\\u043a"));
+        assert!(arg.contains("\\ud83d\\udcd6"));
+        assert!(arg.starts_with(r#"{"path":"/Readest/"#));
+    }
+
+    #[test]
+    fn classifies_endpoint_errors() {
+        let body = |summary: &str| format!(r#"{{"error_summary":"{summary}","error":{{}}}}"#);
+        let missing = classify_error(409, &body("path/not_found/.."), "head");
+        assert_eq!(missing.code, SyncErrorCode::NotFound);
+        assert_eq!(missing.reason.as_deref(), Some("path/not_found"));
+        let full = classify_error(409, &body("path/insufficient_space/."), "upload");
+        assert_eq!(full.code, SyncErrorCode::Unknown);
+        assert_eq!(full.reason.as_deref(), Some("path/insufficient_space"));
+        let reset = classify_error(409, &body("reset/.."), "changes");
+        assert_eq!(reset.code, SyncErrorCode::Conflict);
+        assert_eq!(
+            classify_error(401, "expired", "list").code,
+            SyncErrorCode::AuthFailed
+        );
+    }
+
+    #[test]
+    fn reads_the_correct_offset() {
+        let body = r#"{"error_summary":"incorrect_offset/..","error":{".tag":"incorrect_offset","correct_offset":8388608}}"#;
+        assert_eq!(correct_offset(body), Some(8_388_608));
+        assert_eq!(
+            correct_offset(r#"{"error_summary":"closed/..","error":{".tag":"closed"}}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_loopback_redirects() {
+        let ok = parse_redirect("http://localhost:53682/?code=abc&state=s1", "s1");
+        assert_eq!(ok.unwrap().unwrap(), "abc");
+        assert!(parse_redirect("/favicon.ico", "s1").is_none());
+        assert!(parse_redirect("/?code=abc&state=other", "s1").is_none());
+        let denied = parse_redirect("/?error=access_denied&state=s1", "s1").unwrap();
+        assert_eq!(denied.unwrap_err().code, SyncErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn authorize_url_requests_offline_pkce() {
+        let url = authorize_url("key", "chal", "st", "http://localhost:53682");
+        assert!(url.starts_with(AUTHORIZE_ENDPOINT));
+        assert!(url.contains("code_challenge_method=S256"));
+        assert!(url.contains("token_access_type=offline"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A53682"));
+    }
+}
@@ -0,0 +1,889 @@
+//! Google Drive backend.
+//!
+//! Sign-in stays with the webview's PKCE flow (`providers/gdrive/`); this
+//! side reads the stored token set through [`OAuthClient`] and refreshes it
+//! natively. Files live either under a folder the user picked (or My Drive's
+//! root) with the `drive.file` scope, or in the hidden per-app
+//! `appDataFolder` with `drive.appdata`. Logical paths resolve segment by
+//! segment to file ids the same way `GoogleDriveProvider.ts` does, so both
+//! transports see one tree.
+//!
+//! Uploads open a resumable session and send [`CHUNK_SIZE`] pieces; when a
+//! chunk fails the session is asked how much it holds and the upload picks
+//! up from there instead of starting over. The session URI is journaled, so
+//! an upload cut off by a crash or a lost connection is continued by the
+//! next attempt as well. [`DriveBackend::changes`] pages
+//! through the Changes API so a pull only touches what moved since the
+//! last token.
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::sync::Mutex;
+use tauri::{ipc::Channel, AppHandle};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::oauth::OAuthClient;
+use super::transfer::Upload;
+use super::{
+    backoff, is_transient, normalize, progress, segments, send_with_retry, RemoteChange,
+    RemoteChanges, RemoteEntry, RemoteHead, StorageQuota, SyncError, SyncErrorCode, MAX_RETRIES,
+};
+use crate::transfer_file::{ProgressPayload, TransferStats};
+
+const TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const FILES_ENDPOINT: &str = "https://www.googleapis.com/drive/v3/files";
+const UPLOAD_ENDPOINT: &str = "https://www.googleapis.com/upload/drive/v3/files";
+const CHANGES_ENDPOINT: &str = "https://www.googleapis.com/drive/v3/changes";
+const ABOUT_ENDPOINT: &str = "https://www.googleapis.com/drive/v3/about";
+const FOLDER_MIME: &str = "application/vnd.google-apps.folder";
+const APP_DATA_FOLDER: &str = "appDataFolder";
+const MY_DRIVE_ROOT: &str = "root";
+/// Same key `driveTokenStore.ts` saves the token set under.
+const DEFAULT_TOKEN_KEY: &str = "gdrive_token_set";
+const LIST_PAGE_SIZE: &str = "1000";
+const FILE_FIELDS: &str = "id,name,mimeType,size,modifiedTime,md5Checksum,parents";
+/// Resumable chunks must be a multiple of 256 KiB; 8 MiB keeps the number
+/// of round trips low without resending much after a drop.
+const CHUNK_SIZE: u64 = 32 * 256 * 1024;
+/// Guards the parent walk in [`DriveBackend::path_of`] against cycles.
+const MAX_DEPTH: usize = 64;
+/// 403 reasons that mean "slow down" rather than "not allowed".
+const RATE_LIMIT_REASONS: [&str; 2] = ["rateLimitExceeded", "userRateLimitExceeded"];
+const QUOTA_REASONS: [&str; 2] = ["storageQuotaExceeded", "quotaExceeded"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleDriveTarget {
+    /// The OAuth client the webview signed in with.
+    pub client_id: String,
+    #[serde(default = "default_token_key")]
+    pub token_key: String,
+    /// `appDataFolder`, the id of a folder the user picked, or absent for
+    /// the root of My Drive.
+    #[serde(default)]
+    pub folder_id: Option<String>,
+}
+
+fn default_token_key() -> String {
+    DEFAULT_TOKEN_KEY.to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveFile {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    mime_type: String,
+    /// Drive sends 64-bit numbers as strings.
+    size: Option<String>,
+    modified_time: Option<String>,
+    md5_checksum: Option<String>,
+    #[serde(default)]
+    parents: Vec<String>,
+    #[serde(default)]
+    trashed: bool,
+}
+
+impl DriveFile {
+    fn is_folder(&self) -> bool {
+        self.mime_type == FOLDER_MIME
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.size.as_deref().and_then(|s| s.parse().ok())
+    }
+
+    fn into_entry(self, path: String) -> RemoteEntry {
+        RemoteEntry {
+            is_directory: self.is_folder(),
+            size: self.size(),
+            name: self.name,
+            path,
+            last_modified: self.modified_time,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileList {
+    next_page_token: Option<String>,
+    #[serde(default)]
+    files: Vec<DriveFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangeList {
+    next_page_token: Option<String>,
+    new_start_page_token: Option<String>,
+    #[serde(default)]
+    changes: Vec<Change>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    file_id: String,
+    #[serde(default)]
+    removed: bool,
+    file: Option<DriveFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartPageToken {
+    start_page_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct About {
+    storage_quota: Quota,
+}
+
+#[derive(Deserialize)]
+struct Quota {
+    limit: Option<String>,
+    usage: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    errors: Vec<ErrorItem>,
+}
+
+#[derive(Deserialize)]
+struct ErrorItem {
+    reason: Option<String>,
+}
+
+enum UploadState {
+    Done(DriveFile),
+    /// Bytes the session has committed so far.
+    Partial(u64),
+}
+
+/// Quote a literal for a `files.list` query: backslash first, then `'`.
+fn escape_query(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// `/a/b/c` → (`/a/b`, `c`).
+fn split_parent(path: &str) -> Option<(String, &str)> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    (!name.is_empty()).then(|| (normalize(parent), name))
+}
+
+fn chunk_len(offset: u64, total: u64) -> u64 {
+    CHUNK_SIZE.min(total - offset)
+}
+
+fn content_range(offset: u64, len: u64, total: u64) -> String {
+    if len == 0 {
+        format!("bytes */{total}")
+    } else {
+        format!("bytes {offset}-{}/{total}", offset + len - 1)
+    }
+}
+
+/// Bytes committed according to a 308's `Range: bytes=0-N` header.
+fn committed_bytes(range: Option<&str>) -> u64 {
+    range
+        .and_then(|r| r.rsplit('-').next())
+        .and_then(|end| end.trim().parse::<u64>().ok())
+        .map_or(0, |end| end + 1)
+}
+
+/// Drive reports why a 403 happened in the body; rate limits are retryable
+/// and a full Drive is neither an auth nor a network problem.
+fn classify_error(status: u16, body: &str, operation: &str) -> SyncError {
+    let reason = serde_json::from_str::<ErrorBody>(body)
+        .ok()
+        .and_then(|b| b.error.errors.into_iter().find_map(|e| e.reason));
+    let detail: String = body.trim().chars().take(300).collect();
+    let mut error =
+        SyncError::from_status(status, format!("{operation} failed ({status}): {detail}"));
+    match reason.as_deref() {
+        Some(r) if RATE_LIMIT_REASONS.contains(&r) => error.code = SyncErrorCode::Network,
+        Some(r) if QUOTA_REASONS.contains(&r) => error.code = SyncErrorCode::Unknown,
+        _ => {}
+    }
+    error.reason = reason;
+    error
+}
+
+async fn drive_error(response: reqwest::Response, operation: &str) -> SyncError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    classify_error(status, &body, operation)
+}
+
+async fn upload_state(response: reqwest::Response) -> Result<UploadState, SyncError> {
+    match response.status().as_u16() {
+        200 | 201 => Ok(UploadState::Done(response.json().await?)),
+        308 => Ok(UploadState::Partial(committed_bytes(
+            response
+                .headers()
+                .get(reqwest::header::RANGE)
+                .and_then(|v| v.to_str().ok()),
+        ))),
+        404 | 410 => Err(SyncError::new(
+            SyncErrorCode::Network,
+            "upload session expired; retry the upload",
+        )),
+        _ => Err(drive_error(response, "upload").await),
+    }
+}
+
+pub(crate) struct DriveBackend {
+    auth: OAuthClient,
+    root_id: String,
+    app_data: bool,
+    /// Logical path → file id, so sibling lookups don't re-walk the tree.
+    ids: Mutex<HashMap<String, String>>,
+}
+
+impl DriveBackend {
+    pub(crate) fn new(app: &AppHandle, target: GoogleDriveTarget) -> Result<Self, SyncError> {
+        let auth = OAuthClient::load(
+            app,
+            crate::net::client(),
+            &target.token_key,
+            &target.client_id,
+            TOKEN_ENDPOINT,
+        )?;
+        let root_id = target
+            .folder_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| MY_DRIVE_ROOT.to_string());
+        Ok(Self {
+            auth,
+            app_data: root_id == APP_DATA_FOLDER,
+            root_id,
+            ids: Mutex::default(),
+        })
+    }
+
+    fn spaces(&self) -> &'static str {
+        if self.app_data {
+            APP_DATA_FOLDER
+        } else {
+            "drive"
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        operation: &str,
+    ) -> Result<T, SyncError> {
+        let response = self
+            .auth
+            .send(|| Ok(self.auth.http().get(url).query(query)))
+            .await?;
+        if !response.status().is_success() {
+            return Err(drive_error(response, operation).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Every file matching `q`, draining `nextPageToken`. Oldest first, so
+    /// duplicate names created by racing clients resolve the same way.
+    async fn query(&self, q: &str) -> Result<Vec<DriveFile>, SyncError> {
+        let fields = format!("nextPageToken,files({FILE_FIELDS})");
+        let mut files = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut params = vec![
+                ("q", q),
+                ("fields", fields.as_str()),
+                ("pageSize", LIST_PAGE_SIZE),
+                ("spaces", self.spaces()),
+                ("orderBy", "createdTime"),
+            ];
+            if let Some(token) = &page_token {
+                params.push(("pageToken", token.as_str()));
+            }
+            let page: FileList = self.get_json(FILES_ENDPOINT, &params, "list").await?;
+            files.extend(page.files);
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(files),
+            }
+        }
+    }
+
+    async fn find_child(
+        &self,
+        parent_id: &str,
+        name: &str,
+    ) -> Result<Option<DriveFile>, SyncError> {
+        let q = format!(
+            "name = '{}' and '{}' in parents and trashed = false",
+            escape_query(name),
+            escape_query(parent_id)
+        );
+        Ok(self.query(&q).await?.into_iter().next())
+    }
+
+    async fn create_folder(&self, parent_id: &str, name: &str) -> Result<String, SyncError> {
+        let metadata = json!({ "name": name, "mimeType": FOLDER_MIME, "parents": [parent_id] });
+        let response = self
+            .auth
+            .send(|| {
+                Ok(self
+                    .auth
+                    .http()
+                    .post(FILES_ENDPOINT)
+                    .query(&[("fields", "id")])
+                    .json(&metadata))
+            })
+            .await?;
+        if !response.status().is_success() {
+            return Err(drive_error(response, "create folder").await);
+        }
+        Ok(response.json::<DriveFile>().await?.id)
+    }
+
+    /// Walk `path` to a file id, creating missing folders when `create`.
+    async fn walk(&self, path: &str, create: bool) -> Result<Option<String>, SyncError> {
+        let mut id = self.root_id.clone();
+        let mut current = String::new();
+        for segment in segments(path) {
+            current.push('/');
+            current.push_str(segment);
+            if let Some(cached) = self.ids.lock().unwrap().get(&current) {
+                id = cached.clone();
+                continue;
+            }
+            id = match self.find_child(&id, segment).await? {
+                Some(file) => file.id,
+                None if create => self.create_folder(&id, segment).await?,
+                None => return Ok(None),
+            };
+            self.ids.lock().unwrap().insert(current.clone(), id.clone());
+        }
+        Ok(Some(id))
+    }
+
+    async fn ensure_dir(&self, path: &str) -> Result<String, SyncError> {
+        self.walk(path, true)
+            .await?
+            .ok_or_else(|| SyncError::new(SyncErrorCode::Unknown, format!("cannot create {path}")))
+    }
+
+    /// Fresh metadata for `path`, or `None` when it doesn't exist.
+    async fn stat(&self, path: &str) -> Result<Option<DriveFile>, SyncError> {
+        let Some((parent, name)) = split_parent(path) else {
+            return Ok(None);
+        };
+        let Some(parent_id) = self.walk(&parent, false).await? else {
+            return Ok(None);
+        };
+        let file = self.find_child(&parent_id, name).await?;
+        if let Some(file) = &file {
+            self.ids
+                .lock()
+                .unwrap()
+                .insert(normalize(path), file.id.clone());
+        }
+        Ok(file)
+    }
+
+    pub(crate) async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        // Drive has no paths, so a missing folder simply has no children.
+        let Some(folder_id) = self.walk(path, false).await? else {
+            return Ok(Vec::new());
+        };
+        let q = format!(
+            "'{}' in parents and trashed = false",
+            escape_query(&folder_id)
+        );
+        let files = self.query(&q).await?;
+        let base = normalize(path);
+        let mut ids = self.ids.lock().unwrap();
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let child = format!("{base}/{}", file.name);
+                ids.insert(child.clone(), file.id.clone());
+                file.into_entry(child)
+            })
+            .collect())
+    }
+
+    pub(crate) async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        Ok(self.stat(path).await?.map(|file| RemoteHead {
+            size: file.size(),
+            etag: file.md5_checksum,
+        }))
+    }
+
+    pub(crate) async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let file = self
+            .stat(remote_path)
+            .await?
+            .filter(|file| !file.is_folder())
+            .ok_or_else(|| {
+                SyncError::new(SyncErrorCode::NotFound, format!("{remote_path} not found"))
+            })?;
+        let url = format!("{FILES_ENDPOINT}/{}", file.id);
+        let response = self
+            .auth
+            .send(|| Ok(self.auth.http().get(&url).query(&[("alt", "media")])))
+            .await?;
+        if !response.status().is_success() {
+            return Err(drive_error(response, "download").await);
+        }
+        let total = file.size().or(response.content_length()).unwrap_or(0);
+        let partial = format!("{local_path}.part");
+        let mut out = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stats = TransferStats::default();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk)
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+            stats.record_chunk_transfer(chunk.len());
+            let _ = on_progress.send(progress(&stats, total));
+        }
+        out.flush().await.map_err(|e| SyncError::io("write", e))?;
+        drop(out);
+        tokio::fs::rename(&partial, local_path)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+
+    /// Open a resumable session: an update when the file exists (so its id
+    /// and sharing survive), otherwise a create carrying name and parent.
+    async fn start_session(
+        &self,
+        existing_id: Option<&str>,
+        name: &str,
+        parent_id: &str,
+        size: u64,
+    ) -> Result<String, SyncError> {
+        let response = self
+            .auth
+            .send(|| {
+                let http = self.auth.http();
+                let request = match existing_id {
+                    Some(id) => http
+                        .patch(format!("{UPLOAD_ENDPOINT}/{id}"))
+                        .json(&json!({})),
+                    None => http
+                        .post(UPLOAD_ENDPOINT)
+                        .json(&json!({ "name": name, "parents": [parent_id] })),
+                };
+                Ok(request
+                    .query(&[("uploadType", "resumable"), ("fields", FILE_FIELDS)])
+                    .header("X-Upload-Content-Type", "application/octet-stream")
+                    .header("X-Upload-Content-Length", size))
+            })
+            .await?;
+        if !response.status().is_success() {
+            return Err(drive_error(response, "upload").await);
+        }
+        response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| SyncError::new(SyncErrorCode::Unknown, "upload session has no Location"))
+    }
+
+    /// Ask the session how much it has after a failed chunk.
+    async fn query_session(&self, session: &str, size: u64) -> Result<UploadState, SyncError> {
+        let response = send_with_retry(|| {
+            Ok(self
+                .auth
+                .http()
+                .put(session)
+                .header(reqwest::header::CONTENT_RANGE, format!("bytes */{size}")))
+        })
+        .await?;
+        upload_state(response).await
+    }
+
+    async fn send_chunks(
+        &self,
+        session: &str,
+        local_path: &str,
+        size: u64,
+        mut offset: u64,
+        upload: &Upload,
+        on_progress: &Channel<ProgressPayload>,
+    ) -> Result<DriveFile, SyncError> {
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| SyncError::io("open", e))?;
+        let mut stats = TransferStats::default();
+        stats.total_transferred = offset;
+        let mut failures = 0;
+        loop {
+            upload.check_network()?;
+            let len = chunk_len(offset, size);
+            let mut chunk = vec![0; len as usize];
+            file.seek(SeekFrom::Start(offset))
+                .await
+                .map_err(|e| SyncError::io("seek", e))?;
+            file.read_exact(&mut chunk)
+                .await
+                .map_err(|e| SyncError::io("read", e))?;
+            // The session URI is its own credential, so no bearer token:
+            // a long upload can outlive the access token.
+            let sent = self
+                .auth
+                .http()
+                .put(session)
+                .header(
+                    reqwest::header::CONTENT_RANGE,
+                    content_range(offset, len, size),
+                )
+                .body(upload.body(chunk))
+                .send()
+                .await;
+            let state = match sent {
+                Ok(response) if !is_transient(response.status()) => upload_state(response).await?,
+                Ok(response) if failures >= MAX_RETRIES => {
+                    return Err(drive_error(response, "upload").await)
+                }
+                Err(e) if failures >= MAX_RETRIES => return Err(e.into()),
+                _ => {
+                    tokio::time::sleep(backoff(failures)).await;
+                    failures += 1;
+                    self.query_session(session, size).await?
+                }
+            };
+            let committed = match &state {
+                UploadState::Done(_) => size,
+                UploadState::Partial(committed) => *committed,
+            };
+            if committed > offset {
+                stats.record_chunk_transfer((committed - offset) as usize);
+                let _ = on_progress.send(progress(&stats, size));
+                upload.save(session, committed, &[]);
+                failures = 0;
+            }
+            match state {
+                UploadState::Done(file) => return Ok(file),
+                UploadState::Partial(committed) => offset = committed,
+            }
+        }
+    }
+
+    pub(crate) async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let (parent, name) = split_parent(remote_path).ok_or_else(|| {
+            SyncError::new(
+                SyncErrorCode::Unknown,
+                format!("not a file path: {remote_path}"),
+            )
+        })?;
+        let size = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| SyncError::io("stat", e))?
+            .len();
+        // Continue a journaled session if Drive still holds it.
+        let resumed = match upload.resume() {
+            Some(pending) => match self.query_session(&pending.session, size).await {
+                Ok(UploadState::Partial(committed)) => Some((pending.session, committed)),
+                Ok(UploadState::Done(file)) => {
+                    upload.finish();
+                    self.ids
+                        .lock()
+                        .unwrap()
+                        .insert(normalize(remote_path), file.id);
+                    return Ok(());
+                }
+                Err(_) => None,
+            },
+            None => None,
+        };
+        let (session, offset) = match resumed {
+            Some(resumed) => resumed,
+            None => {
+                let parent_id = self.ensure_dir(&parent).await?;
+                let existing = self.find_child(&parent_id, name).await?;
+                let session = self
+                    .start_session(
+                        existing.as_ref().map(|f| f.id.as_str()),
+                        name,
+                        &parent_id,
+                        size,
+                    )
+                    .await?;
+                upload.save(&session, 0, &[]);
+                (session, 0)
+            }
+        };
+        let file = match self
+            .send_chunks(&session, local_path, size, offset, upload, &on_progress)
+            .await
+        {
+            Ok(file) => file,
+            // Network failures keep the session journaled for next time.
+            Err(e) if e.code == SyncErrorCode::Network => return Err(e),
+            Err(e) => {
+                upload.finish();
+                return Err(e);
+            }
+        };
+        upload.finish();
+        self.ids
+            .lock()
+            .unwrap()
+            .insert(normalize(remote_path), file.id);
+        Ok(())
+    }
+
+    pub(crate) async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        let path = normalize(path);
+        if path.is_empty() {
+            return Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                "refusing to delete the sync root",
+            ));
+        }
+        let Some(id) = self.walk(&path, false).await? else {
+            return Ok(());
+        };
+        let url = format!("{FILES_ENDPOINT}/{id}");
+        let response = self.auth.send(|| Ok(self.auth.http().delete(&url))).await?;
+        let status = response.status();
+        if !status.is_success() && status != reqwest::StatusCode::NOT_FOUND {
+            return Err(drive_error(response, "delete").await);
+        }
+        let prefix = format!("{path}/");
+        self.ids
+            .lock()
+            .unwrap()
+            .retain(|p, _| p != &path && !p.starts_with(&prefix));
+        Ok(())
+    }
+
+    /// Logical path of `file`, or `None` when it sits outside the sync root.
+    /// `known` memoises folder id → path across one change batch.
+    async fn path_of(
+        &self,
+        file: &DriveFile,
+        known: &mut HashMap<String, Option<String>>,
+    ) -> Result<Option<String>, SyncError> {
+        let mut names = vec![file.name.clone()];
+        let mut chain: Vec<String> = Vec::new();
+        let mut parent = file.parents.first().cloned();
+        let base = loop {
+            let Some(id) = parent else { break None };
+            if let Some(path) = known.get(&id) {
+                break path.clone();
+            }
+            if chain.len() >= MAX_DEPTH {
+                break None;
+            }
+            let url = format!("{FILES_ENDPOINT}/{id}");
+            let folder: DriveFile = self
+                .get_json(&url, &[("fields", "id,name,parents")], "stat")
+                .await?;
+            names.push(folder.name);
+            parent = folder.parents.first().cloned();
+            chain.push(id);
+        };
+        let Some(mut path) = base else {
+            for id in chain {
+                known.insert(id, None);
+            }
+            return Ok(None);
+        };
+        for (i, id) in chain.iter().enumerate().rev() {
+            path = format!("{path}/{}", names[i + 1]);
+            known.insert(id.clone(), Some(path.clone()));
+        }
+        Ok(Some(format!("{path}/{}", names[0])))
+    }
+
+    /// Changes since `cursor`. Without one, returns only the current start
+    /// token, which the caller stores after its initial full listing.
+    pub(crate) async fn changes(&self, cursor: Option<&str>) -> Result<RemoteChanges, SyncError> {
+        let spaces = [("spaces", self.spaces())];
+        let Some(cursor) = cursor else {
+            let start: StartPageToken = self
+                .get_json(
+                    &format!("{CHANGES_ENDPOINT}/startPageToken"),
+                    &spaces,
+                    "changes",
+                )
+                .await?;
+            return Ok(RemoteChanges {
+                changes: Vec::new(),
+                cursor: start.start_page_token,
+            });
+        };
+        // Parents come back as real ids, never the `root` alias.
+        let root: DriveFile = self
+            .get_json(
+                &format!("{FILES_ENDPOINT}/{}", self.root_id),
+                &[("fields", "id")],
+                "stat",
+            )
+            .await?;
+        let mut known = HashMap::from([(root.id, Some(String::new()))]);
+        let paths: HashMap<String, String> = self
+            .ids
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, id)| (id.clone(), path.clone()))
+            .collect();
+        let fields = format!(
+            "nextPageToken,newStartPageToken,changes(fileId,removed,file({FILE_FIELDS},trashed))"
+        );
+        let mut changes = Vec::new();
+        let mut page_token = cursor.to_string();
+        loop {
+            let params = [
+                ("pageToken", page_token.as_str()),
+                ("pageSize", LIST_PAGE_SIZE),
+                ("spaces", self.spaces()),
+                ("fields", fields.as_str()),
+            ];
+            let page: ChangeList = self.get_json(CHANGES_ENDPOINT, &params, "changes").await?;
+            for change in page.changes {
+                match change.file.filter(|f| !change.removed && !f.trashed) {
+                    Some(file) => {
+                        let Some(path) = self.path_of(&file, &mut known).await? else {
+                            continue;
+                        };
+                        changes.push(RemoteChange {
+                            id: change.file_id,
+                            path: Some(path),
+                            removed: false,
+                            is_directory: file.is_folder(),
+                            size: file.size(),
+                            last_modified: file.modified_time,
+                        });
+                    }
+                    None => changes.push(RemoteChange {
+                        path: paths.get(&change.file_id).cloned(),
+                        id: change.file_id,
+                        removed: true,
+                        is_directory: false,
+                        size: None,
+                        last_modified: None,
+                    }),
+                }
+            }
+            if let Some(token) = page.new_start_page_token {
+                return Ok(RemoteChanges {
+                    changes,
+                    cursor: token,
+                });
+            }
+            page_token = page.next_page_token.ok_or_else(|| {
+                SyncError::new(SyncErrorCode::Unknown, "change feed ended without a token")
+            })?;
+        }
+    }
+
+    pub(crate) async fn quota(&self) -> Result<StorageQuota, SyncError> {
+        let about: About = self
+            .get_json(
+                ABOUT_ENDPOINT,
+                &[("fields", "storageQuota(limit,usage)")],
+                "quota",
+            )
+            .await?;
+        let parse = |v: Option<String>| v.and_then(|s| s.parse().ok());
+        Ok(StorageQuota {
+            used: parse(about.storage_quota.usage).unwrap_or(0),
+            limit: parse(about.storage_quota.limit),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_query_literals() {
+        assert_eq!(escape_query(r"it's a\b"), r"it\'s a\\b");
+    }
+
+    #[test]
+    fn splits_and_normalizes_paths() {
+        assert_eq!(
+            split_parent("/Readest/books/a.epub"),
+            Some(("/Readest/books".to_string(), "a.epub"))
+        );
+        assert_eq!(split_parent("a.epub"), Some((String::new(), "a.epub")));
+        assert_eq!(split_parent("/"), None);
+    }
+
+    #[test]
+    fn chunks_ranges_on_256k_boundaries() {
+        assert_eq!(CHUNK_SIZE % (256 * 1024), 0);
+        let total = CHUNK_SIZE + 10;
+        assert_eq!(chunk_len(0, total), CHUNK_SIZE);
+        assert_eq!(chunk_len(CHUNK_SIZE, total), 10);
+        assert_eq!(
+            content_range(CHUNK_SIZE, 10, total),
+            format!("bytes {CHUNK_SIZE}-{}/{total}", CHUNK_SIZE + 9)
+        );
+        assert_eq!(content_range(0, 0, 0), "bytes */0");
+    }
+
+    #[test]
+    fn reads_committed_range() {
+        assert_eq!(committed_bytes(Some("bytes=0-524287")), 524288);
+        assert_eq!(committed_bytes(None), 0);
+    }
+
+    #[test]
+    fn classifies_forbidden_by_reason() {
+        let body = |reason: &str| {
+            format!(r#"{{"error":{{"code":403,"errors":[{{"reason":"{reason}"}}]}}}}"#)
+        };
+        let rate = classify_error(403, &body("userRateLimitExceeded"), "list");
+        assert_eq!(rate.code, SyncErrorCode::Network);
+        let quota = classify_error(403, &body("storageQuotaExceeded"), "upload");
+        assert_eq!(quota.code, SyncErrorCode::Unknown);
+        assert_eq!(quota.reason.as_deref(), Some("storageQuotaExceeded"));
+        let denied = classify_error(403, &body("insufficientPermissions"), "list");
+        assert_eq!(denied.code, SyncErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn target_defaults_to_the_ts_token_key() {
+        let target: GoogleDriveTarget =
+            serde_json::from_str(r#"{"clientId":"c","folderId":"appDataFolder"}"#).unwrap();
+        assert_eq!(target.token_key, DEFAULT_TOKEN_KEY);
+        assert_eq!(target.folder_id.as_deref(), Some(APP_DATA_FOLDER));
+    }
+}
@@ -0,0 +1,1496 @@
+//! Local sync between Readest instances on the same network, no cloud
+//! account involved.
+//!
+//! A device that *hosts* serves a sync store (a plain directory under the
+//! app data dir, laid out like a cloud sync root) over HTTPS and advertises
+//! itself over mDNS as [`SERVICE_TYPE`]. The file-sync engine then treats
+//! LAN sync as one more provider: a `lan` [`LanTarget`] without `peerId`
+//! syncs with this device's own store on disk, one with a `peerId` syncs
+//! with a paired device's store over the network. Books, progress and
+//! annotations travel exactly as they do to a cloud provider, and edits
+//! made on both sides go through the same three-way merge.
+//!
+//! Trust is established once per pair of devices:
+//!
+//!   1. Every device has a self-signed certificate, generated on first use
+//!      and kept in the keychain. Its SHA-256 fingerprint is advertised in
+//!      the mDNS TXT record.
+//!   2. The host shows a six-digit pairing code ([`lan_sync_pairing_code`]).
+//!   3. The joining device connects pinned to the advertised fingerprint and
+//!      proves it knows the code with an HMAC, keyed by Argon2id(code), over
+//!      both fingerprints. The host answers with a fresh shared token and a
+//!      proof of its own, so each side knows the other had the code and that
+//!      nobody swapped certificates in between.
+//!   4. Both keep the other's fingerprint and the token. Later connections
+//!      pin the certificate and send the token; the code isn't needed again.
+//!
+//! A code is single-use, expires after [`CODE_TTL`] and is dropped after
+//! [`MAX_CODE_ATTEMPTS`] wrong guesses; the Argon2id cost makes guessing it
+//! offline from an intercepted proof slow.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use axum::body::Body;
+use axum::extract::{Query, Request, State as AxumState};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::{StreamExt, TryStreamExt};
+use hmac::{Hmac, Mac};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rand::{Rng, RngCore};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{ipc::Channel, AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+use super::crypto::blocking;
+use super::s3::civil_from_days;
+use super::transfer::Upload;
+use super::{
+    error_for, load_secret, normalize, progress, save_secret, segments, send_with_retry,
+    RemoteEntry, RemoteHead, SyncError, SyncErrorCode,
+};
+use crate::net::trust::fingerprint;
+use crate::reading_server::token_matches;
+use crate::transfer_file::{ProgressPayload, TransferStats};
+
+pub const SERVICE_TYPE: &str = "_readest-sync._tcp.local.";
+/// Emitted on the host when a device pairs with it.
+pub const PAIRED_EVENT: &str = "lan-sync://paired";
+const PROTOCOL_VERSION: &str = "1";
+const IDENTITY_ITEM: &str = "lan_sync_identity";
+const PEERS_ITEM: &str = "lan_sync_peers";
+const STORE_DIR: &str = "lan-sync";
+/// Suffix of files still being written; never listed or served.
+const PARTIAL_SUFFIX: &str = ".lan-part";
+const CODE_TTL: Duration = Duration::from_secs(5 * 60);
+const MAX_CODE_ATTEMPTS: u32 = 5;
+const PAIRING_MEMORY_KIB: u32 = 19 * 1024;
+const PAIRING_ITERATIONS: u32 = 2;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const COPY_BUFFER: usize = 256 * 1024;
+/// Domain separators for the two pairing proofs.
+const JOIN: &str = "readest-lan-join";
+const ACCEPT: &str = "readest-lan-accept";
+
+/// Mirrors the engine's `lan` backend settings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanTarget {
+    /// Paired device to sync with; absent syncs with this device's store.
+    pub peer_id: Option<String>,
+}
+
+/// This device's certificate and id, kept in the keychain.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Identity {
+    device_id: String,
+    /// Base64 DER of the self-signed certificate.
+    cert: String,
+    /// Base64 PKCS#8 DER of its private key.
+    key: String,
+}
+
+/// A paired device, as persisted. `token` authenticates both directions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Peer {
+    id: String,
+    name: String,
+    fingerprint: String,
+    token: String,
+    /// Last address the device was seen at, refreshed by discovery.
+    #[serde(default)]
+    host: Option<String>,
+    #[serde(default)]
+    port: Option<u16>,
+}
+
+/// A paired device, as the UI sees it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanPeer {
+    pub id: String,
+    pub name: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// A host found on the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanDevice {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    /// SHA-256 of the device's certificate, lowercase hex.
+    pub fingerprint: String,
+    /// Already paired, with a matching certificate.
+    #[serde(default)]
+    pub paired: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanHostInfo {
+    pub device_id: String,
+    pub name: String,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanSyncStatus {
+    /// Set while this device is hosting.
+    pub host: Option<LanHostInfo>,
+    pub peers: Vec<LanPeer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingCode {
+    pub code: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairRequest {
+    device_id: String,
+    name: String,
+    fingerprint: String,
+    nonce: String,
+    proof: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairResponse {
+    device_id: String,
+    name: String,
+    token: String,
+    proof: String,
+}
+
+#[derive(Deserialize)]
+struct PathQuery {
+    #[serde(default)]
+    path: String,
+}
+
+struct Pairing {
+    key: [u8; 32],
+    expires: Instant,
+    attempts: u32,
+}
+
+struct HostState {
+    app: AppHandle,
+    store: Store,
+    device_id: String,
+    name: String,
+    fingerprint: String,
+    pairing: Mutex<Option<Pairing>>,
+}
+
+struct Host {
+    info: LanHostInfo,
+    handle: axum_server::Handle,
+    mdns: ServiceDaemon,
+    fullname: String,
+    state: Arc<HostState>,
+}
+
+#[derive(Default)]
+pub struct LanSync {
+    host: Mutex<Option<Host>>,
+    /// Paired devices; read from the keychain on first use.
+    peers: Mutex<Option<Vec<Peer>>>,
+}
+
+impl LanSync {
+    fn peers(&self, app: &AppHandle) -> Result<Vec<Peer>, SyncError> {
+        let mut cached = self.peers.lock().unwrap();
+        if cached.is_none() {
+            let peers = match load_secret(app, PEERS_ITEM) {
+                Ok(stored) => serde_json::from_str(&stored)
+                    .map_err(|e| lan_error(format!("stored peers are unreadable: {e}")))?,
+                Err(_) => Vec::new(),
+            };
+            *cached = Some(peers);
+        }
+        Ok(cached.clone().unwrap_or_default())
+    }
+
+    fn save_peers(&self, app: &AppHandle, peers: Vec<Peer>) -> Result<(), SyncError> {
+        let json = serde_json::to_string(&peers).map_err(|e| lan_error(e.to_string()))?;
+        save_secret(app, PEERS_ITEM, &json)?;
+        *self.peers.lock().unwrap() = Some(peers);
+        Ok(())
+    }
+}
+
+impl Peer {
+    fn info(&self) -> LanPeer {
+        LanPeer {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            host: self.host.clone(),
+            port: self.port,
+        }
+    }
+}
+
+fn lan_error(message: impl Into<String>) -> SyncError {
+    SyncError::new(SyncErrorCode::Unknown, message)
+}
+
+fn tls_error(e: rustls::Error) -> SyncError {
+    lan_error(format!("TLS setup failed: {e}"))
+}
+
+fn mdns_error(e: mdns_sd::Error) -> SyncError {
+    SyncError::new(SyncErrorCode::Network, format!("mDNS: {e}"))
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+impl Identity {
+    fn generate() -> Result<Self, SyncError> {
+        let certified = rcgen::generate_simple_self_signed(vec!["readest.local".to_string()])
+            .map_err(|e| lan_error(format!("certificate generation failed: {e}")))?;
+        Ok(Self {
+            device_id: random_hex(16),
+            cert: STANDARD.encode(certified.cert.der()),
+            key: STANDARD.encode(certified.key_pair.serialize_der()),
+        })
+    }
+
+    fn load_or_create(app: &AppHandle) -> Result<Self, SyncError> {
+        if let Ok(stored) = load_secret(app, IDENTITY_ITEM) {
+            return serde_json::from_str(&stored)
+                .map_err(|e| lan_error(format!("stored identity is unreadable: {e}")));
+        }
+        let identity = Self::generate()?;
+        let json = serde_json::to_string(&identity).map_err(|e| lan_error(e.to_string()))?;
+        save_secret(app, IDENTITY_ITEM, &json)?;
+        Ok(identity)
+    }
+
+    fn decode(value: &str) -> Result<Vec<u8>, SyncError> {
+        STANDARD
+            .decode(value)
+            .map_err(|e| lan_error(format!("stored identity is unreadable: {e}")))
+    }
+
+    fn fingerprint(&self) -> Result<String, SyncError> {
+        Ok(fingerprint(&Self::decode(&self.cert)?))
+    }
+
+    fn server_config(&self) -> Result<rustls::ServerConfig, SyncError> {
+        let cert = CertificateDer::from(Self::decode(&self.cert)?);
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(Self::decode(&self.key)?));
+        let mut config = rustls::ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .map_err(tls_error)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+/// Accepts exactly one certificate, by fingerprint. The peers' certificates
+/// are self-signed, so there's no chain or hostname to check; signatures
+/// are still verified so the peer must hold the private key.
+#[derive(Debug)]
+struct PinnedCertificate {
+    fingerprint: String,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if fingerprint(end_entity.as_ref()) == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "certificate does not match the paired device".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// An HTTP client that only talks to the device with this certificate.
+fn pinned_client(fingerprint: &str) -> Result<reqwest::Client, SyncError> {
+    let provider = provider();
+    let verifier = PinnedCertificate {
+        fingerprint: fingerprint.to_ascii_lowercase(),
+        provider: provider.clone(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    // Paired devices are on the local network; never route them through
+    // the user's proxy.
+    Ok(reqwest::Client::builder()
+        .no_proxy()
+        .use_preconfigured_tls(config)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()?)
+}
+
+fn base_url(host: &str, port: u16) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("https://[{ip}]:{port}"),
+        _ => format!("https://{host}:{port}"),
+    }
+}
+
+/// Stretch a pairing code into a MAC key. The host's fingerprint is the
+/// salt, so a proof computed for one certificate is useless for another.
+fn pairing_key(code: &str, host_fingerprint: &str) -> Result<[u8; 32], SyncError> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    let params = Params::new(PAIRING_MEMORY_KIB, PAIRING_ITERATIONS, 1, Some(32))
+        .map_err(|e| lan_error(format!("invalid KDF parameters: {e}")))?;
+    let salt = Sha256::digest(format!("readest-lan-pairing\n{host_fingerprint}"));
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(code.as_bytes(), &salt, &mut key)
+        .map_err(|e| lan_error(format!("key derivation failed: {e}")))?;
+    Ok(key)
+}
+
+fn mac(key: &[u8; 32], parts: &[&str]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part.as_bytes());
+        mac.update(b"\n");
+    }
+    mac
+}
+
+fn proof(key: &[u8; 32], parts: &[&str]) -> String {
+    STANDARD.encode(mac(key, parts).finalize().into_bytes())
+}
+
+fn verify_proof(key: &[u8; 32], parts: &[&str], candidate: &str) -> bool {
+    STANDARD
+        .decode(candidate)
+        .is_ok_and(|bytes| mac(key, parts).verify_slice(&bytes).is_ok())
+}
+
+fn upsert(peers: &mut Vec<Peer>, peer: Peer) {
+    peers.retain(|p| p.id != peer.id);
+    peers.push(peer);
+}
+
+/// Record where paired devices turned up. A device whose certificate no
+/// longer matches is left alone; it has to pair again. Returns whether
+/// anything changed.
+fn remember_addresses(peers: &mut [Peer], found: &[LanDevice]) -> bool {
+    let mut changed = false;
+    for peer in peers.iter_mut() {
+        let Some(device) = found
+            .iter()
+            .find(|d| d.id == peer.id && d.fingerprint == peer.fingerprint)
+        else {
+            continue;
+        };
+        if peer.host.as_deref() != Some(device.host.as_str()) || peer.port != Some(device.port) {
+            peer.host = Some(device.host.clone());
+            peer.port = Some(device.port);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` for a Unix timestamp.
+fn iso8601(unix_secs: u64) -> String {
+    let (year, month, day) = civil_from_days((unix_secs / 86_400) as i64);
+    let secs = unix_secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn modified(metadata: &std::fs::Metadata) -> Option<Duration> {
+    metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn not_found(path: &str) -> SyncError {
+    SyncError::new(SyncErrorCode::NotFound, format!("{path} does not exist"))
+}
+
+/// A sync root on local disk.
+pub(crate) struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    fn open(app: &AppHandle) -> Result<Self, SyncError> {
+        let root = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| lan_error(format!("no app data dir: {e}")))?
+            .join(STORE_DIR);
+        std::fs::create_dir_all(&root).map_err(|e| SyncError::io("create store", e))?;
+        Ok(Self { root })
+    }
+
+    /// Map a logical path into the store, refusing anything that could
+    /// step outside it.
+    fn resolve(&self, path: &str) -> Result<PathBuf, SyncError> {
+        let mut resolved = self.root.clone();
+        for segment in segments(path) {
+            if segment == "."
+                || segment == ".."
+                || segment.contains(['\\', ':'])
+                || segment.ends_with(PARTIAL_SUFFIX)
+            {
+                return Err(lan_error(format!("invalid path: {path}")));
+            }
+            resolved.push(segment);
+        }
+        Ok(resolved)
+    }
+
+    /// Like [`Self::resolve`], for operations that need a file path.
+    fn resolve_file(&self, path: &str) -> Result<PathBuf, SyncError> {
+        if normalize(path).is_empty() {
+            return Err(lan_error(format!("not a file path: {path}")));
+        }
+        self.resolve(path)
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        let entries = match std::fs::read_dir(self.resolve(path)?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(SyncError::io("list", e)),
+        };
+        let base = normalize(path);
+        let mut listing = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| SyncError::io("list", e))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(PARTIAL_SUFFIX) {
+                continue;
+            }
+            let metadata = entry.metadata().map_err(|e| SyncError::io("stat", e))?;
+            listing.push(RemoteEntry {
+                path: format!("{base}/{name}"),
+                is_directory: metadata.is_dir(),
+                size: metadata.is_file().then(|| metadata.len()),
+                last_modified: modified(&metadata).map(|d| iso8601(d.as_secs())),
+                name,
+            });
+        }
+        listing.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(listing)
+    }
+
+    fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        match std::fs::metadata(self.resolve(path)?) {
+            Ok(metadata) if metadata.is_file() => Ok(Some(RemoteHead {
+                size: Some(metadata.len()),
+                etag: Some(format!(
+                    "{:x}-{:x}",
+                    metadata.len(),
+                    modified(&metadata).map_or(0, |d| d.as_millis())
+                )),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SyncError::io("stat", e)),
+        }
+    }
+
+    fn delete(&self, path: &str) -> Result<(), SyncError> {
+        let target = self.resolve_file(path)?;
+        let result = match std::fs::symlink_metadata(&target) {
+            Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(&target),
+            Ok(_) => std::fs::remove_file(&target),
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SyncError::io("delete", e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Copy `from` to `to` through a partial file, reporting progress. A
+/// missing source is `NOT_FOUND`.
+async fn copy_file(
+    from: &Path,
+    to: &Path,
+    on_progress: Channel<ProgressPayload>,
+) -> Result<(), SyncError> {
+    let mut input = match tokio::fs::File::open(from).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(not_found(&from.to_string_lossy()))
+        }
+        Err(e) => return Err(SyncError::io("open", e)),
+    };
+    let total = input
+        .metadata()
+        .await
+        .map_err(|e| SyncError::io("stat", e))?
+        .len();
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| SyncError::io("create dir", e))?;
+    }
+    let partial = partial_path(to);
+    let result = async {
+        let mut output = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stats = TransferStats::default();
+        let mut buffer = vec![0u8; COPY_BUFFER];
+        loop {
+            let read = input
+                .read(&mut buffer)
+                .await
+                .map_err(|e| SyncError::io("read", e))?;
+            if read == 0 {
+                break;
+            }
+            output
+                .write_all(&buffer[..read])
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+            stats.record_chunk_transfer(read);
+            let _ = on_progress.send(progress(&stats, total));
+        }
+        output
+            .flush()
+            .await
+            .map_err(|e| SyncError::io("write", e))?;
+        drop(output);
+        tokio::fs::rename(&partial, to)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    result
+}
+
+/// A paired device's store, over HTTPS.
+pub(crate) struct PeerClient {
+    http: reqwest::Client,
+    base: String,
+    token: String,
+}
+
+impl PeerClient {
+    fn new(peer: &Peer) -> Result<Self, SyncError> {
+        let (Some(host), Some(port)) = (&peer.host, peer.port) else {
+            return Err(SyncError::new(
+                SyncErrorCode::Network,
+                format!(
+                    "{} hasn't been seen on this network; search for devices again",
+                    peer.name
+                ),
+            ));
+        };
+        Ok(Self {
+            http: pinned_client(&peer.fingerprint)?,
+            base: base_url(host, port),
+            token: peer.token.clone(),
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, route: &str, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}{route}", self.base))
+            .bearer_auth(&self.token)
+            .query(&[("path", path)])
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        let response =
+            send_with_retry(|| Ok(self.request(reqwest::Method::GET, "/v1/list", path))).await?;
+        if !response.status().is_success() {
+            return Err(error_for(response, "list").await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        let response =
+            send_with_retry(|| Ok(self.request(reqwest::Method::GET, "/v1/head", path))).await?;
+        if !response.status().is_success() {
+            return Err(error_for(response, "head").await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let size = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| SyncError::io("stat", e))?
+            .len();
+        let response = send_with_retry(|| {
+            let file = std::fs::File::open(local_path).map_err(|e| SyncError::io("open", e))?;
+            let body = upload.file_body(on_progress.clone(), tokio::fs::File::from_std(file), size);
+            Ok(self
+                .request(reqwest::Method::PUT, "/v1/file", remote_path)
+                .header(reqwest::header::CONTENT_LENGTH, size)
+                .body(body))
+        })
+        .await?;
+        if !response.status().is_success() {
+            return Err(error_for(response, "upload").await);
+        }
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let response =
+            send_with_retry(|| Ok(self.request(reqwest::Method::GET, "/v1/file", remote_path)))
+                .await?;
+        if !response.status().is_success() {
+            return Err(error_for(response, "download").await);
+        }
+        let total = response.content_length().unwrap_or(0);
+        let partial = format!("{local_path}.part");
+        let mut out = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stats = TransferStats::default();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk)
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+            stats.record_chunk_transfer(chunk.len());
+            let _ = on_progress.send(progress(&stats, total));
+        }
+        out.flush().await.map_err(|e| SyncError::io("write", e))?;
+        drop(out);
+        tokio::fs::rename(&partial, local_path)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        let response =
+            send_with_retry(|| Ok(self.request(reqwest::Method::DELETE, "/v1/file", path))).await?;
+        if !response.status().is_success() {
+            return Err(error_for(response, "delete").await);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) enum LanBackend {
+    Local(Store),
+    Peer(PeerClient),
+}
+
+impl LanBackend {
+    pub(crate) fn new(app: &AppHandle, target: LanTarget) -> Result<Self, SyncError> {
+        let Some(peer_id) = target.peer_id else {
+            return Ok(Self::Local(Store::open(app)?));
+        };
+        let peers = app.state::<LanSync>().peers(app)?;
+        let peer = peers.iter().find(|p| p.id == peer_id).ok_or_else(|| {
+            SyncError::new(
+                SyncErrorCode::AuthFailed,
+                format!("not paired with device {peer_id}"),
+            )
+        })?;
+        Ok(Self::Peer(PeerClient::new(peer)?))
+    }
+
+    pub(crate) async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        match self {
+            Self::Local(store) => store.list(path),
+            Self::Peer(client) => client.list(path).await,
+        }
+    }
+
+    pub(crate) async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        match self {
+            Self::Local(store) => store.head(path),
+            Self::Peer(client) => client.head(path).await,
+        }
+    }
+
+    pub(crate) async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        match self {
+            Self::Local(store) => {
+                let target = store.resolve_file(remote_path)?;
+                copy_file(Path::new(local_path), &target, on_progress).await
+            }
+            Self::Peer(client) => {
+                client
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+        }
+    }
+
+    pub(crate) async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        match self {
+            Self::Local(store) => {
+                let source = store.resolve_file(remote_path)?;
+                copy_file(&source, Path::new(local_path), on_progress)
+                    .await
+                    .map_err(|e| match e.code {
+                        SyncErrorCode::NotFound => not_found(remote_path),
+                        _ => e,
+                    })
+            }
+            Self::Peer(client) => client.download(remote_path, local_path, on_progress).await,
+        }
+    }
+
+    pub(crate) async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        match self {
+            Self::Local(store) => store.delete(path),
+            Self::Peer(client) => client.delete(path).await,
+        }
+    }
+}
+
+/// A [`SyncError`] on its way back to the peer, as the status the client's
+/// [`SyncError::from_status`] maps back to the same code.
+struct Rejection(SyncError);
+
+impl From<SyncError> for Rejection {
+    fn from(error: SyncError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let status = match self.0.code {
+            SyncErrorCode::AuthFailed => StatusCode::FORBIDDEN,
+            SyncErrorCode::NotFound => StatusCode::NOT_FOUND,
+            SyncErrorCode::Conflict => StatusCode::CONFLICT,
+            SyncErrorCode::Network => StatusCode::SERVICE_UNAVAILABLE,
+            SyncErrorCode::Unknown => StatusCode::BAD_REQUEST,
+        };
+        (status, self.0.message).into_response()
+    }
+}
+
+async fn require_token(
+    AxumState(state): AxumState<Arc<HostState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let authorized = presented.is_some_and(|token| {
+        let peers = state.app.state::<LanSync>().peers(&state.app);
+        peers
+            .unwrap_or_default()
+            .iter()
+            .any(|peer| token_matches(token, &peer.token))
+    });
+    if authorized {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, "device is not paired").into_response()
+    }
+}
+
+async fn list(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<Vec<RemoteEntry>>, Rejection> {
+    Ok(Json(state.store.list(&query.path)?))
+}
+
+async fn head(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<Option<RemoteHead>>, Rejection> {
+    Ok(Json(state.store.head(&query.path)?))
+}
+
+async fn get_file(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Query(query): Query<PathQuery>,
+) -> Result<Response, Rejection> {
+    let path = state.store.resolve_file(&query.path)?;
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(not_found(&query.path).into())
+        }
+        Err(e) => return Err(SyncError::io("open", e).into()),
+    };
+    let metadata = file
+        .metadata()
+        .await
+        .map_err(|e| SyncError::io("stat", e))?;
+    if !metadata.is_file() {
+        return Err(not_found(&query.path).into());
+    }
+    let stream = FramedRead::new(file, BytesCodec::new()).map_ok(|bytes| bytes.freeze());
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_LENGTH, metadata.len().to_string()),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+async fn put_file(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Query(query): Query<PathQuery>,
+    body: Body,
+) -> Result<StatusCode, Rejection> {
+    let target = state.store.resolve_file(&query.path)?;
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| SyncError::io("create dir", e))?;
+    }
+    let partial = partial_path(&target);
+    let result = async {
+        let mut out = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| {
+                SyncError::new(SyncErrorCode::Network, format!("upload interrupted: {e}"))
+            })?;
+            out.write_all(&chunk)
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+        }
+        out.flush().await.map_err(|e| SyncError::io("write", e))?;
+        drop(out);
+        tokio::fs::rename(&partial, &target)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    result?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_file(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Query(query): Query<PathQuery>,
+) -> Result<StatusCode, Rejection> {
+    state.store.delete(&query.path)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pair(
+    AxumState(state): AxumState<Arc<HostState>>,
+    Json(request): Json<PairRequest>,
+) -> Result<Json<PairResponse>, Rejection> {
+    let key = {
+        let mut pairing = state.pairing.lock().unwrap();
+        if pairing
+            .as_ref()
+            .map_or(true, |active| active.expires <= Instant::now())
+        {
+            *pairing = None;
+            return Err(SyncError::new(
+                SyncErrorCode::AuthFailed,
+                "no pairing code is active on this device",
+            )
+            .into());
+        }
+        let active = pairing.as_mut().expect("checked above");
+        let parts = [
+            JOIN,
+            &state.fingerprint,
+            &request.device_id,
+            &request.fingerprint,
+            &request.nonce,
+        ];
+        if !verify_proof(&active.key, &parts, &request.proof) {
+            active.attempts += 1;
+            if active.attempts >= MAX_CODE_ATTEMPTS {
+                *pairing = None;
+            }
+            return Err(SyncError::new(SyncErrorCode::AuthFailed, "wrong pairing code").into());
+        }
+        let key = active.key;
+        *pairing = None;
+        key
+    };
+
+    let token = random_hex(32);
+    let peer = Peer {
+        id: request.device_id.clone(),
+        name: request.name,
+        fingerprint: request.fingerprint.to_ascii_lowercase(),
+        token: token.clone(),
+        host: None,
+        port: None,
+    };
+    let info = peer.info();
+    let lan = state.app.state::<LanSync>();
+    let mut peers = lan.peers(&state.app)?;
+    upsert(&mut peers, peer);
+    lan.save_peers(&state.app, peers)?;
+    if let Err(e) = state.app.emit(PAIRED_EVENT, info) {
+        log::warn!("Failed to emit pairing event: {e}");
+    }
+    let parts = [
+        ACCEPT,
+        &state.fingerprint,
+        &request.device_id,
+        &token,
+        &request.nonce,
+    ];
+    Ok(Json(PairResponse {
+        device_id: state.device_id.clone(),
+        name: state.name.clone(),
+        proof: proof(&key, &parts),
+        token,
+    }))
+}
+
+/// Announce the host; returns the daemon and the registered full name.
+fn advertise(
+    device_id: &str,
+    name: &str,
+    fingerprint: &str,
+    port: u16,
+) -> Result<(ServiceDaemon, String), SyncError> {
+    let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+    let instance = format!("readest-{device_id}");
+    // TXT values are capped at 255 bytes; device names are much shorter.
+    let name: String = name.chars().take(63).collect();
+    let properties = [
+        ("v", PROTOCOL_VERSION),
+        ("id", device_id),
+        ("name", name.as_str()),
+        ("fp", fingerprint),
+    ];
+    let info = ServiceInfo::new(
+        SERVICE_TYPE,
+        &instance,
+        &format!("{instance}.local."),
+        "",
+        port,
+        &properties[..],
+    )
+    .map_err(mdns_error)?
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).map_err(mdns_error)?;
+    Ok((daemon, fullname))
+}
+
+fn device_from(info: &ServiceInfo) -> Option<LanDevice> {
+    if info.get_property_val_str("v") != Some(PROTOCOL_VERSION) {
+        return None;
+    }
+    let addresses = info.get_addresses();
+    let host = addresses
+        .iter()
+        .find(|a| a.is_ipv4())
+        .or_else(|| addresses.iter().next())?;
+    Some(LanDevice {
+        id: info.get_property_val_str("id")?.to_string(),
+        name: info
+            .get_property_val_str("name")
+            .unwrap_or_default()
+            .to_string(),
+        host: host.to_string(),
+        port: info.get_port(),
+        fingerprint: info.get_property_val_str("fp")?.to_ascii_lowercase(),
+        paired: false,
+    })
+}
+
+fn browse(timeout: Duration) -> Vec<LanDevice> {
+    let Ok(daemon) = ServiceDaemon::new() else {
+        return Vec::new();
+    };
+    let Ok(receiver) = daemon.browse(SERVICE_TYPE) else {
+        return Vec::new();
+    };
+    let deadline = Instant::now() + timeout;
+    let mut found: Vec<LanDevice> = Vec::new();
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                if let Some(device) = device_from(&info) {
+                    if !found.iter().any(|f| f.id == device.id) {
+                        found.push(device);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.shutdown();
+    found
+}
+
+/// Start hosting: serve this device's store to paired devices and
+/// advertise it. `name` is what other devices show in their list.
+#[tauri::command]
+pub async fn lan_sync_start(
+    app: AppHandle,
+    lan: State<'_, LanSync>,
+    name: String,
+) -> Result<LanHostInfo, SyncError> {
+    if let Some(host) = lan.host.lock().unwrap().as_ref() {
+        return Ok(host.info.clone());
+    }
+    let identity = Identity::load_or_create(&app)?;
+    let fingerprint = identity.fingerprint()?;
+    let tls =
+        axum_server::tls_rustls::RustlsConfig::from_config(Arc::new(identity.server_config()?));
+    let state = Arc::new(HostState {
+        app: app.clone(),
+        store: Store::open(&app)?,
+        device_id: identity.device_id.clone(),
+        name: name.clone(),
+        fingerprint: fingerprint.clone(),
+        pairing: Mutex::new(None),
+    });
+    let router = Router::new()
+        .route("/v1/list", get(list))
+        .route("/v1/head", get(head))
+        .route("/v1/file", get(get_file).put(put_file).delete(delete_file))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .route("/v1/pair", post(pair))
+        .with_state(state.clone());
+
+    let listener = std::net::TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| SyncError::io("bind", e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| SyncError::io("bind", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| SyncError::io("bind", e))?
+        .port();
+    let (mdns, fullname) = advertise(&identity.device_id, &name, &fingerprint, port)?;
+    let handle = axum_server::Handle::new();
+    let server = axum_server::from_tcp_rustls(listener, tls)
+        .handle(handle.clone())
+        .serve(router.into_make_service());
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = server.await {
+            log::error!("LAN sync host stopped: {e}");
+        }
+    });
+
+    let info = LanHostInfo {
+        device_id: identity.device_id,
+        name,
+        port,
+        fingerprint,
+    };
+    log::info!("LAN sync host listening on port {port}");
+    *lan.host.lock().unwrap() = Some(Host {
+        info: info.clone(),
+        handle,
+        mdns,
+        fullname,
+        state,
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn lan_sync_stop(lan: State<'_, LanSync>) {
+    if let Some(host) = lan.host.lock().unwrap().take() {
+        let _ = host.mdns.unregister(&host.fullname);
+        let _ = host.mdns.shutdown();
+        host.handle.graceful_shutdown(Some(Duration::from_secs(3)));
+    }
+}
+
+#[tauri::command]
+pub fn lan_sync_status(
+    app: AppHandle,
+    lan: State<'_, LanSync>,
+) -> Result<LanSyncStatus, SyncError> {
+    let host = lan.host.lock().unwrap().as_ref().map(|h| h.info.clone());
+    let peers = lan.peers(&app)?.iter().map(Peer::info).collect();
+    Ok(LanSyncStatus { host, peers })
+}
+
+/// Show a fresh code for another device to pair with; any earlier code
+/// stops working. Requires hosting.
+#[tauri::command]
+pub async fn lan_sync_pairing_code(lan: State<'_, LanSync>) -> Result<PairingCode, SyncError> {
+    let state = lan
+        .host
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|h| h.state.clone())
+        .ok_or_else(|| lan_error("start hosting before pairing"))?;
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+    let (secret, fingerprint) = (code.clone(), state.fingerprint.clone());
+    let key = blocking(move || pairing_key(&secret, &fingerprint)).await?;
+    *state.pairing.lock().unwrap() = Some(Pairing {
+        key,
+        expires: Instant::now() + CODE_TTL,
+        attempts: 0,
+    });
+    Ok(PairingCode {
+        code,
+        expires_in_secs: CODE_TTL.as_secs(),
+    })
+}
+
+/// Find hosting devices on the network and refresh the addresses of
+/// paired ones.
+#[tauri::command]
+pub async fn lan_sync_discover(
+    app: AppHandle,
+    lan: State<'_, LanSync>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<LanDevice>, SyncError> {
+    let own_id = Identity::load_or_create(&app)?.device_id;
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    let mut found = blocking(move || Ok(browse(timeout))).await?;
+    found.retain(|device| device.id != own_id);
+    let mut peers = lan.peers(&app)?;
+    for device in &mut found {
+        device.paired = peers
+            .iter()
+            .any(|p| p.id == device.id && p.fingerprint == device.fingerprint);
+    }
+    if remember_addresses(&mut peers, &found) {
+        lan.save_peers(&app, peers)?;
+    }
+    Ok(found)
+}
+
+/// Pair with a hosting `device` using the code it shows. `name` is how
+/// this device appears on the other one.
+#[tauri::command]
+pub async fn lan_sync_pair(
+    app: AppHandle,
+    lan: State<'_, LanSync>,
+    device: LanDevice,
+    code: String,
+    name: String,
+) -> Result<LanPeer, SyncError> {
+    let identity = Identity::load_or_create(&app)?;
+    let own_fingerprint = identity.fingerprint()?;
+    let host_fingerprint = device.fingerprint.to_ascii_lowercase();
+    let salt = host_fingerprint.clone();
+    let key = blocking(move || pairing_key(&code, &salt)).await?;
+    let nonce = random_hex(16);
+    let request = PairRequest {
+        proof: proof(
+            &key,
+            &[
+                JOIN,
+                &host_fingerprint,
+                &identity.device_id,
+                &own_fingerprint,
+                &nonce,
+            ],
+        ),
+        device_id: identity.device_id.clone(),
+        name,
+        fingerprint: own_fingerprint,
+        nonce: nonce.clone(),
+    };
+    let response = pinned_client(&host_fingerprint)?
+        .post(format!("{}/v1/pair", base_url(&device.host, device.port)))
+        .json(&request)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(error_for(response, "pairing").await);
+    }
+    let accepted: PairResponse = response.json().await?;
+    let parts = [
+        ACCEPT,
+        &host_fingerprint,
+        &identity.device_id,
+        &accepted.token,
+        &nonce,
+    ];
+    if accepted.device_id != device.id || !verify_proof(&key, &parts, &accepted.proof) {
+        return Err(SyncError::new(
+            SyncErrorCode::AuthFailed,
+            "the other device could not prove it knows the pairing code",
+        ));
+    }
+    let peer = Peer {
+        id: device.id,
+        name: accepted.name,
+        fingerprint: host_fingerprint,
+        token: accepted.token,
+        host: Some(device.host),
+        port: Some(device.port),
+    };
+    let info = peer.info();
+    let mut peers = lan.peers(&app)?;
+    upsert(&mut peers, peer);
+    lan.save_peers(&app, peers)?;
+    Ok(info)
+}
+
+/// Forget a paired device. The token is shared, so this also locks the
+/// other device out of this one's store.
+#[tauri::command]
+pub fn lan_sync_unpair(
+    app: AppHandle,
+    lan: State<'_, LanSync>,
+    peer_id: String,
+) -> Result<(), SyncError> {
+    let mut peers = lan.peers(&app)?;
+    peers.retain(|p| p.id != peer_id);
+    lan.save_peers(&app, peers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> Store {
+        let root = std::env::temp_dir().join(format!("readest-lan-{name}"));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        Store { root }
+    }
+
+    fn peer(id: &str, fingerprint: &str) -> Peer {
+        Peer {
+            id: id.into(),
+            name: id.into(),
+            fingerprint: fingerprint.into(),
+            token: "t".into(),
+            host: None,
+            port: None,
+        }
+    }
+
+    #[test]
+    fn pairing_proof_binds_code_and_certificates() {
+        let key = pairing_key("123 456", "aa").unwrap();
+        let parts = [JOIN, "aa", "phone", "bb", "nonce"];
+        let sent = proof(&key, &parts);
+        assert!(verify_proof(
+            &pairing_key("123456", "aa").unwrap(),
+            &parts,
+            &sent
+        ));
+        assert!(!verify_proof(
+            &pairing_key("123457", "aa").unwrap(),
+            &parts,
+            &sent
+        ));
+        // A proof made against a substituted certificate doesn't verify.
+        assert!(!verify_proof(
+            &pairing_key("123456", "cc").unwrap(),
+            &parts,
+            &sent
+        ));
+        assert!(!verify_proof(
+            &key,
+            &[JOIN, "aa", "phone", "cc", "nonce"],
+            &sent
+        ));
+        assert!(!verify_proof(&key, &parts, "not base64!"));
+    }
+
+    #[test]
+    fn store_paths_cannot_escape_the_root() {
+        let store = temp_store("resolve");
+        assert_eq!(
+            store.resolve("/Readest//books/a.epub").unwrap(),
+            store.root.join("Readest").join("books").join("a.epub")
+        );
+        assert!(store.resolve("/Readest/../../etc").is_err());
+        assert!(store.resolve("/a\\..\\b").is_err());
+        assert!(store.resolve("/C:/Windows").is_err());
+        assert!(store.resolve("/a.epub.lan-part").is_err());
+        assert!(store.resolve_file("/").is_err());
+    }
+
+    #[test]
+    fn store_lists_heads_and_deletes() {
+        let store = temp_store("ops");
+        let books = store.resolve("/Readest/books").unwrap();
+        std::fs::create_dir_all(books.join("abc")).unwrap();
+        std::fs::write(books.join("abc").join("book.epub"), b"epub").unwrap();
+        std::fs::write(books.join("abc").join("book.epub.lan-part"), b"ep").unwrap();
+
+        let listing = store.list("/Readest/books/abc").unwrap();
+        assert_eq!(listing.len(), 1);
+        assert_eq!(listing[0].path, "/Readest/books/abc/book.epub");
+        assert_eq!(listing[0].size, Some(4));
+        assert!(store.list("/Readest/missing").unwrap().is_empty());
+
+        let head = store.head("/Readest/books/abc/book.epub").unwrap().unwrap();
+        assert_eq!(head.size, Some(4));
+        assert!(store.head("/Readest/books/abc").unwrap().is_none());
+
+        store.delete("/Readest/books/abc").unwrap();
+        assert!(store
+            .head("/Readest/books/abc/book.epub")
+            .unwrap()
+            .is_none());
+        store.delete("/Readest/books/abc").unwrap();
+    }
+
+    #[test]
+    fn discovery_updates_only_matching_peers() {
+        let mut peers = vec![peer("a", "fa"), peer("b", "fb")];
+        let device = |id: &str, fingerprint: &str| LanDevice {
+            id: id.into(),
+            name: String::new(),
+            host: "192.168.1.7".into(),
+            port: 4000,
+            fingerprint: fingerprint.into(),
+            paired: false,
+        };
+        let found = [device("a", "fa"), device("b", "other")];
+        assert!(remember_addresses(&mut peers, &found));
+        assert_eq!(peers[0].host.as_deref(), Some("192.168.1.7"));
+        assert_eq!(peers[0].port, Some(4000));
+        assert_eq!(peers[1].host, None);
+        assert!(!remember_addresses(&mut peers, &found));
+    }
+
+    #[test]
+    fn upsert_replaces_by_id() {
+        let mut peers = vec![peer("a", "old")];
+        upsert(&mut peers, peer("a", "new"));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].fingerprint, "new");
+    }
+
+    #[test]
+    fn urls_and_timestamps() {
+        assert_eq!(base_url("192.168.1.2", 80), "https://192.168.1.2:80");
+        assert_eq!(base_url("fe80::1", 80), "https://[fe80::1]:80");
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,380 @@
+//! Three-way merge of annotation and bookmark sets.
+//!
+//! `mergeNotes` in `services/sync/file/merge.ts` is a two-way LWW: when two
+//! devices edit the same highlight offline, the later `updatedAt` wins the
+//! whole note and the other side's change is lost, even if one device only
+//! recolored it and the other only added a comment. Given the last synced
+//! state as a common ancestor, the merge here works per field instead:
+//!
+//!   - a field changed on one side only takes that side's value;
+//!   - a field changed to the same value on both sides is not a conflict;
+//!   - a field changed differently on both sides, or a note edited on one
+//!     side and deleted on the other, is reported as a [`MergeConflict`].
+//!
+//! Notes are matched by their stable `id`. Deletion is a `deletedAt`
+//! tombstone, as everywhere else in sync; a note present in the ancestor
+//! but missing from one side counts as deleted there. Conflicting notes
+//! still appear in `merged`, holding the newer side's values, so the result
+//! is usable as is while the UI asks the user which version to keep.
+//!
+//! Notes are handled as JSON objects so fields this module doesn't know
+//! about (future `BookNote` additions) merge the same way and survive.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+
+const UPDATED_AT: &str = "updatedAt";
+const CREATED_AT: &str = "createdAt";
+const DELETED_AT: &str = "deletedAt";
+
+/// A `BookNote` as the frontend stores it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    /// Both sides changed the same fields to different values.
+    BothEdited,
+    /// One side deleted the note while the other edited it.
+    EditedAndDeleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub id: String,
+    pub kind: ConflictKind,
+    /// The conflicting fields; empty for `editedAndDeleted`.
+    pub fields: Vec<String>,
+    pub base: Option<Note>,
+    pub local: Note,
+    pub remote: Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub merged: Vec<Note>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+fn timestamp(note: &Note, key: &str) -> i64 {
+    note.fields.get(key).and_then(Value::as_i64).unwrap_or(0)
+}
+
+fn is_deleted(note: &Note) -> bool {
+    timestamp(note, DELETED_AT) > 0
+}
+
+/// A field's value, with `null` and absent treated alike.
+fn field<'a>(note: &'a Note, key: &str) -> Option<&'a Value> {
+    note.fields.get(key).filter(|v| !v.is_null())
+}
+
+/// Whether two notes differ in anything but their timestamps.
+fn content_differs(a: &Note, b: &Note) -> bool {
+    let keys: BTreeSet<&String> = a.fields.keys().chain(b.fields.keys()).collect();
+    keys.into_iter()
+        .filter(|k| ![UPDATED_AT, CREATED_AT, DELETED_AT].contains(&k.as_str()))
+        .any(|k| field(a, k) != field(b, k))
+}
+
+/// The side whose values stand in while a conflict is open: the later
+/// write, with ties going to this device.
+fn newer<'a>(local: &'a Note, remote: &'a Note) -> &'a Note {
+    let stamp = |n: &Note| timestamp(n, UPDATED_AT).max(timestamp(n, DELETED_AT));
+    if stamp(remote) > stamp(local) {
+        remote
+    } else {
+        local
+    }
+}
+
+fn new_conflict(
+    kind: ConflictKind,
+    fields: Vec<String>,
+    base: Option<&Note>,
+    local: &Note,
+    remote: &Note,
+) -> MergeConflict {
+    MergeConflict {
+        id: local.id.clone(),
+        kind,
+        fields,
+        base: base.cloned(),
+        local: local.clone(),
+        remote: remote.clone(),
+    }
+}
+
+/// Merge one note present on both sides.
+fn merge_note(base: Option<&Note>, local: &Note, remote: &Note) -> (Note, Option<MergeConflict>) {
+    match (is_deleted(local), is_deleted(remote)) {
+        (true, true) => {
+            let later = if timestamp(remote, DELETED_AT) > timestamp(local, DELETED_AT) {
+                remote
+            } else {
+                local
+            };
+            return (later.clone(), None);
+        }
+        (true, false) | (false, true) => {
+            let (deleted, kept) = if is_deleted(local) {
+                (local, remote)
+            } else {
+                (remote, local)
+            };
+            // Without an ancestor, compare against the deleted copy.
+            let edited = content_differs(base.unwrap_or(deleted), kept);
+            if !edited {
+                return (deleted.clone(), None);
+            }
+            let conflict = new_conflict(
+                ConflictKind::EditedAndDeleted,
+                Vec::new(),
+                base,
+                local,
+                remote,
+            );
+            return (newer(local, remote).clone(), Some(conflict));
+        }
+        (false, false) => {}
+    }
+
+    let preferred = newer(local, remote);
+    let mut merged = Note {
+        id: local.id.clone(),
+        fields: Map::new(),
+    };
+    let mut conflicting = Vec::new();
+    let keys: BTreeSet<&String> = local.fields.keys().chain(remote.fields.keys()).collect();
+    for key in keys {
+        if [UPDATED_AT, CREATED_AT].contains(&key.as_str()) {
+            continue;
+        }
+        let (l, r) = (field(local, key), field(remote, key));
+        let b = base.and_then(|b| field(b, key));
+        let value = if l == r || r == b {
+            l
+        } else if l == b {
+            r
+        } else {
+            conflicting.push(key.clone());
+            field(preferred, key)
+        };
+        if let Some(value) = value {
+            merged.fields.insert(key.clone(), value.clone());
+        }
+    }
+    let updated = timestamp(local, UPDATED_AT).max(timestamp(remote, UPDATED_AT));
+    merged.fields.insert(UPDATED_AT.to_string(), updated.into());
+    let created = [local, remote]
+        .iter()
+        .map(|n| timestamp(n, CREATED_AT))
+        .filter(|&t| t > 0)
+        .min();
+    if let Some(created) = created {
+        merged.fields.insert(CREATED_AT.to_string(), created.into());
+    }
+    let conflict = (!conflicting.is_empty())
+        .then(|| new_conflict(ConflictKind::BothEdited, conflicting, base, local, remote));
+    (merged, conflict)
+}
+
+/// A note one side still has and the other dropped without a tombstone:
+/// a delete, unless the side that kept it changed it meanwhile.
+fn merge_dropped(ancestor: &Note, kept: &Note, kept_locally: bool, result: &mut MergeResult) {
+    if is_deleted(kept) || !content_differs(ancestor, kept) {
+        return;
+    }
+    let mut deleted = ancestor.clone();
+    let deleted_at = timestamp(ancestor, UPDATED_AT).max(1);
+    deleted
+        .fields
+        .insert(DELETED_AT.to_string(), deleted_at.into());
+    let (local, remote) = if kept_locally {
+        (kept, &deleted)
+    } else {
+        (&deleted, kept)
+    };
+    result.conflicts.push(new_conflict(
+        ConflictKind::EditedAndDeleted,
+        Vec::new(),
+        Some(ancestor),
+        local,
+        remote,
+    ));
+    result.merged.push(kept.clone());
+}
+
+/// Three-way merge of `local` and `remote` against their last common state.
+/// Output keeps local order, then notes only the remote has, in its order.
+pub fn merge_notes(base: &[Note], local: &[Note], remote: &[Note]) -> MergeResult {
+    let base: HashMap<&str, &Note> = base.iter().map(|n| (n.id.as_str(), n)).collect();
+    let remote_by_id: HashMap<&str, &Note> = remote.iter().map(|n| (n.id.as_str(), n)).collect();
+    let local_ids: BTreeSet<&str> = local.iter().map(|n| n.id.as_str()).collect();
+    let mut result = MergeResult {
+        merged: Vec::new(),
+        conflicts: Vec::new(),
+    };
+
+    for note in local {
+        let ancestor = base.get(note.id.as_str()).copied();
+        match (remote_by_id.get(note.id.as_str()), ancestor) {
+            (Some(theirs), _) => {
+                let (merged, conflict) = merge_note(ancestor, note, theirs);
+                result.merged.push(merged);
+                result.conflicts.extend(conflict);
+            }
+            // Added here since the last sync.
+            (None, None) => result.merged.push(note.clone()),
+            (None, Some(ancestor)) => merge_dropped(ancestor, note, true, &mut result),
+        }
+    }
+
+    for note in remote {
+        if local_ids.contains(note.id.as_str()) {
+            continue;
+        }
+        match base.get(note.id.as_str()) {
+            None => result.merged.push(note.clone()),
+            Some(ancestor) => merge_dropped(ancestor, note, false, &mut result),
+        }
+    }
+    result
+}
+
+/// Three-way merge a book's notes. `base` is the set both devices last
+/// agreed on (empty on first sync, which degrades to a two-way merge that
+/// reports every differing field).
+#[tauri::command]
+pub fn sync_merge_annotations(base: Vec<Note>, local: Vec<Note>, remote: Vec<Note>) -> MergeResult {
+    merge_notes(&base, &local, &remote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn note(value: Value) -> Note {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn highlight() -> Note {
+        note(json!({
+            "id": "n1", "type": "annotation", "cfi": "epubcfi(/6/4!/4/2)",
+            "color": "yellow", "note": "", "createdAt": 100, "updatedAt": 100
+        }))
+    }
+
+    fn with(mut n: Note, key: &str, value: Value) -> Note {
+        n.fields.insert(key.to_string(), value);
+        n
+    }
+
+    #[test]
+    fn merges_edits_to_different_fields() {
+        let base = highlight();
+        let local = with(
+            with(base.clone(), "color", json!("red")),
+            "updatedAt",
+            json!(200),
+        );
+        let remote = with(
+            with(base.clone(), "note", json!("why")),
+            "updatedAt",
+            json!(300),
+        );
+        let result = merge_notes(&[base], &[local], &[remote]);
+        assert!(result.conflicts.is_empty());
+        let merged = &result.merged[0];
+        assert_eq!(merged.fields["color"], json!("red"));
+        assert_eq!(merged.fields["note"], json!("why"));
+        assert_eq!(merged.fields["updatedAt"], json!(300));
+        assert_eq!(merged.fields["createdAt"], json!(100));
+    }
+
+    #[test]
+    fn reports_conflicting_edits_and_keeps_the_newer_value() {
+        let base = highlight();
+        let local = with(
+            with(base.clone(), "note", json!("mine")),
+            "updatedAt",
+            json!(300),
+        );
+        let remote = with(
+            with(base.clone(), "note", json!("theirs")),
+            "updatedAt",
+            json!(200),
+        );
+        let result = merge_notes(&[base], &[local], &[remote]);
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::BothEdited);
+        assert_eq!(result.conflicts[0].fields, vec!["note".to_string()]);
+        assert_eq!(result.merged[0].fields["note"], json!("mine"));
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_not_a_conflict() {
+        let base = highlight();
+        let local = with(base.clone(), "color", json!("blue"));
+        let remote = with(base.clone(), "color", json!("blue"));
+        assert!(merge_notes(&[base], &[local], &[remote])
+            .conflicts
+            .is_empty());
+    }
+
+    #[test]
+    fn delete_wins_over_an_untouched_note_but_not_an_edit() {
+        let base = highlight();
+        let deleted = with(base.clone(), "deletedAt", json!(500));
+        let result = merge_notes(&[base.clone()], &[deleted.clone()], &[base.clone()]);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.merged, vec![deleted.clone()]);
+
+        let edited = with(
+            with(base.clone(), "note", json!("keep")),
+            "updatedAt",
+            json!(600),
+        );
+        let result = merge_notes(&[base], &[deleted], &[edited.clone()]);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::EditedAndDeleted);
+        assert_eq!(result.merged, vec![edited]);
+    }
+
+    #[test]
+    fn additions_from_both_sides_are_kept_in_order() {
+        let base = highlight();
+        let mine = note(json!({ "id": "a", "type": "bookmark", "cfi": "x", "note": "" }));
+        let theirs = note(json!({ "id": "b", "type": "bookmark", "cfi": "y", "note": "" }));
+        let result = merge_notes(&[base.clone()], &[base.clone(), mine], &[theirs, base]);
+        let ids: Vec<&str> = result.merged.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["n1", "a", "b"]);
+    }
+
+    #[test]
+    fn missing_on_one_side_means_deleted_there() {
+        let base = highlight();
+        let result = merge_notes(&[base.clone()], &[base.clone()], &[]);
+        assert!(result.merged.is_empty());
+        let edited = with(base.clone(), "color", json!("green"));
+        let result = merge_notes(&[base], &[], &[edited.clone()]);
+        assert_eq!(result.merged, vec![edited]);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::EditedAndDeleted);
+    }
+
+    #[test]
+    fn unknown_fields_round_trip() {
+        let n = note(json!({ "id": "n", "futureField": [1, 2], "note": "" }));
+        let out = serde_json::to_value(&n).unwrap();
+        assert_eq!(out["futureField"], json!([1, 2]));
+        assert_eq!(out["id"], json!("n"));
+    }
+}
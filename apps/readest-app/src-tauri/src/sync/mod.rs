@@ -0,0 +1,742 @@
+//! Native cloud-sync backends.
+//!
+//! The file-sync engine (`services/sync/file/engine.ts`) drives every
+//! backend through the `FileSyncProvider` interface, and the TS providers
+//! handle the small JSON reads and writes themselves. The backends here
+//! cover what the webview is bad at: streaming book files to and from disk
+//! without holding them in the JS heap, multipart / resumable uploads,
+//! draining paginated listings, and reading credentials straight from the
+//! OS keychain so secrets never cross the IPC boundary.
+//!
+//! A [`SyncTarget`] names the provider and its non-secret settings; the
+//! secret half lives in the keyed secure store (`setSecureItem`) under the
+//! target's `credentialsKey`. Failures come back as [`SyncError`], carrying
+//! the engine's normalised `FileSyncErrorCode`, so the TS side branches on
+//! auth / not-found / network / conflict the same way for every transport.
+//!
+//! [`lan`] plugs a paired device on the local network in as one more
+//! backend, so the same engine syncs without any cloud account.
+//!
+//! Each transfer reports its progress and outcome per item on
+//! [`status::STATUS_EVENT`].
+
+pub mod crypto;
+pub mod dropbox;
+pub mod gdrive;
+pub mod lan;
+pub mod merge;
+pub(crate) mod oauth;
+pub mod onedrive;
+pub mod policy;
+pub mod queue;
+pub mod s3;
+pub mod status;
+pub mod transfer;
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{ipc::Channel, AppHandle, Emitter, Manager};
+use tauri_plugin_native_bridge::{GetSecureItemRequest, NativeBridgeExt, SetSecureItemRequest};
+
+use crate::notifications;
+use crate::transfer_file::{ensure_path_allowed, ProgressPayload, TransferStats};
+use policy::{SkipReason, SyncPolicyStore};
+use status::{ItemStatus, Operation};
+use transfer::Upload;
+
+/// Retries for a transient (408/429/5xx or connection) failure.
+const MAX_RETRIES: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// At most one sync failure notification per provider in this interval.
+const SYNC_ERROR_NOTIFY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Mirrors `FileSyncErrorCode` in `services/sync/file/provider.ts`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SyncErrorCode {
+    AuthFailed,
+    NotFound,
+    Network,
+    Conflict,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[serde(rename_all = "camelCase")]
+#[error("{message}")]
+pub struct SyncError {
+    pub code: SyncErrorCode,
+    pub message: String,
+    /// HTTP status when the request reached the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Provider-specific cause, e.g. Drive's `storageQuotaExceeded`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl SyncError {
+    pub(crate) fn new(code: SyncErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            status: None,
+            reason: None,
+        }
+    }
+
+    /// Map an HTTP failure the way the TS providers do: 401/403 → auth,
+    /// 404 → not found, 408/429/5xx → network, 409/412 → conflict.
+    pub(crate) fn from_status(status: u16, message: impl Into<String>) -> Self {
+        let code = match status {
+            401 | 403 => SyncErrorCode::AuthFailed,
+            404 => SyncErrorCode::NotFound,
+            409 | 412 => SyncErrorCode::Conflict,
+            408 | 429 | 500..=599 => SyncErrorCode::Network,
+            _ => SyncErrorCode::Unknown,
+        };
+        Self {
+            code,
+            message: message.into(),
+            status: Some(status),
+            reason: None,
+        }
+    }
+
+    pub(crate) fn io(context: &str, e: std::io::Error) -> Self {
+        Self::new(SyncErrorCode::Unknown, format!("{context}: {e}"))
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Self::from_status(status.as_u16(), e.to_string()),
+            None => Self::new(SyncErrorCode::Network, e.to_string()),
+        }
+    }
+}
+
+/// A directory entry, shaped like the engine's `FileEntry`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEntry {
+    pub name: String,
+    /// Absolute logical path: leading slash, decoded.
+    pub path: String,
+    pub is_directory: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+/// Shaped like the engine's `FileHead`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteHead {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+}
+
+/// One entry of an incremental change feed.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteChange {
+    /// Provider file id; stable across renames.
+    pub id: String,
+    /// Logical path, when known. Removals of files this process never
+    /// resolved arrive without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub removed: bool,
+    pub is_directory: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteChanges {
+    pub changes: Vec<RemoteChange>,
+    /// Opaque token to pass to the next pull.
+    pub cursor: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageQuota {
+    pub used: u64,
+    /// Absent for unlimited plans.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// Payload of the `sync://error` event, emitted for every failed command
+/// so the UI can surface quota and auth problems outside the call site.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncErrorEvent<'a> {
+    provider: &'a str,
+    operation: &'a str,
+    error: &'a SyncError,
+}
+
+/// Provider plus its non-secret settings, as stored in `SystemSettings`.
+/// Tags match `FileSyncBackendKind`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "provider", rename_all = "camelCase")]
+pub enum SyncTarget {
+    S3(s3::S3Target),
+    #[serde(rename = "gdrive")]
+    GoogleDrive(gdrive::GoogleDriveTarget),
+    Dropbox(dropbox::DropboxTarget),
+    #[serde(rename = "onedrive")]
+    OneDrive(onedrive::OneDriveTarget),
+    Lan(lan::LanTarget),
+}
+
+impl SyncTarget {
+    fn provider(&self) -> &'static str {
+        match self {
+            Self::S3(_) => "s3",
+            Self::GoogleDrive(_) => "gdrive",
+            Self::Dropbox(_) => "dropbox",
+            Self::OneDrive(_) => "onedrive",
+            Self::Lan(_) => "lan",
+        }
+    }
+
+    /// Account and root the target points at, for keying upload sessions.
+    fn scope(&self) -> String {
+        match self {
+            Self::S3(target) => format!("s3:{}/{}", target.endpoint, target.bucket),
+            Self::GoogleDrive(target) => format!(
+                "gdrive:{}:{}",
+                target.token_key,
+                target.folder_id.as_deref().unwrap_or_default()
+            ),
+            Self::Dropbox(target) => format!("dropbox:{}", target.token_key),
+            Self::OneDrive(target) => format!("onedrive:{}", target.token_key),
+            Self::Lan(target) => format!("lan:{}", target.peer_id.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+pub(crate) enum Backend {
+    S3(s3::S3Backend),
+    GoogleDrive(gdrive::DriveBackend),
+    Dropbox(dropbox::DropboxBackend),
+    OneDrive(onedrive::OneDriveBackend),
+    Lan(lan::LanBackend),
+}
+
+impl Backend {
+    pub(crate) fn connect(app: &AppHandle, target: SyncTarget) -> Result<Self, SyncError> {
+        match target {
+            SyncTarget::S3(target) => {
+                let secret = load_secret(app, &target.credentials_key)?;
+                Ok(Self::S3(s3::S3Backend::new(target, &secret)?))
+            }
+            SyncTarget::GoogleDrive(target) => {
+                Ok(Self::GoogleDrive(gdrive::DriveBackend::new(app, target)?))
+            }
+            SyncTarget::Dropbox(target) => {
+                Ok(Self::Dropbox(dropbox::DropboxBackend::new(app, target)?))
+            }
+            SyncTarget::OneDrive(target) => {
+                Ok(Self::OneDrive(onedrive::OneDriveBackend::new(app, target)?))
+            }
+            SyncTarget::Lan(target) => Ok(Self::Lan(lan::LanBackend::new(app, target)?)),
+        }
+    }
+
+    pub(crate) async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        match self {
+            Self::S3(backend) => backend.list(path).await,
+            Self::GoogleDrive(backend) => backend.list(path).await,
+            Self::Dropbox(backend) => backend.list(path).await,
+            Self::OneDrive(backend) => backend.list(path).await,
+            Self::Lan(backend) => backend.list(path).await,
+        }
+    }
+
+    pub(crate) async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        match self {
+            Self::S3(backend) => backend.head(path).await,
+            Self::GoogleDrive(backend) => backend.head(path).await,
+            Self::Dropbox(backend) => backend.head(path).await,
+            Self::OneDrive(backend) => backend.head(path).await,
+            Self::Lan(backend) => backend.head(path).await,
+        }
+    }
+
+    pub(crate) async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        match self {
+            Self::S3(backend) => {
+                backend
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+            Self::GoogleDrive(backend) => {
+                backend
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+            Self::Dropbox(backend) => {
+                backend
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+            Self::OneDrive(backend) => {
+                backend
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+            Self::Lan(backend) => {
+                backend
+                    .upload(local_path, remote_path, upload, on_progress)
+                    .await
+            }
+        }
+    }
+
+    pub(crate) async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        match self {
+            Self::S3(backend) => backend.download(remote_path, local_path, on_progress).await,
+            Self::GoogleDrive(backend) => {
+                backend.download(remote_path, local_path, on_progress).await
+            }
+            Self::Dropbox(backend) => backend.download(remote_path, local_path, on_progress).await,
+            Self::OneDrive(backend) => backend.download(remote_path, local_path, on_progress).await,
+            Self::Lan(backend) => backend.download(remote_path, local_path, on_progress).await,
+        }
+    }
+
+    pub(crate) async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        match self {
+            Self::S3(backend) => backend.delete(path).await,
+            Self::GoogleDrive(backend) => backend.delete(path).await,
+            Self::Dropbox(backend) => backend.delete(path).await,
+            Self::OneDrive(backend) => backend.delete(path).await,
+            Self::Lan(backend) => backend.delete(path).await,
+        }
+    }
+
+    pub(crate) async fn changes(&self, cursor: Option<&str>) -> Result<RemoteChanges, SyncError> {
+        match self {
+            Self::S3(_) => Err(unsupported("s3", "change feeds")),
+            Self::GoogleDrive(backend) => backend.changes(cursor).await,
+            Self::Dropbox(backend) => backend.changes(cursor).await,
+            Self::OneDrive(backend) => backend.changes(cursor).await,
+            Self::Lan(_) => Err(unsupported("lan", "change feeds")),
+        }
+    }
+
+    pub(crate) async fn quota(&self) -> Result<StorageQuota, SyncError> {
+        match self {
+            Self::S3(_) => Err(unsupported("s3", "storage quotas")),
+            Self::GoogleDrive(backend) => backend.quota().await,
+            Self::Dropbox(backend) => backend.quota().await,
+            Self::OneDrive(backend) => backend.quota().await,
+            Self::Lan(_) => Err(unsupported("lan", "storage quotas")),
+        }
+    }
+}
+
+fn unsupported(provider: &str, feature: &str) -> SyncError {
+    SyncError::new(
+        SyncErrorCode::Unknown,
+        format!("{provider} does not support {feature}"),
+    )
+}
+
+/// Read a secret the frontend stored with `setSecureItem`.
+pub(crate) fn load_secret(app: &AppHandle, key: &str) -> Result<String, SyncError> {
+    let response = app
+        .native_bridge()
+        .get_secure_item(GetSecureItemRequest {
+            key: key.to_string(),
+        })
+        .map_err(|e| SyncError::new(SyncErrorCode::AuthFailed, format!("keychain: {e}")))?;
+    match (response.value, response.error) {
+        (Some(value), _) => Ok(value),
+        (None, Some(e)) => Err(SyncError::new(
+            SyncErrorCode::AuthFailed,
+            format!("keychain: {e}"),
+        )),
+        (None, None) => Err(SyncError::new(
+            SyncErrorCode::AuthFailed,
+            format!("no credentials stored under {key}"),
+        )),
+    }
+}
+
+/// Store a secret where the frontend's `getSecureItem` will find it.
+pub(crate) fn save_secret(app: &AppHandle, key: &str, value: &str) -> Result<(), SyncError> {
+    let response = app
+        .native_bridge()
+        .set_secure_item(SetSecureItemRequest {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .map_err(|e| SyncError::new(SyncErrorCode::AuthFailed, format!("keychain: {e}")))?;
+    if response.success {
+        Ok(())
+    } else {
+        Err(SyncError::new(
+            SyncErrorCode::AuthFailed,
+            format!("keychain: {}", response.error.unwrap_or_default()),
+        ))
+    }
+}
+
+pub(crate) fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+/// `Readest//books/` → `/Readest/books`; the root is the empty string.
+pub(crate) fn normalize(path: &str) -> String {
+    segments(path).fold(String::new(), |acc, s| acc + "/" + s)
+}
+
+pub(crate) fn progress(stats: &TransferStats, total: u64) -> ProgressPayload {
+    ProgressPayload::new(stats.total_transferred, total, stats.transfer_speed)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF * (1 << attempt.min(5))
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let secs: u64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs.min(60)))
+}
+
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Send the request `build` makes, retrying transient failures with
+/// exponential backoff (or the server's `Retry-After`). `build` runs once
+/// per attempt so signatures, tokens and file bodies are fresh.
+pub(crate) async fn send_with_retry<F>(build: F) -> Result<reqwest::Response, SyncError>
+where
+    F: Fn() -> Result<reqwest::RequestBuilder, SyncError>,
+{
+    let mut attempt = 0;
+    loop {
+        let delay = match build()?.send().await {
+            Ok(response) if attempt < MAX_RETRIES && is_transient(response.status()) => {
+                retry_after(&response).unwrap_or_else(|| backoff(attempt))
+            }
+            Err(e) if attempt < MAX_RETRIES && (e.is_connect() || e.is_timeout()) => {
+                backoff(attempt)
+            }
+            result => return result.map_err(SyncError::from),
+        };
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Turn a non-2xx response into a [`SyncError`] naming the operation.
+pub(crate) async fn error_for(response: reqwest::Response, operation: &str) -> SyncError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    let detail: String = body.trim().chars().take(300).collect();
+    SyncError::from_status(status, format!("{operation} failed ({status}): {detail}"))
+}
+
+/// Emit `sync://error` for a failed command and pass the result through.
+fn report<T>(
+    app: &AppHandle,
+    provider: &str,
+    operation: &str,
+    result: Result<T, SyncError>,
+) -> Result<T, SyncError> {
+    if let Err(error) = &result {
+        let event = SyncErrorEvent {
+            provider,
+            operation,
+            error,
+        };
+        if let Err(e) = app.emit("sync://error", event) {
+            log::warn!("Failed to emit sync error: {e}");
+        }
+        // Missing files and conflicts are part of normal sync, and network
+        // failures are retried by the offline queue.
+        if matches!(
+            error.code,
+            SyncErrorCode::AuthFailed | SyncErrorCode::Unknown
+        ) {
+            notifications::notify_throttled(
+                app,
+                &format!("sync:{provider}"),
+                SYNC_ERROR_NOTIFY_INTERVAL,
+                "Sync failed",
+                &format!("{provider}: {}", error.message),
+            );
+        }
+    }
+    result
+}
+
+fn allowed(app: &AppHandle, path: &str) -> Result<(), SyncError> {
+    ensure_path_allowed(app, path)
+        .map_err(|e| SyncError::new(SyncErrorCode::Unknown, e.to_string()))
+}
+
+/// List the immediate children of `path`, draining every page.
+#[tauri::command]
+pub async fn sync_list(
+    app: AppHandle,
+    target: SyncTarget,
+    path: String,
+) -> Result<Vec<RemoteEntry>, SyncError> {
+    let provider = target.provider();
+    let result = async { Backend::connect(&app, target)?.list(&path).await }.await;
+    report(&app, provider, "list", result)
+}
+
+/// Size and ETag of `path`, or `None` when it doesn't exist.
+#[tauri::command]
+pub async fn sync_head(
+    app: AppHandle,
+    target: SyncTarget,
+    path: String,
+) -> Result<Option<RemoteHead>, SyncError> {
+    let provider = target.provider();
+    let result = async { Backend::connect(&app, target)?.head(&path).await }.await;
+    report(&app, provider, "head", result)
+}
+
+/// Where an encrypted copy of `local_path` is staged. It sits next to the
+/// file so it stays inside the scope `allowed` already checked.
+fn staging_path(local_path: &str) -> String {
+    format!("{local_path}.rde2ee")
+}
+
+/// Upload `local_path` under the selective sync policy, sealing it first
+/// when `encrypted`. Shared by [`sync_upload`] and the offline queue; the
+/// caller has already checked that `local_path` may be read.
+pub(crate) async fn upload_file(
+    app: &AppHandle,
+    target: SyncTarget,
+    local_path: &str,
+    remote_path: &str,
+    encrypted: bool,
+    on_progress: Channel<ProgressPayload>,
+) -> Result<Option<SkipReason>, SyncError> {
+    let size = std::fs::metadata(local_path)
+        .map_err(|e| SyncError::io("stat", e))?
+        .len();
+    if let Some(reason) = app.state::<SyncPolicyStore>().evaluate(remote_path, size) {
+        log::debug!("Not uploading {remote_path}: {reason:?}");
+        return Ok(Some(reason));
+    }
+    let scope = target.scope();
+    let backend = Backend::connect(app, target)?;
+    if !encrypted {
+        let upload = Upload::begin(app, &scope, local_path, remote_path, true)?;
+        backend
+            .upload(local_path, remote_path, &upload, on_progress)
+            .await?;
+        return Ok(None);
+    }
+    let key = crypto::require_key(app)?;
+    let staged = staging_path(local_path);
+    let (src, dst) = (local_path.to_string(), staged.clone());
+    let result = async {
+        crypto::blocking(move || crypto::encrypt_file(&key, &src, &dst)).await?;
+        // Every encryption uses fresh nonces, so a session holding an
+        // earlier ciphertext can't be continued.
+        let upload = Upload::begin(app, &scope, &staged, remote_path, false)?;
+        backend
+            .upload(&staged, remote_path, &upload, on_progress)
+            .await
+    }
+    .await;
+    let _ = std::fs::remove_file(&staged);
+    result.map(|()| None)
+}
+
+/// Stream `local_path` to `remote_path`; large files go up in parts. With
+/// `encrypted`, the file is sealed with the E2EE key first and the
+/// ciphertext is what leaves the device. Returns why the selective sync
+/// policy kept the file local, or `None` once it's uploaded.
+#[tauri::command]
+pub async fn sync_upload(
+    app: AppHandle,
+    target: SyncTarget,
+    local_path: String,
+    remote_path: String,
+    encrypted: Option<bool>,
+    on_progress: Channel<ProgressPayload>,
+) -> Result<Option<SkipReason>, SyncError> {
+    let provider = target.provider();
+    let status = ItemStatus::new(&app, provider, Operation::Upload, &remote_path);
+    let result = async {
+        allowed(&app, &local_path)?;
+        upload_file(
+            &app,
+            target,
+            &local_path,
+            &remote_path,
+            encrypted.unwrap_or(false),
+            status.track(on_progress),
+        )
+        .await
+    }
+    .await;
+    status.uploaded(&result, false);
+    report(&app, provider, "upload", result)
+}
+
+/// Stream `remote_path` to `local_path`. A missing file is `NOT_FOUND`.
+/// With `encrypted`, the download is decrypted on the way in; a wrong key
+/// or a tampered file is `AUTH_FAILED` and leaves nothing at `local_path`.
+#[tauri::command]
+pub async fn sync_download(
+    app: AppHandle,
+    target: SyncTarget,
+    remote_path: String,
+    local_path: String,
+    encrypted: Option<bool>,
+    on_progress: Channel<ProgressPayload>,
+) -> Result<(), SyncError> {
+    let provider = target.provider();
+    let status = ItemStatus::new(&app, provider, Operation::Download, &remote_path);
+    let on_progress = status.track(on_progress);
+    let result = async {
+        allowed(&app, &local_path)?;
+        let backend = Backend::connect(&app, target)?;
+        if !encrypted.unwrap_or(false) {
+            return backend
+                .download(&remote_path, &local_path, on_progress)
+                .await;
+        }
+        let key = crypto::require_key(&app)?;
+        let staged = staging_path(&local_path);
+        let result = async {
+            backend.download(&remote_path, &staged, on_progress).await?;
+            let (src, dst) = (staged.clone(), local_path.clone());
+            crypto::blocking(move || crypto::decrypt_file(&key, &src, &dst)).await
+        }
+        .await;
+        let _ = std::fs::remove_file(&staged);
+        result
+    }
+    .await;
+    status.finished(&result);
+    report(&app, provider, "download", result)
+}
+
+/// Delete a file or a whole directory subtree. Missing paths succeed.
+#[tauri::command]
+pub async fn sync_delete(
+    app: AppHandle,
+    target: SyncTarget,
+    path: String,
+) -> Result<(), SyncError> {
+    let provider = target.provider();
+    let status = ItemStatus::new(&app, provider, Operation::Delete, &path);
+    let result = async { Backend::connect(&app, target)?.delete(&path).await }.await;
+    status.finished(&result);
+    report(&app, provider, "delete", result)
+}
+
+/// Remote changes since `cursor`. Without a cursor, returns no changes and
+/// the provider's current position, to store after a full listing.
+#[tauri::command]
+pub async fn sync_changes(
+    app: AppHandle,
+    target: SyncTarget,
+    cursor: Option<String>,
+) -> Result<RemoteChanges, SyncError> {
+    let provider = target.provider();
+    let result = async {
+        Backend::connect(&app, target)?
+            .changes(cursor.as_deref())
+            .await
+    }
+    .await;
+    report(&app, provider, "changes", result)
+}
+
+/// Storage used and available on the account.
+#[tauri::command]
+pub async fn sync_quota(app: AppHandle, target: SyncTarget) -> Result<StorageQuota, SyncError> {
+    let provider = target.provider();
+    let result = async { Backend::connect(&app, target)?.quota().await }.await;
+    report(&app, provider, "quota", result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_statuses_like_the_ts_providers() {
+        let code = |status| SyncError::from_status(status, "").code;
+        assert_eq!(code(401), SyncErrorCode::AuthFailed);
+        assert_eq!(code(403), SyncErrorCode::AuthFailed);
+        assert_eq!(code(404), SyncErrorCode::NotFound);
+        assert_eq!(code(412), SyncErrorCode::Conflict);
+        assert_eq!(code(429), SyncErrorCode::Network);
+        assert_eq!(code(503), SyncErrorCode::Network);
+        assert_eq!(code(400), SyncErrorCode::Unknown);
+    }
+
+    #[test]
+    fn errors_serialize_with_engine_codes() {
+        let json = serde_json::to_value(SyncError::from_status(401, "denied")).unwrap();
+        assert_eq!(json["code"], "AUTH_FAILED");
+        assert_eq!(json["status"], 401);
+        assert_eq!(json["message"], "denied");
+    }
+
+    #[test]
+    fn targets_use_backend_kind_tags() {
+        let target: SyncTarget =
+            serde_json::from_str(r#"{"provider":"gdrive","clientId":"c"}"#).unwrap();
+        assert_eq!(target.provider(), "gdrive");
+    }
+
+    #[test]
+    fn normalizes_logical_paths() {
+        assert_eq!(normalize("Readest//books/"), "/Readest/books");
+        assert_eq!(normalize("/"), "");
+    }
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        assert_eq!(backoff(0), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(9), Duration::from_secs(16));
+    }
+}
@@ -0,0 +1,265 @@
+//! Bearer tokens for the OAuth-backed sync providers.
+//!
+//! The authorization-code + PKCE flow stays in the webview
+//! (`providers/oauth/`), which saves a `TokenSet` as JSON under a
+//! per-provider secure-store key. The native backends read that same entry,
+//! refresh an expired access token against the provider's token endpoint
+//! (public clients, so no secret), and write the result back so the TS
+//! provider sees the fresh token as well.
+//!
+//! Providers whose sign-in lives on this side (Dropbox) use [`pkce_pair`]
+//! and [`exchange_code`] to run the same flow natively and store the
+//! result under their key in the same shape.
+
+use base64::Engine;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use super::{error_for, load_secret, save_secret, send_with_retry, SyncError, SyncErrorCode};
+
+/// Same margin as `TOKEN_EXPIRY_SAFETY_MARGIN_SEC` in `tokenEndpoint.ts`.
+const TOKEN_EXPIRY_SAFETY_MARGIN_SECS: i64 = 60;
+
+/// Mirrors `TokenSet` in `providers/oauth/tokenEndpoint.ts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TokenSet {
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Epoch milliseconds, already shortened by the safety margin.
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+impl TokenSet {
+    /// Build the stored set from a refresh response. Providers that don't
+    /// rotate refresh tokens omit it, so the previous one is kept.
+    fn from_refresh(response: TokenResponse, previous_refresh: String, now_ms: i64) -> Self {
+        Self {
+            access_token: response.access_token,
+            refresh_token: Some(response.refresh_token.unwrap_or(previous_refresh)),
+            expires_at: expiry(now_ms, response.expires_in),
+        }
+    }
+}
+
+/// Absolute expiry for a token issued now, minus the safety margin.
+fn expiry(now_ms: i64, expires_in: i64) -> i64 {
+    now_ms + (expires_in - TOKEN_EXPIRY_SAFETY_MARGIN_SECS).max(0) * 1000
+}
+
+/// RFC 7636 S256 challenge for `verifier`.
+fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// An unguessable token of unreserved characters, for PKCE verifiers and
+/// `state`.
+pub(crate) fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// A fresh `(verifier, challenge)` pair.
+pub(crate) fn pkce_pair() -> (String, String) {
+    let verifier = random_token(64);
+    let challenge = pkce_challenge(&verifier);
+    (verifier, challenge)
+}
+
+/// Trade an authorization code for tokens, proving possession of the
+/// PKCE verifier instead of a client secret.
+pub(crate) async fn exchange_code(
+    http: &reqwest::Client,
+    token_endpoint: &str,
+    client_id: &str,
+    code: &str,
+    verifier: &str,
+    redirect_uri: &str,
+) -> Result<TokenSet, SyncError> {
+    let response = http
+        .post(token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", verifier),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let mut error = error_for(response, "token exchange").await;
+        error.code = SyncErrorCode::AuthFailed;
+        return Err(error);
+    }
+    let body: TokenResponse = response.json().await?;
+    Ok(TokenSet {
+        expires_at: expiry(now_ms(), body.expires_in),
+        access_token: body.access_token,
+        refresh_token: body.refresh_token,
+    })
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub(crate) struct OAuthClient {
+    app: AppHandle,
+    http: reqwest::Client,
+    token_key: String,
+    client_id: String,
+    token_endpoint: &'static str,
+    tokens: Mutex<TokenSet>,
+}
+
+impl OAuthClient {
+    /// Load the token set the webview stored under `token_key`.
+    pub(crate) fn load(
+        app: &AppHandle,
+        http: reqwest::Client,
+        token_key: &str,
+        client_id: &str,
+        token_endpoint: &'static str,
+    ) -> Result<Self, SyncError> {
+        let stored = load_secret(app, token_key)?;
+        let tokens: TokenSet = serde_json::from_str(&stored).map_err(|e| {
+            SyncError::new(
+                SyncErrorCode::AuthFailed,
+                format!("stored token set is unreadable: {e}"),
+            )
+        })?;
+        Ok(Self {
+            app: app.clone(),
+            http,
+            token_key: token_key.to_string(),
+            client_id: client_id.to_string(),
+            token_endpoint,
+            tokens: Mutex::new(tokens),
+        })
+    }
+
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// A currently valid access token, refreshing first if it has expired.
+    pub(crate) async fn access_token(&self) -> Result<String, SyncError> {
+        let tokens = self.tokens.lock().unwrap().clone();
+        if now_ms() < tokens.expires_at {
+            return Ok(tokens.access_token);
+        }
+        self.refresh().await
+    }
+
+    /// Mint a new access token and persist it for the TS side too.
+    pub(crate) async fn refresh(&self) -> Result<String, SyncError> {
+        let refresh_token = self
+            .tokens
+            .lock()
+            .unwrap()
+            .refresh_token
+            .clone()
+            .ok_or_else(|| {
+                SyncError::new(
+                    SyncErrorCode::AuthFailed,
+                    "session expired and no refresh token is stored; reconnect the account",
+                )
+            })?;
+        let response = send_with_retry(|| {
+            Ok(self.http.post(self.token_endpoint).form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+                ("client_id", self.client_id.as_str()),
+            ]))
+        })
+        .await?;
+        if !response.status().is_success() {
+            let mut error = error_for(response, "token refresh").await;
+            // `invalid_grant` comes back as a 400: the grant was revoked.
+            if error.status == Some(400) {
+                error.code = SyncErrorCode::AuthFailed;
+            }
+            return Err(error);
+        }
+        let body: TokenResponse = response.json().await?;
+        let tokens = TokenSet::from_refresh(body, refresh_token, now_ms());
+        match serde_json::to_string(&tokens) {
+            Ok(json) => {
+                if let Err(e) = save_secret(&self.app, &self.token_key, &json) {
+                    log::warn!("Failed to persist refreshed token: {e}");
+                }
+            }
+            Err(e) => log::warn!("Failed to serialize refreshed token: {e}"),
+        }
+        let access_token = tokens.access_token.clone();
+        *self.tokens.lock().unwrap() = tokens;
+        Ok(access_token)
+    }
+
+    /// Send an authorized request, refreshing once if the token is rejected.
+    pub(crate) async fn send<F>(&self, build: F) -> Result<reqwest::Response, SyncError>
+    where
+        F: Fn() -> Result<reqwest::RequestBuilder, SyncError>,
+    {
+        let token = self.access_token().await?;
+        let response = send_with_retry(|| Ok(build()?.bearer_auth(&token))).await?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let token = self.refresh().await?;
+        send_with_retry(|| Ok(build()?.bearer_auth(&token))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_set_round_trips_the_ts_shape() {
+        let json = r#"{"accessToken":"a","refreshToken":"r","expiresAt":1700000000000}"#;
+        let tokens: TokenSet = serde_json::from_str(json).unwrap();
+        assert_eq!(tokens.refresh_token.as_deref(), Some("r"));
+        assert_eq!(serde_json::to_string(&tokens).unwrap(), json);
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(random_token(64).len(), 64);
+    }
+
+    #[test]
+    fn refresh_keeps_the_previous_refresh_token() {
+        let response = TokenResponse {
+            access_token: "new".into(),
+            refresh_token: None,
+            expires_in: 3600,
+        };
+        let tokens = TokenSet::from_refresh(response, "old".into(), 1_000);
+        assert_eq!(tokens.refresh_token.as_deref(), Some("old"));
+        assert_eq!(tokens.expires_at, 1_000 + 3_540_000);
+    }
+}
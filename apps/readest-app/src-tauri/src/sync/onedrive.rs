@@ -0,0 +1,780 @@
+//! OneDrive backend over Microsoft Graph.
+//!
+//! Shares the TS provider's layout (`providers/onedrive/graphRest.ts`):
+//! every logical path is addressed relative to the app folder
+//! (`/me/drive/special/approot`), and the token set comes from the
+//! webview's sign-in under `onedrive_token_set`.
+//!
+//! Personal and work/school accounts differ in two places this module
+//! handles. Business drives only serve `delta` from the drive root, so
+//! [`OneDriveBackend::changes`] reads the root feed there and drops items
+//! outside the app folder. And business upload URLs reject a bearer token,
+//! so chunks go to the session URL without one for either kind. The
+//! drive type comes from the target when the frontend knows it, otherwise
+//! from `/me/drive`. Session URLs are journaled (see `transfer.rs`), so an
+//! interrupted upload continues from the range Graph expects next.
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use tauri::{ipc::Channel, AppHandle};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::oauth::OAuthClient;
+use super::transfer::Upload;
+use super::{
+    backoff, is_transient, normalize, progress, segments, send_with_retry, RemoteChange,
+    RemoteChanges, RemoteEntry, RemoteHead, StorageQuota, SyncError, SyncErrorCode, MAX_RETRIES,
+};
+use crate::transfer_file::{ProgressPayload, TransferStats};
+
+const TOKEN_ENDPOINT: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/token";
+const GRAPH_BASE: &str = "https://graph.microsoft.com/v1.0";
+const APPROOT: &str = "https://graph.microsoft.com/v1.0/me/drive/special/approot";
+const DRIVE: &str = "https://graph.microsoft.com/v1.0/me/drive";
+/// Same key `onedriveTokenStore.ts` saves the token set under.
+const DEFAULT_TOKEN_KEY: &str = "onedrive_token_set";
+const CHILD_SELECT: &str = "name,size,cTag,file,folder,lastModifiedDateTime";
+const HEAD_SELECT: &str = "size,cTag,file,folder";
+const LIST_PAGE_SIZE: &str = "200";
+/// Graph's simple upload takes up to 4 MiB; anything larger needs a session.
+const SIMPLE_UPLOAD_LIMIT: u64 = 4 * 1024 * 1024;
+/// Session chunks must be multiples of 320 KiB.
+const CHUNK_SIZE: u64 = 32 * 320 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DriveType {
+    Personal,
+    /// OneDrive for Business; SharePoint libraries behave the same.
+    #[serde(alias = "documentLibrary")]
+    Business,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OneDriveTarget {
+    pub client_id: String,
+    #[serde(default = "default_token_key")]
+    pub token_key: String,
+    /// Detected from `/me/drive` when absent.
+    #[serde(default)]
+    pub drive_type: Option<DriveType>,
+}
+
+fn default_token_key() -> String {
+    DEFAULT_TOKEN_KEY.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveItem {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    size: Option<u64>,
+    c_tag: Option<String>,
+    last_modified_date_time: Option<String>,
+    folder: Option<Value>,
+    deleted: Option<Value>,
+    parent_reference: Option<ParentReference>,
+}
+
+impl DriveItem {
+    fn is_folder(&self) -> bool {
+        self.folder.is_some()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ParentReference {
+    id: Option<String>,
+    /// Percent-encoded, e.g. `/drive/root:/Apps/Readest`.
+    path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ItemPage {
+    #[serde(default)]
+    value: Vec<DriveItem>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+    #[serde(rename = "@odata.deltaLink")]
+    delta_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Drive {
+    drive_type: Option<DriveType>,
+    quota: Option<Quota>,
+}
+
+#[derive(Deserialize)]
+struct Quota {
+    used: Option<u64>,
+    total: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadSession {
+    upload_url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadStatus {
+    #[serde(default)]
+    next_expected_ranges: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    code: Option<String>,
+}
+
+enum UploadState {
+    Done,
+    /// Offset the session expects next.
+    Partial(u64),
+}
+
+/// `encodeURIComponent` each segment, as `encodeGraphPath` does.
+fn encode_path(path: &str) -> String {
+    segments(path)
+        .map(|segment| {
+            let mut out = String::new();
+            for byte in segment.bytes() {
+                match byte {
+                    b'A'..=b'Z'
+                    | b'a'..=b'z'
+                    | b'0'..=b'9'
+                    | b'-'
+                    | b'_'
+                    | b'.'
+                    | b'!'
+                    | b'~'
+                    | b'*'
+                    | b'\''
+                    | b'('
+                    | b')' => out.push(byte as char),
+                    _ => out.push_str(&format!("%{byte:02X}")),
+                }
+            }
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn item_url(path: &str) -> String {
+    match encode_path(path) {
+        enc if enc.is_empty() => APPROOT.to_string(),
+        enc => format!("{APPROOT}:/{enc}"),
+    }
+}
+
+fn children_url(path: &str) -> String {
+    match encode_path(path) {
+        enc if enc.is_empty() => format!("{APPROOT}/children"),
+        enc => format!("{APPROOT}:/{enc}:/children"),
+    }
+}
+
+/// `full` relative to the app folder's drive path, or `None` outside it.
+fn relative_to(full: &str, root: &str) -> Option<String> {
+    let rest = full.strip_prefix(root)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| rest.to_string())
+}
+
+/// Start of the first `nextExpectedRanges` entry, e.g. `"26214400-"`.
+fn next_offset(ranges: &[String]) -> Option<u64> {
+    ranges.first()?.split('-').next()?.trim().parse().ok()
+}
+
+fn content_range(offset: u64, len: u64, total: u64) -> String {
+    format!("bytes {offset}-{}/{total}", offset + len - 1)
+}
+
+/// Graph error codes refine the status: throttling under a 403 is
+/// transient, a full drive is not an auth problem, and an expired delta
+/// token (410 `resyncRequired`) means "list everything again".
+fn classify_error(status: u16, body: &str, operation: &str) -> SyncError {
+    let code = serde_json::from_str::<ErrorBody>(body)
+        .ok()
+        .and_then(|b| b.error.code);
+    let detail: String = body.trim().chars().take(300).collect();
+    let mut error =
+        SyncError::from_status(status, format!("{operation} failed ({status}): {detail}"));
+    match (status, code.as_deref()) {
+        (_, Some("activityLimitReached")) => error.code = SyncErrorCode::Network,
+        (507, _) | (_, Some("quotaLimitReached")) => error.code = SyncErrorCode::Unknown,
+        (410, _) | (423, _) => error.code = SyncErrorCode::Conflict,
+        _ => {}
+    }
+    error.reason = code;
+    error
+}
+
+async fn graph_error(response: reqwest::Response, operation: &str) -> SyncError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    classify_error(status, &body, operation)
+}
+
+async fn upload_state(response: reqwest::Response) -> Result<UploadState, SyncError> {
+    match response.status().as_u16() {
+        200 | 201 => Ok(UploadState::Done),
+        202 => {
+            let status: UploadStatus = response.json().await?;
+            Ok(UploadState::Partial(
+                next_offset(&status.next_expected_ranges).unwrap_or(0),
+            ))
+        }
+        404 => Err(SyncError::new(
+            SyncErrorCode::Network,
+            "upload session expired; retry the upload",
+        )),
+        _ => Err(graph_error(response, "upload").await),
+    }
+}
+
+pub(crate) struct OneDriveBackend {
+    auth: OAuthClient,
+    drive_type: Option<DriveType>,
+}
+
+impl OneDriveBackend {
+    pub(crate) fn new(app: &AppHandle, target: OneDriveTarget) -> Result<Self, SyncError> {
+        let auth = OAuthClient::load(
+            app,
+            crate::net::client(),
+            &target.token_key,
+            &target.client_id,
+            TOKEN_ENDPOINT,
+        )?;
+        Ok(Self {
+            auth,
+            drive_type: target.drive_type,
+        })
+    }
+
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<reqwest::Response, SyncError> {
+        self.auth
+            .send(|| Ok(self.auth.http().get(url).query(query)))
+            .await
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        operation: &str,
+    ) -> Result<T, SyncError> {
+        let response = self.get(url, query).await?;
+        if !response.status().is_success() {
+            return Err(graph_error(response, operation).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn drive(&self, select: &str) -> Result<Drive, SyncError> {
+        self.get_json(DRIVE, &[("$select", select)], "drive").await
+    }
+
+    async fn drive_type(&self) -> Result<DriveType, SyncError> {
+        if let Some(drive_type) = self.drive_type {
+            return Ok(drive_type);
+        }
+        Ok(self
+            .drive("driveType")
+            .await?
+            .drive_type
+            .unwrap_or(DriveType::Personal))
+    }
+
+    pub(crate) async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, SyncError> {
+        let base = normalize(path);
+        let response = self
+            .get(
+                &children_url(path),
+                &[("$select", CHILD_SELECT), ("$top", LIST_PAGE_SIZE)],
+            )
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !response.status().is_success() {
+            return Err(graph_error(response, "list").await);
+        }
+        let mut page: ItemPage = response.json().await?;
+        let mut entries = Vec::new();
+        loop {
+            for item in page.value {
+                entries.push(RemoteEntry {
+                    path: format!("{base}/{}", item.name),
+                    is_directory: item.is_folder(),
+                    name: item.name,
+                    size: item.size,
+                    last_modified: item.last_modified_date_time,
+                });
+            }
+            let Some(next) = page.next_link else {
+                return Ok(entries);
+            };
+            page = self.get_json(&next, &[], "list").await?;
+        }
+    }
+
+    pub(crate) async fn head(&self, path: &str) -> Result<Option<RemoteHead>, SyncError> {
+        let response = self
+            .get(&item_url(path), &[("$select", HEAD_SELECT)])
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(graph_error(response, "head").await);
+        }
+        let item: DriveItem = response.json().await?;
+        Ok(Some(RemoteHead {
+            size: item.size,
+            etag: item.c_tag,
+        }))
+    }
+
+    pub(crate) async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        // Redirects to a pre-authenticated URL; reqwest drops the bearer
+        // token when it follows to another host.
+        let url = format!("{}:/content", item_url(remote_path));
+        let response = self.get(&url, &[]).await?;
+        if !response.status().is_success() {
+            return Err(graph_error(response, "download").await);
+        }
+        let total = response.content_length().unwrap_or(0);
+        let partial = format!("{local_path}.part");
+        let mut out = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| SyncError::io("create", e))?;
+        let mut stats = TransferStats::default();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk)
+                .await
+                .map_err(|e| SyncError::io("write", e))?;
+            stats.record_chunk_transfer(chunk.len());
+            let _ = on_progress.send(progress(&stats, total));
+        }
+        out.flush().await.map_err(|e| SyncError::io("write", e))?;
+        drop(out);
+        tokio::fs::rename(&partial, local_path)
+            .await
+            .map_err(|e| SyncError::io("rename", e))
+    }
+
+    /// Ask the session which range it expects after a failed chunk.
+    async fn query_session(&self, upload_url: &str) -> Result<UploadState, SyncError> {
+        let response = send_with_retry(|| Ok(self.auth.http().get(upload_url))).await?;
+        if !response.status().is_success() {
+            return upload_state(response).await;
+        }
+        let status: UploadStatus = response.json().await?;
+        // A finished session has no ranges left, but then the PUT that
+        // completed it would have answered 200/201; restart from zero.
+        Ok(UploadState::Partial(
+            next_offset(&status.next_expected_ranges).unwrap_or(0),
+        ))
+    }
+
+    async fn send_chunks(
+        &self,
+        upload_url: &str,
+        local_path: &str,
+        size: u64,
+        mut offset: u64,
+        upload: &Upload,
+        on_progress: &Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| SyncError::io("open", e))?;
+        let mut stats = TransferStats::default();
+        stats.total_transferred = offset;
+        let mut failures = 0;
+        loop {
+            upload.check_network()?;
+            let len = CHUNK_SIZE.min(size - offset);
+            let mut chunk = vec![0; len as usize];
+            file.seek(SeekFrom::Start(offset))
+                .await
+                .map_err(|e| SyncError::io("seek", e))?;
+            file.read_exact(&mut chunk)
+                .await
+                .map_err(|e| SyncError::io("read", e))?;
+            let sent = self
+                .auth
+                .http()
+                .put(upload_url)
+                .header(
+                    reqwest::header::CONTENT_RANGE,
+                    content_range(offset, len, size),
+                )
+                .body(upload.body(chunk))
+                .send()
+                .await;
+            let state = match sent {
+                Ok(response) if !is_transient(response.status()) => upload_state(response).await?,
+                Ok(response) if failures >= MAX_RETRIES => {
+                    return Err(graph_error(response, "upload").await)
+                }
+                Err(e) if failures >= MAX_RETRIES => return Err(e.into()),
+                _ => {
+                    tokio::time::sleep(backoff(failures)).await;
+                    failures += 1;
+                    self.query_session(upload_url).await?
+                }
+            };
+            let committed = match state {
+                UploadState::Done => size,
+                UploadState::Partial(next) => next,
+            };
+            if committed > offset {
+                stats.record_chunk_transfer((committed - offset) as usize);
+                let _ = on_progress.send(progress(&stats, size));
+                upload.save(upload_url, committed, &[]);
+                failures = 0;
+            }
+            match state {
+                UploadState::Done => return Ok(()),
+                UploadState::Partial(next) => offset = next,
+            }
+        }
+    }
+
+    /// Graph creates missing parent folders for path-addressed uploads.
+    pub(crate) async fn upload(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        upload: &Upload,
+        on_progress: Channel<ProgressPayload>,
+    ) -> Result<(), SyncError> {
+        if normalize(remote_path).is_empty() {
+            return Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                format!("not a file path: {remote_path}"),
+            ));
+        }
+        let size = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| SyncError::io("stat", e))?
+            .len();
+        if size <= SIMPLE_UPLOAD_LIMIT {
+            let url = format!("{}:/content", item_url(remote_path));
+            let response = self
+                .auth
+                .send(|| {
+                    let file =
+                        std::fs::File::open(local_path).map_err(|e| SyncError::io("open", e))?;
+                    let body = upload.file_body(
+                        on_progress.clone(),
+                        tokio::fs::File::from_std(file),
+                        size,
+                    );
+                    Ok(self
+                        .auth
+                        .http()
+                        .put(&url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                        .header(reqwest::header::CONTENT_LENGTH, size)
+                        .body(body))
+                })
+                .await?;
+            if !response.status().is_success() {
+                return Err(graph_error(response, "upload").await);
+            }
+            return Ok(());
+        }
+        // Continue a journaled session if Graph still holds it.
+        let resumed = match upload.resume() {
+            Some(pending) => match self.query_session(&pending.session).await {
+                Ok(UploadState::Partial(next)) => Some((pending.session, next)),
+                _ => None,
+            },
+            None => None,
+        };
+        let (upload_url, offset) = match resumed {
+            Some(resumed) => resumed,
+            None => {
+                let url = format!("{}:/createUploadSession", item_url(remote_path));
+                let body = json!({ "item": { "@microsoft.graph.conflictBehavior": "replace" } });
+                let response = self
+                    .auth
+                    .send(|| Ok(self.auth.http().post(&url).json(&body)))
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(graph_error(response, "upload").await);
+                }
+                let session: UploadSession = response.json().await?;
+                upload.save(&session.upload_url, 0, &[]);
+                (session.upload_url, 0)
+            }
+        };
+        let result = self
+            .send_chunks(&upload_url, local_path, size, offset, upload, &on_progress)
+            .await;
+        match &result {
+            // Keep the session journaled so the next attempt continues it.
+            Err(e) if e.code == SyncErrorCode::Network => {}
+            Err(_) => {
+                // Free the reserved space; the session would expire anyway.
+                let _ = self.auth.http().delete(&upload_url).send().await;
+                upload.finish();
+            }
+            Ok(()) => upload.finish(),
+        }
+        result
+    }
+
+    pub(crate) async fn delete(&self, path: &str) -> Result<(), SyncError> {
+        if normalize(path).is_empty() {
+            return Err(SyncError::new(
+                SyncErrorCode::Unknown,
+                "refusing to delete the app folder",
+            ));
+        }
+        let url = item_url(path);
+        let response = self.auth.send(|| Ok(self.auth.http().delete(&url))).await?;
+        let status = response.status();
+        if !status.is_success() && status != reqwest::StatusCode::NOT_FOUND {
+            return Err(graph_error(response, "delete").await);
+        }
+        Ok(())
+    }
+
+    /// The app folder's drive path, e.g. `/drive/root:/Apps/Readest`.
+    async fn approot_path(&self) -> Result<String, SyncError> {
+        let root: DriveItem = self
+            .get_json(APPROOT, &[("$select", "name,parentReference")], "stat")
+            .await?;
+        let parent = root
+            .parent_reference
+            .and_then(|p| p.path)
+            .unwrap_or_default();
+        Ok(format!("{}/{}", percent_decode(&parent), root.name))
+    }
+
+    /// Logical path of folder `id`, memoised in `known`. Delta items omit
+    /// `parentReference.path`, so parents are looked up by id.
+    async fn folder_path(
+        &self,
+        id: &str,
+        root: &str,
+        known: &mut HashMap<String, Option<String>>,
+    ) -> Result<Option<String>, SyncError> {
+        if let Some(path) = known.get(id) {
+            return Ok(path.clone());
+        }
+        let url = format!("{DRIVE}/items/{id}");
+        let folder: DriveItem = self
+            .get_json(&url, &[("$select", "name,parentReference")], "stat")
+            .await?;
+        let path = match folder.parent_reference.and_then(|p| p.path) {
+            Some(parent) => relative_to(
+                &format!("{}/{}", percent_decode(&parent), folder.name),
+                root,
+            ),
+            // The drive root itself.
+            None => None,
+        };
+        known.insert(id.to_string(), path.clone());
+        Ok(path)
+    }
+
+    /// Changes since `cursor` (a Graph delta link). Without one, returns
+    /// the link for "now". An expired link fails as `CONFLICT` with reason
+    /// `resyncRequired`.
+    pub(crate) async fn changes(&self, cursor: Option<&str>) -> Result<RemoteChanges, SyncError> {
+        let feed = match self.drive_type().await? {
+            DriveType::Personal => format!("{APPROOT}/delta"),
+            DriveType::Business => format!("{DRIVE}/root/delta"),
+        };
+        let (mut link, mut query): (String, &[(&str, &str)]) = match cursor {
+            // The link carries the bearer token, so only follow Graph URLs.
+            Some(cursor) if cursor.starts_with(GRAPH_BASE) => (cursor.to_string(), &[]),
+            Some(_) => {
+                return Err(SyncError::new(
+                    SyncErrorCode::Unknown,
+                    "cursor is not a Graph delta link",
+                ))
+            }
+            None => (feed, &[("token", "latest")]),
+        };
+        let root = self.approot_path().await?;
+        let mut known: HashMap<String, Option<String>> = HashMap::new();
+        let mut changes = Vec::new();
+        loop {
+            let page: ItemPage = self.get_json(&link, query, "changes").await?;
+            query = &[];
+            for item in page.value {
+                if item.deleted.is_some() {
+                    changes.push(RemoteChange {
+                        id: item.id,
+                        path: None,
+                        removed: true,
+                        is_directory: item.folder.is_some(),
+                        size: None,
+                        last_modified: None,
+                    });
+                    continue;
+                }
+                let Some(parent_id) = item.parent_reference.as_ref().and_then(|p| p.id.clone())
+                else {
+                    continue;
+                };
+                let Some(parent) = self.folder_path(&parent_id, &root, &mut known).await? else {
+                    continue;
+                };
+                let path = format!("{parent}/{}", item.name);
+                if item.is_folder() {
+                    known.insert(item.id.clone(), Some(path.clone()));
+                }
+                changes.push(RemoteChange {
+                    is_directory: item.is_folder(),
+                    id: item.id,
+                    path: Some(path),
+                    removed: false,
+                    size: item.size,
+                    last_modified: item.last_modified_date_time,
+                });
+            }
+            match (page.next_link, page.delta_link) {
+                (Some(next), _) => link = next,
+                (None, Some(delta)) => {
+                    return Ok(RemoteChanges {
+                        changes,
+                        cursor: delta,
+                    })
+                }
+                (None, None) => {
+                    return Err(SyncError::new(
+                        SyncErrorCode::Unknown,
+                        "delta feed ended without a link",
+                    ))
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn quota(&self) -> Result<StorageQuota, SyncError> {
+        let quota = self
+            .drive("quota")
+            .await?
+            .quota
+            .ok_or_else(|| SyncError::new(SyncErrorCode::Unknown, "drive reported no quota"))?;
+        Ok(StorageQuota {
+            used: quota.used.unwrap_or(0),
+            limit: quota.total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_like_encode_uri_component() {
+        assert_eq!(
+            encode_path("/Readest/books/It's a book (1).epub"),
+            "Readest/books/It's%20a%20book%20(1).epub"
+        );
+        assert_eq!(encode_path("/a:b/ü"), "a%3Ab/%C3%BC");
+        assert_eq!(item_url("/"), APPROOT);
+        assert_eq!(children_url(""), format!("{APPROOT}/children"));
+    }
+
+    #[test]
+    fn resolves_paths_relative_to_the_app_folder() {
+        let root = "/drive/root:/Apps/Readest";
+        assert_eq!(
+            relative_to(
+                &percent_decode("/drive/root:/Apps/Readest/My%20Books"),
+                root
+            ),
+            Some("/My Books".to_string())
+        );
+        assert_eq!(relative_to(root, root), Some(String::new()));
+        assert_eq!(relative_to("/drive/root:/Apps/ReadestOld", root), None);
+        assert_eq!(relative_to("/drive/root:/Documents", root), None);
+    }
+
+    #[test]
+    fn reads_upload_ranges() {
+        assert_eq!(next_offset(&["26214400-".to_string()]), Some(26_214_400));
+        assert_eq!(next_offset(&[]), None);
+        assert_eq!(CHUNK_SIZE % (320 * 1024), 0);
+        assert_eq!(content_range(0, 10, 20), "bytes 0-9/20");
+    }
+
+    #[test]
+    fn classifies_graph_errors() {
+        let body = |code: &str| format!(r#"{{"error":{{"code":"{code}","message":"m"}}}}"#);
+        let throttled = classify_error(403, &body("activityLimitReached"), "list");
+        assert_eq!(throttled.code, SyncErrorCode::Network);
+        let full = classify_error(507, &body("insufficientStorage"), "upload");
+        assert_eq!(full.code, SyncErrorCode::Unknown);
+        assert_eq!(full.reason.as_deref(), Some("insufficientStorage"));
+        let resync = classify_error(410, &body("resyncRequired"), "changes");
+        assert_eq!(resync.code, SyncErrorCode::Conflict);
+        let denied = classify_error(403, &body("accessDenied"), "list");
+        assert_eq!(denied.code, SyncErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn reads_business_drive_types() {
+        let drive: Drive = serde_json::from_str(r#"{"driveType":"documentLibrary"}"#).unwrap();
+        assert_eq!(drive.drive_type, Some(DriveType::Business));
+        let target: OneDriveTarget = serde_json::from_str(r#"{"clientId":"c"}"#).unwrap();
+        assert_eq!(target.token_key, DEFAULT_TOKEN_KEY);
+        assert_eq!(target.drive_type, None);
+    }
+}
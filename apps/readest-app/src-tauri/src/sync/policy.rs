@@ -0,0 +1,322 @@
+//! Selective sync: which kinds of data, and which books, leave the device.
+//!
+//! Uploads are classified by where they land in the frozen remote layout
+//! (`services/sync/file/layout.ts`): the library index, and per book its
+//! file, cover, `config.json` (reading progress and annotations) and TTS
+//! packs. A [`SyncPolicy`] switches each kind on or off, caps the size of
+//! book files, and carries per-book [`BookRule`]s that override both.
+//!
+//! `sync_upload` evaluates the policy before touching the network and
+//! reports a skipped file instead of failing. The small JSON writes the TS
+//! providers make themselves are checked up front with
+//! [`sync_policy_evaluate`]. Downloads are never filtered: the policy says
+//! what this device shares, not what it accepts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::segments;
+use super::transfer::{read_json, write_json};
+
+const POLICY_FILENAME: &str = "sync-policy.json";
+const BASE_DIR: &str = "Readest";
+const BOOKS_DIR: &str = "books";
+const LIBRARY_FILE: &str = "library.json";
+const CONFIG_FILE: &str = "config.json";
+const COVER_FILE: &str = "cover.png";
+const TTS_DIR: &str = "tts";
+
+/// What a remote path holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataKind {
+    Library,
+    BookFile,
+    Cover,
+    Config,
+    Tts,
+    /// Outside the layout; never filtered.
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BookRule {
+    /// Upload the book file even when book files are off or it's over the
+    /// size cap.
+    Always,
+    /// Share progress and annotations, but never the file.
+    NoFile,
+    /// Nothing about this book leaves the device.
+    Never,
+}
+
+/// Why the policy kept a file local.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Uploads of this kind of data are off.
+    TypeDisabled,
+    /// A per-book rule excludes it.
+    BookExcluded,
+    /// The book file is over `maxBookSize`.
+    TooLarge,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPolicy {
+    #[serde(default = "enabled")]
+    pub book_files: bool,
+    /// Book files larger than this stay local; `None` or 0 is no limit.
+    #[serde(default)]
+    pub max_book_size: Option<u64>,
+    #[serde(default = "enabled")]
+    pub covers: bool,
+    /// Each book's `config.json`: reading progress and annotations.
+    #[serde(default = "enabled")]
+    pub progress_and_annotations: bool,
+    #[serde(default = "enabled")]
+    pub tts: bool,
+    /// Overrides by book hash.
+    #[serde(default)]
+    pub books: HashMap<String, BookRule>,
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self {
+            book_files: true,
+            max_book_size: None,
+            covers: true,
+            progress_and_annotations: true,
+            tts: true,
+            books: HashMap::new(),
+        }
+    }
+}
+
+/// Split a logical path into its kind and, for per-book data, the hash.
+/// The last `Readest/books` pair wins, so a root path that itself contains
+/// `Readest` still classifies correctly.
+fn classify(path: &str) -> (DataKind, Option<&str>) {
+    let parts: Vec<&str> = segments(path).collect();
+    if parts.len() >= 2 && parts[parts.len() - 2..] == [BASE_DIR, LIBRARY_FILE] {
+        return (DataKind::Library, None);
+    }
+    let Some(books) = parts
+        .windows(2)
+        .rposition(|pair| pair == [BASE_DIR, BOOKS_DIR])
+    else {
+        return (DataKind::Other, None);
+    };
+    let (Some(&hash), rest) = (parts.get(books + 2), parts.get(books + 3..).unwrap_or(&[])) else {
+        return (DataKind::Other, None);
+    };
+    let kind = match rest {
+        [CONFIG_FILE] => DataKind::Config,
+        [COVER_FILE] => DataKind::Cover,
+        [TTS_DIR, _, ..] => DataKind::Tts,
+        [_] => DataKind::BookFile,
+        _ => return (DataKind::Other, None),
+    };
+    (kind, Some(hash))
+}
+
+impl SyncPolicy {
+    /// Whether `path`, `size` bytes, may be uploaded; the reason if not.
+    pub fn evaluate(&self, path: &str, size: u64) -> Option<SkipReason> {
+        let (kind, hash) = classify(path);
+        let rule = hash.and_then(|hash| self.books.get(hash)).copied();
+        match (kind, rule) {
+            (DataKind::Library | DataKind::Other, _) => None,
+            (_, Some(BookRule::Never)) => Some(SkipReason::BookExcluded),
+            (DataKind::BookFile, Some(BookRule::NoFile)) => Some(SkipReason::BookExcluded),
+            (DataKind::BookFile, Some(BookRule::Always)) => None,
+            (DataKind::BookFile, _) if !self.book_files => Some(SkipReason::TypeDisabled),
+            (DataKind::BookFile, _) => self
+                .max_book_size
+                .filter(|&max| max > 0 && size > max)
+                .map(|_| SkipReason::TooLarge),
+            (DataKind::Cover, _) if !self.covers => Some(SkipReason::TypeDisabled),
+            (DataKind::Config, _) if !self.progress_and_annotations => {
+                Some(SkipReason::TypeDisabled)
+            }
+            (DataKind::Tts, _) if !self.tts => Some(SkipReason::TypeDisabled),
+            _ => None,
+        }
+    }
+}
+
+pub struct SyncPolicyStore {
+    path: Option<PathBuf>,
+    policy: Mutex<SyncPolicy>,
+}
+
+impl SyncPolicyStore {
+    pub fn load(app: &AppHandle) -> Self {
+        let path = app
+            .path()
+            .app_config_dir()
+            .ok()
+            .map(|dir| dir.join(POLICY_FILENAME));
+        Self {
+            policy: Mutex::new(read_json(path.as_ref())),
+            path,
+        }
+    }
+
+    pub(crate) fn evaluate(&self, path: &str, size: u64) -> Option<SkipReason> {
+        self.policy.lock().unwrap().evaluate(path, size)
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SyncPolicy)) {
+        let mut policy = self.policy.lock().unwrap();
+        change(&mut policy);
+        write_json(self.path.as_ref(), &*policy);
+    }
+}
+
+/// A planned upload to check against the policy.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedUpload {
+    pub path: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[tauri::command]
+pub fn get_sync_policy(store: State<'_, SyncPolicyStore>) -> SyncPolicy {
+    store.policy.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_sync_policy(store: State<'_, SyncPolicyStore>, policy: SyncPolicy) {
+    store.update(|current| *current = policy);
+}
+
+/// Set or, with `None`, clear the rule for one book.
+#[tauri::command]
+pub fn set_sync_book_rule(
+    store: State<'_, SyncPolicyStore>,
+    book_hash: String,
+    rule: Option<BookRule>,
+) {
+    store.update(|policy| match rule {
+        Some(rule) => {
+            policy.books.insert(book_hash, rule);
+        }
+        None => {
+            policy.books.remove(&book_hash);
+        }
+    });
+}
+
+/// Check uploads before making them; one entry per input, `null` when it
+/// may go.
+#[tauri::command]
+pub fn sync_policy_evaluate(
+    store: State<'_, SyncPolicyStore>,
+    uploads: Vec<PlannedUpload>,
+) -> Vec<Option<SkipReason>> {
+    uploads
+        .iter()
+        .map(|upload| store.evaluate(&upload.path, upload.size))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = "/Readest/books/abc/Dune.epub";
+
+    #[test]
+    fn classifies_the_remote_layout() {
+        assert_eq!(classify("/Readest/library.json"), (DataKind::Library, None));
+        assert_eq!(classify(BOOK), (DataKind::BookFile, Some("abc")));
+        assert_eq!(
+            classify("/sync/Readest/books/abc/config.json"),
+            (DataKind::Config, Some("abc"))
+        );
+        assert_eq!(
+            classify("/Readest/books/abc/cover.png"),
+            (DataKind::Cover, Some("abc"))
+        );
+        assert_eq!(
+            classify("/Readest/books/abc/tts/1-k.mp3"),
+            (DataKind::Tts, Some("abc"))
+        );
+        assert_eq!(
+            classify("/Readest/Readest/books/abc/x.pdf"),
+            (DataKind::BookFile, Some("abc"))
+        );
+        assert_eq!(classify("/Readest/books/abc"), (DataKind::Other, None));
+        assert_eq!(classify("/elsewhere/a.epub"), (DataKind::Other, None));
+    }
+
+    #[test]
+    fn defaults_upload_everything() {
+        let policy: SyncPolicy = serde_json::from_str("{}").unwrap();
+        assert_eq!(policy, SyncPolicy::default());
+        assert_eq!(policy.evaluate(BOOK, u64::MAX), None);
+    }
+
+    #[test]
+    fn type_toggles_and_size_cap() {
+        let policy = SyncPolicy {
+            book_files: true,
+            max_book_size: Some(1000),
+            covers: false,
+            ..SyncPolicy::default()
+        };
+        assert_eq!(policy.evaluate(BOOK, 1000), None);
+        assert_eq!(policy.evaluate(BOOK, 1001), Some(SkipReason::TooLarge));
+        assert_eq!(
+            policy.evaluate("/Readest/books/abc/cover.png", 1),
+            Some(SkipReason::TypeDisabled)
+        );
+        assert_eq!(policy.evaluate("/Readest/books/abc/config.json", 1), None);
+
+        let annotations_only = SyncPolicy {
+            book_files: false,
+            ..SyncPolicy::default()
+        };
+        assert_eq!(
+            annotations_only.evaluate(BOOK, 1),
+            Some(SkipReason::TypeDisabled)
+        );
+        assert_eq!(
+            annotations_only.evaluate("/Readest/books/abc/config.json", 1),
+            None
+        );
+        assert_eq!(annotations_only.evaluate("/Readest/library.json", 1), None);
+    }
+
+    #[test]
+    fn book_rules_override_types() {
+        let mut policy = SyncPolicy {
+            book_files: false,
+            max_book_size: Some(10),
+            ..SyncPolicy::default()
+        };
+        policy.books.insert("abc".into(), BookRule::Always);
+        assert_eq!(policy.evaluate(BOOK, 1_000_000), None);
+
+        policy.books.insert("abc".into(), BookRule::NoFile);
+        let config = "/Readest/books/abc/config.json";
+        assert_eq!(policy.evaluate(BOOK, 1), Some(SkipReason::BookExcluded));
+        assert_eq!(policy.evaluate(config, 1), None);
+
+        policy.books.insert("abc".into(), BookRule::Never);
+        assert_eq!(policy.evaluate(config, 1), Some(SkipReason::BookExcluded));
+        assert_eq!(policy.evaluate("/Readest/library.json", 1), None);
+    }
+}
@@ -0,0 +1,673 @@
+//! Durable outbound queue for sync mutations made while offline.
+//!
+//! The engine pushes each outbound change (a file upload, a small JSON
+//! write, a delete) through [`sync_queue_enqueue`] instead of calling the
+//! backend directly. Operations are journaled in SQLite (`sync-queue.db` in
+//! the app data dir) before anything is sent, so a change made on a plane
+//! survives the app being killed, and a background worker drains the queue
+//! in order whenever the device is online.
+//!
+//! A newer change to the same remote path replaces a pending one; only the
+//! latest state of a file is worth sending. Failures back off
+//! exponentially: `NETWORK` errors (offline, 5xx, "Wi-Fi only") retry
+//! forever, anything else gives up after [`MAX_ATTEMPTS`] and waits in the
+//! queue, marked failed, for [`sync_queue_retry`] or [`sync_queue_discard`].
+//!
+//! Every change to the queue is broadcast as [`QUEUE_EVENT`] with the
+//! counts the UI shows; [`sync_queue_list`] has the details. Each operation
+//! also reports itself on `sync://status`, keyed by its queue id.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{ipc::Channel, AppHandle, Emitter, Manager, State};
+use tauri_plugin_native_bridge::NativeBridgeExt;
+use tokio::sync::Notify;
+
+use super::policy::SkipReason;
+use super::status::{ItemState, ItemStatus, Operation};
+use super::{allowed, upload_file, Backend, SyncError, SyncErrorCode, SyncTarget};
+
+pub const QUEUE_EVENT: &str = "sync://queue";
+const DATABASE_FILENAME: &str = "sync-queue.db";
+/// Scratch space for queued JSON writes on their way out.
+const SCRATCH_DIR: &str = "sync-queue";
+/// Attempts before a non-network failure stops retrying on its own.
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+/// How often to look again with nothing due, or while offline.
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS ops (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    provider TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    path TEXT NOT NULL,
+    target TEXT NOT NULL,
+    mutation TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_ms INTEGER NOT NULL,
+    failed INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_ms INTEGER NOT NULL,
+    UNIQUE (scope, path)
+);";
+
+/// One outbound change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Mutation {
+    /// Stream a local file, as `sync_upload` does.
+    #[serde(rename_all = "camelCase")]
+    Upload {
+        local_path: String,
+        remote_path: String,
+        #[serde(default)]
+        encrypted: bool,
+    },
+    /// Write small text content (an index, a book's `config.json`).
+    #[serde(rename_all = "camelCase")]
+    Write {
+        remote_path: String,
+        content: String,
+        #[serde(default)]
+        encrypted: bool,
+    },
+    Delete {
+        path: String,
+    },
+}
+
+impl Mutation {
+    fn path(&self) -> &str {
+        match self {
+            Self::Upload { remote_path, .. } | Self::Write { remote_path, .. } => remote_path,
+            Self::Delete { path } => path,
+        }
+    }
+
+    fn operation(&self) -> Operation {
+        match self {
+            Self::Upload { .. } => Operation::Upload,
+            Self::Write { .. } => Operation::Write,
+            Self::Delete { .. } => Operation::Delete,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Upload { .. } => "upload",
+            Self::Write { .. } => "write",
+            Self::Delete { .. } => "delete",
+        }
+    }
+}
+
+/// A queued operation, as the UI lists it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOp {
+    pub id: i64,
+    pub provider: String,
+    pub kind: String,
+    pub path: String,
+    pub attempts: u32,
+    /// Unix ms of the next try; meaningless once `failed`.
+    pub next_attempt_at: i64,
+    pub failed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+/// Payload of [`QUEUE_EVENT`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueState {
+    /// Waiting to be sent, including ones backing off.
+    pub pending: u32,
+    /// Gave up; need a retry or discard.
+    pub failed: u32,
+    pub online: bool,
+    /// Unix ms of the earliest scheduled retry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<i64>,
+}
+
+/// A pending operation as the worker runs it.
+#[derive(Debug)]
+struct Row {
+    id: i64,
+    version: i64,
+    provider: String,
+    attempts: u32,
+    target: String,
+    mutation: Mutation,
+}
+
+#[derive(Debug)]
+enum Next {
+    Due(Row),
+    At(i64),
+    Idle,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn db_error(e: rusqlite::Error) -> SyncError {
+    SyncError::new(SyncErrorCode::Unknown, format!("sync queue: {e}"))
+}
+
+/// Delay before attempt `attempts + 1`: 2 s, 4 s, 8 s, … capped at 15 min.
+fn retry_delay(attempts: u32) -> Duration {
+    BASE_RETRY_DELAY
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// The SQLite journal. Kept free of Tauri so it can be tested on its own.
+struct Journal {
+    conn: Connection,
+}
+
+impl Journal {
+    fn open(path: Option<&PathBuf>) -> Result<Self, SyncError> {
+        let conn = match path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                Connection::open(path)
+            }
+            None => Connection::open_in_memory(),
+        }
+        .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Queue `mutation`, replacing whatever was pending for the same path.
+    fn push(
+        &self,
+        provider: &str,
+        scope: &str,
+        target: &str,
+        mutation: &Mutation,
+        now: i64,
+    ) -> Result<i64, SyncError> {
+        let json = serde_json::to_string(mutation)
+            .map_err(|e| SyncError::new(SyncErrorCode::Unknown, format!("sync queue: {e}")))?;
+        self.conn
+            .query_row(
+                "INSERT INTO ops (provider, scope, path, target, mutation, next_attempt_ms, created_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT (scope, path) DO UPDATE SET
+                    provider = excluded.provider, target = excluded.target,
+                    mutation = excluded.mutation, version = version + 1, attempts = 0,
+                    next_attempt_ms = excluded.next_attempt_ms, failed = 0, last_error = NULL
+                 RETURNING id",
+                params![provider, scope, mutation.path(), target, json, now],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+    }
+
+    /// The oldest operation due at `now`, or when the next one will be.
+    fn next(&self, now: i64) -> Result<Next, SyncError> {
+        let due = self
+            .conn
+            .query_row(
+                "SELECT id, version, provider, attempts, target, mutation FROM ops
+                 WHERE failed = 0 AND next_attempt_ms <= ?1 ORDER BY id LIMIT 1",
+                params![now],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, u32>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(db_error)?;
+        if let Some((id, version, provider, attempts, target, mutation)) = due {
+            let Ok(mutation) = serde_json::from_str(&mutation) else {
+                // Written by a newer build; nothing this one can do with it.
+                self.discard(id)?;
+                return self.next(now);
+            };
+            return Ok(Next::Due(Row {
+                id,
+                version,
+                provider,
+                attempts,
+                target,
+                mutation,
+            }));
+        }
+        let at: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT MIN(next_attempt_ms) FROM ops WHERE failed = 0",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+        Ok(at.map_or(Next::Idle, Next::At))
+    }
+
+    /// Drop a finished operation, unless a newer change replaced it while
+    /// it was in flight.
+    fn complete(&self, row: &Row) -> Result<(), SyncError> {
+        self.conn
+            .execute(
+                "DELETE FROM ops WHERE id = ?1 AND version = ?2",
+                params![row.id, row.version],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Schedule the retry after a failed attempt, or give up on it; returns
+    /// whether it will be retried.
+    fn fail(&self, row: &Row, error: &SyncError, now: i64) -> Result<bool, SyncError> {
+        let attempts = row.attempts + 1;
+        let gave_up = error.code != SyncErrorCode::Network && attempts >= MAX_ATTEMPTS;
+        let next = now + retry_delay(attempts).as_millis() as i64;
+        self.conn
+            .execute(
+                "UPDATE ops SET attempts = ?3, next_attempt_ms = ?4, failed = ?5, last_error = ?6
+                 WHERE id = ?1 AND version = ?2",
+                params![row.id, row.version, attempts, next, gave_up, error.message],
+            )
+            .map_err(db_error)?;
+        Ok(!gave_up)
+    }
+
+    /// Make one operation, or every one, due now, including failed ones.
+    fn retry(&self, id: Option<i64>, now: i64) -> Result<(), SyncError> {
+        self.conn
+            .execute(
+                "UPDATE ops SET failed = 0, next_attempt_ms = ?2
+                 WHERE ?1 IS NULL OR id = ?1",
+                params![id, now],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn discard(&self, id: i64) -> Result<(), SyncError> {
+        self.conn
+            .execute("DELETE FROM ops WHERE id = ?1", params![id])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<QueuedOp>, SyncError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, provider, mutation, path, attempts, next_attempt_ms, failed,
+                        last_error, created_ms
+                 FROM ops ORDER BY id",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                let mutation: String = row.get(2)?;
+                let kind = serde_json::from_str::<Mutation>(&mutation)
+                    .map_or("unknown", |m| m.kind())
+                    .to_string();
+                Ok(QueuedOp {
+                    id: row.get(0)?,
+                    provider: row.get(1)?,
+                    kind,
+                    path: row.get(3)?,
+                    attempts: row.get(4)?,
+                    next_attempt_at: row.get(5)?,
+                    failed: row.get(6)?,
+                    last_error: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })
+            .map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    fn state(&self, online: bool) -> Result<QueueState, SyncError> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FILTER (WHERE failed = 0), COUNT(*) FILTER (WHERE failed = 1),
+                        MIN(next_attempt_ms) FILTER (WHERE failed = 0)
+                 FROM ops",
+                [],
+                |row| {
+                    Ok(QueueState {
+                        pending: row.get(0)?,
+                        failed: row.get(1)?,
+                        online,
+                        next_attempt_at: row.get(2)?,
+                    })
+                },
+            )
+            .map_err(db_error)
+    }
+}
+
+struct Inner {
+    app: AppHandle,
+    journal: Mutex<Journal>,
+    wake: Notify,
+}
+
+#[derive(Clone)]
+pub struct SyncQueue(Arc<Inner>);
+
+impl SyncQueue {
+    /// Open the journal and start draining it in the background.
+    pub fn start(app: &AppHandle) -> Self {
+        let path = app
+            .path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| dir.join(DATABASE_FILENAME));
+        let journal = Journal::open(path.as_ref()).unwrap_or_else(|e| {
+            log::warn!("Sync queue falls back to memory: {e}");
+            Journal::open(None).expect("in-memory SQLite opens")
+        });
+        let queue = Self(Arc::new(Inner {
+            app: app.clone(),
+            journal: Mutex::new(journal),
+            wake: Notify::new(),
+        }));
+        let worker = queue.clone();
+        tauri::async_runtime::spawn(async move { worker.run().await });
+        queue
+    }
+
+    fn journal(&self) -> std::sync::MutexGuard<'_, Journal> {
+        self.0.journal.lock().unwrap()
+    }
+
+    /// Whether the OS reports a connection; a platform that can't tell is
+    /// assumed online and left to the backends' own errors.
+    fn online(&self) -> bool {
+        self.0
+            .app
+            .native_bridge()
+            .get_network_status()
+            .map_or(true, |status| status.connected)
+    }
+
+    fn emit_state(&self) {
+        match self.journal().state(self.online()) {
+            Ok(state) => {
+                if let Err(e) = self.0.app.emit(QUEUE_EVENT, state) {
+                    log::warn!("Failed to emit sync queue state: {e}");
+                }
+            }
+            Err(e) => log::warn!("Failed to read sync queue state: {e}"),
+        }
+    }
+
+    fn wake(&self) {
+        self.emit_state();
+        self.0.wake.notify_one();
+    }
+
+    async fn run(self) {
+        loop {
+            let wait = self.drain().await;
+            let _ = tokio::time::timeout(wait, self.0.wake.notified()).await;
+        }
+    }
+
+    /// Send everything due; returns how long to sleep before looking again.
+    async fn drain(&self) -> Duration {
+        loop {
+            if !self.online() {
+                return IDLE_POLL;
+            }
+            let now = now_ms();
+            let next = self.journal().next(now);
+            let row = match next {
+                Ok(Next::Due(row)) => row,
+                Ok(Next::At(at)) => {
+                    return Duration::from_millis((at - now).max(0) as u64).min(IDLE_POLL)
+                }
+                Ok(Next::Idle) => return IDLE_POLL,
+                Err(e) => {
+                    log::warn!("Failed to read sync queue: {e}");
+                    return IDLE_POLL;
+                }
+            };
+            let status = ItemStatus::new(
+                &self.0.app,
+                &row.provider,
+                row.mutation.operation(),
+                row.mutation.path(),
+            )
+            .queued(row.id);
+            let result = self.perform(&row, &status).await;
+            let journal = self.journal();
+            let recorded = match &result {
+                Ok(_) => journal.complete(&row).map(|()| false),
+                Err(error) => {
+                    log::info!("Queued sync operation {} failed: {error}", row.id);
+                    journal.fail(&row, error, now_ms())
+                }
+            };
+            drop(journal);
+            let will_retry = recorded.unwrap_or_else(|e| {
+                log::warn!("Failed to update sync queue: {e}");
+                false
+            });
+            status.uploaded(&result, will_retry);
+            self.emit_state();
+        }
+    }
+
+    async fn perform(
+        &self,
+        row: &Row,
+        status: &ItemStatus,
+    ) -> Result<Option<SkipReason>, SyncError> {
+        let app = &self.0.app;
+        let target: SyncTarget = serde_json::from_str(&row.target)
+            .map_err(|e| SyncError::new(SyncErrorCode::Unknown, format!("invalid target: {e}")))?;
+        // Progress only goes out as status events.
+        let progress = || status.track(Channel::new(|_| Ok(())));
+        match &row.mutation {
+            Mutation::Upload {
+                local_path,
+                remote_path,
+                encrypted,
+            } => upload_file(app, target, local_path, remote_path, *encrypted, progress()).await,
+            Mutation::Write {
+                remote_path,
+                content,
+                encrypted,
+            } => {
+                let dir = app
+                    .path()
+                    .app_cache_dir()
+                    .map_err(|e| SyncError::new(SyncErrorCode::Unknown, e.to_string()))?
+                    .join(SCRATCH_DIR);
+                std::fs::create_dir_all(&dir).map_err(|e| SyncError::io("create dir", e))?;
+                let scratch = dir.join(format!("{}-{}", row.id, row.version));
+                std::fs::write(&scratch, content).map_err(|e| SyncError::io("write", e))?;
+                let scratch = scratch.to_string_lossy().into_owned();
+                let result =
+                    upload_file(app, target, &scratch, remote_path, *encrypted, progress()).await;
+                let _ = std::fs::remove_file(&scratch);
+                result
+            }
+            Mutation::Delete { path } => Backend::connect(app, target)?
+                .delete(path)
+                .await
+                .map(|()| None),
+        }
+    }
+}
+
+/// Queue `mutation` against `target` (a `SyncTarget`, as `sync_upload`
+/// takes it) and return its id.
+#[tauri::command]
+pub fn sync_queue_enqueue(
+    app: AppHandle,
+    queue: State<'_, SyncQueue>,
+    target: serde_json::Value,
+    mutation: Mutation,
+) -> Result<i64, SyncError> {
+    let parsed: SyncTarget = serde_json::from_value(target.clone())
+        .map_err(|e| SyncError::new(SyncErrorCode::Unknown, format!("invalid target: {e}")))?;
+    if let Mutation::Upload { local_path, .. } = &mutation {
+        allowed(&app, local_path)?;
+    }
+    let id = queue.journal().push(
+        parsed.provider(),
+        &parsed.scope(),
+        &target.to_string(),
+        &mutation,
+        now_ms(),
+    )?;
+    ItemStatus::new(
+        &app,
+        parsed.provider(),
+        mutation.operation(),
+        mutation.path(),
+    )
+    .queued(id)
+    .emit(ItemState::Queued);
+    queue.wake();
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn sync_queue_list(queue: State<'_, SyncQueue>) -> Result<Vec<QueuedOp>, SyncError> {
+    queue.journal().list()
+}
+
+#[tauri::command]
+pub fn sync_queue_state(queue: State<'_, SyncQueue>) -> Result<QueueState, SyncError> {
+    let online = queue.online();
+    queue.journal().state(online)
+}
+
+/// Try one operation, or with `None` every operation, right away; call it
+/// when connectivity returns to skip the remaining backoff.
+#[tauri::command]
+pub fn sync_queue_retry(queue: State<'_, SyncQueue>, id: Option<i64>) -> Result<(), SyncError> {
+    queue.journal().retry(id, now_ms())?;
+    queue.wake();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn sync_queue_discard(queue: State<'_, SyncQueue>, id: i64) -> Result<(), SyncError> {
+    queue.journal().discard(id)?;
+    queue.emit_state();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(path: &str) -> Mutation {
+        Mutation::Upload {
+            local_path: "/tmp/book.epub".into(),
+            remote_path: path.into(),
+            encrypted: false,
+        }
+    }
+
+    fn due(journal: &Journal, now: i64) -> Row {
+        match journal.next(now).unwrap() {
+            Next::Due(row) => row,
+            other => panic!("expected a due operation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn newer_changes_replace_pending_ones() {
+        let journal = Journal::open(None).unwrap();
+        let a = journal.push("s3", "s", "{}", &upload("/a"), 0).unwrap();
+        journal.push("s3", "s", "{}", &upload("/b"), 0).unwrap();
+        let delete = Mutation::Delete { path: "/a".into() };
+        assert_eq!(journal.push("s3", "s", "{}", &delete, 0).unwrap(), a);
+        // Same path under another account is a separate operation.
+        journal.push("s3", "other", "{}", &upload("/a"), 0).unwrap();
+
+        let list = journal.list().unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[0].kind, "delete");
+        assert_eq!(due(&journal, 0).mutation, delete);
+    }
+
+    #[test]
+    fn in_flight_operations_replaced_meanwhile_stay_queued() {
+        let journal = Journal::open(None).unwrap();
+        journal.push("s3", "s", "{}", &upload("/a"), 0).unwrap();
+        let running = due(&journal, 0);
+        journal.push("s3", "s", "{}", &upload("/a"), 1).unwrap();
+        journal.complete(&running).unwrap();
+        assert_eq!(journal.list().unwrap().len(), 1);
+        journal.complete(&due(&journal, 1)).unwrap();
+        assert!(matches!(journal.next(1).unwrap(), Next::Idle));
+    }
+
+    #[test]
+    fn failures_back_off_and_non_network_ones_give_up() {
+        let journal = Journal::open(None).unwrap();
+        journal.push("s3", "s", "{}", &upload("/a"), 0).unwrap();
+        let offline = SyncError::new(SyncErrorCode::Network, "offline");
+        assert!(journal.fail(&due(&journal, 0), &offline, 0).unwrap());
+        assert!(matches!(journal.next(0).unwrap(), Next::At(2_000)));
+
+        let denied = SyncError::new(SyncErrorCode::AuthFailed, "denied");
+        for attempt in 1..MAX_ATTEMPTS {
+            let now = 100_000_000 * i64::from(attempt);
+            let will_retry = journal.fail(&due(&journal, now), &denied, now).unwrap();
+            assert_eq!(will_retry, attempt + 1 < MAX_ATTEMPTS);
+        }
+        assert!(matches!(journal.next(i64::MAX).unwrap(), Next::Idle));
+        let state = journal.state(true).unwrap();
+        assert_eq!((state.pending, state.failed), (0, 1));
+        assert_eq!(
+            journal.list().unwrap()[0].last_error.as_deref(),
+            Some("denied")
+        );
+
+        journal.retry(None, 5).unwrap();
+        assert_eq!(due(&journal, 5).attempts, MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn retry_delay_doubles_and_caps() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(8));
+        assert_eq!(retry_delay(40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn mutations_use_kind_tags() {
+        let mutation: Mutation =
+            serde_json::from_str(r#"{"kind":"write","remotePath":"/x","content":"{}"}"#).unwrap();
+        assert_eq!(mutation.path(), "/x");
+        assert_eq!(mutation.kind(), "write");
+    }
+}
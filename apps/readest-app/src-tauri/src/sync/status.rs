@@ -0,0 +1,258 @@
+//! Per-item sync status, broadcast as [`STATUS_EVENT`].
+//!
+//! Every upload, download, delete and queued write reports where it is:
+//! `queued` when the offline queue takes it, `transferring` with bytes and
+//! percent as it moves, then `done`, `skipped` (the selective sync policy
+//! kept it local) or `error` with the [`SyncError`] that caused it. Events
+//! carry the provider, the operation and the remote path, plus the queue id
+//! for queued work, so the UI can key a row per item and show real
+//! progress instead of a spinner.
+//!
+//! Progress is tapped from the `Channel<ProgressPayload>` each transfer
+//! already reports to (see [`ItemStatus::track`]), so the backends need no
+//! changes, and is throttled to [`PROGRESS_INTERVAL`] per item. Uploads
+//! also drive the taskbar progress bar.
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::{AppHandle, Emitter};
+
+use super::policy::SkipReason;
+use super::SyncError;
+use crate::taskbar_progress;
+use crate::transfer_file::ProgressPayload;
+
+pub const STATUS_EVENT: &str = "sync://status";
+/// Minimum spacing of `transferring` events for one item.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+    Upload,
+    Download,
+    Delete,
+    Write,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ItemState {
+    Queued,
+    #[serde(rename_all = "camelCase")]
+    Transferring {
+        transferred: u64,
+        total: u64,
+        /// Absent while the total size is unknown.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        percent: Option<u8>,
+    },
+    Done,
+    Skipped {
+        reason: SkipReason,
+    },
+    #[serde(rename_all = "camelCase")]
+    Error {
+        error: SyncError,
+        /// The offline queue will try again on its own.
+        will_retry: bool,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusEvent<'a> {
+    provider: &'a str,
+    operation: Operation,
+    path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<i64>,
+    #[serde(flatten)]
+    state: &'a ItemState,
+}
+
+fn percent(transferred: u64, total: u64) -> Option<u8> {
+    (total > 0).then(|| (transferred.min(total) * 100 / total) as u8)
+}
+
+/// Whether a progress update should go out: the first one, the last one,
+/// and otherwise at most one per interval.
+fn due(last: &mut Option<Instant>, now: Instant, transferred: u64, total: u64) -> bool {
+    let finished = total > 0 && transferred >= total;
+    if !finished && last.is_some_and(|at| now.duration_since(at) < PROGRESS_INTERVAL) {
+        return false;
+    }
+    *last = Some(now);
+    true
+}
+
+/// Status reporter for one item.
+#[derive(Clone)]
+pub(crate) struct ItemStatus {
+    app: AppHandle,
+    provider: String,
+    operation: Operation,
+    path: String,
+    queue_id: Option<i64>,
+    last_progress: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ItemStatus {
+    pub(crate) fn new(app: &AppHandle, provider: &str, operation: Operation, path: &str) -> Self {
+        Self {
+            app: app.clone(),
+            provider: provider.to_string(),
+            operation,
+            path: path.to_string(),
+            queue_id: None,
+            last_progress: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn queued(mut self, id: i64) -> Self {
+        self.queue_id = Some(id);
+        self
+    }
+
+    pub(crate) fn emit(&self, state: ItemState) {
+        let event = StatusEvent {
+            provider: &self.provider,
+            operation: self.operation,
+            path: &self.path,
+            queue_id: self.queue_id,
+            state: &state,
+        };
+        if let Err(e) = self.app.emit(STATUS_EVENT, event) {
+            log::warn!("Failed to emit sync status: {e}");
+        }
+    }
+
+    fn progress(&self, transferred: u64, total: u64) {
+        if due(
+            &mut self.last_progress.lock().unwrap(),
+            Instant::now(),
+            transferred,
+            total,
+        ) {
+            self.emit(ItemState::Transferring {
+                transferred,
+                total,
+                percent: percent(transferred, total),
+            });
+            if self.operation == Operation::Upload && total > 0 {
+                let fraction = transferred.min(total) as f64 / total as f64;
+                taskbar_progress::report(&self.app, &self.taskbar_task(), fraction);
+            }
+        }
+    }
+
+    /// Task id of the upload on the taskbar progress bar.
+    fn taskbar_task(&self) -> String {
+        format!("upload:{}:{}", self.provider, self.path)
+    }
+
+    /// A progress channel that reports to this item and passes every update
+    /// on to `forward`.
+    pub(crate) fn track(&self, forward: Channel<ProgressPayload>) -> Channel<ProgressPayload> {
+        let status = self.clone();
+        Channel::new(move |body| {
+            let InvokeResponseBody::Json(json) = &body else {
+                return Ok(());
+            };
+            let Ok(payload) = serde_json::from_str::<ProgressPayload>(json) else {
+                return Ok(());
+            };
+            status.progress(payload.progress(), payload.total());
+            forward.send(payload)
+        })
+    }
+
+    /// Report how an upload ended.
+    pub(crate) fn uploaded(
+        &self,
+        result: &Result<Option<SkipReason>, SyncError>,
+        will_retry: bool,
+    ) {
+        // An upload the offline queue will retry hasn't failed for good yet.
+        let failed = result.is_err() && !will_retry;
+        taskbar_progress::finish(&self.app, &self.taskbar_task(), failed);
+        match result {
+            Ok(None) => self.emit(ItemState::Done),
+            Ok(Some(reason)) => self.emit(ItemState::Skipped { reason: *reason }),
+            Err(error) => self.failed(error, will_retry),
+        }
+    }
+
+    /// Report how anything else ended.
+    pub(crate) fn finished(&self, result: &Result<(), SyncError>) {
+        match result {
+            Ok(()) => self.emit(ItemState::Done),
+            Err(error) => self.failed(error, false),
+        }
+    }
+
+    pub(crate) fn failed(&self, error: &SyncError, will_retry: bool) {
+        self.emit(ItemState::Error {
+            error: error.clone(),
+            will_retry,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::SyncErrorCode;
+
+    #[test]
+    fn percent_is_clamped_and_absent_without_total() {
+        assert_eq!(percent(0, 200), Some(0));
+        assert_eq!(percent(50, 200), Some(25));
+        assert_eq!(percent(300, 200), Some(100));
+        assert_eq!(percent(10, 0), None);
+    }
+
+    #[test]
+    fn progress_is_throttled_but_never_drops_the_end() {
+        let t0 = Instant::now();
+        let mut last = None;
+        assert!(due(&mut last, t0, 1, 100));
+        assert!(!due(&mut last, t0 + Duration::from_millis(100), 50, 100));
+        assert!(due(&mut last, t0 + Duration::from_millis(100), 100, 100));
+        assert!(due(&mut last, t0 + PROGRESS_INTERVAL * 2, 100, 0));
+    }
+
+    #[test]
+    fn events_flatten_the_state() {
+        let state = ItemState::Error {
+            error: SyncError::new(SyncErrorCode::Network, "offline"),
+            will_retry: true,
+        };
+        let event = StatusEvent {
+            provider: "gdrive",
+            operation: Operation::Upload,
+            path: "/Readest/books/abc/config.json",
+            queue_id: Some(3),
+            state: &state,
+        };
+        let json = serde_json::to_value(event).unwrap();
+        assert_eq!(json["state"], "error");
+        assert_eq!(json["operation"], "upload");
+        assert_eq!(json["queueId"], 3);
+        assert_eq!(json["error"]["code"], "NETWORK");
+        assert_eq!(json["willRetry"], true);
+
+        let json = serde_json::to_value(ItemState::Transferring {
+            transferred: 5,
+            total: 0,
+            percent: None,
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "state": "transferring", "transferred": 5, "total": 0 })
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Upload policy and resumable-upload bookkeeping for the sync backends.
+//!
+//! Every backend already sends large files in chunks and recovers from a
+//! failed chunk within one `sync_upload` call. This adds what a flaky
+//! mobile connection needs on top:
+//!
+//!   - a [`TransferPolicy`] the user sets once: an upload bandwidth cap and
+//!     "Wi-Fi only". The cap is shared by all uploads in flight, and request
+//!     bodies are paced in small slices so the link never sees a full-speed
+//!     burst. "Wi-Fi only" means unmetered as the OS reports it (see
+//!     `get_network_status` in the native bridge) and is checked before the
+//!     upload and before every chunk, so leaving Wi-Fi stops at a chunk
+//!     boundary with the session kept for later;
+//!   - a journal of open upload sessions (`sync-uploads.json` in the app
+//!     data dir). When an upload fails or the app is closed mid-transfer,
+//!     the next upload of the same file to the same place continues the
+//!     provider session instead of starting over. Entries are tied to the
+//!     file's size and modification time, so a changed file starts fresh.
+//!
+//! A policy refusal is a `NETWORK` error with reason `wifi_required`, which
+//! the engine treats like any other transient failure and retries later.
+
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{ipc::Channel, AppHandle, Manager, State};
+use tauri_plugin_native_bridge::NativeBridgeExt;
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+use super::{SyncError, SyncErrorCode};
+use crate::transfer_file::{ProgressPayload, TransferStats};
+
+const POLICY_FILENAME: &str = "sync-transfer.json";
+const JOURNAL_FILENAME: &str = "sync-uploads.json";
+/// Pacing granularity for capped uploads.
+const SLICE_SIZE: usize = 64 * 1024;
+/// Drive and Dropbox sessions last a week and Graph's a few days; past this
+/// the provider has most likely dropped it, so don't bother asking.
+const MAX_SESSION_AGE_MS: i64 = 3 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferPolicy {
+    /// Upload cap in bytes per second; `None` or 0 is unlimited.
+    #[serde(default)]
+    pub max_upload_rate: Option<u64>,
+    /// Only upload on unmetered networks.
+    #[serde(default)]
+    pub wifi_only: bool,
+}
+
+impl TransferPolicy {
+    fn rate(&self) -> Option<u64> {
+        self.max_upload_rate.filter(|&rate| rate > 0)
+    }
+}
+
+/// An open provider upload session, as much of it as resuming needs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PendingUpload {
+    /// Session URL (Drive, OneDrive), session id (Dropbox) or upload id (S3).
+    pub session: String,
+    /// Bytes the provider has acknowledged.
+    #[serde(default)]
+    pub offset: u64,
+    /// S3 part ETags, in part order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    modified_ms: i64,
+    #[serde(default)]
+    created_ms: i64,
+}
+
+struct Inner {
+    policy_path: Option<PathBuf>,
+    journal_path: Option<PathBuf>,
+    policy: Mutex<TransferPolicy>,
+    /// When the shared upload pipe is next free.
+    next_slot: Mutex<Instant>,
+    journal: Mutex<HashMap<String, PendingUpload>>,
+}
+
+#[derive(Clone)]
+pub struct TransferControl(Arc<Inner>);
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub(super) fn read_json<T: serde::de::DeserializeOwned + Default>(path: Option<&PathBuf>) -> T {
+    path.and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub(super) fn write_json<T: Serialize>(path: Option<&PathBuf>, value: &T) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(path, json) {
+                log::warn!("Failed to write {}: {e}", path.display());
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize {}: {e}", path.display()),
+    }
+}
+
+/// Reserve `bytes` of a `rate` bytes/s pipe that is next free at `next`,
+/// returning how long to wait before sending. Idle time isn't banked, so a
+/// quiet minute doesn't turn into a full-speed burst afterwards.
+fn reserve(next: &mut Instant, now: Instant, rate: u64, bytes: usize) -> Duration {
+    let start = (*next).max(now);
+    *next = start + Duration::from_secs_f64(bytes as f64 / rate as f64);
+    start - now
+}
+
+/// Whether a journal entry still describes `size`/`modified_ms` and is
+/// young enough to be worth resuming.
+fn is_resumable(entry: &PendingUpload, size: u64, modified_ms: i64, now_ms: i64) -> bool {
+    entry.size == size
+        && entry.modified_ms == modified_ms
+        && now_ms - entry.created_ms < MAX_SESSION_AGE_MS
+}
+
+impl TransferControl {
+    pub fn load(app: &AppHandle) -> Self {
+        let policy_path = app
+            .path()
+            .app_config_dir()
+            .ok()
+            .map(|dir| dir.join(POLICY_FILENAME));
+        let journal_path = app
+            .path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| dir.join(JOURNAL_FILENAME));
+        let mut journal: HashMap<String, PendingUpload> = read_json(journal_path.as_ref());
+        let now = now_ms();
+        journal.retain(|_, entry| now - entry.created_ms < MAX_SESSION_AGE_MS);
+        Self(Arc::new(Inner {
+            policy: Mutex::new(read_json(policy_path.as_ref())),
+            policy_path,
+            journal_path,
+            next_slot: Mutex::new(Instant::now()),
+            journal: Mutex::new(journal),
+        }))
+    }
+
+    fn policy(&self) -> TransferPolicy {
+        *self.0.policy.lock().unwrap()
+    }
+
+    /// Wait until `bytes` more may go out under the cap.
+    async fn pace(&self, bytes: usize) {
+        let Some(rate) = self.policy().rate() else {
+            return;
+        };
+        let wait = reserve(
+            &mut self.0.next_slot.lock().unwrap(),
+            Instant::now(),
+            rate,
+            bytes,
+        );
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn persist_journal(&self) {
+        let journal = self.0.journal.lock().unwrap().clone();
+        write_json(self.0.journal_path.as_ref(), &journal);
+    }
+}
+
+/// One upload under the policy, optionally journaled for resumption.
+pub(crate) struct Upload {
+    app: AppHandle,
+    control: TransferControl,
+    /// Journal key, or `None` when this upload must not be resumed.
+    key: Option<String>,
+    size: u64,
+    modified_ms: i64,
+}
+
+impl Upload {
+    /// Start an upload of `local_path`. `scope` identifies the account and
+    /// root it goes to; pass `resumable: false` for files whose bytes differ
+    /// on every attempt (freshly encrypted copies), which must never be
+    /// spliced onto an earlier session.
+    pub(crate) fn begin(
+        app: &AppHandle,
+        scope: &str,
+        local_path: &str,
+        remote_path: &str,
+        resumable: bool,
+    ) -> Result<Self, SyncError> {
+        let metadata = std::fs::metadata(local_path).map_err(|e| SyncError::io("stat", e))?;
+        let modified_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as i64);
+        let upload = Self {
+            app: app.clone(),
+            control: app.state::<TransferControl>().inner().clone(),
+            key: resumable.then(|| format!("{scope}\n{}", super::normalize(remote_path))),
+            size: metadata.len(),
+            modified_ms,
+        };
+        upload.check_network()?;
+        Ok(upload)
+    }
+
+    /// Refuse to send while "Wi-Fi only" is on and the connection is
+    /// metered. A platform that can't tell is treated as unmetered.
+    pub(crate) fn check_network(&self) -> Result<(), SyncError> {
+        if !self.control.policy().wifi_only {
+            return Ok(());
+        }
+        let status = match self.app.native_bridge().get_network_status() {
+            Ok(status) => status,
+            Err(e) => {
+                log::debug!("Network status unavailable, allowing upload: {e}");
+                return Ok(());
+            }
+        };
+        let (message, reason) = if !status.connected {
+            ("no network connection", "offline")
+        } else if status.metered {
+            (
+                "uploads are limited to Wi-Fi; waiting for an unmetered network",
+                "wifi_required",
+            )
+        } else {
+            return Ok(());
+        };
+        let mut error = SyncError::new(SyncErrorCode::Network, message);
+        error.reason = Some(reason.to_string());
+        Err(error)
+    }
+
+    /// The session to continue, if an earlier attempt left one for this
+    /// exact file.
+    pub(crate) fn resume(&self) -> Option<PendingUpload> {
+        let key = self.key.as_ref()?;
+        let entry = self.control.0.journal.lock().unwrap().get(key).cloned()?;
+        if is_resumable(&entry, self.size, self.modified_ms, now_ms()) {
+            Some(entry)
+        } else {
+            self.finish();
+            None
+        }
+    }
+
+    /// Record progress on `session` so a later attempt can pick it up.
+    pub(crate) fn save(&self, session: &str, offset: u64, parts: &[String]) {
+        let Some(key) = &self.key else {
+            return;
+        };
+        {
+            let mut journal = self.control.0.journal.lock().unwrap();
+            let created_ms = journal
+                .get(key)
+                .filter(|entry| entry.session == session)
+                .map_or_else(now_ms, |entry| entry.created_ms);
+            journal.insert(
+                key.clone(),
+                PendingUpload {
+                    session: session.to_string(),
+                    offset,
+                    parts: parts.to_vec(),
+                    size: self.size,
+                    modified_ms: self.modified_ms,
+                    created_ms,
+                },
+            );
+        }
+        self.control.persist_journal();
+    }
+
+    /// Drop the journal entry: the upload completed or the session is gone.
+    pub(crate) fn finish(&self) {
+        let Some(key) = &self.key else {
+            return;
+        };
+        if self.control.0.journal.lock().unwrap().remove(key).is_some() {
+            self.control.persist_journal();
+        }
+    }
+
+    /// A request body for `chunk`, paced under the bandwidth cap.
+    pub(crate) fn body(&self, chunk: Vec<u8>) -> reqwest::Body {
+        if self.control.policy().rate().is_none() {
+            return chunk.into();
+        }
+        let control = self.control.clone();
+        let slices: Vec<Vec<u8>> = chunk.chunks(SLICE_SIZE).map(<[u8]>::to_vec).collect();
+        reqwest::Body::wrap_stream(futures_util::stream::iter(slices).then(move |slice| {
+            let control = control.clone();
+            async move {
+                control.pace(slice.len()).await;
+                Ok::<_, std::io::Error>(slice)
+            }
+        }))
+    }
+
+    /// A streaming body for a whole file, paced and reporting progress like
+    /// `transfer_file::file_to_body`.
+    pub(crate) fn file_body(
+        &self,
+        channel: Channel<ProgressPayload>,
+        file: tokio::fs::File,
+        file_len: u64,
+    ) -> reqwest::Body {
+        let control = self.control.clone();
+        let mut stats = TransferStats::default();
+        let stream = FramedRead::new(file, BytesCodec::new())
+            .map_ok(|bytes| bytes.freeze())
+            .and_then(move |bytes| {
+                let control = control.clone();
+                async move {
+                    control.pace(bytes.len()).await;
+                    Ok(bytes)
+                }
+            })
+            .inspect_ok(move |bytes| {
+                stats.record_chunk_transfer(bytes.len());
+                let _ = channel.send(ProgressPayload::new(
+                    stats.total_transferred,
+                    file_len,
+                    stats.transfer_speed,
+                ));
+            });
+        reqwest::Body::wrap_stream(stream)
+    }
+}
+
+#[tauri::command]
+pub fn get_sync_transfer_policy(control: State<'_, TransferControl>) -> TransferPolicy {
+    control.policy()
+}
+
+#[tauri::command]
+pub fn set_sync_transfer_policy(control: State<'_, TransferControl>, policy: TransferPolicy) {
+    *control.0.policy.lock().unwrap() = policy;
+    write_json(control.0.policy_path.as_ref(), &policy);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pacing_spreads_bytes_over_time_without_banking_idle() {
+        let t0 = Instant::now();
+        let mut next = t0;
+        assert_eq!(reserve(&mut next, t0, 1000, 500), Duration::ZERO);
+        assert_eq!(
+            reserve(&mut next, t0, 1000, 500),
+            Duration::from_millis(500)
+        );
+        assert_eq!(next, t0 + Duration::from_secs(1));
+        // Ten idle seconds later the next slice goes now, not ten seconds'
+        // worth of slices.
+        let later = t0 + Duration::from_secs(11);
+        assert_eq!(reserve(&mut next, later, 1000, 1000), Duration::ZERO);
+        assert_eq!(reserve(&mut next, later, 1000, 1), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn journal_entries_are_tied_to_the_file_version() {
+        let entry = PendingUpload {
+            session: "s".into(),
+            offset: 10,
+            parts: Vec::new(),
+            size: 100,
+            modified_ms: 5,
+            created_ms: 1_000,
+        };
+        assert!(is_resumable(&entry, 100, 5, 2_000));
+        assert!(!is_resumable(&entry, 101, 5, 2_000));
+        assert!(!is_resumable(&entry, 100, 6, 2_000));
+        assert!(!is_resumable(&entry, 100, 5, 1_000 + MAX_SESSION_AGE_MS));
+    }
+
+    #[test]
+    fn policy_defaults_to_unlimited() {
+        let policy: TransferPolicy = serde_json::from_str("{}").unwrap();
+        assert_eq!(policy, TransferPolicy::default());
+        assert_eq!(policy.rate(), None);
+        let policy: TransferPolicy =
+            serde_json::from_str(r#"{"maxUploadRate":0,"wifiOnly":true}"#).unwrap();
+        assert_eq!(policy.rate(), None);
+        assert!(policy.wifi_only);
+    }
+}
@@ -0,0 +1,186 @@
+//! Progress of long-running work on the taskbar button.
+//!
+//! Imports, conversions and sync uploads each report under a task id while
+//! they run; the taskbar shows the average of everything in flight, so a
+//! conversion finishing during an import doesn't make the bar jump to full.
+//! When the last task ends the bar is cleared, or turns red and the button
+//! flashes if that task failed, until [`ERROR_DISPLAY`] has passed or new work
+//! starts. Only Windows has a backend (`ITaskbarList3`, see
+//! `windows::taskbar`); elsewhere reports are tracked and dropped.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+/// How long the error state stays on the taskbar button.
+const ERROR_DISPLAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskbarState {
+    Idle,
+    /// Fraction done, 0 to 1.
+    Progress(f64),
+    Error,
+}
+
+#[derive(Default)]
+struct Tasks {
+    running: HashMap<String, f64>,
+    failed: bool,
+    /// Bumped on every change, so a delayed clear can tell it is stale.
+    generation: u64,
+}
+
+impl Tasks {
+    /// Record `task`'s progress. A failure is forgotten only when new work
+    /// starts after everything ended, not when a task still running reports.
+    fn report(&mut self, task: &str, fraction: f64) {
+        if self.running.is_empty() {
+            self.failed = false;
+        }
+        self.running
+            .insert(task.to_string(), fraction.clamp(0.0, 1.0));
+        self.generation += 1;
+    }
+
+    fn state(&self) -> TaskbarState {
+        if self.running.is_empty() {
+            return if self.failed {
+                TaskbarState::Error
+            } else {
+                TaskbarState::Idle
+            };
+        }
+        let total: f64 = self.running.values().sum();
+        TaskbarState::Progress(total / self.running.len() as f64)
+    }
+}
+
+#[derive(Default)]
+pub struct TaskbarProgress {
+    tasks: Mutex<Tasks>,
+}
+
+/// Report that `task` is `fraction` done.
+pub fn report(app: &AppHandle, task: &str, fraction: f64) {
+    let Some(progress) = app.try_state::<TaskbarProgress>() else {
+        return;
+    };
+    let state = {
+        let mut tasks = progress.tasks.lock().unwrap();
+        tasks.report(task, fraction);
+        tasks.state()
+    };
+    apply(app, state);
+}
+
+/// Report that `task` ended, successfully or not.
+pub fn finish(app: &AppHandle, task: &str, failed: bool) {
+    let Some(progress) = app.try_state::<TaskbarProgress>() else {
+        return;
+    };
+    let (state, generation) = {
+        let mut tasks = progress.tasks.lock().unwrap();
+        if tasks.running.remove(task).is_none() && !failed {
+            return;
+        }
+        tasks.failed |= failed;
+        tasks.generation += 1;
+        (tasks.state(), tasks.generation)
+    };
+    apply(app, state);
+
+    if state == TaskbarState::Error {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(ERROR_DISPLAY).await;
+            let progress = app.state::<TaskbarProgress>();
+            let cleared = {
+                let mut tasks = progress.tasks.lock().unwrap();
+                let current = tasks.generation == generation;
+                if current {
+                    tasks.failed = false;
+                }
+                current
+            };
+            if cleared {
+                apply(&app, TaskbarState::Idle);
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn apply(app: &AppHandle, state: TaskbarState) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    // The taskbar COM object lives on the main thread, like the window.
+    let _ = app.run_on_main_thread(move || {
+        if let Ok(hwnd) = window.hwnd() {
+            crate::windows::taskbar::set_state(hwnd, state);
+        }
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+fn apply(_app: &AppHandle, _state: TaskbarState) {}
+
+/// Report progress of work the frontend drives, such as imports.
+#[tauri::command]
+pub fn update_taskbar_progress(app: AppHandle, task: String, progress: f64) {
+    report(&app, &task, progress);
+}
+
+#[tauri::command]
+pub fn finish_taskbar_progress(app: AppHandle, task: String, failed: bool) {
+    finish(&app, &task, failed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_without_tasks() {
+        assert_eq!(Tasks::default().state(), TaskbarState::Idle);
+    }
+
+    #[test]
+    fn averages_running_tasks() {
+        let mut tasks = Tasks::default();
+        tasks.running.insert("import".into(), 1.0);
+        tasks.running.insert("conversion:1".into(), 0.5);
+        assert_eq!(tasks.state(), TaskbarState::Progress(0.75));
+    }
+
+    #[test]
+    fn error_only_once_everything_ended() {
+        let mut tasks = Tasks {
+            failed: true,
+            ..Default::default()
+        };
+        assert_eq!(tasks.state(), TaskbarState::Error);
+        tasks.running.insert("import".into(), 0.2);
+        assert_eq!(tasks.state(), TaskbarState::Progress(0.2));
+    }
+
+    #[test]
+    fn failure_survives_reports_from_running_tasks() {
+        let mut tasks = Tasks::default();
+        tasks.report("import", 0.1);
+        tasks.report("upload", 0.1);
+        // The import fails while the upload carries on.
+        tasks.running.remove("import");
+        tasks.failed = true;
+        tasks.report("upload", 0.9);
+        tasks.running.remove("upload");
+        assert_eq!(tasks.state(), TaskbarState::Error);
+        // New work after everything ended clears it.
+        tasks.report("import", 0.0);
+        tasks.running.remove("import");
+        assert_eq!(tasks.state(), TaskbarState::Idle);
+    }
+}
@@ -7,7 +7,7 @@
 //! Download files from a remote HTTP server to disk.
 
 use futures_util::TryStreamExt;
-use serde::{ser::Serializer, Serialize};
+use serde::{ser::Serializer, Deserialize, Serialize};
 use tauri::{command, ipc::Channel, AppHandle};
 use tauri_plugin_fs::FsExt;
 use tokio::{
@@ -116,7 +116,7 @@ fn is_within_app_storage(file_path: &str, app_identifier: &str) -> bool {
 /// privileged Tauri origin — see GHSA-55vr-pvq5-6fmg. We require an absolute,
 /// traversal-free path that is either granted by the fs scope (persisted dialog
 /// grants for custom/external roots) or lives inside the app's own storage.
-fn ensure_path_allowed(app: &AppHandle, file_path: &str) -> Result<()> {
+pub(crate) fn ensure_path_allowed(app: &AppHandle, file_path: &str) -> Result<()> {
     if has_disallowed_components(file_path) {
         return Err(Error::Forbidden(file_path.to_string()));
     }
@@ -137,7 +137,7 @@ impl Serialize for Error {
     }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProgressPayload {
     progress: u64,
@@ -145,8 +145,25 @@ pub struct ProgressPayload {
     transfer_speed: u64,
 }
 
+impl ProgressPayload {
+    pub(crate) fn new(progress: u64, total: u64, transfer_speed: u64) -> Self {
+        Self {
+            progress,
+            total,
+            transfer_speed,
+        }
+    }
+
+    pub(crate) fn progress(&self) -> u64 {
+        self.progress
+    }
+
+    pub(crate) fn total(&self) -> u64 {
+        self.total
+    }
+}
+
 #[command]
-#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
 pub async fn download_file(
     app: AppHandle,
     url: &str,
@@ -154,7 +171,6 @@ pub async fn download_file(
     headers: HashMap<String, String>,
     body: Option<String>,
     single_threaded: Option<bool>,
-    skip_ssl_verification: Option<bool>,
     on_progress: Channel<ProgressPayload>,
 ) -> Result<HashMap<String, String>> {
     use futures::stream::{self, StreamExt};
@@ -165,10 +181,7 @@ pub async fn download_file(
 
     const PART_SIZE: u64 = 1024 * 1024;
 
-    let client = reqwest::ClientBuilder::new()
-        .danger_accept_invalid_certs(skip_ssl_verification.unwrap_or(false))
-        .danger_accept_invalid_hostnames(skip_ssl_verification.unwrap_or(false))
-        .build()?;
+    let client = crate::net::client_builder_for(url).build()?;
     let force_single = single_threaded.unwrap_or(false);
 
     async fn single_threaded_download(
@@ -339,7 +352,7 @@ pub async fn upload_file(
     let file = File::open(file_path).await?;
     let file_len = file.metadata().await.unwrap().len();
 
-    let client = reqwest::Client::new();
+    let client = crate::net::client_builder_for(url).build()?;
     let mut request = match method.to_uppercase().as_str() {
         "POST" => client.post(url),
         "PUT" => client.put(url),
@@ -365,7 +378,11 @@ pub async fn upload_file(
     }
 }
 
-fn file_to_body(channel: Channel<ProgressPayload>, file: File, file_len: u64) -> reqwest::Body {
+pub(crate) fn file_to_body(
+    channel: Channel<ProgressPayload>,
+    file: File,
+    file_len: u64,
+) -> reqwest::Body {
     let stream = FramedRead::new(file, BytesCodec::new()).map_ok(|r| r.freeze());
 
     let mut stats = TransferStats::default();
@@ -0,0 +1,314 @@
+//! System tray icon on desktop.
+//!
+//! The tray menu offers the book to continue and the other recently read
+//! books, "Sync Now", and "Pause Read-Aloud" while any window is reading
+//! aloud, plus showing the main window and quitting. The webview publishes
+//! the books and the translated labels through `update_tray_menu` and its
+//! read-aloud state through `set_tray_read_aloud`; picking an item sends a
+//! `tray-action` event back. With "Minimize to Tray" on
+//! (`set_minimize_to_tray`), closing the main window hides it instead of
+//! quitting, and the tray brings it back.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItem, SubmenuBuilder};
+use tauri::tray::TrayIconBuilder;
+#[cfg(not(target_os = "macos"))]
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow, Wry};
+
+const TRAY_ID: &str = "readest-tray";
+const ACTION_EVENT: &str = "tray-action";
+/// Prefix of the book item ids, followed by the book hash.
+const OPEN_BOOK_PREFIX: &str = "tray:open:";
+const CONTINUE_READING_ID: &str = "tray:continue_reading";
+const SYNC_NOW_ID: &str = "tray:sync_now";
+const PAUSE_READ_ALOUD_ID: &str = "tray:pause_read_aloud";
+const SHOW_ID: &str = "tray:show";
+const QUIT_ID: &str = "tray:quit";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrayBook {
+    hash: String,
+    title: String,
+    /// Percent read, for books that have been opened.
+    progress: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayLabels {
+    continue_reading: String,
+    recent_books: String,
+    sync_now: String,
+    pause_read_aloud: String,
+    show: String,
+    quit: String,
+}
+
+/// English until the webview publishes its translations.
+impl Default for TrayLabels {
+    fn default() -> Self {
+        TrayLabels {
+            continue_reading: "Continue Reading".into(),
+            recent_books: "Recent Books".into(),
+            sync_now: "Sync Now".into(),
+            pause_read_aloud: "Pause Read-Aloud".into(),
+            show: "Show Readest".into(),
+            quit: "Quit Readest".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrayAction {
+    action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    book_hash: Option<String>,
+}
+
+#[derive(Default)]
+struct TrayMenu {
+    books: Vec<TrayBook>,
+    labels: TrayLabels,
+    /// Labels of the windows reading aloud.
+    reading_aloud: HashSet<String>,
+}
+
+#[derive(Default)]
+pub struct Tray {
+    menu: Mutex<TrayMenu>,
+    minimize_to_tray: AtomicBool,
+}
+
+fn book_item_title(book: &TrayBook) -> String {
+    match book.progress {
+        Some(progress) => format!("{} — {progress}%", book.title),
+        None => book.title.clone(),
+    }
+}
+
+fn book_item(app: &AppHandle, book: &TrayBook) -> tauri::Result<MenuItem<Wry>> {
+    MenuItem::with_id(
+        app,
+        format!("{OPEN_BOOK_PREFIX}{}", book.hash),
+        book_item_title(book),
+        true,
+        None::<&str>,
+    )
+}
+
+fn build_menu(app: &AppHandle, state: &TrayMenu) -> tauri::Result<Menu<Wry>> {
+    let labels = &state.labels;
+    let mut menu = MenuBuilder::new(app);
+    if let Some((current, recent)) = state.books.split_first() {
+        menu = menu
+            .item(&MenuItem::with_id(
+                app,
+                CONTINUE_READING_ID,
+                &labels.continue_reading,
+                false,
+                None::<&str>,
+            )?)
+            .item(&book_item(app, current)?);
+        if !recent.is_empty() {
+            let mut submenu = SubmenuBuilder::new(app, &labels.recent_books);
+            for book in recent {
+                submenu = submenu.item(&book_item(app, book)?);
+            }
+            menu = menu.item(&submenu.build()?);
+        }
+        menu = menu.separator();
+    }
+    menu.item(&MenuItem::with_id(
+        app,
+        SYNC_NOW_ID,
+        &labels.sync_now,
+        true,
+        None::<&str>,
+    )?)
+    .item(&MenuItem::with_id(
+        app,
+        PAUSE_READ_ALOUD_ID,
+        &labels.pause_read_aloud,
+        !state.reading_aloud.is_empty(),
+        None::<&str>,
+    )?)
+    .separator()
+    .item(&MenuItem::with_id(
+        app,
+        SHOW_ID,
+        &labels.show,
+        true,
+        None::<&str>,
+    )?)
+    .item(&MenuItem::with_id(
+        app,
+        QUIT_ID,
+        &labels.quit,
+        true,
+        None::<&str>,
+    )?)
+    .build()
+}
+
+fn refresh_menu(app: &AppHandle, state: &TrayMenu) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(build_menu(app, state)?))?;
+    }
+    Ok(())
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    // Books open in the main window, like the other ways in from outside.
+    if let Some(hash) = id.strip_prefix(OPEN_BOOK_PREFIX) {
+        show_main_window(app);
+        let action = TrayAction {
+            action: "openBook",
+            book_hash: Some(hash.to_string()),
+        };
+        let _ = app.emit_to("main", ACTION_EVENT, action);
+        return;
+    }
+    let action = match id {
+        SYNC_NOW_ID => "syncNow",
+        PAUSE_READ_ALOUD_ID => "pauseReadAloud",
+        SHOW_ID => return show_main_window(app),
+        QUIT_ID => return app.exit(0),
+        _ => return,
+    };
+    let _ = app.emit(
+        ACTION_EVENT,
+        TrayAction {
+            action,
+            book_hash: None,
+        },
+    );
+}
+
+/// Add the tray icon. Call once during setup.
+pub fn setup(app: &AppHandle) -> tauri::Result<()> {
+    app.manage(Tray::default());
+    let menu = build_menu(app, &TrayMenu::default())?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Readest")
+        .menu(&menu)
+        .on_menu_event(handle_menu_event);
+    // A left click brings the window back where the desktop reports clicks
+    // (Windows); the macOS menu bar opens the menu on any click.
+    #[cfg(not(target_os = "macos"))]
+    {
+        builder = builder
+            .show_menu_on_left_click(false)
+            .on_tray_icon_event(|tray, event| {
+                if let TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    ..
+                } = event
+                {
+                    show_main_window(tray.app_handle());
+                }
+            });
+    }
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Whether closing the main window should hide it to the tray.
+pub fn minimizes_to_tray(app: &AppHandle) -> bool {
+    app.try_state::<Tray>()
+        .is_some_and(|tray| tray.minimize_to_tray.load(Ordering::Relaxed))
+}
+
+/// Replace the books in the tray menu with `books`, most recently read first.
+#[tauri::command]
+pub fn update_tray_menu(
+    app: AppHandle,
+    tray: State<'_, Tray>,
+    books: Vec<TrayBook>,
+    labels: TrayLabels,
+) -> Result<(), String> {
+    let mut menu = tray.menu.lock().unwrap();
+    menu.books = books;
+    menu.labels = labels;
+    refresh_menu(&app, &menu).map_err(|e| format!("Failed to update the tray menu: {e}"))
+}
+
+/// Enable "Pause Read-Aloud" while the calling window, or any other, reads
+/// aloud.
+#[tauri::command]
+pub fn set_tray_read_aloud(
+    app: AppHandle,
+    window: WebviewWindow,
+    tray: State<'_, Tray>,
+    active: bool,
+) -> Result<(), String> {
+    let mut menu = tray.menu.lock().unwrap();
+    let changed = if active {
+        menu.reading_aloud.insert(window.label().to_string())
+    } else {
+        menu.reading_aloud.remove(window.label())
+    };
+    if !changed {
+        return Ok(());
+    }
+    refresh_menu(&app, &menu).map_err(|e| format!("Failed to update the tray menu: {e}"))
+}
+
+#[tauri::command]
+pub fn set_minimize_to_tray(tray: State<'_, Tray>, enabled: bool) {
+    tray.minimize_to_tray.store(enabled, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn book_items_show_progress_of_opened_books() {
+        let book = |progress| TrayBook {
+            hash: "abc".into(),
+            title: "Dune".into(),
+            progress,
+        };
+        assert_eq!(book_item_title(&book(Some(42))), "Dune — 42%");
+        assert_eq!(book_item_title(&book(None)), "Dune");
+    }
+
+    #[test]
+    fn actions_serialize_for_the_webview() {
+        let action = TrayAction {
+            action: "openBook",
+            book_hash: Some("abc".into()),
+        };
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"action":"openBook","bookHash":"abc"}"#
+        );
+        let action = TrayAction {
+            action: "syncNow",
+            book_hash: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"action":"syncNow"}"#
+        );
+    }
+}
@@ -0,0 +1,563 @@
+//! M4B output: the MP3 frames wrapped in an MP4 container.
+//!
+//! Audiobook players want M4B with its chapters, title and cover stored
+//! the Apple way. The audio stays MP3 (MPEG-1 Layer III in an `mp4a`
+//! sample entry, object type 0x6B) rather than AAC, which saves a second
+//! encoder: the usable AAC encoders are C libraries under licences that
+//! don't mix with ours.
+//!
+//! Layout: `ftyp`, then `moov` up front so players can start without
+//! seeking to the end, then one `mdat` holding the audio frames followed
+//! by the chapter titles. Chapters are written twice, as a QuickTime text
+//! track referenced through `tref/chap` (Apple) and as a Nero `chpl` box
+//! (ffmpeg-based players), the same pair [`crate::audio::chapters`]
+//! reads.
+
+use std::io::{self, Read, Write};
+
+use super::mp3::{FRAME_SAMPLES, SAMPLE_RATE};
+use super::ExportMetadata;
+use crate::audio::chapters::AudioChapter;
+
+const AUDIO_TRACK: u32 = 1;
+const CHAPTER_TRACK: u32 = 2;
+/// Movie and chapter track timescale: milliseconds.
+const TIMESCALE: u32 = 1000;
+/// The Nero box counts its chapters in a byte.
+const MAX_NERO_CHAPTERS: usize = 255;
+/// Marks a chapter title sample as UTF-8.
+const ENCD: [u8; 12] = [0, 0, 0, 12, b'e', b'n', b'c', b'd', 0, 0, 1, 0];
+/// The unity matrix of `mvhd` and `tkhd`.
+const MATRIX: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+
+/// Big-endian byte builder.
+#[derive(Default)]
+struct Bytes(Vec<u8>);
+
+impl Bytes {
+    fn u8(mut self, v: u8) -> Self {
+        self.0.push(v);
+        self
+    }
+
+    fn u16(mut self, v: u16) -> Self {
+        self.0.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    fn u32(mut self, v: u32) -> Self {
+        self.0.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    fn u64(mut self, v: u64) -> Self {
+        self.0.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    fn raw(mut self, v: &[u8]) -> Self {
+        self.0.extend_from_slice(v);
+        self
+    }
+
+    fn zeros(mut self, n: usize) -> Self {
+        self.0.resize(self.0.len() + n, 0);
+        self
+    }
+
+    fn matrix(self) -> Self {
+        MATRIX.iter().fold(self, |b, &v| b.u32(v))
+    }
+}
+
+fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    Bytes::default()
+        .u32(content.len() as u32 + 8)
+        .raw(kind)
+        .raw(content)
+        .0
+}
+
+fn full_box(kind: &[u8; 4], version: u8, flags: u32, content: &[u8]) -> Vec<u8> {
+    let header = Bytes::default().u32((u32::from(version) << 24) | flags);
+    mp4_box(kind, &header.raw(content).0)
+}
+
+/// An MPEG-4 descriptor with a one-byte length; ours are all short.
+fn descriptor(tag: u8, content: &[u8]) -> Vec<u8> {
+    Bytes::default()
+        .u8(tag)
+        .u8(content.len() as u8)
+        .raw(content)
+        .0
+}
+
+fn tkhd(track: u32, flags: u32, duration_ms: u64, volume: u16) -> Vec<u8> {
+    let content = Bytes::default()
+        .u32(0)
+        .u32(0)
+        .u32(track)
+        .u32(0)
+        .u32(duration_ms as u32)
+        .zeros(8)
+        .u16(0)
+        .u16(0)
+        .u16(volume)
+        .u16(0)
+        .matrix()
+        .u32(0)
+        .u32(0);
+    full_box(b"tkhd", 0, flags, &content.0)
+}
+
+fn mdhd(timescale: u32, duration: u64) -> Vec<u8> {
+    // Language `und`, packed as three 5-bit letters.
+    let content = Bytes::default()
+        .u32(0)
+        .u32(0)
+        .u32(timescale)
+        .u32(duration as u32)
+        .u16(0x55c4)
+        .u16(0);
+    full_box(b"mdhd", 0, 0, &content.0)
+}
+
+fn hdlr(handler: &[u8; 4], name: &str) -> Vec<u8> {
+    let content = Bytes::default()
+        .u32(0)
+        .raw(handler)
+        .zeros(12)
+        .raw(name.as_bytes())
+        .u8(0);
+    full_box(b"hdlr", 0, 0, &content.0)
+}
+
+/// Data references: everything is in this file.
+fn dinf() -> Vec<u8> {
+    let url = full_box(b"url ", 0, 1, &[]);
+    let dref = full_box(b"dref", 0, 0, &Bytes::default().u32(1).raw(&url).0);
+    mp4_box(b"dinf", &dref)
+}
+
+/// The sample tables of a track whose samples sit one per chunk at
+/// `offsets`.
+fn stbl(
+    stsd_entry: &[u8],
+    stts: &[(u32, u32)],
+    sizes: &[u32],
+    offsets: &[u64],
+    large: bool,
+) -> Vec<u8> {
+    let stsd = full_box(b"stsd", 0, 0, &Bytes::default().u32(1).raw(stsd_entry).0);
+    let stts = full_box(
+        b"stts",
+        0,
+        0,
+        &stts
+            .iter()
+            .fold(Bytes::default().u32(stts.len() as u32), |b, &(n, d)| {
+                b.u32(n).u32(d)
+            })
+            .0,
+    );
+    let stsc = full_box(
+        b"stsc",
+        0,
+        0,
+        &Bytes::default().u32(1).u32(1).u32(1).u32(1).0,
+    );
+    let stsz = full_box(
+        b"stsz",
+        0,
+        0,
+        &sizes
+            .iter()
+            .fold(Bytes::default().u32(0).u32(sizes.len() as u32), |b, &s| {
+                b.u32(s)
+            })
+            .0,
+    );
+    let count = Bytes::default().u32(offsets.len() as u32);
+    let chunks = if large {
+        full_box(
+            b"co64",
+            0,
+            0,
+            &offsets.iter().fold(count, |b, &o| b.u64(o)).0,
+        )
+    } else {
+        full_box(
+            b"stco",
+            0,
+            0,
+            &offsets.iter().fold(count, |b, &o| b.u32(o as u32)).0,
+        )
+    };
+    mp4_box(b"stbl", &[stsd, stts, stsc, stsz, chunks].concat())
+}
+
+/// `mp4a` sample entry declaring MPEG-1 Layer III audio.
+fn mp3_entry() -> Vec<u8> {
+    let decoder_config = Bytes::default()
+        .u8(0x6b) // MPEG-1 audio
+        .u8(0x15) // audio stream
+        .raw(&[0, 0, 0])
+        .u32(64_000)
+        .u32(64_000);
+    let es = Bytes::default()
+        .u16(AUDIO_TRACK as u16)
+        .u8(0)
+        .raw(&descriptor(0x04, &decoder_config.0))
+        .raw(&descriptor(0x06, &[0x02]));
+    let esds = full_box(b"esds", 0, 0, &descriptor(0x03, &es.0));
+    let content = Bytes::default()
+        .zeros(6)
+        .u16(1)
+        .zeros(8)
+        .u16(1) // mono
+        .u16(16)
+        .u16(0)
+        .u16(0)
+        .u32(SAMPLE_RATE << 16)
+        .raw(&esds);
+    mp4_box(b"mp4a", &content.0)
+}
+
+/// `text` sample entry for the chapter track, as ffmpeg writes it.
+fn text_entry() -> Vec<u8> {
+    let font_table = mp4_box(b"ftab", &Bytes::default().u16(1).u16(1).u8(0).0);
+    let content = Bytes::default()
+        .zeros(6)
+        .u16(1)
+        .u32(1) // display flags
+        .zeros(2) // justification
+        .zeros(4) // background colour
+        .zeros(8) // default text box
+        .zeros(4) // style: start and end character
+        .u16(1) // font id
+        .zeros(2) // face and size
+        .zeros(4) // text colour
+        .raw(&font_table);
+    mp4_box(b"text", &content.0)
+}
+
+/// Base media header for the chapter track, again as ffmpeg writes it.
+fn gmhd() -> Vec<u8> {
+    let gmin = full_box(
+        b"gmin",
+        0,
+        0,
+        &Bytes::default()
+            .u16(0x40)
+            .u16(0x8000)
+            .u16(0x8000)
+            .u16(0x8000)
+            .u16(0)
+            .u16(0)
+            .0,
+    );
+    let text = mp4_box(
+        b"text",
+        &Bytes::default()
+            .u16(1)
+            .zeros(12)
+            .u32(1)
+            .zeros(12)
+            .u32(0x4000)
+            .u16(0)
+            .0,
+    );
+    mp4_box(b"gmhd", &[gmin, text].concat())
+}
+
+fn ilst_item(kind: &[u8; 4], data_type: u32, value: &[u8]) -> Vec<u8> {
+    let data = mp4_box(
+        b"data",
+        &Bytes::default().u32(data_type).u32(0).raw(value).0,
+    );
+    mp4_box(kind, &data)
+}
+
+/// iTunes-style metadata, marked as an audiobook (`stik` 2).
+fn meta(meta: &ExportMetadata) -> Vec<u8> {
+    const UTF8: u32 = 1;
+    let mut items = Vec::new();
+    if let Some(title) = &meta.title {
+        items.extend(ilst_item(b"\xa9nam", UTF8, title.as_bytes()));
+        items.extend(ilst_item(b"\xa9alb", UTF8, title.as_bytes()));
+    }
+    if let Some(author) = &meta.author {
+        items.extend(ilst_item(b"\xa9ART", UTF8, author.as_bytes()));
+        items.extend(ilst_item(b"aART", UTF8, author.as_bytes()));
+    }
+    items.extend(ilst_item(b"\xa9gen", UTF8, b"Audiobook"));
+    items.extend(ilst_item(b"stik", 21, &[2]));
+    if let Some(cover) = &meta.cover {
+        let data_type = if cover.mime == "image/png" { 14 } else { 13 };
+        items.extend(ilst_item(b"covr", data_type, &cover.bytes));
+    }
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &Bytes::default().u32(0).raw(b"mdir").raw(b"appl").zeros(9).0,
+    );
+    full_box(b"meta", 0, 0, &[hdlr, mp4_box(b"ilst", &items)].concat())
+}
+
+/// Nero chapters: start times in 100 ns units.
+fn chpl(chapters: &[AudioChapter]) -> Vec<u8> {
+    let chapters = &chapters[..chapters.len().min(MAX_NERO_CHAPTERS)];
+    let content = chapters.iter().fold(
+        Bytes::default().u32(0).u8(chapters.len() as u8),
+        |b, chapter| {
+            let title = &chapter.title.as_bytes()[..chapter.title.len().min(255)];
+            b.u64(chapter.start_ms * 10_000)
+                .u8(title.len() as u8)
+                .raw(title)
+        },
+    );
+    full_box(b"chpl", 1, 0, &content.0)
+}
+
+/// A chapter title sample: length-prefixed UTF-8 plus an `encd` box.
+fn title_sample(title: &str) -> Vec<u8> {
+    let title = title.as_bytes();
+    let title = &title[..title.len().min(usize::from(u16::MAX))];
+    Bytes::default()
+        .u16(title.len() as u16)
+        .raw(title)
+        .raw(&ENCD)
+        .0
+}
+
+struct Layout<'a> {
+    meta: &'a ExportMetadata,
+    chapters: &'a [AudioChapter],
+    /// Frame offsets (relative to the audio) and sizes.
+    frames: &'a [(u64, u32)],
+    audio_len: u64,
+    title_sizes: Vec<u32>,
+    large: bool,
+}
+
+impl Layout<'_> {
+    /// `moov` for an `mdat` whose payload starts at `base`.
+    fn moov(&self, base: u64) -> Vec<u8> {
+        let samples = self.frames.len() as u64 * u64::from(FRAME_SAMPLES);
+        let duration_ms = samples * 1000 / u64::from(SAMPLE_RATE);
+        let has_chapters = !self.chapters.is_empty();
+
+        let mvhd = Bytes::default()
+            .u32(0)
+            .u32(0)
+            .u32(TIMESCALE)
+            .u32(duration_ms as u32)
+            .u32(0x10000)
+            .u16(0x100)
+            .zeros(10)
+            .matrix()
+            .zeros(24)
+            .u32(CHAPTER_TRACK + 1);
+        let mut moov = full_box(b"mvhd", 0, 0, &mvhd.0);
+
+        let sizes: Vec<u32> = self.frames.iter().map(|&(_, size)| size).collect();
+        let offsets: Vec<u64> = self.frames.iter().map(|&(at, _)| base + at).collect();
+        let audio_minf = [
+            full_box(b"smhd", 0, 0, &[0; 4]),
+            dinf(),
+            stbl(
+                &mp3_entry(),
+                &[(self.frames.len() as u32, FRAME_SAMPLES)],
+                &sizes,
+                &offsets,
+                self.large,
+            ),
+        ]
+        .concat();
+        let audio_mdia = [
+            mdhd(SAMPLE_RATE, samples),
+            hdlr(b"soun", "SoundHandler"),
+            mp4_box(b"minf", &audio_minf),
+        ]
+        .concat();
+        let mut audio = tkhd(AUDIO_TRACK, 7, duration_ms, 0x100);
+        if has_chapters {
+            let chap = mp4_box(b"chap", &CHAPTER_TRACK.to_be_bytes());
+            audio.extend(mp4_box(b"tref", &chap));
+        }
+        audio.extend(mp4_box(b"mdia", &audio_mdia));
+        moov.extend(mp4_box(b"trak", &audio));
+
+        if has_chapters {
+            // Each title lasts until the next chapter starts.
+            let stts: Vec<(u32, u32)> = self
+                .chapters
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let end = self.chapters.get(i + 1).map_or(duration_ms, |n| n.start_ms);
+                    (1, end.saturating_sub(c.start_ms) as u32)
+                })
+                .collect();
+            let mut offsets = Vec::with_capacity(self.title_sizes.len());
+            let mut at = base + self.audio_len;
+            for &size in &self.title_sizes {
+                offsets.push(at);
+                at += u64::from(size);
+            }
+            let text_minf = [
+                gmhd(),
+                dinf(),
+                stbl(
+                    &text_entry(),
+                    &stts,
+                    &self.title_sizes,
+                    &offsets,
+                    self.large,
+                ),
+            ]
+            .concat();
+            let text_mdia = [
+                mdhd(TIMESCALE, duration_ms),
+                hdlr(b"text", "ChapterHandler"),
+                mp4_box(b"minf", &text_minf),
+            ]
+            .concat();
+            // Disabled, so players use it for navigation only.
+            let mut text = tkhd(CHAPTER_TRACK, 0, duration_ms, 0);
+            text.extend(mp4_box(b"mdia", &text_mdia));
+            moov.extend(mp4_box(b"trak", &text));
+        }
+
+        let mut udta = Vec::new();
+        if has_chapters {
+            udta.extend(chpl(self.chapters));
+        }
+        udta.extend(meta(self.meta));
+        moov.extend(mp4_box(b"udta", &udta));
+        mp4_box(b"moov", &moov)
+    }
+}
+
+/// Write an M4B of the MP3 stream `audio` (`audio_len` bytes whose frames
+/// are `frames`) with `chapters`, which start at 0 and tile the audio.
+pub fn write_m4b<W: Write, R: Read>(
+    out: &mut W,
+    audio: &mut R,
+    audio_len: u64,
+    frames: &[(u64, u32)],
+    meta: &ExportMetadata,
+    chapters: &[AudioChapter],
+) -> io::Result<()> {
+    let titles: Vec<Vec<u8>> = chapters.iter().map(|c| title_sample(&c.title)).collect();
+    let titles_len: u64 = titles.iter().map(|t| t.len() as u64).sum();
+    let payload = audio_len + titles_len;
+    // Leave room for the boxes in front of the data when picking 32- or
+    // 64-bit offsets.
+    let large = payload + (64 << 20) > u64::from(u32::MAX);
+    let layout = Layout {
+        meta,
+        chapters,
+        frames,
+        audio_len,
+        title_sizes: titles.iter().map(|t| t.len() as u32).collect(),
+        large,
+    };
+
+    let ftyp = mp4_box(
+        b"ftyp",
+        &Bytes::default()
+            .raw(b"M4B ")
+            .u32(0)
+            .raw(b"M4B M4A mp42isom")
+            .0,
+    );
+    let mdat_header_len = if large { 16 } else { 8 };
+    // The table sizes don't depend on the offsets, so measure once with a
+    // dummy base and then lay out for real.
+    let moov_len = layout.moov(0).len() as u64;
+    let base = ftyp.len() as u64 + moov_len + mdat_header_len;
+    let moov = layout.moov(base);
+
+    out.write_all(&ftyp)?;
+    out.write_all(&moov)?;
+    if large {
+        out.write_all(&Bytes::default().u32(1).raw(b"mdat").u64(payload + 16).0)?;
+    } else {
+        out.write_all(&Bytes::default().u32(payload as u32 + 8).raw(b"mdat").0)?;
+    }
+    let copied = io::copy(&mut audio.take(audio_len), out)?;
+    if copied != audio_len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "audio shorter than expected",
+        ));
+    }
+    for title in titles {
+        out.write_all(&title)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::chapters::mp4_chapters;
+    use std::io::Cursor;
+
+    #[test]
+    fn chapters_read_back_from_the_text_track() {
+        // 100 frames of 1152 samples at 44.1 kHz: 2612 ms.
+        let frames: Vec<(u64, u32)> = (0..100).map(|i| (i * 208, 208)).collect();
+        let audio = vec![0u8; 100 * 208];
+        let chapters = [
+            AudioChapter {
+                title: "Opening".into(),
+                start_ms: 0,
+                end_ms: 1_000,
+            },
+            AudioChapter {
+                title: "Ending".into(),
+                start_ms: 1_000,
+                end_ms: 2_612,
+            },
+        ];
+        let meta = ExportMetadata {
+            title: Some("Book".into()),
+            author: None,
+            cover: None,
+        };
+        let mut out = Vec::new();
+        write_m4b(
+            &mut out,
+            &mut Cursor::new(&audio),
+            audio.len() as u64,
+            &frames,
+            &meta,
+            &chapters,
+        )
+        .unwrap();
+
+        assert_eq!(&out[4..12], b"ftypM4B ");
+        assert_eq!(
+            mp4_chapters(&mut Cursor::new(&out), 2_612).unwrap(),
+            chapters
+        );
+    }
+
+    #[test]
+    fn nero_chapters_are_in_100ns_units() {
+        let chapters = [AudioChapter {
+            title: "One".into(),
+            start_ms: 1_500,
+            end_ms: 2_000,
+        }];
+        let chpl = chpl(&chapters);
+        assert_eq!(&chpl[4..8], b"chpl");
+        assert_eq!(chpl[8], 1);
+        assert_eq!(chpl[16], 1);
+        assert_eq!(&chpl[17..25], &15_000_000u64.to_be_bytes());
+        assert_eq!(&chpl[25..], b"\x03One");
+    }
+}
@@ -0,0 +1,397 @@
+//! Export a book's read-aloud narration as an audiobook file.
+//!
+//! `start_tts_export` takes the text of one chapter or of the whole book,
+//! split into the same segments read-aloud speaks (the JS side owns text
+//! extraction, as it does for braille export), and runs a background job
+//! that speaks it segment by segment through the native TTS plugin's
+//! `synthesize`: the platform engine on Android and iOS, the system's
+//! command-line voice on desktop. Speech is encoded to MP3 as it comes in
+//! ([`mp3`]), so memory stays flat however long the book is, and the file
+//! is written as MP3 with ID3 chapter frames or as M4B ([`m4b`]), with a
+//! chapter mark at the start of every chapter.
+//!
+//! Progress is emitted as `tts-export://progress` after each segment and
+//! the outcome as `tts-export://state`. `cancel_tts_export` stops the job
+//! before its next segment; nothing is left behind at the output path.
+
+pub mod m4b;
+pub mod mp3;
+pub mod wav;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_native_tts::{NativeTtsExt, SynthesizeArgs};
+
+use crate::audio::chapters::AudioChapter;
+use crate::parser_common::RawCoverImage;
+use crate::transfer_file::ensure_path_allowed;
+use m4b::write_m4b;
+use mp3::{id3_tag, FrameIndex, Mp3Encoder, SAMPLE_RATE};
+use wav::{read_wav, resample};
+
+pub const PROGRESS_EVENT: &str = "tts-export://progress";
+pub const STATE_EVENT: &str = "tts-export://state";
+
+const CACHE_DIR: &str = "tts-export";
+/// Silence after each segment and after each chapter.
+const SEGMENT_GAP_MS: u32 = 250;
+const CHAPTER_GAP_MS: u32 = 1500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsExportFormat {
+    Mp3,
+    M4b,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsExportChapter {
+    pub title: String,
+    pub segments: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsExportOptions {
+    pub output_path: String,
+    pub format: TtsExportFormat,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Image embedded as the cover art, e.g. the book's stored cover.
+    pub cover_path: Option<String>,
+    /// Voice id as listed by the TTS plugin; its current voice when unset.
+    pub voice: Option<String>,
+    pub rate: Option<f32>,
+    pub chapters: Vec<TtsExportChapter>,
+}
+
+/// Tags written into the output file.
+pub struct ExportMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub cover: Option<RawCoverImage>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsExportProgress {
+    pub id: String,
+    pub chapter: usize,
+    pub segments_done: usize,
+    pub segments_total: usize,
+    /// Audio rendered so far.
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TtsExportStatus {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsExportState {
+    pub id: String,
+    pub status: TtsExportStatus,
+    pub output_path: String,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Running exports and their cancel flags.
+#[derive(Default)]
+pub struct TtsExports {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "tts-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn silence(ms: u32) -> Vec<i16> {
+    vec![0; (u64::from(SAMPLE_RATE) * u64::from(ms) / 1000) as usize]
+}
+
+fn read_cover(path: &str) -> Option<RawCoverImage> {
+    match fs::read(path) {
+        Ok(bytes) => {
+            let mime = if bytes.starts_with(b"\x89PNG") {
+                "image/png"
+            } else {
+                "image/jpeg"
+            };
+            Some(RawCoverImage {
+                bytes,
+                mime: mime.to_string(),
+            })
+        }
+        Err(e) => {
+            log::warn!("TTS export cover {path}: {e}");
+            None
+        }
+    }
+}
+
+/// Speak `text` into `wav` and read it back at the export sample rate.
+fn synthesize(
+    app: &AppHandle,
+    options: &TtsExportOptions,
+    text: &str,
+    wav: &Path,
+) -> Result<Vec<i16>, String> {
+    let _ = fs::remove_file(wav);
+    app.native_tts()
+        .synthesize(SynthesizeArgs {
+            text: text.to_string(),
+            path: wav.to_string_lossy().into_owned(),
+            voice: options.voice.clone(),
+            rate: options.rate,
+        })
+        .map_err(|e| e.to_string())?;
+    match fs::read(wav) {
+        Ok(bytes) => Ok(resample(&read_wav(&bytes)?, SAMPLE_RATE)),
+        // Engines write nothing for text with nothing to say in it.
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("read synthesized speech: {e}")),
+    }
+}
+
+fn samples_to_ms(samples: u64) -> u64 {
+    samples * 1000 / u64::from(SAMPLE_RATE)
+}
+
+/// Put the tags, chapters and encoded audio together at `path`.
+fn write_output(
+    path: &Path,
+    audio_path: &Path,
+    format: TtsExportFormat,
+    index: &FrameIndex,
+    meta: &ExportMetadata,
+    chapters: &[AudioChapter],
+) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut audio = BufReader::new(File::open(audio_path)?);
+    match format {
+        TtsExportFormat::Mp3 => {
+            out.write_all(&id3_tag(meta, chapters))?;
+            std::io::copy(&mut audio, &mut out)?;
+        }
+        TtsExportFormat::M4b => write_m4b(
+            &mut out,
+            &mut audio,
+            index.written(),
+            &index.frames,
+            meta,
+            chapters,
+        )?,
+    }
+    out.flush()
+}
+
+/// Speak every segment and write the output file. `Ok(None)` when
+/// cancelled; otherwise the length of the audio.
+fn export(
+    app: &AppHandle,
+    id: &str,
+    options: &TtsExportOptions,
+    work_dir: &Path,
+    cancel: &AtomicBool,
+) -> Result<Option<u64>, String> {
+    let segments_total = options.chapters.iter().map(|c| c.segments.len()).sum();
+    let segment_wav = work_dir.join("segment.wav");
+    let audio_path = work_dir.join("audio.mp3");
+    let mut audio = BufWriter::new(
+        File::create(&audio_path).map_err(|e| format!("create {}: {e}", audio_path.display()))?,
+    );
+    let mut encoder = Mp3Encoder::new()?;
+    let mut index = FrameIndex::default();
+    let mut encoded = Vec::new();
+    let segment_gap = silence(SEGMENT_GAP_MS);
+    let chapter_gap = silence(CHAPTER_GAP_MS);
+    let mut samples = 0u64;
+    let mut starts = Vec::with_capacity(options.chapters.len());
+    let mut segments_done = 0;
+
+    for (chapter_index, chapter) in options.chapters.iter().enumerate() {
+        starts.push(samples);
+        for text in &chapter.segments {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            segments_done += 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let speech = synthesize(app, options, text, &segment_wav)?;
+            for pcm in [&speech, &segment_gap] {
+                encoder.encode(pcm, &mut encoded)?;
+                samples += pcm.len() as u64;
+            }
+            index.push(&encoded);
+            audio
+                .write_all(&encoded)
+                .map_err(|e| format!("write audio: {e}"))?;
+            encoded.clear();
+            let _ = app.emit(
+                PROGRESS_EVENT,
+                TtsExportProgress {
+                    id: id.to_string(),
+                    chapter: chapter_index,
+                    segments_done,
+                    segments_total,
+                    duration_ms: samples_to_ms(samples),
+                },
+            );
+        }
+        encoder.encode(&chapter_gap, &mut encoded)?;
+        samples += chapter_gap.len() as u64;
+    }
+    encoder.flush(&mut encoded)?;
+    index.push(&encoded);
+    audio
+        .write_all(&encoded)
+        .and_then(|()| audio.flush())
+        .map_err(|e| format!("write audio: {e}"))?;
+    drop(audio);
+    if cancel.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    let chapters: Vec<AudioChapter> = options
+        .chapters
+        .iter()
+        .zip(&starts)
+        .enumerate()
+        .map(|(i, (chapter, &start))| AudioChapter {
+            title: chapter.title.trim().to_string(),
+            start_ms: samples_to_ms(start),
+            end_ms: samples_to_ms(starts.get(i + 1).copied().unwrap_or(samples)),
+        })
+        .collect();
+    let meta = ExportMetadata {
+        title: options.title.clone(),
+        author: options.author.clone(),
+        cover: options.cover_path.as_deref().and_then(read_cover),
+    };
+
+    let part = PathBuf::from(format!("{}.part", options.output_path));
+    let written = write_output(&part, &audio_path, options.format, &index, &meta, &chapters);
+    if let Err(e) = written.and_then(|()| fs::rename(&part, &options.output_path)) {
+        let _ = fs::remove_file(&part);
+        return Err(format!("write {}: {e}", options.output_path));
+    }
+    Ok(Some(samples_to_ms(samples)))
+}
+
+fn run(
+    app: &AppHandle,
+    id: &str,
+    options: &TtsExportOptions,
+    work_dir: &Path,
+    cancel: &AtomicBool,
+) -> TtsExportState {
+    let result = fs::create_dir_all(work_dir)
+        .map_err(|e| format!("create {}: {e}", work_dir.display()))
+        .and_then(|()| export(app, id, options, work_dir, cancel));
+    let _ = fs::remove_dir_all(work_dir);
+    let (status, duration_ms, error) = match result {
+        Ok(Some(duration_ms)) => (TtsExportStatus::Completed, duration_ms, None),
+        Ok(None) => (TtsExportStatus::Cancelled, 0, None),
+        Err(e) => {
+            log::warn!("TTS export {id} failed: {e}");
+            (TtsExportStatus::Failed, 0, Some(e))
+        }
+    };
+    TtsExportState {
+        id: id.to_string(),
+        status,
+        output_path: options.output_path.clone(),
+        duration_ms,
+        error,
+    }
+}
+
+/// Start exporting; returns the job id that progress and state events
+/// carry.
+#[tauri::command]
+pub fn start_tts_export(
+    app: AppHandle,
+    exports: State<'_, TtsExports>,
+    options: TtsExportOptions,
+) -> Result<String, String> {
+    ensure_path_allowed(&app, &options.output_path).map_err(|e| e.to_string())?;
+    if let Some(cover_path) = &options.cover_path {
+        ensure_path_allowed(&app, cover_path).map_err(|e| e.to_string())?;
+    }
+    let has_text = options
+        .chapters
+        .iter()
+        .flat_map(|c| &c.segments)
+        .any(|s| !s.trim().is_empty());
+    if !has_text {
+        return Err("nothing to export".to_string());
+    }
+
+    let id = new_id();
+    let work_dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join(CACHE_DIR)
+        .join(&id);
+    let cancel = Arc::new(AtomicBool::new(false));
+    exports
+        .jobs
+        .lock()
+        .unwrap()
+        .insert(id.clone(), cancel.clone());
+
+    let job_id = id.clone();
+    let job_app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("tts-export".into())
+        .spawn(move || {
+            let state = run(&job_app, &job_id, &options, &work_dir, &cancel);
+            job_app
+                .state::<TtsExports>()
+                .jobs
+                .lock()
+                .unwrap()
+                .remove(&job_id);
+            let _ = job_app.emit(STATE_EVENT, state);
+        });
+    if let Err(e) = spawned {
+        exports.jobs.lock().unwrap().remove(&id);
+        return Err(format!("spawn export thread: {e}"));
+    }
+    Ok(id)
+}
+
+/// Stop an export before its next segment. A no-op for unknown ids, so a
+/// cancel racing the job's end is harmless.
+#[tauri::command]
+pub fn cancel_tts_export(exports: State<'_, TtsExports>, id: String) {
+    if let Some(cancel) = exports.jobs.lock().unwrap().get(&id) {
+        cancel.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,258 @@
+//! MP3 encoding, frame indexing and the ID3v2 tag of an exported book.
+
+use mp3lame_encoder::{max_required_buffer_size, Bitrate, Builder, Encoder, FlushNoGap, MonoPcm};
+
+use super::ExportMetadata;
+use crate::audio::chapters::AudioChapter;
+
+/// 44.1 kHz keeps the stream MPEG-1 Layer III, the flavour every MP4
+/// demuxer accepts inside M4B; speech doesn't need more than 64 kbit/s.
+pub const SAMPLE_RATE: u32 = 44_100;
+pub const FRAME_SAMPLES: u32 = 1152;
+const BITRATE: Bitrate = Bitrate::Kbps64;
+
+/// ID3v2 counts its table of contents in a byte.
+const MAX_TOC_ENTRIES: usize = 255;
+
+pub struct Mp3Encoder(Encoder);
+
+impl Mp3Encoder {
+    pub fn new() -> Result<Self, String> {
+        let mut builder = Builder::new().ok_or("create MP3 encoder")?;
+        builder
+            .set_num_channels(1)
+            .map_err(|e| format!("MP3 encoder channels: {e}"))?;
+        builder
+            .set_sample_rate(SAMPLE_RATE)
+            .map_err(|e| format!("MP3 encoder sample rate: {e}"))?;
+        builder
+            .set_brate(BITRATE)
+            .map_err(|e| format!("MP3 encoder bitrate: {e}"))?;
+        builder
+            .build()
+            .map(Self)
+            .map_err(|e| format!("create MP3 encoder: {e}"))
+    }
+
+    /// Encode mono samples at [`SAMPLE_RATE`], appending to `out`.
+    pub fn encode(&mut self, samples: &[i16], out: &mut Vec<u8>) -> Result<(), String> {
+        out.reserve(max_required_buffer_size(samples.len()));
+        self.0
+            .encode_to_vec(MonoPcm(samples), out)
+            .map_err(|e| format!("encode MP3: {e}"))?;
+        Ok(())
+    }
+
+    /// Encode what's left in the encoder's buffers.
+    pub fn flush(&mut self, out: &mut Vec<u8>) -> Result<(), String> {
+        out.reserve(max_required_buffer_size(0));
+        self.0
+            .flush_to_vec::<FlushNoGap>(out)
+            .map_err(|e| format!("flush MP3: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Length of the MPEG Layer III frame starting with `header`.
+fn frame_len(header: &[u8]) -> Option<usize> {
+    const MPEG1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    if header[0] != 0xff || header[1] & 0xe0 != 0xe0 || (header[1] >> 1) & 3 != 1 {
+        return None;
+    }
+    let version = (header[1] >> 3) & 3;
+    let bitrate = *match version {
+        3 => &MPEG1,
+        0 | 2 => &MPEG2,
+        _ => return None,
+    }
+    .get(usize::from(header[2] >> 4))?;
+    let base_rate = [44_100, 48_000, 32_000].get(usize::from((header[2] >> 2) & 3))?;
+    let (sample_rate, coefficient) = match version {
+        3 => (*base_rate, 144_000),
+        2 => (base_rate / 2, 72_000),
+        _ => (base_rate / 4, 72_000),
+    };
+    if bitrate == 0 {
+        return None;
+    }
+    let padding = u32::from((header[2] >> 1) & 1);
+    Some((coefficient * bitrate / sample_rate + padding) as usize)
+}
+
+/// Finds the frames in an MP3 stream as it's written, for the MP4 sample
+/// tables.
+#[derive(Default)]
+pub struct FrameIndex {
+    /// Offset and length of each frame.
+    pub frames: Vec<(u64, u32)>,
+    pos: u64,
+    header: Vec<u8>,
+    header_at: u64,
+    remaining: usize,
+}
+
+impl FrameIndex {
+    pub fn push(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(data.len());
+                self.remaining -= n;
+                self.pos += n as u64;
+                data = &data[n..];
+                continue;
+            }
+            if self.header.is_empty() {
+                self.header_at = self.pos;
+            }
+            self.header.push(data[0]);
+            self.pos += 1;
+            data = &data[1..];
+            if self.header.len() < 4 {
+                continue;
+            }
+            match frame_len(&self.header) {
+                Some(len) if len >= 4 => {
+                    self.frames.push((self.header_at, len as u32));
+                    self.remaining = len - 4;
+                    self.header.clear();
+                }
+                // Not a frame header: slide along a byte.
+                _ => {
+                    self.header.remove(0);
+                    self.header_at += 1;
+                }
+            }
+        }
+    }
+
+    /// Bytes pushed so far.
+    pub fn written(&self) -> u64 {
+        self.pos
+    }
+}
+
+/// An ID3v2.3 text frame body: Latin-1 when it fits, UTF-16 otherwise.
+fn text(value: &str) -> Vec<u8> {
+    if value.chars().all(|c| u32::from(c) < 0x100) {
+        let mut out = vec![0];
+        out.extend(value.chars().map(|c| c as u8));
+        out
+    } else {
+        let mut out = vec![1, 0xff, 0xfe];
+        out.extend(value.encode_utf16().flat_map(u16::to_le_bytes));
+        out
+    }
+}
+
+fn frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(body);
+    out
+}
+
+/// The ID3v2.3 tag for the front of the MP3: title, author, cover, and a
+/// `CTOC` table of contents over one `CHAP` frame per chapter.
+pub fn id3_tag(meta: &ExportMetadata, chapters: &[AudioChapter]) -> Vec<u8> {
+    let mut frames = Vec::new();
+    if let Some(title) = &meta.title {
+        frames.extend(frame(b"TIT2", &text(title)));
+        frames.extend(frame(b"TALB", &text(title)));
+    }
+    if let Some(author) = &meta.author {
+        frames.extend(frame(b"TPE1", &text(author)));
+    }
+    frames.extend(frame(b"TCON", &text("Audiobook")));
+    if let Some(cover) = &meta.cover {
+        let mut body = vec![0];
+        body.extend_from_slice(cover.mime.as_bytes());
+        // Front cover, empty description.
+        body.extend_from_slice(&[0, 3, 0]);
+        body.extend_from_slice(&cover.bytes);
+        frames.extend(frame(b"APIC", &body));
+    }
+    if !chapters.is_empty() {
+        let listed = chapters.len().min(MAX_TOC_ENTRIES);
+        // Top-level and ordered.
+        let mut toc = b"toc\0\x03".to_vec();
+        toc.push(listed as u8);
+        for i in 0..listed {
+            toc.extend_from_slice(format!("ch{i}\0").as_bytes());
+        }
+        frames.extend(frame(b"CTOC", &toc));
+    }
+    for (i, chapter) in chapters.iter().enumerate() {
+        let mut chap = format!("ch{i}\0").into_bytes();
+        for ms in [chapter.start_ms, chapter.end_ms] {
+            chap.extend_from_slice(&(ms.min(u64::from(u32::MAX)) as u32).to_be_bytes());
+        }
+        // No byte offsets: players seek by time.
+        chap.extend_from_slice(&[0xff; 8]);
+        chap.extend(frame(b"TIT2", &text(&chapter.title)));
+        frames.extend(frame(b"CHAP", &chap));
+    }
+
+    let size = frames.len() as u32;
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    tag.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7f) as u8));
+    tag.extend(frames);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::chapters::id3_chapters;
+    use crate::parser_common::RawCoverImage;
+    use std::io::Cursor;
+
+    fn chapter(title: &str, start_ms: u64, end_ms: u64) -> AudioChapter {
+        AudioChapter {
+            title: title.into(),
+            start_ms,
+            end_ms,
+        }
+    }
+
+    #[test]
+    fn chapters_read_back_from_the_tag() {
+        let meta = ExportMetadata {
+            title: Some("Book".into()),
+            author: Some("Авторова".into()),
+            cover: Some(RawCoverImage {
+                bytes: vec![0xff, 0xd8, 0xff],
+                mime: "image/jpeg".into(),
+            }),
+        };
+        let chapters = [
+            chapter("Prologue", 0, 61_000),
+            chapter("Глава 1", 61_000, 125_500),
+        ];
+        let tag = id3_tag(&meta, &chapters);
+        assert_eq!(
+            id3_chapters(&mut Cursor::new(tag), 125_500).unwrap(),
+            chapters
+        );
+    }
+
+    #[test]
+    fn indexes_frames_across_pushes() {
+        // MPEG-1 Layer III, 64 kbit/s, 44.1 kHz: 208 bytes, 209 padded.
+        let mut plain = vec![0u8; 208];
+        plain[..4].copy_from_slice(&[0xff, 0xfb, 0x50, 0x00]);
+        let mut padded = vec![0u8; 209];
+        padded[..4].copy_from_slice(&[0xff, 0xfb, 0x52, 0x00]);
+        let stream = [plain.as_slice(), b"junk", &padded, &plain].concat();
+
+        let mut index = FrameIndex::default();
+        for chunk in stream.chunks(100) {
+            index.push(chunk);
+        }
+        assert_eq!(index.frames, [(0, 208), (212, 209), (421, 208)]);
+        assert_eq!(index.written(), stream.len() as u64);
+    }
+}
@@ -0,0 +1,217 @@
+//! Reading the WAV files the TTS engines write.
+//!
+//! Engines differ in what they produce: Android's are 16-bit mono at the
+//! voice's own rate (16, 22.05 or 24 kHz), iOS writes 32-bit float,
+//! `say` 16-bit at 22.05 kHz and SAPI 16-bit stereo at 22.05 kHz. All of
+//! it is folded down to 16-bit mono and resampled to the export rate.
+
+/// WAVE_FORMAT_PCM, WAVE_FORMAT_IEEE_FLOAT and WAVE_FORMAT_EXTENSIBLE.
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Mono 16-bit audio.
+#[derive(Debug, PartialEq)]
+pub struct Pcm {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+struct Format {
+    tag: u16,
+    channels: usize,
+    sample_rate: u32,
+    bits: u16,
+}
+
+fn le_u16(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn parse_format(fmt: &[u8]) -> Result<Format, String> {
+    if fmt.len() < 16 {
+        return Err("truncated WAV format chunk".to_string());
+    }
+    let mut tag = le_u16(fmt);
+    // The extensible format carries the real tag at the start of its
+    // sub-format GUID.
+    if tag == FORMAT_EXTENSIBLE {
+        tag = fmt
+            .get(24..26)
+            .map(le_u16)
+            .ok_or("truncated WAV format chunk")?;
+    }
+    Ok(Format {
+        tag,
+        channels: usize::from(le_u16(&fmt[2..])),
+        sample_rate: le_u32(&fmt[4..]),
+        bits: le_u16(&fmt[14..]),
+    })
+}
+
+/// One sample of `bits` width as a 16-bit value.
+fn sample(format: &Format, b: &[u8]) -> i16 {
+    match (format.tag, format.bits) {
+        (FORMAT_PCM, 8) => (i16::from(b[0]) - 128) << 8,
+        (FORMAT_PCM, 16) => i16::from_le_bytes([b[0], b[1]]),
+        (FORMAT_PCM, 24) => i16::from_le_bytes([b[1], b[2]]),
+        (FORMAT_PCM, 32) => i16::from_le_bytes([b[2], b[3]]),
+        (FORMAT_FLOAT, 32) => {
+            let v = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            (v.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16
+        }
+        (FORMAT_FLOAT, 64) => {
+            let v = f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+            (v.clamp(-1.0, 1.0) * f64::from(i16::MAX)) as i16
+        }
+        _ => 0,
+    }
+}
+
+/// Decode a WAV file into mono 16-bit samples, averaging the channels.
+pub fn read_wav(bytes: &[u8]) -> Result<Pcm, String> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = le_u32(&bytes[pos + 4..]) as usize;
+        let start = pos + 8;
+        // Streaming writers leave the data size at 0 or 0xFFFFFFFF; take
+        // whatever the file actually holds.
+        let end = start.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " => format = Some(parse_format(&bytes[start..end])?),
+            b"data" => {
+                let format = format.ok_or("WAV data before its format")?;
+                let supported = matches!(
+                    (format.tag, format.bits),
+                    (FORMAT_PCM, 8 | 16 | 24 | 32) | (FORMAT_FLOAT, 32 | 64)
+                );
+                if !supported || format.channels == 0 {
+                    return Err(format!(
+                        "unsupported WAV encoding: format {} with {} bits",
+                        format.tag, format.bits
+                    ));
+                }
+                let width = usize::from(format.bits / 8);
+                let frame = width * format.channels;
+                let data = if size == 0 {
+                    &bytes[start..]
+                } else {
+                    &bytes[start..end]
+                };
+                let samples = data
+                    .chunks_exact(frame)
+                    .map(|frame| {
+                        let sum: i32 = frame
+                            .chunks_exact(width)
+                            .map(|b| i32::from(sample(&format, b)))
+                            .sum();
+                        (sum / format.channels as i32) as i16
+                    })
+                    .collect();
+                return Ok(Pcm {
+                    sample_rate: format.sample_rate,
+                    samples,
+                });
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        pos = end + (size & 1);
+    }
+    Err("WAV file has no audio data".to_string())
+}
+
+/// Linear-interpolation resampling. Plenty for speech, whose energy sits
+/// well below either rate's Nyquist frequency.
+pub fn resample(pcm: &Pcm, rate: u32) -> Vec<i16> {
+    if pcm.sample_rate == rate || pcm.samples.len() < 2 || pcm.sample_rate == 0 {
+        return pcm.samples.clone();
+    }
+    let out_len =
+        (pcm.samples.len() as u64 * u64::from(rate) / u64::from(pcm.sample_rate)) as usize;
+    let step = f64::from(pcm.sample_rate) / f64::from(rate);
+    let last = pcm.samples.len() - 1;
+    (0..out_len)
+        .map(|i| {
+            let at = i as f64 * step;
+            let index = (at as usize).min(last);
+            let next = (index + 1).min(last);
+            let frac = at - index as f64;
+            let a = f64::from(pcm.samples[index]);
+            let b = f64::from(pcm.samples[next]);
+            (a + (b - a) * frac).round() as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(tag: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&rate.to_le_bytes());
+        let block = channels * bits / 8;
+        fmt.extend_from_slice(&(rate * u32::from(block)).to_le_bytes());
+        fmt.extend_from_slice(&block.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        let mut out = b"RIFF\0\0\0\0WAVE".to_vec();
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        out.extend(fmt);
+        // An odd-sized chunk the reader has to skip, padding included.
+        out.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    #[test]
+    fn folds_stereo_to_mono() {
+        let data: Vec<u8> = [100i16, 300, -50, -150]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let pcm = read_wav(&wav(FORMAT_PCM, 2, 22_050, 16, &data)).unwrap();
+        assert_eq!(
+            pcm,
+            Pcm {
+                sample_rate: 22_050,
+                samples: vec![200, -100]
+            }
+        );
+    }
+
+    #[test]
+    fn reads_float_samples() {
+        let data: Vec<u8> = [0.5f32, -1.5]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let pcm = read_wav(&wav(FORMAT_FLOAT, 1, 24_000, 32, &data)).unwrap();
+        assert_eq!(pcm.samples, [16_383, -32_767]);
+        assert!(read_wav(b"RIFF\0\0\0\0WAVEdata").is_err());
+    }
+
+    #[test]
+    fn resamples_linearly() {
+        let pcm = Pcm {
+            sample_rate: 22_050,
+            samples: vec![0, 100, 200],
+        };
+        assert_eq!(resample(&pcm, 44_100), [0, 50, 100, 150, 200, 200]);
+        assert_eq!(resample(&pcm, 22_050), [0, 100, 200]);
+    }
+}
@@ -0,0 +1,352 @@
+//! Chapter splitting for large TXT books.
+//!
+//! Shipping a 50 MB novel over IPC and splitting it in the webview stalls
+//! the UI for seconds. Instead `open_txt_book` decodes the file once, finds
+//! chapter headings line by line, and keeps the text here; the reader asks
+//! for one chapter at a time with `get_txt_chapter` and releases the book
+//! with `close_txt_book`.
+//!
+//! A line is a heading when, trimmed, it matches one of the patterns in
+//! full. The defaults cover 「第…章」/「第…回」 style Chinese headings,
+//! 楔子/序章 style front matter, and English "Chapter N" / "Part IV" /
+//! "Prologue" lines; callers can pass their own. Files without enough
+//! headings fall back to fixed runs of paragraphs, like the JS importer.
+
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+
+use super::encoding::{decode, resolve, EncodingSource, SAMPLE_LEN};
+use crate::transfer_file::ensure_path_allowed;
+
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // 第一章, 第12回 天地初开, 第三节：...; the title may attach directly.
+    r"^第[ 　零〇一二三四五六七八九十百千万0-9]+[章节回讲篇话](?:[：:、 　()（）0-9]*.{0,36})?$",
+    // 第一卷 / 第二部: these double as measure words in prose (第四本书),
+    // so a title only counts after a separator.
+    r"^第[ 　零〇一二三四五六七八九十百千万0-9]+[卷本册部](?:[：:、 　()（）].{0,36})?$",
+    r"^(?:楔子|前言|简介|引言|序言|序章|总论|概论|后记|番外篇|番外|外传)(?:[：: 　].{0,36})?$",
+    r"(?i)^(?:chapter|part|section|book|volume|act)\s*(?:\d+|[ivxlcdm]+)\b(?:[:.\-–—]?\s*.{0,50})?$",
+    r"(?i)^(?:prologue|epilogue|introduction|foreword|preface|afterword)(?:[:.\-–—]?\s*.{0,50})?$",
+];
+
+/// Fewer headings than this and the file is split by paragraphs instead.
+const MIN_HEADINGS: usize = 2;
+const DEFAULT_PARAGRAPHS_PER_CHAPTER: usize = 100;
+/// Longer lines are prose, whatever they start with.
+const MAX_HEADING_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitOptions {
+    /// Regexes matched against each trimmed line; replaces the defaults.
+    pub patterns: Option<Vec<String>>,
+    pub paragraphs_per_chapter: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Chapter {
+    title: String,
+    /// Body text, excluding the heading line.
+    body: Range<usize>,
+    detected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxtTocEntry {
+    pub index: usize,
+    pub title: String,
+    /// Body length in bytes of UTF-8, for progress estimates.
+    pub size: usize,
+    /// False for chapters made by the paragraph fallback.
+    pub detected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxtBookInfo {
+    pub id: String,
+    pub encoding: String,
+    pub source: EncodingSource,
+    pub had_errors: bool,
+    pub toc: Vec<TxtTocEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxtChapter {
+    pub index: usize,
+    pub title: String,
+    pub text: String,
+}
+
+struct TxtBook {
+    text: String,
+    chapters: Vec<Chapter>,
+}
+
+#[derive(Default)]
+pub struct TxtBooks {
+    books: Mutex<HashMap<String, TxtBook>>,
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "txt-{millis:x}-{:x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn compile(patterns: Option<&[String]>) -> Result<RegexSet, String> {
+    let set = match patterns {
+        Some(patterns) => {
+            // Report the offending pattern rather than the whole set.
+            for p in patterns {
+                Regex::new(p).map_err(|e| format!("invalid pattern {p:?}: {e}"))?;
+            }
+            RegexSet::new(patterns)
+        }
+        None => RegexSet::new(DEFAULT_PATTERNS),
+    };
+    set.map_err(|e| format!("invalid pattern: {e}"))
+}
+
+/// Lines of `text` as (trimmed line, byte range of the whole line).
+fn lines(text: &str) -> impl Iterator<Item = (&str, Range<usize>)> {
+    let mut start = 0;
+    text.split_inclusive('\n').map(move |line| {
+        let range = start..start + line.len();
+        start = range.end;
+        (line.trim(), range)
+    })
+}
+
+/// Narrow `range` of `text` to exclude surrounding whitespace.
+fn trimmed(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = start + slice.trim().len();
+    start..end
+}
+
+fn split_by_headings(text: &str, patterns: &RegexSet) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut title: Option<String> = None;
+    let mut body_start = 0;
+    for (line, range) in lines(text) {
+        if line.is_empty() || line.chars().count() > MAX_HEADING_CHARS || !patterns.is_match(line) {
+            continue;
+        }
+        let body = trimmed(text, body_start..range.start);
+        // Front matter before the first heading is kept when it has text.
+        if title.is_some() || !body.is_empty() {
+            chapters.push(Chapter {
+                title: title.take().unwrap_or_default(),
+                body,
+                detected: true,
+            });
+        }
+        title = Some(line.to_string());
+        body_start = range.end;
+    }
+    if let Some(title) = title {
+        chapters.push(Chapter {
+            title,
+            body: trimmed(text, body_start..text.len()),
+            detected: true,
+        });
+    }
+    chapters
+}
+
+fn split_by_paragraphs(text: &str, per_chapter: usize) -> Vec<Chapter> {
+    let per_chapter = per_chapter.max(1);
+    let mut chapters = Vec::new();
+    let mut paragraphs = 0;
+    let mut start = None;
+    let mut end = 0;
+    for (line, range) in lines(text) {
+        if line.is_empty() {
+            continue;
+        }
+        start.get_or_insert(range.start);
+        end = range.end;
+        paragraphs += 1;
+        if paragraphs == per_chapter {
+            chapters.push(Chapter {
+                title: (chapters.len() + 1).to_string(),
+                body: trimmed(text, start.take().unwrap_or(0)..end),
+                detected: false,
+            });
+            paragraphs = 0;
+        }
+    }
+    if let Some(start) = start {
+        chapters.push(Chapter {
+            title: (chapters.len() + 1).to_string(),
+            body: trimmed(text, start..end),
+            detected: false,
+        });
+    }
+    chapters
+}
+
+fn split(text: &str, patterns: &RegexSet, paragraphs_per_chapter: usize) -> Vec<Chapter> {
+    let chapters = split_by_headings(text, patterns);
+    if chapters.iter().filter(|c| !c.title.is_empty()).count() >= MIN_HEADINGS {
+        chapters
+    } else {
+        split_by_paragraphs(text, paragraphs_per_chapter)
+    }
+}
+
+/// Decode the TXT file at `file_path`, split it into chapters and keep it
+/// open for `get_txt_chapter`. `encoding` overrides charset detection.
+#[tauri::command]
+pub async fn open_txt_book(
+    app: AppHandle,
+    books: State<'_, TxtBooks>,
+    file_path: String,
+    encoding: Option<String>,
+    options: Option<SplitOptions>,
+) -> Result<TxtBookInfo, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    let options = options.unwrap_or_default();
+    let (info, book) = tauri::async_runtime::spawn_blocking(move || {
+        let patterns = compile(options.patterns.as_deref())?;
+        let data = std::fs::read(&file_path).map_err(|e| format!("read failed: {e}"))?;
+        let truncated = data.len() as u64 > SAMPLE_LEN;
+        let sample = &data[..data.len().min(SAMPLE_LEN as usize)];
+        let (encoding, source) = resolve(sample, truncated, encoding.as_deref())?;
+        let (text, had_errors) = decode(&data, encoding);
+        let text = text.into_owned();
+        drop(data);
+        let per_chapter = options
+            .paragraphs_per_chapter
+            .unwrap_or(DEFAULT_PARAGRAPHS_PER_CHAPTER);
+        let chapters = split(&text, &patterns, per_chapter);
+        let toc = chapters
+            .iter()
+            .enumerate()
+            .map(|(index, c)| TxtTocEntry {
+                index,
+                title: c.title.clone(),
+                size: c.body.len(),
+                detected: c.detected,
+            })
+            .collect();
+        let info = TxtBookInfo {
+            id: new_id(),
+            encoding: encoding.name().to_string(),
+            source,
+            had_errors,
+            toc,
+        };
+        Ok::<_, String>((info, TxtBook { text, chapters }))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+    books.books.lock().unwrap().insert(info.id.clone(), book);
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn get_txt_chapter(
+    books: State<'_, TxtBooks>,
+    id: String,
+    index: usize,
+) -> Result<TxtChapter, String> {
+    let books = books.books.lock().unwrap();
+    let book = books
+        .get(&id)
+        .ok_or_else(|| format!("no open TXT book: {id}"))?;
+    let chapter = book
+        .chapters
+        .get(index)
+        .ok_or_else(|| format!("chapter {index} out of range"))?;
+    Ok(TxtChapter {
+        index,
+        title: chapter.title.clone(),
+        text: book.text[chapter.body.clone()].to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn close_txt_book(books: State<'_, TxtBooks>, id: String) {
+    books.books.lock().unwrap().remove(&id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles_and_bodies(text: &str, chapters: &[Chapter]) -> Vec<(String, String)> {
+        chapters
+            .iter()
+            .map(|c| (c.title.clone(), text[c.body.clone()].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_chinese_headings() {
+        let text = "书名\r\n\r\n第一章 风起\r\n天色渐晚。\r\n\r\n　　第二章天地初开\r\n少年北上。\r\n第四本书他没有读完。\r\n第三卷：终局\r\n完。\r\n";
+        let patterns = compile(None).unwrap();
+        let chapters = split(text, &patterns, 100);
+        assert_eq!(
+            titles_and_bodies(text, &chapters),
+            [
+                ("", "书名"),
+                ("第一章 风起", "天色渐晚。"),
+                ("第二章天地初开", "少年北上。\r\n第四本书他没有读完。"),
+                ("第三卷：终局", "完。"),
+            ]
+            .map(|(t, b)| (t.to_string(), b.to_string()))
+        );
+        assert!(chapters.iter().all(|c| c.detected));
+    }
+
+    #[test]
+    fn splits_english_headings() {
+        let text = "Prologue\nIt was dark.\n\nCHAPTER 1: The Start\nHe left.\nChapter IV\nThe end.";
+        let chapters = split(text, &compile(None).unwrap(), 100);
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Prologue", "CHAPTER 1: The Start", "Chapter IV"]);
+        assert_eq!(&text[chapters[2].body.clone()], "The end.");
+    }
+
+    #[test]
+    fn custom_patterns_replace_defaults() {
+        let text = "== One ==\na\n== Two ==\nb\nChapter 3\nc";
+        let patterns = compile(Some(&[r"^== .+ ==$".to_string()])).unwrap();
+        let titles: Vec<_> = split(text, &patterns, 100)
+            .into_iter()
+            .map(|c| c.title)
+            .collect();
+        assert_eq!(titles, ["== One ==", "== Two =="]);
+        assert!(compile(Some(&["(".to_string()])).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_paragraph_runs() {
+        let text = "one\n\ntwo\nthree\n\n\nfour\nfive\n";
+        let chapters = split(text, &compile(None).unwrap(), 2);
+        assert_eq!(
+            titles_and_bodies(text, &chapters),
+            [("1", "one\n\ntwo"), ("2", "three\n\n\nfour"), ("3", "five")]
+                .map(|(t, b)| (t.to_string(), b.to_string()))
+        );
+        assert!(chapters.iter().all(|c| !c.detected));
+        assert!(split("", &compile(None).unwrap(), 2).is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+//! Charset detection and UTF-8 conversion for TXT files.
+//!
+//! The webview's `TextDecoder` only knows the encoding it's told, and the
+//! JS side's guess (UTF-8, else a fixed East Asian fallback list) turns
+//! Windows-1251 or Shift_JIS novels into mojibake. Detection here goes:
+//!
+//!   1. a byte order mark, which is authoritative;
+//!   2. valid UTF-8 over the whole sample;
+//!   3. `chardetng`, the detector Firefox uses for unlabeled pages.
+//!
+//! The user can override the result with any WHATWG encoding label;
+//! `detect_text_encoding` returns a decoded preview either way so the UI
+//! can show what the override would look like.
+
+use encoding_rs::{Encoding, UTF_8};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::transfer_file::ensure_path_allowed;
+
+/// How much of the file detection looks at. Enough for chardetng to
+/// settle on novels whose first pages are mostly ASCII front matter.
+pub(super) const SAMPLE_LEN: u64 = 1024 * 1024;
+const PREVIEW_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EncodingSource {
+    Bom,
+    Utf8,
+    Detected,
+    Override,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEncoding {
+    /// WHATWG name, e.g. "GBK", "Big5", "Shift_JIS", "windows-1251".
+    pub encoding: String,
+    pub source: EncodingSource,
+    /// The first characters decoded with `encoding`.
+    pub preview: String,
+    /// Whether the preview hit bytes `encoding` can't decode.
+    pub had_errors: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertedText {
+    pub encoding: String,
+    pub source: EncodingSource,
+    pub had_errors: bool,
+    pub output_path: String,
+}
+
+fn lookup(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| format!("unknown encoding: {label}"))
+}
+
+/// Whether `sample` is UTF-8, allowing a sequence cut off at the end when
+/// the sample is only a prefix of the file.
+fn is_utf8(sample: &[u8], truncated: bool) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => truncated && e.error_len().is_none(),
+    }
+}
+
+/// Pick the encoding of a file from its first bytes. `truncated` says
+/// whether `sample` stops short of the end of the file.
+pub fn detect(sample: &[u8], truncated: bool) -> (&'static Encoding, EncodingSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return (encoding, EncodingSource::Bom);
+    }
+    if is_utf8(sample, truncated) {
+        return (UTF_8, EncodingSource::Utf8);
+    }
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(sample, !truncated);
+    (detector.guess(None, false), EncodingSource::Detected)
+}
+
+/// Decode `bytes` as `encoding`, dropping a BOM for it if present.
+pub fn decode<'a>(bytes: &'a [u8], encoding: &'static Encoding) -> (Cow<'a, str>, bool) {
+    let bytes = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, len)) if bom_encoding == encoding => &bytes[len..],
+        _ => bytes,
+    };
+    encoding.decode_without_bom_handling(bytes)
+}
+
+fn read_sample(path: &Path) -> Result<(Vec<u8>, bool), String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut sample = Vec::new();
+    file.take(SAMPLE_LEN)
+        .read_to_end(&mut sample)
+        .map_err(|e| format!("read failed: {e}"))?;
+    Ok((sample, len > sample.len() as u64))
+}
+
+pub(super) fn resolve(
+    sample: &[u8],
+    truncated: bool,
+    label: Option<&str>,
+) -> Result<(&'static Encoding, EncodingSource), String> {
+    match label.filter(|l| !l.trim().is_empty()) {
+        Some(label) => Ok((lookup(label)?, EncodingSource::Override)),
+        None => Ok(detect(sample, truncated)),
+    }
+}
+
+fn preview(sample: &[u8], encoding: &'static Encoding) -> (String, bool) {
+    let (text, had_errors) = decode(sample, encoding);
+    (text.chars().take(PREVIEW_CHARS).collect(), had_errors)
+}
+
+/// Detect the encoding of the TXT file at `file_path`, or apply
+/// `encoding` as an override, and return a decoded preview.
+#[tauri::command]
+pub async fn detect_text_encoding(
+    app: AppHandle,
+    file_path: String,
+    encoding: Option<String>,
+) -> Result<TextEncoding, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (sample, truncated) = read_sample(Path::new(&file_path))?;
+        let (encoding, source) = resolve(&sample, truncated, encoding.as_deref())?;
+        let (preview, had_errors) = preview(&sample, encoding);
+        Ok(TextEncoding {
+            encoding: encoding.name().to_string(),
+            source,
+            preview,
+            had_errors,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Write the TXT file at `file_path` to `output_path` as UTF-8, detecting
+/// its encoding unless `encoding` overrides it.
+#[tauri::command]
+pub async fn convert_text_to_utf8(
+    app: AppHandle,
+    file_path: String,
+    output_path: String,
+    encoding: Option<String>,
+) -> Result<ConvertedText, String> {
+    ensure_path_allowed(&app, &file_path).map_err(|e| e.to_string())?;
+    ensure_path_allowed(&app, &output_path).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let data = std::fs::read(&file_path).map_err(|e| format!("read failed: {e}"))?;
+        let truncated = data.len() as u64 > SAMPLE_LEN;
+        let sample = &data[..data.len().min(SAMPLE_LEN as usize)];
+        let (encoding, source) = resolve(sample, truncated, encoding.as_deref())?;
+        let (text, had_errors) = decode(&data, encoding);
+        std::fs::write(&output_path, text.as_bytes()).map_err(|e| format!("write failed: {e}"))?;
+        Ok(ConvertedText {
+            encoding: encoding.name().to_string(),
+            source,
+            had_errors,
+            output_path,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(text: &str, label: &str) -> Vec<u8> {
+        let (bytes, _, had_errors) = lookup(label).unwrap().encode(text);
+        assert!(!had_errors);
+        bytes.into_owned()
+    }
+
+    fn detected(bytes: &[u8]) -> (&'static str, EncodingSource) {
+        let (encoding, source) = detect(bytes, false);
+        (encoding.name(), source)
+    }
+
+    #[test]
+    fn boms_and_utf8_win() {
+        assert_eq!(
+            detected(b"\xef\xbb\xbfplain"),
+            ("UTF-8", EncodingSource::Bom)
+        );
+        assert_eq!(
+            detected(b"\xff\xfeh\0i\0"),
+            ("UTF-16LE", EncodingSource::Bom)
+        );
+        assert_eq!(
+            detected("第一章 开始".as_bytes()),
+            ("UTF-8", EncodingSource::Utf8)
+        );
+        // A multibyte sequence cut off by the sample boundary is still UTF-8.
+        let text = "第一章".as_bytes();
+        assert_eq!(
+            detect(&text[..text.len() - 1], true).1,
+            EncodingSource::Utf8
+        );
+        assert_eq!(
+            detect(&text[..text.len() - 1], false).1,
+            EncodingSource::Detected
+        );
+    }
+
+    #[test]
+    fn legacy_encodings_are_detected() {
+        let chinese =
+            "第一章 风起云涌\n天色渐晚，城门外的行人越来越少。少年背着行囊，沿着官道一路向北走去。"
+                .repeat(20);
+        let traditional =
+            "第一章 風起雲湧\n天色漸晚，城門外的行人越來越少。少年背著行囊，沿著官道一路向北走去。"
+                .repeat(20);
+        let russian = "Глава первая\nВ начале было слово, и слово было у Бога. Он шёл по улице и думал о жизни.".repeat(20);
+        let japanese =
+            "第一章　はじまり\n吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。"
+                .repeat(20);
+        for (text, label) in [
+            (chinese.as_str(), "GBK"),
+            (traditional.as_str(), "Big5"),
+            (russian.as_str(), "windows-1251"),
+            (japanese.as_str(), "Shift_JIS"),
+        ] {
+            let bytes = encode(text, label);
+            let (encoding, source) = detected(&bytes);
+            assert_eq!((encoding, source), (label, EncodingSource::Detected));
+            assert_eq!(decode(&bytes, lookup(encoding).unwrap()).0, text);
+        }
+    }
+
+    #[test]
+    fn overrides_take_any_label() {
+        let bytes = encode("Привет", "koi8-r");
+        let (encoding, source) = resolve(&bytes, false, Some(" koi8-r ")).unwrap();
+        assert_eq!(
+            (encoding.name(), source),
+            ("KOI8-R", EncodingSource::Override)
+        );
+        assert_eq!(preview(&bytes, encoding), ("Привет".to_string(), false));
+        assert!(resolve(&bytes, false, Some("klingon")).is_err());
+        assert_eq!(
+            resolve(&bytes, false, Some("")).unwrap().1,
+            EncodingSource::Detected
+        );
+    }
+}
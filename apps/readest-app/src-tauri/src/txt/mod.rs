@@ -0,0 +1,10 @@
+//! Plain-text book import.
+//!
+//! [`encoding`] works out the charset of a TXT file (BOM, UTF-8, then
+//! statistical detection for legacy encodings such as GBK, Big5,
+//! Shift_JIS or Windows-1251) and converts it to UTF-8 before the
+//! importer sees it. [`chapters`] splits big TXT books into chapters on
+//! this side and serves them to the reader one at a time.
+
+pub mod chapters;
+pub mod encoding;
@@ -0,0 +1,128 @@
+//! Claim Readest's book formats from inside the app.
+//!
+//! The installer associates the formats listed in `tauri.conf.json`, but since
+//! Windows 10 an app can no longer make itself the default handler: it can
+//! only offer itself and let the user choose. Portable builds and upgrades
+//! that added formats never get registered at all. This writes a per-user
+//! ProgID for every supported extension, offers it under the extension's
+//! `OpenWithProgids`, and declares the formats as Readest's capabilities under
+//! `RegisteredApplications`, then opens the Default Apps page for Readest so
+//! the user can confirm the choice there.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use winreg::enums::HKEY_CURRENT_USER;
+use winreg::RegKey;
+
+/// Name of the app in `RegisteredApplications` and the Default Apps page.
+const APP_NAME: &str = "Readest";
+const APP_DESCRIPTION: &str = "Read EPUB, MOBI, PDF, comics and more.";
+const CAPABILITIES_KEY: &str = r"Software\Readest\Capabilities";
+const REGISTERED_APPLICATIONS_KEY: &str = r"Software\RegisteredApplications";
+const CLASSES_KEY: &str = r"Software\Classes";
+
+/// Extensions Readest opens, with the description shown in Explorer.
+const ASSOCIATIONS: &[(&str, &str)] = &[
+    ("epub", "EPUB file"),
+    ("mobi", "MOBI file"),
+    ("azw", "AZW file"),
+    ("azw3", "AZW3 file"),
+    ("kf8", "KF8 file"),
+    ("prc", "PRC file"),
+    ("fb2", "FB2 file"),
+    ("cbz", "CBZ file"),
+    ("cbr", "CBR file"),
+    ("pdf", "PDF file"),
+];
+
+/// ProgID Readest registers for `ext`.
+fn prog_id(ext: &str) -> String {
+    format!("Readest.{ext}")
+}
+
+/// `shell\open\command` for the executable, passing the file as one argument.
+fn open_command(exe: &Path) -> String {
+    format!("\"{}\" \"%1\"", exe.display())
+}
+
+fn write_associations(exe: &Path) -> io::Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (classes, _) = hkcu.create_subkey(CLASSES_KEY)?;
+    let (capabilities, _) = hkcu.create_subkey(CAPABILITIES_KEY)?;
+    capabilities.set_value("ApplicationName", &APP_NAME)?;
+    capabilities.set_value("ApplicationDescription", &APP_DESCRIPTION)?;
+    let (file_associations, _) = capabilities.create_subkey("FileAssociations")?;
+
+    let icon = format!("\"{}\",0", exe.display());
+    let command = open_command(exe);
+    for (ext, description) in ASSOCIATIONS {
+        let prog_id = prog_id(ext);
+        let (prog_key, _) = classes.create_subkey(&prog_id)?;
+        prog_key.set_value("", description)?;
+        prog_key
+            .create_subkey("DefaultIcon")?
+            .0
+            .set_value("", &icon)?;
+        prog_key
+            .create_subkey(r"shell\open\command")?
+            .0
+            .set_value("", &command)?;
+
+        let (open_with, _) = classes.create_subkey(format!(r".{ext}\OpenWithProgids"))?;
+        open_with.set_value(&prog_id, &"")?;
+        file_associations.set_value(format!(".{ext}"), &prog_id)?;
+    }
+
+    let (registered, _) = hkcu.create_subkey(REGISTERED_APPLICATIONS_KEY)?;
+    registered.set_value(APP_NAME, &CAPABILITIES_KEY)?;
+    Ok(())
+}
+
+/// Open Settings > Default Apps. Windows 11 jumps straight to Readest's page;
+/// Windows 10 ignores the query and shows the general list.
+fn open_default_apps_settings() -> io::Result<()> {
+    Command::new("explorer.exe")
+        .arg(format!(
+            "ms-settings:defaultapps?registeredAppUser={APP_NAME}"
+        ))
+        .spawn()
+        .map(|_| ())
+}
+
+/// Register Readest for all its book formats and ask the user to make it the
+/// default app for them.
+#[tauri::command]
+pub async fn register_default_app() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    write_associations(&exe).map_err(|e| format!("Failed to register file types: {e}"))?;
+    open_default_apps_settings().map_err(|e| format!("Failed to open Default Apps: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn prog_id_is_namespaced() {
+        assert_eq!(prog_id("epub"), "Readest.epub");
+    }
+
+    #[test]
+    fn open_command_quotes_exe_and_file() {
+        let exe = PathBuf::from(r"C:\Program Files\Readest\readest.exe");
+        assert_eq!(
+            open_command(&exe),
+            r#""C:\Program Files\Readest\readest.exe" "%1""#
+        );
+    }
+
+    #[test]
+    fn associations_cover_configured_formats() {
+        for ext in ["epub", "mobi", "azw", "azw3", "fb2", "cbz", "pdf"] {
+            assert!(ASSOCIATIONS.iter().any(|(e, _)| *e == ext), "{ext}");
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Taskbar jump list with the books read most recently.
+//!
+//! Each entry is a shell link back to the app executable carrying a
+//! `readest://book/{hash}` deep link, so picking one reopens the book through
+//! the same single-instance / deep-link path as the home-screen widget. The
+//! list is rebuilt as a whole on every update, which is how
+//! `ICustomDestinationList` works; entries the user removed from the jump list
+//! are left out so the commit isn't rejected.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use windows::core::{Interface, HSTRING, PROPVARIANT};
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+    DestinationList, EnumerableObjectCollection, ICustomDestinationList, IObjectArray,
+    IObjectCollection, IShellLinkW, ShellLink,
+};
+
+/// Deep link the "Import Books…" task launches, handled by the library page.
+const IMPORT_BOOKS_LINK: &str = "readest://import";
+
+#[derive(Debug, Deserialize)]
+pub struct JumpListBook {
+    hash: String,
+    title: String,
+    author: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JumpListLabels {
+    recent_books: String,
+    import_books: String,
+}
+
+fn book_link(hash: &str) -> String {
+    format!("readest://book/{hash}")
+}
+
+unsafe fn shell_link(
+    exe: &HSTRING,
+    arguments: &str,
+    title: &str,
+    description: &str,
+) -> windows::core::Result<IShellLinkW> {
+    let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+    link.SetPath(exe)?;
+    link.SetArguments(&HSTRING::from(arguments))?;
+    link.SetIconLocation(exe, 0)?;
+    if !description.is_empty() {
+        link.SetDescription(&HSTRING::from(description))?;
+    }
+    // Jump list entries show System.Title rather than the link's file name.
+    let store: IPropertyStore = link.cast()?;
+    store.SetValue(&PKEY_Title, &PROPVARIANT::from(title))?;
+    store.Commit()?;
+    Ok(link)
+}
+
+/// Arguments of the links the user removed from the jump list.
+unsafe fn removed_arguments(removed: &IObjectArray) -> Vec<String> {
+    let count = removed.GetCount().unwrap_or(0);
+    (0..count)
+        .filter_map(|i| {
+            let link: IShellLinkW = removed.GetAt(i).ok()?;
+            let mut buf = [0u16; 1024];
+            link.GetArguments(&mut buf).ok()?;
+            let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+            Some(String::from_utf16_lossy(&buf[..len]))
+        })
+        .collect()
+}
+
+unsafe fn update(
+    exe: &Path,
+    books: &[JumpListBook],
+    labels: &JumpListLabels,
+) -> windows::core::Result<()> {
+    let exe = HSTRING::from(exe);
+
+    let list: ICustomDestinationList =
+        CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+    let mut max_slots = 0u32;
+    let removed: IObjectArray = list.BeginList(&mut max_slots)?;
+    let removed = removed_arguments(&removed);
+
+    let recent: IObjectCollection =
+        CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+    let mut added = 0;
+    for book in books {
+        let arguments = book_link(&book.hash);
+        if removed.contains(&arguments) {
+            continue;
+        }
+        if added >= max_slots {
+            break;
+        }
+        recent.AddObject(&shell_link(&exe, &arguments, &book.title, &book.author)?)?;
+        added += 1;
+    }
+    if added > 0 {
+        list.AppendCategory(
+            &HSTRING::from(&labels.recent_books),
+            &recent.cast::<IObjectArray>()?,
+        )?;
+    }
+
+    let tasks: IObjectCollection =
+        CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+    tasks.AddObject(&shell_link(
+        &exe,
+        IMPORT_BOOKS_LINK,
+        &labels.import_books,
+        "",
+    )?)?;
+    list.AddUserTasks(&tasks.cast::<IObjectArray>()?)?;
+
+    list.CommitList()
+}
+
+/// Replace the jump list with `books`, most recent first, and the app's tasks.
+///
+/// Synchronous so that it runs on the main thread, whose COM apartment the
+/// shell objects need.
+#[tauri::command]
+pub fn update_jump_list(books: Vec<JumpListBook>, labels: JumpListLabels) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    unsafe { update(&exe, &books, &labels) }.map_err(|e| format!("Failed to update jump list: {e}"))
+}
@@ -1 +1,4 @@
-
+pub mod file_associations;
+pub mod jump_list;
+pub mod power;
+pub mod taskbar;
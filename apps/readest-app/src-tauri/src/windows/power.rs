@@ -0,0 +1,23 @@
+//! `SetThreadExecutionState` backend for [`crate::keep_awake`].
+
+use tauri::AppHandle;
+use windows::Win32::System::Power::{
+    SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED, EXECUTION_STATE,
+};
+
+/// Keep the system from sleeping, or let it again. The state belongs to the
+/// thread that sets it, so it is always set on the main thread, which lives
+/// as long as the app.
+pub fn set_system_required(app: &AppHandle, required: bool) -> Result<(), String> {
+    app.run_on_main_thread(move || {
+        let state = if required {
+            ES_CONTINUOUS | ES_SYSTEM_REQUIRED
+        } else {
+            ES_CONTINUOUS
+        };
+        if unsafe { SetThreadExecutionState(state) } == EXECUTION_STATE(0) {
+            log::warn!("SetThreadExecutionState failed");
+        }
+    })
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,63 @@
+//! `ITaskbarList3` backend for [`crate::taskbar_progress`].
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::{
+    ITaskbarList3, TaskbarList, TBPF_ERROR, TBPF_NOPROGRESS, TBPF_NORMAL,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    FlashWindowEx, FLASHWINFO, FLASHW_TIMERNOFG, FLASHW_TRAY,
+};
+
+use crate::taskbar_progress::TaskbarState;
+
+/// Steps the progress value is reported in.
+const PROGRESS_STEPS: u64 = 1000;
+
+thread_local! {
+    static TASKBAR: Option<ITaskbarList3> = unsafe { create_taskbar().ok() };
+}
+
+unsafe fn create_taskbar() -> windows::core::Result<ITaskbarList3> {
+    let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+    taskbar.HrInit()?;
+    Ok(taskbar)
+}
+
+/// Show `state` on the taskbar button of `hwnd`. Must run on the main thread.
+pub fn set_state(hwnd: HWND, state: TaskbarState) {
+    TASKBAR.with(|taskbar| {
+        let Some(taskbar) = taskbar else {
+            return;
+        };
+        unsafe {
+            let _ = match state {
+                TaskbarState::Idle => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+                TaskbarState::Progress(fraction) => {
+                    taskbar.SetProgressState(hwnd, TBPF_NORMAL).and_then(|_| {
+                        let completed = (fraction * PROGRESS_STEPS as f64).round() as u64;
+                        taskbar.SetProgressValue(hwnd, completed, PROGRESS_STEPS)
+                    })
+                }
+                TaskbarState::Error => {
+                    flash(hwnd);
+                    taskbar.SetProgressState(hwnd, TBPF_ERROR).and_then(|_| {
+                        taskbar.SetProgressValue(hwnd, PROGRESS_STEPS, PROGRESS_STEPS)
+                    })
+                }
+            };
+        }
+    });
+}
+
+/// Flash the taskbar button until the window comes to the foreground.
+unsafe fn flash(hwnd: HWND) {
+    let info = FLASHWINFO {
+        cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: FLASHW_TRAY | FLASHW_TIMERNOFG,
+        uCount: 0,
+        dwTimeout: 0,
+    };
+    let _ = FlashWindowEx(&info);
+}
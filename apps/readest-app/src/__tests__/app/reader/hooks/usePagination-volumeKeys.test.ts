@@ -2,7 +2,7 @@ import { afterEach, beforeEach, describe, expect, test, vi } from 'vitest';
 import { act, cleanup, renderHook } from '@testing-library/react';
 
 // Real deviceStore + eventDispatcher; only the native bridge boundary is mocked
-// so we can observe the interceptKeys({ volumeKeys }) calls the store makes.
+// so we can observe the setKeyMapping calls the store makes.
 const h = vi.hoisted(() => ({
   appService: { isMobileApp: true } as { isMobileApp: boolean },
   viewSettings: { volumeKeysToFlip: true } as Record<string, unknown> | null,
@@ -12,6 +12,8 @@ const h = vi.hoisted(() => ({
 
 vi.mock('@/utils/bridge', () => ({
   interceptKeys: vi.fn(),
+  setKeyMapping: vi.fn(),
+  listenToMappedKeys: vi.fn(() => Promise.resolve()),
   getScreenBrightness: vi.fn(),
   setScreenBrightness: vi.fn(),
 }));
@@ -44,7 +46,8 @@ vi.mock('@/store/sidebarStore', () => ({
   useSidebarStore: Object.assign(() => ({}), { getState: () => ({ sideBarBookKey: 'book-1' }) }),
 }));
 
-import { interceptKeys } from '@/utils/bridge';
+import { setKeyMapping } from '@/utils/bridge';
+import { volumeKeyMappings } from '@/utils/keybinding';
 import { eventDispatcher } from '@/utils/event';
 import { useDeviceControlStore } from '@/store/deviceStore';
 import { usePagination } from '@/app/reader/hooks/usePagination';
@@ -71,12 +74,16 @@ const setupWithView = (view: ReturnType<typeof makeView>) => {
   return renderHook(() => usePagination(BOOK_KEY, viewRef, containerRef));
 };
 
-const pressVolumeKey = async (keyName: 'VolumeUp' | 'VolumeDown') => {
+const pressVolumeKey = async (key: 'VolumeUp' | 'VolumeDown') => {
+  const action = key === 'VolumeUp' ? 'pagePrev' : 'pageNext';
   await act(async () => {
-    await eventDispatcher.dispatch('native-key-down', { keyName });
+    await eventDispatcher.dispatch('native-key-action', { key, action, longPress: false });
   });
 };
 
+const MAPPED = { mappings: volumeKeyMappings() };
+const RELEASED = { mappings: [] };
+
 const emitPlayback = async (state: string, bookKey = BOOK_KEY) => {
   await act(async () => {
     await eventDispatcher.dispatch('tts-playback-state', { bookKey, state });
@@ -103,63 +110,62 @@ afterEach(() => {
 describe('usePagination volume-key interception with TTS (#4691)', () => {
   test('intercepts volume keys on mount when the setting is on and TTS is idle', () => {
     setup();
-    expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: true });
+    expect(setKeyMapping).toHaveBeenCalledWith(MAPPED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
   });
 
   test('hands volume keys back to the OS while TTS is playing', async () => {
     setup();
-    vi.mocked(interceptKeys).mockClear();
+    vi.mocked(setKeyMapping).mockClear();
 
     await emitPlayback('playing');
 
-    expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: false });
+    expect(setKeyMapping).toHaveBeenCalledWith(RELEASED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(false);
   });
 
   test('restores interception when TTS is paused', async () => {
     setup();
     await emitPlayback('playing');
-    vi.mocked(interceptKeys).mockClear();
+    vi.mocked(setKeyMapping).mockClear();
 
     await emitPlayback('paused');
 
-    expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: true });
+    expect(setKeyMapping).toHaveBeenCalledWith(MAPPED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
   });
 
   test('restores interception when TTS is stopped', async () => {
     setup();
     await emitPlayback('playing');
-    vi.mocked(interceptKeys).mockClear();
+    vi.mocked(setKeyMapping).mockClear();
 
     await emitPlayback('stopped');
 
-    expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: true });
+    expect(setKeyMapping).toHaveBeenCalledWith(MAPPED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
   });
 
   test('ignores TTS playback state from a different book', async () => {
     setup();
-    vi.mocked(interceptKeys).mockClear();
+    vi.mocked(setKeyMapping).mockClear();
 
     await emitPlayback('playing', 'other-book');
 
-    expect(interceptKeys).not.toHaveBeenCalledWith({ volumeKeys: false });
+    expect(setKeyMapping).not.toHaveBeenCalledWith(RELEASED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
   });
 
   test('does not intercept volume keys when the setting is off', () => {
     h.viewSettings = { volumeKeysToFlip: false };
     setup();
-    expect(interceptKeys).not.toHaveBeenCalledWith({ volumeKeys: true });
+    expect(setKeyMapping).not.toHaveBeenCalledWith(MAPPED);
     expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(false);
   });
 
-  // The native side still forwards volume keys to the web layer while TTS plays
-  // (iOS via a lingering KVO, Android calls onNativeKeyDown unconditionally), so
-  // handlePageFlip must itself refuse to page-flip while TTS is playing — the
-  // key should fall through to controlling the volume instead.
+  // A mapped key press can still reach the web layer after TTS starts and the
+  // mapping is released, so handlePageFlip must itself refuse to page-flip
+  // while TTS is playing — the key should control the volume instead.
   test('does not page-flip on volume keys while TTS is playing', async () => {
     const view = makeView();
     setupWithView(view);
@@ -191,4 +197,25 @@ describe('usePagination volume-key interception with TTS (#4691)', () => {
 
     expect(view.prev).toHaveBeenCalled();
   });
+
+  test('skips a section on a long-press action', async () => {
+    const view = { ...makeView(), renderer: { scrolled: false, nextSection: vi.fn() } };
+    setupWithView(view);
+
+    await act(async () => {
+      await eventDispatcher.dispatch('native-key-action', {
+        key: 'VolumeDown',
+        action: 'sectionNext',
+        longPress: true,
+      });
+    });
+
+    expect(view.renderer.nextSection).toHaveBeenCalled();
+  });
+
+  test('uses the swapped mapping from the view settings', () => {
+    h.viewSettings = { volumeKeysToFlip: true, volumeKeysSwapped: true };
+    setup();
+    expect(setKeyMapping).toHaveBeenCalledWith({ mappings: volumeKeyMappings(true) });
+  });
 });
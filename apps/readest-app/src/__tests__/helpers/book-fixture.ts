@@ -0,0 +1,5 @@
+import type { Book } from '@/types/book';
+
+/** A minimal library book; tests override only the fields they look at. */
+export const mkBook = (over: Partial<Book>): Book =>
+  ({ hash: 'h', title: 'T', author: 'A', format: 'EPUB', updatedAt: 0, ...over }) as Book;
@@ -0,0 +1,49 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { renderHook } from '@testing-library/react';
+
+type Listener = (payload: unknown) => void;
+const pluginListeners = new Map<string, Listener>();
+
+vi.mock('@tauri-apps/api/core', () => ({
+  addPluginListener: vi.fn(async (_plugin: string, event: string, cb: Listener) => {
+    pluginListeners.set(event, cb);
+    return { unregister: vi.fn() };
+  }),
+}));
+vi.mock('@tauri-apps/plugin-deep-link', () => ({ onOpenUrl: vi.fn(async () => () => {}) }));
+vi.mock('@tauri-apps/api/window', () => ({
+  getCurrentWindow: () => ({ listen: vi.fn(async () => () => {}) }),
+}));
+vi.mock('@/services/environment', async (orig) => {
+  const actual = await orig<typeof import('@/services/environment')>();
+  return { ...actual, isTauriAppPlatform: () => true };
+});
+vi.mock('@/context/EnvContext', () => ({
+  useEnv: () => ({ appService: { isAndroidApp: true } }),
+}));
+
+import { useAppUrlIngress } from '@/hooks/useAppUrlIngress';
+import { eventDispatcher } from '@/utils/event';
+
+describe('useAppUrlIngress share-import', () => {
+  beforeEach(() => pluginListeners.clear());
+
+  it('forwards shared files as SEND captures and shared links as URLs', async () => {
+    const received: unknown[] = [];
+    const onIncoming = (event: CustomEvent) => received.push(event.detail);
+    eventDispatcher.on('app-incoming-url', onIncoming);
+
+    renderHook(() => useAppUrlIngress());
+    await Promise.resolve();
+    pluginListeners.get('share-import')!({
+      files: ['/data/files/shared_imports/1-2/book.epub'],
+      urls: ['https://example.com/article'],
+    });
+
+    expect(received).toEqual([
+      { urls: ['/data/files/shared_imports/1-2/book.epub'], action: 'SEND' },
+      { urls: ['https://example.com/article'], action: undefined },
+    ]);
+    eventDispatcher.off('app-incoming-url', onIncoming);
+  });
+});
@@ -17,7 +17,10 @@ vi.mock('@/services/send/clipOptions', () => ({ getClipOptions: () => ({}) }));
 vi.mock('@/services/send/conversion/conversionWorker', () => ({
   convertToEpubWithWorker: vi.fn(),
 }));
-vi.mock('@/services/ingestService', () => ({ ingestFile: vi.fn() }));
+const ingestFileMock = vi.fn();
+vi.mock('@/services/ingestService', () => ({
+  ingestFile: (...a: unknown[]) => ingestFileMock(...a),
+}));
 
 import { useClipUrlIngress } from '@/hooks/useClipUrlIngress';
 import { eventDispatcher } from '@/utils/event';
@@ -26,6 +29,7 @@ beforeEach(() => {
   // Reject so clipAndImport short-circuits right after invoke('clip_url') —
   // we only need to observe whether the clip was attempted.
   invokeMock.mockReset().mockRejectedValue(new Error('stub'));
+  ingestFileMock.mockReset().mockResolvedValue(null);
 });
 
 describe('useClipUrlIngress deep-link routing', () => {
@@ -51,3 +55,29 @@ describe('useClipUrlIngress deep-link routing', () => {
     );
   });
 });
+
+describe('useClipUrlIngress share-extension imports', () => {
+  it('ingests shared book files into the chosen group without clipping', async () => {
+    renderHook(() => useClipUrlIngress());
+    const w = window as unknown as {
+      __readestOnShareExtensionImports?: (imports: unknown[]) => boolean;
+    };
+    const acknowledged = w.__readestOnShareExtensionImports!([
+      { path: '/tmp/SharedImports/u1/book.epub', groupId: 'g1', groupName: 'Fiction' },
+      { path: 42 },
+    ]);
+    await Promise.resolve();
+
+    expect(acknowledged).toBe(true);
+    expect(invokeMock).not.toHaveBeenCalled();
+    expect(ingestFileMock).toHaveBeenCalledTimes(1);
+    expect(ingestFileMock).toHaveBeenCalledWith(
+      expect.objectContaining({
+        file: '/tmp/SharedImports/u1/book.epub',
+        groupId: 'g1',
+        groupName: 'Fiction',
+      }),
+      expect.anything(),
+    );
+  });
+});
@@ -0,0 +1,49 @@
+import { describe, it, expect } from 'vitest';
+import { readFileSync } from 'fs';
+import { resolve } from 'path';
+
+/**
+ * The Share Extension bundled from the native-bridge plugin accepts EPUB / PDF
+ * files as well as article URLs. Its activation rule is a predicate in the
+ * committed `Info.plist` (the target points `INFOPLIST_FILE` at it, so xcodegen
+ * does not regenerate it). Same regression as the `.txt` share hang: the rule
+ * must not match text or arbitrary files, which the extension can't handle.
+ */
+
+const infoPlist = readFileSync(
+  resolve(
+    process.cwd(),
+    'src-tauri/plugins/tauri-plugin-native-bridge/ios/ShareExtension/Info.plist',
+  ),
+  'utf-8',
+);
+
+const activationRule = (): string => {
+  const match = infoPlist.match(
+    /<key>NSExtensionActivationRule<\/key>\s*<string>([\s\S]*?)<\/string>/,
+  );
+  expect(match).not.toBeNull();
+  return match![1]!.replace(/&gt;/g, '>');
+};
+
+describe('iOS Share Extension activation rule (book files)', () => {
+  it('activates for EPUB and PDF files', () => {
+    const rule = activationRule();
+    expect(rule).toContain('UTI-CONFORMS-TO "org.idpf.epub-container"');
+    expect(rule).toContain('UTI-CONFORMS-TO "com.adobe.pdf"');
+  });
+
+  it('activates for web URLs but not for file URLs', () => {
+    const rule = activationRule();
+    expect(rule).toContain('UTI-CONFORMS-TO "public.url"');
+    expect(rule).toContain(
+      'NOT ANY $attachment.registeredTypeIdentifiers UTI-CONFORMS-TO "public.file-url"',
+    );
+  });
+
+  it('never matches plain text', () => {
+    expect(infoPlist).not.toContain('NSExtensionActivationSupportsText');
+    expect(activationRule()).not.toContain('public.text');
+    expect(activationRule()).not.toContain('public.plain-text');
+  });
+});
@@ -15,9 +15,19 @@ vi.mock('@tauri-apps/api/core', () => ({
   invoke: vi.fn(),
   addPluginListener: vi.fn(),
 }));
+vi.mock('@tauri-apps/api/event', () => ({
+  listen: vi.fn(),
+}));
 
 import { invoke, addPluginListener, type PluginListener } from '@tauri-apps/api/core';
-import { getMediaSession, IOSCompositeMediaSession, TauriMediaSession } from '@/libs/mediaSession';
+import { listen } from '@tauri-apps/api/event';
+import {
+  getMediaSession,
+  IOSCompositeMediaSession,
+  MacNowPlayingMediaSession,
+  MprisMediaSession,
+  TauriMediaSession,
+} from '@/libs/mediaSession';
 import { getOSPlatform } from '@/utils/misc';
 import { isTauriAppPlatform } from '@/services/environment';
 
@@ -94,6 +104,32 @@ describe('getMediaSession', () => {
     expect(result).toBe(navigator.mediaSession);
   });
 
+  test('returns TauriMediaSession on Windows Tauri (System Media Transport Controls)', () => {
+    vi.mocked(getOSPlatform).mockReturnValue('windows');
+    vi.mocked(isTauriAppPlatform).mockReturnValue(true);
+    setNavigatorMediaSession(true);
+
+    const session = getMediaSession();
+    expect(session).toBeInstanceOf(TauriMediaSession);
+    expect(session).not.toBeInstanceOf(MprisMediaSession);
+  });
+
+  test('returns the Now Playing session on macOS Tauri', () => {
+    vi.mocked(getOSPlatform).mockReturnValue('macos');
+    vi.mocked(isTauriAppPlatform).mockReturnValue(true);
+    setNavigatorMediaSession(true);
+
+    expect(getMediaSession()).toBeInstanceOf(MacNowPlayingMediaSession);
+  });
+
+  test('returns the MPRIS session on Linux Tauri', () => {
+    vi.mocked(getOSPlatform).mockReturnValue('linux');
+    vi.mocked(isTauriAppPlatform).mockReturnValue(true);
+    setNavigatorMediaSession(true);
+
+    expect(getMediaSession()).toBeInstanceOf(MprisMediaSession);
+  });
+
   test('returns null when neither a native nor a web media session is available', () => {
     vi.mocked(getOSPlatform).mockReturnValue('linux');
     vi.mocked(isTauriAppPlatform).mockReturnValue(false);
@@ -148,6 +184,20 @@ describe('TauriMediaSession.setActive', () => {
     });
   });
 
+  test('skips the notification permission on Windows', async () => {
+    vi.mocked(getOSPlatform).mockReturnValue('windows');
+    vi.mocked(invoke).mockResolvedValue(undefined);
+
+    const session = new TauriMediaSession();
+    await session.setActive({ active: true });
+
+    expect(invoke).not.toHaveBeenCalledWith('plugin:native-tts|checkPermissions');
+    expect(invoke).toHaveBeenCalledWith('plugin:native-tts|set_media_session_active', {
+      payload: { active: true },
+    });
+    vi.mocked(getOSPlatform).mockReset();
+  });
+
   test('does not re-prompt once the permission is already decided', async () => {
     vi.mocked(invoke).mockImplementation(async (cmd: string) => {
       if (cmd === 'plugin:native-tts|checkPermissions') {
@@ -338,3 +388,103 @@ describe('TauriMediaSession media-session-toggle', () => {
     expect(toggleHandler).toHaveBeenCalled();
   });
 });
+
+describe('TauriMediaSession chapter skip', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  test('routes the native chapter events to the chapter handlers', async () => {
+    const listeners: Record<string, (payload: unknown) => void> = {};
+    vi.mocked(addPluginListener).mockImplementation((async (
+      _plugin: string,
+      event: string,
+      cb: (payload: unknown) => void,
+    ) => {
+      listeners[event] = cb;
+      return { unregister: vi.fn() } as unknown as PluginListener;
+    }) as unknown as typeof addPluginListener);
+    vi.mocked(invoke).mockResolvedValue({ postNotification: 'granted' } as unknown);
+
+    const session = new TauriMediaSession();
+    const nextChapter = vi.fn();
+    const previousChapter = vi.fn();
+    session.setActionHandler('nextchapter', nextChapter);
+    session.setActionHandler('previouschapter', previousChapter);
+    await session.setActive({ active: true });
+
+    listeners['media-session-next-chapter']!(undefined);
+    expect(nextChapter).toHaveBeenCalledTimes(1);
+    expect(previousChapter).not.toHaveBeenCalled();
+    listeners['media-session-previous-chapter']!(undefined);
+    expect(previousChapter).toHaveBeenCalledTimes(1);
+  });
+});
+
+describe('MprisMediaSession', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  test('routes MPRIS actions to the handlers and serves while active', async () => {
+    let onAction: ((event: { payload: unknown }) => void) | undefined;
+    const unlisten = vi.fn();
+    vi.mocked(listen).mockImplementation((async (
+      _event: string,
+      cb: (event: { payload: unknown }) => void,
+    ) => {
+      onAction = cb;
+      return unlisten;
+    }) as unknown as typeof listen);
+    vi.mocked(invoke).mockResolvedValue(undefined);
+
+    const session = new MprisMediaSession();
+    const toggle = vi.fn();
+    const seekTo = vi.fn();
+    session.setActionHandler('toggle', toggle);
+    session.setActionHandler('seekto', seekTo);
+    await session.setActive({ active: true });
+    expect(invoke).toHaveBeenCalledWith('mpris_set_active', { active: true });
+
+    onAction!({ payload: { action: 'toggle' } });
+    expect(toggle).toHaveBeenCalledTimes(1);
+    onAction!({ payload: { action: 'seekto', position: 42_000 } });
+    expect(seekTo).toHaveBeenCalledWith(42_000);
+    // Actions nothing is registered for are dropped.
+    onAction!({ payload: { action: 'nextchapter' } });
+
+    await session.updatePlaybackState({ playing: true, position: 1000, duration: 5000 });
+    expect(invoke).toHaveBeenCalledWith('mpris_update_state', {
+      state: { playing: true, position: 1000, duration: 5000 },
+    });
+
+    await session.setActive({ active: false });
+    expect(unlisten).toHaveBeenCalled();
+    expect(invoke).toHaveBeenCalledWith('mpris_set_active', { active: false });
+  });
+
+  test('inhibits the screen saver while speech plays', async () => {
+    vi.mocked(listen).mockResolvedValue(vi.fn());
+    vi.mocked(invoke).mockResolvedValue(undefined);
+    const inhibitCalls = () =>
+      vi.mocked(invoke).mock.calls.filter(([command]) => command === 'set_screensaver_inhibited');
+
+    const session = new MprisMediaSession();
+    await session.setActive({ active: true });
+    await session.updatePlaybackState({ playing: true, position: 0 });
+    await session.updatePlaybackState({ playing: true, position: 1000 });
+    expect(inhibitCalls()).toEqual([
+      ['set_screensaver_inhibited', { reason: 'readAloud', inhibited: true }],
+    ]);
+
+    await session.updatePlaybackState({ playing: false, position: 1000 });
+    await session.updatePlaybackState({ playing: true, position: 1000 });
+    await session.setActive({ active: false });
+    expect(inhibitCalls().map(([, args]) => args)).toEqual([
+      { reason: 'readAloud', inhibited: true },
+      { reason: 'readAloud', inhibited: false },
+      { reason: 'readAloud', inhibited: true },
+      { reason: 'readAloud', inhibited: false },
+    ]);
+  });
+});
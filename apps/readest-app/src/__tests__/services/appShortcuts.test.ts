@@ -0,0 +1,40 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { refreshAppShortcuts } from '@/services/appShortcuts';
+import { updateAppShortcuts } from '@/utils/bridge';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
+import type { AppService } from '@/types/system';
+
+vi.mock('@/utils/bridge', () => ({ updateAppShortcuts: vi.fn().mockResolvedValue(undefined) }));
+
+const appService = {
+  isMobileApp: true,
+  resolveFilePath: vi.fn().mockResolvedValue('/data/Books/'),
+} as unknown as AppService;
+
+describe('refreshAppShortcuts', () => {
+  beforeEach(() => vi.mocked(updateAppShortcuts).mockClear());
+
+  it('does nothing outside the mobile apps', async () => {
+    const desktop = { ...appService, isMobileApp: false } as AppService;
+    await refreshAppShortcuts(desktop, [mk({ progress: [1, 2] })]);
+    expect(updateAppShortcuts).not.toHaveBeenCalled();
+  });
+
+  it('publishes once until the recent books change', async () => {
+    const library = [mk({ hash: 'x', title: 'X', updatedAt: 1, progress: [1, 10] })];
+    await refreshAppShortcuts(appService, library);
+    expect(updateAppShortcuts).toHaveBeenCalledWith({
+      books: [{ hash: 'x', title: 'X', author: 'A', coverPath: '/data/Books/x/cover.png' }],
+    });
+
+    // A progress save alone changes nothing the shortcuts show.
+    await refreshAppShortcuts(appService, [{ ...library[0]!, progress: [5, 10] }]);
+    expect(updateAppShortcuts).toHaveBeenCalledTimes(1);
+
+    await refreshAppShortcuts(appService, [
+      ...library,
+      mk({ hash: 'y', updatedAt: 2, progress: [1, 10] }),
+    ]);
+    expect(updateAppShortcuts).toHaveBeenCalledTimes(2);
+  });
+});
@@ -19,6 +19,7 @@ beforeEach(() => {
   Object.assign(env, originalEnv);
   // Clean up any window globals we set
   delete (window as unknown as Record<string, unknown>)['__READEST_CLI_ACCESS'];
+  delete (window as unknown as Record<string, unknown>)['__READEST_READING_SERVER'];
   delete (window as unknown as Record<string, unknown>)['__READEST_RUNTIME_CONFIG'];
 });
 
@@ -65,6 +66,26 @@ describe('environment', () => {
     });
   });
 
+  // ── reading server client ──────────────────────────────────────
+  describe('reading server client', () => {
+    test('runs the Tauri bundle as the web app when served by the reading server', async () => {
+      env['NEXT_PUBLIC_APP_PLATFORM'] = 'tauri';
+      window.__READEST_READING_SERVER = true;
+      const { isReadingServerClient, isTauriAppPlatform, isWebAppPlatform } = await import(
+        '@/services/environment'
+      );
+      expect(isReadingServerClient()).toBe(true);
+      expect(isTauriAppPlatform()).toBe(false);
+      expect(isWebAppPlatform()).toBe(true);
+    });
+
+    test('is not a reading server client without the flag', async () => {
+      env['NEXT_PUBLIC_APP_PLATFORM'] = 'tauri';
+      const { isReadingServerClient } = await import('@/services/environment');
+      expect(isReadingServerClient()).toBe(false);
+    });
+  });
+
   // ── hasCli ─────────────────────────────────────────────────────
   describe('hasCli', () => {
     test('returns true when __READEST_CLI_ACCESS is true', async () => {
@@ -0,0 +1,64 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { revalidateFolderPermissions } from '@/services/folderPermissions';
+import { listPersistedUriPermissions, releasePersistableUriPermission } from '@/utils/bridge';
+import type { PersistedUriPermission } from '@/utils/bridge';
+
+vi.mock('@/utils/bridge', () => ({
+  listPersistedUriPermissions: vi.fn(),
+  releasePersistableUriPermission: vi.fn().mockResolvedValue({ success: true }),
+}));
+
+const grant = (over: Partial<PersistedUriPermission>): PersistedUriPermission => ({
+  uri: 'content://com.android.externalstorage.documents/tree/primary%3ABooks',
+  path: '/storage/emulated/0/Books',
+  read: true,
+  write: true,
+  persistedTime: 0,
+  accessible: true,
+  ...over,
+});
+
+describe('revalidateFolderPermissions', () => {
+  beforeEach(() => {
+    vi.mocked(releasePersistableUriPermission).mockClear();
+  });
+
+  it('keeps accessible grants', async () => {
+    vi.mocked(listPersistedUriPermissions).mockResolvedValue({ permissions: [grant({})] });
+    const lost = await revalidateFolderPermissions(['/storage/emulated/0/Books']);
+    expect(lost).toEqual([]);
+    expect(releasePersistableUriPermission).not.toHaveBeenCalled();
+  });
+
+  it('releases dead grants and reports the watched folders they covered', async () => {
+    vi.mocked(listPersistedUriPermissions).mockResolvedValue({
+      permissions: [
+        grant({ uri: 'content://x/tree/sd', path: '/storage/ABCD-1234/', accessible: false }),
+        grant({}),
+      ],
+    });
+    const lost = await revalidateFolderPermissions([
+      '/storage/ABCD-1234/Comics',
+      '/storage/ABCD-12345',
+      '/storage/emulated/0/Books/Novels',
+    ]);
+    expect(lost).toEqual(['/storage/ABCD-1234/Comics']);
+    expect(releasePersistableUriPermission).toHaveBeenCalledWith({ uri: 'content://x/tree/sd' });
+  });
+
+  it('does not report a folder another live grant still covers', async () => {
+    vi.mocked(listPersistedUriPermissions).mockResolvedValue({
+      permissions: [
+        grant({
+          uri: 'content://x/tree/sub',
+          path: '/storage/emulated/0/Books/Old',
+          accessible: false,
+        }),
+        grant({}),
+      ],
+    });
+    const lost = await revalidateFolderPermissions(['/storage/emulated/0/Books/Old']);
+    expect(lost).toEqual([]);
+    expect(releasePersistableUriPermission).toHaveBeenCalledTimes(1);
+  });
+});
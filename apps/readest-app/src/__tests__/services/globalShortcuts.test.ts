@@ -0,0 +1,22 @@
+import { describe, it, expect } from 'vitest';
+import { clipboardTextFile } from '@/services/globalShortcuts';
+
+describe('clipboardTextFile', () => {
+  it('names the file after the first line of the text', async () => {
+    const file = clipboardTextFile('  Chapter One\nIt was a dark night.\n', 'Clipping');
+    expect(file.name).toBe('Chapter One.txt');
+    expect(file.type).toBe('text/plain');
+    expect(await file.text()).toBe('Chapter One\nIt was a dark night.');
+  });
+
+  it('drops characters file names cannot hold and shortens long titles', () => {
+    expect(clipboardTextFile('Notes: a/b <draft>', 'Clipping').name).toBe('Notes a b draft.txt');
+    const name = clipboardTextFile('word '.repeat(30), 'Clipping').name;
+    expect(name.endsWith('….txt')).toBe(true);
+    expect(name.length).toBeLessThanOrEqual(60 + '….txt'.length);
+  });
+
+  it('falls back to the given title', () => {
+    expect(clipboardTextFile('???', 'Clipping').name).toBe('Clipping.txt');
+  });
+});
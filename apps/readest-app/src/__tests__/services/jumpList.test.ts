@@ -0,0 +1,42 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
+import { selectJumpListBooks, refreshJumpList } from '@/services/jumpList';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
+import type { AppService } from '@/types/system';
+
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
+
+const labels = { recentBooks: 'Recent Books', importBooks: 'Import Books…' };
+
+describe('selectJumpListBooks', () => {
+  it('sends only what the jump list shows', () => {
+    expect(selectJumpListBooks([mk({ hash: 'a', progress: [1, 2], title: 'X' })])).toEqual([
+      { hash: 'a', title: 'X', author: 'A' },
+    ]);
+  });
+});
+
+describe('refreshJumpList', () => {
+  beforeEach(() => vi.mocked(invoke).mockClear());
+
+  it('does nothing outside Windows', async () => {
+    const appService = { isWindowsApp: false } as AppService;
+    await refreshJumpList(appService, [mk({ progress: [1, 2] })], labels);
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('skips the native call when the list is unchanged', async () => {
+    const appService = { isWindowsApp: true } as AppService;
+    const library = [mk({ hash: 'c', progress: [1, 2] })];
+    await refreshJumpList(appService, library, labels);
+    await refreshJumpList(appService, [...library], labels);
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(invoke).toHaveBeenCalledWith('update_jump_list', {
+      books: [{ hash: 'c', title: 'T', author: 'A' }],
+      labels,
+    });
+
+    await refreshJumpList(appService, [mk({ hash: 'd', progress: [1, 2] })], labels);
+    expect(invoke).toHaveBeenCalledTimes(2);
+  });
+});
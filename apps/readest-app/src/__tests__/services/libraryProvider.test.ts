@@ -0,0 +1,74 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
+import {
+  EXPORTED_ANNOTATIONS_DIR,
+  keepExportedAnnotations,
+  refreshLibraryProvider,
+} from '@/services/libraryProvider';
+import type { AppService } from '@/types/system';
+
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
+
+const appService = {
+  isAndroidApp: true,
+  resolveFilePath: vi.fn(async (path: string, base: string) =>
+    base === 'Books' ? '/data/Books/' : `/data/Readest/${path}`,
+  ),
+  writeFile: vi.fn().mockResolvedValue(undefined),
+} as unknown as AppService;
+
+describe('refreshLibraryProvider', () => {
+  beforeEach(() => {
+    vi.mocked(invoke).mockClear();
+  });
+
+  it('does nothing outside the Android app', async () => {
+    await refreshLibraryProvider({ ...appService, isAndroidApp: false } as AppService, true);
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('asks for a re-index of the books and exported annotations', async () => {
+    await refreshLibraryProvider(appService, false);
+    expect(invoke).toHaveBeenCalledWith('refresh_library_provider', {
+      booksDir: '/data/Books',
+      annotationsDir: `/data/Readest/${EXPORTED_ANNOTATIONS_DIR}`,
+      shareWithApps: false,
+    });
+  });
+
+  it('passes on whether other apps may read the library', async () => {
+    await refreshLibraryProvider(appService, true);
+    expect(invoke).toHaveBeenCalledWith(
+      'refresh_library_provider',
+      expect.objectContaining({ shareWithApps: true }),
+    );
+  });
+
+  it('swallows refresh failures', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {});
+    vi.mocked(invoke).mockRejectedValueOnce(new Error('denied'));
+    await expect(refreshLibraryProvider(appService, false)).resolves.toBeUndefined();
+    expect(warn).toHaveBeenCalled();
+    warn.mockRestore();
+  });
+});
+
+describe('keepExportedAnnotations', () => {
+  beforeEach(() => {
+    vi.mocked(appService.writeFile).mockClear();
+  });
+
+  it('writes the export into the annotations directory on Android', async () => {
+    await keepExportedAnnotations(appService, 'Book.md', '# Notes');
+    expect(appService.writeFile).toHaveBeenCalledWith(
+      `${EXPORTED_ANNOTATIONS_DIR}/Book.md`,
+      'Data',
+      '# Notes',
+    );
+  });
+
+  it('does nothing elsewhere', async () => {
+    await keepExportedAnnotations({ ...appService, isAndroidApp: false }, 'Book.md', '# Notes');
+    expect(appService.writeFile).not.toHaveBeenCalled();
+  });
+});
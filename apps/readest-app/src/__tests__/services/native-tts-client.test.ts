@@ -10,7 +10,8 @@ vi.mock('@tauri-apps/api/core', () => ({
 vi.mock('@/services/tts/TTSController', () => ({ TTSController: class {} }));
 
 import { NativeTTSClient } from '@/services/tts/NativeTTSClient';
-import { invoke } from '@tauri-apps/api/core';
+import { addPluginListener, invoke } from '@tauri-apps/api/core';
+import type { TTSController } from '@/services/tts/TTSController';
 
 describe('NativeTTSClient.stop', () => {
   beforeEach(() => {
@@ -51,3 +52,111 @@ describe('NativeTTSClient.stop', () => {
     expect(settled).toBe(true);
   });
 });
+
+describe('NativeTTSClient.speak', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  test('preloads the marks after the one being spoken, in order', async () => {
+    vi.mocked(invoke).mockImplementation(async (cmd) =>
+      cmd === 'plugin:native-tts|speak' ? { utteranceId: 'u1' } : undefined,
+    );
+    const client = new NativeTTSClient();
+    const ssml =
+      '<speak xml:lang="en"><mark name="0"/>One. <mark name="1"/>Two. <mark name="2"/>Three.</speak>';
+    const controller = new AbortController();
+    const first = client.speak(ssml, controller.signal).next();
+
+    type SpeakPayload = { text: string; preload?: boolean };
+    const preloaded = () =>
+      vi
+        .mocked(invoke)
+        .mock.calls.map(([, args]) => (args as { payload?: SpeakPayload } | undefined)?.payload)
+        .filter((payload) => payload?.preload)
+        .map((payload) => payload!.text.trim());
+    await vi.waitFor(() => expect(preloaded()).toEqual(['Two.', 'Three.']));
+
+    controller.abort();
+    await first;
+  });
+
+  test('tags speech with the book whose pronunciations were set', async () => {
+    vi.mocked(invoke).mockImplementation(async (cmd) =>
+      cmd === 'plugin:native-tts|speak' ? { utteranceId: 'u1' } : undefined,
+    );
+    const client = new NativeTTSClient();
+    const entries = [{ pattern: 'Hermione', replacement: 'hɜːˈmaɪ.ə.ni', ipa: true }];
+    await client.setPronunciations(entries, 'hash1');
+    expect(invoke).toHaveBeenCalledWith('plugin:native-tts|set_pronunciations', {
+      payload: { entries, book: 'hash1' },
+    });
+
+    const controller = new AbortController();
+    const ssml = '<speak xml:lang="en"><mark name="0"/>Hermione.</speak>';
+    const first = client.speak(ssml, controller.signal).next();
+    await vi.waitFor(() =>
+      expect(invoke).toHaveBeenCalledWith('plugin:native-tts|speak', {
+        payload: expect.objectContaining({ book: 'hash1' }),
+      }),
+    );
+    controller.abort();
+    await first;
+  });
+});
+
+describe('NativeTTSClient audio route', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  test('pauses the reader when the output it played through is lost', async () => {
+    vi.mocked(invoke).mockResolvedValue({ success: true });
+    const controller = { state: 'playing', pause: vi.fn() };
+    const client = new NativeTTSClient(controller as unknown as TTSController);
+    await client.init();
+
+    await vi.waitFor(() =>
+      expect(addPluginListener).toHaveBeenCalledWith(
+        'native-tts',
+        'audio-route-changed',
+        expect.any(Function),
+      ),
+    );
+    const onRouteChanged = vi
+      .mocked(addPluginListener)
+      .mock.calls.find(([, event]) => event === 'audio-route-changed')![2];
+
+    onRouteChanged({ outputs: [], lost: false });
+    expect(controller.pause).not.toHaveBeenCalled();
+    onRouteChanged({ outputs: [], lost: true });
+    expect(controller.pause).toHaveBeenCalledTimes(1);
+  });
+});
+
+describe('NativeTTSClient system engines', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  test('lists voices again after switching engines', async () => {
+    let engine = 'com.google.android.tts';
+    vi.mocked(invoke).mockImplementation(async (cmd, args) => {
+      if (cmd === 'plugin:native-tts|set_system_engine') {
+        engine = (args as { payload: { name: string } }).payload.name;
+      }
+      if (cmd === 'plugin:native-tts|get_all_voices') {
+        return { voices: [{ id: `${engine}-en`, name: 'English', lang: 'en-US' }] };
+      }
+      return undefined;
+    });
+    const client = new NativeTTSClient();
+    expect((await client.getAllVoices())[0]!.id).toBe('com.google.android.tts-en');
+
+    await client.setSystemEngine('com.samsung.SMT');
+    expect(invoke).toHaveBeenCalledWith('plugin:native-tts|set_system_engine', {
+      payload: { name: 'com.samsung.SMT' },
+    });
+    expect((await client.getAllVoices())[0]!.id).toBe('com.samsung.SMT-en');
+  });
+});
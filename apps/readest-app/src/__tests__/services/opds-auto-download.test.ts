@@ -138,14 +138,11 @@ describe('OPDS auto-download orchestrator', () => {
     });
   });
 
-  it('downloads with skipSslVerification like the manual download path', async () => {
-    // The manual OPDS download (page.tsx handleDownload) passes
-    // skipSslVerification as a workaround for self-signed/private-CA OPDS
-    // servers (#2871): the native download_file validates TLS with rustls,
-    // which ignores the OS trust store, while the feed fetch and auth probe
-    // go through the http plugin with acceptInvalidCerts. Without the same
-    // flag here, auto-download dies in the TLS handshake on servers where
-    // manual download works (#4988).
+  it('downloads without skipping certificate checks, like the manual download path', async () => {
+    // Self-signed/private-CA OPDS servers (#2871, #4988) used to get a
+    // skipSslVerification flag that turned off every check for the
+    // download. They are now trusted per host in the native trust store,
+    // so neither download path asks for verification to be skipped.
     const catalogs: OPDSCatalog[] = [
       { id: 'cat-1', name: 'Shelf', url: 'https://shelf.example.com/opds', autoDownload: true },
     ];
@@ -162,9 +159,7 @@ describe('OPDS auto-download orchestrator', () => {
     await syncSubscribedCatalogs(catalogs, appService, []);
 
     expect(downloadFile).toHaveBeenCalledTimes(1);
-    expect(vi.mocked(downloadFile).mock.calls[0]![0]).toMatchObject({
-      skipSslVerification: true,
-    });
+    expect(vi.mocked(downloadFile).mock.calls[0]![0]).not.toHaveProperty('skipSslVerification');
   });
 
   it('handles import failure by adding to failedEntries', async () => {
@@ -0,0 +1,53 @@
+import { describe, it, expect } from 'vitest';
+import { buildPrintDocument, collectPrintDocument } from '@/services/print';
+
+describe('buildPrintDocument', () => {
+  it('escapes the title and keeps the language and direction', () => {
+    const html = buildPrintDocument({
+      title: 'Tom & Jerry <1>',
+      lang: 'ar',
+      dir: 'rtl',
+      styles: ['p { text-indent: 1em; }'],
+      body: '<p>مرحبا</p>',
+    });
+    expect(html).toContain('<html lang="ar" dir="rtl">');
+    expect(html).toContain('<title>Tom &amp; Jerry &lt;1&gt;</title>');
+    expect(html).toContain('<style>p { text-indent: 1em; }</style>');
+    expect(html).toContain('<body><p>مرحبا</p></body>');
+  });
+
+  it('puts the print overrides after the section styles', () => {
+    const html = buildPrintDocument({ title: 'Book', styles: ['body { color: red; }'], body: '' });
+    expect(html.indexOf('color: red')).toBeLessThan(html.indexOf('color: #000'));
+  });
+
+  it('keeps styles from closing their style element', () => {
+    const html = buildPrintDocument({ title: 'Book', styles: ['</style><script>'], body: '' });
+    expect(html).not.toContain('</style><script>');
+  });
+});
+
+describe('collectPrintDocument', () => {
+  const section = () =>
+    new DOMParser().parseFromString(
+      '<html lang="en"><body><h1>One</h1><p id="p">Hello <b>there</b></p>' +
+        '<script>alert(1)</script></body></html>',
+      'text/html',
+    );
+
+  it('prints the whole section without scripts', async () => {
+    const html = await collectPrintDocument(section(), 'Book');
+    expect(html).toContain('<h1>One</h1>');
+    expect(html).toContain('Hello <b>there</b>');
+    expect(html).not.toContain('alert(1)');
+  });
+
+  it('prints only the selected range', async () => {
+    const doc = section();
+    const range = doc.createRange();
+    range.selectNodeContents(doc.getElementById('p')!);
+    const html = await collectPrintDocument(doc, 'Book', range);
+    expect(html).toContain('Hello <b>there</b>');
+    expect(html).not.toContain('<h1>One</h1>');
+  });
+});
@@ -0,0 +1,22 @@
+import { describe, it, expect } from 'vitest';
+import { quoteImageFilename, toHexColor } from '@/services/quoteImage';
+
+describe('toHexColor', () => {
+  it('turns theme and highlight colors into hex', () => {
+    expect(toHexColor('#FFF')).toBe('#ffffff');
+    expect(toHexColor('yellow')).toBe('#ffff00');
+    expect(toHexColor('rgb(30, 30, 30)')).toBe('#1e1e1e');
+  });
+
+  it('drops colors it cannot read', () => {
+    expect(toHexColor('var(--highlight)')).toBeUndefined();
+    expect(toHexColor(undefined)).toBeUndefined();
+  });
+});
+
+describe('quoteImageFilename', () => {
+  it('names the image after the book', () => {
+    expect(quoteImageFilename('Moby-Dick')).toBe('Moby-Dick - Quote.png');
+    expect(quoteImageFilename('  ')).toBe('Quote - Quote.png');
+  });
+});
@@ -0,0 +1,62 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { refreshReadingIntents, startOfTodaySecs } from '@/services/readingIntents';
+import { updateReadingIntents } from '@/utils/bridge';
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+
+const getReadingSecondsSince = vi.fn();
+
+vi.mock('@/utils/bridge', () => ({ updateReadingIntents: vi.fn().mockResolvedValue(undefined) }));
+vi.mock('@/services/statistics/statisticsDb', () => ({
+  StatisticsDb: { open: vi.fn(async () => ({ getReadingSecondsSince })) },
+}));
+
+const mk = (over: Partial<Book>): Book =>
+  ({ hash: 'h', title: 'T', author: 'A', format: 'EPUB', updatedAt: 0, ...over }) as Book;
+
+const appService = { isIOSApp: true } as unknown as AppService;
+
+describe('startOfTodaySecs', () => {
+  it('returns local midnight in Unix seconds', () => {
+    const now = new Date(2026, 9, 15, 17, 30, 12);
+    expect(startOfTodaySecs(now)).toBe(new Date(2026, 9, 15).getTime() / 1000);
+  });
+});
+
+describe('refreshReadingIntents', () => {
+  beforeEach(() => {
+    vi.mocked(updateReadingIntents).mockClear();
+    getReadingSecondsSince.mockReset().mockResolvedValue(0);
+  });
+
+  it('does nothing outside the iOS app', async () => {
+    const android = { isIOSApp: false } as AppService;
+    await refreshReadingIntents(android, [mk({ progress: [1, 2] })]);
+    expect(updateReadingIntents).not.toHaveBeenCalled();
+  });
+
+  it('publishes the last read book and today reading time once per change', async () => {
+    getReadingSecondsSince.mockResolvedValue(754.4);
+    const library = [
+      mk({ hash: 'old', title: 'Old', updatedAt: 1, progress: [1, 10] }),
+      mk({ hash: 'new', title: 'New', updatedAt: 5, progress: [2, 10] }),
+    ];
+    await refreshReadingIntents(appService, library);
+    await refreshReadingIntents(appService, library);
+    expect(updateReadingIntents).toHaveBeenCalledTimes(1);
+    expect(updateReadingIntents).toHaveBeenCalledWith({
+      book: { hash: 'new', title: 'New' },
+      readingSecondsToday: 754,
+    });
+    expect(getReadingSecondsSince).toHaveBeenCalledWith(startOfTodaySecs());
+
+    getReadingSecondsSince.mockResolvedValue(815);
+    await refreshReadingIntents(appService, library);
+    expect(updateReadingIntents).toHaveBeenCalledTimes(2);
+  });
+
+  it('reports no book before anything has been opened', async () => {
+    await refreshReadingIntents(appService, [mk({ hash: 'unread' })]);
+    expect(updateReadingIntents).toHaveBeenCalledWith({ book: null, readingSecondsToday: 0 });
+  });
+});
@@ -0,0 +1,67 @@
+import { describe, it, expect, vi, afterEach } from 'vitest';
+import { applyRemoteProgress, fetchRemoteBook, pushRemoteProgress } from '@/services/readingServer';
+import type { BookConfig } from '@/types/book';
+
+const config: BookConfig = {
+  location: 'epubcfi(/6/2)',
+  xpointer: '/body/DocFragment[1]',
+  progress: [1, 300],
+  viewSettings: { zoomLevel: 120 },
+  updatedAt: 10,
+};
+
+describe('applyRemoteProgress', () => {
+  it('ignores progress that is not newer than the local config', () => {
+    expect(applyRemoteProgress(config, { hash: 'h', location: 'x', updatedAt: 10 })).toBeNull();
+    expect(applyRemoteProgress(config, { hash: 'h', location: 'x', updatedAt: 5 })).toBeNull();
+  });
+
+  it('moves the location and keeps the rest of the config', () => {
+    const updated = applyRemoteProgress(config, {
+      hash: 'h',
+      location: 'epubcfi(/6/4)',
+      progress: [12, 300],
+      updatedAt: 20,
+    });
+    expect(updated).toMatchObject({
+      location: 'epubcfi(/6/4)',
+      progress: [12, 300],
+      viewSettings: { zoomLevel: 120 },
+      updatedAt: 20,
+    });
+    expect(updated?.xpointer).toBeUndefined();
+  });
+
+  it('keeps the stored location when the remote sends none', () => {
+    const updated = applyRemoteProgress(config, { hash: 'h', progress: [5, 300], updatedAt: 20 });
+    expect(updated?.location).toBe('epubcfi(/6/2)');
+    expect(updated?.xpointer).toBe('/body/DocFragment[1]');
+  });
+});
+
+describe('reading server client', () => {
+  afterEach(() => {
+    vi.unstubAllGlobals();
+  });
+
+  it('names a fetched book after the extension the server sends', async () => {
+    vi.stubGlobal(
+      'fetch',
+      vi.fn().mockResolvedValue(
+        new Response('data', {
+          headers: { 'Content-Disposition': 'attachment; filename="book.epub"' },
+        }),
+      ),
+    );
+    const file = await fetchRemoteBook('abc');
+    expect(fetch).toHaveBeenCalledWith('/api/books/abc/file');
+    expect(file.name).toBe('book.epub');
+  });
+
+  it('treats a conflict as the app holding newer progress', async () => {
+    vi.stubGlobal('fetch', vi.fn().mockResolvedValue(new Response(null, { status: 409 })));
+    await expect(pushRemoteProgress('abc', config)).resolves.toBeUndefined();
+    vi.stubGlobal('fetch', vi.fn().mockResolvedValue(new Response(null, { status: 500 })));
+    await expect(pushRemoteProgress('abc', config)).rejects.toThrow('500');
+  });
+});
@@ -6,6 +6,7 @@ import {
 } from '@/services/widget/readingWidget';
 import type { Book } from '@/types/book';
 
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
 vi.mock('@/utils/bridge', () => ({ updateReadingWidget: vi.fn().mockResolvedValue(undefined) }));
 vi.mock('@/store/libraryStore', () => ({
   useLibraryStore: {
@@ -69,7 +70,7 @@ describe('selectReadingWidgetBooks', () => {
   });
 });
 
-import { refreshReadingWidget } from '@/services/widget/readingWidget';
+import { refreshContinueReading, refreshReadingWidget } from '@/services/widget/readingWidget';
 
 const appServiceForBuild = {
   isMobileApp: true,
@@ -133,3 +134,33 @@ describe('refreshReadingWidget', () => {
     });
   });
 });
+
+describe('refreshContinueReading', () => {
+  const appService = {
+    isAndroidApp: true,
+    resolveFilePath: vi.fn().mockResolvedValue('/data/Books'),
+  } as unknown as import('@/types/system').AppService;
+
+  it('skips when not on Android', async () => {
+    const { invoke } = await import('@tauri-apps/api/core');
+    vi.mocked(invoke).mockClear();
+    await refreshContinueReading({ ...appService, isAndroidApp: false } as never, 'Empty');
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('reports the most recently read book', async () => {
+    const { invoke } = await import('@tauri-apps/api/core');
+    vi.mocked(invoke).mockClear();
+    await refreshContinueReading(appService, 'Empty');
+    expect(invoke).toHaveBeenCalledWith('update_continue_reading', {
+      book: {
+        hash: 'a',
+        title: 'Ta',
+        author: 'Aa',
+        percent: 50,
+        coverPath: '/data/Books/a/cover.png',
+      },
+      emptyTitle: 'Empty',
+    });
+  });
+});
@@ -0,0 +1,44 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
+import { selectRecentMenuBooks, refreshRecentBooksMenus } from '@/services/recentBooksMenu';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
+import type { AppService } from '@/types/system';
+
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
+
+const labels = { continueReading: 'Continue Reading', recentBooks: 'Recent Books' };
+
+describe('selectRecentMenuBooks', () => {
+  it('reports the percentage read', () => {
+    const books = selectRecentMenuBooks([
+      mk({ hash: 'a', updatedAt: 3, progress: [45, 100] }),
+      mk({ hash: 'b', updatedAt: 2, progress: [3, 10], readingStatus: 'finished' }),
+      mk({ hash: 'c', updatedAt: 1, progress: [0, 0] }),
+    ]);
+    expect(books.map((b) => b.progress)).toEqual([45, 100, null]);
+  });
+});
+
+describe('refreshRecentBooksMenus', () => {
+  beforeEach(() => vi.mocked(invoke).mockClear());
+
+  it('does nothing outside macOS', async () => {
+    const appService = { isMacOSApp: false } as AppService;
+    await refreshRecentBooksMenus(appService, [mk({ progress: [1, 2] })], labels);
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('republishes only when the shown percentage changes', async () => {
+    const appService = { isMacOSApp: true } as AppService;
+    await refreshRecentBooksMenus(appService, [mk({ hash: 'c', progress: [100, 1000] })], labels);
+    await refreshRecentBooksMenus(appService, [mk({ hash: 'c', progress: [101, 1000] })], labels);
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(invoke).toHaveBeenCalledWith('update_recent_books_menus', {
+      books: [{ hash: 'c', title: 'T', progress: 10 }],
+      labels,
+    });
+
+    await refreshRecentBooksMenus(appService, [mk({ hash: 'c', progress: [110, 1000] })], labels);
+    expect(invoke).toHaveBeenCalledTimes(2);
+  });
+});
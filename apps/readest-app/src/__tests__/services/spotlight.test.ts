@@ -0,0 +1,55 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
+import { selectSpotlightBooks, refreshSpotlightIndex } from '@/services/spotlight';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
+import type { AppService } from '@/types/system';
+
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
+
+const macAppService = (): AppService =>
+  ({
+    isMacOSApp: true,
+    resolveFilePath: vi.fn().mockResolvedValue('/Users/me/Readest/Books'),
+  }) as unknown as AppService;
+
+describe('selectSpotlightBooks', () => {
+  it('indexes every book in the library except deleted ones', () => {
+    const books = selectSpotlightBooks([
+      mk({ hash: 'a' }),
+      mk({ hash: 'never-opened', progress: undefined }),
+      mk({ hash: 'deleted', deletedAt: 4 }),
+    ]);
+    expect(books.map((b) => b.hash)).toEqual(['a', 'never-opened']);
+  });
+
+  it('sends only what Spotlight shows', () => {
+    expect(selectSpotlightBooks([mk({ hash: 'a', title: 'X', progress: [1, 2] })])).toEqual([
+      { hash: 'a', title: 'X', author: 'A' },
+    ]);
+  });
+});
+
+describe('refreshSpotlightIndex', () => {
+  beforeEach(() => vi.mocked(invoke).mockClear());
+
+  it('does nothing outside macOS', async () => {
+    const appService = { isMacOSApp: false } as AppService;
+    await refreshSpotlightIndex(appService, [mk({})]);
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('skips the native call when no indexed field changed', async () => {
+    const appService = macAppService();
+    const library = [mk({ hash: 'c' })];
+    await refreshSpotlightIndex(appService, library);
+    await refreshSpotlightIndex(appService, [mk({ hash: 'c', progress: [5, 10] })]);
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(invoke).toHaveBeenCalledWith('update_spotlight_index', {
+      books: [{ hash: 'c', title: 'T', author: 'A' }],
+      booksDir: '/Users/me/Readest/Books',
+    });
+
+    await refreshSpotlightIndex(appService, [mk({ hash: 'c', title: 'Renamed' })]);
+    expect(invoke).toHaveBeenCalledTimes(2);
+  });
+});
@@ -0,0 +1,58 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
+import { refreshTrayMenu, setMinimizeToTray } from '@/services/tray';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
+import type { AppService } from '@/types/system';
+
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue(undefined) }));
+
+const labels = {
+  continueReading: 'Continue Reading',
+  recentBooks: 'Recent Books',
+  syncNow: 'Sync Now',
+  pauseReadAloud: 'Pause Read-Aloud',
+  show: 'Show Readest',
+  quit: 'Quit Readest',
+};
+
+describe('refreshTrayMenu', () => {
+  beforeEach(() => vi.mocked(invoke).mockClear());
+
+  it('does nothing outside the desktop apps', async () => {
+    const appService = { isDesktopApp: false } as AppService;
+    await refreshTrayMenu(appService, [mk({ progress: [1, 2] })], labels);
+    expect(invoke).not.toHaveBeenCalled();
+  });
+
+  it('publishes the recently read books once per change', async () => {
+    const appService = { isDesktopApp: true } as AppService;
+    const library = [
+      mk({ hash: 'a', updatedAt: 1, progress: [1, 10] }),
+      mk({ hash: 'b', updatedAt: 2, progress: [5, 10] }),
+    ];
+    await refreshTrayMenu(appService, library, labels);
+    await refreshTrayMenu(appService, library, labels);
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(invoke).toHaveBeenCalledWith('update_tray_menu', {
+      books: [
+        { hash: 'b', title: 'T', progress: 50 },
+        { hash: 'a', title: 'T', progress: 10 },
+      ],
+      labels,
+    });
+
+    await refreshTrayMenu(appService, library, { ...labels, syncNow: 'Synchroniser' });
+    expect(invoke).toHaveBeenCalledTimes(2);
+  });
+});
+
+describe('setMinimizeToTray', () => {
+  beforeEach(() => vi.mocked(invoke).mockClear());
+
+  it('passes the setting on to the desktop apps only', () => {
+    setMinimizeToTray({ isDesktopApp: false } as AppService, true);
+    expect(invoke).not.toHaveBeenCalled();
+    setMinimizeToTray({ isDesktopApp: true } as AppService, true);
+    expect(invoke).toHaveBeenCalledWith('set_minimize_to_tray', { enabled: true });
+  });
+});
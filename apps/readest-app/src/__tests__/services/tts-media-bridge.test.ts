@@ -22,9 +22,12 @@ class FakeController extends EventTarget {
   start = vi.fn().mockResolvedValue(undefined);
   forward = vi.fn().mockResolvedValue(undefined);
   backward = vi.fn().mockResolvedValue(undefined);
+  forwardSection = vi.fn().mockResolvedValue(undefined);
+  backwardSection = vi.fn().mockResolvedValue(undefined);
   seekToTime = vi.fn().mockResolvedValue(undefined);
   ensureTimeline = vi.fn().mockResolvedValue(null);
   getPlaybackInfo = vi.fn().mockReturnValue({ position: 12, duration: 60, measuredFraction: 1 });
+  getSectionProgress = vi.fn().mockReturnValue({ index: 2, count: 10 });
 
   emitMark(text: string, name: string) {
     this.dispatchEvent(new CustomEvent('tts-speak-mark', { detail: { text, name } }));
@@ -151,6 +154,102 @@ describe('TTSMediaBridge', () => {
     expect(controller.start).toHaveBeenCalled();
   });
 
+  test('the Tauri session skips chapters and reports where the reader is', async () => {
+    const pushed: unknown[] = [];
+    class RecordingTauriSession extends TauriMediaSession {
+      actions = new Map<string, (() => void) | ((position: number) => void)>();
+      override setActionHandler(
+        action: string,
+        handler: (() => void) | ((position: number) => void) | null,
+      ) {
+        if (handler) this.actions.set(action, handler);
+        else this.actions.delete(action);
+      }
+      override async setActive() {}
+      override async updateMetadata() {}
+      override async updatePlaybackState(state: unknown) {
+        pushed.push(state);
+      }
+    }
+    const tauriSession = new RecordingTauriSession();
+    bridge = new TTSMediaBridge(() => tauriSession as unknown as MediaSession);
+    await bridge.bind(controller as unknown as TTSController, meta());
+    (tauriSession.actions.get('nextchapter') as () => void)();
+    expect(controller.forwardSection).toHaveBeenCalled();
+    (tauriSession.actions.get('previouschapter') as () => void)();
+    expect(controller.backwardSection).toHaveBeenCalled();
+
+    bridge.unbind();
+    expect(tauriSession.actions.has('nextchapter')).toBe(false);
+    bridge = new TTSMediaBridge(() => tauriSession as unknown as MediaSession);
+    await bridge.bind(controller as unknown as TTSController, meta());
+    controller.emitMark('Hello there, reader.', '0');
+    await vi.waitFor(() =>
+      expect(pushed).toContainEqual(
+        expect.objectContaining({ seekable: true, chapterIndex: 2, chapterCount: 10 }),
+      ),
+    );
+  });
+
+  test('the Tauri session state carries the chapter label', async () => {
+    const pushed: unknown[] = [];
+    class RecordingTauriSession extends TauriMediaSession {
+      override setActionHandler() {}
+      override async setActive() {}
+      override async updateMetadata() {}
+      override async updatePlaybackState(state: unknown) {
+        pushed.push(state);
+      }
+    }
+    bridge = new TTSMediaBridge(() => new RecordingTauriSession() as unknown as MediaSession);
+    await bridge.bind(controller as unknown as TTSController, {
+      ...meta(),
+      getSectionLabel: () => 'Chapter 7',
+    });
+    controller.emitMark('Hello there, reader.', '0');
+    await vi.waitFor(() =>
+      expect(pushed).toContainEqual(expect.objectContaining({ chapterTitle: 'Chapter 7' })),
+    );
+  });
+
+  // The plugin's 'pause' for a call is dropped when it lands mid
+  // paragraph-advance; the next paragraph must not speak over the call.
+  test('an audio interruption holds the pause through a paragraph advance', async () => {
+    class RecordingTauriSession extends TauriMediaSession {
+      actions = new Map<string, unknown>();
+      override setActionHandler(action: string, handler: unknown) {
+        if (handler) this.actions.set(action, handler);
+        else this.actions.delete(action);
+      }
+      override async setActive() {}
+      override async updateMetadata() {}
+      override async updatePlaybackState() {}
+    }
+    const tauriSession = new RecordingTauriSession();
+    bridge = new TTSMediaBridge(() => tauriSession as unknown as MediaSession);
+    await bridge.bind(controller as unknown as TTSController, meta());
+    const interrupt = tauriSession.actions.get('interruption') as (i: object) => void;
+    const began = { phase: 'began', ducked: false, permanent: false, shouldResume: false };
+
+    controller.state = 'stopped'; // transit: the plugin's 'pause' is ignored
+    interrupt(began);
+    (tauriSession.actions.get('pause') as () => void)();
+    expect(controller.pause).not.toHaveBeenCalled();
+    controller.emitState('playing');
+    expect(controller.pause).toHaveBeenCalledTimes(1);
+
+    // Once paused, a play from the app goes through.
+    controller.emitState('paused');
+    controller.emitState('playing');
+    expect(controller.pause).toHaveBeenCalledTimes(1);
+
+    // A ducked interruption keeps speaking.
+    interrupt({ ...began, ducked: true });
+    controller.emitState('stopped');
+    controller.emitState('playing');
+    expect(controller.pause).toHaveBeenCalledTimes(1);
+  });
+
   test('speak-mark events update metadata and clamped position state headless', async () => {
     await bind();
     controller.getPlaybackInfo.mockReturnValue({ position: 90, duration: 60, measuredFraction: 1 });
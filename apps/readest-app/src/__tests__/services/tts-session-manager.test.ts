@@ -24,7 +24,19 @@ vi.mock('@/store/bookDataStore', () => ({
 vi.mock('@/store/settingsStore', () => ({
   useSettingsStore: { getState: () => ({ settings: { fake: true } }) },
 }));
-vi.mock('@/services/environment', () => ({ default: { env: 'test' } }));
+const isTauriAppPlatform = vi.fn().mockReturnValue(false);
+vi.mock('@/services/environment', () => ({
+  default: { env: 'test' },
+  isTauriAppPlatform: () => isTauriAppPlatform(),
+}));
+const pluginListeners: Record<string, (payload: unknown) => void> = {};
+vi.mock('@tauri-apps/api/core', () => ({
+  invoke: vi.fn(),
+  addPluginListener: vi.fn(async (_plugin: string, event: string, cb: (p: unknown) => void) => {
+    pluginListeners[event] = cb;
+    return { unregister: vi.fn() };
+  }),
+}));
 vi.mock('@/utils/bridge', () => ({
   invokeUseBackgroundAudio: vi.fn().mockResolvedValue(undefined),
 }));
@@ -32,11 +44,15 @@ vi.mock('@/utils/bridge', () => ({
 import { TTSSessionManager, getBookHashFromKey } from '@/services/tts/TTSSessionManager';
 import type { TTSController } from '@/services/tts/TTSController';
 import { eventDispatcher } from '@/utils/event';
+import { SLEEP_AT_CHAPTER_END } from '@/services/tts/sleepTimer';
+import { invoke } from '@tauri-apps/api/core';
 
 class FakeController extends EventTarget {
   state = 'playing';
   terminated = false;
   isViewAttached = true;
+  sectionIndex = 3;
+  getSectionProgress = () => ({ index: this.sectionIndex, count: 10 });
   shutdown = vi.fn().mockResolvedValue(undefined);
   detachView = vi.fn().mockImplementation(() => {
     this.isViewAttached = false;
@@ -176,6 +192,43 @@ describe('TTSSessionManager', () => {
     expect(manager.getSleepTimer()).toBeNull();
   });
 
+  test('a chapter-end sleep timer stops once the next section plays', async () => {
+    claim();
+    manager.setSleepTimer(SLEEP_AT_CHAPTER_END);
+    expect(manager.getSleepTimer()).toEqual({ timeoutSec: SLEEP_AT_CHAPTER_END, firesAt: 0 });
+    controller.emitState('stopped');
+    controller.emitState('playing');
+    expect(manager.getActiveSession()).not.toBeNull();
+    controller.sectionIndex = 4;
+    controller.emitState('stopped');
+    controller.emitState('playing');
+    await flush();
+    expect(manager.getActiveSession()).toBeNull();
+    expect(manager.getSleepTimer()).toBeNull();
+  });
+
+  test('in the app the native timer is armed and stops the session when it fires', async () => {
+    isTauriAppPlatform.mockReturnValue(true);
+    vi.mocked(invoke).mockResolvedValue({ active: true, remainingMs: 60_000, endOfChapter: false });
+    try {
+      claim();
+      manager.setSleepTimer(60);
+      await vi.waitFor(() =>
+        expect(invoke).toHaveBeenCalledWith('plugin:native-tts|set_sleep_timer', {
+          payload: { seconds: 60, endOfChapter: false, shakeToExtend: undefined },
+        }),
+      );
+      pluginListeners['sleep-timer-fired']!(undefined);
+      await flush();
+      expect(manager.getActiveSession()).toBeNull();
+      expect(invoke).toHaveBeenLastCalledWith('plugin:native-tts|set_sleep_timer', {
+        payload: { seconds: undefined, endOfChapter: false, shakeToExtend: undefined },
+      });
+    } finally {
+      isTauriAppPlatform.mockReturnValue(false);
+    }
+  });
+
   test('headless persistence writes through setConfig and flushes to disk on stop', async () => {
     vi.useFakeTimers();
     claim();
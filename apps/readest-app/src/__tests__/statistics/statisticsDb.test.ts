@@ -197,6 +197,16 @@ describe('StatisticsDb', () => {
     // Sorted: [10, 20, 30, 40, 50, 60] -> (30 + 40) / 2 = 35.
     expect(await stats.getMedianPageDurationSecs(id)).toBe(35);
   });
+
+  it('sums reading time across books since a start time', async () => {
+    const a = await stats.upsertBook({ bookMd5: 'since-a', title: 'A', authors: 'A' });
+    const b = await stats.upsertBook({ bookMd5: 'since-b', title: 'B', authors: 'B' });
+    await stats.insertPageEvent(a, { page: 1, startTime: 900, duration: 40, totalPages: 9 });
+    await stats.insertPageEvent(a, { page: 2, startTime: 1000, duration: 30, totalPages: 9 });
+    await stats.insertPageEvent(b, { page: 1, startTime: 1200, duration: 20, totalPages: 9 });
+    expect(await stats.getReadingSecondsSince(1000)).toBe(50);
+    expect(await stats.getReadingSecondsSince(5000)).toBe(0);
+  });
 });
 
 describe('StatisticsDb.open', () => {
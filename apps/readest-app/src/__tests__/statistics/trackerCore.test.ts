@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { TrackerCore } from '@/services/statistics/trackerCore';
+import { TrackerCore, resolveIdleTimer } from '@/services/statistics/trackerCore';
 import { DEFAULT_STATS_TRACKING_CONFIG } from '@/types/statistics';
 
 const cfg = DEFAULT_STATS_TRACKING_CONFIG;
@@ -36,6 +36,12 @@ describe('TrackerCore', () => {
     ]);
   });
 
+  it('ends an idle event at the last input', () => {
+    const t = new TrackerCore(cfg);
+    t.onPage(3, 10, 0);
+    expect(t.onIdle(120, 45)).toEqual([{ page: 3, startTime: 0, duration: 45, totalPages: 10 }]);
+  });
+
   it('flushes on hide and on close without double-counting', () => {
     const t = new TrackerCore(cfg);
     t.onPage(7, 10, 0);
@@ -43,3 +49,18 @@ describe('TrackerCore', () => {
     expect(t.onClose(99)).toEqual([]); // already flushed + paused by hide
   });
 });
+
+describe('resolveIdleTimer', () => {
+  it('waits out the timeout while the system still sees input', () => {
+    expect(resolveIdleTimer(120, 30)).toEqual({ kind: 'rearm', inSeconds: 90 });
+  });
+
+  it('goes idle as of the last input once the system has been idle long enough', () => {
+    expect(resolveIdleTimer(120, 300)).toEqual({ kind: 'idle', idleSeconds: 300 });
+    expect(resolveIdleTimer(120, 120)).toEqual({ kind: 'idle', idleSeconds: 120 });
+  });
+
+  it('goes idle now where the system idle time is unknown', () => {
+    expect(resolveIdleTimer(120, null)).toEqual({ kind: 'idle', idleSeconds: 0 });
+  });
+});
@@ -1,9 +1,12 @@
 import { describe, test, expect, beforeEach, vi } from 'vitest';
 import { useDeviceControlStore } from '@/store/deviceStore';
+import { volumeKeyMappings } from '@/utils/keybinding';
 
 // Mock bridge functions
 vi.mock('@/utils/bridge', () => ({
   interceptKeys: vi.fn(),
+  setKeyMapping: vi.fn(),
+  listenToMappedKeys: vi.fn(() => Promise.resolve()),
   getScreenBrightness: vi.fn(),
   setScreenBrightness: vi.fn(),
 }));
@@ -22,6 +25,7 @@ beforeEach(() => {
     backKeyIntercepted: false,
     volumeKeysInterceptionCount: 0,
     backKeyInterceptionCount: 0,
+    volumeKeyMappings: volumeKeyMappings(),
     pageTurnerKeysIntercepted: false,
     pageTurnerKeysInterceptionCount: 0,
   });
@@ -35,22 +39,22 @@ describe('deviceStore', () => {
   // ── Volume key interception ────────────────────────────────────
   describe('acquireVolumeKeyInterception', () => {
     test('sets volumeKeysIntercepted to true on first acquire', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
 
       expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
       expect(useDeviceControlStore.getState().volumeKeysInterceptionCount).toBe(1);
-      expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: true });
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings: volumeKeyMappings() });
     });
 
     test('increments count without re-intercepting on subsequent acquires', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
 
       expect(useDeviceControlStore.getState().volumeKeysInterceptionCount).toBe(2);
-      // interceptKeys called only once (on first acquire)
-      expect(interceptKeys).toHaveBeenCalledTimes(1);
+      // setKeyMapping called only once (on first acquire)
+      expect(setKeyMapping).toHaveBeenCalledTimes(1);
     });
 
     test('sets window.onNativeKeyDown handler', () => {
@@ -61,34 +65,55 @@ describe('deviceStore', () => {
 
   describe('releaseVolumeKeyInterception', () => {
     test('releases interception when count reaches zero', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
       useDeviceControlStore.getState().releaseVolumeKeyInterception();
 
       expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(false);
       expect(useDeviceControlStore.getState().volumeKeysInterceptionCount).toBe(0);
-      expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: false });
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings: [] });
     });
 
     test('decrements count without releasing when count > 1', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
       useDeviceControlStore.getState().releaseVolumeKeyInterception();
 
       expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(true);
       expect(useDeviceControlStore.getState().volumeKeysInterceptionCount).toBe(1);
-      // interceptKeys(false) should NOT have been called
-      expect(interceptKeys).not.toHaveBeenCalledWith({ volumeKeys: false });
+      // the mapping should NOT have been released
+      expect(setKeyMapping).not.toHaveBeenCalledWith({ mappings: [] });
     });
 
     test('does not go below zero', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().releaseVolumeKeyInterception();
 
       expect(useDeviceControlStore.getState().volumeKeysInterceptionCount).toBe(0);
       expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(false);
-      expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: false });
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings: [] });
+    });
+  });
+
+  describe('setVolumeKeyMappings', () => {
+    test('sends the mappings at once while the volume keys are intercepted', async () => {
+      const { setKeyMapping } = await import('@/utils/bridge');
+      const mappings = volumeKeyMappings(true, 'section');
+      useDeviceControlStore.getState().acquireVolumeKeyInterception();
+      useDeviceControlStore.getState().setVolumeKeyMappings(mappings);
+
+      expect(setKeyMapping).toHaveBeenLastCalledWith({ mappings });
+    });
+
+    test('keeps the mappings for the next acquire otherwise', async () => {
+      const { setKeyMapping } = await import('@/utils/bridge');
+      const mappings = volumeKeyMappings(false, 'none');
+      useDeviceControlStore.getState().setVolumeKeyMappings(mappings);
+      expect(setKeyMapping).not.toHaveBeenCalled();
+
+      useDeviceControlStore.getState().acquireVolumeKeyInterception();
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings });
     });
   });
 
@@ -151,7 +176,7 @@ describe('deviceStore', () => {
   // ── Combined volume + back key usage ───────────────────────────
   describe('combined interception', () => {
     test('volume and back key interceptions are independent', async () => {
-      const { interceptKeys } = await import('@/utils/bridge');
+      const { interceptKeys, setKeyMapping } = await import('@/utils/bridge');
       useDeviceControlStore.getState().acquireVolumeKeyInterception();
       useDeviceControlStore.getState().acquireBackKeyInterception();
 
@@ -162,9 +187,9 @@ describe('deviceStore', () => {
       expect(useDeviceControlStore.getState().volumeKeysIntercepted).toBe(false);
       expect(useDeviceControlStore.getState().backKeyIntercepted).toBe(true);
 
-      expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: true });
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings: volumeKeyMappings() });
       expect(interceptKeys).toHaveBeenCalledWith({ backKey: true });
-      expect(interceptKeys).toHaveBeenCalledWith({ volumeKeys: false });
+      expect(setKeyMapping).toHaveBeenCalledWith({ mappings: [] });
     });
   });
 
@@ -257,6 +282,14 @@ describe('deviceStore', () => {
       expect(interceptKeys).toHaveBeenCalledTimes(1);
     });
 
+    test('sends the bound keys to the bridge', async () => {
+      const { interceptKeys } = await import('@/utils/bridge');
+      useDeviceControlStore.getState().setPageTurnerBindings(['ArrowRight', 'MouseBack']);
+      expect(interceptKeys).toHaveBeenCalledWith({
+        pageTurnerBindings: ['ArrowRight', 'MouseBack'],
+      });
+    });
+
     test('setKeyLearnMode toggles learn mode through the bridge', async () => {
       const { interceptKeys } = await import('@/utils/bridge');
       useDeviceControlStore.getState().setKeyLearnMode(true);
@@ -44,6 +44,7 @@ function makeSettings(overrides: Partial<SystemSettings> = {}): SystemSettings {
     savedBookCoverForLockScreenPath: '',
     telemetryEnabled: false,
     discordRichPresenceEnabled: false,
+    shareLibraryWithApps: false,
     libraryViewMode: 'grid',
     librarySortBy: 'updated',
     librarySortAscending: false,
@@ -1,6 +1,7 @@
 import { describe, expect, it } from 'vitest';
 
-import { formatSeries } from '@/utils/book';
+import { formatSeries, selectRecentBooks } from '@/utils/book';
+import { mkBook as mk } from '@/__tests__/helpers/book-fixture';
 
 describe('formatSeries', () => {
   it('returns an empty string when there is no series name', () => {
@@ -32,3 +33,34 @@ describe('formatSeries', () => {
     expect(formatSeries('Harry Potter', Number.NaN)).toBe('Harry Potter');
   });
 });
+
+describe('selectRecentBooks', () => {
+  it('keeps opened books, most recently read first', () => {
+    const books = selectRecentBooks(
+      [
+        mk({ hash: 'a', updatedAt: 1, progress: [1, 10] }),
+        mk({ hash: 'b', updatedAt: 3, progress: [2, 10] }),
+        mk({ hash: 'never-opened', updatedAt: 5 }),
+        mk({ hash: 'deleted', updatedAt: 4, progress: [1, 10], deletedAt: 4 }),
+      ],
+      10,
+    );
+    expect(books.map((b) => b.hash)).toEqual(['b', 'a']);
+  });
+
+  it('includes finished books and caps the count', () => {
+    const library = Array.from({ length: 5 }, (_, i) =>
+      mk({ hash: `${i}`, updatedAt: i, progress: [1, 1], readingStatus: 'finished' }),
+    );
+    expect(selectRecentBooks(library, 2).map((b) => b.hash)).toEqual(['4', '3']);
+  });
+
+  it('leaves the library order untouched', () => {
+    const library = [
+      mk({ hash: 'a', updatedAt: 1, progress: [1, 10] }),
+      mk({ hash: 'b', updatedAt: 2, progress: [1, 10] }),
+    ];
+    selectRecentBooks(library, 10);
+    expect(library.map((b) => b.hash)).toEqual(['a', 'b']);
+  });
+});
@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { parseBookDeepLink } from '@/utils/deeplink';
+import { isImportBooksDeepLink, parseBookDeepLink, parseLookupDeepLink } from '@/utils/deeplink';
 
 describe('parseBookDeepLink', () => {
   it('parses the custom-scheme book-open form', () => {
@@ -28,3 +28,28 @@ describe('parseBookDeepLink', () => {
     expect(parseBookDeepLink('not a url')).toBeNull();
   });
 });
+
+describe('isImportBooksDeepLink', () => {
+  it('matches the jump list import task', () => {
+    expect(isImportBooksDeepLink('readest://import')).toBe(true);
+  });
+  it('ignores other urls', () => {
+    expect(isImportBooksDeepLink('readest://book/abc123')).toBe(false);
+    expect(isImportBooksDeepLink('https://web.readest.com/import')).toBe(false);
+    expect(isImportBooksDeepLink('not a url')).toBe(false);
+  });
+});
+
+describe('parseLookupDeepLink', () => {
+  it('returns the query of the Services lookup', () => {
+    expect(parseLookupDeepLink('readest://lookup?q=serendipity')).toBe('serendipity');
+    expect(parseLookupDeepLink('readest://lookup?q=ad%20hoc%20caf%C3%A9')).toBe('ad hoc café');
+  });
+  it('ignores empty queries and other urls', () => {
+    expect(parseLookupDeepLink('readest://lookup?q=%20')).toBeNull();
+    expect(parseLookupDeepLink('readest://lookup')).toBeNull();
+    expect(parseLookupDeepLink('readest://book/abc123?q=word')).toBeNull();
+    expect(parseLookupDeepLink('https://web.readest.com/lookup?q=word')).toBeNull();
+    expect(parseLookupDeepLink('not a url')).toBeNull();
+  });
+});
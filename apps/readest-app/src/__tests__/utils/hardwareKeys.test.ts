@@ -4,6 +4,8 @@ import {
   normalizeDomKeyEvent,
   matchesBinding,
   resolvePageTurn,
+  boundNativeKeys,
+  volumeKeyMappings,
 } from '@/utils/keybinding';
 import { HardwarePageTurnerSettings } from '@/types/settings';
 
@@ -25,6 +27,21 @@ describe('normalizeNativeKey', () => {
   });
 });
 
+describe('normalizeNativeKey for clicker remotes', () => {
+  test('labels the arrow and mouse buttons remotes send', () => {
+    expect(normalizeNativeKey('ArrowRight').label).toBe('Arrow Right');
+    expect(normalizeNativeKey('MouseBack').label).toBe('Mouse Back');
+  });
+
+  test('renames keys Android used to send by key code', () => {
+    expect(normalizeNativeKey('Keycode22')).toEqual({
+      source: 'native',
+      id: 'ArrowRight',
+      label: 'Arrow Right',
+    });
+  });
+});
+
 describe('normalizeDomKeyEvent', () => {
   test('uses event.code and a friendly label for a known key', () => {
     const event = { code: 'ArrowLeft', key: 'ArrowLeft' } as KeyboardEvent;
@@ -60,6 +77,11 @@ describe('matchesBinding', () => {
     expect(matchesBinding(binding, { source: 'dom', id: 'MediaNext' })).toBe(false);
   });
 
+  test('matches a binding saved under a legacy key code', () => {
+    const legacy = { source: 'native' as const, id: 'Keycode93', label: 'Keycode93' };
+    expect(matchesBinding(legacy, { source: 'native', id: 'PageDown' })).toBe(true);
+  });
+
   test('rejects a null binding', () => {
     expect(matchesBinding(null, { source: 'native', id: 'MediaNext' })).toBe(false);
   });
@@ -106,3 +128,44 @@ describe('resolvePageTurn', () => {
     expect(resolvePageTurn(disabled, { source: 'native', id: 'MediaNext' })).toBeNull();
   });
 });
+
+describe('boundNativeKeys', () => {
+  test('lists each bound native key once, normalized', () => {
+    const settings: HardwarePageTurnerSettings = {
+      enabled: true,
+      bindings: {
+        pagePrev: { source: 'native', id: 'Keycode21', label: 'Keycode21' },
+        pageNext: { source: 'native', id: 'ArrowRight', label: 'Arrow Right' },
+        sectionPrev: { source: 'dom', id: 'PageUp', label: 'Page Up' },
+        sectionNext: { source: 'native', id: 'MouseForward', label: 'Mouse Forward' },
+        refresh: { source: 'native', id: 'ArrowRight', label: 'Arrow Right' },
+      },
+    };
+    expect(boundNativeKeys(settings)).toEqual(['ArrowLeft', 'ArrowRight', 'MouseForward']);
+  });
+});
+
+describe('volumeKeyMappings', () => {
+  test('turns back with volume up and forward with volume down, repeating when held', () => {
+    expect(volumeKeyMappings()).toEqual([
+      { key: 'VolumeUp', action: 'pagePrev', longPress: 'repeat', repeatIntervalMs: 300 },
+      { key: 'VolumeDown', action: 'pageNext', longPress: 'repeat', repeatIntervalMs: 300 },
+    ]);
+  });
+
+  test('swaps the directions of the keys', () => {
+    const [up, down] = volumeKeyMappings(true);
+    expect(up).toMatchObject({ key: 'VolumeUp', action: 'pageNext' });
+    expect(down).toMatchObject({ key: 'VolumeDown', action: 'pagePrev' });
+  });
+
+  test('skips a section in the key direction on a long press', () => {
+    const [up, down] = volumeKeyMappings(true, 'section');
+    expect(up.longPress).toBe('sectionNext');
+    expect(down.longPress).toBe('sectionPrev');
+  });
+
+  test('passes the other long-press modes through', () => {
+    expect(volumeKeyMappings(false, 'none').map((m) => m.longPress)).toEqual(['none', 'none']);
+  });
+});
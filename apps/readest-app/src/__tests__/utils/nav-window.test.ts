@@ -13,6 +13,8 @@ vi.mock('@tauri-apps/api/webviewWindow', () => ({
   },
 }));
 
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue('') }));
+
 vi.mock('@tauri-apps/api/window', () => ({
   getCurrentWindow: () => ({ label: 'main' }),
   ScrollBarStyle: {},
@@ -24,7 +26,7 @@ vi.mock('@/services/environment', () => ({
   isPWA: () => false,
 }));
 
-import { showReaderWindow } from '@/utils/nav';
+import { showLibraryWindow } from '@/utils/nav';
 
 const makeAppService = (os: 'macos' | 'windows' | 'linux'): AppService =>
   ({
@@ -34,24 +36,25 @@ const makeAppService = (os: 'macos' | 'windows' | 'linux'): AppService =>
     osPlatform: os,
   }) as unknown as AppService;
 
-// Regression (#3682): reader/extra windows opened via nav.ts must also be
+// Regression (#3682): extra windows opened via nav.ts must also be
 // opaque on Linux — a transparent WebKitGTK window goes invisible when the web
 // process is busy. Only macOS (native decorations) stays non-transparent by
-// design; Windows keeps its existing behavior.
+// design; Windows keeps its existing behavior. Book windows are built in
+// `book_window.rs`, which keeps them opaque on Linux too.
 describe('nav.ts window transparency', () => {
   beforeEach(() => {
     webviewWindowCtor.mockClear();
   });
 
-  test('Linux reader window is not transparent', () => {
-    showReaderWindow(makeAppService('linux'), ['book-1']);
+  test('Linux window is not transparent', () => {
+    showLibraryWindow(makeAppService('linux'), ['book-1.epub']);
     expect(webviewWindowCtor).toHaveBeenCalledTimes(1);
     const options = webviewWindowCtor.mock.calls[0]![1] as Record<string, unknown>;
     expect(options['transparent']).toBe(false);
   });
 
-  test('macOS reader window is not transparent (native decorations)', () => {
-    showReaderWindow(makeAppService('macos'), ['book-1']);
+  test('macOS window is not transparent (native decorations)', () => {
+    showLibraryWindow(makeAppService('macos'), ['book-1.epub']);
     const options = webviewWindowCtor.mock.calls[0]![1] as Record<string, unknown>;
     expect(options['transparent']).toBe(false);
   });
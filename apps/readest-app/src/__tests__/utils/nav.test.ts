@@ -6,6 +6,8 @@ vi.mock('next/navigation', () => ({
   redirect: vi.fn(),
 }));
 
+vi.mock('@tauri-apps/api/core', () => ({ invoke: vi.fn().mockResolvedValue('reader-book1') }));
+
 vi.mock('@tauri-apps/api/window', () => ({
   getCurrentWindow: vi.fn().mockReturnValue({ label: 'main', close: vi.fn() }),
 }));
@@ -30,6 +32,7 @@ vi.mock('@/services/constants', () => ({
 }));
 
 import { redirect } from 'next/navigation';
+import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { isPWA, isTauriAppPlatform, isWebAppPlatform } from '@/services/environment';
@@ -313,20 +316,30 @@ describe('navigateToUpdatePassword', () => {
 });
 
 describe('showReaderWindow', () => {
-  test('creates a new WebviewWindow with correct URL', () => {
+  test('opens the books through the book window command', () => {
+    showReaderWindow(['book1', 'book2']);
+
+    expect(invoke).toHaveBeenCalledWith('open_book_window', { bookIds: ['book1', 'book2'] });
+    expect(WebviewWindow).not.toHaveBeenCalled();
+  });
+});
+
+describe('showLibraryWindow', () => {
+  test('creates a new WebviewWindow with file params', () => {
     const appService = makeAppService();
-    showReaderWindow(appService as never, ['book1', 'book2']);
+    showLibraryWindow(appService as never, ['file1.epub', 'file2.epub']);
 
     expect(WebviewWindow).toHaveBeenCalled();
     const constructorCall = vi.mocked(WebviewWindow).mock.calls[0]!;
     const url = constructorCall[1]!.url as string;
-    expect(url).toContain('/reader?');
-    expect(url).toContain('ids=book1%2Bbook2');
+    expect(url).toContain('/library?');
+    expect(url).toContain('file=file1.epub');
+    expect(url).toContain('file=file2.epub');
   });
 
   test('uses macOS-specific window options', () => {
     const appService = makeAppService(true);
-    showReaderWindow(appService as never, ['book1']);
+    showLibraryWindow(appService as never, ['file1.epub']);
 
     const constructorCall = vi.mocked(WebviewWindow).mock.calls[0]!;
     const options = constructorCall[1]!;
@@ -337,7 +350,7 @@ describe('showReaderWindow', () => {
 
   test('uses non-macOS window options', () => {
     const appService = makeAppService(false);
-    showReaderWindow(appService as never, ['book1']);
+    showLibraryWindow(appService as never, ['file1.epub']);
 
     const constructorCall = vi.mocked(WebviewWindow).mock.calls[0]!;
     const options = constructorCall[1]!;
@@ -348,20 +361,6 @@ describe('showReaderWindow', () => {
   });
 });
 
-describe('showLibraryWindow', () => {
-  test('creates a new WebviewWindow with file params', () => {
-    const appService = makeAppService();
-    showLibraryWindow(appService as never, ['file1.epub', 'file2.epub']);
-
-    expect(WebviewWindow).toHaveBeenCalled();
-    const constructorCall = vi.mocked(WebviewWindow).mock.calls[0]!;
-    const url = constructorCall[1]!.url as string;
-    expect(url).toContain('/library?');
-    expect(url).toContain('file=file1.epub');
-    expect(url).toContain('file=file2.epub');
-  });
-});
-
 describe('ensureMainLibraryWindow', () => {
   test('shows and focuses the existing main window when present', async () => {
     const main = {
@@ -19,10 +19,11 @@ import { useTranslation } from '@/hooks/useTranslation';
 import { useTrafficLightStore } from '@/store/trafficLightStore';
 import { getBaseUrl, isTauriAppPlatform } from '@/services/environment';
 import { onOpenUrl } from '@tauri-apps/plugin-deep-link';
-import { start, cancel, onUrl, onInvalidUrl } from '@fabianlars/tauri-plugin-oauth';
+import { cancel } from '@fabianlars/tauri-plugin-oauth';
 import { openUrl } from '@tauri-apps/plugin-opener';
 import { invoke } from '@tauri-apps/api/core';
-import { handleAuthCallback, parseOAuthCallbackUrl } from '@/helpers/auth';
+import { listen } from '@tauri-apps/api/event';
+import { handleAuthCallback, OAuthCallbackParams, parseOAuthCallbackUrl } from '@/helpers/auth';
 import { getUserProfilePlan } from '@/utils/access';
 import { getAppleIdAuth, Scope } from './utils/appleIdAuth';
 import { authWithCustomTab, authWithSafari } from './utils/nativeAuth';
@@ -35,6 +36,16 @@ interface SingleInstancePayload {
   cwd: string;
 }
 
+// Returned by the native `start_server` loopback listener.
+interface LoopbackSession {
+  port: number;
+  redirectUri: string;
+  state: string;
+}
+
+// Payload of `oauth://callback`: the parsed tokens, never the raw URL.
+type LoopbackCallback = Partial<Record<keyof OAuthCallbackParams, string>>;
+
 interface ProviderLoginProp {
   provider: OAuthProvider;
   handleSignIn: (provider: OAuthProvider) => Promise<void>;
@@ -79,6 +90,7 @@ export default function AuthPage() {
   const { isTrafficLightVisible } = useTrafficLightStore();
   const { settings, setSettings, saveSettings } = useSettingsStore();
   const [port, setPort] = useState<number | null>(null);
+  const loopbackState = useRef<string | null>(null);
   const [isMounted, setIsMounted] = useState(false);
   const isOAuthServerRunning = useRef(false);
   const useCustomeOAuth = useRef(false);
@@ -102,8 +114,9 @@ export default function AuthPage() {
     }
     // For development env on Desktop, use a custom OAuth callback server
     // it's possible to register a custom URL scheme for the app
-    // but this is not supported by macOS, so we use a local server instead
-    return `http://localhost:${port}`;
+    // but this is not supported by macOS, so we use a local server instead.
+    // Supabase doesn't echo `state`, so it rides along in the redirect URI.
+    return `http://localhost:${port}?state=${loopbackState.current}`;
   };
 
   const getWebRedirectTo = () => {
@@ -172,8 +185,18 @@ export default function AuthPage() {
 
   const handleOAuthUrl = async (url: string) => {
     console.log('Handle OAuth URL:', url);
-    const { accessToken, refreshToken, type, next, error, errorCode, errorDescription } =
-      parseOAuthCallbackUrl(url);
+    handleOAuthParams(parseOAuthCallbackUrl(url));
+  };
+
+  const handleOAuthParams = ({
+    accessToken,
+    refreshToken,
+    type,
+    next,
+    error,
+    errorCode,
+    errorDescription,
+  }: OAuthCallbackParams) => {
     if (error) {
       console.error('OAuth callback error:', error, errorCode, errorDescription);
       handleAuthCallback({ error, errorCode, errorDescription, login, navigate: router.push });
@@ -216,13 +239,21 @@ export default function AuthPage() {
           });
         });
       } else {
-        const port = await start();
-        setPort(port);
-        console.log(`OAuth server started on port ${port}`);
+        const session = await invoke<LoopbackSession>('start_server');
+        loopbackState.current = session.state;
+        setPort(session.port);
+        console.log(`OAuth server started on port ${session.port}`);
 
-        await onUrl(handleOAuthUrl);
-        await onInvalidUrl((url) => {
-          console.log('Received invalid OAuth URL:', url);
+        await listen<LoopbackCallback>('oauth://callback', ({ payload }) => {
+          handleOAuthParams({
+            accessToken: payload.accessToken ?? null,
+            refreshToken: payload.refreshToken ?? null,
+            type: payload.type ?? null,
+            next: payload.next ?? null,
+            error: payload.error ?? null,
+            errorCode: payload.errorCode ?? null,
+            errorDescription: payload.errorDescription ?? null,
+          });
         });
       }
     } catch (error) {
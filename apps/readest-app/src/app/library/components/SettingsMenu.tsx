@@ -157,6 +157,11 @@ const SettingsMenu: React.FC<SettingsMenuProps> = ({ onPullLibrary, setIsDropdow
     setIsDropdownOpen?.(false);
   };
 
+  const toggleMinimizeToTray = () => {
+    saveSysSettings(envConfig, 'minimizeToTray', !settings.minimizeToTray);
+    setIsDropdownOpen?.(false);
+  };
+
   const toggleAlwaysShowStatusBar = () => {
     const newValue = !settings.alwaysShowStatusBar;
     saveSysSettings(envConfig, 'alwaysShowStatusBar', newValue);
@@ -408,6 +413,13 @@ const SettingsMenu: React.FC<SettingsMenuProps> = ({ onPullLibrary, setIsDropdow
       {appService?.hasWindow && (
         <MenuItem label={_('Always on Top')} toggled={isAlwaysOnTop} onClick={toggleAlwaysOnTop} />
       )}
+      {(appService?.isWindowsApp || appService?.isLinuxApp) && (
+        <MenuItem
+          label={_('Minimize to Tray')}
+          toggled={settings.minimizeToTray}
+          onClick={toggleMinimizeToTray}
+        />
+      )}
       {appService?.isMobileApp && (
         <MenuItem
           label={_('Always Show Status Bar')}
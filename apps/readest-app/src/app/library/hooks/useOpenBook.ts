@@ -86,7 +86,7 @@ export const useOpenBook = ({ setLoading, handleBookDownload }: UseOpenBookOptio
       const available = await makeBookAvailable(book);
       if (!available) return;
       if (appService?.hasWindow && settings.openBookInNewWindow) {
-        showReaderWindow(appService, [book.hash]);
+        showReaderWindow([book.hash]);
       } else {
         setTimeout(() => {
           navigateToReader(router, [book.hash]);
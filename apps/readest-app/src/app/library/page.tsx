@@ -22,6 +22,8 @@ import { eventDispatcher } from '@/utils/event';
 import { ProgressPayload } from '@/utils/transfer';
 import { throttle } from '@/utils/throttle';
 import { transferManager } from '@/services/transferManager';
+import { finishTaskbarProgress, reportTaskbarProgress } from '@/services/taskbarProgress';
+import { notifyJobFinished } from '@/services/jobNotifications';
 import { isReadestCloudStorageActive } from '@/services/sync/cloudSyncProvider';
 import { getDirPath, getFilename, joinPaths } from '@/utils/path';
 import { parseOpenWithFiles } from '@/helpers/openWith';
@@ -56,6 +58,15 @@ import { useOpenWithBooks } from '@/hooks/useOpenWithBooks';
 import { useOpenAnnotationLink } from '@/hooks/useOpenAnnotationLink';
 import { useOpenBookLink } from '@/hooks/useOpenBookLink';
 import { useReadingWidget } from '@/hooks/useReadingWidget';
+import { useJumpList } from '@/hooks/useJumpList';
+import { useSpotlightIndex } from '@/hooks/useSpotlightIndex';
+import { useRecentBooksMenu } from '@/hooks/useRecentBooksMenu';
+import { useAppShortcuts } from '@/hooks/useAppShortcuts';
+import { useReadingIntents } from '@/hooks/useReadingIntents';
+import { useLibraryProvider } from '@/hooks/useLibraryProvider';
+import { useFolderPermissions } from '@/hooks/useFolderPermissions';
+import { useTray } from '@/hooks/useTray';
+import { useGlobalShortcuts } from '@/hooks/useGlobalShortcuts';
 import { useOpenShareLink } from '@/hooks/useOpenShareLink';
 import { useClipUrlIngress } from '@/hooks/useClipUrlIngress';
 import { useKeyDownActions } from '@/hooks/useKeyDownActions';
@@ -74,6 +85,7 @@ import { LibraryGroupByType } from '@/types/settings';
 import { BookMetadata } from '@/libs/document';
 import { AboutWindow } from '@/components/AboutWindow';
 import { KeyboardShortcutsHelp } from '@/components/KeyboardShortcutsHelp';
+import { LookupDictionary } from '@/components/LookupDictionary';
 import { BookDetailModal } from '@/components/metadata';
 import { UpdaterWindow } from '@/components/UpdaterWindow';
 import { CatalogDialog } from './components/OPDSDialog';
@@ -86,6 +98,7 @@ import { BackupWindow } from './components/BackupWindow';
 import { CacheManagerWindow } from './components/CacheManagerWindow';
 import { useDragDropImport } from './hooks/useDragDropImport';
 import { useTransferQueue } from '@/hooks/useTransferQueue';
+import { useReadingServerProgress } from '@/hooks/useReadingServerProgress';
 import { useAppRouter } from '@/hooks/useAppRouter';
 import { Toast } from '@/components/Toast';
 import {
@@ -296,11 +309,23 @@ const LibraryPageContent = ({ searchParams }: { searchParams: ReadonlyURLSearchP
   useOpenAnnotationLink();
   useOpenBookLink();
   useReadingWidget();
+  useJumpList(() => handleImportBooksFromFiles());
+  useSpotlightIndex();
+  useRecentBooksMenu();
+  useAppShortcuts();
+  useReadingIntents();
+  useLibraryProvider();
+  useFolderPermissions();
   useOpenShareLink();
   useClipUrlIngress();
+  useGlobalShortcuts();
   useTransferQueue(libraryLoaded);
+  useReadingServerProgress(true);
 
   const { pullLibrary, pushLibrary } = useBooksSync();
+  useTray(() => {
+    if (user) void pullLibrary(false, true);
+  });
   // Library-scoped auto-sync for the active third-party cloud provider (WebDAV /
   // Google Drive): keeps library.json current on import / delete / book-close,
   // parity with useBooksSync. No-op when no provider is enabled.
@@ -843,6 +868,8 @@ const LibraryPageContent = ({ searchParams }: { searchParams: ReadonlyURLSearchP
     };
 
     const concurrency = 4;
+    // A single file imports too quickly for a taskbar bar to mean anything.
+    const showTaskbarProgress = files.length > 1;
     for (let i = 0; i < files.length; i += concurrency) {
       const batch = files.slice(i, i + concurrency);
       const importedBooks = (await Promise.all(batch.map(processFile))).filter((book) => !!book);
@@ -851,6 +878,19 @@ const LibraryPageContent = ({ searchParams }: { searchParams: ReadonlyURLSearchP
       // done — saving library.json once per batch of 4 books was the dominant
       // cost for large imports.
       await updateBooks(envConfig, importedBooks, { skipSave: true });
+      if (showTaskbarProgress) {
+        reportTaskbarProgress(appService, 'import', (i + batch.length) / files.length);
+      }
+    }
+    if (showTaskbarProgress) {
+      finishTaskbarProgress(appService, 'import', !options.silent && failedImports.length > 0);
+    }
+    if (showTaskbarProgress && successfulImports.length > 0) {
+      notifyJobFinished(
+        appService,
+        _('Import complete'),
+        _('Successfully imported {{count}} book(s)', { count: successfulImports.length }),
+      );
     }
 
     // Persist the full library once after every file in the batch is done.
@@ -1684,6 +1724,7 @@ const LibraryPageContent = ({ searchParams }: { searchParams: ReadonlyURLSearchP
       )}
       <AboutWindow />
       <KeyboardShortcutsHelp />
+      <LookupDictionary />
       <UpdaterWindow />
       <MigrateDataWindow />
       <BackupWindow onPullLibrary={pullLibrary} />
@@ -602,7 +602,6 @@ export default function BrowserPage() {
             url: downloadUrl,
             headers,
             singleThreaded: true,
-            skipSslVerification: true,
             onProgress,
           });
           const probedFilename = await probeFilename(responseHeaders);
@@ -708,7 +707,6 @@ export default function BrowserPage() {
           cfp: '',
           url: downloadUrl,
           singleThreaded: true,
-          skipSslVerification: true,
           headers,
         });
         return await appService.getImageURL(cachedPath);
@@ -16,6 +16,8 @@ import { useSettingsStore } from '@/store/settingsStore';
 import { useDeviceControlStore } from '@/store/deviceStore';
 import { useScreenWakeLock } from '@/hooks/useScreenWakeLock';
 import { useScreenBrightness } from '@/app/reader/hooks/useScreenBrightness';
+import { useKeepAwake } from '@/app/reader/hooks/useKeepAwake';
+import { useReadingServerBooks } from '@/app/reader/hooks/useReadingServerBooks';
 import { useTransferQueue } from '@/hooks/useTransferQueue';
 import { useReplicaPull } from '@/hooks/useReplicaPull';
 import { eventDispatcher } from '@/utils/event';
@@ -25,6 +27,7 @@ import { isTauriAppPlatform } from '@/services/environment';
 import { getSysFontsList, setSystemUIVisibility } from '@/utils/bridge';
 import { AboutWindow } from '@/components/AboutWindow';
 import { KeyboardShortcutsHelp } from '@/components/KeyboardShortcutsHelp';
+import { LookupDictionary } from '@/components/LookupDictionary';
 import { UpdaterWindow } from '@/components/UpdaterWindow';
 import { ProofreadRulesManager } from './ProofreadRules';
 import { Toast } from '@/components/Toast';
@@ -58,6 +61,7 @@ const Reader: React.FC<{ ids?: string }> = ({ ids }) => {
   const { appService } = useEnv();
   const { settings } = useSettingsStore();
   const { libraryLoaded } = useLibrary();
+  const remoteBooksReady = useReadingServerBooks(ids, libraryLoaded);
   const { sideBarBookKey } = useSidebarStore();
   const { hoveredBookKey } = useReaderStore();
   const { showSystemUI, dismissSystemUI } = useThemeStore();
@@ -69,7 +73,13 @@ const Reader: React.FC<{ ids?: string }> = ({ ids }) => {
   const { isDarkMode, systemUIAlwaysHidden, isRoundedWindow } = useThemeStore();
 
   useTheme({ systemUIVisible: settings.alwaysShowStatusBar, appThemeColor: 'base-100' });
-  useScreenWakeLock(settings.screenWakeLock, appService?.hasWindow);
+  // Mobile apps keep the screen on through the native bridge (useKeepAwake).
+  useScreenWakeLock(
+    settings.screenWakeLock && !appService?.isMobileApp,
+    appService?.hasWindow,
+    appService?.isLinuxApp,
+  );
+  useKeepAwake();
   useScreenBrightness();
   useTransferQueue(libraryLoaded, 5000);
   // Reader needs dictionaries for word-lookup, fonts for rendering, and
@@ -148,7 +158,7 @@ const Reader: React.FC<{ ids?: string }> = ({ ids }) => {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [hoveredBookKey]);
 
-  return libraryLoaded && settings.globalReadSettings ? (
+  return libraryLoaded && remoteBooksReady && settings.globalReadSettings ? (
     <div
       className={clsx(
         'reader-page bg-base-100 text-base-content full-height select-none overflow-hidden',
@@ -159,6 +169,7 @@ const Reader: React.FC<{ ids?: string }> = ({ ids }) => {
         <ReaderContent ids={ids} settings={settings} />
         <AboutWindow />
         <KeyboardShortcutsHelp />
+        <LookupDictionary />
         <UpdaterWindow />
         <ProofreadRulesManager />
         <Toast />
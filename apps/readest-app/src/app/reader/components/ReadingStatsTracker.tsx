@@ -1,12 +1,18 @@
 'use client';
 
 import { useEffect, useRef } from 'react';
+import { addPluginListener } from '@tauri-apps/api/core';
 import { useBookProgress } from '@/store/readerProgressStore';
 import { useBookDataStore } from '@/store/bookDataStore';
 import { useEnv } from '@/context/EnvContext';
 import { useAuth } from '@/context/AuthContext';
 import { StatisticsDb } from '@/services/statistics/statisticsDb';
-import { TrackerCore, type FlushedEvent } from '@/services/statistics/trackerCore';
+import {
+  TrackerCore,
+  resolveIdleTimer,
+  type FlushedEvent,
+} from '@/services/statistics/trackerCore';
+import { getSystemIdleSeconds } from '@/services/statistics/systemIdle';
 import { DEFAULT_STATS_TRACKING_CONFIG } from '@/types/statistics';
 import { SyncClient } from '@/libs/sync';
 import { pushStats, pullStats } from '@/services/statistics/statsSync';
@@ -85,12 +91,28 @@ export default function ReadingStatsTracker({ bookKey }: { bookKey: string }) {
     }
   };
 
-  const armIdle = () => {
+  // On desktop the timer checks the OS idle time before pausing, so reading
+  // a long page while scrolling or moving the mouse keeps counting, and time
+  // away from the computer stops counting at the last input.
+  const armIdle = (seconds = DEFAULT_STATS_TRACKING_CONFIG.idleTimeoutSeconds) => {
     if (idleRef.current) clearTimeout(idleRef.current);
-    idleRef.current = setTimeout(
-      () => void persist(coreRef.current.onIdle(nowSec())),
-      DEFAULT_STATS_TRACKING_CONFIG.idleTimeoutSeconds * 1000,
-    );
+    const timer = setTimeout(async () => {
+      const systemIdle = appService?.isDesktopApp ? await getSystemIdleSeconds() : null;
+      // A page turn or hide while asking re-armed or cleared the timer.
+      if (idleRef.current !== timer) return;
+      const decision = resolveIdleTimer(
+        DEFAULT_STATS_TRACKING_CONFIG.idleTimeoutSeconds,
+        systemIdle,
+      );
+      if (decision.kind === 'rearm') {
+        armIdle(decision.inSeconds);
+        return;
+      }
+      idleRef.current = null;
+      const now = nowSec();
+      void persist(coreRef.current.onIdle(now, now - decision.idleSeconds));
+    }, seconds * 1000);
+    idleRef.current = timer;
   };
 
   // Page changes drive the tracker.
@@ -109,6 +131,7 @@ export default function ReadingStatsTracker({ bookKey }: { bookKey: string }) {
     const onVis = () => {
       if (document.visibilityState === 'hidden') {
         if (idleRef.current) clearTimeout(idleRef.current);
+        idleRef.current = null;
         void persist(coreRef.current.onHide(nowSec()));
       }
     };
@@ -117,6 +140,25 @@ export default function ReadingStatsTracker({ bookKey }: { bookKey: string }) {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [bookMd5]);
 
+  // The screen turning off on mobile, which doesn't always hide the webview.
+  useEffect(() => {
+    if (!appService?.isMobileApp) return;
+    const listener = addPluginListener(
+      'native-bridge',
+      'screen-state',
+      ({ on }: { on: boolean }) => {
+        if (on) return;
+        if (idleRef.current) clearTimeout(idleRef.current);
+        idleRef.current = null;
+        void persist(coreRef.current.onHide(nowSec()));
+      },
+    );
+    return () => {
+      listener.then((l) => l.unregister()).catch(() => {});
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [appService, bookMd5]);
+
   // Book close (unmount).
   useEffect(() => {
     return () => {
@@ -8,7 +8,7 @@ import { MdZoomOut, MdZoomIn, MdCheck, MdInfoOutline } from 'react-icons/md';
 import { MdRemove, MdAdd, MdContrast } from 'react-icons/md';
 import { MdSync, MdSyncProblem } from 'react-icons/md';
 import { IoMdExpand } from 'react-icons/io';
-import { IoShareOutline } from 'react-icons/io5';
+import { IoPrintOutline, IoShareOutline } from 'react-icons/io5';
 import { TbArrowAutofitWidth } from 'react-icons/tb';
 import { TbColumns1, TbColumns2 } from 'react-icons/tb';
 
@@ -34,6 +34,7 @@ import { eventDispatcher } from '@/utils/event';
 import { getMaxInlineSize } from '@/utils/config';
 import dayjs from 'dayjs';
 import { saveViewSettings } from '@/helpers/settings';
+import { collectPrintDocument, printContent } from '@/services/print';
 import { tauriHandleToggleFullScreen } from '@/utils/window';
 import MenuItem from '@/components/MenuItem';
 import Menu from '@/components/Menu';
@@ -138,6 +139,36 @@ const ViewMenu: React.FC<ViewMenuProps> = ({
     });
   };
 
+  // Text selected in the book when the menu opened prints instead of the chapter.
+  const printSelection = getView(bookKey)
+    ?.renderer.getContents()
+    .map(({ doc }) => doc.getSelection())
+    .find((sel) => sel && !sel.isCollapsed && sel.rangeCount > 0);
+
+  const handlePrint = async () => {
+    setIsDropdownOpen?.(false);
+    const view = getView(bookKey);
+    if (!view) return;
+    const range = printSelection?.getRangeAt(0);
+    const contents = view.renderer.getContents();
+    const primary = contents.find((x) => x.index === view.renderer.primaryIndex) ?? contents[0];
+    const doc = range?.startContainer.ownerDocument ?? primary?.doc;
+    if (!doc) return;
+    const bookTitle = bookData.book?.title ?? '';
+    const sectionLabel = getProgress(bookKey)?.sectionLabel;
+    const title = !range && sectionLabel ? `${bookTitle} - ${sectionLabel}` : bookTitle;
+    try {
+      await printContent(await collectPrintDocument(doc, title, range), title);
+    } catch (err) {
+      console.error('Failed to print', err);
+      eventDispatcher.dispatch('toast', {
+        type: 'error',
+        message: _('Could not open the print dialog.'),
+        timeout: 3500,
+      });
+    }
+  };
+
   useEffect(() => {
     if (isScrolledMode === viewSettings!.scrolled) return;
     viewSettings!.scrolled = isScrolledMode;
@@ -473,6 +504,14 @@ const ViewMenu: React.FC<ViewMenuProps> = ({
       <hr aria-hidden='true' className='border-base-300 my-1' />
 
       <MenuItem label={_('Share Book')} Icon={IoShareOutline} onClick={handleShare} />
+      {appService?.isDesktopApp && (
+        <MenuItem
+          label={printSelection ? _('Print Selection') : _('Print Chapter')}
+          Icon={IoPrintOutline}
+          onClick={handlePrint}
+          disabled={bookData.isFixedLayout}
+        />
+      )}
     </Menu>
   );
 };
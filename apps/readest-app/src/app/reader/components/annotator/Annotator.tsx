@@ -37,6 +37,7 @@ import {
 } from '@/utils/sel';
 import { eventDispatcher } from '@/utils/event';
 import { findTocItemBS } from '@/services/nav';
+import { keepExportedAnnotations } from '@/services/libraryProvider';
 import { throttle } from '@/utils/throttle';
 import {
   beginGesture,
@@ -1568,6 +1569,7 @@ const Annotator: React.FC<{ bookKey: string; contentInsets: Insets }> = ({
     const ext = isPlainText ? 'txt' : 'md';
     const mimeType = isPlainText ? 'text/plain' : 'text/markdown';
     const filename = `${makeSafeFilename(book.title)}.${ext}`;
+    if (appService) await keepExportedAnnotations(appService, filename, content);
     const saved = await appService?.saveFile(filename, content, {
       mimeType,
       share: true,
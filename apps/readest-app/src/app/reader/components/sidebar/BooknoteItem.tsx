@@ -1,7 +1,7 @@
 import clsx from 'clsx';
 import dayjs from 'dayjs';
 import React, { useMemo, useRef, useState } from 'react';
-import { MdEdit, MdDelete, MdContentCopy } from 'react-icons/md';
+import { MdEdit, MdDelete, MdContentCopy, MdOutlineImage } from 'react-icons/md';
 
 import { marked } from 'marked';
 import { useEnv } from '@/context/EnvContext';
@@ -10,6 +10,7 @@ import { useSettingsStore } from '@/store/settingsStore';
 import { useReaderStore } from '@/store/readerStore';
 import { useNotebookStore } from '@/store/notebookStore';
 import { useBookDataStore } from '@/store/bookDataStore';
+import { useThemeStore } from '@/store/themeStore';
 import { useTranslation } from '@/hooks/useTranslation';
 import { useResponsiveSize } from '@/hooks/useResponsiveSize';
 import { eventDispatcher } from '@/utils/event';
@@ -17,6 +18,10 @@ import { isCfiInLocation } from '@/utils/cfi';
 import { buildAnnotationUrl } from '@/utils/deeplink';
 import { buildAnnotationCopyMarkdown } from '@/utils/note';
 import { writeTextToClipboard } from '@/utils/clipboard';
+import { getCoverFilename } from '@/utils/book';
+import { findTocItemBS } from '@/services/nav';
+import { isTauriAppPlatform } from '@/services/environment';
+import { quoteImageFilename, renderQuoteImage } from '@/services/quoteImage';
 import { DEFAULT_NOTE_EXPORT_CONFIG } from '@/services/constants';
 import { removeBookNoteOverlays } from '../../utils/annotatorUtil';
 import TextButton from '@/components/TextButton';
@@ -31,9 +36,10 @@ interface BooknoteItemProps {
 
 const BooknoteItem: React.FC<BooknoteItemProps> = ({ bookKey, item, isNearest, onClick }) => {
   const _ = useTranslation();
-  const { envConfig } = useEnv();
+  const { envConfig, appService } = useEnv();
   const { settings } = useSettingsStore();
-  const { getConfig, saveConfig, updateBooknotes } = useBookDataStore();
+  const { themeCode } = useThemeStore();
+  const { getConfig, getBookData, saveConfig, updateBooknotes } = useBookDataStore();
   const { getProgress, getView, getViewsById, getViewSettings } = useReaderStore();
   const { setNotebookEditAnnotation, setNotebookVisible } = useNotebookStore();
 
@@ -123,6 +129,38 @@ const BooknoteItem: React.FC<BooknoteItemProps> = ({ bookKey, item, isNearest, o
     });
   };
 
+  const handleShareImage = async () => {
+    const bookData = getBookData(bookKey);
+    const book = bookData?.book;
+    if (!appService || !book || !item.text) return;
+    try {
+      const coverPath = book.coverImageUrl
+        ? await appService.resolveFilePath(getCoverFilename(book), 'Books')
+        : undefined;
+      const png = await renderQuoteImage({
+        text: item.text,
+        title: book.title,
+        author: book.author,
+        chapter: findTocItemBS(bookData.bookDoc?.toc ?? [], item.cfi)?.label,
+        coverPath,
+        background: themeCode.bg,
+        foreground: themeCode.fg,
+        accent: customColors[item.color as HighlightColor] || item.color,
+      });
+      await appService.saveFile(quoteImageFilename(book.title), png, {
+        mimeType: 'image/png',
+        share: true,
+      });
+    } catch (err) {
+      console.error('Failed to share the quote image', err);
+      eventDispatcher.dispatch('toast', {
+        type: 'error',
+        message: _('Could not create the quote image.'),
+        timeout: 3500,
+      });
+    }
+  };
+
   const editBookmark = () => {
     setEditorDraft(text || '');
     setInlineEditMode(true);
@@ -295,6 +333,16 @@ const BooknoteItem: React.FC<BooknoteItemProps> = ({ bookKey, item, isNearest, o
               <MdContentCopy size={size18} />
             </button>
 
+            {isTauriAppPlatform() && item.type === 'annotation' && item.text && (
+              <button
+                onClick={handleShareImage}
+                className='btn btn-ghost btn-xs text-base-content p-0 opacity-0 transition duration-300 ease-in-out hover:bg-transparent group-focus-within:opacity-100 group-hover:opacity-100'
+                aria-label={_('Share as Image')}
+              >
+                <MdOutlineImage size={size18} />
+              </button>
+            )}
+
             <button
               onClick={deleteNote.bind(null, item)}
               className='btn btn-ghost btn-xs p-0 text-red-500 opacity-0 transition duration-300 ease-in-out hover:bg-transparent group-focus-within:opacity-100 group-hover:opacity-100'
@@ -18,6 +18,7 @@ import { useRouter } from 'next/navigation';
 import { TTSVoicesGroup } from '@/services/tts';
 import { DEFAULT_SENTENCE_GAP_SEC } from '@/services/tts/EdgeTTSClient';
 import { DEFAULT_PARAGRAPH_GAP_SEC } from '@/services/tts/TTSController';
+import { SLEEP_AT_CHAPTER_END } from '@/services/tts/sleepTimer';
 import { useEnv } from '@/context/EnvContext';
 import { useAuth } from '@/context/AuthContext';
 import { useReaderStore } from '@/store/readerStore';
@@ -52,6 +53,7 @@ const PARAGRAPH_PAUSE_MARKS = [0, 0.5, 1, 1.5, 2];
 const getTTSTimeoutOptions = (_: TranslationFunc) => {
   return [
     { label: _('No Timeout'), value: 0 },
+    { label: _('End of Chapter'), value: SLEEP_AT_CHAPTER_END },
     { label: _('{{value}} minute', { value: 1 }), value: 60 },
     { label: _('{{value}} minutes', { value: 3 }), value: 180 },
     { label: _('{{value}} minutes', { value: 5 }), value: 300 },
@@ -274,7 +276,12 @@ const TTSPlayerSheet = ({
     .find((voice) => voice.id === selectedVoice)?.name;
   // Armed timer shows its live countdown on the button; otherwise the button
   // just names itself (the alarm icon already carries the affordance).
-  const timerCaption = timeoutOption > 0 && timerLabel ? timerLabel : _('Sleep Timer');
+  const timerCaption =
+    timeoutOption === SLEEP_AT_CHAPTER_END
+      ? _('End of Chapter')
+      : timeoutOption > 0 && timerLabel
+        ? timerLabel
+        : _('Sleep Timer');
 
   // The main view carries no header label (the content speaks for itself and
   // vertical space is tight); sub-views keep the back button and their title.
@@ -0,0 +1,60 @@
+import { useEffect, useState } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useReaderStore } from '@/store/readerStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { setKeepAwake } from '@/services/keepAwake';
+import { ttsSessionManager } from '@/services/tts/TTSSessionManager';
+import { eventDispatcher } from '@/utils/event';
+import { setKeepScreenAwake } from '@/utils/bridge';
+
+const isReadingAloud = () =>
+  ttsSessionManager.getActiveSession()?.controller.state === 'playing';
+
+/**
+ * Keep the system awake while a book in this window reads aloud or
+ * auto-scrolls, so a long chapter isn't cut off by sleep. On mobile the
+ * screen itself stays on through the native bridge, for the Keep Screen
+ * Awake setting as well, since the webviews' Screen Wake Lock doesn't hold
+ * across slow pages there.
+ */
+export const useKeepAwake = () => {
+  const { appService } = useEnv();
+  const screenWakeLock = useSettingsStore((state) => state.settings.screenWakeLock);
+  const autoScrolling = useReaderStore((state) =>
+    state.bookKeys.some((key) => state.viewStates[key]?.autoScrollEnabled),
+  );
+  const [readingAloud, setReadingAloud] = useState(isReadingAloud);
+  const isMobileApp = !!appService?.isMobileApp;
+
+  useEffect(() => {
+    const update = () => setReadingAloud(isReadingAloud());
+    ttsSessionManager.addEventListener('session-changed', update);
+    eventDispatcher.on('tts-playback-state', update);
+    return () => {
+      ttsSessionManager.removeEventListener('session-changed', update);
+      eventDispatcher.off('tts-playback-state', update);
+    };
+  }, []);
+
+  const keepAwake = autoScrolling || readingAloud;
+  useEffect(() => {
+    if (!appService?.isDesktopApp || !keepAwake) return;
+    void setKeepAwake(true);
+    return () => {
+      void setKeepAwake(false);
+    };
+  }, [appService, keepAwake]);
+
+  const keepScreenOn = isMobileApp && (keepAwake || screenWakeLock);
+  useEffect(() => {
+    if (!keepScreenOn) return;
+    setKeepScreenAwake({ enabled: true }).catch((err) =>
+      console.warn('Failed to keep the screen awake', err),
+    );
+    return () => {
+      setKeepScreenAwake({ enabled: false }).catch((err) =>
+        console.warn('Failed to let the screen sleep', err),
+      );
+    };
+  }, [keepScreenOn]);
+};
@@ -8,8 +8,14 @@ import { useDeviceControlStore } from '@/store/deviceStore';
 import { useSettingsStore } from '@/store/settingsStore';
 import { useSidebarStore } from '@/store/sidebarStore';
 import { eventDispatcher } from '@/utils/event';
-import { resolvePageTurn, normalizeDomKeyEvent, KeyCandidate } from '@/utils/keybinding';
-import { refreshEinkScreen } from '@/utils/bridge';
+import {
+  resolvePageTurn,
+  normalizeDomKeyEvent,
+  volumeKeyMappings,
+  boundNativeKeys,
+  KeyCandidate,
+} from '@/utils/keybinding';
+import { refreshEinkScreen, MappedKeyEvent } from '@/utils/bridge';
 import { isTauriAppPlatform } from '@/services/environment';
 import { tauriGetWindowLogicalPosition } from '@/utils/window';
 import { getReadingRulerMoveDirection } from '../utils/readingRuler';
@@ -190,10 +196,12 @@ export const usePagination = (
   const { getViewSettings, getViewState } = useReaderStore();
   const { hoveredBookKey, setHoveredBookKey } = useReaderStore();
   const {
+    setVolumeKeyMappings,
     acquireVolumeKeyInterception,
     releaseVolumeKeyInterception,
     acquirePageTurnerKeyInterception,
     releasePageTurnerKeyInterception,
+    setPageTurnerBindings,
   } = useDeviceControlStore();
   // Reactive subscription: drives the effect dependency array below. The
   // handlers themselves re-read via getState() to avoid stale closures.
@@ -313,26 +321,27 @@ export const usePagination = (
     } else if (msg instanceof CustomEvent) {
       const viewSettings = getViewSettings(bookKey);
       // While TTS is playing, volume keys control the volume, not pagination.
-      // The native layer still forwards the key here (iOS via a lingering KVO,
-      // Android calls onNativeKeyDown unconditionally), so guard it here too.
+      // A key press can still be on its way from the native layer when the
+      // mapping is released, so guard it here too.
       if (
-        msg.type === 'native-key-down' &&
+        msg.type === 'native-key-action' &&
         viewSettings?.volumeKeysToFlip &&
         !ttsPlayingRef.current
       ) {
-        const { keyName } = msg.detail;
+        const { action } = msg.detail as MappedKeyEvent;
+        const side: PaginationSide | null =
+          action === 'pagePrev' || action === 'sectionPrev'
+            ? 'up'
+            : action === 'pageNext' || action === 'sectionNext'
+              ? 'down'
+              : null;
+        if (!side) return;
+        const mode = action === 'sectionPrev' || action === 'sectionNext' ? 'section' : 'page';
         setHoveredBookKey('');
-        if (keyName === 'VolumeUp') {
-          if (viewSettings.readingRulerEnabled && dispatchReadingRulerMove('up')) {
-            return;
-          }
-          viewPagination(viewRef.current, viewSettings, 'up');
-        } else if (keyName === 'VolumeDown') {
-          if (viewSettings.readingRulerEnabled && dispatchReadingRulerMove('down')) {
-            return;
-          }
-          viewPagination(viewRef.current, viewSettings, 'down');
+        if (mode === 'page' && viewSettings.readingRulerEnabled && dispatchReadingRulerMove(side)) {
+          return;
         }
+        viewPagination(viewRef.current, viewSettings, side, mode);
       }
     } else {
       if (msg.type === 'click') {
@@ -434,9 +443,9 @@ export const usePagination = (
   useEffect(() => {
     if (!appService?.isMobileApp) return;
 
-    eventDispatcher.on('native-key-down', handlePageFlip);
+    eventDispatcher.on('native-key-action', handlePageFlip);
     return () => {
-      eventDispatcher.off('native-key-down', handlePageFlip);
+      eventDispatcher.off('native-key-action', handlePageFlip);
     };
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
@@ -466,6 +475,9 @@ export const usePagination = (
     const viewSettings = getViewSettings(bookKey);
     if (!viewSettings?.volumeKeysToFlip || ttsPlaying) return;
 
+    setVolumeKeyMappings(
+      volumeKeyMappings(viewSettings.volumeKeysSwapped, viewSettings.volumeKeysLongPress),
+    );
     acquireVolumeKeyInterception();
     return () => {
       releaseVolumeKeyInterception();
@@ -474,7 +486,8 @@ export const usePagination = (
   }, [ttsPlaying]);
 
   // Hardware page turner: native-key + DOM-key listeners and native
-  // media-key interception, re-evaluated whenever the setting changes.
+  // interception of the bound keys, re-evaluated whenever the setting changes.
+  const nativeBindings = hardwarePageTurner ? boundNativeKeys(hardwarePageTurner).join(',') : '';
   useEffect(() => {
     const hasNativeBinding =
       hardwarePageTurner?.bindings.pagePrev?.source === 'native' ||
@@ -486,6 +499,7 @@ export const usePagination = (
       !!appService?.isMobileApp && !!hardwarePageTurner?.enabled && hasNativeBinding;
 
     if (needsNativeInterception) {
+      setPageTurnerBindings(nativeBindings ? nativeBindings.split(',') : []);
       acquirePageTurnerKeyInterception();
     }
     if (hasNativeBinding) {
@@ -512,6 +526,7 @@ export const usePagination = (
     hardwarePageTurner?.bindings.sectionPrev?.source,
     hardwarePageTurner?.bindings.sectionNext?.source,
     hardwarePageTurner?.bindings.refresh?.source,
+    nativeBindings,
   ]);
 
   // Touch swipe page flip for fixed-layout books — registered as a touch interceptor
@@ -0,0 +1,59 @@
+import { useEffect, useState } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { isReadingServerClient } from '@/services/environment';
+import { BOOK_IDS_SEPARATOR } from '@/services/constants';
+import {
+  applyRemoteProgress,
+  fetchRemoteBook,
+  fetchRemoteProgress,
+} from '@/services/readingServer';
+
+/**
+ * In a browser served by the app's reading server, bring the books in the
+ * reader URL into this browser's library before they open: fetch the files
+ * it doesn't have yet and take the app's progress when it is newer. Returns
+ * whether the reader can open them; always true outside the reading server.
+ */
+export function useReadingServerBooks(ids: string | undefined, libraryLoaded: boolean) {
+  const { appService } = useEnv();
+  const [ready, setReady] = useState(() => !isReadingServerClient());
+
+  useEffect(() => {
+    if (ready || !libraryLoaded || !appService) return;
+    const bookIds = ids || new URLSearchParams(window.location.search).get('ids') || '';
+    const hashes = new Set(bookIds.split(BOOK_IDS_SEPARATOR).filter(Boolean));
+
+    const prepare = async () => {
+      const { settings } = useSettingsStore.getState();
+      const { library, setLibrary } = useLibraryStore.getState();
+      const books = [...library];
+      let imported = false;
+      for (const hash of hashes) {
+        try {
+          let book = books.find((b) => b.hash === hash && !b.deletedAt);
+          if (!book) {
+            book = (await appService.importBook(await fetchRemoteBook(hash), books)) ?? undefined;
+            imported = true;
+          }
+          const remote = await fetchRemoteProgress(hash);
+          if (!book || !remote) continue;
+          const config = await appService.loadBookConfig(book, settings);
+          const updated = applyRemoteProgress(config, remote);
+          if (updated) await appService.saveBookConfig(book, updated, settings);
+        } catch (err) {
+          console.error('Failed to load book from the reading server', hash, err);
+        }
+      }
+      if (imported) {
+        await appService.saveLibraryBooks(books);
+        setLibrary(books);
+      }
+      setReady(true);
+    };
+    prepare();
+  }, [ready, ids, libraryLoaded, appService]);
+
+  return ready;
+}
@@ -0,0 +1,34 @@
+import { useCallback, useEffect } from 'react';
+import { useBookDataStore } from '@/store/bookDataStore';
+import { useBookProgress } from '@/store/readerProgressStore';
+import { isReadingServerClient } from '@/services/environment';
+import { pushRemoteProgress } from '@/services/readingServer';
+import { debounce } from '@/utils/debounce';
+
+/**
+ * In a browser served by the app's reading server, send the reading position
+ * back to the app as the reader moves, so the library and other devices
+ * pick it up.
+ */
+export const useReadingServerSync = (bookKey: string) => {
+  const progress = useBookProgress(bookKey);
+
+  // eslint-disable-next-line react-hooks/exhaustive-deps
+  const pushProgress = useCallback(
+    debounce(() => {
+      const config = useBookDataStore.getState().getConfig(bookKey);
+      if (!config?.location) return;
+      pushRemoteProgress(bookKey.split('-')[0]!, config).catch((err) =>
+        console.error('Failed to send progress to the reading server', err),
+      );
+    }, 2000),
+    [bookKey],
+  );
+
+  useEffect(() => {
+    if (!isReadingServerClient() || !progress) return;
+    pushProgress();
+  }, [progress, pushProgress]);
+
+  useEffect(() => () => pushProgress.flush(), [pushProgress]);
+};
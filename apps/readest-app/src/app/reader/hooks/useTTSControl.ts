@@ -6,6 +6,7 @@ import { useBookDataStore } from '@/store/bookDataStore';
 import { useReaderStore } from '@/store/readerStore';
 import { useBookProgress } from '@/store/readerProgressStore';
 import { useProofreadStore } from '@/store/proofreadStore';
+import { useSettingsStore } from '@/store/settingsStore';
 import { TransformContext } from '@/services/transformers/types';
 import { proofreadTransformer } from '@/services/transformers/proofread';
 import { useTranslation } from '@/hooks/useTranslation';
@@ -155,6 +156,12 @@ export const useTTSControl = ({ bookKey, onRequestHidePanel }: UseTTSControlProp
     }
   };
 
+  // A shake of the device extended the native sleep timer.
+  const handleTTSSleepTimerChanged = (event: CustomEvent) => {
+    const detail = event.detail as { firesAt: number } | undefined;
+    if (detail) setTimeoutTimestamp(detail.firesAt);
+  };
+
   useEffect(() => {
     eventDispatcher.on('tts-speak', handleTTSSpeak);
     eventDispatcher.on('tts-stop', handleTTSStop);
@@ -165,6 +172,7 @@ export const useTTSControl = ({ bookKey, onRequestHidePanel }: UseTTSControlProp
     eventDispatcher.on('tts-set-rate', handleTTSSetRate);
     eventDispatcher.on('tts-highlight-sentence', handleTTSHighlightSentence);
     eventDispatcher.on('tts-sync-request', handleTTSSyncRequest);
+    eventDispatcher.on('tts-sleep-timer-changed', handleTTSSleepTimerChanged);
     return () => {
       eventDispatcher.off('tts-speak', handleTTSSpeak);
       eventDispatcher.off('tts-stop', handleTTSStop);
@@ -175,6 +183,7 @@ export const useTTSControl = ({ bookKey, onRequestHidePanel }: UseTTSControlProp
       eventDispatcher.off('tts-set-rate', handleTTSSetRate);
       eventDispatcher.off('tts-highlight-sentence', handleTTSHighlightSentence);
       eventDispatcher.off('tts-sync-request', handleTTSSyncRequest);
+      eventDispatcher.off('tts-sleep-timer-changed', handleTTSSleepTimerChanged);
       if (ttsControllerRef.current) {
         const controller = ttsControllerRef.current;
         const bookHash = getBookHashFromKey(bookKey);
@@ -810,6 +819,11 @@ export const useTTSControl = ({ bookKey, onRequestHidePanel }: UseTTSControlProp
           ttsController.setRate(viewSettings.ttsRate);
           ttsController.setSentenceGap(viewSettings.ttsSentenceGap ?? DEFAULT_SENTENCE_GAP_SEC);
           ttsController.setParagraphGap(viewSettings.ttsParagraphGap ?? DEFAULT_PARAGRAPH_GAP_SEC);
+          const { globalViewSettings } = useSettingsStore.getState().settings;
+          await ttsController.setPronunciations(
+            globalViewSettings?.ttsPronunciations ?? [],
+            viewSettings.ttsPronunciations ?? [],
+          );
           ttsController.speak(ssml, oneTime, () => handleStop(bookKey));
           ttsController.setTargetLang(getTTSTargetLang() || '');
         } else {
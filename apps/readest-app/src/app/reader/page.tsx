@@ -8,9 +8,19 @@ import { useOpenWithBooks } from '@/hooks/useOpenWithBooks';
 import { useOpenAnnotationLink } from '@/hooks/useOpenAnnotationLink';
 import { useOpenBookLink } from '@/hooks/useOpenBookLink';
 import { useReadingWidget } from '@/hooks/useReadingWidget';
+import { useJumpList } from '@/hooks/useJumpList';
+import { useSpotlightIndex } from '@/hooks/useSpotlightIndex';
+import { useRecentBooksMenu } from '@/hooks/useRecentBooksMenu';
+import { useAppShortcuts } from '@/hooks/useAppShortcuts';
+import { useReadingIntents } from '@/hooks/useReadingIntents';
+import { useLibraryProvider } from '@/hooks/useLibraryProvider';
+import { useTray } from '@/hooks/useTray';
+import { useGlobalShortcuts } from '@/hooks/useGlobalShortcuts';
 import { useOpenShareLink } from '@/hooks/useOpenShareLink';
 import { useClipUrlIngress } from '@/hooks/useClipUrlIngress';
 import { useSettingsStore } from '@/store/settingsStore';
+import { useReaderStore } from '@/store/readerStore';
+import { eventDispatcher } from '@/utils/event';
 import { checkForAppUpdates, checkAppReleaseNotes } from '@/helpers/updater';
 import { tauriHandleSetAlwaysOnTop } from '@/utils/window';
 import Reader from './components/Reader';
@@ -26,8 +36,20 @@ export default function Page() {
   useOpenAnnotationLink();
   useOpenBookLink();
   useReadingWidget();
+  useJumpList();
+  useSpotlightIndex();
+  useRecentBooksMenu();
+  useAppShortcuts();
+  useReadingIntents();
+  useLibraryProvider();
+  useTray(() => {
+    for (const bookKey of useReaderStore.getState().bookKeys) {
+      eventDispatcher.dispatch('sync-book-progress', { bookKey });
+    }
+  });
   useOpenShareLink();
   useClipUrlIngress();
+  useGlobalShortcuts();
 
   useEffect(() => {
     const doCheckAppUpdates = async () => {
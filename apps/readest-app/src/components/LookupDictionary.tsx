@@ -0,0 +1,13 @@
+import DictionarySheet from '@/app/reader/components/annotator/DictionarySheet';
+import { useLookupLink } from '@/hooks/useLookupLink';
+
+/**
+ * Dictionary results for words looked up from other apps through the macOS
+ * Services menu. Mounted on both the library and reader pages.
+ */
+export const LookupDictionary = () => {
+  const { word, dismiss } = useLookupLink();
+  if (!word) return null;
+  // Keyed by the word so that a new lookup starts a fresh history.
+  return <DictionarySheet key={word} word={word} onDismiss={dismiss} />;
+};
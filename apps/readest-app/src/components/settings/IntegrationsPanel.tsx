@@ -14,6 +14,7 @@ import {
   RiDatabase2Line,
   RiGoogleLine,
   RiMicrosoftLine,
+  RiApps2Line,
 } from 'react-icons/ri';
 import { useEnv } from '@/context/EnvContext';
 import { useAuth } from '@/context/AuthContext';
@@ -138,6 +139,10 @@ const IntegrationsPanel: React.FC = () => {
     }
   };
 
+  const toggleShareLibraryWithApps = () => {
+    saveSysSettings(envConfig, 'shareLibraryWithApps', !settings.shareLibraryWithApps);
+  };
+
   // Deep-link consumption: when a caller (e.g. OPDS browser close handler)
   // sets `requestedSubPage` in the store before opening the dialog, drill
   // straight into that sub-page on mount and clear the request so it doesn't
@@ -643,6 +648,25 @@ const IntegrationsPanel: React.FC = () => {
           </div>
         </div>
       )}
+
+      {appService?.isAndroidApp && (
+        <div className='w-full' data-setting-id='settings.integrations.otherApps'>
+          <SectionTitle className='mb-2'>{_('Other Apps')}</SectionTitle>
+          <div className='card eink-bordered border-base-200 bg-base-100 overflow-hidden border'>
+            <div className='divide-base-200 divide-y'>
+              <IntegrationToggleRow
+                icon={RiApps2Line}
+                title={_('Share Library with Apps')}
+                description={_(
+                  'Let automation apps and launchers see your books, covers and reading progress, and open books',
+                )}
+                checked={!!settings.shareLibraryWithApps}
+                onChange={toggleShareLibraryWithApps}
+              />
+            </div>
+          </div>
+        </div>
+      )}
     </div>
   );
 };
@@ -1,5 +1,6 @@
 import clsx from 'clsx';
 import React, { useEffect, useRef, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 
 import { useEnv } from '@/context/EnvContext';
 import { useReaderStore } from '@/store/readerStore';
@@ -10,7 +11,8 @@ import { SettingsPanelPanelProp } from './SettingsDialog';
 import { saveViewSettings } from '@/helpers/settings';
 import { validateCSS, formatCSS } from '@/utils/css';
 import { getStyles } from '@/utils/style';
-import { BoxedList } from './primitives';
+import { eventDispatcher } from '@/utils/event';
+import { BoxedList, SettingsRow } from './primitives';
 
 type CSSType = 'book' | 'reader';
 
@@ -109,6 +111,19 @@ const MiscPanel: React.FC<SettingsPanelPanelProp> = ({ bookKey, onRegisterReset
     );
   };
 
+  const handleSetDefaultApp = async () => {
+    try {
+      await invoke('register_default_app');
+    } catch (error) {
+      console.error('Failed to register Readest as the default app:', error);
+      eventDispatcher.dispatch('toast', {
+        type: 'error',
+        message: _('Failed to register Readest for eBook files'),
+        timeout: 4000,
+      });
+    }
+  };
+
   const handleInput = (e: React.FormEvent<HTMLTextAreaElement>) => {
     e.stopPropagation();
     e.nativeEvent.stopImmediatePropagation();
@@ -213,6 +228,23 @@ const MiscPanel: React.FC<SettingsPanelPanelProp> = ({ bookKey, onRegisterReset
         uiTextareaRef,
         'settings.custom.readerUiCss',
       )}
+
+      {appService?.isWindowsApp && (
+        <BoxedList title={_('File Associations')}>
+          <SettingsRow
+            label={_('Default App')}
+            description={_('Open EPUB, MOBI and other eBook files with Readest')}
+          >
+            <button
+              type='button'
+              onClick={handleSetDefaultApp}
+              className='btn btn-ghost btn-sm eink-bordered shrink-0'
+            >
+              {_('Set as Default')}
+            </button>
+          </SettingsRow>
+        </BoxedList>
+      )}
     </div>
   );
 };
@@ -9,11 +9,13 @@ import { eventDispatcher } from '@/utils/event';
 import {
   normalizeNativeKey,
   normalizeDomKeyEvent,
+  volumeKeyMappings,
   PAGE_TURN_ACTIONS,
   PageTurnAction,
 } from '@/utils/keybinding';
 import { HardwarePageTurnerSettings, KeyBinding } from '@/types/settings';
-import { BoxedList, SettingsRow, SettingsSwitchRow } from './primitives';
+import { VolumeKeysLongPress } from '@/types/book';
+import { BoxedList, SettingsRow, SettingsSelect, SettingsSwitchRow } from './primitives';
 import { useReaderStore } from '@/store/readerStore';
 
 type Slot = PageTurnAction;
@@ -28,13 +30,21 @@ const PageTurnerSettings: React.FC<PageTurnerSettingsProps> = ({ bookKey, onRegi
   const _ = useTranslation();
   const { envConfig, appService } = useEnv();
   const { getViewSettings } = useReaderStore();
-  const { setKeyLearnMode, acquireVolumeKeyInterception, releaseVolumeKeyInterception } =
-    useDeviceControlStore();
+  const {
+    setKeyLearnMode,
+    setVolumeKeyMappings,
+    acquireVolumeKeyInterception,
+    releaseVolumeKeyInterception,
+  } = useDeviceControlStore();
   const { settings } = useSettingsStore();
   const viewSettings = getViewSettings(bookKey) || settings.globalViewSettings;
   const resetToDefaults = useResetViewSettings();
 
   const [volumeKeysToFlip, setVolumeKeysToFlip] = useState(viewSettings.volumeKeysToFlip);
+  const [volumeKeysSwapped, setVolumeKeysSwapped] = useState(!!viewSettings.volumeKeysSwapped);
+  const [volumeKeysLongPress, setVolumeKeysLongPress] = useState<VolumeKeysLongPress>(
+    viewSettings.volumeKeysLongPress || 'repeat',
+  );
   const [config, setConfig] = useState<HardwarePageTurnerSettings>(settings.hardwarePageTurner);
   const configRef = useRef(config);
   configRef.current = config;
@@ -123,7 +133,21 @@ const PageTurnerSettings: React.FC<PageTurnerSettingsProps> = ({ bookKey, onRegi
   }, [volumeKeysToFlip]);
 
   useEffect(() => {
-    onRegisterReset?.(() => resetToDefaults({ volumeKeysToFlip: setVolumeKeysToFlip }));
+    saveViewSettings(envConfig, bookKey, 'volumeKeysSwapped', volumeKeysSwapped, false, false);
+    saveViewSettings(envConfig, bookKey, 'volumeKeysLongPress', volumeKeysLongPress, false, false);
+    setVolumeKeyMappings(volumeKeyMappings(volumeKeysSwapped, volumeKeysLongPress));
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [volumeKeysSwapped, volumeKeysLongPress]);
+
+  useEffect(() => {
+    onRegisterReset?.(() => {
+      resetToDefaults({
+        volumeKeysToFlip: setVolumeKeysToFlip,
+        volumeKeysSwapped: setVolumeKeysSwapped,
+      });
+      const defaults = appService?.getDefaultViewSettings();
+      if (defaults?.volumeKeysLongPress) setVolumeKeysLongPress(defaults.volumeKeysLongPress);
+    });
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
@@ -184,6 +208,29 @@ const PageTurnerSettings: React.FC<PageTurnerSettingsProps> = ({ bookKey, onRegi
             onChange={() => setVolumeKeysToFlip(!volumeKeysToFlip)}
           />
         )}
+        {appService?.isMobileApp && (
+          <SettingsSwitchRow
+            label={_('Swap Volume Keys')}
+            checked={volumeKeysSwapped}
+            disabled={!volumeKeysToFlip}
+            onChange={() => setVolumeKeysSwapped(!volumeKeysSwapped)}
+          />
+        )}
+        {appService?.isMobileApp && (
+          <SettingsRow label={_('Hold Volume Key')} disabled={!volumeKeysToFlip}>
+            <SettingsSelect
+              value={volumeKeysLongPress}
+              onChange={(e) => setVolumeKeysLongPress(e.target.value as VolumeKeysLongPress)}
+              ariaLabel={_('Hold Volume Key')}
+              disabled={!volumeKeysToFlip}
+              options={[
+                { value: 'repeat', label: _('Keep Turning Pages') },
+                { value: 'none', label: _('Turn One Page') },
+                { value: 'section', label: _('Skip Section') },
+              ]}
+            />
+          </SettingsRow>
+        )}
         <SettingsSwitchRow
           label={_('Custom Page Turner')}
           checked={config.enabled}
@@ -0,0 +1,25 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { refreshAppShortcuts } from '@/services/appShortcuts';
+
+const APP_SHORTCUTS_PUBLISH_DELAY = 1000;
+
+/**
+ * Keep the Android and iOS launcher shortcuts in step with the recently read
+ * books. Their items open `readest://book/{hash}`, handled by useOpenBookLink.
+ */
+export function useAppShortcuts() {
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+
+  useEffect(() => {
+    if (!appService?.isMobileApp || !libraryLoaded) return;
+    const timer = setTimeout(
+      () => void refreshAppShortcuts(appService, library),
+      APP_SHORTCUTS_PUBLISH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded]);
+}
@@ -30,6 +30,15 @@ interface SharedIntentPayload {
   action?: 'VIEW' | 'SEND';
 }
 
+/**
+ * Android "Share → Readest" through the native bridge's share receiver:
+ * shared books already copied into app storage, and shared links.
+ */
+interface ShareImportPayload {
+  files: string[];
+  urls: string[];
+}
+
 /**
  * Single ingress point for incoming URLs from the operating system.
  *
@@ -37,6 +46,7 @@ interface SharedIntentPayload {
  *   - `single-instance` event  — Win/Linux deep link, macOS open-file
  *   - `open-files` event       — macOS in-app open-files
  *   - `shared-intent` plugin   — Android "Share to Readest" intent
+ *   - `share-import` plugin    — Android share receiver (copied files, links)
  *   - `onOpenUrl`              — iOS / Android / macOS via Tauri v2
  *
  * Re-broadcasts every URL list as the `app-incoming-url` event. Consumers
@@ -106,6 +116,7 @@ export function useAppUrlIngress() {
     // on iOS in the past, so it's gated to Android. The Tauri v2 onOpenUrl
     // listener below covers iOS.
     let unlistenSharedIntent: Promise<PluginListener> | null = null;
+    let unlistenShareImport: Promise<PluginListener> | null = null;
     if (appService?.isAndroidApp) {
       unlistenSharedIntent = addPluginListener<SharedIntentPayload>(
         'native-bridge',
@@ -114,6 +125,16 @@ export function useAppUrlIngress() {
           if (payload.urls?.length) dispatch(payload.urls, payload.action);
         },
       );
+      // Shared files are imported like any "Send to Readest" capture and
+      // shared links are saved as articles by useClipUrlIngress.
+      unlistenShareImport = addPluginListener<ShareImportPayload>(
+        'native-bridge',
+        'share-import',
+        (payload) => {
+          if (payload.files?.length) dispatch(payload.files, 'SEND');
+          if (payload.urls?.length) dispatch(payload.urls);
+        },
+      );
     }
 
     const unlistenOpenUrl = onOpenUrl((urls) => {
@@ -125,6 +146,7 @@ export function useAppUrlIngress() {
       unlistenOpenFiles.then((f) => f());
       unlistenOpenUrl.then((f) => f());
       unlistenSharedIntent?.then((f) => f.unregister());
+      unlistenShareImport?.then((f) => f.unregister());
     };
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [appService]);
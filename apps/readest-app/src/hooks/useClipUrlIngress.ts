@@ -25,6 +25,13 @@ interface PendingShareSave {
   addedAt?: string;
 }
 
+interface PendingShareImport {
+  path: string;
+  groupId?: string | null;
+  groupName?: string | null;
+  addedAt?: string;
+}
+
 /**
  * Handle "Share to Readest" article URLs from the OS share sheet
  * (Safari, Chrome, etc.). Two paths feed in:
@@ -40,6 +47,13 @@ interface PendingShareSave {
  *      `window.__readestOnShareExtensionPending(saves)`. Same ingest
  *      pipeline, but the chosen library group is preserved.
  *
+ *   3. iOS Share-Extension book files — EPUB / PDF shared to the
+ *      extension are copied through the App Group container and handed
+ *      over as local paths via `window.__readestOnShareExtensionImports`.
+ *      They skip the clipper and go straight to `ingestFile`, again in
+ *      the chosen group. The `readest://import-shared` link the
+ *      extension opens only wakes the app; no consumer acts on it.
+ *
  * On iOS we also expose `window.__readestGetGroups()` so the
  * Share-Extension picker can show up-to-date library groups, and we
  * post a `{type:'ready'}` to the WKScriptMessageHandler the plugin
@@ -115,6 +129,43 @@ export function useClipUrlIngress() {
     [_, appService, envConfig, user],
   );
 
+  const importSharedFile = useCallback(
+    async (path: string, options: ClipOptions = {}) => {
+      if (!appService) return;
+      if (inflight.current.has(path)) return;
+      inflight.current.add(path);
+      try {
+        const { library } = useLibraryStore.getState();
+        const { settings } = useSettingsStore.getState();
+        const ingested = await ingestFile(
+          { file: path, books: library, groupId: options.groupId, groupName: options.groupName },
+          { appService, settings, isLoggedIn: !!user },
+        );
+        if (!ingested) {
+          throw new Error('Import produced no book');
+        }
+        await useLibraryStore.getState().updateBooks(envConfig, [ingested]);
+        eventDispatcher.dispatch('toast', {
+          type: 'success',
+          message: _('Saved “{{title}}” to your library.', { title: ingested.title }),
+          timeout: 3000,
+        });
+      } catch (err) {
+        console.error('Failed to import shared file:', path, err);
+        eventDispatcher.dispatch('toast', {
+          type: 'error',
+          message: _('Failed to import book(s): {{filenames}}', {
+            filenames: path.split('/').pop(),
+          }),
+          timeout: 3500,
+        });
+      } finally {
+        inflight.current.delete(path);
+      }
+    },
+    [_, appService, envConfig, user],
+  );
+
   // Deep-link path (existing).
   useEffect(() => {
     if (!isTauriAppPlatform() || !appService) return;
@@ -180,6 +231,7 @@ export function useClipUrlIngress() {
         defaultGroupName: string;
       };
       __readestOnShareExtensionPending?: (saves: PendingShareSave[]) => boolean;
+      __readestOnShareExtensionImports?: (imports: PendingShareImport[]) => boolean;
       webkit?: {
         messageHandlers?: {
           readestShareBridge?: { postMessage: (msg: { type: string }) => void };
@@ -214,6 +266,18 @@ export function useClipUrlIngress() {
       return true;
     };
 
+    w.__readestOnShareExtensionImports = (imports) => {
+      if (!Array.isArray(imports)) return false;
+      for (const item of imports) {
+        if (!item || typeof item.path !== 'string') continue;
+        void importSharedFile(item.path, {
+          groupId: item.groupId ?? undefined,
+          groupName: item.groupName ?? undefined,
+        });
+      }
+      return true;
+    };
+
     // Tell the plugin we're mounted so it can drain any pending saves
     // queued before the hook was alive (cold-start path).
     try {
@@ -226,9 +290,11 @@ export function useClipUrlIngress() {
       const cleanup = window as unknown as {
         __readestGetGroups?: unknown;
         __readestOnShareExtensionPending?: unknown;
+        __readestOnShareExtensionImports?: unknown;
       };
       delete cleanup.__readestGetGroups;
       delete cleanup.__readestOnShareExtensionPending;
+      delete cleanup.__readestOnShareExtensionImports;
     };
-  }, [appService, clipAndImport]);
+  }, [appService, clipAndImport, importSharedFile]);
 }
@@ -0,0 +1,39 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useSettingsStore } from '@/store/settingsStore';
+import { revalidateFolderPermissions } from '@/services/folderPermissions';
+import { eventDispatcher } from '@/utils/event';
+import { useTranslation } from './useTranslation';
+
+// Once per launch: grants only change across reboots, removed SD cards and
+// deleted folders, not while the library page comes and goes.
+let revalidated = false;
+
+/**
+ * On Android, re-check the persisted folder grants at startup, drop the dead
+ * ones and tell the user which auto-import folders have to be picked again.
+ */
+export function useFolderPermissions() {
+  const _ = useTranslation();
+  const { appService } = useEnv();
+  const settings = useSettingsStore((s) => s.settings);
+
+  useEffect(() => {
+    if (!appService?.isAndroidApp || !settings.version || revalidated) return;
+    revalidated = true;
+    revalidateFolderPermissions(settings.autoImportFolders ?? [])
+      .then((lost) => {
+        for (const folder of lost) {
+          eventDispatcher.dispatch('toast', {
+            type: 'warning',
+            message: _(
+              'Readest lost access to {{folder}}. Choose it again in Import from Folder.',
+              { folder },
+            ),
+            timeout: 5000,
+          });
+        }
+      })
+      .catch((err) => console.warn('Failed to check folder permissions', err));
+  }, [_, appService, settings]);
+}
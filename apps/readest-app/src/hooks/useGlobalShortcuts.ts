@@ -0,0 +1,100 @@
+import { useCallback, useEffect } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { useAuth } from '@/context/AuthContext';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { useReaderStore } from '@/store/readerStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { useSidebarStore } from '@/store/sidebarStore';
+import { clipboardTextFile, GlobalShortcutAction } from '@/services/globalShortcuts';
+import { ingestFile } from '@/services/ingestService';
+import { ttsSessionManager } from '@/services/tts/TTSSessionManager';
+import { eventDispatcher } from '@/utils/event';
+import { useTranslation } from './useTranslation';
+
+const toggleReadAloud = () => {
+  const { bookKeys } = useReaderStore.getState();
+  const session = ttsSessionManager.getActiveSession();
+  if (session) {
+    // Through the book's read-aloud controls when it is open here, so they
+    // show the new state.
+    if (bookKeys.includes(session.bookKey)) {
+      eventDispatcher.dispatch('tts-toggle-play', { bookKey: session.bookKey });
+    } else if (session.controller.state === 'playing') {
+      void session.controller.pause();
+    } else {
+      void session.controller.resume();
+    }
+    return;
+  }
+  const { sideBarBookKey } = useSidebarStore.getState();
+  if (sideBarBookKey && bookKeys.includes(sideBarBookKey)) {
+    eventDispatcher.dispatch('tts-speak', { bookKey: sideBarBookKey });
+  }
+};
+
+/**
+ * Carry out the desktop global shortcuts (`global_shortcuts.rs`), which fire
+ * while Readest is in the background: play or pause read-aloud, starting it
+ * in the book being read, and save the clipboard to the library. Links go
+ * through the article clipper (useClipUrlIngress); other text is imported as
+ * a text book. Mounted on both the library and reader pages.
+ */
+export function useGlobalShortcuts() {
+  const _ = useTranslation();
+  const { envConfig, appService } = useEnv();
+  const { user } = useAuth();
+
+  const importText = useCallback(
+    async (text: string) => {
+      if (!appService) return;
+      try {
+        const { library } = useLibraryStore.getState();
+        const { settings } = useSettingsStore.getState();
+        const ingested = await ingestFile(
+          { file: clipboardTextFile(text, _('Clipping')), books: library },
+          { appService, settings, isLoggedIn: !!user },
+        );
+        if (!ingested) throw new Error('Import produced no book');
+        await useLibraryStore.getState().updateBooks(envConfig, [ingested]);
+        eventDispatcher.dispatch('toast', {
+          type: 'success',
+          message: _('Saved “{{title}}” to your library.', { title: ingested.title }),
+          timeout: 3000,
+        });
+      } catch (err) {
+        console.error('Failed to import clipboard text', err);
+        eventDispatcher.dispatch('toast', {
+          type: 'error',
+          message: _('Could not save the clipboard to your library.'),
+          timeout: 3500,
+        });
+      }
+    },
+    [_, appService, envConfig, user],
+  );
+
+  useEffect(() => {
+    if (!appService?.isDesktopApp) return;
+    const unlisten = getCurrentWindow().listen<GlobalShortcutAction>(
+      'global-shortcut',
+      ({ payload }) => {
+        switch (payload.action) {
+          case 'toggleReadAloud':
+            toggleReadAloud();
+            break;
+          case 'captureClipboard':
+            if (payload.url) {
+              eventDispatcher.dispatch('app-incoming-url', { urls: [payload.url] });
+            } else if (payload.text) {
+              void importText(payload.text);
+            }
+            break;
+        }
+      },
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [appService, importText]);
+}
@@ -0,0 +1,65 @@
+import { useEffect, useRef } from 'react';
+import { getCurrent } from '@tauri-apps/plugin-deep-link';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { refreshJumpList } from '@/services/jumpList';
+import { eventDispatcher } from '@/utils/event';
+import { isImportBooksDeepLink } from '@/utils/deeplink';
+import { useTranslation } from './useTranslation';
+
+const JUMP_LIST_PUBLISH_DELAY = 1000;
+
+// Module-scoped like useOpenBookLink's: getCurrent() keeps returning the
+// launch URL for the session, so only the first mount may act on it.
+let coldStartConsumed = false;
+
+/**
+ * Keep the Windows taskbar jump list in step with the recently read books, and
+ * run its "Import Books…" task (`readest://import`) with `onImportBooks` when
+ * given. Mounted on both the library and reader pages; only the library page
+ * can import.
+ */
+export function useJumpList(onImportBooks?: () => void) {
+  const _ = useTranslation();
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+  const onImportBooksRef = useRef(onImportBooks);
+  onImportBooksRef.current = onImportBooks;
+
+  useEffect(() => {
+    if (!appService?.isWindowsApp || !libraryLoaded) return;
+    const labels = {
+      recentBooks: _('Recent Books'),
+      importBooks: _('Import Books…'),
+    };
+    const timer = setTimeout(
+      () => void refreshJumpList(appService, library, labels),
+      JUMP_LIST_PUBLISH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded, _]);
+
+  const canImport = !!onImportBooks;
+  useEffect(() => {
+    if (!appService?.isWindowsApp || !canImport) return;
+
+    if (!coldStartConsumed) {
+      coldStartConsumed = true;
+      getCurrent()
+        .then((urls) => {
+          if (urls?.some(isImportBooksDeepLink)) onImportBooksRef.current?.();
+        })
+        .catch(() => {});
+    }
+
+    const onIncoming = (event: CustomEvent) => {
+      const { urls } = event.detail as { urls: string[] };
+      if (urls.some(isImportBooksDeepLink)) onImportBooksRef.current?.();
+    };
+    eventDispatcher.on('app-incoming-url', onIncoming);
+    return () => {
+      eventDispatcher.off('app-incoming-url', onIncoming);
+    };
+  }, [appService, canImport]);
+}
@@ -0,0 +1,30 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { refreshLibraryProvider } from '@/services/libraryProvider';
+
+// Give the library store time to write library.json, which the index reads.
+const LIBRARY_PROVIDER_REFRESH_DELAY = 1000;
+
+/**
+ * Keep the Android content and documents providers' copy of the library
+ * (titles, covers, progress, book files) in step with the library. Their books
+ * open `readest://book/{hash}`, handled by useOpenBookLink. Other apps only
+ * get answers once the user turns on sharing in the Integrations settings.
+ */
+export function useLibraryProvider() {
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+  const shareWithApps = useSettingsStore((s) => !!s.settings.shareLibraryWithApps);
+
+  useEffect(() => {
+    if (!appService?.isAndroidApp || !libraryLoaded) return;
+    const timer = setTimeout(
+      () => void refreshLibraryProvider(appService, shareWithApps),
+      LIBRARY_PROVIDER_REFRESH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded, shareWithApps]);
+}
@@ -0,0 +1,49 @@
+import { useCallback, useEffect, useState } from 'react';
+import { getCurrent } from '@tauri-apps/plugin-deep-link';
+import { useEnv } from '@/context/EnvContext';
+import { eventDispatcher } from '@/utils/event';
+import { parseLookupDeepLink } from '@/utils/deeplink';
+
+// Module-scoped like useOpenBookLink's: getCurrent() keeps returning the
+// launch URL for the session, so only the first mount may act on it.
+let coldStartConsumed = false;
+
+/**
+ * The word sent by the macOS "Look Up in Readest" service
+ * (`readest://lookup?q=…`), until `dismiss` is called. Live lookups arrive
+ * through 'app-incoming-url'; one that launched the app is read from
+ * getCurrent() once.
+ */
+export function useLookupLink() {
+  const { appService } = useEnv();
+  const [word, setWord] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!appService?.isMacOSApp) return;
+
+    const handle = (urls: string[] | null | undefined) => {
+      const query = urls?.map(parseLookupDeepLink).find(Boolean);
+      if (query) setWord(query);
+    };
+
+    if (!coldStartConsumed) {
+      coldStartConsumed = true;
+      getCurrent()
+        .then(handle)
+        .catch(() => {});
+    }
+
+    const onIncoming = (event: CustomEvent) => {
+      const { urls } = event.detail as { urls: string[] };
+      handle(urls);
+    };
+    eventDispatcher.on('app-incoming-url', onIncoming);
+    return () => {
+      eventDispatcher.off('app-incoming-url', onIncoming);
+    };
+  }, [appService]);
+
+  const dismiss = useCallback(() => setWord(null), []);
+
+  return { word, dismiss };
+}
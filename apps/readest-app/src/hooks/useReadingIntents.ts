@@ -0,0 +1,31 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { refreshReadingIntents } from '@/services/readingIntents';
+
+const READING_INTENTS_PUBLISH_DELAY = 1000;
+
+/**
+ * Keep the iOS Shortcuts / Siri intents' current book and today's reading
+ * time up to date. Reading positions are saved into the library, so library
+ * changes cover page turns; leaving the app publishes the final tally.
+ */
+export function useReadingIntents() {
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+
+  useEffect(() => {
+    if (!appService?.isIOSApp || !libraryLoaded) return;
+    const publish = () => void refreshReadingIntents(appService, library);
+    const timer = setTimeout(publish, READING_INTENTS_PUBLISH_DELAY);
+    const onVisibilityChange = () => {
+      if (document.visibilityState === 'hidden') publish();
+    };
+    document.addEventListener('visibilitychange', onVisibilityChange);
+    return () => {
+      clearTimeout(timer);
+      document.removeEventListener('visibilitychange', onVisibilityChange);
+    };
+  }, [appService, library, libraryLoaded]);
+}
@@ -0,0 +1,63 @@
+import { useEffect } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { useEnv } from '@/context/EnvContext';
+import { useBookDataStore } from '@/store/bookDataStore';
+import { useLibraryStore } from '@/store/libraryStore';
+import { useReaderStore } from '@/store/readerStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { isTauriAppPlatform } from '@/services/environment';
+import {
+  READING_SERVER_PROGRESS_EVENT,
+  applyRemoteProgress,
+  type ReadingServerProgress,
+} from '@/services/readingServer';
+
+/**
+ * Apply progress a remote reader sent to the embedded reading server. A book
+ * open in this window is moved to the new location and its reader saves the
+ * config as usual; with `saveClosedBooks` (the library window) any other book
+ * has its config and library entry updated through the app service.
+ */
+export function useReadingServerProgress(saveClosedBooks = false) {
+  const { envConfig, appService } = useEnv();
+
+  useEffect(() => {
+    if (!appService || !isTauriAppPlatform()) return;
+    const unlisten = listen<ReadingServerProgress>(
+      READING_SERVER_PROGRESS_EVENT,
+      async ({ payload }) => {
+        const { bookKeys, getView } = useReaderStore.getState();
+        const openKey = bookKeys.find((key) => key.split('-')[0] === payload.hash);
+        if (openKey) {
+          const config = useBookDataStore.getState().getConfig(openKey);
+          if (config && payload.location && applyRemoteProgress(config, payload)) {
+            getView(openKey)?.goTo(payload.location);
+          }
+          return;
+        }
+        if (!saveClosedBooks) return;
+        const book = useLibraryStore.getState().getBookByHash(payload.hash);
+        if (!book) return;
+        try {
+          const { settings } = useSettingsStore.getState();
+          const config = await appService.loadBookConfig(book, settings);
+          const updated = applyRemoteProgress(config, payload);
+          if (!updated) return;
+          await appService.saveBookConfig(book, updated, settings);
+          if (updated.progress) {
+            await useLibraryStore.getState().updateBook(envConfig, {
+              ...book,
+              progress: updated.progress,
+              updatedAt: Date.now(),
+            });
+          }
+        } catch (err) {
+          console.error('Failed to apply reading server progress', err);
+        }
+      },
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [appService, envConfig, saveClosedBooks]);
+}
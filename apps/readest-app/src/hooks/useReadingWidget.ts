@@ -1,7 +1,7 @@
 import { useEffect, useRef } from 'react';
 import { useEnv } from '@/context/EnvContext';
 import { useLibraryStore } from '@/store/libraryStore';
-import { refreshReadingWidget } from '@/services/widget/readingWidget';
+import { refreshContinueReading, refreshReadingWidget } from '@/services/widget/readingWidget';
 import { debounce } from '@/utils/debounce';
 import { eventDispatcher } from '@/utils/event';
 import { useTranslation } from './useTranslation';
@@ -12,6 +12,8 @@ import { useTranslation } from './useTranslation';
  * (2) whenever the app goes to the background, (3) immediately on a TTS
  * playback-state change (so controls appear/disappear), and (4) throttled on
  * TTS position advances so the progress percent stays live while speaking.
+ * The Android "Continue reading" widget is also reported to on every library
+ * change, which includes each saved reading position.
  * Mounted on both the library and reader pages.
  */
 export function useReadingWidget() {
@@ -51,7 +53,18 @@ export function useReadingWidget() {
     const TTS_POSITION_PUBLISH_INTERVAL = 5000;
     let lastPositionPublishAt = 0;
 
-    if (libraryLoaded) publish();
+    const publishContinueReading = debounce(
+      () => void refreshContinueReading(appService, labels.emptyTitle),
+      1000,
+    );
+
+    if (libraryLoaded) {
+      publish();
+      publishContinueReading();
+    }
+    const unsubscribeLibrary = useLibraryStore.subscribe((state, prev) => {
+      if (state.libraryLoaded && state.library !== prev.library) publishContinueReading();
+    });
 
     const onVisibility = () => {
       if (document.visibilityState === 'hidden') {
@@ -60,6 +73,8 @@ export function useReadingWidget() {
         // reading progress.
         publish();
         publish.flush();
+        publishContinueReading();
+        publishContinueReading.flush();
       }
     };
 
@@ -92,7 +107,9 @@ export function useReadingWidget() {
       document.removeEventListener('visibilitychange', onVisibility);
       eventDispatcher.off('tts-playback-state', onPlaybackState);
       eventDispatcher.off('tts-position', onPosition);
+      unsubscribeLibrary();
       publish.cancel();
+      publishContinueReading.cancel();
     };
   }, [appService, libraryLoaded, _]);
 }
@@ -0,0 +1,32 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { refreshRecentBooksMenus } from '@/services/recentBooksMenu';
+import { useTranslation } from './useTranslation';
+
+const RECENT_MENU_PUBLISH_DELAY = 1000;
+
+/**
+ * Keep the macOS Dock menu and File ▸ Open Recent in step with the recently
+ * read books. Their items open `readest://book/{hash}`, handled by
+ * useOpenBookLink.
+ */
+export function useRecentBooksMenu() {
+  const _ = useTranslation();
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+
+  useEffect(() => {
+    if (!appService?.isMacOSApp || !libraryLoaded) return;
+    const labels = {
+      continueReading: _('Continue Reading'),
+      recentBooks: _('Recent Books'),
+    };
+    const timer = setTimeout(
+      () => void refreshRecentBooksMenus(appService, library, labels),
+      RECENT_MENU_PUBLISH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded, _]);
+}
@@ -1,8 +1,15 @@
 import { useEffect, useRef } from 'react';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { isTauriAppPlatform, isWebAppPlatform } from '@/services/environment';
+import { setScreenSaverInhibited } from '@/services/screenSaver';
 
-export const useScreenWakeLock = (lock: boolean, hasWindow?: boolean) => {
+// `inhibitScreenSaver` holds the lock through the desktop's screen saver
+// instead of the Screen Wake Lock API, which WebKitGTK on Linux lacks.
+export const useScreenWakeLock = (
+  lock: boolean,
+  hasWindow?: boolean,
+  inhibitScreenSaver = false,
+) => {
   const wakeLockRef = useRef<WakeLockSentinel | null>(null);
 
   useEffect(() => {
@@ -13,6 +20,10 @@ export const useScreenWakeLock = (lock: boolean, hasWindow?: boolean) => {
 
     const requestWakeLock = async () => {
       shouldHoldWakeLock = true;
+      if (inhibitScreenSaver) {
+        void setScreenSaverInhibited('reading', true);
+        return;
+      }
       if (requestPending || wakeLockRef.current) return;
       requestPending = true;
       try {
@@ -39,6 +50,10 @@ export const useScreenWakeLock = (lock: boolean, hasWindow?: boolean) => {
 
     const releaseWakeLock = () => {
       shouldHoldWakeLock = false;
+      if (inhibitScreenSaver) {
+        void setScreenSaverInhibited('reading', false);
+        return;
+      }
       const sentinel = wakeLockRef.current;
       if (sentinel) {
         wakeLockRef.current = null;
@@ -87,5 +102,5 @@ export const useScreenWakeLock = (lock: boolean, hasWindow?: boolean) => {
       }
       void unlistenOnFocusChanged?.then((unlisten) => unlisten());
     };
-  }, [lock, hasWindow]);
+  }, [lock, hasWindow, inhibitScreenSaver]);
 };
@@ -1,4 +1,6 @@
-import { useEffect, useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { isTauriAppPlatform } from '@/services/environment';
 import { loadShortcuts, ShortcutConfig } from '../helpers/shortcuts';
 import { matchesShortcut, ShortcutEventLike } from '../utils/shortcutKeys';
 
@@ -8,6 +10,8 @@ export type KeyActionHandlers = {
 
 const useShortcuts = (actions: KeyActionHandlers, dependencies: React.DependencyList = []) => {
   const [shortcuts, setShortcuts] = useState<ShortcutConfig>(loadShortcuts);
+  const actionsRef = useRef(actions);
+  actionsRef.current = actions;
 
   useEffect(() => {
     const handleShortcutUpdate = () => {
@@ -18,6 +22,17 @@ const useShortcuts = (actions: KeyActionHandlers, dependencies: React.Dependency
     return () => window.removeEventListener('shortcutUpdate', handleShortcutUpdate);
   }, []);
 
+  // Native menu items (the macOS Go menu) send the action they trigger
+  useEffect(() => {
+    if (!isTauriAppPlatform()) return;
+    const unlisten = getCurrentWindow().listen<string>('menu-action', ({ payload }) => {
+      actionsRef.current[payload as keyof ShortcutConfig]?.();
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
   const processKeyEvent = (eventLike: ShortcutEventLike, event: KeyboardEvent | MessageEvent) => {
     // FIXME: This is a temporary fix to disable Back button navigation
     if (eventLike.key.toLowerCase() === 'backspace') return true;
@@ -0,0 +1,26 @@
+import { useEffect } from 'react';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { refreshSpotlightIndex } from '@/services/spotlight';
+
+const SPOTLIGHT_PUBLISH_DELAY = 2000;
+
+/**
+ * Keep the books in Spotlight in step with the library on macOS. Picking a
+ * result opens the book through `readest://book/{hash}`, handled by
+ * useOpenBookLink.
+ */
+export function useSpotlightIndex() {
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+
+  useEffect(() => {
+    if (!appService?.isMacOSApp || !libraryLoaded) return;
+    const timer = setTimeout(
+      () => void refreshSpotlightIndex(appService, library),
+      SPOTLIGHT_PUBLISH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded]);
+}
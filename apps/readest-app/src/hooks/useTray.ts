@@ -0,0 +1,96 @@
+import { useEffect, useRef } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { useEnv } from '@/context/EnvContext';
+import { useLibraryStore } from '@/store/libraryStore';
+import { useSettingsStore } from '@/store/settingsStore';
+import { refreshTrayMenu, setMinimizeToTray, setTrayReadAloud, TrayAction } from '@/services/tray';
+import { ttsSessionManager } from '@/services/tts/TTSSessionManager';
+import { eventDispatcher } from '@/utils/event';
+import { useTranslation } from './useTranslation';
+
+const TRAY_MENU_PUBLISH_DELAY = 1000;
+
+const isReadingAloud = () =>
+  ttsSessionManager.getActiveSession()?.controller.state === 'playing';
+
+/**
+ * Keep the desktop tray menu in step with the recently read books and this
+ * window's read-aloud, and carry out its actions: books open through
+ * `readest://book/{hash}` (useOpenBookLink), "Sync Now" runs `onSyncNow` and
+ * "Pause Read-Aloud" pauses the session playing here. Mounted on both the
+ * library and reader pages.
+ */
+export function useTray(onSyncNow?: () => void) {
+  const _ = useTranslation();
+  const { appService } = useEnv();
+  const library = useLibraryStore((s) => s.library);
+  const libraryLoaded = useLibraryStore((s) => s.libraryLoaded);
+  const minimizeToTray = useSettingsStore((s) => s.settings.minimizeToTray);
+  const onSyncNowRef = useRef(onSyncNow);
+  onSyncNowRef.current = onSyncNow;
+
+  useEffect(() => {
+    if (!appService?.isDesktopApp || !libraryLoaded) return;
+    const labels = {
+      continueReading: _('Continue Reading'),
+      recentBooks: _('Recent Books'),
+      syncNow: _('Sync Now'),
+      pauseReadAloud: _('Pause Read-Aloud'),
+      show: _('Show Readest'),
+      quit: _('Quit Readest'),
+    };
+    const timer = setTimeout(
+      () => void refreshTrayMenu(appService, library, labels),
+      TRAY_MENU_PUBLISH_DELAY,
+    );
+    return () => clearTimeout(timer);
+  }, [appService, library, libraryLoaded, _]);
+
+  useEffect(() => {
+    setMinimizeToTray(appService, !!minimizeToTray);
+  }, [appService, minimizeToTray]);
+
+  useEffect(() => {
+    if (!appService?.isDesktopApp) return;
+    let readingAloud = isReadingAloud();
+    setTrayReadAloud(appService, readingAloud);
+    const update = () => {
+      if (isReadingAloud() === readingAloud) return;
+      readingAloud = !readingAloud;
+      setTrayReadAloud(appService, readingAloud);
+    };
+    ttsSessionManager.addEventListener('session-changed', update);
+    eventDispatcher.on('tts-playback-state', update);
+    return () => {
+      ttsSessionManager.removeEventListener('session-changed', update);
+      eventDispatcher.off('tts-playback-state', update);
+      if (readingAloud) setTrayReadAloud(appService, false);
+    };
+  }, [appService]);
+
+  useEffect(() => {
+    if (!appService?.isDesktopApp) return;
+    const unlisten = getCurrentWindow().listen<TrayAction>('tray-action', ({ payload }) => {
+      switch (payload.action) {
+        case 'openBook':
+          if (payload.bookHash) {
+            eventDispatcher.dispatch('app-incoming-url', {
+              urls: [`readest://book/${payload.bookHash}`],
+            });
+          }
+          break;
+        case 'syncNow':
+          onSyncNowRef.current?.();
+          break;
+        case 'pauseReadAloud': {
+          const controller = ttsSessionManager.getActiveSession()?.controller;
+          if (controller?.state === 'playing') void controller.pause();
+          break;
+        }
+      }
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [appService]);
+}
@@ -142,15 +142,25 @@ export interface DocumentLoaderOptions {
    * EPUB when the prefetch cache is hit.
    */
   nativeFilePath?: string;
+  /**
+   * Hash of the library book being opened. Together with `nativeFilePath`
+   * it routes the book's (X)HTML documents through the `bookres` scheme,
+   * which applies the book's content-security policy to them.
+   */
+  bookHash?: string;
 }
 
+const isDocumentEntry = (name: string) => /\.(xhtml|xht|html|htm)$/i.test(name);
+
 export class DocumentLoader {
   private file: File;
   private nativeFilePath?: string;
+  private bookHash?: string;
 
   constructor(file: File, options: DocumentLoaderOptions = {}) {
     this.file = file;
     this.nativeFilePath = options.nativeFilePath;
+    this.bookHash = options.bookHash;
   }
 
   private async isZip(): Promise<boolean> {
@@ -201,6 +211,30 @@ export class DocumentLoader {
     );
   }
 
+  /**
+   * Loader for a zip entry's text through the `bookres` scheme, or null when
+   * this isn't a library book on disk. The response comes back with the
+   * book's policy as a `<meta>` CSP (and the storage guard when needed)
+   * injected, so it survives foliate-js turning the text into a blob URL.
+   */
+  private async makeBookResourceLoader() {
+    if (!this.nativeFilePath || !this.bookHash) return null;
+    const { convertFileSrc } = await import('@tauri-apps/api/core');
+    // `bookres://localhost/` or `http://bookres.localhost/`, per platform.
+    const origin = convertFileSrc('', 'bookres');
+    const query =
+      `?path=${encodeURIComponent(this.nativeFilePath)}` +
+      `&book=${encodeURIComponent(this.bookHash)}`;
+    return async (name: string): Promise<string | null> => {
+      const entry = name.split('/').map(encodeURIComponent).join('/');
+      const response = await fetch(`${origin}${entry}${query}`);
+      if (response.status === 404) return null;
+      // No zip.js fallback: that would render the document without its policy.
+      if (!response.ok) throw new Error(`Failed to load ${name}: ${response.status}`);
+      return response.text();
+    };
+  }
+
   private async makeZipLoader(prefetch?: {
     textCache?: Map<string, string>;
     sizes?: Map<string, number>;
@@ -257,6 +291,11 @@ export class DocumentLoader {
     const zipLoadText = load((entry: Entry) =>
       !entry.directory ? entry.getData(new TextWriter()) : null,
     );
+    const bookResourceText = await this.makeBookResourceLoader();
+    const entryText = bookResourceText
+      ? (name: string, ...args: [string?]) =>
+          isDocumentEntry(name) ? bookResourceText(name) : zipLoadText(name, ...args)
+      : zipLoadText;
     const loadBlob = load((entry: Entry, type?: string) =>
       !entry.directory ? entry.getData(new BlobWriter(type!)) : null,
     );
@@ -295,7 +334,7 @@ export class DocumentLoader {
       const existing = inflight.get(name);
       if (existing) return existing;
       const p =
-        (zipLoadText(name, ...args) as Promise<string | null> | null) ?? Promise.resolve(null);
+        (entryText(name, ...args) as Promise<string | null> | null) ?? Promise.resolve(null);
       const wrapped = Promise.resolve(p).finally(() => {
         // Release as soon as the promise settles; subsequent independent
         // reads will re-inflate (intentional — we don't want a nav-time
@@ -308,7 +347,10 @@ export class DocumentLoader {
 
     const loadText = textCache
       ? (name: string, ...args: [string?]) => {
-          const cached = textCache.get(name);
+          // A prefetched nav document may also be in the spine, so documents
+          // still go through `bookres` for their policy.
+          const cached =
+            bookResourceText && isDocumentEntry(name) ? undefined : textCache.get(name);
           if (cached !== undefined) return Promise.resolve(cached);
           return dedupedZipLoadText(name, ...args);
         }
@@ -2,6 +2,8 @@ import { isTauriAppPlatform } from '@/services/environment';
 import { getOSPlatform } from '@/utils/misc';
 import { invoke } from '@tauri-apps/api/core';
 import { addPluginListener, PluginListener, PermissionState } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { setScreenSaverInhibited } from '@/services/screenSaver';
 
 export interface MediaMetadata {
   title?: string;
@@ -14,6 +16,14 @@ export interface PlaybackState {
   playing: boolean;
   position?: number; // in milliseconds
   duration?: number; // in milliseconds
+  // Lets the lock-screen scrubber be dragged (delivered as 'seekto').
+  seekable?: boolean;
+  // The chapter being read; the chapter skip actions show when there is
+  // one to go to.
+  chapterIndex?: number;
+  chapterCount?: number;
+  // The chapter's label, shown on the iOS read-aloud Live Activity.
+  chapterTitle?: string;
 }
 
 export interface MediaSessionState {
@@ -29,12 +39,34 @@ export interface MediaSessionState {
   bookAuthor?: string;
 }
 
+// A phone call, navigation prompt or another app taking the audio. The
+// plugin pauses (or ducks) speech itself and also delivers 'pause'/'play';
+// these bracket the interruption so callers can hold their position.
+export interface AudioInterruption {
+  phase: 'began' | 'ended';
+  // Lowered rather than paused (interruption mode 'duck').
+  ducked: boolean;
+  // The audio is gone for good, e.g. another player took over.
+  permanent: boolean;
+  // On 'ended': whether the system suggests picking up again.
+  shouldResume: boolean;
+}
+
+// 'pause' stops speech for any interruption; 'duck' keeps it speaking
+// quieter under transient ones that allow it (e.g. navigation prompts).
+export type InterruptionMode = 'pause' | 'duck';
+
+type ActionHandler =
+  | (() => void)
+  | ((position: number) => void)
+  | ((interruption: AudioInterruption) => void);
+
 interface Permissions {
   postNotification: PermissionState;
 }
 
 export class TauriMediaSession {
-  private handlers: { [key: string]: (() => void) | ((position: number) => void) } = {};
+  protected handlers: { [key: string]: ActionHandler } = {};
   private eventListenerInited: boolean = false;
   private eventListeners: PluginListener[] = [];
 
@@ -89,6 +121,30 @@ export class TauriMediaSession {
     });
     this.eventListeners.push(previousListener);
 
+    // Chapter skip: Android custom session actions, iOS press-and-hold on the
+    // track buttons. Not in the web MediaSession vocabulary.
+    const nextChapterListener = await addPluginListener(
+      'native-tts',
+      'media-session-next-chapter',
+      () => {
+        if (this.handlers['nextchapter']) {
+          (this.handlers['nextchapter'] as () => void)();
+        }
+      },
+    );
+    this.eventListeners.push(nextChapterListener);
+
+    const previousChapterListener = await addPluginListener(
+      'native-tts',
+      'media-session-previous-chapter',
+      () => {
+        if (this.handlers['previouschapter']) {
+          (this.handlers['previouschapter'] as () => void)();
+        }
+      },
+    );
+    this.eventListeners.push(previousChapterListener);
+
     // iOS skip-interval commands (the icons the lock-screen card renders);
     // routed to the sentence-level seek handlers.
     const seekForwardListener = await addPluginListener(
@@ -127,6 +183,17 @@ export class TauriMediaSession {
       },
     );
     this.eventListeners.push(seekListener);
+
+    const interruptionListener = await addPluginListener(
+      'native-tts',
+      'audio-interruption',
+      (payload: AudioInterruption) => {
+        if (this.handlers['interruption']) {
+          (this.handlers['interruption'] as (interruption: AudioInterruption) => void)(payload);
+        }
+      },
+    );
+    this.eventListeners.push(interruptionListener);
   }
 
   private async cleanupListeners() {
@@ -159,11 +226,14 @@ export class TauriMediaSession {
       // on Android 13+ it is silently suppressed unless POST_NOTIFICATIONS is
       // granted. Request it on every activation (no-op once decided).
       // Best-effort: it must never block or abort the foreground-service start
-      // below, so it gets its own catch.
-      try {
-        await this.requestPostNotificationPermission();
-      } catch (error) {
-        console.warn('POST_NOTIFICATIONS request failed:', error);
+      // below, so it gets its own catch. Windows (System Media Transport
+      // Controls) has no notification to ask for.
+      if (getOSPlatform() !== 'windows') {
+        try {
+          await this.requestPostNotificationPermission();
+        } catch (error) {
+          console.warn('POST_NOTIFICATIONS request failed:', error);
+        }
       }
       try {
         await this.initializeListeners();
@@ -186,7 +256,15 @@ export class TauriMediaSession {
     }
   }
 
-  setActionHandler(action: string, handler: (() => void) | ((position: number) => void) | null) {
+  async setInterruptionMode(mode: InterruptionMode) {
+    try {
+      await invoke('plugin:native-tts|set_interruption_mode', { payload: { mode } });
+    } catch (error) {
+      console.error('Failed to set interruption mode:', error);
+    }
+  }
+
+  setActionHandler(action: string, handler: ActionHandler | null) {
     if (handler) {
       this.handlers[action] = handler;
     } else {
@@ -277,13 +355,10 @@ export class IOSCompositeMediaSession extends TauriMediaSession {
     await super.setActive(sessionState);
   }
 
-  override setActionHandler(
-    action: string,
-    handler: (() => void) | ((position: number) => void) | null,
-  ): void {
+  override setActionHandler(action: string, handler: ActionHandler | null): void {
     super.setActionHandler(action, handler);
-    // 'toggle' is not in the web MediaSession action vocabulary.
-    if (action === 'toggle') return;
+    // These are not in the web MediaSession action vocabulary.
+    if (['toggle', 'nextchapter', 'previouschapter', 'interruption'].includes(action)) return;
     try {
       if (!handler) {
         this.web.setActionHandler(action as MediaSessionAction, null);
@@ -305,6 +380,103 @@ export class IOSCompositeMediaSession extends TauriMediaSession {
   }
 }
 
+interface AppMediaAction {
+  action: string;
+  // Milliseconds, for 'seekto'.
+  position?: number;
+}
+
+// A desktop media surface served by the app itself rather than the plugin,
+// through its `${prefix}_set_active`, `_update_metadata` and `_update_state`
+// commands. The system's requests come back as `event`, carrying the action
+// names registered here.
+class AppMediaSession extends TauriMediaSession {
+  private unlisten: UnlistenFn | null = null;
+
+  constructor(
+    private readonly prefix: string,
+    private readonly event: string,
+  ) {
+    super();
+  }
+
+  override async updateMetadata(metadata: MediaMetadata): Promise<void> {
+    try {
+      await invoke(`${this.prefix}_update_metadata`, { metadata });
+    } catch (error) {
+      console.error('Failed to update media metadata:', error);
+    }
+  }
+
+  override async updatePlaybackState(state: PlaybackState): Promise<void> {
+    try {
+      await invoke(`${this.prefix}_update_state`, { state });
+    } catch (error) {
+      console.error('Failed to update playback state:', error);
+    }
+  }
+
+  override async setActive(sessionState: MediaSessionState): Promise<void> {
+    if (sessionState.active && !this.unlisten) {
+      this.unlisten = await listen<AppMediaAction>(this.event, ({ payload }) => {
+        const handler = this.handlers[payload.action];
+        if (!handler) return;
+        if (payload.action === 'seekto') {
+          (handler as (position: number) => void)(payload.position ?? 0);
+        } else {
+          (handler as () => void)();
+        }
+      });
+    } else if (!sessionState.active) {
+      this.unlisten?.();
+      this.unlisten = null;
+    }
+    try {
+      await invoke(`${this.prefix}_set_active`, { active: sessionState.active });
+    } catch (error) {
+      // e.g. no session bus for MPRIS: speech plays on regardless.
+      console.warn('Media session unavailable:', error);
+    }
+  }
+}
+
+// Linux: WebKitGTK publishes nothing to the desktop's media panel for
+// WebAudio speech, so the app serves MPRIS itself (src-tauri/src/mpris.rs).
+// Nor does it keep the screen on, so the screen saver is inhibited while
+// speech plays (src-tauri/src/screensaver.rs).
+export class MprisMediaSession extends AppMediaSession {
+  private inhibiting = false;
+
+  constructor() {
+    super('mpris', 'mpris-action');
+  }
+
+  private inhibitScreenSaver(inhibit: boolean) {
+    if (inhibit === this.inhibiting) return;
+    this.inhibiting = inhibit;
+    void setScreenSaverInhibited('readAloud', inhibit);
+  }
+
+  override async updatePlaybackState(state: PlaybackState): Promise<void> {
+    this.inhibitScreenSaver(state.playing);
+    await super.updatePlaybackState(state);
+  }
+
+  override async setActive(sessionState: MediaSessionState): Promise<void> {
+    if (!sessionState.active) this.inhibitScreenSaver(false);
+    await super.setActive(sessionState);
+  }
+}
+
+// macOS: Control Center, AirPods taps and the media keys, through
+// MPNowPlayingInfoCenter in the app (src-tauri/src/macos/now_playing.rs);
+// WKWebView publishes only the media elements it plays.
+export class MacNowPlayingMediaSession extends AppMediaSession {
+  constructor() {
+    super('now_playing', 'now-playing-action');
+  }
+}
+
 export function getMediaSession() {
   const platform = getOSPlatform();
   // Android: the native foreground-service media session (TextToSpeech and
@@ -328,6 +500,18 @@ export function getMediaSession() {
     }
     return new TauriMediaSession();
   }
+  // Windows: the System Media Transport Controls (overlay, lock screen,
+  // media keys) through the plugin. WebView2 doesn't connect
+  // navigator.mediaSession to them.
+  if (platform === 'windows' && isTauriAppPlatform()) {
+    return new TauriMediaSession();
+  }
+  if (platform === 'macos' && isTauriAppPlatform()) {
+    return new MacNowPlayingMediaSession();
+  }
+  if (platform === 'linux' && isTauriAppPlatform()) {
+    return new MprisMediaSession();
+  }
   // Web: navigator.mediaSession, driven by whatever media element plays.
   if ('mediaSession' in navigator) {
     return navigator.mediaSession;
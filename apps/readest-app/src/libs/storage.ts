@@ -156,7 +156,6 @@ type DownloadFileParams = {
   url?: string;
   headers?: Record<string, string>;
   singleThreaded?: boolean;
-  skipSslVerification?: boolean;
   onProgress?: ProgressHandler;
 };
 
@@ -167,7 +166,6 @@ export const downloadFile = async ({
   url,
   headers,
   singleThreaded,
-  skipSslVerification,
   onProgress,
 }: DownloadFileParams) => {
   try {
@@ -209,7 +207,6 @@ export const downloadFile = async ({
         headers,
         undefined,
         singleThreaded,
-        skipSslVerification,
       );
     }
   } catch (error) {
@@ -0,0 +1,43 @@
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+import { getCoverFilename, selectRecentBooks } from '@/utils/book';
+import { updateAppShortcuts } from '@/utils/bridge';
+import type { AppShortcutBook } from '@/utils/bridge';
+
+/** Launchers show few shortcuts; three leaves room for the static ones. */
+export const APP_SHORTCUT_LIMIT = 3;
+
+// The shortcuts show no progress, so only a change in which books are most
+// recent (or their titles) needs a native call, not every progress save.
+let lastPublished = '';
+
+/**
+ * Point the long-press launcher shortcuts at the most recently read books.
+ * Each opens `readest://book/{hash}`, handled by useOpenBookLink, and the
+ * reader resumes at the book's saved position.
+ */
+export const refreshAppShortcuts = async (
+  appService: AppService,
+  library: Book[],
+): Promise<void> => {
+  if (!appService.isMobileApp) return;
+  const selected = selectRecentBooks(library, APP_SHORTCUT_LIMIT);
+  const published = JSON.stringify(
+    selected.map(({ hash, title, author }) => [hash, title, author]),
+  );
+  if (published === lastPublished) return;
+  lastPublished = published;
+  const booksDir = (await appService.resolveFilePath('', 'Books')).replace(/\/+$/, '');
+  const books: AppShortcutBook[] = selected.map((book) => ({
+    hash: book.hash,
+    title: book.title ?? '',
+    author: book.author ?? '',
+    coverPath: `${booksDir}/${getCoverFilename(book)}`,
+  }));
+  try {
+    await updateAppShortcuts({ books });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update app shortcuts', err);
+  }
+};
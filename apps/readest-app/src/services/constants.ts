@@ -145,6 +145,7 @@ export const DEFAULT_ONEDRIVE_SETTINGS = {
 export const DEFAULT_SYSTEM_SETTINGS: Partial<SystemSettings> = {
   keepLogin: false,
   alwaysOnTop: false,
+  minimizeToTray: false,
   openBookInNewWindow: true,
   alwaysShowStatusBar: false,
   autoCheckUpdates: true,
@@ -168,6 +169,7 @@ export const DEFAULT_SYSTEM_SETTINGS: Partial<SystemSettings> = {
   autoImportBooksOnOpen: false,
   telemetryEnabled: true,
   discordRichPresenceEnabled: false,
+  shareLibraryWithApps: false,
   libraryViewMode: 'grid',
   librarySortBy: LibrarySortByType.Updated,
   librarySortAscending: false,
@@ -301,6 +303,8 @@ export const DEFAULT_BOOK_LAYOUT: BookLayout = {
   swapClickArea: false,
   disableDoubleClick: false,
   volumeKeysToFlip: false,
+  volumeKeysSwapped: false,
+  volumeKeysLongPress: 'repeat',
   maxColumnCount: 2,
   maxInlineSize: getDefaultMaxInlineSize(),
   maxBlockSize: getDefaultMaxBlockSize(),
@@ -425,6 +429,7 @@ export const DEFAULT_TTS_CONFIG: TTSConfig = {
   ttsHighlightGranularity: 'word',
   ttsMediaMetadata: 'sentence',
   ttsPlayerStyle: 'full',
+  ttsPronunciations: [],
 };
 
 export const DEFAULT_TRANSLATOR_CONFIG: TranslatorConfig = {
@@ -5,11 +5,18 @@ import { getRuntimeConfig } from './runtimeConfig';
 declare global {
   interface Window {
     __READEST_CLI_ACCESS?: boolean;
+    __READEST_READING_SERVER?: boolean;
   }
 }
 
-export const isTauriAppPlatform = () => process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'tauri';
-export const isWebAppPlatform = () => process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'web';
+// The app's embedded reading server hands its bundle to plain browsers and
+// flags the page, which then has no Tauri runtime and runs as the web app.
+export const isReadingServerClient = () =>
+  typeof window !== 'undefined' && window.__READEST_READING_SERVER === true;
+export const isTauriAppPlatform = () =>
+  process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'tauri' && !isReadingServerClient();
+export const isWebAppPlatform = () =>
+  process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'web' || isReadingServerClient();
 export const hasCli = () => window.__READEST_CLI_ACCESS === true;
 export const isPWA = () => window.matchMedia('(display-mode: standalone)').matches;
 export const getBaseUrl = () =>
@@ -0,0 +1,41 @@
+import {
+  listPersistedUriPermissions,
+  releasePersistableUriPermission,
+  type PersistedUriPermission,
+} from '@/utils/bridge';
+
+const trimSlashes = (path: string) => path.replace(/\/+$/, '');
+
+const coversFolder = (grant: PersistedUriPermission, folder: string): boolean => {
+  if (!grant.path) return false;
+  const root = trimSlashes(grant.path);
+  const target = trimSlashes(folder);
+  return target === root || target.startsWith(`${root}/`);
+};
+
+/**
+ * Re-check the folder grants Android keeps for the app (the Storage Access
+ * Framework tree URIs the folder picker persisted). A grant outlives its
+ * folder, and the system caps how many an app may hold, so grants whose
+ * folder no longer resolves are released. Returns the watched folders that
+ * were covered only by such a grant: the user has to pick those again.
+ */
+export const revalidateFolderPermissions = async (
+  watchedFolders: string[],
+): Promise<string[]> => {
+  const { permissions } = await listPersistedUriPermissions();
+  const stale = permissions.filter((grant) => !grant.accessible);
+  const live = permissions.filter((grant) => grant.accessible);
+  for (const grant of stale) {
+    try {
+      await releasePersistableUriPermission({ uri: grant.uri });
+    } catch (err) {
+      console.warn('Failed to release folder permission', grant.uri, err);
+    }
+  }
+  return watchedFolders.filter(
+    (folder) =>
+      stale.some((grant) => coversFolder(grant, folder)) &&
+      !live.some((grant) => coversFolder(grant, folder)),
+  );
+};
@@ -0,0 +1,23 @@
+/** What a desktop global shortcut asks the webview to do. */
+export interface GlobalShortcutAction {
+  action: 'toggleReadAloud' | 'captureClipboard';
+  /** The link on the clipboard, clipped as an article. */
+  url?: string;
+  /** The clipboard's text when it is not a link, imported as a text book. */
+  text?: string;
+}
+
+const CLIPPING_TITLE_MAX_LENGTH = 60;
+
+/**
+ * A `.txt` file holding clipboard text, named after its first line so the
+ * imported book gets a recognizable title.
+ */
+export const clipboardTextFile = (text: string, fallbackTitle: string): File => {
+  const firstLine = text.trim().split('\n')[0]!.trim();
+  let title = firstLine.replace(/[\\/:*?"<>|]+/g, ' ').replace(/\s+/g, ' ').trim();
+  if (title.length > CLIPPING_TITLE_MAX_LENGTH) {
+    title = `${title.slice(0, CLIPPING_TITLE_MAX_LENGTH).trimEnd()}…`;
+  }
+  return new File([text.trim()], `${title || fallbackTitle}.txt`, { type: 'text/plain' });
+};
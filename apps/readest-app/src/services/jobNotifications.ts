@@ -0,0 +1,14 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { AppService } from '@/types/system';
+
+/**
+ * Announce a finished background job through the system notification center.
+ * The backend drops it while the window has focus, where the in-app toast is
+ * enough.
+ */
+export const notifyJobFinished = (appService: AppService | null, title: string, body: string) => {
+  if (!appService?.isDesktopApp) return;
+  invoke('notify_job_finished', { title, body }).catch((err) =>
+    console.warn('Failed to show notification', err),
+  );
+};
@@ -0,0 +1,49 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+import { selectRecentBooks } from '@/utils/book';
+
+/** Recent books shown in the Windows taskbar jump list. */
+export const JUMP_LIST_LIMIT = 10;
+
+export interface JumpListBook {
+  hash: string;
+  title: string;
+  author: string;
+}
+
+export interface JumpListLabels {
+  recentBooks: string;
+  importBooks: string;
+}
+
+/** The recent books, reduced to what the jump list shows. */
+export const selectJumpListBooks = (library: Book[], limit = JUMP_LIST_LIMIT): JumpListBook[] =>
+  selectRecentBooks(library, limit).map((book) => ({
+    hash: book.hash,
+    title: book.title ?? '',
+    author: book.author ?? '',
+  }));
+
+// The jump list is rebuilt from scratch on every update, so skip the native
+// call when nothing it shows has changed (progress saves touch the library
+// constantly while reading).
+let lastPublished = '';
+
+export const refreshJumpList = async (
+  appService: AppService,
+  library: Book[],
+  labels: JumpListLabels,
+): Promise<void> => {
+  if (!appService.isWindowsApp) return;
+  const books = selectJumpListBooks(library);
+  const published = JSON.stringify({ books, labels });
+  if (published === lastPublished) return;
+  lastPublished = published;
+  try {
+    await invoke('update_jump_list', { books, labels });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update jump list', err);
+  }
+};
@@ -0,0 +1,11 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * Keep the computer from sleeping while this window reads aloud or
+ * auto-scrolls, or stop doing so. The backend holds the system awake while
+ * any window asks; the screen may still turn off.
+ */
+export const setKeepAwake = (enabled: boolean) =>
+  invoke('set_keep_awake', { enabled }).catch((err) =>
+    console.warn('Failed to keep the system awake', err),
+  );
@@ -0,0 +1,46 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { AppService, BaseDir } from '@/types/system';
+
+/** Annotation exports kept under the Data dir, listed by the Files app. */
+export const EXPORTED_ANNOTATIONS_DIR = 'exports/annotations';
+
+const resolveDir = async (appService: AppService, path: string, base: BaseDir) =>
+  (await appService.resolveFilePath(path, base)).replace(/\/+$/, '');
+
+/**
+ * Ask the Rust side to re-index `library.json` for the Android content and
+ * documents providers that automation apps, launchers and the Files app
+ * query. The index is rebuilt from disk and only reaches the native bridge
+ * when it changed, so this is cheap to call whenever the library changes.
+ * The content provider only answers other apps when `shareWithApps` is on.
+ */
+export const refreshLibraryProvider = async (
+  appService: AppService,
+  shareWithApps: boolean,
+): Promise<void> => {
+  if (!appService.isAndroidApp) return;
+  const booksDir = await resolveDir(appService, '', 'Books');
+  const annotationsDir = await resolveDir(appService, EXPORTED_ANNOTATIONS_DIR, 'Data');
+  try {
+    await invoke('refresh_library_provider', { booksDir, annotationsDir, shareWithApps });
+  } catch (err) {
+    console.warn('Failed to refresh library provider', err);
+  }
+};
+
+/**
+ * Keep a copy of an annotations export on Android, where the share sheet is
+ * the only other way out, so it shows up in the Files app next to the books.
+ */
+export const keepExportedAnnotations = async (
+  appService: AppService,
+  filename: string,
+  content: string,
+): Promise<void> => {
+  if (!appService.isAndroidApp) return;
+  try {
+    await appService.writeFile(`${EXPORTED_ANNOTATIONS_DIR}/${filename}`, 'Data', content);
+  } catch (err) {
+    console.warn('Failed to keep exported annotations', err);
+  }
+};
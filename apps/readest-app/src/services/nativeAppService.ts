@@ -471,7 +471,7 @@ export const nativeFileSystem: FileSystem = {
     // Use Rust WalkDir for massive performance gain on absolute paths
     if (!baseDir || baseDir === 0) {
       try {
-        const files = await invoke<{ path: string; size: number }[]>('read_dir', {
+        const { files } = await invoke<{ files: { path: string; size: number }[] }>('read_dir', {
           path: fp,
           recursive: true,
           extensions: ['*'],
@@ -74,13 +74,10 @@ async function downloadAndImport(
     cfp: '',
     url: downloadUrl,
     headers,
+    // The native downloader always checks the certificate. Self-signed or
+    // private-CA servers (#2871, #4988) are trusted per host in the app's
+    // trusted hosts, the same as for the manual download path.
     singleThreaded: true,
-    // Same self-signed/private-CA workaround as the manual download path
-    // (#2871): the native downloader's rustls validation ignores the OS
-    // trust store, so without this flag auto-download fails the TLS
-    // handshake on servers where feed browsing and manual download work
-    // (#4988).
-    skipSslVerification: true,
   });
 
   const probedFilename = await probeFilename(responseHeaders);
@@ -0,0 +1,120 @@
+import { invoke } from '@tauri-apps/api/core';
+
+// Elements that can't print or would reach outside the document.
+const UNPRINTABLE = 'script, iframe, frame, object, embed, video, audio, link, meta, base';
+
+// The reader's theme colors and columns don't belong on paper.
+const PRINT_STYLE = `
+html, body { background: #fff !important; color: #000 !important; }
+html { columns: auto !important; height: auto !important; width: auto !important; }
+body { margin: 0 auto !important; max-width: none !important; }
+img, svg { max-width: 100% !important; height: auto; break-inside: avoid; }
+`;
+
+const escapeHtml = (text: string) =>
+  text
+    .replace(/&/g, '&amp;')
+    .replace(/</g, '&lt;')
+    .replace(/>/g, '&gt;')
+    .replace(/"/g, '&quot;');
+
+export interface PrintDocument {
+  title: string;
+  lang?: string;
+  dir?: string;
+  /** The section's CSS, with the print overrides going after it. */
+  styles: string[];
+  /** Serialized body content. */
+  body: string;
+}
+
+export const buildPrintDocument = ({ title, lang, dir, styles, body }: PrintDocument): string => {
+  const attrs = [lang && `lang="${escapeHtml(lang)}"`, dir && `dir="${escapeHtml(dir)}"`]
+    .filter(Boolean)
+    .join(' ');
+  return [
+    '<!DOCTYPE html>',
+    `<html${attrs ? ` ${attrs}` : ''}>`,
+    '<head>',
+    '<meta charset="utf-8">',
+    `<title>${escapeHtml(title)}</title>`,
+    ...[...styles, PRINT_STYLE].map((css) => `<style>${css.replace(/<\//g, '<\\/')}</style>`),
+    '</head>',
+    `<body>${body}</body>`,
+    '</html>',
+  ].join('\n');
+};
+
+const toDataUrl = async (url: string): Promise<string | null> => {
+  try {
+    const blob = await (await fetch(url)).blob();
+    return await new Promise((resolve, reject) => {
+      const reader = new FileReader();
+      reader.onload = () => resolve(reader.result as string);
+      reader.onerror = () => reject(reader.error);
+      reader.readAsDataURL(blob);
+    });
+  } catch (err) {
+    console.warn('Failed to inline image for printing', url, err);
+    return null;
+  }
+};
+
+// The print window can't reach the book's blob URLs, so images go in as data.
+const inlineImages = async (root: Element, baseURI: string) => {
+  const images: [Element, string][] = [];
+  root.querySelectorAll('img[src]').forEach((el) => images.push([el, 'src']));
+  root.querySelectorAll('image').forEach((el) => {
+    if (el.hasAttribute('href')) images.push([el, 'href']);
+    else if (el.hasAttribute('xlink:href')) images.push([el, 'xlink:href']);
+  });
+  await Promise.all(
+    images.map(async ([el, attr]) => {
+      el.removeAttribute('srcset');
+      const value = el.getAttribute(attr)!;
+      if (value.startsWith('data:')) return;
+      const dataUrl = await toDataUrl(new URL(value, baseURI).href);
+      if (dataUrl) el.setAttribute(attr, dataUrl);
+      else el.remove();
+    }),
+  );
+};
+
+const sectionStyles = (doc: Document): string[] =>
+  Array.from(doc.styleSheets).flatMap((sheet) => {
+    try {
+      return [Array.from(sheet.cssRules, (rule) => rule.cssText).join('\n')];
+    } catch {
+      return [];
+    }
+  });
+
+/**
+ * The HTML to print for `range` of a section, or the whole section when there
+ * is no range, with everything it needs inlined.
+ */
+export const collectPrintDocument = async (
+  doc: Document,
+  title: string,
+  range?: Range | null,
+): Promise<string> => {
+  const root = doc.createElement('div');
+  if (range && !range.collapsed) {
+    root.appendChild(range.cloneContents());
+  } else {
+    Array.from(doc.body.childNodes).forEach((node) => root.appendChild(node.cloneNode(true)));
+  }
+  root.querySelectorAll(UNPRINTABLE).forEach((el) => el.remove());
+  await inlineImages(root, doc.baseURI);
+  return buildPrintDocument({
+    title,
+    lang: doc.documentElement.lang,
+    dir: doc.documentElement.dir,
+    styles: sectionStyles(doc),
+    body: root.innerHTML,
+  });
+};
+
+/** Open the system print dialog for `html`, from a print window of its own. */
+export const printContent = (html: string, title: string) =>
+  invoke<void>('print_content', { html, title });
@@ -0,0 +1,40 @@
+import tinycolor from 'tinycolor2';
+import { invoke } from '@tauri-apps/api/core';
+import { makeSafeFilename } from '@/utils/misc';
+
+export interface QuoteImageRequest {
+  text: string;
+  title: string;
+  author: string;
+  chapter?: string;
+  /** Absolute path of the book's cover. */
+  coverPath?: string;
+  background?: string;
+  foreground?: string;
+  /** The highlight's color. */
+  accent?: string;
+}
+
+/** Any CSS color as `#rrggbb`, which is what the renderer reads. */
+export const toHexColor = (color?: string): string | undefined => {
+  if (!color) return undefined;
+  const parsed = tinycolor(color);
+  return parsed.isValid() ? parsed.toHexString() : undefined;
+};
+
+export const quoteImageFilename = (title: string) =>
+  `${makeSafeFilename(title.trim() || 'Quote')} - Quote.png`;
+
+/**
+ * Render a highlighted passage as a PNG card with the book's cover, author
+ * and title (`quote_image.rs`). Tauri apps only.
+ */
+export const renderQuoteImage = async (request: QuoteImageRequest): Promise<ArrayBuffer> =>
+  invoke<ArrayBuffer>('render_quote_image', {
+    request: {
+      ...request,
+      background: toHexColor(request.background),
+      foreground: toHexColor(request.foreground),
+      accent: toHexColor(request.accent),
+    },
+  });
@@ -0,0 +1,42 @@
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+import { StatisticsDb } from '@/services/statistics/statisticsDb';
+import { updateReadingIntents } from '@/utils/bridge';
+import { selectRecentBooks } from '@/utils/book';
+
+/** Local midnight as Unix seconds, the unit of the statistics' start_time. */
+export const startOfTodaySecs = (now = new Date()): number =>
+  Math.floor(new Date(now.getFullYear(), now.getMonth(), now.getDate()).getTime() / 1000);
+
+let lastPublished = '';
+
+/**
+ * Tell the iOS Shortcuts / Siri intents which book is current and how long
+ * the user has read today. "Open current book" and "Start read-aloud" open
+ * `readest://book/{hash}` (the latter with `?autoplay=tts`); the reading time
+ * answers "How many minutes did I read today?" without opening the app.
+ */
+export const refreshReadingIntents = async (
+  appService: AppService,
+  library: Book[],
+): Promise<void> => {
+  if (!appService.isIOSApp) return;
+  const [current] = selectRecentBooks(library, 1);
+  let readingSecondsToday = 0;
+  try {
+    const db = await StatisticsDb.open(appService);
+    readingSecondsToday = Math.round(await db.getReadingSecondsSince(startOfTodaySecs()));
+  } catch (err) {
+    console.warn('Failed to read reading time for intents', err);
+  }
+  const book = current ? { hash: current.hash, title: current.title ?? '' } : null;
+  const published = JSON.stringify([book, readingSecondsToday]);
+  if (published === lastPublished) return;
+  lastPublished = published;
+  try {
+    await updateReadingIntents({ book, readingSecondsToday });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update reading intents', err);
+  }
+};
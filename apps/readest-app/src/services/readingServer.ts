@@ -0,0 +1,73 @@
+import type { BookConfig } from '@/types/book';
+
+/** Emitted by the native reading server for each progress update it accepts. */
+export const READING_SERVER_PROGRESS_EVENT = 'reading-server://progress';
+
+export interface ReadingServerProgress {
+  hash: string;
+  location?: string | null;
+  progress?: [number, number] | null;
+  updatedAt: number;
+}
+
+/**
+ * The book config with progress from a remote reader applied, last writer
+ * wins on `updatedAt`. Returns null when the local config is at least as new.
+ * A new location drops the stored XPointer, which would still point at the
+ * old one.
+ */
+export const applyRemoteProgress = (
+  config: BookConfig,
+  remote: ReadingServerProgress,
+): BookConfig | null => {
+  if (remote.updatedAt <= (config.updatedAt ?? 0)) return null;
+  const updated: BookConfig = { ...config, updatedAt: remote.updatedAt };
+  if (remote.location) {
+    updated.location = remote.location;
+    delete updated.xpointer;
+  }
+  if (remote.progress) updated.progress = remote.progress;
+  return updated;
+};
+
+// The calls below run in a browser that loaded the app from the reading
+// server, so the API is on the page's own origin and the session cookie
+// authenticates them.
+
+/** A book file from the reading server, named so its extension gives the format. */
+export const fetchRemoteBook = async (hash: string): Promise<File> => {
+  const response = await fetch(`/api/books/${hash}/file`);
+  if (!response.ok) {
+    throw new Error(`Reading server returned ${response.status} for book ${hash}`);
+  }
+  const disposition = response.headers.get('content-disposition') ?? '';
+  const filename = /filename="([^"]+)"/.exec(disposition)?.[1] ?? hash;
+  const blob = await response.blob();
+  return new File([blob], filename, { type: blob.type || 'application/octet-stream' });
+};
+
+export const fetchRemoteProgress = async (hash: string): Promise<ReadingServerProgress | null> => {
+  const response = await fetch(`/api/books/${hash}/progress`);
+  if (!response.ok) return null;
+  const progress = (await response.json()) as Omit<ReadingServerProgress, 'hash'>;
+  return { ...progress, hash };
+};
+
+/**
+ * Send this browser's position back to the app. A `409` means the app has
+ * newer progress, which the next open picks up, so it isn't an error.
+ */
+export const pushRemoteProgress = async (hash: string, config: BookConfig) => {
+  const response = await fetch(`/api/books/${hash}/progress`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      location: config.location ?? null,
+      progress: config.progress ?? null,
+      updatedAt: Date.now(),
+    }),
+  });
+  if (!response.ok && response.status !== 409) {
+    throw new Error(`Reading server returned ${response.status} for book ${hash}`);
+  }
+};
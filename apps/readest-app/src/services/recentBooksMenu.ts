@@ -0,0 +1,61 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+import { selectRecentBooks } from '@/utils/book';
+
+/**
+ * Books in the macOS Dock menu (the one to continue and the recent ones) and
+ * in File ▸ Open Recent.
+ */
+export const RECENT_MENU_LIMIT = 10;
+
+export interface RecentMenuBook {
+  hash: string;
+  title: string;
+  /** Percent read. */
+  progress: number | null;
+}
+
+export interface RecentMenuLabels {
+  continueReading: string;
+  recentBooks: string;
+}
+
+const percentRead = (book: Book): number | null => {
+  if (book.readingStatus === 'finished') return 100;
+  const [current, total] = book.progress ?? [0, 0];
+  return total > 0 ? Math.floor((current / total) * 100) : null;
+};
+
+/** The recent books, reduced to what the menus show. */
+export const selectRecentMenuBooks = (
+  library: Book[],
+  limit = RECENT_MENU_LIMIT,
+): RecentMenuBook[] =>
+  selectRecentBooks(library, limit).map((book) => ({
+    hash: book.hash,
+    title: book.title ?? '',
+    progress: percentRead(book),
+  }));
+
+// Skip the native call when nothing the menu shows has changed; progress saves
+// touch the library on every page turn but the percentage moves far less often.
+let lastPublished = '';
+
+export const refreshRecentBooksMenus = async (
+  appService: AppService,
+  library: Book[],
+  labels: RecentMenuLabels,
+): Promise<void> => {
+  if (!appService.isMacOSApp) return;
+  const books = selectRecentMenuBooks(library);
+  const published = JSON.stringify({ books, labels });
+  if (published === lastPublished) return;
+  lastPublished = published;
+  try {
+    await invoke('update_recent_books_menus', { books, labels });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update recent books menus', err);
+  }
+};
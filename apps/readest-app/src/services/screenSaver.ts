@@ -0,0 +1,13 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export type ScreenSaverInhibitReason = 'reading' | 'readAloud';
+
+/**
+ * Keep the screen on for `reason`, or stop doing so, on Linux, where WebKitGTK
+ * has no Screen Wake Lock. The backend holds an org.freedesktop.ScreenSaver
+ * inhibit while any reason stands.
+ */
+export const setScreenSaverInhibited = (reason: ScreenSaverInhibitReason, inhibited: boolean) =>
+  invoke('set_screensaver_inhibited', { reason, inhibited }).catch((err) =>
+    console.warn('Failed to inhibit the screen saver', err),
+  );
@@ -2,6 +2,7 @@ import { FileSystem } from '@/types/system';
 import { ReadSettings, SystemSettings } from '@/types/settings';
 import { DEFAULT_HIGHLIGHT_COLORS, UserHighlightColor, ViewSettings } from '@/types/book';
 import { v4 as uuidv4 } from 'uuid';
+import { invoke } from '@tauri-apps/api/core';
 import {
   DEFAULT_BOOK_LAYOUT,
   DEFAULT_BOOK_STYLE,
@@ -27,6 +28,7 @@ import {
 import { DEFAULT_AI_SETTINGS } from './ai/constants';
 import { getTargetLang, isCJKEnv } from '@/utils/misc';
 import { safeLoadJSON, safeSaveJSON } from './persistence';
+import { isTauriAppPlatform } from './environment';
 
 export interface Context {
   fs: FileSystem;
@@ -124,6 +126,17 @@ export async function loadSettings(ctx: Context): Promise<SystemSettings> {
     defaultSettings,
   );
 
+  // Tokens and passwords live in the OS credential store, not in the file.
+  let secretsInFile = false;
+  if (isTauriAppPlatform()) {
+    const loaded = await invoke<{ settings: SystemSettings; needsSave: boolean }>(
+      'secure_load_settings',
+      { settings },
+    );
+    settings = loaded.settings;
+    secretsInFile = loaded.needsSave;
+  }
+
   const version = settings.version ?? 0;
   if (ctx.isAppDataSandbox || version < SYSTEM_SETTINGS_VERSION) {
     settings.version = SYSTEM_SETTINGS_VERSION;
@@ -166,11 +179,16 @@ export async function loadSettings(ctx: Context): Promise<SystemSettings> {
   if (!settings.replicaDeviceId) {
     settings.replicaDeviceId = uuidv4();
     await saveSettings(ctx.fs, settings);
+  } else if (secretsInFile) {
+    await saveSettings(ctx.fs, settings);
   }
 
   return settings;
 }
 
 export async function saveSettings(fs: FileSystem, settings: SystemSettings): Promise<void> {
-  await safeSaveJSON(fs, SETTINGS_FILENAME, 'Settings', settings);
+  const stored = isTauriAppPlatform()
+    ? await invoke<SystemSettings>('secure_save_settings', { settings })
+    : settings;
+  await safeSaveJSON(fs, SETTINGS_FILENAME, 'Settings', stored);
 }
@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+
+export interface SpotlightBook {
+  hash: string;
+  title: string;
+  author: string;
+}
+
+/** Books searchable from Spotlight: the whole library, minus deleted books. */
+export const selectSpotlightBooks = (library: Book[]): SpotlightBook[] =>
+  library
+    .filter((book) => !book.deletedAt)
+    .map((book) => ({ hash: book.hash, title: book.title ?? '', author: book.author ?? '' }));
+
+// The index is replaced as a whole on every update, so skip the native call
+// unless a title, author or the set of books changed (progress saves touch
+// the library constantly while reading).
+let lastPublished = '';
+
+export const refreshSpotlightIndex = async (
+  appService: AppService,
+  library: Book[],
+): Promise<void> => {
+  if (!appService.isMacOSApp) return;
+  const books = selectSpotlightBooks(library);
+  const published = JSON.stringify(books);
+  if (published === lastPublished) return;
+  lastPublished = published;
+  try {
+    const booksDir = await appService.resolveFilePath('', 'Books');
+    await invoke('update_spotlight_index', { books, booksDir });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update Spotlight index', err);
+  }
+};
@@ -166,6 +166,15 @@ export class StatisticsDb {
       : ((pageDurations[mid - 1] ?? 0) + (pageDurations[mid] ?? 0)) / 2;
   }
 
+  /** Seconds read across all books in events starting at or after `since` (Unix seconds). */
+  async getReadingSecondsSince(since: number): Promise<number> {
+    const rows = await this.db.select<{ total: number | null }>(
+      `SELECT SUM(duration) AS total FROM page_stat_data WHERE start_time >= ?`,
+      [since],
+    );
+    return rows[0]?.total ?? 0;
+  }
+
   async getBookByMd5(md5: string): Promise<BookRow | null> {
     const rows = await this.db.select<BookRow>(`SELECT * FROM book WHERE md5 = ? LIMIT 1`, [md5]);
     return rows[0] ?? null;
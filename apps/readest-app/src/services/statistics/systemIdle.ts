@@ -0,0 +1,14 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * Seconds since the last keyboard or mouse input anywhere on the computer,
+ * or null where the desktop can't tell. Desktop apps only.
+ */
+export const getSystemIdleSeconds = async (): Promise<number | null> => {
+  try {
+    return await invoke<number>('get_system_idle_seconds');
+  } catch (err) {
+    console.warn('[stats] failed to read the system idle time:', err);
+    return null;
+  }
+};
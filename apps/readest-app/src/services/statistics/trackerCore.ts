@@ -36,8 +36,9 @@ export class TrackerCore {
     return flushed;
   }
 
-  onIdle(now: number): FlushedEvent[] {
-    return this.flush(now); // flush + pause (pending cleared)
+  /** `lastActive` is when the system last saw input, where known; the event ends there. */
+  onIdle(now: number, lastActive = now): FlushedEvent[] {
+    return this.flush(Math.min(lastActive, now)); // flush + pause (pending cleared)
   }
 
   onHide(now: number): FlushedEvent[] {
@@ -58,3 +59,24 @@ export class TrackerCore {
     return [{ page: p.page, startTime: p.startTime, duration, totalPages: p.totalPages }];
   }
 }
+
+export type IdleTimerDecision =
+  | { kind: 'rearm'; inSeconds: number }
+  | { kind: 'idle'; idleSeconds: number };
+
+/**
+ * What the idle timer does when it fires, given the seconds since the system
+ * last saw input (null where the OS can't tell): wait out the rest of the
+ * timeout if there was input since, e.g. scrolling a long page, otherwise go
+ * idle as of the last input so the time away isn't counted.
+ */
+export const resolveIdleTimer = (
+  timeoutSeconds: number,
+  systemIdleSeconds: number | null,
+): IdleTimerDecision => {
+  if (systemIdleSeconds === null) return { kind: 'idle', idleSeconds: 0 };
+  if (systemIdleSeconds < timeoutSeconds) {
+    return { kind: 'rearm', inSeconds: timeoutSeconds - systemIdleSeconds };
+  }
+  return { kind: 'idle', idleSeconds: systemIdleSeconds };
+};
@@ -0,0 +1,29 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { AppService } from '@/types/system';
+
+/**
+ * Show progress of work the frontend drives on the Windows taskbar button.
+ * The backend averages it with its own conversions and sync uploads, and
+ * clears the bar (or flashes it red on failure) once everything has ended.
+ */
+export const reportTaskbarProgress = (
+  appService: AppService | null,
+  task: string,
+  progress: number,
+) => {
+  if (!appService?.isWindowsApp) return;
+  invoke('update_taskbar_progress', { task, progress }).catch((err) =>
+    console.warn('Failed to update taskbar progress', err),
+  );
+};
+
+export const finishTaskbarProgress = (
+  appService: AppService | null,
+  task: string,
+  failed = false,
+) => {
+  if (!appService?.isWindowsApp) return;
+  invoke('finish_taskbar_progress', { task, failed }).catch((err) =>
+    console.warn('Failed to finish taskbar progress', err),
+  );
+};
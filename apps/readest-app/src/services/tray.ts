@@ -0,0 +1,56 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Book } from '@/types/book';
+import type { AppService } from '@/types/system';
+import { selectRecentMenuBooks } from './recentBooksMenu';
+
+/** Items of the desktop tray menu, translated by the webview. */
+export interface TrayLabels {
+  continueReading: string;
+  recentBooks: string;
+  syncNow: string;
+  pauseReadAloud: string;
+  show: string;
+  quit: string;
+}
+
+/** What a tray menu item asks the webview to do. */
+export interface TrayAction {
+  action: 'openBook' | 'syncNow' | 'pauseReadAloud';
+  bookHash?: string;
+}
+
+// Skip the native call when nothing the menu shows has changed, as for the
+// recent books menus.
+let lastPublished = '';
+
+export const refreshTrayMenu = async (
+  appService: AppService,
+  library: Book[],
+  labels: TrayLabels,
+): Promise<void> => {
+  if (!appService.isDesktopApp) return;
+  const books = selectRecentMenuBooks(library);
+  const published = JSON.stringify({ books, labels });
+  if (published === lastPublished) return;
+  lastPublished = published;
+  try {
+    await invoke('update_tray_menu', { books, labels });
+  } catch (err) {
+    lastPublished = '';
+    console.warn('Failed to update tray menu', err);
+  }
+};
+
+export const setTrayReadAloud = (appService: AppService | null, active: boolean) => {
+  if (!appService?.isDesktopApp) return;
+  invoke('set_tray_read_aloud', { active }).catch((err) =>
+    console.warn('Failed to update tray read-aloud state', err),
+  );
+};
+
+export const setMinimizeToTray = (appService: AppService | null, enabled: boolean) => {
+  if (!appService?.isDesktopApp) return;
+  invoke('set_minimize_to_tray', { enabled }).catch((err) =>
+    console.warn('Failed to set minimize to tray', err),
+  );
+};
@@ -5,7 +5,7 @@ import { isSameLang } from '@/utils/lang';
 import { parseSSMLMarks } from '@/utils/ssml';
 import { stubTranslation as _ } from '@/utils/misc';
 import { TTSCapabilities, TTSClient, TTSMessageEvent } from './TTSClient';
-import { TTSGranularity, TTSMark, TTSVoice, TTSVoicesGroup } from './types';
+import { TTSGranularity, TTSMark, TTSPronunciation, TTSVoice, TTSVoicesGroup } from './types';
 import { TTSUtils } from './TTSUtils';
 import { TTSController } from './TTSController';
 
@@ -13,6 +13,25 @@ type TTSEventPayload = {
   utteranceId: string;
 } & TTSMessageEvent;
 
+export type AudioOutput = {
+  id: string;
+  name: string;
+  kind: 'speaker' | 'wired' | 'bluetooth' | 'usb' | 'hdmi' | 'air-play' | 'other';
+  selected: boolean;
+};
+
+// An installed speech engine on Android, e.g. Google's or Samsung's.
+export type SystemEngine = {
+  name: string;
+  label: string;
+  selected: boolean;
+};
+
+type AudioRouteChangedPayload = {
+  outputs: AudioOutput[];
+  lost: boolean;
+};
+
 const TTSEngines = {
   default: 'System TTS',
   msctts: 'Msc TTS',
@@ -36,6 +55,13 @@ const TTSEngines = {
   aispeech: 'Aispeech',
 } as Record<string, string>;
 
+// Marks handed to the plugin ahead of time, so engines that render in
+// process (Piper) have the next one ready when the current one ends.
+const LOOKAHEAD_MARKS = 2;
+// Upcoming SSML kept from the controller's preloads, for lookahead across
+// paragraph boundaries.
+const MAX_PRELOADED_SSML = 4;
+
 export class NativeTTSClient implements TTSClient {
   name = 'native-tts';
   initialized = false;
@@ -47,8 +73,12 @@ export class NativeTTSClient implements TTSClient {
   #currentVoiceId = '';
   #rate = 1.0;
   #pitch = 1.0;
+  #preloaded: string[] = [];
+  // Book hash sent with each utterance so its pronunciations apply.
+  #book: string | undefined;
 
   #eventListener: PluginListener | null = null;
+  #routeListener: PluginListener | null = null;
   #activeUtterances = new Map<
     string,
     {
@@ -69,12 +99,12 @@ export class NativeTTSClient implements TTSClient {
         'native-tts',
         'tts_events',
         (event) => {
-          const { utteranceId, code, message, mark } = event;
+          const { utteranceId, code, message, mark, charIndex, charLength } = event;
 
           const utteranceData = this.#activeUtterances.get(utteranceId);
           if (!utteranceData) return;
 
-          const ttsEvent: TTSMessageEvent = { code, message, mark };
+          const ttsEvent: TTSMessageEvent = { code, message, mark, charIndex, charLength };
           utteranceData.eventQueue.push(ttsEvent);
           if (code === 'end' || code === 'error') {
             utteranceData.finished = true;
@@ -90,6 +120,22 @@ export class NativeTTSClient implements TTSClient {
     } catch (error) {
       console.error('Failed to setup TTS event listener:', error);
     }
+    try {
+      if (this.#routeListener) return;
+      this.#routeListener = await addPluginListener<AudioRouteChangedPayload>(
+        'native-tts',
+        'audio-route-changed',
+        ({ lost }) => {
+          // The output went away (e.g. headphones unplugged): pause rather
+          // than carry on from the speaker.
+          if (lost && this.controller?.state === 'playing') {
+            this.controller.pause();
+          }
+        },
+      );
+    } catch (error) {
+      console.error('Failed to setup audio route listener:', error);
+    }
   }
 
   async init(): Promise<boolean> {
@@ -101,7 +147,12 @@ export class NativeTTSClient implements TTSClient {
     return this.initialized;
   }
 
-  async *speakMark(mark: TTSMark, preload: boolean, signal: AbortSignal) {
+  async *speakMark(
+    mark: TTSMark,
+    preload: boolean,
+    signal: AbortSignal,
+    upcoming: TTSMark[] = [],
+  ) {
     if (preload) {
       yield { code: 'end', message: 'Dummy preload finished' } as TTSMessageEvent;
       return;
@@ -113,8 +164,9 @@ export class NativeTTSClient implements TTSClient {
     await this.setVoice(voiceId);
     try {
       const result = await invoke<{ utteranceId: string }>('plugin:native-tts|speak', {
-        payload: { text: mark.text, preload },
+        payload: { text: mark.text, preload, lang: voiceLang, book: this.#book },
       });
+      this.preloadMarks(upcoming);
 
       const utteranceId = result.utteranceId;
       this.#activeUtterances.set(utteranceId, {
@@ -178,12 +230,36 @@ export class NativeTTSClient implements TTSClient {
     }
   }
 
+  // Sent one after another so the plugin queues them in reading order.
+  private async preloadMarks(marks: TTSMark[]) {
+    for (const mark of marks) {
+      try {
+        await invoke('plugin:native-tts|speak', {
+          payload: { text: mark.text, preload: true, lang: mark.language, book: this.#book },
+        });
+      } catch (error) {
+        console.warn('Failed to preload TTS mark:', error);
+        return;
+      }
+    }
+  }
+
+  private marksAfter(marks: TTSMark[], index: number) {
+    const following = this.#preloaded.flatMap(
+      (ssml) => parseSSMLMarks(ssml, this.#primaryLang).marks,
+    );
+    return [...marks.slice(index + 1), ...following].slice(0, LOOKAHEAD_MARKS);
+  }
+
   async *speak(ssml: string, signal: AbortSignal, preload: boolean = false) {
     const { marks } = parseSSMLMarks(ssml, this.#primaryLang);
+    const others = this.#preloaded.filter((s) => s !== ssml);
+    this.#preloaded = preload ? [...others, ssml].slice(-MAX_PRELOADED_SSML) : others;
 
-    for (const mark of marks) {
+    for (const [index, mark] of marks.entries()) {
       if (!preload) this.controller?.dispatchSpeakMark(mark);
-      for await (const ev of this.speakMark(mark, preload, signal)) {
+      const upcoming = preload ? [] : this.marksAfter(marks, index);
+      for await (const ev of this.speakMark(mark, preload, signal, upcoming)) {
         if (signal.aborted) {
           yield { code: 'error', message: 'Aborted' } as TTSMessageEvent;
           return;
@@ -244,6 +320,39 @@ export class NativeTTSClient implements TTSClient {
     await invoke('plugin:native-tts|set_voice', { payload: { voice } });
   }
 
+  async setPronunciations(entries: TTSPronunciation[], book?: string) {
+    if (book) this.#book = book;
+    await invoke('plugin:native-tts|set_pronunciations', { payload: { entries, book } });
+  }
+
+  // Engines the system voices can come from; `selectable` is false where
+  // there is only one (iOS, desktop).
+  async getSystemEngines() {
+    return invoke<{ engines: SystemEngine[]; selectable: boolean }>(
+      'plugin:native-tts|list_system_engines',
+    );
+  }
+
+  // Restarts the engine, so the voices listed so far no longer apply; null
+  // follows the system default.
+  async setSystemEngine(name: string | null) {
+    await invoke('plugin:native-tts|set_system_engine', { payload: { name } });
+    this.#voices = [];
+  }
+
+  // Outputs to play through; `selectable` is false where the system picks
+  // the output itself (Android, iOS).
+  async getAudioOutputs() {
+    return invoke<{ outputs: AudioOutput[]; selectable: boolean }>(
+      'plugin:native-tts|list_audio_outputs',
+    );
+  }
+
+  // Takes effect from the next utterance; null follows the system default.
+  async setAudioOutput(id: string | null) {
+    await invoke('plugin:native-tts|set_audio_output', { payload: { id } });
+  }
+
   async getAllVoices() {
     if (this.#voices.length > 0) {
       return this.#voices;
@@ -329,6 +438,10 @@ export class NativeTTSClient implements TTSClient {
       this.#eventListener.unregister();
       this.#eventListener = null;
     }
+    if (this.#routeListener) {
+      this.#routeListener.unregister();
+      this.#routeListener = null;
+    }
     await this.stop();
   }
 }
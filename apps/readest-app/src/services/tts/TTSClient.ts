@@ -1,4 +1,4 @@
-import { TTSGranularity, TTSVoice, TTSVoicesGroup } from './types';
+import { TTSGranularity, TTSPronunciation, TTSVoice, TTSVoicesGroup } from './types';
 
 type TTSMessageCode = 'boundary' | 'error' | 'end';
 
@@ -6,6 +6,10 @@ export interface TTSMessageEvent {
   code: TTSMessageCode;
   message?: string;
   mark?: string;
+  // For 'word' and 'sentence' boundaries from native TTS: the range being
+  // spoken, in UTF-16 units of the utterance text.
+  charIndex?: number;
+  charLength?: number;
 }
 
 // What the active engine can actually do, so the controller and UI degrade
@@ -46,6 +50,9 @@ export interface TTSClient {
   // Cached per-ordinal audio durations (seconds) for a section under the
   // current voice; empty when the client has no persistent cache.
   getSectionDurations?(section: number): Promise<Map<number, number>>;
+  // Replaces the pronunciation dictionary of the library, or of `book` (a
+  // book hash) when given; only engines that apply one implement it.
+  setPronunciations?(entries: TTSPronunciation[], book?: string): Promise<void>;
   getVoiceId(): string;
   getSpeakingLang(): string;
   // Playback position within the currently audible sentence, in trimmed media
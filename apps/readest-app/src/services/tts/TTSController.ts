@@ -7,6 +7,7 @@ import {
   TTSHighlightGranularity,
   TTSHighlightOptions,
   TTSMark,
+  TTSPronunciation,
   TTSVoice,
 } from './types';
 import { createRejectFilter } from '@/utils/node';
@@ -1129,6 +1130,36 @@ export class TTSController extends EventTarget {
     if (isPlaying && !byMark) this.preloadNextSSML();
   }
 
+  // goto the start of the next section
+  async forwardSection() {
+    await this.initViewTTS();
+    const isPlaying = this.state === 'playing';
+    await this.stop();
+    if (!isPlaying) this.state = 'forward-paused';
+    await this.#handleNavigationWithoutSSML(() => this.#initTTSForNextSection(), isPlaying);
+    if (isPlaying) this.preloadNextSSML();
+  }
+
+  // goto the start of the previous section; the first one restarts
+  async backwardSection() {
+    await this.initViewTTS();
+    const isPlaying = this.state === 'playing';
+    await this.stop();
+    if (!isPlaying) this.state = 'backward-paused';
+    const index = Math.max(this.#ttsSectionIndex, 0);
+    await this.#handleNavigationWithoutSSML(
+      () => (index > 0 ? this.#initTTSForPrevSection() : this.#initTTSForSection(index)),
+      isPlaying,
+    );
+  }
+
+  // The section being read and how many the book has, for chapter skip.
+  getSectionProgress(): { index: number; count: number } | null {
+    const count = this.view.book.sections?.length ?? 0;
+    if (this.#ttsSectionIndex < 0 || count === 0) return null;
+    return { index: this.#ttsSectionIndex, count };
+  }
+
   async setLang(lang: string) {
     this.ttsLang = lang;
     this.setPrimaryLang(lang);
@@ -1147,6 +1178,20 @@ export class TTSController extends EventTarget {
     await this.ttsClient.setRate(this.ttsRate);
   }
 
+  // Only the native client reads the dictionary; the others speak the text
+  // as the book has it.
+  async setPronunciations(library: TTSPronunciation[], book: TTSPronunciation[]) {
+    const client = this.ttsNativeClient;
+    const bookHash = this.bookKey?.split('-')[0];
+    if (!client?.setPronunciations || !bookHash) return;
+    try {
+      await client.setPronunciations(library);
+      await client.setPronunciations(book, bookHash);
+    } catch (error) {
+      console.warn('Failed to set TTS pronunciations:', error);
+    }
+  }
+
   async getVoices(lang: string) {
     const ttsWebVoices = await this.ttsWebClient.getVoices(lang);
     const ttsEdgeVoices = await this.ttsEdgeClient.getVoices(lang);
@@ -9,11 +9,17 @@
 // keeps its current per-window behavior).
 
 import env from '@/services/environment';
-import { stubTranslation as _ } from '@/utils/misc';
+import { getOSPlatform, stubTranslation as _ } from '@/utils/misc';
 import { useBookDataStore } from '@/store/bookDataStore';
 import { useSettingsStore } from '@/store/settingsStore';
 import { eventDispatcher } from '@/utils/event';
 import { releaseUnblockAudio, ttsMediaBridge, TTSMediaBridgeMeta } from './ttsMediaBridge';
+import {
+  listenNativeSleepTimer,
+  setNativeSleepTimer,
+  SLEEP_AT_CHAPTER_END,
+  type NativeSleepTimerState,
+} from './sleepTimer';
 import type { TTSController } from './TTSController';
 
 export type TTSSessionMeta = TTSMediaBridgeMeta;
@@ -39,6 +45,12 @@ export const getBookHashFromKey = (bookKey: string): string => bookKey.split('-'
 // flushes the final position regardless.
 const PERSIST_THROTTLE_MS = 10_000;
 
+// While the native timer runs, the JS one only backs it up: it fires this
+// much later, so the native fade plays out and a shake can still extend it.
+const NATIVE_SLEEP_GRACE_MS = 5_000;
+// What a shake adds to the sleep timer on mobile.
+const SLEEP_SHAKE_EXTEND_SEC = 5 * 60;
+
 export class TTSSessionManager extends EventTarget {
   #session: TTSSession | null = null;
   #meta: TTSSessionMeta | null = null;
@@ -47,8 +59,12 @@ export class TTSSessionManager extends EventTarget {
   #onHighlightMark: ((e: Event) => void) | null = null;
   #lastRelayedState: 'playing' | 'paused' | null = null;
   #sleepTimer: ReturnType<typeof setTimeout> | null = null;
+  #sleepArmed = false;
   #sleepTimeoutSec = 0;
   #sleepFiresAt = 0;
+  // Section being read when armed to stop at the end of the chapter.
+  #sleepSection: number | null = null;
+  #nativeSleepListening = false;
   #lastPersistAt = 0;
   #pendingLocation: string | null = null;
   #stopping = false;
@@ -171,32 +187,99 @@ export class TTSSessionManager extends EventTarget {
 
   // Sleep timer lives here so a timer armed in the reader survives unmount
   // and can actually stop a background session (a hook-local timer would
-  // fire into a dead closure and orphan the audio).
+  // fire into a dead closure and orphan the audio). In the app the plugin
+  // keeps time too, natively, and fades out before it stops; the timer here
+  // backs it up and is the only one on the web. SLEEP_AT_CHAPTER_END stops
+  // when the controller moves on to another section.
   setSleepTimer(seconds: number): void {
     this.#clearSleepTimer();
-    if (seconds > 0) {
-      this.#sleepTimeoutSec = seconds;
+    if (seconds === 0) {
+      void setNativeSleepTimer({});
+      return;
+    }
+    this.#sleepArmed = true;
+    this.#sleepTimeoutSec = seconds;
+    const endOfChapter = seconds === SLEEP_AT_CHAPTER_END;
+    if (endOfChapter) {
+      this.#sleepSection = this.#session?.controller.getSectionProgress()?.index ?? null;
+    } else {
       this.#sleepFiresAt = Date.now() + seconds * 1000;
-      this.#sleepTimer = setTimeout(() => {
-        this.#sleepTimer = null;
-        void this.stopActive('timeout');
-      }, seconds * 1000);
+      this.#scheduleSleepTimer(seconds * 1000);
     }
+    const platform = getOSPlatform();
+    const shakeToExtend =
+      platform === 'android' || platform === 'ios' ? SLEEP_SHAKE_EXTEND_SEC : undefined;
+    void this.#armNativeSleepTimer({
+      seconds: endOfChapter ? undefined : seconds,
+      endOfChapter,
+      shakeToExtend,
+    });
   }
 
   getSleepTimer(): { timeoutSec: number; firesAt: number } | null {
-    return this.#sleepTimer
+    return this.#sleepArmed
       ? { timeoutSec: this.#sleepTimeoutSec, firesAt: this.#sleepFiresAt }
       : null;
   }
 
+  #scheduleSleepTimer(delayMs: number): void {
+    if (this.#sleepTimer) clearTimeout(this.#sleepTimer);
+    this.#sleepTimer = setTimeout(() => {
+      this.#sleepTimer = null;
+      void this.stopActive('timeout');
+    }, delayMs);
+  }
+
+  async #armNativeSleepTimer(options: Parameters<typeof setNativeSleepTimer>[0]): Promise<void> {
+    if (!this.#nativeSleepListening) {
+      this.#nativeSleepListening = true;
+      await listenNativeSleepTimer({
+        onFired: () => {
+          if (this.#sleepArmed) void this.stopActive('timeout');
+        },
+        onChanged: (state) => this.#onNativeSleepTimerChanged(state),
+      }).catch((err) => console.warn('Failed to listen to the native sleep timer:', err));
+    }
+    const timeoutSec = this.#sleepTimeoutSec;
+    const state = await setNativeSleepTimer(options);
+    // Superseded while the plugin answered.
+    if (!state || !this.#sleepArmed || this.#sleepTimeoutSec !== timeoutSec) return;
+    if (state.remainingMs != null) {
+      this.#scheduleSleepTimer(state.remainingMs + NATIVE_SLEEP_GRACE_MS);
+    }
+  }
+
+  // A shake extended the native timer: follow it.
+  #onNativeSleepTimerChanged(state: NativeSleepTimerState): void {
+    if (!this.#sleepArmed || state.remainingMs == null) return;
+    this.#sleepFiresAt = Date.now() + state.remainingMs;
+    this.#scheduleSleepTimer(state.remainingMs + NATIVE_SLEEP_GRACE_MS);
+    eventDispatcher.dispatch('tts-sleep-timer-changed', { firesAt: this.#sleepFiresAt });
+  }
+
+  #checkSleepAtChapterEnd(controller: TTSController): void {
+    if (!this.#sleepArmed || this.#sleepTimeoutSec !== SLEEP_AT_CHAPTER_END) return;
+    const index = controller.getSectionProgress()?.index;
+    if (index === undefined) return;
+    if (this.#sleepSection === null) {
+      this.#sleepSection = index;
+    } else if (index !== this.#sleepSection) {
+      void this.stopActive('timeout');
+    }
+  }
+
   #clearSleepTimer(): void {
     if (this.#sleepTimer) {
       clearTimeout(this.#sleepTimer);
       this.#sleepTimer = null;
     }
+    if (this.#sleepArmed) {
+      void setNativeSleepTimer({});
+    }
+    this.#sleepArmed = false;
     this.#sleepTimeoutSec = 0;
     this.#sleepFiresAt = 0;
+    this.#sleepSection = null;
   }
 
   #subscribe(controller: TTSController): void {
@@ -204,6 +287,9 @@ export class TTSSessionManager extends EventTarget {
       const { state } = (e as CustomEvent<{ state: string }>).detail;
       const session = this.#session;
       if (!session || session.controller !== controller) return;
+      // A new chapter starts playing once its first paragraph is spoken.
+      if (state === 'playing') this.#checkSleepAtChapterEnd(controller);
+      if (this.#session !== session) return;
       // 'stopped' is a TRANSIT value (every paragraph advance, chapter
       // transitions) — relaying it would flicker every follower and the
       // now-playing bar. Terminal stops arrive via tts-session-ended.
@@ -0,0 +1,56 @@
+import { addPluginListener, invoke, type PluginListener } from '@tauri-apps/api/core';
+import { isTauriAppPlatform } from '@/services/environment';
+
+// Sleep timer value (in place of seconds) that stops at the end of the
+// chapter being read.
+export const SLEEP_AT_CHAPTER_END = -1;
+
+export interface NativeSleepTimerOptions {
+  seconds?: number;
+  endOfChapter?: boolean;
+  // Seconds a shake of the device adds in the timer's last minute (mobile).
+  shakeToExtend?: number;
+}
+
+export interface NativeSleepTimerState {
+  active: boolean;
+  remainingMs?: number | null;
+  endOfChapter: boolean;
+}
+
+// Arm the plugin's sleep timer, which keeps time natively (with the screen
+// off) and fades the speech it plays before stopping it; with neither
+// seconds nor endOfChapter it is turned off. Resolves null off Tauri or
+// when the plugin fails, leaving the caller's own timer in charge.
+export async function setNativeSleepTimer(
+  options: NativeSleepTimerOptions,
+): Promise<NativeSleepTimerState | null> {
+  if (!isTauriAppPlatform()) return null;
+  try {
+    return await invoke<NativeSleepTimerState>('plugin:native-tts|set_sleep_timer', {
+      payload: {
+        seconds: options.seconds,
+        endOfChapter: options.endOfChapter ?? false,
+        shakeToExtend: options.shakeToExtend,
+      },
+    });
+  } catch (error) {
+    console.warn('Failed to set the native sleep timer:', error);
+    return null;
+  }
+}
+
+// 'sleep-timer-fired' comes once the plugin has stopped speech;
+// 'sleep-timer-changed' when a shake extended the timer.
+export async function listenNativeSleepTimer(handlers: {
+  onFired: () => void;
+  onChanged: (state: NativeSleepTimerState) => void;
+}): Promise<PluginListener[]> {
+  if (!isTauriAppPlatform()) return [];
+  return Promise.all([
+    addPluginListener('native-tts', 'sleep-timer-fired', () => handlers.onFired()),
+    addPluginListener('native-tts', 'sleep-timer-changed', (state: NativeSleepTimerState) =>
+      handlers.onChanged(state),
+    ),
+  ]);
+}
@@ -13,7 +13,7 @@
 
 import { buildTTSMediaMetadata } from '@/utils/ttsMetadata';
 import { fetchImageAsBase64 } from '@/utils/image';
-import { getMediaSession, TauriMediaSession } from '@/libs/mediaSession';
+import { getMediaSession, TauriMediaSession, type AudioInterruption } from '@/libs/mediaSession';
 import { isTauriAppPlatform } from '@/services/environment';
 import { getOSPlatform } from '@/utils/misc';
 import { notifyCarPlayState } from './carPlaySession';
@@ -112,6 +112,13 @@ export class TTSMediaBridge {
   // segment's mark lands (or a safety timeout fires).
   #skipping = false;
   #skipTimer: ReturnType<typeof setTimeout> | null = null;
+  // Between an audio interruption's began and ended (a call, a nav prompt).
+  // The plugin's 'pause' only lands when the controller is 'playing'; one
+  // arriving mid paragraph-advance would be lost and the next paragraph
+  // would start speaking over the call, so it is re-applied on the next
+  // 'playing' transition instead. Cleared once paused, so a deliberate play
+  // from the app still goes through.
+  #interrupted = false;
 
   constructor(resolveMediaSession: () => BridgeMediaSession | null = getMediaSession) {
     this.#resolveMediaSession = resolveMediaSession;
@@ -186,6 +193,12 @@ export class TTSMediaBridge {
       void this.#updatePositionState();
     };
     this.#onStateChange = () => {
+      const state = this.#controller?.state;
+      if (this.#interrupted && state === 'playing') {
+        void this.#controller?.pause();
+        return;
+      }
+      if (state?.includes('paused')) this.#interrupted = false;
       void this.#updatePlaybackState();
       // Pause/resume must also refresh the timeline. The scrubber's playbackRate
       // and frozen position only change on state transitions, not on marks — the
@@ -227,6 +240,9 @@ export class TTSMediaBridge {
         'nexttrack',
         'previoustrack',
         'seekto',
+        'nextchapter',
+        'previouschapter',
+        'interruption',
       ]) {
         try {
           mediaSession.setActionHandler(action as MediaSessionAction, null);
@@ -239,6 +255,7 @@ export class TTSMediaBridge {
       }
     }
     this.#endSkip();
+    this.#interrupted = false;
     this.#controller = null;
     this.#meta = null;
     this.#mediaSession = null;
@@ -301,6 +318,22 @@ export class TTSMediaBridge {
       mediaSession.setActionHandler('seekto', ((positionMs: number) => {
         void controller()?.seekToTime(positionMs / 1000);
       }) as (position: number) => void);
+      // Custom actions: the Android notification's chapter buttons and a
+      // press-and-hold on the iOS track buttons.
+      mediaSession.setActionHandler('nextchapter', () => {
+        this.#beginSkip();
+        void controller()?.forwardSection();
+      });
+      mediaSession.setActionHandler('previouschapter', () => {
+        this.#beginSkip();
+        void controller()?.backwardSection();
+      });
+      // A ducked interruption keeps speaking; a permanent one never ends, so
+      // its 'pause' is left as an ordinary one.
+      mediaSession.setActionHandler('interruption', ((interruption: AudioInterruption) => {
+        this.#interrupted =
+          interruption.phase === 'began' && !interruption.ducked && !interruption.permanent;
+      }) as (interruption: AudioInterruption) => void);
     } else {
       try {
         mediaSession.setActionHandler('seekto', (details: MediaSessionActionDetails) => {
@@ -378,10 +411,15 @@ export class TTSMediaBridge {
     if (!info || !Number.isFinite(info.duration) || info.duration <= 0) return;
     const position = Math.min(Math.max(info.position, 0), info.duration);
     if (mediaSession instanceof TauriMediaSession) {
+      const chapter = ctrl.getSectionProgress();
       await mediaSession.updatePlaybackState({
         playing: ctrl.state === 'playing',
         position: Math.round(position * 1000),
         duration: Math.round(info.duration * 1000),
+        seekable: true,
+        chapterIndex: chapter?.index,
+        chapterCount: chapter?.count,
+        chapterTitle: this.#lastSectionLabel || undefined,
       });
     } else if ('setPositionState' in mediaSession) {
       try {
@@ -13,6 +13,17 @@ export type TTSHighlightOptions = {
   color: string;
 };
 
+// An entry of the native TTS pronunciation dictionary: `pattern` (a word or
+// phrase, or a regular expression) is said as `replacement`, which is IPA
+// when `ipa` is set.
+export type TTSPronunciation = {
+  pattern: string;
+  replacement: string;
+  isRegex?: boolean;
+  caseSensitive?: boolean;
+  ipa?: boolean;
+};
+
 export type TTSVoice = {
   id: string;
   name: string;
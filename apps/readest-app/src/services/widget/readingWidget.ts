@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/core';
 import type { Book } from '@/types/book';
 import type { AppService } from '@/types/system';
 import { useLibraryStore } from '@/store/libraryStore';
@@ -80,3 +81,26 @@ export const refreshReadingWidget = async (
     console.warn('Failed to update reading widget', err);
   }
 };
+
+/**
+ * Report the most recently read book to the Android "Continue reading" widget.
+ * The Rust side only forwards changes, so this is cheap to call whenever the
+ * library changes.
+ */
+export const refreshContinueReading = async (
+  appService: AppService,
+  emptyTitle: string,
+): Promise<void> => {
+  if (!appService.isAndroidApp) return;
+  const library = useLibraryStore.getState().library;
+  const selected = selectReadingWidgetBooks(library, 1);
+  const { books } = await buildReadingWidgetPayload(selected, appService, {
+    sectionTitle: '',
+    emptyTitle,
+  });
+  try {
+    await invoke('update_continue_reading', { book: books[0] ?? null, emptyTitle });
+  } catch (err) {
+    console.warn('Failed to update continue reading widget', err);
+  }
+};
@@ -1,6 +1,14 @@
 import { create } from 'zustand';
-import { interceptKeys, getScreenBrightness, setScreenBrightness } from '@/utils/bridge';
+import {
+  interceptKeys,
+  getScreenBrightness,
+  setScreenBrightness,
+  setKeyMapping,
+  listenToMappedKeys,
+  KeyMapping,
+} from '@/utils/bridge';
 import { eventDispatcher } from '@/utils/event';
+import { volumeKeyMappings } from '@/utils/keybinding';
 import { NativeTouchEventType } from '@/types/system';
 
 declare global {
@@ -20,11 +28,25 @@ const handleNativeKeyDown = (keyName: string, keyCode?: number) => {
   return eventDispatcher.dispatch('native-key-down', { keyName, keyCode });
 };
 
+// Mapped volume keys arrive as plugin events rather than through
+// window.onNativeKeyDown; one listener serves every acquisition.
+let mappedKeyListener: Promise<unknown> | null = null;
+const listenToVolumeKeyActions = () => {
+  mappedKeyListener ??= listenToMappedKeys((event) => {
+    eventDispatcher.dispatch('native-key-action', event);
+  }).catch((error) => {
+    console.warn('Failed to listen to mapped keys', error);
+    mappedKeyListener = null;
+  });
+};
+
 type DeviceControlState = {
   volumeKeysIntercepted: boolean;
   backKeyIntercepted: boolean;
   volumeKeysInterceptionCount: number;
   backKeyInterceptionCount: number;
+  volumeKeyMappings: KeyMapping[];
+  setVolumeKeyMappings: (mappings: KeyMapping[]) => void;
   getScreenBrightness: () => Promise<number>; // 0.0 to 1.0
   setScreenBrightness: (brightness: number) => Promise<void>; // brightness: 0.0 to 1.0
   acquireVolumeKeyInterception: () => void;
@@ -35,6 +57,7 @@ type DeviceControlState = {
   pageTurnerKeysInterceptionCount: number;
   acquirePageTurnerKeyInterception: () => void;
   releasePageTurnerKeyInterception: () => void;
+  setPageTurnerBindings: (keys: string[]) => void;
   setKeyLearnMode: (enabled: boolean) => void;
   listenToNativeTouchEvents: () => void;
 };
@@ -44,14 +67,27 @@ export const useDeviceControlStore = create<DeviceControlState>((set, get) => ({
   backKeyIntercepted: false,
   volumeKeysInterceptionCount: 0,
   backKeyInterceptionCount: 0,
+  volumeKeyMappings: volumeKeyMappings(),
   pageTurnerKeysIntercepted: false,
   pageTurnerKeysInterceptionCount: 0,
 
+  // Takes effect at once while the volume keys are intercepted.
+  setVolumeKeyMappings: (mappings: KeyMapping[]) => {
+    set({ volumeKeyMappings: mappings });
+    if (get().volumeKeysIntercepted) {
+      setKeyMapping({ mappings });
+    }
+  },
+
+  // The volume keys go through the native key mapping, which turns holding
+  // a key into repeats or a long press and hands the keys back to the system
+  // while the app is in the background.
   acquireVolumeKeyInterception: () => {
-    const { volumeKeysInterceptionCount } = get();
+    const { volumeKeysInterceptionCount, volumeKeyMappings } = get();
     if (volumeKeysInterceptionCount == 0) {
       window.onNativeKeyDown = handleNativeKeyDown;
-      interceptKeys({ volumeKeys: true });
+      listenToVolumeKeyActions();
+      setKeyMapping({ mappings: volumeKeyMappings });
       set({ volumeKeysIntercepted: true });
     }
     set({ volumeKeysInterceptionCount: volumeKeysInterceptionCount + 1 });
@@ -60,7 +96,7 @@ export const useDeviceControlStore = create<DeviceControlState>((set, get) => ({
   releaseVolumeKeyInterception: () => {
     const { volumeKeysInterceptionCount } = get();
     if (volumeKeysInterceptionCount <= 1) {
-      interceptKeys({ volumeKeys: false });
+      setKeyMapping({ mappings: [] });
       set({ volumeKeysIntercepted: false, volumeKeysInterceptionCount: 0 });
     } else {
       set({ volumeKeysInterceptionCount: volumeKeysInterceptionCount - 1 });
@@ -107,6 +143,12 @@ export const useDeviceControlStore = create<DeviceControlState>((set, get) => ({
     }
   },
 
+  // Narrows the page-turner interception to the keys that are bound, so an
+  // arrow-key remote doesn't take the arrows from a paired keyboard unasked.
+  setPageTurnerBindings: (keys: string[]) => {
+    interceptKeys({ pageTurnerBindings: keys });
+  },
+
   // Learn mode is a stateless UI toggle (used while capturing a binding),
   // not reference-counted like the acquire/release interception actions.
   setKeyLearnMode: (enabled: boolean) => {
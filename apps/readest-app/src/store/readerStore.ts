@@ -218,6 +218,7 @@ export const useReaderStore = create<ReaderStore>((set, get) => ({
           }
           const doc = await new DocumentLoader(file, {
             nativeFilePath: nativeFilePath ?? undefined,
+            bookHash: book.hash,
           }).open();
           bookDoc = doc.book;
         }
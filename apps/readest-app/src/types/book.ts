@@ -3,6 +3,7 @@ import { TTSHighlightOptions } from '@/services/tts/types';
 import { TTSHighlightGranularity } from '@/services/tts/types';
 import { TTSMediaMetadataMode } from '@/services/tts/types';
 import { TTSPlayerStyle } from '@/services/tts/types';
+import { TTSPronunciation } from '@/services/tts/types';
 import type { AnnotationLinkType } from '@/utils/deeplink';
 import { AnnotationToolType } from './annotator';
 
@@ -179,6 +180,8 @@ export interface BooknoteGroup {
 }
 
 export type WritingMode = 'auto' | 'horizontal-tb' | 'horizontal-rl' | 'vertical-rl';
+/* Holding a volume key: keep turning pages, turn one page only, or skip to the next section. */
+export type VolumeKeysLongPress = 'repeat' | 'none' | 'section';
 
 export interface BookLayout {
   marginTopPx: number;
@@ -201,6 +204,9 @@ export interface BookLayout {
   swapClickArea: boolean;
   disableDoubleClick: boolean;
   volumeKeysToFlip: boolean;
+  /* Volume up turns to the next page instead of the previous one. */
+  volumeKeysSwapped: boolean;
+  volumeKeysLongPress: VolumeKeysLongPress;
   maxColumnCount: number;
   maxInlineSize: number;
   maxBlockSize: number;
@@ -323,6 +329,9 @@ export interface TTSConfig {
   ttsHighlightGranularity: TTSHighlightGranularity;
   ttsMediaMetadata: TTSMediaMetadataMode;
   ttsPlayerStyle: TTSPlayerStyle;
+  // Pronunciation dictionary: the global settings hold the library's, a
+  // book's settings its own, which are tried first.
+  ttsPronunciations: TTSPronunciation[];
 }
 
 export interface TranslatorConfig {
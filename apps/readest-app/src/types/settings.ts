@@ -334,6 +334,7 @@ export interface SystemSettings {
 
   keepLogin: boolean;
   alwaysOnTop: boolean;
+  minimizeToTray: boolean;
   openBookInNewWindow: boolean;
   autoCheckUpdates: boolean;
   updateChannel: 'stable' | 'nightly';
@@ -350,6 +351,8 @@ export interface SystemSettings {
   savedBookCoverForLockScreenPath: string;
   telemetryEnabled: boolean;
   discordRichPresenceEnabled: boolean;
+  /** Android: let other apps read the library through the content provider. */
+  shareLibraryWithApps: boolean;
   libraryViewMode: LibraryViewModeType;
   librarySortBy: LibrarySortByType;
   librarySortAscending: boolean;
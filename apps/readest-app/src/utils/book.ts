@@ -298,6 +298,17 @@ export const isCurrentlyReadingBook = (book: Book): boolean =>
   book.readingStatus !== 'abandoned' &&
   book.readingStatus !== 'unread';
 
+/**
+ * Books that have been opened, most recently read first. Backs the native
+ * recent-books surfaces (jump list, Dock and tray menus, launcher shortcuts,
+ * Siri intents); unlike the recently-read shelf it keeps finished books.
+ */
+export const selectRecentBooks = (library: Book[], limit: number): Book[] =>
+  library
+    .filter((book) => !book.deletedAt && book.progress != null)
+    .sort((a, b) => (b.updatedAt ?? 0) - (a.updatedAt ?? 0))
+    .slice(0, limit);
+
 export const getBookDirFromWritingMode = (writingMode: WritingMode) => {
   switch (writingMode) {
     case 'horizontal-tb':
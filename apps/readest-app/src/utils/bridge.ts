@@ -1,4 +1,4 @@
-import { invoke, Channel } from '@tauri-apps/api/core';
+import { invoke, addPluginListener, Channel } from '@tauri-apps/api/core';
 
 export interface CopyURIRequest {
   uri: string;
@@ -31,6 +31,33 @@ export interface SetTextSelectionSuppressedRequest {
   suppressed: boolean;
 }
 
+export interface SetKeepAwakeRequest {
+  enabled: boolean;
+}
+
+export interface KeyMapping {
+  key: 'VolumeUp' | 'VolumeDown';
+  /** Sent in a `mapped-key` event when the key is pressed. */
+  action: string;
+  /**
+   * Holding the key: `repeat` sends `action` every `repeatIntervalMs`, `none`
+   * ignores the hold, and any other value is sent once as its own action.
+   */
+  longPress?: string;
+  repeatIntervalMs?: number;
+}
+
+export interface SetKeyMappingRequest {
+  /** An empty list hands the keys back to the system. */
+  mappings: KeyMapping[];
+}
+
+export interface MappedKeyEvent {
+  key: string;
+  action: string;
+  longPress: boolean;
+}
+
 export interface InstallPackageRequest {
   path: string;
 }
@@ -65,6 +92,11 @@ export interface InterceptKeysRequest {
   backKey?: boolean;
   /** Intercept media keys (next/previous/play-pause) for the hardware page turner. */
   pageTurnerKeys?: boolean;
+  /**
+   * Normalized names of the native keys the page turner has bindings for
+   * (see `normalizeNativeKeyId`); only these are taken from the system.
+   */
+  pageTurnerBindings?: string[];
   /** Forward every key press to JS so the settings UI can capture a binding. */
   learnMode?: boolean;
 }
@@ -112,6 +144,26 @@ interface SelectDirectoryResponse {
   error?: string;
 }
 
+export interface PersistableUriPermissionResponse {
+  success: boolean;
+  error?: string;
+}
+
+export interface PersistedUriPermission {
+  uri: string;
+  /** Filesystem path of the tree, when it is on local storage. */
+  path?: string | null;
+  read: boolean;
+  write: boolean;
+  persistedTime: number;
+  /** `false` once the folder was deleted or its storage removed. */
+  accessible: boolean;
+}
+
+export interface ListPersistedUriPermissionsResponse {
+  permissions: PersistedUriPermission[];
+}
+
 export interface GetStorefrontRegionCodeResponse {
   regionCode?: string;
   error?: string;
@@ -156,6 +208,26 @@ export async function setTextSelectionSuppressed(
   });
 }
 
+// Mobile-only: keep the screen from sleeping while the app is in the
+// foreground (FLAG_KEEP_SCREEN_ON on Android, the idle timer on iOS).
+export async function setKeepScreenAwake(request: SetKeepAwakeRequest): Promise<void> {
+  await invoke('plugin:native-bridge|set_keep_awake', {
+    payload: request,
+  });
+}
+
+// Mobile-only: the native side consumes mapped keys, handles holding them
+// down and releases them while the app is in the background.
+export async function setKeyMapping(request: SetKeyMappingRequest): Promise<void> {
+  await invoke('plugin:native-bridge|set_key_mapping', {
+    payload: request,
+  });
+}
+
+export async function listenToMappedKeys(callback: (event: MappedKeyEvent) => void) {
+  return addPluginListener<MappedKeyEvent>('native-bridge', 'mapped-key', callback);
+}
+
 export async function installPackage(
   request: InstallPackageRequest,
 ): Promise<InstallPackageResponse> {
@@ -254,6 +326,35 @@ export async function selectDirectory(): Promise<SelectDirectoryResponse> {
   return result;
 }
 
+/** Android: keep access to a picked folder's tree URI across reboots. */
+export async function takePersistableUriPermission(request: {
+  uri: string;
+  write?: boolean;
+}): Promise<PersistableUriPermissionResponse> {
+  const result = await invoke<PersistableUriPermissionResponse>(
+    'plugin:native-bridge|take_persistable_uri_permission',
+    { payload: request },
+  );
+  return result;
+}
+
+export async function releasePersistableUriPermission(request: {
+  uri: string;
+}): Promise<PersistableUriPermissionResponse> {
+  const result = await invoke<PersistableUriPermissionResponse>(
+    'plugin:native-bridge|release_persistable_uri_permission',
+    { payload: request },
+  );
+  return result;
+}
+
+export async function listPersistedUriPermissions(): Promise<ListPersistedUriPermissionsResponse> {
+  const result = await invoke<ListPersistedUriPermissionsResponse>(
+    'plugin:native-bridge|list_persisted_uri_permissions',
+  );
+  return result;
+}
+
 export async function getStorefrontRegionCode(): Promise<GetStorefrontRegionCodeResponse> {
   const result = await invoke<GetStorefrontRegionCodeResponse>(
     'plugin:native-bridge|get_storefront_region_code',
@@ -407,6 +508,39 @@ export async function updateReadingWidget(request: UpdateReadingWidgetRequest):
   await invoke('plugin:native-bridge|update_reading_widget', { payload: request });
 }
 
+// ── Launcher shortcuts ───────────────────────────────────────────────────
+
+export interface AppShortcutBook {
+  hash: string;
+  title: string;
+  author: string;
+  coverPath: string;
+}
+
+export interface UpdateAppShortcutsRequest {
+  books: AppShortcutBook[];
+}
+
+export async function updateAppShortcuts(request: UpdateAppShortcutsRequest): Promise<void> {
+  await invoke('plugin:native-bridge|update_app_shortcuts', { payload: request });
+}
+
+// ── Shortcuts / Siri (iOS App Intents) ───────────────────────────────────
+
+export interface ReadingIntentsBook {
+  hash: string;
+  title: string;
+}
+
+export interface UpdateReadingIntentsRequest {
+  book: ReadingIntentsBook | null;
+  readingSecondsToday: number;
+}
+
+export async function updateReadingIntents(request: UpdateReadingIntentsRequest): Promise<void> {
+  await invoke('plugin:native-bridge|update_reading_intents', { payload: request });
+}
+
 // ── Nightly updater (main-app commands, no native-bridge prefix) ─────────
 // `verify_update_signature` gates the custom install flows (portable /
 // AppImage / Android); `install_nightly_update` drives the Tauri updater for
@@ -131,3 +131,30 @@ export const parseBookDeepLink = (url: string): { bookHash: string; autoplay?: b
   }
   return null;
 };
+
+/**
+ * Whether an incoming URL is `readest://import`, the Windows jump list's
+ * "Import Books…" task.
+ */
+export const isImportBooksDeepLink = (url: string): boolean => {
+  try {
+    const parsed = new URL(url);
+    return parsed.protocol === 'readest:' && parsed.host === 'import';
+  } catch {
+    return false;
+  }
+};
+
+/**
+ * The query of an incoming `readest://lookup?q=…` URL, sent by the macOS
+ * "Look Up in Readest" service. Returns null for other URLs or an empty query.
+ */
+export const parseLookupDeepLink = (url: string): string | null => {
+  try {
+    const parsed = new URL(url);
+    if (parsed.protocol !== 'readest:' || parsed.host !== 'lookup') return null;
+    return parsed.searchParams.get('q')?.trim() || null;
+  } catch {
+    return null;
+  }
+};
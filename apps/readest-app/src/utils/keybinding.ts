@@ -1,4 +1,6 @@
 import { HardwarePageTurnerSettings, KeyBinding } from '@/types/settings';
+import { VolumeKeysLongPress } from '@/types/book';
+import { KeyMapping } from '@/utils/bridge';
 import { stubTranslation as _ } from '@/utils/misc';
 
 export type KeyCandidate = { source: 'native' | 'dom'; id: string };
@@ -15,6 +17,20 @@ export const PAGE_TURN_ACTIONS: PageTurnAction[] = [
 ];
 
 const NATIVE_KEY_LABELS: Record<string, string> = {
+  ArrowUp: _('Arrow Up'),
+  ArrowDown: _('Arrow Down'),
+  ArrowLeft: _('Arrow Left'),
+  ArrowRight: _('Arrow Right'),
+  DpadCenter: _('D-pad Center'),
+  PageUp: _('Page Up'),
+  PageDown: _('Page Down'),
+  Space: _('Space'),
+  Enter: _('Enter'),
+  Camera: _('Camera'),
+  MouseSecondary: _('Right Click'),
+  MouseMiddle: _('Middle Click'),
+  MouseBack: _('Mouse Back'),
+  MouseForward: _('Mouse Forward'),
   MediaNext: _('Media Next'),
   MediaPrevious: _('Media Previous'),
   MediaPlayPause: _('Media Play/Pause'),
@@ -38,12 +54,33 @@ const DOM_KEY_LABELS: Record<string, string> = {
   MediaPlayPause: _('Media Play/Pause'),
 };
 
+// Android used to send these keys by key code; bindings saved then keep
+// working under the names the native bridge sends now.
+const LEGACY_NATIVE_KEY_IDS: Record<string, string> = {
+  Keycode19: 'ArrowUp',
+  Keycode20: 'ArrowDown',
+  Keycode21: 'ArrowLeft',
+  Keycode22: 'ArrowRight',
+  Keycode23: 'DpadCenter',
+  Keycode27: 'Camera',
+  Keycode62: 'Space',
+  Keycode66: 'Enter',
+  Keycode92: 'PageUp',
+  Keycode93: 'PageDown',
+  Keycode160: 'Enter',
+};
+
+export const normalizeNativeKeyId = (id: string): string => LEGACY_NATIVE_KEY_IDS[id] ?? id;
+
 /** Normalize a native key name (from the OS bridge) into a `KeyBinding`. */
-export const normalizeNativeKey = (name: string): KeyBinding => ({
-  source: 'native',
-  id: name,
-  label: NATIVE_KEY_LABELS[name] ?? name,
-});
+export const normalizeNativeKey = (name: string): KeyBinding => {
+  const id = normalizeNativeKeyId(name);
+  return {
+    source: 'native',
+    id,
+    label: NATIVE_KEY_LABELS[id] ?? id,
+  };
+};
 
 /** Normalize a DOM `KeyboardEvent` into a `KeyBinding`. */
 export const normalizeDomKeyEvent = (event: KeyboardEvent): KeyBinding => {
@@ -55,11 +92,17 @@ export const normalizeDomKeyEvent = (event: KeyboardEvent): KeyBinding => {
   };
 };
 
+const normalizeKeyId = (source: KeyCandidate['source'], id: string) =>
+  source === 'native' ? normalizeNativeKeyId(id) : id;
+
 /** True when `candidate` is the key described by `binding`. */
 export const matchesBinding = (
   binding: KeyBinding | null | undefined,
   candidate: KeyCandidate,
-): boolean => !!binding && binding.source === candidate.source && binding.id === candidate.id;
+): boolean =>
+  !!binding &&
+  binding.source === candidate.source &&
+  normalizeKeyId(binding.source, binding.id) === normalizeKeyId(candidate.source, candidate.id);
 
 /**
  * Decide which page-turn action an incoming key triggers. Returns the
@@ -75,3 +118,33 @@ export const resolvePageTurn = (
   }
   return null;
 };
+
+/** The native keys bound to an action, which the native bridge takes from the system. */
+export const boundNativeKeys = (settings: HardwarePageTurnerSettings): string[] => {
+  const ids = PAGE_TURN_ACTIONS.flatMap((action) => {
+    const binding = settings.bindings[action];
+    return binding?.source === 'native' ? [normalizeNativeKeyId(binding.id)] : [];
+  });
+  return [...new Set(ids)];
+};
+
+// Time between pages while a volume key is held with `repeat`.
+export const VOLUME_KEY_REPEAT_INTERVAL_MS = 300;
+
+/**
+ * The native key mappings for turning pages with the volume keys: volume up
+ * goes back a page unless `swapped`, and holding a key keeps turning pages,
+ * turns just the one, or skips a section, by `longPress`.
+ */
+export const volumeKeyMappings = (
+  swapped = false,
+  longPress: VolumeKeysLongPress = 'repeat',
+): KeyMapping[] => {
+  const mapping = (key: KeyMapping['key'], forward: boolean): KeyMapping => ({
+    key,
+    action: forward ? 'pageNext' : 'pagePrev',
+    longPress: longPress === 'section' ? (forward ? 'sectionNext' : 'sectionPrev') : longPress,
+    repeatIntervalMs: VOLUME_KEY_REPEAT_INTERVAL_MS,
+  });
+  return [mapping('VolumeUp', swapped), mapping('VolumeDown', !swapped)];
+};
@@ -1,4 +1,5 @@
 import { redirect, useRouter } from 'next/navigation';
+import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow, ScrollBarStyle } from '@tauri-apps/api/window';
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { isPWA, isTauriAppPlatform, isWebAppPlatform } from '@/services/environment';
@@ -40,12 +41,13 @@ const createReaderWindow = (appService: AppService, url: string) => {
   });
 };
 
-export const showReaderWindow = (appService: AppService, bookIds: string[]) => {
-  const ids = bookIds.join(BOOK_IDS_SEPARATOR);
-  const params = new URLSearchParams('');
-  params.set('ids', ids);
-  const url = `/reader?${params.toString()}`;
-  createReaderWindow(appService, url);
+// Book windows are labeled after their books (`book_window.rs`), so each
+// book keeps its own window size and position, and opening a book that
+// already has a window focuses it.
+export const showReaderWindow = (bookIds: string[]) => {
+  invoke<string>('open_book_window', { bookIds }).catch((err) =>
+    console.error('error opening book window', err),
+  );
 };
 
 export const showLibraryWindow = (appService: AppService, filenames: string[]) => {
@@ -189,7 +189,6 @@ export const tauriDownload = async (
   headers?: Record<string, string>,
   body?: string,
   singleThreaded?: boolean,
-  skipSslVerification?: boolean,
 ): Promise<Record<string, string>> => {
   const ids = new Uint32Array(1);
   window.crypto.getRandomValues(ids);
@@ -208,7 +207,6 @@ export const tauriDownload = async (
     onProgress,
     body,
     singleThreaded,
-    skipSslVerification,
   });
   return responseHeaders;
 };